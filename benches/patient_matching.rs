@@ -0,0 +1,48 @@
+//! Matching throughput benchmarks
+//!
+//! Run with `cargo bench --features testdata --bench patient_matching`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use master_patient_index::config::Config;
+use master_patient_index::matching::{DeterministicMatcher, PatientMatcher, ProbabilisticMatcher};
+use master_patient_index::testdata::PatientGenerator;
+
+fn bench_match_pair(c: &mut Criterion) {
+    let config = Config::default().matching;
+    let mut generator = PatientGenerator::new(1);
+    let patient = generator.patient();
+    let duplicate = generator.duplicate(&patient);
+
+    let probabilistic = ProbabilisticMatcher::new(config.clone());
+    let deterministic = DeterministicMatcher::new(config);
+
+    let mut group = c.benchmark_group("match_pair");
+    group.bench_function("probabilistic", |b| {
+        b.iter(|| probabilistic.match_patients(black_box(&patient), black_box(&duplicate)))
+    });
+    group.bench_function("deterministic", |b| {
+        b.iter(|| deterministic.match_patients(black_box(&patient), black_box(&duplicate)))
+    });
+    group.finish();
+}
+
+fn bench_find_matches(c: &mut Criterion) {
+    let matcher = ProbabilisticMatcher::new(Config::default().matching);
+    let mut generator = PatientGenerator::new(2);
+
+    let mut group = c.benchmark_group("find_matches");
+    for size in [100, 1_000, 10_000] {
+        let candidates = generator.patients(size);
+        let patient = generator.duplicate(&candidates[size / 2]);
+
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &candidates, |b, candidates| {
+            b.iter(|| matcher.find_matches(black_box(&patient), black_box(candidates)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_match_pair, bench_find_matches);
+criterion_main!(benches);
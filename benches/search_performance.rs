@@ -0,0 +1,41 @@
+//! Search latency benchmarks
+//!
+//! Run with `cargo bench --features testdata --bench search_performance`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use master_patient_index::search::{FuzzyOptions, SearchEngine};
+use master_patient_index::testdata::PatientGenerator;
+
+const INDEX_SIZE: usize = 10_000;
+
+fn bench_search(c: &mut Criterion) {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let engine = SearchEngine::new(dir.path()).expect("create search engine");
+
+    let mut generator = PatientGenerator::new(3);
+    let patients = generator.patients(INDEX_SIZE);
+    engine.index_patients(&patients).expect("index patients");
+
+    let target = &patients[INDEX_SIZE / 2];
+    let family = target.name.family.clone();
+    let birth_year = target.birth_date.map(|d| chrono::Datelike::year(&d));
+
+    let mut group = c.benchmark_group("search");
+    group.bench_function("exact", |b| {
+        b.iter(|| engine.search(black_box(&family), 10))
+    });
+    group.bench_function("fuzzy", |b| {
+        b.iter(|| engine.fuzzy_search(black_box("Smyth"), &FuzzyOptions::default(), 10))
+    });
+    group.bench_function("name_and_year", |b| {
+        b.iter(|| engine.search_by_name_and_year(black_box(&family), birth_year, 100))
+    });
+    group.bench_function("suggest", |b| {
+        b.iter(|| engine.suggest(black_box("John"), 10))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_search);
+criterion_main!(benches);
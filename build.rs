@@ -0,0 +1,416 @@
+//! Build script
+//!
+//! With the `client` feature, generates the typed HTTP client in
+//! `src/client` from an OpenAPI document written by `mpi openapi`. The
+//! document is read from `$MPI_OPENAPI_SPEC`, else the committed
+//! `openapi.json` in the crate root. A missing or invalid document leaves
+//! the client without operations and is reported as a build warning.
+
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=MPI_OPENAPI_SPEC");
+
+    if env::var_os("CARGO_FEATURE_CLIENT").is_none() {
+        return;
+    }
+
+    let spec_path = env::var_os("MPI_OPENAPI_SPEC")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("openapi.json"));
+    println!("cargo:rerun-if-changed={}", spec_path.display());
+
+    let spec = fs::read_to_string(&spec_path)
+        .map_err(|e| e.to_string())
+        .and_then(|spec| serde_json::from_str::<Value>(&spec).map_err(|e| e.to_string()))
+        .unwrap_or_else(|e| {
+            println!(
+                "cargo:warning=No client operations generated: cannot read the OpenAPI document {} ({}); \
+                 write one with `cargo run --bin mpi -- openapi --output openapi.json` or set MPI_OPENAPI_SPEC",
+                spec_path.display(),
+                e
+            );
+            Value::Null
+        });
+
+    let code = generate_client(&spec);
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("client.rs");
+    if let Err(e) = fs::write(&out, code) {
+        println!("cargo:warning=Failed to write the generated client to {}: {}", out.display(), e);
+    }
+}
+
+/// Generate the `types` module and the `impl Client` operations
+fn generate_client(spec: &Value) -> String {
+    let mut code = String::new();
+    let mut query_types = String::new();
+
+    let mut operations = String::new();
+    if let Some(paths) = spec["paths"].as_object() {
+        for (path, item) in paths {
+            let Some(item) = item.as_object() else { continue };
+            for (method, operation) in item {
+                if !matches!(method.as_str(), "get" | "post" | "put" | "delete" | "patch") {
+                    continue;
+                }
+                operations.push_str(&generate_operation(path, method, operation, &mut query_types));
+            }
+        }
+    }
+
+    code.push_str("/// Request and response types from the OpenAPI components\n");
+    code.push_str("pub mod types {\n    #![allow(clippy::all, dead_code)]\n    use serde::{Deserialize, Serialize};\n\n");
+    if let Some(schemas) = spec["components"]["schemas"].as_object() {
+        for (name, schema) in schemas {
+            code.push_str(&generate_schema(&type_name(name), schema));
+        }
+    }
+    code.push_str(&query_types);
+    code.push_str("}\n\n");
+
+    code.push_str("impl Client {\n");
+    code.push_str(&operations);
+    code.push_str("}\n");
+    code
+}
+
+/// One named component schema as a struct, enum or alias
+fn generate_schema(name: &str, schema: &Value) -> String {
+    let mut code = doc_comment(schema["description"].as_str(), "    ");
+
+    if let Some(values) = string_enum(schema) {
+        let _ = writeln!(code, "    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]");
+        let _ = writeln!(code, "    pub enum {} {{", name);
+        for value in values {
+            let _ = writeln!(code, "        #[serde(rename = {:?})]", value);
+            let _ = writeln!(code, "        {},", variant_name(&value));
+        }
+        code.push_str("    }\n\n");
+    } else if let Some(properties) = schema["properties"].as_object() {
+        code.push_str(&generate_struct(name, properties, required(schema), "#[derive(Debug, Clone, Serialize, Deserialize)]"));
+    } else {
+        let _ = writeln!(code, "    pub type {} = {};\n", name, rust_type(schema, None));
+    }
+    code
+}
+
+/// A struct with one field per property
+fn generate_struct(name: &str, properties: &serde_json::Map<String, Value>, required: Vec<&str>, derive: &str) -> String {
+    let mut code = String::new();
+    let _ = writeln!(code, "    {}", derive);
+    let _ = writeln!(code, "    pub struct {} {{", name);
+    for (property, schema) in properties {
+        let field = field_name(property);
+        let (ty, nullable) = nullable(schema)
+            .map(|inner| (rust_type(&inner, Some(name)), true))
+            .unwrap_or_else(|| (rust_type(schema, Some(name)), false));
+
+        code.push_str(&doc_comment(schema["description"].as_str(), "        "));
+        if field != *property {
+            let _ = writeln!(code, "        #[serde(rename = {:?})]", property);
+        }
+        if nullable || !required.contains(&property.as_str()) {
+            let _ = writeln!(code, "        #[serde(default, skip_serializing_if = \"Option::is_none\")]");
+            let _ = writeln!(code, "        pub {}: Option<{}>,", field, ty);
+        } else {
+            let _ = writeln!(code, "        pub {}: {},", field, ty);
+        }
+    }
+    code.push_str("    }\n\n");
+    code
+}
+
+/// One `Client` method for an operation
+fn generate_operation(path: &str, method: &str, operation: &Value, query_types: &mut String) -> String {
+    let Some(operation_id) = operation["operationId"].as_str() else {
+        return String::new();
+    };
+    let fn_name = field_name(operation_id);
+    let params = operation["parameters"].as_array().cloned().unwrap_or_default();
+
+    let mut args = Vec::new();
+    let mut url_args = Vec::new();
+    let mut headers = Vec::new();
+    let mut query_properties = serde_json::Map::new();
+    let mut query_required = Vec::new();
+    for param in &params {
+        let Some(name) = param["name"].as_str() else { continue };
+        match param["in"].as_str() {
+            Some("path") => {
+                let arg = field_name(name);
+                args.push(format!("{}: impl std::fmt::Display", arg));
+                url_args.push((name.to_string(), arg));
+            }
+            Some("header") => {
+                let arg = field_name(name);
+                args.push(format!("{}: Option<&str>", arg));
+                headers.push(format!("({:?}, {})", name, arg));
+            }
+            Some("query") => {
+                let mut schema = param["schema"].clone();
+                if let Some(description) = param["description"].as_str() {
+                    schema["description"] = Value::String(description.to_string());
+                }
+                if param["required"].as_bool() == Some(true) {
+                    query_required.push(name.to_string());
+                }
+                query_properties.insert(name.to_string(), schema);
+            }
+            _ => {}
+        }
+    }
+
+    let query = if query_properties.is_empty() {
+        "Vec::new()".to_string()
+    } else {
+        let query_type = format!("{}Query", type_name(operation_id));
+        // Only all-optional parameters can default
+        let derive = if query_required.is_empty() {
+            "#[derive(Debug, Clone, Default, Serialize, Deserialize)]"
+        } else {
+            "#[derive(Debug, Clone, Serialize, Deserialize)]"
+        };
+        let required = query_required.iter().map(String::as_str).collect();
+        let _ = writeln!(query_types, "    /// Query parameters for `Client::{}`", fn_name);
+        query_types.push_str(&generate_struct(&query_type, &query_properties, required, derive));
+        args.push(format!("query: &types::{}", query_type));
+        "query_pairs(query)".to_string()
+    };
+
+    let body_schema = &operation["requestBody"]["content"]["application/json"]["schema"];
+    let body = body_schema.is_object().then(|| rust_type(body_schema, None));
+    if let Some(body) = &body {
+        args.push(format!("body: &{}", qualify(body)));
+    }
+
+    let returns = success_type(operation);
+
+    let mut url = format!("{:?}", path);
+    let mut format_args = String::new();
+    for (name, arg) in &url_args {
+        url = url.replace(&format!("{{{}}}", name), "{}");
+        let _ = write!(format_args, ", {}", arg);
+    }
+
+    let mut code = doc_comment(
+        operation["summary"].as_str().or(operation["description"].as_str()),
+        "    ",
+    );
+    if args.len() > 6 {
+        code.push_str("    #[allow(clippy::too_many_arguments)]\n");
+    }
+    let _ = writeln!(
+        code,
+        "    pub fn {}(&self{}) -> Result<{}, ClientError> {{",
+        fn_name,
+        args.iter().map(|a| format!(", {}", a)).collect::<String>(),
+        qualify(&returns)
+    );
+    let url = if url_args.is_empty() { url } else { format!("&format!({}{})", url, format_args) };
+    let _ = writeln!(
+        code,
+        "        self.send({:?}, {}, {}, &[{}], {})",
+        method.to_uppercase(),
+        url,
+        query,
+        headers.join(", "),
+        if body.is_some() { "Some(body)" } else { "None::<&()>" }
+    );
+    code.push_str("    }\n\n");
+    code
+}
+
+/// The JSON type of the first successful response, `()` if it has no body
+fn success_type(operation: &Value) -> String {
+    let Some(responses) = operation["responses"].as_object() else {
+        return "serde_json::Value".to_string();
+    };
+    for (status, response) in responses {
+        if !status.starts_with('2') {
+            continue;
+        }
+        if status == "204" {
+            return "()".to_string();
+        }
+        let schema = &response["content"]["application/json"]["schema"];
+        if schema.is_object() {
+            return rust_type(schema, None);
+        }
+        return "serde_json::Value".to_string();
+    }
+    "serde_json::Value".to_string()
+}
+
+/// Refer to generated component types from outside the `types` module
+fn qualify(ty: &str) -> String {
+    fn flush(out: &mut String, ident: &mut String) {
+        let builtin = ident.contains("::")
+            || matches!(
+                ident.as_str(),
+                "Option" | "Vec" | "Box" | "String" | "bool" | "i32" | "i64" | "u32" | "u64" | "f32" | "f64"
+            );
+        if !ident.is_empty() && !builtin {
+            out.push_str("types::");
+        }
+        out.push_str(ident);
+        ident.clear();
+    }
+
+    let mut out = String::new();
+    let mut ident = String::new();
+    for c in ty.chars() {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            ident.push(c);
+        } else {
+            flush(&mut out, &mut ident);
+            out.push(c);
+        }
+    }
+    flush(&mut out, &mut ident);
+    out
+}
+
+/// The Rust type for a schema, boxing direct references to `containing`
+fn rust_type(schema: &Value, containing: Option<&str>) -> String {
+    if let Some(inner) = nullable(schema) {
+        return format!("Option<{}>", rust_type(&inner, containing));
+    }
+    if let Some(reference) = schema["$ref"].as_str() {
+        let name = type_name(reference.rsplit('/').next().unwrap_or(reference));
+        return if Some(name.as_str()) == containing { format!("Box<{}>", name) } else { name };
+    }
+    match schema["type"].as_str() {
+        Some("string") => match schema["format"].as_str() {
+            Some("date-time") => "chrono::DateTime<chrono::Utc>".to_string(),
+            Some("date") => "chrono::NaiveDate".to_string(),
+            Some("uuid") => "uuid::Uuid".to_string(),
+            _ => "String".to_string(),
+        },
+        Some("integer") => match schema["format"].as_str() {
+            Some("int32") => "i32",
+            Some("uint32") => "u32",
+            Some("uint64") => "u64",
+            _ => "i64",
+        }
+        .to_string(),
+        Some("number") => match schema["format"].as_str() {
+            Some("float") => "f32",
+            _ => "f64",
+        }
+        .to_string(),
+        Some("boolean") => "bool".to_string(),
+        Some("array") => format!("Vec<{}>", rust_type(&schema["items"], None)),
+        _ => "serde_json::Value".to_string(),
+    }
+}
+
+/// The inner schema of an OpenAPI 3.1 nullable schema
+///
+/// Handles `"type": ["T", "null"]` and `oneOf: [{"type": "null"}, T]`.
+fn nullable(schema: &Value) -> Option<Value> {
+    if let Some(types) = schema["type"].as_array() {
+        let non_null: Vec<&Value> = types.iter().filter(|t| t.as_str() != Some("null")).collect();
+        if non_null.len() == 1 && non_null.len() < types.len() {
+            let mut inner = schema.clone();
+            inner["type"] = non_null[0].clone();
+            if let Some(values) = inner["enum"].as_array_mut() {
+                values.retain(|v| !v.is_null());
+            }
+            return Some(inner);
+        }
+    }
+    if let Some(variants) = schema["oneOf"].as_array() {
+        let non_null: Vec<&Value> = variants.iter().filter(|v| v["type"].as_str() != Some("null")).collect();
+        if non_null.len() == 1 && non_null.len() < variants.len() {
+            return Some(non_null[0].clone());
+        }
+    }
+    None
+}
+
+/// The values of a string enum schema
+fn string_enum(schema: &Value) -> Option<Vec<String>> {
+    if schema["type"].as_str() != Some("string") {
+        return None;
+    }
+    let values = schema["enum"].as_array()?;
+    Some(values.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+}
+
+fn required(schema: &Value) -> Vec<&str> {
+    schema["required"]
+        .as_array()
+        .map(|r| r.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default()
+}
+
+/// A component name as a Rust type name, e.g. `ApiResponse_Patient` to `ApiResponsePatient`
+fn type_name(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(capitalize)
+        .collect()
+}
+
+/// An enum value as a variant name, e.g. `half_open` to `HalfOpen`
+fn variant_name(value: &str) -> String {
+    let name = type_name(value);
+    match name.chars().next() {
+        Some(c) if c.is_ascii_digit() => format!("V{}", name),
+        Some(_) => name,
+        None => "Empty".to_string(),
+    }
+}
+
+/// A property or operation name as a snake_case field name
+fn field_name(name: &str) -> String {
+    let mut out = String::new();
+    let mut previous_lower = false;
+    for c in name.trim_start_matches('_').chars() {
+        if c.is_uppercase() && previous_lower {
+            out.push('_');
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        if c.is_alphanumeric() {
+            out.extend(c.to_lowercase());
+        } else if !out.ends_with('_') {
+            out.push('_');
+        }
+    }
+    if out.is_empty() || out.starts_with(|c: char| c.is_ascii_digit()) {
+        out.insert_str(0, "f_");
+    }
+    if KEYWORDS.contains(&out.as_str()) {
+        out.push('_');
+    }
+    out
+}
+
+fn capitalize(part: &str) -> String {
+    let mut chars = part.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn doc_comment(text: Option<&str>, indent: &str) -> String {
+    let mut code = String::new();
+    for line in text.unwrap_or_default().lines() {
+        let _ = writeln!(code, "{}/// {}", indent, line);
+    }
+    code.replace("/// \n", "///\n")
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "unsafe", "use", "where",
+    "while", "yield",
+];
@@ -0,0 +1,7 @@
+-- Drop source record tables
+
+DROP TRIGGER IF EXISTS source_records_immutable ON source_records;
+DROP FUNCTION IF EXISTS prevent_source_record_update();
+
+DROP TABLE IF EXISTS source_record_links CASCADE;
+DROP TABLE IF EXISTS source_records CASCADE;
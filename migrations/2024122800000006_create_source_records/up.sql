@@ -0,0 +1,54 @@
+-- Split immutable source records from the curated enterprise record
+--
+-- Every submission from a feed is stored once, unchanged, in source_records.
+-- The patients table holds the enterprise (master) record the MPI curates,
+-- and source_record_links ties each source record to its current enterprise
+-- record. Re-linking supersedes the old link rather than editing the source.
+
+-- Source records (one per feed submission)
+CREATE TABLE source_records (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    source_system VARCHAR(255) NOT NULL,
+    source_record_id VARCHAR(255) NOT NULL,
+    payload JSONB NOT NULL,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    received_by VARCHAR(255)
+);
+
+-- Links between source records and enterprise records
+CREATE TABLE source_record_links (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    source_record_id UUID NOT NULL REFERENCES source_records(id) ON DELETE CASCADE,
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    match_score NUMERIC(5, 4),
+    linked_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    linked_by VARCHAR(255),
+    unlinked_at TIMESTAMPTZ,
+    unlinked_by VARCHAR(255)
+);
+
+-- Indexes for source_records
+CREATE INDEX idx_source_records_source ON source_records(source_system, source_record_id);
+CREATE INDEX idx_source_records_received_at ON source_records(received_at);
+
+-- Indexes for source_record_links
+CREATE INDEX idx_source_record_links_source_record_id ON source_record_links(source_record_id);
+CREATE INDEX idx_source_record_links_patient_id ON source_record_links(patient_id);
+
+-- A source record has at most one current enterprise record
+CREATE UNIQUE INDEX idx_source_record_links_active
+    ON source_record_links(source_record_id)
+    WHERE unlinked_at IS NULL;
+
+-- Source records are immutable once received
+CREATE OR REPLACE FUNCTION prevent_source_record_update()
+RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'source_records are immutable';
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER source_records_immutable
+    BEFORE UPDATE ON source_records
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_source_record_update();
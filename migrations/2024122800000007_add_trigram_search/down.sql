@@ -0,0 +1,7 @@
+-- Drop trigram search indexes
+
+DROP INDEX IF EXISTS idx_patient_identifiers_value_trgm;
+DROP INDEX IF EXISTS idx_patient_names_family_lower_trgm;
+DROP INDEX IF EXISTS idx_patient_names_text_trgm;
+
+DROP FUNCTION IF EXISTS patient_name_text(VARCHAR, TEXT[]);
@@ -0,0 +1,20 @@
+-- Trigram indexes for the Postgres search backend
+--
+-- Deployments without a persistent Tantivy directory can search the
+-- patient tables directly. Names are folded into one lowercase string so a
+-- single GIN index serves "given family" and "family, given" queries.
+
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- array_to_string is only STABLE, so wrap it to make the expression indexable
+CREATE OR REPLACE FUNCTION patient_name_text(family VARCHAR, given TEXT[])
+RETURNS TEXT AS $$
+    SELECT lower(family || ' ' || array_to_string(given, ' '));
+$$ LANGUAGE sql IMMUTABLE;
+
+CREATE INDEX idx_patient_names_text_trgm
+    ON patient_names USING gin(patient_name_text(family, given) gin_trgm_ops);
+CREATE INDEX idx_patient_names_family_lower_trgm
+    ON patient_names USING gin(lower(family) gin_trgm_ops);
+CREATE INDEX idx_patient_identifiers_value_trgm
+    ON patient_identifiers USING gin(lower(value) gin_trgm_ops);
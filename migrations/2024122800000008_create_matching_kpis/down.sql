@@ -0,0 +1,6 @@
+-- Drop matching KPI table
+
+DROP INDEX IF EXISTS idx_source_record_links_unlinked_at;
+DROP INDEX IF EXISTS idx_source_record_links_linked_at;
+
+DROP TABLE IF EXISTS matching_kpis_daily;
@@ -0,0 +1,22 @@
+-- Daily matching quality KPIs, one row per source system per day
+--
+-- Rows are recomputed by the KPI job over a trailing window, so late merges
+-- are reflected in the day the duplicate record was created.
+
+CREATE TABLE matching_kpis_daily (
+    day DATE NOT NULL,
+    source_system VARCHAR(255) NOT NULL,
+    records_received BIGINT NOT NULL DEFAULT 0,
+    new_patients BIGINT NOT NULL DEFAULT 0,
+    duplicates_created BIGINT NOT NULL DEFAULT 0,
+    auto_links BIGINT NOT NULL DEFAULT 0,
+    manual_links BIGINT NOT NULL DEFAULT 0,
+    average_match_score DOUBLE PRECISION,
+    overlay_incidents BIGINT NOT NULL DEFAULT 0,
+    computed_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (day, source_system)
+);
+
+CREATE INDEX idx_matching_kpis_daily_source ON matching_kpis_daily(source_system, day);
+CREATE INDEX idx_source_record_links_linked_at ON source_record_links(linked_at);
+CREATE INDEX idx_source_record_links_unlinked_at ON source_record_links(unlinked_at);
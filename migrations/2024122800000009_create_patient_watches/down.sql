@@ -0,0 +1,3 @@
+-- Drop patient watches
+
+DROP TABLE IF EXISTS patient_watches CASCADE;
@@ -0,0 +1,15 @@
+-- Per-patient change subscriptions
+--
+-- A watch delivers notifications for one patient's changes, either to a
+-- webhook or to connected server-sent event streams.
+
+CREATE TABLE patient_watches (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    callback_url VARCHAR(2048),
+    event_types TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_by VARCHAR(255)
+);
+
+CREATE INDEX idx_patient_watches_patient_id ON patient_watches(patient_id);
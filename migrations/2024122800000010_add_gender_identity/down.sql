@@ -0,0 +1,5 @@
+-- Drop gender identity and pronouns
+
+ALTER TABLE patients
+    DROP COLUMN IF EXISTS pronouns,
+    DROP COLUMN IF EXISTS gender_identity;
@@ -0,0 +1,9 @@
+-- Gender identity and pronouns
+--
+-- Self-reported values carried by the FHIR individual-genderIdentity and
+-- individual-pronouns extensions. They are distinct from the administrative
+-- gender used for matching.
+
+ALTER TABLE patients
+    ADD COLUMN gender_identity VARCHAR(255),
+    ADD COLUMN pronouns VARCHAR(100);
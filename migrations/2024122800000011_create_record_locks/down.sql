@@ -0,0 +1,3 @@
+-- Drop record locks
+
+DROP TABLE IF EXISTS record_locks CASCADE;
@@ -0,0 +1,15 @@
+-- Advisory record locks for data steward workflows
+--
+-- A steward reviewing or merging a pair of records locks both, so other
+-- writers are refused until the steward releases them or the lock expires.
+-- Expired rows are ignored and overwritten by the next lock on the record.
+
+CREATE TABLE record_locks (
+    patient_id UUID PRIMARY KEY REFERENCES patients(id) ON DELETE CASCADE,
+    locked_by VARCHAR(255) NOT NULL,
+    reason TEXT,
+    acquired_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_record_locks_locked_by ON record_locks(locked_by);
@@ -0,0 +1,6 @@
+-- Drop the anonymization marker
+
+DROP INDEX IF EXISTS idx_patients_updated_at;
+
+ALTER TABLE patients
+    DROP COLUMN IF EXISTS anonymized_at;
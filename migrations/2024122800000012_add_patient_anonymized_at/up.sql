@@ -0,0 +1,9 @@
+-- Anonymization marker
+--
+-- Set when the retention job strips a patient's direct identifiers, so the
+-- record is not picked up again by the same rule.
+
+ALTER TABLE patients
+    ADD COLUMN anonymized_at TIMESTAMPTZ;
+
+CREATE INDEX idx_patients_updated_at ON patients(updated_at);
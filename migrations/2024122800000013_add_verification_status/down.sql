@@ -0,0 +1,7 @@
+-- Drop the verification status columns
+
+ALTER TABLE patient_addresses
+    DROP COLUMN IF EXISTS verification_status;
+
+ALTER TABLE patient_identifiers
+    DROP COLUMN IF EXISTS verification_status;
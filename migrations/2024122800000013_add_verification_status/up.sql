@@ -0,0 +1,12 @@
+-- Verification status of identifiers and addresses
+--
+-- One of 'unverified', 'patient-confirmed' or 'document-verified'. Existing
+-- rows were never checked, so they start out unverified.
+
+ALTER TABLE patient_identifiers
+    ADD COLUMN verification_status VARCHAR(20) NOT NULL DEFAULT 'unverified'
+        CHECK (verification_status IN ('unverified', 'patient-confirmed', 'document-verified'));
+
+ALTER TABLE patient_addresses
+    ADD COLUMN verification_status VARCHAR(20) NOT NULL DEFAULT 'unverified'
+        CHECK (verification_status IN ('unverified', 'patient-confirmed', 'document-verified'));
@@ -0,0 +1,3 @@
+-- Drop the assigning authority registry
+
+DROP TABLE IF EXISTS assigning_authorities;
@@ -0,0 +1,14 @@
+-- Assigning authority registry
+--
+-- Maps identifier systems and OIDs to the facility or agency that issues
+-- them. MRNs are unique across every system of one authority.
+
+CREATE TABLE assigning_authorities (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    system VARCHAR(255) NOT NULL UNIQUE,
+    oid VARCHAR(255) UNIQUE,
+    name VARCHAR(255) NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
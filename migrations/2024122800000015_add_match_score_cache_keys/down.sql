@@ -0,0 +1,7 @@
+-- Drop the pair score cache keys
+
+ALTER TABLE patient_match_scores
+    DROP COLUMN IF EXISTS candidate_updated_at,
+    DROP COLUMN IF EXISTS patient_updated_at,
+    DROP COLUMN IF EXISTS config_hash,
+    DROP COLUMN IF EXISTS algorithm_version;
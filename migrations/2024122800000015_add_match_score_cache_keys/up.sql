@@ -0,0 +1,12 @@
+-- Pair score cache keys
+--
+-- A stored score is reused only while it was computed by the same scoring
+-- algorithm, under the same matching configuration, from the same versions
+-- of both records. Rows written before this migration have no keys and are
+-- never reused.
+
+ALTER TABLE patient_match_scores
+    ADD COLUMN algorithm_version VARCHAR(64),
+    ADD COLUMN config_hash VARCHAR(64),
+    ADD COLUMN patient_updated_at TIMESTAMPTZ,
+    ADD COLUMN candidate_updated_at TIMESTAMPTZ;
@@ -0,0 +1,3 @@
+-- Drop the duplicate review queue
+
+DROP TABLE IF EXISTS duplicate_candidates CASCADE;
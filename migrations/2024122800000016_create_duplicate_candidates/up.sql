@@ -0,0 +1,18 @@
+-- Duplicate review queue
+--
+-- Pairs of enterprise records that scored at or above the match threshold,
+-- waiting for a data steward. Each pair is stored once, lower ID first, so a
+-- pair found again from either side is not queued twice.
+
+CREATE TABLE duplicate_candidates (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    candidate_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    score NUMERIC(5, 4) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    detected_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (patient_id, candidate_id),
+    CHECK (patient_id < candidate_id)
+);
+
+CREATE INDEX idx_duplicate_candidates_pending ON duplicate_candidates(score DESC) WHERE status = 'pending';
@@ -0,0 +1,3 @@
+-- Drop the practitioner registry
+
+DROP TABLE IF EXISTS practitioners CASCADE;
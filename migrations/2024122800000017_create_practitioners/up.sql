@@ -0,0 +1,23 @@
+-- Practitioner registry
+--
+-- The full practitioner is stored as JSONB; the NPI and family name are
+-- copied into columns for lookups and candidate selection during provider
+-- deduplication.
+
+CREATE TABLE practitioners (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    npi VARCHAR(10),
+    family_name VARCHAR(255) NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT true,
+    resource JSONB NOT NULL,
+
+    -- Audit fields
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    -- Soft delete
+    deleted_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_practitioners_npi ON practitioners(npi) WHERE deleted_at IS NULL;
+CREATE INDEX idx_practitioners_family_name ON practitioners(lower(family_name)) WHERE deleted_at IS NULL;
@@ -0,0 +1,3 @@
+-- Drop patient contact persons
+
+DROP TABLE IF EXISTS patient_related_persons;
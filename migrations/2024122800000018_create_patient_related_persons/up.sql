@@ -0,0 +1,17 @@
+-- Patient contact persons (next of kin, guardians, emergency contacts)
+--
+-- Each contact keeps its id across patient updates, as it is also the id of
+-- the FHIR RelatedPerson served for it. The contact itself is stored as
+-- JSONB.
+
+CREATE TABLE patient_related_persons (
+    id UUID PRIMARY KEY,
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    contact JSONB NOT NULL,
+
+    -- Audit fields
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_patient_related_persons_patient_id ON patient_related_persons(patient_id);
@@ -0,0 +1,3 @@
+-- Drop the edit distance extension
+
+DROP EXTENSION IF EXISTS fuzzystrmatch;
@@ -0,0 +1,6 @@
+-- Edit distance for the Postgres search backend
+--
+-- Fuzzy searches bound the number of edits between a query word and a
+-- name or identifier, which trigram similarity alone cannot express.
+
+CREATE EXTENSION IF NOT EXISTS fuzzystrmatch;
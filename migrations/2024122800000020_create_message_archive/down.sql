@@ -0,0 +1,3 @@
+-- Drop the inbound message archive
+
+DROP TABLE IF EXISTS message_archive CASCADE;
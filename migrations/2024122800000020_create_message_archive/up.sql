@@ -0,0 +1,19 @@
+-- Archive of inbound payloads
+--
+-- Each create or update through the REST, FHIR or HL7 interfaces keeps the
+-- payload exactly as received, linked to the patient and to the audit log
+-- entry of the change.
+
+CREATE TABLE message_archive (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    audit_log_id UUID REFERENCES audit_log(id) ON DELETE SET NULL,
+    action VARCHAR(50) NOT NULL,
+    channel VARCHAR(20) NOT NULL,
+    content_type VARCHAR(255) NOT NULL,
+    payload TEXT NOT NULL,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_message_archive_patient ON message_archive(patient_id, received_at DESC);
+CREATE INDEX idx_message_archive_audit_log ON message_archive(audit_log_id);
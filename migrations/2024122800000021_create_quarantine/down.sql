@@ -0,0 +1,3 @@
+-- Drop the inbound record quarantine
+
+DROP TABLE IF EXISTS quarantined_records CASCADE;
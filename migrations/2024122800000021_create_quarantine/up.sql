@@ -0,0 +1,19 @@
+-- Quarantine of inbound records that failed validation
+--
+-- Refused HL7 messages and FHIR resources are kept with their errors until
+-- a steward resubmits or discards them.
+
+CREATE TABLE quarantined_records (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    channel VARCHAR(20) NOT NULL,
+    content_type VARCHAR(255) NOT NULL,
+    payload TEXT NOT NULL,
+    errors JSONB NOT NULL,
+    patient_id UUID REFERENCES patients(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    received_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    resolved_at TIMESTAMPTZ,
+    resolved_patient_id UUID
+);
+
+CREATE INDEX idx_quarantined_records_status ON quarantined_records(status, received_at);
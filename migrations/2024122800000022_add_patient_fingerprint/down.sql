@@ -0,0 +1,6 @@
+-- Drop exact-duplicate fingerprints
+
+DROP INDEX IF EXISTS idx_patients_fingerprint;
+
+ALTER TABLE patients
+    DROP COLUMN IF EXISTS fingerprint;
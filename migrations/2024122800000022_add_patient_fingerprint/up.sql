@@ -0,0 +1,12 @@
+-- Exact-duplicate fingerprints
+--
+-- SHA-256 of the normalized legal name, birth date, sex and SSN last four,
+-- written with every create and update. Ingestion looks a record's
+-- fingerprint up before matching, so resubmissions of a stored patient are
+-- recognized without scoring. Patients stored before this migration get
+-- theirs at their next update.
+
+ALTER TABLE patients
+    ADD COLUMN fingerprint VARCHAR(64);
+
+CREATE INDEX idx_patients_fingerprint ON patients(fingerprint) WHERE deleted_at IS NULL;
@@ -0,0 +1,3 @@
+-- Drop the MRN sequences
+
+DROP TABLE IF EXISTS mrn_sequences CASCADE;
@@ -0,0 +1,11 @@
+-- MRN sequences
+--
+-- One row per assigning authority this MPI issues MRNs for. Numbers are
+-- taken with a single upsert, which locks the row, so concurrent requests
+-- never receive the same one.
+
+CREATE TABLE mrn_sequences (
+    system VARCHAR(255) PRIMARY KEY,
+    next_value BIGINT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,4 @@
+-- Remove patient confidentiality
+
+DROP INDEX IF EXISTS idx_patients_confidentiality;
+ALTER TABLE patients DROP COLUMN IF EXISTS confidentiality;
@@ -0,0 +1,8 @@
+-- Patient confidentiality
+--
+-- Restricted and VIP patients are shown only to privileged users; a
+-- privacy officer sets the level.
+
+ALTER TABLE patients ADD COLUMN confidentiality VARCHAR(20) NOT NULL DEFAULT 'normal';
+
+CREATE INDEX idx_patients_confidentiality ON patients(confidentiality) WHERE confidentiality <> 'normal';
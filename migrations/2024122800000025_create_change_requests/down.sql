@@ -0,0 +1,3 @@
+-- Drop patient change requests
+
+DROP TABLE IF EXISTS change_requests CASCADE;
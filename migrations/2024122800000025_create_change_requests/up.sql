@@ -0,0 +1,20 @@
+-- Patient-proposed demographic corrections
+--
+-- Corrections submitted through a patient portal wait here until a steward
+-- approves them, which applies them to the patient, or rejects them.
+
+CREATE TABLE change_requests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    changes JSONB NOT NULL,
+    reason TEXT,
+    submitted_by VARCHAR(255),
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    submitted_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    reviewed_by VARCHAR(255),
+    reviewed_at TIMESTAMPTZ,
+    review_note TEXT
+);
+
+CREATE INDEX idx_change_requests_status ON change_requests(status, submitted_at);
+CREATE INDEX idx_change_requests_patient ON change_requests(patient_id, submitted_at);
@@ -0,0 +1,3 @@
+-- Drop master record field provenance
+
+DROP TABLE IF EXISTS patient_field_provenance CASCADE;
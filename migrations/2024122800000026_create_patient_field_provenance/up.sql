@@ -0,0 +1,12 @@
+-- Provenance of master record demographics
+--
+-- One row per patient field, naming the source that supplied its current
+-- value. Survivorship consults it before letting a source overwrite a field.
+
+CREATE TABLE patient_field_provenance (
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    field VARCHAR(50) NOT NULL,
+    source VARCHAR(255) NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (patient_id, field)
+);
@@ -0,0 +1,4 @@
+-- Remove address periods
+
+ALTER TABLE patient_addresses DROP COLUMN IF EXISTS period_end;
+ALTER TABLE patient_addresses DROP COLUMN IF EXISTS period_start;
@@ -0,0 +1,7 @@
+-- Address periods
+--
+-- An address an update drops is kept with the end of its period set, so
+-- patients can be matched on where they used to live.
+
+ALTER TABLE patient_addresses ADD COLUMN period_start TIMESTAMPTZ;
+ALTER TABLE patient_addresses ADD COLUMN period_end TIMESTAMPTZ;
@@ -0,0 +1,4 @@
+-- Drop duplicate review decisions
+
+DROP INDEX IF EXISTS idx_duplicate_candidates_decided;
+DROP TABLE IF EXISTS duplicate_decisions CASCADE;
@@ -0,0 +1,18 @@
+-- Steward decisions on the duplicate review queue
+--
+-- Every confirm, reject and reopen is kept, with who made it and why, so a
+-- pair's current status in duplicate_candidates can always be explained and
+-- decided pairs can be exported as labeled training data.
+
+CREATE TABLE duplicate_decisions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    duplicate_candidate_id UUID NOT NULL REFERENCES duplicate_candidates(id) ON DELETE CASCADE,
+    action VARCHAR(20) NOT NULL,
+    reason_code VARCHAR(40) NOT NULL,
+    reason TEXT,
+    decided_by VARCHAR(255),
+    decided_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_duplicate_decisions_candidate ON duplicate_decisions(duplicate_candidate_id, decided_at);
+CREATE INDEX idx_duplicate_candidates_decided ON duplicate_candidates(detected_at) WHERE status <> 'pending';
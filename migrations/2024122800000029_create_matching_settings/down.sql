@@ -0,0 +1,3 @@
+-- Drop stored matching profiles
+
+DROP TABLE IF EXISTS matching_settings CASCADE;
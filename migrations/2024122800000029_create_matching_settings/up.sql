@@ -0,0 +1,17 @@
+-- Matching profiles stored through the admin API
+--
+-- Each change to a profile's threshold, weights or blocking is a new row
+-- with the next version number; the highest version is in effect. The
+-- profile named 'default' holds the settings of the `matching` section.
+
+CREATE TABLE matching_settings (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    profile VARCHAR(100) NOT NULL,
+    version INTEGER NOT NULL,
+    settings JSONB NOT NULL,
+    changed_by VARCHAR(255),
+    note TEXT,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (profile, version),
+    CHECK (version > 0)
+);
@@ -0,0 +1,3 @@
+-- Remove patient photo hashes
+
+ALTER TABLE patients DROP COLUMN IF EXISTS photo_hashes;
@@ -0,0 +1,6 @@
+-- Patient photo hashes
+--
+-- Photos are not stored, but a perceptual hash of each is kept when it is
+-- uploaded, for matching and for stewards reviewing possible duplicates.
+
+ALTER TABLE patients ADD COLUMN photo_hashes TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,4 @@
+-- Remove patient groups
+
+DROP TABLE IF EXISTS patient_group_members;
+DROP TABLE IF EXISTS patient_groups;
@@ -0,0 +1,24 @@
+-- Patient groups
+--
+-- Named cohorts of patients, such as a dedup cluster or the output of a
+-- batch match job, exposed as FHIR Group resources and usable as the input
+-- to exports and batch matching. Members leave a group when their patient
+-- row is removed.
+
+CREATE TABLE patient_groups (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    created_by VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE patient_group_members (
+    group_id UUID NOT NULL REFERENCES patient_groups(id) ON DELETE CASCADE,
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    added_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (group_id, patient_id)
+);
+
+CREATE INDEX idx_patient_group_members_patient ON patient_group_members(patient_id);
@@ -0,0 +1,9 @@
+-- Remove source record link confidence
+
+DROP INDEX IF EXISTS idx_source_record_links_reverification;
+
+ALTER TABLE source_record_links
+    DROP COLUMN IF EXISTS verified_by,
+    DROP COLUMN IF EXISTS verified_at,
+    DROP COLUMN IF EXISTS needs_reverification,
+    DROP COLUMN IF EXISTS confidence;
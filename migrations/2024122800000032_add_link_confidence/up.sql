@@ -0,0 +1,22 @@
+-- Confidence in source record links
+--
+-- Each link records how confident the MPI is that the source record belongs
+-- to its enterprise record: the match score when the link was made, or 1 for
+-- links made without one. A scheduled job lowers it as the enterprise
+-- record's name and address drift from the source record's, and flags
+-- automatic links that fall too low for a steward to verify again.
+
+ALTER TABLE source_record_links
+    ADD COLUMN confidence NUMERIC(5, 4) NOT NULL DEFAULT 1,
+    ADD COLUMN needs_reverification BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN verified_at TIMESTAMPTZ,
+    ADD COLUMN verified_by VARCHAR(255);
+
+UPDATE source_record_links
+SET confidence = LEAST(GREATEST(match_score, 0), 1)
+WHERE match_score IS NOT NULL;
+
+-- Steward re-verification queue
+CREATE INDEX idx_source_record_links_reverification
+    ON source_record_links(confidence)
+    WHERE needs_reverification AND unlinked_at IS NULL;
@@ -0,0 +1,5 @@
+-- Remove patient lifecycle status
+
+DROP INDEX IF EXISTS idx_patients_status;
+
+ALTER TABLE patients DROP COLUMN IF EXISTS status;
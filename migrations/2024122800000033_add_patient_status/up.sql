@@ -0,0 +1,21 @@
+-- Patient lifecycle status
+--
+-- Whether a patient is active, inactive, deceased, merged into another
+-- record or entered in error, stored explicitly rather than read from the
+-- active and deceased flags and the patient's links. Existing patients take
+-- the status their flags and links imply.
+
+ALTER TABLE patients ADD COLUMN status VARCHAR(32) NOT NULL DEFAULT 'active'
+    CHECK (status IN ('active', 'inactive', 'deceased', 'merged', 'entered-in-error'));
+
+UPDATE patients p
+SET status = CASE
+    WHEN NOT p.active AND EXISTS (
+        SELECT 1 FROM patient_links l WHERE l.patient_id = p.id AND l.link_type = 'ReplacedBy'
+    ) THEN 'merged'
+    WHEN p.deceased OR p.deceased_datetime IS NOT NULL THEN 'deceased'
+    WHEN NOT p.active THEN 'inactive'
+    ELSE 'active'
+END;
+
+CREATE INDEX idx_patients_status ON patients(status) WHERE status <> 'active';
@@ -0,0 +1,3 @@
+-- Drop bulk import checkpoints
+
+DROP TABLE IF EXISTS import_checkpoints CASCADE;
@@ -0,0 +1,26 @@
+-- Bulk import checkpoints
+--
+-- One row per NDJSON patient import, recording the byte offset of the
+-- first unprocessed line and the running counts. An import that stops part
+-- way through is resumed from its offset; lines processed after the last
+-- checkpoint are recognized by record fingerprint and not stored twice.
+
+CREATE TABLE import_checkpoints (
+    id UUID PRIMARY KEY,
+    path TEXT NOT NULL,
+    requested_by VARCHAR(255),
+    status VARCHAR(20) NOT NULL DEFAULT 'running',
+    job_id UUID NOT NULL,
+    byte_offset BIGINT NOT NULL DEFAULT 0,
+    lines_processed BIGINT NOT NULL DEFAULT 0,
+    created BIGINT NOT NULL DEFAULT 0,
+    duplicates BIGINT NOT NULL DEFAULT 0,
+    invalid BIGINT NOT NULL DEFAULT 0,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (status IN ('running', 'completed', 'failed')),
+    CHECK (byte_offset >= 0)
+);
+
+CREATE INDEX idx_import_checkpoints_created_at ON import_checkpoints(created_at DESC);
@@ -0,0 +1,7 @@
+-- Remove identifier and contact point periods
+
+ALTER TABLE patient_contacts DROP COLUMN IF EXISTS period_end;
+ALTER TABLE patient_contacts DROP COLUMN IF EXISTS period_start;
+
+ALTER TABLE patient_identifiers DROP COLUMN IF EXISTS period_end;
+ALTER TABLE patient_identifiers DROP COLUMN IF EXISTS period_start;
@@ -0,0 +1,11 @@
+-- Identifier and contact point periods
+--
+-- Feeds supply when an identifier or contact point took effect and when it
+-- was retired, as they already do for addresses. Matching counts an expired
+-- identifier for less than a current one.
+
+ALTER TABLE patient_identifiers ADD COLUMN period_start TIMESTAMPTZ;
+ALTER TABLE patient_identifiers ADD COLUMN period_end TIMESTAMPTZ;
+
+ALTER TABLE patient_contacts ADD COLUMN period_start TIMESTAMPTZ;
+ALTER TABLE patient_contacts ADD COLUMN period_end TIMESTAMPTZ;
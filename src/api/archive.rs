@@ -0,0 +1,109 @@
+//! Archiving of inbound payloads
+//!
+//! Handlers that create or update patients read their body through
+//! [`WithRawBody`], which keeps the bytes alongside the parsed value, and
+//! hand them to [`archive`] once the change is stored. The archive answers
+//! "where did this address come from" long after the sending system's own
+//! logs have rotated.
+
+use axum::{
+    async_trait,
+    body::{Body, Bytes},
+    extract::{FromRequest, Request},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use uuid::Uuid;
+
+use crate::db::MessageArchiveRepository;
+
+/// A request body as received
+#[derive(Debug, Clone)]
+pub struct RawBody {
+    /// `Content-Type` of the request, or `application/octet-stream` when absent
+    pub content_type: String,
+    pub bytes: Bytes,
+}
+
+/// Extractor running `E` on the request body while keeping the raw body
+///
+/// Rejections are `E`'s own, so a handler behaves as it would with `E` alone.
+pub struct WithRawBody<E>(pub E, pub RawBody);
+
+#[async_trait]
+impl<S, E> FromRequest<S> for WithRawBody<E>
+where
+    S: Send + Sync,
+    E: FromRequest<S>,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let (parts, body) = request.into_parts();
+        let content_type = parts
+            .headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        let bytes = Bytes::from_request(Request::from_parts(parts.clone(), body), state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let value = E::from_request(Request::from_parts(parts, Body::from(bytes.clone())), state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        Ok(Self(value, RawBody { content_type, bytes }))
+    }
+}
+
+/// Archive the payload behind a stored change
+///
+/// Failures are logged and never fail the change, which is already stored.
+pub fn archive(
+    archive: &dyn MessageArchiveRepository,
+    patient_id: Uuid,
+    action: &str,
+    channel: &str,
+    content_type: &str,
+    payload: &[u8],
+) {
+    let payload = String::from_utf8_lossy(payload);
+    if let Err(e) = archive.archive(&patient_id, action, channel, content_type, &payload) {
+        tracing::warn!("Failed to archive {} payload for patient {}: {}", channel, patient_id, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, routing::post, Json, Router};
+    use tower::ServiceExt;
+
+    async fn echo(WithRawBody(Json(value), raw): WithRawBody<Json<serde_json::Value>>) -> Bytes {
+        assert_eq!(value["family"], "Nakamura");
+        assert_eq!(raw.content_type, "application/json");
+        raw.bytes
+    }
+
+    #[tokio::test]
+    async fn test_raw_body_kept_and_rejections_preserved() {
+        let app = Router::new().route("/", post(echo));
+
+        let body = r#"{ "family":  "Nakamura" }"#;
+        let request = Request::builder()
+            .method("POST")
+            .uri("/")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let echoed = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        // Whitespace and all, not a re-serialization
+        assert_eq!(echoed, body.as_bytes());
+
+        let request = Request::builder().method("POST").uri("/").body(Body::from(body)).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
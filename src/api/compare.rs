@@ -0,0 +1,189 @@
+//! Side-by-side comparison of two patient records
+//!
+//! A steward deciding whether two records are the same person reads them
+//! field by field. [`compare`] lines up each demographic field of both
+//! records, says whether they agree, and carries the matcher's score for
+//! the fields it weighs, so the review UI need not fetch both records and
+//! diff them itself.
+
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::matching::{quality_label, MatchResult, MatchScoreBreakdown};
+use crate::models::Patient;
+
+/// How the two records' values of a field relate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldAgreement {
+    /// Both have the same value; lists are compared regardless of order
+    Same,
+    /// Both have a value and they differ
+    Different,
+    /// Only record `a` has a value
+    OnlyA,
+    /// Only record `b` has a value
+    OnlyB,
+    /// Neither has a value
+    Neither,
+}
+
+/// One field of both records
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FieldComparison {
+    /// Field name, as in the patient resource
+    pub field: String,
+    /// Value in record `a`
+    #[schema(value_type = Object)]
+    pub a: serde_json::Value,
+    /// Value in record `b`
+    #[schema(value_type = Object)]
+    pub b: serde_json::Value,
+    pub agreement: FieldAgreement,
+    /// Matcher score of the field, for the fields the matcher weighs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+}
+
+/// Two patient records side by side, with their match score
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PatientComparison {
+    pub a: Uuid,
+    pub b: Uuid,
+    /// Overall match score
+    pub score: f64,
+    /// "certain", "probable" or "possible"
+    pub quality: String,
+    /// Whether the score meets the matcher's threshold
+    pub is_match: bool,
+    /// Components that matched well
+    pub summary: String,
+    pub breakdown: MatchScoreBreakdown,
+    pub fields: Vec<FieldComparison>,
+}
+
+/// Compare `a` and `b` field by field, given the matcher's result for the pair
+pub fn compare(a: &Patient, b: &Patient, result: &MatchResult, is_match: bool) -> PatientComparison {
+    let breakdown = &result.breakdown;
+    let fields = vec![
+        field("name", &a.name, &b.name, Some(breakdown.name_score)),
+        field("additional_names", &a.additional_names, &b.additional_names, None),
+        field("birth_date", a.birth_date, b.birth_date, Some(breakdown.birth_date_score)),
+        field("gender", &a.gender, &b.gender, Some(breakdown.gender_score)),
+        field("gender_identity", &a.gender_identity, &b.gender_identity, None),
+        field("pronouns", &a.pronouns, &b.pronouns, None),
+        field("identifiers", &a.identifiers, &b.identifiers, Some(breakdown.identifier_score)),
+        field("addresses", &a.addresses, &b.addresses, Some(breakdown.address_score)),
+        field("telecom", &a.telecom, &b.telecom, None),
+        field("marital_status", &a.marital_status, &b.marital_status, None),
+        field("multiple_birth", a.multiple_birth, b.multiple_birth, None),
+        field("deceased", a.deceased, b.deceased, None),
+        field("deceased_datetime", a.deceased_datetime, b.deceased_datetime, None),
+        field("photo_hashes", &a.photo_hashes, &b.photo_hashes, breakdown.photo_score),
+        field("managing_organization", a.managing_organization, b.managing_organization, None),
+        field("status", &a.status, &b.status, None),
+    ];
+    PatientComparison {
+        a: a.id,
+        b: b.id,
+        score: result.score,
+        quality: quality_label(result.score).to_string(),
+        is_match,
+        summary: breakdown.summary(),
+        breakdown: breakdown.clone(),
+        fields,
+    }
+}
+
+fn field<T: Serialize>(name: &str, a: T, b: T, score: Option<f64>) -> FieldComparison {
+    let a = serde_json::to_value(a).unwrap_or_default();
+    let b = serde_json::to_value(b).unwrap_or_default();
+    let agreement = match (is_empty(&a), is_empty(&b)) {
+        (true, true) => FieldAgreement::Neither,
+        (false, true) => FieldAgreement::OnlyA,
+        (true, false) => FieldAgreement::OnlyB,
+        (false, false) if same(&a, &b) => FieldAgreement::Same,
+        (false, false) => FieldAgreement::Different,
+    };
+    FieldComparison {
+        field: name.to_string(),
+        a,
+        b,
+        agreement,
+        score,
+    }
+}
+
+fn is_empty(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Null => true,
+        serde_json::Value::String(s) => s.is_empty(),
+        serde_json::Value::Array(items) => items.is_empty(),
+        _ => false,
+    }
+}
+
+/// Equal values, with lists compared as multisets
+fn same(a: &serde_json::Value, b: &serde_json::Value) -> bool {
+    match (a, b) {
+        (serde_json::Value::Array(a), serde_json::Value::Array(b)) => sorted(a) == sorted(b),
+        _ => a == b,
+    }
+}
+
+fn sorted(items: &[serde_json::Value]) -> Vec<String> {
+    let mut items: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+    items.sort();
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::config::Config;
+    use crate::matching::{PatientMatcher, ProbabilisticMatcher};
+    use crate::models::{ContactPoint, ContactPointSystem, Gender, Identifier};
+
+    fn patient(given: &str, phone: Option<&str>) -> Patient {
+        let mut patient = crate::fixtures::patient("Kowalczyk", &[given], Gender::Female);
+        patient.birth_date = NaiveDate::from_ymd_opt(1979, 11, 2);
+        patient.identifiers = vec![
+            Identifier::ssn("345-67-8901".to_string()),
+            Identifier::mrn("GENERAL".to_string(), "200".to_string()),
+        ];
+        patient.telecom = phone
+            .map(|phone| ContactPoint {
+                system: ContactPointSystem::Phone,
+                value: phone.to_string(),
+                use_type: None,
+                period: None,
+            })
+            .into_iter()
+            .collect();
+        patient
+    }
+
+    #[test]
+    fn test_compare_lines_up_fields() {
+        let a = patient("Agnieszka", Some("555-0100"));
+        let mut b = patient("Agnes", None);
+        b.identifiers.reverse();
+
+        let matcher = ProbabilisticMatcher::new(Config::default().matching);
+        let result = matcher.match_patients(&a, &b).unwrap();
+        let comparison = compare(&a, &b, &result, matcher.is_match(result.score));
+        let agreement = |name: &str| {
+            let field = comparison.fields.iter().find(|f| f.field == name).unwrap();
+            (field.agreement, field.score)
+        };
+
+        assert_eq!((comparison.a, comparison.b), (a.id, b.id));
+        assert_eq!(agreement("name"), (FieldAgreement::Different, Some(result.breakdown.name_score)));
+        assert_eq!(agreement("birth_date"), (FieldAgreement::Same, Some(result.breakdown.birth_date_score)));
+        assert_eq!(agreement("identifiers").0, FieldAgreement::Same);
+        assert_eq!(agreement("telecom"), (FieldAgreement::OnlyA, None));
+        assert_eq!(agreement("marital_status"), (FieldAgreement::Neither, None));
+    }
+}
@@ -0,0 +1,105 @@
+//! Conditional GET support shared by the REST and FHIR routes
+//!
+//! A patient's ETag is a weak validator derived from `updated_at`, which
+//! every write bumps, and from whether the patient is shown masked. Clients
+//! polling a patient send it back in `If-None-Match` and get `304 Not
+//! Modified` with no body until the patient changes.
+//!
+//! Reference data such as the assigning authority registry is instead
+//! served with a `Cache-Control` lifetime, so registration front ends
+//! looking it up on every form stop asking for a while.
+
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::Response,
+};
+
+use crate::models::Patient;
+
+/// Weak ETag for the current version of a patient, as shown masked or not
+///
+/// The masked view has its own tag, so a client shown one view never
+/// revalidates a cached copy of the other.
+pub fn patient_etag(patient: &Patient, masked: bool) -> HeaderValue {
+    let version = patient.updated_at.timestamp_micros();
+    let value = match masked {
+        true => format!("W/\"{}-masked\"", version),
+        false => format!("W/\"{}\"", version),
+    };
+    HeaderValue::from_str(&value).expect("ETag is ASCII")
+}
+
+/// Whether `If-None-Match` lists `etag`, or is `*`
+///
+/// Uses the weak comparison RFC 9110 requires for `If-None-Match`, so the
+/// `W/` prefix is ignored on both sides.
+pub fn if_none_match(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    let Ok(etag) = etag.to_str() else {
+        return false;
+    };
+    let etag = opaque_tag(etag);
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || opaque_tag(candidate) == etag)
+}
+
+fn opaque_tag(etag: &str) -> &str {
+    etag.strip_prefix("W/").unwrap_or(etag)
+}
+
+/// `304 Not Modified` carrying the unchanged ETag
+pub fn not_modified(etag: HeaderValue) -> Response {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::NOT_MODIFIED;
+    response.headers_mut().insert(header::ETAG, etag);
+    response
+}
+
+/// `Cache-Control` letting a client reuse a lookup response for
+/// `max_age_secs`, or revalidate every time when 0
+///
+/// Responses are `private`: they come from an authenticated API and must
+/// not be stored by shared caches.
+pub fn lookup_cache_control(max_age_secs: u64) -> HeaderValue {
+    let value = match max_age_secs {
+        0 => "private, no-cache".to_string(),
+        max_age => format!("private, max-age={}", max_age),
+    };
+    HeaderValue::from_str(&value).expect("Cache-Control is ASCII")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::patient;
+    use crate::models::Gender;
+
+    fn if_none_match_header(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_if_none_match() {
+        let mut patient = patient("Okafor", &["Ada"], Gender::Female);
+        let etag = patient_etag(&patient, false);
+        let current = etag.to_str().unwrap().to_string();
+
+        assert!(!if_none_match(&HeaderMap::new(), &etag));
+        assert!(if_none_match(&if_none_match_header(&current), &etag));
+        assert!(if_none_match(&if_none_match_header(current.trim_start_matches("W/")), &etag));
+        assert!(if_none_match(&if_none_match_header(&format!("\"other\", {}", current)), &etag));
+        assert!(if_none_match(&if_none_match_header("*"), &etag));
+
+        assert!(!if_none_match(&if_none_match_header(&current), &patient_etag(&patient, true)));
+
+        patient.updated_at += chrono::Duration::seconds(1);
+        assert!(!if_none_match(&if_none_match_header(&current), &patient_etag(&patient, false)));
+    }
+}
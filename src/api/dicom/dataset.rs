@@ -0,0 +1,235 @@
+//! DICOM data sets in Implicit VR Little Endian
+//!
+//! Command sets always use this transfer syntax, and it is the only one the
+//! adapter negotiates for query identifiers, so element values are kept as
+//! raw bytes and read as strings or integers by the caller, who knows each
+//! tag's VR.
+
+use std::collections::BTreeMap;
+
+use crate::{Error, Result};
+
+/// A (group, element) data element tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Tag(pub u16, pub u16);
+
+impl std::fmt::Display for Tag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({:04X},{:04X})", self.0, self.1)
+    }
+}
+
+pub mod tags {
+    use super::Tag;
+
+    pub const COMMAND_GROUP_LENGTH: Tag = Tag(0x0000, 0x0000);
+    pub const AFFECTED_SOP_CLASS_UID: Tag = Tag(0x0000, 0x0002);
+    pub const COMMAND_FIELD: Tag = Tag(0x0000, 0x0100);
+    pub const MESSAGE_ID: Tag = Tag(0x0000, 0x0110);
+    pub const MESSAGE_ID_BEING_RESPONDED_TO: Tag = Tag(0x0000, 0x0120);
+    pub const COMMAND_DATA_SET_TYPE: Tag = Tag(0x0000, 0x0800);
+    pub const STATUS: Tag = Tag(0x0000, 0x0900);
+    pub const ERROR_COMMENT: Tag = Tag(0x0000, 0x0902);
+
+    pub const SPECIFIC_CHARACTER_SET: Tag = Tag(0x0008, 0x0005);
+    pub const QUERY_RETRIEVE_LEVEL: Tag = Tag(0x0008, 0x0052);
+    pub const PATIENT_NAME: Tag = Tag(0x0010, 0x0010);
+    pub const PATIENT_ID: Tag = Tag(0x0010, 0x0020);
+    pub const ISSUER_OF_PATIENT_ID: Tag = Tag(0x0010, 0x0021);
+    pub const PATIENT_BIRTH_DATE: Tag = Tag(0x0010, 0x0030);
+    pub const PATIENT_SEX: Tag = Tag(0x0010, 0x0040);
+
+    /// Item and delimiters inside sequences of undefined length
+    pub const ITEM: Tag = Tag(0xFFFE, 0xE000);
+    pub const ITEM_DELIMITATION: Tag = Tag(0xFFFE, 0xE00D);
+    pub const SEQUENCE_DELIMITATION: Tag = Tag(0xFFFE, 0xE0DD);
+}
+
+/// `CommandDataSetType` value meaning no data set follows the command
+pub const NO_DATA_SET: u16 = 0x0101;
+
+const UNDEFINED_LENGTH: u32 = 0xFFFF_FFFF;
+
+/// Elements of a data set, ordered by tag
+///
+/// Sequences are skipped when decoding; the adapter neither matches on nor
+/// returns them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dataset {
+    elements: BTreeMap<Tag, Vec<u8>>,
+}
+
+impl Dataset {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contains(&self, tag: Tag) -> bool {
+        self.elements.contains_key(&tag)
+    }
+
+    pub fn tags(&self) -> impl Iterator<Item = Tag> + '_ {
+        self.elements.keys().copied()
+    }
+
+    /// A text value with its padding removed, or `None` when absent
+    pub fn string(&self, tag: Tag) -> Option<String> {
+        self.elements.get(&tag).map(|value| {
+            String::from_utf8_lossy(value)
+                .trim_end_matches(['\0', ' '])
+                .trim_start()
+                .to_string()
+        })
+    }
+
+    pub fn u16(&self, tag: Tag) -> Option<u16> {
+        self.elements
+            .get(&tag)
+            .and_then(|v| v.get(..2))
+            .map(|v| u16::from_le_bytes([v[0], v[1]]))
+    }
+
+    /// Set a text value, padded to even length with a space
+    pub fn set_string(&mut self, tag: Tag, value: &str) {
+        self.set_padded(tag, value, b' ');
+    }
+
+    /// Set a UI value, padded to even length with a NUL
+    pub fn set_uid(&mut self, tag: Tag, value: &str) {
+        self.set_padded(tag, value, b'\0');
+    }
+
+    pub fn set_u16(&mut self, tag: Tag, value: u16) {
+        self.elements.insert(tag, value.to_le_bytes().to_vec());
+    }
+
+    fn set_padded(&mut self, tag: Tag, value: &str, padding: u8) {
+        let mut bytes = value.as_bytes().to_vec();
+        if bytes.len() % 2 == 1 {
+            bytes.push(padding);
+        }
+        self.elements.insert(tag, bytes);
+    }
+
+    /// Encode, computing `CommandGroupLength` when this is a command set
+    pub fn encode(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (tag, value) in self.elements.iter().filter(|(t, _)| **t != tags::COMMAND_GROUP_LENGTH) {
+            write_element(&mut body, *tag, value);
+        }
+
+        if self.elements.keys().any(|t| t.0 == 0x0000) {
+            let mut command = Vec::with_capacity(body.len() + 12);
+            write_element(&mut command, tags::COMMAND_GROUP_LENGTH, &(body.len() as u32).to_le_bytes());
+            command.extend_from_slice(&body);
+            command
+        } else {
+            body
+        }
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let mut elements = BTreeMap::new();
+        let mut pos = 0;
+
+        while pos < data.len() {
+            let (tag, length) = element_header(data, pos)?;
+            pos += 8;
+            if length == UNDEFINED_LENGTH {
+                pos = skip_undefined(data, pos)?;
+                continue;
+            }
+            let end = pos + length as usize;
+            let value = data
+                .get(pos..end)
+                .ok_or_else(|| Error::Validation(format!("DICOM element {} is truncated", tag)))?;
+            elements.insert(tag, value.to_vec());
+            pos = end;
+        }
+
+        Ok(Self { elements })
+    }
+}
+
+fn write_element(out: &mut Vec<u8>, tag: Tag, value: &[u8]) {
+    out.extend_from_slice(&tag.0.to_le_bytes());
+    out.extend_from_slice(&tag.1.to_le_bytes());
+    out.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    out.extend_from_slice(value);
+}
+
+fn element_header(data: &[u8], pos: usize) -> Result<(Tag, u32)> {
+    let header = data
+        .get(pos..pos + 8)
+        .ok_or_else(|| Error::Validation("DICOM element header is truncated".to_string()))?;
+    let tag = Tag(
+        u16::from_le_bytes([header[0], header[1]]),
+        u16::from_le_bytes([header[2], header[3]]),
+    );
+    Ok((tag, u32::from_le_bytes([header[4], header[5], header[6], header[7]])))
+}
+
+/// Skip the contents of a sequence or item of undefined length, returning
+/// the position after its delimiter
+fn skip_undefined(data: &[u8], mut pos: usize) -> Result<usize> {
+    let mut depth = 1;
+    while depth > 0 {
+        let (tag, length) = element_header(data, pos)?;
+        pos += 8;
+        match (tag, length) {
+            (tags::ITEM_DELIMITATION | tags::SEQUENCE_DELIMITATION, _) => depth -= 1,
+            (_, UNDEFINED_LENGTH) => depth += 1,
+            _ => pos += length as usize,
+        }
+    }
+    Ok(pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut dataset = Dataset::new();
+        dataset.set_string(tags::PATIENT_NAME, "Smith^John");
+        dataset.set_string(tags::PATIENT_ID, "A100");
+        dataset.set_string(tags::PATIENT_BIRTH_DATE, "");
+
+        let decoded = Dataset::decode(&dataset.encode()).unwrap();
+        assert_eq!(decoded, dataset);
+        assert_eq!(decoded.string(tags::PATIENT_NAME).as_deref(), Some("Smith^John"));
+        assert_eq!(decoded.string(tags::PATIENT_BIRTH_DATE).as_deref(), Some(""));
+        assert_eq!(decoded.string(tags::PATIENT_SEX), None);
+    }
+
+    #[test]
+    fn test_command_group_length() {
+        let mut command = Dataset::new();
+        command.set_u16(tags::COMMAND_FIELD, 0x8030);
+        command.set_u16(tags::STATUS, 0);
+        let encoded = command.encode();
+
+        // Group length element, then two 10-byte elements
+        assert_eq!(&encoded[8..12], &20u32.to_le_bytes());
+        assert_eq!(Dataset::decode(&encoded).unwrap().u16(tags::COMMAND_FIELD), Some(0x8030));
+    }
+
+    #[test]
+    fn test_skips_undefined_length_sequence() {
+        let mut data = Vec::new();
+        write_element(&mut data, tags::PATIENT_ID, b"A100");
+        // Other Patient IDs Sequence with one undefined-length item
+        data.extend_from_slice(&[0x10, 0x00, 0x00, 0x12, 0xFF, 0xFF, 0xFF, 0xFF]);
+        data.extend_from_slice(&[0xFE, 0xFF, 0x00, 0xE0, 0xFF, 0xFF, 0xFF, 0xFF]);
+        write_element(&mut data, tags::PATIENT_ID, b"B200");
+        data.extend_from_slice(&[0xFE, 0xFF, 0x0D, 0xE0, 0, 0, 0, 0]);
+        data.extend_from_slice(&[0xFE, 0xFF, 0xDD, 0xE0, 0, 0, 0, 0]);
+        write_element(&mut data, tags::PATIENT_SEX, b"F ");
+
+        let dataset = Dataset::decode(&data).unwrap();
+        assert_eq!(dataset.string(tags::PATIENT_ID).as_deref(), Some("A100"));
+        assert_eq!(dataset.string(tags::PATIENT_SEX).as_deref(), Some("F"));
+        assert_eq!(dataset.tags().count(), 2);
+    }
+}
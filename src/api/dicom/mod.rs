@@ -0,0 +1,256 @@
+//! DICOM patient-root C-FIND adapter
+//!
+//! Lets imaging systems (PACS, modalities, RIS) cross-check demographics
+//! against the master index with a PATIENT-level Patient Root
+//! Query/Retrieve C-FIND. Queries may use PatientName, PatientID (with
+//! IssuerOfPatientID), PatientBirthDate and PatientSex as matching keys.
+//! C-ECHO is answered as well so peers can verify connectivity.
+//!
+//! Only Implicit VR Little Endian is negotiated, which every DICOM
+//! implementation supports. Patients are read-only over DICOM.
+
+pub mod dataset;
+pub mod pdu;
+pub mod query;
+
+use std::collections::HashMap;
+
+use tokio::io::{AsyncWrite, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::api::rest::AppState;
+use crate::Result;
+
+use dataset::{tags, Dataset, NO_DATA_SET};
+use pdu::{ContextResult, Pdu};
+
+/// Verification SOP class (C-ECHO)
+pub const VERIFICATION: &str = "1.2.840.10008.1.1";
+/// Patient Root Query/Retrieve Information Model - FIND
+pub const PATIENT_ROOT_FIND: &str = "1.2.840.10008.5.1.4.1.2.1.1";
+/// Implicit VR Little Endian transfer syntax
+pub const IMPLICIT_VR_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2";
+/// Implementation Class UID sent in association responses
+pub const IMPLEMENTATION_CLASS_UID: &str = "2.25.100856668006127990821259056019139441966";
+
+const C_FIND_RQ: u16 = 0x0020;
+const C_ECHO_RQ: u16 = 0x0030;
+const C_CANCEL_RQ: u16 = 0x0FFF;
+/// Response command fields set this bit on the request's
+const RESPONSE: u16 = 0x8000;
+
+const STATUS_SUCCESS: u16 = 0x0000;
+const STATUS_PENDING: u16 = 0xFF00;
+const STATUS_UNRECOGNIZED_OPERATION: u16 = 0x0211;
+
+/// Accept DICOM associations on the configured address
+pub async fn serve(state: AppState) -> Result<()> {
+    let addr = format!("{}:{}", state.config.dicom.host, state.config.dicom.port);
+    let listener = TcpListener::bind(&addr)
+        .await
+        .map_err(|e| crate::Error::Api(e.to_string()))?;
+
+    tracing::info!("DICOM C-FIND adapter listening on {} as {}", addr, state.config.dicom.ae_title);
+
+    loop {
+        let (stream, peer) = listener
+            .accept()
+            .await
+            .map_err(|e| crate::Error::Api(e.to_string()))?;
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_association(stream, &state).await {
+                tracing::warn!("DICOM association from {} ended: {}", peer, e);
+            }
+        });
+    }
+}
+
+/// Decide each proposed presentation context
+pub fn negotiate(contexts: &[pdu::PresentationContext]) -> Vec<ContextResult> {
+    contexts
+        .iter()
+        .map(|context| {
+            let supported = [VERIFICATION, PATIENT_ROOT_FIND].contains(&context.abstract_syntax.as_str());
+            let implicit = context.transfer_syntaxes.iter().any(|ts| ts == IMPLICIT_VR_LITTLE_ENDIAN);
+            let result = match (supported, implicit) {
+                (false, _) => 3,
+                (true, false) => 4,
+                (true, true) => 0,
+            };
+            ContextResult {
+                id: context.id,
+                result,
+                transfer_syntax: IMPLICIT_VR_LITTLE_ENDIAN.to_string(),
+            }
+        })
+        .collect()
+}
+
+async fn handle_association(stream: TcpStream, state: &AppState) -> Result<()> {
+    let config = &state.config.dicom;
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    let request = match pdu::read_pdu(&mut reader).await? {
+        Some(Pdu::AssociateRequest(request)) => request,
+        Some(_) => return pdu::write_pdu(&mut writer, &pdu::abort()).await,
+        None => return Ok(()),
+    };
+    if !request.called_ae.eq_ignore_ascii_case(&config.ae_title) {
+        tracing::info!(
+            "Rejected DICOM association from {} addressed to '{}'",
+            request.calling_ae,
+            request.called_ae
+        );
+        // Called AE title not recognized
+        return pdu::write_pdu(&mut writer, &pdu::associate_reject(7)).await;
+    }
+
+    let results = negotiate(&request.contexts);
+    pdu::write_pdu(&mut writer, &pdu::associate_accept(&request, &results, IMPLEMENTATION_CLASS_UID)).await?;
+    let accepted: HashMap<u8, &str> = request
+        .contexts
+        .iter()
+        .zip(&results)
+        .filter(|(_, result)| result.result == 0)
+        .map(|(context, _)| (context.id, context.abstract_syntax.as_str()))
+        .collect();
+
+    let mut command_bytes = Vec::new();
+    let mut data_bytes = Vec::new();
+    let mut pending_command: Option<Dataset> = None;
+
+    while let Some(pdu) = pdu::read_pdu(&mut reader).await? {
+        let pdvs = match pdu {
+            Pdu::Data(pdvs) => pdvs,
+            Pdu::ReleaseRequest => return pdu::write_pdu(&mut writer, &pdu::release_response()).await,
+            Pdu::Abort => return Ok(()),
+            Pdu::AssociateRequest(_) | Pdu::Unexpected(_) => {
+                return pdu::write_pdu(&mut writer, &pdu::abort()).await;
+            }
+        };
+
+        for pdv in pdvs {
+            if !accepted.contains_key(&pdv.context_id) {
+                return pdu::write_pdu(&mut writer, &pdu::abort()).await;
+            }
+            let buffer = if pdv.is_command { &mut command_bytes } else { &mut data_bytes };
+            buffer.extend_from_slice(&pdv.data);
+            if !pdv.is_last {
+                continue;
+            }
+
+            let (command, identifier) = if pdv.is_command {
+                let command = Dataset::decode(&std::mem::take(&mut command_bytes))?;
+                if command.u16(tags::COMMAND_DATA_SET_TYPE) != Some(NO_DATA_SET) {
+                    pending_command = Some(command);
+                    continue;
+                }
+                (command, None)
+            } else {
+                let Some(command) = pending_command.take() else {
+                    return pdu::write_pdu(&mut writer, &pdu::abort()).await;
+                };
+                (command, Some(Dataset::decode(&std::mem::take(&mut data_bytes))?))
+            };
+
+            for (response, data) in dispatch(state, &command, identifier.as_ref()) {
+                send(&mut writer, pdv.context_id, &response, data.as_ref(), request.max_pdu_length).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Responses to one DIMSE request, each a command with an optional data set
+fn dispatch(state: &AppState, command: &Dataset, identifier: Option<&Dataset>) -> Vec<(Dataset, Option<Dataset>)> {
+    let command_field = command.u16(tags::COMMAND_FIELD).unwrap_or_default();
+    let respond = |status: u16, has_data: bool| {
+        let mut response = Dataset::new();
+        if let Some(sop_class) = command.string(tags::AFFECTED_SOP_CLASS_UID) {
+            response.set_uid(tags::AFFECTED_SOP_CLASS_UID, &sop_class);
+        }
+        response.set_u16(tags::COMMAND_FIELD, command_field | RESPONSE);
+        response.set_u16(
+            tags::MESSAGE_ID_BEING_RESPONDED_TO,
+            command.u16(tags::MESSAGE_ID).unwrap_or_default(),
+        );
+        response.set_u16(tags::COMMAND_DATA_SET_TYPE, if has_data { 0x0000 } else { NO_DATA_SET });
+        response.set_u16(tags::STATUS, status);
+        response
+    };
+
+    match command_field {
+        C_ECHO_RQ => vec![(respond(STATUS_SUCCESS, false), None)],
+        // Queries are answered in full before the next request is read
+        C_CANCEL_RQ => Vec::new(),
+        C_FIND_RQ => {
+            let identifier = identifier.cloned().unwrap_or_default();
+            match query::find(state, &identifier, state.config.dicom.max_results) {
+                Ok(matches) => {
+                    let mut responses: Vec<_> = matches
+                        .into_iter()
+                        .map(|data| (respond(STATUS_PENDING, true), Some(data)))
+                        .collect();
+                    responses.push((respond(STATUS_SUCCESS, false), None));
+                    responses
+                }
+                Err(e) => {
+                    tracing::info!("DICOM C-FIND failed: {}", e.comment());
+                    let mut response = respond(e.status(), false);
+                    response.set_string(tags::ERROR_COMMENT, &truncate(e.comment(), 64));
+                    vec![(response, None)]
+                }
+            }
+        }
+        _ => vec![(respond(STATUS_UNRECOGNIZED_OPERATION, false), None)],
+    }
+}
+
+/// ErrorComment is an LO, limited to 64 characters
+fn truncate(text: &str, max: usize) -> String {
+    text.chars().take(max).collect()
+}
+
+async fn send<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    context_id: u8,
+    command: &Dataset,
+    data: Option<&Dataset>,
+    max_pdu_length: u32,
+) -> Result<()> {
+    for bytes in pdu::data_pdus(context_id, true, &command.encode(), max_pdu_length) {
+        pdu::write_pdu(writer, &bytes).await?;
+    }
+    if let Some(data) = data {
+        for bytes in pdu::data_pdus(context_id, false, &data.encode(), max_pdu_length) {
+            pdu::write_pdu(writer, &bytes).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pdu::PresentationContext;
+
+    #[test]
+    fn test_negotiate() {
+        let context = |id, abstract_syntax: &str, transfer_syntax: &str| PresentationContext {
+            id,
+            abstract_syntax: abstract_syntax.to_string(),
+            transfer_syntaxes: vec![transfer_syntax.to_string()],
+        };
+        let results = negotiate(&[
+            context(1, PATIENT_ROOT_FIND, IMPLICIT_VR_LITTLE_ENDIAN),
+            context(3, PATIENT_ROOT_FIND, "1.2.840.10008.1.2.1"),
+            context(5, "1.2.840.10008.5.1.4.1.2.2.1", IMPLICIT_VR_LITTLE_ENDIAN),
+            context(7, VERIFICATION, IMPLICIT_VR_LITTLE_ENDIAN),
+        ]);
+
+        let outcomes: Vec<_> = results.iter().map(|r| (r.id, r.result)).collect();
+        assert_eq!(outcomes, vec![(1, 0), (3, 4), (5, 3), (7, 0)]);
+    }
+}
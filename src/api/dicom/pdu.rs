@@ -0,0 +1,336 @@
+//! DICOM upper layer protocol data units (PS3.8 section 9)
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::{Error, Result};
+
+/// DICOM application context name
+pub const APPLICATION_CONTEXT: &str = "1.2.840.10008.3.1.1.1";
+
+/// Largest PDU accepted from a peer, and the maximum we advertise
+pub const MAX_PDU_LENGTH: u32 = 64 * 1024;
+
+/// Largest association request accepted, to bound memory per connection
+const MAX_REQUEST_LENGTH: u32 = 1024 * 1024;
+
+/// A proposed presentation context
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PresentationContext {
+    pub id: u8,
+    pub abstract_syntax: String,
+    pub transfer_syntaxes: Vec<String>,
+}
+
+/// A-ASSOCIATE-RQ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssociateRequest {
+    pub called_ae: String,
+    pub calling_ae: String,
+    pub contexts: Vec<PresentationContext>,
+    /// Largest PDU the requester accepts; 0 means unlimited
+    pub max_pdu_length: u32,
+}
+
+/// Result of one presentation context in an A-ASSOCIATE-AC
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextResult {
+    pub id: u8,
+    /// 0 acceptance, 3 abstract syntax not supported, 4 transfer syntaxes not supported
+    pub result: u8,
+    pub transfer_syntax: String,
+}
+
+/// One presentation data value in a P-DATA-TF
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pdv {
+    pub context_id: u8,
+    pub is_command: bool,
+    pub is_last: bool,
+    pub data: Vec<u8>,
+}
+
+/// A PDU received from the peer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pdu {
+    AssociateRequest(AssociateRequest),
+    Data(Vec<Pdv>),
+    ReleaseRequest,
+    Abort,
+    /// A PDU type the adapter never expects from a requester
+    Unexpected(u8),
+}
+
+/// Read the next PDU, or `None` when the peer closed the connection
+pub async fn read_pdu<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Option<Pdu>> {
+    let io_error = |e: std::io::Error| Error::Api(format!("DICOM read failed: {}", e));
+
+    let mut header = [0u8; 6];
+    match reader.read_exact(&mut header).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(io_error(e)),
+    }
+    let pdu_type = header[0];
+    let length = u32::from_be_bytes([header[2], header[3], header[4], header[5]]);
+    let limit = if pdu_type == 0x01 { MAX_REQUEST_LENGTH } else { MAX_PDU_LENGTH };
+    if length > limit {
+        return Err(Error::Api(format!("DICOM PDU of {} bytes exceeds {}", length, limit)));
+    }
+
+    let mut body = vec![0u8; length as usize];
+    reader.read_exact(&mut body).await.map_err(io_error)?;
+
+    let pdu = match pdu_type {
+        0x01 => Pdu::AssociateRequest(parse_associate_request(&body)?),
+        0x04 => Pdu::Data(parse_pdvs(&body)?),
+        0x05 => Pdu::ReleaseRequest,
+        0x07 => Pdu::Abort,
+        other => Pdu::Unexpected(other),
+    };
+    Ok(Some(pdu))
+}
+
+fn truncated() -> Error {
+    Error::Validation("DICOM PDU is truncated".to_string())
+}
+
+fn ae_title(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim().to_string()
+}
+
+fn uid(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim_end_matches(['\0', ' ']).to_string()
+}
+
+/// Split variable items into (type, value) pairs
+fn items(data: &[u8]) -> Result<Vec<(u8, &[u8])>> {
+    let mut items = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let header = data.get(pos..pos + 4).ok_or_else(truncated)?;
+        let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+        let value = data.get(pos + 4..pos + 4 + length).ok_or_else(truncated)?;
+        items.push((header[0], value));
+        pos += 4 + length;
+    }
+    Ok(items)
+}
+
+fn parse_associate_request(body: &[u8]) -> Result<AssociateRequest> {
+    if body.len() < 68 {
+        return Err(truncated());
+    }
+    let mut request = AssociateRequest {
+        called_ae: ae_title(&body[4..20]),
+        calling_ae: ae_title(&body[20..36]),
+        contexts: Vec::new(),
+        max_pdu_length: 0,
+    };
+
+    for (item_type, value) in items(&body[68..])? {
+        match item_type {
+            0x20 => {
+                let id = *value.first().ok_or_else(truncated)?;
+                let mut context = PresentationContext {
+                    id,
+                    abstract_syntax: String::new(),
+                    transfer_syntaxes: Vec::new(),
+                };
+                for (sub_type, sub_value) in items(value.get(4..).ok_or_else(truncated)?)? {
+                    match sub_type {
+                        0x30 => context.abstract_syntax = uid(sub_value),
+                        0x40 => context.transfer_syntaxes.push(uid(sub_value)),
+                        _ => {}
+                    }
+                }
+                request.contexts.push(context);
+            }
+            0x50 => {
+                for (sub_type, sub_value) in items(value)? {
+                    if sub_type == 0x51 && sub_value.len() == 4 {
+                        request.max_pdu_length =
+                            u32::from_be_bytes([sub_value[0], sub_value[1], sub_value[2], sub_value[3]]);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(request)
+}
+
+fn parse_pdvs(body: &[u8]) -> Result<Vec<Pdv>> {
+    let mut pdvs = Vec::new();
+    let mut pos = 0;
+    while pos < body.len() {
+        let header = body.get(pos..pos + 6).ok_or_else(truncated)?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        if length < 2 {
+            return Err(truncated());
+        }
+        let data = body.get(pos + 6..pos + 4 + length).ok_or_else(truncated)?;
+        pdvs.push(Pdv {
+            context_id: header[4],
+            is_command: header[5] & 0x01 != 0,
+            is_last: header[5] & 0x02 != 0,
+            data: data.to_vec(),
+        });
+        pos += 4 + length;
+    }
+    Ok(pdvs)
+}
+
+fn item(out: &mut Vec<u8>, item_type: u8, value: &[u8]) {
+    out.extend_from_slice(&[item_type, 0]);
+    out.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    out.extend_from_slice(value);
+}
+
+fn pdu(pdu_type: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len() + 6);
+    out.extend_from_slice(&[pdu_type, 0]);
+    out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    out.extend_from_slice(body);
+    out
+}
+
+fn padded_ae(title: &str) -> [u8; 16] {
+    let mut padded = [b' '; 16];
+    for (slot, byte) in padded.iter_mut().zip(title.bytes()) {
+        *slot = byte;
+    }
+    padded
+}
+
+/// Encode an A-ASSOCIATE-AC
+pub fn associate_accept(
+    request: &AssociateRequest,
+    results: &[ContextResult],
+    implementation_class_uid: &str,
+) -> Vec<u8> {
+    let mut body = vec![0x00, 0x01, 0x00, 0x00];
+    body.extend_from_slice(&padded_ae(&request.called_ae));
+    body.extend_from_slice(&padded_ae(&request.calling_ae));
+    body.extend_from_slice(&[0u8; 32]);
+
+    item(&mut body, 0x10, APPLICATION_CONTEXT.as_bytes());
+    for result in results {
+        let mut value = vec![result.id, 0, result.result, 0];
+        item(&mut value, 0x40, result.transfer_syntax.as_bytes());
+        item(&mut body, 0x21, &value);
+    }
+    let mut user_information = Vec::new();
+    item(&mut user_information, 0x51, &MAX_PDU_LENGTH.to_be_bytes());
+    item(&mut user_information, 0x52, implementation_class_uid.as_bytes());
+    item(&mut body, 0x50, &user_information);
+
+    pdu(0x02, &body)
+}
+
+/// Encode an A-ASSOCIATE-RJ from the service user
+pub fn associate_reject(reason: u8) -> Vec<u8> {
+    // Permanent rejection by the service user
+    pdu(0x03, &[0, 1, 1, reason])
+}
+
+pub fn release_response() -> Vec<u8> {
+    pdu(0x06, &[0; 4])
+}
+
+pub fn abort() -> Vec<u8> {
+    pdu(0x07, &[0; 4])
+}
+
+/// Encode a command or data set as P-DATA-TF PDUs no larger than `max_pdu_length`
+pub fn data_pdus(context_id: u8, is_command: bool, data: &[u8], max_pdu_length: u32) -> Vec<Vec<u8>> {
+    let max_pdu_length = match max_pdu_length {
+        0 => MAX_PDU_LENGTH,
+        n => n.min(MAX_PDU_LENGTH),
+    };
+    // Each PDU carries one PDV: 4-byte length, context ID and control header
+    let fragment = (max_pdu_length as usize).saturating_sub(6).max(1);
+    let chunks: Vec<&[u8]> = if data.is_empty() { vec![&[]] } else { data.chunks(fragment).collect() };
+    let count = chunks.len();
+
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            let control = u8::from(is_command) | if i + 1 == count { 0x02 } else { 0 };
+            let mut body = Vec::with_capacity(chunk.len() + 6);
+            body.extend_from_slice(&(chunk.len() as u32 + 2).to_be_bytes());
+            body.extend_from_slice(&[context_id, control]);
+            body.extend_from_slice(chunk);
+            pdu(0x04, &body)
+        })
+        .collect()
+}
+
+pub async fn write_pdu<W: AsyncWrite + Unpin>(writer: &mut W, pdu: &[u8]) -> Result<()> {
+    writer
+        .write_all(pdu)
+        .await
+        .map_err(|e| Error::Api(format!("DICOM write failed: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A-ASSOCIATE-RQ as sent by a modality or PACS
+    fn associate_request_pdu(called: &str, abstract_syntax: &str, transfer_syntax: &str) -> Vec<u8> {
+        let mut body = vec![0x00, 0x01, 0x00, 0x00];
+        body.extend_from_slice(&padded_ae(called));
+        body.extend_from_slice(&padded_ae("PACS"));
+        body.extend_from_slice(&[0u8; 32]);
+        item(&mut body, 0x10, APPLICATION_CONTEXT.as_bytes());
+        let mut context = vec![1, 0, 0, 0];
+        item(&mut context, 0x30, abstract_syntax.as_bytes());
+        item(&mut context, 0x40, transfer_syntax.as_bytes());
+        item(&mut body, 0x20, &context);
+        let mut user_information = Vec::new();
+        item(&mut user_information, 0x51, &16384u32.to_be_bytes());
+        item(&mut body, 0x50, &user_information);
+        pdu(0x01, &body)
+    }
+
+    #[tokio::test]
+    async fn test_parse_associate_request() {
+        let bytes = associate_request_pdu("MPI", "1.2.840.10008.1.1", "1.2.840.10008.1.2\0");
+        let Some(Pdu::AssociateRequest(request)) = read_pdu(&mut bytes.as_slice()).await.unwrap() else {
+            panic!("expected an association request");
+        };
+
+        assert_eq!(request.called_ae, "MPI");
+        assert_eq!(request.calling_ae, "PACS");
+        assert_eq!(request.max_pdu_length, 16384);
+        assert_eq!(
+            request.contexts,
+            vec![PresentationContext {
+                id: 1,
+                abstract_syntax: "1.2.840.10008.1.1".to_string(),
+                transfer_syntaxes: vec!["1.2.840.10008.1.2".to_string()],
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_data_fragments() {
+        let data = vec![7u8; 25];
+        let pdus = data_pdus(3, false, &data, 16);
+        assert_eq!(pdus.len(), 3);
+
+        let mut received = Vec::new();
+        for (i, bytes) in pdus.iter().enumerate() {
+            let Some(Pdu::Data(pdvs)) = read_pdu(&mut bytes.as_slice()).await.unwrap() else {
+                panic!("expected P-DATA-TF");
+            };
+            assert_eq!(pdvs[0].context_id, 3);
+            assert!(!pdvs[0].is_command);
+            assert_eq!(pdvs[0].is_last, i == 2);
+            received.extend_from_slice(&pdvs[0].data);
+        }
+        assert_eq!(received, data);
+    }
+}
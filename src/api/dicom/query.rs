@@ -0,0 +1,394 @@
+//! Patient-root C-FIND at the PATIENT level
+//!
+//! Candidates come from the search index; each is then checked against the
+//! query keys using DICOM matching rules (PS3.4 C.2.2.2): `*` and `?`
+//! wildcards, a zero-length key matching everything, and date ranges such
+//! as `19800101-19891231`. Person names are compared component by
+//! component and without regard to case.
+
+use chrono::NaiveDate;
+
+use crate::api::rest::AppState;
+use crate::models::{Gender, HumanName, Identifier, IdentifierType, Patient};
+use crate::Error;
+
+use super::dataset::{tags, Dataset, Tag};
+
+/// Why a query could not be answered, with its DICOM status
+#[derive(Debug)]
+pub enum QueryError {
+    /// 0xA900, the identifier is not a PATIENT-level query
+    IdentifierMismatch(String),
+    /// 0xC000, the query could not be processed
+    UnableToProcess(String),
+}
+
+impl QueryError {
+    pub fn status(&self) -> u16 {
+        match self {
+            QueryError::IdentifierMismatch(_) => 0xA900,
+            QueryError::UnableToProcess(_) => 0xC000,
+        }
+    }
+
+    pub fn comment(&self) -> &str {
+        match self {
+            QueryError::IdentifierMismatch(comment) | QueryError::UnableToProcess(comment) => comment,
+        }
+    }
+}
+
+impl From<Error> for QueryError {
+    fn from(err: Error) -> Self {
+        QueryError::UnableToProcess(err.to_string())
+    }
+}
+
+/// Matching keys of a PATIENT-level C-FIND identifier
+#[derive(Debug, Clone)]
+pub struct PatientQuery {
+    name: String,
+    patient_id: String,
+    issuer: String,
+    birth_date: String,
+    sex: String,
+    /// Every key in the identifier, all of which are returned
+    requested: Vec<Tag>,
+}
+
+impl PatientQuery {
+    pub fn from_identifier(identifier: &Dataset) -> std::result::Result<Self, QueryError> {
+        let level = identifier.string(tags::QUERY_RETRIEVE_LEVEL).unwrap_or_default();
+        if level != "PATIENT" {
+            return Err(QueryError::IdentifierMismatch(format!(
+                "Only PATIENT level queries are supported, not '{}'",
+                level
+            )));
+        }
+
+        let key = |tag| identifier.string(tag).unwrap_or_default();
+        Ok(Self {
+            name: key(tags::PATIENT_NAME),
+            patient_id: key(tags::PATIENT_ID),
+            issuer: key(tags::ISSUER_OF_PATIENT_ID),
+            birth_date: key(tags::PATIENT_BIRTH_DATE),
+            sex: key(tags::PATIENT_SEX),
+            requested: identifier
+                .tags()
+                .filter(|t| t.0 != 0x0000 && *t != tags::SPECIFIC_CHARACTER_SET)
+                .collect(),
+        })
+    }
+
+    /// Whether a patient satisfies every matching key
+    pub fn matches(&self, patient: &Patient) -> bool {
+        let names_match = self.name.is_empty()
+            || std::iter::once(&patient.name)
+                .chain(&patient.additional_names)
+                .any(|name| person_name_matches(&self.name, name));
+
+        names_match
+            && (self.patient_id.is_empty() || self.matching_identifier(patient).is_some())
+            && date_matches(&self.birth_date, patient.birth_date)
+            && (self.sex.is_empty() || self.sex == sex_code(patient.gender))
+    }
+
+    /// The identifier returned as PatientID: the one matching the query,
+    /// otherwise the first MRN, otherwise the first identifier
+    fn matching_identifier<'a>(&self, patient: &'a Patient) -> Option<&'a Identifier> {
+        let issued = |id: &&Identifier| {
+            self.issuer.is_empty()
+                || id.system == self.issuer
+                || id.assigner.as_deref() == Some(self.issuer.as_str())
+        };
+        if !self.patient_id.is_empty() {
+            return patient
+                .identifiers
+                .iter()
+                .filter(issued)
+                .find(|id| wildcard_matches(&self.patient_id, &id.value, false));
+        }
+        patient
+            .identifiers
+            .iter()
+            .filter(issued)
+            .find(|id| matches!(id.identifier_type, IdentifierType::MRN))
+            .or_else(|| patient.identifiers.iter().find(issued))
+    }
+
+    /// The C-FIND response identifier for a matching patient
+    pub fn response(&self, patient: &Patient) -> Dataset {
+        let identifier = self.matching_identifier(patient);
+        let mut response = Dataset::new();
+        response.set_string(tags::SPECIFIC_CHARACTER_SET, "ISO_IR 192");
+
+        for tag in &self.requested {
+            let value = match *tag {
+                tags::QUERY_RETRIEVE_LEVEL => "PATIENT".to_string(),
+                tags::PATIENT_NAME => person_name(&patient.name),
+                tags::PATIENT_ID => identifier.map(|id| id.value.clone()).unwrap_or_default(),
+                tags::ISSUER_OF_PATIENT_ID => identifier.map(|id| id.system.clone()).unwrap_or_default(),
+                tags::PATIENT_BIRTH_DATE => patient
+                    .birth_date
+                    .map(|d| d.format("%Y%m%d").to_string())
+                    .unwrap_or_default(),
+                tags::PATIENT_SEX => sex_code(patient.gender).to_string(),
+                // Keys the MPI does not hold are returned empty
+                _ => String::new(),
+            };
+            response.set_string(*tag, &value);
+        }
+        response
+    }
+
+    /// Text for the search index that finds every possible match
+    ///
+    /// Wildcard-free family names and patient IDs are searched directly.
+    /// A family name prefix such as `SMI*` is expanded through the index's
+    /// family name suggestions.
+    fn search_text(&self, state: &AppState, limit: usize) -> std::result::Result<String, QueryError> {
+        let mut terms: Vec<String> = Vec::new();
+
+        if !self.patient_id.is_empty() && !has_wildcard(&self.patient_id) {
+            terms.push(self.patient_id.clone());
+        }
+
+        let family = self.name.split('^').next().unwrap_or_default();
+        match family.strip_suffix('*') {
+            Some(prefix) if prefix.chars().count() >= 2 && !has_wildcard(prefix) => {
+                let suggestions = state.search_engine.suggest(prefix, limit)?;
+                terms.extend(suggestions.into_iter().filter(|s| s.distance == 0).map(|s| s.term));
+            }
+            _ if !family.is_empty() && !has_wildcard(family) => terms.push(family.to_string()),
+            _ => {}
+        }
+
+        let text: Vec<String> = terms
+            .iter()
+            .flat_map(|t| t.split(|c: char| !c.is_alphanumeric()))
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        if text.is_empty() {
+            return Err(QueryError::UnableToProcess(
+                "Query needs a PatientID or a PatientName with at least two leading characters".to_string(),
+            ));
+        }
+        Ok(text.join(" "))
+    }
+}
+
+/// Answer a PATIENT-level query with at most `max_results` identifiers
+pub fn find(
+    state: &AppState,
+    identifier: &Dataset,
+    max_results: usize,
+) -> std::result::Result<Vec<Dataset>, QueryError> {
+    let query = PatientQuery::from_identifier(identifier)?;
+    let text = query.search_text(state, max_results)?;
+
+    let mut responses = Vec::new();
+    for patient_id in state.search_engine.search(&text, max_results.saturating_mul(5))? {
+        let Ok(id) = uuid::Uuid::parse_str(&patient_id) else {
+            continue;
+        };
+        let Some(patient) = state.patient_repository.get_by_id(&id)? else {
+            continue;
+        };
+        if patient.active && query.matches(&patient) {
+            responses.push(query.response(&patient));
+            if responses.len() == max_results {
+                tracing::info!("DICOM C-FIND for '{}' truncated at {} results", text, max_results);
+                break;
+            }
+        }
+    }
+    Ok(responses)
+}
+
+fn has_wildcard(value: &str) -> bool {
+    value.contains(['*', '?'])
+}
+
+/// `Family^Given^Middle^Prefix^Suffix`, without trailing empty components
+pub fn person_name(name: &HumanName) -> String {
+    let components = [
+        name.family.clone(),
+        name.given.first().cloned().unwrap_or_default(),
+        name.given.get(1..).unwrap_or_default().join(" "),
+        name.prefix.join(" "),
+        name.suffix.join(" "),
+    ];
+    components.join("^").trim_end_matches('^').to_string()
+}
+
+fn person_name_matches(pattern: &str, name: &HumanName) -> bool {
+    let value = person_name(name);
+    let mut values = value.split('^');
+    pattern.split('^').all(|p| {
+        let value = values.next().unwrap_or_default();
+        p.is_empty() || wildcard_matches(p, value, true)
+    })
+}
+
+fn sex_code(gender: Gender) -> &'static str {
+    match gender {
+        Gender::Male => "M",
+        Gender::Female => "F",
+        Gender::Other => "O",
+        Gender::Unknown => "",
+    }
+}
+
+/// Match a value against a pattern where `*` is any run and `?` any character
+pub fn wildcard_matches(pattern: &str, value: &str, ignore_case: bool) -> bool {
+    let fold = |s: &str| if ignore_case { s.to_lowercase() } else { s.to_string() };
+    let pattern: Vec<char> = fold(pattern).chars().collect();
+    let value: Vec<char> = fold(value).chars().collect();
+
+    let (mut p, mut v) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while v < value.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, v));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == value[v] => {
+                p += 1;
+                v += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` absorb one more character
+                Some((star, matched)) => {
+                    p = star + 1;
+                    v = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Match a birth date against a DA key: a date, or a range with either end open
+fn date_matches(key: &str, date: Option<NaiveDate>) -> bool {
+    if key.is_empty() {
+        return true;
+    }
+    let Some(date) = date else {
+        return false;
+    };
+    let parse = |s: &str| NaiveDate::parse_from_str(s.trim(), "%Y%m%d").ok();
+
+    match key.split_once('-') {
+        Some((from, to)) => {
+            (from.trim().is_empty() || parse(from).is_some_and(|from| date >= from))
+                && (to.trim().is_empty() || parse(to).is_some_and(|to| date <= to))
+        }
+        None => parse(key) == Some(date),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::VerificationStatus;
+
+    fn patient() -> Patient {
+        let mut patient = crate::fixtures::patient("Smith", &["John", "Quincy"], Gender::Male);
+        patient.birth_date = NaiveDate::from_ymd_opt(1980, 1, 15);
+        patient.identifiers = vec![
+            Identifier {
+                use_type: None,
+                identifier_type: IdentifierType::SSN,
+                system: "SSA".to_string(),
+                value: "123456789".to_string(),
+                assigner: None,
+                verification: VerificationStatus::Unverified,
+                period: None,
+            },
+            Identifier {
+                use_type: None,
+                identifier_type: IdentifierType::MRN,
+                system: "GENERAL".to_string(),
+                value: "A100".to_string(),
+                assigner: None,
+                verification: VerificationStatus::Unverified,
+                period: None,
+            },
+        ];
+        patient
+    }
+
+    fn query(keys: &[(Tag, &str)]) -> PatientQuery {
+        let mut identifier = Dataset::new();
+        identifier.set_string(tags::QUERY_RETRIEVE_LEVEL, "PATIENT");
+        for (tag, value) in keys {
+            identifier.set_string(*tag, value);
+        }
+        PatientQuery::from_identifier(&identifier).unwrap()
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(wildcard_matches("SMI*", "Smith", true));
+        assert!(wildcard_matches("*TH", "Smith", true));
+        assert!(wildcard_matches("S?ITH", "Smith", true));
+        assert!(wildcard_matches("*", "", true));
+        assert!(!wildcard_matches("SMI*", "Smith", false));
+        assert!(!wildcard_matches("SM?", "Smith", true));
+        assert!(wildcard_matches("a*b*c", "axxbyyc", false));
+        assert!(!wildcard_matches("a*b*c", "axxbyy", false));
+    }
+
+    #[test]
+    fn test_matching_keys() {
+        let patient = patient();
+
+        assert!(query(&[(tags::PATIENT_NAME, "SMITH^JOHN")]).matches(&patient));
+        assert!(query(&[(tags::PATIENT_NAME, "SMI*")]).matches(&patient));
+        assert!(query(&[(tags::PATIENT_NAME, "SMITH^J*^QUINCY")]).matches(&patient));
+        assert!(!query(&[(tags::PATIENT_NAME, "SMITH^JANE")]).matches(&patient));
+
+        assert!(query(&[(tags::PATIENT_ID, "A100")]).matches(&patient));
+        assert!(query(&[(tags::PATIENT_ID, "A100"), (tags::ISSUER_OF_PATIENT_ID, "GENERAL")]).matches(&patient));
+        assert!(!query(&[(tags::PATIENT_ID, "A100"), (tags::ISSUER_OF_PATIENT_ID, "SSA")]).matches(&patient));
+
+        assert!(query(&[(tags::PATIENT_BIRTH_DATE, "19800115")]).matches(&patient));
+        assert!(query(&[(tags::PATIENT_BIRTH_DATE, "19800101-19801231")]).matches(&patient));
+        assert!(!query(&[(tags::PATIENT_BIRTH_DATE, "-19791231")]).matches(&patient));
+        assert!(query(&[(tags::PATIENT_SEX, "M"), (tags::PATIENT_BIRTH_DATE, "")]).matches(&patient));
+        assert!(!query(&[(tags::PATIENT_SEX, "F")]).matches(&patient));
+    }
+
+    #[test]
+    fn test_response_returns_requested_keys() {
+        let query = query(&[
+            (tags::PATIENT_NAME, "SMITH*"),
+            (tags::PATIENT_ID, ""),
+            (tags::ISSUER_OF_PATIENT_ID, ""),
+            (tags::PATIENT_BIRTH_DATE, ""),
+            (Tag(0x0010, 0x1010), ""),
+        ]);
+        let response = query.response(&patient());
+
+        assert_eq!(response.string(tags::QUERY_RETRIEVE_LEVEL).as_deref(), Some("PATIENT"));
+        assert_eq!(response.string(tags::PATIENT_NAME).as_deref(), Some("Smith^John^Quincy"));
+        // The MRN is preferred when the query does not name an identifier
+        assert_eq!(response.string(tags::PATIENT_ID).as_deref(), Some("A100"));
+        assert_eq!(response.string(tags::ISSUER_OF_PATIENT_ID).as_deref(), Some("GENERAL"));
+        assert_eq!(response.string(tags::PATIENT_BIRTH_DATE).as_deref(), Some("19800115"));
+        assert_eq!(response.string(Tag(0x0010, 0x1010)).as_deref(), Some(""));
+        assert_eq!(response.string(tags::PATIENT_SEX), None);
+    }
+
+    #[test]
+    fn test_rejects_other_levels() {
+        let mut identifier = Dataset::new();
+        identifier.set_string(tags::QUERY_RETRIEVE_LEVEL, "STUDY");
+        let err = PatientQuery::from_identifier(&identifier).unwrap_err();
+        assert_eq!(err.status(), 0xA900);
+    }
+}
@@ -0,0 +1,239 @@
+//! FHIR AuditEvent resources derived from the audit log
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::db::models::DbAuditLog;
+use super::resources::{FhirCodeableConcept, FhirCoding, FhirReference};
+
+const AUDIT_EVENT_TYPE_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/audit-event-type";
+const RESTFUL_INTERACTION_SYSTEM: &str = "http://hl7.org/fhir/restful-interaction";
+const AUDIT_EVENT_OUTCOME_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/audit-event-outcome";
+
+/// FHIR AuditEvent resource (R5)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FhirAuditEvent {
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub category: Vec<FhirCodeableConcept>,
+    pub code: FhirCodeableConcept,
+    pub action: String,
+    pub recorded: String,
+    pub outcome: FhirAuditEventOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub patient: Option<FhirReference>,
+    pub agent: Vec<FhirAuditEventAgent>,
+    pub source: FhirAuditEventSource,
+    pub entity: Vec<FhirAuditEventEntity>,
+}
+
+/// AuditEvent outcome
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FhirAuditEventOutcome {
+    pub code: FhirCoding,
+}
+
+/// AuditEvent agent (who performed the action)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FhirAuditEventAgent {
+    pub who: FhirReference,
+    pub requestor: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network_string: Option<String>,
+}
+
+/// AuditEvent source (the system that recorded the event)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FhirAuditEventSource {
+    pub observer: FhirReference,
+}
+
+/// AuditEvent entity (the resource acted on)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FhirAuditEventEntity {
+    pub what: FhirReference,
+}
+
+/// Build an AuditEvent resource from an audit log row
+pub fn to_fhir_audit_event(log: &DbAuditLog) -> FhirAuditEvent {
+    let (interaction, action) = match log.action.as_str() {
+        "CREATE" => ("create", "C"),
+        "READ" => ("read", "R"),
+        "UPDATE" => ("update", "U"),
+        "DELETE" => ("delete", "D"),
+        _ => ("operation", "E"),
+    };
+
+    let entity_reference = format!("{}/{}", log.entity_type, log.entity_id);
+    let patient = (log.entity_type == "Patient").then(|| FhirReference {
+        reference: Some(entity_reference.clone()),
+        display: None,
+    });
+
+    FhirAuditEvent {
+        resource_type: "AuditEvent".to_string(),
+        id: Some(log.id.to_string()),
+        category: vec![FhirCodeableConcept {
+            coding: Some(vec![FhirCoding {
+                system: Some(AUDIT_EVENT_TYPE_SYSTEM.to_string()),
+                code: Some("rest".to_string()),
+                display: Some("RESTful Operation".to_string()),
+            }]),
+            text: None,
+        }],
+        code: FhirCodeableConcept {
+            coding: Some(vec![FhirCoding {
+                system: Some(RESTFUL_INTERACTION_SYSTEM.to_string()),
+                code: Some(interaction.to_string()),
+                display: None,
+            }]),
+            text: Some(log.action.clone()),
+        },
+        action: action.to_string(),
+        recorded: log.timestamp.to_rfc3339(),
+        // Only completed actions are written to the audit log
+        outcome: FhirAuditEventOutcome {
+            code: FhirCoding {
+                system: Some(AUDIT_EVENT_OUTCOME_SYSTEM.to_string()),
+                code: Some("0".to_string()),
+                display: Some("Success".to_string()),
+            },
+        },
+        patient,
+        agent: vec![FhirAuditEventAgent {
+            who: FhirReference {
+                reference: None,
+                display: Some(log.user_id.clone().unwrap_or_else(|| "system".to_string())),
+            },
+            requestor: true,
+            network_string: log.ip_address.clone(),
+        }],
+        source: FhirAuditEventSource {
+            observer: FhirReference {
+                reference: None,
+                display: Some("master-patient-index".to_string()),
+            },
+        },
+        entity: vec![FhirAuditEventEntity {
+            what: FhirReference {
+                reference: Some(entity_reference),
+                display: None,
+            },
+        }],
+    }
+}
+
+/// Time range from one or more FHIR `date` search parameters
+///
+/// `since` is inclusive and `until` is exclusive.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DateRange {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl DateRange {
+    /// Narrow the range with a `date` parameter value such as `ge2024-01-01`
+    ///
+    /// Supports the `eq`, `ge`, `gt`, `le` and `lt` prefixes (default `eq`)
+    /// with a `YYYY-MM-DD` date or an RFC 3339 date-time.
+    pub fn apply(&mut self, value: &str) -> crate::Result<()> {
+        let (prefix, rest) = match value.get(..2) {
+            Some(p @ ("eq" | "ge" | "gt" | "le" | "lt")) => (p, &value[2..]),
+            _ => ("eq", value),
+        };
+
+        // A date covers the whole day; a date-time is a single instant
+        let (start, end) = if let Ok(date) = NaiveDate::parse_from_str(rest, "%Y-%m-%d") {
+            let start = date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+            (start, start + Duration::days(1))
+        } else {
+            let instant = DateTime::parse_from_rfc3339(rest)
+                .map_err(|e| crate::Error::Validation(format!("Invalid date '{}': {}", value, e)))?
+                .with_timezone(&Utc);
+            (instant, instant + Duration::microseconds(1))
+        };
+
+        match prefix {
+            "eq" => {
+                self.narrow_since(start);
+                self.narrow_until(end);
+            }
+            "ge" => self.narrow_since(start),
+            "gt" => self.narrow_since(end),
+            "le" => self.narrow_until(end),
+            "lt" => self.narrow_until(start),
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    fn narrow_since(&mut self, since: DateTime<Utc>) {
+        self.since = Some(self.since.map_or(since, |s| s.max(since)));
+    }
+
+    fn narrow_until(&mut self, until: DateTime<Utc>) {
+        self.until = Some(self.until.map_or(until, |u| u.min(until)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_to_fhir_audit_event() {
+        let patient_id = Uuid::new_v4();
+        let log = DbAuditLog {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            user_id: Some("clerk".to_string()),
+            action: "UPDATE".to_string(),
+            entity_type: "Patient".to_string(),
+            entity_id: patient_id,
+            old_values: None,
+            new_values: None,
+            ip_address: Some("10.0.0.5".to_string()),
+            user_agent: None,
+        };
+
+        let json = serde_json::to_value(to_fhir_audit_event(&log)).unwrap();
+
+        assert_eq!(json["resourceType"], "AuditEvent");
+        assert_eq!(json["action"], "U");
+        assert_eq!(json["code"]["coding"][0]["code"], "update");
+        assert_eq!(json["patient"]["reference"], format!("Patient/{}", patient_id));
+        assert_eq!(json["agent"][0]["who"]["display"], "clerk");
+        assert_eq!(json["agent"][0]["networkString"], "10.0.0.5");
+    }
+
+    #[test]
+    fn test_date_range() {
+        let day = |d| Utc.with_ymd_and_hms(2024, 3, d, 0, 0, 0).unwrap();
+
+        let mut range = DateRange::default();
+        range.apply("2024-03-10").unwrap();
+        assert_eq!(range, DateRange { since: Some(day(10)), until: Some(day(11)) });
+
+        let mut range = DateRange::default();
+        range.apply("ge2024-03-01").unwrap();
+        range.apply("lt2024-03-15").unwrap();
+        assert_eq!(range, DateRange { since: Some(day(1)), until: Some(day(15)) });
+
+        let mut range = DateRange::default();
+        range.apply("le2024-03-15").unwrap();
+        assert_eq!(range.until, Some(day(16)));
+
+        assert!(DateRange::default().apply("yesterday").is_err());
+    }
+}
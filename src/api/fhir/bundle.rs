@@ -1,3 +1,32 @@
 //! FHIR bundle support
 
-// FHIR Bundle resource implementation
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// FHIR Bundle resource (R5), as returned by searches and history
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FhirBundle {
+    pub resource_type: String,
+    /// `searchset` or `history`
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub total: usize,
+    pub entry: Vec<FhirBundleEntry>,
+}
+
+/// One resource in a bundle
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FhirBundleEntry {
+    pub full_url: String,
+    /// Patient, Provenance or AuditEvent resource; absent for deleted history versions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource: Option<serde_json::Value>,
+    /// How a history version was written
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request: Option<serde_json::Value>,
+    /// Outcome of a history version's write
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<serde_json::Value>,
+}
@@ -0,0 +1,146 @@
+//! FHIR R5 individual-genderIdentity and individual-pronouns extensions
+//!
+//! Both are complex extensions whose `value` sub-extension carries a
+//! CodeableConcept. Well-known values are coded (SNOMED CT for gender
+//! identity, LOINC answers for pronouns); anything else is sent as text.
+
+use super::resources::{FhirCodeableConcept, FhirCoding, FhirExtension};
+
+pub const GENDER_IDENTITY_URL: &str = "http://hl7.org/fhir/StructureDefinition/individual-genderIdentity";
+pub const PRONOUNS_URL: &str = "http://hl7.org/fhir/StructureDefinition/individual-pronouns";
+
+const SNOMED: &str = "http://snomed.info/sct";
+const LOINC: &str = "http://loinc.org";
+
+/// (value, code, display)
+const GENDER_IDENTITY_CODES: [(&str, &str, &str); 3] = [
+    ("male", "446151000124109", "Identifies as male gender"),
+    ("female", "446141000124107", "Identifies as female gender"),
+    ("non-binary", "33791000087105", "Identifies as nonbinary gender"),
+];
+
+const PRONOUN_CODES: [(&str, &str, &str); 3] = [
+    ("he/him", "LA29518-0", "he/him/his/his/himself"),
+    ("she/her", "LA29519-8", "she/her/her/hers/herself"),
+    ("they/them", "LA29520-6", "they/them/their/theirs/themselves"),
+];
+
+/// Build the genderIdentity extension
+pub fn gender_identity_extension(value: &str) -> FhirExtension {
+    identity_extension(GENDER_IDENTITY_URL, SNOMED, &GENDER_IDENTITY_CODES, value)
+}
+
+/// Build the pronouns extension
+pub fn pronouns_extension(value: &str) -> FhirExtension {
+    identity_extension(PRONOUNS_URL, LOINC, &PRONOUN_CODES, value)
+}
+
+/// Read gender identity and pronouns from a resource's extensions
+pub fn parse_identity_extensions(extensions: &[FhirExtension]) -> (Option<String>, Option<String>) {
+    let find = |url: &str, codes: &[(&str, &str, &str)]| {
+        extensions
+            .iter()
+            .find(|e| e.url == url)
+            .and_then(|e| identity_value(e, codes))
+    };
+    (
+        find(GENDER_IDENTITY_URL, &GENDER_IDENTITY_CODES),
+        find(PRONOUNS_URL, &PRONOUN_CODES),
+    )
+}
+
+fn identity_extension(url: &str, system: &str, codes: &[(&str, &str, &str)], value: &str) -> FhirExtension {
+    let coding = codes
+        .iter()
+        .find(|(known, _, _)| known.eq_ignore_ascii_case(value))
+        .map(|(_, code, display)| {
+            vec![FhirCoding {
+                system: Some(system.to_string()),
+                code: Some(code.to_string()),
+                display: Some(display.to_string()),
+            }]
+        });
+
+    FhirExtension {
+        url: url.to_string(),
+        value_codeable_concept: None,
+        value_string: None,
+        extension: Some(vec![FhirExtension {
+            url: "value".to_string(),
+            value_codeable_concept: Some(FhirCodeableConcept {
+                coding,
+                text: Some(value.to_string()),
+            }),
+            value_string: None,
+            extension: None,
+        }]),
+    }
+}
+
+/// Prefer the concept text, then a known code, then a coding's display
+fn identity_value(extension: &FhirExtension, codes: &[(&str, &str, &str)]) -> Option<String> {
+    let concept = extension
+        .extension
+        .iter()
+        .flatten()
+        .find(|e| e.url == "value")
+        .and_then(|e| e.value_codeable_concept.as_ref())?;
+
+    if let Some(text) = concept.text.as_ref().filter(|t| !t.trim().is_empty()) {
+        return Some(text.clone());
+    }
+    let codings = concept.coding.as_deref().unwrap_or_default();
+    codings
+        .iter()
+        .find_map(|c| {
+            let code = c.code.as_deref()?;
+            codes.iter().find(|(_, known, _)| *known == code).map(|(value, _, _)| value.to_string())
+        })
+        .or_else(|| codings.iter().find_map(|c| c.display.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_values_are_coded() {
+        let extension = pronouns_extension("They/Them");
+        let concept = extension.extension.as_ref().unwrap()[0]
+            .value_codeable_concept
+            .as_ref()
+            .unwrap();
+
+        assert_eq!(extension.url, PRONOUNS_URL);
+        assert_eq!(concept.text.as_deref(), Some("They/Them"));
+        assert_eq!(concept.coding.as_ref().unwrap()[0].code.as_deref(), Some("LA29520-6"));
+
+        let free_text = gender_identity_extension("two-spirit");
+        let concept = free_text.extension.as_ref().unwrap()[0]
+            .value_codeable_concept
+            .as_ref()
+            .unwrap();
+        assert!(concept.coding.is_none());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let extensions = vec![gender_identity_extension("non-binary"), pronouns_extension("ze/zir")];
+        assert_eq!(
+            parse_identity_extensions(&extensions),
+            (Some("non-binary".to_string()), Some("ze/zir".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_code_without_text() {
+        let mut extension = gender_identity_extension("female");
+        extension.extension.as_mut().unwrap()[0]
+            .value_codeable_concept
+            .as_mut()
+            .unwrap()
+            .text = None;
+
+        assert_eq!(parse_identity_extensions(&[extension]), (Some("female".to_string()), None));
+    }
+}
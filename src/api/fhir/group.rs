@@ -0,0 +1,190 @@
+//! FHIR Group resources
+//!
+//! Patient groups are served as Group resources of type `person` with
+//! `enumerated` membership, each member a reference to a Patient. Groups are
+//! created and replaced whole through FHIR; single members are added and
+//! removed through the REST API.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::PatientGroup;
+use crate::{Error, Result};
+use super::resources::{FhirMeta, FhirReference};
+
+/// FHIR Group resource (R5)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FhirGroup {
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<FhirMeta>,
+    /// Only `person` is supported
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// Only `enumerated` is supported
+    pub membership: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub member: Option<Vec<FhirGroupMember>>,
+}
+
+/// One member of a Group
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FhirGroupMember {
+    /// `Patient/{id}` reference
+    pub entity: FhirReference,
+    /// Inactive members are not stored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inactive: Option<bool>,
+}
+
+/// Convert a patient group to a FHIR Group resource
+pub fn to_fhir_group(group: &PatientGroup) -> FhirGroup {
+    let member: Vec<FhirGroupMember> = group
+        .members
+        .iter()
+        .map(|patient_id| FhirGroupMember {
+            entity: FhirReference {
+                reference: Some(format!("Patient/{}", patient_id)),
+                display: None,
+            },
+            inactive: None,
+        })
+        .collect();
+
+    FhirGroup {
+        resource_type: "Group".to_string(),
+        id: Some(group.id.to_string()),
+        meta: Some(FhirMeta {
+            version_id: None,
+            last_updated: Some(group.updated_at.to_rfc3339()),
+        }),
+        type_: "person".to_string(),
+        membership: "enumerated".to_string(),
+        name: Some(group.name.clone()),
+        description: group.description.clone(),
+        quantity: Some(member.len()),
+        member: if member.is_empty() { None } else { Some(member) },
+    }
+}
+
+/// Convert a FHIR Group to a patient group, without checking that the
+/// member patients exist
+///
+/// The group is given a new ID and no creator; callers set those.
+pub fn from_fhir_group(fhir_group: &FhirGroup) -> Result<PatientGroup> {
+    if fhir_group.type_ != "person" {
+        return Err(Error::Validation(format!(
+            "Group.type '{}' is not supported; only 'person' groups of patients are",
+            fhir_group.type_
+        )));
+    }
+    if fhir_group.membership != "enumerated" {
+        return Err(Error::Validation(format!(
+            "Group.membership '{}' is not supported; members must be listed",
+            fhir_group.membership
+        )));
+    }
+    let name = fhir_group
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| Error::Validation("Group.name is required".to_string()))?;
+
+    let mut group = PatientGroup::new(name.to_string(), fhir_group.description.clone(), None);
+    for (i, member) in fhir_group.member.iter().flatten().enumerate() {
+        if member.inactive == Some(true) {
+            continue;
+        }
+        let patient_id = member
+            .entity
+            .reference
+            .as_deref()
+            .and_then(patient_reference)
+            .ok_or_else(|| Error::Validation(format!("Group.member[{}].entity must reference a Patient", i)))?;
+        if !group.members.contains(&patient_id) {
+            group.members.push(patient_id);
+        }
+    }
+    Ok(group)
+}
+
+/// Patient ID of a relative or absolute `Patient/{id}` reference
+fn patient_reference(reference: &str) -> Option<Uuid> {
+    let (base, id) = reference.rsplit_once("Patient/")?;
+    if !(base.is_empty() || base.ends_with('/')) {
+        return None;
+    }
+    Uuid::parse_str(id).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fhir_group(members: &[&str]) -> FhirGroup {
+        FhirGroup {
+            resource_type: "Group".to_string(),
+            id: None,
+            meta: None,
+            type_: "person".to_string(),
+            membership: "enumerated".to_string(),
+            name: Some("Roster reconciliation, March".to_string()),
+            description: None,
+            quantity: None,
+            member: Some(
+                members
+                    .iter()
+                    .map(|reference| FhirGroupMember {
+                        entity: FhirReference {
+                            reference: Some(reference.to_string()),
+                            display: None,
+                        },
+                        inactive: None,
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    #[test]
+    fn test_group_round_trips_members() {
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let group = from_fhir_group(&fhir_group(&[
+            &format!("Patient/{}", first),
+            &format!("https://mpi.example.org/fhir/Patient/{}", second),
+            &format!("Patient/{}", first),
+        ]))
+        .unwrap();
+        assert_eq!(group.members, vec![first, second]);
+
+        let fhir = to_fhir_group(&group);
+        assert_eq!(fhir.quantity, Some(2));
+        assert_eq!(from_fhir_group(&fhir).unwrap().members, group.members);
+    }
+
+    #[test]
+    fn test_rejects_groups_that_are_not_patient_lists() {
+        assert!(from_fhir_group(&fhir_group(&[&format!("Practitioner/{}", Uuid::new_v4())])).is_err());
+        assert!(from_fhir_group(&fhir_group(&[&format!("RelatedPatient/{}", Uuid::new_v4())])).is_err());
+
+        let mut definitional = fhir_group(&[]);
+        definitional.membership = "definitional".to_string();
+        assert!(from_fhir_group(&definitional).is_err());
+
+        let mut unnamed = fhir_group(&[]);
+        unnamed.name = Some("  ".to_string());
+        assert!(from_fhir_group(&unnamed).is_err());
+    }
+}
@@ -2,18 +2,46 @@
 
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
     Json,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
 };
 use serde::Deserialize;
+use utoipa::IntoParams;
 use uuid::Uuid;
 
+use crate::api::archive::{self, RawBody, WithRawBody};
+use crate::api::groups;
+use crate::api::privacy::{self, Read, Requester};
+use crate::api::quarantine;
+use crate::api::survivorship;
+use crate::api::{conditional, fields};
 use crate::api::rest::AppState;
-use super::{FhirPatient, FhirOperationOutcome, to_fhir_patient, from_fhir_patient};
+use crate::matching::find_resubmitted;
+use crate::models::{AuthorityRegistry, IdentifierType, Patient, Practitioner};
+use crate::models::archived_message::CHANNEL_FHIR;
+use crate::config::FhirHandling;
+use crate::validation::{assign_mrn, assign_patient_id, IdentifierRules};
+use super::{
+    FhirPatient, FhirOperationOutcome, FhirOperationOutcomeIssue, to_fhir_patient_with_authorities,
+    from_fhir_patient_with_issues, unsupported_patient_elements,
+};
+use super::bundle::FhirBundle;
+use super::practitioner::{
+    FhirPractitioner, from_fhir_practitioner_with_issues, to_fhir_practitioner, unsupported_practitioner_elements,
+};
+use super::related_person::{to_fhir_related_persons, FhirRelatedPerson};
+use super::group::{from_fhir_group, to_fhir_group, FhirGroup};
+use super::provenance::{to_fhir_provenance, patient_version_reference};
+use super::audit_event::{to_fhir_audit_event, DateRange};
+
+/// Default and maximum number of entries in history and Provenance bundles
+const DEFAULT_HISTORY_COUNT: usize = 50;
+const MAX_HISTORY_COUNT: usize = 500;
 
 /// FHIR search parameters
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct FhirSearchParams {
     /// Patient name (any part)
     #[serde(rename = "name")]
@@ -39,110 +67,440 @@ pub struct FhirSearchParams {
     #[serde(rename = "gender")]
     pub gender: Option<String>,
 
+    /// Phone number, in any formatting
+    #[serde(rename = "phone")]
+    pub phone: Option<String>,
+
+    /// Email address
+    #[serde(rename = "email")]
+    pub email: Option<String>,
+
+    /// Phone or email, optionally as a `phone|...` or `email|...` token
+    #[serde(rename = "telecom")]
+    pub telecom: Option<String>,
+
     /// Number of results
     #[serde(rename = "_count")]
     pub count: Option<usize>,
+
+    /// Comma-separated elements to return, e.g. `name,birthDate,identifier`
+    #[serde(rename = "_elements")]
+    pub elements: Option<String>,
+}
+
+/// FHIR read parameters
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FhirReadParams {
+    /// Comma-separated elements to return, e.g. `name,birthDate,identifier`
+    #[serde(rename = "_elements")]
+    pub elements: Option<String>,
 }
 
 /// Get FHIR Patient by ID
+///
+/// Honors `If-None-Match` with `304 Not Modified`, as the FHIR read
+/// interaction allows. With `_elements`, the Patient is trimmed to those
+/// elements and tagged `SUBSETTED`.
+#[utoipa::path(
+    get,
+    path = "/fhir/Patient/{id}",
+    tag = "fhir",
+    params(
+        ("id" = Uuid, Path, description = "Patient UUID"),
+        FhirReadParams
+    ),
+    responses(
+        (status = 200, description = "Patient found", body = FhirPatient, headers(("ETag" = String, description = "Weak validator for If-None-Match"))),
+        (status = 304, description = "Patient unchanged since the ETag in If-None-Match"),
+        (status = 404, description = "Patient not found", body = FhirOperationOutcome),
+        (status = 500, description = "Internal server error", body = FhirOperationOutcome)
+    )
+)]
 pub async fn get_fhir_patient(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Path(id): Path<Uuid>,
-) -> impl IntoResponse {
+    Query(params): Query<FhirReadParams>,
+) -> Response {
     match state.patient_repository.get_by_id(&id) {
         Ok(Some(patient)) => {
-            let fhir_patient = to_fhir_patient(&patient);
-            (StatusCode::OK, Json(serde_json::to_value(fhir_patient).unwrap()))
+            let requester = Requester::from_headers(&headers);
+            let masked = privacy::is_masked(&state.config.privacy, &requester, &patient);
+            let Some(patient) = privacy::view(&state, &requester, patient, Read::Direct, "GET /fhir/Patient/{id}") else {
+                unreachable!("direct reads are masked, never excluded");
+            };
+            let etag = conditional::patient_etag(&patient, masked);
+            if conditional::if_none_match(&headers, &etag) {
+                return conditional::not_modified(etag);
+            }
+            let fhir_patient = to_fhir_patient_with_authorities(&patient, &state.authority_registry());
+            let mut resource = serde_json::to_value(fhir_patient).unwrap();
+            if let Some(elements) = fields::parse(params.elements.as_deref()) {
+                fields::select_fhir(&mut resource, &elements);
+            }
+            (StatusCode::OK, [(header::ETAG, etag)], Json(resource)).into_response()
         }
         Ok(None) => {
             let outcome = FhirOperationOutcome::not_found("Patient", &id.to_string());
-            (StatusCode::NOT_FOUND, Json(serde_json::to_value(outcome).unwrap()))
+            (StatusCode::NOT_FOUND, Json(serde_json::to_value(outcome).unwrap())).into_response()
         }
         Err(e) => {
             let outcome = FhirOperationOutcome::error("database-error", &e.to_string());
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap()))
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap())).into_response()
+        }
+    }
+}
+
+/// What a create or update returns, from the `Prefer` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PreferReturn {
+    /// The stored resource (the default)
+    Representation,
+    /// Only an OperationOutcome, listing any data that was not stored
+    OperationOutcome,
+}
+
+/// Preferences a client sent in `Prefer` headers
+#[derive(Debug, Clone, Copy)]
+struct Preferences {
+    return_: PreferReturn,
+    handling: FhirHandling,
+}
+
+impl Preferences {
+    /// Read `return=` and `handling=` preferences, falling back to the configured handling
+    fn from_headers(headers: &HeaderMap, default_handling: FhirHandling) -> Self {
+        let mut preferences = Self {
+            return_: PreferReturn::Representation,
+            handling: default_handling,
+        };
+        let values = headers
+            .get_all("prefer")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split([',', ';']));
+        for preference in values {
+            match preference.trim().to_ascii_lowercase().as_str() {
+                "return=operationoutcome" => preferences.return_ = PreferReturn::OperationOutcome,
+                "return=representation" => preferences.return_ = PreferReturn::Representation,
+                "handling=strict" => preferences.handling = FhirHandling::Strict,
+                "handling=lenient" => preferences.handling = FhirHandling::Lenient,
+                _ => {}
+            }
+        }
+        preferences
+    }
+}
+
+type FhirErrorResponse = (StatusCode, Json<serde_json::Value>);
+
+/// Read a Patient from a request body along with what cannot be stored
+///
+/// Under strict handling any such issue rejects the resource with 422.
+fn read_patient(
+    body: &serde_json::Value,
+    handling: FhirHandling,
+    identifier_rules: &IdentifierRules,
+) -> std::result::Result<(Patient, Vec<FhirOperationOutcomeIssue>), FhirErrorResponse> {
+    let invalid = |message: String| {
+        let outcome = FhirOperationOutcome::invalid(&message);
+        (StatusCode::BAD_REQUEST, Json(serde_json::to_value(outcome).unwrap()))
+    };
+
+    let fhir_patient: FhirPatient = serde_json::from_value(body.clone())
+        .map_err(|e| invalid(format!("Invalid Patient resource: {}", e)))?;
+    let mut issues = unsupported_patient_elements(body);
+    let (patient, mapping_issues) = from_fhir_patient_with_issues(&fhir_patient, identifier_rules).map_err(|e| invalid(e.to_string()))?;
+    issues.extend(mapping_issues);
+
+    if handling == FhirHandling::Strict && !issues.is_empty() {
+        let outcome = FhirOperationOutcome::rejected(issues);
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::to_value(outcome).unwrap())));
+    }
+    Ok((patient, issues))
+}
+
+/// Response body for a stored Patient
+///
+/// Issues found while reading the request are logged either way, so lossy
+/// feeds can be spotted from the server side too.
+fn stored_patient_body(
+    patient: &Patient,
+    action: &str,
+    issues: Vec<FhirOperationOutcomeIssue>,
+    prefer: PreferReturn,
+    authorities: &AuthorityRegistry,
+) -> serde_json::Value {
+    if !issues.is_empty() {
+        tracing::info!("{} Patient/{} with {} ingestion issue(s)", action, patient.id, issues.len());
+    }
+    match prefer {
+        PreferReturn::Representation => {
+            serde_json::to_value(to_fhir_patient_with_authorities(patient, authorities)).unwrap()
+        }
+        PreferReturn::OperationOutcome => {
+            let outcome = FhirOperationOutcome::success(&format!("{} Patient/{}", action, patient.id), issues);
+            serde_json::to_value(outcome).unwrap()
         }
     }
 }
 
 /// Create FHIR Patient
+///
+/// Send `Prefer: return=OperationOutcome` to get back an OperationOutcome
+/// listing every element that was ignored or stored in a reduced form, and
+/// `Prefer: handling=strict` to have such a resource rejected instead.
+/// A resubmission of a stored patient returns that patient with 200,
+/// masked as it is for a read.
+#[utoipa::path(
+    post,
+    path = "/fhir/Patient",
+    tag = "fhir",
+    params(
+        ("Prefer" = Option<String>, Header, description = "`return=OperationOutcome` and/or `handling=strict`")
+    ),
+    request_body = FhirPatient,
+    responses(
+        (status = 200, description = "Resubmission of a stored patient; that Patient or an OperationOutcome, per `Prefer`", body = FhirPatient),
+        (status = 201, description = "Patient created; the stored Patient or an OperationOutcome, per `Prefer`", body = FhirPatient),
+        (status = 400, description = "Invalid Patient resource", body = FhirOperationOutcome),
+        (status = 409, description = "An MRN already belongs to another patient under the same assigning authority, or the derived ID to a deleted patient", body = FhirOperationOutcome),
+        (status = 422, description = "Unmapped data under strict handling", body = FhirOperationOutcome),
+        (status = 500, description = "Internal server error", body = FhirOperationOutcome)
+    )
+)]
 pub async fn create_fhir_patient(
     State(state): State<AppState>,
-    Json(fhir_patient): Json<FhirPatient>,
+    headers: HeaderMap,
+    WithRawBody(Json(body), raw): WithRawBody<Json<serde_json::Value>>,
 ) -> impl IntoResponse {
-    // Convert FHIR to internal model
-    match from_fhir_patient(&fhir_patient) {
-        Ok(mut patient) => {
-            // Ensure patient has a UUID
-            if patient.id == Uuid::nil() {
-                patient.id = Uuid::new_v4();
-            }
+    let preferences = Preferences::from_headers(&headers, state.config.fhir.handling);
+    let rules = state.identifier_rules();
 
-            // Insert into database
-            match state.patient_repository.create(&patient) {
-                Ok(created_patient) => {
-                    // Index in search engine
-                    if let Err(e) = state.search_engine.index_patient(&created_patient) {
-                        tracing::warn!("Failed to index patient in search engine: {}", e);
-                    }
-
-                    let fhir_response = to_fhir_patient(&created_patient);
-                    (StatusCode::CREATED, Json(serde_json::to_value(fhir_response).unwrap()))
-                }
-                Err(e) => {
-                    let outcome = FhirOperationOutcome::error("database-error", &e.to_string());
-                    (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap()))
-                }
-            }
+    match store_patient(&state, &rules, &body, preferences.handling, None, &headers) {
+        // Like a conditional create that found its match
+        Ok(stored) if stored.resubmitted => {
+            let requester = Requester::from_headers(&headers);
+            let Some(patient) = privacy::view(&state, &requester, stored.patient, Read::Direct, "POST /fhir/Patient") else {
+                unreachable!("direct reads are masked, never excluded");
+            };
+            let body = stored_patient_body(&patient, "Found existing", stored.issues, preferences.return_, &rules.authorities);
+            (StatusCode::OK, Json(body))
         }
-        Err(e) => {
-            let outcome = FhirOperationOutcome::invalid(&e.to_string());
-            (StatusCode::BAD_REQUEST, Json(serde_json::to_value(outcome).unwrap()))
+        Ok(stored) => {
+            archive::archive(state.message_archive.as_ref(), stored.patient.id, "CREATE", CHANNEL_FHIR, &raw.content_type, &raw.bytes);
+
+            let body = stored_patient_body(&stored.patient, "Created", stored.issues, preferences.return_, &rules.authorities);
+            (StatusCode::CREATED, Json(body))
         }
+        Err(response) => quarantine_refused(&state, &raw, None, response),
     }
 }
 
 /// Update FHIR Patient
+///
+/// Honours the same `Prefer` headers as [`create_fhir_patient`].
+#[utoipa::path(
+    put,
+    path = "/fhir/Patient/{id}",
+    tag = "fhir",
+    params(
+        ("id" = Uuid, Path, description = "Patient UUID"),
+        ("Prefer" = Option<String>, Header, description = "`return=OperationOutcome` and/or `handling=strict`"),
+        ("X-Lock-Holder" = Option<String>, Header, description = "Steward holding the record lock, if any")
+    ),
+    request_body = FhirPatient,
+    responses(
+        (status = 200, description = "Patient updated; the stored Patient or an OperationOutcome, per `Prefer`", body = FhirPatient),
+        (status = 400, description = "Invalid Patient resource", body = FhirOperationOutcome),
+        (status = 409, description = "An MRN already belongs to another patient under the same assigning authority", body = FhirOperationOutcome),
+        (status = 422, description = "Unmapped data under strict handling", body = FhirOperationOutcome),
+        (status = 423, description = "Patient is locked by another steward", body = FhirOperationOutcome),
+        (status = 500, description = "Internal server error", body = FhirOperationOutcome)
+    )
+)]
 pub async fn update_fhir_patient(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
-    Json(fhir_patient): Json<FhirPatient>,
+    headers: HeaderMap,
+    WithRawBody(Json(body), raw): WithRawBody<Json<serde_json::Value>>,
 ) -> impl IntoResponse {
+    let preferences = Preferences::from_headers(&headers, state.config.fhir.handling);
+    if let Err(response) = check_record_lock(&state, id, &headers) {
+        return response;
+    }
+    let rules = state.identifier_rules();
+
+    match store_patient(&state, &rules, &body, preferences.handling, Some(id), &headers) {
+        Ok(stored) => {
+            archive::archive(state.message_archive.as_ref(), stored.patient.id, "UPDATE", CHANNEL_FHIR, &raw.content_type, &raw.bytes);
+
+            let body = stored_patient_body(&stored.patient, "Updated", stored.issues, preferences.return_, &rules.authorities);
+            (StatusCode::OK, Json(body))
+        }
+        Err(response) => quarantine_refused(&state, &raw, Some(id), response),
+    }
+}
+
+/// Source of a Patient write: `X-Source-System`, else the resource's
+/// `meta.source`, else the FHIR channel
+pub(crate) fn write_source(headers: &HeaderMap, body: &serde_json::Value) -> String {
+    survivorship::source_from_headers(headers)
+        .or_else(|| body.pointer("/meta/source").and_then(|v| v.as_str()).map(str::to_string))
+        .unwrap_or_else(|| CHANNEL_FHIR.to_string())
+}
+
+/// A Patient resource as stored
+pub(crate) struct StoredPatient {
+    pub patient: Patient,
+    pub issues: Vec<FhirOperationOutcomeIssue>,
+    /// The resource resubmitted a stored patient, which is returned unchanged
+    pub resubmitted: bool,
+}
+
+/// Map, check and store a Patient resource, creating it or, with `id`,
+/// updating that patient
+///
+/// A created resource with the fingerprint of a stored patient, or whose ID
+/// derived through `identifiers.id_namespaces` is on file, is taken as a
+/// resubmission of that patient and nothing is created; otherwise a created
+/// patient without an MRN may be given one. Shared by the create
+/// and update interactions and by resubmission of quarantined resources.
+/// The resource is kept as a source record from its [`write_source`].
+pub(crate) fn store_patient(
+    state: &AppState,
+    rules: &IdentifierRules,
+    body: &serde_json::Value,
+    handling: FhirHandling,
+    id: Option<Uuid>,
+    headers: &HeaderMap,
+) -> std::result::Result<StoredPatient, FhirErrorResponse> {
+    let source = &write_source(headers, body);
     // Convert FHIR to internal model
-    match from_fhir_patient(&fhir_patient) {
-        Ok(mut patient) => {
-            // Ensure ID in path matches payload
-            patient.id = id;
-
-            // Update in database
-            match state.patient_repository.update(&patient) {
-                Ok(updated_patient) => {
-                    // Update in search index
-                    if let Err(e) = state.search_engine.index_patient(&updated_patient) {
-                        tracing::warn!("Failed to update patient in search engine: {}", e);
-                    }
+    let (mut patient, issues) = read_patient(body, handling, rules)?;
 
-                    let fhir_response = to_fhir_patient(&updated_patient);
-                    (StatusCode::OK, Json(serde_json::to_value(fhir_response).unwrap()))
-                }
-                Err(e) => {
-                    let outcome = FhirOperationOutcome::error("database-error", &e.to_string());
-                    (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap()))
-                }
+    match id {
+        // Ensure ID in path matches payload
+        Some(id) => patient.id = id,
+        // Ensure patient has a UUID; a replayed source record gets its patient back
+        None => {
+            let replayed =
+                assign_patient_id(&state.config.identifiers, state.patient_repository.as_ref(), &mut patient).map_err(|e| {
+                    let (status, code) = match e {
+                        crate::Error::PatientDeleted(_) => (StatusCode::CONFLICT, "conflict"),
+                        _ => (StatusCode::INTERNAL_SERVER_ERROR, "database-error"),
+                    };
+                    let outcome = FhirOperationOutcome::error(code, &e.to_string());
+                    (status, Json(serde_json::to_value(outcome).unwrap()))
+                })?;
+            if let Some(existing) = replayed {
+                return Ok(StoredPatient { patient: existing, issues, resubmitted: true });
             }
         }
-        Err(e) => {
-            let outcome = FhirOperationOutcome::invalid(&e.to_string());
-            (StatusCode::BAD_REQUEST, Json(serde_json::to_value(outcome).unwrap()))
+    }
+    if id.is_none() {
+        match find_resubmitted(&patient, state.patient_repository.as_ref()) {
+            Ok(Some(existing)) => {
+                return Ok(StoredPatient { patient: existing, issues, resubmitted: true });
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Fingerprint lookup failed: {}", e),
+        }
+    }
+    check_mrn_conflicts(state, rules, &patient)?;
+    if id.is_none() {
+        assign_mrn(state.mrn_sequences.as_ref(), &state.config.identifiers, &mut patient).map_err(|e| {
+            let outcome = FhirOperationOutcome::error("exception", &format!("Failed to assign an MRN: {}", e));
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap()))
+        })?;
+    }
+
+    let mut existing = None;
+    let stored = match id {
+        Some(id) => {
+            // FHIR does not carry verification status, so keep what is on file;
+            // contact persons are served as RelatedPerson and kept as well
+            existing = state.patient_repository.get_by_id_for_update(&id).map_err(|e| {
+                let outcome = FhirOperationOutcome::error("database-error", &e.to_string());
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap()))
+            })?;
+            if let Some(existing) = &existing {
+                patient.keep_verification_from(existing);
+                patient.contacts = existing.contacts.clone();
+                survivorship::apply(state, existing, &mut patient, source);
+                // A merged patient cannot be reactivated, nor a death undone by deactivating
+                patient.keep_status_from(existing).map_err(|e| {
+                    let outcome = FhirOperationOutcome::error("business-rule", &e.to_string());
+                    (StatusCode::CONFLICT, Json(serde_json::to_value(outcome).unwrap()))
+                })?;
+            }
+            let submission = survivorship::submission_from_headers(headers, source, &patient);
+            state.patient_repository.update_from_source(&patient, &submission)
+        }
+        None => {
+            let submission = survivorship::submission_from_headers(headers, source, &patient);
+            state.patient_repository.create_from_source(&patient, &submission)
         }
+    };
+    let stored = stored.map_err(|e| {
+        let outcome = FhirOperationOutcome::error("database-error", &e.to_string());
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap()))
+    })?;
+    survivorship::record(state, stored.id, existing.as_ref(), &stored, source);
+
+    // Index in search engine
+    if let Err(e) = state.search_engine.index_patient(&stored) {
+        tracing::warn!("Failed to index patient in search engine: {}", e);
     }
+    Ok(StoredPatient { patient: stored, issues, resubmitted: false })
+}
+
+/// Quarantine a Patient resource refused for its content, passing the
+/// refusal through
+fn quarantine_refused(
+    state: &AppState,
+    raw: &RawBody,
+    patient_id: Option<Uuid>,
+    response: FhirErrorResponse,
+) -> FhirErrorResponse {
+    if quarantine::is_refusal(response.0) {
+        quarantine::quarantine(
+            state.quarantine.as_ref(),
+            CHANNEL_FHIR,
+            &raw.content_type,
+            &String::from_utf8_lossy(&raw.bytes),
+            &response.1 .0,
+            patient_id,
+        );
+    }
+    response
 }
 
 /// Delete FHIR Patient
+#[utoipa::path(
+    delete,
+    path = "/fhir/Patient/{id}",
+    tag = "fhir",
+    params(
+        ("id" = Uuid, Path, description = "Patient UUID"),
+        ("X-Lock-Holder" = Option<String>, Header, description = "Steward holding the record lock, if any")
+    ),
+    responses(
+        (status = 204, description = "Patient deleted"),
+        (status = 423, description = "Patient is locked by another steward", body = FhirOperationOutcome),
+        (status = 500, description = "Internal server error", body = FhirOperationOutcome)
+    )
+)]
 pub async fn delete_fhir_patient(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
+    if let Err(response) = check_record_lock(&state, id, &headers) {
+        return response;
+    }
+
     match state.patient_repository.delete(&id) {
         Ok(()) => {
             (StatusCode::NO_CONTENT, Json(serde_json::json!({})))
@@ -154,30 +512,114 @@ pub async fn delete_fhir_patient(
     }
 }
 
+/// Refuse a write to a patient locked by a steward other than the `X-Lock-Holder`
+fn check_record_lock(state: &AppState, id: Uuid, headers: &HeaderMap) -> Result<(), FhirErrorResponse> {
+    use crate::api::rest::handlers::lock_holder;
+
+    match state.record_locks.find_conflict(&[id], lock_holder(headers)) {
+        Ok(None) => Ok(()),
+        Ok(Some(lock)) => {
+            let outcome = FhirOperationOutcome::error(
+                "lock-error",
+                &format!(
+                    "Patient '{}' is locked by '{}' until {}",
+                    lock.patient_id, lock.locked_by, lock.expires_at.to_rfc3339()
+                ),
+            );
+            Err((StatusCode::LOCKED, Json(serde_json::to_value(outcome).unwrap())))
+        }
+        Err(e) => {
+            let outcome = FhirOperationOutcome::error("database-error", &e.to_string());
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap())))
+        }
+    }
+}
+
+/// Refuse a Patient with an MRN another patient holds under the same assigning authority
+fn check_mrn_conflicts(state: &AppState, rules: &IdentifierRules, patient: &Patient) -> Result<(), FhirErrorResponse> {
+    match rules.mrn_conflicts(patient, state.patient_repository.as_ref()) {
+        Ok(conflicts) if conflicts.is_empty() => Ok(()),
+        Ok(conflicts) => {
+            let issues = conflicts
+                .into_iter()
+                .map(|conflict| {
+                    FhirOperationOutcomeIssue::warning(
+                        "duplicate",
+                        format!("MRN from '{}' already belongs to Patient/{}", conflict.authority, conflict.patient_id),
+                        format!("Patient.identifier[{}].value", conflict.index),
+                    )
+                })
+                .collect();
+            let outcome = FhirOperationOutcome::rejected(issues);
+            Err((StatusCode::CONFLICT, Json(serde_json::to_value(outcome).unwrap())))
+        }
+        Err(e) => {
+            let outcome = FhirOperationOutcome::error("database-error", &e.to_string());
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap())))
+        }
+    }
+}
+
 /// Search FHIR Patients
+///
+/// With `_elements`, each Patient is trimmed to those elements and tagged
+/// `SUBSETTED`.
+#[utoipa::path(
+    get,
+    path = "/fhir/Patient",
+    tag = "fhir",
+    params(FhirSearchParams),
+    responses(
+        (status = 200, description = "Searchset bundle of Patients", body = FhirBundle),
+        (status = 400, description = "No search parameters", body = FhirOperationOutcome),
+        (status = 500, description = "Search error", body = FhirOperationOutcome)
+    )
+)]
 pub async fn search_fhir_patients(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Query(params): Query<FhirSearchParams>,
 ) -> impl IntoResponse {
+    let requester = Requester::from_headers(&headers);
+    let limit = params.count.unwrap_or(10).min(100);
+
+    // A telecom token without a system is an email if it contains '@'
+    let (telecom_phone, telecom_email) = match params.telecom.as_deref().map(|t| t.split_once('|').unwrap_or(("", t))) {
+        Some(("email", value)) => (None, Some(value)),
+        Some(("phone" | "sms", value)) => (Some(value), None),
+        Some((_, value)) if value.contains('@') => (None, Some(value)),
+        Some((_, value)) => (Some(value), None),
+        None => (None, None),
+    };
+    let phone = params.phone.as_deref().or(telecom_phone);
+    let email = params.email.as_deref().or(telecom_email);
+
     // Build search query from FHIR parameters
-    let search_query = if let Some(ref name) = params.name {
-        name.clone()
-    } else if let Some(ref family) = params.family {
-        family.clone()
-    } else if let Some(ref given) = params.given {
-        given.clone()
+    let patient_ids = if phone.is_some() || email.is_some() {
+        state
+            .search_engine
+            .search_by_telecom(phone, email, limit)
+            .map(|hits| hits.into_iter().map(|hit| hit.patient_id).collect::<Vec<_>>())
     } else {
-        // No search criteria provided
-        let outcome = FhirOperationOutcome::invalid("At least one search parameter is required");
-        return (StatusCode::BAD_REQUEST, Json(serde_json::to_value(outcome).unwrap()));
+        let search_query = if let Some(ref name) = params.name {
+            name.clone()
+        } else if let Some(ref family) = params.family {
+            family.clone()
+        } else if let Some(ref given) = params.given {
+            given.clone()
+        } else {
+            // No search criteria provided
+            let outcome = FhirOperationOutcome::invalid("At least one search parameter is required");
+            return (StatusCode::BAD_REQUEST, Json(serde_json::to_value(outcome).unwrap()));
+        };
+        state.search_engine.search(&search_query, limit)
     };
 
-    let limit = params.count.unwrap_or(10).min(100);
-
-    // Search using search engine
-    match state.search_engine.search(&search_query, limit) {
+    match patient_ids {
         Ok(patient_ids) => {
             // Fetch patients from database and convert to FHIR
+            let authorities = state.authority_registry();
+            let elements = fields::parse(params.elements.as_deref());
             let mut fhir_entries = Vec::new();
             for patient_id_str in &patient_ids {
                 // Parse string ID to UUID
@@ -191,7 +633,16 @@ pub async fn search_fhir_patients(
 
                 match state.patient_repository.get_by_id(&patient_id) {
                     Ok(Some(patient)) => {
-                        let fhir_patient = to_fhir_patient(&patient);
+                        let Some(patient) =
+                            privacy::view(&state, &requester, patient, Read::Search, "GET /fhir/Patient")
+                        else {
+                            continue;
+                        };
+                        let fhir_patient = to_fhir_patient_with_authorities(&patient, &authorities);
+                        let mut fhir_patient = serde_json::to_value(fhir_patient).unwrap();
+                        if let Some(elements) = &elements {
+                            fields::select_fhir(&mut fhir_patient, elements);
+                        }
                         fhir_entries.push(serde_json::json!({
                             "fullUrl": format!("Patient/{}", patient.id),
                             "resource": fhir_patient
@@ -220,3 +671,857 @@ pub async fn search_fhir_patients(
         }
     }
 }
+
+/// FHIR history parameters
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FhirHistoryParams {
+    /// Number of versions to return
+    #[serde(rename = "_count")]
+    pub count: Option<usize>,
+}
+
+/// Get the version history of a FHIR Patient
+///
+/// Each audit log entry for the patient is one version; the version id is
+/// the audit log id, which is also the id of the matching Provenance.
+#[utoipa::path(
+    get,
+    path = "/fhir/Patient/{id}/_history",
+    tag = "fhir",
+    params(
+        ("id" = Uuid, Path, description = "Patient UUID"),
+        FhirHistoryParams
+    ),
+    responses(
+        (status = 200, description = "History bundle, newest version first", body = FhirBundle),
+        (status = 500, description = "Internal server error", body = FhirOperationOutcome)
+    )
+)]
+pub async fn get_fhir_patient_history(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    Query(params): Query<FhirHistoryParams>,
+) -> impl IntoResponse {
+    let limit = params.count.unwrap_or(DEFAULT_HISTORY_COUNT).min(MAX_HISTORY_COUNT);
+    let requester = Requester::from_headers(&headers);
+
+    let history = state.audit_log.get_logs_for_entity("Patient", id, limit as i64).and_then(|logs| {
+        let versions = logs
+            .iter()
+            .filter(|log| log.action != "RESTRICTED_ACCESS")
+            .filter_map(|log| log.new_values.as_ref());
+        let masked = privacy::masks_versions(&state, &requester, &id, versions, "GET /fhir/Patient/{id}/_history")?;
+        Ok((logs, masked))
+    });
+    match history {
+        Ok((logs, masked)) => {
+            let authorities = state.authority_registry();
+            let entries: Vec<serde_json::Value> = logs
+                .iter()
+                .map(|log| {
+                    let version_url = patient_version_reference(&log.entity_id, &log.id);
+                    let method = match log.action.as_str() {
+                        "CREATE" => "POST",
+                        "DELETE" => "DELETE",
+                        _ => "PUT",
+                    };
+
+                    let version = match masked {
+                        true => log.new_values.as_ref().and_then(privacy::masked_version),
+                        false => log.new_values.clone(),
+                    };
+                    let resource = version
+                        .and_then(|v| serde_json::from_value::<Patient>(v).ok())
+                        .map(|patient| {
+                            let mut fhir_patient = to_fhir_patient_with_authorities(&patient, &authorities);
+                            if let Some(meta) = fhir_patient.meta.as_mut() {
+                                meta.version_id = Some(log.id.to_string());
+                            }
+                            fhir_patient
+                        });
+
+                    let mut entry = serde_json::json!({
+                        "fullUrl": version_url,
+                        "request": {
+                            "method": method,
+                            "url": format!("Patient/{}", log.entity_id)
+                        },
+                        "response": {
+                            "status": if method == "POST" { "201" } else { "200" },
+                            "lastModified": log.timestamp.to_rfc3339()
+                        }
+                    });
+                    if let Some(resource) = resource {
+                        entry["resource"] = serde_json::to_value(resource).unwrap();
+                    }
+                    entry
+                })
+                .collect();
+
+            let bundle = serde_json::json!({
+                "resourceType": "Bundle",
+                "type": "history",
+                "total": entries.len(),
+                "entry": entries
+            });
+            (StatusCode::OK, Json(bundle))
+        }
+        Err(e) => {
+            let outcome = FhirOperationOutcome::error("database-error", &e.to_string());
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap()))
+        }
+    }
+}
+
+/// FHIR Provenance search parameters
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FhirProvenanceSearchParams {
+    /// Target resource, as `Patient/{id}` or a bare patient id
+    pub target: Option<String>,
+
+    /// Number of results
+    #[serde(rename = "_count")]
+    pub count: Option<usize>,
+}
+
+/// Search FHIR Provenance by target patient
+#[utoipa::path(
+    get,
+    path = "/fhir/Provenance",
+    tag = "fhir",
+    params(FhirProvenanceSearchParams),
+    responses(
+        (status = 200, description = "Searchset bundle of Provenance resources", body = FhirBundle),
+        (status = 400, description = "Missing or unsupported target", body = FhirOperationOutcome),
+        (status = 500, description = "Internal server error", body = FhirOperationOutcome)
+    )
+)]
+pub async fn search_fhir_provenance(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<FhirProvenanceSearchParams>,
+) -> impl IntoResponse {
+    let target = match params.target.as_deref() {
+        Some(target) => target,
+        None => {
+            let outcome = FhirOperationOutcome::invalid("The 'target' search parameter is required");
+            return (StatusCode::BAD_REQUEST, Json(serde_json::to_value(outcome).unwrap()));
+        }
+    };
+
+    // Accept Patient/{id} and Patient/{id}/_history/{vid}, or a bare id
+    let patient_id = target
+        .strip_prefix("Patient/")
+        .unwrap_or(target)
+        .split('/')
+        .next()
+        .and_then(|id| Uuid::parse_str(id).ok());
+    let patient_id = match patient_id {
+        Some(id) => id,
+        None => {
+            let outcome = FhirOperationOutcome::invalid(&format!("Unsupported target '{}'", target));
+            return (StatusCode::BAD_REQUEST, Json(serde_json::to_value(outcome).unwrap()));
+        }
+    };
+
+    let limit = params.count.unwrap_or(DEFAULT_HISTORY_COUNT).min(MAX_HISTORY_COUNT);
+
+    // Provenance carries no demographics, but reading it is a read of the
+    // patient and is audited like one
+    let requester = Requester::from_headers(&headers);
+    if let Err(e) = privacy::masks_patient(&state, &requester, &patient_id, "GET /fhir/Provenance") {
+        let outcome = FhirOperationOutcome::error("database-error", &e.to_string());
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap()));
+    }
+
+    // Fetch one extra row so the oldest returned version can reference its predecessor
+    match state.audit_log.get_logs_for_entity("Patient", patient_id, limit as i64 + 1) {
+        Ok(logs) => {
+            let entries: Vec<serde_json::Value> = logs
+                .iter()
+                .enumerate()
+                .take(limit)
+                .map(|(i, log)| {
+                    let provenance = to_fhir_provenance(log, logs.get(i + 1));
+                    serde_json::json!({
+                        "fullUrl": format!("Provenance/{}", log.id),
+                        "resource": provenance
+                    })
+                })
+                .collect();
+
+            let bundle = serde_json::json!({
+                "resourceType": "Bundle",
+                "type": "searchset",
+                "total": entries.len(),
+                "entry": entries
+            });
+            (StatusCode::OK, Json(bundle))
+        }
+        Err(e) => {
+            let outcome = FhirOperationOutcome::error("database-error", &e.to_string());
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap()))
+        }
+    }
+}
+
+/// Search FHIR AuditEvents
+///
+/// Supports `patient` (`Patient/{id}` or a bare id), repeated `date`
+/// parameters with comparison prefixes, and `_count`.
+#[utoipa::path(
+    get,
+    path = "/fhir/AuditEvent",
+    tag = "fhir",
+    params(
+        ("patient" = Option<String>, Query, description = "`Patient/{id}` or a bare patient id"),
+        ("date" = Option<Vec<String>>, Query, description = "Recorded time with an optional `eq`, `gt`, `ge`, `lt` or `le` prefix; may repeat"),
+        ("_count" = Option<usize>, Query, description = "Number of results (default 50, max 500)")
+    ),
+    responses(
+        (status = 200, description = "Searchset bundle of AuditEvents", body = FhirBundle),
+        (status = 400, description = "Invalid patient or date", body = FhirOperationOutcome),
+        (status = 500, description = "Internal server error", body = FhirOperationOutcome)
+    )
+)]
+pub async fn search_fhir_audit_events(
+    State(state): State<AppState>,
+    Query(params): Query<Vec<(String, String)>>,
+) -> impl IntoResponse {
+    let mut patient_id = None;
+    let mut range = DateRange::default();
+    let mut limit = DEFAULT_HISTORY_COUNT;
+
+    for (name, value) in &params {
+        match name.as_str() {
+            "patient" => {
+                let id = value.strip_prefix("Patient/").unwrap_or(value);
+                match Uuid::parse_str(id) {
+                    Ok(id) => patient_id = Some(id),
+                    Err(_) => {
+                        let outcome = FhirOperationOutcome::invalid(&format!("Invalid patient '{}'", value));
+                        return (StatusCode::BAD_REQUEST, Json(serde_json::to_value(outcome).unwrap()));
+                    }
+                }
+            }
+            "date" => {
+                if let Err(e) = range.apply(value) {
+                    let outcome = FhirOperationOutcome::invalid(&e.to_string());
+                    return (StatusCode::BAD_REQUEST, Json(serde_json::to_value(outcome).unwrap()));
+                }
+            }
+            "_count" => {
+                limit = value.parse::<usize>().unwrap_or(DEFAULT_HISTORY_COUNT).min(MAX_HISTORY_COUNT);
+            }
+            _ => {}
+        }
+    }
+
+    let entity_type = patient_id.map(|_| "Patient");
+
+    match state.audit_log.query_logs(entity_type, patient_id, range.since, range.until, limit as i64) {
+        Ok(logs) => {
+            let entries: Vec<serde_json::Value> = logs
+                .iter()
+                .map(|log| {
+                    serde_json::json!({
+                        "fullUrl": format!("AuditEvent/{}", log.id),
+                        "resource": to_fhir_audit_event(log)
+                    })
+                })
+                .collect();
+
+            let bundle = serde_json::json!({
+                "resourceType": "Bundle",
+                "type": "searchset",
+                "total": entries.len(),
+                "entry": entries
+            });
+            (StatusCode::OK, Json(bundle))
+        }
+        Err(e) => {
+            let outcome = FhirOperationOutcome::error("database-error", &e.to_string());
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap()))
+        }
+    }
+}
+
+/// Get FHIR Practitioner by ID
+#[utoipa::path(
+    get,
+    path = "/fhir/Practitioner/{id}",
+    tag = "fhir",
+    params(
+        ("id" = Uuid, Path, description = "Practitioner UUID")
+    ),
+    responses(
+        (status = 200, description = "Practitioner found", body = FhirPractitioner),
+        (status = 404, description = "Practitioner not found", body = FhirOperationOutcome),
+        (status = 500, description = "Internal server error", body = FhirOperationOutcome)
+    )
+)]
+pub async fn get_fhir_practitioner(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match state.practitioners.get_by_id(&id) {
+        Ok(Some(practitioner)) => {
+            let fhir_practitioner = to_fhir_practitioner(&practitioner, &state.authority_registry());
+            (StatusCode::OK, Json(serde_json::to_value(fhir_practitioner).unwrap()))
+        }
+        Ok(None) => {
+            let outcome = FhirOperationOutcome::not_found("Practitioner", &id.to_string());
+            (StatusCode::NOT_FOUND, Json(serde_json::to_value(outcome).unwrap()))
+        }
+        Err(e) => {
+            let outcome = FhirOperationOutcome::error("database-error", &e.to_string());
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap()))
+        }
+    }
+}
+
+/// Read a Practitioner from a request body along with what cannot be stored
+///
+/// Under strict handling any such issue rejects the resource with 422.
+fn read_practitioner(
+    body: &serde_json::Value,
+    handling: FhirHandling,
+    identifier_rules: &IdentifierRules,
+) -> std::result::Result<(Practitioner, Vec<FhirOperationOutcomeIssue>), FhirErrorResponse> {
+    let invalid = |message: String| {
+        let outcome = FhirOperationOutcome::invalid(&message);
+        (StatusCode::BAD_REQUEST, Json(serde_json::to_value(outcome).unwrap()))
+    };
+
+    let fhir_practitioner: FhirPractitioner = serde_json::from_value(body.clone())
+        .map_err(|e| invalid(format!("Invalid Practitioner resource: {}", e)))?;
+    let mut issues = unsupported_practitioner_elements(body);
+    let (practitioner, mapping_issues) = from_fhir_practitioner_with_issues(&fhir_practitioner, identifier_rules)
+        .map_err(|e| invalid(e.to_string()))?;
+    issues.extend(mapping_issues);
+
+    if handling == FhirHandling::Strict && !issues.is_empty() {
+        let outcome = FhirOperationOutcome::rejected(issues);
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::to_value(outcome).unwrap())));
+    }
+    Ok((practitioner, issues))
+}
+
+/// Response body for a stored Practitioner
+fn stored_practitioner_body(
+    practitioner: &Practitioner,
+    action: &str,
+    issues: Vec<FhirOperationOutcomeIssue>,
+    prefer: PreferReturn,
+    authorities: &AuthorityRegistry,
+) -> serde_json::Value {
+    if !issues.is_empty() {
+        tracing::info!("{} Practitioner/{} with {} ingestion issue(s)", action, practitioner.id, issues.len());
+    }
+    match prefer {
+        PreferReturn::Representation => {
+            serde_json::to_value(to_fhir_practitioner(practitioner, authorities)).unwrap()
+        }
+        PreferReturn::OperationOutcome => {
+            let outcome = FhirOperationOutcome::success(&format!("{} Practitioner/{}", action, practitioner.id), issues);
+            serde_json::to_value(outcome).unwrap()
+        }
+    }
+}
+
+/// Refuse a Practitioner with an NPI another practitioner holds
+fn check_npi_conflict(state: &AppState, practitioner: &Practitioner) -> Result<(), FhirErrorResponse> {
+    match state.practitioners.npi_holder(practitioner) {
+        Ok(None) => Ok(()),
+        Ok(Some(holder)) => {
+            let index = practitioner
+                .identifiers
+                .iter()
+                .position(|id| id.identifier_type == IdentifierType::NPI)
+                .unwrap_or_default();
+            let issue = FhirOperationOutcomeIssue::warning(
+                "duplicate",
+                format!("NPI already belongs to Practitioner/{}", holder),
+                format!("Practitioner.identifier[{}].value", index),
+            );
+            let outcome = FhirOperationOutcome::rejected(vec![issue]);
+            Err((StatusCode::CONFLICT, Json(serde_json::to_value(outcome).unwrap())))
+        }
+        Err(e) => {
+            let outcome = FhirOperationOutcome::error("database-error", &e.to_string());
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap())))
+        }
+    }
+}
+
+/// Create FHIR Practitioner
+///
+/// Honours the same `Prefer` headers as [`create_fhir_patient`].
+#[utoipa::path(
+    post,
+    path = "/fhir/Practitioner",
+    tag = "fhir",
+    params(
+        ("Prefer" = Option<String>, Header, description = "`return=OperationOutcome` and/or `handling=strict`")
+    ),
+    request_body = FhirPractitioner,
+    responses(
+        (status = 201, description = "Practitioner created; the stored Practitioner or an OperationOutcome, per `Prefer`", body = FhirPractitioner),
+        (status = 400, description = "Invalid Practitioner resource", body = FhirOperationOutcome),
+        (status = 409, description = "The NPI already belongs to another practitioner", body = FhirOperationOutcome),
+        (status = 422, description = "Unmapped data under strict handling", body = FhirOperationOutcome),
+        (status = 500, description = "Internal server error", body = FhirOperationOutcome)
+    )
+)]
+pub async fn create_fhir_practitioner(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(body): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    let preferences = Preferences::from_headers(&headers, state.config.fhir.handling);
+
+    let rules = state.identifier_rules();
+    let (mut practitioner, issues) = match read_practitioner(&body, preferences.handling, &rules) {
+        Ok(read) => read,
+        Err(response) => return response,
+    };
+    if practitioner.id == Uuid::nil() {
+        practitioner.id = Uuid::new_v4();
+    }
+    if let Err(response) = check_npi_conflict(&state, &practitioner) {
+        return response;
+    }
+
+    match state.practitioners.create(&practitioner) {
+        Ok(created) => {
+            let body = stored_practitioner_body(&created, "Created", issues, preferences.return_, &rules.authorities);
+            (StatusCode::CREATED, Json(body))
+        }
+        Err(e) => {
+            let outcome = FhirOperationOutcome::error("database-error", &e.to_string());
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap()))
+        }
+    }
+}
+
+/// Update FHIR Practitioner
+///
+/// Honours the same `Prefer` headers as [`create_fhir_patient`].
+#[utoipa::path(
+    put,
+    path = "/fhir/Practitioner/{id}",
+    tag = "fhir",
+    params(
+        ("id" = Uuid, Path, description = "Practitioner UUID"),
+        ("Prefer" = Option<String>, Header, description = "`return=OperationOutcome` and/or `handling=strict`")
+    ),
+    request_body = FhirPractitioner,
+    responses(
+        (status = 200, description = "Practitioner updated; the stored Practitioner or an OperationOutcome, per `Prefer`", body = FhirPractitioner),
+        (status = 400, description = "Invalid Practitioner resource", body = FhirOperationOutcome),
+        (status = 404, description = "Practitioner not found", body = FhirOperationOutcome),
+        (status = 409, description = "The NPI already belongs to another practitioner", body = FhirOperationOutcome),
+        (status = 422, description = "Unmapped data under strict handling", body = FhirOperationOutcome),
+        (status = 500, description = "Internal server error", body = FhirOperationOutcome)
+    )
+)]
+pub async fn update_fhir_practitioner(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(body): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    let preferences = Preferences::from_headers(&headers, state.config.fhir.handling);
+
+    let rules = state.identifier_rules();
+    let (mut practitioner, issues) = match read_practitioner(&body, preferences.handling, &rules) {
+        Ok(read) => read,
+        Err(response) => return response,
+    };
+    practitioner.id = id;
+    if let Err(response) = check_npi_conflict(&state, &practitioner) {
+        return response;
+    }
+
+    match state.practitioners.update(&practitioner) {
+        Ok(Some(updated)) => {
+            let body = stored_practitioner_body(&updated, "Updated", issues, preferences.return_, &rules.authorities);
+            (StatusCode::OK, Json(body))
+        }
+        Ok(None) => {
+            let outcome = FhirOperationOutcome::not_found("Practitioner", &id.to_string());
+            (StatusCode::NOT_FOUND, Json(serde_json::to_value(outcome).unwrap()))
+        }
+        Err(e) => {
+            let outcome = FhirOperationOutcome::error("database-error", &e.to_string());
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap()))
+        }
+    }
+}
+
+/// Delete FHIR Practitioner
+#[utoipa::path(
+    delete,
+    path = "/fhir/Practitioner/{id}",
+    tag = "fhir",
+    params(
+        ("id" = Uuid, Path, description = "Practitioner UUID")
+    ),
+    responses(
+        (status = 204, description = "Practitioner deleted"),
+        (status = 500, description = "Internal server error", body = FhirOperationOutcome)
+    )
+)]
+pub async fn delete_fhir_practitioner(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    // Deleting a resource that is already gone succeeds, as for Patient
+    match state.practitioners.delete(&id) {
+        Ok(_) => (StatusCode::NO_CONTENT, Json(serde_json::json!({}))),
+        Err(e) => {
+            let outcome = FhirOperationOutcome::error("database-error", &e.to_string());
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap()))
+        }
+    }
+}
+
+/// Get a FHIR RelatedPerson
+///
+/// RelatedPersons are the contact persons of non-deleted patients. Contacts
+/// of restricted and VIP patients are not found by requesters who see the
+/// patient masked.
+#[utoipa::path(
+    get,
+    path = "/fhir/RelatedPerson/{id}",
+    tag = "fhir",
+    params(
+        ("id" = Uuid, Path, description = "Contact person UUID")
+    ),
+    responses(
+        (status = 200, description = "RelatedPerson found", body = FhirRelatedPerson),
+        (status = 404, description = "RelatedPerson not found", body = FhirOperationOutcome),
+        (status = 500, description = "Internal server error", body = FhirOperationOutcome)
+    )
+)]
+pub async fn get_fhir_related_person(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let requester = Requester::from_headers(&headers);
+    let patient = state
+        .patient_repository
+        .find_by_contact(&id)
+        .and_then(|patient_id| match patient_id {
+            Some(patient_id) => state.patient_repository.get_by_id(&patient_id),
+            None => Ok(None),
+        })
+        .map(|patient| {
+            // A masked patient has no contacts, so its contact persons are not found
+            patient.and_then(|patient| {
+                privacy::view(&state, &requester, patient, Read::Direct, "GET /fhir/RelatedPerson/{id}")
+            })
+        });
+
+    let related = patient.map(|patient| {
+        patient.and_then(|patient| {
+            to_fhir_related_persons(&patient)
+                .into_iter()
+                .find(|related| related.id.as_deref() == Some(id.to_string().as_str()))
+        })
+    });
+    match related {
+        Ok(Some(related)) => (StatusCode::OK, Json(serde_json::to_value(related).unwrap())),
+        Ok(None) => {
+            let outcome = FhirOperationOutcome::not_found("RelatedPerson", &id.to_string());
+            (StatusCode::NOT_FOUND, Json(serde_json::to_value(outcome).unwrap()))
+        }
+        Err(e) => {
+            let outcome = FhirOperationOutcome::error("database-error", &e.to_string());
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap()))
+        }
+    }
+}
+
+/// FHIR RelatedPerson search parameters
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FhirRelatedPersonSearchParams {
+    /// `Patient/{id}` or a bare patient id
+    pub patient: Option<String>,
+}
+
+/// Search FHIR RelatedPersons
+///
+/// Requires `patient`; returns that patient's contact persons, in the order
+/// they are recorded, or none when the requester sees the patient masked.
+#[utoipa::path(
+    get,
+    path = "/fhir/RelatedPerson",
+    tag = "fhir",
+    params(FhirRelatedPersonSearchParams),
+    responses(
+        (status = 200, description = "Searchset bundle of RelatedPersons", body = FhirBundle),
+        (status = 400, description = "Missing or invalid patient", body = FhirOperationOutcome),
+        (status = 500, description = "Internal server error", body = FhirOperationOutcome)
+    )
+)]
+pub async fn search_fhir_related_persons(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<FhirRelatedPersonSearchParams>,
+) -> impl IntoResponse {
+    let patient = match params.patient.as_deref() {
+        Some(patient) => patient,
+        None => {
+            let outcome = FhirOperationOutcome::invalid("The 'patient' search parameter is required");
+            return (StatusCode::BAD_REQUEST, Json(serde_json::to_value(outcome).unwrap()));
+        }
+    };
+    let patient_id = match Uuid::parse_str(patient.strip_prefix("Patient/").unwrap_or(patient)) {
+        Ok(id) => id,
+        Err(_) => {
+            let outcome = FhirOperationOutcome::invalid(&format!("Invalid patient '{}'", patient));
+            return (StatusCode::BAD_REQUEST, Json(serde_json::to_value(outcome).unwrap()));
+        }
+    };
+
+    match state.patient_repository.get_by_id(&patient_id) {
+        Ok(patient) => {
+            let requester = Requester::from_headers(&headers);
+            let patient = patient.and_then(|patient| {
+                privacy::view(&state, &requester, patient, Read::Direct, "GET /fhir/RelatedPerson")
+            });
+            let entries: Vec<serde_json::Value> = patient
+                .iter()
+                .flat_map(to_fhir_related_persons)
+                .map(|related| {
+                    serde_json::json!({
+                        "fullUrl": format!("RelatedPerson/{}", related.id.as_deref().unwrap_or_default()),
+                        "resource": related
+                    })
+                })
+                .collect();
+
+            let bundle = serde_json::json!({
+                "resourceType": "Bundle",
+                "type": "searchset",
+                "total": entries.len(),
+                "entry": entries
+            });
+            (StatusCode::OK, Json(bundle))
+        }
+        Err(e) => {
+            let outcome = FhirOperationOutcome::error("database-error", &e.to_string());
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap()))
+        }
+    }
+}
+
+/// Read a Group from a request body
+fn read_group(body: &serde_json::Value) -> std::result::Result<crate::models::PatientGroup, FhirErrorResponse> {
+    let invalid = |message: String| {
+        let outcome = FhirOperationOutcome::invalid(&message);
+        (StatusCode::BAD_REQUEST, Json(serde_json::to_value(outcome).unwrap()))
+    };
+    let fhir_group: FhirGroup = serde_json::from_value(body.clone())
+        .map_err(|e| invalid(format!("Invalid Group resource: {}", e)))?;
+    from_fhir_group(&fhir_group).map_err(|e| invalid(e.to_string()))
+}
+
+/// Response for a failed group write: 400 for unknown members, else 500
+fn group_error(e: crate::Error) -> FhirErrorResponse {
+    match e {
+        crate::Error::Validation(message) => {
+            let outcome = FhirOperationOutcome::invalid(&message);
+            (StatusCode::BAD_REQUEST, Json(serde_json::to_value(outcome).unwrap()))
+        }
+        e => {
+            let outcome = FhirOperationOutcome::error("database-error", &e.to_string());
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::to_value(outcome).unwrap()))
+        }
+    }
+}
+
+/// Get a FHIR Group
+#[utoipa::path(
+    get,
+    path = "/fhir/Group/{id}",
+    tag = "fhir",
+    params(
+        ("id" = Uuid, Path, description = "Group UUID")
+    ),
+    responses(
+        (status = 200, description = "Group found", body = FhirGroup),
+        (status = 404, description = "Group not found", body = FhirOperationOutcome),
+        (status = 500, description = "Internal server error", body = FhirOperationOutcome)
+    )
+)]
+pub async fn get_fhir_group(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let requester = Requester::from_headers(&headers);
+    let group = state.groups.get_by_id(&id).and_then(|group| {
+        group
+            .map(|group| groups::visible(&state, group, &requester, "GET /fhir/Group/{id}"))
+            .transpose()
+    });
+    match group {
+        Ok(Some(group)) => (StatusCode::OK, Json(serde_json::to_value(to_fhir_group(&group)).unwrap())),
+        Ok(None) => {
+            let outcome = FhirOperationOutcome::not_found("Group", &id.to_string());
+            (StatusCode::NOT_FOUND, Json(serde_json::to_value(outcome).unwrap()))
+        }
+        Err(e) => group_error(e),
+    }
+}
+
+/// Create a FHIR Group of patients
+///
+/// The Group must be of type `person` with `enumerated` membership, and
+/// every active member must reference a stored patient. Inactive members
+/// are dropped. The creator is taken from `X-User-Id` and the group is
+/// audited.
+#[utoipa::path(
+    post,
+    path = "/fhir/Group",
+    tag = "fhir",
+    request_body = FhirGroup,
+    responses(
+        (status = 201, description = "Group created", body = FhirGroup),
+        (status = 400, description = "Invalid Group resource or unknown member patients", body = FhirOperationOutcome),
+        (status = 500, description = "Internal server error", body = FhirOperationOutcome)
+    )
+)]
+pub async fn create_fhir_group(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(body): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    let group = match read_group(&body) {
+        Ok(group) => group,
+        Err(response) => return response,
+    };
+
+    match groups::create(&state, &group, &Requester::from_headers(&headers)) {
+        Ok(created) => (StatusCode::CREATED, Json(serde_json::to_value(to_fhir_group(&created)).unwrap())),
+        Err(e) => group_error(e),
+    }
+}
+
+/// Replace a FHIR Group
+///
+/// Patients that stay members keep their place in the group.
+#[utoipa::path(
+    put,
+    path = "/fhir/Group/{id}",
+    tag = "fhir",
+    params(
+        ("id" = Uuid, Path, description = "Group UUID")
+    ),
+    request_body = FhirGroup,
+    responses(
+        (status = 200, description = "Group updated", body = FhirGroup),
+        (status = 400, description = "Invalid Group resource or unknown member patients", body = FhirOperationOutcome),
+        (status = 404, description = "Group not found", body = FhirOperationOutcome),
+        (status = 500, description = "Internal server error", body = FhirOperationOutcome)
+    )
+)]
+pub async fn update_fhir_group(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(body): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    let mut group = match read_group(&body) {
+        Ok(group) => group,
+        Err(response) => return response,
+    };
+    group.id = id;
+
+    match groups::replace(&state, &group, &Requester::from_headers(&headers)) {
+        Ok(Some(updated)) => (StatusCode::OK, Json(serde_json::to_value(to_fhir_group(&updated)).unwrap())),
+        Ok(None) => {
+            let outcome = FhirOperationOutcome::not_found("Group", &id.to_string());
+            (StatusCode::NOT_FOUND, Json(serde_json::to_value(outcome).unwrap()))
+        }
+        Err(e) => group_error(e),
+    }
+}
+
+/// Delete a FHIR Group
+///
+/// Only the group is deleted; its member patients are untouched.
+#[utoipa::path(
+    delete,
+    path = "/fhir/Group/{id}",
+    tag = "fhir",
+    params(
+        ("id" = Uuid, Path, description = "Group UUID")
+    ),
+    responses(
+        (status = 204, description = "Group deleted"),
+        (status = 500, description = "Internal server error", body = FhirOperationOutcome)
+    )
+)]
+pub async fn delete_fhir_group(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    // Deleting a resource that is already gone succeeds, as for Patient
+    match groups::delete(&state, &id, &Requester::from_headers(&headers)) {
+        Ok(_) => (StatusCode::NO_CONTENT, Json(serde_json::json!({}))),
+        Err(e) => group_error(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_preferences_from_headers() {
+        let mut headers = HeaderMap::new();
+        let preferences = Preferences::from_headers(&headers, FhirHandling::Lenient);
+        assert_eq!(preferences.return_, PreferReturn::Representation);
+        assert_eq!(preferences.handling, FhirHandling::Lenient);
+
+        headers.insert("prefer", HeaderValue::from_static("return=OperationOutcome; handling=strict"));
+        let preferences = Preferences::from_headers(&headers, FhirHandling::Lenient);
+        assert_eq!(preferences.return_, PreferReturn::OperationOutcome);
+        assert_eq!(preferences.handling, FhirHandling::Strict);
+
+        headers.insert("prefer", HeaderValue::from_static("handling=lenient"));
+        assert_eq!(Preferences::from_headers(&headers, FhirHandling::Strict).handling, FhirHandling::Lenient);
+    }
+
+    #[test]
+    fn test_strict_handling_rejects_unmapped_data() {
+        let body = serde_json::json!({
+            "resourceType": "Patient",
+            "name": [{ "family": "Osei", "given": ["Kwame"] }],
+            "gender": "male",
+            "communication": [{ "language": { "text": "Twi" } }]
+        });
+
+        let (patient, issues) = read_patient(&body, FhirHandling::Lenient, &IdentifierRules::default()).unwrap();
+        assert_eq!(patient.name.family, "Osei");
+        assert_eq!(issues.len(), 1);
+
+        let (status, Json(outcome)) = read_patient(&body, FhirHandling::Strict, &IdentifierRules::default()).unwrap_err();
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(outcome["issue"][0]["severity"], "error");
+        assert_eq!(outcome["issue"][0]["expression"][0], "Patient.communication");
+
+        let clean = serde_json::json!({ "resourceType": "Patient", "name": [{ "family": "Osei" }] });
+        assert!(read_patient(&clean, FhirHandling::Strict, &IdentifierRules::default()).is_ok());
+    }
+}
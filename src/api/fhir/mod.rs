@@ -1,17 +1,68 @@
 //! HL7 FHIR R5 API implementation
 
-use crate::models::{Patient, Address, ContactPoint, Identifier};
+use crate::models::{Patient, Address, ContactPoint, Identifier, Period, VerificationStatus, AuthorityRegistry};
+use crate::validation::IdentifierRules;
 use crate::Result;
 
 pub mod resources;
 pub mod bundle;
 pub mod search_parameters;
 pub mod handlers;
-
-pub use resources::{FhirPatient, FhirOperationOutcome};
+pub mod provenance;
+pub mod audit_event;
+pub mod extensions;
+pub mod practitioner;
+pub mod related_person;
+pub mod group;
+
+pub use resources::{FhirPatient, FhirOperationOutcome, FhirOperationOutcomeIssue};
+pub use provenance::FhirProvenance;
+pub use audit_event::FhirAuditEvent;
+pub use practitioner::FhirPractitioner;
+pub use related_person::FhirRelatedPerson;
+pub use group::FhirGroup;
+
+/// Create the FHIR API routes
+pub fn routes() -> axum::Router<crate::api::rest::AppState> {
+    use axum::routing::{get, post};
+
+    axum::Router::new()
+        .route("/Patient", get(handlers::search_fhir_patients).post(handlers::create_fhir_patient))
+        .route(
+            "/Patient/:id",
+            get(handlers::get_fhir_patient)
+                .put(handlers::update_fhir_patient)
+                .delete(handlers::delete_fhir_patient),
+        )
+        .route("/Patient/:id/_history", get(handlers::get_fhir_patient_history))
+        .route("/Practitioner", post(handlers::create_fhir_practitioner))
+        .route(
+            "/Practitioner/:id",
+            get(handlers::get_fhir_practitioner)
+                .put(handlers::update_fhir_practitioner)
+                .delete(handlers::delete_fhir_practitioner),
+        )
+        .route("/Group", post(handlers::create_fhir_group))
+        .route(
+            "/Group/:id",
+            get(handlers::get_fhir_group)
+                .put(handlers::update_fhir_group)
+                .delete(handlers::delete_fhir_group),
+        )
+        .route("/RelatedPerson", get(handlers::search_fhir_related_persons))
+        .route("/RelatedPerson/:id", get(handlers::get_fhir_related_person))
+        .route("/Provenance", get(handlers::search_fhir_provenance))
+        .route("/AuditEvent", get(handlers::search_fhir_audit_events))
+}
 
 /// Convert internal Patient model to FHIR Patient resource
 pub fn to_fhir_patient(patient: &Patient) -> FhirPatient {
+    to_fhir_patient_with_authorities(patient, &AuthorityRegistry::default())
+}
+
+/// Convert internal Patient model to FHIR Patient resource, naming each
+/// identifier's assigner after its registered assigning authority
+pub fn to_fhir_patient_with_authorities(patient: &Patient, authorities: &AuthorityRegistry) -> FhirPatient {
     use resources::*;
 
     let mut fhir_patient = FhirPatient::new();
@@ -44,10 +95,15 @@ pub fn to_fhir_patient(patient: &Patient) -> FhirPatient {
                     }),
                     system: Some(id.system.clone()),
                     value: Some(id.value.clone()),
-                    assigner: id.assigner.as_ref().map(|a| FhirReference {
-                        reference: None,
-                        display: Some(a.clone()),
-                    }),
+                    assigner: authorities
+                        .for_identifier(id)
+                        .map(|authority| &authority.name)
+                        .or(id.assigner.as_ref())
+                        .map(|a| FhirReference {
+                            reference: None,
+                            display: Some(a.clone()),
+                        }),
+                    period: id.period.map(to_fhir_period),
                 })
                 .collect(),
         );
@@ -110,6 +166,7 @@ pub fn to_fhir_patient(patient: &Patient) -> FhirPatient {
                     system: Some(format!("{:?}", cp.system).to_lowercase()),
                     value: Some(cp.value.clone()),
                     use_: cp.use_type.as_ref().map(|u| format!("{:?}", u).to_lowercase()),
+                    period: cp.period.map(to_fhir_period),
                 })
                 .collect(),
         );
@@ -146,7 +203,7 @@ pub fn to_fhir_patient(patient: &Patient) -> FhirPatient {
                     }
 
                     FhirAddress {
-                        use_: None, // Not stored in our model
+                        use_: (!addr.is_current()).then(|| "old".to_string()),
                         type_: None, // Not stored in our model
                         text: None, // Not stored in our model
                         line: if lines.is_empty() { None } else { Some(lines) },
@@ -154,6 +211,7 @@ pub fn to_fhir_patient(patient: &Patient) -> FhirPatient {
                         state: addr.state.clone(),
                         postal_code: addr.postal_code.clone(),
                         country: addr.country.clone(),
+                        period: addr.period.map(to_fhir_period),
                     }
                 })
                 .collect(),
@@ -202,16 +260,73 @@ pub fn to_fhir_patient(patient: &Patient) -> FhirPatient {
         });
     }
 
+    // Gender identity and pronouns
+    let identity: Vec<FhirExtension> = patient
+        .gender_identity
+        .as_deref()
+        .map(extensions::gender_identity_extension)
+        .into_iter()
+        .chain(patient.pronouns.as_deref().map(extensions::pronouns_extension))
+        .collect();
+    if !identity.is_empty() {
+        fhir_patient.extension = Some(identity);
+    }
+
     fhir_patient
 }
 
+/// Top-level Patient elements read by [`from_fhir_patient`]
+const PATIENT_ELEMENTS: [&str; 17] = [
+    "resourceType", "id", "meta", "identifier", "active", "name", "telecom", "gender", "birthDate",
+    "deceased", "address", "maritalStatus", "multipleBirth", "photo", "link", "managingOrganization",
+    "extension",
+];
+
+/// Issues for top-level elements of a Patient that are not read at all
+///
+/// These never reach [`FhirPatient`], so they are found on the raw JSON.
+pub fn unsupported_patient_elements(resource: &serde_json::Value) -> Vec<FhirOperationOutcomeIssue> {
+    resource
+        .as_object()
+        .into_iter()
+        .flat_map(|object| object.keys())
+        .filter(|key| !PATIENT_ELEMENTS.contains(&key.as_str()))
+        .map(|key| {
+            FhirOperationOutcomeIssue::warning(
+                "not-supported",
+                format!("Patient.{} is not supported and was ignored", key),
+                format!("Patient.{}", key),
+            )
+        })
+        .collect()
+}
+
 /// Convert FHIR Patient resource to internal Patient model
+///
+/// Anything that cannot be mapped is dropped; use
+/// [`from_fhir_patient_with_issues`] to find out what.
 pub fn from_fhir_patient(fhir_patient: &FhirPatient) -> Result<Patient> {
-    use crate::models::{HumanName, NameUse, Gender, ContactPointSystem, ContactPointUse, PatientLink, LinkType};
+    from_fhir_patient_with_issues(fhir_patient, &IdentifierRules::default()).map(|(patient, _)| patient)
+}
+
+/// Convert a FHIR Patient, listing the data that was not stored
+///
+/// Each element that is ignored or only partly kept gets an OperationOutcome
+/// issue with its FHIRPath location: a `warning` when patient data is lost,
+/// and `information` when a value was kept in a more general form.
+/// Identifiers that fail `identifier_rules` are dropped with a warning on
+/// their value.
+pub fn from_fhir_patient_with_issues(
+    fhir_patient: &FhirPatient,
+    identifier_rules: &IdentifierRules,
+) -> Result<(Patient, Vec<FhirOperationOutcomeIssue>)> {
+    use crate::models::{HumanName, NameUse, Gender, ContactPointSystem, ContactPointUse, IdentifierType, IdentifierUse};
     use crate::api::fhir::resources::FhirDeceased;
     use uuid::Uuid;
     use chrono::Utc;
 
+    let mut issues = Vec::new();
+
     // Parse ID
     let id = if let Some(ref id_str) = fhir_patient.id {
         Uuid::parse_str(id_str).map_err(|e| crate::Error::Validation(format!("Invalid UUID: {}", e)))?
@@ -219,48 +334,91 @@ pub fn from_fhir_patient(fhir_patient: &FhirPatient) -> Result<Patient> {
         Uuid::new_v4()
     };
 
-    // Parse name (use first name)
-    let name = if let Some(ref names) = fhir_patient.name {
-        if let Some(first_name) = names.first() {
-            HumanName {
-                use_type: first_name.use_.as_ref().and_then(|u| match u.as_str() {
-                    "usual" => Some(NameUse::Usual),
-                    "official" => Some(NameUse::Official),
-                    "temp" => Some(NameUse::Temp),
-                    "nickname" => Some(NameUse::Nickname),
-                    "anonymous" => Some(NameUse::Anonymous),
-                    "old" => Some(NameUse::Old),
-                    "maiden" => Some(NameUse::Maiden),
-                    _ => None,
-                }),
-                family: first_name.family.clone().unwrap_or_default(),
-                given: first_name.given.clone().unwrap_or_default(),
-                prefix: first_name.prefix.clone().unwrap_or_default(),
-                suffix: first_name.suffix.clone().unwrap_or_default(),
+    // Parse names; the first is primary, the rest (e.g. a preferred name) are additional
+    let fhir_names = fhir_patient.name.as_deref().unwrap_or_default();
+    let mut names = Vec::new();
+    for (i, fhir_name) in fhir_names.iter().enumerate() {
+        let path = format!("Patient.name[{}]", i);
+        let use_type = fhir_name.use_.as_ref().and_then(|u| match u.as_str() {
+            "usual" => Some(NameUse::Usual),
+            "official" => Some(NameUse::Official),
+            "temp" => Some(NameUse::Temp),
+            "nickname" => Some(NameUse::Nickname),
+            "anonymous" => Some(NameUse::Anonymous),
+            "old" => Some(NameUse::Old),
+            "maiden" => Some(NameUse::Maiden),
+            other => {
+                issues.push(FhirOperationOutcomeIssue::warning(
+                    "code-invalid",
+                    format!("Unknown name use '{}' was dropped", other),
+                    format!("{}.use", path),
+                ));
+                None
+            }
+        });
+        if fhir_name.family.is_none() && fhir_name.given.is_none() {
+            if let Some(text) = &fhir_name.text {
+                issues.push(FhirOperationOutcomeIssue::warning(
+                    "not-supported",
+                    format!("Name '{}' has no family or given parts and was stored empty", text),
+                    format!("{}.text", path),
+                ));
             }
-        } else {
-            return Err(crate::Error::Validation("Patient must have at least one name".to_string()));
         }
-    } else {
+        names.push(HumanName {
+            use_type,
+            family: fhir_name.family.clone().unwrap_or_default(),
+            given: fhir_name.given.clone().unwrap_or_default(),
+            prefix: fhir_name.prefix.clone().unwrap_or_default(),
+            suffix: fhir_name.suffix.clone().unwrap_or_default(),
+        });
+    }
+    if names.is_empty() {
         return Err(crate::Error::Validation("Patient must have at least one name".to_string()));
-    };
+    }
+    let name = names.remove(0);
+    let additional_names = names;
 
     // Parse gender
-    let gender = if let Some(ref g) = fhir_patient.gender {
-        match g.as_str() {
-            "male" => Gender::Male,
-            "female" => Gender::Female,
-            "other" => Gender::Other,
-            "unknown" => Gender::Unknown,
-            _ => Gender::Unknown,
+    let gender = match fhir_patient.gender.as_deref() {
+        Some("male") => Gender::Male,
+        Some("female") => Gender::Female,
+        Some("other") => Gender::Other,
+        Some("unknown") | None => Gender::Unknown,
+        Some(other) => {
+            issues.push(FhirOperationOutcomeIssue::warning(
+                "code-invalid",
+                format!("Unknown gender '{}' was stored as unknown", other),
+                "Patient.gender",
+            ));
+            Gender::Unknown
         }
-    } else {
-        Gender::Unknown
     };
 
+    // Parse gender identity and pronouns; other extensions are not stored
+    let fhir_extensions = fhir_patient.extension.as_deref().unwrap_or_default();
+    let (gender_identity, pronouns) = extensions::parse_identity_extensions(fhir_extensions);
+    for (i, extension) in fhir_extensions.iter().enumerate() {
+        if extension.url != extensions::GENDER_IDENTITY_URL && extension.url != extensions::PRONOUNS_URL {
+            issues.push(FhirOperationOutcomeIssue::warning(
+                "extension",
+                format!("Extension '{}' is not supported and was ignored", extension.url),
+                format!("Patient.extension[{}]", i),
+            ));
+        }
+    }
+
     // Parse birth date
     let birth_date = fhir_patient.birth_date.as_ref().and_then(|d| {
-        chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()
+        let parsed = chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok();
+        if parsed.is_none() {
+            issues.push(FhirOperationOutcomeIssue::warning(
+                "value",
+                format!("Birth date '{}' is not a full date and was ignored", d),
+                "Patient.birthDate",
+            ));
+        }
+        parsed
     });
 
     // Parse deceased
@@ -269,88 +427,203 @@ pub fn from_fhir_patient(fhir_patient: &FhirPatient) -> Result<Patient> {
         Some(FhirDeceased::DateTime(dt)) => {
             let parsed_dt = chrono::DateTime::parse_from_rfc3339(dt).ok()
                 .map(|d| d.with_timezone(&Utc));
+            if parsed_dt.is_none() {
+                issues.push(FhirOperationOutcomeIssue::warning(
+                    "value",
+                    format!("Deceased date '{}' could not be parsed; recorded as deceased without a date", dt),
+                    "Patient.deceasedDateTime",
+                ));
+            }
             (true, parsed_dt)
         }
         None => (false, None),
     };
 
     // Parse identifiers
-    let identifiers = if let Some(ref ids) = fhir_patient.identifier {
-        ids.iter()
-            .filter_map(|fid| {
-                Some(Identifier::new(
-                    crate::models::IdentifierType::Other, // TODO: Parse from coding
-                    fid.system.clone()?,
-                    fid.value.clone()?,
-                ))
-            })
-            .collect()
-    } else {
-        vec![]
-    };
+    let mut identifiers = Vec::new();
+    for (i, fid) in fhir_patient.identifier.iter().flatten().enumerate() {
+        let path = format!("Patient.identifier[{}]", i);
+        let (Some(system), Some(value)) = (&fid.system, &fid.value) else {
+            issues.push(FhirOperationOutcomeIssue::warning(
+                "required",
+                "Identifier without both a system and a value was ignored",
+                path,
+            ));
+            continue;
+        };
+
+        let codes: Vec<&str> = fid
+            .type_
+            .iter()
+            .flat_map(|t| t.coding.iter().flatten())
+            .filter_map(|c| c.code.as_deref())
+            .collect();
+        let identifier_type = codes.iter().find_map(|code| match *code {
+            "MR" | "MRN" => Some(IdentifierType::MRN),
+            "SS" | "SSN" => Some(IdentifierType::SSN),
+            "DL" => Some(IdentifierType::DL),
+            "NPI" => Some(IdentifierType::NPI),
+            "PPN" => Some(IdentifierType::PPN),
+            "TAX" | "TN" => Some(IdentifierType::TAX),
+            "OTHER" => Some(IdentifierType::Other),
+            _ => None,
+        });
+        let identifier_type = match identifier_type {
+            Some(identifier_type) => identifier_type,
+            None if codes.is_empty() => IdentifierType::Other,
+            None => {
+                issues.push(FhirOperationOutcomeIssue::information(
+                    "code-invalid",
+                    format!("Identifier type '{}' is not recognized and was stored as OTHER", codes.join(", ")),
+                    format!("{}.type", path),
+                ));
+                IdentifierType::Other
+            }
+        };
+
+        let mut identifier = Identifier::new(identifier_type, system.clone(), value.clone());
+        identifier.use_type = fid.use_.as_deref().and_then(|u| match u {
+            "usual" => Some(IdentifierUse::Usual),
+            "official" => Some(IdentifierUse::Official),
+            "temp" => Some(IdentifierUse::Temp),
+            "secondary" => Some(IdentifierUse::Secondary),
+            "old" => Some(IdentifierUse::Old),
+            other => {
+                issues.push(FhirOperationOutcomeIssue::warning(
+                    "code-invalid",
+                    format!("Unknown identifier use '{}' was dropped", other),
+                    format!("{}.use", path),
+                ));
+                None
+            }
+        });
+        identifier.assigner = fid.assigner.as_ref().and_then(|a| a.display.clone());
+        identifier.period = fid.period.as_ref().and_then(|p| from_fhir_period(p, &path, &mut issues));
+        if let Some(problem) = identifier_rules.problem(&identifier) {
+            issues.push(FhirOperationOutcomeIssue::warning(
+                "value",
+                format!("{}; the identifier was ignored", problem),
+                format!("{}.value", path),
+            ));
+            continue;
+        }
+        identifiers.push(identifier);
+    }
 
     // Parse addresses
-    let addresses = if let Some(ref addrs) = fhir_patient.address {
-        addrs.iter()
-            .map(|faddr| {
-                let lines = faddr.line.clone().unwrap_or_default();
-                Address {
-                    line1: lines.get(0).cloned(),
-                    line2: lines.get(1).cloned(),
-                    city: faddr.city.clone(),
-                    state: faddr.state.clone(),
-                    postal_code: faddr.postal_code.clone(),
-                    country: faddr.country.clone(),
-                }
-            })
-            .collect()
-    } else {
-        vec![]
-    };
+    let mut addresses = Vec::new();
+    for (i, faddr) in fhir_patient.address.iter().flatten().enumerate() {
+        let path = format!("Patient.address[{}]", i);
+        let lines = faddr.line.clone().unwrap_or_default();
+        if lines.len() > 2 {
+            issues.push(FhirOperationOutcomeIssue::warning(
+                "not-supported",
+                format!("Only two address lines are stored; {} more were dropped", lines.len() - 2),
+                format!("{}.line", path),
+            ));
+        }
+        // A former address keeps the period on file, if it has one, and
+        // otherwise ends now
+        let former = faddr.use_.as_deref() == Some("old");
+        let mut period = faddr.period.as_ref().and_then(|p| from_fhir_period(p, &path, &mut issues));
+        if former && period.is_none_or(|p| p.end.is_none()) {
+            period = Some(Period { start: period.and_then(|p| p.start), end: Some(Utc::now()) });
+        }
+        let unstored_use = faddr.use_.is_some() && !former;
+        for (element, present) in [("use", unstored_use), ("type", faddr.type_.is_some()), ("text", faddr.text.is_some())] {
+            if present {
+                issues.push(FhirOperationOutcomeIssue::information(
+                    "not-supported",
+                    format!("Address {} is not stored", element),
+                    format!("{}.{}", path, element),
+                ));
+            }
+        }
+        addresses.push(Address {
+            line1: lines.first().cloned(),
+            line2: lines.get(1).cloned(),
+            city: faddr.city.clone(),
+            state: faddr.state.clone(),
+            postal_code: faddr.postal_code.clone(),
+            country: faddr.country.clone(),
+            verification: VerificationStatus::Unverified,
+            period,
+        });
+    }
 
     // Parse telecom
-    let telecom = if let Some(ref tels) = fhir_patient.telecom {
-        tels.iter()
-            .filter_map(|ftel| {
-                let system = ftel.system.as_ref().and_then(|s| match s.as_str() {
-                    "phone" => Some(ContactPointSystem::Phone),
-                    "fax" => Some(ContactPointSystem::Fax),
-                    "email" => Some(ContactPointSystem::Email),
-                    "pager" => Some(ContactPointSystem::Pager),
-                    "url" => Some(ContactPointSystem::Url),
-                    "sms" => Some(ContactPointSystem::Sms),
-                    "other" => Some(ContactPointSystem::Other),
-                    _ => None,
-                })?;
-
-                let value = ftel.value.clone()?;
-
-                Some(ContactPoint {
-                    system,
-                    value,
-                    use_type: ftel.use_.as_ref().and_then(|u| match u.as_str() {
-                        "home" => Some(ContactPointUse::Home),
-                        "work" => Some(ContactPointUse::Work),
-                        "temp" => Some(ContactPointUse::Temp),
-                        "old" => Some(ContactPointUse::Old),
-                        "mobile" => Some(ContactPointUse::Mobile),
-                        _ => None,
-                    }),
-                })
-            })
-            .collect()
-    } else {
-        vec![]
-    };
+    let mut telecom = Vec::new();
+    for (i, ftel) in fhir_patient.telecom.iter().flatten().enumerate() {
+        let path = format!("Patient.telecom[{}]", i);
+        let system = ftel.system.as_ref().and_then(|s| match s.as_str() {
+            "phone" => Some(ContactPointSystem::Phone),
+            "fax" => Some(ContactPointSystem::Fax),
+            "email" => Some(ContactPointSystem::Email),
+            "pager" => Some(ContactPointSystem::Pager),
+            "url" => Some(ContactPointSystem::Url),
+            "sms" => Some(ContactPointSystem::Sms),
+            "other" => Some(ContactPointSystem::Other),
+            _ => None,
+        });
+        let (Some(system), Some(value)) = (system, ftel.value.clone()) else {
+            issues.push(FhirOperationOutcomeIssue::warning(
+                "required",
+                "Contact point without a known system and a value was ignored",
+                path,
+            ));
+            continue;
+        };
+
+        let period = ftel.period.as_ref().and_then(|p| from_fhir_period(p, &path, &mut issues));
+        telecom.push(ContactPoint {
+            system,
+            value,
+            use_type: ftel.use_.as_ref().and_then(|u| match u.as_str() {
+                "home" => Some(ContactPointUse::Home),
+                "work" => Some(ContactPointUse::Work),
+                "temp" => Some(ContactPointUse::Temp),
+                "old" => Some(ContactPointUse::Old),
+                "mobile" => Some(ContactPointUse::Mobile),
+                other => {
+                    issues.push(FhirOperationOutcomeIssue::warning(
+                        "code-invalid",
+                        format!("Unknown contact point use '{}' was dropped", other),
+                        format!("{}.use", path),
+                    ));
+                    None
+                }
+            }),
+            period,
+        });
+    }
 
-    Ok(Patient {
+    // Elements that are not mapped yet
+    for (element, present) in [
+        ("maritalStatus", fhir_patient.marital_status.is_some()),
+        ("multipleBirth", fhir_patient.multiple_birth.is_some()),
+        ("photo", fhir_patient.photo.is_some()),
+        ("managingOrganization", fhir_patient.managing_organization.is_some()),
+        ("link", fhir_patient.link.is_some()),
+    ] {
+        if present {
+            issues.push(FhirOperationOutcomeIssue::warning(
+                "not-supported",
+                format!("Patient.{} is not stored", element),
+                format!("Patient.{}", element),
+            ));
+        }
+    }
+
+    let patient = Patient {
         id,
         identifiers,
         active: fhir_patient.active.unwrap_or(true),
         name,
-        additional_names: vec![], // TODO: Parse additional names from FHIR
+        additional_names,
         telecom,
         gender,
+        gender_identity,
+        pronouns,
         birth_date,
         deceased,
         deceased_datetime,
@@ -358,9 +631,267 @@ pub fn from_fhir_patient(fhir_patient: &FhirPatient) -> Result<Patient> {
         marital_status: None, // TODO: Parse marital status
         multiple_birth: None, // TODO: Parse multiple birth
         photo: vec![],
+        photo_hashes: vec![],
         managing_organization: None, // TODO: Parse organization reference
         links: vec![],
+        contacts: vec![],
+        confidentiality: Default::default(),
+        status: Default::default(),
         created_at: Utc::now(),
         updated_at: Utc::now(),
-    })
+    };
+
+    Ok((patient, issues))
+}
+
+/// FHIR Period for a validity period
+fn to_fhir_period(period: Period) -> resources::FhirPeriod {
+    resources::FhirPeriod {
+        start: period.start.map(|start| start.to_rfc3339()),
+        end: period.end.map(|end| end.to_rfc3339()),
+    }
+}
+
+/// Validity period of the element at `path`, reporting bounds that are not
+/// a full date or dateTime
+fn from_fhir_period(
+    period: &resources::FhirPeriod,
+    path: &str,
+    issues: &mut Vec<FhirOperationOutcomeIssue>,
+) -> Option<Period> {
+    let mut bound = |value: &Option<String>, name: &str| {
+        let value = value.as_deref()?;
+        let parsed = chrono::DateTime::parse_from_rfc3339(value)
+            .map(|datetime| datetime.with_timezone(&chrono::Utc))
+            .ok()
+            .or_else(|| {
+                chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+                    .map(|datetime| datetime.and_utc())
+            });
+        if parsed.is_none() {
+            issues.push(FhirOperationOutcomeIssue::warning(
+                "value",
+                format!("Period {} '{}' is not a full date and was ignored", name, value),
+                format!("{}.period.{}", path, name),
+            ));
+        }
+        parsed
+    };
+    let start = bound(&period.start, "start");
+    Period::from_bounds(start, bound(&period.end, "end"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use resources::*;
+
+    fn fhir_patient() -> FhirPatient {
+        let mut fhir_patient = FhirPatient::new();
+        fhir_patient.name = Some(vec![FhirHumanName {
+            use_: Some("official".to_string()),
+            text: None,
+            family: Some("Osei".to_string()),
+            given: Some(vec!["Kwame".to_string()]),
+            prefix: None,
+            suffix: None,
+        }]);
+        fhir_patient
+    }
+
+    #[test]
+    fn test_clean_resource_has_no_issues() {
+        let mut fhir_patient = fhir_patient();
+        fhir_patient.birth_date = Some("1984-02-29".to_string());
+        fhir_patient.extension = Some(vec![extensions::pronouns_extension("he/him")]);
+
+        let (patient, issues) = from_fhir_patient_with_issues(&fhir_patient, &IdentifierRules::default()).unwrap();
+        assert_eq!(patient.pronouns.as_deref(), Some("he/him"));
+        assert!(issues.is_empty(), "{:?}", issues);
+    }
+
+    #[test]
+    fn test_unsupported_elements() {
+        let resource = serde_json::json!({
+            "resourceType": "Patient",
+            "name": [{ "family": "Osei" }],
+            "communication": [{ "language": { "text": "Twi" } }],
+            "generalPractitioner": [{ "reference": "Practitioner/1" }]
+        });
+
+        let issues = unsupported_patient_elements(&resource);
+        let paths: Vec<&str> = issues.iter().map(|i| i.expression.as_ref().unwrap()[0].as_str()).collect();
+        assert_eq!(paths, vec!["Patient.communication", "Patient.generalPractitioner"]);
+    }
+
+    #[test]
+    fn test_dropped_data_is_reported() {
+        let mut fhir_patient = fhir_patient();
+        fhir_patient.birth_date = Some("1984-02".to_string());
+        fhir_patient.identifier = Some(vec![
+            FhirIdentifier {
+                use_: None,
+                type_: Some(FhirCodeableConcept {
+                    coding: Some(vec![FhirCoding { system: None, code: Some("MR".to_string()), display: None }]),
+                    text: None,
+                }),
+                system: Some("urn:oid:1.2.3".to_string()),
+                value: Some("A100".to_string()),
+                assigner: None,
+                period: None,
+            },
+            FhirIdentifier {
+                use_: None,
+                type_: Some(FhirCodeableConcept {
+                    coding: Some(vec![FhirCoding { system: None, code: Some("BCT".to_string()), display: None }]),
+                    text: None,
+                }),
+                system: Some("urn:oid:1.2.4".to_string()),
+                value: Some("B200".to_string()),
+                assigner: None,
+                period: None,
+            },
+            FhirIdentifier {
+                use_: None,
+                type_: None,
+                system: None,
+                value: Some("C300".to_string()),
+                assigner: None,
+                period: None,
+            },
+        ]);
+        fhir_patient.extension = Some(vec![FhirExtension {
+            url: "http://example.org/fhir/birth-place".to_string(),
+            value_codeable_concept: None,
+            value_string: Some("Kumasi".to_string()),
+            extension: None,
+        }]);
+        fhir_patient.marital_status = Some(FhirCodeableConcept { coding: None, text: Some("Married".to_string()) });
+
+        let (patient, issues) = from_fhir_patient_with_issues(&fhir_patient, &IdentifierRules::default()).unwrap();
+
+        assert_eq!(patient.identifiers.len(), 2);
+        assert_eq!(patient.identifiers[0].identifier_type, crate::models::IdentifierType::MRN);
+        assert_eq!(patient.birth_date, None);
+
+        let reported: Vec<(&str, &str)> = issues
+            .iter()
+            .map(|i| (i.severity.as_str(), i.expression.as_ref().unwrap()[0].as_str()))
+            .collect();
+        assert_eq!(
+            reported,
+            vec![
+                ("warning", "Patient.extension[0]"),
+                ("warning", "Patient.birthDate"),
+                ("information", "Patient.identifier[1].type"),
+                ("warning", "Patient.identifier[2]"),
+                ("warning", "Patient.maritalStatus"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_periods_round_trip() {
+        let period = |start: Option<&str>, end: Option<&str>| {
+            Some(FhirPeriod { start: start.map(String::from), end: end.map(String::from) })
+        };
+        let mut fhir_patient = fhir_patient();
+        fhir_patient.identifier = Some(vec![FhirIdentifier {
+            use_: None,
+            type_: None,
+            system: Some("urn:oid:1.2.3".to_string()),
+            value: Some("A100".to_string()),
+            assigner: None,
+            period: period(Some("2010-03-01"), Some("2024-06-30T12:00:00Z")),
+        }]);
+        fhir_patient.telecom = Some(vec![FhirContactPoint {
+            system: Some("phone".to_string()),
+            value: Some("207-555-0142".to_string()),
+            use_: None,
+            period: period(Some("2019"), None),
+        }]);
+
+        let (patient, issues) = from_fhir_patient_with_issues(&fhir_patient, &IdentifierRules::default()).unwrap();
+        let identifier_period = patient.identifiers[0].period.unwrap();
+        assert_eq!(identifier_period.start.unwrap().to_rfc3339(), "2010-03-01T00:00:00+00:00");
+        assert_eq!(identifier_period.end.unwrap().to_rfc3339(), "2024-06-30T12:00:00+00:00");
+        assert!(patient.telecom[0].period.is_none());
+        let reported: Vec<&str> = issues.iter().map(|i| i.expression.as_ref().unwrap()[0].as_str()).collect();
+        assert_eq!(reported, vec!["Patient.telecom[0].period.start"]);
+
+        let identifier = to_fhir_patient(&patient).identifier.unwrap().remove(0);
+        assert_eq!(identifier.period.unwrap().end.as_deref(), Some("2024-06-30T12:00:00+00:00"));
+    }
+
+    #[test]
+    fn test_malformed_identifiers_are_reported() {
+        let identifier = |code: &str, value: &str| FhirIdentifier {
+            use_: None,
+            type_: Some(FhirCodeableConcept {
+                coding: Some(vec![FhirCoding { system: None, code: Some(code.to_string()), display: None }]),
+                text: None,
+            }),
+            system: Some("urn:oid:1.2.3".to_string()),
+            value: Some(value.to_string()),
+            assigner: None,
+            period: None,
+        };
+        let mut fhir_patient = fhir_patient();
+        fhir_patient.identifier = Some(vec![
+            identifier("NPI", "1234567893"),
+            identifier("NPI", "1234567890"),
+            identifier("SS", "666-12-3456"),
+            identifier("MR", "12345"),
+        ]);
+        let mut rules = IdentifierRules::default();
+        rules.config.mrn_patterns.insert("urn:oid:1.2.3".to_string(), vec!["A9999".to_string()]);
+
+        let (patient, issues) = from_fhir_patient_with_issues(&fhir_patient, &rules).unwrap();
+
+        assert_eq!(patient.identifiers.len(), 1);
+        assert_eq!(patient.identifiers[0].value, "1234567893");
+        let reported: Vec<&str> = issues.iter().map(|i| i.expression.as_ref().unwrap()[0].as_str()).collect();
+        assert_eq!(
+            reported,
+            vec!["Patient.identifier[1].value", "Patient.identifier[2].value", "Patient.identifier[3].value"]
+        );
+        assert!(issues[1].diagnostics.as_deref().unwrap().starts_with("SSN area number"));
+    }
+
+    #[test]
+    fn test_assigner_named_after_authority() {
+        use crate::models::{AssigningAuthority, Gender, HumanName, IdentifierType};
+
+        let mut patient = Patient::new(
+            HumanName { use_type: None, family: "Osei".to_string(), given: vec![], prefix: vec![], suffix: vec![] },
+            Gender::Unknown,
+        );
+        let mut registered = Identifier::new(IdentifierType::MRN, "urn:oid:1.2.3".to_string(), "100".to_string());
+        registered.assigner = Some("GEN".to_string());
+        let mut unregistered = Identifier::new(IdentifierType::MRN, "EAST".to_string(), "200".to_string());
+        unregistered.assigner = Some("East Clinic".to_string());
+        patient.identifiers = vec![registered, unregistered];
+
+        let authorities = AuthorityRegistry::new(vec![AssigningAuthority {
+            id: uuid::Uuid::new_v4(),
+            system: "GENERAL".to_string(),
+            oid: Some("1.2.3".to_string()),
+            name: "General Hospital".to_string(),
+            active: true,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }]);
+        let assigners: Vec<Option<String>> = to_fhir_patient_with_authorities(&patient, &authorities)
+            .identifier
+            .unwrap()
+            .into_iter()
+            .map(|identifier| identifier.assigner.and_then(|a| a.display))
+            .collect();
+        assert_eq!(assigners, vec![Some("General Hospital".to_string()), Some("East Clinic".to_string())]);
+
+        let plain = to_fhir_patient(&patient).identifier.unwrap();
+        assert_eq!(plain[0].assigner.as_ref().unwrap().display.as_deref(), Some("GEN"));
+    }
 }
@@ -0,0 +1,304 @@
+//! FHIR Practitioner resources
+//!
+//! Identifiers, names, telecom, addresses and gender are the same elements as
+//! on Patient and go through the Patient mapping, so they are validated and
+//! reported on the same way. Only qualifications are mapped here.
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::{AuthorityRegistry, Practitioner, Qualification};
+use crate::validation::IdentifierRules;
+use crate::Result;
+use super::resources::{
+    FhirAddress, FhirCodeableConcept, FhirCoding, FhirContactPoint, FhirHumanName, FhirIdentifier, FhirMeta,
+    FhirOperationOutcomeIssue, FhirPatient, FhirPeriod, FhirReference,
+};
+
+/// FHIR Practitioner resource (R5)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FhirPractitioner {
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<FhirMeta>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identifier: Option<Vec<FhirIdentifier>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<Vec<FhirHumanName>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub telecom: Option<Vec<FhirContactPoint>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gender: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<Vec<FhirAddress>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qualification: Option<Vec<FhirPractitionerQualification>>,
+}
+
+/// Practitioner qualification (license, certification or degree)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FhirPractitionerQualification {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identifier: Option<Vec<FhirIdentifier>>,
+    pub code: FhirCodeableConcept,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<FhirPeriod>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<FhirReference>,
+}
+
+/// Top-level Practitioner elements read by [`from_fhir_practitioner_with_issues`]
+const PRACTITIONER_ELEMENTS: [&str; 10] = [
+    "resourceType", "id", "meta", "identifier", "active", "name", "telecom", "gender", "address",
+    "qualification",
+];
+
+/// Issues for top-level elements of a Practitioner that are not read at all
+pub fn unsupported_practitioner_elements(resource: &serde_json::Value) -> Vec<FhirOperationOutcomeIssue> {
+    resource
+        .as_object()
+        .into_iter()
+        .flat_map(|object| object.keys())
+        .filter(|key| !PRACTITIONER_ELEMENTS.contains(&key.as_str()))
+        .map(|key| {
+            FhirOperationOutcomeIssue::warning(
+                "not-supported",
+                format!("Practitioner.{} is not supported and was ignored", key),
+                format!("Practitioner.{}", key),
+            )
+        })
+        .collect()
+}
+
+/// Convert an internal Practitioner to a FHIR Practitioner resource
+pub fn to_fhir_practitioner(practitioner: &Practitioner, authorities: &AuthorityRegistry) -> FhirPractitioner {
+    let shared = super::to_fhir_patient_with_authorities(&practitioner.as_patient_record(), authorities);
+
+    let qualification: Vec<FhirPractitionerQualification> = practitioner
+        .qualifications
+        .iter()
+        .map(|q| FhirPractitionerQualification {
+            identifier: q.identifier.as_ref().map(|value| {
+                vec![FhirIdentifier {
+                    use_: None,
+                    type_: None,
+                    system: None,
+                    value: Some(value.clone()),
+                    assigner: None,
+                    period: None,
+                }]
+            }),
+            code: FhirCodeableConcept {
+                coding: Some(vec![FhirCoding {
+                    system: q.system.clone(),
+                    code: Some(q.code.clone()),
+                    display: q.display.clone(),
+                }]),
+                text: q.display.clone(),
+            },
+            period: if q.valid_from.is_none() && q.valid_until.is_none() {
+                None
+            } else {
+                Some(FhirPeriod {
+                    start: q.valid_from.map(|d| d.to_string()),
+                    end: q.valid_until.map(|d| d.to_string()),
+                })
+            },
+            issuer: q.issuer.as_ref().map(|issuer| FhirReference {
+                reference: None,
+                display: Some(issuer.clone()),
+            }),
+        })
+        .collect();
+
+    FhirPractitioner {
+        resource_type: "Practitioner".to_string(),
+        id: Some(practitioner.id.to_string()),
+        meta: Some(FhirMeta {
+            version_id: None,
+            last_updated: Some(practitioner.updated_at.to_rfc3339()),
+        }),
+        identifier: shared.identifier,
+        active: Some(practitioner.active),
+        name: shared.name,
+        telecom: shared.telecom,
+        gender: practitioner.gender.and(shared.gender),
+        address: shared.address,
+        qualification: if qualification.is_empty() { None } else { Some(qualification) },
+    }
+}
+
+/// Convert a FHIR Practitioner, listing the data that was not stored
+///
+/// Issues are raised as for Patient, located on the Practitioner.
+pub fn from_fhir_practitioner_with_issues(
+    fhir_practitioner: &FhirPractitioner,
+    identifier_rules: &IdentifierRules,
+) -> Result<(Practitioner, Vec<FhirOperationOutcomeIssue>)> {
+    if fhir_practitioner.name.as_deref().unwrap_or_default().is_empty() {
+        return Err(crate::Error::Validation("Practitioner must have at least one name".to_string()));
+    }
+
+    let mut shared = FhirPatient::new();
+    shared.id = fhir_practitioner.id.clone();
+    shared.identifier = fhir_practitioner.identifier.clone();
+    shared.active = fhir_practitioner.active;
+    shared.name = fhir_practitioner.name.clone();
+    shared.telecom = fhir_practitioner.telecom.clone();
+    shared.gender = fhir_practitioner.gender.clone();
+    shared.address = fhir_practitioner.address.clone();
+    let (record, shared_issues) = super::from_fhir_patient_with_issues(&shared, identifier_rules)?;
+
+    let mut issues: Vec<FhirOperationOutcomeIssue> = shared_issues
+        .into_iter()
+        .map(|mut issue| {
+            for expression in issue.expression.iter_mut().flatten() {
+                if let Some(rest) = expression.strip_prefix("Patient.") {
+                    *expression = format!("Practitioner.{}", rest);
+                }
+            }
+            if let Some(diagnostics) = issue.diagnostics.as_mut() {
+                *diagnostics = diagnostics.replace("Patient.", "Practitioner.");
+            }
+            issue
+        })
+        .collect();
+
+    let mut qualifications = Vec::new();
+    for (i, fq) in fhir_practitioner.qualification.iter().flatten().enumerate() {
+        let path = format!("Practitioner.qualification[{}]", i);
+        let coding = fq.code.coding.iter().flatten().find(|c| c.code.is_some());
+        let Some(code) = coding.and_then(|c| c.code.clone()).or_else(|| fq.code.text.clone()) else {
+            issues.push(FhirOperationOutcomeIssue::warning(
+                "required",
+                "Qualification without a code was ignored",
+                format!("{}.code", path),
+            ));
+            continue;
+        };
+
+        let mut period_date = |value: &Option<String>, element: &str| {
+            let value = value.as_deref()?;
+            let parsed = value.get(..10).and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+            if parsed.is_none() {
+                issues.push(FhirOperationOutcomeIssue::warning(
+                    "value",
+                    format!("Qualification period {} '{}' is not a full date and was ignored", element, value),
+                    format!("{}.period.{}", path, element),
+                ));
+            }
+            parsed
+        };
+        let (valid_from, valid_until) = match &fq.period {
+            Some(period) => (period_date(&period.start, "start"), period_date(&period.end, "end")),
+            None => (None, None),
+        };
+
+        qualifications.push(Qualification {
+            system: coding.and_then(|c| c.system.clone()),
+            code,
+            display: coding.and_then(|c| c.display.clone()).or_else(|| fq.code.text.clone()),
+            identifier: fq.identifier.iter().flatten().find_map(|id| id.value.clone()),
+            issuer: fq.issuer.as_ref().and_then(|issuer| issuer.display.clone().or_else(|| issuer.reference.clone())),
+            valid_from,
+            valid_until,
+        });
+    }
+
+    let practitioner = Practitioner {
+        id: record.id,
+        identifiers: record.identifiers,
+        active: record.active,
+        name: record.name,
+        additional_names: record.additional_names,
+        telecom: record.telecom,
+        addresses: record.addresses,
+        gender: fhir_practitioner.gender.as_ref().map(|_| record.gender),
+        qualifications,
+        created_at: record.created_at,
+        updated_at: record.updated_at,
+    };
+
+    Ok((practitioner, issues))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::practitioner::NPI_SYSTEM;
+    use crate::models::{Gender, HumanName, Identifier, IdentifierType};
+
+    fn practitioner() -> Practitioner {
+        let mut practitioner = Practitioner::new(HumanName {
+            use_type: None,
+            family: "Mensah".to_string(),
+            given: vec!["Kofi".to_string()],
+            prefix: vec!["Dr".to_string()],
+            suffix: vec![],
+        });
+        practitioner
+            .identifiers
+            .push(Identifier::new(IdentifierType::NPI, NPI_SYSTEM.to_string(), "1234567893".to_string()));
+        practitioner.qualifications.push(Qualification {
+            system: Some("http://terminology.hl7.org/CodeSystem/v2-0360".to_string()),
+            code: "MD".to_string(),
+            display: Some("Doctor of Medicine".to_string()),
+            identifier: Some("ME-48213".to_string()),
+            issuer: Some("Maine Board of Licensure in Medicine".to_string()),
+            valid_from: NaiveDate::from_ymd_opt(2015, 7, 1),
+            valid_until: None,
+        });
+        practitioner
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let original = practitioner();
+        let fhir = to_fhir_practitioner(&original, &AuthorityRegistry::default());
+        assert_eq!(fhir.resource_type, "Practitioner");
+        assert!(fhir.gender.is_none());
+
+        let (parsed, issues) = from_fhir_practitioner_with_issues(&fhir, &IdentifierRules::default()).unwrap();
+        assert!(issues.is_empty(), "unexpected issues: {:?}", issues);
+        assert_eq!(parsed.id, original.id);
+        assert_eq!(parsed.npi(), Some("1234567893"));
+        assert_eq!(parsed.gender, None);
+        assert_eq!(parsed.qualifications.len(), 1);
+        let qualification = &parsed.qualifications[0];
+        assert_eq!(qualification.code, "MD");
+        assert_eq!(qualification.identifier.as_deref(), Some("ME-48213"));
+        assert_eq!(qualification.valid_from, NaiveDate::from_ymd_opt(2015, 7, 1));
+    }
+
+    #[test]
+    fn test_issues_are_located_on_the_practitioner() {
+        let mut fhir = to_fhir_practitioner(&practitioner(), &AuthorityRegistry::default());
+        fhir.gender = Some("female".to_string());
+        if let Some(identifiers) = fhir.identifier.as_mut() {
+            identifiers[0].value = Some("1234567890".to_string());
+        }
+        if let Some(qualifications) = fhir.qualification.as_mut() {
+            qualifications[0].period = Some(FhirPeriod { start: Some("2015".to_string()), end: None });
+        }
+
+        let (parsed, issues) = from_fhir_practitioner_with_issues(&fhir, &IdentifierRules::default()).unwrap();
+        assert_eq!(parsed.gender, Some(Gender::Female));
+        assert!(parsed.npi().is_none());
+        let expressions: Vec<&str> = issues
+            .iter()
+            .flat_map(|issue| issue.expression.iter().flatten())
+            .map(String::as_str)
+            .collect();
+        assert_eq!(
+            expressions,
+            vec!["Practitioner.identifier[0].value", "Practitioner.qualification[0].period.start"]
+        );
+    }
+}
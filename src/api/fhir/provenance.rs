@@ -0,0 +1,170 @@
+//! FHIR Provenance resources derived from the audit log
+//!
+//! Each audit log row for a patient is one version of that patient. The
+//! Provenance for a version targets it, records who made the change and what
+//! kind of change it was, and references the version it replaced.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::db::models::DbAuditLog;
+use super::resources::{FhirCodeableConcept, FhirCoding, FhirMeta, FhirReference};
+
+const DATA_OPERATION_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/v3-DataOperation";
+const LIFECYCLE_EVENT_SYSTEM: &str = "http://hl7.org/fhir/iso-21089-lifecycle";
+const PARTICIPANT_TYPE_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/provenance-participant-type";
+
+/// FHIR Provenance resource (R5)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FhirProvenance {
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<FhirMeta>,
+    pub target: Vec<FhirReference>,
+    pub recorded: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activity: Option<FhirCodeableConcept>,
+    pub agent: Vec<FhirProvenanceAgent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity: Option<Vec<FhirProvenanceEntity>>,
+}
+
+/// Provenance agent (who made the change)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FhirProvenanceAgent {
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub type_: Option<FhirCodeableConcept>,
+    pub who: FhirReference,
+}
+
+/// Provenance entity (what the change was derived from)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FhirProvenanceEntity {
+    pub role: String,
+    pub what: FhirReference,
+}
+
+/// Reference to one version of a patient
+pub fn patient_version_reference(patient_id: &uuid::Uuid, version_id: &uuid::Uuid) -> String {
+    format!("Patient/{}/_history/{}", patient_id, version_id)
+}
+
+/// Build a Provenance resource from an audit log row
+///
+/// `previous` is the audit row for the version this change replaced, if any.
+pub fn to_fhir_provenance(log: &DbAuditLog, previous: Option<&DbAuditLog>) -> FhirProvenance {
+    let activity = match log.action.as_str() {
+        "CREATE" | "UPDATE" | "DELETE" => Some((DATA_OPERATION_SYSTEM, log.action.as_str(), log.action.to_lowercase())),
+        "MERGE" => Some((LIFECYCLE_EVENT_SYSTEM, "merge", "merge".to_string())),
+        _ => None,
+    }
+    .map(|(system, code, display)| FhirCodeableConcept {
+        coding: Some(vec![FhirCoding {
+            system: Some(system.to_string()),
+            code: Some(code.to_string()),
+            display: Some(display),
+        }]),
+        text: None,
+    });
+
+    // The audit log records the authenticated user; changes without one
+    // came from a source system feed or an internal job
+    let who = FhirReference {
+        reference: None,
+        display: Some(log.user_id.clone().unwrap_or_else(|| "system".to_string())),
+    };
+
+    let entity = previous.map(|prev| {
+        vec![FhirProvenanceEntity {
+            role: "revision".to_string(),
+            what: FhirReference {
+                reference: Some(patient_version_reference(&prev.entity_id, &prev.id)),
+                display: None,
+            },
+        }]
+    });
+
+    FhirProvenance {
+        resource_type: "Provenance".to_string(),
+        id: Some(log.id.to_string()),
+        meta: None,
+        target: vec![FhirReference {
+            reference: Some(patient_version_reference(&log.entity_id, &log.id)),
+            display: None,
+        }],
+        recorded: log.timestamp.to_rfc3339(),
+        activity,
+        agent: vec![FhirProvenanceAgent {
+            type_: Some(FhirCodeableConcept {
+                coding: Some(vec![FhirCoding {
+                    system: Some(PARTICIPANT_TYPE_SYSTEM.to_string()),
+                    code: Some("author".to_string()),
+                    display: Some("Author".to_string()),
+                }]),
+                text: None,
+            }),
+            who,
+        }],
+        entity,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn audit_row(entity_id: Uuid, action: &str, user_id: Option<&str>) -> DbAuditLog {
+        DbAuditLog {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            user_id: user_id.map(String::from),
+            action: action.to_string(),
+            entity_type: "Patient".to_string(),
+            entity_id,
+            old_values: None,
+            new_values: None,
+            ip_address: None,
+            user_agent: None,
+        }
+    }
+
+    #[test]
+    fn test_update_provenance() {
+        let patient_id = Uuid::new_v4();
+        let create = audit_row(patient_id, "CREATE", None);
+        let update = audit_row(patient_id, "UPDATE", Some("dr.jones"));
+
+        let provenance = to_fhir_provenance(&update, Some(&create));
+        let json = serde_json::to_value(&provenance).unwrap();
+
+        assert_eq!(json["resourceType"], "Provenance");
+        assert_eq!(
+            json["target"][0]["reference"],
+            format!("Patient/{}/_history/{}", patient_id, update.id)
+        );
+        assert_eq!(json["activity"]["coding"][0]["code"], "UPDATE");
+        assert_eq!(json["agent"][0]["who"]["display"], "dr.jones");
+        assert_eq!(json["entity"][0]["role"], "revision");
+        assert_eq!(
+            json["entity"][0]["what"]["reference"],
+            format!("Patient/{}/_history/{}", patient_id, create.id)
+        );
+    }
+
+    #[test]
+    fn test_create_provenance_without_user() {
+        let create = audit_row(Uuid::new_v4(), "CREATE", None);
+
+        let provenance = to_fhir_provenance(&create, None);
+
+        assert!(provenance.entity.is_none());
+        assert_eq!(provenance.agent[0].who.display.as_deref(), Some("system"));
+    }
+}
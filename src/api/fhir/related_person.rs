@@ -0,0 +1,195 @@
+//! FHIR RelatedPerson resources
+//!
+//! Patient contact persons are served as RelatedPerson resources with the
+//! contact's own id, referencing the patient, since care-management tools
+//! query relationships through RelatedPerson rather than Patient.contact.
+//! Contacts are written through the patient, so RelatedPerson is read-only.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::{Gender, HumanName, Patient, PatientContact};
+use super::resources::{
+    FhirAddress, FhirCodeableConcept, FhirCoding, FhirContactPoint, FhirHumanName, FhirMeta, FhirReference,
+};
+
+/// HL7 v2 table 0131 (contact role), e.g. "N" for next of kin
+pub const CONTACT_ROLE_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/v2-0131";
+
+/// HL7 v3 RoleCode, for personal relationships such as "MTH" or "GUARD"
+pub const ROLE_CODE_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/v3-RoleCode";
+
+/// Codes of HL7 v2 table 0131
+const CONTACT_ROLE_CODES: [&str; 12] = ["BP", "C", "CP", "E", "EP", "F", "I", "N", "O", "PR", "S", "U"];
+
+/// FHIR RelatedPerson resource (R5)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FhirRelatedPerson {
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<FhirMeta>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active: Option<bool>,
+    pub patient: FhirReference,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relationship: Option<Vec<FhirCodeableConcept>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<Vec<FhirHumanName>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub telecom: Option<Vec<FhirContactPoint>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gender: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<Vec<FhirAddress>>,
+}
+
+/// Code system of a relationship code
+pub fn relationship_system(code: &str) -> &'static str {
+    if CONTACT_ROLE_CODES.contains(&code) {
+        CONTACT_ROLE_SYSTEM
+    } else {
+        ROLE_CODE_SYSTEM
+    }
+}
+
+/// Convert a patient's contact person to a FHIR RelatedPerson resource
+///
+/// Name, telecom, address and gender go through the Patient mapping.
+pub fn to_fhir_related_person(patient: &Patient, contact: &PatientContact) -> FhirRelatedPerson {
+    let name = contact.name.clone().unwrap_or(HumanName {
+        use_type: None,
+        family: String::new(),
+        given: Vec::new(),
+        prefix: Vec::new(),
+        suffix: Vec::new(),
+    });
+    let mut record = Patient::new(name, contact.gender.unwrap_or(Gender::Unknown));
+    record.telecom = contact.telecom.clone();
+    record.addresses = contact.address.iter().cloned().collect();
+    let shared = super::to_fhir_patient(&record);
+
+    let relationship: Vec<FhirCodeableConcept> = contact
+        .relationship
+        .iter()
+        .map(|code| FhirCodeableConcept {
+            coding: Some(vec![FhirCoding {
+                system: Some(relationship_system(code).to_string()),
+                code: Some(code.clone()),
+                display: None,
+            }]),
+            text: None,
+        })
+        .collect();
+
+    FhirRelatedPerson {
+        resource_type: "RelatedPerson".to_string(),
+        id: Some(contact.id.to_string()),
+        meta: Some(FhirMeta {
+            version_id: None,
+            last_updated: Some(patient.updated_at.to_rfc3339()),
+        }),
+        active: Some(patient.active),
+        patient: FhirReference {
+            reference: Some(format!("Patient/{}", patient.id)),
+            display: Some(patient.full_name()),
+        },
+        relationship: if relationship.is_empty() { None } else { Some(relationship) },
+        name: contact.name.as_ref().and(shared.name),
+        telecom: shared.telecom,
+        gender: contact.gender.and(shared.gender),
+        address: shared.address,
+    }
+}
+
+/// All of a patient's contact persons as FHIR RelatedPerson resources
+pub fn to_fhir_related_persons(patient: &Patient) -> Vec<FhirRelatedPerson> {
+    patient
+        .contacts
+        .iter()
+        .map(|contact| to_fhir_related_person(patient, contact))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ContactPoint, ContactPointSystem};
+    use uuid::Uuid;
+
+    fn patient_with_contact(contact: PatientContact) -> Patient {
+        let mut patient = Patient::new(
+            HumanName {
+                use_type: None,
+                family: "Okafor".to_string(),
+                given: vec!["Chidi".to_string()],
+                prefix: vec![],
+                suffix: vec![],
+            },
+            Gender::Male,
+        );
+        patient.contacts.push(contact);
+        patient
+    }
+
+    #[test]
+    fn test_contact_maps_to_related_person() {
+        let contact = PatientContact {
+            id: Uuid::new_v4(),
+            relationship: vec!["N".to_string(), "MTH".to_string()],
+            name: Some(HumanName {
+                use_type: None,
+                family: "Okafor".to_string(),
+                given: vec!["Ngozi".to_string()],
+                prefix: vec![],
+                suffix: vec![],
+            }),
+            telecom: vec![ContactPoint {
+                system: ContactPointSystem::Phone,
+                value: "555-0100".to_string(),
+                use_type: None,
+                period: None,
+            }],
+            address: None,
+            gender: Some(Gender::Female),
+        };
+        let patient = patient_with_contact(contact.clone());
+
+        let related = to_fhir_related_persons(&patient);
+        assert_eq!(related.len(), 1);
+        let related = &related[0];
+        assert_eq!(related.id, Some(contact.id.to_string()));
+        assert_eq!(related.patient.reference, Some(format!("Patient/{}", patient.id)));
+        assert_eq!(related.gender.as_deref(), Some("female"));
+        assert_eq!(related.name.as_ref().unwrap()[0].given, Some(vec!["Ngozi".to_string()]));
+        assert_eq!(related.telecom.as_ref().unwrap()[0].value.as_deref(), Some("555-0100"));
+
+        let systems: Vec<_> = related
+            .relationship
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|r| r.coding.as_ref().unwrap()[0].system.clone().unwrap())
+            .collect();
+        assert_eq!(systems, vec![CONTACT_ROLE_SYSTEM, ROLE_CODE_SYSTEM]);
+    }
+
+    #[test]
+    fn test_unnamed_contact_has_no_name_or_gender() {
+        let contact = PatientContact {
+            id: Uuid::new_v4(),
+            relationship: vec!["C".to_string()],
+            name: None,
+            telecom: vec![],
+            address: None,
+            gender: None,
+        };
+        let related = to_fhir_related_person(&patient_with_contact(contact.clone()), &contact);
+
+        assert!(related.name.is_none());
+        assert!(related.gender.is_none());
+        assert!(related.telecom.is_none());
+    }
+}
@@ -1,10 +1,11 @@
 //! FHIR R5 resource definitions
 
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use chrono::NaiveDate;
 
 /// FHIR Patient resource (R5)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FhirPatient {
     pub resource_type: String,
@@ -38,10 +39,12 @@ pub struct FhirPatient {
     pub link: Option<Vec<FhirPatientLink>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub managing_organization: Option<FhirReference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extension: Option<Vec<FhirExtension>>,
 }
 
 /// FHIR Meta element
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FhirMeta {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -51,7 +54,7 @@ pub struct FhirMeta {
 }
 
 /// FHIR Identifier
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FhirIdentifier {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -64,10 +67,12 @@ pub struct FhirIdentifier {
     pub value: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub assigner: Option<FhirReference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<FhirPeriod>,
 }
 
 /// FHIR HumanName
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FhirHumanName {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -85,7 +90,7 @@ pub struct FhirHumanName {
 }
 
 /// FHIR ContactPoint
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FhirContactPoint {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -94,10 +99,12 @@ pub struct FhirContactPoint {
     pub value: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub use_: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<FhirPeriod>,
 }
 
 /// FHIR Address
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FhirAddress {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -116,10 +123,22 @@ pub struct FhirAddress {
     pub postal_code: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub country: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<FhirPeriod>,
+}
+
+/// FHIR Period
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FhirPeriod {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<String>,
 }
 
 /// FHIR CodeableConcept
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FhirCodeableConcept {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -128,8 +147,22 @@ pub struct FhirCodeableConcept {
     pub text: Option<String>,
 }
 
+/// FHIR Extension, simple or complex
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FhirExtension {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value_codeable_concept: Option<FhirCodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value_string: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(no_recursion)]
+    pub extension: Option<Vec<FhirExtension>>,
+}
+
 /// FHIR Coding
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FhirCoding {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -141,7 +174,7 @@ pub struct FhirCoding {
 }
 
 /// FHIR Reference
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FhirReference {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -151,7 +184,7 @@ pub struct FhirReference {
 }
 
 /// FHIR Patient Link
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FhirPatientLink {
     pub other: FhirReference,
@@ -159,7 +192,7 @@ pub struct FhirPatientLink {
 }
 
 /// FHIR Attachment
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FhirAttachment {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -171,7 +204,7 @@ pub struct FhirAttachment {
 }
 
 /// FHIR Deceased (boolean or dateTime)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(untagged)]
 pub enum FhirDeceased {
     Boolean(bool),
@@ -179,7 +212,7 @@ pub enum FhirDeceased {
 }
 
 /// FHIR MultipleBirth (boolean or integer)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(untagged)]
 pub enum FhirMultipleBirth {
     Boolean(bool),
@@ -187,7 +220,7 @@ pub enum FhirMultipleBirth {
 }
 
 /// FHIR OperationOutcome for errors
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FhirOperationOutcome {
     pub resource_type: String,
@@ -195,7 +228,7 @@ pub struct FhirOperationOutcome {
 }
 
 /// FHIR OperationOutcome Issue
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FhirOperationOutcomeIssue {
     pub severity: String,
@@ -204,6 +237,36 @@ pub struct FhirOperationOutcomeIssue {
     pub details: Option<FhirCodeableConcept>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub diagnostics: Option<String>,
+    /// FHIRPath of the element the issue is about
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expression: Option<Vec<String>>,
+}
+
+impl FhirOperationOutcomeIssue {
+    /// Create a warning issue about an element
+    pub fn warning(code: &str, diagnostics: impl Into<String>, expression: impl Into<String>) -> Self {
+        Self::about("warning", code, diagnostics.into(), expression.into())
+    }
+
+    /// Create an informational issue about an element
+    pub fn information(code: &str, diagnostics: impl Into<String>, expression: impl Into<String>) -> Self {
+        Self::about("information", code, diagnostics.into(), expression.into())
+    }
+
+    /// The same issue, raised to an error
+    pub fn into_error(self) -> Self {
+        Self { severity: "error".to_string(), ..self }
+    }
+
+    fn about(severity: &str, code: &str, diagnostics: String, expression: String) -> Self {
+        Self {
+            severity: severity.to_string(),
+            code: code.to_string(),
+            details: None,
+            diagnostics: Some(diagnostics),
+            expression: Some(vec![expression]),
+        }
+    }
 }
 
 impl FhirOperationOutcome {
@@ -216,6 +279,7 @@ impl FhirOperationOutcome {
                 code: code.to_string(),
                 details: None,
                 diagnostics: Some(diagnostics.to_string()),
+                expression: None,
             }],
         }
     }
@@ -232,6 +296,30 @@ impl FhirOperationOutcome {
     pub fn invalid(message: &str) -> Self {
         Self::error("invalid", message)
     }
+
+    /// Reject a resource because of the given issues
+    pub fn rejected(issues: Vec<FhirOperationOutcomeIssue>) -> Self {
+        Self {
+            resource_type: "OperationOutcome".to_string(),
+            issue: issues.into_iter().map(FhirOperationOutcomeIssue::into_error).collect(),
+        }
+    }
+
+    /// Report a successful operation along with any issues found on the way
+    pub fn success(message: &str, issues: Vec<FhirOperationOutcomeIssue>) -> Self {
+        let mut issue = vec![FhirOperationOutcomeIssue {
+            severity: "information".to_string(),
+            code: "informational".to_string(),
+            details: None,
+            diagnostics: Some(message.to_string()),
+            expression: None,
+        }];
+        issue.extend(issues);
+        Self {
+            resource_type: "OperationOutcome".to_string(),
+            issue,
+        }
+    }
 }
 
 impl FhirPatient {
@@ -254,6 +342,7 @@ impl FhirPatient {
             photo: None,
             link: None,
             managing_organization: None,
+            extension: None,
         }
     }
 }
@@ -0,0 +1,108 @@
+//! Sparse fieldsets shared by the REST and FHIR routes
+//!
+//! Typeahead and picker screens need a patient's id, name, birth date and
+//! identifiers, not the full record with its addresses, contacts and links.
+//! Reads and searches take a comma-separated list of top-level elements
+//! (`fields` on REST, `_elements` on FHIR) and drop every other element of
+//! the serialized resource.
+
+use serde_json::Value;
+
+/// FHIR code system of the `SUBSETTED` security tag
+pub const SUBSETTED_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/v3-ObservationValue";
+
+/// Elements kept in every FHIR resource, whether requested or not
+const FHIR_MANDATORY: [&str; 3] = ["resourceType", "id", "meta"];
+
+/// Elements kept in every REST record, whether requested or not
+const REST_MANDATORY: [&str; 1] = ["id"];
+
+/// Requested element names, or `None` when the parameter is absent or blank
+pub fn parse(list: Option<&str>) -> Option<Vec<String>> {
+    let fields: Vec<String> = list?
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(str::to_string)
+        .collect();
+    if fields.is_empty() {
+        None
+    } else {
+        Some(fields)
+    }
+}
+
+/// Keep only the requested top-level elements of an object, plus `mandatory`
+///
+/// Unknown element names are ignored, as FHIR requires for `_elements`.
+fn retain(value: &mut Value, fields: &[String], mandatory: &[&str]) {
+    if let Value::Object(map) = value {
+        map.retain(|key, _| mandatory.contains(&key.as_str()) || fields.iter().any(|field| field == key));
+    }
+}
+
+/// Trim a REST record to the requested fields; `id` is always kept
+pub fn select_rest(value: &mut Value, fields: &[String]) {
+    retain(value, fields, &REST_MANDATORY);
+}
+
+/// Trim a FHIR resource to the requested elements
+///
+/// `resourceType`, `id` and `meta` are always kept, and the resource is
+/// tagged `SUBSETTED` so it is not mistaken for the full resource.
+pub fn select_fhir(value: &mut Value, elements: &[String]) {
+    retain(value, elements, &FHIR_MANDATORY);
+    let Value::Object(resource) = value else {
+        return;
+    };
+    let meta = resource
+        .entry("meta")
+        .or_insert_with(|| Value::Object(Default::default()));
+    if let Value::Object(meta) = meta {
+        let tags = meta.entry("tag").or_insert_with(|| Value::Array(Vec::new()));
+        if let Value::Array(tags) = tags {
+            tags.push(serde_json::json!({ "system": SUBSETTED_SYSTEM, "code": "SUBSETTED" }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse(Some(" name, birthDate ,,")),
+            Some(vec!["name".to_string(), "birthDate".to_string()])
+        );
+        assert_eq!(parse(Some(" , ")), None);
+        assert_eq!(parse(None), None);
+    }
+
+    #[test]
+    fn test_select_rest_keeps_id() {
+        let mut record = json!({ "id": "a", "name": {}, "addresses": [], "links": [] });
+        select_rest(&mut record, &["name".to_string(), "unknown".to_string()]);
+
+        assert_eq!(record, json!({ "id": "a", "name": {} }));
+    }
+
+    #[test]
+    fn test_select_fhir_tags_subsetted() {
+        let mut resource = json!({
+            "resourceType": "Patient",
+            "id": "a",
+            "meta": { "lastUpdated": "2024-01-01T00:00:00Z" },
+            "name": [],
+            "address": []
+        });
+        select_fhir(&mut resource, &["name".to_string()]);
+
+        assert!(resource.get("address").is_none());
+        assert_eq!(resource["name"], json!([]));
+        assert_eq!(resource["meta"]["lastUpdated"], "2024-01-01T00:00:00Z");
+        assert_eq!(resource["meta"]["tag"][0]["code"], "SUBSETTED");
+    }
+}
@@ -0,0 +1,155 @@
+//! Patient groups shared by the FHIR and REST APIs
+//!
+//! Groups are written whole through FHIR `Group` and their members added and
+//! removed one request at a time through REST. Either way the members must
+//! be stored, non-deleted patients, and every change is recorded in the
+//! audit log as a `PatientGroup`. [`member_records`] loads a group's
+//! patients as the input to exports and batch matching, and [`visible`] leaves
+//! out the members a requester may not see in group reads.
+
+use uuid::Uuid;
+
+use crate::api::privacy::{self, Read, Requester};
+use crate::api::rest::AppState;
+use crate::models::{Patient, PatientGroup};
+use crate::{Error, Result};
+
+/// Audit log entity type of group changes
+const ENTITY_TYPE: &str = "PatientGroup";
+
+/// Fail with a validation error listing the patients that are not stored
+fn check_members(state: &AppState, patient_ids: &[Uuid]) -> Result<()> {
+    let mut unknown = Vec::new();
+    for id in patient_ids {
+        if state.patient_repository.get_by_id(id)?.is_none() {
+            unknown.push(id.to_string());
+        }
+    }
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Validation(format!("Unknown or deleted patients: {}", unknown.join(", "))))
+    }
+}
+
+/// Record a group change in the audit log
+fn audit(state: &AppState, id: Uuid, old: Option<&PatientGroup>, new: Option<&PatientGroup>, requester: &Requester) {
+    let value = |group: Option<&PatientGroup>| serde_json::to_value(group).unwrap_or_default();
+    let (user_id, ip_address, user_agent) =
+        (requester.user_id.clone(), requester.ip_address.clone(), requester.user_agent.clone());
+    let result = match (old, new) {
+        (None, _) => state.audit_log.log_create(ENTITY_TYPE, id, value(new), user_id, ip_address, user_agent),
+        (Some(_), None) => state.audit_log.log_delete(ENTITY_TYPE, id, value(old), user_id, ip_address, user_agent),
+        (Some(_), Some(_)) => state.audit_log.log_update(ENTITY_TYPE, id, value(old), value(new), user_id, ip_address, user_agent),
+    };
+    if let Err(e) = result {
+        tracing::warn!("Failed to record a change to group {} in the audit log: {}", id, e);
+    }
+}
+
+/// Store a new group, created by the requester
+pub fn create(state: &AppState, group: &PatientGroup, requester: &Requester) -> Result<PatientGroup> {
+    check_members(state, &group.members)?;
+    let mut group = group.clone();
+    group.created_by = requester.user_id.clone();
+    let created = state.groups.create(&group)?;
+    audit(state, created.id, None, Some(&created), requester);
+    Ok(created)
+}
+
+/// Replace a group's name, description and members, returning `None` if it
+/// does not exist
+pub fn replace(state: &AppState, group: &PatientGroup, requester: &Requester) -> Result<Option<PatientGroup>> {
+    let Some(old) = state.groups.get_by_id(&group.id)? else {
+        return Ok(None);
+    };
+    check_members(state, &group.members)?;
+    let updated = state.groups.update(group)?;
+    if let Some(updated) = &updated {
+        audit(state, group.id, Some(&old), Some(updated), requester);
+    }
+    Ok(updated)
+}
+
+/// Delete a group, returning whether it existed
+pub fn delete(state: &AppState, id: &Uuid, requester: &Requester) -> Result<bool> {
+    let Some(old) = state.groups.get_by_id(id)? else {
+        return Ok(false);
+    };
+    let deleted = state.groups.delete(id)?;
+    if deleted {
+        audit(state, *id, Some(&old), None, requester);
+    }
+    Ok(deleted)
+}
+
+/// Add patients to a group, returning `None` if it does not exist
+pub fn add_members(
+    state: &AppState,
+    id: &Uuid,
+    patient_ids: &[Uuid],
+    requester: &Requester,
+) -> Result<Option<PatientGroup>> {
+    let Some(old) = state.groups.get_by_id(id)? else {
+        return Ok(None);
+    };
+    check_members(state, patient_ids)?;
+    let updated = state.groups.add_members(id, patient_ids)?;
+    if let Some(updated) = &updated {
+        if updated.members != old.members {
+            audit(state, *id, Some(&old), Some(updated), requester);
+        }
+    }
+    Ok(updated)
+}
+
+/// Remove a patient from a group, returning `None` if the group does not
+/// exist and otherwise whether the patient was a member
+pub fn remove_member(state: &AppState, id: &Uuid, patient_id: &Uuid, requester: &Requester) -> Result<Option<bool>> {
+    let Some(old) = state.groups.get_by_id(id)? else {
+        return Ok(None);
+    };
+    if !state.groups.remove_member(id, patient_id)? {
+        return Ok(Some(false));
+    }
+    let mut new = old.clone();
+    new.members.retain(|member| member != patient_id);
+    audit(state, *id, Some(&old), Some(&new), requester);
+    Ok(Some(true))
+}
+
+/// The group as `requester` may see it
+///
+/// Members are treated like search results: restricted and VIP members are
+/// audited, and left out when `privacy.restricted_search` excludes them.
+pub fn visible(state: &AppState, mut group: PatientGroup, requester: &Requester, endpoint: &str) -> Result<PatientGroup> {
+    let mut members = Vec::with_capacity(group.members.len());
+    for patient_id in group.members {
+        let shown = match state.patient_repository.get_by_id(&patient_id)? {
+            Some(patient) => privacy::view(state, requester, patient, Read::Search, endpoint).is_some(),
+            None => true,
+        };
+        if shown {
+            members.push(patient_id);
+        }
+    }
+    group.members = members;
+    Ok(group)
+}
+
+/// Current records of a group's members, in group order, or `None` if the
+/// group does not exist
+///
+/// Members deleted since they were added are left out.
+pub fn member_records(state: &AppState, id: &Uuid) -> Result<Option<Vec<Patient>>> {
+    let Some(group) = state.groups.get_by_id(id)? else {
+        return Ok(None);
+    };
+    let mut patients = Vec::with_capacity(group.members.len());
+    for patient_id in &group.members {
+        if let Some(patient) = state.patient_repository.get_by_id(patient_id)? {
+            patients.push(patient);
+        }
+    }
+    Ok(Some(patients))
+}
@@ -0,0 +1,314 @@
+//! Acknowledgment (ACK) generation
+//!
+//! Every message gets an original-mode acknowledgment: `AA` when it was
+//! applied, `AE` when its content could not be applied and `AR` when the
+//! message type or version is not supported. Each problem is reported in
+//! an ERR segment with its location, an HL7 table 0357 error code and a
+//! readable description.
+
+use chrono::Utc;
+use uuid::Uuid;
+
+use super::message::{Delimiters, Message};
+
+/// MSA-1 acknowledgment code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AckCode {
+    /// Application accept
+    AA,
+    /// Application error
+    AE,
+    /// Application reject
+    AR,
+}
+
+impl AckCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AckCode::AA => "AA",
+            AckCode::AE => "AE",
+            AckCode::AR => "AR",
+        }
+    }
+}
+
+/// HL7 table 0357 message error condition codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    SegmentSequenceError,
+    RequiredFieldMissing,
+    DataTypeError,
+    TableValueNotFound,
+    UnsupportedMessageType,
+    UnsupportedEventCode,
+    UnsupportedVersionId,
+    DuplicateKeyIdentifier,
+    ApplicationInternalError,
+}
+
+impl ErrorCode {
+    pub fn code(self) -> u16 {
+        match self {
+            ErrorCode::SegmentSequenceError => 100,
+            ErrorCode::RequiredFieldMissing => 101,
+            ErrorCode::DataTypeError => 102,
+            ErrorCode::TableValueNotFound => 103,
+            ErrorCode::UnsupportedMessageType => 200,
+            ErrorCode::UnsupportedEventCode => 201,
+            ErrorCode::UnsupportedVersionId => 203,
+            ErrorCode::DuplicateKeyIdentifier => 205,
+            ErrorCode::ApplicationInternalError => 207,
+        }
+    }
+
+    pub fn text(self) -> &'static str {
+        match self {
+            ErrorCode::SegmentSequenceError => "Segment sequence error",
+            ErrorCode::RequiredFieldMissing => "Required field missing",
+            ErrorCode::DataTypeError => "Data type error",
+            ErrorCode::TableValueNotFound => "Table value not found",
+            ErrorCode::UnsupportedMessageType => "Unsupported message type",
+            ErrorCode::UnsupportedEventCode => "Unsupported event code",
+            ErrorCode::UnsupportedVersionId => "Unsupported version id",
+            ErrorCode::DuplicateKeyIdentifier => "Duplicate key identifier",
+            ErrorCode::ApplicationInternalError => "Application internal error",
+        }
+    }
+}
+
+/// ERR-4 severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "E",
+            Severity::Warning => "W",
+        }
+    }
+}
+
+/// ERR-2 location of the offending field, e.g. `PID^1^7`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorLocation {
+    pub segment: String,
+    pub sequence: usize,
+    pub field: usize,
+}
+
+/// One problem found in a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorDetail {
+    pub location: Option<ErrorLocation>,
+    pub code: ErrorCode,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl ErrorDetail {
+    pub fn error(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            location: None,
+            code,
+            severity: Severity::Error,
+            message: message.into(),
+        }
+    }
+
+    pub fn warning(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(code, message)
+        }
+    }
+
+    /// Locate the problem at a field of the first segment with this ID
+    pub fn at(mut self, segment: &str, field: usize) -> Self {
+        self.location = Some(ErrorLocation {
+            segment: segment.to_string(),
+            sequence: 1,
+            field,
+        });
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// JSON form kept with quarantined messages, e.g.
+    /// `{"location": "PID-3", "code": 205, "text": "Duplicate key identifier", "severity": "E", "message": "..."}`
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "location": self.location.as_ref().map(|l| format!("{}-{}", l.segment, l.field)),
+            "code": self.code.code(),
+            "text": self.code.text(),
+            "severity": self.severity.as_str(),
+            "message": self.message,
+        })
+    }
+
+    /// Encode as an ERR segment
+    fn segment(&self, delimiters: &Delimiters) -> String {
+        let (f, c) = (delimiters.field, delimiters.component);
+        let location = self
+            .location
+            .as_ref()
+            .map(|l| format!("{}{c}{}{c}{}", l.segment, l.sequence, l.field))
+            .unwrap_or_default();
+        let severity = self.severity.as_str();
+        // ERR-2 location, ERR-3 error code, ERR-4 severity, ERR-8 user message
+        format!(
+            "ERR{f}{f}{location}{f}{}{c}{}{c}HL70357{f}{severity}{f}{f}{f}{f}{}",
+            self.code.code(),
+            self.code.text(),
+            delimiters.escape(&self.message),
+        )
+    }
+}
+
+/// An acknowledgment to send back for a message
+#[derive(Debug, Clone)]
+pub struct Acknowledgment {
+    pub code: AckCode,
+    pub errors: Vec<ErrorDetail>,
+}
+
+impl Acknowledgment {
+    /// `AE` if any problem is an error, otherwise `AA` carrying the warnings
+    pub fn from_details(errors: Vec<ErrorDetail>) -> Self {
+        let code = if errors.iter().any(ErrorDetail::is_error) {
+            AckCode::AE
+        } else {
+            AckCode::AA
+        };
+        Self { code, errors }
+    }
+
+    pub fn reject(errors: Vec<ErrorDetail>) -> Self {
+        Self {
+            code: AckCode::AR,
+            errors,
+        }
+    }
+
+    /// Encode the ACK for `original`, or for unparseable input when `None`
+    ///
+    /// The ACK is addressed back to the original sender, echoes its control
+    /// ID in MSA-2 and uses its delimiters, processing ID and version.
+    pub fn encode(&self, original: Option<&Message>, application: &str, facility: &str) -> String {
+        let delimiters = original.map(|m| m.delimiters).unwrap_or_default();
+        let (f, c) = (delimiters.field, delimiters.component);
+        let header = original.map(Message::header);
+        let field = |n: usize| header.map(|h| h.field(n)).unwrap_or_default();
+        let trigger = original.map(|m| m.message_type().1).unwrap_or_default();
+        let message_type = if trigger.is_empty() {
+            "ACK".to_string()
+        } else {
+            format!("ACK{c}{trigger}{c}ACK")
+        };
+        let processing_id = Some(field(11)).filter(|p| !p.is_empty()).unwrap_or("P");
+        let version = Some(field(12)).filter(|v| !v.is_empty()).unwrap_or("2.5.1");
+        let control_id: String = Uuid::new_v4().simple().to_string().chars().take(20).collect();
+
+        let mut segments = vec![
+            format!(
+                "MSH{f}{}{f}{}{f}{}{f}{}{f}{}{f}{}{f}{f}{}{f}{}{f}{}{f}{}",
+                delimiters.encoding_characters(),
+                delimiters.escape(application),
+                delimiters.escape(facility),
+                field(3),
+                field(4),
+                Utc::now().format("%Y%m%d%H%M%S"),
+                message_type,
+                control_id,
+                processing_id,
+                version,
+            ),
+            // MSA-3 text is deprecated but still read by many v2.3 interface engines
+            match self.errors.first() {
+                Some(first) => format!(
+                    "MSA{f}{}{f}{}{f}{}",
+                    self.code.as_str(),
+                    field(10),
+                    delimiters.escape(&first.message)
+                ),
+                None => format!("MSA{f}{}{f}{}", self.code.as_str(), field(10)),
+            },
+        ];
+        segments.extend(self.errors.iter().map(|e| e.segment(&delimiters)));
+
+        segments.join("\r") + "\r"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ack_with_errors() {
+        let original = Message::parse(
+            "MSH|^~\\&|REG|GENERAL|MPI|MPI|20250102083000||ADT^A04^ADT_A01|MSG0001|T|2.3\rPID|1",
+        )
+        .unwrap();
+        let ack = Acknowledgment::from_details(vec![
+            ErrorDetail::error(ErrorCode::RequiredFieldMissing, "PID-5 patient name is required").at("PID", 5),
+            ErrorDetail::warning(ErrorCode::TableValueNotFound, "Gender 'Q' is not recognized").at("PID", 8),
+        ]);
+        assert_eq!(ack.code, AckCode::AE);
+
+        let encoded = ack.encode(Some(&original), "MPI", "HOSP");
+        let reply = Message::parse(&encoded).unwrap();
+        let header = reply.header();
+        assert_eq!(header.field(3), "MPI");
+        assert_eq!(header.field(4), "HOSP");
+        assert_eq!(header.field(5), "REG");
+        assert_eq!(header.field(6), "GENERAL");
+        assert_eq!(header.field(9), "ACK^A04^ACK");
+        assert!(!header.field(10).is_empty() && header.field(10).len() <= 20);
+        assert_eq!(header.field(11), "T");
+        assert_eq!(header.field(12), "2.3");
+
+        let msa = reply.segment("MSA").unwrap();
+        assert_eq!(msa.field(1), "AE");
+        assert_eq!(msa.field(2), "MSG0001");
+
+        let errors: Vec<_> = reply.segments.iter().filter(|s| s.id == "ERR").collect();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].field(2), "PID^1^5");
+        assert_eq!(errors[0].field(3), "101^Required field missing^HL70357");
+        assert_eq!(errors[0].field(4), "E");
+        assert_eq!(errors[0].field(8), "PID-5 patient name is required");
+        assert_eq!(errors[1].field(4), "W");
+        assert_eq!(errors[1].field(8), "Gender 'Q' is not recognized");
+    }
+
+    #[test]
+    fn test_ack_without_original() {
+        let ack = Acknowledgment::reject(vec![ErrorDetail::error(
+            ErrorCode::SegmentSequenceError,
+            "Message does not start with an MSH segment",
+        )]);
+        let reply = Message::parse(&ack.encode(None, "MPI", "MPI")).unwrap();
+
+        assert_eq!(reply.header().field(9), "ACK");
+        let msa = reply.segment("MSA").unwrap();
+        assert_eq!(msa.field(1), "AR");
+        assert_eq!(msa.field(2), "");
+        assert_eq!(reply.segment("ERR").unwrap().field(3), "100^Segment sequence error^HL70357");
+    }
+
+    #[test]
+    fn test_warnings_only_accept() {
+        let ack = Acknowledgment::from_details(vec![ErrorDetail::warning(
+            ErrorCode::DataTypeError,
+            "Birth date '1984' is not a full date and was ignored",
+        )]);
+        assert_eq!(ack.code, AckCode::AA);
+    }
+}
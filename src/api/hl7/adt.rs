@@ -0,0 +1,428 @@
+//! ADT message to patient mapping
+//!
+//! Registration events (A01, A04, A05, A28) carry a new patient's
+//! demographics in PID. Problems are reported as [`ErrorDetail`]s: errors
+//! prevent the patient from being stored, warnings describe data that was
+//! dropped.
+
+use crate::models::{
+    Address, ContactPoint, ContactPointSystem, ContactPointUse, Gender, HumanName, Identifier,
+    IdentifierType, NameUse, Patient, Period, VerificationStatus,
+};
+use crate::validation::{parse_date, IdentifierRules};
+
+use super::ack::{ErrorCode, ErrorDetail};
+use super::message::{component, Message};
+
+/// ADT trigger events that register a patient
+pub const SUPPORTED_EVENTS: &[&str] = &["A01", "A04", "A05", "A28"];
+
+/// Check MSH-9 and MSH-12, returning the reason to reject the message
+pub fn check_header(message: &Message) -> Option<ErrorDetail> {
+    let (code, event) = message.message_type();
+    let version = message.header().field(12);
+
+    if code != "ADT" {
+        return Some(
+            ErrorDetail::error(
+                ErrorCode::UnsupportedMessageType,
+                format!("Message type '{}' is not supported; send ADT messages", code),
+            )
+            .at("MSH", 9),
+        );
+    }
+    if !SUPPORTED_EVENTS.contains(&event.as_str()) {
+        return Some(
+            ErrorDetail::error(
+                ErrorCode::UnsupportedEventCode,
+                format!(
+                    "Event '{}' is not supported; supported events are {}",
+                    event,
+                    SUPPORTED_EVENTS.join(", ")
+                ),
+            )
+            .at("MSH", 9),
+        );
+    }
+    if !version.starts_with("2.") {
+        return Some(
+            ErrorDetail::error(
+                ErrorCode::UnsupportedVersionId,
+                format!("HL7 version '{}' is not supported; send version 2.x", version),
+            )
+            .at("MSH", 12),
+        );
+    }
+    None
+}
+
+/// Map the PID segment of an ADT message to a patient
+///
+/// Identifiers failing `identifier_rules` are dropped with a warning.
+/// Returns `None` for the patient when any detail is an error.
+pub fn patient_from_adt(message: &Message, identifier_rules: &IdentifierRules) -> (Option<Patient>, Vec<ErrorDetail>) {
+    let mut details = Vec::new();
+
+    let Some(pid) = message.segment("PID") else {
+        details.push(ErrorDetail::error(
+            ErrorCode::SegmentSequenceError,
+            "Message has no PID segment",
+        ));
+        return (None, details);
+    };
+
+    // PID-5 patient name; the first repetition is the primary name
+    let mut names = Vec::new();
+    for xpn in message.repetitions(pid, 5) {
+        let family = component(&xpn, 1);
+        if family.is_empty() {
+            continue;
+        }
+        let use_type = match component(&xpn, 7) {
+            "L" => Some(NameUse::Official),
+            "D" => Some(NameUse::Usual),
+            "M" => Some(NameUse::Maiden),
+            "N" => Some(NameUse::Nickname),
+            "S" => Some(NameUse::Anonymous),
+            _ => None,
+        };
+        let non_empty = |n: usize| Some(component(&xpn, n)).filter(|s| !s.is_empty()).map(String::from);
+        names.push(HumanName {
+            use_type,
+            family: family.to_string(),
+            given: [non_empty(2), non_empty(3)].into_iter().flatten().collect(),
+            prefix: non_empty(5).into_iter().collect(),
+            suffix: non_empty(4).into_iter().collect(),
+        });
+    }
+    if names.is_empty() {
+        details.push(
+            ErrorDetail::error(
+                ErrorCode::RequiredFieldMissing,
+                "PID-5 patient name is required and must include a family name",
+            )
+            .at("PID", 5),
+        );
+    }
+
+    // PID-3 patient identifier list, with CX-7 and CX-8 effective and expiration dates
+    let mut identifiers = Vec::new();
+    for cx in message.repetitions(pid, 3) {
+        let value = component(&cx, 1);
+        let authority = component(&cx, 4);
+        if value.is_empty() {
+            continue;
+        }
+        if authority.is_empty() {
+            details.push(
+                ErrorDetail::warning(
+                    ErrorCode::RequiredFieldMissing,
+                    format!("Identifier '{}' has no assigning authority and was ignored", value),
+                )
+                .at("PID", 3),
+            );
+            continue;
+        }
+        let identifier_type = match component(&cx, 5) {
+            "MR" | "MRN" => IdentifierType::MRN,
+            "SS" | "SSN" => IdentifierType::SSN,
+            "DL" => IdentifierType::DL,
+            "NPI" => IdentifierType::NPI,
+            "PPN" => IdentifierType::PPN,
+            "TAX" | "TN" => IdentifierType::TAX,
+            _ => IdentifierType::Other,
+        };
+        let identifier = Identifier {
+            use_type: None,
+            identifier_type,
+            system: authority.to_string(),
+            value: value.to_string(),
+            assigner: Some(authority.to_string()),
+            verification: VerificationStatus::Unverified,
+            period: period(&cx, 7, 8, 3, &mut details),
+        };
+        if let Some(problem) = identifier_rules.problem(&identifier) {
+            details.push(
+                ErrorDetail::warning(
+                    ErrorCode::DataTypeError,
+                    format!("{} identifier from '{}' was ignored: {}", identifier.identifier_type, authority, problem),
+                )
+                .at("PID", 3),
+            );
+            continue;
+        }
+        identifiers.push(identifier);
+    }
+    if identifiers.is_empty() {
+        details.push(
+            ErrorDetail::error(
+                ErrorCode::RequiredFieldMissing,
+                "PID-3 needs at least one identifier with an assigning authority",
+            )
+            .at("PID", 3),
+        );
+    }
+
+    // PID-7 date of birth, a DTM whose first eight characters are the date
+    let dob = component(&message.field_components(pid, 7), 1).to_string();
+    let birth_date = match dob.len() {
+        0 => None,
+        4 | 6 if dob.bytes().all(|b| b.is_ascii_digit()) => {
+            details.push(
+                ErrorDetail::warning(
+                    ErrorCode::DataTypeError,
+                    format!("PID-7 date of birth '{}' is not a full date and was ignored", dob),
+                )
+                .at("PID", 7),
+            );
+            None
+        }
+        _ => match dob.get(..8).filter(|d| d.bytes().all(|b| b.is_ascii_digit())).map(|d| parse_date(d, None)) {
+            Some(Ok(parsed)) => Some(parsed.date),
+            _ => {
+                details.push(
+                    ErrorDetail::error(
+                        ErrorCode::DataTypeError,
+                        format!("PID-7 date of birth '{}' is not a valid YYYYMMDD date", dob),
+                    )
+                    .at("PID", 7),
+                );
+                None
+            }
+        },
+    };
+
+    // PID-8 administrative sex, HL7 table 0001
+    let gender = match component(&message.field_components(pid, 8), 1) {
+        "M" => Gender::Male,
+        "F" => Gender::Female,
+        "O" | "A" => Gender::Other,
+        "U" | "N" | "" => Gender::Unknown,
+        other => {
+            details.push(
+                ErrorDetail::warning(
+                    ErrorCode::TableValueNotFound,
+                    format!("PID-8 sex '{}' is not in HL7 table 0001 and was stored as unknown", other),
+                )
+                .at("PID", 8),
+            );
+            Gender::Unknown
+        }
+    };
+
+    // PID-11 patient address, with XAD-13 and XAD-14 effective and expiration dates
+    let addresses = message
+        .repetitions(pid, 11)
+        .iter()
+        .map(|xad| {
+            let non_empty = |n: usize| Some(component(xad, n)).filter(|s| !s.is_empty()).map(String::from);
+            Address {
+                line1: non_empty(1),
+                line2: non_empty(2),
+                city: non_empty(3),
+                state: non_empty(4),
+                postal_code: non_empty(5),
+                country: non_empty(6),
+                verification: VerificationStatus::Unverified,
+                period: period(xad, 13, 14, 11, &mut details),
+            }
+        })
+        .filter(|a| a.line1.is_some() || a.city.is_some() || a.postal_code.is_some())
+        .collect();
+
+    // PID-13 home and PID-14 business phone numbers
+    let mut telecom = Vec::new();
+    for (field, default_use) in [(13, ContactPointUse::Home), (14, ContactPointUse::Work)] {
+        for xtn in message.repetitions(pid, field) {
+            let use_type = match (component(&xtn, 2), component(&xtn, 3)) {
+                (_, "CP") => ContactPointUse::Mobile,
+                ("WPN", _) => ContactPointUse::Work,
+                _ => default_use.clone(),
+            };
+            let (system, value) = match component(&xtn, 3) {
+                "Internet" | "X.400" => (ContactPointSystem::Email, component(&xtn, 4).to_string()),
+                "FX" => (ContactPointSystem::Fax, component(&xtn, 1).to_string()),
+                "BP" => (ContactPointSystem::Pager, component(&xtn, 1).to_string()),
+                _ => {
+                    // Pre-2.3 senders put the whole number in XTN-1; later ones split it
+                    let split: String = [6, 7, 8].iter().map(|&n| component(&xtn, n)).collect();
+                    let value = if component(&xtn, 1).is_empty() { split } else { component(&xtn, 1).to_string() };
+                    (ContactPointSystem::Phone, value)
+                }
+            };
+            if !value.is_empty() {
+                telecom.push(ContactPoint {
+                    system,
+                    value,
+                    use_type: Some(use_type),
+                    period: period(&xtn, 13, 14, field, &mut details),
+                });
+            }
+        }
+    }
+
+    if details.iter().any(ErrorDetail::is_error) {
+        return (None, details);
+    }
+
+    let mut names = names.into_iter();
+    let Some(name) = names.next() else {
+        return (None, details);
+    };
+    let mut patient = Patient::new(name, gender);
+    patient.additional_names = names.collect();
+    patient.identifiers = identifiers;
+    patient.birth_date = birth_date;
+    patient.addresses = addresses;
+    patient.telecom = telecom;
+
+    // PID-30 patient death indicator
+    patient.deceased = component(&message.field_components(pid, 30), 1) == "Y";
+
+    (Some(patient), details)
+}
+
+/// Validity period from the effective and expiration date components of a
+/// PID field, DTs or DTMs whose first eight characters are the date
+///
+/// A date that cannot be read is dropped with a warning.
+fn period(
+    components: &[String],
+    start: usize,
+    end: usize,
+    field: usize,
+    details: &mut Vec<ErrorDetail>,
+) -> Option<Period> {
+    let mut date = |n: usize| {
+        let text = component(components, n);
+        if text.is_empty() {
+            return None;
+        }
+        match text.get(..8).filter(|d| d.bytes().all(|b| b.is_ascii_digit())).map(|d| parse_date(d, None)) {
+            Some(Ok(parsed)) => parsed.date.and_hms_opt(0, 0, 0).map(|datetime| datetime.and_utc()),
+            _ => {
+                details.push(
+                    ErrorDetail::warning(
+                        ErrorCode::DataTypeError,
+                        format!("PID-{} date '{}' is not a valid YYYYMMDD date and was ignored", field, text),
+                    )
+                    .at("PID", field),
+                );
+                None
+            }
+        }
+    };
+    let start = date(start);
+    Period::from_bounds(start, date(end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn adt(pid: &str) -> Message {
+        Message::parse(&format!(
+            "MSH|^~\\&|REG|GENERAL|MPI|MPI|20250102083000||ADT^A04^ADT_A01|MSG0001|P|2.5.1\r{}",
+            pid
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_maps_pid() {
+        let message = adt(
+            "PID|1||A100^^^GENERAL^MR~123456789^^^SSA^SS||Smith^John^Q^Jr^Dr||19800115|M|||1 Main St^^Portland^ME^04101^US||^PRN^PH^^^207^5551234~^NET^Internet^john@example.com",
+        );
+        let (patient, details) = patient_from_adt(&message, &IdentifierRules::default());
+        let patient = patient.unwrap();
+
+        assert!(details.is_empty(), "{:?}", details);
+        assert_eq!(patient.name.family, "Smith");
+        assert_eq!(patient.name.given, vec!["John", "Q"]);
+        assert_eq!(patient.name.suffix, vec!["Jr"]);
+        assert_eq!(patient.birth_date, NaiveDate::from_ymd_opt(1980, 1, 15));
+        assert_eq!(patient.gender, Gender::Male);
+        assert_eq!(patient.identifiers.len(), 2);
+        assert_eq!(patient.identifiers[0].system, "GENERAL");
+        assert!(matches!(patient.identifiers[1].identifier_type, IdentifierType::SSN));
+        assert_eq!(patient.addresses[0].postal_code.as_deref(), Some("04101"));
+        assert_eq!(patient.telecom.len(), 2);
+        assert_eq!(patient.telecom[0].value, "2075551234");
+        assert!(matches!(patient.telecom[1].system, ContactPointSystem::Email));
+    }
+
+    #[test]
+    fn test_maps_effective_and_expiration_dates() {
+        let message = adt(
+            "PID|1||A100^^^GENERAL^MR^^20100301^20240630||Smith^John||19800115|M|||1 Main St^^Portland^ME^04101^US^^^^^^^20200101||^PRN^PH^^^207^5551234^^^^^^2025",
+        );
+        let (patient, details) = patient_from_adt(&message, &IdentifierRules::default());
+        let patient = patient.unwrap();
+
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap().and_hms_opt(0, 0, 0).unwrap().and_utc();
+        let identifier_period = patient.identifiers[0].period.unwrap();
+        assert_eq!(identifier_period.start, Some(date(2010, 3, 1)));
+        assert_eq!(identifier_period.end, Some(date(2024, 6, 30)));
+        assert_eq!(patient.addresses[0].period.unwrap().start, Some(date(2020, 1, 1)));
+        assert!(patient.telecom[0].period.is_none());
+        assert_eq!(details.len(), 1);
+        assert_eq!(details[0].location.as_ref().unwrap().field, 13);
+    }
+
+    #[test]
+    fn test_missing_name_and_bad_birth_date() {
+        let (patient, details) = patient_from_adt(&adt("PID|1||A100^^^GENERAL^MR||^John||19801345|M"), &IdentifierRules::default());
+
+        assert!(patient.is_none());
+        let codes: Vec<_> = details.iter().map(|d| (d.code, d.location.as_ref().unwrap().field)).collect();
+        assert_eq!(codes, vec![(ErrorCode::RequiredFieldMissing, 5), (ErrorCode::DataTypeError, 7)]);
+        assert!(details[1].message.contains("19801345"));
+    }
+
+    #[test]
+    fn test_warnings_keep_patient() {
+        let (patient, details) = patient_from_adt(&adt("PID|1||A100^^^GENERAL^MR~999||Smith^John||1980|Q"), &IdentifierRules::default());
+
+        let patient = patient.unwrap();
+        assert_eq!(patient.birth_date, None);
+        assert_eq!(patient.gender, Gender::Unknown);
+        assert_eq!(details.len(), 3);
+        assert!(details.iter().all(|d| !d.is_error()));
+    }
+
+    #[test]
+    fn test_malformed_identifiers_are_dropped() {
+        let mut rules = IdentifierRules::default();
+        rules.config.mrn_patterns.insert("GENERAL".to_string(), vec!["A999".to_string()]);
+
+        let (patient, details) = patient_from_adt(
+            &adt("PID|1||A100^^^GENERAL^MR~000123456^^^SSA^SS||Smith^John||19800115|M"),
+            &rules,
+        );
+        let patient = patient.unwrap();
+        assert_eq!(patient.identifiers.len(), 1);
+        assert_eq!(details.len(), 1);
+        assert_eq!((details[0].code, details[0].location.as_ref().unwrap().field), (ErrorCode::DataTypeError, 3));
+        assert!(!details[0].message.contains("000123456"));
+
+        // With no identifier left the message is refused
+        let (patient, details) = patient_from_adt(&adt("PID|1||1000^^^GENERAL^MR||Smith^John||19800115|M"), &rules);
+        assert!(patient.is_none());
+        assert!(details.iter().any(ErrorDetail::is_error));
+    }
+
+    #[test]
+    fn test_check_header() {
+        assert!(check_header(&adt("PID|1")).is_none());
+
+        let orm = Message::parse("MSH|^~\\&|A|B|C|D|20250102||ORM^O01|1|P|2.3").unwrap();
+        assert_eq!(check_header(&orm).unwrap().code, ErrorCode::UnsupportedMessageType);
+
+        let a03 = Message::parse("MSH|^~\\&|A|B|C|D|20250102||ADT^A03|1|P|2.3").unwrap();
+        assert_eq!(check_header(&a03).unwrap().code, ErrorCode::UnsupportedEventCode);
+
+        let v3 = Message::parse("MSH|^~\\&|A|B|C|D|20250102||ADT^A04|1|P|3.0").unwrap();
+        assert_eq!(check_header(&v3).unwrap().code, ErrorCode::UnsupportedVersionId);
+    }
+}
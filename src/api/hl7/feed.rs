@@ -0,0 +1,645 @@
+//! Outbound identity feed
+//!
+//! Registration, lab and billing systems keep their patient tables in step
+//! with the MPI by listening to its ADT feed. Created and updated patients
+//! are sent as A31 (update person information) and merges as A40 (merge
+//! patient identifier list) to each configured destination whose event types
+//! and assigning authorities the change matches.
+//!
+//! [`FeedEventProducer`] only queues events. [`IdentityFeed`] encodes them on
+//! a background thread and hands them to one delivery thread per
+//! destination, so an unreachable system holds up only its own messages.
+//! Each destination receives its messages in order; a message is retried with
+//! backoff until it is acknowledged or the configured attempts are used up.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::circuit_breaker::CircuitBreakers;
+use crate::config::{FeedDestination, Hl7Config, IdentityFeedConfig};
+use crate::db::PatientRepository;
+use crate::models::{ContactPointSystem, ContactPointUse, Gender, HumanName, IdentifierType, NameUse, Patient};
+use crate::streaming::{EventEnvelope, EventProducer, PatientEvent};
+use crate::{Error, Result};
+use super::message::{Delimiters, Message};
+use super::mllp;
+
+/// Identifier type code (CX-5) of the MPI's own patient id in PID-3 and MRG-1
+pub const ENTERPRISE_ID_TYPE: &str = "PI";
+
+/// Event types a destination can filter on
+pub const FEED_EVENT_TYPES: [&str; 3] = ["Created", "Updated", "Merged"];
+
+/// Longest wait between delivery attempts
+const MAX_BACKOFF_SECS: u64 = 60;
+
+/// Encode an ADT^A31 carrying a patient's current demographics
+pub fn encode_a31(patient: &Patient, hl7: &Hl7Config, destination: &FeedDestination, timestamp: DateTime<Utc>) -> String {
+    let delimiters = Delimiters::default();
+    let segments = [
+        header(&delimiters, hl7, destination, "A31^ADT_A05", timestamp),
+        event(&delimiters, timestamp),
+        pid(&delimiters, patient, hl7),
+    ];
+    segments.join("\r") + "\r"
+}
+
+/// Encode an ADT^A40 merging `source_id` into the surviving `target`
+pub fn encode_a40(
+    target: &Patient,
+    source_id: Uuid,
+    hl7: &Hl7Config,
+    destination: &FeedDestination,
+    timestamp: DateTime<Utc>,
+) -> String {
+    let delimiters = Delimiters::default();
+    // MRG-1 prior patient identifier list
+    let mrg = segment(&delimiters, "MRG", vec![enterprise_id(&delimiters, source_id, hl7)]);
+    let segments = [
+        header(&delimiters, hl7, destination, "A40^ADT_A39", timestamp),
+        event(&delimiters, timestamp),
+        pid(&delimiters, target, hl7),
+        mrg,
+    ];
+    segments.join("\r") + "\r"
+}
+
+/// Join fields into a segment, dropping trailing empty fields
+fn segment(delimiters: &Delimiters, id: &str, mut fields: Vec<String>) -> String {
+    while fields.last().is_some_and(String::is_empty) {
+        fields.pop();
+    }
+    std::iter::once(id.to_string())
+        .chain(fields)
+        .collect::<Vec<_>>()
+        .join(&delimiters.field.to_string())
+}
+
+/// Join components, dropping trailing empty ones
+fn components(delimiters: &Delimiters, mut parts: Vec<String>) -> String {
+    while parts.last().is_some_and(String::is_empty) {
+        parts.pop();
+    }
+    parts.join(&delimiters.component.to_string())
+}
+
+fn header(delimiters: &Delimiters, hl7: &Hl7Config, destination: &FeedDestination, event: &str, timestamp: DateTime<Utc>) -> String {
+    let (f, c) = (delimiters.field, delimiters.component);
+    let control_id: String = Uuid::new_v4().simple().to_string().chars().take(20).collect();
+    format!(
+        "MSH{f}{}{f}{}{f}{}{f}{}{f}{}{f}{}{f}{f}ADT{c}{}{f}{}{f}P{f}2.5.1",
+        delimiters.encoding_characters(),
+        delimiters.escape(&hl7.application),
+        delimiters.escape(&hl7.facility),
+        delimiters.escape(&destination.application),
+        delimiters.escape(&destination.facility),
+        timestamp.format("%Y%m%d%H%M%S"),
+        event.replace('^', &c.to_string()),
+        control_id,
+    )
+}
+
+/// EVN segment; EVN-2 is when the change was recorded
+fn event(delimiters: &Delimiters, timestamp: DateTime<Utc>) -> String {
+    segment(delimiters, "EVN", vec![String::new(), timestamp.format("%Y%m%d%H%M%S").to_string()])
+}
+
+/// CX of the MPI's own id, assigned by the MPI application
+fn enterprise_id(delimiters: &Delimiters, id: Uuid, hl7: &Hl7Config) -> String {
+    components(
+        delimiters,
+        vec![
+            id.to_string(),
+            String::new(),
+            String::new(),
+            delimiters.escape(&hl7.application),
+            ENTERPRISE_ID_TYPE.to_string(),
+        ],
+    )
+}
+
+/// PID segment, the reverse of the inbound mapping in [`super::adt`]
+fn pid(delimiters: &Delimiters, patient: &Patient, hl7: &Hl7Config) -> String {
+    let repeat = |values: Vec<String>| values.join(&delimiters.repetition.to_string());
+    let escape = |text: &str| delimiters.escape(text);
+
+    // PID-3 patient identifier list, starting with the enterprise id
+    let mut identifiers = vec![enterprise_id(delimiters, patient.id, hl7)];
+    identifiers.extend(patient.identifiers.iter().map(|identifier| {
+        let type_code = match identifier.identifier_type {
+            IdentifierType::MRN => "MR",
+            IdentifierType::SSN => "SS",
+            IdentifierType::DL => "DL",
+            IdentifierType::NPI => "NPI",
+            IdentifierType::PPN => "PPN",
+            IdentifierType::TAX => "TAX",
+            IdentifierType::Other => "",
+        };
+        components(
+            delimiters,
+            vec![
+                escape(&identifier.value),
+                String::new(),
+                String::new(),
+                escape(&identifier.system),
+                type_code.to_string(),
+            ],
+        )
+    }));
+
+    // PID-5 patient name; the primary name is the first repetition
+    let names = std::iter::once(&patient.name)
+        .chain(&patient.additional_names)
+        .map(|name| xpn(delimiters, name))
+        .collect();
+
+    // PID-11 patient address
+    let addresses = patient
+        .addresses
+        .iter()
+        .map(|address| {
+            let part = |value: &Option<String>| value.as_deref().map(escape).unwrap_or_default();
+            components(
+                delimiters,
+                vec![
+                    part(&address.line1),
+                    part(&address.line2),
+                    part(&address.city),
+                    part(&address.state),
+                    part(&address.postal_code),
+                    part(&address.country),
+                ],
+            )
+        })
+        .collect();
+
+    // PID-13 home and PID-14 business phone numbers
+    let (mut home, mut business) = (Vec::new(), Vec::new());
+    for contact in &patient.telecom {
+        let use_code = match contact.use_type {
+            Some(ContactPointUse::Work) => "WPN",
+            _ => "PRN",
+        };
+        let value = escape(&contact.value);
+        let xtn = match contact.system {
+            ContactPointSystem::Email => components(delimiters, vec![String::new(), "NET".to_string(), "Internet".to_string(), value]),
+            ContactPointSystem::Fax => components(delimiters, vec![value, use_code.to_string(), "FX".to_string()]),
+            ContactPointSystem::Pager => components(delimiters, vec![value, use_code.to_string(), "BP".to_string()]),
+            ContactPointSystem::Phone | ContactPointSystem::Sms => {
+                let equipment = if matches!(contact.use_type, Some(ContactPointUse::Mobile)) { "CP" } else { "PH" };
+                components(delimiters, vec![value, use_code.to_string(), equipment.to_string()])
+            }
+            ContactPointSystem::Url | ContactPointSystem::Other => continue,
+        };
+        if matches!(contact.use_type, Some(ContactPointUse::Work)) {
+            business.push(xtn);
+        } else {
+            home.push(xtn);
+        }
+    }
+
+    let sex = match patient.gender {
+        Gender::Male => "M",
+        Gender::Female => "F",
+        Gender::Other => "O",
+        Gender::Unknown => "U",
+    };
+
+    let mut fields = vec![String::new(); 30];
+    fields[0] = "1".to_string();
+    fields[2] = repeat(identifiers);
+    fields[4] = repeat(names);
+    fields[6] = patient.birth_date.map(|d| d.format("%Y%m%d").to_string()).unwrap_or_default();
+    fields[7] = sex.to_string();
+    fields[10] = repeat(addresses);
+    fields[12] = repeat(home);
+    fields[13] = repeat(business);
+    fields[29] = if patient.deceased { "Y" } else { "N" }.to_string();
+    segment(delimiters, "PID", fields)
+}
+
+/// XPN: family^given^middle^suffix^prefix^^name type code
+fn xpn(delimiters: &Delimiters, name: &HumanName) -> String {
+    let escape = |text: &str| delimiters.escape(text);
+    let name_type = match name.use_type {
+        Some(NameUse::Official) => "L",
+        Some(NameUse::Usual) => "D",
+        Some(NameUse::Maiden) => "M",
+        Some(NameUse::Nickname) => "N",
+        Some(NameUse::Anonymous) => "S",
+        _ => "",
+    };
+    components(
+        delimiters,
+        vec![
+            escape(&name.family),
+            name.given.first().map(|g| escape(g)).unwrap_or_default(),
+            escape(&name.given.iter().skip(1).cloned().collect::<Vec<_>>().join(" ")),
+            escape(&name.suffix.join(" ")),
+            escape(&name.prefix.join(" ")),
+            String::new(),
+            name_type.to_string(),
+        ],
+    )
+}
+
+/// Whether a destination wants an event about `patient`
+fn accepts(destination: &FeedDestination, event_type: &str, patient: &Patient) -> bool {
+    (destination.event_types.is_empty() || destination.event_types.iter().any(|t| t == event_type))
+        && (destination.authorities.is_empty()
+            || patient
+                .identifiers
+                .iter()
+                .any(|identifier| destination.authorities.contains(&identifier.system)))
+}
+
+/// Delivers an encoded message to a destination
+pub trait FeedSender: Send + Sync {
+    fn send(&self, destination: &FeedDestination, message: &str) -> Result<()>;
+}
+
+/// Feed sender that opens an MLLP connection per message and waits for the ACK
+///
+/// `AA` and `CA` acknowledgments count as delivered; anything else is an
+/// error, so the message is retried.
+pub struct MllpFeedSender {
+    timeout: Duration,
+}
+
+impl MllpFeedSender {
+    /// Create a sender with the given connect, write and read timeout
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+}
+
+impl FeedSender for MllpFeedSender {
+    fn send(&self, destination: &FeedDestination, message: &str) -> Result<()> {
+        let failed = |e: std::io::Error| {
+            Error::Streaming(format!("Identity feed to {} failed: {}", destination.name, e))
+        };
+        let addr = (destination.host.as_str(), destination.port)
+            .to_socket_addrs()
+            .map_err(failed)?
+            .next()
+            .ok_or_else(|| Error::Streaming(format!("Identity feed host {} did not resolve", destination.host)))?;
+        let mut stream = TcpStream::connect_timeout(&addr, self.timeout).map_err(failed)?;
+        stream.set_read_timeout(Some(self.timeout)).map_err(failed)?;
+        stream.set_write_timeout(Some(self.timeout)).map_err(failed)?;
+
+        let mut frame = Vec::with_capacity(message.len() + 3);
+        frame.push(mllp::START);
+        frame.extend_from_slice(message.as_bytes());
+        frame.extend_from_slice(&[mllp::END, mllp::CARRIAGE_RETURN]);
+        stream.write_all(&frame).and_then(|_| stream.flush()).map_err(failed)?;
+
+        let mut reader = BufReader::new(stream.take(mllp::MAX_FRAME_BYTES as u64));
+        let mut reply = Vec::new();
+        reader.read_until(mllp::START, &mut Vec::new()).map_err(failed)?;
+        reader.read_until(mllp::END, &mut reply).map_err(failed)?;
+        if reply.pop() != Some(mllp::END) {
+            return Err(Error::Streaming(format!("{} closed the connection before acknowledging", destination.name)));
+        }
+
+        let ack = Message::parse(&String::from_utf8_lossy(&reply))?;
+        let msa = ack.segment("MSA").map(|msa| msa.field(1)).unwrap_or_default();
+        match msa {
+            "AA" | "CA" => Ok(()),
+            code => Err(Error::Streaming(format!(
+                "{} answered {} to message {}",
+                destination.name,
+                if code.is_empty() { "without MSA" } else { code },
+                ack.segment("MSA").map(|msa| msa.field(2)).unwrap_or_default(),
+            ))),
+        }
+    }
+}
+
+/// Feed sender with a circuit breaker per destination, named `feed:<name>`
+pub struct CircuitBreakingFeedSender {
+    inner: Arc<dyn FeedSender>,
+    breakers: Arc<CircuitBreakers>,
+}
+
+impl CircuitBreakingFeedSender {
+    pub fn new(inner: Arc<dyn FeedSender>, breakers: Arc<CircuitBreakers>) -> Self {
+        Self { inner, breakers }
+    }
+}
+
+impl FeedSender for CircuitBreakingFeedSender {
+    fn send(&self, destination: &FeedDestination, message: &str) -> Result<()> {
+        self.breakers
+            .get(&format!("feed:{}", destination.name))
+            .call(|| self.inner.send(destination, message))
+    }
+}
+
+/// Encodes patient events and queues them for each destination
+pub struct IdentityFeed {
+    hl7: Hl7Config,
+    patients: Arc<dyn PatientRepository>,
+    queues: Vec<(FeedDestination, mpsc::Sender<String>)>,
+}
+
+impl IdentityFeed {
+    /// Start a delivery thread per configured destination
+    pub fn new(
+        config: &IdentityFeedConfig,
+        hl7: &Hl7Config,
+        patients: Arc<dyn PatientRepository>,
+        sender: Arc<dyn FeedSender>,
+    ) -> Self {
+        let max_attempts = config.max_attempts.max(1);
+        let queues = config
+            .destinations
+            .iter()
+            .map(|destination| {
+                let (queue, messages) = mpsc::channel::<String>();
+                let sender = sender.clone();
+                let target = destination.clone();
+                std::thread::Builder::new()
+                    .name(format!("identity-feed-{}", destination.name))
+                    .spawn(move || {
+                        for message in messages {
+                            deliver(sender.as_ref(), &target, &message, max_attempts);
+                        }
+                    })
+                    .expect("failed to spawn identity feed delivery thread");
+                (destination.clone(), queue)
+            })
+            .collect();
+
+        Self {
+            hl7: hl7.clone(),
+            patients,
+            queues,
+        }
+    }
+
+    /// Queue an event's message for every destination that accepts it,
+    /// returning how many it was queued for
+    pub fn dispatch(&self, event: &PatientEvent) -> Result<usize> {
+        let timestamp = event.timestamp();
+        let (patient, merged_from) = match event {
+            PatientEvent::Created { patient, .. } | PatientEvent::Updated { patient, .. } => (patient.clone(), None),
+            PatientEvent::Merged { source_id, target_id, .. } => {
+                let Some(target) = self.patients.get_by_id(target_id)? else {
+                    return Err(Error::PatientNotFound(target_id.to_string()));
+                };
+                (target, Some(*source_id))
+            }
+            _ => return Ok(0),
+        };
+
+        let mut queued = 0;
+        for (destination, queue) in &self.queues {
+            if !accepts(destination, event.event_type(), &patient) {
+                continue;
+            }
+            let message = match merged_from {
+                Some(source_id) => encode_a40(&patient, source_id, &self.hl7, destination, timestamp),
+                None => encode_a31(&patient, &self.hl7, destination, timestamp),
+            };
+            if queue.send(message).is_err() {
+                tracing::warn!("Identity feed thread for {} has stopped; dropping {} event", destination.name, event.event_type());
+                continue;
+            }
+            queued += 1;
+        }
+        Ok(queued)
+    }
+
+    /// Dispatch every event received on `events` on a background thread
+    ///
+    /// The thread stops once every sender has been dropped.
+    pub fn spawn(self, events: mpsc::Receiver<PatientEvent>) {
+        std::thread::Builder::new()
+            .name("identity-feed".to_string())
+            .spawn(move || {
+                for event in events {
+                    if let Err(e) = self.dispatch(&event) {
+                        tracing::warn!("Identity feed skipped {} event for patient {}: {}", event.event_type(), event.patient_id(), e);
+                    }
+                }
+            })
+            .expect("failed to spawn identity feed thread");
+    }
+}
+
+fn deliver(sender: &dyn FeedSender, destination: &FeedDestination, message: &str, max_attempts: u32) {
+    for attempt in 1..=max_attempts {
+        match sender.send(destination, message) {
+            Ok(()) => return,
+            Err(e) if attempt < max_attempts => {
+                tracing::debug!("{} (attempt {} of {})", e, attempt, max_attempts);
+                std::thread::sleep(Duration::from_secs((1u64 << attempt.min(6)).min(MAX_BACKOFF_SECS)));
+            }
+            Err(e) => tracing::error!("{}; dropping message for {} after {} attempts", e, destination.name, max_attempts),
+        }
+    }
+}
+
+/// Event producer that also hands events to an [`IdentityFeed`]
+///
+/// Events are only queued here; the feed never delays or fails the publish.
+pub struct FeedEventProducer {
+    inner: Arc<dyn EventProducer>,
+    events: mpsc::Sender<PatientEvent>,
+}
+
+impl FeedEventProducer {
+    /// Wrap a producer, returning the receiver to pass to [`IdentityFeed::spawn`]
+    pub fn new(inner: Arc<dyn EventProducer>) -> (Self, mpsc::Receiver<PatientEvent>) {
+        let (events, received) = mpsc::channel();
+        (Self { inner, events }, received)
+    }
+
+    fn enqueue(&self, event: &PatientEvent) {
+        if FEED_EVENT_TYPES.contains(&event.event_type()) && self.events.send(event.clone()).is_err() {
+            tracing::warn!("Identity feed thread has stopped; skipping {} event", event.event_type());
+        }
+    }
+}
+
+impl EventProducer for FeedEventProducer {
+    fn publish(&self, event: PatientEvent) -> Result<()> {
+        self.inner.publish(event.clone())?;
+        self.enqueue(&event);
+        Ok(())
+    }
+
+    fn publish_envelope(&self, envelope: EventEnvelope) -> Result<()> {
+        let event = envelope.event.clone();
+        self.inner.publish_envelope(envelope)?;
+        self.enqueue(&event);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use crate::db::InMemoryPatientRepository;
+    use crate::models::{Address, ContactPoint, Identifier, VerificationStatus};
+    use crate::validation::IdentifierRules;
+
+    fn destination(event_types: &[&str], authorities: &[&str]) -> FeedDestination {
+        FeedDestination {
+            name: "lab".to_string(),
+            host: "127.0.0.1".to_string(),
+            port: 2575,
+            application: "LIS".to_string(),
+            facility: "LAB".to_string(),
+            event_types: event_types.iter().map(|t| t.to_string()).collect(),
+            authorities: authorities.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    fn patient() -> Patient {
+        let mut patient = crate::fixtures::patient("O'Neil^Smith", &["Aoife", "Mary"], Gender::Female);
+        patient.name.use_type = Some(NameUse::Official);
+        patient.identifiers.push(Identifier {
+            use_type: None,
+            identifier_type: IdentifierType::MRN,
+            system: "GENERAL".to_string(),
+            value: "MRN-1001".to_string(),
+            assigner: None,
+            verification: VerificationStatus::Unverified,
+            period: None,
+        });
+        patient.birth_date = chrono::NaiveDate::from_ymd_opt(1990, 6, 15);
+        patient.addresses.push(Address {
+            line1: Some("1 Quay St".to_string()),
+            line2: None,
+            city: Some("Galway".to_string()),
+            state: None,
+            postal_code: Some("H91".to_string()),
+            country: Some("IE".to_string()),
+            verification: VerificationStatus::Unverified,
+            period: None,
+        });
+        patient.telecom.push(ContactPoint {
+            system: ContactPointSystem::Phone,
+            value: "555-0100".to_string(),
+            use_type: Some(ContactPointUse::Work),
+            period: None,
+        });
+        patient
+    }
+
+    struct RecordingSender {
+        sent: Mutex<mpsc::Sender<(String, String)>>,
+    }
+
+    impl FeedSender for RecordingSender {
+        fn send(&self, destination: &FeedDestination, message: &str) -> Result<()> {
+            self.sent.lock().unwrap().send((destination.name.clone(), message.to_string())).unwrap();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_a31_round_trips_through_inbound_mapping() {
+        let patient = patient();
+        let encoded = encode_a31(&patient, &Hl7Config::default(), &destination(&[], &[]), Utc::now());
+        let message = Message::parse(&encoded).unwrap();
+
+        assert_eq!(message.message_type(), ("ADT".to_string(), "A31".to_string()));
+        assert_eq!(message.header().field(5), "LIS");
+        assert_eq!(message.header().field(6), "LAB");
+
+        let (parsed, details) = super::super::adt::patient_from_adt(&message, &IdentifierRules::default());
+        assert!(details.is_empty(), "{:?}", details);
+        let parsed = parsed.unwrap();
+        assert_eq!(parsed.name.family, "O'Neil^Smith");
+        assert_eq!(parsed.name.given, vec!["Aoife", "Mary"]);
+        assert_eq!(parsed.name.use_type, Some(NameUse::Official));
+        assert_eq!(parsed.birth_date, patient.birth_date);
+        assert_eq!(parsed.gender, Gender::Female);
+        assert_eq!(parsed.addresses[0].city.as_deref(), Some("Galway"));
+        assert!(matches!(parsed.telecom[0].use_type, Some(ContactPointUse::Work)));
+
+        // The enterprise id leads PID-3, followed by the patient's own identifiers
+        let values: Vec<_> = parsed.identifiers.iter().map(|i| (i.system.as_str(), i.value.clone())).collect();
+        assert_eq!(values, vec![("MPI", patient.id.to_string()), ("GENERAL", "MRN-1001".to_string())]);
+    }
+
+    #[test]
+    fn test_a40_names_the_retired_id() {
+        let target = patient();
+        let source_id = Uuid::new_v4();
+        let encoded = encode_a40(&target, source_id, &Hl7Config::default(), &destination(&[], &[]), Utc::now());
+        let message = Message::parse(&encoded).unwrap();
+
+        assert_eq!(message.header().field(9), "ADT^A40^ADT_A39");
+        let mrg = message.segment("MRG").unwrap();
+        assert_eq!(mrg.field(1), format!("{}^^^MPI^PI", source_id));
+        let pid = message.segment("PID").unwrap();
+        assert!(pid.field(3).starts_with(&format!("{}^^^MPI^PI~", target.id)));
+    }
+
+    #[test]
+    fn test_dispatch_filters_per_destination() {
+        let patients = Arc::new(InMemoryPatientRepository::new());
+        let target = patients.create(&patient()).unwrap();
+        let config = IdentityFeedConfig {
+            destinations: vec![
+                FeedDestination { name: "all".to_string(), ..destination(&[], &[]) },
+                FeedDestination { name: "merges".to_string(), ..destination(&["Merged"], &[]) },
+                FeedDestination { name: "radiology".to_string(), ..destination(&[], &["RAD"]) },
+            ],
+            ..IdentityFeedConfig::default()
+        };
+        let (sent, received) = mpsc::channel();
+        let feed = IdentityFeed::new(
+            &config,
+            &Hl7Config::default(),
+            patients,
+            Arc::new(RecordingSender { sent: Mutex::new(sent) }),
+        );
+
+        let updated = PatientEvent::Updated { patient: target.clone(), timestamp: Utc::now() };
+        assert_eq!(feed.dispatch(&updated).unwrap(), 1);
+        let (name, message) = received.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(name, "all");
+        assert!(message.contains("ADT^A31"));
+
+        let merged = PatientEvent::Merged { source_id: Uuid::new_v4(), target_id: target.id, timestamp: Utc::now() };
+        assert_eq!(feed.dispatch(&merged).unwrap(), 2);
+        let linked = PatientEvent::Linked { patient_id: target.id, linked_id: Uuid::new_v4(), timestamp: Utc::now() };
+        assert_eq!(feed.dispatch(&linked).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_mllp_sender_requires_accept() {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            for code in ["AA", "AE"] {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut frame = Vec::new();
+                reader.read_until(mllp::END, &mut frame).unwrap();
+                let reply = format!("\x0bMSH|^~\\&|LIS|LAB|MPI|MPI|20250101000000||ACK|1|P|2.5.1\rMSA|{}|X\r\x1c\r", code);
+                (&stream).write_all(reply.as_bytes()).unwrap();
+            }
+        });
+
+        let sender = MllpFeedSender::new(Duration::from_secs(5));
+        let destination = FeedDestination { port, ..destination(&[], &[]) };
+        let message = encode_a31(&patient(), &Hl7Config::default(), &destination, Utc::now());
+        assert!(sender.send(&destination, &message).is_ok());
+        assert!(sender.send(&destination, &message).is_err());
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,240 @@
+//! HL7 v2 message parsing
+//!
+//! Only what the listener needs: segments, fields, repetitions and
+//! components, using the delimiters declared in MSH-1 and MSH-2.
+
+use crate::{Error, Result};
+
+/// Delimiters declared by a message's MSH segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delimiters {
+    pub field: char,
+    pub component: char,
+    pub repetition: char,
+    pub escape: char,
+    pub subcomponent: char,
+}
+
+impl Default for Delimiters {
+    fn default() -> Self {
+        Self {
+            field: '|',
+            component: '^',
+            repetition: '~',
+            escape: '\\',
+            subcomponent: '&',
+        }
+    }
+}
+
+impl Delimiters {
+    /// MSH-2 encoding characters
+    pub fn encoding_characters(&self) -> String {
+        [self.component, self.repetition, self.escape, self.subcomponent].iter().collect()
+    }
+
+    /// Escape delimiter characters in free text
+    pub fn escape(&self, text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            let sequence = match c {
+                c if c == self.field => "F",
+                c if c == self.component => "S",
+                c if c == self.repetition => "R",
+                c if c == self.escape => "E",
+                c if c == self.subcomponent => "T",
+                '\r' | '\n' => {
+                    escaped.push(' ');
+                    continue;
+                }
+                _ => {
+                    escaped.push(c);
+                    continue;
+                }
+            };
+            escaped.push(self.escape);
+            escaped.push_str(sequence);
+            escaped.push(self.escape);
+        }
+        escaped
+    }
+
+    /// Replace delimiter escape sequences in a value
+    pub fn unescape(&self, value: &str) -> String {
+        let mut parts = value.split(self.escape);
+        let mut unescaped = parts.next().unwrap_or_default().to_string();
+        // Escape sequences sit between pairs of escape characters
+        while let (Some(sequence), Some(rest)) = (parts.next(), parts.next()) {
+            match sequence {
+                "F" => unescaped.push(self.field),
+                "S" => unescaped.push(self.component),
+                "R" => unescaped.push(self.repetition),
+                "E" => unescaped.push(self.escape),
+                "T" => unescaped.push(self.subcomponent),
+                _ => {}
+            }
+            unescaped.push_str(rest);
+        }
+        unescaped
+    }
+}
+
+/// A segment, with fields numbered as in the HL7 specification
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub id: String,
+    /// `fields[n]` is field n; `fields[0]` is the segment ID
+    fields: Vec<String>,
+}
+
+impl Segment {
+    /// Raw value of a field, or "" when absent
+    pub fn field(&self, n: usize) -> &str {
+        self.fields.get(n).map(String::as_str).unwrap_or_default()
+    }
+}
+
+/// A parsed HL7 v2 message
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub delimiters: Delimiters,
+    pub segments: Vec<Segment>,
+}
+
+impl Message {
+    /// Parse a message whose segments are separated by carriage returns
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim_start_matches(['\r', '\n']);
+        if !text.starts_with("MSH") {
+            return Err(Error::Validation("Message does not start with an MSH segment".to_string()));
+        }
+
+        let mut header = text[3..].chars();
+        let field = header
+            .next()
+            .ok_or_else(|| Error::Validation("MSH segment is truncated".to_string()))?;
+        let encoding: Vec<char> = header.take_while(|c| *c != field).collect();
+        let defaults = Delimiters::default();
+        let delimiters = Delimiters {
+            field,
+            component: encoding.first().copied().unwrap_or(defaults.component),
+            repetition: encoding.get(1).copied().unwrap_or(defaults.repetition),
+            escape: encoding.get(2).copied().unwrap_or(defaults.escape),
+            subcomponent: encoding.get(3).copied().unwrap_or(defaults.subcomponent),
+        };
+
+        let segments = text
+            .split(['\r', '\n'])
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let mut fields: Vec<String> = line.split(field).map(String::from).collect();
+                let id = fields[0].clone();
+                // MSH-1 is the field separator itself, so MSH-2 is the first split value
+                if id == "MSH" {
+                    fields.insert(1, field.to_string());
+                }
+                Segment { id, fields }
+            })
+            .collect();
+
+        Ok(Self { delimiters, segments })
+    }
+
+    /// The first segment with the given ID
+    pub fn segment(&self, id: &str) -> Option<&Segment> {
+        self.segments.iter().find(|s| s.id == id)
+    }
+
+    /// The MSH segment, which [`Message::parse`] guarantees is first
+    pub fn header(&self) -> &Segment {
+        &self.segments[0]
+    }
+
+    /// Repetitions of a field, each split into unescaped components
+    pub fn repetitions(&self, segment: &Segment, field: usize) -> Vec<Vec<String>> {
+        segment
+            .field(field)
+            .split(self.delimiters.repetition)
+            .filter(|r| !r.is_empty())
+            .map(|r| self.components(r))
+            .collect()
+    }
+
+    /// Components of the first repetition of a field
+    pub fn field_components(&self, segment: &Segment, field: usize) -> Vec<String> {
+        self.repetitions(segment, field).into_iter().next().unwrap_or_default()
+    }
+
+    /// Split a value into components, keeping the first subcomponent of each
+    fn components(&self, value: &str) -> Vec<String> {
+        value
+            .split(self.delimiters.component)
+            .map(|c| {
+                let first = c.split(self.delimiters.subcomponent).next().unwrap_or_default();
+                self.delimiters.unescape(first)
+            })
+            .collect()
+    }
+
+    /// MSH-9 message code and trigger event, e.g. ("ADT", "A04")
+    pub fn message_type(&self) -> (String, String) {
+        let components = self.field_components(self.header(), 9);
+        let part = |i: usize| components.get(i).cloned().unwrap_or_default();
+        (part(0), part(1))
+    }
+
+    /// MSH-10 message control ID
+    pub fn control_id(&self) -> &str {
+        self.header().field(10)
+    }
+
+    /// MSH-3 sending application, e.g. "REG"
+    pub fn sending_application(&self) -> String {
+        component(&self.field_components(self.header(), 3), 1).to_string()
+    }
+}
+
+/// Component `n` (1-based) of a split field, or "" when absent
+pub fn component(components: &[String], n: usize) -> &str {
+    components.get(n - 1).map(String::as_str).unwrap_or_default().trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADT: &str = "MSH|^~\\&|REG|GENERAL|MPI|MPI|20250102083000||ADT^A04^ADT_A01|MSG0001|P|2.5.1\r\
+PID|1||A100^^^GENERAL&1.2.3&ISO^MR~123-45-6789^^^SSA^SS||O\\T\\Brien^Siobhan^M||19840229|F";
+
+    #[test]
+    fn test_parse_fields_and_components() {
+        let message = Message::parse(ADT).unwrap();
+
+        assert_eq!(message.header().field(1), "|");
+        assert_eq!(message.header().field(2), "^~\\&");
+        assert_eq!(message.message_type(), ("ADT".to_string(), "A04".to_string()));
+        assert_eq!(message.control_id(), "MSG0001");
+        assert_eq!(message.sending_application(), "REG");
+
+        let pid = message.segment("PID").unwrap();
+        let identifiers = message.repetitions(pid, 3);
+        assert_eq!(identifiers.len(), 2);
+        assert_eq!(component(&identifiers[0], 4), "GENERAL");
+        assert_eq!(component(&identifiers[1], 5), "SS");
+        assert_eq!(component(&message.field_components(pid, 5), 1), "O&Brien");
+        assert_eq!(pid.field(7), "19840229");
+    }
+
+    #[test]
+    fn test_escape_round_trip() {
+        let delimiters = Delimiters::default();
+        let escaped = delimiters.escape("PID-5 | missing ^ name");
+        assert_eq!(escaped, "PID-5 \\F\\ missing \\S\\ name");
+        assert_eq!(delimiters.unescape(&escaped), "PID-5 | missing ^ name");
+    }
+
+    #[test]
+    fn test_rejects_non_hl7() {
+        assert!(Message::parse("{\"resourceType\":\"Patient\"}").is_err());
+    }
+}
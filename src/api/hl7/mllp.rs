@@ -0,0 +1,98 @@
+//! Minimal Lower Layer Protocol framing
+//!
+//! Each message is sent as `<VT> message <FS><CR>`.
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::{Error, Result};
+
+/// Start block
+pub const START: u8 = 0x0B;
+/// End block
+pub const END: u8 = 0x1C;
+/// Trailing carriage return
+pub const CARRIAGE_RETURN: u8 = 0x0D;
+
+/// Largest frame accepted, to bound memory per connection
+pub const MAX_FRAME_BYTES: usize = 1024 * 1024;
+
+/// Read the next frame, or `None` when the peer closed the connection
+///
+/// Bytes before the start block are discarded.
+pub async fn read_frame<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<Vec<u8>>> {
+    let io_error = |e: std::io::Error| Error::Api(format!("MLLP read failed: {}", e));
+
+    let mut skipped = Vec::new();
+    if reader.read_until(START, &mut skipped).await.map_err(io_error)? == 0
+        || skipped.last() != Some(&START)
+    {
+        return Ok(None);
+    }
+
+    let mut frame = Vec::new();
+    loop {
+        let read = (&mut *reader)
+            .take((MAX_FRAME_BYTES + 1 - frame.len()) as u64)
+            .read_until(END, &mut frame)
+            .await
+            .map_err(io_error)?;
+        if frame.last() == Some(&END) {
+            frame.pop();
+            break;
+        }
+        if frame.len() > MAX_FRAME_BYTES {
+            return Err(Error::Api(format!("MLLP frame exceeds {} bytes", MAX_FRAME_BYTES)));
+        }
+        if read == 0 {
+            return Err(Error::Api("Connection closed inside an MLLP frame".to_string()));
+        }
+    }
+
+    // The carriage return after the end block is required but some senders omit it
+    let next = reader.fill_buf().await.map_err(io_error)?;
+    if next.first() == Some(&CARRIAGE_RETURN) {
+        reader.consume(1);
+    }
+
+    Ok(Some(frame))
+}
+
+/// Write one framed message
+pub async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, message: &[u8]) -> Result<()> {
+    let mut frame = Vec::with_capacity(message.len() + 3);
+    frame.push(START);
+    frame.extend_from_slice(message);
+    frame.extend_from_slice(&[END, CARRIAGE_RETURN]);
+
+    writer
+        .write_all(&frame)
+        .await
+        .map_err(|e| Error::Api(format!("MLLP write failed: {}", e)))?;
+    writer
+        .flush()
+        .await
+        .map_err(|e| Error::Api(format!("MLLP write failed: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_frame_round_trip() {
+        let mut buffer = b"noise".to_vec();
+        write_frame(&mut buffer, b"MSH|^~\\&|first").await.unwrap();
+        write_frame(&mut buffer, b"MSH|^~\\&|second").await.unwrap();
+
+        let mut reader = buffer.as_slice();
+        assert_eq!(read_frame(&mut reader).await.unwrap().unwrap(), b"MSH|^~\\&|first");
+        assert_eq!(read_frame(&mut reader).await.unwrap().unwrap(), b"MSH|^~\\&|second");
+        assert!(read_frame(&mut reader).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_truncated_frame() {
+        let mut reader: &[u8] = &[START, b'M', b'S', b'H'];
+        assert!(read_frame(&mut reader).await.is_err());
+    }
+}
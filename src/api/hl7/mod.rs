@@ -0,0 +1,225 @@
+//! HL7 v2 ADT listener over MLLP
+//!
+//! Registration messages from interface engines create patients, the same
+//! way `POST /api/v1/patients` does. Every framed message is answered with
+//! an ACK: `AA` when the patient was stored, `AE` with ERR segments naming
+//! the fields that failed validation, or `AR` for message types and
+//! versions the MPI does not accept. The connection stays open either way.
+//! Messages answered `AE` are quarantined until corrected and resubmitted.
+//!
+//! In the other direction, [`feed`] broadcasts identity changes to
+//! downstream systems as A31 and A40 messages.
+
+pub mod ack;
+pub mod adt;
+pub mod feed;
+pub mod message;
+pub mod mllp;
+
+pub use ack::{AckCode, Acknowledgment, ErrorCode, ErrorDetail, Severity};
+pub use message::Message;
+
+/// Media type of archived ER7-encoded messages
+pub const HL7_V2_MEDIA_TYPE: &str = "x-application/hl7-v2+er7";
+
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
+use uuid::Uuid;
+
+use crate::api::rest::AppState;
+use crate::models::archived_message::CHANNEL_HL7;
+use crate::models::SourceSubmission;
+use crate::Result;
+
+/// Accept MLLP connections on the configured HL7 address
+pub async fn serve(state: AppState) -> Result<()> {
+    let addr = format!("{}:{}", state.config.hl7.host, state.config.hl7.port);
+    let listener = TcpListener::bind(&addr)
+        .await
+        .map_err(|e| crate::Error::Api(e.to_string()))?;
+
+    tracing::info!("HL7 MLLP listener on {}", addr);
+
+    loop {
+        let (stream, peer) = listener
+            .accept()
+            .await
+            .map_err(|e| crate::Error::Api(e.to_string()))?;
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &state).await {
+                tracing::warn!("HL7 connection from {} closed: {}", peer, e);
+            }
+        });
+    }
+}
+
+/// Answer each message on a connection until the peer disconnects
+async fn handle_connection(stream: TcpStream, state: &AppState) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    while let Some(frame) = mllp::read_frame(&mut reader).await? {
+        let text = String::from_utf8_lossy(&frame);
+        let reply = handle_message(&text, state);
+        mllp::write_frame(&mut writer, reply.as_bytes()).await?;
+    }
+    Ok(())
+}
+
+/// Outcome of taking in one message
+#[derive(Debug)]
+pub struct Ingested {
+    /// The message, or `None` when it could not be parsed
+    pub message: Option<Message>,
+    pub ack: Acknowledgment,
+    /// The patient stored from the message
+    pub patient_id: Option<Uuid>,
+}
+
+impl Ingested {
+    /// Whether the message was refused for its content, so that a corrected
+    /// copy could be accepted
+    ///
+    /// Unsupported message types and versions are not, and neither are
+    /// failures on this side.
+    pub fn is_refusal(&self) -> bool {
+        self.ack.code != AckCode::AA
+            && !self.ack.errors.iter().any(|e| {
+                matches!(
+                    e.code,
+                    ErrorCode::UnsupportedMessageType
+                        | ErrorCode::UnsupportedEventCode
+                        | ErrorCode::UnsupportedVersionId
+                        | ErrorCode::ApplicationInternalError
+                )
+            })
+    }
+}
+
+/// Process one message and encode its acknowledgment
+///
+/// Messages refused for their content are quarantined for correction.
+pub fn handle_message(text: &str, state: &AppState) -> String {
+    let config = &state.config.hl7;
+    let ingested = ingest(text, state);
+
+    if ingested.is_refusal() {
+        let errors = ingested.ack.errors.iter().map(ErrorDetail::to_json).collect();
+        crate::api::quarantine::quarantine(
+            state.quarantine.as_ref(),
+            CHANNEL_HL7,
+            HL7_V2_MEDIA_TYPE,
+            text,
+            &serde_json::Value::Array(errors),
+            None,
+        );
+    }
+    if let Some(message) = ingested.message.as_ref().filter(|_| ingested.ack.code != AckCode::AA) {
+        tracing::info!(
+            "HL7 message {} answered {} with {} error(s)",
+            message.control_id(),
+            ingested.ack.code.as_str(),
+            ingested.ack.errors.len()
+        );
+    }
+    ingested.ack.encode(ingested.message.as_ref(), &config.application, &config.facility)
+}
+
+/// Parse one message and store its patient
+pub fn ingest(text: &str, state: &AppState) -> Ingested {
+    let message = match Message::parse(text) {
+        Ok(message) => message,
+        Err(e) => {
+            return Ingested {
+                message: None,
+                ack: Acknowledgment::reject(vec![ErrorDetail::error(ErrorCode::SegmentSequenceError, e.to_string())]),
+                patient_id: None,
+            };
+        }
+    };
+
+    let (ack, patient_id) = match process(&message, text, state) {
+        Ok(processed) => processed,
+        Err(e) => {
+            tracing::error!("Failed to store patient from HL7 message {}: {}", message.control_id(), e);
+            let ack = Acknowledgment::from_details(vec![ErrorDetail::error(
+                ErrorCode::ApplicationInternalError,
+                format!("Patient could not be stored: {}", e),
+            )]);
+            (ack, None)
+        }
+    };
+    Ingested { message: Some(message), ack, patient_id }
+}
+
+/// Validate a message and store its patient, with the message as its source
+/// record, archiving the message text
+fn process(message: &Message, text: &str, state: &AppState) -> Result<(Acknowledgment, Option<Uuid>)> {
+    if let Some(rejection) = adt::check_header(message) {
+        return Ok((Acknowledgment::reject(vec![rejection]), None));
+    }
+
+    let rules = state.identifier_rules();
+    let (patient, mut details) = adt::patient_from_adt(message, &rules);
+    let Some(mut patient) = patient else {
+        return Ok((Acknowledgment::from_details(details), None));
+    };
+
+    // PID carries no MPI ID, so the one the patient was built with is
+    // replaced, by one derived from its source identifiers where configured
+    patient.id = Uuid::nil();
+    let replayed = crate::validation::assign_patient_id(
+        &state.config.identifiers,
+        state.patient_repository.as_ref(),
+        &mut patient,
+    )?;
+    if let Some(existing) = replayed {
+        tracing::info!("HL7 message {} replays patient {}", message.control_id(), existing.id);
+        return Ok((Acknowledgment::from_details(details), Some(existing.id)));
+    }
+
+    // A resent registration is acknowledged without storing it again
+    match crate::matching::find_resubmitted(&patient, state.patient_repository.as_ref()) {
+        Ok(Some(existing)) => {
+            tracing::info!("HL7 message {} resubmits patient {}", message.control_id(), existing.id);
+            return Ok((Acknowledgment::from_details(details), Some(existing.id)));
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Fingerprint lookup failed: {}", e),
+    }
+
+    let conflicts = rules.mrn_conflicts(&patient, state.patient_repository.as_ref())?;
+    if !conflicts.is_empty() {
+        details.extend(conflicts.into_iter().map(|conflict| {
+            ErrorDetail::error(
+                ErrorCode::DuplicateKeyIdentifier,
+                format!("MRN from '{}' already belongs to patient {}", conflict.authority, conflict.patient_id),
+            )
+            .at("PID", 3)
+        }));
+        return Ok((Acknowledgment::from_details(details), None));
+    }
+
+    crate::validation::assign_mrn(state.mrn_sequences.as_ref(), &state.config.identifiers, &mut patient)?;
+    let source = match message.sending_application() {
+        application if application.is_empty() => CHANNEL_HL7.to_string(),
+        application => application,
+    };
+    let submission = SourceSubmission::new(&source, None, &patient);
+    let patient = state.patient_repository.create_from_source(&patient, &submission)?;
+    crate::api::survivorship::record(state, patient.id, None, &patient, &source);
+    crate::api::archive::archive(
+        state.message_archive.as_ref(),
+        patient.id,
+        "CREATE",
+        CHANNEL_HL7,
+        HL7_V2_MEDIA_TYPE,
+        text.as_bytes(),
+    );
+    if let Err(e) = state.search_engine.index_patient(&patient) {
+        tracing::warn!("Failed to index patient in search engine: {}", e);
+    }
+
+    Ok((Acknowledgment::from_details(details), Some(patient.id)))
+}
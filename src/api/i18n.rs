@@ -0,0 +1,377 @@
+//! Localized error messages shared by the REST and FHIR routes
+//!
+//! Patient-facing portals show validation errors and OperationOutcome
+//! diagnostics to patients as they are. Messages are produced in English
+//! throughout the crate; the English catalog names each one by id with
+//! `{placeholder}` templates, and the `localize_errors` middleware reads a
+//! failing response's messages against those templates and renders them
+//! again from the catalog the `Accept-Language` header picks. Messages
+//! without a template, or with no translation, stay in English.
+//!
+//! English, Spanish and French are built in. Downstream users embed more
+//! with [`Localizer::with_catalog`], e.g. from `include_str!`.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::Value;
+
+use crate::{Error, Result};
+
+/// Messages of one language, keyed by message id
+#[derive(Debug, Clone)]
+pub struct MessageCatalog {
+    language: String,
+    messages: BTreeMap<String, String>,
+}
+
+impl MessageCatalog {
+    /// Read a catalog from a JSON object of message ids to templates
+    pub fn from_json(language: &str, json: &str) -> Result<Self> {
+        let messages = serde_json::from_str(json)
+            .map_err(|e| Error::Config(format!("Invalid '{}' message catalog: {}", language, e)))?;
+        Ok(Self {
+            language: language.to_ascii_lowercase(),
+            messages,
+        })
+    }
+
+    /// Primary language subtag, e.g. "es"
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Template of a message, if the catalog has it
+    pub fn get(&self, id: &str) -> Option<&str> {
+        self.messages.get(id).map(String::as_str)
+    }
+}
+
+/// A template split into literal text and named placeholders
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Literal(String),
+    Placeholder(String),
+}
+
+fn parse_template(template: &str) -> Vec<Part> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        if start > 0 {
+            parts.push(Part::Literal(rest[..start].to_string()));
+        }
+        parts.push(Part::Placeholder(rest[start + 1..start + end].to_string()));
+        rest = &rest[start + end + 1..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Literal(rest.to_string()));
+    }
+    parts
+}
+
+/// Match `text` against template parts, collecting placeholder values
+///
+/// Placeholders take the shortest non-empty text that lets the rest match.
+fn capture<'a>(parts: &[Part], text: &'a str, args: &mut Vec<(String, &'a str)>) -> bool {
+    match parts.split_first() {
+        None => text.is_empty(),
+        Some((Part::Literal(literal), rest)) => {
+            text.starts_with(literal.as_str()) && capture(rest, &text[literal.len()..], args)
+        }
+        Some((Part::Placeholder(name), [])) => {
+            if text.is_empty() {
+                return false;
+            }
+            args.push((name.clone(), text));
+            true
+        }
+        Some((Part::Placeholder(name), rest)) => {
+            for end in (1..=text.len()).filter(|&end| text.is_char_boundary(end)) {
+                let mark = args.len();
+                if capture(rest, &text[end..], args) {
+                    args.insert(mark, (name.clone(), &text[..end]));
+                    return true;
+                }
+                args.truncate(mark);
+            }
+            false
+        }
+    }
+}
+
+/// Translates English messages into the catalogs' languages
+#[derive(Debug, Clone)]
+pub struct Localizer {
+    /// English templates by message id, most literal text first
+    templates: Vec<(String, Vec<Part>)>,
+    catalogs: Vec<MessageCatalog>,
+}
+
+impl Localizer {
+    /// Localizer with the built-in English, Spanish and French catalogs
+    pub fn builtin() -> Self {
+        let english = MessageCatalog::from_json("en", include_str!("../../locales/en.json"))
+            .expect("built-in English catalog is valid");
+        let mut localizer = Self::new(english);
+        for (language, json) in [
+            ("es", include_str!("../../locales/es.json")),
+            ("fr", include_str!("../../locales/fr.json")),
+        ] {
+            let catalog = MessageCatalog::from_json(language, json).expect("built-in catalog is valid");
+            localizer = localizer.with_catalog(catalog);
+        }
+        localizer
+    }
+
+    /// Localizer reading messages against the templates of `english`
+    pub fn new(english: MessageCatalog) -> Self {
+        let mut templates: Vec<(String, Vec<Part>)> = english
+            .messages
+            .iter()
+            .map(|(id, template)| (id.clone(), parse_template(template)))
+            .collect();
+        // Prefer the most specific template when several match
+        let literal_len = |parts: &[Part]| -> usize {
+            parts
+                .iter()
+                .map(|part| match part {
+                    Part::Literal(text) => text.len(),
+                    Part::Placeholder(_) => 0,
+                })
+                .sum()
+        };
+        templates.sort_by_key(|(_, parts)| std::cmp::Reverse(literal_len(parts)));
+        Self {
+            templates,
+            catalogs: vec![english],
+        }
+    }
+
+    /// Add a catalog, replacing any catalog for the same language
+    pub fn with_catalog(mut self, catalog: MessageCatalog) -> Self {
+        self.catalogs.retain(|existing| existing.language != catalog.language);
+        self.catalogs.push(catalog);
+        self
+    }
+
+    /// The catalog for a primary language subtag
+    pub fn catalog(&self, language: &str) -> Option<&MessageCatalog> {
+        self.catalogs.iter().find(|catalog| catalog.language == language)
+    }
+
+    /// The catalog for the most preferred language in `Accept-Language`
+    ///
+    /// Only primary subtags are compared, so `es-MX` picks Spanish.
+    pub fn negotiate(&self, headers: &HeaderMap) -> Option<&MessageCatalog> {
+        let mut ranges: Vec<(f32, String)> = headers
+            .get_all(header::ACCEPT_LANGUAGE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|range| {
+                let mut params = range.split(';');
+                let tag = params.next()?.trim();
+                let quality = params
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .find_map(|q| q.trim().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                let primary = tag.split('-').next()?.to_ascii_lowercase();
+                (quality > 0.0 && !primary.is_empty()).then_some((quality, primary))
+            })
+            .collect();
+        // Stable, so equally preferred languages keep their order
+        ranges.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        ranges
+            .iter()
+            .find_map(|(_, language)| self.catalog(language))
+    }
+
+    /// `message` in the catalog's language, or `None` if it cannot be translated
+    ///
+    /// Placeholder values are translated too, so a wrapped message such as
+    /// "Validation error: NPI must be ten digits" is translated throughout.
+    pub fn translate(&self, message: &str, catalog: &MessageCatalog) -> Option<String> {
+        self.translate_nested(message, catalog, 2)
+    }
+
+    fn translate_nested(&self, message: &str, catalog: &MessageCatalog, depth: usize) -> Option<String> {
+        self.templates.iter().find_map(|(id, parts)| {
+            let mut args = Vec::new();
+            if !capture(parts, message, &mut args) {
+                return None;
+            }
+            let mut translated = catalog.get(id)?.to_string();
+            for (name, value) in args {
+                let value = match depth {
+                    0 => value.to_string(),
+                    _ => self
+                        .translate_nested(value, catalog, depth - 1)
+                        .unwrap_or_else(|| value.to_string()),
+                };
+                translated = translated.replace(&format!("{{{}}}", name), &value);
+            }
+            Some(translated)
+        })
+    }
+
+    /// Translate the messages of a REST error envelope or OperationOutcome in place
+    pub fn localize_body(&self, body: &mut Value, catalog: &MessageCatalog) {
+        let translate = |text: &mut Value| {
+            if let Some(translated) = text.as_str().and_then(|message| self.translate(message, catalog)) {
+                *text = Value::String(translated);
+            }
+        };
+
+        if let Some(error) = body.get_mut("error") {
+            if let Some(message) = error.get_mut("message") {
+                translate(message);
+            }
+            if let Some(details) = error.get_mut("details").and_then(Value::as_array_mut) {
+                for detail in details {
+                    if let Some(message) = detail.get_mut("message") {
+                        translate(message);
+                    }
+                }
+            }
+        }
+        if let Some(issues) = body.get_mut("issue").and_then(Value::as_array_mut) {
+            for issue in issues {
+                if let Some(diagnostics) = issue.get_mut("diagnostics") {
+                    translate(diagnostics);
+                }
+            }
+        }
+    }
+}
+
+impl Default for Localizer {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// Middleware translating the messages of failing JSON responses
+///
+/// Successful responses, non-JSON bodies and requests preferring English
+/// are left alone. Translated responses carry `Content-Language`.
+pub async fn localize_errors(State(localizer): State<Arc<Localizer>>, request: Request, next: Next) -> Response {
+    let language = localizer
+        .negotiate(request.headers())
+        .map(|catalog| catalog.language().to_string())
+        .filter(|language| language != "en");
+    let response = next.run(request).await;
+    let Some(catalog) = language.as_deref().and_then(|language| localizer.catalog(language)) else {
+        return response;
+    };
+
+    super::rewrite_error_json(response, |json, headers| {
+        localizer.localize_body(json, catalog);
+        if let Ok(language) = HeaderValue::from_str(catalog.language()) {
+            headers.insert(header::CONTENT_LANGUAGE, language);
+        }
+        headers.append(header::VARY, HeaderValue::from_static("accept-language"));
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation::identifiers::{npi_problem, ssn_problem};
+
+    fn accept_language(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT_LANGUAGE, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    fn spanish(localizer: &Localizer) -> &MessageCatalog {
+        localizer.negotiate(&accept_language("es")).unwrap()
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let localizer = Localizer::builtin();
+
+        assert_eq!(localizer.negotiate(&accept_language("es-MX,es;q=0.9,en;q=0.8")).unwrap().language(), "es");
+        assert_eq!(localizer.negotiate(&accept_language("de, fr;q=0.5, es;q=0.4")).unwrap().language(), "fr");
+        assert_eq!(localizer.negotiate(&accept_language("fr;q=0, en")).unwrap().language(), "en");
+        assert!(localizer.negotiate(&accept_language("de")).is_none());
+        assert!(localizer.negotiate(&HeaderMap::new()).is_none());
+    }
+
+    #[test]
+    fn test_translates_validation_messages() {
+        let localizer = Localizer::builtin();
+        let es = spanish(&localizer);
+
+        assert_eq!(
+            localizer.translate(&npi_problem("123").unwrap(), es).as_deref(),
+            Some("El NPI debe tener diez dígitos")
+        );
+        assert_eq!(
+            localizer.translate(&ssn_problem("666-12-3456").unwrap(), es).as_deref(),
+            Some("El número de área del SSN nunca se emite")
+        );
+        assert_eq!(
+            localizer
+                .translate(&Error::Validation("NPI must be ten digits".to_string()).to_string(), es)
+                .as_deref(),
+            Some("Error de validación: El NPI debe tener diez dígitos")
+        );
+        assert_eq!(
+            localizer.translate("Patient with id 'abc' not found", es).as_deref(),
+            Some("No se encontró el paciente con id 'abc'")
+        );
+        assert!(localizer.translate("Something unexpected", es).is_none());
+    }
+
+    #[test]
+    fn test_localize_body() {
+        let localizer = Localizer::builtin();
+        let fr = localizer.negotiate(&accept_language("fr")).unwrap();
+
+        let mut envelope = serde_json::json!({
+            "success": false,
+            "error": {
+                "code": "VALIDATION_ERROR",
+                "message": "1 identifier(s) failed validation",
+                "details": [{ "field": "identifiers[0].value", "message": "NPI check digit is wrong" }]
+            }
+        });
+        localizer.localize_body(&mut envelope, fr);
+        assert_eq!(envelope["error"]["message"], "1 identifiant(s) n'ont pas passé la validation");
+        assert_eq!(envelope["error"]["details"][0]["message"], "La clé de contrôle du NPI est incorrecte");
+
+        let mut outcome = serde_json::json!({
+            "resourceType": "OperationOutcome",
+            "issue": [{ "severity": "error", "code": "invalid", "diagnostics": "At least one search parameter is required" }]
+        });
+        localizer.localize_body(&mut outcome, fr);
+        assert_eq!(outcome["issue"][0]["diagnostics"], "Au moins un paramètre de recherche est obligatoire");
+    }
+
+    #[test]
+    fn test_downstream_catalog() {
+        let german = MessageCatalog::from_json("de", r#"{ "identifier.npi.length": "Die NPI muss zehn Ziffern haben" }"#)
+            .unwrap();
+        let localizer = Localizer::builtin().with_catalog(german);
+        let de = localizer.negotiate(&accept_language("de-AT")).unwrap();
+
+        assert_eq!(
+            localizer.translate("NPI must be ten digits", de).as_deref(),
+            Some("Die NPI muss zehn Ziffern haben")
+        );
+        assert!(localizer.translate("SSN must be nine digits", de).is_none());
+    }
+}
@@ -0,0 +1,187 @@
+//! Query plans of match requests
+//!
+//! "The MPI didn't find an obvious match" is answered by knowing where the
+//! record dropped out: it was never a candidate, it was not in the
+//! database, it scored below a threshold, or it was hidden from the
+//! requester. A match request with `debug` set returns a [`MatchQueryPlan`]
+//! alongside its results, recording what each stage found and why
+//! candidates were left out.
+
+use std::time::Duration;
+
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::config::BlockingStrategy;
+use crate::matching::{MatchResult, PatientMatcher};
+use crate::models::Patient;
+use crate::observability::metrics::MatchStage;
+use crate::search::PassYield;
+
+/// Why a candidate is not among the results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterReason {
+    /// The search index returned an ID that is not a UUID
+    InvalidId,
+    /// In the search index but not in the database
+    NotInDatabase,
+    /// The database failed to load the candidate
+    LoadFailed,
+    /// The request ran out of time before the candidate was loaded
+    DeadlineExpired,
+    /// Scored below the matcher's own threshold
+    BelowMatcherThreshold,
+    /// Scored below the request's threshold
+    BelowThreshold,
+    /// Managed by an organization other than the one the request was
+    /// scoped to
+    OtherOrganization,
+    /// Hidden from the requester by its confidentiality
+    Restricted,
+    /// Past the request's `limit`
+    OverLimit,
+}
+
+/// A candidate left out of the results
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FilteredCandidate {
+    /// Stage that left it out: `identifier_lookup` or `blocking`
+    pub stage: String,
+    /// Omitted for candidates hidden from the requester
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub patient_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    pub reason: FilterReason,
+}
+
+/// Milliseconds spent in each stage that ran
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct StageTimings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identifier_lookup_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocking_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hydration_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scoring_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_ms: Option<f64>,
+}
+
+/// How a match request found, scored and filtered its candidates
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MatchQueryPlan {
+    /// Blocking strategy of the request's profile
+    pub blocking: BlockingStrategy,
+    /// Stored patients holding the record's MRN or SSN
+    pub identifier_candidates: usize,
+    /// The identifier lookup answered the request, so blocking did not run
+    pub answered_by_identifiers: bool,
+    /// Candidates the blocking search returned
+    pub blocked: usize,
+    /// What each pass of multi-pass blocking found
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub passes: Vec<PassYield>,
+    /// Candidates scored, by identifier lookup and blocking together
+    pub scored: usize,
+    pub filtered: Vec<FilteredCandidate>,
+    pub timings: StageTimings,
+}
+
+impl MatchQueryPlan {
+    /// An empty plan for a request blocking with `blocking`
+    pub fn new(blocking: BlockingStrategy) -> Self {
+        Self {
+            blocking,
+            identifier_candidates: 0,
+            answered_by_identifiers: false,
+            blocked: 0,
+            passes: Vec::new(),
+            scored: 0,
+            filtered: Vec::new(),
+            timings: StageTimings::default(),
+        }
+    }
+
+    /// Record the time spent in a stage
+    pub fn time(&mut self, stage: MatchStage, elapsed: Duration) {
+        let ms = Some(elapsed.as_secs_f64() * 1000.0);
+        match stage {
+            MatchStage::IdentifierLookup => self.timings.identifier_lookup_ms = ms,
+            MatchStage::Blocking => self.timings.blocking_ms = ms,
+            MatchStage::Hydration => self.timings.hydration_ms = ms,
+            MatchStage::Scoring => self.timings.scoring_ms = ms,
+            MatchStage::Total => self.timings.total_ms = ms,
+        }
+    }
+
+    /// Record a candidate left out of the results
+    pub fn filter(&mut self, stage: MatchStage, patient_id: Option<Uuid>, score: Option<f64>, reason: FilterReason) {
+        self.filtered.push(FilteredCandidate {
+            stage: stage.as_str().to_string(),
+            patient_id,
+            score,
+            reason,
+        });
+    }
+
+    /// Record the candidates `matcher` scored but left out of `results`,
+    /// scoring them again for their scores
+    pub fn below_matcher_threshold(
+        &mut self,
+        stage: MatchStage,
+        matcher: &dyn PatientMatcher,
+        patient: &Patient,
+        candidates: &[Patient],
+        results: &[MatchResult],
+    ) {
+        self.scored += candidates.len();
+        for candidate in candidates {
+            if results.iter().any(|result| result.patient.id == candidate.id) {
+                continue;
+            }
+            let score = matcher.match_patients(patient, candidate).ok().map(|result| result.score);
+            self.filter(stage, Some(candidate.id), score, FilterReason::BelowMatcherThreshold);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, MatchingConfig};
+    use crate::matching::ProbabilisticMatcher;
+    use crate::models::Gender;
+
+    fn patient(family: &str, birth_year: i32) -> Patient {
+        let mut patient = crate::fixtures::patient(family, &["Amara"], Gender::Female);
+        patient.birth_date = chrono::NaiveDate::from_ymd_opt(birth_year, 8, 21);
+        patient
+    }
+
+    #[test]
+    fn test_records_candidates_below_matcher_threshold() {
+        let matcher = ProbabilisticMatcher::new(MatchingConfig {
+            threshold_score: 0.7,
+            ..Config::default().matching
+        });
+        let incoming = patient("Okonkwo", 1991);
+        let candidates = vec![patient("Okonkwo", 1991), patient("Zimmermann", 1958)];
+        let results = matcher.find_matches(&incoming, &candidates).unwrap();
+
+        let mut plan = MatchQueryPlan::new(BlockingStrategy::default());
+        plan.below_matcher_threshold(MatchStage::Blocking, &matcher, &incoming, &candidates, &results);
+
+        assert_eq!(plan.scored, 2);
+        assert_eq!(plan.filtered.len(), 1);
+        let filtered = &plan.filtered[0];
+        assert_eq!(filtered.patient_id, Some(candidates[1].id));
+        assert_eq!(filtered.reason, FilterReason::BelowMatcherThreshold);
+        assert!(filtered.score.is_some_and(|score| score < 0.7));
+        assert_eq!(serde_json::to_value(filtered).unwrap()["stage"], "blocking");
+    }
+}
@@ -1,12 +1,34 @@
-//! API modules for REST, gRPC, and FHIR
+//! API modules for REST, gRPC, FHIR and HL7 v2
 
+pub mod archive;
+pub mod compare;
+pub mod conditional;
+pub mod fields;
+pub mod groups;
+pub mod i18n;
+pub mod match_plan;
+pub mod privacy;
+pub mod survivorship;
+pub mod timeline;
+pub mod quarantine;
 pub mod rest;
 pub mod grpc;
 pub mod fhir;
+pub mod hl7;
+#[cfg(feature = "dicom")]
+pub mod dicom;
 
+use axum::{
+    body::{Body, HttpBody},
+    http::{header, HeaderMap},
+    response::Response,
+};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+/// Largest error body [`rewrite_error_json`] reads; larger ones pass through untouched
+const MAX_ERROR_BODY: usize = 256 * 1024;
+
 /// Standard API response wrapper
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct ApiResponse<T> {
@@ -23,6 +45,16 @@ pub struct ApiError {
     pub details: Option<serde_json::Value>,
 }
 
+/// Error response body, as documented in the OpenAPI spec
+///
+/// Every failing REST endpoint returns an [`ApiResponse`] with no `data`;
+/// this is that shape without the type parameter.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ApiErrorResponse {
+    pub success: bool,
+    pub error: ApiError,
+}
+
 impl<T> ApiResponse<T> {
     /// Create a successful response
     pub fn success(data: T) -> Self {
@@ -45,6 +77,14 @@ impl<T> ApiResponse<T> {
             }),
         }
     }
+
+    /// Attach structured details to an error response
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        if let Some(error) = self.error.as_mut() {
+            error.details = Some(details);
+        }
+        self
+    }
 }
 
 impl<T> From<crate::Error> for ApiResponse<T> {
@@ -60,3 +100,46 @@ impl<T> From<crate::Error> for ApiResponse<T> {
         }
     }
 }
+
+/// Rewrite the JSON body of a failing response, for error-shaping middleware
+///
+/// Successful responses, non-JSON bodies and large bodies are returned as
+/// they are. `rewrite` may change the headers too; `Content-Length` is
+/// dropped since the body changes size.
+pub(crate) async fn rewrite_error_json(
+    response: Response,
+    rewrite: impl FnOnce(&mut serde_json::Value, &mut HeaderMap),
+) -> Response {
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("json"));
+    let small = response
+        .body()
+        .size_hint()
+        .upper()
+        .is_some_and(|len| len <= MAX_ERROR_BODY as u64);
+    if !(response.status().is_client_error() || response.status().is_server_error()) || !is_json || !small {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_ERROR_BODY).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("Failed to read error response: {}", e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    let Ok(mut json) = serde_json::from_slice::<serde_json::Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    rewrite(&mut json, &mut parts.headers);
+    let Ok(rewritten) = serde_json::to_vec(&json) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(rewritten))
+}
@@ -0,0 +1,333 @@
+//! Enforcement of patient confidentiality on reads
+//!
+//! The MPI does not authenticate users itself. The gateway in front of it
+//! passes the user in `X-User-Id` and their roles in `X-User-Roles`, and
+//! `privacy` configuration says which roles see restricted and VIP patients
+//! and which may change a patient's level. Every read of such a patient is
+//! audited as `RESTRICTED_ACCESS`, masked or not, and every unmasked read
+//! raises a `break_the_glass` steward alert.
+
+use std::collections::HashMap;
+
+use axum::http::HeaderMap;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api::rest::AppState;
+use crate::config::{PrivacyConfig, RestrictedSearch, ServerConfig};
+use crate::db::models::DbAuditLog;
+use crate::models::archived_message::ArchivedMessage;
+use crate::models::{Confidentiality, Patient};
+use crate::notifications::{Alert, AlertKind};
+use crate::search::Suggestion;
+
+/// Header naming the user a request is made for
+pub const USER_HEADER: &str = "x-user-id";
+
+/// Header listing the user's roles, separated by commas
+pub const ROLES_HEADER: &str = "x-user-roles";
+
+/// The user a request is made for, as the gateway describes them
+#[derive(Debug, Clone, Default)]
+pub struct Requester {
+    pub user_id: Option<String>,
+    pub roles: Vec<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl Requester {
+    /// Read the requester from request headers
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let value = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+        Self {
+            user_id: value(USER_HEADER),
+            roles: value(ROLES_HEADER)
+                .map(|roles| {
+                    roles
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|role| !role.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            ip_address: value("x-forwarded-for").and_then(|v| v.split(',').next().map(|ip| ip.trim().to_string())),
+            user_agent: value("user-agent"),
+        }
+    }
+
+    fn has_any(&self, roles: &[String]) -> bool {
+        self.roles.iter().any(|role| roles.contains(role))
+    }
+
+    /// Whether the requester sees restricted and VIP patients unmasked
+    pub fn is_privileged(&self, config: &PrivacyConfig) -> bool {
+        self.has_any(&config.privileged_roles)
+    }
+
+    /// Whether the requester may change a patient's confidentiality
+    pub fn is_privacy_officer(&self, config: &PrivacyConfig) -> bool {
+        self.has_any(&config.officer_roles)
+    }
+
+    /// Whether the requester may run administrative operations
+    pub fn is_admin(&self, config: &ServerConfig) -> bool {
+        self.has_any(&config.admin_roles)
+    }
+}
+
+/// How a read shows a patient
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Read {
+    /// Reading one patient by ID
+    Direct,
+    /// Searching or matching, where `privacy.restricted_search` may leave
+    /// the patient out
+    Search,
+}
+
+/// Whether `requester` sees `patient` masked, when shown at all
+pub fn is_masked(config: &PrivacyConfig, requester: &Requester, patient: &Patient) -> bool {
+    patient.confidentiality.is_restricted() && !requester.is_privileged(config)
+}
+
+/// The patient as `requester` may see it, or `None` to leave it out
+///
+/// Reads of restricted and VIP patients are audited; `endpoint` names the
+/// route in the audit entry.
+pub fn view(state: &AppState, requester: &Requester, patient: Patient, read: Read, endpoint: &str) -> Option<Patient> {
+    if !patient.confidentiality.is_restricted() {
+        return Some(patient);
+    }
+    let privileged = requester.is_privileged(&state.config.privacy);
+    let (shown, view) = match (privileged, read, state.config.privacy.restricted_search) {
+        (true, _, _) => ("full", Some(patient.clone())),
+        (false, Read::Search, RestrictedSearch::Exclude) => ("excluded", None),
+        (false, _, _) => ("masked", Some(patient.masked())),
+    };
+
+    let details = json!({
+        "confidentiality": patient.confidentiality,
+        "endpoint": endpoint,
+        "shown": shown,
+        "roles": requester.roles,
+    });
+    if let Err(e) = state.audit_log.log_restricted_access(
+        patient.id,
+        details,
+        requester.user_id.clone(),
+        requester.ip_address.clone(),
+        requester.user_agent.clone(),
+    ) {
+        tracing::warn!("Failed to audit access to restricted patient {}: {}", patient.id, e);
+    }
+    if privileged {
+        state.notifier.notify(
+            Alert::new(AlertKind::BreakTheGlass)
+                .with("patient_id", patient.id)
+                .with("confidentiality", patient.confidentiality)
+                .with("endpoint", endpoint)
+                .with("user", requester.user_id.as_deref().unwrap_or("An unnamed user"))
+                .with("ip_address", requester.ip_address.as_deref().unwrap_or("an unknown address")),
+        );
+    }
+    view
+}
+
+/// Whether `requester` sees patient `id` masked, auditing the read like a
+/// direct read; false when there is no such patient
+pub fn masks_patient(state: &AppState, requester: &Requester, id: &Uuid, endpoint: &str) -> crate::Result<bool> {
+    let Some(patient) = state.patient_repository.get_by_id(id)? else {
+        return Ok(false);
+    };
+    let masked = is_masked(&state.config.privacy, requester, &patient);
+    view(state, requester, patient, Read::Direct, endpoint);
+    Ok(masked)
+}
+
+/// Whether `requester` sees the stored versions of patient `id` masked
+///
+/// They are when the patient is restricted or VIP now, or was in any of
+/// `versions`, since earlier versions give away what a masked read hides.
+pub fn masks_versions<'a>(
+    state: &AppState,
+    requester: &Requester,
+    id: &Uuid,
+    versions: impl IntoIterator<Item = &'a Value>,
+    endpoint: &str,
+) -> crate::Result<bool> {
+    let masked = masks_patient(state, requester, id, endpoint)?;
+    let restricted_version = || {
+        versions.into_iter().any(|version| {
+            version
+                .get("confidentiality")
+                .and_then(Value::as_str)
+                .and_then(Confidentiality::parse)
+                .is_some_and(|confidentiality| confidentiality.is_restricted())
+        })
+    };
+    Ok(masked || (!requester.is_privileged(&state.config.privacy) && restricted_version()))
+}
+
+/// A stored patient version with what a masked read hides removed, or
+/// `None` when it is not a whole patient
+pub fn masked_version(version: &Value) -> Option<Value> {
+    serde_json::from_value::<Patient>(version.clone())
+        .ok()
+        .and_then(|patient| serde_json::to_value(patient.masked()).ok())
+}
+
+/// Audit log entries as `requester` may see them
+///
+/// The old and new values of patients whose versions `requester` sees
+/// masked are masked too. `RESTRICTED_ACCESS` entries hold only who read
+/// what and are left as they are.
+pub fn redact_audit_logs(
+    state: &AppState,
+    requester: &Requester,
+    mut logs: Vec<DbAuditLog>,
+    endpoint: &str,
+) -> crate::Result<Vec<DbAuditLog>> {
+    let is_version = |log: &DbAuditLog| log.entity_type == "Patient" && log.action != "RESTRICTED_ACCESS";
+    let mut masked: HashMap<Uuid, bool> = HashMap::new();
+    for log in logs.iter().filter(|log| is_version(log)) {
+        if masked.contains_key(&log.entity_id) {
+            continue;
+        }
+        let versions = logs
+            .iter()
+            .filter(|other| is_version(other) && other.entity_id == log.entity_id)
+            .flat_map(|other| other.old_values.iter().chain(other.new_values.iter()));
+        masked.insert(log.entity_id, masks_versions(state, requester, &log.entity_id, versions, endpoint)?);
+    }
+    for log in logs.iter_mut() {
+        if is_version(log) && masked.get(&log.entity_id) == Some(&true) {
+            log.old_values = log.old_values.as_ref().and_then(masked_version);
+            log.new_values = log.new_values.as_ref().and_then(masked_version);
+        }
+    }
+    Ok(logs)
+}
+
+/// Archived inbound messages as `requester` may see them
+///
+/// The payloads of patients `requester` sees masked are left empty.
+pub fn redact_messages(
+    state: &AppState,
+    requester: &Requester,
+    mut messages: Vec<ArchivedMessage>,
+    endpoint: &str,
+) -> crate::Result<Vec<ArchivedMessage>> {
+    let mut masked: HashMap<Uuid, bool> = HashMap::new();
+    for message in messages.iter_mut() {
+        let masked = match masked.get(&message.patient_id) {
+            Some(masked) => *masked,
+            None => {
+                let patient_masked = masks_patient(state, requester, &message.patient_id, endpoint)?;
+                *masked.entry(message.patient_id).or_insert(patient_masked)
+            }
+        };
+        if masked {
+            message.payload.clear();
+        }
+    }
+    Ok(messages)
+}
+
+/// How many patients carrying a suggested family name are checked for one
+/// the requester may see
+const SUGGESTION_CHECK_LIMIT: usize = 200;
+
+/// The suggestions `requester` may see
+///
+/// The term dictionary knows nothing of confidentiality, so for requesters
+/// who see restricted and VIP patients masked, each term is looked up and
+/// counts only the patients it does not reveal. Terms carried by restricted
+/// patients alone are left out.
+pub fn visible_suggestions(
+    state: &AppState,
+    requester: &Requester,
+    suggestions: Vec<Suggestion>,
+) -> crate::Result<Vec<Suggestion>> {
+    if requester.is_privileged(&state.config.privacy) {
+        return Ok(suggestions);
+    }
+    let mut visible = Vec::with_capacity(suggestions.len());
+    for mut suggestion in suggestions {
+        let limit = (suggestion.doc_freq as usize).min(SUGGESTION_CHECK_LIMIT);
+        let mut hidden = 0;
+        for id in state.search_engine.search(&suggestion.term, limit)? {
+            let Ok(id) = Uuid::parse_str(&id) else { continue };
+            let Some(patient) = state.patient_repository.get_by_id(&id)? else { continue };
+            let carries_term = patient.name.family.to_lowercase().contains(&suggestion.term);
+            if carries_term && patient.confidentiality.is_restricted() {
+                hidden += 1;
+            }
+        }
+        suggestion.doc_freq = suggestion.doc_freq.saturating_sub(hidden);
+        if suggestion.doc_freq > 0 {
+            visible.push(suggestion);
+        }
+    }
+    Ok(visible)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_requester_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_HEADER, HeaderValue::from_static("jdoe"));
+        headers.insert(ROLES_HEADER, HeaderValue::from_static("registrar, privacy-officer,"));
+        headers.insert("x-forwarded-for", HeaderValue::from_static("10.1.2.3, 10.0.0.1"));
+        let requester = Requester::from_headers(&headers);
+
+        assert_eq!(requester.user_id.as_deref(), Some("jdoe"));
+        assert_eq!(requester.roles, vec!["registrar", "privacy-officer"]);
+        assert_eq!(requester.ip_address.as_deref(), Some("10.1.2.3"));
+
+        let config = PrivacyConfig::default();
+        assert!(requester.is_privileged(&config));
+        assert!(requester.is_privacy_officer(&config));
+        assert!(!Requester::from_headers(&HeaderMap::new()).is_privileged(&config));
+        assert!(!requester.is_admin(&crate::config::Config::default().server));
+    }
+
+    #[test]
+    fn test_is_masked() {
+        use crate::models::{Confidentiality, Gender, HumanName};
+
+        let config = PrivacyConfig::default();
+        let mut patient = Patient::new(
+            HumanName {
+                use_type: None,
+                family: "Okafor".to_string(),
+                given: vec!["Ada".to_string()],
+                prefix: vec![],
+                suffix: vec![],
+            },
+            Gender::Female,
+        );
+        let clerk = Requester::default();
+        let officer = Requester {
+            roles: config.privileged_roles.clone(),
+            ..Requester::default()
+        };
+
+        assert!(!is_masked(&config, &clerk, &patient));
+        patient.confidentiality = Confidentiality::Vip;
+        assert!(is_masked(&config, &clerk, &patient));
+        assert!(!is_masked(&config, &officer, &patient));
+    }
+}
@@ -0,0 +1,124 @@
+//! Quarantine of inbound records refused by validation
+//!
+//! HL7 v2 messages answered `AE` and FHIR Patient resources refused with
+//! 400, 409 or 422 are parked with the reasons they were refused instead of
+//! being dropped. A data steward lists them, corrects the payload and
+//! [`resubmit`]s it through the path it first took, or discards it.
+
+use axum::{http::{HeaderMap, StatusCode}, Json};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::api::fhir::handlers::store_patient;
+use crate::api::fhir::FhirOperationOutcome;
+use crate::api::hl7::{self, ErrorCode, ErrorDetail};
+use crate::api::rest::AppState;
+use crate::db::QuarantineRepository;
+use crate::models::archived_message::{CHANNEL_FHIR, CHANNEL_HL7};
+use crate::models::QuarantinedRecord;
+use crate::Result;
+
+/// Whether a FHIR error status refuses the resource for its content
+///
+/// Server-side failures are not quarantined; the sender retries those.
+pub fn is_refusal(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_REQUEST | StatusCode::CONFLICT | StatusCode::UNPROCESSABLE_ENTITY
+    )
+}
+
+/// Quarantine a refused payload
+///
+/// Failures are logged; the sender is told of the refusal either way.
+pub fn quarantine(
+    repo: &dyn QuarantineRepository,
+    channel: &str,
+    content_type: &str,
+    payload: &str,
+    errors: &Value,
+    patient_id: Option<Uuid>,
+) {
+    match repo.quarantine(channel, content_type, payload, errors, patient_id) {
+        Ok(record) => tracing::info!("Quarantined refused {} payload as {}", channel, record.id),
+        Err(e) => tracing::warn!("Failed to quarantine refused {} payload: {}", channel, e),
+    }
+}
+
+/// Outcome of resubmitting a quarantined payload
+#[derive(Debug, Clone)]
+pub enum Resubmission {
+    /// Stored as this patient
+    Stored(Uuid),
+    /// Refused again, for these reasons
+    Refused(Value),
+}
+
+/// Submit a (corrected) payload for a quarantined record through the
+/// channel it arrived on
+///
+/// Stored payloads are archived like any other. Server-side failures are
+/// errors, so the record stays as it was.
+pub fn resubmit(state: &AppState, record: &QuarantinedRecord, payload: &str) -> Result<Resubmission> {
+    match record.channel.as_str() {
+        CHANNEL_HL7 => {
+            let ingested = hl7::ingest(payload, state);
+            if let Some(patient_id) = ingested.patient_id {
+                return Ok(Resubmission::Stored(patient_id));
+            }
+            if let Some(failure) = ingested
+                .ack
+                .errors
+                .iter()
+                .find(|e| e.code == ErrorCode::ApplicationInternalError)
+            {
+                return Err(crate::Error::Api(failure.message.clone()));
+            }
+            let errors = ingested.ack.errors.iter().map(ErrorDetail::to_json).collect();
+            Ok(Resubmission::Refused(Value::Array(errors)))
+        }
+        CHANNEL_FHIR => {
+            let body: Value = match serde_json::from_str(payload) {
+                Ok(body) => body,
+                Err(e) => {
+                    let outcome = FhirOperationOutcome::invalid(&format!("Invalid JSON: {}", e));
+                    return Ok(Resubmission::Refused(serde_json::to_value(outcome).unwrap_or_default()));
+                }
+            };
+            let rules = state.identifier_rules();
+            // The original request headers are gone; the resource's meta.source remains
+            match store_patient(state, &rules, &body, state.config.fhir.handling, record.patient_id, &HeaderMap::new()) {
+                Ok(stored) if stored.resubmitted => Ok(Resubmission::Stored(stored.patient.id)),
+                Ok(stored) => {
+                    let action = if record.patient_id.is_some() { "UPDATE" } else { "CREATE" };
+                    crate::api::archive::archive(
+                        state.message_archive.as_ref(),
+                        stored.patient.id,
+                        action,
+                        CHANNEL_FHIR,
+                        &record.content_type,
+                        payload.as_bytes(),
+                    );
+                    Ok(Resubmission::Stored(stored.patient.id))
+                }
+                Err((status, Json(outcome))) if is_refusal(status) => Ok(Resubmission::Refused(outcome)),
+                Err((_, Json(outcome))) => Err(crate::Error::Fhir(outcome.to_string())),
+            }
+        }
+        other => Err(crate::Error::Validation(format!("Unknown quarantine channel '{}'", other))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_content_refusals_are_quarantined() {
+        assert!(is_refusal(StatusCode::BAD_REQUEST));
+        assert!(is_refusal(StatusCode::CONFLICT));
+        assert!(is_refusal(StatusCode::UNPROCESSABLE_ENTITY));
+        assert!(!is_refusal(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(!is_refusal(StatusCode::LOCKED));
+    }
+}
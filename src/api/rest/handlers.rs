@@ -1,21 +1,48 @@
 //! REST API request handlers
 
+use std::time::Instant;
+
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
     Json,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use utoipa::ToSchema;
 use chrono::Datelike;
 
-use crate::models::Patient;
-use crate::api::{ApiResponse, ApiError};
-use crate::matching::MatchResult;
+use crate::models::{
+    ArchivedMessage, AssigningAuthority, ChangeRequest, Confidentiality, DemographicChanges, DuplicateCandidate,
+    Identifier, IdentifierType, Patient, PatientGroup, PatientStatus, Practitioner, QuarantinedRecord, RecordLock, ReviewAction,
+    ReviewDecision, ReviewReason, VerificationStatus,
+};
+use crate::models::duplicate_candidate::{DUPLICATE_CONFIRMED, PENDING_REVIEW};
+use crate::models::change_request::{CHANGE_APPROVED, CHANGE_PENDING, CHANGE_REJECTED, CHANGE_REQUEST_SOURCE};
+use crate::models::archived_message::CHANNEL_REST;
+use crate::models::quarantined_record::{QUARANTINE_DISCARDED, QUARANTINE_PENDING, QUARANTINE_RESUBMITTED};
+use crate::api::archive::{self, WithRawBody};
+use crate::api::compare::PatientComparison;
+use crate::api::quarantine::{self, Resubmission};
+use crate::api::privacy::{self, Read, Requester};
+use crate::api::{survivorship, timeline};
+use crate::api::match_plan::{FilterReason, MatchQueryPlan};
+use crate::api::{conditional, fields, ApiResponse};
+use crate::matching::evaluation::{write_pairs_csv, LabeledPair};
+use crate::matching::{
+    BatchMatchRecord, BatchMatchResult, BatchMatchSummary, BatchMatcher, BatchOptions, MatchResult, PractitionerMatch,
+    PractitionerMatcher,
+};
+use crate::observability::metrics::MatchStage;
+use crate::validation::{assign_mrn, issue_mrn};
+use super::negotiation::{self, ResponseFormat};
 use super::state::AppState;
 
+/// Response a helper hands back for its handler to return, boxed to keep
+/// the helper's `Result` small
+pub(crate) type HandlerError<T> = Box<(StatusCode, Json<ApiResponse<T>>)>;
+
 /// Health check response
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct HealthResponse {
@@ -41,6 +68,61 @@ pub async fn health_check() -> impl IntoResponse {
     })
 }
 
+/// Readiness check response
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReadinessResponse {
+    /// "ready", or "degraded" while any circuit breaker is not closed
+    pub status: String,
+    /// State of each circuit breaker around an external dependency
+    pub breakers: Vec<crate::circuit_breaker::BreakerStatus>,
+}
+
+/// Readiness check endpoint
+///
+/// A degraded instance still serves requests: events and webhooks behind an
+/// open breaker are dropped rather than delaying callers, so the status is
+/// reported without failing the check.
+#[utoipa::path(
+    get,
+    path = "/api/v1/ready",
+    tag = "health",
+    responses(
+        (status = 200, description = "Service is ready", body = ReadinessResponse)
+    )
+)]
+pub async fn readiness_check(State(state): State<AppState>) -> impl IntoResponse {
+    let breakers = state.breakers.statuses();
+    let degraded = breakers
+        .iter()
+        .any(|breaker| breaker.state != crate::circuit_breaker::BreakerState::Closed);
+    Json(ReadinessResponse {
+        status: if degraded { "degraded" } else { "ready" }.to_string(),
+        breakers,
+    })
+}
+
+/// Prometheus metrics endpoint
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "health",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", body = String, content_type = "text/plain"),
+        (status = 500, description = "Metrics could not be encoded")
+    )
+)]
+pub async fn prometheus_metrics(State(state): State<AppState>) -> Response {
+    match state.metrics.render() {
+        Ok(text) => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+            text,
+        )
+            .into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
 /// Create patient request
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct CreatePatientRequest {
@@ -49,28 +131,88 @@ pub struct CreatePatientRequest {
 }
 
 /// Create a new patient
+///
+/// A patient with the same normalized name, birth date, sex and SSN last
+/// four as a stored patient is taken as a resubmission: the stored patient
+/// is returned and nothing is created, as it is for a patient whose ID,
+/// derived through `identifiers.id_namespaces`, is already on file. The
+/// stored patient is masked as it is for `GET`. A patient whose derived ID
+/// belongs to a soft-deleted patient is refused with 409 until that patient
+/// is purged. A patient without an MRN is given one when
+/// `identifiers.assign_mrn` names a sequence.
 #[utoipa::path(
     post,
     path = "/api/v1/patients",
     tag = "patients",
     request_body = Patient,
     responses(
+        (status = 200, description = "Resubmission of a stored patient, which is returned"),
         (status = 201, description = "Patient created successfully"),
-        (status = 500, description = "Internal server error")
+        (status = 400, description = "Malformed identifiers, listed per field in `details`", body = crate::api::ApiErrorResponse),
+        (status = 409, description = "An MRN already belongs to another patient under the same assigning authority, or the derived ID to a deleted patient", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
     )
 )]
 pub async fn create_patient(
     State(state): State<AppState>,
-    Json(mut payload): Json<Patient>,
+    headers: HeaderMap,
+    WithRawBody(Json(mut payload), raw): WithRawBody<Json<Patient>>,
 ) -> impl IntoResponse {
-    // Ensure patient has a UUID
-    if payload.id == Uuid::nil() {
-        payload.id = Uuid::new_v4();
+    // Ensure patient has a UUID; a replayed source record gets its patient back
+    match crate::validation::assign_patient_id(&state.config.identifiers, state.patient_repository.as_ref(), &mut payload) {
+        Ok(Some(existing)) => {
+            tracing::info!("Source record of patient {} replayed; nothing created", existing.id);
+            let requester = Requester::from_headers(&headers);
+            let Some(existing) = privacy::view(&state, &requester, existing, Read::Direct, "POST /api/v1/patients") else {
+                unreachable!("direct reads are masked, never excluded");
+            };
+            return (StatusCode::OK, Json(ApiResponse::success(existing)));
+        }
+        Ok(None) => {}
+        Err(e @ crate::Error::PatientDeleted(_)) => {
+            let error = ApiResponse::<Patient>::error(
+                "CONFLICT",
+                format!("{}; the source record cannot be created again until that patient is purged", e)
+            );
+            return (StatusCode::CONFLICT, Json(error));
+        }
+        Err(e) => {
+            let error = ApiResponse::<Patient>::error("DATABASE_ERROR", format!("Failed to look up patient: {}", e));
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+        }
+    }
+    crate::matching::refresh_photo_hashes(&mut payload);
+
+    // A resubmission of a stored patient gets that patient back
+    match crate::matching::find_resubmitted(&payload, state.patient_repository.as_ref()) {
+        Ok(Some(existing)) => {
+            tracing::info!("Patient {} resubmitted; nothing created", existing.id);
+            let requester = Requester::from_headers(&headers);
+            let Some(existing) = privacy::view(&state, &requester, existing, Read::Direct, "POST /api/v1/patients") else {
+                unreachable!("direct reads are masked, never excluded");
+            };
+            return (StatusCode::OK, Json(ApiResponse::success(existing)));
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Fingerprint lookup failed: {}", e),
+    }
+
+    if let Err(response) = check_identifiers(&state, &payload) {
+        return *response;
+    }
+    if let Err(e) = assign_mrn(state.mrn_sequences.as_ref(), &state.config.identifiers, &mut payload) {
+        let error = ApiResponse::<Patient>::error("INTERNAL_ERROR", format!("Failed to assign an MRN: {}", e));
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
     }
 
-    // Insert into database
-    match state.patient_repository.create(&payload) {
+    // Insert into database, keeping the submission as a source record
+    let source = survivorship::source_from_headers(&headers).unwrap_or_else(|| CHANNEL_REST.to_string());
+    let submission = survivorship::submission_from_headers(&headers, &source, &payload);
+    match state.patient_repository.create_from_source(&payload, &submission) {
         Ok(patient) => {
+            archive::archive(state.message_archive.as_ref(), patient.id, "CREATE", CHANNEL_REST, &raw.content_type, &raw.bytes);
+            survivorship::record(&state, patient.id, None, &patient, &source);
+
             // Index in search engine
             if let Err(e) = state.search_engine.index_patient(&patient) {
                 tracing::warn!("Failed to index patient in search engine: {}", e);
@@ -88,456 +230,5935 @@ pub async fn create_patient(
     }
 }
 
+/// Sparse fieldset query parameters
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FieldsQuery {
+    /// Comma-separated top-level fields to return, e.g. `name,birth_date,identifiers`;
+    /// `id` is always returned
+    pub fields: Option<String>,
+
+    /// Include the source of each demographic field's current value
+    #[serde(default)]
+    pub provenance: bool,
+}
+
 /// Get a patient by ID
+///
+/// With `fields`, only those fields of the patient are returned. With
+/// `provenance=true`, a `provenance` object gives the source and time of
+/// each demographic field's current value.
 #[utoipa::path(
     get,
     path = "/api/v1/patients/{id}",
     tag = "patients",
     params(
-        ("id" = Uuid, Path, description = "Patient UUID")
+        ("id" = Uuid, Path, description = "Patient UUID"),
+        FieldsQuery
     ),
     responses(
-        (status = 200, description = "Patient found"),
-        (status = 404, description = "Patient not found"),
-        (status = 500, description = "Internal server error")
+        (status = 200, description = "Patient found", headers(("ETag" = String, description = "Weak validator for If-None-Match"))),
+        (status = 304, description = "Patient unchanged since the ETag in If-None-Match"),
+        (status = 404, description = "Patient not found", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
     )
 )]
 pub async fn get_patient(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Path(id): Path<Uuid>,
-) -> impl IntoResponse {
+    Query(query): Query<FieldsQuery>,
+) -> Response {
     match state.patient_repository.get_by_id(&id) {
         Ok(Some(patient)) => {
-            (StatusCode::OK, Json(ApiResponse::success(patient)))
+            let requester = Requester::from_headers(&headers);
+            let masked = privacy::is_masked(&state.config.privacy, &requester, &patient);
+            let Some(patient) = privacy::view(&state, &requester, patient, Read::Direct, "GET /api/v1/patients/{id}") else {
+                unreachable!("direct reads are masked, never excluded");
+            };
+            let etag = conditional::patient_etag(&patient, masked);
+            if conditional::if_none_match(&headers, &etag) {
+                return conditional::not_modified(etag);
+            }
+            let selected = fields::parse(query.fields.as_deref());
+            if selected.is_none() && !query.provenance {
+                return (StatusCode::OK, [(header::ETAG, etag)], Json(ApiResponse::success(patient))).into_response();
+            }
+
+            let mut record = serde_json::to_value(&patient).unwrap_or_default();
+            if let Some(selected) = &selected {
+                fields::select_rest(&mut record, selected);
+            }
+            // A masked patient's sources would say what was masked
+            if query.provenance && !masked {
+                match state.field_provenance.list_for_patient(&id) {
+                    Ok(provenance) => {
+                        let provenance: serde_json::Map<String, serde_json::Value> = provenance
+                            .into_iter()
+                            .filter(|p| selected.as_ref().is_none_or(|selected| selected.contains(&p.field)))
+                            .map(|p| (p.field.clone(), serde_json::to_value(p).unwrap_or_default()))
+                            .collect();
+                        record["provenance"] = serde_json::Value::Object(provenance);
+                    }
+                    Err(e) => {
+                        let error = ApiResponse::<Patient>::error(
+                            "DATABASE_ERROR",
+                            format!("Failed to retrieve field provenance: {}", e)
+                        );
+                        return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+                    }
+                }
+            }
+            (StatusCode::OK, [(header::ETAG, etag)], Json(ApiResponse::success(record))).into_response()
         }
         Ok(None) => {
             let error = ApiResponse::<Patient>::error(
                 "NOT_FOUND",
                 format!("Patient with id '{}' not found", id)
             );
-            (StatusCode::NOT_FOUND, Json(error))
+            (StatusCode::NOT_FOUND, Json(error)).into_response()
         }
         Err(e) => {
             let error = ApiResponse::<Patient>::error(
                 "DATABASE_ERROR",
                 format!("Failed to retrieve patient: {}", e)
             );
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
         }
     }
 }
 
-/// Update a patient
+/// Patient list query parameters
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PatientListQuery {
+    /// SCIM filter expression, e.g. `family eq "Smith" and birthDate ge "1980-01-01"`
+    #[serde(rename = "_filter")]
+    pub filter: Option<String>,
+
+    /// Maximum number of patients (default: 10, max: 100)
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+
+    /// Number of patients to skip
+    #[serde(default)]
+    pub offset: usize,
+}
+
+/// List active patients, oldest first
+///
+/// `_filter` takes a SCIM-style expression over `id`, `family`, `given`,
+/// `name`, `birthDate`, `gender`, `active`, `deceased`, `identifier`,
+/// `phone`, `email`, `city`, `state` and `postalCode`. A filter naming
+/// `active` also lists inactive patients. Restricted and VIP patients are
+/// masked or left out as in search.
 #[utoipa::path(
-    put,
-    path = "/api/v1/patients/{id}",
+    get,
+    path = "/api/v1/patients",
     tag = "patients",
-    params(
-        ("id" = Uuid, Path, description = "Patient UUID")
-    ),
-    request_body = Patient,
+    params(PatientListQuery),
     responses(
-        (status = 200, description = "Patient updated successfully"),
-        (status = 500, description = "Internal server error")
+        (status = 200, description = "Patients", body = Vec<Patient>),
+        (status = 400, description = "Malformed filter expression", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
     )
 )]
-pub async fn update_patient(
+pub async fn list_patients(
     State(state): State<AppState>,
-    Path(id): Path<Uuid>,
-    Json(mut payload): Json<Patient>,
+    headers: HeaderMap,
+    Query(query): Query<PatientListQuery>,
 ) -> impl IntoResponse {
-    // Ensure ID in path matches payload
-    payload.id = id;
-
-    match state.patient_repository.update(&payload) {
-        Ok(patient) => {
-            // Update search index
-            if let Err(e) = state.search_engine.index_patient(&patient) {
-                tracing::warn!("Failed to update patient in search engine: {}", e);
+    let limit = query.limit.clamp(1, 100);
+    let patients = match query.filter.as_deref().map(str::trim).filter(|f| !f.is_empty()) {
+        Some(expression) => match crate::search::PatientFilter::parse(expression) {
+            Ok(filter) => filter.find(state.patient_repository.as_ref(), limit, query.offset),
+            Err(e) => {
+                let error = ApiResponse::<Vec<Patient>>::error("VALIDATION_ERROR", e.to_string());
+                return (StatusCode::BAD_REQUEST, Json(error));
             }
+        },
+        None => state.patient_repository.list_active(limit as i64, query.offset as i64),
+    };
 
-            (StatusCode::OK, Json(ApiResponse::success(patient)))
+    match patients {
+        Ok(patients) => {
+            let requester = Requester::from_headers(&headers);
+            let patients: Vec<Patient> = patients
+                .into_iter()
+                .filter_map(|patient| privacy::view(&state, &requester, patient, Read::Search, "GET /api/v1/patients"))
+                .collect();
+            (StatusCode::OK, Json(ApiResponse::success(patients)))
         }
         Err(e) => {
-            let error = ApiResponse::<Patient>::error(
+            let error = ApiResponse::<Vec<Patient>>::error(
                 "DATABASE_ERROR",
-                format!("Failed to update patient: {}", e)
+                format!("Failed to list patients: {}", e)
             );
             (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
         }
     }
 }
 
-/// Delete a patient (soft delete)
+/// Audit entries included in a patient summary
+const SUMMARY_AUDIT_LIMIT: i64 = 20;
+
+/// Scored pairs included in a patient summary
+const SUMMARY_SCORE_LIMIT: i64 = 50;
+
+/// A source record contributing to the enterprise record
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ContributingRecord {
+    /// The record as the source submitted it
+    pub record: crate::models::SourceRecord,
+    /// Its current link to this patient
+    pub link: Option<crate::models::SourceRecordLink>,
+}
+
+/// A link to another enterprise record
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LinkSummary {
+    pub other_patient_id: Uuid,
+    pub link_type: crate::models::LinkType,
+    /// Most recent match score of the pair, if it was scored
+    pub confidence: Option<f64>,
+}
+
+/// Work waiting on a steward for this patient
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct ReviewTasks {
+    /// Possible duplicate pairs awaiting review
+    pub duplicates: Vec<DuplicateCandidate>,
+    /// Steward lock on the record, if held
+    pub lock: Option<RecordLock>,
+}
+
+/// A previous score of this patient against another
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MatchScoreEntry {
+    /// The other patient of the pair
+    pub other_patient_id: Uuid,
+    pub score: f64,
+    pub breakdown: crate::matching::MatchScoreBreakdown,
+    pub calculated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Everything a steward detail screen shows about one patient
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PatientSummary {
+    pub patient: Patient,
+    /// Source records the enterprise record is composed from
+    pub source_records: Vec<ContributingRecord>,
+    pub links: Vec<LinkSummary>,
+    /// Most recent audit entries, newest first
+    #[schema(value_type = Vec<Object>)]
+    pub audit: Vec<crate::db::models::DbAuditLog>,
+    pub review: ReviewTasks,
+    /// Most recent scored pairs, newest first
+    pub match_scores: Vec<MatchScoreEntry>,
+    /// Sections that could not be loaded and are left empty
+    pub unavailable: Vec<String>,
+}
+
+/// Get a patient with its source records, links, audit trail, review tasks
+/// and match score history
+///
+/// Sections that fail to load are left empty and named in `unavailable`,
+/// so one slow or missing store does not hide the rest.
+/// A restricted or VIP patient the requester sees masked comes with its
+/// lock only.
 #[utoipa::path(
-    delete,
-    path = "/api/v1/patients/{id}",
+    get,
+    path = "/api/v1/patients/{id}/summary",
     tag = "patients",
     params(
         ("id" = Uuid, Path, description = "Patient UUID")
     ),
     responses(
-        (status = 204, description = "Patient deleted successfully"),
-        (status = 500, description = "Internal server error")
+        (status = 200, description = "Patient summary", body = PatientSummary),
+        (status = 404, description = "Patient not found", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
     )
 )]
-pub async fn delete_patient(
+pub async fn get_patient_summary(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Path(id): Path<Uuid>,
 ) -> impl IntoResponse {
-    match state.patient_repository.delete(&id) {
-        Ok(()) => {
-            // Remove from search index
-            if let Err(e) = state.search_engine.delete_patient(&id.to_string()) {
-                tracing::warn!("Failed to delete patient from search engine: {}", e);
-            }
-
-            (StatusCode::NO_CONTENT, Json(ApiResponse::<()>::success(())))
+    let patient = match state.patient_repository.get_by_id(&id) {
+        Ok(Some(patient)) => patient,
+        Ok(None) => {
+            let error = ApiResponse::<PatientSummary>::error(
+                "NOT_FOUND",
+                format!("Patient with id '{}' not found", id)
+            );
+            return (StatusCode::NOT_FOUND, Json(error));
         }
         Err(e) => {
-            let error = ApiResponse::<()>::error(
+            let error = ApiResponse::<PatientSummary>::error(
                 "DATABASE_ERROR",
-                format!("Failed to delete patient: {}", e)
+                format!("Failed to retrieve patient: {}", e)
             );
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+        }
+    };
+    let requester = Requester::from_headers(&headers);
+    // A masked patient's source records, audit trail, scored pairs and
+    // duplicate pairs would say what was masked, so only its lock is shown
+    let masked = privacy::is_masked(&state.config.privacy, &requester, &patient);
+    let Some(patient) = privacy::view(&state, &requester, patient, Read::Direct, "GET /api/v1/patients/{id}/summary") else {
+        unreachable!("direct reads are masked, never excluded");
+    };
+
+    let mut unavailable = Vec::new();
+    let mut section = |name: &str, e: crate::Error| {
+        tracing::warn!("Patient summary of {} is missing {}: {}", id, name, e);
+        unavailable.push(name.to_string());
+    };
+
+    let mut source_records = Vec::new();
+    let records = match masked {
+        true => Ok(Vec::new()),
+        false => state.source_records.list_for_patient(&id),
+    };
+    match records {
+        Ok(records) => {
+            for record in records {
+                let link = match state.source_records.current_link(&record.id) {
+                    Ok(link) => link,
+                    Err(e) => {
+                        section("source_records", e);
+                        None
+                    }
+                };
+                source_records.push(ContributingRecord { record, link });
+            }
         }
+        Err(e) => section("source_records", e),
     }
-}
 
-/// Search query parameters
-#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
-pub struct SearchQuery {
-    /// Search query string
-    pub q: String,
+    let scores = match masked {
+        true => Ok(Vec::new()),
+        false => state.match_scores.list_for_patient(&id, SUMMARY_SCORE_LIMIT),
+    };
+    let match_scores: Vec<MatchScoreEntry> = match scores {
+        Ok(scores) => scores
+            .into_iter()
+            .map(|row| {
+                let value = |v: Option<bigdecimal::BigDecimal>| {
+                    v.and_then(|v| bigdecimal::ToPrimitive::to_f64(&v)).unwrap_or(0.0)
+                };
+                MatchScoreEntry {
+                    other_patient_id: if row.patient_id == id { row.candidate_id } else { row.patient_id },
+                    score: bigdecimal::ToPrimitive::to_f64(&row.total_score).unwrap_or(0.0),
+                    breakdown: crate::matching::MatchScoreBreakdown {
+                        name_score: value(row.name_score),
+                        birth_date_score: value(row.birth_date_score),
+                        gender_score: value(row.gender_score),
+                        address_score: value(row.address_score),
+                        identifier_score: value(row.identifier_score),
+                        names_transposed: false,
+                        photo_score: None,
+                    },
+                    calculated_at: row.calculated_at,
+                }
+            })
+            .collect(),
+        Err(e) => {
+            section("match_scores", e);
+            Vec::new()
+        }
+    };
 
-    /// Maximum number of results (default: 10, max: 100)
-    #[serde(default = "default_limit")]
-    pub limit: usize,
+    let links = patient
+        .links
+        .iter()
+        .map(|link| LinkSummary {
+            other_patient_id: link.other_patient_id,
+            link_type: link.link_type.clone(),
+            confidence: match_scores
+                .iter()
+                .find(|entry| entry.other_patient_id == link.other_patient_id)
+                .map(|entry| entry.score),
+        })
+        .collect();
 
-    /// Use fuzzy search
-    #[serde(default)]
-    pub fuzzy: bool,
+    let audit = match masked {
+        true => Ok(Vec::new()),
+        false => state.audit_log.get_logs_for_entity("Patient", id, SUMMARY_AUDIT_LIMIT),
+    };
+    let audit = audit.unwrap_or_else(|e| {
+        section("audit", e);
+        Vec::new()
+    });
+
+    let mut review = ReviewTasks::default();
+    let duplicates = match masked {
+        true => Ok(Vec::new()),
+        false => state.duplicates.list_pending_for_patient(&id),
+    };
+    match duplicates {
+        Ok(duplicates) => review.duplicates = duplicates,
+        Err(e) => section("review.duplicates", e),
+    }
+    match state.record_locks.get(&id) {
+        Ok(lock) => review.lock = lock,
+        Err(e) => section("review.lock", e),
+    }
+
+    let summary = PatientSummary {
+        patient,
+        source_records,
+        links,
+        audit,
+        review,
+        match_scores,
+        unavailable,
+    };
+    (StatusCode::OK, Json(ApiResponse::success(summary)))
 }
 
-fn default_limit() -> usize {
-    10
+/// Timeline query parameters
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TimelineQuery {
+    /// Most recent audit entries to build from (default: 100, max: 1000)
+    #[serde(default = "default_timeline_limit")]
+    pub limit: i64,
 }
 
-/// Search results response
+fn default_timeline_limit() -> i64 {
+    100
+}
+
+/// A patient's demographic changes, oldest first
 #[derive(Debug, Serialize, ToSchema)]
-pub struct SearchResponse {
-    pub patients: Vec<Patient>,
-    pub total: usize,
-    pub query: String,
+pub struct PatientTimeline {
+    pub patient_id: Uuid,
+    pub entries: Vec<timeline::TimelineEntry>,
 }
 
-/// Search for patients
+/// Get a timeline of a patient's demographic changes
+///
+/// Rebuilt from the patient's audited versions: each entry says what changed
+/// (created, name or address changed, identifier added, merged, ...) and the
+/// demographics after it. For a restricted patient, a requester without a
+/// privileged role sees only the kinds of change and when they happened.
 #[utoipa::path(
     get,
-    path = "/api/v1/patients/search",
-    tag = "search",
-    params(SearchQuery),
+    path = "/api/v1/patients/{id}/timeline",
+    tag = "patients",
+    params(
+        ("id" = Uuid, Path, description = "Patient UUID"),
+        TimelineQuery
+    ),
     responses(
-        (status = 200, description = "Search results", body = SearchResponse),
-        (status = 500, description = "Search error")
+        (status = 200, description = "Patient timeline", body = PatientTimeline),
+        (status = 404, description = "Patient not found", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
     )
 )]
-pub async fn search_patients(
+pub async fn get_patient_timeline(
     State(state): State<AppState>,
-    Query(params): Query<SearchQuery>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    Query(query): Query<TimelineQuery>,
 ) -> impl IntoResponse {
-    // Limit to max 100 results
-    let limit = params.limit.min(100);
-
-    // Perform search using search engine
-    let patient_ids = if params.fuzzy {
-        state.search_engine.fuzzy_search(&params.q, limit)
-    } else {
-        state.search_engine.search(&params.q, limit)
+    let patient = match state.patient_repository.get_by_id(&id) {
+        Ok(Some(patient)) => patient,
+        Ok(None) => {
+            let error = ApiResponse::<PatientTimeline>::error(
+                "NOT_FOUND",
+                format!("Patient with id '{}' not found", id)
+            );
+            return (StatusCode::NOT_FOUND, Json(error));
+        }
+        Err(e) => {
+            let error = ApiResponse::<PatientTimeline>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve patient: {}", e)
+            );
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+        }
     };
 
-    match patient_ids {
-        Ok(ids) => {
-            // Fetch full patient records from database
-            let mut patients = Vec::new();
-            for patient_id_str in ids {
-                // Parse string ID to UUID
-                let patient_id = match Uuid::parse_str(&patient_id_str) {
-                    Ok(id) => id,
-                    Err(e) => {
-                        tracing::error!("Failed to parse patient ID {}: {}", patient_id_str, e);
-                        continue;
-                    }
-                };
+    let requester = Requester::from_headers(&headers);
+    let masked = privacy::is_masked(&state.config.privacy, &requester, &patient);
+    if privacy::view(&state, &requester, patient, Read::Direct, "GET /api/v1/patients/{id}/timeline").is_none() {
+        let error = ApiResponse::<PatientTimeline>::error(
+            "NOT_FOUND",
+            format!("Patient with id '{}' not found", id)
+        );
+        return (StatusCode::NOT_FOUND, Json(error));
+    }
 
-                match state.patient_repository.get_by_id(&patient_id) {
-                    Ok(Some(patient)) => patients.push(patient),
-                    Ok(None) => {
-                        tracing::warn!("Patient {} found in search index but not in database", patient_id);
-                    }
-                    Err(e) => {
-                        tracing::error!("Failed to fetch patient {}: {}", patient_id, e);
-                    }
-                }
+    match state.audit_log.get_logs_for_entity("Patient", id, query.limit.clamp(1, 1000)) {
+        Ok(logs) => {
+            let mut entries = timeline::build(&logs);
+            if masked {
+                entries.iter_mut().for_each(timeline::TimelineEntry::redact);
             }
-
-            let response = SearchResponse {
-                total: patients.len(),
-                patients,
-                query: params.q,
-            };
-            (StatusCode::OK, Json(ApiResponse::success(response)))
+            (StatusCode::OK, Json(ApiResponse::success(PatientTimeline { patient_id: id, entries })))
         }
         Err(e) => {
-            let error = ApiResponse::<SearchResponse>::error(
-                "SEARCH_ERROR",
-                format!("Search failed: {}", e)
+            let error = ApiResponse::<PatientTimeline>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve patient history: {}", e)
             );
             (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
         }
     }
 }
 
-/// Match request payload
-#[derive(Debug, Deserialize, ToSchema)]
-pub struct MatchRequest {
-    /// Patient to match against existing records
-    #[serde(flatten)]
-    pub patient: Patient,
-
-    /// Minimum match score threshold (0.0 to 1.0)
-    #[serde(default)]
-    pub threshold: Option<f64>,
-
-    /// Maximum number of matches to return
-    #[serde(default = "default_match_limit")]
-    pub limit: usize,
-}
-
-fn default_match_limit() -> usize {
-    10
+/// Patient comparison query parameters
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CompareQuery {
+    /// First patient
+    pub a: Uuid,
+    /// Second patient
+    pub b: Uuid,
 }
 
-/// Match result with score
+/// Compare two patients field by field
+///
+/// Each demographic field of both records is listed side by side with
+/// whether they agree and, for the fields the matcher weighs, its score,
+/// for steward review. Restricted patients are masked for requesters
+/// without a privileged role, and are scored as masked.
+#[utoipa::path(
+    get,
+    path = "/api/v1/patients/compare",
+    tag = "patients",
+    params(CompareQuery),
+    responses(
+        (status = 200, description = "Field-by-field comparison", body = crate::api::compare::PatientComparison),
+        (status = 400, description = "Both IDs name the same patient", body = crate::api::ApiErrorResponse),
+        (status = 404, description = "Patient not found", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn compare_patients(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<CompareQuery>,
+) -> impl IntoResponse {
+    if query.a == query.b {
+        let error = ApiResponse::<PatientComparison>::error(
+            "VALIDATION_ERROR",
+            "a and b must name different patients".to_string()
+        );
+        return (StatusCode::BAD_REQUEST, Json(error));
+    }
+
+    let requester = Requester::from_headers(&headers);
+    let mut records = Vec::with_capacity(2);
+    for id in [query.a, query.b] {
+        let patient = match state.patient_repository.get_by_id(&id) {
+            Ok(Some(patient)) => patient,
+            Ok(None) => {
+                let error = ApiResponse::<PatientComparison>::error(
+                    "NOT_FOUND",
+                    format!("Patient with id '{}' not found", id)
+                );
+                return (StatusCode::NOT_FOUND, Json(error));
+            }
+            Err(e) => {
+                let error = ApiResponse::<PatientComparison>::error(
+                    "DATABASE_ERROR",
+                    format!("Failed to retrieve patient: {}", e)
+                );
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+            }
+        };
+        let Some(patient) = privacy::view(&state, &requester, patient, Read::Direct, "GET /api/v1/patients/compare") else {
+            unreachable!("direct reads are masked, never excluded");
+        };
+        records.push(patient);
+    }
+    let (a, b) = (&records[0], &records[1]);
+
+    match state.matcher.match_patients(a, b) {
+        Ok(result) => {
+            let comparison = crate::api::compare::compare(a, b, &result, state.matcher.is_match(result.score));
+            (StatusCode::OK, Json(ApiResponse::success(comparison)))
+        }
+        Err(e) => {
+            let error = ApiResponse::<PatientComparison>::error(
+                "MATCH_ERROR",
+                format!("Matching failed: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Update a patient
+///
+/// With `survivorship.source_priority` configured, a demographic field whose
+/// current value came from a more authoritative source than this request's
+/// `X-Source-System` keeps that value.
+///
+/// The patient's `status` is not taken from the body; changes to `active`,
+/// `deceased` and the links move it, and are refused with a 409 when the
+/// transition is not allowed, e.g. reactivating a merged patient.
+#[utoipa::path(
+    put,
+    path = "/api/v1/patients/{id}",
+    tag = "patients",
+    params(
+        ("id" = Uuid, Path, description = "Patient UUID")
+    ),
+    request_body = Patient,
+    responses(
+        (status = 200, description = "Patient updated successfully"),
+        (status = 400, description = "Malformed identifiers, listed per field in `details`", body = crate::api::ApiErrorResponse),
+        (status = 409, description = "An MRN already belongs to another patient under the same assigning authority, or the status change is not allowed", body = crate::api::ApiErrorResponse),
+        (status = 423, description = "Patient is locked by another steward", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn update_patient(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    WithRawBody(Json(mut payload), raw): WithRawBody<Json<Patient>>,
+) -> impl IntoResponse {
+    if let Err(response) = check_record_locks(&state, &[id], &headers) {
+        return *response;
+    }
+    // Ensure ID in path matches payload
+    payload.id = id;
+    crate::matching::refresh_photo_hashes(&mut payload);
+
+    if let Err(response) = check_identifiers(&state, &payload) {
+        return *response;
+    }
+
+    // Verification is lowered only through the verification endpoint, and
+    // fields from more authoritative sources survive
+    let source = survivorship::source_from_headers(&headers).unwrap_or_else(|| CHANNEL_REST.to_string());
+    let existing = match state.patient_repository.get_by_id_for_update(&id) {
+        Ok(existing) => existing,
+        Err(e) => {
+            let error = ApiResponse::<Patient>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve patient: {}", e)
+            );
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+        }
+    };
+    if let Some(existing) = &existing {
+        payload.keep_verification_from(existing);
+        survivorship::apply(&state, existing, &mut payload, &source);
+        if let Err(e) = payload.keep_status_from(existing) {
+            let error = ApiResponse::<Patient>::error("CONFLICT", e.to_string());
+            return (StatusCode::CONFLICT, Json(error));
+        }
+    }
+
+    let submission = survivorship::submission_from_headers(&headers, &source, &payload);
+    match state.patient_repository.update_from_source(&payload, &submission) {
+        Ok(patient) => {
+            archive::archive(state.message_archive.as_ref(), patient.id, "UPDATE", CHANNEL_REST, &raw.content_type, &raw.bytes);
+            survivorship::record(&state, patient.id, existing.as_ref(), &patient, &source);
+
+            // Update search index
+            if let Err(e) = state.search_engine.index_patient(&patient) {
+                tracing::warn!("Failed to update patient in search engine: {}", e);
+            }
+
+            (StatusCode::OK, Json(ApiResponse::success(patient)))
+        }
+        Err(e) => {
+            let error = ApiResponse::<Patient>::error(
+                "DATABASE_ERROR",
+                format!("Failed to update patient: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Refuse a patient whose identifiers fail their format or authority checks
+///
+/// Every rejected identifier is listed in the error details with its field,
+/// e.g. `{"field": "identifiers[1].value", "message": "NPI check digit is wrong"}`.
+/// An MRN another patient already holds under the same assigning authority
+/// is a 409 instead, with that patient's ID in the details.
+fn check_identifiers(state: &AppState, patient: &Patient) -> Result<(), HandlerError<Patient>> {
+    let rules = state.identifier_rules();
+    let violations: Vec<serde_json::Value> = patient
+        .identifiers
+        .iter()
+        .enumerate()
+        .filter_map(|(i, identifier)| {
+            rules.problem(identifier).map(|message| {
+                serde_json::json!({ "field": format!("identifiers[{}].value", i), "message": message })
+            })
+        })
+        .collect();
+    if !violations.is_empty() {
+        let error = ApiResponse::<Patient>::error(
+            "VALIDATION_ERROR",
+            format!("{} identifier(s) failed validation", violations.len())
+        ).with_details(serde_json::Value::Array(violations));
+        return Err(Box::new((StatusCode::BAD_REQUEST, Json(error))));
+    }
+
+    let conflicts = match rules.mrn_conflicts(patient, state.patient_repository.as_ref()) {
+        Ok(conflicts) => conflicts,
+        Err(e) => {
+            let error = ApiResponse::<Patient>::error(
+                "DATABASE_ERROR",
+                format!("Failed to check MRN uniqueness: {}", e)
+            );
+            return Err(Box::new((StatusCode::INTERNAL_SERVER_ERROR, Json(error))));
+        }
+    };
+    if conflicts.is_empty() {
+        return Ok(());
+    }
+
+    let details = conflicts
+        .iter()
+        .map(|conflict| {
+            serde_json::json!({
+                "field": format!("identifiers[{}].value", conflict.index),
+                "authority": conflict.authority,
+                "patient_id": conflict.patient_id,
+            })
+        })
+        .collect();
+    let error = ApiResponse::<Patient>::error(
+        "CONFLICT",
+        format!("{} MRN(s) already belong to another patient", conflicts.len())
+    ).with_details(serde_json::Value::Array(details));
+    Err(Box::new((StatusCode::CONFLICT, Json(error))))
+}
+
+/// Delete a patient (soft delete)
+#[utoipa::path(
+    delete,
+    path = "/api/v1/patients/{id}",
+    tag = "patients",
+    params(
+        ("id" = Uuid, Path, description = "Patient UUID")
+    ),
+    responses(
+        (status = 204, description = "Patient deleted successfully"),
+        (status = 423, description = "Patient is locked by another steward", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn delete_patient(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(response) = check_record_locks(&state, &[id], &headers) {
+        return *response;
+    }
+
+    match state.patient_repository.delete(&id) {
+        Ok(()) => {
+            // Remove from search index
+            if let Err(e) = state.search_engine.delete_patient(&id.to_string()) {
+                tracing::warn!("Failed to delete patient from search engine: {}", e);
+            }
+
+            (StatusCode::NO_CONTENT, Json(ApiResponse::<()>::success(())))
+        }
+        Err(e) => {
+            let error = ApiResponse::<()>::error(
+                "DATABASE_ERROR",
+                format!("Failed to delete patient: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Search query parameters
+#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct SearchQuery {
+    /// Search query string; optional when searching by phone or email
+    #[serde(default)]
+    pub q: String,
+
+    /// Phone number, in any formatting
+    pub phone: Option<String>,
+
+    /// Email address
+    pub email: Option<String>,
+
+    /// Maximum number of results (default: 10, max: 100)
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+
+    /// Use fuzzy search
+    #[serde(default)]
+    pub fuzzy: bool,
+
+    /// Most edits per word in a fuzzy search (0-2, default: 2)
+    pub fuzzy_distance: Option<u8>,
+
+    /// Leading characters of each word a fuzzy search must match exactly (default: 0)
+    pub fuzzy_prefix_length: Option<usize>,
+
+    /// Comma-separated fields for a fuzzy search: family_name, given_names,
+    /// identifiers (default: family_name)
+    pub fuzzy_fields: Option<String>,
+
+    /// Comma-separated top-level patient fields to return, e.g.
+    /// `name,birth_date,identifiers`; `id` is always returned
+    pub fields: Option<String>,
+
+    /// Only return patients managed by this organization
+    pub organization: Option<Uuid>,
+}
+
+fn default_limit() -> usize {
+    10
+}
+
+impl SearchQuery {
+    /// Fuzzy search options, with defaults for parameters not given
+    fn fuzzy_options(&self) -> crate::Result<crate::search::FuzzyOptions> {
+        let mut options = crate::search::FuzzyOptions::default();
+        if let Some(distance) = self.fuzzy_distance {
+            options.distance = distance;
+        }
+        if let Some(prefix_length) = self.fuzzy_prefix_length {
+            options.prefix_length = prefix_length;
+        }
+        if let Some(fields) = &self.fuzzy_fields {
+            options.fields = fields
+                .split(',')
+                .filter(|field| !field.trim().is_empty())
+                .map(str::parse)
+                .collect::<crate::Result<_>>()?;
+        }
+        options.validate()?;
+        Ok(options)
+    }
+}
+
+/// Search results response
 #[derive(Debug, Serialize, ToSchema)]
-pub struct MatchResponse {
+pub struct SearchResponse {
+    pub patients: Vec<Patient>,
+    /// Relevance scores, in the same order as `patients`
+    pub hits: Vec<SearchHitResponse>,
+    pub total: usize,
+    pub query: String,
+}
+
+/// Relevance information for a single search result
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SearchHitResponse {
+    pub patient_id: Uuid,
+    pub score: f32,
+    /// Matched-term fragments keyed by field, with matches wrapped in `<b>`
+    /// tags; empty for a masked patient
+    pub highlights: std::collections::HashMap<String, String>,
+}
+
+/// One line of an NDJSON search response
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SearchResultLine {
     pub patient: Patient,
-    pub score: f64,
-    pub quality: String,
+    pub score: f32,
+    /// Matched-term fragments keyed by field, with matches wrapped in `<b>` tags
+    pub highlights: std::collections::HashMap<String, String>,
 }
 
-/// Match results response
+/// Search for patients
+///
+/// With `Accept: application/x-ndjson` each result is sent as one
+/// [`SearchResultLine`] instead of a single envelope. With `fields`, only
+/// those fields of each patient are returned.
+#[utoipa::path(
+    get,
+    path = "/api/v1/patients/search",
+    tag = "search",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "Search results", body = SearchResponse, content_type = "application/json"),
+        (status = 200, description = "Search results, one per line", body = SearchResultLine, content_type = "application/x-ndjson"),
+        (status = 400, description = "No search criteria", body = crate::api::ApiErrorResponse),
+        (status = 406, description = "Neither JSON nor NDJSON is acceptable", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Search error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn search_patients(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<SearchQuery>,
+) -> Response {
+    let Some(format) = ResponseFormat::negotiate(&headers) else {
+        return negotiation::not_acceptable();
+    };
+
+    // Limit to max 100 results
+    let limit = params.limit.min(100);
+    let fetch = match params.organization {
+        Some(_) => limit.saturating_mul(crate::search::ORGANIZATION_OVERFETCH),
+        None => limit,
+    };
+
+    // Perform search using search engine
+    let search_hits = if params.phone.is_some() || params.email.is_some() {
+        state
+            .search_engine
+            .search_by_telecom(params.phone.as_deref(), params.email.as_deref(), fetch)
+    } else if params.q.trim().is_empty() {
+        let error = ApiResponse::<SearchResponse>::error(
+            "VALIDATION_ERROR",
+            "One of q, phone or email is required",
+        );
+        return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+    } else if params.fuzzy {
+        let options = match params.fuzzy_options() {
+            Ok(options) => options,
+            Err(e) => {
+                let error = ApiResponse::<SearchResponse>::error("VALIDATION_ERROR", e.to_string());
+                return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+            }
+        };
+        state.search_engine.fuzzy_search_with_scores(&params.q, &options, fetch)
+    } else {
+        state.search_engine.search_with_scores(&params.q, fetch)
+    };
+    let search_hits = search_hits.and_then(|mut hits| {
+        if let Some(organization) = &params.organization {
+            let ids: Vec<String> = hits.iter().map(|hit| hit.patient_id.clone()).collect();
+            let scoped = state.search_engine.in_organization(&ids, organization)?;
+            hits.retain(|hit| scoped.contains(&hit.patient_id));
+            hits.truncate(limit);
+        }
+        Ok(hits)
+    });
+
+    match search_hits {
+        Ok(search_hits) => {
+            let requester = Requester::from_headers(&headers);
+            // Fetch full patient records from database
+            let mut patients = Vec::new();
+            let mut hits = Vec::new();
+            for hit in search_hits {
+                // Parse string ID to UUID
+                let patient_id = match Uuid::parse_str(&hit.patient_id) {
+                    Ok(id) => id,
+                    Err(e) => {
+                        tracing::error!("Failed to parse patient ID {}: {}", hit.patient_id, e);
+                        continue;
+                    }
+                };
+
+                match state.patient_repository.get_by_id(&patient_id) {
+                    Ok(Some(patient)) => {
+                        // Highlights quote the indexed values a masked patient hides
+                        let masked = privacy::is_masked(&state.config.privacy, &requester, &patient);
+                        let Some(patient) =
+                            privacy::view(&state, &requester, patient, Read::Search, "GET /api/v1/patients/search")
+                        else {
+                            continue;
+                        };
+                        patients.push(patient);
+                        hits.push(SearchHitResponse {
+                            patient_id,
+                            score: hit.score,
+                            highlights: if masked { Default::default() } else { hit.highlights },
+                        });
+                    }
+                    Ok(None) => {
+                        tracing::warn!("Patient {} found in search index but not in database", patient_id);
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to fetch patient {}: {}", patient_id, e);
+                    }
+                }
+            }
+
+            let selected = fields::parse(params.fields.as_deref());
+            if format == ResponseFormat::Ndjson {
+                let lines = patients.into_iter().zip(hits).map(|(patient, hit)| SearchResultLine {
+                    patient,
+                    score: hit.score,
+                    highlights: hit.highlights,
+                });
+                let Some(selected) = selected else {
+                    return negotiation::ndjson(lines.collect::<Vec<_>>());
+                };
+                let lines = lines.map(|line| {
+                    let mut line = serde_json::to_value(line).unwrap_or_default();
+                    fields::select_rest(&mut line["patient"], &selected);
+                    line
+                });
+                return negotiation::ndjson(lines.collect::<Vec<_>>());
+            }
+
+            let response = SearchResponse {
+                total: patients.len(),
+                patients,
+                hits,
+                query: params.q,
+            };
+            if let Some(selected) = selected {
+                let mut response = serde_json::to_value(response).unwrap_or_default();
+                if let Some(patients) = response["patients"].as_array_mut() {
+                    patients.iter_mut().for_each(|patient| fields::select_rest(patient, &selected));
+                }
+                return (StatusCode::OK, Json(ApiResponse::success(response))).into_response();
+            }
+            (StatusCode::OK, Json(ApiResponse::success(response))).into_response()
+        }
+        Err(e) => {
+            let error = ApiResponse::<SearchResponse>::error(
+                "SEARCH_ERROR",
+                format!("Search failed: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+/// Suggestion query parameters
+#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct SuggestQuery {
+    /// Partial or misspelled family name
+    pub q: String,
+
+    /// Maximum number of suggestions (default: 10, max: 50)
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+/// Suggestion results response
 #[derive(Debug, Serialize, ToSchema)]
-pub struct MatchResultsResponse {
-    pub matches: Vec<MatchResponse>,
-    pub total: usize,
+pub struct SuggestResponse {
+    pub suggestions: Vec<crate::search::Suggestion>,
+    pub query: String,
+}
+
+/// Suggest family name completions and corrections
+#[utoipa::path(
+    get,
+    path = "/api/v1/patients/suggest",
+    tag = "search",
+    params(SuggestQuery),
+    responses(
+        (status = 200, description = "Suggestions", body = SuggestResponse),
+        (status = 500, description = "Search error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn suggest_patients(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<SuggestQuery>,
+) -> impl IntoResponse {
+    let limit = params.limit.min(50);
+    let requester = Requester::from_headers(&headers);
+
+    let suggestions = state
+        .search_engine
+        .suggest(&params.q, limit)
+        .and_then(|suggestions| privacy::visible_suggestions(&state, &requester, suggestions));
+    match suggestions {
+        Ok(suggestions) => {
+            let response = SuggestResponse {
+                suggestions,
+                query: params.q,
+            };
+            (StatusCode::OK, Json(ApiResponse::success(response)))
+        }
+        Err(e) => {
+            let error = ApiResponse::<SuggestResponse>::error(
+                "SEARCH_ERROR",
+                format!("Suggestion failed: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Match request payload
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MatchRequest {
+    /// Patient to match against existing records
+    #[serde(flatten)]
+    pub patient: Patient,
+
+    /// Minimum match score threshold (0.0 to 1.0)
+    #[serde(default)]
+    pub threshold: Option<f64>,
+
+    /// Maximum number of matches to return
+    #[serde(default = "default_match_limit")]
+    pub limit: usize,
+
+    /// Name of a configured (`matching_profiles`) or stored matching profile
+    #[serde(default)]
+    pub profile: Option<String>,
+
+    /// Also return the query plan: what each stage found, how long it took
+    /// and why candidates were left out
+    #[serde(default)]
+    pub debug: bool,
+
+    /// Only consider patients managed by this organization, e.g. one campus
+    /// of a multi-facility deployment
+    #[serde(default)]
+    pub organization: Option<Uuid>,
+}
+
+/// The matching profile called `name`, or a 400 response naming the known ones
+fn matching_profile<T>(
+    state: &AppState,
+    name: &str,
+) -> Result<crate::config::MatchingProfile, HandlerError<T>> {
+    state.matching_profile(name).ok_or_else(|| {
+        let error = ApiResponse::<T>::error("VALIDATION_ERROR", format!("Unknown matching profile '{}'", name))
+            .with_details(serde_json::json!({
+                "profiles": state.matching_profile_names()
+            }));
+        Box::new((StatusCode::BAD_REQUEST, Json(error)))
+    })
+}
+
+fn default_match_limit() -> usize {
+    10
+}
+
+/// Match result with score
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MatchResponse {
+    pub patient: Patient,
+    pub score: f64,
+    pub quality: String,
+}
+
+/// Match results response
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MatchResultsResponse {
+    pub matches: Vec<MatchResponse>,
+    pub total: usize,
+    /// How the request was answered, when `debug` was set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan: Option<MatchQueryPlan>,
+}
+
+/// Match a patient against existing records
+///
+/// Stored patients holding the record's MRN or SSN are scored first; if any
+/// of them reaches the matcher's threshold they are the answer and
+/// name-based blocking is skipped. Otherwise candidates come from the search index as
+/// usual. When the record is a stored, unchanged patient its pair scores are
+/// cached and reused by later requests.
+///
+/// A `profile` selects the threshold, weights and blocking strategy of one
+/// of the configured `matching_profiles` or of the profiles stored through
+/// `/api/v1/admin/matching/profiles`. With `debug` the response also
+/// carries the query plan.
+#[utoipa::path(
+    post,
+    path = "/api/v1/patients/match",
+    tag = "matching",
+    request_body = MatchRequest,
+    responses(
+        (status = 200, description = "Match results", body = MatchResultsResponse),
+        (status = 400, description = "Unknown matching profile", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Matching error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn match_patient(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<MatchRequest>,
+) -> impl IntoResponse {
+    use crate::config::BlockingStrategy;
+
+    let requester = Requester::from_headers(&headers);
+
+    let started = Instant::now();
+    let profile = match payload.profile.as_deref() {
+        Some(name) => match matching_profile::<MatchResultsResponse>(&state, name) {
+            Ok(profile) => Some(profile),
+            Err(response) => return *response,
+        },
+        None => None,
+    };
+    let threshold = payload
+        .threshold
+        .or(profile.as_ref().and_then(|p| p.threshold_score))
+        .unwrap_or(0.5);
+
+    // A stored, unchanged patient reuses the scores of earlier requests
+    let stored = matches!(
+        state.patient_repository.get_by_id(&payload.patient.id),
+        Ok(Some(patient)) if patient.updated_at == payload.patient.updated_at
+    );
+    let matcher: std::sync::Arc<dyn crate::matching::PatientMatcher> = match &profile {
+        Some(profile) => match state.profile_matcher(profile, stored) {
+            Ok(matcher) => matcher,
+            Err(e) => {
+                let error = ApiResponse::<MatchResultsResponse>::error(
+                    "MATCH_ERROR",
+                    format!("Matching profile '{}' is invalid: {}", payload.profile.as_deref().unwrap_or_default(), e)
+                );
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+            }
+        },
+        None if stored => std::sync::Arc::new(state.pair_score_matcher()),
+        None => state.matcher.clone(),
+    };
+    let profile = profile.unwrap_or_else(|| state.default_profile());
+    let mut plan = payload.debug.then(|| MatchQueryPlan::new(profile.blocking));
+    let observe = |plan: &mut Option<MatchQueryPlan>, stage: MatchStage, elapsed: std::time::Duration| {
+        state.metrics.observe_match_stage(stage, elapsed);
+        if let Some(plan) = plan {
+            plan.time(stage, elapsed);
+        }
+    };
+
+    // Exact identifier lookup settles most requests without blocking
+    let lookup_started = Instant::now();
+    let identifier_candidates = crate::matching::identifier_candidates(
+        &payload.patient,
+        state.patient_repository.as_ref(),
+        &state.authority_registry(),
+    );
+    observe(&mut plan, MatchStage::IdentifierLookup, lookup_started.elapsed());
+    let identifier_candidates = identifier_candidates.map(|mut candidates| {
+        if let Some(organization) = payload.organization {
+            candidates.retain(|candidate| {
+                let scoped = candidate.managing_organization == Some(organization);
+                if let (false, Some(plan)) = (scoped, plan.as_mut()) {
+                    plan.filter(MatchStage::IdentifierLookup, Some(candidate.id), None, FilterReason::OtherOrganization);
+                }
+                scoped
+            });
+        }
+        candidates
+    });
+    match identifier_candidates {
+        Ok(candidates) if !candidates.is_empty() => {
+            let scoring_started = Instant::now();
+            let match_results = match matcher.find_matches(&payload.patient, &candidates) {
+                Ok(results) => results,
+                Err(e) => {
+                    let error = ApiResponse::<MatchResultsResponse>::error(
+                        "MATCH_ERROR",
+                        format!("Matching failed: {}", e)
+                    );
+                    return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+                }
+            };
+            observe(&mut plan, MatchStage::Scoring, scoring_started.elapsed());
+            if let Some(plan) = plan.as_mut() {
+                plan.identifier_candidates = candidates.len();
+                let stage = MatchStage::IdentifierLookup;
+                plan.below_matcher_threshold(stage, matcher.as_ref(), &payload.patient, &candidates, &match_results);
+            }
+
+            let settled = crate::matching::settled_by_identifiers(matcher.as_ref(), &match_results);
+            let mut response = match_results_response(
+                &state,
+                &requester,
+                match_results,
+                threshold,
+                payload.limit,
+                plan.as_mut().map(|plan| (MatchStage::IdentifierLookup, plan)),
+            );
+            if settled && response.total > 0 {
+                observe(&mut plan, MatchStage::Total, started.elapsed());
+                response.plan = plan.map(|mut plan| {
+                    plan.answered_by_identifiers = true;
+                    plan
+                });
+                return (StatusCode::OK, Json(ApiResponse::success(response)));
+            }
+        }
+        Ok(_) => {}
+        Err(e) => {
+            tracing::warn!("Exact identifier lookup failed, falling back to blocking: {}", e);
+        }
+    }
+
+    // Use search engine to get candidate patients (blocking)
+    let blocking_started = Instant::now();
+    let family_name = &payload.patient.legal_name().family;
+    let birth_year = payload.patient.birth_date.map(|d| d.year());
+
+    let max_candidates = match payload.organization {
+        Some(_) => profile.max_candidates.saturating_mul(crate::search::ORGANIZATION_OVERFETCH),
+        None => profile.max_candidates,
+    };
+
+    let candidate_ids = match profile.blocking {
+        BlockingStrategy::NameAndBirthYear => state.search_engine
+            .search_by_name_and_year(family_name, birth_year, max_candidates),
+        BlockingStrategy::Name => state.search_engine
+            .search_by_name_and_year(family_name, None, max_candidates),
+        BlockingStrategy::BlockingKeys => state.search_engine
+            .search_by_blocking_keys(&crate::search::blocking_keys(&payload.patient), max_candidates),
+        BlockingStrategy::MultiPass => crate::search::BlockingQuery::of(&payload.patient)
+            .run(state.search_engine.as_ref(), &state.config.matching.blocking.passes, max_candidates)
+            .map(|blocked| {
+                for pass in &blocked.passes {
+                    state.metrics.observe_blocking_pass(pass.pass.as_str(), pass.added);
+                }
+                if let Some(plan) = plan.as_mut() {
+                    plan.passes = blocked.passes;
+                }
+                blocked.ids
+            }),
+        BlockingStrategy::IdentifierOnly => Ok(Vec::new()),
+    };
+    let candidate_ids = candidate_ids.and_then(|mut ids| {
+        if let Some(organization) = &payload.organization {
+            let scoped = state.search_engine.in_organization(&ids, organization)?;
+            ids.retain(|id| {
+                let kept = scoped.contains(id);
+                if let (false, Some(plan)) = (kept, plan.as_mut()) {
+                    plan.filter(MatchStage::Blocking, Uuid::parse_str(id).ok(), None, FilterReason::OtherOrganization);
+                }
+                kept
+            });
+            ids.truncate(profile.max_candidates);
+        }
+        Ok(ids)
+    });
+    observe(&mut plan, MatchStage::Blocking, blocking_started.elapsed());
+
+    match candidate_ids {
+        Ok(ids) => {
+            if let Some(plan) = plan.as_mut() {
+                plan.blocked = ids.len();
+            }
+            let mut filter = |id: Option<Uuid>, reason: FilterReason| {
+                if let Some(plan) = plan.as_mut() {
+                    plan.filter(MatchStage::Blocking, id, None, reason);
+                }
+            };
+
+            // Fetch full patient records from database
+            let hydration_started = Instant::now();
+            let mut candidates = Vec::new();
+            for patient_id_str in ids {
+                // Out of time; scoring reports the timeout
+                if crate::deadline::expired() {
+                    filter(Uuid::parse_str(&patient_id_str).ok(), FilterReason::DeadlineExpired);
+                    continue;
+                }
+
+                // Parse string ID to UUID
+                let patient_id = match Uuid::parse_str(&patient_id_str) {
+                    Ok(id) => id,
+                    Err(e) => {
+                        tracing::error!("Failed to parse patient ID {}: {}", patient_id_str, e);
+                        filter(None, FilterReason::InvalidId);
+                        continue;
+                    }
+                };
+
+                match state.patient_repository.get_by_id(&patient_id) {
+                    Ok(Some(patient)) => candidates.push(patient),
+                    Ok(None) => {
+                        tracing::warn!("Patient {} found in search index but not in database", patient_id);
+                        filter(Some(patient_id), FilterReason::NotInDatabase);
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to fetch patient {}: {}", patient_id, e);
+                        filter(Some(patient_id), FilterReason::LoadFailed);
+                    }
+                }
+            }
+
+            observe(&mut plan, MatchStage::Hydration, hydration_started.elapsed());
+
+            // Run matcher on candidates
+            let scoring_started = Instant::now();
+            let match_results = match matcher.find_matches(&payload.patient, &candidates) {
+                Ok(results) => results,
+                Err(e) => {
+                    let error = ApiResponse::<MatchResultsResponse>::error(
+                        "MATCH_ERROR",
+                        format!("Matching failed: {}", e)
+                    );
+                    return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+                }
+            };
+            observe(&mut plan, MatchStage::Scoring, scoring_started.elapsed());
+            if let Some(plan) = plan.as_mut() {
+                plan.below_matcher_threshold(MatchStage::Blocking, matcher.as_ref(), &payload.patient, &candidates, &match_results);
+            }
+
+            let mut response = match_results_response(
+                &state,
+                &requester,
+                match_results,
+                threshold,
+                payload.limit,
+                plan.as_mut().map(|plan| (MatchStage::Blocking, plan)),
+            );
+            observe(&mut plan, MatchStage::Total, started.elapsed());
+            response.plan = plan;
+            (StatusCode::OK, Json(ApiResponse::success(response)))
+        }
+        Err(e) => {
+            let error = ApiResponse::<MatchResultsResponse>::error(
+                "MATCH_ERROR",
+                format!("Matching failed: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Keep the results at or above `threshold`, best first, labelled by quality,
+/// with restricted patients shown as `requester` may see them
+///
+/// Results left out are recorded in `plan` against its stage, when given.
+fn match_results_response(
+    state: &AppState,
+    requester: &Requester,
+    match_results: Vec<MatchResult>,
+    threshold: f64,
+    limit: usize,
+    mut plan: Option<(MatchStage, &mut MatchQueryPlan)>,
+) -> MatchResultsResponse {
+    let mut filter = |id: Option<Uuid>, score: f64, reason: FilterReason| {
+        if let Some((stage, plan)) = plan.as_mut() {
+            plan.filter(*stage, id, Some(score), reason);
+        }
+    };
+
+    let mut matches = Vec::new();
+    for m in match_results {
+        if m.score < threshold {
+            filter(Some(m.patient.id), m.score, FilterReason::BelowThreshold);
+            continue;
+        }
+        if matches.len() >= limit {
+            filter(Some(m.patient.id), m.score, FilterReason::OverLimit);
+            continue;
+        }
+        let score = m.score;
+        match privacy::view(state, requester, m.patient, Read::Search, "POST /api/v1/patients/match") {
+            Some(patient) => matches.push(MatchResponse {
+                quality: crate::matching::quality_label(score).to_string(),
+                patient,
+                score,
+            }),
+            None => filter(None, score, FilterReason::Restricted),
+        }
+    }
+
+    MatchResultsResponse {
+        total: matches.len(),
+        matches,
+        plan: None,
+    }
+}
+
+/// Batch match request payload
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BatchMatchRequest {
+    /// Records to match, such as the members of a payer roster
+    #[serde(default)]
+    pub records: Vec<BatchMatchRecord>,
+
+    /// Also match the members of this patient group, each referenced by its
+    /// patient ID
+    #[serde(default)]
+    pub group: Option<Uuid>,
+
+    /// Minimum match score threshold (0.0 to 1.0)
+    #[serde(default)]
+    pub threshold: Option<f64>,
+
+    /// Maximum number of matches to return per record
+    #[serde(default = "default_batch_match_limit")]
+    pub limit: usize,
+
+    /// Name of a configured (`matching_profiles`) or stored matching profile
+    #[serde(default)]
+    pub profile: Option<String>,
+
+    /// Match as a background job and download the results once it completes
+    #[serde(default)]
+    pub job: bool,
+
+    /// User or system requesting the batch, recorded on the job
+    #[serde(default)]
+    pub requested_by: Option<String>,
+}
+
+fn default_batch_match_limit() -> usize {
+    3
+}
+
+/// Batch match results
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchMatchResponse {
+    pub summary: BatchMatchSummary,
+    /// One entry per record, in batch order
+    pub results: Vec<BatchMatchResult>,
+}
+
+/// Match a batch of records against existing records
+///
+/// Records sharing a blocking key share one search, and each candidate is
+/// loaded once for the whole batch; scoring runs on `batch_match.workers`
+/// threads. Batches of up to `batch_match.max_records` are answered
+/// directly. With `job` the batch, up to `batch_match.max_job_records`,
+/// runs in the background; poll `/api/v1/admin/jobs/{id}` and download the
+/// results from `/api/v1/patients/match/batch/{id}`. The members of a
+/// patient `group` are matched after `records`.
+#[utoipa::path(
+    post,
+    path = "/api/v1/patients/match/batch",
+    tag = "matching",
+    request_body = BatchMatchRequest,
+    responses(
+        (status = 200, description = "Matches per record", body = BatchMatchResponse),
+        (status = 202, description = "Batch match job started", body = crate::jobs::Job),
+        (status = 400, description = "Batch too large or unknown matching profile", body = crate::api::ApiErrorResponse),
+        (status = 404, description = "Group not found", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Matching error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn match_batch(
+    State(state): State<AppState>,
+    Json(mut payload): Json<BatchMatchRequest>,
+) -> Response {
+    if let Some(group_id) = payload.group {
+        match crate::api::groups::member_records(&state, &group_id) {
+            Ok(Some(members)) => payload.records.extend(members.into_iter().map(|patient| BatchMatchRecord {
+                reference: Some(patient.id.to_string()),
+                patient,
+            })),
+            Ok(None) => {
+                let error = ApiResponse::<BatchMatchResponse>::error(
+                    "NOT_FOUND",
+                    format!("Group with id '{}' not found", group_id)
+                );
+                return (StatusCode::NOT_FOUND, Json(error)).into_response();
+            }
+            Err(e) => {
+                let error = ApiResponse::<BatchMatchResponse>::error(
+                    "DATABASE_ERROR",
+                    format!("Failed to load group members: {}", e)
+                );
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+            }
+        }
+    }
+
+    let config = &state.config.batch_match;
+    let max_records = if payload.job { config.max_job_records } else { config.max_records };
+    if payload.records.len() > max_records {
+        let message = if payload.job {
+            format!("Batch of {} records exceeds the job limit of {}", payload.records.len(), max_records)
+        } else {
+            format!("Batch of {} records exceeds the limit of {}; submit it with job: true", payload.records.len(), max_records)
+        };
+        let error = ApiResponse::<BatchMatchResponse>::error("VALIDATION_ERROR", message);
+        return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+    }
+
+    let profile = match payload.profile.as_deref() {
+        Some(name) => match matching_profile::<BatchMatchResponse>(&state, name) {
+            Ok(profile) => Some(profile),
+            Err(response) => return response.into_response(),
+        },
+        None => None,
+    };
+    let threshold = payload
+        .threshold
+        .or(profile.as_ref().and_then(|p| p.threshold_score))
+        .unwrap_or(0.5);
+    let matcher = match &profile {
+        Some(profile) => match state.profile_matcher(profile, false) {
+            Ok(matcher) => matcher,
+            Err(e) => {
+                let error = ApiResponse::<BatchMatchResponse>::error(
+                    "MATCH_ERROR",
+                    format!("Matching profile '{}' is invalid: {}", payload.profile.as_deref().unwrap_or_default(), e)
+                );
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+            }
+        },
+        None => state.matcher.clone(),
+    };
+    let profile = profile.unwrap_or_else(|| state.default_profile());
+
+    let batch_matcher = BatchMatcher::new(
+        state.patient_repository.clone(),
+        state.search_engine.clone(),
+        matcher,
+        state.authority_registry(),
+        BatchOptions {
+            threshold,
+            limit: payload.limit,
+            blocking: profile.blocking,
+            max_candidates: profile.max_candidates,
+            passes: state.config.matching.blocking.passes.clone(),
+            workers: config.workers,
+        },
+    );
+
+    if !payload.job {
+        let results = batch_matcher.run(&payload.records, None);
+        let response = BatchMatchResponse {
+            summary: BatchMatchSummary::of(&results),
+            results,
+        };
+        return (StatusCode::OK, Json(ApiResponse::success(response))).into_response();
+    }
+
+    let handle = state.jobs.create("batch_match", payload.requested_by.clone());
+    let job = handle.snapshot();
+    let path = batch_results_path(&state, handle.id());
+    batch_matcher.spawn(payload.records, path, handle);
+
+    match job {
+        Some(job) => (StatusCode::ACCEPTED, Json(ApiResponse::success(job))).into_response(),
+        None => {
+            let error = ApiResponse::<crate::jobs::Job>::error(
+                "INTERNAL_ERROR",
+                "Batch match job was not registered".to_string()
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+/// Download the results of a completed batch match job as NDJSON, one
+/// record's matches per line in batch order
+#[utoipa::path(
+    get,
+    path = "/api/v1/patients/match/batch/{id}",
+    tag = "matching",
+    params(
+        ("id" = Uuid, Path, description = "Batch match job ID")
+    ),
+    responses(
+        (status = 200, description = "One record's matches per line", body = BatchMatchResult, content_type = "application/x-ndjson"),
+        (status = 404, description = "Batch match job not found", body = crate::api::ApiErrorResponse),
+        (status = 406, description = "NDJSON is not acceptable", body = crate::api::ApiErrorResponse),
+        (status = 409, description = "Job has not completed", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Results could not be read", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn get_batch_match_results(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Response {
+    if !negotiation::accepts_ndjson(&headers) {
+        return negotiation::not_acceptable();
+    }
+
+    let job = match state.jobs.get(&id) {
+        Some(job) if job.kind == "batch_match" => job,
+        _ => {
+            let error = ApiResponse::<()>::error("NOT_FOUND", format!("Batch match job {} not found", id));
+            return (StatusCode::NOT_FOUND, Json(error)).into_response();
+        }
+    };
+    if job.status != crate::jobs::JobStatus::Completed {
+        let error = ApiResponse::<()>::error(
+            "CONFLICT",
+            format!("Batch match job {} has not completed ({:?})", id, job.status)
+        );
+        return (StatusCode::CONFLICT, Json(error)).into_response();
+    }
+
+    match tokio::fs::read(batch_results_path(&state, id)).await {
+        Ok(results) => negotiation::ndjson_body(axum::body::Body::from(results)),
+        Err(e) => {
+            let error = ApiResponse::<()>::error(
+                "INTERNAL_ERROR",
+                format!("Failed to read batch match results: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+fn batch_results_path(state: &AppState, job_id: Uuid) -> std::path::PathBuf {
+    std::path::Path::new(&state.config.batch_match.results_dir).join(format!("{}.ndjson", job_id))
+}
+
+/// Match simulation request payload
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SimulateMatchRequest {
+    /// First patient (need not exist in the database)
+    pub patient: Patient,
+
+    /// Second patient (need not exist in the database)
+    pub candidate: Patient,
+
+    /// Override the configured probabilistic match threshold
+    #[serde(default)]
+    pub threshold: Option<f64>,
+
+    /// Override the configured probabilistic component weights
+    #[serde(default)]
+    pub weights: Option<crate::config::MatchWeights>,
+
+    /// Start from a configured matching profile instead of `matching`
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+/// Outcome of a simulated match under one matcher
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SimulatedMatch {
+    /// Matcher name ("probabilistic" or "deterministic")
+    pub matcher: String,
+    pub score: f64,
+    pub is_match: bool,
+    /// Components that matched well
+    pub summary: String,
+    pub breakdown: crate::matching::MatchScoreBreakdown,
+}
+
+/// Match simulation response
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SimulateMatchResponse {
+    /// Threshold used by the probabilistic matcher
+    pub threshold: f64,
+    /// Weights used by the probabilistic matcher
+    pub weights: crate::config::MatchWeights,
+    pub results: Vec<SimulatedMatch>,
+}
+
+/// Score two patient payloads under each matcher without touching stored records
+#[utoipa::path(
+    post,
+    path = "/api/v1/matching/simulate",
+    tag = "matching",
+    request_body = SimulateMatchRequest,
+    responses(
+        (status = 200, description = "Score breakdown per matcher", body = SimulateMatchResponse),
+        (status = 400, description = "Invalid threshold or weights, or unknown matching profile", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Matching error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn simulate_match(
+    State(state): State<AppState>,
+    Json(payload): Json<SimulateMatchRequest>,
+) -> impl IntoResponse {
+    let mut config = state.matching_config();
+    if let Some(name) = payload.profile.as_deref() {
+        match matching_profile::<SimulateMatchResponse>(&state, name) {
+            Ok(profile) => config = profile.apply(&config),
+            Err(response) => return *response,
+        }
+    }
+    if let Some(threshold) = payload.threshold {
+        config.threshold_score = threshold;
+    }
+    if let Some(weights) = payload.weights {
+        config.weights = weights;
+    }
+
+    if let Err(crate::Error::Validation(message)) = config.validate() {
+        let error = ApiResponse::<SimulateMatchResponse>::error("VALIDATION_ERROR", message);
+        return (StatusCode::BAD_REQUEST, Json(error));
+    }
+
+    let matchers: Vec<(&str, Box<dyn crate::matching::PatientMatcher>)> = vec![
+        ("probabilistic", Box::new(crate::matching::ProbabilisticMatcher::new(config.clone()))),
+        ("deterministic", Box::new(crate::matching::DeterministicMatcher::new(config.clone()))),
+    ];
+
+    let mut results = Vec::with_capacity(matchers.len());
+    for (name, matcher) in matchers {
+        match matcher.match_patients(&payload.patient, &payload.candidate) {
+            Ok(result) => results.push(SimulatedMatch {
+                matcher: name.to_string(),
+                score: result.score,
+                is_match: matcher.is_match(result.score),
+                summary: result.breakdown.summary(),
+                breakdown: result.breakdown,
+            }),
+            Err(e) => {
+                let error = ApiResponse::<SimulateMatchResponse>::error(
+                    "MATCH_ERROR",
+                    format!("Matching failed: {}", e)
+                );
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+            }
+        }
+    }
+
+    let response = SimulateMatchResponse {
+        threshold: config.threshold_score,
+        weights: config.weights,
+        results,
+    };
+    (StatusCode::OK, Json(ApiResponse::success(response)))
+}
+
+/// Duplicate review queue query parameters
+#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct DuplicateQuery {
+    /// Maximum number of pairs (default: 50, max: 500)
+    #[serde(default = "default_duplicate_limit")]
+    pub limit: i64,
+
+    /// Number of pairs to skip
+    #[serde(default)]
+    pub offset: i64,
+}
+
+fn default_duplicate_limit() -> i64 {
+    50
+}
+
+/// List possible duplicate pairs awaiting review, highest score first
+///
+/// Pairs are queued by incremental dedup (`dedup.incremental`) as records
+/// are created and updated. Where both records have photos,
+/// `photo_similarity` compares their perceptual hashes, as an aid to
+/// stewards rather than part of the score.
+#[utoipa::path(
+    get,
+    path = "/api/v1/duplicates",
+    tag = "matching",
+    params(DuplicateQuery),
+    responses(
+        (status = 200, description = "Pending duplicate pairs", body = Vec<DuplicateCandidate>),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn list_duplicates(
+    State(state): State<AppState>,
+    Query(params): Query<DuplicateQuery>,
+) -> impl IntoResponse {
+    let limit = params.limit.clamp(0, 500);
+
+    match state.duplicates.list_pending(limit, params.offset.max(0)) {
+        Ok(mut candidates) => {
+            add_photo_similarity(&state, &mut candidates);
+            (StatusCode::OK, Json(ApiResponse::success(candidates)))
+        }
+        Err(e) => {
+            let error = ApiResponse::<Vec<DuplicateCandidate>>::error(
+                "DATABASE_ERROR",
+                format!("Failed to list duplicate candidates: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Compare the photo hashes of each pair's records
+///
+/// A record that cannot be loaded leaves its pairs without a similarity.
+fn add_photo_similarity(state: &AppState, candidates: &mut [DuplicateCandidate]) {
+    let mut patients: std::collections::HashMap<Uuid, Option<Patient>> = std::collections::HashMap::new();
+    for candidate in candidates.iter_mut() {
+        for id in [candidate.patient_id, candidate.candidate_id] {
+            patients.entry(id).or_insert_with(|| match state.patient_repository.get_by_id(&id) {
+                Ok(patient) => patient,
+                Err(e) => {
+                    tracing::warn!("Failed to load patient {} for photo comparison: {}", id, e);
+                    None
+                }
+            });
+        }
+        if let (Some(Some(patient)), Some(Some(other))) =
+            (patients.get(&candidate.patient_id), patients.get(&candidate.candidate_id))
+        {
+            candidate.photo_similarity = crate::matching::photo_similarity(patient, other);
+        }
+    }
+}
+
+/// A steward's decision on a duplicate pair
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DuplicateDecisionRequest {
+    /// Coded reason; must fit the action, or be `other`
+    pub reason_code: ReviewReason,
+
+    /// Free-text explanation, required with reason code `other`
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Confirm that a pending pair is the same person
+///
+/// Records the decision only; merging the records is left to the systems
+/// that own them. The steward is taken from `X-User-Id` and the decision is
+/// audited.
+#[utoipa::path(
+    post,
+    path = "/api/v1/duplicates/{id}/confirm",
+    tag = "matching",
+    params(
+        ("id" = Uuid, Path, description = "Duplicate candidate UUID")
+    ),
+    request_body = DuplicateDecisionRequest,
+    responses(
+        (status = 200, description = "Pair confirmed", body = DuplicateCandidate),
+        (status = 400, description = "Reason code does not fit, or `other` without a reason", body = crate::api::ApiErrorResponse),
+        (status = 404, description = "Duplicate candidate not found", body = crate::api::ApiErrorResponse),
+        (status = 409, description = "Pair was already decided", body = crate::api::ApiErrorResponse),
+        (status = 423, description = "Patient is locked by another steward", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn confirm_duplicate(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(payload): Json<DuplicateDecisionRequest>,
+) -> impl IntoResponse {
+    decide_duplicate(&state, id, ReviewAction::Confirm, payload, &headers)
+}
+
+/// Reject a pending pair as two different people
+#[utoipa::path(
+    post,
+    path = "/api/v1/duplicates/{id}/reject",
+    tag = "matching",
+    params(
+        ("id" = Uuid, Path, description = "Duplicate candidate UUID")
+    ),
+    request_body = DuplicateDecisionRequest,
+    responses(
+        (status = 200, description = "Pair rejected", body = DuplicateCandidate),
+        (status = 400, description = "Reason code does not fit, or `other` without a reason", body = crate::api::ApiErrorResponse),
+        (status = 404, description = "Duplicate candidate not found", body = crate::api::ApiErrorResponse),
+        (status = 409, description = "Pair was already decided", body = crate::api::ApiErrorResponse),
+        (status = 423, description = "Patient is locked by another steward", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn reject_duplicate(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(payload): Json<DuplicateDecisionRequest>,
+) -> impl IntoResponse {
+    decide_duplicate(&state, id, ReviewAction::Reject, payload, &headers)
+}
+
+/// Put a confirmed or rejected pair back in the review queue
+///
+/// The earlier decision stays in the pair's history.
+#[utoipa::path(
+    post,
+    path = "/api/v1/duplicates/{id}/reopen",
+    tag = "matching",
+    params(
+        ("id" = Uuid, Path, description = "Duplicate candidate UUID")
+    ),
+    request_body = DuplicateDecisionRequest,
+    responses(
+        (status = 200, description = "Pair reopened", body = DuplicateCandidate),
+        (status = 400, description = "Reason code does not fit, or `other` without a reason", body = crate::api::ApiErrorResponse),
+        (status = 404, description = "Duplicate candidate not found", body = crate::api::ApiErrorResponse),
+        (status = 409, description = "Pair is still pending", body = crate::api::ApiErrorResponse),
+        (status = 423, description = "Patient is locked by another steward", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn reopen_duplicate(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(payload): Json<DuplicateDecisionRequest>,
+) -> impl IntoResponse {
+    decide_duplicate(&state, id, ReviewAction::Reopen, payload, &headers)
+}
+
+/// Record a steward's decision on a pair and audit it
+fn decide_duplicate(
+    state: &AppState,
+    id: Uuid,
+    action: ReviewAction,
+    payload: DuplicateDecisionRequest,
+    headers: &HeaderMap,
+) -> (StatusCode, Json<ApiResponse<DuplicateCandidate>>) {
+    let reason = payload.reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+    if !payload.reason_code.applies_to(action) {
+        let error = ApiResponse::<DuplicateCandidate>::error(
+            "VALIDATION_ERROR",
+            format!("Reason code '{}' does not apply to {}", payload.reason_code.as_str(), action.as_str())
+        );
+        return (StatusCode::BAD_REQUEST, Json(error));
+    }
+    if payload.reason_code == ReviewReason::Other && reason.is_none() {
+        let error = ApiResponse::<DuplicateCandidate>::error(
+            "VALIDATION_ERROR",
+            "A reason is required with reason code 'other'"
+        );
+        return (StatusCode::BAD_REQUEST, Json(error));
+    }
+
+    let mut candidate = match find_duplicate(state, id) {
+        Ok(candidate) => candidate,
+        Err(response) => return *response,
+    };
+    if let Err(response) = check_record_locks(state, &[candidate.patient_id, candidate.candidate_id], headers) {
+        return *response;
+    }
+    let decidable = match action {
+        ReviewAction::Confirm | ReviewAction::Reject => candidate.status == PENDING_REVIEW,
+        ReviewAction::Reopen => candidate.status != PENDING_REVIEW,
+    };
+    if !decidable {
+        return duplicate_conflict(&candidate);
+    }
+
+    let steward = Requester::from_headers(headers);
+    let decision = ReviewDecision {
+        id: Uuid::new_v4(),
+        duplicate_candidate_id: id,
+        action,
+        reason_code: payload.reason_code,
+        reason,
+        decided_by: steward.user_id.clone(),
+        decided_at: chrono::Utc::now(),
+    };
+    match state.duplicates.decide(&id, &candidate.status, &decision) {
+        Ok(true) => {}
+        Ok(false) => {
+            let error = ApiResponse::<DuplicateCandidate>::error(
+                "CONFLICT",
+                format!("Duplicate candidate '{}' was decided by someone else", id)
+            );
+            return (StatusCode::CONFLICT, Json(error));
+        }
+        Err(e) => {
+            let error = ApiResponse::<DuplicateCandidate>::error(
+                "DATABASE_ERROR",
+                format!("Failed to record decision: {}", e)
+            );
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+        }
+    }
+
+    if let Err(e) = state.audit_log.log_create(
+        "DuplicateDecision",
+        decision.id,
+        serde_json::json!({
+            "decision": decision,
+            "patient_id": candidate.patient_id,
+            "candidate_id": candidate.candidate_id,
+            "previous_status": candidate.status,
+        }),
+        steward.user_id,
+        steward.ip_address,
+        steward.user_agent,
+    ) {
+        tracing::warn!("Failed to audit decision on duplicate candidate {}: {}", id, e);
+    }
+
+    candidate.status = action.status().to_string();
+    (StatusCode::OK, Json(ApiResponse::success(candidate)))
+}
+
+/// List a pair's decisions, oldest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/duplicates/{id}/decisions",
+    tag = "matching",
+    params(
+        ("id" = Uuid, Path, description = "Duplicate candidate UUID")
+    ),
+    responses(
+        (status = 200, description = "Decisions on the pair", body = Vec<ReviewDecision>),
+        (status = 404, description = "Duplicate candidate not found", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn list_duplicate_decisions(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Response {
+    if let Err(response) = find_duplicate(&state, id) {
+        return response.into_response();
+    }
+    match state.duplicates.decisions(&id) {
+        Ok(decisions) => (StatusCode::OK, Json(ApiResponse::success(decisions))).into_response(),
+        Err(e) => {
+            let error = ApiResponse::<Vec<ReviewDecision>>::error(
+                "DATABASE_ERROR",
+                format!("Failed to list decisions: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+/// Export decided pairs as labeled training data
+///
+/// Confirmed pairs are labeled `1` and rejected pairs `0`, with each
+/// record's current demographics, in the CSV format read by `mpi evaluate
+/// --pairs`. Pairs involving a restricted record are left out unless the
+/// requester holds a privileged role. The export is audited.
+#[utoipa::path(
+    get,
+    path = "/api/v1/duplicates/training-data",
+    tag = "matching",
+    responses(
+        (status = 200, description = "Labeled pairs", body = String, content_type = "text/csv"),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn export_duplicate_training_data(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Response {
+    let database_error = |e: crate::Error| {
+        let error = ApiResponse::<String>::error(
+            "DATABASE_ERROR",
+            format!("Failed to export training data: {}", e)
+        );
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+    };
+
+    let requester = Requester::from_headers(&headers);
+    let privileged = requester.is_privileged(&state.config.privacy);
+    let mut pairs = Vec::new();
+    let mut withheld = 0;
+    let mut offset = 0;
+    loop {
+        let candidates = match state.duplicates.list_decided(500, offset) {
+            Ok(candidates) => candidates,
+            Err(e) => return database_error(e),
+        };
+        if candidates.is_empty() {
+            break;
+        }
+        offset += candidates.len() as i64;
+
+        for candidate in candidates {
+            let load = |id: &Uuid| state.patient_repository.get_by_id(id);
+            let (patient, other) = match (load(&candidate.patient_id), load(&candidate.candidate_id)) {
+                (Ok(Some(patient)), Ok(Some(other))) => (patient, other),
+                (Err(e), _) | (_, Err(e)) => return database_error(e),
+                // A purged record leaves nothing to learn from
+                _ => continue,
+            };
+            if !privileged && (patient.confidentiality.is_restricted() || other.confidentiality.is_restricted()) {
+                withheld += 1;
+                continue;
+            }
+            pairs.push(LabeledPair {
+                patient,
+                candidate: other,
+                is_match: candidate.status == DUPLICATE_CONFIRMED,
+            });
+        }
+    }
+
+    let mut csv = Vec::new();
+    if let Err(e) = write_pairs_csv(&mut csv, &pairs) {
+        return database_error(e);
+    }
+
+    if let Err(e) = state.audit_log.log_create(
+        "TrainingDataExport",
+        Uuid::new_v4(),
+        serde_json::json!({ "pairs": pairs.len(), "withheld": withheld }),
+        requester.user_id,
+        requester.ip_address,
+        requester.user_agent,
+    ) {
+        tracing::warn!("Failed to audit training data export: {}", e);
+    }
+
+    (StatusCode::OK, [(header::CONTENT_TYPE, "text/csv")], csv).into_response()
+}
+
+/// Look up a duplicate review queue entry
+fn find_duplicate(
+    state: &AppState,
+    id: Uuid,
+) -> Result<DuplicateCandidate, HandlerError<DuplicateCandidate>> {
+    match state.duplicates.get_by_id(&id) {
+        Ok(Some(candidate)) => Ok(candidate),
+        Ok(None) => {
+            let error = ApiResponse::<DuplicateCandidate>::error(
+                "NOT_FOUND",
+                format!("Duplicate candidate with id '{}' not found", id)
+            );
+            Err(Box::new((StatusCode::NOT_FOUND, Json(error))))
+        }
+        Err(e) => {
+            let error = ApiResponse::<DuplicateCandidate>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve duplicate candidate: {}", e)
+            );
+            Err(Box::new((StatusCode::INTERNAL_SERVER_ERROR, Json(error))))
+        }
+    }
+}
+
+fn duplicate_conflict(candidate: &DuplicateCandidate) -> (StatusCode, Json<ApiResponse<DuplicateCandidate>>) {
+    let message = if candidate.status == PENDING_REVIEW {
+        format!("Duplicate candidate '{}' has not been decided", candidate.id)
+    } else {
+        format!("Duplicate candidate '{}' was already {}", candidate.id, candidate.status)
+    };
+    let error = ApiResponse::<DuplicateCandidate>::error("CONFLICT", message);
+    (StatusCode::CONFLICT, Json(error))
+}
+
+/// Audit log query parameters
+#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct AuditLogQuery {
+    /// Maximum number of results (default: 50, max: 500)
+    #[serde(default = "default_audit_limit")]
+    pub limit: i64,
+}
+
+fn default_audit_limit() -> i64 {
+    50
+}
+
+/// Get audit logs for a specific patient
+#[utoipa::path(
+    get,
+    path = "/api/v1/patients/{id}/audit",
+    tag = "audit",
+    params(
+        ("id" = Uuid, Path, description = "Patient UUID"),
+        AuditLogQuery
+    ),
+    responses(
+        (status = 200, description = "Audit logs retrieved successfully"),
+        (status = 406, description = "Neither JSON nor NDJSON is acceptable", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn get_patient_audit_logs(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    Query(params): Query<AuditLogQuery>,
+) -> Response {
+    let Some(format) = ResponseFormat::negotiate(&headers) else {
+        return negotiation::not_acceptable();
+    };
+    let limit = params.limit.min(500);
+
+    let requester = Requester::from_headers(&headers);
+    let logs = state
+        .audit_log
+        .get_logs_for_entity("Patient", id, limit)
+        .and_then(|logs| privacy::redact_audit_logs(&state, &requester, logs, "GET /api/v1/patients/{id}/audit"));
+    audit_logs_response(format, logs)
+}
+
+/// Get recent audit logs
+#[utoipa::path(
+    get,
+    path = "/api/v1/audit/recent",
+    tag = "audit",
+    params(AuditLogQuery),
+    responses(
+        (status = 200, description = "Recent audit logs retrieved successfully"),
+        (status = 406, description = "Neither JSON nor NDJSON is acceptable", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn get_recent_audit_logs(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<AuditLogQuery>,
+) -> Response {
+    let Some(format) = ResponseFormat::negotiate(&headers) else {
+        return negotiation::not_acceptable();
+    };
+    let limit = params.limit.min(500);
+
+    let requester = Requester::from_headers(&headers);
+    let logs = state
+        .audit_log
+        .get_recent_logs(limit)
+        .and_then(|logs| privacy::redact_audit_logs(&state, &requester, logs, "GET /api/v1/audit/recent"));
+    audit_logs_response(format, logs)
+}
+
+/// User audit log query parameters
+#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct UserAuditLogQuery {
+    /// User ID to filter by
+    pub user_id: String,
+
+    /// Maximum number of results (default: 50, max: 500)
+    #[serde(default = "default_audit_limit")]
+    pub limit: i64,
+}
+
+/// Get audit logs by user
+#[utoipa::path(
+    get,
+    path = "/api/v1/audit/user",
+    tag = "audit",
+    params(UserAuditLogQuery),
+    responses(
+        (status = 200, description = "User audit logs retrieved successfully"),
+        (status = 406, description = "Neither JSON nor NDJSON is acceptable", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn get_user_audit_logs(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<UserAuditLogQuery>,
+) -> Response {
+    let Some(format) = ResponseFormat::negotiate(&headers) else {
+        return negotiation::not_acceptable();
+    };
+    let limit = params.limit.min(500);
+
+    let requester = Requester::from_headers(&headers);
+    let logs = state
+        .audit_log
+        .get_logs_by_user(&params.user_id, limit)
+        .and_then(|logs| privacy::redact_audit_logs(&state, &requester, logs, "GET /api/v1/audit/user"));
+    audit_logs_response(format, logs)
+}
+
+/// Audit log entries as a JSON envelope or NDJSON lines
+fn audit_logs_response(
+    format: ResponseFormat,
+    logs: crate::Result<Vec<crate::db::models::DbAuditLog>>,
+) -> Response {
+    match logs {
+        Ok(logs) if format == ResponseFormat::Ndjson => negotiation::ndjson(logs),
+        Ok(logs) => (StatusCode::OK, Json(ApiResponse::success(logs))).into_response(),
+        Err(e) => {
+            let error = ApiResponse::<Vec<crate::db::models::DbAuditLog>>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve audit logs: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+/// List the payloads that created or updated a patient, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/patients/{id}/messages",
+    tag = "audit",
+    params(
+        ("id" = Uuid, Path, description = "Patient UUID"),
+        AuditLogQuery
+    ),
+    responses(
+        (status = 200, description = "Archived messages retrieved successfully", body = Vec<ArchivedMessage>),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn get_patient_messages(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    Query(params): Query<AuditLogQuery>,
+) -> impl IntoResponse {
+    let limit = params.limit.min(500);
+    let requester = Requester::from_headers(&headers);
+
+    let messages = state
+        .message_archive
+        .list_for_patient(&id, limit)
+        .and_then(|messages| privacy::redact_messages(&state, &requester, messages, "GET /api/v1/patients/{id}/messages"));
+    match messages {
+        Ok(messages) => (StatusCode::OK, Json(ApiResponse::success(messages))),
+        Err(e) => {
+            let error = ApiResponse::<Vec<ArchivedMessage>>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve archived messages: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Get an archived inbound message
+#[utoipa::path(
+    get,
+    path = "/api/v1/messages/{id}",
+    tag = "audit",
+    params(
+        ("id" = Uuid, Path, description = "Archived message UUID")
+    ),
+    responses(
+        (status = 200, description = "Archived message found", body = ArchivedMessage),
+        (status = 404, description = "Archived message not found", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn get_archived_message(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let message = visible_message(
+        &state,
+        &headers,
+        state.message_archive.get_by_id(&id),
+        "GET /api/v1/messages/{id}",
+    );
+    archived_message_response(message, "Archived message", id)
+}
+
+/// Get the inbound message behind an audit log entry
+#[utoipa::path(
+    get,
+    path = "/api/v1/audit/{id}/message",
+    tag = "audit",
+    params(
+        ("id" = Uuid, Path, description = "Audit log entry UUID")
+    ),
+    responses(
+        (status = 200, description = "Archived message found", body = ArchivedMessage),
+        (status = 404, description = "No message was archived for the audit log entry", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn get_audit_entry_message(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let message = visible_message(
+        &state,
+        &headers,
+        state.message_archive.get_for_audit_entry(&id),
+        "GET /api/v1/audit/{id}/message",
+    );
+    archived_message_response(message, "Message for audit entry", id)
+}
+
+/// An archived message with its payload emptied when the requester sees
+/// its patient masked
+fn visible_message(
+    state: &AppState,
+    headers: &HeaderMap,
+    message: crate::Result<Option<ArchivedMessage>>,
+    endpoint: &str,
+) -> crate::Result<Option<ArchivedMessage>> {
+    let requester = Requester::from_headers(headers);
+    let messages = privacy::redact_messages(state, &requester, message?.into_iter().collect(), endpoint)?;
+    Ok(messages.into_iter().next())
+}
+
+fn archived_message_response(
+    message: crate::Result<Option<ArchivedMessage>>,
+    resource: &str,
+    id: Uuid,
+) -> (StatusCode, Json<ApiResponse<ArchivedMessage>>) {
+    match message {
+        Ok(Some(message)) => (StatusCode::OK, Json(ApiResponse::success(message))),
+        Ok(None) => {
+            let error = ApiResponse::<ArchivedMessage>::error(
+                "NOT_FOUND",
+                format!("{} with id '{}' not found", resource, id)
+            );
+            (StatusCode::NOT_FOUND, Json(error))
+        }
+        Err(e) => {
+            let error = ApiResponse::<ArchivedMessage>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve archived message: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Quarantine queue query parameters
+#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct QuarantineQuery {
+    /// Only records in this status: "pending", "resubmitted" or "discarded"
+    pub status: Option<String>,
+
+    /// Maximum number of records (default: 50, max: 500)
+    #[serde(default = "default_quarantine_limit")]
+    pub limit: i64,
+
+    /// Number of records to skip
+    #[serde(default)]
+    pub offset: i64,
+}
+
+fn default_quarantine_limit() -> i64 {
+    50
+}
+
+/// Resubmit a quarantined record
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ResubmitRequest {
+    /// Corrected payload; the quarantined payload is resubmitted as is when absent
+    pub payload: Option<String>,
+}
+
+/// List inbound records refused by validation, oldest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/quarantine",
+    tag = "quarantine",
+    params(QuarantineQuery),
+    responses(
+        (status = 200, description = "Quarantined records", body = Vec<QuarantinedRecord>),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn list_quarantined(
+    State(state): State<AppState>,
+    Query(params): Query<QuarantineQuery>,
+) -> impl IntoResponse {
+    let limit = params.limit.clamp(0, 500);
+
+    match state.quarantine.list(params.status.as_deref(), limit, params.offset.max(0)) {
+        Ok(records) => (StatusCode::OK, Json(ApiResponse::success(records))),
+        Err(e) => {
+            let error = ApiResponse::<Vec<QuarantinedRecord>>::error(
+                "DATABASE_ERROR",
+                format!("Failed to list quarantined records: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Get a quarantined record with the reasons it was refused
+#[utoipa::path(
+    get,
+    path = "/api/v1/quarantine/{id}",
+    tag = "quarantine",
+    params(
+        ("id" = Uuid, Path, description = "Quarantined record UUID")
+    ),
+    responses(
+        (status = 200, description = "Quarantined record found", body = QuarantinedRecord),
+        (status = 404, description = "Quarantined record not found", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn get_quarantined(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match find_quarantined(&state, id, false) {
+        Ok(record) => (StatusCode::OK, Json(ApiResponse::success(record))),
+        Err(response) => *response,
+    }
+}
+
+/// Resubmit a quarantined record, optionally corrected
+///
+/// The payload goes through the channel it first arrived on. When it is
+/// stored the record is closed as resubmitted; when it is refused again the
+/// corrected payload and the new errors are kept on the pending record.
+#[utoipa::path(
+    post,
+    path = "/api/v1/quarantine/{id}/resubmit",
+    tag = "quarantine",
+    params(
+        ("id" = Uuid, Path, description = "Quarantined record UUID")
+    ),
+    request_body = ResubmitRequest,
+    responses(
+        (status = 200, description = "Payload stored; the record is resubmitted", body = QuarantinedRecord),
+        (status = 404, description = "Quarantined record not found", body = crate::api::ApiErrorResponse),
+        (status = 409, description = "Record was already resubmitted or discarded", body = crate::api::ApiErrorResponse),
+        (status = 422, description = "Payload refused again; errors are in the details", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn resubmit_quarantined(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<ResubmitRequest>,
+) -> impl IntoResponse {
+    let record = match find_quarantined(&state, id, true) {
+        Ok(record) => record,
+        Err(response) => return *response,
+    };
+    let payload = request.payload.unwrap_or_else(|| record.payload.clone());
+
+    let stored = match quarantine::resubmit(&state, &record, &payload) {
+        Ok(Resubmission::Stored(patient_id)) => {
+            state.quarantine.resolve(&id, QUARANTINE_RESUBMITTED, &payload, Some(patient_id))
+        }
+        Ok(Resubmission::Refused(errors)) => {
+            if let Err(e) = state.quarantine.revise(&id, &payload, &errors) {
+                tracing::warn!("Failed to revise quarantined record {}: {}", id, e);
+            }
+            let error = ApiResponse::<QuarantinedRecord>::error(
+                "VALIDATION_ERROR",
+                "Resubmitted payload was refused"
+            ).with_details(errors);
+            return (StatusCode::UNPROCESSABLE_ENTITY, Json(error));
+        }
+        Err(e) => {
+            let error = ApiResponse::<QuarantinedRecord>::error(
+                "INTERNAL_ERROR",
+                format!("Failed to resubmit quarantined record: {}", e)
+            );
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+        }
+    };
+    if let Err(e) = stored {
+        tracing::warn!("Resubmitted quarantined record {} but could not close it: {}", id, e);
+    }
+    match find_quarantined(&state, id, false) {
+        Ok(record) => (StatusCode::OK, Json(ApiResponse::success(record))),
+        Err(response) => *response,
+    }
+}
+
+/// Discard a quarantined record
+#[utoipa::path(
+    delete,
+    path = "/api/v1/quarantine/{id}",
+    tag = "quarantine",
+    params(
+        ("id" = Uuid, Path, description = "Quarantined record UUID")
+    ),
+    responses(
+        (status = 204, description = "Record discarded"),
+        (status = 404, description = "Quarantined record not found", body = crate::api::ApiErrorResponse),
+        (status = 409, description = "Record was already resubmitted or discarded", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn discard_quarantined(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Response {
+    let record = match find_quarantined(&state, id, true) {
+        Ok(record) => record,
+        Err(response) => return response.into_response(),
+    };
+
+    match state.quarantine.resolve(&id, QUARANTINE_DISCARDED, &record.payload, None) {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => quarantine_conflict(id).into_response(),
+        Err(e) => {
+            let error = ApiResponse::<()>::error(
+                "DATABASE_ERROR",
+                format!("Failed to discard quarantined record: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+/// Look up a quarantined record, refusing resolved ones when `pending_only` is set
+fn find_quarantined(
+    state: &AppState,
+    id: Uuid,
+    pending_only: bool,
+) -> Result<QuarantinedRecord, HandlerError<QuarantinedRecord>> {
+    match state.quarantine.get_by_id(&id) {
+        Ok(Some(record)) if pending_only && record.status != QUARANTINE_PENDING => Err(Box::new(quarantine_conflict(id))),
+        Ok(Some(record)) => Ok(record),
+        Ok(None) => {
+            let error = ApiResponse::<QuarantinedRecord>::error(
+                "NOT_FOUND",
+                format!("Quarantined record with id '{}' not found", id)
+            );
+            Err(Box::new((StatusCode::NOT_FOUND, Json(error))))
+        }
+        Err(e) => {
+            let error = ApiResponse::<QuarantinedRecord>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve quarantined record: {}", e)
+            );
+            Err(Box::new((StatusCode::INTERNAL_SERVER_ERROR, Json(error))))
+        }
+    }
+}
+
+fn quarantine_conflict(id: Uuid) -> (StatusCode, Json<ApiResponse<QuarantinedRecord>>) {
+    let error = ApiResponse::<QuarantinedRecord>::error(
+        "CONFLICT",
+        format!("Quarantined record '{}' was already resubmitted or discarded", id)
+    );
+    (StatusCode::CONFLICT, Json(error))
+}
+
+/// A correction proposed by a patient
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ChangeRequestSubmission {
+    /// Fields to change and their proposed values
+    pub changes: DemographicChanges,
+    /// Why the record is wrong
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Change request list query parameters
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ChangeRequestQuery {
+    /// Only requests in this status: "pending", "approved" or "rejected"
+    pub status: Option<String>,
+
+    /// Only requests for this patient
+    pub patient_id: Option<Uuid>,
+
+    /// Maximum number of requests (default: 50, max: 500)
+    #[serde(default = "default_quarantine_limit")]
+    pub limit: i64,
+
+    /// Number of requests to skip
+    #[serde(default)]
+    pub offset: i64,
+}
+
+/// A steward's decision on a change request
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ChangeReviewRequest {
+    /// Note for the patient, e.g. why the request was rejected
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// Propose a correction to a patient's demographics
+///
+/// For patient portals, which may not update patients directly. The
+/// request waits for a steward to approve or reject it; the submitter is
+/// taken from `X-User-Id`.
+#[utoipa::path(
+    post,
+    path = "/api/v1/patients/{id}/change-requests",
+    tag = "change-requests",
+    params(
+        ("id" = Uuid, Path, description = "Patient UUID")
+    ),
+    request_body = ChangeRequestSubmission,
+    responses(
+        (status = 201, description = "Change request submitted", body = ChangeRequest),
+        (status = 400, description = "No changes proposed", body = crate::api::ApiErrorResponse),
+        (status = 404, description = "Patient not found", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn submit_change_request(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(payload): Json<ChangeRequestSubmission>,
+) -> impl IntoResponse {
+    if payload.changes.is_empty() {
+        let error = ApiResponse::<ChangeRequest>::error(
+            "VALIDATION_ERROR",
+            "At least one field must be changed"
+        );
+        return (StatusCode::BAD_REQUEST, Json(error));
+    }
+
+    match state.patient_repository.get_by_id(&id) {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            let error = ApiResponse::<ChangeRequest>::error(
+                "NOT_FOUND",
+                format!("Patient with id '{}' not found", id)
+            );
+            return (StatusCode::NOT_FOUND, Json(error));
+        }
+        Err(e) => {
+            let error = ApiResponse::<ChangeRequest>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve patient: {}", e)
+            );
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+        }
+    }
+
+    let requester = Requester::from_headers(&headers);
+    match state.change_requests.submit(id, &payload.changes, payload.reason, requester.user_id.clone()) {
+        Ok(request) => {
+            if let Err(e) = state.audit_log.log_create(
+                "ChangeRequest",
+                request.id,
+                serde_json::to_value(&request).unwrap_or_default(),
+                requester.user_id,
+                requester.ip_address,
+                requester.user_agent,
+            ) {
+                tracing::warn!("Failed to audit change request {}: {}", request.id, e);
+            }
+            (StatusCode::CREATED, Json(ApiResponse::success(request)))
+        }
+        Err(e) => {
+            let error = ApiResponse::<ChangeRequest>::error(
+                "DATABASE_ERROR",
+                format!("Failed to submit change request: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// List patient change requests, oldest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/change-requests",
+    tag = "change-requests",
+    params(ChangeRequestQuery),
+    responses(
+        (status = 200, description = "Change requests", body = Vec<ChangeRequest>),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn list_change_requests(
+    State(state): State<AppState>,
+    Query(params): Query<ChangeRequestQuery>,
+) -> impl IntoResponse {
+    let limit = params.limit.clamp(0, 500);
+
+    match state.change_requests.list(params.status.as_deref(), params.patient_id, limit, params.offset.max(0)) {
+        Ok(requests) => (StatusCode::OK, Json(ApiResponse::success(requests))),
+        Err(e) => {
+            let error = ApiResponse::<Vec<ChangeRequest>>::error(
+                "DATABASE_ERROR",
+                format!("Failed to list change requests: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Get a patient change request
+#[utoipa::path(
+    get,
+    path = "/api/v1/change-requests/{id}",
+    tag = "change-requests",
+    params(
+        ("id" = Uuid, Path, description = "Change request UUID")
+    ),
+    responses(
+        (status = 200, description = "Change request found", body = ChangeRequest),
+        (status = 404, description = "Change request not found", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn get_change_request(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match find_change_request(&state, id, false) {
+        Ok(request) => (StatusCode::OK, Json(ApiResponse::success(request))),
+        Err(response) => *response,
+    }
+}
+
+/// Approve a change request, applying it to the patient
+///
+/// The proposed fields overwrite the patient's, which is updated and
+/// reindexed like any other update. The reviewer is taken from `X-User-Id`
+/// and the decision is audited.
+#[utoipa::path(
+    post,
+    path = "/api/v1/change-requests/{id}/approve",
+    tag = "change-requests",
+    params(
+        ("id" = Uuid, Path, description = "Change request UUID")
+    ),
+    request_body = ChangeReviewRequest,
+    responses(
+        (status = 200, description = "Request approved and applied", body = ChangeRequest),
+        (status = 404, description = "Change request or patient not found", body = crate::api::ApiErrorResponse),
+        (status = 409, description = "Request was already approved or rejected", body = crate::api::ApiErrorResponse),
+        (status = 423, description = "Patient is locked by another steward", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn approve_change_request(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(payload): Json<ChangeReviewRequest>,
+) -> impl IntoResponse {
+    let request = match find_change_request(&state, id, true) {
+        Ok(request) => request,
+        Err(response) => return *response,
+    };
+    if let Err(response) = check_record_locks(&state, &[request.patient_id], &headers) {
+        return *response;
+    }
+
+    let existing = match state.patient_repository.get_by_id_for_update(&request.patient_id) {
+        Ok(Some(patient)) => patient,
+        Ok(None) => {
+            let error = ApiResponse::<ChangeRequest>::error(
+                "NOT_FOUND",
+                format!("Patient with id '{}' not found", request.patient_id)
+            );
+            return (StatusCode::NOT_FOUND, Json(error));
+        }
+        Err(e) => {
+            let error = ApiResponse::<ChangeRequest>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve patient: {}", e)
+            );
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+        }
+    };
+    let mut patient = existing.clone();
+    request.changes.apply_to(&mut patient);
+    patient.keep_verification_from(&existing);
+
+    match state.patient_repository.update(&patient) {
+        Ok(patient) => {
+            // Approved corrections are steward decisions and bypass survivorship
+            survivorship::record(&state, patient.id, Some(&existing), &patient, CHANGE_REQUEST_SOURCE);
+            if let Err(e) = state.search_engine.index_patient(&patient) {
+                tracing::warn!("Failed to update patient in search engine: {}", e);
+            }
+        }
+        Err(e) => {
+            let error = ApiResponse::<ChangeRequest>::error(
+                "DATABASE_ERROR",
+                format!("Failed to update patient: {}", e)
+            );
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+        }
+    }
+
+    review_change_request(&state, &request, CHANGE_APPROVED, payload.note, &headers)
+}
+
+/// Reject a change request, leaving the patient as it is
+#[utoipa::path(
+    post,
+    path = "/api/v1/change-requests/{id}/reject",
+    tag = "change-requests",
+    params(
+        ("id" = Uuid, Path, description = "Change request UUID")
+    ),
+    request_body = ChangeReviewRequest,
+    responses(
+        (status = 200, description = "Request rejected", body = ChangeRequest),
+        (status = 404, description = "Change request not found", body = crate::api::ApiErrorResponse),
+        (status = 409, description = "Request was already approved or rejected", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn reject_change_request(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(payload): Json<ChangeReviewRequest>,
+) -> impl IntoResponse {
+    let request = match find_change_request(&state, id, true) {
+        Ok(request) => request,
+        Err(response) => return *response,
+    };
+    review_change_request(&state, &request, CHANGE_REJECTED, payload.note, &headers)
+}
+
+/// Close a pending change request with a steward's decision and audit it
+fn review_change_request(
+    state: &AppState,
+    request: &ChangeRequest,
+    status: &str,
+    note: Option<String>,
+    headers: &HeaderMap,
+) -> (StatusCode, Json<ApiResponse<ChangeRequest>>) {
+    let reviewer = Requester::from_headers(headers);
+    match state.change_requests.resolve(&request.id, status, reviewer.user_id.clone(), note.clone()) {
+        Ok(true) => {}
+        Ok(false) => return change_request_conflict(request.id),
+        Err(e) => {
+            let error = ApiResponse::<ChangeRequest>::error(
+                "DATABASE_ERROR",
+                format!("Failed to close change request: {}", e)
+            );
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+        }
+    }
+
+    if let Err(e) = state.audit_log.log_update(
+        "ChangeRequest",
+        request.id,
+        serde_json::json!({ "status": request.status }),
+        serde_json::json!({ "status": status, "patient_id": request.patient_id, "note": note }),
+        reviewer.user_id,
+        reviewer.ip_address,
+        reviewer.user_agent,
+    ) {
+        tracing::warn!("Failed to audit review of change request {}: {}", request.id, e);
+    }
+
+    match find_change_request(state, request.id, false) {
+        Ok(request) => (StatusCode::OK, Json(ApiResponse::success(request))),
+        Err(response) => *response,
+    }
+}
+
+/// Look up a change request, refusing reviewed ones when `pending_only` is set
+fn find_change_request(
+    state: &AppState,
+    id: Uuid,
+    pending_only: bool,
+) -> Result<ChangeRequest, HandlerError<ChangeRequest>> {
+    match state.change_requests.get_by_id(&id) {
+        Ok(Some(request)) if pending_only && request.status != CHANGE_PENDING => Err(Box::new(change_request_conflict(id))),
+        Ok(Some(request)) => Ok(request),
+        Ok(None) => {
+            let error = ApiResponse::<ChangeRequest>::error(
+                "NOT_FOUND",
+                format!("Change request with id '{}' not found", id)
+            );
+            Err(Box::new((StatusCode::NOT_FOUND, Json(error))))
+        }
+        Err(e) => {
+            let error = ApiResponse::<ChangeRequest>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve change request: {}", e)
+            );
+            Err(Box::new((StatusCode::INTERNAL_SERVER_ERROR, Json(error))))
+        }
+    }
+}
+
+fn change_request_conflict(id: Uuid) -> (StatusCode, Json<ApiResponse<ChangeRequest>>) {
+    let error = ApiResponse::<ChangeRequest>::error(
+        "CONFLICT",
+        format!("Change request '{}' was already approved or rejected", id)
+    );
+    (StatusCode::CONFLICT, Json(error))
+}
+
+/// Index snapshot/restore request
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct IndexSnapshotRequest {
+    /// Directory to write the snapshot to, or read it from, relative to `search.backup_dir`
+    pub path: String,
+}
+
+/// The requester, if they hold one of `server.admin_roles`; 403 otherwise
+fn require_admin<T>(state: &AppState, headers: &HeaderMap, action: &str) -> Result<Requester, HandlerError<T>> {
+    let requester = Requester::from_headers(headers);
+    if !requester.is_admin(&state.config.server) {
+        let error = ApiResponse::<T>::error("FORBIDDEN", format!("Only administrators may {}", action));
+        return Err(Box::new((StatusCode::FORBIDDEN, Json(error))));
+    }
+    Ok(requester)
+}
+
+/// Refuse requesters without one of `server.admin_roles`, and resolve the
+/// requested snapshot inside `search.backup_dir`
+fn backup_path(
+    state: &AppState,
+    headers: &HeaderMap,
+    requested: &str,
+) -> Result<std::path::PathBuf, HandlerError<crate::search::SnapshotInfo>> {
+    require_admin(state, headers, "snapshot or restore the search index")?;
+
+    match crate::search::snapshot_path(&state.config.search.backup_dir, requested) {
+        Ok(path) => Ok(path),
+        Err(crate::Error::Validation(message)) => {
+            let error = ApiResponse::<crate::search::SnapshotInfo>::error("VALIDATION_ERROR", message);
+            Err(Box::new((StatusCode::BAD_REQUEST, Json(error))))
+        }
+        Err(e) => {
+            let error = ApiResponse::<crate::search::SnapshotInfo>::error(
+                "SEARCH_ERROR",
+                format!("Failed to resolve snapshot path: {}", e)
+            );
+            Err(Box::new((StatusCode::INTERNAL_SERVER_ERROR, Json(error))))
+        }
+    }
+}
+
+/// Take a snapshot of the search index
+///
+/// Only users holding one of `server.admin_roles` in `X-User-Roles` may
+/// take a snapshot, and only inside `search.backup_dir`.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/search/snapshot",
+    tag = "admin",
+    request_body = IndexSnapshotRequest,
+    responses(
+        (status = 200, description = "Snapshot written", body = crate::search::SnapshotInfo),
+        (status = 400, description = "Path is outside the backup directory", body = crate::api::ApiErrorResponse),
+        (status = 403, description = "Requester is not an administrator", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Snapshot failed", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn snapshot_search_index(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<IndexSnapshotRequest>,
+) -> impl IntoResponse {
+    let path = match backup_path(&state, &headers, &payload.path) {
+        Ok(path) => path,
+        Err(response) => return *response,
+    };
+
+    match state.search_engine.snapshot(&path) {
+        Ok(info) => {
+            tracing::info!("Search index snapshot written to {}", info.path);
+            (StatusCode::OK, Json(ApiResponse::success(info)))
+        }
+        Err(e) => {
+            let error = ApiResponse::<crate::search::SnapshotInfo>::error(
+                "SEARCH_ERROR",
+                format!("Failed to snapshot search index: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Restore the search index from a snapshot
+///
+/// Only users holding one of `server.admin_roles` in `X-User-Roles` may
+/// restore, and only from inside `search.backup_dir`.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/search/restore",
+    tag = "admin",
+    request_body = IndexSnapshotRequest,
+    responses(
+        (status = 200, description = "Index restored", body = crate::search::SnapshotInfo),
+        (status = 400, description = "Path is outside the backup directory", body = crate::api::ApiErrorResponse),
+        (status = 403, description = "Requester is not an administrator", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Restore failed", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn restore_search_index(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<IndexSnapshotRequest>,
+) -> impl IntoResponse {
+    let path = match backup_path(&state, &headers, &payload.path) {
+        Ok(path) => path,
+        Err(response) => return *response,
+    };
+
+    match state.search_engine.restore(&path) {
+        Ok(info) => {
+            tracing::info!("Search index restored from {}", info.path);
+            (StatusCode::OK, Json(ApiResponse::success(info)))
+        }
+        Err(e) => {
+            let error = ApiResponse::<crate::search::SnapshotInfo>::error(
+                "SEARCH_ERROR",
+                format!("Failed to restore search index: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Reindex every active patient in the background
+///
+/// Patients are read a page at a time and committed in batches of
+/// `search.bulk_index.batch_size`, replacing their existing documents, so
+/// search keeps working throughout. Poll the job for progress.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/search/reindex",
+    tag = "admin",
+    responses(
+        (status = 202, description = "Reindex job started", body = crate::jobs::Job)
+    )
+)]
+pub async fn reindex_search(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let config = &state.config.search.bulk_index;
+    // The statistics are only a progress estimate; the sandbox has none
+    let total = state.statistics.patient_counts().ok().map(|counts| counts.active.max(0) as u64);
+    let patients = crate::search::active_patients(state.patient_repository.clone(), config.batch_size as i64);
+
+    let handle = state.jobs.create("search_reindex", Requester::from_headers(&headers).user_id);
+    let job = handle.snapshot();
+    crate::search::BulkIndexer::new(state.search_engine.clone(), config).spawn(patients, total, handle);
+
+    match job {
+        Some(job) => (StatusCode::ACCEPTED, Json(ApiResponse::success(job))),
+        None => {
+            let error = ApiResponse::<crate::jobs::Job>::error(
+                "INTERNAL_ERROR",
+                "Reindex job was not registered".to_string()
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Group linked patients into clusters and flag those whose members do not
+/// all match each other
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/clusters",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Cluster report", body = crate::matching::ClusterReport),
+        (status = 500, description = "Clustering failed", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn run_clustering(
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let pair_matcher = state.pair_score_matcher();
+    let job = crate::matching::ClusteringJob::new(&pair_matcher, state.config.clustering.clone());
+
+    match job.run(state.patient_repository.as_ref()) {
+        Ok(report) => {
+            tracing::info!(
+                "Clustering: {} patients in {} clusters; {} conflicting",
+                report.patients_checked,
+                report.clusters,
+                report.conflicting.len()
+            );
+            (StatusCode::OK, Json(ApiResponse::success(report)))
+        }
+        Err(e) => {
+            let error = ApiResponse::<crate::matching::ClusterReport>::error(
+                "MATCH_ERROR",
+                format!("Clustering failed: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Re-score existing links under the current matching configuration
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/relink",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Re-linkage report", body = crate::matching::RelinkageReport),
+        (status = 500, description = "Re-linkage review failed", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn run_relinkage(
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let pair_matcher = state.pair_score_matcher();
+    let job = crate::matching::RelinkageJob::new(state.matcher.as_ref()).with_pair_matcher(&pair_matcher);
+
+    match job.run(
+        state.patient_repository.as_ref(),
+        state.source_records.as_ref(),
+        &state.match_scores,
+    ) {
+        Ok(report) => {
+            tracing::info!(
+                "Re-linkage review: {} links, {} non-links checked; {} weakened, {} new matches",
+                report.links_checked,
+                report.non_links_checked,
+                report.weakened_links.len(),
+                report.new_matches.len()
+            );
+            (StatusCode::OK, Json(ApiResponse::success(report)))
+        }
+        Err(e) => {
+            let error = ApiResponse::<crate::matching::RelinkageReport>::error(
+                "MATCH_ERROR",
+                format!("Re-linkage review failed: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Recompute the confidence of every current source record link now
+///
+/// The same job runs every `link_confidence.interval_hours` when
+/// `link_confidence.enabled`.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/link-confidence",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Link confidence report", body = crate::jobs::LinkConfidenceReport),
+        (status = 500, description = "Link confidence job failed", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn run_link_confidence(
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let job = state.link_confidence_job();
+    match tokio::task::spawn_blocking(move || job.run()).await {
+        Ok(Ok(report)) => {
+            tracing::info!(
+                "Link confidence: {} links checked; {} lowered, {} need re-verification",
+                report.links_checked,
+                report.lowered,
+                report.flagged
+            );
+            (StatusCode::OK, Json(ApiResponse::success(report)))
+        }
+        Ok(Err(e)) => {
+            let error = ApiResponse::<crate::jobs::LinkConfidenceReport>::error(
+                "DATABASE_ERROR",
+                format!("Link confidence job failed: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+        Err(e) => {
+            let error = ApiResponse::<crate::jobs::LinkConfidenceReport>::error(
+                "INTERNAL_ERROR",
+                format!("Link confidence job stopped: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Re-verification queue query parameters
+#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct LinkReverificationQuery {
+    /// Maximum number of links (default: 50, max: 500)
+    #[serde(default = "default_reverification_limit")]
+    pub limit: i64,
+
+    /// Number of links to skip
+    #[serde(default)]
+    pub offset: i64,
+}
+
+fn default_reverification_limit() -> i64 {
+    50
+}
+
+/// Automatic source record links flagged for re-verification, least
+/// confident first
+#[utoipa::path(
+    get,
+    path = "/api/v1/links/reverification",
+    tag = "matching",
+    params(LinkReverificationQuery),
+    responses(
+        (status = 200, description = "Links awaiting re-verification", body = Vec<crate::models::SourceRecordLink>),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn list_links_needing_reverification(
+    State(state): State<AppState>,
+    Query(query): Query<LinkReverificationQuery>,
+) -> impl IntoResponse {
+    let limit = query.limit.clamp(1, 500);
+    match state.source_records.list_needing_reverification(limit, query.offset.max(0)) {
+        Ok(links) => (StatusCode::OK, Json(ApiResponse::success(links))),
+        Err(e) => {
+            let error = ApiResponse::<Vec<crate::models::SourceRecordLink>>::error(
+                "DATABASE_ERROR",
+                format!("Failed to list links awaiting re-verification: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Confirm that a source record still belongs to its enterprise record
+///
+/// Restores the link to full confidence and clears its re-verification
+/// flag until the enterprise record changes again. The steward is taken
+/// from `X-User-Id` and the verification is audited. To move the source
+/// record instead, re-link it.
+#[utoipa::path(
+    post,
+    path = "/api/v1/links/{id}/verify",
+    tag = "matching",
+    params(
+        ("id" = Uuid, Path, description = "Source record link UUID")
+    ),
+    responses(
+        (status = 200, description = "Link verified", body = crate::models::SourceRecordLink),
+        (status = 404, description = "No current link with this ID", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn verify_link(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let steward = Requester::from_headers(&headers);
+    match state.source_records.verify_link(&id, steward.user_id.clone()) {
+        Ok(Some(link)) => {
+            if let Err(e) = state.audit_log.log_update(
+                "SourceRecordLink",
+                id,
+                serde_json::Value::Null,
+                serde_json::json!({
+                    "source_record_id": link.source_record_id,
+                    "patient_id": link.patient_id,
+                    "confidence": link.confidence,
+                    "verified_at": link.verified_at,
+                }),
+                steward.user_id,
+                steward.ip_address,
+                steward.user_agent,
+            ) {
+                tracing::warn!("Failed to audit verification of link {}: {}", id, e);
+            }
+            (StatusCode::OK, Json(ApiResponse::success(link)))
+        }
+        Ok(None) => {
+            let error = ApiResponse::<crate::models::SourceRecordLink>::error(
+                "NOT_FOUND",
+                format!("No current link with id '{}'", id)
+            );
+            (StatusCode::NOT_FOUND, Json(error))
+        }
+        Err(e) => {
+            let error = ApiResponse::<crate::models::SourceRecordLink>::error(
+                "DATABASE_ERROR",
+                format!("Failed to verify link: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Data integrity check request
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct IntegrityCheckRequest {
+    /// Repair the problems that have a safe fix; otherwise only report
+    #[serde(default)]
+    pub fix: bool,
+
+    /// User or system requesting the check, recorded in the audit log with each repair
+    #[serde(default)]
+    pub requested_by: Option<String>,
+}
+
+/// Check the database and search index for integrity problems, optionally
+/// repairing them
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/integrity",
+    tag = "admin",
+    request_body = IntegrityCheckRequest,
+    responses(
+        (status = 200, description = "Integrity report", body = crate::jobs::IntegrityReport),
+        (status = 500, description = "Integrity check failed", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn check_integrity(
+    State(state): State<AppState>,
+    Json(payload): Json<IntegrityCheckRequest>,
+) -> impl IntoResponse {
+    let check = state.integrity_check();
+
+    match check.run(payload.fix, payload.requested_by.as_deref()) {
+        Ok(report) => {
+            tracing::info!(
+                "Integrity check: {} problems found, {} repaired",
+                report.found(),
+                report.fixed()
+            );
+            (StatusCode::OK, Json(ApiResponse::success(report)))
+        }
+        Err(e) => {
+            let error = ApiResponse::<crate::jobs::IntegrityReport>::error(
+                "DATABASE_ERROR",
+                format!("Integrity check failed: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Re-read the configuration and apply matching, load shedding and log level settings
+///
+/// Only users holding one of `server.admin_roles` in `X-User-Roles` may reload.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/config/reload",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Settings before and after the reload", body = crate::reload::ReloadReport),
+        (status = 400, description = "Invalid configuration; nothing was applied", body = crate::api::ApiErrorResponse),
+        (status = 403, description = "Requester is not an administrator", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Configuration could not be read or applied", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn reload_config(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(response) = require_admin::<crate::reload::ReloadReport>(&state, &headers, "reload the configuration") {
+        return *response;
+    }
+
+    match state.reload_config() {
+        Ok(report) => (StatusCode::OK, Json(ApiResponse::success(report))),
+        Err(e @ (crate::Error::Validation(_) | crate::Error::Config(_))) => {
+            let error = ApiResponse::<crate::reload::ReloadReport>::error("VALIDATION_ERROR", e.to_string());
+            (StatusCode::BAD_REQUEST, Json(error))
+        }
+        Err(e) => {
+            let error = ApiResponse::<crate::reload::ReloadReport>::error(
+                "CONFIG_ERROR",
+                format!("Configuration reload failed: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// A matching profile as it applies now
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MatchingProfileView {
+    pub name: String,
+
+    /// Match threshold in effect, from the profile or else `default`
+    pub threshold_score: f64,
+
+    /// Component weights in effect, from the profile or else `default`
+    pub weights: crate::config::MatchWeights,
+
+    /// The profile's own settings
+    pub settings: crate::config::MatchingProfile,
+
+    /// Stored version in effect; absent for profiles from the configuration file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<i32>,
+}
+
+/// New settings for a matching profile
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MatchingProfileUpdate {
+    #[serde(flatten)]
+    pub settings: crate::config::MatchingProfile,
+
+    /// Why the change is made, kept with the version
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// Restore an earlier version of a matching profile
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MatchingProfileRestore {
+    /// Version to restore
+    pub version: i32,
+
+    /// Why the version is restored, kept with the new version
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// List every matching profile with the settings in effect
+///
+/// `default` is the profile of requests that name none; storing it changes
+/// the threshold and weights in `matching`.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/matching/profiles",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Matching profiles, default first", body = Vec<MatchingProfileView>)
+    )
+)]
+pub async fn list_matching_profiles(
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let profiles: Vec<MatchingProfileView> = state
+        .matching_profile_names()
+        .into_iter()
+        .filter_map(|name| matching_profile_view(&state, name))
+        .collect();
+    (StatusCode::OK, Json(ApiResponse::success(profiles)))
+}
+
+/// Get a matching profile with the settings in effect
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/matching/profiles/{name}",
+    tag = "admin",
+    params(
+        ("name" = String, Path, description = "Profile name")
+    ),
+    responses(
+        (status = 200, description = "Matching profile found", body = MatchingProfileView),
+        (status = 404, description = "No such profile", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn get_matching_profile(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    match matching_profile_view(&state, name.clone()) {
+        Some(view) => (StatusCode::OK, Json(ApiResponse::success(view))),
+        None => {
+            let error = ApiResponse::<MatchingProfileView>::error(
+                "NOT_FOUND",
+                format!("Matching profile '{}' not found", name)
+            );
+            (StatusCode::NOT_FOUND, Json(error))
+        }
+    }
+}
+
+/// Store new settings for a matching profile, creating it if needed
+///
+/// The settings are stored as the profile's next version and apply to
+/// requests that start afterwards, on other instances within
+/// `matching_settings.refresh_secs`. A stored profile replaces the
+/// configured one of the same name. Only users holding one of
+/// `server.admin_roles` in `X-User-Roles` may store settings, and the change
+/// is audited, with the user taken from `X-User-Id`.
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/matching/profiles/{name}",
+    tag = "admin",
+    params(
+        ("name" = String, Path, description = "Profile name")
+    ),
+    request_body = MatchingProfileUpdate,
+    responses(
+        (status = 200, description = "Version stored and applied", body = crate::models::MatchingSettingsVersion),
+        (status = 400, description = "Invalid name or settings; nothing was stored", body = crate::api::ApiErrorResponse),
+        (status = 403, description = "Requester is not an administrator", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn update_matching_profile(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<MatchingProfileUpdate>,
+) -> impl IntoResponse {
+    let requester = match require_admin(&state, &headers, "change matching profiles") {
+        Ok(requester) => requester,
+        Err(response) => return *response,
+    };
+    save_matching_profile(&state, &name, &payload.settings, payload.note, requester)
+}
+
+/// List every stored version of a matching profile, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/matching/profiles/{name}/versions",
+    tag = "admin",
+    params(
+        ("name" = String, Path, description = "Profile name")
+    ),
+    responses(
+        (status = 200, description = "Stored versions; empty if the profile was never stored", body = Vec<crate::models::MatchingSettingsVersion>),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn list_matching_profile_versions(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    match state.matching_settings.history(&name) {
+        Ok(versions) => (StatusCode::OK, Json(ApiResponse::success(versions))),
+        Err(e) => {
+            let error = ApiResponse::<Vec<crate::models::MatchingSettingsVersion>>::error(
+                "DATABASE_ERROR",
+                format!("Failed to list matching profile versions: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Restore an earlier version of a matching profile
+///
+/// The version's settings are stored again as the newest version, so the
+/// history keeps every change. Only users holding one of
+/// `server.admin_roles` in `X-User-Roles` may restore.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/matching/profiles/{name}/restore",
+    tag = "admin",
+    params(
+        ("name" = String, Path, description = "Profile name")
+    ),
+    request_body = MatchingProfileRestore,
+    responses(
+        (status = 200, description = "Version restored and applied", body = crate::models::MatchingSettingsVersion),
+        (status = 400, description = "Settings no longer valid; nothing was stored", body = crate::api::ApiErrorResponse),
+        (status = 403, description = "Requester is not an administrator", body = crate::api::ApiErrorResponse),
+        (status = 404, description = "No such version", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Database error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn restore_matching_profile(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<MatchingProfileRestore>,
+) -> impl IntoResponse {
+    let requester = match require_admin(&state, &headers, "change matching profiles") {
+        Ok(requester) => requester,
+        Err(response) => return *response,
+    };
+    let earlier = match state.matching_settings.get_version(&name, payload.version) {
+        Ok(Some(earlier)) => earlier,
+        Ok(None) => {
+            let error = ApiResponse::<crate::models::MatchingSettingsVersion>::error(
+                "NOT_FOUND",
+                format!("Matching profile '{}' has no version {}", name, payload.version)
+            );
+            return (StatusCode::NOT_FOUND, Json(error));
+        }
+        Err(e) => {
+            let error = ApiResponse::<crate::models::MatchingSettingsVersion>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve matching profile version: {}", e)
+            );
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+        }
+    };
+    let note = payload.note.or_else(|| Some(format!("Restored version {}", earlier.version)));
+    save_matching_profile(&state, &name, &earlier.settings, note, requester)
+}
+
+/// The profile called `name` with its effective threshold and weights
+fn matching_profile_view(state: &AppState, name: String) -> Option<MatchingProfileView> {
+    let settings = state.matching_profile(&name)?;
+    let matching = settings.apply(&state.matching_config());
+    let version = state.config_reload.stored_profile(&name).map(|stored| stored.version);
+    Some(MatchingProfileView {
+        name,
+        threshold_score: matching.threshold_score,
+        weights: matching.weights,
+        settings,
+        version,
+    })
+}
+
+/// Store a profile version and answer with it
+fn save_matching_profile(
+    state: &AppState,
+    name: &str,
+    settings: &crate::config::MatchingProfile,
+    note: Option<String>,
+    requester: Requester,
+) -> (StatusCode, Json<ApiResponse<crate::models::MatchingSettingsVersion>>) {
+    let valid_name = !name.is_empty()
+        && name.len() <= 100
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid_name {
+        let error = ApiResponse::<crate::models::MatchingSettingsVersion>::error(
+            "VALIDATION_ERROR",
+            "Profile names are 1 to 100 letters, digits, '-', '_' or '.'"
+        );
+        return (StatusCode::BAD_REQUEST, Json(error));
+    }
+
+    match state.save_matching_profile(name, settings, requester.user_id, note) {
+        Ok(version) => (StatusCode::OK, Json(ApiResponse::success(version))),
+        Err(e @ (crate::Error::Validation(_) | crate::Error::Config(_))) => {
+            let error = ApiResponse::<crate::models::MatchingSettingsVersion>::error("VALIDATION_ERROR", e.to_string());
+            (StatusCode::BAD_REQUEST, Json(error))
+        }
+        Err(e) => {
+            let error = ApiResponse::<crate::models::MatchingSettingsVersion>::error(
+                "DATABASE_ERROR",
+                format!("Failed to store matching profile: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Event replay request
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReplayRequest {
+    /// Replay events at or after this time; omit to rebuild from the beginning
+    #[serde(default)]
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Rebuild the search index by replaying the patient event topic
+///
+/// Only users holding one of `server.admin_roles` in `X-User-Roles` may
+/// replay events.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/replay",
+    tag = "admin",
+    request_body = ReplayRequest,
+    responses(
+        (status = 200, description = "Replay completed", body = crate::streaming::replay::ReplayReport),
+        (status = 403, description = "Requester is not an administrator", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Replay failed", body = crate::api::ApiErrorResponse),
+        (status = 501, description = "The event broker does not support replay", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn replay_events(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ReplayRequest>,
+) -> impl IntoResponse {
+    use crate::streaming::replay::{replay, ReplayStart};
+
+    if let Err(response) = require_admin::<crate::streaming::replay::ReplayReport>(&state, &headers, "replay events") {
+        return *response;
+    }
+
+    let start = match payload.from {
+        Some(from) => ReplayStart::Timestamp(from),
+        None => ReplayStart::Beginning,
+    };
+    let mut search_index = crate::search::SearchIndexProjection::new(state.search_engine.as_ref());
+
+    match replay(state.event_source.as_ref(), start, &mut [&mut search_index]) {
+        Ok(report) => (StatusCode::OK, Json(ApiResponse::success(report))),
+        Err(crate::Error::Unsupported(message)) => {
+            let error = ApiResponse::<crate::streaming::replay::ReplayReport>::error("UNSUPPORTED", message);
+            (StatusCode::NOT_IMPLEMENTED, Json(error))
+        }
+        Err(e) => {
+            let error = ApiResponse::<crate::streaming::replay::ReplayReport>::error(
+                "STREAMING_ERROR",
+                format!("Replay failed: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Start removing the records contributed by a source system
+///
+/// Runs in the background; poll the returned job for progress and the report.
+/// Only users holding one of `server.admin_roles` in `X-User-Roles` may
+/// purge, and the purge is audited as the `X-User-Id` user.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/purge",
+    tag = "admin",
+    request_body = crate::jobs::SourcePurgeRequest,
+    responses(
+        (status = 202, description = "Purge job started", body = crate::jobs::Job),
+        (status = 400, description = "Invalid request", body = crate::api::ApiErrorResponse),
+        (status = 403, description = "Requester is not an administrator", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn start_source_purge(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(mut payload): Json<crate::jobs::SourcePurgeRequest>,
+) -> impl IntoResponse {
+    let requester = match require_admin::<crate::jobs::Job>(&state, &headers, "purge a source system") {
+        Ok(requester) => requester,
+        Err(response) => return *response,
+    };
+    payload.requested_by = requester.user_id;
+
+    if payload.source_system.trim().is_empty() {
+        let error = ApiResponse::<crate::jobs::Job>::error(
+            "VALIDATION_ERROR",
+            "source_system is required".to_string()
+        );
+        return (StatusCode::BAD_REQUEST, Json(error));
+    }
+
+    let handle = state.jobs.create("source_purge", payload.requested_by.clone());
+    tracing::info!(
+        "Source purge job {} started for '{}' ({:?}{})",
+        handle.id(),
+        payload.source_system,
+        payload.mode,
+        if payload.dry_run { ", dry run" } else { "" }
+    );
+
+    let job = handle.snapshot();
+    crate::jobs::SourcePurgeJob::new(
+        state.patient_repository.clone(),
+        state.source_records.clone(),
+        state.search_engine.clone(),
+    )
+    .with_audit_log(state.audit_log.clone())
+    .spawn(payload, handle);
+
+    match job {
+        Some(job) => (StatusCode::ACCEPTED, Json(ApiResponse::success(job))),
+        None => {
+            let error = ApiResponse::<crate::jobs::Job>::error(
+                "INTERNAL_ERROR",
+                "Purge job was not registered".to_string()
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Start a bulk NDJSON export of active patients, or of a patient group's
+/// members, de-identified by default
+///
+/// Only users holding one of `server.admin_roles` in `X-User-Roles` may
+/// export, and the export is audited as the `X-User-Id` user.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/export",
+    tag = "admin",
+    request_body = crate::export::ExportRequest,
+    responses(
+        (status = 202, description = "Export job started", body = crate::jobs::Job),
+        (status = 400, description = "Invalid request or de-identification not configured", body = crate::api::ApiErrorResponse),
+        (status = 403, description = "Requester is not an administrator", body = crate::api::ApiErrorResponse),
+        (status = 404, description = "Group not found", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn start_export(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(mut payload): Json<crate::export::ExportRequest>,
+) -> impl IntoResponse {
+    let requester = match require_admin::<crate::jobs::Job>(&state, &headers, "export patients") {
+        Ok(requester) => requester,
+        Err(response) => return *response,
+    };
+    payload.requested_by = requester.user_id;
+
+    if payload.path.trim().is_empty() {
+        let error = ApiResponse::<crate::jobs::Job>::error(
+            "VALIDATION_ERROR",
+            "path is required".to_string()
+        );
+        return (StatusCode::BAD_REQUEST, Json(error));
+    }
+
+    let mut exporter = crate::export::PatientExporter::new(state.patient_repository.clone())
+        .with_audit_log(state.audit_log.clone());
+    if payload.deidentify {
+        match crate::export::Deidentifier::from_config(&state.config.export) {
+            Ok(deidentifier) => exporter = exporter.with_deidentifier(deidentifier),
+            Err(e) => {
+                let error = ApiResponse::<crate::jobs::Job>::error("VALIDATION_ERROR", e.to_string());
+                return (StatusCode::BAD_REQUEST, Json(error));
+            }
+        }
+    }
+    if let Some(group_id) = payload.group {
+        match patient_group::<crate::jobs::Job>(&state, &group_id) {
+            Ok(group) => exporter = exporter.with_group(group),
+            Err(response) => return *response,
+        }
+    }
+
+    let handle = state.jobs.create("patient_export", payload.requested_by.clone());
+    let job = handle.snapshot();
+    exporter.spawn(payload, handle);
+
+    match job {
+        Some(job) => (StatusCode::ACCEPTED, Json(ApiResponse::success(job))),
+        None => {
+            let error = ApiResponse::<crate::jobs::Job>::error(
+                "INTERNAL_ERROR",
+                "Export job was not registered".to_string()
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Streaming export query parameters
+#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct ExportStreamQuery {
+    /// De-identify patients using `export.hmac_key` (default true)
+    #[serde(default = "default_deidentify")]
+    pub deidentify: bool,
+
+    /// Export only the members of this patient group
+    pub group: Option<Uuid>,
+}
+
+fn default_deidentify() -> bool {
+    true
+}
+
+/// Stream every active patient, or a patient group's members, to the client
+/// as NDJSON, de-identified by default
+///
+/// Only users holding one of `server.admin_roles` in `X-User-Roles` may
+/// export, and the export is audited as the `X-User-Id` user.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/export",
+    tag = "admin",
+    params(ExportStreamQuery),
+    responses(
+        (status = 200, description = "One patient per line", body = Patient, content_type = "application/x-ndjson"),
+        (status = 400, description = "De-identification not configured", body = crate::api::ApiErrorResponse),
+        (status = 403, description = "Requester is not an administrator", body = crate::api::ApiErrorResponse),
+        (status = 404, description = "Group not found", body = crate::api::ApiErrorResponse),
+        (status = 406, description = "NDJSON is not acceptable", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn stream_export(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<ExportStreamQuery>,
+) -> Response {
+    if !negotiation::accepts_ndjson(&headers) {
+        return negotiation::not_acceptable();
+    }
+    let requester = match require_admin::<()>(&state, &headers, "export patients") {
+        Ok(requester) => requester,
+        Err(response) => return response.into_response(),
+    };
+
+    let mut exporter = crate::export::PatientExporter::new(state.patient_repository.clone())
+        .with_audit_log(state.audit_log.clone());
+    if params.deidentify {
+        match crate::export::Deidentifier::from_config(&state.config.export) {
+            Ok(deidentifier) => exporter = exporter.with_deidentifier(deidentifier),
+            Err(e) => {
+                let error = ApiResponse::<()>::error("VALIDATION_ERROR", e.to_string());
+                return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+            }
+        }
+    }
+    if let Some(group_id) = params.group {
+        match patient_group::<()>(&state, &group_id) {
+            Ok(group) => exporter = exporter.with_group(group),
+            Err(response) => return response.into_response(),
+        }
+    }
+
+    let chunks = tokio_stream::wrappers::ReceiverStream::new(exporter.stream(requester.user_id));
+    negotiation::ndjson_body(axum::body::Body::from_stream(chunks))
+}
+
+/// Start a resumable bulk import of NDJSON patients from a server-side file
+///
+/// The import's ID is the ID of the job started here. Progress is
+/// checkpointed, so an import that stops part way through can be resumed
+/// through `/api/v1/admin/import/{id}/resume`. Only users holding one of
+/// `server.admin_roles` in `X-User-Roles` may import, and only files inside
+/// `import.import_dir`.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/import",
+    tag = "admin",
+    request_body = crate::import::ImportRequest,
+    responses(
+        (status = 202, description = "Import job started", body = crate::jobs::Job),
+        (status = 400, description = "Invalid request or file outside the import directory", body = crate::api::ApiErrorResponse),
+        (status = 403, description = "Requester is not an administrator", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Checkpoint could not be stored", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn start_import(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<crate::import::ImportRequest>,
+) -> impl IntoResponse {
+    let requester = match require_admin::<crate::jobs::Job>(&state, &headers, "import patients") {
+        Ok(requester) => requester,
+        Err(response) => return *response,
+    };
+
+    if payload.path.trim().is_empty() {
+        let error = ApiResponse::<crate::jobs::Job>::error(
+            "VALIDATION_ERROR",
+            "path is required".to_string()
+        );
+        return (StatusCode::BAD_REQUEST, Json(error));
+    }
+
+    let path = match crate::import::import_path(&state.config.import.import_dir, &payload.path) {
+        Ok(path) => path,
+        Err(crate::Error::Validation(message)) => {
+            let error = ApiResponse::<crate::jobs::Job>::error("VALIDATION_ERROR", message);
+            return (StatusCode::BAD_REQUEST, Json(error));
+        }
+        Err(e) => {
+            let error = ApiResponse::<crate::jobs::Job>::error(
+                "CONFIG_ERROR",
+                format!("Failed to resolve import file: {}", e)
+            );
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+        }
+    };
+
+    let handle = state.jobs.create("patient_import", requester.user_id.clone());
+    let checkpoint = crate::models::ImportCheckpoint::new(handle.id(), path.to_string_lossy().into_owned(), requester.user_id);
+    if let Err(e) = state.import_checkpoints.save(&checkpoint) {
+        handle.fail(&e.to_string());
+        let error = ApiResponse::<crate::jobs::Job>::error(
+            "DATABASE_ERROR",
+            format!("Failed to store import checkpoint: {}", e)
+        );
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+    }
+    spawn_import(&state, checkpoint, handle)
+}
+
+/// Get a bulk import's checkpoint: how far it got and what it stored
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/import/{id}",
+    tag = "admin",
+    params(
+        ("id" = Uuid, Path, description = "Import ID")
+    ),
+    responses(
+        (status = 200, description = "Import found", body = crate::models::ImportCheckpoint),
+        (status = 404, description = "Import not found", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn get_import(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match import_checkpoint::<crate::models::ImportCheckpoint>(&state, &id) {
+        Ok(checkpoint) => (StatusCode::OK, Json(ApiResponse::success(checkpoint))),
+        Err(response) => *response,
+    }
+}
+
+/// Resume a bulk import from its last checkpoint
+///
+/// Lines read again since the checkpoint are recognized by patient ID or
+/// record fingerprint and not stored twice. The import keeps its ID; the
+/// job started here has a new one. Only users holding one of
+/// `server.admin_roles` in `X-User-Roles` may resume an import.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/import/{id}/resume",
+    tag = "admin",
+    params(
+        ("id" = Uuid, Path, description = "Import ID")
+    ),
+    responses(
+        (status = 202, description = "Import job resumed", body = crate::jobs::Job),
+        (status = 403, description = "Requester is not an administrator", body = crate::api::ApiErrorResponse),
+        (status = 404, description = "Import not found", body = crate::api::ApiErrorResponse),
+        (status = 409, description = "Import already completed or still running", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn resume_import(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    use crate::jobs::JobStatus;
+    use crate::models::import_checkpoint::IMPORT_COMPLETED;
+
+    if let Err(response) = require_admin::<crate::jobs::Job>(&state, &headers, "resume imports") {
+        return *response;
+    }
+
+    let checkpoint = match import_checkpoint::<crate::jobs::Job>(&state, &id) {
+        Ok(checkpoint) => checkpoint,
+        Err(response) => return *response,
+    };
+    if checkpoint.status == IMPORT_COMPLETED {
+        let error = ApiResponse::<crate::jobs::Job>::error(
+            "CONFLICT",
+            format!("Import {} has already completed", id)
+        );
+        return (StatusCode::CONFLICT, Json(error));
+    }
+    // A running checkpoint whose job this process does not know was left
+    // behind by a crash or restart
+    if let Some(job) = state.jobs.get(&checkpoint.job_id) {
+        if matches!(job.status, JobStatus::Queued | JobStatus::Running) {
+            let error = ApiResponse::<crate::jobs::Job>::error(
+                "CONFLICT",
+                format!("Import {} is still running as job {}", id, job.id)
+            );
+            return (StatusCode::CONFLICT, Json(error));
+        }
+    }
+
+    let handle = state.jobs.create("patient_import", checkpoint.requested_by.clone());
+    spawn_import(&state, checkpoint, handle)
+}
+
+/// Run an import from `checkpoint` under `handle`, answering with the job
+fn spawn_import(
+    state: &AppState,
+    checkpoint: crate::models::ImportCheckpoint,
+    handle: crate::jobs::JobHandle,
+) -> (StatusCode, Json<ApiResponse<crate::jobs::Job>>) {
+    let job = handle.snapshot();
+    crate::import::PatientImporter::new(
+        state.patient_repository.clone(),
+        state.search_engine.clone(),
+        state.import_checkpoints.clone(),
+        &state.config.import,
+    )
+    .with_identifiers(&state.config.identifiers)
+    .with_audit_log(state.audit_log.clone())
+    .spawn(checkpoint, handle);
+
+    match job {
+        Some(job) => (StatusCode::ACCEPTED, Json(ApiResponse::success(job))),
+        None => {
+            let error = ApiResponse::<crate::jobs::Job>::error(
+                "INTERNAL_ERROR",
+                "Import job was not registered".to_string()
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Load an import's checkpoint, or the error response to send instead
+fn import_checkpoint<T>(
+    state: &AppState,
+    id: &Uuid,
+) -> Result<crate::models::ImportCheckpoint, HandlerError<T>> {
+    match state.import_checkpoints.get_by_id(id) {
+        Ok(Some(checkpoint)) => Ok(checkpoint),
+        Ok(None) => {
+            let error = ApiResponse::<T>::error("NOT_FOUND", format!("Import with id '{}' not found", id));
+            Err(Box::new((StatusCode::NOT_FOUND, Json(error))))
+        }
+        Err(e) => {
+            let error = ApiResponse::<T>::error("DATABASE_ERROR", format!("Failed to load import: {}", e));
+            Err(Box::new((StatusCode::INTERNAL_SERVER_ERROR, Json(error))))
+        }
+    }
+}
+
+/// List background admin jobs, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/jobs",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Jobs started since the server last restarted", body = Vec<crate::jobs::Job>)
+    )
+)]
+pub async fn list_jobs(
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    (StatusCode::OK, Json(ApiResponse::success(state.jobs.list())))
+}
+
+/// Get a background admin job's progress and report
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/jobs/{id}",
+    tag = "admin",
+    params(
+        ("id" = Uuid, Path, description = "Job ID")
+    ),
+    responses(
+        (status = 200, description = "Job found", body = crate::jobs::Job),
+        (status = 404, description = "Job not found", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn get_job(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match state.jobs.get(&id) {
+        Some(job) => (StatusCode::OK, Json(ApiResponse::success(job))),
+        None => {
+            let error = ApiResponse::<crate::jobs::Job>::error(
+                "NOT_FOUND",
+                format!("Job {} not found", id)
+            );
+            (StatusCode::NOT_FOUND, Json(error))
+        }
+    }
+}
+
+/// Patient, link, review, and index statistics
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StatsResponse {
+    pub patients: crate::db::statistics::PatientCounts,
+    pub links: crate::db::statistics::LinkCounts,
+    /// Scored candidate pairs at or above the match threshold that are not yet linked
+    pub pending_review: i64,
+    pub search_backend: String,
+    /// Omitted when the search backend does not report statistics
+    pub search_index: Option<crate::search::IndexStats>,
+}
+
+/// Get patient counts and MPI statistics for dashboards
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Current statistics", body = StatsResponse),
+        (status = 500, description = "Statistics query failed", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn get_stats(
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let threshold = state.matching_config().threshold_score;
+    let counts = state.statistics.patient_counts().and_then(|patients| {
+        let links = state.statistics.link_counts()?;
+        let pending_review = state.statistics.pending_review_count(threshold)?;
+        Ok((patients, links, pending_review))
+    });
+
+    match counts {
+        Ok((patients, links, pending_review)) => {
+            let response = StatsResponse {
+                patients,
+                links,
+                pending_review,
+                search_backend: state.search_engine.name().to_string(),
+                search_index: state.search_engine.stats().ok(),
+            };
+            (StatusCode::OK, Json(ApiResponse::success(response)))
+        }
+        Err(e) => {
+            let error = ApiResponse::<StatsResponse>::error(
+                "DATABASE_ERROR",
+                format!("Failed to compute statistics: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Matching report query parameters
+#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct MatchingReportQuery {
+    /// First day (inclusive); defaults to 30 days before `to`
+    pub from: Option<chrono::NaiveDate>,
+    /// Last day (inclusive); defaults to yesterday
+    pub to: Option<chrono::NaiveDate>,
+    /// Restrict to one source system
+    pub source_system: Option<String>,
+}
+
+/// Get daily matching quality KPIs per source system
+#[utoipa::path(
+    get,
+    path = "/api/v1/reports/matching",
+    tag = "reports",
+    params(MatchingReportQuery),
+    responses(
+        (status = 200, description = "Matching KPIs for the range", body = crate::reporting::MatchingReport),
+        (status = 400, description = "Invalid date range", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Report query failed", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn get_matching_report(
+    State(state): State<AppState>,
+    Query(params): Query<MatchingReportQuery>,
+) -> impl IntoResponse {
+    let to = params
+        .to
+        .unwrap_or_else(|| chrono::Utc::now().date_naive() - chrono::Duration::days(1));
+    let from = params.from.unwrap_or(to - chrono::Duration::days(29));
+
+    if from > to {
+        let error = ApiResponse::<crate::reporting::MatchingReport>::error(
+            "VALIDATION_ERROR",
+            "from must not be after to"
+        );
+        return (StatusCode::BAD_REQUEST, Json(error));
+    }
+
+    match state.matching_kpis.list(from, to, params.source_system.as_deref()) {
+        Ok(daily) => {
+            let report = crate::reporting::MatchingReport::new(from, to, daily);
+            (StatusCode::OK, Json(ApiResponse::success(report)))
+        }
+        Err(e) => {
+            let error = ApiResponse::<crate::reporting::MatchingReport>::error(
+                "DATABASE_ERROR",
+                format!("Failed to load matching report: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Get data quality and duplicate creation rates per source system
+#[utoipa::path(
+    get,
+    path = "/api/v1/reports/data-quality",
+    tag = "reports",
+    params(MatchingReportQuery),
+    responses(
+        (status = 200, description = "Data quality per source, worst first", body = crate::reporting::DataQualityReport),
+        (status = 400, description = "Invalid date range", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Report query failed", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn get_data_quality_report(
+    State(state): State<AppState>,
+    Query(params): Query<MatchingReportQuery>,
+) -> impl IntoResponse {
+    let to = params
+        .to
+        .unwrap_or_else(|| chrono::Utc::now().date_naive() - chrono::Duration::days(1));
+    let from = params.from.unwrap_or(to - chrono::Duration::days(29));
+
+    if from > to {
+        let error = ApiResponse::<crate::reporting::DataQualityReport>::error(
+            "VALIDATION_ERROR",
+            "from must not be after to"
+        );
+        return (StatusCode::BAD_REQUEST, Json(error));
+    }
+
+    let report = state
+        .matching_kpis
+        .list(from, to, params.source_system.as_deref())
+        .and_then(|kpis| {
+            crate::reporting::data_quality::data_quality_report(
+                state.source_records.as_ref(),
+                &kpis,
+                from,
+                to,
+            )
+        });
+
+    match report {
+        Ok(mut report) => {
+            if let Some(source_system) = &params.source_system {
+                report.sources.retain(|s| &s.source_system == source_system);
+            }
+            (StatusCode::OK, Json(ApiResponse::success(report)))
+        }
+        Err(e) => {
+            let error = ApiResponse::<crate::reporting::DataQualityReport>::error(
+                "DATABASE_ERROR",
+                format!("Failed to build data quality report: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Watch a patient for changes
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct CreateWatchRequest {
+    /// http(s) URL to POST notifications to; omit to use the event stream only
+    pub callback_url: Option<String>,
+    /// Event types to notify about (e.g. `Updated`, `Linked`, `Merged`); empty for all
+    #[serde(default)]
+    pub event_types: Vec<String>,
+}
+
+/// Watch a patient and receive its changes by webhook or event stream
+#[utoipa::path(
+    post,
+    path = "/api/v1/patients/{id}/watch",
+    tag = "patients",
+    params(
+        ("id" = Uuid, Path, description = "Patient UUID")
+    ),
+    request_body = CreateWatchRequest,
+    responses(
+        (status = 201, description = "Watch created", body = crate::models::PatientWatch),
+        (status = 400, description = "Invalid callback URL or event type", body = crate::api::ApiErrorResponse),
+        (status = 404, description = "Patient not found", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn create_patient_watch(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<CreateWatchRequest>,
+) -> impl IntoResponse {
+    use crate::models::PatientWatch;
+    use crate::streaming::watch::WATCHABLE_EVENT_TYPES;
+
+    if let Some(url) = &payload.callback_url {
+        if !(url.starts_with("http://") || url.starts_with("https://")) {
+            let error = ApiResponse::<PatientWatch>::error(
+                "VALIDATION_ERROR",
+                "callback_url must be an http or https URL"
+            );
+            return (StatusCode::BAD_REQUEST, Json(error));
+        }
+    }
+    if let Some(unknown) = payload
+        .event_types
+        .iter()
+        .find(|t| !WATCHABLE_EVENT_TYPES.contains(&t.as_str()))
+    {
+        let error = ApiResponse::<PatientWatch>::error(
+            "VALIDATION_ERROR",
+            format!(
+                "Unknown event type '{}'; expected one of {}",
+                unknown,
+                WATCHABLE_EVENT_TYPES.join(", ")
+            )
+        );
+        return (StatusCode::BAD_REQUEST, Json(error));
+    }
+
+    match state.patient_repository.get_by_id(&id) {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            let error = ApiResponse::<PatientWatch>::error(
+                "NOT_FOUND",
+                format!("Patient with id '{}' not found", id)
+            );
+            return (StatusCode::NOT_FOUND, Json(error));
+        }
+        Err(e) => {
+            let error = ApiResponse::<PatientWatch>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve patient: {}", e)
+            );
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+        }
+    }
+
+    match state.watches.create(&id, payload.callback_url, payload.event_types, None) {
+        Ok(watch) => (StatusCode::CREATED, Json(ApiResponse::success(watch))),
+        Err(e) => {
+            let error = ApiResponse::<PatientWatch>::error(
+                "DATABASE_ERROR",
+                format!("Failed to create watch: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Stop watching a patient
+#[utoipa::path(
+    delete,
+    path = "/api/v1/watches/{id}",
+    tag = "patients",
+    params(
+        ("id" = Uuid, Path, description = "Watch UUID")
+    ),
+    responses(
+        (status = 204, description = "Watch removed"),
+        (status = 404, description = "Watch not found", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn delete_watch(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match state.watches.delete(&id) {
+        Ok(true) => (StatusCode::NO_CONTENT, Json(ApiResponse::<()>::success(()))),
+        Ok(false) => {
+            let error = ApiResponse::<()>::error(
+                "NOT_FOUND",
+                format!("Watch with id '{}' not found", id)
+            );
+            (StatusCode::NOT_FOUND, Json(error))
+        }
+        Err(e) => {
+            let error = ApiResponse::<()>::error(
+                "DATABASE_ERROR",
+                format!("Failed to delete watch: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Stream a watch's notifications as server-sent events
+#[utoipa::path(
+    get,
+    path = "/api/v1/watches/{id}/events",
+    tag = "patients",
+    params(
+        ("id" = Uuid, Path, description = "Watch UUID")
+    ),
+    responses(
+        (status = 200, description = "Event stream of notifications", body = crate::streaming::watch::WatchNotification, content_type = "text/event-stream"),
+        (status = 404, description = "Watch not found", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn stream_watch_events(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> axum::response::Response {
+    use axum::response::sse::{Event, KeepAlive, Sse};
+    use tokio_stream::wrappers::BroadcastStream;
+    use tokio_stream::StreamExt;
+
+    match state.watches.get_by_id(&id) {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            let error = ApiResponse::<()>::error(
+                "NOT_FOUND",
+                format!("Watch with id '{}' not found", id)
+            );
+            return (StatusCode::NOT_FOUND, Json(error)).into_response();
+        }
+        Err(e) => {
+            let error = ApiResponse::<()>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve watch: {}", e)
+            );
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    }
+
+    // Lagged receivers skip what they missed rather than closing the stream
+    let events = BroadcastStream::new(state.watch_notifier.subscribe()).filter_map(move |received| {
+        let notification = received.ok().filter(|n| n.watch_id == id)?;
+        Some(
+            Event::default()
+                .event(notification.event_type.clone())
+                .json_data(&notification),
+        )
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default()).into_response()
+}
+
+/// Header naming the steward making a change, compared with record lock holders
+pub const LOCK_HOLDER_HEADER: &str = "x-lock-holder";
+
+/// Lock request
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LockRequest {
+    /// Steward taking the lock; send the same value in `X-Lock-Holder` when writing
+    pub locked_by: String,
+    /// Why the records are locked
+    pub reason: Option<String>,
+    /// Other patients to lock together with this one, such as the other half of a duplicate pair
+    #[serde(default)]
+    pub with: Vec<Uuid>,
+    /// Lock lifetime in seconds (default: `locking.default_ttl_secs`, capped at `locking.max_ttl_secs`)
+    pub ttl_secs: Option<u64>,
+}
+
+/// 423 Locked naming the lock holder
+pub(crate) fn locked_response<T>(lock: &RecordLock) -> (StatusCode, Json<ApiResponse<T>>) {
+    let error = ApiResponse::<T>::error(
+        "LOCKED",
+        format!(
+            "Patient '{}' is locked by '{}' until {}",
+            lock.patient_id, lock.locked_by, lock.expires_at.to_rfc3339()
+        )
+    ).with_details(serde_json::to_value(lock).unwrap_or_default());
+    (StatusCode::LOCKED, Json(error))
+}
+
+/// The lock holder named by the request, if any
+pub(crate) fn lock_holder(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(LOCK_HOLDER_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
+/// Refuse a write to patients locked by a steward other than the requester
+pub(crate) fn check_record_locks<T>(
+    state: &AppState,
+    patient_ids: &[Uuid],
+    headers: &HeaderMap,
+) -> Result<(), HandlerError<T>> {
+    match state.record_locks.find_conflict(patient_ids, lock_holder(headers)) {
+        Ok(None) => Ok(()),
+        Ok(Some(lock)) => Err(Box::new(locked_response(&lock))),
+        Err(e) => {
+            let error = ApiResponse::<T>::error(
+                "DATABASE_ERROR",
+                format!("Failed to check record locks: {}", e)
+            );
+            Err(Box::new((StatusCode::INTERNAL_SERVER_ERROR, Json(error))))
+        }
+    }
+}
+
+/// Lock a patient, and optionally related patients, for review
+#[utoipa::path(
+    post,
+    path = "/api/v1/patients/{id}/lock",
+    tag = "patients",
+    params(
+        ("id" = Uuid, Path, description = "Patient UUID")
+    ),
+    request_body = LockRequest,
+    responses(
+        (status = 200, description = "All requested patients locked or renewed", body = Vec<RecordLock>),
+        (status = 400, description = "Missing lock holder or invalid lifetime", body = crate::api::ApiErrorResponse),
+        (status = 404, description = "Patient not found", body = crate::api::ApiErrorResponse),
+        (status = 423, description = "A patient is locked by another steward; nothing was locked", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn lock_patient(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<LockRequest>,
+) -> impl IntoResponse {
+    use crate::db::LockOutcome;
+
+    let locked_by = payload.locked_by.trim();
+    if locked_by.is_empty() {
+        let error = ApiResponse::<Vec<RecordLock>>::error("VALIDATION_ERROR", "locked_by is required");
+        return (StatusCode::BAD_REQUEST, Json(error));
+    }
+    let config = &state.config.locking;
+    let ttl_secs = payload.ttl_secs.unwrap_or(config.default_ttl_secs).min(config.max_ttl_secs);
+    if ttl_secs == 0 {
+        let error = ApiResponse::<Vec<RecordLock>>::error("VALIDATION_ERROR", "ttl_secs must be positive");
+        return (StatusCode::BAD_REQUEST, Json(error));
+    }
+
+    let patient_ids: Vec<Uuid> = std::iter::once(id).chain(payload.with.iter().copied()).collect();
+    for patient_id in &patient_ids {
+        match state.patient_repository.get_by_id(patient_id) {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                let error = ApiResponse::<Vec<RecordLock>>::error(
+                    "NOT_FOUND",
+                    format!("Patient with id '{}' not found", patient_id)
+                );
+                return (StatusCode::NOT_FOUND, Json(error));
+            }
+            Err(e) => {
+                let error = ApiResponse::<Vec<RecordLock>>::error(
+                    "DATABASE_ERROR",
+                    format!("Failed to retrieve patient: {}", e)
+                );
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+            }
+        }
+    }
+
+    let ttl = chrono::Duration::seconds(ttl_secs.min(i64::MAX as u64) as i64);
+    match state.record_locks.acquire(&patient_ids, locked_by, payload.reason.as_deref(), ttl) {
+        Ok(LockOutcome::Acquired(locks)) => (StatusCode::OK, Json(ApiResponse::success(locks))),
+        Ok(LockOutcome::Held(lock)) => locked_response(&lock),
+        Err(e) => {
+            let error = ApiResponse::<Vec<RecordLock>>::error(
+                "DATABASE_ERROR",
+                format!("Failed to lock patient: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Get the lock on a patient
+#[utoipa::path(
+    get,
+    path = "/api/v1/patients/{id}/lock",
+    tag = "patients",
+    params(
+        ("id" = Uuid, Path, description = "Patient UUID")
+    ),
+    responses(
+        (status = 200, description = "Current lock", body = RecordLock),
+        (status = 404, description = "Patient is not locked", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn get_patient_lock(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match state.record_locks.get(&id) {
+        Ok(Some(lock)) => (StatusCode::OK, Json(ApiResponse::success(lock))),
+        Ok(None) => {
+            let error = ApiResponse::<RecordLock>::error(
+                "NOT_FOUND",
+                format!("Patient '{}' is not locked", id)
+            );
+            (StatusCode::NOT_FOUND, Json(error))
+        }
+        Err(e) => {
+            let error = ApiResponse::<RecordLock>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve lock: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Release a patient's lock; only the holder named in `X-Lock-Holder` may release it
+#[utoipa::path(
+    delete,
+    path = "/api/v1/patients/{id}/lock",
+    tag = "patients",
+    params(
+        ("id" = Uuid, Path, description = "Patient UUID"),
+        ("X-Lock-Holder" = String, Header, description = "Steward holding the lock")
+    ),
+    responses(
+        (status = 204, description = "Lock released"),
+        (status = 404, description = "Patient is not locked", body = crate::api::ApiErrorResponse),
+        (status = 423, description = "Patient is locked by another steward", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn unlock_patient(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let lock = match state.record_locks.get(&id) {
+        Ok(Some(lock)) => lock,
+        Ok(None) => {
+            let error = ApiResponse::<()>::error(
+                "NOT_FOUND",
+                format!("Patient '{}' is not locked", id)
+            );
+            return (StatusCode::NOT_FOUND, Json(error));
+        }
+        Err(e) => {
+            let error = ApiResponse::<()>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve lock: {}", e)
+            );
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+        }
+    };
+    if !lock.permits(lock_holder(&headers)) {
+        return locked_response(&lock);
+    }
+
+    match state.record_locks.release(&id) {
+        Ok(_) => (StatusCode::NO_CONTENT, Json(ApiResponse::<()>::success(()))),
+        Err(e) => {
+            let error = ApiResponse::<()>::error(
+                "DATABASE_ERROR",
+                format!("Failed to release lock: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Verification status for one of a patient's identifiers
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct IdentifierVerification {
+    pub identifier_type: IdentifierType,
+    pub system: String,
+    /// Identifier value; case and formatting are ignored
+    pub value: String,
+    pub status: VerificationStatus,
+}
+
+/// Verification status for one of a patient's addresses
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AddressVerification {
+    /// Position in the patient's address list, 0 for the primary address
+    pub index: usize,
+    pub status: VerificationStatus,
+}
+
+/// Verification statuses to set on a patient
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct VerificationRequest {
+    #[serde(default)]
+    pub identifiers: Vec<IdentifierVerification>,
+    #[serde(default)]
+    pub addresses: Vec<AddressVerification>,
+}
+
+/// Set the verification status of a patient's identifiers and addresses
+///
+/// Statuses are set as given, so this is also how a status is lowered;
+/// patient updates only ever raise them.
+#[utoipa::path(
+    put,
+    path = "/api/v1/patients/{id}/verification",
+    tag = "patients",
+    params(
+        ("id" = Uuid, Path, description = "Patient UUID")
+    ),
+    request_body = VerificationRequest,
+    responses(
+        (status = 200, description = "Statuses set", body = Patient),
+        (status = 400, description = "An identifier or address is not on the patient; nothing was set", body = crate::api::ApiErrorResponse),
+        (status = 404, description = "Patient not found", body = crate::api::ApiErrorResponse),
+        (status = 423, description = "Patient is locked by another steward", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn set_patient_verification(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(payload): Json<VerificationRequest>,
+) -> impl IntoResponse {
+    if let Err(response) = check_record_locks(&state, &[id], &headers) {
+        return *response;
+    }
+
+    let mut patient = match state.patient_repository.get_by_id_for_update(&id) {
+        Ok(Some(patient)) => patient,
+        Ok(None) => {
+            let error = ApiResponse::<Patient>::error(
+                "NOT_FOUND",
+                format!("Patient with id '{}' not found", id)
+            );
+            return (StatusCode::NOT_FOUND, Json(error));
+        }
+        Err(e) => {
+            let error = ApiResponse::<Patient>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve patient: {}", e)
+            );
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+        }
+    };
+
+    for update in &payload.identifiers {
+        let wanted = Identifier::new(update.identifier_type.clone(), update.system.clone(), update.value.clone());
+        let mut found = false;
+        for identifier in patient.identifiers.iter_mut().filter(|i| i.same_as(&wanted)) {
+            identifier.verification = update.status;
+            found = true;
+        }
+        if !found {
+            let error = ApiResponse::<Patient>::error(
+                "VALIDATION_ERROR",
+                format!("Patient has no {} identifier '{}' in '{}'", update.identifier_type, update.value, update.system)
+            );
+            return (StatusCode::BAD_REQUEST, Json(error));
+        }
+    }
+    for update in &payload.addresses {
+        match patient.addresses.get_mut(update.index) {
+            Some(address) => address.verification = update.status,
+            None => {
+                let error = ApiResponse::<Patient>::error(
+                    "VALIDATION_ERROR",
+                    format!("Patient has no address at index {}", update.index)
+                );
+                return (StatusCode::BAD_REQUEST, Json(error));
+            }
+        }
+    }
+
+    match state.patient_repository.update(&patient) {
+        Ok(patient) => {
+            if let Err(e) = state.search_engine.index_patient(&patient) {
+                tracing::warn!("Failed to update patient in search engine: {}", e);
+            }
+            (StatusCode::OK, Json(ApiResponse::success(patient)))
+        }
+        Err(e) => {
+            let error = ApiResponse::<Patient>::error(
+                "DATABASE_ERROR",
+                format!("Failed to update patient: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Confidentiality level to set on a patient
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ConfidentialityRequest {
+    pub confidentiality: Confidentiality,
+    /// Why the level is changing, recorded in the audit log
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Set a patient's confidentiality level
+///
+/// Only users holding one of `privacy.officer_roles` in `X-User-Roles` may
+/// change the level. Restricted and VIP patients are masked for users
+/// without one of `privacy.privileged_roles`.
+#[utoipa::path(
+    put,
+    path = "/api/v1/patients/{id}/confidentiality",
+    tag = "patients",
+    params(
+        ("id" = Uuid, Path, description = "Patient UUID")
+    ),
+    request_body = ConfidentialityRequest,
+    responses(
+        (status = 200, description = "Level set", body = Patient),
+        (status = 403, description = "Requester is not a privacy officer", body = crate::api::ApiErrorResponse),
+        (status = 404, description = "Patient not found", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn set_patient_confidentiality(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(payload): Json<ConfidentialityRequest>,
+) -> impl IntoResponse {
+    let requester = Requester::from_headers(&headers);
+    if !requester.is_privacy_officer(&state.config.privacy) {
+        let error = ApiResponse::<Patient>::error(
+            "FORBIDDEN",
+            "Only privacy officers may change a patient's confidentiality"
+        );
+        return (StatusCode::FORBIDDEN, Json(error));
+    }
+
+    let previous = match state.patient_repository.get_by_id_for_update(&id) {
+        Ok(Some(patient)) => patient.confidentiality,
+        Ok(None) => {
+            let error = ApiResponse::<Patient>::error(
+                "NOT_FOUND",
+                format!("Patient with id '{}' not found", id)
+            );
+            return (StatusCode::NOT_FOUND, Json(error));
+        }
+        Err(e) => {
+            let error = ApiResponse::<Patient>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve patient: {}", e)
+            );
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+        }
+    };
+
+    match state.patient_repository.set_confidentiality(&id, payload.confidentiality) {
+        Ok(Some(patient)) => {
+            if let Err(e) = state.audit_log.log_confidentiality_change(
+                id,
+                serde_json::json!({ "confidentiality": previous }),
+                serde_json::json!({ "confidentiality": patient.confidentiality, "reason": payload.reason }),
+                requester.user_id,
+                requester.ip_address,
+                requester.user_agent,
+            ) {
+                tracing::warn!("Failed to audit confidentiality change of patient {}: {}", id, e);
+            }
+            (StatusCode::OK, Json(ApiResponse::success(patient)))
+        }
+        Ok(None) => {
+            let error = ApiResponse::<Patient>::error(
+                "NOT_FOUND",
+                format!("Patient with id '{}' not found", id)
+            );
+            (StatusCode::NOT_FOUND, Json(error))
+        }
+        Err(e) => {
+            let error = ApiResponse::<Patient>::error(
+                "DATABASE_ERROR",
+                format!("Failed to set confidentiality: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Lifecycle status to move a patient to
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PatientStatusRequest {
+    pub status: PatientStatus,
+    /// When the patient died, recorded when moving to `deceased`
+    #[serde(default)]
+    pub deceased_datetime: Option<chrono::DateTime<chrono::Utc>>,
+    /// Why the status is changing, recorded in the audit log
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Move a patient to another lifecycle status
+///
+/// Patients go between active and inactive freely, may be reported deceased
+/// and have that corrected back to active, and may be marked entered in
+/// error from any status but merged. Merged patients are final, and only a
+/// merge makes a patient merged.
+#[utoipa::path(
+    post,
+    path = "/api/v1/patients/{id}/status",
+    tag = "patients",
+    params(
+        ("id" = Uuid, Path, description = "Patient UUID")
+    ),
+    request_body = PatientStatusRequest,
+    responses(
+        (status = 200, description = "Status changed", body = Patient),
+        (status = 404, description = "Patient not found", body = crate::api::ApiErrorResponse),
+        (status = 409, description = "The patient cannot move to that status", body = crate::api::ApiErrorResponse),
+        (status = 423, description = "Patient is locked by another steward", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn set_patient_status(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(payload): Json<PatientStatusRequest>,
+) -> impl IntoResponse {
+    if let Err(response) = check_record_locks(&state, &[id], &headers) {
+        return *response;
+    }
+    let requester = Requester::from_headers(&headers);
+
+    let previous = match state.patient_repository.get_by_id_for_update(&id) {
+        Ok(Some(patient)) => patient,
+        Ok(None) => {
+            let error = ApiResponse::<Patient>::error(
+                "NOT_FOUND",
+                format!("Patient with id '{}' not found", id)
+            );
+            return (StatusCode::NOT_FOUND, Json(error));
+        }
+        Err(e) => {
+            let error = ApiResponse::<Patient>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve patient: {}", e)
+            );
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
+        }
+    };
+    // Merging needs a survivor to point at, so it goes through the merge endpoints
+    if payload.status == PatientStatus::Merged || !previous.status.can_transition_to(payload.status) {
+        let error = ApiResponse::<Patient>::error(
+            "CONFLICT",
+            format!("Patient {} cannot move from {} to {}", id, previous.status, payload.status)
+        );
+        return (StatusCode::CONFLICT, Json(error));
+    }
+
+    match state.patient_repository.set_status(&id, payload.status, payload.deceased_datetime) {
+        Ok(Some(patient)) => {
+            if let Err(e) = state.audit_log.log_update(
+                "Patient",
+                id,
+                serde_json::json!({ "status": previous.status }),
+                serde_json::json!({ "status": patient.status, "reason": payload.reason }),
+                requester.user_id,
+                requester.ip_address,
+                requester.user_agent,
+            ) {
+                tracing::warn!("Failed to audit status change of patient {}: {}", id, e);
+            }
+            if let Err(e) = state.search_engine.index_patient(&patient) {
+                tracing::warn!("Failed to update patient in search engine: {}", e);
+            }
+            (StatusCode::OK, Json(ApiResponse::success(patient)))
+        }
+        Ok(None) => {
+            let error = ApiResponse::<Patient>::error(
+                "NOT_FOUND",
+                format!("Patient with id '{}' not found", id)
+            );
+            (StatusCode::NOT_FOUND, Json(error))
+        }
+        Err(crate::Error::Validation(message)) => {
+            let error = ApiResponse::<Patient>::error("CONFLICT", message);
+            (StatusCode::CONFLICT, Json(error))
+        }
+        Err(e) => {
+            let error = ApiResponse::<Patient>::error(
+                "DATABASE_ERROR",
+                format!("Failed to set status: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Register or change an assigning authority
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AuthorityRequest {
+    /// Identifier system the authority issues under
+    pub system: String,
+    /// OID of the authority, if it has one
+    pub oid: Option<String>,
+    /// Name shown in FHIR output and error messages
+    pub name: String,
+    /// Whether identifiers from the authority are accepted; defaults to true
+    pub active: Option<bool>,
+}
+
+/// Refuse an authority with no system or name, or one claiming another's system or OID
+fn check_authority(
+    state: &AppState,
+    payload: &AuthorityRequest,
+    id: Option<Uuid>,
+) -> Result<(), HandlerError<AssigningAuthority>> {
+    if payload.system.trim().is_empty() || payload.name.trim().is_empty() {
+        let error = ApiResponse::<AssigningAuthority>::error(
+            "VALIDATION_ERROR",
+            "system and name are required"
+        );
+        return Err(Box::new((StatusCode::BAD_REQUEST, Json(error))));
+    }
+
+    let authorities = match state.authorities.list() {
+        Ok(authorities) => authorities,
+        Err(e) => {
+            let error = ApiResponse::<AssigningAuthority>::error(
+                "DATABASE_ERROR",
+                format!("Failed to list assigning authorities: {}", e)
+            );
+            return Err(Box::new((StatusCode::INTERNAL_SERVER_ERROR, Json(error))));
+        }
+    };
+    let taken = authorities.iter().filter(|a| Some(a.id) != id).find(|a| {
+        a.issues(payload.system.trim()) || payload.oid.as_deref().is_some_and(|oid| a.issues(oid.trim()))
+    });
+    match taken {
+        Some(authority) => {
+            let error = ApiResponse::<AssigningAuthority>::error(
+                "CONFLICT",
+                format!("System or OID is already registered to '{}' ({})", authority.name, authority.id)
+            );
+            Err(Box::new((StatusCode::CONFLICT, Json(error))))
+        }
+        None => Ok(()),
+    }
+}
+
+/// List the registered assigning authorities
+///
+/// Served with a `Cache-Control` lifetime of `lookup_cache.max_age_secs`.
+#[utoipa::path(
+    get,
+    path = "/api/v1/authorities",
+    tag = "authorities",
+    responses(
+        (status = 200, description = "Authorities, ordered by name", body = Vec<AssigningAuthority>),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn list_authorities(State(state): State<AppState>) -> Response {
+    match state.authorities.list() {
+        Ok(authorities) => (
+            StatusCode::OK,
+            [(header::CACHE_CONTROL, conditional::lookup_cache_control(state.config.lookup_cache.max_age_secs))],
+            Json(ApiResponse::success(authorities)),
+        )
+            .into_response(),
+        Err(e) => {
+            let error = ApiResponse::<Vec<AssigningAuthority>>::error(
+                "DATABASE_ERROR",
+                format!("Failed to list assigning authorities: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+/// Register an assigning authority
+#[utoipa::path(
+    post,
+    path = "/api/v1/authorities",
+    tag = "authorities",
+    request_body = AuthorityRequest,
+    responses(
+        (status = 201, description = "Authority registered", body = AssigningAuthority),
+        (status = 400, description = "Missing system or name", body = crate::api::ApiErrorResponse),
+        (status = 409, description = "System or OID already registered", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn create_authority(
+    State(state): State<AppState>,
+    Json(payload): Json<AuthorityRequest>,
+) -> impl IntoResponse {
+    if let Err(response) = check_authority(&state, &payload, None) {
+        return *response;
+    }
+
+    match state.authorities.create(
+        payload.system.trim().to_string(),
+        payload.oid.map(|oid| oid.trim().to_string()),
+        payload.name.trim().to_string(),
+        payload.active.unwrap_or(true),
+    ) {
+        Ok(authority) => (StatusCode::CREATED, Json(ApiResponse::success(authority))),
+        Err(e) => {
+            let error = ApiResponse::<AssigningAuthority>::error(
+                "DATABASE_ERROR",
+                format!("Failed to register assigning authority: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Get an assigning authority
+///
+/// Served with a `Cache-Control` lifetime of `lookup_cache.max_age_secs`.
+#[utoipa::path(
+    get,
+    path = "/api/v1/authorities/{id}",
+    tag = "authorities",
+    params(
+        ("id" = Uuid, Path, description = "Authority UUID")
+    ),
+    responses(
+        (status = 200, description = "Authority found", body = AssigningAuthority),
+        (status = 404, description = "Authority not found", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn get_authority(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Response {
+    match state.authorities.get_by_id(&id) {
+        Ok(Some(authority)) => (
+            StatusCode::OK,
+            [(header::CACHE_CONTROL, conditional::lookup_cache_control(state.config.lookup_cache.max_age_secs))],
+            Json(ApiResponse::success(authority)),
+        )
+            .into_response(),
+        Ok(None) => {
+            let error = ApiResponse::<AssigningAuthority>::error(
+                "NOT_FOUND",
+                format!("Assigning authority with id '{}' not found", id)
+            );
+            (StatusCode::NOT_FOUND, Json(error)).into_response()
+        }
+        Err(e) => {
+            let error = ApiResponse::<AssigningAuthority>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve assigning authority: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+/// Change an assigning authority
+///
+/// Deactivating an authority refuses its identifiers on new and updated
+/// patients; identifiers already stored are kept.
+#[utoipa::path(
+    put,
+    path = "/api/v1/authorities/{id}",
+    tag = "authorities",
+    params(
+        ("id" = Uuid, Path, description = "Authority UUID")
+    ),
+    request_body = AuthorityRequest,
+    responses(
+        (status = 200, description = "Authority changed", body = AssigningAuthority),
+        (status = 400, description = "Missing system or name", body = crate::api::ApiErrorResponse),
+        (status = 404, description = "Authority not found", body = crate::api::ApiErrorResponse),
+        (status = 409, description = "System or OID registered to another authority", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn update_authority(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<AuthorityRequest>,
+) -> impl IntoResponse {
+    if let Err(response) = check_authority(&state, &payload, Some(id)) {
+        return *response;
+    }
+
+    match state.authorities.update(
+        &id,
+        payload.system.trim().to_string(),
+        payload.oid.map(|oid| oid.trim().to_string()),
+        payload.name.trim().to_string(),
+        payload.active.unwrap_or(true),
+    ) {
+        Ok(Some(authority)) => (StatusCode::OK, Json(ApiResponse::success(authority))),
+        Ok(None) => {
+            let error = ApiResponse::<AssigningAuthority>::error(
+                "NOT_FOUND",
+                format!("Assigning authority with id '{}' not found", id)
+            );
+            (StatusCode::NOT_FOUND, Json(error))
+        }
+        Err(e) => {
+            let error = ApiResponse::<AssigningAuthority>::error(
+                "DATABASE_ERROR",
+                format!("Failed to update assigning authority: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Remove an assigning authority
+#[utoipa::path(
+    delete,
+    path = "/api/v1/authorities/{id}",
+    tag = "authorities",
+    params(
+        ("id" = Uuid, Path, description = "Authority UUID")
+    ),
+    responses(
+        (status = 204, description = "Authority removed"),
+        (status = 404, description = "Authority not found", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn delete_authority(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match state.authorities.delete(&id) {
+        Ok(true) => (StatusCode::NO_CONTENT, Json(ApiResponse::<()>::success(()))),
+        Ok(false) => {
+            let error = ApiResponse::<()>::error(
+                "NOT_FOUND",
+                format!("Assigning authority with id '{}' not found", id)
+            );
+            (StatusCode::NOT_FOUND, Json(error))
+        }
+        Err(e) => {
+            let error = ApiResponse::<()>::error(
+                "DATABASE_ERROR",
+                format!("Failed to delete assigning authority: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Request for the next MRN of a sequence
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NextMrnRequest {
+    /// Identifier system of the sequence; defaults to `identifiers.assign_mrn`
+    pub system: Option<String>,
+}
+
+/// Issue the next MRN of a configured sequence
+///
+/// Every call takes a new number, so concurrent callers never share one.
+/// Numbers issued but never used are not reclaimed.
+#[utoipa::path(
+    post,
+    path = "/api/v1/identifiers/mrn/next",
+    tag = "authorities",
+    request_body = NextMrnRequest,
+    responses(
+        (status = 200, description = "MRN issued", body = Identifier),
+        (status = 400, description = "No sequence is configured for the system", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn next_mrn(
+    State(state): State<AppState>,
+    Json(payload): Json<NextMrnRequest>,
+) -> impl IntoResponse {
+    let Some(system) = payload.system.or_else(|| state.config.identifiers.assign_mrn.clone()) else {
+        let error = ApiResponse::<Identifier>::error("VALIDATION_ERROR", "system is required");
+        return (StatusCode::BAD_REQUEST, Json(error));
+    };
+
+    match issue_mrn(state.mrn_sequences.as_ref(), &state.config.identifiers, &system) {
+        Ok(mrn) => (StatusCode::OK, Json(ApiResponse::success(mrn))),
+        Err(crate::Error::Validation(message)) => {
+            let error = ApiResponse::<Identifier>::error("VALIDATION_ERROR", message);
+            (StatusCode::BAD_REQUEST, Json(error))
+        }
+        Err(e) => {
+            let error = ApiResponse::<Identifier>::error(
+                "DATABASE_ERROR",
+                format!("Failed to issue an MRN: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Refuse a practitioner with malformed identifiers or an NPI another practitioner holds
+fn check_practitioner(
+    state: &AppState,
+    practitioner: &Practitioner,
+) -> Result<(), HandlerError<Practitioner>> {
+    let rules = state.identifier_rules();
+    let violations: Vec<serde_json::Value> = practitioner
+        .identifiers
+        .iter()
+        .enumerate()
+        .filter_map(|(i, identifier)| {
+            rules.problem(identifier).map(|message| {
+                serde_json::json!({ "field": format!("identifiers[{}].value", i), "message": message })
+            })
+        })
+        .collect();
+    if !violations.is_empty() {
+        let error = ApiResponse::<Practitioner>::error(
+            "VALIDATION_ERROR",
+            format!("{} identifier(s) failed validation", violations.len())
+        ).with_details(serde_json::Value::Array(violations));
+        return Err(Box::new((StatusCode::BAD_REQUEST, Json(error))));
+    }
+
+    match state.practitioners.npi_holder(practitioner) {
+        Ok(None) => Ok(()),
+        Ok(Some(holder)) => {
+            let error = ApiResponse::<Practitioner>::error(
+                "CONFLICT",
+                format!("NPI already belongs to practitioner '{}'", holder)
+            ).with_details(serde_json::json!({ "practitioner_id": holder }));
+            Err(Box::new((StatusCode::CONFLICT, Json(error))))
+        }
+        Err(e) => {
+            let error = ApiResponse::<Practitioner>::error(
+                "DATABASE_ERROR",
+                format!("Failed to check NPI uniqueness: {}", e)
+            );
+            Err(Box::new((StatusCode::INTERNAL_SERVER_ERROR, Json(error))))
+        }
+    }
+}
+
+/// Practitioner list query parameters
+#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct PractitionerQuery {
+    /// Maximum number of practitioners (default: 50, max: 500)
+    #[serde(default = "default_practitioner_limit")]
+    pub limit: i64,
+
+    /// Number of practitioners to skip
+    #[serde(default)]
+    pub offset: i64,
+}
+
+fn default_practitioner_limit() -> i64 {
+    50
+}
+
+/// List practitioners, oldest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/practitioners",
+    tag = "practitioners",
+    params(PractitionerQuery),
+    responses(
+        (status = 200, description = "Practitioners", body = Vec<Practitioner>),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn list_practitioners(
+    State(state): State<AppState>,
+    Query(query): Query<PractitionerQuery>,
+) -> impl IntoResponse {
+    let limit = query.limit.clamp(1, 500);
+    match state.practitioners.list(limit, query.offset.max(0)) {
+        Ok(practitioners) => (StatusCode::OK, Json(ApiResponse::success(practitioners))),
+        Err(e) => {
+            let error = ApiResponse::<Vec<Practitioner>>::error(
+                "DATABASE_ERROR",
+                format!("Failed to list practitioners: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
 }
 
-/// Match a patient against existing records
+/// Register a practitioner
 #[utoipa::path(
     post,
-    path = "/api/v1/patients/match",
-    tag = "matching",
-    request_body = MatchRequest,
+    path = "/api/v1/practitioners",
+    tag = "practitioners",
+    request_body = Practitioner,
     responses(
-        (status = 200, description = "Match results", body = MatchResultsResponse),
-        (status = 500, description = "Matching error")
+        (status = 201, description = "Practitioner registered", body = Practitioner),
+        (status = 400, description = "Malformed identifiers, listed per field in `details`", body = crate::api::ApiErrorResponse),
+        (status = 409, description = "The NPI already belongs to another practitioner", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
     )
 )]
-pub async fn match_patient(
+pub async fn create_practitioner(
     State(state): State<AppState>,
-    Json(payload): Json<MatchRequest>,
+    Json(mut payload): Json<Practitioner>,
 ) -> impl IntoResponse {
-    // Use search engine to get candidate patients (blocking)
-    let family_name = &payload.patient.name.family;
-    let birth_year = payload.patient.birth_date.map(|d| d.year());
-
-    let candidate_ids = state.search_engine
-        .search_by_name_and_year(family_name, birth_year, 100);
+    if payload.id == Uuid::nil() {
+        payload.id = Uuid::new_v4();
+    }
+    if let Err(response) = check_practitioner(&state, &payload) {
+        return *response;
+    }
 
-    match candidate_ids {
-        Ok(ids) => {
-            // Fetch full patient records from database
-            let mut candidates = Vec::new();
-            for patient_id_str in ids {
-                // Parse string ID to UUID
-                let patient_id = match Uuid::parse_str(&patient_id_str) {
-                    Ok(id) => id,
-                    Err(e) => {
-                        tracing::error!("Failed to parse patient ID {}: {}", patient_id_str, e);
-                        continue;
-                    }
-                };
+    match state.practitioners.create(&payload) {
+        Ok(practitioner) => (StatusCode::CREATED, Json(ApiResponse::success(practitioner))),
+        Err(e) => {
+            let error = ApiResponse::<Practitioner>::error(
+                "DATABASE_ERROR",
+                format!("Failed to register practitioner: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
 
-                match state.patient_repository.get_by_id(&patient_id) {
-                    Ok(Some(patient)) => candidates.push(patient),
-                    Ok(None) => {
-                        tracing::warn!("Patient {} found in search index but not in database", patient_id);
-                    }
-                    Err(e) => {
-                        tracing::error!("Failed to fetch patient {}: {}", patient_id, e);
-                    }
-                }
-            }
+/// Get a practitioner
+#[utoipa::path(
+    get,
+    path = "/api/v1/practitioners/{id}",
+    tag = "practitioners",
+    params(
+        ("id" = Uuid, Path, description = "Practitioner UUID")
+    ),
+    responses(
+        (status = 200, description = "Practitioner found", body = Practitioner),
+        (status = 404, description = "Practitioner not found", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn get_practitioner(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match state.practitioners.get_by_id(&id) {
+        Ok(Some(practitioner)) => (StatusCode::OK, Json(ApiResponse::success(practitioner))),
+        Ok(None) => {
+            let error = ApiResponse::<Practitioner>::error(
+                "NOT_FOUND",
+                format!("Practitioner with id '{}' not found", id)
+            );
+            (StatusCode::NOT_FOUND, Json(error))
+        }
+        Err(e) => {
+            let error = ApiResponse::<Practitioner>::error(
+                "DATABASE_ERROR",
+                format!("Failed to retrieve practitioner: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
 
-            // Run matcher on candidates
-            let match_results = match state.matcher.find_matches(&payload.patient, &candidates) {
-                Ok(results) => results,
-                Err(e) => {
-                    let error = ApiResponse::<MatchResultsResponse>::error(
-                        "MATCH_ERROR",
-                        format!("Matching failed: {}", e)
-                    );
-                    return (StatusCode::INTERNAL_SERVER_ERROR, Json(error));
-                }
-            };
+/// Replace a practitioner
+#[utoipa::path(
+    put,
+    path = "/api/v1/practitioners/{id}",
+    tag = "practitioners",
+    params(
+        ("id" = Uuid, Path, description = "Practitioner UUID")
+    ),
+    request_body = Practitioner,
+    responses(
+        (status = 200, description = "Practitioner updated", body = Practitioner),
+        (status = 400, description = "Malformed identifiers, listed per field in `details`", body = crate::api::ApiErrorResponse),
+        (status = 404, description = "Practitioner not found", body = crate::api::ApiErrorResponse),
+        (status = 409, description = "The NPI already belongs to another practitioner", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn update_practitioner(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(mut payload): Json<Practitioner>,
+) -> impl IntoResponse {
+    payload.id = id;
+    if let Err(response) = check_practitioner(&state, &payload) {
+        return *response;
+    }
 
-            // Filter by threshold if provided
-            let threshold = payload.threshold.unwrap_or(0.5);
-            let matches: Vec<MatchResponse> = match_results.into_iter()
-                .filter(|m| m.score >= threshold)
-                .take(payload.limit)
-                .map(|m| {
-                    let quality = if m.score >= 0.9 {
-                        "certain"
-                    } else if m.score >= 0.7 {
-                        "probable"
-                    } else {
-                        "possible"
-                    };
-
-                    MatchResponse {
-                        patient: m.patient.clone(),
-                        score: m.score,
-                        quality: quality.to_string(),
-                    }
-                })
-                .collect();
+    match state.practitioners.update(&payload) {
+        Ok(Some(practitioner)) => (StatusCode::OK, Json(ApiResponse::success(practitioner))),
+        Ok(None) => {
+            let error = ApiResponse::<Practitioner>::error(
+                "NOT_FOUND",
+                format!("Practitioner with id '{}' not found", id)
+            );
+            (StatusCode::NOT_FOUND, Json(error))
+        }
+        Err(e) => {
+            let error = ApiResponse::<Practitioner>::error(
+                "DATABASE_ERROR",
+                format!("Failed to update practitioner: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
 
-            let response = MatchResultsResponse {
-                total: matches.len(),
-                matches,
-            };
-            (StatusCode::OK, Json(ApiResponse::success(response)))
+/// Delete a practitioner (soft delete)
+#[utoipa::path(
+    delete,
+    path = "/api/v1/practitioners/{id}",
+    tag = "practitioners",
+    params(
+        ("id" = Uuid, Path, description = "Practitioner UUID")
+    ),
+    responses(
+        (status = 204, description = "Practitioner deleted"),
+        (status = 404, description = "Practitioner not found", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn delete_practitioner(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match state.practitioners.delete(&id) {
+        Ok(true) => (StatusCode::NO_CONTENT, Json(ApiResponse::<()>::success(()))),
+        Ok(false) => {
+            let error = ApiResponse::<()>::error(
+                "NOT_FOUND",
+                format!("Practitioner with id '{}' not found", id)
+            );
+            (StatusCode::NOT_FOUND, Json(error))
         }
         Err(e) => {
-            let error = ApiResponse::<MatchResultsResponse>::error(
+            let error = ApiResponse::<()>::error(
+                "DATABASE_ERROR",
+                format!("Failed to delete practitioner: {}", e)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
+        }
+    }
+}
+
+/// Find registered practitioners that may be the same provider
+///
+/// Practitioners sharing the NPI or family name are scored with the
+/// provider weights in `practitioners.matching`; those at or above its
+/// threshold are returned, best first. The practitioner need not be
+/// registered.
+#[utoipa::path(
+    post,
+    path = "/api/v1/practitioners/match",
+    tag = "practitioners",
+    request_body = Practitioner,
+    responses(
+        (status = 200, description = "Likely duplicates, best first", body = Vec<PractitionerMatch>),
+        (status = 500, description = "Matching error", body = crate::api::ApiErrorResponse)
+    )
+)]
+pub async fn match_practitioner(
+    State(state): State<AppState>,
+    Json(payload): Json<Practitioner>,
+) -> impl IntoResponse {
+    let config = &state.config.practitioners;
+    let matcher = PractitionerMatcher::new(config.matching.clone());
+
+    let matches = matcher
+        .candidates(&payload, state.practitioners.as_ref(), config.max_candidates)
+        .and_then(|candidates| matcher.find_matches(&payload, &candidates));
+    match matches {
+        Ok(matches) => (StatusCode::OK, Json(ApiResponse::success(matches))),
+        Err(e) => {
+            let error = ApiResponse::<Vec<PractitionerMatch>>::error(
                 "MATCH_ERROR",
-                format!("Matching failed: {}", e)
+                format!("Failed to match practitioner: {}", e)
             );
             (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
         }
     }
 }
 
-/// Audit log query parameters
+/// Load a patient group, answering 404 if it does not exist
+fn patient_group<T>(state: &AppState, id: &Uuid) -> Result<PatientGroup, HandlerError<T>> {
+    match state.groups.get_by_id(id) {
+        Ok(Some(group)) => Ok(group),
+        Ok(None) => {
+            let error = ApiResponse::<T>::error("NOT_FOUND", format!("Group with id '{}' not found", id));
+            Err(Box::new((StatusCode::NOT_FOUND, Json(error))))
+        }
+        Err(e) => {
+            let error = ApiResponse::<T>::error("DATABASE_ERROR", format!("Failed to load group: {}", e));
+            Err(Box::new((StatusCode::INTERNAL_SERVER_ERROR, Json(error))))
+        }
+    }
+}
+
+/// Group list query parameters
 #[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
-pub struct AuditLogQuery {
-    /// Maximum number of results (default: 50, max: 500)
-    #[serde(default = "default_audit_limit")]
+pub struct GroupQuery {
+    /// Maximum number of groups (default: 50, max: 500)
+    #[serde(default = "default_group_limit")]
     pub limit: i64,
+
+    /// Number of groups to skip
+    #[serde(default)]
+    pub offset: i64,
 }
 
-fn default_audit_limit() -> i64 {
+fn default_group_limit() -> i64 {
     50
 }
 
-/// Get audit logs for a specific patient
+/// List patient groups, newest first
 #[utoipa::path(
     get,
-    path = "/api/v1/patients/{id}/audit",
-    tag = "audit",
-    params(
-        ("id" = Uuid, Path, description = "Patient UUID"),
-        AuditLogQuery
-    ),
+    path = "/api/v1/groups",
+    tag = "groups",
+    params(GroupQuery),
     responses(
-        (status = 200, description = "Audit logs retrieved successfully"),
-        (status = 500, description = "Database error")
+        (status = 200, description = "Patient groups", body = Vec<PatientGroup>),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
     )
 )]
-pub async fn get_patient_audit_logs(
+pub async fn list_groups(
     State(state): State<AppState>,
-    Path(id): Path<Uuid>,
-    Query(params): Query<AuditLogQuery>,
+    headers: HeaderMap,
+    Query(query): Query<GroupQuery>,
 ) -> impl IntoResponse {
-    let limit = params.limit.min(500);
-
-    match state.audit_log.get_logs_for_entity("patient", id, limit) {
-        Ok(logs) => (StatusCode::OK, Json(ApiResponse::success(logs))),
+    let limit = query.limit.clamp(1, 500);
+    let requester = Requester::from_headers(&headers);
+    let groups = state.groups.list(limit, query.offset.max(0)).and_then(|groups| {
+        groups
+            .into_iter()
+            .map(|group| crate::api::groups::visible(&state, group, &requester, "GET /api/v1/groups"))
+            .collect::<crate::Result<Vec<_>>>()
+    });
+    match groups {
+        Ok(groups) => (StatusCode::OK, Json(ApiResponse::success(groups))),
         Err(e) => {
-            let error = ApiResponse::<Vec<crate::db::models::DbAuditLog>>::error(
+            let error = ApiResponse::<Vec<PatientGroup>>::error(
                 "DATABASE_ERROR",
-                format!("Failed to retrieve audit logs: {}", e)
+                format!("Failed to list groups: {}", e)
             );
             (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
         }
     }
 }
 
-/// Get recent audit logs
+/// Patients to add to a group
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GroupMembersRequest {
+    /// Patients to add
+    #[serde(default)]
+    pub patient_ids: Vec<Uuid>,
+
+    /// Also add every patient matched by this completed batch match job
+    #[serde(default)]
+    pub batch_job: Option<Uuid>,
+}
+
+/// Patients matched by a completed batch match job, in batch order
+fn batch_job_matches(state: &AppState, job_id: Uuid) -> Result<Vec<Uuid>, (StatusCode, String)> {
+    match state.jobs.get(&job_id) {
+        Some(job) if job.kind == "batch_match" && job.status == crate::jobs::JobStatus::Completed => {}
+        Some(job) if job.kind == "batch_match" => {
+            return Err((
+                StatusCode::CONFLICT,
+                format!("Batch match job {} has not completed ({:?})", job_id, job.status),
+            ));
+        }
+        _ => return Err((StatusCode::NOT_FOUND, format!("Batch match job {} not found", job_id))),
+    }
+
+    let results = std::fs::read_to_string(batch_results_path(state, job_id)).map_err(|e| {
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read batch match results: {}", e))
+    })?;
+    let mut matched = Vec::new();
+    for line in results.lines().filter(|line| !line.trim().is_empty()) {
+        let result: BatchMatchResult = serde_json::from_str(line).map_err(|e| {
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read batch match results: {}", e))
+        })?;
+        for found in result.matches {
+            if !matched.contains(&found.patient_id) {
+                matched.push(found.patient_id);
+            }
+        }
+    }
+    Ok(matched)
+}
+
+/// Add patients to a group
+///
+/// Patients are given by ID, taken from the matches of a completed batch
+/// match job, or both. Current members are skipped. Every patient must be
+/// stored and not deleted.
 #[utoipa::path(
-    get,
-    path = "/api/v1/audit/recent",
-    tag = "audit",
-    params(AuditLogQuery),
+    post,
+    path = "/api/v1/groups/{id}/members",
+    tag = "groups",
+    params(
+        ("id" = Uuid, Path, description = "Group UUID")
+    ),
+    request_body = GroupMembersRequest,
     responses(
-        (status = 200, description = "Recent audit logs retrieved successfully"),
-        (status = 500, description = "Database error")
+        (status = 200, description = "Group with its new members", body = PatientGroup),
+        (status = 400, description = "Unknown or deleted patients", body = crate::api::ApiErrorResponse),
+        (status = 404, description = "Group or batch match job not found", body = crate::api::ApiErrorResponse),
+        (status = 409, description = "Batch match job has not completed", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
     )
 )]
-pub async fn get_recent_audit_logs(
+pub async fn add_group_members(
     State(state): State<AppState>,
-    Query(params): Query<AuditLogQuery>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(payload): Json<GroupMembersRequest>,
 ) -> impl IntoResponse {
-    let limit = params.limit.min(500);
+    let mut patient_ids = payload.patient_ids;
+    if let Some(job_id) = payload.batch_job {
+        match batch_job_matches(&state, job_id) {
+            Ok(matched) => patient_ids.extend(matched),
+            Err((status, message)) => {
+                let code = match status {
+                    StatusCode::NOT_FOUND => "NOT_FOUND",
+                    StatusCode::CONFLICT => "CONFLICT",
+                    _ => "INTERNAL_ERROR",
+                };
+                return (status, Json(ApiResponse::<PatientGroup>::error(code, message)));
+            }
+        }
+    }
+    let mut seen = std::collections::HashSet::new();
+    patient_ids.retain(|patient_id| seen.insert(*patient_id));
 
-    match state.audit_log.get_recent_logs(limit) {
-        Ok(logs) => (StatusCode::OK, Json(ApiResponse::success(logs))),
+    match crate::api::groups::add_members(&state, &id, &patient_ids, &Requester::from_headers(&headers)) {
+        Ok(Some(group)) => (StatusCode::OK, Json(ApiResponse::success(group))),
+        Ok(None) => {
+            let error = ApiResponse::<PatientGroup>::error(
+                "NOT_FOUND",
+                format!("Group with id '{}' not found", id)
+            );
+            (StatusCode::NOT_FOUND, Json(error))
+        }
+        Err(crate::Error::Validation(message)) => {
+            let error = ApiResponse::<PatientGroup>::error("VALIDATION_ERROR", message);
+            (StatusCode::BAD_REQUEST, Json(error))
+        }
         Err(e) => {
-            let error = ApiResponse::<Vec<crate::db::models::DbAuditLog>>::error(
+            let error = ApiResponse::<PatientGroup>::error(
                 "DATABASE_ERROR",
-                format!("Failed to retrieve audit logs: {}", e)
+                format!("Failed to add group members: {}", e)
             );
             (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
         }
     }
 }
 
-/// User audit log query parameters
-#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
-pub struct UserAuditLogQuery {
-    /// User ID to filter by
-    pub user_id: String,
-
-    /// Maximum number of results (default: 50, max: 500)
-    #[serde(default = "default_audit_limit")]
-    pub limit: i64,
-}
-
-/// Get audit logs by user
+/// Remove a patient from a group
 #[utoipa::path(
-    get,
-    path = "/api/v1/audit/user",
-    tag = "audit",
-    params(UserAuditLogQuery),
+    delete,
+    path = "/api/v1/groups/{id}/members/{patient_id}",
+    tag = "groups",
+    params(
+        ("id" = Uuid, Path, description = "Group UUID"),
+        ("patient_id" = Uuid, Path, description = "Member patient UUID")
+    ),
     responses(
-        (status = 200, description = "User audit logs retrieved successfully"),
-        (status = 500, description = "Database error")
+        (status = 204, description = "Patient removed from the group"),
+        (status = 404, description = "Group not found or patient not a member", body = crate::api::ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::ApiErrorResponse)
     )
 )]
-pub async fn get_user_audit_logs(
+pub async fn remove_group_member(
     State(state): State<AppState>,
-    Query(params): Query<UserAuditLogQuery>,
+    Path((id, patient_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
-    let limit = params.limit.min(500);
-
-    match state.audit_log.get_logs_by_user(&params.user_id, limit) {
-        Ok(logs) => (StatusCode::OK, Json(ApiResponse::success(logs))),
+    match crate::api::groups::remove_member(&state, &id, &patient_id, &Requester::from_headers(&headers)) {
+        Ok(Some(true)) => (StatusCode::NO_CONTENT, Json(ApiResponse::<()>::success(()))),
+        Ok(Some(false)) => {
+            let error = ApiResponse::<()>::error(
+                "NOT_FOUND",
+                format!("Patient '{}' is not a member of group '{}'", patient_id, id)
+            );
+            (StatusCode::NOT_FOUND, Json(error))
+        }
+        Ok(None) => {
+            let error = ApiResponse::<()>::error(
+                "NOT_FOUND",
+                format!("Group with id '{}' not found", id)
+            );
+            (StatusCode::NOT_FOUND, Json(error))
+        }
         Err(e) => {
-            let error = ApiResponse::<Vec<crate::db::models::DbAuditLog>>::error(
+            let error = ApiResponse::<()>::error(
                 "DATABASE_ERROR",
-                format!("Failed to retrieve audit logs: {}", e)
+                format!("Failed to remove group member: {}", e)
             );
             (StatusCode::INTERNAL_SERVER_ERROR, Json(error))
         }
@@ -0,0 +1,233 @@
+//! Load shedding
+//!
+//! While the database pool is slow to hand out connections, or too many
+//! match requests are already running, new requests are rejected at once with
+//! 503 and `Retry-After` instead of queueing behind the backlog. Short
+//! interactive lookups then keep their latency while the server recovers.
+//!
+//! Pool wait is read from the [`PoolWaitMonitor`] that
+//! [`crate::db::create_pool`] returns with the pool.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use crate::api::ApiResponse;
+use crate::config::LoadSheddingConfig;
+use crate::db::PoolWaitMonitor;
+use crate::observability::metrics::Metrics;
+
+/// Why a request was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShedReason {
+    /// Database connections are slow to come
+    PoolWait,
+    /// Too many match requests in flight
+    InflightMatches,
+}
+
+impl ShedReason {
+    /// Value of the `reason` metric label
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShedReason::PoolWait => "pool_wait",
+            ShedReason::InflightMatches => "inflight_matches",
+        }
+    }
+}
+
+/// Admits or rejects requests against the configured limits
+pub struct LoadShedder {
+    config: RwLock<LoadSheddingConfig>,
+    pool_wait: PoolWaitMonitor,
+    inflight_matches: AtomicUsize,
+    metrics: Arc<Metrics>,
+}
+
+/// Slot of an admitted match request, released on drop
+pub struct MatchSlot<'a> {
+    inflight: &'a AtomicUsize,
+}
+
+impl Drop for MatchSlot<'_> {
+    fn drop(&mut self) {
+        self.inflight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl LoadShedder {
+    /// Create a shedder reading pool wait from `pool_wait`
+    pub fn new(config: LoadSheddingConfig, pool_wait: PoolWaitMonitor, metrics: Arc<Metrics>) -> Self {
+        Self {
+            config: RwLock::new(config),
+            pool_wait,
+            inflight_matches: AtomicUsize::new(0),
+            metrics,
+        }
+    }
+
+    /// Apply new limits to requests admitted from now on
+    ///
+    /// `enabled` is read once when the router is built and is not changed here.
+    pub fn set_limits(&self, config: LoadSheddingConfig) {
+        *self.config.write().unwrap_or_else(|e| e.into_inner()) = config;
+    }
+
+    /// Current limits
+    pub fn limits(&self) -> LoadSheddingConfig {
+        self.config.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Admit a request, returning the slot a match request holds while it runs
+    pub fn admit(&self, is_match: bool) -> std::result::Result<Option<MatchSlot<'_>>, ShedReason> {
+        let config = self.limits();
+        if self.pool_wait.average_wait() > Duration::from_millis(config.max_pool_wait_ms) {
+            return Err(ShedReason::PoolWait);
+        }
+        if !is_match {
+            return Ok(None);
+        }
+        let previous = self.inflight_matches.fetch_add(1, Ordering::SeqCst);
+        let slot = MatchSlot { inflight: &self.inflight_matches };
+        if previous >= config.max_inflight_matches {
+            return Err(ShedReason::InflightMatches);
+        }
+        Ok(Some(slot))
+    }
+
+    /// Match requests currently running
+    pub fn inflight_matches(&self) -> usize {
+        self.inflight_matches.load(Ordering::SeqCst)
+    }
+
+    fn overloaded_response(&self, reason: ShedReason) -> Response {
+        self.metrics.observe_shed(reason.as_str());
+        let message = match reason {
+            ShedReason::PoolWait => "Database is saturated; retry shortly",
+            ShedReason::InflightMatches => "Too many match requests in progress; retry shortly",
+        };
+        let error = ApiResponse::<()>::error("SERVICE_UNAVAILABLE", message);
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, self.limits().retry_after_secs.to_string())],
+            Json(error),
+        )
+            .into_response()
+    }
+}
+
+/// Middleware rejecting requests while over a limit
+pub async fn shed_load(State(shedder): State<Arc<LoadShedder>>, request: Request, next: Next) -> Response {
+    let path = request.uri().path();
+    if is_exempt(path) {
+        return next.run(request).await;
+    }
+    match shedder.admit(is_match_request(path)) {
+        Ok(_slot) => next.run(request).await,
+        Err(reason) => {
+            tracing::warn!("Shedding {} {}: {}", request.method(), path, reason.as_str());
+            shedder.overloaded_response(reason)
+        }
+    }
+}
+
+/// Health checks, metrics and documentation are always served
+fn is_exempt(path: &str) -> bool {
+    path == "/api/v1/health"
+        || path == "/api/v1/ready"
+        || path == "/metrics"
+        || path.starts_with("/swagger-ui")
+        || path.starts_with("/api-docs")
+}
+
+fn is_match_request(path: &str) -> bool {
+    path == "/api/v1/patients/match"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shedder(max_inflight_matches: usize, pool_wait: PoolWaitMonitor) -> LoadShedder {
+        LoadShedder::new(
+            LoadSheddingConfig {
+                enabled: true,
+                max_pool_wait_ms: 100,
+                max_inflight_matches,
+                retry_after_secs: 2,
+            },
+            pool_wait,
+            Arc::new(Metrics::new()),
+        )
+    }
+
+    #[test]
+    fn test_limits_inflight_matches() {
+        let shedder = shedder(2, PoolWaitMonitor::default());
+
+        let first = shedder.admit(true).unwrap();
+        let second = shedder.admit(true).unwrap();
+        assert_eq!(shedder.admit(true).err(), Some(ShedReason::InflightMatches));
+        // Other requests are not limited by the match count
+        assert!(shedder.admit(false).is_ok());
+        assert_eq!(shedder.inflight_matches(), 2);
+
+        drop(first);
+        assert!(shedder.admit(true).is_ok());
+        drop(second);
+        assert_eq!(shedder.inflight_matches(), 0);
+    }
+
+    #[test]
+    fn test_set_limits() {
+        let shedder = shedder(1, PoolWaitMonitor::default());
+        let _slot = shedder.admit(true).unwrap();
+        assert_eq!(shedder.admit(true).err(), Some(ShedReason::InflightMatches));
+
+        shedder.set_limits(LoadSheddingConfig {
+            max_inflight_matches: 3,
+            ..shedder.limits()
+        });
+        assert!(shedder.admit(true).is_ok());
+    }
+
+    #[test]
+    fn test_sheds_on_pool_wait() {
+        let monitor = PoolWaitMonitor::default();
+        let shedder = shedder(10, monitor.clone());
+
+        monitor.record(Duration::from_millis(20));
+        assert!(shedder.admit(false).is_ok());
+
+        monitor.record(Duration::from_secs(2));
+        assert_eq!(shedder.admit(false).err(), Some(ShedReason::PoolWait));
+        assert_eq!(shedder.admit(true).err(), Some(ShedReason::PoolWait));
+    }
+
+    #[test]
+    fn test_request_classes() {
+        assert!(is_match_request("/api/v1/patients/match"));
+        assert!(!is_match_request("/api/v1/patients/search"));
+        assert!(is_exempt("/api/v1/health"));
+        assert!(is_exempt("/metrics"));
+        assert!(!is_exempt("/api/v1/patients/search"));
+    }
+
+    #[tokio::test]
+    async fn test_overloaded_response() {
+        let shedder = shedder(0, PoolWaitMonitor::default());
+        let reason = shedder.admit(true).err().unwrap();
+
+        let response = shedder.overloaded_response(reason);
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "2");
+    }
+}
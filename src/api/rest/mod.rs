@@ -1,16 +1,22 @@
 //! RESTful API implementation with Axum
 
+use std::sync::Arc;
+
 use axum::{
     Router,
     routing::{get, post, put, delete},
 };
-use tower_http::cors::CorsLayer;
+use tower_http::{compression::CompressionLayer, cors::CorsLayer};
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
 pub mod handlers;
+pub mod load_shedding;
+pub mod negotiation;
+pub mod problem;
 pub mod routes;
 pub mod state;
+pub mod timeouts;
 
 pub use state::AppState;
 
@@ -30,15 +36,112 @@ use crate::Result;
     ),
     paths(
         handlers::health_check,
+        handlers::readiness_check,
+        handlers::prometheus_metrics,
         handlers::create_patient,
+        handlers::list_patients,
         handlers::get_patient,
+        handlers::get_patient_summary,
+        handlers::get_patient_timeline,
+        handlers::compare_patients,
         handlers::update_patient,
         handlers::delete_patient,
         handlers::search_patients,
+        handlers::suggest_patients,
         handlers::match_patient,
+        handlers::match_batch,
+        handlers::get_batch_match_results,
+        handlers::simulate_match,
+        handlers::list_duplicates,
+        handlers::confirm_duplicate,
+        handlers::reject_duplicate,
+        handlers::reopen_duplicate,
+        handlers::list_duplicate_decisions,
+        handlers::export_duplicate_training_data,
         handlers::get_patient_audit_logs,
         handlers::get_recent_audit_logs,
         handlers::get_user_audit_logs,
+        handlers::get_patient_messages,
+        handlers::get_archived_message,
+        handlers::get_audit_entry_message,
+        handlers::list_quarantined,
+        handlers::get_quarantined,
+        handlers::resubmit_quarantined,
+        handlers::discard_quarantined,
+        handlers::submit_change_request,
+        handlers::list_change_requests,
+        handlers::get_change_request,
+        handlers::approve_change_request,
+        handlers::reject_change_request,
+        handlers::snapshot_search_index,
+        handlers::restore_search_index,
+        handlers::reindex_search,
+        handlers::run_relinkage,
+        handlers::run_link_confidence,
+        handlers::list_links_needing_reverification,
+        handlers::verify_link,
+        handlers::run_clustering,
+        handlers::check_integrity,
+        handlers::reload_config,
+        handlers::list_matching_profiles,
+        handlers::get_matching_profile,
+        handlers::update_matching_profile,
+        handlers::list_matching_profile_versions,
+        handlers::restore_matching_profile,
+        handlers::replay_events,
+        handlers::start_source_purge,
+        handlers::start_export,
+        handlers::stream_export,
+        handlers::start_import,
+        handlers::get_import,
+        handlers::resume_import,
+        handlers::list_jobs,
+        handlers::get_job,
+        handlers::get_stats,
+        handlers::get_matching_report,
+        handlers::get_data_quality_report,
+        handlers::create_patient_watch,
+        handlers::delete_watch,
+        handlers::stream_watch_events,
+        handlers::lock_patient,
+        handlers::get_patient_lock,
+        handlers::unlock_patient,
+        handlers::set_patient_verification,
+        handlers::set_patient_confidentiality,
+        handlers::set_patient_status,
+        handlers::list_authorities,
+        handlers::create_authority,
+        handlers::get_authority,
+        handlers::update_authority,
+        handlers::delete_authority,
+        handlers::next_mrn,
+        handlers::list_practitioners,
+        handlers::create_practitioner,
+        handlers::get_practitioner,
+        handlers::update_practitioner,
+        handlers::delete_practitioner,
+        handlers::match_practitioner,
+        handlers::list_groups,
+        handlers::add_group_members,
+        handlers::remove_group_member,
+        crate::api::fhir::handlers::get_fhir_patient,
+        crate::api::fhir::handlers::create_fhir_patient,
+        crate::api::fhir::handlers::update_fhir_patient,
+        crate::api::fhir::handlers::delete_fhir_patient,
+        crate::api::fhir::handlers::search_fhir_patients,
+        crate::api::fhir::handlers::get_fhir_patient_history,
+        crate::api::fhir::handlers::get_fhir_practitioner,
+        crate::api::fhir::handlers::create_fhir_practitioner,
+        crate::api::fhir::handlers::update_fhir_practitioner,
+        crate::api::fhir::handlers::delete_fhir_practitioner,
+        crate::api::fhir::handlers::get_fhir_group,
+        crate::api::fhir::handlers::create_fhir_group,
+        crate::api::fhir::handlers::update_fhir_group,
+        crate::api::fhir::handlers::delete_fhir_group,
+        crate::api::fhir::handlers::get_fhir_related_person,
+        crate::api::fhir::handlers::search_fhir_related_persons,
+        crate::api::fhir::handlers::search_fhir_provenance,
+        crate::api::fhir::handlers::search_fhir_audit_events,
     ),
     components(
         schemas(
@@ -51,15 +154,156 @@ use crate::Result;
             crate::models::identifier::IdentifierUse,
             crate::api::ApiResponse::<crate::models::Patient>,
             crate::api::ApiError,
+            crate::api::ApiErrorResponse,
+            problem::ProblemDetails,
             handlers::HealthResponse,
+            handlers::ReadinessResponse,
+            crate::circuit_breaker::BreakerStatus,
+            crate::circuit_breaker::BreakerState,
             handlers::CreatePatientRequest,
+            handlers::PatientSummary,
+            handlers::PatientTimeline,
+            crate::api::timeline::TimelineEntry,
+            crate::api::timeline::TimelineEvent,
+            crate::api::timeline::TimelineEventKind,
+            crate::api::compare::PatientComparison,
+            crate::api::compare::FieldComparison,
+            crate::api::compare::FieldAgreement,
+            handlers::ContributingRecord,
+            handlers::LinkSummary,
+            handlers::ReviewTasks,
+            handlers::MatchScoreEntry,
             handlers::SearchQuery,
             handlers::SearchResponse,
+            handlers::SearchHitResponse,
+            handlers::SearchResultLine,
+            handlers::SuggestQuery,
+            handlers::SuggestResponse,
+            crate::search::Suggestion,
             handlers::MatchRequest,
             handlers::MatchResponse,
             handlers::MatchResultsResponse,
+            crate::api::match_plan::MatchQueryPlan,
+            crate::api::match_plan::FilteredCandidate,
+            crate::api::match_plan::FilterReason,
+            crate::api::match_plan::StageTimings,
+            crate::search::PassYield,
+            crate::config::BlockingStrategy,
+            crate::config::BlockingPass,
+            handlers::BatchMatchRequest,
+            handlers::BatchMatchResponse,
+            crate::matching::BatchMatchRecord,
+            crate::matching::batch::BatchMatch,
+            crate::matching::BatchMatchResult,
+            crate::matching::BatchMatchSummary,
             handlers::AuditLogQuery,
             handlers::UserAuditLogQuery,
+            handlers::IndexSnapshotRequest,
+            crate::search::SnapshotInfo,
+            handlers::SimulateMatchRequest,
+            handlers::SimulatedMatch,
+            handlers::SimulateMatchResponse,
+            handlers::DuplicateQuery,
+            handlers::DuplicateDecisionRequest,
+            crate::models::DuplicateCandidate,
+            crate::models::ReviewDecision,
+            crate::models::ReviewAction,
+            crate::models::ReviewReason,
+            crate::config::MatchWeights,
+            crate::matching::MatchScoreBreakdown,
+            crate::matching::RelinkageReport,
+            crate::matching::RelinkagePair,
+            crate::jobs::LinkConfidenceReport,
+            handlers::LinkReverificationQuery,
+            crate::models::SourceRecordLink,
+            crate::matching::ClusterReport,
+            crate::matching::PatientCluster,
+            crate::matching::ClusterConflict,
+            handlers::IntegrityCheckRequest,
+            crate::jobs::IntegrityReport,
+            crate::jobs::IntegrityIssue,
+            crate::jobs::IntegrityIssueCount,
+            crate::jobs::IntegrityIssueKind,
+            crate::reload::ReloadableSettings,
+            crate::reload::ReloadReport,
+            handlers::MatchingProfileView,
+            handlers::MatchingProfileUpdate,
+            handlers::MatchingProfileRestore,
+            crate::config::MatchingProfile,
+            crate::models::MatchingSettingsVersion,
+            handlers::ReplayRequest,
+            crate::streaming::replay::ReplayReport,
+            crate::jobs::SourcePurgeRequest,
+            crate::jobs::PurgeMode,
+            crate::jobs::SourcePurgeReport,
+            crate::export::ExportRequest,
+            crate::export::ExportReport,
+            crate::search::BulkIndexReport,
+            crate::import::ImportRequest,
+            crate::models::ImportCheckpoint,
+            handlers::ExportStreamQuery,
+            crate::jobs::Job,
+            crate::jobs::JobStatus,
+            handlers::StatsResponse,
+            crate::db::statistics::PatientCounts,
+            crate::db::statistics::GenderCount,
+            crate::db::statistics::OrganizationCount,
+            crate::db::statistics::LinkCounts,
+            crate::db::statistics::LinkTypeCount,
+            crate::search::IndexStats,
+            handlers::MatchingReportQuery,
+            crate::reporting::MatchingReport,
+            crate::reporting::DailyMatchingKpis,
+            crate::reporting::KpiCounts,
+            crate::reporting::SourceKpiTotals,
+            crate::reporting::DataQualityReport,
+            crate::reporting::SourceDataQuality,
+            crate::reporting::QualityField,
+            handlers::CreateWatchRequest,
+            crate::models::PatientWatch,
+            crate::streaming::watch::WatchNotification,
+            handlers::LockRequest,
+            crate::models::RecordLock,
+            handlers::VerificationRequest,
+            handlers::ConfidentialityRequest,
+            handlers::PatientStatusRequest,
+            crate::models::Confidentiality,
+            crate::models::PatientStatus,
+            handlers::IdentifierVerification,
+            handlers::AddressVerification,
+            crate::models::VerificationStatus,
+            handlers::AuthorityRequest,
+            handlers::NextMrnRequest,
+            crate::models::AssigningAuthority,
+            handlers::PractitionerQuery,
+            crate::models::Practitioner,
+            crate::models::Qualification,
+            crate::models::PatientContact,
+            crate::models::ArchivedMessage,
+            handlers::QuarantineQuery,
+            handlers::ResubmitRequest,
+            crate::models::QuarantinedRecord,
+            handlers::ChangeRequestSubmission,
+            handlers::ChangeReviewRequest,
+            crate::models::ChangeRequest,
+            crate::models::DemographicChanges,
+            crate::matching::PractitionerMatch,
+            handlers::GroupQuery,
+            handlers::GroupMembersRequest,
+            crate::models::PatientGroup,
+            crate::api::fhir::FhirPatient,
+            crate::api::fhir::FhirOperationOutcome,
+            crate::api::fhir::FhirOperationOutcomeIssue,
+            crate::api::fhir::FhirProvenance,
+            crate::api::fhir::FhirAuditEvent,
+            crate::api::fhir::FhirPractitioner,
+            crate::api::fhir::practitioner::FhirPractitionerQualification,
+            crate::api::fhir::resources::FhirPeriod,
+            crate::api::fhir::FhirRelatedPerson,
+            crate::api::fhir::FhirGroup,
+            crate::api::fhir::group::FhirGroupMember,
+            crate::api::fhir::bundle::FhirBundle,
+            crate::api::fhir::bundle::FhirBundleEntry,
         )
     ),
     tags(
@@ -68,6 +312,14 @@ use crate::Result;
         (name = "search", description = "Patient search endpoints"),
         (name = "matching", description = "Patient matching endpoints"),
         (name = "audit", description = "Audit log query endpoints"),
+        (name = "quarantine", description = "Refused inbound records awaiting correction"),
+        (name = "change-requests", description = "Patient-proposed corrections awaiting steward review"),
+        (name = "authorities", description = "Assigning authority registry endpoints"),
+        (name = "practitioners", description = "Practitioner registry and provider matching endpoints"),
+        (name = "groups", description = "Patient group membership endpoints"),
+        (name = "admin", description = "Operational endpoints"),
+        (name = "reports", description = "Quality reporting endpoints"),
+        (name = "fhir", description = "HL7 FHIR R5 Patient, Practitioner, RelatedPerson, Group, Provenance and AuditEvent endpoints"),
     )
 )]
 pub struct ApiDoc;
@@ -76,21 +328,138 @@ pub struct ApiDoc;
 pub fn create_router(state: AppState) -> Router {
     let api_routes = Router::new()
         .route("/health", get(handlers::health_check))
-        .route("/patients", post(handlers::create_patient))
+        .route("/ready", get(handlers::readiness_check))
+        .route("/patients", get(handlers::list_patients).post(handlers::create_patient))
         .route("/patients/:id", get(handlers::get_patient))
         .route("/patients/:id", put(handlers::update_patient))
         .route("/patients/:id", delete(handlers::delete_patient))
         .route("/patients/search", get(handlers::search_patients))
+        .route("/patients/suggest", get(handlers::suggest_patients))
+        .route("/patients/compare", get(handlers::compare_patients))
         .route("/patients/match", post(handlers::match_patient))
+        .route("/patients/match/batch", post(handlers::match_batch))
+        .route("/patients/match/batch/:id", get(handlers::get_batch_match_results))
+        .route("/matching/simulate", post(handlers::simulate_match))
+        .route("/duplicates", get(handlers::list_duplicates))
+        .route("/duplicates/training-data", get(handlers::export_duplicate_training_data))
+        .route("/duplicates/:id/confirm", post(handlers::confirm_duplicate))
+        .route("/duplicates/:id/reject", post(handlers::reject_duplicate))
+        .route("/duplicates/:id/reopen", post(handlers::reopen_duplicate))
+        .route("/duplicates/:id/decisions", get(handlers::list_duplicate_decisions))
+        .route("/links/reverification", get(handlers::list_links_needing_reverification))
+        .route("/links/:id/verify", post(handlers::verify_link))
+        .route("/patients/:id/summary", get(handlers::get_patient_summary))
+        .route("/patients/:id/timeline", get(handlers::get_patient_timeline))
         .route("/patients/:id/audit", get(handlers::get_patient_audit_logs))
+        .route("/patients/:id/messages", get(handlers::get_patient_messages))
+        .route("/patients/:id/watch", post(handlers::create_patient_watch))
+        .route("/patients/:id/lock", post(handlers::lock_patient))
+        .route("/patients/:id/lock", get(handlers::get_patient_lock))
+        .route("/patients/:id/lock", delete(handlers::unlock_patient))
+        .route("/patients/:id/verification", put(handlers::set_patient_verification))
+        .route("/patients/:id/confidentiality", put(handlers::set_patient_confidentiality))
+        .route("/patients/:id/status", post(handlers::set_patient_status))
+        .route("/authorities", get(handlers::list_authorities).post(handlers::create_authority))
+        .route(
+            "/authorities/:id",
+            get(handlers::get_authority)
+                .put(handlers::update_authority)
+                .delete(handlers::delete_authority),
+        )
+        .route("/identifiers/mrn/next", post(handlers::next_mrn))
+        .route("/practitioners", get(handlers::list_practitioners).post(handlers::create_practitioner))
+        .route("/practitioners/match", post(handlers::match_practitioner))
+        .route(
+            "/practitioners/:id",
+            get(handlers::get_practitioner)
+                .put(handlers::update_practitioner)
+                .delete(handlers::delete_practitioner),
+        )
+        .route("/groups", get(handlers::list_groups))
+        .route("/groups/:id/members", post(handlers::add_group_members))
+        .route("/groups/:id/members/:patient_id", delete(handlers::remove_group_member))
+        .route("/watches/:id", delete(handlers::delete_watch))
+        .route("/watches/:id/events", get(handlers::stream_watch_events))
         .route("/audit/recent", get(handlers::get_recent_audit_logs))
         .route("/audit/user", get(handlers::get_user_audit_logs))
+        .route("/audit/:id/message", get(handlers::get_audit_entry_message))
+        .route("/messages/:id", get(handlers::get_archived_message))
+        .route("/quarantine", get(handlers::list_quarantined))
+        .route("/quarantine/:id", get(handlers::get_quarantined).delete(handlers::discard_quarantined))
+        .route("/quarantine/:id/resubmit", post(handlers::resubmit_quarantined))
+        .route("/patients/:id/change-requests", post(handlers::submit_change_request))
+        .route("/change-requests", get(handlers::list_change_requests))
+        .route("/change-requests/:id", get(handlers::get_change_request))
+        .route("/change-requests/:id/approve", post(handlers::approve_change_request))
+        .route("/change-requests/:id/reject", post(handlers::reject_change_request))
+        .route("/admin/search/snapshot", post(handlers::snapshot_search_index))
+        .route("/admin/search/restore", post(handlers::restore_search_index))
+        .route("/admin/search/reindex", post(handlers::reindex_search))
+        .route("/admin/relink", post(handlers::run_relinkage))
+        .route("/admin/link-confidence", post(handlers::run_link_confidence))
+        .route("/admin/clusters", post(handlers::run_clustering))
+        .route("/admin/integrity", post(handlers::check_integrity))
+        .route("/admin/matching/profiles", get(handlers::list_matching_profiles))
+        .route("/admin/matching/profiles/:name", get(handlers::get_matching_profile).put(handlers::update_matching_profile))
+        .route("/admin/matching/profiles/:name/versions", get(handlers::list_matching_profile_versions))
+        .route("/admin/matching/profiles/:name/restore", post(handlers::restore_matching_profile))
+        .route("/admin/config/reload", post(handlers::reload_config))
+        .route("/admin/replay", post(handlers::replay_events))
+        .route("/admin/purge", post(handlers::start_source_purge))
+        .route("/admin/export", post(handlers::start_export))
+        .route("/admin/export", get(handlers::stream_export))
+        .route("/admin/import", post(handlers::start_import))
+        .route("/admin/import/:id", get(handlers::get_import))
+        .route("/admin/import/:id/resume", post(handlers::resume_import))
+        .route("/admin/jobs", get(handlers::list_jobs))
+        .route("/admin/jobs/:id", get(handlers::get_job))
+        .route("/stats", get(handlers::get_stats))
+        .route("/reports/matching", get(handlers::get_matching_report))
+        .route("/reports/data-quality", get(handlers::get_data_quality_report))
+        .with_state(state.clone());
+
+    let fhir_routes = crate::api::fhir::routes().with_state(state.clone());
+
+    let load_shedder = state.load_shedder.clone();
+    let shedding = state.config.load_shedding.enabled;
+    let timeout_config = state.config.timeouts.clone();
+    let localizer = state.localizer.clone();
+    let compression = state.config.server.compression;
+
+    let metrics_routes = Router::new()
+        .route("/metrics", get(handlers::prometheus_metrics))
         .with_state(state);
 
-    Router::new()
+    let router = Router::new()
         .nest("/api/v1", api_routes)
-        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
-        .layer(CorsLayer::permissive())
+        .nest("/fhir", fhir_routes)
+        .merge(metrics_routes)
+        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()));
+
+    let router = router.layer(axum::middleware::from_fn_with_state(localizer, crate::api::i18n::localize_errors));
+
+    let router = router.layer(axum::middleware::from_fn_with_state(
+        Arc::new(timeout_config),
+        timeouts::enforce_budget,
+    ));
+
+    let router = if shedding {
+        router.layer(axum::middleware::from_fn_with_state(load_shedder, load_shedding::shed_load))
+    } else {
+        router
+    };
+
+    // Outside the layers that answer with their own errors, so those are converted too
+    let router = router.layer(axum::middleware::from_fn(problem::problem_details));
+
+    // Gzip or Brotli by `Accept-Encoding`; event streams and tiny bodies are left alone
+    let router = if compression {
+        router.layer(CompressionLayer::new().no_deflate().no_zstd())
+    } else {
+        router
+    };
+
+    router.layer(CorsLayer::permissive())
 }
 
 /// Start the REST API server
@@ -104,9 +473,59 @@ pub async fn serve(state: AppState) -> Result<()> {
     tracing::info!("REST API server listening on {}", addr);
     tracing::info!("Swagger UI available at http://{}/swagger-ui", addr);
 
+    #[cfg(unix)]
+    reload_on_sighup(state)?;
+
     axum::serve(listener, app)
         .await
         .map_err(|e| crate::Error::Api(e.to_string()))?;
 
     Ok(())
 }
+
+/// Reload the configuration on every SIGHUP, as `POST /api/v1/admin/config/reload` does
+#[cfg(unix)]
+fn reload_on_sighup(state: AppState) -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = signal(SignalKind::hangup()).map_err(|e| crate::Error::Api(e.to_string()))?;
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            match state.reload_config() {
+                Ok(report) if report.changed => tracing::info!("SIGHUP: configuration reloaded"),
+                Ok(_) => tracing::info!("SIGHUP: configuration unchanged"),
+                Err(e) => tracing::error!("SIGHUP: configuration reload failed: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi_covers_fhir_and_error_schemas() {
+        let doc = ApiDoc::openapi();
+        for path in ["/fhir/Patient", "/fhir/Patient/{id}", "/fhir/Patient/{id}/_history", "/fhir/AuditEvent"] {
+            assert!(doc.paths.paths.contains_key(path), "missing {}", path);
+        }
+
+        let schemas = &doc.components.as_ref().unwrap().schemas;
+        for schema in ["ApiErrorResponse", "ProblemDetails", "FhirOperationOutcome", "FhirBundle", "FhirExtension"] {
+            assert!(schemas.contains_key(schema), "missing {}", schema);
+        }
+    }
+
+    #[test]
+    fn test_committed_openapi_document_is_current() {
+        let committed: serde_json::Value =
+            serde_json::from_str(include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json"))).unwrap();
+        let current = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        assert!(
+            committed == current,
+            "openapi.json is out of date; regenerate it with `cargo run --bin mpi -- openapi --output openapi.json`"
+        );
+    }
+}
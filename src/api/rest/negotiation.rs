@@ -0,0 +1,195 @@
+//! Response format negotiation
+//!
+//! List, search and export endpoints answer with the usual JSON envelope, or
+//! with newline-delimited JSON (one item per line, no envelope) when the
+//! client's `Accept` header prefers `application/x-ndjson`. NDJSON bodies are
+//! streamed, so large results reach the client without being assembled in
+//! memory first. Requests accepting neither get `406 Not Acceptable`.
+
+use std::convert::Infallible;
+
+use axum::{
+    body::{Body, Bytes},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+use crate::api::ApiResponse;
+
+/// Media type of newline-delimited JSON responses
+pub const NDJSON: &str = "application/x-ndjson";
+
+/// Media type of RFC 7807 problem details
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Response body format chosen from the `Accept` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat {
+    /// `application/json` with the `ApiResponse` envelope
+    Json,
+    /// `application/x-ndjson`, one item per line
+    Ndjson,
+}
+
+impl ResponseFormat {
+    /// Format with the highest quality value in `Accept`
+    ///
+    /// A missing header or a wildcard means JSON. Returns `None` when the
+    /// client accepts neither format.
+    pub fn negotiate(headers: &HeaderMap) -> Option<Self> {
+        let Some(ranges) = media_ranges(headers) else {
+            return Some(ResponseFormat::Json);
+        };
+
+        let mut best: Option<(ResponseFormat, f32)> = None;
+        for (media_type, quality) in ranges {
+            let format = match media_type.as_str() {
+                "application/json" | "application/*" | "*/*" => ResponseFormat::Json,
+                NDJSON | "application/ndjson" => ResponseFormat::Ndjson,
+                _ => continue,
+            };
+            if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) {
+                best = Some((format, quality));
+            }
+        }
+        best.map(|(format, _)| format)
+    }
+}
+
+/// Whether NDJSON is acceptable at all, for endpoints that only produce NDJSON
+pub fn accepts_ndjson(headers: &HeaderMap) -> bool {
+    media_ranges(headers).is_none_or(|mut ranges| {
+        ranges.any(|(media_type, quality)| {
+            quality > 0.0
+                && matches!(media_type.as_str(), NDJSON | "application/ndjson" | "application/*" | "*/*")
+        })
+    })
+}
+
+/// Whether `Accept` asks for RFC 7807 problem details at least as strongly as for JSON
+pub fn prefers_problem_json(headers: &HeaderMap) -> bool {
+    let Some(ranges) = media_ranges(headers) else {
+        return false;
+    };
+    let (mut problem, mut json) = (0.0_f32, 0.0_f32);
+    for (media_type, quality) in ranges {
+        match media_type.as_str() {
+            PROBLEM_JSON => problem = problem.max(quality),
+            "application/json" => json = json.max(quality),
+            _ => {}
+        }
+    }
+    problem > 0.0 && problem >= json
+}
+
+/// Lowercased media ranges of `Accept` with their quality values, or `None`
+/// when the header is missing or empty
+fn media_ranges(headers: &HeaderMap) -> Option<impl Iterator<Item = (String, f32)> + '_> {
+    let accept = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.trim().is_empty())?;
+    Some(accept.split(',').map(|range| {
+        let mut parts = range.split(';');
+        let media_type = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+        let quality = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        (media_type, quality)
+    }))
+}
+
+/// `406 Not Acceptable` for a request whose `Accept` header rules out both formats
+pub fn not_acceptable() -> Response {
+    let error = ApiResponse::<()>::error(
+        "NOT_ACCEPTABLE",
+        format!("Supported response types are application/json and {}", NDJSON),
+    );
+    (StatusCode::NOT_ACCEPTABLE, Json(error)).into_response()
+}
+
+/// Stream `items` as NDJSON, serializing each line as it is sent
+pub fn ndjson<T, I>(items: I) -> Response
+where
+    T: Serialize,
+    I: IntoIterator<Item = T>,
+    I::IntoIter: Send + 'static,
+{
+    let lines = items.into_iter().filter_map(|item| match serde_json::to_vec(&item) {
+        Ok(mut line) => {
+            line.push(b'\n');
+            Some(Ok::<_, Infallible>(Bytes::from(line)))
+        }
+        Err(e) => {
+            tracing::error!("Failed to serialize NDJSON line: {}", e);
+            None
+        }
+    });
+    ndjson_body(Body::from_stream(tokio_stream::iter(lines)))
+}
+
+/// NDJSON response around an already streaming body
+pub fn ndjson_body(body: Body) -> Response {
+    let mut response = Response::new(body);
+    response
+        .headers_mut()
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static(NDJSON));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accept(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(ResponseFormat::negotiate(&HeaderMap::new()), Some(ResponseFormat::Json));
+        assert_eq!(ResponseFormat::negotiate(&accept("*/*")), Some(ResponseFormat::Json));
+        assert_eq!(ResponseFormat::negotiate(&accept(NDJSON)), Some(ResponseFormat::Ndjson));
+        assert_eq!(
+            ResponseFormat::negotiate(&accept("application/json;q=0.5, application/x-ndjson")),
+            Some(ResponseFormat::Ndjson)
+        );
+        assert_eq!(
+            ResponseFormat::negotiate(&accept("application/x-ndjson;q=0.2, */*;q=0.8")),
+            Some(ResponseFormat::Json)
+        );
+        assert_eq!(ResponseFormat::negotiate(&accept("text/csv")), None);
+        assert_eq!(ResponseFormat::negotiate(&accept("application/json;q=0")), None);
+    }
+
+    #[test]
+    fn test_accepts_ndjson() {
+        assert!(accepts_ndjson(&HeaderMap::new()));
+        assert!(accepts_ndjson(&accept("*/*")));
+        assert!(accepts_ndjson(&accept(NDJSON)));
+        assert!(!accepts_ndjson(&accept("application/json")));
+    }
+
+    #[test]
+    fn test_prefers_problem_json() {
+        assert!(prefers_problem_json(&accept(PROBLEM_JSON)));
+        assert!(prefers_problem_json(&accept("application/json, application/problem+json")));
+        assert!(!prefers_problem_json(&accept("application/json, application/problem+json;q=0.5")));
+        assert!(!prefers_problem_json(&accept("*/*")));
+        assert!(!prefers_problem_json(&HeaderMap::new()));
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_lines() {
+        let response = ndjson(vec![serde_json::json!({"a": 1}), serde_json::json!({"a": 2})]);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], NDJSON);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"{\"a\":1}\n{\"a\":2}\n");
+    }
+}
@@ -0,0 +1,145 @@
+//! RFC 7807 problem details
+//!
+//! API gateways that standardize on problem details can ask for them with
+//! `Accept: application/problem+json`. Failing REST responses are then sent
+//! as a [`ProblemDetails`] instead of the `ApiResponse` error envelope: the
+//! error code becomes the problem `type` (and the `code` extension), the
+//! message the `detail`, and any structured details the `errors` extension.
+//! FHIR routes keep answering with OperationOutcome.
+
+use axum::{
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use super::negotiation::{self, PROBLEM_JSON};
+
+/// Prefix of problem type URIs; the error code follows in kebab case
+pub const PROBLEM_TYPE_PREFIX: &str = "urn:mpi:problem:";
+
+/// RFC 7807 problem details for a failed request
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProblemDetails {
+    /// URI identifying the kind of problem, e.g. `urn:mpi:problem:validation-error`
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    /// Short summary of the kind of problem: the HTTP reason phrase
+    pub title: String,
+    /// HTTP status code
+    pub status: u16,
+    /// Explanation of this occurrence
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Path of the request that failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    /// Error code of the `ApiResponse` envelope, e.g. `VALIDATION_ERROR`
+    pub code: String,
+    /// Structured details, such as per-field validation messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<Value>,
+}
+
+impl ProblemDetails {
+    /// Problem details for an `ApiResponse` error envelope, or `None` for any other body
+    pub fn from_envelope(status: StatusCode, envelope: &Value, instance: Option<&str>) -> Option<Self> {
+        if envelope.get("success") != Some(&Value::Bool(false)) {
+            return None;
+        }
+        let error = envelope.get("error")?;
+        let code = error.get("code")?.as_str()?;
+        Some(Self {
+            problem_type: format!("{}{}", PROBLEM_TYPE_PREFIX, code.to_ascii_lowercase().replace('_', "-")),
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            detail: error.get("message").and_then(Value::as_str).map(str::to_string),
+            instance: instance.map(str::to_string),
+            code: code.to_string(),
+            errors: error.get("details").filter(|details| !details.is_null()).cloned(),
+        })
+    }
+}
+
+/// Middleware sending failing REST responses as problem details when asked for
+pub async fn problem_details(request: Request, next: Next) -> Response {
+    if !negotiation::prefers_problem_json(request.headers()) || request.uri().path().starts_with("/fhir") {
+        return next.run(request).await;
+    }
+    let instance = request.uri().path().to_string();
+    let response = next.run(request).await;
+    let status = response.status();
+
+    crate::api::rewrite_error_json(response, |body, headers| {
+        let Some(problem) = ProblemDetails::from_envelope(status, body, Some(&instance)) else {
+            return;
+        };
+        if let Ok(problem) = serde_json::to_value(problem) {
+            *body = problem;
+            headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::ApiResponse;
+
+    #[test]
+    fn test_from_envelope() {
+        let envelope = ApiResponse::<()>::error("VALIDATION_ERROR", "1 identifier(s) failed validation")
+            .with_details(serde_json::json!([{ "field": "identifiers[0].value", "message": "NPI must be ten digits" }]));
+        let envelope = serde_json::to_value(envelope).unwrap();
+
+        let problem = ProblemDetails::from_envelope(StatusCode::BAD_REQUEST, &envelope, Some("/api/v1/patients")).unwrap();
+        assert_eq!(problem.problem_type, "urn:mpi:problem:validation-error");
+        assert_eq!(problem.title, "Bad Request");
+        assert_eq!(problem.status, 400);
+        assert_eq!(problem.detail.as_deref(), Some("1 identifier(s) failed validation"));
+        assert_eq!(problem.instance.as_deref(), Some("/api/v1/patients"));
+        assert_eq!(problem.errors.unwrap()[0]["field"], "identifiers[0].value");
+    }
+
+    #[test]
+    fn test_other_bodies_are_not_envelopes() {
+        let outcome = serde_json::json!({ "resourceType": "OperationOutcome", "issue": [] });
+        assert!(ProblemDetails::from_envelope(StatusCode::NOT_FOUND, &outcome, None).is_none());
+
+        let success = serde_json::to_value(ApiResponse::success(1)).unwrap();
+        assert!(ProblemDetails::from_envelope(StatusCode::OK, &success, None).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_middleware_converts_errors() {
+        use axum::{body::Body, routing::get, Json, Router};
+        use tower::ServiceExt;
+
+        let app = Router::new()
+            .route(
+                "/missing",
+                get(|| async {
+                    (StatusCode::NOT_FOUND, Json(ApiResponse::<()>::error("NOT_FOUND", "Patient with id 'x' not found")))
+                }),
+            )
+            .layer(axum::middleware::from_fn(problem_details));
+        let request = Request::builder()
+            .uri("/missing")
+            .header(header::ACCEPT, PROBLEM_JSON)
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], PROBLEM_JSON);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let problem: ProblemDetails = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem.code, "NOT_FOUND");
+        assert_eq!(problem.instance.as_deref(), Some("/missing"));
+    }
+}
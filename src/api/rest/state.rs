@@ -1,14 +1,49 @@
 //! Application state for REST API
 
-use std::sync::Arc;
+use std::sync::{mpsc, Arc};
 use diesel::r2d2::{ConnectionManager, Pool};
 use diesel::PgConnection;
 
-use crate::search::SearchEngine;
-use crate::matching::{ProbabilisticMatcher, PatientMatcher};
-use crate::config::Config;
-use crate::db::{PatientRepository, DieselPatientRepository, AuditLogRepository};
-use crate::streaming::{EventProducer, InMemoryEventPublisher};
+use crate::search::SearchBackend;
+use crate::matching::{
+    CachingMatcher, DecisionLoggingMatcher, DedupEventProducer, DuplicateDetector, NameFrequencies, PairScoreCache,
+    ProbabilisticMatcher, PatientMatcher, ReloadableMatcher,
+};
+use crate::config::{Config, MatchingConfig, MatchingProfile, DEFAULT_PROFILE};
+use crate::db::{
+    PatientRepository, DieselPatientRepository, AuditLogRepository,
+    SourceRecordRepository, DieselSourceRecordRepository, MatchScoreRepository,
+    StatisticsRepository, MatchingKpiRepository, WatchRepository, DieselWatchRepository,
+    RecordLockRepository, DieselRecordLockRepository, RetentionRepository,
+    AssigningAuthorityRepository, DieselAssigningAuthorityRepository, CachedAssigningAuthorityRepository,
+    DuplicateCandidateRepository, DieselDuplicateCandidateRepository,
+    PractitionerRepository, DieselPractitionerRepository,
+    MessageArchiveRepository, DieselMessageArchiveRepository,
+    QuarantineRepository, DieselQuarantineRepository,
+    MrnSequenceRepository, DieselMrnSequenceRepository,
+    ChangeRequestRepository, DieselChangeRequestRepository,
+    FieldProvenanceRepository, DieselFieldProvenanceRepository, IntegrityRepository, ReadPool,
+    MatchingSettingsRepository, DieselMatchingSettingsRepository,
+    PatientGroupRepository, DieselPatientGroupRepository,
+    ImportCheckpointRepository, DieselImportCheckpointRepository, PoolWaitMonitor,
+};
+use crate::api::hl7::feed::{CircuitBreakingFeedSender, FeedEventProducer, IdentityFeed, MllpFeedSender};
+use crate::api::i18n::Localizer;
+use crate::jobs::{IntegrityCheck, JobRegistry};
+use crate::notifications::{AlertKind, Notifier};
+use crate::observability::audit_sink::AuditForwarder;
+use crate::observability::metrics::Metrics;
+use super::load_shedding::LoadShedder;
+use crate::circuit_breaker::CircuitBreakers;
+use crate::streaming::{CircuitBreakingProducer, EventProducer, InMemoryEventPublisher, PatientEvent};
+use crate::streaming::watch::{
+    CircuitBreakingWebhookSender, HttpWebhookSender, NotifyingEventProducer, WatchNotifier,
+};
+use crate::streaming::replay::EventSource;
+use crate::observability::LogLevelHandle;
+use crate::reload::{ConfigReloader, ReloadReport};
+use crate::models::{AuthorityRegistry, MatchingSettingsVersion, Patient};
+use crate::validation::IdentifierRules;
 
 /// Shared application state
 #[derive(Clone)]
@@ -19,53 +54,905 @@ pub struct AppState {
     /// Patient repository for database operations
     pub patient_repository: Arc<dyn PatientRepository>,
 
-    /// Event publisher for patient events
+    /// Source record repository (feed submissions and their links)
+    pub source_records: Arc<dyn SourceRecordRepository>,
+
+    /// Event publisher for patient events; also notifies patient watches
     pub event_publisher: Arc<dyn EventProducer>,
 
+    /// Readable view of the patient event topic, for replay
+    pub event_source: Arc<dyn EventSource>,
+
     /// Audit log repository
     pub audit_log: Arc<AuditLogRepository>,
 
-    /// Search engine for patient lookups
-    pub search_engine: Arc<SearchEngine>,
+    /// Raw inbound payloads behind creates and updates
+    pub message_archive: Arc<dyn MessageArchiveRepository>,
+
+    /// Inbound records refused by validation, held for correction
+    pub quarantine: Arc<dyn QuarantineRepository>,
+
+    /// Sequences MRNs are issued from
+    pub mrn_sequences: Arc<dyn MrnSequenceRepository>,
+
+    /// Patient-proposed corrections awaiting steward review
+    pub change_requests: Arc<dyn ChangeRequestRepository>,
+
+    /// Source of each master record field's current value
+    pub field_provenance: Arc<dyn FieldProvenanceRepository>,
+
+    /// Scored candidate pair repository
+    pub match_scores: Arc<MatchScoreRepository>,
+
+    /// Stored pair scores reused by [`AppState::pair_score_matcher`]
+    pub pair_scores: Arc<dyn PairScoreCache>,
+
+    /// Aggregate counts for the statistics endpoint
+    pub statistics: Arc<StatisticsRepository>,
+
+    /// Daily matching quality KPIs
+    pub matching_kpis: Arc<MatchingKpiRepository>,
+
+    /// Family name frequencies the matcher weighs name agreement by
+    pub name_frequencies: Arc<NameFrequencies>,
+
+    /// Patient watch subscriptions
+    pub watches: Arc<dyn WatchRepository>,
+
+    /// Delivers watch notifications by webhook and to SSE subscribers
+    pub watch_notifier: Arc<WatchNotifier>,
+
+    /// Steward locks on patient records
+    pub record_locks: Arc<dyn RecordLockRepository>,
+
+    /// Registered assigning authorities
+    pub authorities: Arc<dyn AssigningAuthorityRepository>,
+
+    /// Possible duplicate pairs awaiting review
+    pub duplicates: Arc<dyn DuplicateCandidateRepository>,
+
+    /// Registered practitioners (providers)
+    pub practitioners: Arc<dyn PractitionerRepository>,
+
+    /// Matching profiles stored through the admin API, every version
+    pub matching_settings: Arc<dyn MatchingSettingsRepository>,
+
+    /// Patient cohorts, exposed as FHIR Group resources
+    pub groups: Arc<dyn PatientGroupRepository>,
+
+    /// How far each bulk import got, for resuming it
+    pub import_checkpoints: Arc<dyn ImportCheckpointRepository>,
+
+    /// Background admin jobs and their progress
+    pub jobs: Arc<JobRegistry>,
+
+    /// Email and webhook alerts to stewards
+    pub notifier: Arc<Notifier>,
+
+    /// Prometheus metrics
+    pub metrics: Arc<Metrics>,
+
+    /// Rejects requests while the server is overloaded
+    pub load_shedder: Arc<LoadShedder>,
+
+    /// Circuit breakers around the event broker, webhooks and read replica
+    pub breakers: Arc<CircuitBreakers>,
+
+    /// Translates error messages by `Accept-Language`; replace it to embed
+    /// more message catalogs
+    pub localizer: Arc<Localizer>,
+
+    /// Search backend for patient lookups
+    pub search_engine: Arc<dyn SearchBackend>,
 
     /// Patient matcher for finding duplicates
     pub matcher: Arc<dyn PatientMatcher>,
 
-    /// Application configuration
+    /// Settings changed since startup by configuration reloads
+    pub config_reload: Arc<ConfigReloader>,
+
+    /// Application configuration, as at startup
+    ///
+    /// Read reloadable settings through [`AppState::matching_config`] or
+    /// [`AppState::config_reload`] instead.
     pub config: Arc<Config>,
 }
 
 impl AppState {
     /// Create a new application state
+    ///
+    /// `pool_wait` is the monitor [`crate::db::create_pool`] returned with
+    /// `db_pool`.
     pub fn new(
         db_pool: Pool<ConnectionManager<PgConnection>>,
-        search_engine: SearchEngine,
+        pool_wait: PoolWaitMonitor,
+        search_engine: Arc<dyn SearchBackend>,
         matcher: ProbabilisticMatcher,
         config: Config,
     ) -> Self {
+        let metrics = Arc::new(Metrics::new());
+        let notifier = steward_notifier(&config);
+        let breakers = Arc::new(
+            CircuitBreakers::new(config.circuit_breaker.clone()).with_metrics(metrics.clone())
+        );
+
         // Create event publisher
-        let event_publisher = Arc::new(InMemoryEventPublisher::new()) as Arc<dyn EventProducer>;
+        let publisher = Arc::new(InMemoryEventPublisher::new());
+        let event_source = publisher.clone() as Arc<dyn EventSource>;
+
+        // Notify patient watches of everything published
+        let watches = Arc::new(DieselWatchRepository::new(db_pool.clone())) as Arc<dyn WatchRepository>;
+        let (watch_notifier, event_publisher) = notifying_producer(publisher, watches.clone(), &breakers);
+
+        // Hand changed records to incremental dedup, when enabled
+        let (event_publisher, dedup_changes) = dedup_producer(event_publisher, &config);
+
+        // Broadcast identity changes to downstream systems, when configured
+        let (event_publisher, feed_events) = feed_producer(event_publisher, &config);
+
+        // Reads go to the read replica, when configured
+        let reads = read_pool(&db_pool, &config, &breakers);
 
-        // Create audit log repository
-        let audit_log = Arc::new(AuditLogRepository::new(db_pool.clone()));
+        // Create audit log repository, forwarding to SIEM collectors when configured
+        let mut audit_log = AuditLogRepository::new(db_pool.clone()).with_reads(reads.clone());
+        if let Some(forwarder) = AuditForwarder::from_config(&config.observability) {
+            audit_log = audit_log.with_forwarder(Arc::new(forwarder));
+        }
+        let audit_log = Arc::new(audit_log);
+
+        let message_archive = Arc::new(
+            DieselMessageArchiveRepository::new(db_pool.clone())
+        ) as Arc<dyn MessageArchiveRepository>;
+
+        let quarantine = Arc::new(
+            DieselQuarantineRepository::new(db_pool.clone())
+        ) as Arc<dyn QuarantineRepository>;
+
+        let mrn_sequences = Arc::new(
+            DieselMrnSequenceRepository::new(db_pool.clone())
+        ) as Arc<dyn MrnSequenceRepository>;
+
+        let change_requests = Arc::new(
+            DieselChangeRequestRepository::new(db_pool.clone())
+        ) as Arc<dyn ChangeRequestRepository>;
+
+        let field_provenance = Arc::new(
+            DieselFieldProvenanceRepository::new(db_pool.clone())
+        ) as Arc<dyn FieldProvenanceRepository>;
 
         // Create patient repository with event publisher and audit log
         let patient_repository = Arc::new(
             DieselPatientRepository::new(db_pool.clone())
+                .with_reads(reads)
                 .with_event_publisher(event_publisher.clone())
                 .with_audit_log(audit_log.clone())
         ) as Arc<dyn PatientRepository>;
 
-        let patient_matcher = Arc::new(matcher) as Arc<dyn PatientMatcher>;
+        let source_records = Arc::new(
+            DieselSourceRecordRepository::new(db_pool.clone())
+        ) as Arc<dyn SourceRecordRepository>;
+
+        let match_scores = Arc::new(MatchScoreRepository::new(db_pool.clone()));
+
+        let statistics = Arc::new(StatisticsRepository::new(db_pool.clone()));
+
+        let matching_kpis = Arc::new(MatchingKpiRepository::new(db_pool.clone()));
+
+        let record_locks = Arc::new(
+            DieselRecordLockRepository::new(db_pool.clone())
+        ) as Arc<dyn RecordLockRepository>;
+
+        let authorities = cached_authorities(
+            Arc::new(DieselAssigningAuthorityRepository::new(db_pool.clone())),
+            &config,
+        );
+
+        let name_frequencies = Arc::new(NameFrequencies::new(config.name_frequency.clone()));
+        let matcher = frequency_weighted(matcher, &name_frequencies, &config);
+        let (patient_matcher, config_reload) = reloadable_matcher(Arc::new(matcher), &config);
+
+        let practitioners = Arc::new(
+            DieselPractitionerRepository::new(db_pool.clone())
+        ) as Arc<dyn PractitionerRepository>;
+
+        let duplicates = Arc::new(
+            DieselDuplicateCandidateRepository::new(db_pool.clone())
+        ) as Arc<dyn DuplicateCandidateRepository>;
+        if let Some(changes) = dedup_changes {
+            DuplicateDetector::new(
+                patient_repository.clone(),
+                search_engine.clone(),
+                patient_matcher.clone(),
+                duplicates.clone(),
+                config.dedup.neighborhood_size,
+            )
+            .spawn(changes);
+        }
+
+        if let Some(events) = feed_events {
+            let sender = CircuitBreakingFeedSender::new(
+                Arc::new(MllpFeedSender::new(std::time::Duration::from_secs(config.identity_feed.timeout_secs))),
+                breakers.clone(),
+            );
+            IdentityFeed::new(&config.identity_feed, &config.hl7, patient_repository.clone(), Arc::new(sender))
+                .spawn(events);
+        }
+
+        let matching_settings = Arc::new(
+            DieselMatchingSettingsRepository::new(db_pool.clone())
+        ) as Arc<dyn MatchingSettingsRepository>;
+
+        let groups = Arc::new(
+            DieselPatientGroupRepository::new(db_pool.clone())
+        ) as Arc<dyn PatientGroupRepository>;
+
+        let import_checkpoints = Arc::new(
+            DieselImportCheckpointRepository::new(db_pool.clone())
+        ) as Arc<dyn ImportCheckpointRepository>;
+
+        let load_shedder = Arc::new(LoadShedder::new(
+            config.load_shedding.clone(),
+            pool_wait,
+            metrics.clone(),
+        ));
 
         Self {
-            db_pool,
             patient_repository,
+            source_records,
             event_publisher,
+            event_source,
             audit_log,
-            search_engine: Arc::new(search_engine),
+            message_archive,
+            quarantine,
+            mrn_sequences,
+            change_requests,
+            field_provenance,
+            pair_scores: match_scores.clone() as Arc<dyn PairScoreCache>,
+            match_scores,
+            statistics,
+            matching_kpis,
+            name_frequencies,
+            watches,
+            watch_notifier,
+            record_locks,
+            authorities,
+            duplicates,
+            practitioners,
+            matching_settings,
+            groups,
+            import_checkpoints,
+            localizer: Arc::new(Localizer::builtin()),
+            jobs: Arc::new(JobRegistry::new().with_notifier(notifier.clone())),
+            notifier,
+            metrics,
+            load_shedder,
+            breakers,
+            search_engine,
             matcher: patient_matcher,
+            config_reload,
+            config: Arc::new(config),
+            db_pool,
+        }
+    }
+
+    /// Create a database-free sandbox pre-loaded with `patients` synthetic
+    /// patients, about one in ten of them with a second, slightly different
+    /// record from another source
+    ///
+    /// Patients, source records, watches, locks, assigning authorities,
+    /// practitioners, cached pair scores, archived and quarantined messages,
+    /// MRN sequences, change requests, field provenance, stored matching
+    /// profiles, patient groups, import checkpoints and the duplicate review
+    /// queue are held in memory and the search index lives in a temporary
+    /// directory. Endpoints backed only by PostgreSQL (audit log, match
+    /// scores, statistics and reports) respond with database errors.
+    #[cfg(feature = "sandbox")]
+    pub fn sandbox(mut config: Config, patients: usize, seed: u64) -> crate::Result<Self> {
+        use crate::db::{
+            InMemoryAssigningAuthorityRepository, InMemoryChangeRequestRepository, InMemoryDuplicateCandidateRepository,
+            InMemoryFieldProvenanceRepository, InMemoryMessageArchiveRepository, InMemoryMrnSequenceRepository,
+            InMemoryPairScoreCache, InMemoryPatientRepository, InMemoryPractitionerRepository,
+            InMemoryQuarantineRepository, InMemoryRecordLockRepository, InMemorySourceRecordRepository,
+            InMemoryWatchRepository, InMemoryMatchingSettingsRepository, InMemoryPatientGroupRepository,
+            InMemoryImportCheckpointRepository,
+        };
+        use crate::models::SourceSubmission;
+
+        // Connections are never made; the pool only satisfies the type
+        let db_pool = Pool::builder()
+            .max_size(1)
+            .min_idle(Some(0))
+            .connection_timeout(std::time::Duration::from_millis(250))
+            .build_unchecked(ConnectionManager::<PgConnection>::new("postgres://sandbox.invalid/mpi"));
+
+        let index_path = std::env::temp_dir().join(format!("mpi-sandbox-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&index_path)
+            .map_err(|e| crate::Error::Search(format!("Failed to create {}: {}", index_path.display(), e)))?;
+        config.search.backend = crate::config::SearchBackendKind::Tantivy;
+        config.search.index_path = index_path.to_string_lossy().into_owned();
+        config.search.replication = Default::default();
+        if config.search.partitioning.enabled {
+            let cold_path = std::env::temp_dir().join(format!("mpi-sandbox-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&cold_path)
+                .map_err(|e| crate::Error::Search(format!("Failed to create {}: {}", cold_path.display(), e)))?;
+            config.search.partitioning.cold_index_path = cold_path.to_string_lossy().into_owned();
+        }
+        let search_engine = crate::search::create_backend(&config, &db_pool)?;
+
+        let metrics = Arc::new(Metrics::new());
+        let notifier = steward_notifier(&config);
+        let breakers = Arc::new(
+            CircuitBreakers::new(config.circuit_breaker.clone()).with_metrics(metrics.clone())
+        );
+        let publisher = Arc::new(InMemoryEventPublisher::new());
+        let event_source = publisher.clone() as Arc<dyn EventSource>;
+        let watches = Arc::new(InMemoryWatchRepository::new()) as Arc<dyn WatchRepository>;
+        let (watch_notifier, event_publisher) = notifying_producer(publisher, watches.clone(), &breakers);
+        let (event_publisher, dedup_changes) = dedup_producer(event_publisher, &config);
+
+        let source_records = Arc::new(InMemorySourceRecordRepository::new()) as Arc<dyn SourceRecordRepository>;
+        let patient_repository = Arc::new(
+            InMemoryPatientRepository::new()
+                .with_event_publisher(event_publisher.clone())
+                .with_source_records(source_records.clone())
+        ) as Arc<dyn PatientRepository>;
+
+        // Load the synthetic population through the same paths a feed would use
+        let mut generator = crate::testdata::PatientGenerator::new(seed);
+        let mut loaded = Vec::with_capacity(patients + patients / 10);
+        for n in 0..patients {
+            let patient = generator.patient();
+            let mrn = patient.identifiers.first().map(|id| id.value.clone());
+            let hospital = SourceSubmission::new("sandbox-hospital", mrn.as_deref(), &patient);
+            if n % 10 == 0 {
+                let duplicate = generator.duplicate(&patient);
+                let clinic = SourceSubmission::new("sandbox-clinic", Some(&duplicate.id.to_string()), &duplicate);
+                loaded.push(patient_repository.create_from_source(&duplicate, &clinic)?);
+            }
+            loaded.push(patient_repository.create_from_source(&patient, &hospital)?);
+        }
+        search_engine.index_patients(&loaded)?;
+        tracing::info!("Sandbox loaded {} synthetic patients (seed {})", loaded.len(), seed);
+
+        let name_frequencies = Arc::new(NameFrequencies::new(config.name_frequency.clone()));
+        let (matcher, config_reload) = reloadable_matcher(
+            Arc::new(frequency_weighted(ProbabilisticMatcher::new(config.matching.clone()), &name_frequencies, &config)),
+            &config,
+        );
+        let duplicates = Arc::new(InMemoryDuplicateCandidateRepository::new()) as Arc<dyn DuplicateCandidateRepository>;
+        if let Some(changes) = dedup_changes {
+            DuplicateDetector::new(
+                patient_repository.clone(),
+                search_engine.clone(),
+                matcher.clone(),
+                duplicates.clone(),
+                config.dedup.neighborhood_size,
+            )
+            .spawn(changes);
+        }
+        let load_shedder = Arc::new(LoadShedder::new(
+            config.load_shedding.clone(),
+            PoolWaitMonitor::default(),
+            metrics.clone(),
+        ));
+
+        Ok(Self {
+            audit_log: Arc::new(AuditLogRepository::new(db_pool.clone())),
+            message_archive: Arc::new(InMemoryMessageArchiveRepository::new()),
+            quarantine: Arc::new(InMemoryQuarantineRepository::new()),
+            mrn_sequences: Arc::new(InMemoryMrnSequenceRepository::new()),
+            change_requests: Arc::new(InMemoryChangeRequestRepository::new()),
+            field_provenance: Arc::new(InMemoryFieldProvenanceRepository::new()),
+            match_scores: Arc::new(MatchScoreRepository::new(db_pool.clone())),
+            pair_scores: Arc::new(InMemoryPairScoreCache::new()),
+            statistics: Arc::new(StatisticsRepository::new(db_pool.clone())),
+            matching_kpis: Arc::new(MatchingKpiRepository::new(db_pool.clone())),
+            name_frequencies,
+            db_pool,
+            patient_repository,
+            source_records,
+            event_publisher,
+            event_source,
+            watches,
+            watch_notifier,
+            record_locks: Arc::new(InMemoryRecordLockRepository::new()),
+            authorities: cached_authorities(Arc::new(InMemoryAssigningAuthorityRepository::new()), &config),
+            duplicates,
+            practitioners: Arc::new(InMemoryPractitionerRepository::new()),
+            matching_settings: Arc::new(InMemoryMatchingSettingsRepository::new()),
+            groups: Arc::new(InMemoryPatientGroupRepository::new()),
+            import_checkpoints: Arc::new(InMemoryImportCheckpointRepository::new()),
+            localizer: Arc::new(Localizer::builtin()),
+            jobs: Arc::new(JobRegistry::new().with_notifier(notifier.clone())),
+            notifier,
+            metrics,
+            load_shedder,
+            breakers,
+            search_engine,
+            matcher,
+            config_reload,
             config: Arc::new(config),
+        })
+    }
+
+    /// Share the search index with other replicas as configured in
+    /// `search.replication`
+    ///
+    /// Must be called from within a Tokio runtime. Returns the background
+    /// replication task, if replication is enabled.
+    pub fn start_index_replication(&mut self) -> crate::Result<Option<tokio::task::JoinHandle<()>>> {
+        let replication = crate::search::replication::start_replication(
+            &self.config.search.replication,
+            self.search_engine.clone(),
+            self.event_source.clone(),
+            &self.db_pool,
+        )?;
+        Ok(replication.map(|handle| {
+            self.search_engine = handle.index;
+            handle.task
+        }))
+    }
+
+    /// Change the log level through `handle` on configuration reloads
+    pub fn with_log_level_handle(self, handle: LogLevelHandle) -> Self {
+        self.config_reload.set_log_level_handle(handle);
+        self
+    }
+
+    /// Identifier checks with the assigning authorities registered now
+    ///
+    /// If the registry cannot be read, only the format checks apply.
+    pub fn identifier_rules(&self) -> IdentifierRules {
+        IdentifierRules::new(self.config.identifiers.clone(), self.authority_registry())
+    }
+
+    /// Snapshot of the registered assigning authorities, empty if it cannot be read
+    pub fn authority_registry(&self) -> AuthorityRegistry {
+        self.authorities.registry().unwrap_or_else(|e| {
+            tracing::warn!("Failed to load assigning authorities: {}", e);
+            AuthorityRegistry::default()
+        })
+    }
+
+    /// The matcher, reusing stored scores of pairs of stored patients
+    ///
+    /// Use it only where both patients of every pair are stored, unchanged
+    /// records; scores of other pairs cannot be stored.
+    pub fn pair_score_matcher(&self) -> CachingMatcher {
+        let matcher = CachingMatcher::new(self.matcher.clone(), self.pair_scores.clone(), &self.matching_config());
+        frequency_keyed(matcher, &self.name_frequencies, &self.config)
+    }
+
+    /// Matching configuration with reloaded threshold and weights, and those
+    /// of the stored `default` profile over them
+    pub fn matching_config(&self) -> MatchingConfig {
+        let mut config = (*self.config).clone();
+        self.config_reload.settings().apply(&mut config);
+        match self.config_reload.stored_profile(DEFAULT_PROFILE) {
+            Some(stored) => stored.settings.apply(&config.matching),
+            None => config.matching,
         }
     }
+
+    /// The matching profile called `name`: the stored one, else the
+    /// configured one
+    ///
+    /// `default` is the profile of requests that name none.
+    pub fn matching_profile(&self, name: &str) -> Option<MatchingProfile> {
+        match self.config_reload.stored_profile(name) {
+            Some(stored) => Some(stored.settings),
+            None if name == DEFAULT_PROFILE => Some(MatchingProfile::default()),
+            None => self.config.matching_profiles.get(name).cloned(),
+        }
+    }
+
+    /// The profile of requests that name none
+    pub fn default_profile(&self) -> MatchingProfile {
+        self.matching_profile(DEFAULT_PROFILE).unwrap_or_default()
+    }
+
+    /// Names of every configured and stored matching profile, `default` first
+    pub fn matching_profile_names(&self) -> Vec<String> {
+        let mut names: std::collections::BTreeSet<String> = self.config.matching_profiles.keys().cloned().collect();
+        names.extend(self.config_reload.stored_profiles().into_keys());
+        names.remove(DEFAULT_PROFILE);
+        std::iter::once(DEFAULT_PROFILE.to_string()).chain(names).collect()
+    }
+
+    /// Store new settings for the matching profile `name` and apply them to
+    /// requests that start afterwards
+    ///
+    /// The settings must be valid over [`AppState::matching_config`]. The
+    /// change is recorded in the audit log with the settings it replaced.
+    pub fn save_matching_profile(
+        &self,
+        name: &str,
+        settings: &MatchingProfile,
+        changed_by: Option<String>,
+        note: Option<String>,
+    ) -> crate::Result<MatchingSettingsVersion> {
+        settings.apply(&self.matching_config()).validate()?;
+        if settings.max_candidates == 0 {
+            return Err(crate::Error::Validation("max_candidates must be at least 1".to_string()));
+        }
+
+        let old = self.matching_profile(name);
+        let version = self.matching_settings.save(name, settings, changed_by.clone(), note)?;
+        if let Err(e) = self.refresh_matching_settings() {
+            // The next refresh or reload applies it
+            tracing::warn!("Failed to apply matching profile '{}' version {}: {}", name, version.version, e);
+        }
+
+        if let Err(e) = self.audit_log.log_update(
+            "MatchingProfile",
+            version.id,
+            serde_json::json!({ "profile": name, "settings": old }),
+            serde_json::json!({ "profile": name, "version": version.version, "settings": settings, "note": version.note }),
+            changed_by,
+            None,
+            None,
+        ) {
+            tracing::warn!("Failed to record matching profile change in the audit log: {}", e);
+        }
+        Ok(version)
+    }
+
+    /// Re-read the stored matching profiles, returning whether any changed
+    ///
+    /// A change to the `default` profile replaces the matcher.
+    pub fn refresh_matching_settings(&self) -> crate::Result<bool> {
+        let stored: std::collections::BTreeMap<String, MatchingSettingsVersion> = self
+            .matching_settings
+            .current()?
+            .into_iter()
+            .map(|version| (version.profile.clone(), version))
+            .collect();
+        let previous = self.config_reload.stored_profiles();
+        if stored == previous {
+            return Ok(false);
+        }
+
+        let default_changed = stored.get(DEFAULT_PROFILE) != previous.get(DEFAULT_PROFILE);
+        self.config_reload.store_profiles(stored);
+        if default_changed {
+            self.replace_matcher();
+        }
+        Ok(true)
+    }
+
+    /// Replace the matcher with one built from [`AppState::matching_config`]
+    fn replace_matcher(&self) {
+        let mut config = (*self.config).clone();
+        config.matching = self.matching_config();
+        let matcher = ProbabilisticMatcher::new(config.matching.clone());
+        let matcher = log_decisions(Arc::new(frequency_weighted(matcher, &self.name_frequencies, &config)), &config);
+        self.config_reload.matcher().replace(matcher);
+    }
+
+    /// Matcher for a matching profile, applied over [`AppState::matching_config`]
+    ///
+    /// With `cached`, pair scores are reused and stored as by
+    /// [`AppState::pair_score_matcher`].
+    pub fn profile_matcher(&self, profile: &MatchingProfile, cached: bool) -> crate::Result<Arc<dyn PatientMatcher>> {
+        let mut config = (*self.config).clone();
+        config.matching = profile.apply(&self.matching_config());
+        config.matching.validate()?;
+
+        let matcher = ProbabilisticMatcher::new(config.matching.clone());
+        let matcher = log_decisions(Arc::new(frequency_weighted(matcher, &self.name_frequencies, &config)), &config);
+        if cached {
+            let matcher = CachingMatcher::new(matcher, self.pair_scores.clone(), &config.matching);
+            Ok(Arc::new(frequency_keyed(matcher, &self.name_frequencies, &config)))
+        } else {
+            Ok(matcher)
+        }
+    }
+
+    /// Re-read the configuration and apply the settings that can change at runtime
+    ///
+    /// Nothing is applied unless the new settings are valid. The matcher is
+    /// replaced for requests that start afterwards, and a change is recorded
+    /// in the audit log. Stored matching profiles are re-read as well.
+    pub fn reload_config(&self) -> crate::Result<ReloadReport> {
+        if let Err(e) = self.refresh_matching_settings() {
+            tracing::warn!("Failed to re-read stored matching profiles: {}", e);
+        }
+
+        let old = self.config_reload.settings();
+        let new = self.config_reload.load()?;
+        if new == old {
+            return Ok(ReloadReport { changed: false, old, new });
+        }
+
+        self.config_reload.set_log_level(&new.log_level)?;
+
+        let mut config = (*self.config).clone();
+        new.apply(&mut config);
+        self.config_reload.store(new.clone());
+        self.replace_matcher();
+        self.load_shedder.set_limits(config.load_shedding.clone());
+
+        tracing::info!("Configuration reloaded: {:?} -> {:?}", old, new);
+        if let Err(e) = self.audit_log.log_config_reload(
+            serde_json::to_value(&old).unwrap_or_default(),
+            serde_json::to_value(&new).unwrap_or_default(),
+            None,
+        ) {
+            tracing::warn!("Failed to record configuration reload in the audit log: {}", e);
+        }
+
+        Ok(ReloadReport { changed: true, old, new })
+    }
+
+    /// Start the daily matching KPI job
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn start_kpi_reporting(&self) -> tokio::task::JoinHandle<()> {
+        crate::reporting::MatchingKpiJob::new(
+            self.matching_kpis.clone(),
+            self.config.reporting.clone(),
+        )
+        .spawn()
+    }
+
+    /// Start the retention job, if `retention.enabled`
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn start_retention(&self) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config.retention.enabled {
+            return None;
+        }
+        let job = crate::jobs::RetentionJob::new(
+            self.patient_repository.clone(),
+            Arc::new(RetentionRepository::new(self.db_pool.clone())),
+            self.search_engine.clone(),
+            self.config.retention.clone(),
+        );
+        Some(job.spawn())
+    }
+
+    /// Link confidence job over the current source record links
+    pub fn link_confidence_job(&self) -> crate::jobs::LinkConfidenceJob {
+        crate::jobs::LinkConfidenceJob::new(
+            self.patient_repository.clone(),
+            self.source_records.clone(),
+            self.config.link_confidence.clone(),
+            self.config.reporting.automatic_linker.clone(),
+        )
+        .with_notifier(self.notifier.clone())
+    }
+
+    /// Start the link confidence job, if `link_confidence.enabled`
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn start_link_confidence(&self) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config.link_confidence.enabled {
+            return None;
+        }
+        Some(self.link_confidence_job().spawn())
+    }
+
+    /// Data integrity check over the database and search index
+    pub fn integrity_check(&self) -> IntegrityCheck {
+        IntegrityCheck::new(
+            Arc::new(IntegrityRepository::new(self.db_pool.clone())),
+            self.patient_repository.clone(),
+            self.search_engine.clone(),
+            self.audit_log.clone(),
+            self.authority_registry(),
+            self.config.integrity.clone(),
+        )
+    }
+
+    /// Run the data integrity check once without repairs, if
+    /// `integrity.check_on_startup`, and log what it finds
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn start_integrity_check(&self) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config.integrity.check_on_startup {
+            return None;
+        }
+        let check = self.integrity_check();
+        Some(tokio::task::spawn_blocking(move || match check.run(false, None) {
+            Ok(report) if report.found() == 0 => tracing::info!("Integrity check found no problems"),
+            Ok(report) => {
+                for (kind, count) in &report.counts {
+                    if count.found > 0 {
+                        tracing::warn!(
+                            "Integrity check: {} {:?} problems{}",
+                            count.found,
+                            kind,
+                            if count.truncated { " or more" } else { "" }
+                        );
+                    }
+                }
+                tracing::warn!(
+                    "Integrity check found {} problems; POST /api/v1/admin/integrity for the report",
+                    report.found()
+                );
+            }
+            Err(e) => tracing::error!("Integrity check failed: {}", e),
+        }))
+    }
+
+    /// Load the stored matching profiles, then check for changes made by
+    /// other instances every `matching_settings.refresh_secs`
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn start_matching_settings_refresh(&self) -> tokio::task::JoinHandle<()> {
+        let state = self.clone();
+        let refresh_secs = self.config.matching_settings.refresh_secs;
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(refresh_secs.max(1)));
+            loop {
+                interval.tick().await;
+                let refresh = state.clone();
+                match tokio::task::spawn_blocking(move || refresh.refresh_matching_settings()).await {
+                    Ok(Ok(true)) => tracing::info!("Applied stored matching profiles"),
+                    Ok(Ok(false)) => {}
+                    Ok(Err(e)) => tracing::warn!("Failed to read stored matching profiles: {}", e),
+                    Err(e) => tracing::error!("Matching profile refresh failed: {}", e),
+                }
+                if refresh_secs == 0 {
+                    break;
+                }
+            }
+        })
+    }
+
+    /// Start the review backlog alert, if `notifications.enabled` and the
+    /// `review_backlog` alert has recipients
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn start_review_backlog_alerts(&self) -> Option<tokio::task::JoinHandle<()>> {
+        if self.notifier.recipients(AlertKind::ReviewBacklog).is_empty() {
+            return None;
+        }
+        let monitor = crate::jobs::ReviewBacklogMonitor::new(
+            self.duplicates.clone(),
+            self.notifier.clone(),
+            self.config.notifications.review_backlog.clone(),
+        );
+        Some(monitor.spawn())
+    }
+
+    /// Start the name frequency recount, if `name_frequency.enabled`
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn start_name_frequencies(&self) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config.name_frequency.enabled {
+            return None;
+        }
+        let job = crate::jobs::NameFrequencyJob::new(
+            self.statistics.clone(),
+            self.name_frequencies.clone(),
+            self.config.name_frequency.refresh_hours,
+        );
+        Some(job.spawn())
+    }
+}
+
+/// Weigh the matcher's name agreement by `frequencies`, if `name_frequency.enabled`
+fn frequency_weighted(
+    matcher: ProbabilisticMatcher,
+    frequencies: &Arc<NameFrequencies>,
+    config: &Config,
+) -> ProbabilisticMatcher {
+    if config.name_frequency.enabled {
+        matcher.with_name_frequencies(frequencies.clone())
+    } else {
+        matcher
+    }
+}
+
+/// Key the cached scores of a matcher from [`frequency_weighted`] by
+/// `frequencies`, if `name_frequency.enabled`
+fn frequency_keyed(matcher: CachingMatcher, frequencies: &Arc<NameFrequencies>, config: &Config) -> CachingMatcher {
+    if config.name_frequency.enabled {
+        matcher.with_name_frequencies(frequencies.clone())
+    } else {
+        matcher
+    }
+}
+
+/// Reads from `database.read_replica_url` behind the `read_replica` breaker,
+/// or from `db_pool` when no replica is configured
+fn read_pool(
+    db_pool: &Pool<ConnectionManager<PgConnection>>,
+    config: &Config,
+    breakers: &CircuitBreakers,
+) -> ReadPool {
+    let reads = ReadPool::new(db_pool.clone());
+    match crate::db::create_replica_pool(&config.database) {
+        Some(replica) => reads.with_replica(replica, breakers.get("read_replica")),
+        None => reads,
+    }
+}
+
+/// Serve authority lookups from memory, unless `lookup_cache.ttl_secs` is 0
+fn cached_authorities(
+    authorities: Arc<dyn AssigningAuthorityRepository>,
+    config: &Config,
+) -> Arc<dyn AssigningAuthorityRepository> {
+    if config.lookup_cache.ttl_secs == 0 {
+        return authorities;
+    }
+    Arc::new(CachedAssigningAuthorityRepository::new(
+        authorities,
+        std::time::Duration::from_secs(config.lookup_cache.ttl_secs),
+    ))
+}
+
+/// Wrap the matcher to log its decisions, if `decision_log.enabled`
+fn log_decisions(matcher: Arc<dyn PatientMatcher>, config: &Config) -> Arc<dyn PatientMatcher> {
+    if !config.decision_log.enabled {
+        return matcher;
+    }
+    Arc::new(DecisionLoggingMatcher::new(
+        matcher,
+        "probabilistic",
+        config.matching.threshold_score,
+        config.decision_log.clone(),
+    ))
+}
+
+/// The matcher behind a [`ReloadableMatcher`], and the reloader that replaces it
+fn reloadable_matcher(matcher: Arc<dyn PatientMatcher>, config: &Config) -> (Arc<dyn PatientMatcher>, Arc<ConfigReloader>) {
+    let reloadable = Arc::new(ReloadableMatcher::new(log_decisions(matcher, config)));
+    let config_reload = Arc::new(ConfigReloader::new(config, reloadable.clone()));
+    (reloadable as Arc<dyn PatientMatcher>, config_reload)
+}
+
+/// Event producer that also feeds incremental dedup when `dedup.incremental`
+/// is set, with the receiver to start the detector on
+fn dedup_producer(
+    inner: Arc<dyn EventProducer>,
+    config: &Config,
+) -> (Arc<dyn EventProducer>, Option<mpsc::Receiver<Patient>>) {
+    if !config.dedup.incremental {
+        return (inner, None);
+    }
+    let (producer, changes) = DedupEventProducer::new(inner);
+    (Arc::new(producer), Some(changes))
+}
+
+/// Event producer that also feeds identity changes to downstream systems,
+/// when any destination is configured
+fn feed_producer(
+    inner: Arc<dyn EventProducer>,
+    config: &Config,
+) -> (Arc<dyn EventProducer>, Option<mpsc::Receiver<PatientEvent>>) {
+    if config.identity_feed.destinations.is_empty() {
+        return (inner, None);
+    }
+    let (producer, events) = FeedEventProducer::new(inner);
+    (Arc::new(producer), Some(events))
+}
+
+/// Steward alerts as `notifications` configures them, or none if the mail
+/// server settings are unusable
+fn steward_notifier(config: &Config) -> Arc<Notifier> {
+    match Notifier::from_config(&config.notifications) {
+        Ok(notifier) => Arc::new(notifier),
+        Err(e) => {
+            tracing::error!("Steward alerts are disabled: {}", e);
+            Arc::new(Notifier::disabled())
+        }
+    }
+}
+
+/// Event producer that notifies watches, with the broker and webhooks behind
+/// circuit breakers
+fn notifying_producer(
+    publisher: Arc<InMemoryEventPublisher>,
+    watches: Arc<dyn WatchRepository>,
+    breakers: &Arc<CircuitBreakers>,
+) -> (Arc<WatchNotifier>, Arc<dyn EventProducer>) {
+    let webhooks = CircuitBreakingWebhookSender::new(Arc::new(HttpWebhookSender::default()), breakers.clone());
+    let watch_notifier = Arc::new(WatchNotifier::new(watches).with_webhook_sender(Arc::new(webhooks)));
+    let broker = CircuitBreakingProducer::new(publisher, breakers.get("event_broker"));
+    let event_publisher = Arc::new(
+        NotifyingEventProducer::new(Arc::new(broker), watch_notifier.clone())
+    ) as Arc<dyn EventProducer>;
+    (watch_notifier, event_publisher)
 }
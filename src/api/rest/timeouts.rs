@@ -0,0 +1,162 @@
+//! Per-request time budgets
+//!
+//! Each request gets the budget of its endpoint class from `timeouts`. When
+//! the budget runs out the client gets `504 Gateway Timeout` with a
+//! `TIMEOUT` error instead of a hanging connection. The budget is also set as
+//! the task's [`deadline`](crate::deadline), so repository, search and
+//! matching loops stop at their next check rather than running on.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::api::ApiResponse;
+use crate::config::TimeoutConfig;
+
+/// Endpoint classes with their own budgets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndpointClass {
+    /// Searches and suggestions
+    Search,
+    /// Matching
+    Match,
+    /// Admin batch operations
+    Batch,
+    /// Server-sent event streams, never timed out
+    Stream,
+    /// Everything else
+    Default,
+}
+
+impl EndpointClass {
+    /// Class of a request
+    pub fn of(method: &Method, path: &str) -> Self {
+        if path.starts_with("/api/v1/watches/") && path.ends_with("/events") {
+            EndpointClass::Stream
+        } else if path.starts_with("/api/v1/admin/") || path.starts_with("/api/v1/patients/match/batch") {
+            EndpointClass::Batch
+        } else if matches!(path, "/api/v1/patients/match" | "/api/v1/matching/simulate") {
+            EndpointClass::Match
+        } else if matches!(path, "/api/v1/patients/search" | "/api/v1/patients/suggest")
+            || (method == Method::GET && matches!(path, "/fhir/Patient" | "/fhir/Provenance" | "/fhir/AuditEvent"))
+        {
+            EndpointClass::Search
+        } else {
+            EndpointClass::Default
+        }
+    }
+
+    /// Budget of the class, or `None` for unlimited
+    pub fn budget(&self, config: &TimeoutConfig) -> Option<Duration> {
+        let millis = match self {
+            EndpointClass::Search => config.search_ms,
+            EndpointClass::Match => config.match_ms,
+            EndpointClass::Batch => config.batch_ms,
+            EndpointClass::Stream => 0,
+            EndpointClass::Default => config.default_ms,
+        };
+        (millis > 0).then(|| Duration::from_millis(millis))
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            EndpointClass::Search => "search",
+            EndpointClass::Match => "match",
+            EndpointClass::Batch => "batch",
+            EndpointClass::Stream => "stream",
+            EndpointClass::Default => "default",
+        }
+    }
+}
+
+/// Middleware enforcing the budget of each request's endpoint class
+///
+/// A handler that notices the deadline usually reports it as its own server
+/// error; once the deadline has passed, server errors are replaced by the
+/// timeout response so clients see one consistent error.
+pub async fn enforce_budget(State(config): State<Arc<TimeoutConfig>>, request: Request, next: Next) -> Response {
+    let class = EndpointClass::of(request.method(), request.uri().path());
+    let Some(budget) = class.budget(&config) else {
+        return next.run(request).await;
+    };
+
+    let deadline = Instant::now() + budget;
+    match tokio::time::timeout(budget, crate::deadline::scope(deadline, next.run(request))).await {
+        Ok(response) if response.status().is_server_error() && Instant::now() >= deadline => {
+            timeout_response(class, budget)
+        }
+        Ok(response) => response,
+        Err(_) => timeout_response(class, budget),
+    }
+}
+
+fn timeout_response(class: EndpointClass, budget: Duration) -> Response {
+    let error = ApiResponse::<()>::error(
+        "TIMEOUT",
+        format!("Request exceeded its {} ms {} budget", budget.as_millis(), class.as_str()),
+    )
+    .with_details(serde_json::json!({
+        "class": class.as_str(),
+        "budget_ms": budget.as_millis() as u64,
+    }));
+    (StatusCode::GATEWAY_TIMEOUT, Json(error)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_endpoint_classes() {
+        let config = TimeoutConfig::default();
+        let class = |method: Method, path: &str| EndpointClass::of(&method, path);
+
+        assert_eq!(class(Method::GET, "/api/v1/patients/search"), EndpointClass::Search);
+        assert_eq!(class(Method::GET, "/fhir/Patient"), EndpointClass::Search);
+        assert_eq!(class(Method::POST, "/fhir/Patient"), EndpointClass::Default);
+        assert_eq!(class(Method::POST, "/api/v1/patients/match"), EndpointClass::Match);
+        assert_eq!(class(Method::POST, "/api/v1/patients/match/batch"), EndpointClass::Batch);
+        assert_eq!(class(Method::POST, "/api/v1/admin/relink"), EndpointClass::Batch);
+        assert_eq!(class(Method::GET, "/api/v1/watches/abc/events"), EndpointClass::Stream);
+
+        assert_eq!(EndpointClass::Search.budget(&config), Some(Duration::from_secs(2)));
+        assert_eq!(EndpointClass::Match.budget(&config), Some(Duration::from_secs(10)));
+        assert_eq!(EndpointClass::Batch.budget(&config), None);
+        assert_eq!(EndpointClass::Stream.budget(&config), None);
+    }
+
+    #[tokio::test]
+    async fn test_slow_request_times_out() {
+        let config = TimeoutConfig {
+            search_ms: 20,
+            ..TimeoutConfig::default()
+        };
+        let app = Router::new()
+            .route(
+                "/api/v1/patients/search",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    "late"
+                }),
+            )
+            .route("/api/v1/patients/suggest", get(|| async { "on time" }))
+            .layer(axum::middleware::from_fn_with_state(Arc::new(config), enforce_budget));
+
+        let request = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let slow = app.clone().oneshot(request("/api/v1/patients/search")).await.unwrap();
+        assert_eq!(slow.status(), StatusCode::GATEWAY_TIMEOUT);
+
+        let fast = app.oneshot(request("/api/v1/patients/suggest")).await.unwrap();
+        assert_eq!(fast.status(), StatusCode::OK);
+    }
+}
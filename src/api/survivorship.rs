@@ -0,0 +1,175 @@
+//! Survivorship of master record fields
+//!
+//! Every write to a patient names its source: the sender's `X-Source-System`
+//! header, a FHIR `meta.source`, the HL7 sending application, or else the
+//! channel it arrived on. Before an update is stored, [`apply`] puts back
+//! each changed field whose current value came from a source ranked higher
+//! in `survivorship.source_priority`; once it is stored, [`record`] notes
+//! the write's source against the fields it changed. The write itself is
+//! kept as a source record linked to the patient.
+
+use axum::http::HeaderMap;
+use uuid::Uuid;
+
+use crate::api::rest::AppState;
+use crate::config::SurvivorshipConfig;
+use crate::models::field_provenance::DEMOGRAPHIC_FIELDS;
+use crate::models::{FieldProvenance, Patient, SourceSubmission};
+
+/// Header naming the system a write comes from
+pub const SOURCE_HEADER: &str = "x-source-system";
+
+/// Source named by the request headers, if any
+pub fn source_from_headers(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(SOURCE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+/// Header giving the sender's own identifier of the record it writes
+pub const SOURCE_RECORD_HEADER: &str = "x-source-record-id";
+
+/// Submission of `patient` by `source`, as kept in the source records,
+/// identified and attributed by the request headers where they say so
+pub fn submission_from_headers(headers: &HeaderMap, source: &str, patient: &Patient) -> SourceSubmission {
+    let record_id = headers.get(SOURCE_RECORD_HEADER).and_then(|v| v.to_str().ok());
+    SourceSubmission::new(source, record_id, patient)
+        .with_received_by(crate::api::privacy::Requester::from_headers(headers).user_id)
+}
+
+/// Demographic fields whose value differs between `old` and `new`, or every
+/// field `new` has a value for when there is no `old`
+pub fn changed_fields(old: Option<&Patient>, new: &Patient) -> Vec<&'static str> {
+    let new = serde_json::to_value(new).unwrap_or_default();
+    let old = old.map(|old| serde_json::to_value(old).unwrap_or_default());
+    DEMOGRAPHIC_FIELDS
+        .iter()
+        .copied()
+        .filter(|field| {
+            let value = &new[*field];
+            match &old {
+                Some(old) => old[*field] != *value,
+                None => !value.is_null() && value.as_array().is_none_or(|values| !values.is_empty()),
+            }
+        })
+        .collect()
+}
+
+/// Keep `existing`'s value of each changed field `source` may not
+/// overwrite, returning those fields
+pub fn survive(
+    config: &SurvivorshipConfig,
+    provenance: &[FieldProvenance],
+    existing: &Patient,
+    incoming: &mut Patient,
+    source: &str,
+) -> Vec<&'static str> {
+    let rank = config.rank(source);
+    let kept: Vec<&'static str> = changed_fields(Some(existing), incoming)
+        .into_iter()
+        .filter(|field| {
+            provenance
+                .iter()
+                .find(|p| p.field == *field)
+                .is_some_and(|current| config.rank(&current.source) < rank)
+        })
+        .collect();
+    for field in &kept {
+        copy_field(field, existing, incoming);
+    }
+    kept
+}
+
+/// Copy one of [`DEMOGRAPHIC_FIELDS`] from `from` to `to`
+fn copy_field(field: &str, from: &Patient, to: &mut Patient) {
+    match field {
+        "name" => to.name = from.name.clone(),
+        "additional_names" => to.additional_names = from.additional_names.clone(),
+        "telecom" => to.telecom = from.telecom.clone(),
+        "gender" => to.gender = from.gender,
+        "gender_identity" => to.gender_identity = from.gender_identity.clone(),
+        "pronouns" => to.pronouns = from.pronouns.clone(),
+        "birth_date" => to.birth_date = from.birth_date,
+        "deceased" => to.deceased = from.deceased,
+        "deceased_datetime" => to.deceased_datetime = from.deceased_datetime,
+        "addresses" => to.addresses = from.addresses.clone(),
+        "marital_status" => to.marital_status = from.marital_status.clone(),
+        "multiple_birth" => to.multiple_birth = from.multiple_birth,
+        _ => {}
+    }
+}
+
+/// Apply survivorship to an update of `existing` from `source`
+///
+/// Returns the fields kept. Without a configured source priority every
+/// write goes through and provenance is not read.
+pub fn apply(state: &AppState, existing: &Patient, incoming: &mut Patient, source: &str) -> Vec<&'static str> {
+    let config = &state.config.survivorship;
+    if config.source_priority.is_empty() {
+        return Vec::new();
+    }
+    let provenance = match state.field_provenance.list_for_patient(&existing.id) {
+        Ok(provenance) => provenance,
+        Err(e) => {
+            tracing::warn!("Failed to read field provenance of patient {}: {}", existing.id, e);
+            return Vec::new();
+        }
+    };
+    let kept = survive(config, &provenance, existing, incoming, source);
+    if !kept.is_empty() {
+        tracing::info!(
+            "Kept {} of patient {} over less authoritative source '{}'",
+            kept.join(", "),
+            existing.id,
+            source
+        );
+    }
+    kept
+}
+
+/// Record `source` against the fields a stored create or update changed
+///
+/// Failures are logged and never fail the change, which is already stored.
+pub fn record(state: &AppState, patient_id: Uuid, old: Option<&Patient>, new: &Patient, source: &str) {
+    let fields = changed_fields(old, new);
+    if let Err(e) = state.field_provenance.record(&patient_id, &fields, source) {
+        tracing::warn!("Failed to record field provenance of patient {}: {}", patient_id, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::patient;
+    use chrono::Utc;
+    use crate::models::Gender;
+
+    #[test]
+    fn test_less_authoritative_source_cannot_overwrite() {
+        let config = SurvivorshipConfig {
+            source_priority: vec!["registration".to_string(), "lab".to_string()],
+        };
+        let existing = patient("Adeyemi", &["Ngozi"], Gender::Female);
+        let provenance = vec![FieldProvenance {
+            field: "name".to_string(),
+            source: "registration".to_string(),
+            recorded_at: Utc::now(),
+        }];
+        assert_eq!(changed_fields(None, &existing), vec!["name", "gender", "deceased"]);
+
+        let mut incoming = existing.clone();
+        incoming.name.family = "Adeyemy".to_string();
+        incoming.marital_status = Some("M".to_string());
+        assert_eq!(survive(&config, &provenance, &existing, &mut incoming, "lab"), vec!["name"]);
+        assert_eq!(incoming.name.family, "Adeyemi");
+        assert_eq!(incoming.marital_status.as_deref(), Some("M"));
+
+        let mut incoming = existing.clone();
+        incoming.name.family = "Adeyemi-Cole".to_string();
+        assert!(survive(&config, &provenance, &existing, &mut incoming, "registration").is_empty());
+        assert_eq!(incoming.name.family, "Adeyemi-Cole");
+    }
+}
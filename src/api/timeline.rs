@@ -0,0 +1,299 @@
+//! Timeline of a patient's demographic changes
+//!
+//! Every create and update of a patient is audited with the record before
+//! and after. [`build`] walks those entries oldest first and describes each
+//! as events a UI can draw: created, name or address changed, identifier
+//! added or removed, merged, deleted. Each entry also carries the
+//! demographics as they stood after it.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::db::models::DbAuditLog;
+use crate::models::field_provenance::DEMOGRAPHIC_FIELDS;
+
+/// What happened to a patient
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineEventKind {
+    Created,
+    NameChanged,
+    AddressChanged,
+    TelecomChanged,
+    BirthDateChanged,
+    GenderChanged,
+    Deceased,
+    /// Any other demographic field changed; `field` names it
+    FieldChanged,
+    IdentifierAdded,
+    IdentifierRemoved,
+    /// The patient was merged into, or absorbed, the patient in `after`
+    Merged,
+    ConfidentialityChanged,
+    Deleted,
+    Purged,
+}
+
+/// One change within a timeline entry
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TimelineEvent {
+    pub kind: TimelineEventKind,
+
+    /// Short description for display, e.g. "Address changed"
+    pub label: String,
+
+    /// Patient field the event concerns, if one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+
+    /// Value before the change
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub before: Option<Value>,
+
+    /// Value after the change
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub after: Option<Value>,
+}
+
+/// Everything one audited change did to a patient
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TimelineEntry {
+    pub timestamp: DateTime<Utc>,
+
+    /// Audit entry the changes were read from
+    pub audit_id: Uuid,
+
+    /// User who made the change, if known
+    pub user_id: Option<String>,
+
+    pub events: Vec<TimelineEvent>,
+
+    /// Identifiers and demographic fields after the change, when recorded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub state: Option<Value>,
+}
+
+impl TimelineEntry {
+    /// Drop every value, keeping only what kind of change happened when
+    pub fn redact(&mut self) {
+        self.state = None;
+        for event in &mut self.events {
+            event.before = None;
+            event.after = None;
+        }
+    }
+}
+
+/// Timeline entries for a patient's audit entries, oldest first
+///
+/// Entries with no visible change, such as an update that rewrote the same
+/// values, are left out.
+pub fn build(logs: &[DbAuditLog]) -> Vec<TimelineEntry> {
+    let mut logs: Vec<&DbAuditLog> = logs.iter().collect();
+    logs.sort_by_key(|log| log.timestamp);
+    logs.into_iter()
+        .filter_map(|log| {
+            let events = events(log);
+            if events.is_empty() {
+                return None;
+            }
+            Some(TimelineEntry {
+                timestamp: log.timestamp,
+                audit_id: log.id,
+                user_id: log.user_id.clone(),
+                events,
+                state: match log.action.as_str() {
+                    "CREATE" | "UPDATE" => log.new_values.as_ref().map(demographics),
+                    _ => None,
+                },
+            })
+        })
+        .collect()
+}
+
+fn events(log: &DbAuditLog) -> Vec<TimelineEvent> {
+    let null = Value::Null;
+    let old = log.old_values.as_ref().unwrap_or(&null);
+    let new = log.new_values.as_ref().unwrap_or(&null);
+    match log.action.as_str() {
+        "CREATE" => vec![event(TimelineEventKind::Created, "Patient registered", None, None, None)],
+        "DELETE" => vec![event(TimelineEventKind::Deleted, "Patient deleted", None, None, None)],
+        "PURGE" => vec![event(TimelineEventKind::Purged, "Patient purged", None, None, None)],
+        "CONFIDENTIALITY" => vec![event(
+            TimelineEventKind::ConfidentialityChanged,
+            "Confidentiality changed",
+            Some("confidentiality"),
+            Some(old["confidentiality"].clone()),
+            Some(new["confidentiality"].clone()),
+        )],
+        "UPDATE" => update_events(old, new),
+        _ => Vec::new(),
+    }
+}
+
+fn update_events(old: &Value, new: &Value) -> Vec<TimelineEvent> {
+    let mut events = Vec::new();
+
+    for field in DEMOGRAPHIC_FIELDS {
+        let (before, after) = (&old[*field], &new[*field]);
+        if before == after {
+            continue;
+        }
+        let (kind, label) = match *field {
+            "name" => (TimelineEventKind::NameChanged, "Name changed".to_string()),
+            "addresses" => (TimelineEventKind::AddressChanged, "Address changed".to_string()),
+            "telecom" => (TimelineEventKind::TelecomChanged, "Contact details changed".to_string()),
+            "birth_date" => (TimelineEventKind::BirthDateChanged, "Birth date changed".to_string()),
+            "gender" => (TimelineEventKind::GenderChanged, "Gender changed".to_string()),
+            "deceased" if after == &Value::Bool(true) => (TimelineEventKind::Deceased, "Recorded as deceased".to_string()),
+            _ => (TimelineEventKind::FieldChanged, format!("{} changed", field.replace('_', " "))),
+        };
+        events.push(TimelineEvent {
+            kind,
+            label,
+            field: Some(field.to_string()),
+            before: Some(before.clone()),
+            after: Some(after.clone()),
+        });
+    }
+
+    let identifier_key = |identifier: &Value| {
+        (
+            identifier["identifier_type"].clone(),
+            identifier["system"].clone(),
+            identifier["value"].clone(),
+        )
+    };
+    let identifiers = |values: &Value| values["identifiers"].as_array().cloned().unwrap_or_default();
+    let (before, after) = (identifiers(old), identifiers(new));
+    for identifier in &after {
+        if !before.iter().any(|b| identifier_key(b) == identifier_key(identifier)) {
+            events.push(event(
+                TimelineEventKind::IdentifierAdded,
+                "Identifier added",
+                Some("identifiers"),
+                None,
+                Some(identifier.clone()),
+            ));
+        }
+    }
+    for identifier in &before {
+        if !after.iter().any(|a| identifier_key(a) == identifier_key(identifier)) {
+            events.push(event(
+                TimelineEventKind::IdentifierRemoved,
+                "Identifier removed",
+                Some("identifiers"),
+                Some(identifier.clone()),
+                None,
+            ));
+        }
+    }
+
+    let links = |values: &Value| values["links"].as_array().cloned().unwrap_or_default();
+    let old_links = links(old);
+    for link in links(new) {
+        if old_links.contains(&link) {
+            continue;
+        }
+        let label = match link["link_type"].as_str() {
+            Some("replacedby") => "Merged into another patient",
+            Some("replaces") => "Absorbed a merged patient",
+            _ => continue,
+        };
+        events.push(event(TimelineEventKind::Merged, label, Some("links"), None, Some(link["other_patient_id"].clone())));
+    }
+
+    events
+}
+
+fn event(
+    kind: TimelineEventKind,
+    label: &str,
+    field: Option<&str>,
+    before: Option<Value>,
+    after: Option<Value>,
+) -> TimelineEvent {
+    TimelineEvent {
+        kind,
+        label: label.to_string(),
+        field: field.map(str::to_string),
+        before,
+        after,
+    }
+}
+
+/// The identifiers and demographic fields of a recorded patient
+fn demographics(values: &Value) -> Value {
+    let mut state = Map::new();
+    for field in DEMOGRAPHIC_FIELDS.iter().chain(&["identifiers"]) {
+        if let Some(value) = values.get(*field) {
+            state.insert(field.to_string(), value.clone());
+        }
+    }
+    json!(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn log(action: &str, minutes: i64, old: Option<Value>, new: Option<Value>) -> DbAuditLog {
+        DbAuditLog {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now() + Duration::minutes(minutes),
+            user_id: Some("registrar".to_string()),
+            action: action.to_string(),
+            entity_type: "Patient".to_string(),
+            entity_id: Uuid::nil(),
+            old_values: old,
+            new_values: new,
+            ip_address: None,
+            user_agent: None,
+        }
+    }
+
+    #[test]
+    fn test_build_orders_and_describes_changes() {
+        let mrn = json!({ "identifier_type": "MRN", "system": "urn:oid:facility:GENERAL", "value": "G1" });
+        let created = json!({ "name": { "family": "Mensah" }, "addresses": [], "identifiers": [mrn], "links": [] });
+        let mut moved = created.clone();
+        moved["addresses"] = json!([{ "city": "Kumasi" }]);
+        moved["identifiers"] = json!([]);
+        let mut merged = moved.clone();
+        merged["links"] = json!([{ "other_patient_id": Uuid::nil(), "link_type": "replacedby" }]);
+
+        let logs = vec![
+            log("UPDATE", 2, Some(moved.clone()), Some(merged)),
+            log("CREATE", 0, None, Some(created.clone())),
+            log("UPDATE", 1, Some(created), Some(moved.clone())),
+            log("UPDATE", 3, Some(moved.clone()), Some(moved)),
+            log("DELETE", 4, None, None),
+        ];
+        let timeline = build(&logs);
+
+        let kinds: Vec<Vec<TimelineEventKind>> =
+            timeline.iter().map(|entry| entry.events.iter().map(|e| e.kind).collect()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                vec![TimelineEventKind::Created],
+                vec![TimelineEventKind::AddressChanged, TimelineEventKind::IdentifierRemoved],
+                vec![TimelineEventKind::Merged],
+                vec![TimelineEventKind::Deleted],
+            ]
+        );
+        assert_eq!(timeline[1].state.as_ref().unwrap()["addresses"][0]["city"], "Kumasi");
+        assert!(timeline[3].state.is_none());
+
+        let mut entry = timeline[1].clone();
+        entry.redact();
+        assert!(entry.state.is_none() && entry.events[0].after.is_none());
+    }
+}
@@ -0,0 +1,266 @@
+//! MPI command-line tools
+//!
+//! ```text
+//! mpi evaluate --pairs file.csv [--matcher probabilistic|deterministic]
+//!              [--thresholds 0.5,0.6,0.7] [--date-order dmy|mdy] [--json]
+//! mpi sandbox [--patients 1000] [--seed 42] [--port 8080]
+//! mpi openapi [--output openapi.json]
+//! mpi snapshot <name>
+//! mpi restore <name>
+//! ```
+
+use std::fs::File;
+use std::io::BufReader;
+use std::process::ExitCode;
+
+use master_patient_index::config::Config;
+use master_patient_index::matching::evaluation::{self, EvaluationReport};
+use master_patient_index::matching::{DeterministicMatcher, PatientMatcher, ProbabilisticMatcher};
+use master_patient_index::validation::DateOrder;
+
+const USAGE: &str = "\
+Usage: mpi evaluate --pairs <file.csv> [options]
+       mpi sandbox [--patients <n>] [--seed <n>] [--port <port>]
+       mpi openapi [--output <file>]
+       mpi snapshot <name>
+       mpi restore <name>
+
+Evaluate options:
+  --pairs <file>         Labeled pair CSV (see matching::evaluation::read_pairs_csv)
+  --matcher <name>       probabilistic (default) or deterministic
+  --thresholds <list>    Comma-separated thresholds (default 0.00 to 1.00 by 0.05)
+  --date-order <order>   Birth dates are dmy or mdy (default: locale config, else inferred)
+  --json                 Print the report as JSON
+
+Sandbox options (requires the `sandbox` feature):
+  --patients <n>         Synthetic patients to load (default 1000)
+  --seed <n>             Generator seed (default 42)
+  --port <port>          HTTP port (default: server config)
+
+OpenAPI options:
+  --output <file>        Write the document to a file (default: stdout)
+
+Snapshot and restore copy the Tantivy search index to or from <name> in
+search.backup_dir. Run them while the server is stopped: the server holds
+the index writer lock.
+";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        Some("evaluate") => run_evaluate(&args[1..]),
+        Some("sandbox") => run_sandbox(&args[1..]),
+        Some("openapi") => run_openapi(&args[1..]),
+        Some("snapshot") => run_snapshot(&args[1..], false),
+        Some("restore") => run_snapshot(&args[1..], true),
+        Some("-h" | "--help") => {
+            print!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        _ => Err(USAGE.to_string()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_evaluate(args: &[String]) -> Result<(), String> {
+    let mut pairs_path = None;
+    let mut matcher_name = "probabilistic".to_string();
+    let mut thresholds = evaluation::default_thresholds();
+    let mut date_order = None;
+    let mut json = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--pairs" => pairs_path = iter.next().cloned(),
+            "--matcher" => matcher_name = iter.next().cloned().ok_or(USAGE)?,
+            "--thresholds" => {
+                let list = iter.next().ok_or(USAGE)?;
+                thresholds = list
+                    .split(',')
+                    .map(|t| t.trim().parse::<f64>().map_err(|e| format!("Invalid threshold '{}': {}", t, e)))
+                    .collect::<Result<_, _>>()?;
+            }
+            "--date-order" => {
+                date_order = match iter.next().map(String::as_str) {
+                    Some("dmy") => Some(DateOrder::Dmy),
+                    Some("mdy") => Some(DateOrder::Mdy),
+                    Some("ymd") => Some(DateOrder::Ymd),
+                    other => return Err(format!("Invalid date order {:?}\n\n{}", other, USAGE)),
+                }
+            }
+            "--json" => json = true,
+            other => return Err(format!("Unknown option '{}'\n\n{}", other, USAGE)),
+        }
+    }
+
+    let pairs_path = pairs_path.ok_or(USAGE)?;
+    let config = Config::from_env().map_err(|e| e.to_string())?;
+    let file = File::open(&pairs_path).map_err(|e| format!("Failed to open {}: {}", pairs_path, e))?;
+    let pairs = evaluation::read_pairs_csv(BufReader::new(file), date_order.or(config.locale.date_order))
+        .map_err(|e| e.to_string())?;
+
+    let matcher: Box<dyn PatientMatcher> = match matcher_name.as_str() {
+        "probabilistic" => Box::new(ProbabilisticMatcher::new(config.matching)),
+        "deterministic" => Box::new(DeterministicMatcher::new(config.matching)),
+        other => return Err(format!("Unknown matcher '{}'", other)),
+    };
+
+    let report = evaluation::evaluate(matcher.as_ref(), &pairs, &thresholds).map_err(|e| e.to_string())?;
+
+    if json {
+        let output = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+        println!("{}", output);
+    } else {
+        print_report(&matcher_name, &report);
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "sandbox")]
+fn run_sandbox(args: &[String]) -> Result<(), String> {
+    use master_patient_index::api::rest::{serve, AppState};
+    use master_patient_index::observability::LogLevelHandle;
+
+    let mut patients = 1000;
+    let mut seed = 42;
+    let mut config = Config::from_env().map_err(|e| e.to_string())?;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .ok_or_else(|| format!("{} needs a value\n\n{}", name, USAGE))
+                .cloned()
+        };
+        match arg.as_str() {
+            "--patients" => {
+                patients = value("--patients")?
+                    .parse()
+                    .map_err(|e| format!("Invalid --patients: {}", e))?;
+            }
+            "--seed" => {
+                seed = value("--seed")?
+                    .parse()
+                    .map_err(|e| format!("Invalid --seed: {}", e))?;
+            }
+            "--port" => {
+                config.server.port = value("--port")?
+                    .parse()
+                    .map_err(|e| format!("Invalid --port: {}", e))?;
+            }
+            other => return Err(format!("Unknown option '{}'\n\n{}", other, USAGE)),
+        }
+    }
+    config.validate().map_err(|e| e.to_string())?;
+
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
+        )
+        .with_filter_reloading();
+    let log_level = LogLevelHandle::new(subscriber.reload_handle());
+    subscriber.init();
+
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+    runtime.block_on(async move {
+        let state = AppState::sandbox(config, patients, seed)
+            .map_err(|e| e.to_string())?
+            .with_log_level_handle(log_level);
+        serve(state).await.map_err(|e| e.to_string())
+    })
+}
+
+#[cfg(not(feature = "sandbox"))]
+fn run_sandbox(_args: &[String]) -> Result<(), String> {
+    Err("The sandbox requires the `sandbox` feature: cargo run --features sandbox --bin mpi -- sandbox".to_string())
+}
+
+/// Write the OpenAPI document the `client` feature generates from
+fn run_openapi(args: &[String]) -> Result<(), String> {
+    use master_patient_index::api::rest::ApiDoc;
+    use utoipa::OpenApi;
+
+    let output = match args {
+        [] => None,
+        [flag, path] if flag == "--output" => Some(path),
+        _ => return Err(USAGE.to_string()),
+    };
+
+    let json = ApiDoc::openapi().to_pretty_json().map_err(|e| e.to_string())?;
+    match output {
+        Some(path) => std::fs::write(path, json + "\n").map_err(|e| format!("Cannot write {}: {}", path, e)),
+        None => {
+            println!("{}", json);
+            Ok(())
+        }
+    }
+}
+
+/// Snapshot the search index into, or restore it from, `search.backup_dir`
+fn run_snapshot(args: &[String], restore: bool) -> Result<(), String> {
+    use master_patient_index::config::SearchBackendKind;
+    use master_patient_index::search::{snapshot_path, SearchEngine};
+
+    let [name] = args else {
+        return Err(USAGE.to_string());
+    };
+    let config = Config::from_env().map_err(|e| e.to_string())?;
+    if config.search.backend != SearchBackendKind::Tantivy {
+        return Err("Snapshots are only supported by the tantivy search backend".to_string());
+    }
+
+    let path = snapshot_path(&config.search.backup_dir, name).map_err(|e| e.to_string())?;
+    let engine = SearchEngine::new(&config.search.index_path).map_err(|e| e.to_string())?;
+    let info = if restore { engine.restore(&path) } else { engine.snapshot(&path) }.map_err(|e| e.to_string())?;
+
+    println!(
+        "{} {}: {} documents in {} segments, {} files",
+        if restore { "Restored from" } else { "Snapshot written to" },
+        info.path,
+        info.num_docs,
+        info.num_segments,
+        info.num_files
+    );
+    Ok(())
+}
+
+fn print_report(matcher_name: &str, report: &EvaluationReport) {
+    println!(
+        "Matcher: {}  Pairs: {} ({} match, {} non-match)  ROC AUC: {:.4}",
+        matcher_name, report.pairs, report.matches, report.non_matches, report.roc_auc
+    );
+    println!();
+    println!(
+        "{:>9} {:>6} {:>6} {:>6} {:>6} {:>9} {:>9} {:>9} {:>9}",
+        "threshold", "TP", "FP", "TN", "FN", "precision", "recall", "F1", "FPR"
+    );
+    for m in &report.thresholds {
+        println!(
+            "{:>9.2} {:>6} {:>6} {:>6} {:>6} {:>9.4} {:>9.4} {:>9.4} {:>9.4}",
+            m.threshold,
+            m.true_positives,
+            m.false_positives,
+            m.true_negatives,
+            m.false_negatives,
+            m.precision,
+            m.recall,
+            m.f1,
+            m.false_positive_rate
+        );
+    }
+
+    if let Some(best) = report.best_f1() {
+        println!();
+        println!("Best F1 {:.4} at threshold {:.2}", best.f1, best.threshold);
+    }
+}
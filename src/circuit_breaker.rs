@@ -0,0 +1,325 @@
+//! Circuit breakers around calls to external systems
+//!
+//! A breaker counts consecutive failures of the calls made through it. After
+//! `failure_threshold` failures it opens and fails every call at once, so a
+//! broker or endpoint that is down costs callers nothing instead of a timeout
+//! each. After `open_secs` it lets a single probe call through (half-open):
+//! success closes it again, failure re-opens it.
+//!
+//! Breakers are kept in a [`CircuitBreakers`] registry, which reports their
+//! state to the readiness check and to the `mpi_circuit_breaker_state` and
+//! `mpi_circuit_breaker_trips_total` metrics.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::config::CircuitBreakerConfig;
+use crate::observability::metrics::Metrics;
+use crate::{Error, Result};
+
+/// State of a breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Calls go through
+    Closed,
+    /// Calls fail at once
+    Open,
+    /// One probe call is let through
+    HalfOpen,
+}
+
+impl BreakerState {
+    /// Value of the `mpi_circuit_breaker_state` gauge
+    pub fn gauge_value(&self) -> i64 {
+        match self {
+            BreakerState::Closed => 0,
+            BreakerState::HalfOpen => 1,
+            BreakerState::Open => 2,
+        }
+    }
+}
+
+/// Point-in-time view of a breaker
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BreakerStatus {
+    pub name: String,
+    pub state: BreakerState,
+    /// Failures since the last success
+    pub consecutive_failures: u32,
+    /// Times the breaker has opened
+    pub trips: u64,
+    /// When the breaker last opened, while open or half-open
+    pub opened_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: BreakerState,
+    consecutive_failures: u32,
+    trips: u64,
+    opened: Option<(Instant, DateTime<Utc>)>,
+    probe_in_flight: bool,
+}
+
+/// Breaker guarding one external dependency
+pub struct CircuitBreaker {
+    name: String,
+    failure_threshold: u32,
+    open_duration: Duration,
+    inner: Mutex<Inner>,
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl CircuitBreaker {
+    /// Create a closed breaker
+    pub fn new(name: impl Into<String>, config: &CircuitBreakerConfig) -> Self {
+        Self {
+            name: name.into(),
+            failure_threshold: config.failure_threshold.max(1),
+            open_duration: Duration::from_secs(config.open_secs),
+            inner: Mutex::new(Inner {
+                state: BreakerState::Closed,
+                consecutive_failures: 0,
+                trips: 0,
+                opened: None,
+                probe_in_flight: false,
+            }),
+            metrics: None,
+        }
+    }
+
+    /// Report state changes to `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        metrics.observe_breaker_state(&self.name, BreakerState::Closed.gauge_value());
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Name used in errors, metrics and the readiness check
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Run `call` unless the breaker is open
+    pub fn call<T>(&self, call: impl FnOnce() -> Result<T>) -> Result<T> {
+        self.acquire()?;
+        let result = call();
+        self.record(result.is_ok());
+        result
+    }
+
+    fn acquire(&self) -> Result<()> {
+        let mut inner = self.lock();
+        match inner.state {
+            BreakerState::Closed => Ok(()),
+            BreakerState::Open => {
+                let waited = inner.opened.map(|(since, _)| since.elapsed()).unwrap_or_default();
+                if waited < self.open_duration {
+                    return Err(self.open_error());
+                }
+                inner.probe_in_flight = true;
+                self.transition(&mut inner, BreakerState::HalfOpen);
+                Ok(())
+            }
+            BreakerState::HalfOpen if inner.probe_in_flight => Err(self.open_error()),
+            BreakerState::HalfOpen => {
+                inner.probe_in_flight = true;
+                Ok(())
+            }
+        }
+    }
+
+    fn record(&self, success: bool) {
+        let mut inner = self.lock();
+        inner.probe_in_flight = false;
+        if success {
+            inner.consecutive_failures = 0;
+            inner.opened = None;
+            self.transition(&mut inner, BreakerState::Closed);
+            return;
+        }
+
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        let trip = inner.state == BreakerState::HalfOpen
+            || (inner.state == BreakerState::Closed && inner.consecutive_failures >= self.failure_threshold);
+        if trip {
+            inner.trips += 1;
+            inner.opened = Some((Instant::now(), Utc::now()));
+            self.transition(&mut inner, BreakerState::Open);
+            tracing::warn!(
+                "Circuit '{}' opened after {} consecutive failures",
+                self.name,
+                inner.consecutive_failures
+            );
+            if let Some(metrics) = &self.metrics {
+                metrics.observe_breaker_trip(&self.name);
+            }
+        }
+    }
+
+    fn transition(&self, inner: &mut Inner, state: BreakerState) {
+        if inner.state == state {
+            return;
+        }
+        if state == BreakerState::Closed {
+            tracing::info!("Circuit '{}' closed", self.name);
+        }
+        inner.state = state;
+        if let Some(metrics) = &self.metrics {
+            metrics.observe_breaker_state(&self.name, state.gauge_value());
+        }
+    }
+
+    /// Current state
+    pub fn status(&self) -> BreakerStatus {
+        let inner = self.lock();
+        BreakerStatus {
+            name: self.name.clone(),
+            state: inner.state,
+            consecutive_failures: inner.consecutive_failures,
+            trips: inner.trips,
+            opened_at: inner.opened.map(|(_, at)| at),
+        }
+    }
+
+    fn open_error(&self) -> Error {
+        Error::Streaming(format!("Circuit '{}' is open", self.name))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Registry of the application's breakers
+pub struct CircuitBreakers {
+    config: CircuitBreakerConfig,
+    metrics: Option<Arc<Metrics>>,
+    breakers: Mutex<BTreeMap<String, Arc<CircuitBreaker>>>,
+}
+
+impl CircuitBreakers {
+    /// Create an empty registry whose breakers use `config`
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            metrics: None,
+            breakers: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Report the breakers' state to `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// The breaker with the given name, created on first use
+    pub fn get(&self, name: &str) -> Arc<CircuitBreaker> {
+        let mut breakers = self.breakers.lock().unwrap_or_else(|e| e.into_inner());
+        breakers
+            .entry(name.to_string())
+            .or_insert_with(|| {
+                let breaker = CircuitBreaker::new(name, &self.config);
+                Arc::new(match &self.metrics {
+                    Some(metrics) => breaker.with_metrics(metrics.clone()),
+                    None => breaker,
+                })
+            })
+            .clone()
+    }
+
+    /// Status of every breaker, by name
+    pub fn statuses(&self) -> Vec<BreakerStatus> {
+        let breakers = self.breakers.lock().unwrap_or_else(|e| e.into_inner());
+        breakers.values().map(|breaker| breaker.status()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(open_secs: u64) -> CircuitBreaker {
+        CircuitBreaker::new(
+            "broker",
+            &CircuitBreakerConfig {
+                failure_threshold: 3,
+                open_secs,
+            },
+        )
+    }
+
+    fn fail() -> Result<()> {
+        Err(Error::Streaming("down".to_string()))
+    }
+
+    #[test]
+    fn test_opens_after_consecutive_failures() {
+        let breaker = breaker(60);
+
+        assert!(breaker.call(fail).is_err());
+        assert!(breaker.call(fail).is_err());
+        assert!(breaker.call(|| Ok(())).is_ok());
+        assert_eq!(breaker.status().consecutive_failures, 0);
+
+        for _ in 0..3 {
+            assert!(breaker.call(fail).is_err());
+        }
+        let status = breaker.status();
+        assert_eq!(status.state, BreakerState::Open);
+        assert_eq!(status.trips, 1);
+        assert!(status.opened_at.is_some());
+
+        // Open: the call is not made at all
+        let mut called = false;
+        assert!(breaker.call(|| { called = true; Ok(()) }).is_err());
+        assert!(!called);
+    }
+
+    #[test]
+    fn test_half_open_probe() {
+        let breaker = breaker(0);
+        for _ in 0..3 {
+            let _ = breaker.call(fail);
+        }
+        assert_eq!(breaker.status().state, BreakerState::Open);
+
+        // A failed probe re-opens the breaker
+        assert!(breaker.call(fail).is_err());
+        assert_eq!(breaker.status().state, BreakerState::Open);
+        assert_eq!(breaker.status().trips, 2);
+
+        // A successful probe closes it
+        assert!(breaker.call(|| Ok(())).is_ok());
+        assert_eq!(breaker.status().state, BreakerState::Closed);
+    }
+
+    #[test]
+    fn test_registry_and_metrics() {
+        let metrics = Arc::new(Metrics::new());
+        let breakers = CircuitBreakers::new(CircuitBreakerConfig {
+            failure_threshold: 1,
+            open_secs: 60,
+        })
+        .with_metrics(metrics.clone());
+
+        let broker = breakers.get("event_broker");
+        assert!(Arc::ptr_eq(&broker, &breakers.get("event_broker")));
+        let _ = broker.call(fail);
+
+        let statuses = breakers.statuses();
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].state, BreakerState::Open);
+
+        let text = metrics.render().unwrap();
+        assert!(text.contains("mpi_circuit_breaker_state{breaker=\"event_broker\"} 2"));
+        assert!(text.contains("mpi_circuit_breaker_trips_total{breaker=\"event_broker\"} 1"));
+    }
+}
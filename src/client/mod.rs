@@ -0,0 +1,121 @@
+//! Typed HTTP client for the REST and FHIR APIs
+//!
+//! Operations and their request and response types are generated at build
+//! time from the OpenAPI document (see `mpi openapi`), one method per
+//! operation named after its handler:
+//!
+//! ```ignore
+//! let client = Client::new("http://mpi.internal:8080");
+//! let results = client.search_patients(&types::SearchPatientsQuery {
+//!     q: Some("Osei".to_string()),
+//!     ..Default::default()
+//! })?;
+//! ```
+
+use serde::{de::DeserializeOwned, Serialize};
+
+include!(concat!(env!("OUT_DIR"), "/client.rs"));
+
+/// Client errors
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    /// The server answered with an error status; `body` is usually an
+    /// `ApiErrorResponse` or a FHIR OperationOutcome
+    #[error("HTTP {status}: {body}")]
+    Status { status: u16, body: serde_json::Value },
+
+    #[error("Transport error: {0}")]
+    Transport(String),
+
+    #[error("Invalid response: {0}")]
+    Decode(String),
+}
+
+/// Blocking client for one MPI server
+#[derive(Clone)]
+pub struct Client {
+    agent: ureq::Agent,
+    base_url: String,
+}
+
+impl Client {
+    /// Client for the server at `base_url`, e.g. `http://localhost:8080`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_agent(base_url, ureq::Agent::new())
+    }
+
+    /// Client using a preconfigured agent, for timeouts, proxies or TLS
+    pub fn with_agent(base_url: impl Into<String>, agent: ureq::Agent) -> Self {
+        Self {
+            agent,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Send a request and decode the JSON body; an empty body decodes as `null`
+    fn send<B: Serialize, T: DeserializeOwned>(
+        &self,
+        method: &str,
+        path: &str,
+        query: Vec<(String, String)>,
+        headers: &[(&str, Option<&str>)],
+        body: Option<&B>,
+    ) -> Result<T, ClientError> {
+        let mut request = self
+            .agent
+            .request(method, &format!("{}{}", self.base_url, path))
+            .set("Accept", "application/json");
+        for (name, value) in &query {
+            request = request.query(name, value);
+        }
+        for (name, value) in headers {
+            if let Some(value) = value {
+                request = request.set(name, value);
+            }
+        }
+
+        let response = match body {
+            Some(body) => request.send_json(body),
+            None => request.call(),
+        };
+        let response = match response {
+            Ok(response) => response,
+            Err(ureq::Error::Status(status, response)) => {
+                let text = response.into_string().unwrap_or_default();
+                let body = serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text));
+                return Err(ClientError::Status { status, body });
+            }
+            Err(e) => return Err(ClientError::Transport(e.to_string())),
+        };
+
+        let text = response.into_string().map_err(|e| ClientError::Transport(e.to_string()))?;
+        if text.trim().is_empty() {
+            serde_json::from_value(serde_json::Value::Null)
+        } else {
+            serde_json::from_str(&text)
+        }
+        .map_err(|e| ClientError::Decode(e.to_string()))
+    }
+}
+
+/// Flatten a query parameter struct into pairs; arrays repeat the parameter
+fn query_pairs<Q: Serialize>(query: &Q) -> Vec<(String, String)> {
+    let Ok(serde_json::Value::Object(fields)) = serde_json::to_value(query) else {
+        return Vec::new();
+    };
+    let mut pairs = Vec::new();
+    for (name, value) in fields {
+        let values = match value {
+            serde_json::Value::Array(values) => values,
+            value => vec![value],
+        };
+        for value in values {
+            match value {
+                serde_json::Value::Null => {}
+                serde_json::Value::String(s) => pairs.push((name.clone(), s)),
+                other => pairs.push((name.clone(), other.to_string())),
+            }
+        }
+    }
+    pairs
+}
@@ -1,6 +1,17 @@
 //! Configuration management for the MPI system
 
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::matching::transliteration::Script;
+use crate::models::VerificationStatus;
+use crate::validation::DateOrder;
+
+mod validation;
+
+pub use validation::ConfigProblem;
 
 /// Main configuration structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,11 +28,123 @@ pub struct Config {
     /// Matching configuration
     pub matching: MatchingConfig,
 
+    /// Named matching profiles, selected with `profile` on the match APIs
+    #[serde(default)]
+    pub matching_profiles: BTreeMap<String, MatchingProfile>,
+
+    /// Matching profiles stored through the admin API
+    #[serde(default)]
+    pub matching_settings: MatchingSettingsConfig,
+
     /// Observability configuration
     pub observability: ObservabilityConfig,
 
     /// Streaming configuration
     pub streaming: StreamingConfig,
+
+    /// Quality reporting configuration
+    #[serde(default)]
+    pub reporting: ReportingConfig,
+
+    /// Locale of imported data
+    #[serde(default)]
+    pub locale: LocaleConfig,
+
+    /// Identifier format checks
+    #[serde(default)]
+    pub identifiers: IdentifierConfig,
+
+    /// FHIR API configuration
+    #[serde(default)]
+    pub fhir: FhirConfig,
+
+    /// HL7 v2 MLLP listener configuration
+    #[serde(default)]
+    pub hl7: Hl7Config,
+
+    /// Outbound HL7 ADT feed of identity changes to downstream systems
+    #[serde(default)]
+    pub identity_feed: IdentityFeedConfig,
+
+    /// DICOM C-FIND adapter configuration, used with the `dicom` feature
+    #[serde(default)]
+    pub dicom: DicomConfig,
+
+    /// Steward record locking configuration
+    #[serde(default)]
+    pub locking: LockingConfig,
+
+    /// Retention rules for inactive and deceased patients
+    #[serde(default)]
+    pub retention: RetentionConfig,
+
+    /// Bulk export and de-identification settings
+    #[serde(default)]
+    pub export: ExportConfig,
+
+    /// Bulk import settings
+    #[serde(default)]
+    pub import: ImportConfig,
+
+    /// Structured logging of individual match decisions
+    #[serde(default)]
+    pub decision_log: DecisionLogConfig,
+
+    /// Rejection of requests while the server is overloaded
+    #[serde(default)]
+    pub load_shedding: LoadSheddingConfig,
+
+    /// Time budgets per endpoint class
+    #[serde(default)]
+    pub timeouts: TimeoutConfig,
+
+    /// Circuit breakers around the event broker and webhooks
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+
+    /// Duplicate detection as records change
+    #[serde(default)]
+    pub dedup: DedupConfig,
+
+    /// Validation of clusters of linked patients
+    #[serde(default)]
+    pub clustering: ClusteringConfig,
+
+    /// Bulk matching of record batches
+    #[serde(default)]
+    pub batch_match: BatchMatchConfig,
+
+    /// Caching of rarely changing reference data
+    #[serde(default)]
+    pub lookup_cache: LookupCacheConfig,
+
+    /// Access to restricted and VIP patients
+    #[serde(default)]
+    pub privacy: PrivacyConfig,
+
+    /// Which sources' values survive on the master record
+    #[serde(default)]
+    pub survivorship: SurvivorshipConfig,
+
+    /// Practitioner deduplication
+    #[serde(default)]
+    pub practitioners: PractitionerConfig,
+
+    /// Weighting of name agreement by how common the name is
+    #[serde(default)]
+    pub name_frequency: NameFrequencyConfig,
+
+    /// Data integrity check
+    #[serde(default)]
+    pub integrity: IntegrityConfig,
+
+    /// Decay of source record link confidence as demographics drift
+    #[serde(default)]
+    pub link_confidence: LinkConfidenceConfig,
+
+    /// Email and webhook alerts to stewards
+    #[serde(default)]
+    pub notifications: NotificationConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,6 +152,16 @@ pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub grpc_port: u16,
+    /// Compress REST and FHIR responses with gzip or Brotli when the client accepts it
+    #[serde(default = "default_true")]
+    pub compression: bool,
+    /// Roles, passed in `X-User-Roles`, that may snapshot and restore the search index
+    #[serde(default = "default_admin_roles")]
+    pub admin_roles: Vec<String>,
+}
+
+fn default_admin_roles() -> Vec<String> {
+    vec!["mpi-admin".to_string()]
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,12 +169,213 @@ pub struct DatabaseConfig {
     pub url: String,
     pub max_connections: u32,
     pub min_connections: u32,
+    /// Streaming replica serving patient reads, searches and audit queries
+    #[serde(default)]
+    pub read_replica_url: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchConfig {
+    /// Which search backend to use
+    #[serde(default)]
+    pub backend: SearchBackendKind,
     pub index_path: String,
     pub cache_size_mb: usize,
+    #[serde(default)]
+    pub field_boosts: FieldBoosts,
+    /// Connection settings for the OpenSearch backend
+    #[serde(default)]
+    pub opensearch: Option<OpenSearchConfig>,
+    /// How the local index is shared between API replicas
+    #[serde(default)]
+    pub replication: IndexReplicationConfig,
+    /// Separate indexes for active and for inactive or deceased patients
+    #[serde(default)]
+    pub partitioning: PartitioningConfig,
+    /// Batching of bulk indexing, such as a full reindex
+    #[serde(default)]
+    pub bulk_index: BulkIndexConfig,
+    /// Directory backup snapshots are written to and restored from; paths
+    /// given to the snapshot and restore endpoints must resolve inside it
+    #[serde(default = "default_backup_dir")]
+    pub backup_dir: String,
+}
+
+fn default_backup_dir() -> String {
+    "./data/search_backups".to_string()
+}
+
+/// Search backend selection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchBackendKind {
+    /// Embedded Tantivy index on local disk
+    #[default]
+    Tantivy,
+    /// External OpenSearch or Elasticsearch cluster
+    OpenSearch,
+    /// Trigram queries against the patient tables in Postgres
+    Postgres,
+}
+
+/// OpenSearch/Elasticsearch connection settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenSearchConfig {
+    /// Cluster base URL, e.g. `https://search.example.org:9200`
+    pub url: String,
+    /// Index holding patient documents
+    pub index: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+/// Role of this instance in index replication
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexRole {
+    /// Single instance that indexes its own writes; no replication
+    #[default]
+    Standalone,
+    /// Owns the index: applies the event stream and publishes snapshots
+    Writer,
+    /// Serves searches from snapshots published by the writer
+    Reader,
+    /// Writer while holding the Postgres advisory lock, reader otherwise
+    Elected,
+}
+
+/// Shared index settings for running several API replicas
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexReplicationConfig {
+    #[serde(default)]
+    pub role: IndexRole,
+    /// Directory shared by all replicas (e.g. a network volume) holding published snapshots
+    #[serde(default = "default_snapshot_dir")]
+    pub snapshot_dir: String,
+    /// Seconds between snapshots published by the writer
+    #[serde(default = "default_publish_interval_secs")]
+    pub publish_interval_secs: u64,
+    /// Seconds between replication ticks (event catch-up, refresh, election)
+    #[serde(default = "default_refresh_interval_secs")]
+    pub refresh_interval_secs: u64,
+    /// Advisory lock key contended for when the role is `elected`
+    #[serde(default = "default_election_lock_id")]
+    pub election_lock_id: i64,
+}
+
+fn default_snapshot_dir() -> String {
+    "./data/search_snapshots".to_string()
+}
+
+fn default_publish_interval_secs() -> u64 {
+    60
+}
+
+fn default_refresh_interval_secs() -> u64 {
+    10
+}
+
+fn default_election_lock_id() -> i64 {
+    // "MPI_IDX" as ASCII bytes
+    0x004d_5049_5f49_4458
+}
+
+impl Default for IndexReplicationConfig {
+    fn default() -> Self {
+        Self {
+            role: IndexRole::Standalone,
+            snapshot_dir: default_snapshot_dir(),
+            publish_interval_secs: default_publish_interval_secs(),
+            refresh_interval_secs: default_refresh_interval_secs(),
+            election_lock_id: default_election_lock_id(),
+        }
+    }
+}
+
+/// Bulk indexing settings
+///
+/// At most `batch_size * queue_batches` patients are held in memory at once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkIndexConfig {
+    /// Patients written per index commit
+    #[serde(default = "default_bulk_batch_size")]
+    pub batch_size: usize,
+    /// Batches a producer may queue ahead of the indexer before it waits
+    #[serde(default = "default_bulk_queue_batches")]
+    pub queue_batches: usize,
+}
+
+fn default_bulk_batch_size() -> usize {
+    1000
+}
+
+fn default_bulk_queue_batches() -> usize {
+    2
+}
+
+impl Default for BulkIndexConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: default_bulk_batch_size(),
+            queue_batches: default_bulk_queue_batches(),
+        }
+    }
+}
+
+/// Hot/cold partitioning of the search index
+///
+/// Active, living patients are indexed in the hot partition and inactive or
+/// deceased ones in the cold partition. Searches query the hot partition
+/// first and fall back to the cold one only for the results still missing,
+/// so interactive lookups stay fast on installations with many historical
+/// records.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartitioningConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Directory of the cold Tantivy index
+    #[serde(default = "default_cold_index_path")]
+    pub cold_index_path: String,
+    /// OpenSearch index of the cold partition; `search.opensearch.index`
+    /// with `-cold` appended when unset
+    #[serde(default)]
+    pub cold_index: Option<String>,
+}
+
+fn default_cold_index_path() -> String {
+    "./data/search_index_cold".to_string()
+}
+
+impl Default for PartitioningConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cold_index_path: default_cold_index_path(),
+            cold_index: None,
+        }
+    }
+}
+
+/// Query-time boosts applied to each searchable field
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldBoosts {
+    pub identifiers: f32,
+    pub family_name: f32,
+    pub given_names: f32,
+    pub full_name: f32,
+}
+
+impl Default for FieldBoosts {
+    fn default() -> Self {
+        Self {
+            identifiers: 3.0,
+            family_name: 2.0,
+            given_names: 1.5,
+            full_name: 1.0,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,6 +383,341 @@ pub struct MatchingConfig {
     pub threshold_score: f64,
     pub exact_match_score: f64,
     pub fuzzy_match_score: f64,
+    #[serde(default)]
+    pub weights: MatchWeights,
+    #[serde(default)]
+    pub verification: VerificationWeights,
+    #[serde(default)]
+    pub transliteration: TransliterationConfig,
+    #[serde(default)]
+    pub similarity: SimilarityConfig,
+    #[serde(default)]
+    pub blocking: BlockingConfig,
+    #[serde(default)]
+    pub photo: PhotoMatchingConfig,
+}
+
+impl MatchingConfig {
+    /// Check that the threshold is between 0.0 and 1.0 and weights are non-negative
+    pub fn validate(&self) -> crate::Result<()> {
+        let weights = &self.weights;
+        let weights_valid = [weights.name, weights.birth_date, weights.gender, weights.address, weights.identifier]
+            .iter()
+            .chain([&self.verification.patient_confirmed, &self.verification.document_verified])
+            .all(|w| w.is_finite() && *w >= 0.0);
+        if !(0.0..=1.0).contains(&self.threshold_score) || !weights_valid {
+            return Err(crate::Error::Validation(
+                "Threshold must be between 0.0 and 1.0 and weights must be non-negative".to_string(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.similarity.transposed_name_score) {
+            return Err(crate::Error::Validation(
+                "Transposed name score must be between 0.0 and 1.0".to_string(),
+            ));
+        }
+        if !self.photo.weight.is_finite()
+            || self.photo.weight < 0.0
+            || !(0.0..=1.0).contains(&self.photo.min_similarity)
+        {
+            return Err(crate::Error::Validation(
+                "Photo weight must be non-negative and photo minimum similarity between 0.0 and 1.0".to_string(),
+            ));
+        }
+        for name in [&self.similarity.family_name, &self.similarity.given_name] {
+            if crate::matching::similarity::lookup(name).is_none() {
+                return Err(crate::Error::Validation(format!(
+                    "Similarity algorithm '{}' is not registered; registered are {}",
+                    name,
+                    crate::matching::similarity::registered().join(", ")
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Name under which the settings in `matching` are stored as a profile
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Matching settings for one kind of caller, such as registration lookup,
+/// billing reconciliation or research linkage, or for one tenant
+///
+/// The threshold and weights replace those in `matching` when set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct MatchingProfile {
+    /// Score at or above which a pair is a match; also the default minimum
+    /// score of returned matches
+    #[serde(default)]
+    pub threshold_score: Option<f64>,
+    #[serde(default)]
+    pub weights: Option<MatchWeights>,
+    #[serde(default)]
+    pub blocking: BlockingStrategy,
+    /// Most candidates taken from the search index
+    #[serde(default = "default_blocking_candidates")]
+    pub max_candidates: usize,
+}
+
+/// Matching profiles stored through the admin API
+///
+/// A stored profile replaces the configured one of the same name; the one
+/// named `default` replaces the threshold and weights in `matching`, and the
+/// blocking of requests that name no profile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchingSettingsConfig {
+    /// Seconds between checks for profiles another instance stored; 0 to
+    /// pick them up only on reload
+    #[serde(default = "default_matching_settings_refresh_secs")]
+    pub refresh_secs: u64,
+}
+
+fn default_matching_settings_refresh_secs() -> u64 {
+    30
+}
+
+impl Default for MatchingSettingsConfig {
+    fn default() -> Self {
+        Self {
+            refresh_secs: default_matching_settings_refresh_secs(),
+        }
+    }
+}
+
+/// How candidates are found once the exact MRN and SSN lookup has not settled a match
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockingStrategy {
+    /// Same family name and birth year
+    #[default]
+    NameAndBirthYear,
+    /// Same family name, any birth year
+    Name,
+    /// A Soundex family name and birth year, first initial and birth date, or
+    /// postal code and family name prefix key in common, looked up as exact
+    /// terms in the index (Tantivy and OpenSearch backends)
+    BlockingKeys,
+    /// The passes in `matching.blocking.passes`, in order, until
+    /// `max_candidates` are found
+    MultiPass,
+    /// No name-based blocking; only identifier holders are candidates
+    IdentifierOnly,
+}
+
+/// Candidate searches of the `multi_pass` blocking strategy, run in order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockingConfig {
+    #[serde(default = "default_blocking_passes")]
+    pub passes: Vec<BlockingPass>,
+}
+
+fn default_blocking_passes() -> Vec<BlockingPass> {
+    vec![
+        BlockingPass::NameAndBirthYear,
+        BlockingPass::SoundexYear,
+        BlockingPass::InitialBirthDate,
+        BlockingPass::Zip3FamilyPrefix,
+    ]
+}
+
+impl Default for BlockingConfig {
+    fn default() -> Self {
+        Self {
+            passes: default_blocking_passes(),
+        }
+    }
+}
+
+/// One candidate search of multi-pass blocking
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockingPass {
+    /// Fuzzy family name with the same birth year ranked first
+    NameAndBirthYear,
+    /// Fuzzy family name, any birth year
+    Name,
+    /// Soundex of the family name and birth year index key
+    SoundexYear,
+    /// First initial and birth date index key
+    InitialBirthDate,
+    /// Postal code and family name prefix index key
+    Zip3FamilyPrefix,
+}
+
+impl BlockingPass {
+    /// Configured name, also the `pass` metric label
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BlockingPass::NameAndBirthYear => "name_and_birth_year",
+            BlockingPass::Name => "name",
+            BlockingPass::SoundexYear => "soundex_year",
+            BlockingPass::InitialBirthDate => "initial_birth_date",
+            BlockingPass::Zip3FamilyPrefix => "zip3_family_prefix",
+        }
+    }
+}
+
+fn default_blocking_candidates() -> usize {
+    100
+}
+
+impl Default for MatchingProfile {
+    fn default() -> Self {
+        Self {
+            threshold_score: None,
+            weights: None,
+            blocking: BlockingStrategy::default(),
+            max_candidates: default_blocking_candidates(),
+        }
+    }
+}
+
+impl MatchingProfile {
+    /// `matching` with this profile's threshold and weights
+    pub fn apply(&self, matching: &MatchingConfig) -> MatchingConfig {
+        let mut config = matching.clone();
+        if let Some(threshold) = self.threshold_score {
+            config.threshold_score = threshold;
+        }
+        if let Some(weights) = &self.weights {
+            config.weights = weights.clone();
+        }
+        config
+    }
+}
+
+/// Romanization of names written in non-Latin scripts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransliterationConfig {
+    /// Compare and index names across scripts
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Entries added to or replacing the built-in table for a script, e.g.
+    /// `han = { "王" = "wang" }`
+    #[serde(default)]
+    pub tables: BTreeMap<Script, BTreeMap<String, String>>,
+}
+
+impl Default for TransliterationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            tables: BTreeMap::new(),
+        }
+    }
+}
+
+/// String comparators for name components, by the name they are registered
+/// under in [`crate::matching::similarity`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimilarityConfig {
+    #[serde(default = "default_similarity_algorithm")]
+    pub family_name: String,
+    #[serde(default = "default_similarity_algorithm")]
+    pub given_name: String,
+    /// Share of the name score kept by names that only agree with given and
+    /// family names swapped, as "SMITH JOHN" against "JOHN SMITH"
+    #[serde(default = "default_transposed_name_score")]
+    pub transposed_name_score: f64,
+}
+
+fn default_transposed_name_score() -> f64 {
+    crate::matching::similarity::DEFAULT_TRANSPOSED_SCORE
+}
+
+fn default_similarity_algorithm() -> String {
+    crate::matching::similarity::DEFAULT_ALGORITHM.to_string()
+}
+
+impl Default for SimilarityConfig {
+    fn default() -> Self {
+        Self {
+            family_name: default_similarity_algorithm(),
+            given_name: default_similarity_algorithm(),
+            transposed_name_score: default_transposed_name_score(),
+        }
+    }
+}
+
+/// Photo hash similarity as a weak matching signal
+///
+/// Two photos of one person taken years apart can differ more than photos of
+/// two siblings, so similar photos only add to a probabilistic score and
+/// dissimilar ones never take from it. Off while `weight` is 0.0.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PhotoMatchingConfig {
+    /// Weight of the photo similarity added to the score, which stays capped at 1.0
+    #[serde(default)]
+    pub weight: f64,
+    /// Similarity below which photos add nothing; 0.875 is at most 8 of the
+    /// 64 hash bits differing
+    #[serde(default = "default_photo_min_similarity")]
+    pub min_similarity: f64,
+}
+
+fn default_photo_min_similarity() -> f64 {
+    0.875
+}
+
+impl Default for PhotoMatchingConfig {
+    fn default() -> Self {
+        Self {
+            weight: 0.0,
+            min_similarity: default_photo_min_similarity(),
+        }
+    }
+}
+
+/// Relative weight of each component in the probabilistic match score
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct MatchWeights {
+    pub name: f64,
+    pub birth_date: f64,
+    pub gender: f64,
+    pub address: f64,
+    pub identifier: f64,
+}
+
+impl Default for MatchWeights {
+    fn default() -> Self {
+        Self {
+            name: 0.35,
+            birth_date: 0.30,
+            gender: 0.10,
+            address: 0.15,
+            identifier: 0.10,
+        }
+    }
+}
+
+/// Multipliers on the identifier and address weights for verified values
+///
+/// The status used is the better one of the two values that matched, so a
+/// document-verified SSN on file counts for more than one typed in at the
+/// front desk. The total score is capped at 1.0.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerificationWeights {
+    pub patient_confirmed: f64,
+    pub document_verified: f64,
+}
+
+impl VerificationWeights {
+    /// Multiplier for a value with `status`
+    pub fn multiplier(&self, status: VerificationStatus) -> f64 {
+        match status {
+            VerificationStatus::Unverified => 1.0,
+            VerificationStatus::PatientConfirmed => self.patient_confirmed,
+            VerificationStatus::DocumentVerified => self.document_verified,
+        }
+    }
+}
+
+impl Default for VerificationWeights {
+    fn default() -> Self {
+        Self {
+            patient_confirmed: 1.2,
+            document_verified: 1.5,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,12 +725,1133 @@ pub struct ObservabilityConfig {
     pub service_name: String,
     pub otlp_endpoint: String,
     pub log_level: String,
+    /// Collectors audit entries are forwarded to as they are written, in
+    /// addition to the `audit_log` table
+    #[serde(default)]
+    pub audit_sinks: Vec<AuditSinkConfig>,
+}
+
+/// A security information and event management (SIEM) collector for audit entries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum AuditSinkConfig {
+    /// RFC 5424 syslog messages carrying ArcSight CEF
+    Syslog {
+        /// `host:port` of the collector
+        address: String,
+        #[serde(default)]
+        transport: SyslogTransport,
+    },
+    /// One JSON entry POSTed per request
+    Http {
+        url: String,
+        /// `Authorization` header value, e.g. `Splunk <token>`
+        #[serde(default)]
+        authorization: Option<String>,
+    },
+}
+
+/// How syslog messages reach the collector
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyslogTransport {
+    #[default]
+    Udp,
+    /// Octet-counted framing (RFC 6587)
+    Tcp,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StreamingConfig {
     pub broker_url: String,
     pub topic: String,
+    /// Wire format for published events
+    #[serde(default)]
+    pub format: EventFormat,
+    /// Tenant stamped on published event envelopes
+    #[serde(default)]
+    pub tenant: Option<String>,
+    /// Identifier of this MPI instance, stamped as the event producer
+    #[serde(default = "default_instance_id")]
+    pub instance_id: String,
+    /// Remote MPI topics whose changes are applied locally
+    #[serde(default)]
+    pub inbound: Vec<InboundTopicConfig>,
+}
+
+fn default_instance_id() -> String {
+    "master-patient-index".to_string()
+}
+
+/// A remote patient-event topic to apply locally
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InboundTopicConfig {
+    pub topic: String,
+    #[serde(default = "default_true")]
+    pub apply_creates: bool,
+    #[serde(default = "default_true")]
+    pub apply_updates: bool,
+    #[serde(default = "default_true")]
+    pub apply_merges: bool,
+    #[serde(default)]
+    pub apply_deletes: bool,
+    #[serde(default = "default_true")]
+    pub apply_links: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Matching KPI job settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportingConfig {
+    /// Completed days recomputed on each run, so late merges are counted
+    #[serde(default = "default_lookback_days")]
+    pub lookback_days: u32,
+    /// Actor recorded on links made by the matcher rather than a reviewer
+    #[serde(default = "default_automatic_linker")]
+    pub automatic_linker: String,
+}
+
+fn default_lookback_days() -> u32 {
+    7
+}
+
+fn default_automatic_linker() -> String {
+    "system".to_string()
+}
+
+impl Default for ReportingConfig {
+    fn default() -> Self {
+        Self {
+            lookback_days: default_lookback_days(),
+            automatic_linker: default_automatic_linker(),
+        }
+    }
+}
+
+/// How imported demographics are written
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LocaleConfig {
+    /// Day/month order of year-last birth dates; inferred per file when unset
+    #[serde(default)]
+    pub date_order: Option<DateOrder>,
+}
+
+/// Identifier format checks beyond the built-in NPI and SSN rules
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IdentifierConfig {
+    /// Accepted MRN formats per assigning authority, keyed by identifier
+    /// system or assigner, e.g. `GENERAL = ["A999999", "99-99999"]`. `9` is
+    /// a digit, `A` a letter, `X` either; other characters stand for
+    /// themselves.
+    #[serde(default)]
+    pub mrn_patterns: BTreeMap<String, Vec<String>>,
+
+    /// Refuse identifiers whose system is not a registered assigning
+    /// authority. Identifiers from an inactive authority are always refused.
+    #[serde(default)]
+    pub require_registered_authority: bool,
+
+    /// MRN sequences this MPI issues numbers from, keyed by identifier system
+    #[serde(default)]
+    pub mrn_sequences: BTreeMap<String, MrnSequenceConfig>,
+
+    /// System whose sequence assigns an MRN to patients created without
+    /// one; unset leaves such patients without an MRN
+    #[serde(default)]
+    pub assign_mrn: Option<String>,
+
+    /// UUIDv5 namespaces per assigning authority, keyed by identifier system
+    /// or assigner. A patient created without an ID that holds an
+    /// identifier from one of these authorities is given the ID derived
+    /// from the namespace, the authority and the identifier value, so a
+    /// replayed source record maps to the same patient.
+    #[serde(default)]
+    pub id_namespaces: BTreeMap<String, Uuid>,
+}
+
+/// One assigning authority's MRN sequence
+///
+/// An MRN is the prefix, then the sequence value zero-padded to `digits`,
+/// then the check digit if any.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MrnSequenceConfig {
+    #[serde(default)]
+    pub prefix: String,
+    /// Digits of the sequence value, excluding the check digit
+    #[serde(default = "default_mrn_digits")]
+    pub digits: u32,
+    /// First value issued
+    #[serde(default = "default_mrn_start")]
+    pub start: i64,
+    #[serde(default)]
+    pub check_digit: MrnCheckDigit,
+}
+
+fn default_mrn_digits() -> u32 {
+    7
+}
+
+fn default_mrn_start() -> i64 {
+    1
+}
+
+impl Default for MrnSequenceConfig {
+    fn default() -> Self {
+        Self {
+            prefix: String::new(),
+            digits: default_mrn_digits(),
+            start: default_mrn_start(),
+            check_digit: MrnCheckDigit::default(),
+        }
+    }
+}
+
+/// Check digit scheme of issued MRNs, named as in HL7 table 0061
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MrnCheckDigit {
+    /// Mod 10 (Luhn)
+    #[default]
+    M10,
+    /// Mod 11 with weights 2 to 7; values whose check would be 10 are skipped
+    M11,
+    /// No check digit
+    None,
+}
+
+/// FHIR API settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FhirConfig {
+    /// Handling of resources with elements or codes the MPI cannot store,
+    /// unless a request overrides it with `Prefer: handling=...`
+    #[serde(default)]
+    pub handling: FhirHandling,
+}
+
+/// How FHIR ingestion treats data it cannot map
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FhirHandling {
+    /// Store what can be mapped and report the rest as warnings
+    #[default]
+    Lenient,
+    /// Reject the resource with a 422 OperationOutcome
+    Strict,
+}
+
+/// Event serialization format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventFormat {
+    #[default]
+    Json,
+    Protobuf,
+}
+
+/// HL7 v2 ADT listener settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hl7Config {
+    /// Whether to accept MLLP connections
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_hl7_host")]
+    pub host: String,
+    #[serde(default = "default_hl7_port")]
+    pub port: u16,
+    /// Sending application (MSH-3) of acknowledgments
+    #[serde(default = "default_hl7_identity")]
+    pub application: String,
+    /// Sending facility (MSH-4) of acknowledgments
+    #[serde(default = "default_hl7_identity")]
+    pub facility: String,
+}
+
+fn default_hl7_host() -> String {
+    "0.0.0.0".to_string()
+}
+
+fn default_hl7_port() -> u16 {
+    2575
+}
+
+fn default_hl7_identity() -> String {
+    "MPI".to_string()
+}
+
+impl Default for Hl7Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: default_hl7_host(),
+            port: default_hl7_port(),
+            application: default_hl7_identity(),
+            facility: default_hl7_identity(),
+        }
+    }
+}
+
+/// Outbound HL7 ADT identity feed settings
+///
+/// The feed runs when at least one destination is configured.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdentityFeedConfig {
+    /// Downstream systems that receive the feed
+    #[serde(default)]
+    pub destinations: Vec<FeedDestination>,
+    /// Delivery attempts per message before it is dropped
+    #[serde(default = "default_feed_attempts")]
+    pub max_attempts: u32,
+    /// Connect, write and acknowledgment timeout per attempt
+    #[serde(default = "default_feed_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_feed_attempts() -> u32 {
+    5
+}
+
+fn default_feed_timeout_secs() -> u64 {
+    10
+}
+
+impl Default for IdentityFeedConfig {
+    fn default() -> Self {
+        Self {
+            destinations: Vec::new(),
+            max_attempts: default_feed_attempts(),
+            timeout_secs: default_feed_timeout_secs(),
+        }
+    }
+}
+
+/// A downstream MLLP endpoint of the identity feed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedDestination {
+    /// Name used in logs and circuit breaker names
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    /// Receiving application (MSH-5)
+    #[serde(default)]
+    pub application: String,
+    /// Receiving facility (MSH-6)
+    #[serde(default)]
+    pub facility: String,
+    /// Event types to send ("Created", "Updated", "Merged"); all when empty
+    #[serde(default)]
+    pub event_types: Vec<String>,
+    /// Only send patients with an identifier from one of these assigning
+    /// authorities; all patients when empty
+    #[serde(default)]
+    pub authorities: Vec<String>,
+}
+
+/// DICOM patient-root C-FIND adapter settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DicomConfig {
+    /// Whether to accept DICOM associations
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_dicom_host")]
+    pub host: String,
+    #[serde(default = "default_dicom_port")]
+    pub port: u16,
+    /// AE title peers must call; associations to other titles are rejected
+    #[serde(default = "default_dicom_ae_title")]
+    pub ae_title: String,
+    /// Most patients returned for one C-FIND
+    #[serde(default = "default_dicom_max_results")]
+    pub max_results: usize,
+}
+
+fn default_dicom_host() -> String {
+    "0.0.0.0".to_string()
+}
+
+fn default_dicom_port() -> u16 {
+    11112
+}
+
+fn default_dicom_ae_title() -> String {
+    "MPI".to_string()
+}
+
+fn default_dicom_max_results() -> usize {
+    100
+}
+
+impl Default for DicomConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: default_dicom_host(),
+            port: default_dicom_port(),
+            ae_title: default_dicom_ae_title(),
+            max_results: default_dicom_max_results(),
+        }
+    }
+}
+
+/// Steward record lock settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockingConfig {
+    /// Lock lifetime when a request does not ask for one
+    #[serde(default = "default_lock_ttl_secs")]
+    pub default_ttl_secs: u64,
+    /// Longest lifetime a request may ask for
+    #[serde(default = "default_max_lock_ttl_secs")]
+    pub max_ttl_secs: u64,
+}
+
+fn default_lock_ttl_secs() -> u64 {
+    900
+}
+
+fn default_max_lock_ttl_secs() -> u64 {
+    3600
+}
+
+impl Default for LockingConfig {
+    fn default() -> Self {
+        Self {
+            default_ttl_secs: default_lock_ttl_secs(),
+            max_ttl_secs: default_max_lock_ttl_secs(),
+        }
+    }
+}
+
+/// Retention job settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    /// Run the retention job; off unless rules have been agreed
+    #[serde(default)]
+    pub enabled: bool,
+    /// Hours between runs
+    #[serde(default = "default_retention_interval_hours")]
+    pub interval_hours: u64,
+    /// Patients handled per query
+    #[serde(default = "default_retention_batch_size")]
+    pub batch_size: i64,
+    /// Rules evaluated in order on each run
+    #[serde(default)]
+    pub rules: Vec<RetentionRule>,
+}
+
+fn default_retention_interval_hours() -> u64 {
+    24
+}
+
+fn default_retention_batch_size() -> i64 {
+    500
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_hours: default_retention_interval_hours(),
+            batch_size: default_retention_batch_size(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// Patients a retention rule applies to, and what happens to them
+///
+/// Every condition that is set must hold; a rule with no conditions is
+/// ignored rather than applied to every patient.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionRule {
+    /// Name used in logs and reports
+    pub name: String,
+    /// Years since the patient record or any of its source records last changed
+    #[serde(default)]
+    pub inactive_years: Option<u32>,
+    /// Years since the recorded date of death
+    #[serde(default)]
+    pub deceased_years: Option<u32>,
+    /// What to do with matching patients
+    pub action: RetentionAction,
+}
+
+/// Disposal of patients matched by a retention rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionAction {
+    /// Strip direct identifiers but keep the record and its links
+    Anonymize,
+    /// Permanently delete the record
+    Purge,
+}
+
+/// Bulk export settings
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExportConfig {
+    /// Secret key for identifier hashes, pseudonyms and date shifts; at least
+    /// 32 bytes. De-identified exports are refused while unset. Changing it
+    /// changes every pseudonym, so extracts made with different keys cannot
+    /// be linked to each other.
+    #[serde(default)]
+    pub hmac_key: Option<String>,
+    /// De-identified dates move by up to this many days either way
+    #[serde(default = "default_max_date_shift_days")]
+    pub max_date_shift_days: u32,
+}
+
+fn default_max_date_shift_days() -> u32 {
+    180
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            hmac_key: None,
+            max_date_shift_days: default_max_date_shift_days(),
+        }
+    }
+}
+
+impl std::fmt::Debug for ExportConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExportConfig")
+            .field("hmac_key", &self.hmac_key.as_ref().map(|_| "<redacted>"))
+            .field("max_date_shift_days", &self.max_date_shift_days)
+            .finish()
+    }
+}
+
+/// Bulk import settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportConfig {
+    /// Lines processed between checkpoints. A resumed import re-reads at
+    /// most this many lines, which are recognized as already stored.
+    #[serde(default = "default_checkpoint_every")]
+    pub checkpoint_every: u64,
+    /// Directory imported files are read from; paths given to the import
+    /// endpoint must resolve inside it
+    #[serde(default = "default_import_dir")]
+    pub import_dir: String,
+}
+
+fn default_checkpoint_every() -> u64 {
+    1000
+}
+
+fn default_import_dir() -> String {
+    "./data/imports".to_string()
+}
+
+impl Default for ImportConfig {
+    fn default() -> Self {
+        Self {
+            checkpoint_every: default_checkpoint_every(),
+            import_dir: default_import_dir(),
+        }
+    }
+}
+
+/// Match decision log settings
+///
+/// Decisions are logged under the `mpi::match_decision` tracing target, so
+/// they can also be routed or filtered with `RUST_LOG`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecisionLogConfig {
+    /// Log match decisions
+    #[serde(default)]
+    pub enabled: bool,
+    /// Fraction of decisions logged, from 0.0 to 1.0
+    #[serde(default = "default_decision_sample_rate")]
+    pub sample_rate: f64,
+    /// Non-matches scoring within this distance below the threshold are
+    /// always logged, whatever the sample rate
+    #[serde(default = "default_near_miss_margin")]
+    pub near_miss_margin: f64,
+}
+
+fn default_decision_sample_rate() -> f64 {
+    1.0
+}
+
+fn default_near_miss_margin() -> f64 {
+    0.1
+}
+
+impl Default for DecisionLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sample_rate: default_decision_sample_rate(),
+            near_miss_margin: default_near_miss_margin(),
+        }
+    }
+}
+
+/// Load shedding settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadSheddingConfig {
+    /// Reject requests with 503 while over a limit
+    #[serde(default)]
+    pub enabled: bool,
+    /// Recent average wait for a database connection above which requests
+    /// are rejected
+    #[serde(default = "default_max_pool_wait_ms")]
+    pub max_pool_wait_ms: u64,
+    /// Match requests allowed in flight at once
+    #[serde(default = "default_max_inflight_matches")]
+    pub max_inflight_matches: usize,
+    /// Seconds clients are told to wait in `Retry-After`
+    #[serde(default = "default_retry_after_secs")]
+    pub retry_after_secs: u64,
+}
+
+fn default_max_pool_wait_ms() -> u64 {
+    250
+}
+
+fn default_max_inflight_matches() -> usize {
+    32
+}
+
+fn default_retry_after_secs() -> u64 {
+    1
+}
+
+impl Default for LoadSheddingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_pool_wait_ms: default_max_pool_wait_ms(),
+            max_inflight_matches: default_max_inflight_matches(),
+            retry_after_secs: default_retry_after_secs(),
+        }
+    }
+}
+
+/// Request time budgets in milliseconds; 0 means unlimited
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeoutConfig {
+    /// Search and suggest requests
+    #[serde(default = "default_search_timeout_ms")]
+    pub search_ms: u64,
+    /// Match requests
+    #[serde(default = "default_match_timeout_ms")]
+    pub match_ms: u64,
+    /// Admin batch operations such as relinkage, replay and index restores
+    #[serde(default)]
+    pub batch_ms: u64,
+    /// Every other request, except event streams
+    #[serde(default = "default_request_timeout_ms")]
+    pub default_ms: u64,
+}
+
+fn default_search_timeout_ms() -> u64 {
+    2_000
+}
+
+fn default_match_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_request_timeout_ms() -> u64 {
+    30_000
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            search_ms: default_search_timeout_ms(),
+            match_ms: default_match_timeout_ms(),
+            batch_ms: 0,
+            default_ms: default_request_timeout_ms(),
+        }
+    }
+}
+
+/// Circuit breaker settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures that open a breaker
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+    /// Seconds a breaker stays open before a probe call is let through
+    #[serde(default = "default_open_secs")]
+    pub open_secs: u64,
+}
+
+fn default_failure_threshold() -> u32 {
+    5
+}
+
+fn default_open_secs() -> u64 {
+    30
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: default_failure_threshold(),
+            open_secs: default_open_secs(),
+        }
+    }
+}
+
+/// Duplicate detection settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DedupConfig {
+    /// Score every created or updated record against its blocking
+    /// neighbourhood and queue new pairs for review
+    #[serde(default)]
+    pub incremental: bool,
+    /// Candidates fetched from the search index per changed record
+    #[serde(default = "default_dedup_neighborhood")]
+    pub neighborhood_size: usize,
+}
+
+fn default_dedup_neighborhood() -> usize {
+    100
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            incremental: false,
+            neighborhood_size: default_dedup_neighborhood(),
+        }
+    }
+}
+
+/// Cluster validation settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusteringConfig {
+    /// Clusters with more members are flagged without scoring their pairs
+    #[serde(default = "default_max_cluster_size")]
+    pub max_cluster_size: usize,
+    /// Lowest score every pair in a cluster must reach; the match threshold
+    /// when unset
+    #[serde(default)]
+    pub min_pair_score: Option<f64>,
+}
+
+fn default_max_cluster_size() -> usize {
+    20
+}
+
+impl Default for ClusteringConfig {
+    fn default() -> Self {
+        Self {
+            max_cluster_size: default_max_cluster_size(),
+            min_pair_score: None,
+        }
+    }
+}
+
+/// Bulk matching settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchMatchConfig {
+    /// Most records matched within one request; larger batches must run as a job
+    #[serde(default = "default_batch_max_records")]
+    pub max_records: usize,
+    /// Most records in one batch match job
+    #[serde(default = "default_batch_max_job_records")]
+    pub max_job_records: usize,
+    /// Scoring threads per batch; the number of CPUs when 0
+    #[serde(default)]
+    pub workers: usize,
+    /// Directory job results are written to, one NDJSON file per job
+    #[serde(default = "default_batch_results_dir")]
+    pub results_dir: String,
+}
+
+fn default_batch_max_records() -> usize {
+    1_000
+}
+
+fn default_batch_max_job_records() -> usize {
+    100_000
+}
+
+fn default_batch_results_dir() -> String {
+    "./data/batch_match".to_string()
+}
+
+impl Default for BatchMatchConfig {
+    fn default() -> Self {
+        Self {
+            max_records: default_batch_max_records(),
+            max_job_records: default_batch_max_job_records(),
+            workers: 0,
+            results_dir: default_batch_results_dir(),
+        }
+    }
+}
+
+/// Caching of reference data read on every registration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LookupCacheConfig {
+    /// Seconds a loaded lookup is served from memory; 0 disables the cache.
+    /// Writes through this instance invalidate it at once, writes through
+    /// other instances after at most this long.
+    #[serde(default = "default_lookup_ttl_secs")]
+    pub ttl_secs: u64,
+    /// `max-age` of the `Cache-Control` header on lookup GET endpoints
+    #[serde(default = "default_lookup_max_age_secs")]
+    pub max_age_secs: u64,
+}
+
+fn default_lookup_ttl_secs() -> u64 {
+    300
+}
+
+fn default_lookup_max_age_secs() -> u64 {
+    60
+}
+
+impl Default for LookupCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: default_lookup_ttl_secs(),
+            max_age_secs: default_lookup_max_age_secs(),
+        }
+    }
+}
+
+/// Access rules for restricted and VIP patients
+///
+/// Roles come from the `X-User-Roles` header set by the authenticating
+/// gateway in front of the MPI.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivacyConfig {
+    /// Roles that see restricted and VIP patients unmasked
+    #[serde(default = "default_privileged_roles")]
+    pub privileged_roles: Vec<String>,
+    /// Roles that may change a patient's confidentiality
+    #[serde(default = "default_privileged_roles")]
+    pub officer_roles: Vec<String>,
+    /// What searches by other users return for restricted and VIP patients
+    #[serde(default)]
+    pub restricted_search: RestrictedSearch,
+}
+
+fn default_privileged_roles() -> Vec<String> {
+    vec!["privacy-officer".to_string()]
+}
+
+impl Default for PrivacyConfig {
+    fn default() -> Self {
+        Self {
+            privileged_roles: default_privileged_roles(),
+            officer_roles: default_privileged_roles(),
+            restricted_search: RestrictedSearch::default(),
+        }
+    }
+}
+
+/// Treatment of restricted patients in searches by unprivileged users
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RestrictedSearch {
+    /// Returned with every demographic field removed
+    #[default]
+    Mask,
+    /// Left out of the results
+    Exclude,
+}
+
+/// Survivorship of master record fields
+///
+/// A write may change a demographic field only when its source is at least
+/// as authoritative as the source of the field's current value. Sources are
+/// named by `X-Source-System`, a FHIR `meta.source`, the HL7 sending
+/// application, or else the channel ("rest", "fhir", "hl7").
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SurvivorshipConfig {
+    /// Sources from most to least authoritative; unlisted sources rank
+    /// below every listed one and equal to each other. Empty lets every
+    /// write through.
+    #[serde(default)]
+    pub source_priority: Vec<String>,
+}
+
+impl SurvivorshipConfig {
+    /// Rank of a source, lower being more authoritative
+    pub fn rank(&self, source: &str) -> usize {
+        self.source_priority
+            .iter()
+            .position(|s| s == source)
+            .unwrap_or(self.source_priority.len())
+    }
+}
+
+/// Name frequency weighting settings
+///
+/// When enabled, a background job counts family names among stored patients
+/// and the probabilistic scorer gives agreement on a common family name
+/// less weight than agreement on a rare one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameFrequencyConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Hours between recounts
+    #[serde(default = "default_name_frequency_refresh_hours")]
+    pub refresh_hours: u64,
+    /// Share of patients at or below which a name keeps the full name weight
+    #[serde(default = "default_reference_frequency")]
+    pub reference_frequency: f64,
+    /// Least share of the name weight the most common names keep
+    #[serde(default = "default_min_frequency_factor")]
+    pub min_factor: f64,
+    /// Patients needed before frequencies are trusted
+    #[serde(default = "default_min_population")]
+    pub min_population: i64,
+}
+
+fn default_name_frequency_refresh_hours() -> u64 {
+    24
+}
+
+fn default_reference_frequency() -> f64 {
+    0.001
+}
+
+fn default_min_frequency_factor() -> f64 {
+    0.5
+}
+
+fn default_min_population() -> i64 {
+    1000
+}
+
+impl Default for NameFrequencyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_hours: default_name_frequency_refresh_hours(),
+            reference_frequency: default_reference_frequency(),
+            min_factor: default_min_frequency_factor(),
+            min_population: default_min_population(),
+        }
+    }
+}
+
+/// Practitioner deduplication settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PractitionerConfig {
+    /// Threshold and weights for scoring practitioner pairs
+    ///
+    /// Providers have no birth date on file and share names more often than
+    /// patients do, so by default the NPI and other identifiers carry most
+    /// of the weight and a name match alone is never enough.
+    #[serde(default = "default_practitioner_matching")]
+    pub matching: MatchingConfig,
+    /// Practitioners with the same family name scored per match request
+    #[serde(default = "default_practitioner_candidates")]
+    pub max_candidates: i64,
+}
+
+fn default_practitioner_matching() -> MatchingConfig {
+    MatchingConfig {
+        threshold_score: 0.85,
+        exact_match_score: 1.0,
+        fuzzy_match_score: 0.8,
+        weights: MatchWeights {
+            name: 0.50,
+            birth_date: 0.0,
+            gender: 0.0,
+            address: 0.10,
+            identifier: 0.40,
+        },
+        verification: VerificationWeights::default(),
+        transliteration: TransliterationConfig::default(),
+        similarity: SimilarityConfig::default(),
+        blocking: BlockingConfig::default(),
+        photo: PhotoMatchingConfig::default(),
+    }
+}
+
+fn default_practitioner_candidates() -> i64 {
+    200
+}
+
+impl Default for PractitionerConfig {
+    fn default() -> Self {
+        Self {
+            matching: default_practitioner_matching(),
+            max_candidates: default_practitioner_candidates(),
+        }
+    }
+}
+
+/// Data integrity check settings
+///
+/// The check looks for records the database constraints cannot rule out:
+/// patients without a primary name, links to deleted patients, child rows
+/// left behind by imports without foreign keys, MRNs held twice under one
+/// authority and differences between the search index and the database.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityConfig {
+    /// Check, without repairing anything, when the server starts
+    #[serde(default)]
+    pub check_on_startup: bool,
+    /// Most problems of each kind reported by one check
+    #[serde(default = "default_integrity_max_issues")]
+    pub max_issues: i64,
+}
+
+fn default_integrity_max_issues() -> i64 {
+    1000
+}
+
+impl Default for IntegrityConfig {
+    fn default() -> Self {
+        Self {
+            check_on_startup: false,
+            max_issues: default_integrity_max_issues(),
+        }
+    }
+}
+
+/// Link confidence job settings
+///
+/// Each run compares every current source record link's source record with
+/// its enterprise record. The link's recorded confidence is multiplied by
+/// `1 - name_decay` when the names no longer agree and by
+/// `1 - address_decay` when the source record's addresses are no longer on
+/// file. Automatic links that end below `reverify_below` are flagged for
+/// stewards to verify again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkConfidenceConfig {
+    /// Run the link confidence job
+    #[serde(default)]
+    pub enabled: bool,
+    /// Hours between runs
+    #[serde(default = "default_link_confidence_interval_hours")]
+    pub interval_hours: u64,
+    /// Share of confidence lost when the names have diverged
+    #[serde(default = "default_name_decay")]
+    pub name_decay: f64,
+    /// Share of confidence lost when the addresses have diverged
+    #[serde(default = "default_address_decay")]
+    pub address_decay: f64,
+    /// Automatic links less confident than this are flagged for re-verification
+    #[serde(default = "default_reverify_below")]
+    pub reverify_below: f64,
+}
+
+fn default_link_confidence_interval_hours() -> u64 {
+    24
+}
+
+fn default_name_decay() -> f64 {
+    0.2
+}
+
+fn default_address_decay() -> f64 {
+    0.1
+}
+
+fn default_reverify_below() -> f64 {
+    0.7
+}
+
+impl Default for LinkConfidenceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_hours: default_link_confidence_interval_hours(),
+            name_decay: default_name_decay(),
+            address_decay: default_address_decay(),
+            reverify_below: default_reverify_below(),
+        }
+    }
+}
+
+/// Steward alerts
+///
+/// Each alert type is routed to its own recipients in `routes`, keyed by
+/// `overlay`, `review_backlog`, `job_failed` or `break_the_glass`. A
+/// recipient is an email address, sent through `smtp`, or an http(s) URL
+/// that receives the alert as a JSON POST. Alert types without recipients
+/// are not sent.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotificationConfig {
+    /// Send alerts
+    #[serde(default)]
+    pub enabled: bool,
+    /// Mail server for email recipients
+    #[serde(default)]
+    pub smtp: Option<SmtpConfig>,
+    /// Recipients per alert type
+    #[serde(default)]
+    pub routes: BTreeMap<String, Vec<String>>,
+    /// Subject and body per alert type, replacing the built-in wording;
+    /// `{{field}}` is replaced by the alert's value for that field
+    #[serde(default)]
+    pub templates: BTreeMap<String, AlertTemplate>,
+    /// Review backlog alerts
+    #[serde(default)]
+    pub review_backlog: ReviewBacklogAlertConfig,
+}
+
+/// Mail server used to send email alerts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Sender address, e.g. `MPI Alerts <mpi-alerts@example.org>`
+    pub from: String,
+    /// Upgrade the connection with STARTTLS; only disable for a local relay
+    #[serde(default = "default_true")]
+    pub starttls: bool,
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+/// Wording of one alert type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertTemplate {
+    pub subject: String,
+    pub body: String,
+}
+
+/// When the duplicate review queue is long enough to alert stewards
+///
+/// Stewards are alerted once when the queue reaches `threshold` pending
+/// pairs, and again only after it has dropped below it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewBacklogAlertConfig {
+    /// Pending pairs at which stewards are alerted
+    #[serde(default = "default_review_backlog_threshold")]
+    pub threshold: i64,
+    /// Minutes between checks of the queue
+    #[serde(default = "default_review_backlog_interval_minutes")]
+    pub interval_minutes: u64,
+}
+
+fn default_review_backlog_threshold() -> i64 {
+    500
+}
+
+fn default_review_backlog_interval_minutes() -> u64 {
+    15
+}
+
+impl Default for ReviewBacklogAlertConfig {
+    fn default() -> Self {
+        Self {
+            threshold: default_review_backlog_threshold(),
+            interval_minutes: default_review_backlog_interval_minutes(),
+        }
+    }
 }
 
 impl Default for Config {
@@ -71,30 +1861,79 @@ impl Default for Config {
                 host: "0.0.0.0".to_string(),
                 port: 8080,
                 grpc_port: 50051,
+                compression: true,
+                admin_roles: default_admin_roles(),
             },
             database: DatabaseConfig {
                 url: "postgres://localhost/mpi".to_string(),
                 max_connections: 10,
                 min_connections: 2,
+                read_replica_url: None,
             },
             search: SearchConfig {
+                backend: SearchBackendKind::Tantivy,
                 index_path: "./data/search_index".to_string(),
                 cache_size_mb: 512,
+                field_boosts: FieldBoosts::default(),
+                opensearch: None,
+                replication: IndexReplicationConfig::default(),
+                partitioning: PartitioningConfig::default(),
+                bulk_index: BulkIndexConfig::default(),
+                backup_dir: default_backup_dir(),
             },
             matching: MatchingConfig {
                 threshold_score: 0.85,
                 exact_match_score: 1.0,
                 fuzzy_match_score: 0.8,
+                weights: MatchWeights::default(),
+                verification: VerificationWeights::default(),
+                transliteration: TransliterationConfig::default(),
+                similarity: SimilarityConfig::default(),
+                blocking: BlockingConfig::default(),
+                photo: PhotoMatchingConfig::default(),
             },
+            matching_profiles: BTreeMap::new(),
+            matching_settings: MatchingSettingsConfig::default(),
             observability: ObservabilityConfig {
                 service_name: "master-patient-index".to_string(),
                 otlp_endpoint: "http://localhost:4317".to_string(),
                 log_level: "info".to_string(),
+                audit_sinks: Vec::new(),
             },
             streaming: StreamingConfig {
                 broker_url: "localhost:9003".to_string(),
                 topic: "patient-events".to_string(),
+                format: EventFormat::Json,
+                tenant: None,
+                instance_id: default_instance_id(),
+                inbound: Vec::new(),
             },
+            reporting: ReportingConfig::default(),
+            locale: LocaleConfig::default(),
+            identifiers: IdentifierConfig::default(),
+            fhir: FhirConfig::default(),
+            hl7: Hl7Config::default(),
+            identity_feed: IdentityFeedConfig::default(),
+            dicom: DicomConfig::default(),
+            locking: LockingConfig::default(),
+            retention: RetentionConfig::default(),
+            export: ExportConfig::default(),
+            import: ImportConfig::default(),
+            decision_log: DecisionLogConfig::default(),
+            load_shedding: LoadSheddingConfig::default(),
+            timeouts: TimeoutConfig::default(),
+            circuit_breaker: CircuitBreakerConfig::default(),
+            dedup: DedupConfig::default(),
+            clustering: ClusteringConfig::default(),
+            batch_match: BatchMatchConfig::default(),
+            lookup_cache: LookupCacheConfig::default(),
+            privacy: PrivacyConfig::default(),
+            survivorship: SurvivorshipConfig::default(),
+            practitioners: PractitionerConfig::default(),
+            name_frequency: NameFrequencyConfig::default(),
+            integrity: IntegrityConfig::default(),
+            link_confidence: LinkConfidenceConfig::default(),
+            notifications: NotificationConfig::default(),
         }
     }
 }
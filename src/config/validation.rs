@@ -0,0 +1,352 @@
+//! Checks of the whole configuration before startup
+//!
+//! Each module checks the settings it uses when it starts using them, so a
+//! bad value used to surface as whichever error came first, often deep in
+//! the search or matching setup, and the next one only after a restart.
+//! [`Config::validate`] checks every section up front, including values
+//! that only make sense together, and reports every problem at once.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::notifications::{AlertKind, Recipient};
+use super::{Config, IndexRole, MatchWeights, MatchingConfig, SearchBackendKind, DEFAULT_PROFILE};
+
+/// How far the match weights may sum from 1.0, for decimal rounding
+const WEIGHT_SUM_TOLERANCE: f64 = 1e-6;
+
+/// A setting that is invalid on its own or together with another
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigProblem {
+    /// Dotted path of the setting, such as `matching.threshold_score`
+    pub key: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.key, self.message)
+    }
+}
+
+#[derive(Default)]
+struct Problems(Vec<ConfigProblem>);
+
+impl Problems {
+    fn push(&mut self, key: impl Into<String>, message: impl Into<String>) {
+        self.0.push(ConfigProblem {
+            key: key.into(),
+            message: message.into(),
+        });
+    }
+
+    /// A score, rate or fraction between 0.0 and 1.0
+    fn unit(&mut self, key: impl Into<String>, value: f64) {
+        if !(0.0..=1.0).contains(&value) {
+            self.push(key, format!("must be between 0.0 and 1.0, got {}", value));
+        }
+    }
+
+    fn matching(&mut self, prefix: &str, matching: &MatchingConfig) {
+        self.unit(format!("{}.threshold_score", prefix), matching.threshold_score);
+        self.unit(format!("{}.exact_match_score", prefix), matching.exact_match_score);
+        self.unit(format!("{}.fuzzy_match_score", prefix), matching.fuzzy_match_score);
+        if matching.fuzzy_match_score >= matching.exact_match_score {
+            self.push(
+                format!("{}.fuzzy_match_score", prefix),
+                format!("must be below exact_match_score ({})", matching.exact_match_score),
+            );
+        }
+        self.weights(&format!("{}.weights", prefix), &matching.weights);
+
+        let verification = &matching.verification;
+        for (name, multiplier) in [
+            ("patient_confirmed", verification.patient_confirmed),
+            ("document_verified", verification.document_verified),
+        ] {
+            if !(multiplier.is_finite() && multiplier >= 0.0) {
+                self.push(
+                    format!("{}.verification.{}", prefix, name),
+                    format!("must be a non-negative number, got {}", multiplier),
+                );
+            }
+        }
+
+        let similarity = &matching.similarity;
+        self.unit(format!("{}.similarity.transposed_name_score", prefix), similarity.transposed_name_score);
+        for (name, algorithm) in [("family_name", &similarity.family_name), ("given_name", &similarity.given_name)] {
+            if crate::matching::similarity::lookup(algorithm).is_none() {
+                self.push(
+                    format!("{}.similarity.{}", prefix, name),
+                    format!(
+                        "'{}' is not registered; registered are {}",
+                        algorithm,
+                        crate::matching::similarity::registered().join(", ")
+                    ),
+                );
+            }
+        }
+
+        if matching.blocking.passes.is_empty() {
+            self.push(format!("{}.blocking.passes", prefix), "must list at least one pass");
+        }
+    }
+
+    fn weights(&mut self, key: &str, weights: &MatchWeights) {
+        let components = [
+            ("name", weights.name),
+            ("birth_date", weights.birth_date),
+            ("gender", weights.gender),
+            ("address", weights.address),
+            ("identifier", weights.identifier),
+        ];
+        let mut valid = true;
+        for (name, weight) in components {
+            if !(weight.is_finite() && weight >= 0.0) {
+                self.push(format!("{}.{}", key, name), format!("must be a non-negative number, got {}", weight));
+                valid = false;
+            }
+        }
+        let sum: f64 = components.iter().map(|(_, weight)| weight).sum();
+        if valid && (sum - 1.0).abs() > WEIGHT_SUM_TOLERANCE {
+            self.push(key, format!("must sum to 1.0, got {}", sum));
+        }
+    }
+}
+
+impl Config {
+    /// Every invalid setting, in the order the sections are declared
+    pub fn problems(&self) -> Vec<ConfigProblem> {
+        let mut problems = Problems::default();
+
+        if self.server.port == self.server.grpc_port {
+            problems.push("server.grpc_port", format!("must differ from server.port ({})", self.server.port));
+        }
+
+        let database = &self.database;
+        if let Some(message) = database_url_problem(&database.url) {
+            problems.push("database.url", message);
+        }
+        if let Some(message) = database.read_replica_url.as_deref().and_then(database_url_problem) {
+            problems.push("database.read_replica_url", message);
+        }
+        if database.max_connections == 0 {
+            problems.push("database.max_connections", "must be at least 1");
+        }
+        if database.min_connections > database.max_connections {
+            problems.push(
+                "database.min_connections",
+                format!("must not exceed database.max_connections ({})", database.max_connections),
+            );
+        }
+
+        let search = &self.search;
+        match search.backend {
+            SearchBackendKind::Tantivy => {
+                if let Some(message) = writable_dir_problem(&search.index_path) {
+                    problems.push("search.index_path", message);
+                }
+                if search.partitioning.enabled {
+                    if let Some(message) = writable_dir_problem(&search.partitioning.cold_index_path) {
+                        problems.push("search.partitioning.cold_index_path", message);
+                    }
+                    if Path::new(&search.partitioning.cold_index_path) == Path::new(&search.index_path) {
+                        problems.push("search.partitioning.cold_index_path", "must differ from search.index_path");
+                    }
+                }
+            }
+            SearchBackendKind::OpenSearch => match &search.opensearch {
+                Some(opensearch) if !opensearch.url.starts_with("http://") && !opensearch.url.starts_with("https://") => {
+                    problems.push("search.opensearch.url", format!("must be an http or https URL, got '{}'", opensearch.url));
+                }
+                Some(_) => {}
+                None => problems.push("search.opensearch", "must be set for the opensearch backend"),
+            },
+            SearchBackendKind::Postgres => {
+                if search.partitioning.enabled {
+                    problems.push("search.partitioning.enabled", "is not supported by the postgres backend");
+                }
+            }
+        }
+        let replication = &search.replication;
+        if replication.role != IndexRole::Standalone {
+            if replication.publish_interval_secs == 0 {
+                problems.push("search.replication.publish_interval_secs", "must be at least 1");
+            }
+            if replication.refresh_interval_secs == 0 {
+                problems.push("search.replication.refresh_interval_secs", "must be at least 1");
+            }
+        }
+        if search.bulk_index.batch_size == 0 {
+            problems.push("search.bulk_index.batch_size", "must be at least 1");
+        }
+        if search.bulk_index.queue_batches == 0 {
+            problems.push("search.bulk_index.queue_batches", "must be at least 1");
+        }
+
+        problems.matching("matching", &self.matching);
+        for (name, profile) in &self.matching_profiles {
+            let prefix = format!("matching_profiles.{}", name);
+            if name == DEFAULT_PROFILE {
+                problems.push(prefix.clone(), "the name 'default' is reserved for the settings in `matching`");
+            }
+            if let Some(threshold) = profile.threshold_score {
+                problems.unit(format!("{}.threshold_score", prefix), threshold);
+            }
+            if let Some(weights) = &profile.weights {
+                problems.weights(&format!("{}.weights", prefix), weights);
+            }
+            if profile.max_candidates == 0 {
+                problems.push(format!("{}.max_candidates", prefix), "must be at least 1");
+            }
+        }
+
+        let log_level = &self.observability.log_level;
+        if crate::observability::parse_log_level(log_level).is_err() {
+            problems.push("observability.log_level", format!("'{}' is not a valid log level or filter", log_level));
+        }
+
+        problems.unit("decision_log.sample_rate", self.decision_log.sample_rate);
+        problems.unit("decision_log.near_miss_margin", self.decision_log.near_miss_margin);
+
+        if let Some(score) = self.clustering.min_pair_score {
+            problems.unit("clustering.min_pair_score", score);
+        }
+
+        for (authority, namespace) in &self.identifiers.id_namespaces {
+            if namespace.is_nil() {
+                problems.push(format!("identifiers.id_namespaces.{}", authority), "must not be the nil UUID");
+            }
+        }
+
+        let batch = &self.batch_match;
+        if batch.max_records > batch.max_job_records {
+            problems.push(
+                "batch_match.max_records",
+                format!("must not exceed batch_match.max_job_records ({})", batch.max_job_records),
+            );
+        }
+
+        problems.matching("practitioners.matching", &self.practitioners.matching);
+
+        let frequency = &self.name_frequency;
+        problems.unit("name_frequency.reference_frequency", frequency.reference_frequency);
+        problems.unit("name_frequency.min_factor", frequency.min_factor);
+
+        if self.integrity.max_issues < 1 {
+            problems.push("integrity.max_issues", "must be at least 1");
+        }
+
+        if self.import.checkpoint_every < 1 {
+            problems.push("import.checkpoint_every", "must be at least 1");
+        }
+
+        let link_confidence = &self.link_confidence;
+        problems.unit("link_confidence.name_decay", link_confidence.name_decay);
+        problems.unit("link_confidence.address_decay", link_confidence.address_decay);
+        problems.unit("link_confidence.reverify_below", link_confidence.reverify_below);
+
+        let notifications = &self.notifications;
+        if notifications.enabled {
+            for (kind, recipients) in &notifications.routes {
+                let key = format!("notifications.routes.{}", kind);
+                if AlertKind::parse(kind).is_none() {
+                    problems.push(key.clone(), "is not an alert type");
+                }
+                for recipient in recipients {
+                    match Recipient::parse(recipient) {
+                        None => problems.push(key.clone(), format!("'{}' is neither an email address nor an http(s) URL", recipient)),
+                        Some(Recipient::Email(_)) if notifications.smtp.is_none() => {
+                            problems.push(key.clone(), format!("'{}' needs notifications.smtp to be set", recipient));
+                        }
+                        Some(_) => {}
+                    }
+                }
+            }
+            for kind in notifications.templates.keys() {
+                if AlertKind::parse(kind).is_none() {
+                    problems.push(format!("notifications.templates.{}", kind), "is not an alert type");
+                }
+            }
+            if notifications.review_backlog.threshold < 1 {
+                problems.push("notifications.review_backlog.threshold", "must be at least 1");
+            }
+            if notifications.review_backlog.interval_minutes < 1 {
+                problems.push("notifications.review_backlog.interval_minutes", "must be at least 1");
+            }
+        }
+
+        problems.0
+    }
+
+    /// Check every section, reporting all invalid settings in one error
+    pub fn validate(&self) -> crate::Result<()> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            return Ok(());
+        }
+        let mut report = match problems.len() {
+            1 => "1 invalid setting:".to_string(),
+            n => format!("{} invalid settings:", n),
+        };
+        for problem in &problems {
+            report.push_str("\n  ");
+            report.push_str(&problem.to_string());
+        }
+        Err(crate::Error::Config(report))
+    }
+}
+
+/// Why `url` is not a PostgreSQL connection string, in URI or key/value form
+fn database_url_problem(url: &str) -> Option<String> {
+    let url = url.trim();
+    if url.is_empty() {
+        return Some("must be set".to_string());
+    }
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return url
+            .split_whitespace()
+            .find(|pair| !pair.contains('='))
+            .map(|pair| format!("'{}' is neither a postgres:// URL nor a key=value pair", pair));
+    };
+    if scheme != "postgres" && scheme != "postgresql" {
+        return Some(format!("scheme must be postgres or postgresql, got '{}'", scheme));
+    }
+    let authority = rest.split(['/', '?']).next().unwrap_or_default();
+    let hosts = authority.rsplit_once('@').map_or(authority, |(_, hosts)| hosts);
+    for host in hosts.split(',') {
+        // IPv6 hosts are bracketed and contain colons of their own
+        let port = match host.rsplit_once(']') {
+            Some((_, after)) => after.strip_prefix(':'),
+            None => host.rsplit_once(':').map(|(_, port)| port),
+        };
+        if let Some(port) = port {
+            if port.parse::<u16>().is_err() {
+                return Some(format!("port '{}' is not a number", port));
+            }
+        }
+    }
+    None
+}
+
+/// Why a directory at `path` could not be written, creating it if needed
+///
+/// The nearest existing ancestor is checked, since the rest of the path is
+/// created at startup.
+fn writable_dir_problem(path: &str) -> Option<String> {
+    let existing = Path::new(path)
+        .ancestors()
+        .map(|dir| if dir.as_os_str().is_empty() { Path::new(".") } else { dir })
+        .find(|dir| dir.exists())?;
+    if !existing.is_dir() {
+        return Some(format!("{} is not a directory", existing.display()));
+    }
+    let probe = existing.join(format!(".mpi-write-check-{}", uuid::Uuid::new_v4()));
+    match std::fs::File::create(&probe) {
+        Ok(_) => {
+            let _ = std::fs::remove_file(&probe);
+            None
+        }
+        Err(e) => Some(format!("{} is not writable: {}", existing.display(), e)),
+    }
+}
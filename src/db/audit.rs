@@ -4,8 +4,10 @@ use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, Pool};
 use diesel::PgConnection;
 use uuid::Uuid;
+use chrono::{DateTime, Utc};
 use serde_json::Value as JsonValue;
 
+use crate::observability::audit_sink::{AuditEntry, AuditForwarder};
 use crate::Result;
 use super::models::{NewDbAuditLog, DbAuditLog};
 use super::schema::audit_log;
@@ -13,12 +15,30 @@ use super::schema::audit_log;
 /// Audit log repository for recording changes
 pub struct AuditLogRepository {
     pool: Pool<ConnectionManager<PgConnection>>,
+    reads: super::ReadPool,
+    forwarder: Option<std::sync::Arc<AuditForwarder>>,
 }
 
 impl AuditLogRepository {
     /// Create a new audit log repository
     pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
-        Self { pool }
+        Self {
+            reads: super::ReadPool::new(pool.clone()),
+            pool,
+            forwarder: None,
+        }
+    }
+
+    /// Serve audit log queries through `reads`
+    pub fn with_reads(mut self, reads: super::ReadPool) -> Self {
+        self.reads = reads;
+        self
+    }
+
+    /// Also forward every entry written to SIEM collectors
+    pub fn with_forwarder(mut self, forwarder: std::sync::Arc<AuditForwarder>) -> Self {
+        self.forwarder = Some(forwarder);
+        self
     }
 
     /// Get a database connection from the pool
@@ -93,6 +113,109 @@ impl AuditLogRepository {
         )
     }
 
+    /// Log a permanent deletion
+    pub fn log_purge(
+        &self,
+        entity_type: &str,
+        entity_id: Uuid,
+        old_values: JsonValue,
+        user_id: Option<String>,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<()> {
+        self.log_action(
+            "PURGE",
+            entity_type,
+            entity_id,
+            Some(old_values),
+            None,
+            user_id,
+            ip_address,
+            user_agent,
+        )
+    }
+
+    /// Log a configuration reload, with the settings before and after
+    pub fn log_config_reload(
+        &self,
+        old_values: JsonValue,
+        new_values: JsonValue,
+        user_id: Option<String>,
+    ) -> Result<()> {
+        self.log_action(
+            "CONFIG_RELOAD",
+            "Config",
+            Uuid::nil(),
+            Some(old_values),
+            Some(new_values),
+            user_id,
+            None,
+            None,
+        )
+    }
+
+    /// Log a change of a patient's confidentiality, with the reason given
+    pub fn log_confidentiality_change(
+        &self,
+        entity_id: Uuid,
+        old_values: JsonValue,
+        new_values: JsonValue,
+        user_id: Option<String>,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<()> {
+        self.log_action(
+            "CONFIDENTIALITY",
+            "Patient",
+            entity_id,
+            Some(old_values),
+            Some(new_values),
+            user_id,
+            ip_address,
+            user_agent,
+        )
+    }
+
+    /// Log a repair made by the data integrity check, with what was repaired
+    pub fn log_integrity_repair(
+        &self,
+        entity_id: Uuid,
+        details: JsonValue,
+        user_id: Option<String>,
+    ) -> Result<()> {
+        self.log_action(
+            "INTEGRITY_REPAIR",
+            "Patient",
+            entity_id,
+            None,
+            Some(details),
+            user_id,
+            None,
+            None,
+        )
+    }
+
+    /// Log a read of a restricted or VIP patient, with how it was shown
+    pub fn log_restricted_access(
+        &self,
+        entity_id: Uuid,
+        details: JsonValue,
+        user_id: Option<String>,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<()> {
+        self.log_action(
+            "RESTRICTED_ACCESS",
+            "Patient",
+            entity_id,
+            None,
+            Some(details),
+            user_id,
+            ip_address,
+            user_agent,
+        )
+    }
+
     /// Log a generic action
     fn log_action(
         &self,
@@ -118,9 +241,14 @@ impl AuditLogRepository {
             user_agent,
         };
 
-        diesel::insert_into(audit_log::table)
+        let written: DbAuditLog = diesel::insert_into(audit_log::table)
             .values(&new_audit)
-            .execute(&mut conn)?;
+            .returning(DbAuditLog::as_returning())
+            .get_result(&mut conn)?;
+
+        if let Some(forwarder) = &self.forwarder {
+            forwarder.forward(AuditEntry::from(&written));
+        }
 
         Ok(())
     }
@@ -132,7 +260,7 @@ impl AuditLogRepository {
         entity_id: Uuid,
         limit: i64,
     ) -> Result<Vec<DbAuditLog>> {
-        let mut conn = self.get_conn()?;
+        let mut conn = self.reads.get()?;
 
         let logs = audit_log::table
             .filter(audit_log::entity_type.eq(entity_type))
@@ -146,7 +274,7 @@ impl AuditLogRepository {
 
     /// Get recent audit logs
     pub fn get_recent_logs(&self, limit: i64) -> Result<Vec<DbAuditLog>> {
-        let mut conn = self.get_conn()?;
+        let mut conn = self.reads.get()?;
 
         let logs = audit_log::table
             .order(audit_log::timestamp.desc())
@@ -162,7 +290,7 @@ impl AuditLogRepository {
         user_id: &str,
         limit: i64,
     ) -> Result<Vec<DbAuditLog>> {
-        let mut conn = self.get_conn()?;
+        let mut conn = self.reads.get()?;
 
         let logs = audit_log::table
             .filter(audit_log::user_id.eq(user_id))
@@ -172,4 +300,39 @@ impl AuditLogRepository {
 
         Ok(logs)
     }
+
+    /// Get audit logs matching optional entity and time range filters
+    ///
+    /// `since` is inclusive and `until` is exclusive.
+    pub fn query_logs(
+        &self,
+        entity_type: Option<&str>,
+        entity_id: Option<Uuid>,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<DbAuditLog>> {
+        let mut conn = self.reads.get()?;
+
+        let mut query = audit_log::table.into_boxed();
+        if let Some(entity_type) = entity_type {
+            query = query.filter(audit_log::entity_type.eq(entity_type.to_string()));
+        }
+        if let Some(entity_id) = entity_id {
+            query = query.filter(audit_log::entity_id.eq(entity_id));
+        }
+        if let Some(since) = since {
+            query = query.filter(audit_log::timestamp.ge(since));
+        }
+        if let Some(until) = until {
+            query = query.filter(audit_log::timestamp.lt(until));
+        }
+
+        let logs = query
+            .order(audit_log::timestamp.desc())
+            .limit(limit)
+            .load::<DbAuditLog>(&mut conn)?;
+
+        Ok(logs)
+    }
 }
@@ -0,0 +1,146 @@
+//! Assigning authority repository
+
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::models::{AssigningAuthority, AuthorityRegistry};
+use crate::Result;
+use super::models::{DbAssigningAuthority, NewDbAssigningAuthority, UpdateDbAssigningAuthority};
+use super::schema::assigning_authorities;
+
+/// Assigning authority repository trait
+pub trait AssigningAuthorityRepository: Send + Sync {
+    /// Register an authority
+    fn create(&self, system: String, oid: Option<String>, name: String, active: bool) -> Result<AssigningAuthority>;
+
+    /// Get an authority by ID
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<AssigningAuthority>>;
+
+    /// List every authority, ordered by name
+    fn list(&self) -> Result<Vec<AssigningAuthority>>;
+
+    /// Replace an authority's fields, returning `None` if it does not exist
+    fn update(
+        &self,
+        id: &Uuid,
+        system: String,
+        oid: Option<String>,
+        name: String,
+        active: bool,
+    ) -> Result<Option<AssigningAuthority>>;
+
+    /// Remove an authority, returning whether it existed
+    fn delete(&self, id: &Uuid) -> Result<bool>;
+
+    /// Snapshot of every authority for lookups by system
+    fn registry(&self) -> Result<AuthorityRegistry> {
+        Ok(AuthorityRegistry::new(self.list()?))
+    }
+}
+
+/// Diesel-based assigning authority repository implementation
+pub struct DieselAssigningAuthorityRepository {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl DieselAssigningAuthorityRepository {
+    /// Create a new repository with the given connection pool
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+
+    /// Get a database connection from the pool
+    fn get_conn(&self) -> Result<diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>> {
+        self.pool.get().map_err(|e| crate::Error::Pool(e.to_string()))
+    }
+
+    /// Convert a database authority to the domain model
+    fn to_authority(db_authority: DbAssigningAuthority) -> AssigningAuthority {
+        AssigningAuthority {
+            id: db_authority.id,
+            system: db_authority.system,
+            oid: db_authority.oid,
+            name: db_authority.name,
+            active: db_authority.active,
+            created_at: db_authority.created_at,
+            updated_at: db_authority.updated_at,
+        }
+    }
+}
+
+impl AssigningAuthorityRepository for DieselAssigningAuthorityRepository {
+    fn create(&self, system: String, oid: Option<String>, name: String, active: bool) -> Result<AssigningAuthority> {
+        let mut conn = self.get_conn()?;
+
+        let new_authority = NewDbAssigningAuthority { system, oid, name, active };
+
+        let db_authority: DbAssigningAuthority = diesel::insert_into(assigning_authorities::table)
+            .values(&new_authority)
+            .returning(DbAssigningAuthority::as_returning())
+            .get_result(&mut conn)?;
+
+        Ok(Self::to_authority(db_authority))
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<AssigningAuthority>> {
+        let mut conn = self.get_conn()?;
+
+        let db_authority = assigning_authorities::table
+            .find(id)
+            .select(DbAssigningAuthority::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(db_authority.map(Self::to_authority))
+    }
+
+    fn list(&self) -> Result<Vec<AssigningAuthority>> {
+        let mut conn = self.get_conn()?;
+
+        let db_authorities = assigning_authorities::table
+            .order(assigning_authorities::name.asc())
+            .select(DbAssigningAuthority::as_select())
+            .load(&mut conn)?;
+
+        Ok(db_authorities.into_iter().map(Self::to_authority).collect())
+    }
+
+    fn update(
+        &self,
+        id: &Uuid,
+        system: String,
+        oid: Option<String>,
+        name: String,
+        active: bool,
+    ) -> Result<Option<AssigningAuthority>> {
+        let mut conn = self.get_conn()?;
+
+        let changes = UpdateDbAssigningAuthority {
+            system,
+            oid,
+            name,
+            active,
+            updated_at: Utc::now(),
+        };
+
+        let db_authority = diesel::update(assigning_authorities::table.find(id))
+            .set(&changes)
+            .returning(DbAssigningAuthority::as_returning())
+            .get_result(&mut conn)
+            .optional()?;
+
+        Ok(db_authority.map(Self::to_authority))
+    }
+
+    fn delete(&self, id: &Uuid) -> Result<bool> {
+        let mut conn = self.get_conn()?;
+
+        let deleted = diesel::delete(assigning_authorities::table.find(id))
+            .execute(&mut conn)?;
+
+        Ok(deleted > 0)
+    }
+}
@@ -0,0 +1,150 @@
+//! Change request repository for patient-proposed corrections
+
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::models::change_request::CHANGE_PENDING;
+use crate::models::{ChangeRequest, DemographicChanges};
+use crate::Result;
+use super::models::{DbChangeRequest, NewDbChangeRequest};
+use super::schema::change_requests;
+
+/// Change request repository trait
+pub trait ChangeRequestRepository: Send + Sync {
+    /// Record a proposed correction to a patient
+    fn submit(
+        &self,
+        patient_id: Uuid,
+        changes: &DemographicChanges,
+        reason: Option<String>,
+        submitted_by: Option<String>,
+    ) -> Result<ChangeRequest>;
+
+    /// Get a change request by ID
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<ChangeRequest>>;
+
+    /// List requests, optionally only those in one status or for one
+    /// patient, oldest first
+    fn list(&self, status: Option<&str>, patient_id: Option<Uuid>, limit: i64, offset: i64) -> Result<Vec<ChangeRequest>>;
+
+    /// Close a pending request as approved or rejected, returning whether it
+    /// was still pending
+    fn resolve(&self, id: &Uuid, status: &str, reviewed_by: Option<String>, note: Option<String>) -> Result<bool>;
+}
+
+/// Diesel-based change request repository implementation
+pub struct DieselChangeRequestRepository {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl DieselChangeRequestRepository {
+    /// Create a new repository with the given connection pool
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+
+    /// Get a database connection from the pool
+    fn get_conn(&self) -> Result<diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>> {
+        self.pool.get().map_err(|e| crate::Error::Pool(e.to_string()))
+    }
+
+    /// Convert a database record to the domain model
+    fn to_request(db_request: DbChangeRequest) -> Result<ChangeRequest> {
+        let changes = serde_json::from_value(db_request.changes)
+            .map_err(|e| crate::Error::Internal(format!("Invalid change request changes: {}", e)))?;
+        Ok(ChangeRequest {
+            id: db_request.id,
+            patient_id: db_request.patient_id,
+            changes,
+            reason: db_request.reason,
+            submitted_by: db_request.submitted_by,
+            status: db_request.status,
+            submitted_at: db_request.submitted_at,
+            reviewed_by: db_request.reviewed_by,
+            reviewed_at: db_request.reviewed_at,
+            review_note: db_request.review_note,
+        })
+    }
+}
+
+impl ChangeRequestRepository for DieselChangeRequestRepository {
+    fn submit(
+        &self,
+        patient_id: Uuid,
+        changes: &DemographicChanges,
+        reason: Option<String>,
+        submitted_by: Option<String>,
+    ) -> Result<ChangeRequest> {
+        let mut conn = self.get_conn()?;
+
+        let new_request = NewDbChangeRequest {
+            patient_id,
+            changes: serde_json::to_value(changes)
+                .map_err(|e| crate::Error::Internal(format!("Failed to serialize changes: {}", e)))?,
+            reason,
+            submitted_by,
+        };
+
+        let db_request: DbChangeRequest = diesel::insert_into(change_requests::table)
+            .values(&new_request)
+            .returning(DbChangeRequest::as_returning())
+            .get_result(&mut conn)?;
+
+        Self::to_request(db_request)
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<ChangeRequest>> {
+        let mut conn = self.get_conn()?;
+
+        let db_request = change_requests::table
+            .find(id)
+            .select(DbChangeRequest::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        db_request.map(Self::to_request).transpose()
+    }
+
+    fn list(&self, status: Option<&str>, patient_id: Option<Uuid>, limit: i64, offset: i64) -> Result<Vec<ChangeRequest>> {
+        let mut conn = self.get_conn()?;
+
+        let mut query = change_requests::table
+            .select(DbChangeRequest::as_select())
+            .into_boxed();
+        if let Some(status) = status {
+            query = query.filter(change_requests::status.eq(status));
+        }
+        if let Some(patient_id) = patient_id {
+            query = query.filter(change_requests::patient_id.eq(patient_id));
+        }
+        let db_requests = query
+            .order((change_requests::submitted_at.asc(), change_requests::id.asc()))
+            .limit(limit)
+            .offset(offset)
+            .load(&mut conn)?;
+
+        db_requests.into_iter().map(Self::to_request).collect()
+    }
+
+    fn resolve(&self, id: &Uuid, status: &str, reviewed_by: Option<String>, note: Option<String>) -> Result<bool> {
+        let mut conn = self.get_conn()?;
+
+        let updated = diesel::update(
+            change_requests::table
+                .find(id)
+                .filter(change_requests::status.eq(CHANGE_PENDING)),
+        )
+        .set((
+            change_requests::status.eq(status),
+            change_requests::reviewed_by.eq(reviewed_by),
+            change_requests::reviewed_at.eq(Some(Utc::now())),
+            change_requests::review_note.eq(note),
+        ))
+        .execute(&mut conn)?;
+
+        Ok(updated > 0)
+    }
+}
@@ -0,0 +1,223 @@
+//! Duplicate review queue repository
+
+use bigdecimal::{BigDecimal, ToPrimitive};
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::models::duplicate_candidate::PENDING_REVIEW;
+use crate::models::{DuplicateCandidate, ReviewAction, ReviewDecision, ReviewReason};
+use crate::{Error, Result};
+use super::models::{DbDuplicateCandidate, DbDuplicateDecision, NewDbDuplicateCandidate};
+use super::schema::{duplicate_candidates, duplicate_decisions};
+
+/// Duplicate review queue repository trait
+pub trait DuplicateCandidateRepository: Send + Sync {
+    /// Queue a pair for review, in either order
+    ///
+    /// Returns `None` if the pair is already queued or has been reviewed.
+    fn enqueue(&self, patient_id: &Uuid, candidate_id: &Uuid, score: f64) -> Result<Option<DuplicateCandidate>>;
+
+    /// List pairs awaiting review, highest score first
+    fn list_pending(&self, limit: i64, offset: i64) -> Result<Vec<DuplicateCandidate>>;
+
+    /// Number of pairs awaiting review
+    fn count_pending(&self) -> Result<i64>;
+
+    /// List the pairs awaiting review that involve a patient, highest score first
+    fn list_pending_for_patient(&self, patient_id: &Uuid) -> Result<Vec<DuplicateCandidate>>;
+
+    /// Get a queue entry by ID
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<DuplicateCandidate>>;
+
+    /// Record a decision and move the pair to the status it leads to
+    ///
+    /// Returns false, recording nothing, if the pair is no longer in
+    /// `from_status` because another steward got there first.
+    fn decide(&self, id: &Uuid, from_status: &str, decision: &ReviewDecision) -> Result<bool>;
+
+    /// A pair's decisions, oldest first
+    fn decisions(&self, id: &Uuid) -> Result<Vec<ReviewDecision>>;
+
+    /// List confirmed and rejected pairs, oldest first
+    fn list_decided(&self, limit: i64, offset: i64) -> Result<Vec<DuplicateCandidate>>;
+}
+
+/// Diesel-based duplicate review queue implementation
+pub struct DieselDuplicateCandidateRepository {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl DieselDuplicateCandidateRepository {
+    /// Create a new repository with the given connection pool
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+
+    /// Get a database connection from the pool
+    fn get_conn(&self) -> Result<diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>> {
+        self.pool.get().map_err(|e| Error::Pool(e.to_string()))
+    }
+
+    /// Convert a database queue entry to the domain model
+    fn to_candidate(db_candidate: DbDuplicateCandidate) -> DuplicateCandidate {
+        DuplicateCandidate {
+            id: db_candidate.id,
+            patient_id: db_candidate.patient_id,
+            candidate_id: db_candidate.candidate_id,
+            score: db_candidate.score.to_f64().unwrap_or(0.0),
+            status: db_candidate.status,
+            detected_at: db_candidate.detected_at,
+            photo_similarity: None,
+        }
+    }
+
+    /// Convert a database decision to the domain model
+    fn to_decision(db_decision: DbDuplicateDecision) -> Result<ReviewDecision> {
+        let action = ReviewAction::parse(&db_decision.action)
+            .ok_or_else(|| Error::Internal(format!("Invalid review action '{}'", db_decision.action)))?;
+        let reason_code = ReviewReason::parse(&db_decision.reason_code)
+            .ok_or_else(|| Error::Internal(format!("Invalid review reason '{}'", db_decision.reason_code)))?;
+        Ok(ReviewDecision {
+            id: db_decision.id,
+            duplicate_candidate_id: db_decision.duplicate_candidate_id,
+            action,
+            reason_code,
+            reason: db_decision.reason,
+            decided_by: db_decision.decided_by,
+            decided_at: db_decision.decided_at,
+        })
+    }
+}
+
+impl DuplicateCandidateRepository for DieselDuplicateCandidateRepository {
+    fn enqueue(&self, patient_id: &Uuid, candidate_id: &Uuid, score: f64) -> Result<Option<DuplicateCandidate>> {
+        let mut conn = self.get_conn()?;
+
+        let (patient_id, candidate_id) = DuplicateCandidate::ordered(*patient_id, *candidate_id);
+        let new_candidate = NewDbDuplicateCandidate {
+            patient_id,
+            candidate_id,
+            score: BigDecimal::try_from(score)
+                .map_err(|e| Error::Validation(format!("Invalid match score: {}", e)))?,
+        };
+
+        let db_candidate = diesel::insert_into(duplicate_candidates::table)
+            .values(&new_candidate)
+            .on_conflict((duplicate_candidates::patient_id, duplicate_candidates::candidate_id))
+            .do_nothing()
+            .returning(DbDuplicateCandidate::as_returning())
+            .get_result(&mut conn)
+            .optional()?;
+
+        Ok(db_candidate.map(Self::to_candidate))
+    }
+
+    fn list_pending(&self, limit: i64, offset: i64) -> Result<Vec<DuplicateCandidate>> {
+        let mut conn = self.get_conn()?;
+
+        let db_candidates = duplicate_candidates::table
+            .filter(duplicate_candidates::status.eq(PENDING_REVIEW))
+            .order((duplicate_candidates::score.desc(), duplicate_candidates::detected_at.asc()))
+            .limit(limit)
+            .offset(offset)
+            .select(DbDuplicateCandidate::as_select())
+            .load(&mut conn)?;
+
+        Ok(db_candidates.into_iter().map(Self::to_candidate).collect())
+    }
+
+    fn count_pending(&self) -> Result<i64> {
+        let mut conn = self.get_conn()?;
+        Ok(duplicate_candidates::table
+            .filter(duplicate_candidates::status.eq(PENDING_REVIEW))
+            .count()
+            .get_result(&mut conn)?)
+    }
+
+    fn list_pending_for_patient(&self, patient_id: &Uuid) -> Result<Vec<DuplicateCandidate>> {
+        let mut conn = self.get_conn()?;
+
+        let db_candidates = duplicate_candidates::table
+            .filter(duplicate_candidates::status.eq(PENDING_REVIEW))
+            .filter(
+                duplicate_candidates::patient_id.eq(patient_id)
+                    .or(duplicate_candidates::candidate_id.eq(patient_id))
+            )
+            .order(duplicate_candidates::score.desc())
+            .select(DbDuplicateCandidate::as_select())
+            .load(&mut conn)?;
+
+        Ok(db_candidates.into_iter().map(Self::to_candidate).collect())
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<DuplicateCandidate>> {
+        let mut conn = self.get_conn()?;
+
+        let db_candidate = duplicate_candidates::table
+            .find(id)
+            .select(DbDuplicateCandidate::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(db_candidate.map(Self::to_candidate))
+    }
+
+    fn decide(&self, id: &Uuid, from_status: &str, decision: &ReviewDecision) -> Result<bool> {
+        let mut conn = self.get_conn()?;
+
+        conn.transaction(|conn| {
+            let updated = diesel::update(
+                duplicate_candidates::table
+                    .find(id)
+                    .filter(duplicate_candidates::status.eq(from_status)),
+            )
+            .set(duplicate_candidates::status.eq(decision.action.status()))
+            .execute(conn)?;
+            if updated == 0 {
+                return Ok(false);
+            }
+
+            let db_decision = DbDuplicateDecision {
+                id: decision.id,
+                duplicate_candidate_id: *id,
+                action: decision.action.as_str().to_string(),
+                reason_code: decision.reason_code.as_str().to_string(),
+                reason: decision.reason.clone(),
+                decided_by: decision.decided_by.clone(),
+                decided_at: decision.decided_at,
+            };
+            diesel::insert_into(duplicate_decisions::table)
+                .values(&db_decision)
+                .execute(conn)?;
+            Ok(true)
+        })
+    }
+
+    fn decisions(&self, id: &Uuid) -> Result<Vec<ReviewDecision>> {
+        let mut conn = self.get_conn()?;
+
+        let db_decisions = duplicate_decisions::table
+            .filter(duplicate_decisions::duplicate_candidate_id.eq(id))
+            .order((duplicate_decisions::decided_at.asc(), duplicate_decisions::id.asc()))
+            .select(DbDuplicateDecision::as_select())
+            .load(&mut conn)?;
+
+        db_decisions.into_iter().map(Self::to_decision).collect()
+    }
+
+    fn list_decided(&self, limit: i64, offset: i64) -> Result<Vec<DuplicateCandidate>> {
+        let mut conn = self.get_conn()?;
+
+        let db_candidates = duplicate_candidates::table
+            .filter(duplicate_candidates::status.ne(PENDING_REVIEW))
+            .order((duplicate_candidates::detected_at.asc(), duplicate_candidates::id.asc()))
+            .limit(limit)
+            .offset(offset)
+            .select(DbDuplicateCandidate::as_select())
+            .load(&mut conn)?;
+
+        Ok(db_candidates.into_iter().map(Self::to_candidate).collect())
+    }
+}
@@ -0,0 +1,90 @@
+//! Field provenance repository for master record demographics
+
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::upsert::excluded;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::models::FieldProvenance;
+use crate::Result;
+use super::models::DbFieldProvenance;
+use super::schema::patient_field_provenance;
+
+/// Field provenance repository trait
+pub trait FieldProvenanceRepository: Send + Sync {
+    /// Provenance of each of a patient's fields that has any
+    fn list_for_patient(&self, patient_id: &Uuid) -> Result<Vec<FieldProvenance>>;
+
+    /// Record `source` as having just supplied `fields` of a patient
+    fn record(&self, patient_id: &Uuid, fields: &[&str], source: &str) -> Result<()>;
+}
+
+/// Diesel-based field provenance repository implementation
+pub struct DieselFieldProvenanceRepository {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl DieselFieldProvenanceRepository {
+    /// Create a new repository with the given connection pool
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+
+    /// Get a database connection from the pool
+    fn get_conn(&self) -> Result<diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>> {
+        self.pool.get().map_err(|e| crate::Error::Pool(e.to_string()))
+    }
+}
+
+impl FieldProvenanceRepository for DieselFieldProvenanceRepository {
+    fn list_for_patient(&self, patient_id: &Uuid) -> Result<Vec<FieldProvenance>> {
+        let mut conn = self.get_conn()?;
+
+        let rows = patient_field_provenance::table
+            .filter(patient_field_provenance::patient_id.eq(patient_id))
+            .select(DbFieldProvenance::as_select())
+            .order(patient_field_provenance::field.asc())
+            .load(&mut conn)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| FieldProvenance {
+                field: row.field,
+                source: row.source,
+                recorded_at: row.recorded_at,
+            })
+            .collect())
+    }
+
+    fn record(&self, patient_id: &Uuid, fields: &[&str], source: &str) -> Result<()> {
+        if fields.is_empty() {
+            return Ok(());
+        }
+        let mut conn = self.get_conn()?;
+
+        let now = Utc::now();
+        let rows: Vec<DbFieldProvenance> = fields
+            .iter()
+            .map(|field| DbFieldProvenance {
+                patient_id: *patient_id,
+                field: field.to_string(),
+                source: source.to_string(),
+                recorded_at: now,
+            })
+            .collect();
+
+        diesel::insert_into(patient_field_provenance::table)
+            .values(&rows)
+            .on_conflict((patient_field_provenance::patient_id, patient_field_provenance::field))
+            .do_update()
+            .set((
+                patient_field_provenance::source.eq(excluded(patient_field_provenance::source)),
+                patient_field_provenance::recorded_at.eq(excluded(patient_field_provenance::recorded_at)),
+            ))
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+}
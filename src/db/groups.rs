@@ -0,0 +1,230 @@
+//! Patient group repository
+
+use std::collections::HashMap;
+
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::models::PatientGroup;
+use crate::{Error, Result};
+use super::models::{DbPatientGroup, DbPatientGroupMember};
+use super::schema::{patient_group_members, patient_groups};
+
+/// Patient group repository trait
+pub trait PatientGroupRepository: Send + Sync {
+    /// Create a group with its members
+    fn create(&self, group: &PatientGroup) -> Result<PatientGroup>;
+
+    /// Get a group by ID
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<PatientGroup>>;
+
+    /// Replace a group's name, description and members, returning `None` if
+    /// it does not exist
+    ///
+    /// Patients that stay members keep their place in the group.
+    fn update(&self, group: &PatientGroup) -> Result<Option<PatientGroup>>;
+
+    /// Delete a group, returning whether it existed
+    fn delete(&self, id: &Uuid) -> Result<bool>;
+
+    /// List groups, newest first
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<PatientGroup>>;
+
+    /// Add patients to a group, skipping current members, returning `None`
+    /// if the group does not exist
+    fn add_members(&self, id: &Uuid, patient_ids: &[Uuid]) -> Result<Option<PatientGroup>>;
+
+    /// Remove a patient from a group, returning whether it was a member
+    fn remove_member(&self, id: &Uuid, patient_id: &Uuid) -> Result<bool>;
+}
+
+/// Diesel-based patient group repository implementation
+pub struct DieselPatientGroupRepository {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl DieselPatientGroupRepository {
+    /// Create a new repository with the given connection pool
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+
+    /// Get a database connection from the pool
+    fn get_conn(&self) -> Result<diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>> {
+        self.pool.get().map_err(|e| Error::Pool(e.to_string()))
+    }
+
+    /// Convert a database group and its member IDs to the domain model
+    fn to_group(db_group: DbPatientGroup, members: Vec<Uuid>) -> PatientGroup {
+        PatientGroup {
+            id: db_group.id,
+            name: db_group.name,
+            description: db_group.description,
+            members,
+            created_by: db_group.created_by,
+            created_at: db_group.created_at,
+            updated_at: db_group.updated_at,
+        }
+    }
+
+    /// Load a group and its members, earliest added first
+    fn load(conn: &mut PgConnection, id: &Uuid) -> QueryResult<Option<PatientGroup>> {
+        let Some(db_group) = patient_groups::table
+            .find(id)
+            .select(DbPatientGroup::as_select())
+            .first(conn)
+            .optional()?
+        else {
+            return Ok(None);
+        };
+        let members = patient_group_members::table
+            .filter(patient_group_members::group_id.eq(id))
+            .order((patient_group_members::added_at.asc(), patient_group_members::patient_id.asc()))
+            .select(patient_group_members::patient_id)
+            .load(conn)?;
+        Ok(Some(Self::to_group(db_group, members)))
+    }
+
+    /// Insert memberships that do not exist yet
+    ///
+    /// Each is stamped a microsecond after the one before, so members come
+    /// back in the order given.
+    fn insert_members(conn: &mut PgConnection, id: &Uuid, patient_ids: &[Uuid]) -> QueryResult<usize> {
+        let now = Utc::now();
+        let rows: Vec<DbPatientGroupMember> = patient_ids
+            .iter()
+            .enumerate()
+            .map(|(i, patient_id)| DbPatientGroupMember {
+                group_id: *id,
+                patient_id: *patient_id,
+                added_at: now + chrono::Duration::microseconds(i as i64),
+            })
+            .collect();
+        diesel::insert_into(patient_group_members::table)
+            .values(&rows)
+            .on_conflict_do_nothing()
+            .execute(conn)
+    }
+}
+
+impl PatientGroupRepository for DieselPatientGroupRepository {
+    fn create(&self, group: &PatientGroup) -> Result<PatientGroup> {
+        let mut conn = self.get_conn()?;
+
+        let created = conn.transaction(|conn| {
+            diesel::insert_into(patient_groups::table)
+                .values(&DbPatientGroup {
+                    id: group.id,
+                    name: group.name.clone(),
+                    description: group.description.clone(),
+                    created_by: group.created_by.clone(),
+                    created_at: group.created_at,
+                    updated_at: group.updated_at,
+                })
+                .execute(conn)?;
+            Self::insert_members(conn, &group.id, &group.members)?;
+            Self::load(conn, &group.id)
+        })?;
+
+        created.ok_or_else(|| Error::Internal(format!("Group {} was not stored", group.id)))
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<PatientGroup>> {
+        let mut conn = self.get_conn()?;
+        Ok(Self::load(&mut conn, id)?)
+    }
+
+    fn update(&self, group: &PatientGroup) -> Result<Option<PatientGroup>> {
+        let mut conn = self.get_conn()?;
+
+        Ok(conn.transaction(|conn| {
+            let updated = diesel::update(patient_groups::table.find(group.id))
+                .set((
+                    patient_groups::name.eq(&group.name),
+                    patient_groups::description.eq(&group.description),
+                    patient_groups::updated_at.eq(Utc::now()),
+                ))
+                .execute(conn)?;
+            if updated == 0 {
+                return Ok(None);
+            }
+
+            diesel::delete(
+                patient_group_members::table
+                    .filter(patient_group_members::group_id.eq(group.id))
+                    .filter(patient_group_members::patient_id.ne_all(&group.members)),
+            )
+            .execute(conn)?;
+            Self::insert_members(conn, &group.id, &group.members)?;
+            Self::load(conn, &group.id)
+        })?)
+    }
+
+    fn delete(&self, id: &Uuid) -> Result<bool> {
+        let mut conn = self.get_conn()?;
+        let deleted = diesel::delete(patient_groups::table.find(id)).execute(&mut conn)?;
+        Ok(deleted > 0)
+    }
+
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<PatientGroup>> {
+        let mut conn = self.get_conn()?;
+
+        let db_groups: Vec<DbPatientGroup> = patient_groups::table
+            .order((patient_groups::created_at.desc(), patient_groups::id.asc()))
+            .limit(limit)
+            .offset(offset)
+            .select(DbPatientGroup::as_select())
+            .load(&mut conn)?;
+
+        let ids: Vec<Uuid> = db_groups.iter().map(|group| group.id).collect();
+        let memberships: Vec<(Uuid, Uuid)> = patient_group_members::table
+            .filter(patient_group_members::group_id.eq_any(&ids))
+            .order((patient_group_members::added_at.asc(), patient_group_members::patient_id.asc()))
+            .select((patient_group_members::group_id, patient_group_members::patient_id))
+            .load(&mut conn)?;
+        let mut members: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for (group_id, patient_id) in memberships {
+            members.entry(group_id).or_default().push(patient_id);
+        }
+
+        Ok(db_groups
+            .into_iter()
+            .map(|db_group| {
+                let group_members = members.remove(&db_group.id).unwrap_or_default();
+                Self::to_group(db_group, group_members)
+            })
+            .collect())
+    }
+
+    fn add_members(&self, id: &Uuid, patient_ids: &[Uuid]) -> Result<Option<PatientGroup>> {
+        let mut conn = self.get_conn()?;
+
+        Ok(conn.transaction(|conn| {
+            let updated = diesel::update(patient_groups::table.find(id))
+                .set(patient_groups::updated_at.eq(Utc::now()))
+                .execute(conn)?;
+            if updated == 0 {
+                return Ok(None);
+            }
+            Self::insert_members(conn, id, patient_ids)?;
+            Self::load(conn, id)
+        })?)
+    }
+
+    fn remove_member(&self, id: &Uuid, patient_id: &Uuid) -> Result<bool> {
+        let mut conn = self.get_conn()?;
+
+        Ok(conn.transaction(|conn| {
+            let removed = diesel::delete(patient_group_members::table.find((id, patient_id))).execute(conn)?;
+            if removed > 0 {
+                diesel::update(patient_groups::table.find(id))
+                    .set(patient_groups::updated_at.eq(Utc::now()))
+                    .execute(conn)?;
+            }
+            Ok::<_, diesel::result::Error>(removed > 0)
+        })?)
+    }
+}
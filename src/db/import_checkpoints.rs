@@ -0,0 +1,117 @@
+//! Bulk import checkpoint repository
+
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::models::ImportCheckpoint;
+use crate::{Error, Result};
+use super::models::DbImportCheckpoint;
+use super::schema::import_checkpoints;
+
+/// Bulk import checkpoint repository trait
+pub trait ImportCheckpointRepository: Send + Sync {
+    /// Store a checkpoint, replacing the import's previous one, and return
+    /// it with `updated_at` set
+    fn save(&self, checkpoint: &ImportCheckpoint) -> Result<ImportCheckpoint>;
+
+    /// Get an import's checkpoint
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<ImportCheckpoint>>;
+
+    /// List imports, newest first
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<ImportCheckpoint>>;
+}
+
+/// Diesel-based bulk import checkpoint implementation
+pub struct DieselImportCheckpointRepository {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl DieselImportCheckpointRepository {
+    /// Create a new repository with the given connection pool
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+
+    /// Get a database connection from the pool
+    fn get_conn(&self) -> Result<diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>> {
+        self.pool.get().map_err(|e| Error::Pool(e.to_string()))
+    }
+
+    /// Convert a database record to the domain model
+    fn to_checkpoint(db_checkpoint: DbImportCheckpoint) -> ImportCheckpoint {
+        ImportCheckpoint {
+            id: db_checkpoint.id,
+            path: db_checkpoint.path,
+            requested_by: db_checkpoint.requested_by,
+            status: db_checkpoint.status,
+            job_id: db_checkpoint.job_id,
+            byte_offset: db_checkpoint.byte_offset,
+            lines_processed: db_checkpoint.lines_processed,
+            created: db_checkpoint.created,
+            duplicates: db_checkpoint.duplicates,
+            invalid: db_checkpoint.invalid,
+            error: db_checkpoint.error,
+            created_at: db_checkpoint.created_at,
+            updated_at: db_checkpoint.updated_at,
+        }
+    }
+}
+
+impl ImportCheckpointRepository for DieselImportCheckpointRepository {
+    fn save(&self, checkpoint: &ImportCheckpoint) -> Result<ImportCheckpoint> {
+        let mut conn = self.get_conn()?;
+
+        let row = DbImportCheckpoint {
+            id: checkpoint.id,
+            path: checkpoint.path.clone(),
+            requested_by: checkpoint.requested_by.clone(),
+            status: checkpoint.status.clone(),
+            job_id: checkpoint.job_id,
+            byte_offset: checkpoint.byte_offset,
+            lines_processed: checkpoint.lines_processed,
+            created: checkpoint.created,
+            duplicates: checkpoint.duplicates,
+            invalid: checkpoint.invalid,
+            error: checkpoint.error.clone(),
+            created_at: checkpoint.created_at,
+            updated_at: Utc::now(),
+        };
+        let db_checkpoint = diesel::insert_into(import_checkpoints::table)
+            .values(&row)
+            .on_conflict(import_checkpoints::id)
+            .do_update()
+            .set(&row)
+            .returning(DbImportCheckpoint::as_returning())
+            .get_result(&mut conn)?;
+
+        Ok(Self::to_checkpoint(db_checkpoint))
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<ImportCheckpoint>> {
+        let mut conn = self.get_conn()?;
+
+        let db_checkpoint = import_checkpoints::table
+            .find(id)
+            .select(DbImportCheckpoint::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(db_checkpoint.map(Self::to_checkpoint))
+    }
+
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<ImportCheckpoint>> {
+        let mut conn = self.get_conn()?;
+
+        let db_checkpoints = import_checkpoints::table
+            .order(import_checkpoints::created_at.desc())
+            .limit(limit)
+            .offset(offset)
+            .select(DbImportCheckpoint::as_select())
+            .load(&mut conn)?;
+
+        Ok(db_checkpoints.into_iter().map(Self::to_checkpoint).collect())
+    }
+}
@@ -0,0 +1,233 @@
+//! Queries behind the data integrity check
+//!
+//! Each query finds rows the schema constraints allow but the rest of the
+//! code does not expect. The checks and repairs themselves are in
+//! [`crate::jobs::integrity`].
+
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::sql_types::{BigInt, Nullable, Text, Uuid as SqlUuid};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::{Error, Result};
+use super::schema::{patient_addresses, patient_contacts, patient_identifiers, patient_links, patient_names, patients};
+
+/// Live patients without a primary name, which cannot be loaded
+const MISSING_PRIMARY_NAME_SQL: &str = "
+    SELECT p.id, COUNT(n.id) AS names
+    FROM patients p
+    LEFT JOIN patient_names n ON n.patient_id = p.id
+    WHERE p.deleted_at IS NULL
+    GROUP BY p.id
+    HAVING NOT COALESCE(bool_or(n.is_primary), false)
+    ORDER BY p.id
+    LIMIT $1";
+
+/// Make a patient's official name primary, else its oldest one
+const PROMOTE_NAME_SQL: &str = "
+    UPDATE patient_names
+    SET is_primary = true, updated_at = now()
+    WHERE id = (
+        SELECT id FROM patient_names
+        WHERE patient_id = $1
+        ORDER BY use_type IS DISTINCT FROM 'Official', created_at, id
+        LIMIT 1
+    )";
+
+/// Links from live patients to soft-deleted ones
+///
+/// A survivor's `Replaces` link records a merge and stays meaningful after
+/// the merged-away record is deleted, so those are left out.
+const DANGLING_LINKS_SQL: &str = "
+    SELECT l.id, l.patient_id, l.other_patient_id, l.link_type
+    FROM patient_links l
+    JOIN patients p ON p.id = l.patient_id AND p.deleted_at IS NULL
+    JOIN patients o ON o.id = l.other_patient_id
+    WHERE o.deleted_at IS NOT NULL
+      AND l.link_type <> 'Replaces'
+    ORDER BY l.patient_id, l.id
+    LIMIT $1";
+
+/// Child rows whose patient row no longer exists
+///
+/// The foreign keys cascade deletes, so these only appear after bulk loads
+/// or restores made with the constraints disabled.
+const ORPHANED_ROWS_SQL: &str = "
+    SELECT * FROM (
+        SELECT 'patient_names' AS table_name, c.id, c.patient_id
+        FROM patient_names c
+        WHERE NOT EXISTS (SELECT 1 FROM patients p WHERE p.id = c.patient_id)
+        UNION ALL
+        SELECT 'patient_identifiers', c.id, c.patient_id
+        FROM patient_identifiers c
+        WHERE NOT EXISTS (SELECT 1 FROM patients p WHERE p.id = c.patient_id)
+        UNION ALL
+        SELECT 'patient_addresses', c.id, c.patient_id
+        FROM patient_addresses c
+        WHERE NOT EXISTS (SELECT 1 FROM patients p WHERE p.id = c.patient_id)
+        UNION ALL
+        SELECT 'patient_contacts', c.id, c.patient_id
+        FROM patient_contacts c
+        WHERE NOT EXISTS (SELECT 1 FROM patients p WHERE p.id = c.patient_id)
+        UNION ALL
+        SELECT 'patient_links', c.id, c.patient_id
+        FROM patient_links c
+        WHERE NOT EXISTS (SELECT 1 FROM patients p WHERE p.id = c.patient_id)
+           OR NOT EXISTS (SELECT 1 FROM patients p WHERE p.id = c.other_patient_id)
+    ) orphans
+    LIMIT $1";
+
+/// MRNs of live patients whose trimmed value another live patient also holds
+///
+/// Values are compared across all systems here; whether two systems belong
+/// to the same assigning authority is decided with the registry.
+const SHARED_MRNS_SQL: &str = "
+    WITH mrns AS (
+        SELECT i.patient_id, i.system, i.assigner, btrim(i.value) AS value
+        FROM patient_identifiers i
+        JOIN patients p ON p.id = i.patient_id AND p.deleted_at IS NULL
+        WHERE i.identifier_type = 'MRN'
+    )
+    SELECT m.patient_id, m.system, m.assigner, m.value
+    FROM mrns m
+    WHERE m.value IN (
+        SELECT value FROM mrns GROUP BY value HAVING COUNT(DISTINCT patient_id) > 1
+    )
+    ORDER BY m.value, m.patient_id
+    LIMIT $1";
+
+/// A live patient without a primary name
+#[derive(Debug, Clone, QueryableByName)]
+pub struct MissingPrimaryName {
+    #[diesel(sql_type = SqlUuid)]
+    pub id: Uuid,
+    /// Name rows the patient has, none of them primary
+    #[diesel(sql_type = BigInt)]
+    pub names: i64,
+}
+
+/// A link from a live patient to a soft-deleted one
+#[derive(Debug, Clone, QueryableByName)]
+pub struct DanglingLink {
+    #[diesel(sql_type = SqlUuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = SqlUuid)]
+    pub patient_id: Uuid,
+    #[diesel(sql_type = SqlUuid)]
+    pub other_patient_id: Uuid,
+    #[diesel(sql_type = Text)]
+    pub link_type: String,
+}
+
+/// A child row without its patient row
+#[derive(Debug, Clone, QueryableByName)]
+pub struct OrphanedRow {
+    #[diesel(sql_type = Text)]
+    pub table_name: String,
+    #[diesel(sql_type = SqlUuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = SqlUuid)]
+    pub patient_id: Uuid,
+}
+
+/// An MRN whose value another live patient also holds
+#[derive(Debug, Clone, QueryableByName)]
+pub struct SharedMrn {
+    #[diesel(sql_type = SqlUuid)]
+    pub patient_id: Uuid,
+    #[diesel(sql_type = Text)]
+    pub system: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub assigner: Option<String>,
+    #[diesel(sql_type = Text)]
+    pub value: String,
+}
+
+/// Repository for the data integrity check
+pub struct IntegrityRepository {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl IntegrityRepository {
+    /// Create a new integrity repository
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+
+    /// Get a database connection from the pool
+    fn get_conn(&self) -> Result<diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>> {
+        self.pool.get().map_err(|e| Error::Pool(e.to_string()))
+    }
+
+    /// Live patients without a primary name, up to `limit`
+    pub fn missing_primary_names(&self, limit: i64) -> Result<Vec<MissingPrimaryName>> {
+        let mut conn = self.get_conn()?;
+        Ok(diesel::sql_query(MISSING_PRIMARY_NAME_SQL)
+            .bind::<BigInt, _>(limit)
+            .load(&mut conn)?)
+    }
+
+    /// Make one of a patient's names primary; false if it has none
+    pub fn promote_primary_name(&self, patient_id: &Uuid) -> Result<bool> {
+        let mut conn = self.get_conn()?;
+        let updated = diesel::sql_query(PROMOTE_NAME_SQL)
+            .bind::<SqlUuid, _>(patient_id)
+            .execute(&mut conn)?;
+        Ok(updated > 0)
+    }
+
+    /// Links from live patients to soft-deleted ones, up to `limit`
+    pub fn dangling_links(&self, limit: i64) -> Result<Vec<DanglingLink>> {
+        let mut conn = self.get_conn()?;
+        Ok(diesel::sql_query(DANGLING_LINKS_SQL)
+            .bind::<BigInt, _>(limit)
+            .load(&mut conn)?)
+    }
+
+    /// Remove a patient link row; false if it was already gone
+    pub fn delete_link(&self, link_id: &Uuid) -> Result<bool> {
+        let mut conn = self.get_conn()?;
+        let deleted = diesel::delete(patient_links::table.find(link_id)).execute(&mut conn)?;
+        Ok(deleted > 0)
+    }
+
+    /// Child rows without their patient row, up to `limit`
+    pub fn orphaned_rows(&self, limit: i64) -> Result<Vec<OrphanedRow>> {
+        let mut conn = self.get_conn()?;
+        Ok(diesel::sql_query(ORPHANED_ROWS_SQL)
+            .bind::<BigInt, _>(limit)
+            .load(&mut conn)?)
+    }
+
+    /// Remove an orphaned child row; false if it was already gone
+    pub fn delete_orphan(&self, row: &OrphanedRow) -> Result<bool> {
+        let mut conn = self.get_conn()?;
+        let deleted = match row.table_name.as_str() {
+            "patient_names" => diesel::delete(patient_names::table.find(row.id)).execute(&mut conn)?,
+            "patient_identifiers" => diesel::delete(patient_identifiers::table.find(row.id)).execute(&mut conn)?,
+            "patient_addresses" => diesel::delete(patient_addresses::table.find(row.id)).execute(&mut conn)?,
+            "patient_contacts" => diesel::delete(patient_contacts::table.find(row.id)).execute(&mut conn)?,
+            "patient_links" => diesel::delete(patient_links::table.find(row.id)).execute(&mut conn)?,
+            other => return Err(Error::Internal(format!("Unknown patient child table '{}'", other))),
+        };
+        Ok(deleted > 0)
+    }
+
+    /// MRNs whose value more than one live patient holds, up to `limit` rows
+    pub fn shared_mrns(&self, limit: i64) -> Result<Vec<SharedMrn>> {
+        let mut conn = self.get_conn()?;
+        Ok(diesel::sql_query(SHARED_MRNS_SQL)
+            .bind::<BigInt, _>(limit)
+            .load(&mut conn)?)
+    }
+
+    /// IDs of every live patient
+    pub fn live_patient_ids(&self) -> Result<Vec<Uuid>> {
+        let mut conn = self.get_conn()?;
+        Ok(patients::table
+            .filter(patients::deleted_at.is_null())
+            .select(patients::id)
+            .load(&mut conn)?)
+    }
+}
@@ -0,0 +1,153 @@
+//! Read-through caching of reference data
+//!
+//! Every registration checks its identifiers against the assigning authority
+//! registry, which changes a few times a year. [`CachedAssigningAuthorityRepository`]
+//! serves reads from one in-memory snapshot, reloaded when it is older than
+//! the configured time to live or after a write through it.
+
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::models::{AssigningAuthority, AuthorityRegistry};
+use crate::Result;
+use super::AssigningAuthorityRepository;
+
+/// Authorities as last loaded
+struct Snapshot {
+    loaded_at: Instant,
+    authorities: Arc<Vec<AssigningAuthority>>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    snapshot: Option<Snapshot>,
+    /// Bumped by every invalidation, so a load that began before a write
+    /// does not cache what it read
+    generation: u64,
+}
+
+/// Assigning authority repository serving reads from a snapshot
+pub struct CachedAssigningAuthorityRepository {
+    inner: Arc<dyn AssigningAuthorityRepository>,
+    ttl: Duration,
+    state: RwLock<CacheState>,
+}
+
+impl CachedAssigningAuthorityRepository {
+    /// Cache `inner`'s authorities for `ttl`
+    pub fn new(inner: Arc<dyn AssigningAuthorityRepository>, ttl: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            state: RwLock::new(CacheState::default()),
+        }
+    }
+
+    /// Drop the snapshot so the next read reloads it
+    pub fn invalidate(&self) {
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        state.snapshot = None;
+        state.generation += 1;
+    }
+
+    /// The cached authorities, loading them if stale
+    fn authorities(&self) -> Result<Arc<Vec<AssigningAuthority>>> {
+        let generation = {
+            let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+            if let Some(snapshot) = &state.snapshot {
+                if snapshot.loaded_at.elapsed() < self.ttl {
+                    return Ok(snapshot.authorities.clone());
+                }
+            }
+            state.generation
+        };
+
+        let authorities = Arc::new(self.inner.list()?);
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        if state.generation == generation {
+            state.snapshot = Some(Snapshot {
+                loaded_at: Instant::now(),
+                authorities: authorities.clone(),
+            });
+        }
+        Ok(authorities)
+    }
+}
+
+impl AssigningAuthorityRepository for CachedAssigningAuthorityRepository {
+    fn create(&self, system: String, oid: Option<String>, name: String, active: bool) -> Result<AssigningAuthority> {
+        let created = self.inner.create(system, oid, name, active);
+        self.invalidate();
+        created
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<AssigningAuthority>> {
+        Ok(self.authorities()?.iter().find(|authority| authority.id == *id).cloned())
+    }
+
+    fn list(&self) -> Result<Vec<AssigningAuthority>> {
+        Ok(self.authorities()?.as_ref().clone())
+    }
+
+    fn update(
+        &self,
+        id: &Uuid,
+        system: String,
+        oid: Option<String>,
+        name: String,
+        active: bool,
+    ) -> Result<Option<AssigningAuthority>> {
+        let updated = self.inner.update(id, system, oid, name, active);
+        self.invalidate();
+        updated
+    }
+
+    fn delete(&self, id: &Uuid) -> Result<bool> {
+        let deleted = self.inner.delete(id);
+        self.invalidate();
+        deleted
+    }
+
+    fn registry(&self) -> Result<AuthorityRegistry> {
+        Ok(AuthorityRegistry::new(self.list()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::InMemoryAssigningAuthorityRepository;
+
+    #[test]
+    fn test_reads_cached_until_written_through() {
+        let inner = Arc::new(InMemoryAssigningAuthorityRepository::new());
+        let cached = CachedAssigningAuthorityRepository::new(inner.clone(), Duration::from_secs(3600));
+
+        let general = cached.create("urn:oid:facility:GENERAL".to_string(), None, "General".to_string(), true).unwrap();
+        assert_eq!(cached.list().unwrap().len(), 1);
+
+        // A write behind the cache's back is not seen until the snapshot expires
+        inner.create("urn:oid:facility:EAST".to_string(), None, "East".to_string(), true).unwrap();
+        assert_eq!(cached.list().unwrap().len(), 1);
+
+        cached.update(&general.id, general.system.clone(), None, "General Hospital".to_string(), false).unwrap();
+        assert_eq!(cached.list().unwrap().len(), 2);
+        let reloaded = cached.get_by_id(&general.id).unwrap().unwrap();
+        assert_eq!(reloaded.name, "General Hospital");
+        assert!(!reloaded.active);
+
+        assert!(cached.delete(&general.id).unwrap());
+        assert!(cached.get_by_id(&general.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_zero_ttl_always_reloads() {
+        let inner = Arc::new(InMemoryAssigningAuthorityRepository::new());
+        let cached = CachedAssigningAuthorityRepository::new(inner.clone(), Duration::ZERO);
+        assert!(cached.list().unwrap().is_empty());
+        inner.create("urn:oid:facility:EAST".to_string(), None, "East".to_string(), true).unwrap();
+        assert_eq!(cached.list().unwrap().len(), 1);
+    }
+}
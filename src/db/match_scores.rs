@@ -0,0 +1,124 @@
+//! Match score repository for scored candidate pairs
+
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::matching::cache::{CachedScore, PairKey, PairScoreCache};
+use crate::matching::MatchScoreBreakdown;
+use crate::Result;
+use super::models::{DbPatientMatchScore, NewDbPatientMatchScore};
+use super::schema::patient_match_scores;
+
+/// Repository for previously scored patient/candidate pairs
+pub struct MatchScoreRepository {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl MatchScoreRepository {
+    /// Create a new match score repository
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+
+    /// Get a database connection from the pool
+    fn get_conn(&self) -> Result<diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>> {
+        self.pool.get().map_err(|e| crate::Error::Pool(e.to_string()))
+    }
+
+    /// List scored pairs, oldest first
+    pub fn list(&self, limit: i64, offset: i64) -> Result<Vec<DbPatientMatchScore>> {
+        let mut conn = self.get_conn()?;
+
+        let scores = patient_match_scores::table
+            .order(patient_match_scores::calculated_at.asc())
+            .limit(limit)
+            .offset(offset)
+            .load::<DbPatientMatchScore>(&mut conn)?;
+
+        Ok(scores)
+    }
+
+    /// Most recently scored pairs involving a patient, from either side
+    pub fn list_for_patient(&self, patient_id: &Uuid, limit: i64) -> Result<Vec<DbPatientMatchScore>> {
+        let mut conn = self.get_conn()?;
+
+        let scores = patient_match_scores::table
+            .filter(
+                patient_match_scores::patient_id.eq(patient_id)
+                    .or(patient_match_scores::candidate_id.eq(patient_id))
+            )
+            .order(patient_match_scores::calculated_at.desc())
+            .limit(limit)
+            .load::<DbPatientMatchScore>(&mut conn)?;
+
+        Ok(scores)
+    }
+}
+
+impl PairScoreCache for MatchScoreRepository {
+    fn get(&self, key: &PairKey) -> Result<Option<CachedScore>> {
+        let mut conn = self.get_conn()?;
+
+        let score = patient_match_scores::table
+            .filter(patient_match_scores::patient_id.eq(key.patient_id))
+            .filter(patient_match_scores::candidate_id.eq(key.candidate_id))
+            .filter(patient_match_scores::algorithm_version.eq(&key.algorithm_version))
+            .filter(patient_match_scores::config_hash.eq(&key.config_hash))
+            .filter(patient_match_scores::patient_updated_at.eq(key.patient_updated_at))
+            .filter(patient_match_scores::candidate_updated_at.eq(key.candidate_updated_at))
+            .first::<DbPatientMatchScore>(&mut conn)
+            .optional()?;
+
+        let component = |value: Option<BigDecimal>| value.and_then(|v| v.to_f64()).unwrap_or(0.0);
+        Ok(score.map(|score| CachedScore {
+            score: score.total_score.to_f64().unwrap_or(0.0),
+            breakdown: MatchScoreBreakdown {
+                name_score: component(score.name_score),
+                birth_date_score: component(score.birth_date_score),
+                gender_score: component(score.gender_score),
+                address_score: component(score.address_score),
+                identifier_score: component(score.identifier_score),
+                names_transposed: false,
+                photo_score: None,
+            },
+        }))
+    }
+
+    fn put(&self, key: &PairKey, score: &CachedScore) -> Result<()> {
+        let mut conn = self.get_conn()?;
+
+        let decimal = |value: f64| {
+            BigDecimal::try_from(value)
+                .map_err(|e| crate::Error::Validation(format!("Invalid match score: {}", e)))
+        };
+        let breakdown = &score.breakdown;
+        let row = NewDbPatientMatchScore {
+            patient_id: key.patient_id,
+            candidate_id: key.candidate_id,
+            total_score: decimal(score.score)?,
+            name_score: Some(decimal(breakdown.name_score)?),
+            birth_date_score: Some(decimal(breakdown.birth_date_score)?),
+            gender_score: Some(decimal(breakdown.gender_score)?),
+            address_score: Some(decimal(breakdown.address_score)?),
+            identifier_score: Some(decimal(breakdown.identifier_score)?),
+            calculated_at: Utc::now(),
+            algorithm_version: Some(key.algorithm_version.clone()),
+            config_hash: Some(key.config_hash.clone()),
+            patient_updated_at: Some(key.patient_updated_at),
+            candidate_updated_at: Some(key.candidate_updated_at),
+        };
+
+        diesel::insert_into(patient_match_scores::table)
+            .values(&row)
+            .on_conflict((patient_match_scores::patient_id, patient_match_scores::candidate_id))
+            .do_update()
+            .set(&row)
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,210 @@
+//! Matching KPI repository: daily aggregates and their persistence
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::sql_types::{BigInt, Double, Nullable, Text, Timestamptz};
+use diesel::PgConnection;
+
+use crate::reporting::{DailyMatchingKpis, KpiCounts};
+use crate::Result;
+use super::models::DbMatchingKpis;
+use super::schema::matching_kpis_daily;
+
+/// Source records received in the window
+const RECEIVED_SQL: &str = "
+    SELECT source_system, COUNT(*) AS count
+    FROM source_records
+    WHERE received_at >= $1 AND received_at < $2
+    GROUP BY source_system";
+
+/// Links made in the window. A link is a new patient when it is the first
+/// link to its enterprise record, and a duplicate when that record has since
+/// been merged away.
+const LINKS_SQL: &str = "
+    SELECT r.source_system,
+           COUNT(*) FILTER (WHERE l.linked_by IS NULL OR l.linked_by = $3) AS auto_links,
+           COUNT(*) FILTER (WHERE l.linked_by <> $3) AS manual_links,
+           AVG(l.match_score)::float8 AS average_match_score,
+           COUNT(*) FILTER (WHERE first_link) AS new_patients,
+           COUNT(*) FILTER (WHERE first_link AND EXISTS (
+               SELECT 1 FROM patient_links pl
+               WHERE pl.patient_id = l.patient_id AND pl.link_type = 'ReplacedBy'
+           )) AS duplicates_created
+    FROM (
+        SELECT l.*, NOT EXISTS (
+            SELECT 1 FROM source_record_links e
+            WHERE e.patient_id = l.patient_id AND e.linked_at < l.linked_at
+        ) AS first_link
+        FROM source_record_links l
+        WHERE l.linked_at >= $1 AND l.linked_at < $2
+    ) l
+    JOIN source_records r ON r.id = l.source_record_id
+    GROUP BY r.source_system";
+
+/// Links removed in the window by someone other than the automatic linker
+const OVERLAYS_SQL: &str = "
+    SELECT r.source_system, COUNT(*) AS count
+    FROM source_record_links l
+    JOIN source_records r ON r.id = l.source_record_id
+    WHERE l.unlinked_at >= $1 AND l.unlinked_at < $2
+      AND l.unlinked_by IS NOT NULL AND l.unlinked_by <> $3
+    GROUP BY r.source_system";
+
+#[derive(QueryableByName)]
+struct SourceCountRow {
+    #[diesel(sql_type = Text)]
+    source_system: String,
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+#[derive(QueryableByName)]
+struct LinkStatsRow {
+    #[diesel(sql_type = Text)]
+    source_system: String,
+    #[diesel(sql_type = BigInt)]
+    auto_links: i64,
+    #[diesel(sql_type = BigInt)]
+    manual_links: i64,
+    #[diesel(sql_type = Nullable<Double>)]
+    average_match_score: Option<f64>,
+    #[diesel(sql_type = BigInt)]
+    new_patients: i64,
+    #[diesel(sql_type = BigInt)]
+    duplicates_created: i64,
+}
+
+/// Repository for daily matching KPIs
+pub struct MatchingKpiRepository {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl MatchingKpiRepository {
+    /// Create a new matching KPI repository
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+
+    /// Get a database connection from the pool
+    fn get_conn(&self) -> Result<diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>> {
+        self.pool.get().map_err(|e| crate::Error::Pool(e.to_string()))
+    }
+
+    /// Aggregate one UTC day of source activity, per source system
+    ///
+    /// Links made or removed by `automatic_linker` (or with no actor) count
+    /// as automatic; any other actor is a reviewer.
+    pub fn compute_day(&self, day: NaiveDate, automatic_linker: &str) -> Result<Vec<DailyMatchingKpis>> {
+        let mut conn = self.get_conn()?;
+        let start: DateTime<Utc> = day.and_hms_opt(0, 0, 0).expect("valid time").and_utc();
+        let end = start + chrono::Duration::days(1);
+
+        let received: Vec<SourceCountRow> = diesel::sql_query(RECEIVED_SQL)
+            .bind::<Timestamptz, _>(start)
+            .bind::<Timestamptz, _>(end)
+            .load(&mut conn)?;
+        let links: Vec<LinkStatsRow> = diesel::sql_query(LINKS_SQL)
+            .bind::<Timestamptz, _>(start)
+            .bind::<Timestamptz, _>(end)
+            .bind::<Text, _>(automatic_linker)
+            .load(&mut conn)?;
+        let overlays: Vec<SourceCountRow> = diesel::sql_query(OVERLAYS_SQL)
+            .bind::<Timestamptz, _>(start)
+            .bind::<Timestamptz, _>(end)
+            .bind::<Text, _>(automatic_linker)
+            .load(&mut conn)?;
+
+        let mut by_source: BTreeMap<String, KpiCounts> = BTreeMap::new();
+        for row in received {
+            by_source.entry(row.source_system).or_default().records_received = row.count;
+        }
+        for row in links {
+            let counts = by_source.entry(row.source_system).or_default();
+            counts.auto_links = row.auto_links;
+            counts.manual_links = row.manual_links;
+            counts.average_match_score = row.average_match_score;
+            counts.new_patients = row.new_patients;
+            counts.duplicates_created = row.duplicates_created;
+        }
+        for row in overlays {
+            by_source.entry(row.source_system).or_default().overlay_incidents = row.count;
+        }
+
+        Ok(by_source
+            .into_iter()
+            .map(|(source_system, counts)| DailyMatchingKpis { day, source_system, counts })
+            .collect())
+    }
+
+    /// Insert or replace daily rows
+    pub fn save(&self, kpis: &[DailyMatchingKpis]) -> Result<()> {
+        let mut conn = self.get_conn()?;
+        let computed_at = Utc::now();
+
+        for kpi in kpis {
+            let row = DbMatchingKpis {
+                day: kpi.day,
+                source_system: kpi.source_system.clone(),
+                records_received: kpi.counts.records_received,
+                new_patients: kpi.counts.new_patients,
+                duplicates_created: kpi.counts.duplicates_created,
+                auto_links: kpi.counts.auto_links,
+                manual_links: kpi.counts.manual_links,
+                average_match_score: kpi.counts.average_match_score,
+                overlay_incidents: kpi.counts.overlay_incidents,
+                computed_at,
+            };
+            diesel::insert_into(matching_kpis_daily::table)
+                .values(&row)
+                .on_conflict((matching_kpis_daily::day, matching_kpis_daily::source_system))
+                .do_update()
+                .set(&row)
+                .execute(&mut conn)?;
+        }
+
+        Ok(())
+    }
+
+    /// Load persisted rows for an inclusive date range, oldest first
+    pub fn list(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        source_system: Option<&str>,
+    ) -> Result<Vec<DailyMatchingKpis>> {
+        let mut conn = self.get_conn()?;
+
+        let mut query = matching_kpis_daily::table
+            .filter(matching_kpis_daily::day.ge(from))
+            .filter(matching_kpis_daily::day.le(to))
+            .into_boxed();
+        if let Some(source_system) = source_system {
+            query = query.filter(matching_kpis_daily::source_system.eq(source_system.to_string()));
+        }
+
+        let rows = query
+            .order((matching_kpis_daily::day.asc(), matching_kpis_daily::source_system.asc()))
+            .select(DbMatchingKpis::as_select())
+            .load(&mut conn)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| DailyMatchingKpis {
+                day: row.day,
+                source_system: row.source_system,
+                counts: KpiCounts {
+                    records_received: row.records_received,
+                    new_patients: row.new_patients,
+                    duplicates_created: row.duplicates_created,
+                    auto_links: row.auto_links,
+                    manual_links: row.manual_links,
+                    average_match_score: row.average_match_score,
+                    overlay_incidents: row.overlay_incidents,
+                },
+            })
+            .collect())
+    }
+}
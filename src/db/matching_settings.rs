@@ -0,0 +1,140 @@
+//! Stored matching profile repository
+
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::config::MatchingProfile;
+use crate::models::MatchingSettingsVersion;
+use crate::{Error, Result};
+use super::models::DbMatchingSettings;
+use super::schema::matching_settings;
+
+/// Stored matching profile repository trait
+pub trait MatchingSettingsRepository: Send + Sync {
+    /// Store `settings` as the next version of `profile`
+    fn save(
+        &self,
+        profile: &str,
+        settings: &MatchingProfile,
+        changed_by: Option<String>,
+        note: Option<String>,
+    ) -> Result<MatchingSettingsVersion>;
+
+    /// The version in effect of every stored profile, by name
+    fn current(&self) -> Result<Vec<MatchingSettingsVersion>>;
+
+    /// Every version of a profile, newest first
+    fn history(&self, profile: &str) -> Result<Vec<MatchingSettingsVersion>>;
+
+    /// One version of a profile
+    fn get_version(&self, profile: &str, version: i32) -> Result<Option<MatchingSettingsVersion>>;
+}
+
+/// Diesel-based stored matching profile implementation
+pub struct DieselMatchingSettingsRepository {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl DieselMatchingSettingsRepository {
+    /// Create a new repository with the given connection pool
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+
+    /// Get a database connection from the pool
+    fn get_conn(&self) -> Result<diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>> {
+        self.pool.get().map_err(|e| Error::Pool(e.to_string()))
+    }
+
+    /// Convert a database record to the domain model
+    fn to_version(db_settings: DbMatchingSettings) -> Result<MatchingSettingsVersion> {
+        let settings = serde_json::from_value(db_settings.settings)
+            .map_err(|e| Error::Internal(format!("Invalid stored matching profile: {}", e)))?;
+        Ok(MatchingSettingsVersion {
+            id: db_settings.id,
+            profile: db_settings.profile,
+            version: db_settings.version,
+            settings,
+            changed_by: db_settings.changed_by,
+            note: db_settings.note,
+            changed_at: db_settings.changed_at,
+        })
+    }
+}
+
+impl MatchingSettingsRepository for DieselMatchingSettingsRepository {
+    fn save(
+        &self,
+        profile: &str,
+        settings: &MatchingProfile,
+        changed_by: Option<String>,
+        note: Option<String>,
+    ) -> Result<MatchingSettingsVersion> {
+        let mut conn = self.get_conn()?;
+        let settings = serde_json::to_value(settings)
+            .map_err(|e| Error::Internal(format!("Failed to serialize matching profile: {}", e)))?;
+
+        // Two changes racing for the same version fail on the unique
+        // (profile, version) constraint rather than overwrite each other
+        let db_settings = conn.transaction(|conn| {
+            let latest: Option<i32> = matching_settings::table
+                .filter(matching_settings::profile.eq(profile))
+                .select(diesel::dsl::max(matching_settings::version))
+                .first(conn)?;
+
+            diesel::insert_into(matching_settings::table)
+                .values(&DbMatchingSettings {
+                    id: Uuid::new_v4(),
+                    profile: profile.to_string(),
+                    version: latest.unwrap_or(0) + 1,
+                    settings,
+                    changed_by,
+                    note,
+                    changed_at: chrono::Utc::now(),
+                })
+                .returning(DbMatchingSettings::as_returning())
+                .get_result(conn)
+        })?;
+
+        Self::to_version(db_settings)
+    }
+
+    fn current(&self) -> Result<Vec<MatchingSettingsVersion>> {
+        let mut conn = self.get_conn()?;
+
+        let db_settings = matching_settings::table
+            .distinct_on(matching_settings::profile)
+            .order((matching_settings::profile.asc(), matching_settings::version.desc()))
+            .select(DbMatchingSettings::as_select())
+            .load(&mut conn)?;
+
+        db_settings.into_iter().map(Self::to_version).collect()
+    }
+
+    fn history(&self, profile: &str) -> Result<Vec<MatchingSettingsVersion>> {
+        let mut conn = self.get_conn()?;
+
+        let db_settings = matching_settings::table
+            .filter(matching_settings::profile.eq(profile))
+            .order(matching_settings::version.desc())
+            .select(DbMatchingSettings::as_select())
+            .load(&mut conn)?;
+
+        db_settings.into_iter().map(Self::to_version).collect()
+    }
+
+    fn get_version(&self, profile: &str, version: i32) -> Result<Option<MatchingSettingsVersion>> {
+        let mut conn = self.get_conn()?;
+
+        let db_settings = matching_settings::table
+            .filter(matching_settings::profile.eq(profile))
+            .filter(matching_settings::version.eq(version))
+            .select(DbMatchingSettings::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        db_settings.map(Self::to_version).transpose()
+    }
+}
@@ -0,0 +1,1628 @@
+//! In-memory repositories
+//!
+//! Implement the repository traits without a database, for the synthetic
+//! sandbox and for tests. Data lives only as long as the process.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::models::duplicate_candidate::PENDING_REVIEW;
+use crate::models::quarantined_record::QUARANTINE_PENDING;
+use crate::models::change_request::CHANGE_PENDING;
+use crate::models::{
+    ArchivedMessage, AssigningAuthority, ChangeRequest, Confidentiality, DemographicChanges, DuplicateCandidate,
+    FieldProvenance, ImportCheckpoint, MatchingSettingsVersion, Patient, PatientGroup, PatientStatus, PatientWatch, Practitioner, QuarantinedRecord, RecordLock,
+    ReviewDecision, SourceRecord, SourceRecordLink, SourceSubmission,
+};
+use crate::config::MatchingProfile;
+use crate::matching::cache::{CachedScore, PairKey, PairScoreCache};
+use crate::streaming::{EventProducer, PatientEvent};
+use crate::Result;
+use super::{
+    AssigningAuthorityRepository, ChangeRequestRepository, DuplicateCandidateRepository, FieldProvenanceRepository,
+    ImportCheckpointRepository, LockOutcome, MatchingSettingsRepository, MessageArchiveRepository, MrnSequenceRepository,
+    PatientCriteria, PatientGroupRepository, PatientRepository, PractitionerRepository, QuarantineRepository,
+    RecordLockRepository, SourceRecordRepository, WatchRepository,
+};
+
+fn poisoned() -> crate::Error {
+    crate::Error::Internal("In-memory repository lock poisoned".to_string())
+}
+
+/// Patient repository backed by a map
+#[derive(Default)]
+pub struct InMemoryPatientRepository {
+    /// Patients and whether they are soft-deleted
+    patients: RwLock<HashMap<Uuid, (Patient, bool)>>,
+    event_publisher: Option<Arc<dyn EventProducer>>,
+    source_records: Option<Arc<dyn SourceRecordRepository>>,
+}
+
+impl InMemoryPatientRepository {
+    /// Create an empty repository
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the event publisher for this repository
+    pub fn with_event_publisher(mut self, publisher: Arc<dyn EventProducer>) -> Self {
+        self.event_publisher = Some(publisher);
+        self
+    }
+
+    /// Keep feed submissions in `source_records`; without it they are
+    /// dropped
+    pub fn with_source_records(mut self, source_records: Arc<dyn SourceRecordRepository>) -> Self {
+        self.source_records = Some(source_records);
+        self
+    }
+
+    /// Store a submission as a source record linked to `patient_id`
+    ///
+    /// Unlike the database, this is not atomic with the patient write.
+    fn link_source(&self, source: &SourceSubmission, patient: &Patient, patient_id: &Uuid) -> Result<()> {
+        if let Some(source_records) = &self.source_records {
+            let record = source_records.receive(
+                &source.source_system,
+                &source.source_record_id,
+                patient,
+                source.received_by.clone(),
+            )?;
+            source_records.link(&record.id, patient_id, None, source.received_by.clone())?;
+        }
+        Ok(())
+    }
+
+    fn publish_event(&self, event: PatientEvent) {
+        if let Some(ref publisher) = self.event_publisher {
+            if let Err(e) = publisher.publish(event) {
+                tracing::error!("Failed to publish event: {}", e);
+            }
+        }
+    }
+}
+
+impl PatientRepository for InMemoryPatientRepository {
+    fn create(&self, patient: &Patient) -> Result<Patient> {
+        let mut created = patient.clone();
+        created.confidentiality = Confidentiality::Normal;
+        created.status = PatientStatus::implied_by(&created);
+        self.patients.write().map_err(|_| poisoned())?.insert(created.id, (created.clone(), false));
+        self.publish_event(PatientEvent::Created {
+            patient: created.clone(),
+            timestamp: Utc::now(),
+        });
+        Ok(created)
+    }
+
+    fn create_from_source(&self, patient: &Patient, source: &SourceSubmission) -> Result<Patient> {
+        let created = self.create(patient)?;
+        self.link_source(source, patient, &created.id)?;
+        Ok(created)
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<Patient>> {
+        let patients = self.patients.read().map_err(|_| poisoned())?;
+        Ok(patients.get(id).filter(|(_, deleted)| !deleted).map(|(patient, _)| patient.clone()))
+    }
+
+    fn is_deleted(&self, id: &Uuid) -> Result<bool> {
+        let patients = self.patients.read().map_err(|_| poisoned())?;
+        Ok(patients.get(id).is_some_and(|(_, deleted)| *deleted))
+    }
+
+    fn update(&self, patient: &Patient) -> Result<Patient> {
+        let mut updated = patient.clone();
+        updated.updated_at = Utc::now();
+        {
+            let mut patients = self.patients.write().map_err(|_| poisoned())?;
+            match patients.get(&patient.id) {
+                Some((existing, false)) => {
+                    updated.confidentiality = existing.confidentiality;
+                    updated.keep_address_history_from(existing, updated.updated_at);
+                    updated.keep_status_from(existing)?;
+                    patients.insert(patient.id, (updated.clone(), false));
+                }
+                _ => return Err(crate::Error::PatientNotFound(patient.id.to_string())),
+            }
+        }
+        self.publish_event(PatientEvent::Updated {
+            patient: updated.clone(),
+            timestamp: Utc::now(),
+        });
+        Ok(updated)
+    }
+
+    fn update_from_source(&self, patient: &Patient, source: &SourceSubmission) -> Result<Patient> {
+        let updated = self.update(patient)?;
+        self.link_source(source, patient, &updated.id)?;
+        Ok(updated)
+    }
+
+    fn delete(&self, id: &Uuid) -> Result<()> {
+        if let Some((_, deleted)) = self.patients.write().map_err(|_| poisoned())?.get_mut(id) {
+            *deleted = true;
+        }
+        self.publish_event(PatientEvent::Deleted {
+            patient_id: *id,
+            timestamp: Utc::now(),
+        });
+        Ok(())
+    }
+
+    fn purge(&self, id: &Uuid) -> Result<()> {
+        self.patients.write().map_err(|_| poisoned())?.remove(id);
+        self.publish_event(PatientEvent::Deleted {
+            patient_id: *id,
+            timestamp: Utc::now(),
+        });
+        Ok(())
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<Patient>> {
+        let query = query.to_lowercase();
+        let patients = self.patients.read().map_err(|_| poisoned())?;
+        Ok(patients
+            .values()
+            .filter(|(patient, deleted)| !deleted && patient.name.family.to_lowercase().contains(&query))
+            .map(|(patient, _)| patient.clone())
+            .collect())
+    }
+
+    fn list_active(&self, limit: i64, offset: i64) -> Result<Vec<Patient>> {
+        let patients = self.patients.read().map_err(|_| poisoned())?;
+        let mut active: Vec<&Patient> = patients
+            .values()
+            .filter(|(patient, deleted)| !deleted && patient.active)
+            .map(|(patient, _)| patient)
+            .collect();
+        active.sort_by_key(|patient| (patient.created_at, patient.id));
+        Ok(active
+            .into_iter()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    fn scan(
+        &self,
+        criteria: &PatientCriteria,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<Patient>> {
+        let patients = self.patients.read().map_err(|_| poisoned())?;
+        let mut matching: Vec<&Patient> = patients
+            .values()
+            .filter(|(patient, deleted)| !deleted && criteria.matches(patient))
+            .map(|(patient, _)| patient)
+            .filter(|patient| after.is_none_or(|after| (patient.created_at, patient.id) > after))
+            .collect();
+        matching.sort_by_key(|patient| (patient.created_at, patient.id));
+        Ok(matching.into_iter().take(limit.max(0) as usize).cloned().collect())
+    }
+
+    fn find_by_identifier(&self, systems: &[String], value: &str) -> Result<Vec<Uuid>> {
+        let patients = self.patients.read().map_err(|_| poisoned())?;
+        Ok(patients
+            .values()
+            .filter(|(patient, deleted)| {
+                !deleted
+                    && patient
+                        .identifiers
+                        .iter()
+                        .any(|identifier| identifier.value == value && systems.contains(&identifier.system))
+            })
+            .map(|(patient, _)| patient.id)
+            .collect())
+    }
+
+    fn find_by_contact(&self, contact_id: &Uuid) -> Result<Option<Uuid>> {
+        let patients = self.patients.read().map_err(|_| poisoned())?;
+        Ok(patients
+            .values()
+            .find(|(patient, deleted)| !deleted && patient.contacts.iter().any(|contact| contact.id == *contact_id))
+            .map(|(patient, _)| patient.id))
+    }
+
+    fn find_by_fingerprint(&self, fingerprint: &str) -> Result<Vec<Uuid>> {
+        let patients = self.patients.read().map_err(|_| poisoned())?;
+        Ok(patients
+            .values()
+            .filter(|(patient, deleted)| {
+                !deleted && crate::matching::record_fingerprint(patient).as_deref() == Some(fingerprint)
+            })
+            .map(|(patient, _)| patient.id)
+            .collect())
+    }
+
+    fn find_linked_to(&self, id: &Uuid) -> Result<Vec<Uuid>> {
+        let patients = self.patients.read().map_err(|_| poisoned())?;
+        Ok(patients
+            .values()
+            .filter(|(patient, deleted)| !deleted && patient.links.iter().any(|link| link.other_patient_id == *id))
+            .map(|(patient, _)| patient.id)
+            .collect())
+    }
+
+    fn set_confidentiality(&self, id: &Uuid, confidentiality: Confidentiality) -> Result<Option<Patient>> {
+        let mut patients = self.patients.write().map_err(|_| poisoned())?;
+        Ok(patients.get_mut(id).filter(|(_, deleted)| !deleted).map(|(patient, _)| {
+            patient.confidentiality = confidentiality;
+            patient.updated_at = Utc::now();
+            patient.clone()
+        }))
+    }
+
+    fn set_status(
+        &self,
+        id: &Uuid,
+        status: PatientStatus,
+        deceased_datetime: Option<DateTime<Utc>>,
+    ) -> Result<Option<Patient>> {
+        let mut patients = self.patients.write().map_err(|_| poisoned())?;
+        let Some((patient, false)) = patients.get_mut(id) else {
+            return Ok(None);
+        };
+        patient.transition_to(status, deceased_datetime)?;
+        patient.updated_at = Utc::now();
+        Ok(Some(patient.clone()))
+    }
+}
+
+/// Source record repository backed by vectors
+#[derive(Default)]
+pub struct InMemorySourceRecordRepository {
+    records: RwLock<Vec<SourceRecord>>,
+    links: RwLock<Vec<SourceRecordLink>>,
+}
+
+impl InMemorySourceRecordRepository {
+    /// Create an empty repository
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn records_where(&self, keep: impl Fn(&SourceRecord) -> bool) -> Result<Vec<SourceRecord>> {
+        let records = self.records.read().map_err(|_| poisoned())?;
+        Ok(records.iter().filter(|record| keep(record)).cloned().collect())
+    }
+
+    fn page<T>(items: Vec<T>, limit: i64, offset: i64) -> Vec<T> {
+        items
+            .into_iter()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .collect()
+    }
+}
+
+impl SourceRecordRepository for InMemorySourceRecordRepository {
+    fn receive(
+        &self,
+        source_system: &str,
+        source_record_id: &str,
+        patient: &Patient,
+        received_by: Option<String>,
+    ) -> Result<SourceRecord> {
+        let record = SourceRecord {
+            id: Uuid::new_v4(),
+            source_system: source_system.to_string(),
+            source_record_id: source_record_id.to_string(),
+            patient: patient.clone(),
+            received_at: Utc::now(),
+            received_by,
+        };
+        self.records.write().map_err(|_| poisoned())?.push(record.clone());
+        Ok(record)
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<SourceRecord>> {
+        Ok(self.records_where(|record| record.id == *id)?.pop())
+    }
+
+    fn list_by_source(&self, source_system: &str, source_record_id: &str) -> Result<Vec<SourceRecord>> {
+        let mut records = self.records_where(|record| {
+            record.source_system == source_system && record.source_record_id == source_record_id
+        })?;
+        records.reverse();
+        Ok(records)
+    }
+
+    fn count_by_source_system(&self, source_system: &str) -> Result<i64> {
+        Ok(self.records_where(|record| record.source_system == source_system)?.len() as i64)
+    }
+
+    fn list_by_source_system(&self, source_system: &str, limit: i64, offset: i64) -> Result<Vec<SourceRecord>> {
+        let records = self.records_where(|record| record.source_system == source_system)?;
+        Ok(Self::page(records, limit, offset))
+    }
+
+    fn purge(&self, id: &Uuid) -> Result<bool> {
+        self.links.write().map_err(|_| poisoned())?.retain(|link| link.source_record_id != *id);
+        let mut records = self.records.write().map_err(|_| poisoned())?;
+        let before = records.len();
+        records.retain(|record| record.id != *id);
+        Ok(records.len() < before)
+    }
+
+    fn list_for_patient(&self, patient_id: &Uuid) -> Result<Vec<SourceRecord>> {
+        let linked: Vec<Uuid> = self
+            .links
+            .read()
+            .map_err(|_| poisoned())?
+            .iter()
+            .filter(|link| link.patient_id == *patient_id && link.is_active())
+            .map(|link| link.source_record_id)
+            .collect();
+        let mut records = self.records_where(|record| linked.contains(&record.id))?;
+        records.reverse();
+        Ok(records)
+    }
+
+    fn link(
+        &self,
+        source_record_id: &Uuid,
+        patient_id: &Uuid,
+        match_score: Option<f64>,
+        linked_by: Option<String>,
+    ) -> Result<SourceRecordLink> {
+        let now = Utc::now();
+        let mut links = self.links.write().map_err(|_| poisoned())?;
+        for link in links.iter_mut() {
+            if link.source_record_id == *source_record_id && link.is_active() {
+                link.unlinked_at = Some(now);
+                link.unlinked_by = linked_by.clone();
+            }
+        }
+        let link = SourceRecordLink {
+            id: Uuid::new_v4(),
+            source_record_id: *source_record_id,
+            patient_id: *patient_id,
+            match_score,
+            linked_at: now,
+            linked_by,
+            unlinked_at: None,
+            unlinked_by: None,
+            confidence: SourceRecordLink::initial_confidence(match_score),
+            needs_reverification: false,
+            verified_at: None,
+            verified_by: None,
+        };
+        links.push(link.clone());
+        Ok(link)
+    }
+
+    fn unlink(&self, source_record_id: &Uuid, unlinked_by: Option<String>) -> Result<()> {
+        let now = Utc::now();
+        for link in self.links.write().map_err(|_| poisoned())?.iter_mut() {
+            if link.source_record_id == *source_record_id && link.is_active() {
+                link.unlinked_at = Some(now);
+                link.unlinked_by = unlinked_by.clone();
+            }
+        }
+        Ok(())
+    }
+
+    fn current_link(&self, source_record_id: &Uuid) -> Result<Option<SourceRecordLink>> {
+        let links = self.links.read().map_err(|_| poisoned())?;
+        Ok(links
+            .iter()
+            .find(|link| link.source_record_id == *source_record_id && link.is_active())
+            .cloned())
+    }
+
+    fn list_active_links(&self, limit: i64, offset: i64) -> Result<Vec<SourceRecordLink>> {
+        let links: Vec<SourceRecordLink> = self
+            .links
+            .read()
+            .map_err(|_| poisoned())?
+            .iter()
+            .filter(|link| link.is_active())
+            .cloned()
+            .collect();
+        Ok(Self::page(links, limit, offset))
+    }
+
+    fn set_confidence(&self, link_id: &Uuid, confidence: f64, needs_reverification: bool) -> Result<()> {
+        for link in self.links.write().map_err(|_| poisoned())?.iter_mut() {
+            if link.id == *link_id {
+                link.confidence = confidence.clamp(0.0, 1.0);
+                link.needs_reverification = needs_reverification;
+            }
+        }
+        Ok(())
+    }
+
+    fn list_needing_reverification(&self, limit: i64, offset: i64) -> Result<Vec<SourceRecordLink>> {
+        let mut links: Vec<SourceRecordLink> = self
+            .links
+            .read()
+            .map_err(|_| poisoned())?
+            .iter()
+            .filter(|link| link.needs_reverification && link.is_active())
+            .cloned()
+            .collect();
+        links.sort_by(|a, b| a.confidence.total_cmp(&b.confidence).then(a.linked_at.cmp(&b.linked_at)));
+        Ok(Self::page(links, limit, offset))
+    }
+
+    fn verify_link(&self, link_id: &Uuid, verified_by: Option<String>) -> Result<Option<SourceRecordLink>> {
+        let mut links = self.links.write().map_err(|_| poisoned())?;
+        Ok(links.iter_mut().find(|link| link.id == *link_id && link.is_active()).map(|link| {
+            link.confidence = 1.0;
+            link.needs_reverification = false;
+            link.verified_at = Some(Utc::now());
+            link.verified_by = verified_by;
+            link.clone()
+        }))
+    }
+
+    fn list_received(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<SourceRecord>> {
+        let records = self.records_where(|record| record.received_at >= since && record.received_at < until)?;
+        Ok(Self::page(records, limit, offset))
+    }
+}
+
+/// Watch repository backed by a map
+#[derive(Default)]
+pub struct InMemoryWatchRepository {
+    watches: RwLock<HashMap<Uuid, PatientWatch>>,
+}
+
+impl InMemoryWatchRepository {
+    /// Create an empty repository
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl WatchRepository for InMemoryWatchRepository {
+    fn create(
+        &self,
+        patient_id: &Uuid,
+        callback_url: Option<String>,
+        event_types: Vec<String>,
+        created_by: Option<String>,
+    ) -> Result<PatientWatch> {
+        let watch = PatientWatch {
+            id: Uuid::new_v4(),
+            patient_id: *patient_id,
+            callback_url,
+            event_types,
+            created_at: Utc::now(),
+            created_by,
+        };
+        self.watches.write().map_err(|_| poisoned())?.insert(watch.id, watch.clone());
+        Ok(watch)
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<PatientWatch>> {
+        Ok(self.watches.read().map_err(|_| poisoned())?.get(id).cloned())
+    }
+
+    fn delete(&self, id: &Uuid) -> Result<bool> {
+        Ok(self.watches.write().map_err(|_| poisoned())?.remove(id).is_some())
+    }
+
+    fn list_for_patients(&self, patient_ids: &[Uuid]) -> Result<Vec<PatientWatch>> {
+        let watches = self.watches.read().map_err(|_| poisoned())?;
+        Ok(watches
+            .values()
+            .filter(|watch| patient_ids.contains(&watch.patient_id))
+            .cloned()
+            .collect())
+    }
+}
+
+/// Assigning authority repository backed by a map
+#[derive(Default)]
+pub struct InMemoryAssigningAuthorityRepository {
+    authorities: RwLock<HashMap<Uuid, AssigningAuthority>>,
+}
+
+impl InMemoryAssigningAuthorityRepository {
+    /// Create an empty repository
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AssigningAuthorityRepository for InMemoryAssigningAuthorityRepository {
+    fn create(&self, system: String, oid: Option<String>, name: String, active: bool) -> Result<AssigningAuthority> {
+        let now = Utc::now();
+        let authority = AssigningAuthority {
+            id: Uuid::new_v4(),
+            system,
+            oid,
+            name,
+            active,
+            created_at: now,
+            updated_at: now,
+        };
+        self.authorities.write().map_err(|_| poisoned())?.insert(authority.id, authority.clone());
+        Ok(authority)
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<AssigningAuthority>> {
+        Ok(self.authorities.read().map_err(|_| poisoned())?.get(id).cloned())
+    }
+
+    fn list(&self) -> Result<Vec<AssigningAuthority>> {
+        let mut authorities: Vec<AssigningAuthority> =
+            self.authorities.read().map_err(|_| poisoned())?.values().cloned().collect();
+        authorities.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(authorities)
+    }
+
+    fn update(
+        &self,
+        id: &Uuid,
+        system: String,
+        oid: Option<String>,
+        name: String,
+        active: bool,
+    ) -> Result<Option<AssigningAuthority>> {
+        let mut authorities = self.authorities.write().map_err(|_| poisoned())?;
+        Ok(authorities.get_mut(id).map(|authority| {
+            authority.system = system;
+            authority.oid = oid;
+            authority.name = name;
+            authority.active = active;
+            authority.updated_at = Utc::now();
+            authority.clone()
+        }))
+    }
+
+    fn delete(&self, id: &Uuid) -> Result<bool> {
+        Ok(self.authorities.write().map_err(|_| poisoned())?.remove(id).is_some())
+    }
+}
+
+/// Practitioner repository backed by a map
+#[derive(Default)]
+pub struct InMemoryPractitionerRepository {
+    /// Practitioners and whether they are soft-deleted
+    practitioners: RwLock<HashMap<Uuid, (Practitioner, bool)>>,
+}
+
+impl InMemoryPractitionerRepository {
+    /// Create an empty repository
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn find(&self, predicate: impl Fn(&Practitioner) -> bool) -> Result<Vec<Practitioner>> {
+        let practitioners = self.practitioners.read().map_err(|_| poisoned())?;
+        let mut found: Vec<Practitioner> = practitioners
+            .values()
+            .filter(|(practitioner, deleted)| !deleted && predicate(practitioner))
+            .map(|(practitioner, _)| practitioner.clone())
+            .collect();
+        found.sort_by_key(|practitioner| (practitioner.created_at, practitioner.id));
+        Ok(found)
+    }
+}
+
+impl PractitionerRepository for InMemoryPractitionerRepository {
+    fn create(&self, practitioner: &Practitioner) -> Result<Practitioner> {
+        self.practitioners
+            .write()
+            .map_err(|_| poisoned())?
+            .insert(practitioner.id, (practitioner.clone(), false));
+        Ok(practitioner.clone())
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<Practitioner>> {
+        let practitioners = self.practitioners.read().map_err(|_| poisoned())?;
+        Ok(practitioners.get(id).filter(|(_, deleted)| !deleted).map(|(practitioner, _)| practitioner.clone()))
+    }
+
+    fn update(&self, practitioner: &Practitioner) -> Result<Option<Practitioner>> {
+        let mut practitioners = self.practitioners.write().map_err(|_| poisoned())?;
+        let Some((stored, false)) = practitioners.get_mut(&practitioner.id) else {
+            return Ok(None);
+        };
+        let created_at = stored.created_at;
+        *stored = practitioner.clone();
+        stored.created_at = created_at;
+        stored.updated_at = Utc::now();
+        Ok(Some(stored.clone()))
+    }
+
+    fn delete(&self, id: &Uuid) -> Result<bool> {
+        let mut practitioners = self.practitioners.write().map_err(|_| poisoned())?;
+        match practitioners.get_mut(id) {
+            Some((_, deleted)) if !*deleted => {
+                *deleted = true;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<Practitioner>> {
+        Ok(self
+            .find(|_| true)?
+            .into_iter()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .collect())
+    }
+
+    fn find_by_npi(&self, npi: &str) -> Result<Vec<Practitioner>> {
+        let npi = npi.trim();
+        self.find(|practitioner| practitioner.npi() == Some(npi))
+    }
+
+    fn find_by_family_name(&self, family: &str, limit: i64) -> Result<Vec<Practitioner>> {
+        let family = family.trim().to_lowercase();
+        let mut found = self.find(|practitioner| practitioner.name.family.to_lowercase() == family)?;
+        found.truncate(limit.max(0) as usize);
+        Ok(found)
+    }
+}
+
+/// Pair score cache backed by a map, holding one score per pair
+#[derive(Default)]
+pub struct InMemoryPairScoreCache {
+    scores: RwLock<HashMap<(Uuid, Uuid), (PairKey, CachedScore)>>,
+}
+
+impl InMemoryPairScoreCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl PairScoreCache for InMemoryPairScoreCache {
+    fn get(&self, key: &PairKey) -> Result<Option<CachedScore>> {
+        let scores = self.scores.read().map_err(|_| poisoned())?;
+        Ok(scores
+            .get(&(key.patient_id, key.candidate_id))
+            .filter(|(stored, _)| stored == key)
+            .map(|(_, score)| score.clone()))
+    }
+
+    fn put(&self, key: &PairKey, score: &CachedScore) -> Result<()> {
+        self.scores
+            .write()
+            .map_err(|_| poisoned())?
+            .insert((key.patient_id, key.candidate_id), (key.clone(), score.clone()));
+        Ok(())
+    }
+}
+
+/// Duplicate review queue backed by a map keyed by ordered pair
+#[derive(Default)]
+pub struct InMemoryDuplicateCandidateRepository {
+    candidates: RwLock<HashMap<(Uuid, Uuid), DuplicateCandidate>>,
+    decisions: RwLock<Vec<ReviewDecision>>,
+}
+
+impl InMemoryDuplicateCandidateRepository {
+    /// Create an empty queue
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl DuplicateCandidateRepository for InMemoryDuplicateCandidateRepository {
+    fn enqueue(&self, patient_id: &Uuid, candidate_id: &Uuid, score: f64) -> Result<Option<DuplicateCandidate>> {
+        let pair = DuplicateCandidate::ordered(*patient_id, *candidate_id);
+        let mut candidates = self.candidates.write().map_err(|_| poisoned())?;
+        if candidates.contains_key(&pair) {
+            return Ok(None);
+        }
+        let candidate = DuplicateCandidate {
+            id: Uuid::new_v4(),
+            patient_id: pair.0,
+            candidate_id: pair.1,
+            score,
+            status: PENDING_REVIEW.to_string(),
+            detected_at: Utc::now(),
+            photo_similarity: None,
+        };
+        candidates.insert(pair, candidate.clone());
+        Ok(Some(candidate))
+    }
+
+    fn list_pending(&self, limit: i64, offset: i64) -> Result<Vec<DuplicateCandidate>> {
+        let candidates = self.candidates.read().map_err(|_| poisoned())?;
+        let mut pending: Vec<_> = candidates
+            .values()
+            .filter(|candidate| candidate.status == PENDING_REVIEW)
+            .cloned()
+            .collect();
+        pending.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.detected_at.cmp(&b.detected_at)));
+        Ok(pending
+            .into_iter()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .collect())
+    }
+
+    fn count_pending(&self) -> Result<i64> {
+        let candidates = self.candidates.read().map_err(|_| poisoned())?;
+        Ok(candidates.values().filter(|candidate| candidate.status == PENDING_REVIEW).count() as i64)
+    }
+
+    fn list_pending_for_patient(&self, patient_id: &Uuid) -> Result<Vec<DuplicateCandidate>> {
+        let mut pending = self.list_pending(i64::MAX, 0)?;
+        pending.retain(|candidate| candidate.patient_id == *patient_id || candidate.candidate_id == *patient_id);
+        Ok(pending)
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<DuplicateCandidate>> {
+        let candidates = self.candidates.read().map_err(|_| poisoned())?;
+        Ok(candidates.values().find(|candidate| candidate.id == *id).cloned())
+    }
+
+    fn decide(&self, id: &Uuid, from_status: &str, decision: &ReviewDecision) -> Result<bool> {
+        let mut candidates = self.candidates.write().map_err(|_| poisoned())?;
+        let Some(candidate) = candidates
+            .values_mut()
+            .find(|candidate| candidate.id == *id && candidate.status == from_status)
+        else {
+            return Ok(false);
+        };
+        candidate.status = decision.action.status().to_string();
+        self.decisions.write().map_err(|_| poisoned())?.push(ReviewDecision {
+            duplicate_candidate_id: *id,
+            ..decision.clone()
+        });
+        Ok(true)
+    }
+
+    fn decisions(&self, id: &Uuid) -> Result<Vec<ReviewDecision>> {
+        let decisions = self.decisions.read().map_err(|_| poisoned())?;
+        Ok(decisions
+            .iter()
+            .filter(|decision| decision.duplicate_candidate_id == *id)
+            .cloned()
+            .collect())
+    }
+
+    fn list_decided(&self, limit: i64, offset: i64) -> Result<Vec<DuplicateCandidate>> {
+        let candidates = self.candidates.read().map_err(|_| poisoned())?;
+        let mut decided: Vec<_> = candidates
+            .values()
+            .filter(|candidate| candidate.status != PENDING_REVIEW)
+            .cloned()
+            .collect();
+        decided.sort_by(|a, b| a.detected_at.cmp(&b.detected_at).then(a.id.cmp(&b.id)));
+        Ok(decided
+            .into_iter()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .collect())
+    }
+}
+
+/// Record lock repository backed by a map
+#[derive(Default)]
+pub struct InMemoryRecordLockRepository {
+    locks: RwLock<HashMap<Uuid, RecordLock>>,
+}
+
+impl InMemoryRecordLockRepository {
+    /// Create an empty repository
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl RecordLockRepository for InMemoryRecordLockRepository {
+    fn acquire(
+        &self,
+        patient_ids: &[Uuid],
+        locked_by: &str,
+        reason: Option<&str>,
+        ttl: Duration,
+    ) -> Result<LockOutcome> {
+        let now = Utc::now();
+        let mut locks = self.locks.write().map_err(|_| poisoned())?;
+
+        let held = patient_ids.iter().filter_map(|id| locks.get(id)).find(|lock| {
+            lock.expires_at > now && lock.locked_by != locked_by
+        });
+        if let Some(held) = held {
+            return Ok(LockOutcome::Held(held.clone()));
+        }
+
+        let mut acquired = Vec::new();
+        for id in patient_ids {
+            let acquired_at = match locks.get(id) {
+                Some(lock) if lock.expires_at > now => lock.acquired_at,
+                _ => now,
+            };
+            let lock = RecordLock {
+                patient_id: *id,
+                locked_by: locked_by.to_string(),
+                reason: reason.map(str::to_string),
+                acquired_at,
+                expires_at: now + ttl,
+            };
+            locks.insert(*id, lock.clone());
+            acquired.push(lock);
+        }
+        Ok(LockOutcome::Acquired(acquired))
+    }
+
+    fn get(&self, patient_id: &Uuid) -> Result<Option<RecordLock>> {
+        let locks = self.locks.read().map_err(|_| poisoned())?;
+        Ok(locks.get(patient_id).filter(|lock| lock.expires_at > Utc::now()).cloned())
+    }
+
+    fn release(&self, patient_id: &Uuid) -> Result<bool> {
+        let removed = self.locks.write().map_err(|_| poisoned())?.remove(patient_id);
+        Ok(removed.is_some_and(|lock| lock.expires_at > Utc::now()))
+    }
+
+    fn find_conflict(&self, patient_ids: &[Uuid], holder: Option<&str>) -> Result<Option<RecordLock>> {
+        let now = Utc::now();
+        let locks = self.locks.read().map_err(|_| poisoned())?;
+        let mut conflicts: Vec<&RecordLock> = patient_ids
+            .iter()
+            .filter_map(|id| locks.get(id))
+            .filter(|lock| lock.expires_at > now && Some(lock.locked_by.as_str()) != holder)
+            .collect();
+        conflicts.sort_by_key(|lock| lock.acquired_at);
+        Ok(conflicts.first().map(|lock| (*lock).clone()))
+    }
+}
+
+/// Message archive backed by a list
+///
+/// There is no audit log without a database, so entries are never linked
+/// to an audit log entry.
+#[derive(Default)]
+pub struct InMemoryMessageArchiveRepository {
+    messages: RwLock<Vec<ArchivedMessage>>,
+}
+
+impl InMemoryMessageArchiveRepository {
+    /// Create an empty archive
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MessageArchiveRepository for InMemoryMessageArchiveRepository {
+    fn archive(
+        &self,
+        patient_id: &Uuid,
+        action: &str,
+        channel: &str,
+        content_type: &str,
+        payload: &str,
+    ) -> Result<ArchivedMessage> {
+        let message = ArchivedMessage {
+            id: Uuid::new_v4(),
+            patient_id: *patient_id,
+            audit_log_id: None,
+            action: action.to_string(),
+            channel: channel.to_string(),
+            content_type: content_type.to_string(),
+            payload: payload.to_string(),
+            received_at: Utc::now(),
+        };
+        self.messages.write().map_err(|_| poisoned())?.push(message.clone());
+        Ok(message)
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<ArchivedMessage>> {
+        let messages = self.messages.read().map_err(|_| poisoned())?;
+        Ok(messages.iter().find(|message| message.id == *id).cloned())
+    }
+
+    fn get_for_audit_entry(&self, audit_log_id: &Uuid) -> Result<Option<ArchivedMessage>> {
+        let messages = self.messages.read().map_err(|_| poisoned())?;
+        Ok(messages.iter().find(|message| message.audit_log_id == Some(*audit_log_id)).cloned())
+    }
+
+    fn list_for_patient(&self, patient_id: &Uuid, limit: i64) -> Result<Vec<ArchivedMessage>> {
+        let messages = self.messages.read().map_err(|_| poisoned())?;
+        Ok(messages
+            .iter()
+            .rev()
+            .filter(|message| message.patient_id == *patient_id)
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+}
+
+/// Quarantine repository backed by a list
+#[derive(Default)]
+pub struct InMemoryQuarantineRepository {
+    records: RwLock<Vec<QuarantinedRecord>>,
+}
+
+impl InMemoryQuarantineRepository {
+    /// Create an empty repository
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl QuarantineRepository for InMemoryQuarantineRepository {
+    fn quarantine(
+        &self,
+        channel: &str,
+        content_type: &str,
+        payload: &str,
+        errors: &serde_json::Value,
+        patient_id: Option<Uuid>,
+    ) -> Result<QuarantinedRecord> {
+        let record = QuarantinedRecord {
+            id: Uuid::new_v4(),
+            channel: channel.to_string(),
+            content_type: content_type.to_string(),
+            payload: payload.to_string(),
+            errors: errors.clone(),
+            patient_id,
+            status: QUARANTINE_PENDING.to_string(),
+            received_at: Utc::now(),
+            resolved_at: None,
+            resolved_patient_id: None,
+        };
+        self.records.write().map_err(|_| poisoned())?.push(record.clone());
+        Ok(record)
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<QuarantinedRecord>> {
+        let records = self.records.read().map_err(|_| poisoned())?;
+        Ok(records.iter().find(|record| record.id == *id).cloned())
+    }
+
+    fn list(&self, status: Option<&str>, limit: i64, offset: i64) -> Result<Vec<QuarantinedRecord>> {
+        let records = self.records.read().map_err(|_| poisoned())?;
+        Ok(records
+            .iter()
+            .filter(|record| status.is_none_or(|status| record.status == status))
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    fn revise(&self, id: &Uuid, payload: &str, errors: &serde_json::Value) -> Result<bool> {
+        let mut records = self.records.write().map_err(|_| poisoned())?;
+        let Some(record) = records
+            .iter_mut()
+            .find(|record| record.id == *id && record.status == QUARANTINE_PENDING)
+        else {
+            return Ok(false);
+        };
+        record.payload = payload.to_string();
+        record.errors = errors.clone();
+        Ok(true)
+    }
+
+    fn resolve(&self, id: &Uuid, status: &str, payload: &str, patient_id: Option<Uuid>) -> Result<bool> {
+        let mut records = self.records.write().map_err(|_| poisoned())?;
+        let Some(record) = records
+            .iter_mut()
+            .find(|record| record.id == *id && record.status == QUARANTINE_PENDING)
+        else {
+            return Ok(false);
+        };
+        record.status = status.to_string();
+        record.payload = payload.to_string();
+        record.resolved_at = Some(Utc::now());
+        record.resolved_patient_id = patient_id;
+        Ok(true)
+    }
+}
+
+/// MRN sequences backed by a map of next values
+#[derive(Default)]
+pub struct InMemoryMrnSequenceRepository {
+    next_values: RwLock<HashMap<String, i64>>,
+}
+
+impl InMemoryMrnSequenceRepository {
+    /// Create an empty repository
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MrnSequenceRepository for InMemoryMrnSequenceRepository {
+    fn allocate(&self, system: &str, start: i64) -> Result<i64> {
+        let mut next_values = self.next_values.write().map_err(|_| poisoned())?;
+        let next = next_values.entry(system.to_string()).or_insert(start);
+        let value = (*next).max(start);
+        *next = value + 1;
+        Ok(value)
+    }
+}
+
+/// Change requests backed by a list
+#[derive(Default)]
+pub struct InMemoryChangeRequestRepository {
+    requests: RwLock<Vec<ChangeRequest>>,
+}
+
+impl InMemoryChangeRequestRepository {
+    /// Create an empty repository
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ChangeRequestRepository for InMemoryChangeRequestRepository {
+    fn submit(
+        &self,
+        patient_id: Uuid,
+        changes: &DemographicChanges,
+        reason: Option<String>,
+        submitted_by: Option<String>,
+    ) -> Result<ChangeRequest> {
+        let request = ChangeRequest {
+            id: Uuid::new_v4(),
+            patient_id,
+            changes: changes.clone(),
+            reason,
+            submitted_by,
+            status: CHANGE_PENDING.to_string(),
+            submitted_at: Utc::now(),
+            reviewed_by: None,
+            reviewed_at: None,
+            review_note: None,
+        };
+        self.requests.write().map_err(|_| poisoned())?.push(request.clone());
+        Ok(request)
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<ChangeRequest>> {
+        let requests = self.requests.read().map_err(|_| poisoned())?;
+        Ok(requests.iter().find(|request| request.id == *id).cloned())
+    }
+
+    fn list(&self, status: Option<&str>, patient_id: Option<Uuid>, limit: i64, offset: i64) -> Result<Vec<ChangeRequest>> {
+        let requests = self.requests.read().map_err(|_| poisoned())?;
+        Ok(requests
+            .iter()
+            .filter(|request| status.is_none_or(|status| request.status == status))
+            .filter(|request| patient_id.is_none_or(|patient_id| request.patient_id == patient_id))
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    fn resolve(&self, id: &Uuid, status: &str, reviewed_by: Option<String>, note: Option<String>) -> Result<bool> {
+        let mut requests = self.requests.write().map_err(|_| poisoned())?;
+        let Some(request) = requests
+            .iter_mut()
+            .find(|request| request.id == *id && request.status == CHANGE_PENDING)
+        else {
+            return Ok(false);
+        };
+        request.status = status.to_string();
+        request.reviewed_by = reviewed_by;
+        request.reviewed_at = Some(Utc::now());
+        request.review_note = note;
+        Ok(true)
+    }
+}
+
+/// Field provenance backed by a map per patient
+#[derive(Default)]
+pub struct InMemoryFieldProvenanceRepository {
+    fields: RwLock<HashMap<Uuid, BTreeMap<String, FieldProvenance>>>,
+}
+
+impl InMemoryFieldProvenanceRepository {
+    /// Create an empty repository
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl FieldProvenanceRepository for InMemoryFieldProvenanceRepository {
+    fn list_for_patient(&self, patient_id: &Uuid) -> Result<Vec<FieldProvenance>> {
+        let fields = self.fields.read().map_err(|_| poisoned())?;
+        Ok(fields.get(patient_id).map(|fields| fields.values().cloned().collect()).unwrap_or_default())
+    }
+
+    fn record(&self, patient_id: &Uuid, fields: &[&str], source: &str) -> Result<()> {
+        let mut all = self.fields.write().map_err(|_| poisoned())?;
+        let patient = all.entry(*patient_id).or_default();
+        let now = Utc::now();
+        for field in fields {
+            patient.insert(
+                field.to_string(),
+                FieldProvenance {
+                    field: field.to_string(),
+                    source: source.to_string(),
+                    recorded_at: now,
+                },
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Stored matching profiles backed by a list of every version
+#[derive(Default)]
+pub struct InMemoryMatchingSettingsRepository {
+    versions: RwLock<Vec<MatchingSettingsVersion>>,
+}
+
+impl InMemoryMatchingSettingsRepository {
+    /// Create an empty repository
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MatchingSettingsRepository for InMemoryMatchingSettingsRepository {
+    fn save(
+        &self,
+        profile: &str,
+        settings: &MatchingProfile,
+        changed_by: Option<String>,
+        note: Option<String>,
+    ) -> Result<MatchingSettingsVersion> {
+        let mut versions = self.versions.write().map_err(|_| poisoned())?;
+        let latest = versions
+            .iter()
+            .filter(|v| v.profile == profile)
+            .map(|v| v.version)
+            .max()
+            .unwrap_or(0);
+        let version = MatchingSettingsVersion {
+            id: Uuid::new_v4(),
+            profile: profile.to_string(),
+            version: latest + 1,
+            settings: settings.clone(),
+            changed_by,
+            note,
+            changed_at: Utc::now(),
+        };
+        versions.push(version.clone());
+        Ok(version)
+    }
+
+    fn current(&self) -> Result<Vec<MatchingSettingsVersion>> {
+        let versions = self.versions.read().map_err(|_| poisoned())?;
+        let mut current: HashMap<&str, &MatchingSettingsVersion> = HashMap::new();
+        for version in versions.iter() {
+            let entry = current.entry(version.profile.as_str()).or_insert(version);
+            if version.version > entry.version {
+                *entry = version;
+            }
+        }
+        let mut current: Vec<_> = current.into_values().cloned().collect();
+        current.sort_by(|a, b| a.profile.cmp(&b.profile));
+        Ok(current)
+    }
+
+    fn history(&self, profile: &str) -> Result<Vec<MatchingSettingsVersion>> {
+        let versions = self.versions.read().map_err(|_| poisoned())?;
+        let mut history: Vec<_> = versions.iter().filter(|v| v.profile == profile).cloned().collect();
+        history.sort_by_key(|v| std::cmp::Reverse(v.version));
+        Ok(history)
+    }
+
+    fn get_version(&self, profile: &str, version: i32) -> Result<Option<MatchingSettingsVersion>> {
+        let versions = self.versions.read().map_err(|_| poisoned())?;
+        Ok(versions.iter().find(|v| v.profile == profile && v.version == version).cloned())
+    }
+}
+
+/// Patient group repository backed by a map
+#[derive(Default)]
+pub struct InMemoryPatientGroupRepository {
+    groups: RwLock<HashMap<Uuid, PatientGroup>>,
+}
+
+impl InMemoryPatientGroupRepository {
+    /// Create an empty repository
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl PatientGroupRepository for InMemoryPatientGroupRepository {
+    fn create(&self, group: &PatientGroup) -> Result<PatientGroup> {
+        let mut stored = group.clone();
+        stored.members = Vec::new();
+        for patient_id in &group.members {
+            if !stored.members.contains(patient_id) {
+                stored.members.push(*patient_id);
+            }
+        }
+        self.groups.write().map_err(|_| poisoned())?.insert(stored.id, stored.clone());
+        Ok(stored)
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<PatientGroup>> {
+        Ok(self.groups.read().map_err(|_| poisoned())?.get(id).cloned())
+    }
+
+    fn update(&self, group: &PatientGroup) -> Result<Option<PatientGroup>> {
+        let mut groups = self.groups.write().map_err(|_| poisoned())?;
+        let Some(stored) = groups.get_mut(&group.id) else {
+            return Ok(None);
+        };
+        stored.name = group.name.clone();
+        stored.description = group.description.clone();
+        stored.members.retain(|member| group.members.contains(member));
+        for patient_id in &group.members {
+            if !stored.members.contains(patient_id) {
+                stored.members.push(*patient_id);
+            }
+        }
+        stored.updated_at = Utc::now();
+        Ok(Some(stored.clone()))
+    }
+
+    fn delete(&self, id: &Uuid) -> Result<bool> {
+        Ok(self.groups.write().map_err(|_| poisoned())?.remove(id).is_some())
+    }
+
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<PatientGroup>> {
+        let groups = self.groups.read().map_err(|_| poisoned())?;
+        let mut listed: Vec<_> = groups.values().cloned().collect();
+        listed.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(a.id.cmp(&b.id)));
+        Ok(listed.into_iter().skip(offset.max(0) as usize).take(limit.max(0) as usize).collect())
+    }
+
+    fn add_members(&self, id: &Uuid, patient_ids: &[Uuid]) -> Result<Option<PatientGroup>> {
+        let mut groups = self.groups.write().map_err(|_| poisoned())?;
+        let Some(stored) = groups.get_mut(id) else {
+            return Ok(None);
+        };
+        for patient_id in patient_ids {
+            if !stored.members.contains(patient_id) {
+                stored.members.push(*patient_id);
+            }
+        }
+        stored.updated_at = Utc::now();
+        Ok(Some(stored.clone()))
+    }
+
+    fn remove_member(&self, id: &Uuid, patient_id: &Uuid) -> Result<bool> {
+        let mut groups = self.groups.write().map_err(|_| poisoned())?;
+        let Some(stored) = groups.get_mut(id) else {
+            return Ok(false);
+        };
+        let before = stored.members.len();
+        stored.members.retain(|member| member != patient_id);
+        if stored.members.len() == before {
+            return Ok(false);
+        }
+        stored.updated_at = Utc::now();
+        Ok(true)
+    }
+}
+
+/// Bulk import checkpoint repository backed by a map
+#[derive(Default)]
+pub struct InMemoryImportCheckpointRepository {
+    checkpoints: RwLock<HashMap<Uuid, ImportCheckpoint>>,
+}
+
+impl InMemoryImportCheckpointRepository {
+    /// Create an empty repository
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ImportCheckpointRepository for InMemoryImportCheckpointRepository {
+    fn save(&self, checkpoint: &ImportCheckpoint) -> Result<ImportCheckpoint> {
+        let mut saved = checkpoint.clone();
+        saved.updated_at = Utc::now();
+        self.checkpoints
+            .write()
+            .map_err(|_| poisoned())?
+            .insert(saved.id, saved.clone());
+        Ok(saved)
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<ImportCheckpoint>> {
+        Ok(self.checkpoints.read().map_err(|_| poisoned())?.get(id).cloned())
+    }
+
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<ImportCheckpoint>> {
+        let checkpoints = self.checkpoints.read().map_err(|_| poisoned())?;
+        let mut listed: Vec<_> = checkpoints.values().cloned().collect();
+        listed.sort_by_key(|checkpoint| std::cmp::Reverse(checkpoint.created_at));
+        Ok(listed.into_iter().skip(offset.max(0) as usize).take(limit.max(0) as usize).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::patient;
+    use crate::models::{Address, Gender};
+
+    #[test]
+    fn test_patient_soft_delete() {
+        let repository = InMemoryPatientRepository::new();
+        let kept = repository.create(&patient("Okonkwo", &["Ada"], Gender::Female)).unwrap();
+        let deleted = repository.create(&patient("Okafor", &["Ada"], Gender::Female)).unwrap();
+
+        repository.delete(&deleted.id).unwrap();
+
+        assert!(repository.get_by_id(&deleted.id).unwrap().is_none());
+        assert_eq!(repository.search("oko").unwrap().len(), 1);
+        let active = repository.list_active(10, 0).unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].id, kept.id);
+    }
+
+    #[test]
+    fn test_update_keeps_former_addresses() {
+        let address = |line1: &str| Address {
+            line1: Some(line1.to_string()),
+            line2: None,
+            city: Some("Enugu".to_string()),
+            state: None,
+            postal_code: None,
+            country: Some("NG".to_string()),
+            verification: Default::default(),
+            period: None,
+        };
+        let repository = InMemoryPatientRepository::new();
+        let mut record = patient("Nwosu", &["Ada"], Gender::Female);
+        record.addresses.push(address("4 Ogui Road"));
+        let mut record = repository.create(&record).unwrap();
+
+        record.addresses = vec![address("17 Zik Avenue")];
+        let updated = repository.update(&record).unwrap();
+
+        assert_eq!(updated.addresses.len(), 2);
+        assert!(updated.addresses[0].is_current());
+        assert_eq!(updated.addresses[1].line1.as_deref(), Some("4 Ogui Road"));
+        assert!(updated.addresses[1].ended().is_some());
+    }
+
+    #[test]
+    fn test_patient_status_transitions() {
+        use crate::models::{LinkType, PatientLink};
+
+        let repository = InMemoryPatientRepository::new();
+        let mut record = patient("Eze", &["Ada"], Gender::Female);
+        record.deceased = true;
+        let created = repository.create(&record).unwrap();
+        assert_eq!(created.status, PatientStatus::Deceased);
+
+        // A reported death is corrected, then the record is found to be a mistake
+        let corrected = repository.set_status(&created.id, PatientStatus::Active, None).unwrap().unwrap();
+        assert!(corrected.active && !corrected.deceased);
+        let voided = repository.set_status(&created.id, PatientStatus::EnteredInError, None).unwrap().unwrap();
+        assert!(!voided.active);
+
+        // Updates that leave the flags alone keep the status
+        let mut renamed = voided.clone();
+        renamed.name.family = "Ezeh".to_string();
+        renamed.status = PatientStatus::Active;
+        assert_eq!(repository.update(&renamed).unwrap().status, PatientStatus::EnteredInError);
+
+        // Retiring a record in favour of another merges it for good
+        let survivor = repository.create(&patient("Ezeh", &["Ada"], Gender::Female)).unwrap();
+        let mut merged = repository.set_status(&created.id, PatientStatus::Active, None).unwrap().unwrap();
+        merged.active = false;
+        merged.links.push(PatientLink {
+            other_patient_id: survivor.id,
+            link_type: LinkType::ReplacedBy,
+        });
+        let mut merged = repository.update(&merged).unwrap();
+        assert_eq!(merged.status, PatientStatus::Merged);
+        assert!(repository.set_status(&created.id, PatientStatus::Active, None).is_err());
+        merged.active = true;
+        assert!(repository.update(&merged).is_err());
+    }
+
+    #[test]
+    fn test_source_record_links() {
+        let repository = InMemorySourceRecordRepository::new();
+        let first = patient("Ibekwe", &["Ada"], Gender::Female);
+        let second = patient("Ibekwe", &["Ada"], Gender::Female);
+        let record = repository.receive("lab-feed", "L1", &first, None).unwrap();
+
+        repository.link(&record.id, &first.id, Some(0.97), None).unwrap();
+        repository.link(&record.id, &second.id, None, Some("steward".to_string())).unwrap();
+
+        assert!(repository.list_for_patient(&first.id).unwrap().is_empty());
+        assert_eq!(repository.list_for_patient(&second.id).unwrap().len(), 1);
+        assert_eq!(repository.current_link(&record.id).unwrap().unwrap().patient_id, second.id);
+
+        assert!(repository.purge(&record.id).unwrap());
+        assert!(repository.current_link(&record.id).unwrap().is_none());
+        assert_eq!(repository.count_by_source_system("lab-feed").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_feed_writes_keep_source_records() {
+        let source_records = Arc::new(InMemorySourceRecordRepository::new());
+        let repository = InMemoryPatientRepository::new().with_source_records(source_records.clone());
+        let mut submitted = patient("Adeyemi", &["Ada"], Gender::Female);
+        submitted.identifiers.push(crate::models::Identifier::mrn("GENERAL".to_string(), "300".to_string()));
+
+        let created = repository
+            .create_from_source(&submitted, &SourceSubmission::new("adt", None, &submitted))
+            .unwrap();
+        submitted.birth_date = chrono::NaiveDate::from_ymd_opt(1990, 6, 1);
+        let submission = SourceSubmission::new("adt", Some("A-300"), &submitted).with_received_by(Some("adt-feed".to_string()));
+        repository.update_from_source(&submitted, &submission).unwrap();
+
+        let records = source_records.list_for_patient(&created.id).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].source_record_id, "A-300");
+        assert_eq!(records[0].received_by.as_deref(), Some("adt-feed"));
+        assert_eq!(records[1].source_record_id, "300");
+        assert_eq!(source_records.current_link(&records[1].id).unwrap().unwrap().patient_id, created.id);
+    }
+
+    #[test]
+    fn test_link_reverification() {
+        let repository = InMemorySourceRecordRepository::new();
+        let linked = patient("Oyelaran", &["Ada"], Gender::Female);
+        let record = repository.receive("lab-feed", "L2", &linked, None).unwrap();
+
+        let link = repository.link(&record.id, &linked.id, Some(0.91), None).unwrap();
+        assert_eq!(link.confidence, 0.91);
+
+        repository.set_confidence(&link.id, 0.6, true).unwrap();
+        let flagged = repository.list_needing_reverification(10, 0).unwrap();
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].confidence, 0.6);
+
+        let verified = repository.verify_link(&link.id, Some("steward".to_string())).unwrap().unwrap();
+        assert_eq!(verified.confidence, 1.0);
+        assert_eq!(verified.verified_by.as_deref(), Some("steward"));
+        assert!(repository.list_needing_reverification(10, 0).unwrap().is_empty());
+
+        repository.unlink(&record.id, None).unwrap();
+        assert!(repository.verify_link(&link.id, None).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_record_locks() {
+        let repository = InMemoryRecordLockRepository::new();
+        let ids = [Uuid::new_v4(), Uuid::new_v4()];
+
+        let outcome = repository.acquire(&ids, "steward-1", Some("review"), Duration::minutes(5)).unwrap();
+        assert!(matches!(outcome, LockOutcome::Acquired(locks) if locks.len() == 2));
+
+        let outcome = repository.acquire(&ids[1..], "steward-2", None, Duration::minutes(5)).unwrap();
+        assert!(matches!(outcome, LockOutcome::Held(lock) if lock.locked_by == "steward-1"));
+
+        assert!(repository.find_conflict(&ids, Some("steward-1")).unwrap().is_none());
+        assert!(repository.find_conflict(&ids, None).unwrap().is_some());
+        assert!(repository.release(&ids[0]).unwrap());
+    }
+
+    #[test]
+    fn test_change_requests_resolve_once() {
+        use crate::models::change_request::{CHANGE_APPROVED, CHANGE_REJECTED};
+
+        let repository = InMemoryChangeRequestRepository::new();
+        let mut record = patient("Okafor", &["Ada"], Gender::Female);
+        let changes = DemographicChanges {
+            name: Some(patient("Okafor-Eze", &["Ada"], Gender::Female).name),
+            ..Default::default()
+        };
+        let request = repository.submit(record.id, &changes, Some("Married".to_string()), None).unwrap();
+        repository.submit(Uuid::new_v4(), &changes, None, None).unwrap();
+        assert_eq!(repository.list(Some(CHANGE_PENDING), Some(record.id), 10, 0).unwrap().len(), 1);
+
+        assert!(repository.resolve(&request.id, CHANGE_APPROVED, Some("steward".to_string()), None).unwrap());
+        assert!(!repository.resolve(&request.id, CHANGE_REJECTED, None, None).unwrap());
+        let resolved = repository.get_by_id(&request.id).unwrap().unwrap();
+        assert_eq!(resolved.status, CHANGE_APPROVED);
+        assert_eq!(resolved.reviewed_by.as_deref(), Some("steward"));
+
+        resolved.changes.apply_to(&mut record);
+        assert_eq!(record.name.family, "Okafor-Eze");
+        assert_eq!(record.gender, Gender::Female);
+    }
+
+    #[test]
+    fn test_duplicate_decisions_reopen() {
+        use crate::models::duplicate_candidate::{DUPLICATE_CONFIRMED, DUPLICATE_REJECTED};
+        use crate::models::{ReviewAction, ReviewReason};
+
+        let decision = |action, reason_code| ReviewDecision {
+            id: Uuid::new_v4(),
+            duplicate_candidate_id: Uuid::nil(),
+            action,
+            reason_code,
+            reason: None,
+            decided_by: Some("steward".to_string()),
+            decided_at: Utc::now(),
+        };
+        let repository = InMemoryDuplicateCandidateRepository::new();
+        let pair = repository.enqueue(&Uuid::new_v4(), &Uuid::new_v4(), 0.93).unwrap().unwrap();
+
+        let confirm = decision(ReviewAction::Confirm, ReviewReason::SameIdentifier);
+        assert!(repository.decide(&pair.id, PENDING_REVIEW, &confirm).unwrap());
+        // A second steward deciding the same pending pair loses
+        assert!(!repository.decide(&pair.id, PENDING_REVIEW, &confirm).unwrap());
+        assert!(repository.list_pending(10, 0).unwrap().is_empty());
+        assert_eq!(repository.list_decided(10, 0).unwrap()[0].status, DUPLICATE_CONFIRMED);
+
+        let reopen = decision(ReviewAction::Reopen, ReviewReason::DecidedInError);
+        assert!(repository.decide(&pair.id, DUPLICATE_CONFIRMED, &reopen).unwrap());
+        assert_eq!(repository.list_pending(10, 0).unwrap().len(), 1);
+        let reject = decision(ReviewAction::Reject, ReviewReason::MultipleBirth);
+        assert!(repository.decide(&pair.id, PENDING_REVIEW, &reject).unwrap());
+
+        let history = repository.decisions(&pair.id).unwrap();
+        let actions: Vec<_> = history.iter().map(|d| d.action).collect();
+        assert_eq!(actions, vec![ReviewAction::Confirm, ReviewAction::Reopen, ReviewAction::Reject]);
+        assert!(history.iter().all(|d| d.duplicate_candidate_id == pair.id));
+        assert_eq!(repository.get_by_id(&pair.id).unwrap().unwrap().status, DUPLICATE_REJECTED);
+    }
+    #[test]
+    fn test_matching_settings_versions() {
+        let repository = InMemoryMatchingSettingsRepository::new();
+        let strict = MatchingProfile {
+            threshold_score: Some(0.9),
+            ..MatchingProfile::default()
+        };
+        let loose = MatchingProfile {
+            threshold_score: Some(0.6),
+            ..MatchingProfile::default()
+        };
+
+        repository.save("billing", &strict, Some("admin".to_string()), None).unwrap();
+        let latest = repository.save("billing", &loose, None, Some("Too many misses".to_string())).unwrap();
+        repository.save("default", &strict, None, None).unwrap();
+        assert_eq!(latest.version, 2);
+
+        let current = repository.current().unwrap();
+        assert_eq!(current.len(), 2);
+        assert_eq!(current[0], latest);
+        assert_eq!(current[1].version, 1);
+
+        let history = repository.history("billing").unwrap();
+        assert_eq!(history.iter().map(|v| v.version).collect::<Vec<_>>(), vec![2, 1]);
+        assert_eq!(repository.get_version("billing", 1).unwrap().unwrap().settings, strict);
+        assert!(repository.get_version("billing", 3).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_patient_group_membership() {
+        let repository = InMemoryPatientGroupRepository::new();
+        let (first, second, third) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut group = PatientGroup::new("Cluster 17".to_string(), None, Some("steward".to_string()));
+        group.members = vec![first, second, first];
+        let created = repository.create(&group).unwrap();
+        assert_eq!(created.members, vec![first, second]);
+
+        let added = repository.add_members(&group.id, &[second, third]).unwrap().unwrap();
+        assert_eq!(added.members, vec![first, second, third]);
+        assert!(repository.remove_member(&group.id, &first).unwrap());
+        assert!(!repository.remove_member(&group.id, &first).unwrap());
+
+        // Members kept by a replacement keep their place
+        group.name = "Cluster 17, reviewed".to_string();
+        group.members = vec![third, second];
+        let updated = repository.update(&group).unwrap().unwrap();
+        assert_eq!(updated.members, vec![second, third]);
+        assert_eq!(updated.name, "Cluster 17, reviewed");
+
+        assert!(repository.add_members(&Uuid::new_v4(), &[first]).unwrap().is_none());
+        assert!(repository.delete(&group.id).unwrap());
+        assert!(repository.get_by_id(&group.id).unwrap().is_none());
+    }
+}
@@ -0,0 +1,142 @@
+//! Inbound message archive repository
+
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::models::ArchivedMessage;
+use crate::Result;
+use super::models::{DbArchivedMessage, NewDbArchivedMessage};
+use super::schema::{audit_log, message_archive};
+
+/// Message archive repository trait
+pub trait MessageArchiveRepository: Send + Sync {
+    /// Keep the payload behind a change to a patient
+    ///
+    /// The entry is linked to the patient's latest audit log entry for
+    /// `action`, which the repository writes before the change returns.
+    fn archive(
+        &self,
+        patient_id: &Uuid,
+        action: &str,
+        channel: &str,
+        content_type: &str,
+        payload: &str,
+    ) -> Result<ArchivedMessage>;
+
+    /// Get an archived message by ID
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<ArchivedMessage>>;
+
+    /// Get the message behind an audit log entry
+    fn get_for_audit_entry(&self, audit_log_id: &Uuid) -> Result<Option<ArchivedMessage>>;
+
+    /// List the messages that changed a patient, newest first
+    fn list_for_patient(&self, patient_id: &Uuid, limit: i64) -> Result<Vec<ArchivedMessage>>;
+}
+
+/// Diesel-based message archive implementation
+pub struct DieselMessageArchiveRepository {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl DieselMessageArchiveRepository {
+    /// Create a new repository with the given connection pool
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+
+    /// Get a database connection from the pool
+    fn get_conn(&self) -> Result<diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>> {
+        self.pool.get().map_err(|e| crate::Error::Pool(e.to_string()))
+    }
+
+    /// Convert a database entry to the domain model
+    fn to_message(db_message: DbArchivedMessage) -> ArchivedMessage {
+        ArchivedMessage {
+            id: db_message.id,
+            patient_id: db_message.patient_id,
+            audit_log_id: db_message.audit_log_id,
+            action: db_message.action,
+            channel: db_message.channel,
+            content_type: db_message.content_type,
+            payload: db_message.payload,
+            received_at: db_message.received_at,
+        }
+    }
+}
+
+impl MessageArchiveRepository for DieselMessageArchiveRepository {
+    fn archive(
+        &self,
+        patient_id: &Uuid,
+        action: &str,
+        channel: &str,
+        content_type: &str,
+        payload: &str,
+    ) -> Result<ArchivedMessage> {
+        let mut conn = self.get_conn()?;
+
+        let audit_log_id = audit_log::table
+            .filter(audit_log::entity_type.eq("Patient"))
+            .filter(audit_log::entity_id.eq(patient_id))
+            .filter(audit_log::action.eq(action))
+            .order(audit_log::timestamp.desc())
+            .select(audit_log::id)
+            .first::<Uuid>(&mut conn)
+            .optional()?;
+
+        let new_message = NewDbArchivedMessage {
+            patient_id: *patient_id,
+            audit_log_id,
+            action: action.to_string(),
+            channel: channel.to_string(),
+            content_type: content_type.to_string(),
+            payload: payload.to_string(),
+        };
+
+        let db_message: DbArchivedMessage = diesel::insert_into(message_archive::table)
+            .values(&new_message)
+            .returning(DbArchivedMessage::as_returning())
+            .get_result(&mut conn)?;
+
+        Ok(Self::to_message(db_message))
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<ArchivedMessage>> {
+        let mut conn = self.get_conn()?;
+
+        let db_message = message_archive::table
+            .find(id)
+            .select(DbArchivedMessage::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(db_message.map(Self::to_message))
+    }
+
+    fn get_for_audit_entry(&self, audit_log_id: &Uuid) -> Result<Option<ArchivedMessage>> {
+        let mut conn = self.get_conn()?;
+
+        let db_message = message_archive::table
+            .filter(message_archive::audit_log_id.eq(audit_log_id))
+            .select(DbArchivedMessage::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(db_message.map(Self::to_message))
+    }
+
+    fn list_for_patient(&self, patient_id: &Uuid, limit: i64) -> Result<Vec<ArchivedMessage>> {
+        let mut conn = self.get_conn()?;
+
+        let db_messages = message_archive::table
+            .filter(message_archive::patient_id.eq(patient_id))
+            .order(message_archive::received_at.desc())
+            .limit(limit)
+            .select(DbArchivedMessage::as_select())
+            .load(&mut conn)?;
+
+        Ok(db_messages.into_iter().map(Self::to_message).collect())
+    }
+}
@@ -10,21 +10,80 @@ pub mod schema;
 pub mod models;
 pub mod repositories;
 pub mod audit;
+pub mod source_records;
+pub mod match_scores;
+pub mod statistics;
+pub mod matching_kpis;
+pub mod watches;
+pub mod authorities;
+pub mod duplicates;
+pub mod practitioners;
+pub mod record_locks;
+pub mod retention;
+pub mod message_archive;
+pub mod quarantine;
+pub mod mrn_sequences;
+pub mod change_requests;
+pub mod field_provenance;
+pub mod lookup_cache;
+pub mod replica;
+pub mod integrity;
+pub mod matching_settings;
+pub mod groups;
+pub mod import_checkpoints;
+pub mod pool_wait;
+pub mod memory;
 
-pub use repositories::{PatientRepository, DieselPatientRepository, AuditContext};
+pub use repositories::{PatientRepository, PatientCriteria, DieselPatientRepository, AuditContext};
 pub use audit::AuditLogRepository;
+pub use source_records::{SourceRecordRepository, DieselSourceRecordRepository};
+pub use match_scores::MatchScoreRepository;
+pub use statistics::StatisticsRepository;
+pub use matching_kpis::MatchingKpiRepository;
+pub use watches::{WatchRepository, DieselWatchRepository};
+pub use authorities::{AssigningAuthorityRepository, DieselAssigningAuthorityRepository};
+pub use duplicates::{DuplicateCandidateRepository, DieselDuplicateCandidateRepository};
+pub use practitioners::{PractitionerRepository, DieselPractitionerRepository};
+pub use record_locks::{RecordLockRepository, DieselRecordLockRepository, LockOutcome};
+pub use retention::RetentionRepository;
+pub use message_archive::{MessageArchiveRepository, DieselMessageArchiveRepository};
+pub use quarantine::{QuarantineRepository, DieselQuarantineRepository};
+pub use mrn_sequences::{MrnSequenceRepository, DieselMrnSequenceRepository};
+pub use change_requests::{ChangeRequestRepository, DieselChangeRequestRepository};
+pub use field_provenance::{FieldProvenanceRepository, DieselFieldProvenanceRepository};
+pub use lookup_cache::CachedAssigningAuthorityRepository;
+pub use replica::{ReadPool, create_replica_pool};
+pub use integrity::IntegrityRepository;
+pub use matching_settings::{MatchingSettingsRepository, DieselMatchingSettingsRepository};
+pub use groups::{PatientGroupRepository, DieselPatientGroupRepository};
+pub use import_checkpoints::{ImportCheckpointRepository, DieselImportCheckpointRepository};
+pub use pool_wait::PoolWaitMonitor;
+pub use memory::{
+    InMemoryPatientRepository, InMemorySourceRecordRepository, InMemoryWatchRepository,
+    InMemoryRecordLockRepository, InMemoryAssigningAuthorityRepository, InMemoryPairScoreCache,
+    InMemoryDuplicateCandidateRepository, InMemoryPractitionerRepository, InMemoryMessageArchiveRepository,
+    InMemoryQuarantineRepository, InMemoryMrnSequenceRepository, InMemoryChangeRequestRepository,
+    InMemoryFieldProvenanceRepository, InMemoryMatchingSettingsRepository, InMemoryPatientGroupRepository,
+    InMemoryImportCheckpointRepository,
+};
 
 pub type DbPool = Pool<ConnectionManager<PgConnection>>;
 
 /// Create a database connection pool
-pub fn create_pool(config: &DatabaseConfig) -> Result<DbPool> {
+///
+/// Returns the pool with the monitor of its checkout wait times, which load
+/// shedding reads.
+pub fn create_pool(config: &DatabaseConfig) -> Result<(DbPool, PoolWaitMonitor)> {
     let manager = ConnectionManager::<PgConnection>::new(&config.url);
+    let pool_wait = PoolWaitMonitor::default();
 
-    Pool::builder()
+    let pool = Pool::builder()
         .max_size(config.max_connections)
         .min_idle(Some(config.min_connections))
+        .event_handler(Box::new(pool_wait.clone()))
         .build(manager)
-        .map_err(|e| crate::Error::Pool(e.to_string()))
+        .map_err(|e| crate::Error::Pool(e.to_string()))?;
+    Ok((pool, pool_wait))
 }
 
 /// Get a database connection from the pool
@@ -34,6 +34,14 @@ pub struct DbPatient {
     pub updated_by: Option<String>,
     pub deleted_at: Option<DateTime<Utc>>,
     pub deleted_by: Option<String>,
+    pub gender_identity: Option<String>,
+    pub pronouns: Option<String>,
+    pub anonymized_at: Option<DateTime<Utc>>,
+    pub fingerprint: Option<String>,
+    /// Set only by [`PatientRepository::set_confidentiality`](super::PatientRepository::set_confidentiality)
+    pub confidentiality: String,
+    pub photo_hashes: Vec<String>,
+    pub status: String,
 }
 
 /// New patient model (Insertable)
@@ -50,6 +58,11 @@ pub struct NewDbPatient {
     pub multiple_birth: Option<bool>,
     pub managing_organization_id: Option<Uuid>,
     pub created_by: Option<String>,
+    pub gender_identity: Option<String>,
+    pub pronouns: Option<String>,
+    pub fingerprint: Option<String>,
+    pub photo_hashes: Vec<String>,
+    pub status: String,
 }
 
 /// Patient update model
@@ -65,6 +78,12 @@ pub struct UpdateDbPatient {
     pub multiple_birth: Option<bool>,
     pub managing_organization_id: Option<Uuid>,
     pub updated_by: Option<String>,
+    /// Always written, so a removed value is cleared
+    pub gender_identity: Option<Option<String>>,
+    pub pronouns: Option<Option<String>>,
+    pub fingerprint: Option<Option<String>>,
+    pub photo_hashes: Option<Vec<String>>,
+    pub status: Option<String>,
 }
 
 // ============================================================================
@@ -116,6 +135,9 @@ pub struct DbPatientIdentifier {
     pub assigner: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub verification_status: String,
+    pub period_start: Option<DateTime<Utc>>,
+    pub period_end: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Insertable)]
@@ -127,6 +149,9 @@ pub struct NewDbPatientIdentifier {
     pub system: String,
     pub value: String,
     pub assigner: Option<String>,
+    pub verification_status: String,
+    pub period_start: Option<DateTime<Utc>>,
+    pub period_end: Option<DateTime<Utc>>,
 }
 
 // ============================================================================
@@ -149,6 +174,9 @@ pub struct DbPatientAddress {
     pub is_primary: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub verification_status: String,
+    pub period_start: Option<DateTime<Utc>>,
+    pub period_end: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Insertable)]
@@ -163,6 +191,9 @@ pub struct NewDbPatientAddress {
     pub postal_code: Option<String>,
     pub country: Option<String>,
     pub is_primary: bool,
+    pub verification_status: String,
+    pub period_start: Option<DateTime<Utc>>,
+    pub period_end: Option<DateTime<Utc>>,
 }
 
 // ============================================================================
@@ -181,6 +212,8 @@ pub struct DbPatientContact {
     pub is_primary: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub period_start: Option<DateTime<Utc>>,
+    pub period_end: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Insertable)]
@@ -191,6 +224,8 @@ pub struct NewDbPatientContact {
     pub value: String,
     pub use_type: Option<String>,
     pub is_primary: bool,
+    pub period_start: Option<DateTime<Utc>>,
+    pub period_end: Option<DateTime<Utc>>,
 }
 
 // ============================================================================
@@ -218,6 +253,30 @@ pub struct NewDbPatientLink {
     pub created_by: Option<String>,
 }
 
+// ============================================================================
+// Patient Related Person Models
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = patient_related_persons)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbPatientRelatedPerson {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub position: i32,
+    pub contact: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = patient_related_persons)]
+pub struct NewDbPatientRelatedPerson {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub position: i32,
+    pub contact: serde_json::Value,
+}
+
 // ============================================================================
 // Organization Models
 // ============================================================================
@@ -270,9 +329,13 @@ pub struct DbPatientMatchScore {
     pub address_score: Option<bigdecimal::BigDecimal>,
     pub identifier_score: Option<bigdecimal::BigDecimal>,
     pub calculated_at: DateTime<Utc>,
+    pub algorithm_version: Option<String>,
+    pub config_hash: Option<String>,
+    pub patient_updated_at: Option<DateTime<Utc>>,
+    pub candidate_updated_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Clone, Insertable)]
+#[derive(Debug, Clone, Insertable, AsChangeset)]
 #[diesel(table_name = patient_match_scores)]
 pub struct NewDbPatientMatchScore {
     pub patient_id: Uuid,
@@ -283,6 +346,64 @@ pub struct NewDbPatientMatchScore {
     pub gender_score: Option<bigdecimal::BigDecimal>,
     pub address_score: Option<bigdecimal::BigDecimal>,
     pub identifier_score: Option<bigdecimal::BigDecimal>,
+    pub calculated_at: DateTime<Utc>,
+    pub algorithm_version: Option<String>,
+    pub config_hash: Option<String>,
+    pub patient_updated_at: Option<DateTime<Utc>>,
+    pub candidate_updated_at: Option<DateTime<Utc>>,
+}
+
+// ============================================================================
+// Source Record Models
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = source_records)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbSourceRecord {
+    pub id: Uuid,
+    pub source_system: String,
+    pub source_record_id: String,
+    pub payload: serde_json::Value,
+    pub received_at: DateTime<Utc>,
+    pub received_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = source_records)]
+pub struct NewDbSourceRecord {
+    pub source_system: String,
+    pub source_record_id: String,
+    pub payload: serde_json::Value,
+    pub received_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = source_record_links)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbSourceRecordLink {
+    pub id: Uuid,
+    pub source_record_id: Uuid,
+    pub patient_id: Uuid,
+    pub match_score: Option<bigdecimal::BigDecimal>,
+    pub linked_at: DateTime<Utc>,
+    pub linked_by: Option<String>,
+    pub unlinked_at: Option<DateTime<Utc>>,
+    pub unlinked_by: Option<String>,
+    pub confidence: bigdecimal::BigDecimal,
+    pub needs_reverification: bool,
+    pub verified_at: Option<DateTime<Utc>>,
+    pub verified_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = source_record_links)]
+pub struct NewDbSourceRecordLink {
+    pub source_record_id: Uuid,
+    pub patient_id: Uuid,
+    pub match_score: Option<bigdecimal::BigDecimal>,
+    pub linked_by: Option<String>,
+    pub confidence: bigdecimal::BigDecimal,
 }
 
 // ============================================================================
@@ -317,3 +438,346 @@ pub struct NewDbAuditLog {
     pub ip_address: Option<String>,
     pub user_agent: Option<String>,
 }
+
+// ============================================================================
+// Watch Models
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = patient_watches)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbPatientWatch {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub callback_url: Option<String>,
+    pub event_types: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub created_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = patient_watches)]
+pub struct NewDbPatientWatch {
+    pub patient_id: Uuid,
+    pub callback_url: Option<String>,
+    pub event_types: Vec<String>,
+    pub created_by: Option<String>,
+}
+
+// ============================================================================
+// Assigning Authority Models
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = assigning_authorities)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbAssigningAuthority {
+    pub id: Uuid,
+    pub system: String,
+    pub oid: Option<String>,
+    pub name: String,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = assigning_authorities)]
+pub struct NewDbAssigningAuthority {
+    pub system: String,
+    pub oid: Option<String>,
+    pub name: String,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, AsChangeset)]
+#[diesel(table_name = assigning_authorities)]
+#[diesel(treat_none_as_null = true)]
+pub struct UpdateDbAssigningAuthority {
+    pub system: String,
+    pub oid: Option<String>,
+    pub name: String,
+    pub active: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+// ============================================================================
+// Duplicate Candidate Models
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = duplicate_candidates)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbDuplicateCandidate {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub candidate_id: Uuid,
+    pub score: bigdecimal::BigDecimal,
+    pub status: String,
+    pub detected_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = duplicate_candidates)]
+pub struct NewDbDuplicateCandidate {
+    pub patient_id: Uuid,
+    pub candidate_id: Uuid,
+    pub score: bigdecimal::BigDecimal,
+}
+
+#[derive(Debug, Clone, Queryable, Selectable, Insertable)]
+#[diesel(table_name = duplicate_decisions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbDuplicateDecision {
+    pub id: Uuid,
+    pub duplicate_candidate_id: Uuid,
+    pub action: String,
+    pub reason_code: String,
+    pub reason: Option<String>,
+    pub decided_by: Option<String>,
+    pub decided_at: DateTime<Utc>,
+}
+
+// ============================================================================
+// Practitioner Models
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = practitioners)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbPractitioner {
+    pub id: Uuid,
+    pub npi: Option<String>,
+    pub family_name: String,
+    pub active: bool,
+    pub resource: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = practitioners)]
+pub struct NewDbPractitioner {
+    pub id: Uuid,
+    pub npi: Option<String>,
+    pub family_name: String,
+    pub active: bool,
+    pub resource: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, AsChangeset)]
+#[diesel(table_name = practitioners)]
+#[diesel(treat_none_as_null = true)]
+pub struct UpdateDbPractitioner {
+    pub npi: Option<String>,
+    pub family_name: String,
+    pub active: bool,
+    pub resource: serde_json::Value,
+    pub updated_at: DateTime<Utc>,
+}
+
+// ============================================================================
+// Record Lock Models
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, QueryableByName)]
+#[diesel(table_name = record_locks)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbRecordLock {
+    pub patient_id: Uuid,
+    pub locked_by: String,
+    pub reason: Option<String>,
+    pub acquired_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+// ============================================================================
+// Reporting Models
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Insertable, AsChangeset)]
+#[diesel(table_name = matching_kpis_daily)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbMatchingKpis {
+    pub day: NaiveDate,
+    pub source_system: String,
+    pub records_received: i64,
+    pub new_patients: i64,
+    pub duplicates_created: i64,
+    pub auto_links: i64,
+    pub manual_links: i64,
+    pub average_match_score: Option<f64>,
+    pub overlay_incidents: i64,
+    pub computed_at: DateTime<Utc>,
+}
+
+// ============================================================================
+// Message Archive Models
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = message_archive)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbArchivedMessage {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub audit_log_id: Option<Uuid>,
+    pub action: String,
+    pub channel: String,
+    pub content_type: String,
+    pub payload: String,
+    pub received_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = message_archive)]
+pub struct NewDbArchivedMessage {
+    pub patient_id: Uuid,
+    pub audit_log_id: Option<Uuid>,
+    pub action: String,
+    pub channel: String,
+    pub content_type: String,
+    pub payload: String,
+}
+
+// ============================================================================
+// Quarantine Models
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = quarantined_records)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbQuarantinedRecord {
+    pub id: Uuid,
+    pub channel: String,
+    pub content_type: String,
+    pub payload: String,
+    pub errors: serde_json::Value,
+    pub patient_id: Option<Uuid>,
+    pub status: String,
+    pub received_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub resolved_patient_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = quarantined_records)]
+pub struct NewDbQuarantinedRecord {
+    pub channel: String,
+    pub content_type: String,
+    pub payload: String,
+    pub errors: serde_json::Value,
+    pub patient_id: Option<Uuid>,
+}
+
+// ============================================================================
+// Change Request Models
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = change_requests)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbChangeRequest {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub changes: serde_json::Value,
+    pub reason: Option<String>,
+    pub submitted_by: Option<String>,
+    pub status: String,
+    pub submitted_at: DateTime<Utc>,
+    pub reviewed_by: Option<String>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub review_note: Option<String>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = change_requests)]
+pub struct NewDbChangeRequest {
+    pub patient_id: Uuid,
+    pub changes: serde_json::Value,
+    pub reason: Option<String>,
+    pub submitted_by: Option<String>,
+}
+
+// ============================================================================
+// Field Provenance Models
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Insertable)]
+#[diesel(table_name = patient_field_provenance)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbFieldProvenance {
+    pub patient_id: Uuid,
+    pub field: String,
+    pub source: String,
+    pub recorded_at: DateTime<Utc>,
+}
+
+// ============================================================================
+// Matching Settings Models
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Insertable)]
+#[diesel(table_name = matching_settings)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbMatchingSettings {
+    pub id: Uuid,
+    pub profile: String,
+    pub version: i32,
+    pub settings: serde_json::Value,
+    pub changed_by: Option<String>,
+    pub note: Option<String>,
+    pub changed_at: DateTime<Utc>,
+}
+
+// ============================================================================
+// Patient Group Models
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Insertable)]
+#[diesel(table_name = patient_groups)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbPatientGroup {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Queryable, Selectable, Insertable)]
+#[diesel(table_name = patient_group_members)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbPatientGroupMember {
+    pub group_id: Uuid,
+    pub patient_id: Uuid,
+    pub added_at: DateTime<Utc>,
+}
+
+// ============================================================================
+// Import Checkpoint Models
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Insertable, AsChangeset)]
+#[diesel(table_name = import_checkpoints)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(treat_none_as_null = true)]
+pub struct DbImportCheckpoint {
+    pub id: Uuid,
+    pub path: String,
+    pub requested_by: Option<String>,
+    pub status: String,
+    pub job_id: Uuid,
+    pub byte_offset: i64,
+    pub lines_processed: i64,
+    pub created: i64,
+    pub duplicates: i64,
+    pub invalid: i64,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
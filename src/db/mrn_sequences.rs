@@ -0,0 +1,63 @@
+//! MRN sequence repository
+
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::sql_types::{BigInt, Text};
+use diesel::PgConnection;
+
+use crate::Result;
+
+/// Take the next value of a sequence, creating it at `$2` on first use
+///
+/// The upsert locks the sequence's row until commit, so concurrent callers
+/// queue behind each other and each receives a different value. A start
+/// raised in configuration above the stored value takes effect.
+const ALLOCATE_SQL: &str = "
+    INSERT INTO mrn_sequences (system, next_value)
+    VALUES ($1, $2 + 1)
+    ON CONFLICT (system) DO UPDATE
+        SET next_value = GREATEST(mrn_sequences.next_value, $2) + 1,
+            updated_at = CURRENT_TIMESTAMP
+    RETURNING next_value - 1 AS value";
+
+#[derive(QueryableByName)]
+struct AllocatedRow {
+    #[diesel(sql_type = BigInt)]
+    value: i64,
+}
+
+/// MRN sequence repository trait
+pub trait MrnSequenceRepository: Send + Sync {
+    /// Allocate the next value of `system`'s sequence, never below `start`
+    fn allocate(&self, system: &str, start: i64) -> Result<i64>;
+}
+
+/// Diesel-based MRN sequence repository implementation
+pub struct DieselMrnSequenceRepository {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl DieselMrnSequenceRepository {
+    /// Create a new repository with the given connection pool
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+
+    /// Get a database connection from the pool
+    fn get_conn(&self) -> Result<diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>> {
+        self.pool.get().map_err(|e| crate::Error::Pool(e.to_string()))
+    }
+}
+
+impl MrnSequenceRepository for DieselMrnSequenceRepository {
+    fn allocate(&self, system: &str, start: i64) -> Result<i64> {
+        let mut conn = self.get_conn()?;
+
+        let row: AllocatedRow = diesel::sql_query(ALLOCATE_SQL)
+            .bind::<Text, _>(system)
+            .bind::<BigInt, _>(start)
+            .get_result(&mut conn)?;
+
+        Ok(row.value)
+    }
+}
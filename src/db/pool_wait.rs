@@ -0,0 +1,81 @@
+//! Connection pool wait tracking
+//!
+//! [`super::create_pool`] returns each pool with the [`PoolWaitMonitor`] its
+//! checkouts are reported to. Load shedding rejects requests while its
+//! average is above `load_shedding.max_pool_wait_ms`.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use diesel::r2d2::{event::CheckoutEvent, event::TimeoutEvent, HandleEvent};
+
+/// Weight of the newest checkout in the running average
+const SMOOTHING: f64 = 0.2;
+
+/// An average older than this no longer describes the pool
+///
+/// While requests are shed there are few checkouts, so without this the last
+/// slow average would keep the server shedding forever.
+const STALE_AFTER: Duration = Duration::from_secs(5);
+
+/// Running average of the time spent waiting for a pool connection
+#[derive(Debug, Clone, Default)]
+pub struct PoolWaitMonitor {
+    state: Arc<Mutex<PoolWaitState>>,
+}
+
+#[derive(Debug, Default)]
+struct PoolWaitState {
+    average_secs: f64,
+    updated: Option<Instant>,
+}
+
+impl PoolWaitMonitor {
+    /// Record one wait for a connection
+    pub fn record(&self, wait: Duration) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let wait = wait.as_secs_f64();
+        state.average_secs = match state.updated {
+            Some(updated) if updated.elapsed() < STALE_AFTER => {
+                state.average_secs + SMOOTHING * (wait - state.average_secs)
+            }
+            _ => wait,
+        };
+        state.updated = Some(Instant::now());
+    }
+
+    /// Recent average wait, or zero without recent checkouts
+    pub fn average_wait(&self) -> Duration {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.updated {
+            Some(updated) if updated.elapsed() < STALE_AFTER => Duration::from_secs_f64(state.average_secs),
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+impl HandleEvent for PoolWaitMonitor {
+    fn handle_checkout(&self, event: CheckoutEvent) {
+        self.record(event.duration());
+    }
+
+    fn handle_timeout(&self, event: TimeoutEvent) {
+        self.record(event.timeout());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_wait_average() {
+        let monitor = PoolWaitMonitor::default();
+        assert_eq!(monitor.average_wait(), Duration::ZERO);
+
+        monitor.record(Duration::from_millis(100));
+        monitor.record(Duration::from_millis(200));
+        let average = monitor.average_wait().as_secs_f64();
+        assert!((average - 0.120).abs() < 1e-9, "average {}", average);
+    }
+}
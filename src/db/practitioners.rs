@@ -0,0 +1,193 @@
+//! Practitioner repository
+
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::models::Practitioner;
+use crate::Result;
+use super::models::{DbPractitioner, NewDbPractitioner, UpdateDbPractitioner};
+use super::schema::practitioners;
+
+/// Practitioner repository trait
+pub trait PractitionerRepository: Send + Sync {
+    /// Create a new practitioner
+    fn create(&self, practitioner: &Practitioner) -> Result<Practitioner>;
+
+    /// Get a practitioner by ID
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<Practitioner>>;
+
+    /// Replace a practitioner, returning `None` if it does not exist
+    fn update(&self, practitioner: &Practitioner) -> Result<Option<Practitioner>>;
+
+    /// Delete a practitioner (soft delete), returning whether it existed
+    fn delete(&self, id: &Uuid) -> Result<bool>;
+
+    /// List non-deleted practitioners, oldest first
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<Practitioner>>;
+
+    /// Non-deleted practitioners holding an NPI
+    fn find_by_npi(&self, npi: &str) -> Result<Vec<Practitioner>>;
+
+    /// Non-deleted practitioners with a family name, ignoring case
+    fn find_by_family_name(&self, family: &str, limit: i64) -> Result<Vec<Practitioner>>;
+
+    /// Another practitioner already holding the NPI of `practitioner`, if any
+    fn npi_holder(&self, practitioner: &Practitioner) -> Result<Option<Uuid>> {
+        let Some(npi) = practitioner.npi() else {
+            return Ok(None);
+        };
+        Ok(self.find_by_npi(npi)?.into_iter().map(|p| p.id).find(|id| *id != practitioner.id))
+    }
+}
+
+/// Diesel-based practitioner repository implementation
+pub struct DieselPractitionerRepository {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl DieselPractitionerRepository {
+    /// Create a new repository with the given connection pool
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+
+    /// Get a database connection from the pool
+    fn get_conn(&self) -> Result<diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>> {
+        self.pool.get().map_err(|e| crate::Error::Pool(e.to_string()))
+    }
+
+    /// Convert a database practitioner to the domain model
+    fn to_practitioner(db_practitioner: DbPractitioner) -> Result<Practitioner> {
+        let mut practitioner: Practitioner = serde_json::from_value(db_practitioner.resource)
+            .map_err(|e| crate::Error::Internal(format!("Invalid practitioner resource: {}", e)))?;
+        practitioner.id = db_practitioner.id;
+        practitioner.active = db_practitioner.active;
+        practitioner.created_at = db_practitioner.created_at;
+        practitioner.updated_at = db_practitioner.updated_at;
+        Ok(practitioner)
+    }
+
+    fn to_resource(practitioner: &Practitioner) -> Result<serde_json::Value> {
+        serde_json::to_value(practitioner)
+            .map_err(|e| crate::Error::Internal(format!("Failed to serialize practitioner: {}", e)))
+    }
+}
+
+impl PractitionerRepository for DieselPractitionerRepository {
+    fn create(&self, practitioner: &Practitioner) -> Result<Practitioner> {
+        let mut conn = self.get_conn()?;
+
+        let new_practitioner = NewDbPractitioner {
+            id: practitioner.id,
+            npi: practitioner.npi().map(str::to_string),
+            family_name: practitioner.name.family.clone(),
+            active: practitioner.active,
+            resource: Self::to_resource(practitioner)?,
+            created_at: practitioner.created_at,
+            updated_at: practitioner.updated_at,
+        };
+
+        let db_practitioner = diesel::insert_into(practitioners::table)
+            .values(&new_practitioner)
+            .returning(DbPractitioner::as_returning())
+            .get_result(&mut conn)?;
+
+        Self::to_practitioner(db_practitioner)
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<Practitioner>> {
+        let mut conn = self.get_conn()?;
+
+        let db_practitioner = practitioners::table
+            .find(id)
+            .filter(practitioners::deleted_at.is_null())
+            .select(DbPractitioner::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        db_practitioner.map(Self::to_practitioner).transpose()
+    }
+
+    fn update(&self, practitioner: &Practitioner) -> Result<Option<Practitioner>> {
+        let mut conn = self.get_conn()?;
+
+        let changes = UpdateDbPractitioner {
+            npi: practitioner.npi().map(str::to_string),
+            family_name: practitioner.name.family.clone(),
+            active: practitioner.active,
+            resource: Self::to_resource(practitioner)?,
+            updated_at: Utc::now(),
+        };
+
+        let db_practitioner = diesel::update(
+            practitioners::table
+                .find(practitioner.id)
+                .filter(practitioners::deleted_at.is_null()),
+        )
+        .set(&changes)
+        .returning(DbPractitioner::as_returning())
+        .get_result(&mut conn)
+        .optional()?;
+
+        db_practitioner.map(Self::to_practitioner).transpose()
+    }
+
+    fn delete(&self, id: &Uuid) -> Result<bool> {
+        let mut conn = self.get_conn()?;
+
+        let deleted = diesel::update(
+            practitioners::table
+                .find(id)
+                .filter(practitioners::deleted_at.is_null()),
+        )
+        .set(practitioners::deleted_at.eq(Some(Utc::now())))
+        .execute(&mut conn)?;
+
+        Ok(deleted > 0)
+    }
+
+    fn list(&self, limit: i64, offset: i64) -> Result<Vec<Practitioner>> {
+        let mut conn = self.get_conn()?;
+
+        let db_practitioners = practitioners::table
+            .filter(practitioners::deleted_at.is_null())
+            .order((practitioners::created_at.asc(), practitioners::id.asc()))
+            .limit(limit)
+            .offset(offset)
+            .select(DbPractitioner::as_select())
+            .load(&mut conn)?;
+
+        db_practitioners.into_iter().map(Self::to_practitioner).collect()
+    }
+
+    fn find_by_npi(&self, npi: &str) -> Result<Vec<Practitioner>> {
+        let mut conn = self.get_conn()?;
+
+        let db_practitioners = practitioners::table
+            .filter(practitioners::deleted_at.is_null())
+            .filter(practitioners::npi.eq(npi.trim()))
+            .select(DbPractitioner::as_select())
+            .load(&mut conn)?;
+
+        db_practitioners.into_iter().map(Self::to_practitioner).collect()
+    }
+
+    fn find_by_family_name(&self, family: &str, limit: i64) -> Result<Vec<Practitioner>> {
+        use diesel::dsl::sql;
+        use diesel::sql_types::{Bool, Text};
+
+        let mut conn = self.get_conn()?;
+
+        let db_practitioners = practitioners::table
+            .filter(practitioners::deleted_at.is_null())
+            .filter(sql::<Bool>("lower(family_name) = lower(").bind::<Text, _>(family.trim()).sql(")"))
+            .limit(limit)
+            .select(DbPractitioner::as_select())
+            .load(&mut conn)?;
+
+        db_practitioners.into_iter().map(Self::to_practitioner).collect()
+    }
+}
@@ -0,0 +1,166 @@
+//! Quarantine repository for refused inbound records
+
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::models::quarantined_record::QUARANTINE_PENDING;
+use crate::models::QuarantinedRecord;
+use crate::Result;
+use super::models::{DbQuarantinedRecord, NewDbQuarantinedRecord};
+use super::schema::quarantined_records;
+
+/// Quarantine repository trait
+pub trait QuarantineRepository: Send + Sync {
+    /// Park a refused payload with its errors
+    fn quarantine(
+        &self,
+        channel: &str,
+        content_type: &str,
+        payload: &str,
+        errors: &serde_json::Value,
+        patient_id: Option<Uuid>,
+    ) -> Result<QuarantinedRecord>;
+
+    /// Get a quarantined record by ID
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<QuarantinedRecord>>;
+
+    /// List records, optionally only those in one status, oldest first
+    fn list(&self, status: Option<&str>, limit: i64, offset: i64) -> Result<Vec<QuarantinedRecord>>;
+
+    /// Replace a pending record's payload and errors after a failed resubmission
+    fn revise(&self, id: &Uuid, payload: &str, errors: &serde_json::Value) -> Result<bool>;
+
+    /// Close a pending record as resubmitted or discarded, returning whether
+    /// it was still pending
+    fn resolve(&self, id: &Uuid, status: &str, payload: &str, patient_id: Option<Uuid>) -> Result<bool>;
+}
+
+/// Diesel-based quarantine repository implementation
+pub struct DieselQuarantineRepository {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl DieselQuarantineRepository {
+    /// Create a new repository with the given connection pool
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+
+    /// Get a database connection from the pool
+    fn get_conn(&self) -> Result<diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>> {
+        self.pool.get().map_err(|e| crate::Error::Pool(e.to_string()))
+    }
+
+    /// Convert a database record to the domain model
+    fn to_record(db_record: DbQuarantinedRecord) -> QuarantinedRecord {
+        QuarantinedRecord {
+            id: db_record.id,
+            channel: db_record.channel,
+            content_type: db_record.content_type,
+            payload: db_record.payload,
+            errors: db_record.errors,
+            patient_id: db_record.patient_id,
+            status: db_record.status,
+            received_at: db_record.received_at,
+            resolved_at: db_record.resolved_at,
+            resolved_patient_id: db_record.resolved_patient_id,
+        }
+    }
+}
+
+impl QuarantineRepository for DieselQuarantineRepository {
+    fn quarantine(
+        &self,
+        channel: &str,
+        content_type: &str,
+        payload: &str,
+        errors: &serde_json::Value,
+        patient_id: Option<Uuid>,
+    ) -> Result<QuarantinedRecord> {
+        let mut conn = self.get_conn()?;
+
+        let new_record = NewDbQuarantinedRecord {
+            channel: channel.to_string(),
+            content_type: content_type.to_string(),
+            payload: payload.to_string(),
+            errors: errors.clone(),
+            patient_id,
+        };
+
+        let db_record: DbQuarantinedRecord = diesel::insert_into(quarantined_records::table)
+            .values(&new_record)
+            .returning(DbQuarantinedRecord::as_returning())
+            .get_result(&mut conn)?;
+
+        Ok(Self::to_record(db_record))
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<QuarantinedRecord>> {
+        let mut conn = self.get_conn()?;
+
+        let db_record = quarantined_records::table
+            .find(id)
+            .select(DbQuarantinedRecord::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(db_record.map(Self::to_record))
+    }
+
+    fn list(&self, status: Option<&str>, limit: i64, offset: i64) -> Result<Vec<QuarantinedRecord>> {
+        let mut conn = self.get_conn()?;
+
+        let mut query = quarantined_records::table
+            .select(DbQuarantinedRecord::as_select())
+            .into_boxed();
+        if let Some(status) = status {
+            query = query.filter(quarantined_records::status.eq(status));
+        }
+        let db_records = query
+            .order((quarantined_records::received_at.asc(), quarantined_records::id.asc()))
+            .limit(limit)
+            .offset(offset)
+            .load(&mut conn)?;
+
+        Ok(db_records.into_iter().map(Self::to_record).collect())
+    }
+
+    fn revise(&self, id: &Uuid, payload: &str, errors: &serde_json::Value) -> Result<bool> {
+        let mut conn = self.get_conn()?;
+
+        let updated = diesel::update(
+            quarantined_records::table
+                .find(id)
+                .filter(quarantined_records::status.eq(QUARANTINE_PENDING)),
+        )
+        .set((
+            quarantined_records::payload.eq(payload),
+            quarantined_records::errors.eq(errors),
+        ))
+        .execute(&mut conn)?;
+
+        Ok(updated > 0)
+    }
+
+    fn resolve(&self, id: &Uuid, status: &str, payload: &str, patient_id: Option<Uuid>) -> Result<bool> {
+        let mut conn = self.get_conn()?;
+
+        let updated = diesel::update(
+            quarantined_records::table
+                .find(id)
+                .filter(quarantined_records::status.eq(QUARANTINE_PENDING)),
+        )
+        .set((
+            quarantined_records::status.eq(status),
+            quarantined_records::payload.eq(payload),
+            quarantined_records::resolved_at.eq(Some(Utc::now())),
+            quarantined_records::resolved_patient_id.eq(patient_id),
+        ))
+        .execute(&mut conn)?;
+
+        Ok(updated > 0)
+    }
+}
@@ -0,0 +1,197 @@
+//! Record lock repository
+
+use chrono::Duration;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::sql_types::{Array, Double, Nullable, Text, Uuid as SqlUuid};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::models::RecordLock;
+use crate::Result;
+use super::models::DbRecordLock;
+use super::schema::record_locks;
+
+/// Take or renew locks, skipping records locked by someone else
+///
+/// The conditional `DO UPDATE` only overwrites expired locks and the
+/// caller's own, so concurrent requests for the same record serialize on
+/// its primary key and exactly one of them gets it.
+const ACQUIRE_SQL: &str = r#"
+    INSERT INTO record_locks (patient_id, locked_by, reason, acquired_at, expires_at)
+    SELECT id, $2, $3, now(), now() + ($4 * interval '1 second')
+    FROM unnest($1::uuid[]) AS id
+    ON CONFLICT (patient_id) DO UPDATE SET
+        locked_by = EXCLUDED.locked_by,
+        reason = EXCLUDED.reason,
+        acquired_at = CASE
+            WHEN record_locks.locked_by = EXCLUDED.locked_by AND record_locks.expires_at > now()
+            THEN record_locks.acquired_at
+            ELSE EXCLUDED.acquired_at
+        END,
+        expires_at = EXCLUDED.expires_at
+    WHERE record_locks.expires_at <= now() OR record_locks.locked_by = EXCLUDED.locked_by
+    RETURNING patient_id, locked_by, reason, acquired_at, expires_at
+"#;
+
+/// Result of a lock request
+#[derive(Debug, Clone)]
+pub enum LockOutcome {
+    /// Every requested record is now locked by the caller
+    Acquired(Vec<RecordLock>),
+    /// A record is locked by someone else; nothing was locked
+    Held(RecordLock),
+}
+
+/// Record lock repository trait
+pub trait RecordLockRepository: Send + Sync {
+    /// Lock all of the given patients for `locked_by`, or none of them
+    ///
+    /// Locks the caller already holds are renewed.
+    fn acquire(
+        &self,
+        patient_ids: &[Uuid],
+        locked_by: &str,
+        reason: Option<&str>,
+        ttl: Duration,
+    ) -> Result<LockOutcome>;
+
+    /// The unexpired lock on a patient, if any
+    fn get(&self, patient_id: &Uuid) -> Result<Option<RecordLock>>;
+
+    /// Remove a patient's lock, returning whether one was held
+    fn release(&self, patient_id: &Uuid) -> Result<bool>;
+
+    /// The first unexpired lock on any of the patients that `holder` does not hold
+    fn find_conflict(&self, patient_ids: &[Uuid], holder: Option<&str>) -> Result<Option<RecordLock>>;
+}
+
+/// Diesel-based record lock repository implementation
+pub struct DieselRecordLockRepository {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+/// Rolls back a partial acquisition
+enum AcquireError {
+    Held,
+    Database(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for AcquireError {
+    fn from(err: diesel::result::Error) -> Self {
+        AcquireError::Database(err)
+    }
+}
+
+impl DieselRecordLockRepository {
+    /// Create a new repository with the given connection pool
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+
+    /// Get a database connection from the pool
+    fn get_conn(&self) -> Result<diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>> {
+        self.pool.get().map_err(|e| crate::Error::Pool(e.to_string()))
+    }
+
+    /// Convert a database lock to the domain model
+    fn to_lock(db_lock: DbRecordLock) -> RecordLock {
+        RecordLock {
+            patient_id: db_lock.patient_id,
+            locked_by: db_lock.locked_by,
+            reason: db_lock.reason,
+            acquired_at: db_lock.acquired_at,
+            expires_at: db_lock.expires_at,
+        }
+    }
+}
+
+impl RecordLockRepository for DieselRecordLockRepository {
+    fn acquire(
+        &self,
+        patient_ids: &[Uuid],
+        locked_by: &str,
+        reason: Option<&str>,
+        ttl: Duration,
+    ) -> Result<LockOutcome> {
+        let mut ids = patient_ids.to_vec();
+        ids.sort();
+        ids.dedup();
+
+        // A lock released between the failed insert and the conflict lookup
+        // leaves nothing to report, so try again
+        for _ in 0..3 {
+            let mut conn = self.get_conn()?;
+            let acquired = conn.transaction::<_, AcquireError, _>(|conn| {
+                let locks: Vec<DbRecordLock> = diesel::sql_query(ACQUIRE_SQL)
+                    .bind::<Array<SqlUuid>, _>(&ids)
+                    .bind::<Text, _>(locked_by)
+                    .bind::<Nullable<Text>, _>(reason)
+                    .bind::<Double, _>(ttl.num_milliseconds() as f64 / 1000.0)
+                    .load(conn)?;
+                if locks.len() < ids.len() {
+                    return Err(AcquireError::Held);
+                }
+                Ok(locks)
+            });
+
+            match acquired {
+                Ok(locks) => return Ok(LockOutcome::Acquired(locks.into_iter().map(Self::to_lock).collect())),
+                Err(AcquireError::Database(e)) => return Err(e.into()),
+                Err(AcquireError::Held) => {
+                    if let Some(held) = self.find_conflict(&ids, Some(locked_by))? {
+                        return Ok(LockOutcome::Held(held));
+                    }
+                }
+            }
+        }
+
+        Err(crate::Error::Internal("Record locks are changing too quickly to acquire".to_string()))
+    }
+
+    fn get(&self, patient_id: &Uuid) -> Result<Option<RecordLock>> {
+        let mut conn = self.get_conn()?;
+
+        let db_lock = record_locks::table
+            .find(patient_id)
+            .filter(record_locks::expires_at.gt(diesel::dsl::now))
+            .select(DbRecordLock::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(db_lock.map(Self::to_lock))
+    }
+
+    fn release(&self, patient_id: &Uuid) -> Result<bool> {
+        let mut conn = self.get_conn()?;
+
+        let deleted = diesel::delete(
+            record_locks::table
+                .find(patient_id)
+                .filter(record_locks::expires_at.gt(diesel::dsl::now)),
+        )
+        .execute(&mut conn)?;
+
+        Ok(deleted > 0)
+    }
+
+    fn find_conflict(&self, patient_ids: &[Uuid], holder: Option<&str>) -> Result<Option<RecordLock>> {
+        let mut conn = self.get_conn()?;
+
+        let mut query = record_locks::table
+            .filter(record_locks::patient_id.eq_any(patient_ids))
+            .filter(record_locks::expires_at.gt(diesel::dsl::now))
+            .into_boxed();
+        if let Some(holder) = holder {
+            query = query.filter(record_locks::locked_by.ne(holder));
+        }
+
+        let db_lock = query
+            .order(record_locks::acquired_at.asc())
+            .select(DbRecordLock::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(db_lock.map(Self::to_lock))
+    }
+}
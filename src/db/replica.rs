@@ -0,0 +1,77 @@
+//! Routing of reads to a Postgres read replica
+//!
+//! Registration lookups read far more than they write. With
+//! `database.read_replica_url` set, patient reads, searches and audit
+//! queries are served from a streaming replica while every write, and
+//! every read a write depends on, stays on the primary. Checkouts from the
+//! replica go through the `read_replica` circuit breaker: while the replica
+//! is down, reads fall back to the primary at once instead of waiting for
+//! a connection timeout each.
+//!
+//! A replica lags the primary by its replication delay, so a read straight
+//! after a write may not see it yet.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use diesel::pg::PgConnection;
+use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
+
+use crate::circuit_breaker::CircuitBreaker;
+use crate::config::DatabaseConfig;
+use crate::{Error, Result};
+use super::DbPool;
+
+/// Longest wait for a replica connection before the breaker counts a failure
+const REPLICA_CHECKOUT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Connections for reads: from the replica when one is configured and up,
+/// otherwise from the primary
+#[derive(Clone)]
+pub struct ReadPool {
+    primary: DbPool,
+    replica: Option<(DbPool, Arc<CircuitBreaker>)>,
+}
+
+impl ReadPool {
+    /// Read from the primary only
+    pub fn new(primary: DbPool) -> Self {
+        Self { primary, replica: None }
+    }
+
+    /// Read from `replica` while `breaker` lets calls through
+    pub fn with_replica(mut self, replica: DbPool, breaker: Arc<CircuitBreaker>) -> Self {
+        self.replica = Some((replica, breaker));
+        self
+    }
+
+    /// Whether reads are routed to a replica
+    pub fn has_replica(&self) -> bool {
+        self.replica.is_some()
+    }
+
+    /// A connection to read from, falling back to the primary
+    pub fn get(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>> {
+        if let Some((replica, breaker)) = &self.replica {
+            match breaker.call(|| replica.get().map_err(|e| Error::Pool(e.to_string()))) {
+                Ok(conn) => return Ok(conn),
+                Err(e) => tracing::debug!("Reading from the primary: {}", e),
+            }
+        }
+        self.primary.get().map_err(|e| Error::Pool(e.to_string()))
+    }
+}
+
+/// Pool for the configured read replica, if any
+///
+/// The pool is created without connecting, so a replica that is down at
+/// startup only sends reads to the primary until it is back.
+pub fn create_replica_pool(config: &DatabaseConfig) -> Option<DbPool> {
+    let url = config.read_replica_url.as_deref()?;
+    let pool = Pool::builder()
+        .max_size(config.max_connections)
+        .min_idle(Some(0))
+        .connection_timeout(REPLICA_CHECKOUT_TIMEOUT)
+        .build_unchecked(ConnectionManager::<PgConnection>::new(url));
+    Some(pool)
+}
@@ -5,7 +5,10 @@ use diesel::r2d2::{ConnectionManager, Pool};
 use chrono::Utc;
 use uuid::Uuid;
 
-use crate::models::{Patient, HumanName, Address, ContactPoint, Identifier, PatientLink};
+use crate::models::{
+    Confidentiality, Patient, PatientStatus, HumanName, Address, ContactPoint, Identifier, PatientContact, PatientLink, Period,
+    SourceSubmission, VerificationStatus,
+};
 use crate::Result;
 use super::models::*;
 use super::schema::*;
@@ -28,30 +31,124 @@ impl Default for AuditContext {
     }
 }
 
+/// Column predicates a patient scan applies in the database
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PatientCriteria {
+    /// Only patients with this `active` flag; any patient when `None`
+    pub active: Option<bool>,
+    /// Only patients with a name whose family name, lowercased, is this
+    pub family: Option<String>,
+    /// Only patients born on or after this date
+    pub born_from: Option<chrono::NaiveDate>,
+    /// Only patients born on or before this date
+    pub born_to: Option<chrono::NaiveDate>,
+}
+
+impl PatientCriteria {
+    /// Whether a patient satisfies every predicate
+    pub fn matches(&self, patient: &Patient) -> bool {
+        self.active.is_none_or(|active| patient.active == active)
+            && self.family.as_ref().is_none_or(|family| {
+                std::iter::once(&patient.name)
+                    .chain(&patient.additional_names)
+                    .any(|name| name.family.to_lowercase() == *family)
+            })
+            && self.born_from.is_none_or(|from| patient.birth_date.is_some_and(|date| date >= from))
+            && self.born_to.is_none_or(|to| patient.birth_date.is_some_and(|date| date <= to))
+    }
+}
+
 /// Patient repository trait
 pub trait PatientRepository: Send + Sync {
     /// Create a new patient
     fn create(&self, patient: &Patient) -> Result<Patient>;
 
+    /// Create a patient from a feed submission, storing the submission as a
+    /// source record linked to the new patient in the same transaction
+    fn create_from_source(&self, patient: &Patient, source: &SourceSubmission) -> Result<Patient>;
+
     /// Get a patient by ID
     fn get_by_id(&self, id: &Uuid) -> Result<Option<Patient>>;
 
+    /// Get a patient by ID for a write that depends on it
+    ///
+    /// Unlike `get_by_id`, never served by a read replica, so the patient is
+    /// never older than the last committed write. Takes no row lock.
+    fn get_by_id_for_update(&self, id: &Uuid) -> Result<Option<Patient>> {
+        self.get_by_id(id)
+    }
+
+    /// Whether `id` belongs to a soft-deleted patient, which the other
+    /// reads do not return
+    fn is_deleted(&self, id: &Uuid) -> Result<bool>;
+
     /// Update a patient
     fn update(&self, patient: &Patient) -> Result<Patient>;
 
+    /// Update a patient from a feed submission, storing the submission as a
+    /// source record linked to the patient in the same transaction
+    fn update_from_source(&self, patient: &Patient, source: &SourceSubmission) -> Result<Patient>;
+
     /// Delete a patient (soft delete)
     fn delete(&self, id: &Uuid) -> Result<()>;
 
+    /// Permanently delete a patient and everything attached to it
+    fn purge(&self, id: &Uuid) -> Result<()>;
+
     /// Search patients by name
     fn search(&self, query: &str) -> Result<Vec<Patient>>;
 
-    /// List all active patients (non-deleted)
+    /// List all active patients (non-deleted), oldest first
     fn list_active(&self, limit: i64, offset: i64) -> Result<Vec<Patient>>;
+
+    /// Non-deleted patients satisfying `criteria`, oldest first, starting
+    /// after the patient created at and with the ID given in `after`
+    ///
+    /// Pages by key rather than offset, so a page deep into the scan costs
+    /// no more than the first.
+    fn scan(
+        &self,
+        criteria: &PatientCriteria,
+        after: Option<(chrono::DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<Patient>>;
+
+    /// IDs of non-deleted patients holding `value` under any of `systems`
+    fn find_by_identifier(&self, systems: &[String], value: &str) -> Result<Vec<Uuid>>;
+
+    /// Find the non-deleted patient a contact person belongs to
+    fn find_by_contact(&self, contact_id: &Uuid) -> Result<Option<Uuid>>;
+
+    /// IDs of non-deleted patients with this
+    /// [`record_fingerprint`](crate::matching::record_fingerprint)
+    fn find_by_fingerprint(&self, fingerprint: &str) -> Result<Vec<Uuid>>;
+
+    /// IDs of non-deleted patients holding a link to `id`
+    fn find_linked_to(&self, id: &Uuid) -> Result<Vec<Uuid>>;
+
+    /// Set a patient's confidentiality, returning the patient as changed or
+    /// `None` if it does not exist
+    ///
+    /// Creates and updates never change it.
+    fn set_confidentiality(&self, id: &Uuid, confidentiality: Confidentiality) -> Result<Option<Patient>>;
+
+    /// Move a patient to a lifecycle status, setting its `active` and
+    /// `deceased` flags to match, and return the patient as changed or `None`
+    /// if it does not exist
+    ///
+    /// Fails with a validation error if the transition is not allowed.
+    fn set_status(
+        &self,
+        id: &Uuid,
+        status: PatientStatus,
+        deceased_datetime: Option<chrono::DateTime<Utc>>,
+    ) -> Result<Option<Patient>>;
 }
 
 /// Diesel-based patient repository implementation
 pub struct DieselPatientRepository {
     pool: Pool<ConnectionManager<PgConnection>>,
+    reads: super::ReadPool,
     event_publisher: Option<std::sync::Arc<dyn crate::streaming::EventProducer>>,
     audit_log: Option<std::sync::Arc<super::audit::AuditLogRepository>>,
 }
@@ -60,12 +157,22 @@ impl DieselPatientRepository {
     /// Create a new repository with the given connection pool
     pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
         Self {
+            reads: super::ReadPool::new(pool.clone()),
             pool,
             event_publisher: None,
             audit_log: None,
         }
     }
 
+    /// Serve `get_by_id`, `search` and `list_active` through `reads`
+    ///
+    /// Writes, and the reads they depend on through `get_by_id_for_update`,
+    /// stay on the primary.
+    pub fn with_reads(mut self, reads: super::ReadPool) -> Self {
+        self.reads = reads;
+        self
+    }
+
     /// Set the event publisher for this repository
     pub fn with_event_publisher(
         mut self,
@@ -129,6 +236,14 @@ impl DieselPatientRepository {
                     context.ip_address.clone(),
                     context.user_agent.clone(),
                 ),
+                "PURGE" => audit_log.log_purge(
+                    "Patient",
+                    entity_id,
+                    old_values.unwrap_or(serde_json::Value::Null),
+                    context.user_id.clone(),
+                    context.ip_address.clone(),
+                    context.user_agent.clone(),
+                ),
                 _ => Ok(()),
             };
 
@@ -143,12 +258,56 @@ impl DieselPatientRepository {
         self.pool.get().map_err(|e| crate::Error::Pool(e.to_string()))
     }
 
+    /// Load a non-deleted patient with everything attached to it through `conn`
+    fn load_patient(&self, conn: &mut PgConnection, id: &Uuid) -> Result<Option<Patient>> {
+        let db_patient: Option<DbPatient> = patients::table
+            .filter(patients::id.eq(id))
+            .filter(patients::deleted_at.is_null())
+            .first(conn)
+            .optional()?;
+
+        let db_patient = match db_patient {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+
+        // Get associated data
+        let db_names: Vec<DbPatientName> = patient_names::table
+            .filter(patient_names::patient_id.eq(id))
+            .load(conn)?;
+
+        let db_identifiers: Vec<DbPatientIdentifier> = patient_identifiers::table
+            .filter(patient_identifiers::patient_id.eq(id))
+            .load(conn)?;
+
+        let db_addresses: Vec<DbPatientAddress> = patient_addresses::table
+            .filter(patient_addresses::patient_id.eq(id))
+            .load(conn)?;
+
+        let db_contacts: Vec<DbPatientContact> = patient_contacts::table
+            .filter(patient_contacts::patient_id.eq(id))
+            .load(conn)?;
+
+        let db_links: Vec<DbPatientLink> = patient_links::table
+            .filter(patient_links::patient_id.eq(id))
+            .load(conn)?;
+
+        let db_related: Vec<DbPatientRelatedPerson> = patient_related_persons::table
+            .filter(patient_related_persons::patient_id.eq(id))
+            .load(conn)?;
+
+        let mut patient =
+            self.from_db_models(db_patient, db_names, db_identifiers, db_addresses, db_contacts, db_links)?;
+        patient.contacts = Self::from_db_related_persons(db_related)?;
+        Ok(Some(patient))
+    }
+
     /// Convert domain Patient model to database models
     fn to_db_models(&self, patient: &Patient) -> (NewDbPatient, Vec<NewDbPatientName>, Vec<NewDbPatientIdentifier>, Vec<NewDbPatientAddress>, Vec<NewDbPatientContact>, Vec<NewDbPatientLink>) {
         let new_patient = NewDbPatient {
             id: Some(patient.id),
             active: patient.active,
-            gender: format!("{:?}", patient.gender),
+            gender: format!("{:?}", patient.gender).to_lowercase(),
             birth_date: patient.birth_date,
             deceased: patient.deceased,
             deceased_datetime: patient.deceased_datetime,
@@ -156,6 +315,11 @@ impl DieselPatientRepository {
             multiple_birth: patient.multiple_birth,
             managing_organization_id: patient.managing_organization,
             created_by: None, // TODO: Get from context
+            gender_identity: patient.gender_identity.clone(),
+            pronouns: patient.pronouns.clone(),
+            fingerprint: crate::matching::record_fingerprint(patient),
+            photo_hashes: patient.photo_hashes.clone(),
+            status: patient.status.as_str().to_string(),
         };
 
         // Primary name
@@ -190,6 +354,9 @@ impl DieselPatientRepository {
             system: id.system.clone(),
             value: id.value.clone(),
             assigner: id.assigner.clone(),
+            verification_status: id.verification.as_str().to_string(),
+            period_start: id.period.and_then(|period| period.start),
+            period_end: id.period.and_then(|period| period.end),
         }).collect();
 
         // Addresses
@@ -203,6 +370,9 @@ impl DieselPatientRepository {
             postal_code: addr.postal_code.clone(),
             country: addr.country.clone(),
             is_primary: idx == 0,
+            verification_status: addr.verification.as_str().to_string(),
+            period_start: addr.period.and_then(|period| period.start),
+            period_end: addr.ended(),
         }).collect();
 
         // Contacts
@@ -212,6 +382,8 @@ impl DieselPatientRepository {
             value: cp.value.clone(),
             use_type: cp.use_type.as_ref().map(|u| format!("{:?}", u)),
             is_primary: idx == 0,
+            period_start: cp.period.and_then(|period| period.start),
+            period_end: cp.period.and_then(|period| period.end),
         }).collect();
 
         // Links
@@ -225,23 +397,55 @@ impl DieselPatientRepository {
         (new_patient, names, identifiers, addresses, contacts, links)
     }
 
+    /// Convert a patient's contact persons to database models, in order
+    fn to_db_related_persons(patient: &Patient) -> Result<Vec<NewDbPatientRelatedPerson>> {
+        patient
+            .contacts
+            .iter()
+            .enumerate()
+            .map(|(idx, contact)| {
+                Ok(NewDbPatientRelatedPerson {
+                    id: contact.id,
+                    patient_id: patient.id,
+                    position: idx as i32,
+                    contact: serde_json::to_value(contact)
+                        .map_err(|e| crate::Error::Internal(format!("Failed to serialize contact: {}", e)))?,
+                })
+            })
+            .collect()
+    }
+
+    /// Convert database models to contact persons, in order
+    fn from_db_related_persons(mut db_related: Vec<DbPatientRelatedPerson>) -> Result<Vec<PatientContact>> {
+        db_related.sort_by_key(|related| related.position);
+        db_related
+            .into_iter()
+            .map(|related| {
+                let mut contact: PatientContact = serde_json::from_value(related.contact)
+                    .map_err(|e| crate::Error::Internal(format!("Invalid contact: {}", e)))?;
+                contact.id = related.id;
+                Ok(contact)
+            })
+            .collect()
+    }
+
     /// Convert database models to domain Patient model
     fn from_db_models(
         &self,
         db_patient: DbPatient,
         db_names: Vec<DbPatientName>,
         db_identifiers: Vec<DbPatientIdentifier>,
-        db_addresses: Vec<DbPatientAddress>,
+        mut db_addresses: Vec<DbPatientAddress>,
         db_contacts: Vec<DbPatientContact>,
         db_links: Vec<DbPatientLink>,
     ) -> Result<Patient> {
         use crate::models::{Gender, NameUse, ContactPointSystem, ContactPointUse, LinkType, IdentifierType, IdentifierUse};
 
         // Parse gender
-        let gender = match db_patient.gender.as_str() {
-            "Male" => Gender::Male,
-            "Female" => Gender::Female,
-            "Other" => Gender::Other,
+        let gender = match db_patient.gender.to_lowercase().as_str() {
+            "male" => Gender::Male,
+            "female" => Gender::Female,
+            "other" => Gender::Other,
             _ => Gender::Unknown,
         };
 
@@ -316,12 +520,15 @@ impl DieselPatientRepository {
                     system: id.system.clone(),
                     value: id.value.clone(),
                     assigner: id.assigner.clone(),
+                    verification: VerificationStatus::parse(&id.verification_status).unwrap_or_default(),
+                    period: Period::from_bounds(id.period_start, id.period_end),
                 }
             })
             .collect();
 
-        // Addresses
-        let addresses = db_addresses.iter()
+        // Addresses: current ones, primary first, then former ones, latest first
+        db_addresses.sort_by_key(|addr| !addr.is_primary);
+        let mut addresses: Vec<Address> = db_addresses.iter()
             .map(|addr| Address {
                 line1: addr.line1.clone(),
                 line2: addr.line2.clone(),
@@ -329,8 +536,11 @@ impl DieselPatientRepository {
                 state: addr.state.clone(),
                 postal_code: addr.postal_code.clone(),
                 country: addr.country.clone(),
+                verification: VerificationStatus::parse(&addr.verification_status).unwrap_or_default(),
+                period: Period::from_bounds(addr.period_start, addr.period_end),
             })
             .collect();
+        addresses.sort_by_key(|addr| addr.ended().map(std::cmp::Reverse));
 
         // Telecom
         let telecom = db_contacts.iter()
@@ -359,6 +569,7 @@ impl DieselPatientRepository {
                     system,
                     value: cp.value.clone(),
                     use_type,
+                    period: Period::from_bounds(cp.period_start, cp.period_end),
                 })
             })
             .collect();
@@ -389,6 +600,8 @@ impl DieselPatientRepository {
             additional_names,
             telecom,
             gender,
+            gender_identity: db_patient.gender_identity,
+            pronouns: db_patient.pronouns,
             birth_date: db_patient.birth_date,
             deceased: db_patient.deceased,
             deceased_datetime: db_patient.deceased_datetime,
@@ -396,18 +609,27 @@ impl DieselPatientRepository {
             marital_status: db_patient.marital_status,
             multiple_birth: db_patient.multiple_birth,
             photo: vec![], // Not stored in DB yet
+            photo_hashes: db_patient.photo_hashes,
             managing_organization: db_patient.managing_organization_id,
             links,
+            contacts: Vec::new(),
+            confidentiality: Confidentiality::parse(&db_patient.confidentiality).unwrap_or_default(),
+            status: PatientStatus::parse(&db_patient.status).unwrap_or_default(),
             created_at: db_patient.created_at,
             updated_at: db_patient.updated_at,
         })
     }
-}
 
-impl PatientRepository for DieselPatientRepository {
-    fn create(&self, patient: &Patient) -> Result<Patient> {
+    /// Create a patient, with the submission it came from when given, in
+    /// one transaction
+    fn insert_patient(&self, patient: &Patient, source: Option<&SourceSubmission>) -> Result<Patient> {
         let mut conn = self.get_conn()?;
 
+        // A new record starts in the status its flags imply
+        let mut patient = patient.clone();
+        patient.status = PatientStatus::implied_by(&patient);
+        let patient = &patient;
+
         let result = conn.transaction(|conn| {
             let (new_patient, new_names, new_identifiers, new_addresses, new_contacts, new_links) =
                 self.to_db_models(patient);
@@ -458,7 +680,23 @@ impl PatientRepository for DieselPatientRepository {
                 vec![]
             };
 
-            self.from_db_models(db_patient, db_names, db_identifiers, db_addresses, db_contacts, db_links)
+            // Insert contact persons
+            let new_related = Self::to_db_related_persons(patient)?;
+            let db_related: Vec<DbPatientRelatedPerson> = if !new_related.is_empty() {
+                diesel::insert_into(patient_related_persons::table)
+                    .values(&new_related)
+                    .get_results(conn)?
+            } else {
+                vec![]
+            };
+
+            let mut created =
+                self.from_db_models(db_patient, db_names, db_identifiers, db_addresses, db_contacts, db_links)?;
+            created.contacts = Self::from_db_related_persons(db_related)?;
+            if let Some(source) = source {
+                Self::link_source(conn, source, patient, &created.id)?;
+            }
+            Ok::<_, crate::Error>(created)
         })?;
 
         // Publish event
@@ -475,57 +713,27 @@ impl PatientRepository for DieselPatientRepository {
         Ok(result)
     }
 
-    fn get_by_id(&self, id: &Uuid) -> Result<Option<Patient>> {
-        let mut conn = self.get_conn()?;
-
-        // Get patient
-        let db_patient: Option<DbPatient> = patients::table
-            .filter(patients::id.eq(id))
-            .filter(patients::deleted_at.is_null())
-            .first(&mut conn)
-            .optional()?;
-
-        let db_patient = match db_patient {
-            Some(p) => p,
-            None => return Ok(None),
-        };
-
-        // Get associated data
-        let db_names: Vec<DbPatientName> = patient_names::table
-            .filter(patient_names::patient_id.eq(id))
-            .load(&mut conn)?;
-
-        let db_identifiers: Vec<DbPatientIdentifier> = patient_identifiers::table
-            .filter(patient_identifiers::patient_id.eq(id))
-            .load(&mut conn)?;
-
-        let db_addresses: Vec<DbPatientAddress> = patient_addresses::table
-            .filter(patient_addresses::patient_id.eq(id))
-            .load(&mut conn)?;
-
-        let db_contacts: Vec<DbPatientContact> = patient_contacts::table
-            .filter(patient_contacts::patient_id.eq(id))
-            .load(&mut conn)?;
-
-        let db_links: Vec<DbPatientLink> = patient_links::table
-            .filter(patient_links::patient_id.eq(id))
-            .load(&mut conn)?;
-
-        self.from_db_models(db_patient, db_names, db_identifiers, db_addresses, db_contacts, db_links)
-            .map(Some)
-    }
-
-    fn update(&self, patient: &Patient) -> Result<Patient> {
+    /// Update a patient, with the submission it came from when given, in
+    /// one transaction
+    fn update_patient(&self, patient: &Patient, source: Option<&SourceSubmission>) -> Result<Patient> {
         let mut conn = self.get_conn()?;
 
         // Get old values for audit
-        let old_patient = self.get_by_id(&patient.id)?;
+        let old_patient = self.load_patient(&mut conn, &patient.id)?;
+
+        // Addresses the update drops are kept as former addresses
+        let mut patient = patient.clone();
+        if let Some(old) = &old_patient {
+            patient.keep_address_history_from(old, chrono::Utc::now());
+            patient.keep_status_from(old)?;
+        }
+        let patient = &patient;
 
         let result = conn.transaction(|conn| {
             // Update patient
             let update_patient = UpdateDbPatient {
                 active: Some(patient.active),
-                gender: Some(format!("{:?}", patient.gender)),
+                gender: Some(format!("{:?}", patient.gender).to_lowercase()),
                 birth_date: patient.birth_date,
                 deceased: Some(patient.deceased),
                 deceased_datetime: patient.deceased_datetime,
@@ -533,6 +741,11 @@ impl PatientRepository for DieselPatientRepository {
                 multiple_birth: patient.multiple_birth,
                 managing_organization_id: patient.managing_organization,
                 updated_by: None, // TODO: Get from context
+                gender_identity: Some(patient.gender_identity.clone()),
+                pronouns: Some(patient.pronouns.clone()),
+                fingerprint: Some(crate::matching::record_fingerprint(patient)),
+                photo_hashes: Some(patient.photo_hashes.clone()),
+                status: Some(patient.status.as_str().to_string()),
             };
 
             diesel::update(patients::table.filter(patients::id.eq(patient.id)))
@@ -555,6 +768,9 @@ impl PatientRepository for DieselPatientRepository {
             diesel::delete(patient_links::table.filter(patient_links::patient_id.eq(patient.id)))
                 .execute(conn)?;
 
+            diesel::delete(patient_related_persons::table.filter(patient_related_persons::patient_id.eq(patient.id)))
+                .execute(conn)?;
+
             // Re-insert associated data
             let (_, new_names, new_identifiers, new_addresses, new_contacts, new_links) =
                 self.to_db_models(patient);
@@ -587,8 +803,19 @@ impl PatientRepository for DieselPatientRepository {
                     .execute(conn)?;
             }
 
+            let new_related = Self::to_db_related_persons(patient)?;
+            if !new_related.is_empty() {
+                diesel::insert_into(patient_related_persons::table)
+                    .values(&new_related)
+                    .execute(conn)?;
+            }
+
+            if let Some(source) = source {
+                Self::link_source(conn, source, patient, &patient.id)?;
+            }
+
             // Fetch and return updated patient
-            self.get_by_id(&patient.id)?
+            self.load_patient(conn, &patient.id)?
                 .ok_or_else(|| crate::Error::Validation("Patient not found after update".to_string()))
         })?;
 
@@ -608,11 +835,67 @@ impl PatientRepository for DieselPatientRepository {
         Ok(result)
     }
 
+    /// Store a submission as a source record linked to `patient_id`
+    fn link_source(conn: &mut PgConnection, source: &SourceSubmission, patient: &Patient, patient_id: &Uuid) -> Result<()> {
+        let record = super::DieselSourceRecordRepository::receive_in(
+            conn,
+            &source.source_system,
+            &source.source_record_id,
+            patient,
+            source.received_by.clone(),
+        )?;
+        super::DieselSourceRecordRepository::link_in(conn, &record.id, patient_id, None, source.received_by.clone())?;
+        Ok(())
+    }
+}
+
+impl PatientRepository for DieselPatientRepository {
+    fn create(&self, patient: &Patient) -> Result<Patient> {
+        self.insert_patient(patient, None)
+    }
+
+    fn create_from_source(&self, patient: &Patient, source: &SourceSubmission) -> Result<Patient> {
+        self.insert_patient(patient, Some(source))
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<Patient>> {
+        crate::deadline::check()?;
+        let mut conn = self.reads.get()?;
+        self.load_patient(&mut conn, id)
+    }
+
+    fn get_by_id_for_update(&self, id: &Uuid) -> Result<Option<Patient>> {
+        crate::deadline::check()?;
+        let mut conn = self.get_conn()?;
+        self.load_patient(&mut conn, id)
+    }
+
+    fn is_deleted(&self, id: &Uuid) -> Result<bool> {
+        let mut conn = self.get_conn()?;
+
+        let deleted = diesel::select(diesel::dsl::exists(
+            patients::table
+                .filter(patients::id.eq(id))
+                .filter(patients::deleted_at.is_not_null()),
+        ))
+        .get_result(&mut conn)?;
+
+        Ok(deleted)
+    }
+
+    fn update(&self, patient: &Patient) -> Result<Patient> {
+        self.update_patient(patient, None)
+    }
+
+    fn update_from_source(&self, patient: &Patient, source: &SourceSubmission) -> Result<Patient> {
+        self.update_patient(patient, Some(source))
+    }
+
     fn delete(&self, id: &Uuid) -> Result<()> {
         let mut conn = self.get_conn()?;
 
         // Get old values for audit
-        let old_patient = self.get_by_id(id)?;
+        let old_patient = self.load_patient(&mut conn, id)?;
 
         // Soft delete
         diesel::update(patients::table.filter(patients::id.eq(id)))
@@ -638,9 +921,35 @@ impl PatientRepository for DieselPatientRepository {
         Ok(())
     }
 
-    fn search(&self, query: &str) -> Result<Vec<Patient>> {
+    fn purge(&self, id: &Uuid) -> Result<()> {
         let mut conn = self.get_conn()?;
 
+        // Get old values for audit
+        let old_patient = self.load_patient(&mut conn, id)?;
+
+        // Names, identifiers, links, scores and locks go with it by ON DELETE CASCADE
+        diesel::delete(patients::table.filter(patients::id.eq(id)))
+            .execute(&mut conn)?;
+
+        // Publish event
+        self.publish_event(crate::streaming::PatientEvent::Deleted {
+            patient_id: *id,
+            timestamp: chrono::Utc::now(),
+        });
+
+        // Log audit
+        if let Some(old_patient) = old_patient {
+            if let Ok(old_json) = serde_json::to_value(&old_patient) {
+                self.log_audit("PURGE", *id, Some(old_json), None, &AuditContext::default());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<Patient>> {
+        let mut conn = self.reads.get()?;
+
         // Search by family name (simple implementation)
         let search_pattern = format!("%{}%", query.to_lowercase());
 
@@ -653,7 +962,8 @@ impl PatientRepository for DieselPatientRepository {
         // Fetch full patient records
         let mut patients = Vec::new();
         for patient_id in patient_ids {
-            if let Some(patient) = self.get_by_id(&patient_id)? {
+            crate::deadline::check()?;
+            if let Some(patient) = self.load_patient(&mut conn, &patient_id)? {
                 patients.push(patient);
             }
         }
@@ -662,11 +972,12 @@ impl PatientRepository for DieselPatientRepository {
     }
 
     fn list_active(&self, limit: i64, offset: i64) -> Result<Vec<Patient>> {
-        let mut conn = self.get_conn()?;
+        let mut conn = self.reads.get()?;
 
         let patient_ids: Vec<Uuid> = patients::table
             .filter(patients::deleted_at.is_null())
             .filter(patients::active.eq(true))
+            .order((patients::created_at.asc(), patients::id.asc()))
             .select(patients::id)
             .limit(limit)
             .offset(offset)
@@ -674,11 +985,160 @@ impl PatientRepository for DieselPatientRepository {
 
         let mut patients = Vec::new();
         for patient_id in patient_ids {
-            if let Some(patient) = self.get_by_id(&patient_id)? {
+            crate::deadline::check()?;
+            if let Some(patient) = self.load_patient(&mut conn, &patient_id)? {
                 patients.push(patient);
             }
         }
 
         Ok(patients)
     }
+
+    fn scan(
+        &self,
+        criteria: &PatientCriteria,
+        after: Option<(chrono::DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<Patient>> {
+        use diesel::dsl::sql;
+        use diesel::sql_types::{Bool, Text};
+
+        let mut conn = self.reads.get()?;
+
+        let mut query = patients::table
+            .filter(patients::deleted_at.is_null())
+            .into_boxed();
+        if let Some(active) = criteria.active {
+            query = query.filter(patients::active.eq(active));
+        }
+        if let Some(family) = &criteria.family {
+            query = query.filter(
+                sql::<Bool>("EXISTS (SELECT 1 FROM patient_names n WHERE n.patient_id = patients.id AND lower(n.family) = ")
+                    .bind::<Text, _>(family.clone())
+                    .sql(")"),
+            );
+        }
+        if let Some(from) = criteria.born_from {
+            query = query.filter(patients::birth_date.ge(from));
+        }
+        if let Some(to) = criteria.born_to {
+            query = query.filter(patients::birth_date.le(to));
+        }
+        if let Some((created_at, id)) = after {
+            query = query.filter(
+                patients::created_at
+                    .gt(created_at)
+                    .or(patients::created_at.eq(created_at).and(patients::id.gt(id))),
+            );
+        }
+
+        let patient_ids: Vec<Uuid> = query
+            .order((patients::created_at.asc(), patients::id.asc()))
+            .select(patients::id)
+            .limit(limit)
+            .load(&mut conn)?;
+
+        let mut patients = Vec::new();
+        for patient_id in patient_ids {
+            crate::deadline::check()?;
+            if let Some(patient) = self.load_patient(&mut conn, &patient_id)? {
+                patients.push(patient);
+            }
+        }
+
+        Ok(patients)
+    }
+
+    fn find_by_identifier(&self, systems: &[String], value: &str) -> Result<Vec<Uuid>> {
+        let mut conn = self.get_conn()?;
+
+        let patient_ids = patient_identifiers::table
+            .inner_join(patients::table)
+            .filter(patients::deleted_at.is_null())
+            .filter(patient_identifiers::system.eq_any(systems))
+            .filter(patient_identifiers::value.eq(value))
+            .select(patient_identifiers::patient_id)
+            .distinct()
+            .load(&mut conn)?;
+
+        Ok(patient_ids)
+    }
+
+    fn find_by_contact(&self, contact_id: &Uuid) -> Result<Option<Uuid>> {
+        let mut conn = self.get_conn()?;
+
+        let patient_id = patient_related_persons::table
+            .inner_join(patients::table)
+            .filter(patients::deleted_at.is_null())
+            .filter(patient_related_persons::id.eq(contact_id))
+            .select(patient_related_persons::patient_id)
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(patient_id)
+    }
+
+    fn find_by_fingerprint(&self, fingerprint: &str) -> Result<Vec<Uuid>> {
+        let mut conn = self.get_conn()?;
+
+        let patient_ids = patients::table
+            .filter(patients::deleted_at.is_null())
+            .filter(patients::fingerprint.eq(fingerprint))
+            .select(patients::id)
+            .load(&mut conn)?;
+
+        Ok(patient_ids)
+    }
+
+    fn find_linked_to(&self, id: &Uuid) -> Result<Vec<Uuid>> {
+        let mut conn = self.get_conn()?;
+
+        let patient_ids = patient_links::table
+            .inner_join(patients::table)
+            .filter(patient_links::other_patient_id.eq(id))
+            .filter(patients::deleted_at.is_null())
+            .select(patient_links::patient_id)
+            .distinct()
+            .load(&mut conn)?;
+
+        Ok(patient_ids)
+    }
+
+    fn set_confidentiality(&self, id: &Uuid, confidentiality: Confidentiality) -> Result<Option<Patient>> {
+        let mut conn = self.get_conn()?;
+        let updated = diesel::update(patients::table.find(id).filter(patients::deleted_at.is_null()))
+            .set((
+                patients::confidentiality.eq(confidentiality.as_str()),
+                patients::updated_at.eq(Utc::now()),
+            ))
+            .execute(&mut conn)?;
+        if updated == 0 {
+            return Ok(None);
+        }
+        self.load_patient(&mut conn, id)
+    }
+
+    fn set_status(
+        &self,
+        id: &Uuid,
+        status: PatientStatus,
+        deceased_datetime: Option<chrono::DateTime<Utc>>,
+    ) -> Result<Option<Patient>> {
+        let mut conn = self.get_conn()?;
+        let Some(mut patient) = self.load_patient(&mut conn, id)? else {
+            return Ok(None);
+        };
+        patient.transition_to(status, deceased_datetime)?;
+
+        diesel::update(patients::table.find(id).filter(patients::deleted_at.is_null()))
+            .set((
+                patients::status.eq(patient.status.as_str()),
+                patients::active.eq(patient.active),
+                patients::deceased.eq(patient.deceased),
+                patients::deceased_datetime.eq(patient.deceased_datetime),
+                patients::updated_at.eq(Utc::now()),
+            ))
+            .execute(&mut conn)?;
+        self.load_patient(&mut conn, id)
+    }
 }
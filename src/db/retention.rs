@@ -0,0 +1,130 @@
+//! Retention candidate queries and anonymization bookkeeping
+
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::sql_types::{BigInt, Bool, Nullable, Timestamptz, Uuid as SqlUuid};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::models::Patient;
+use crate::Result;
+use super::models::DbSourceRecord;
+use super::schema::{patients, source_record_links, source_records};
+
+/// Patients matching a rule's cutoffs, least recently changed first
+///
+/// A patient is inactive when neither the patient row nor any source record
+/// linked to it has changed since the cutoff. Deceased patients without a
+/// date of death never match a deceased cutoff.
+const CANDIDATES_SQL: &str = "
+    SELECT p.id
+    FROM patients p
+    WHERE ($1::timestamptz IS NULL OR (
+              p.updated_at < $1
+              AND NOT EXISTS (
+                  SELECT 1
+                  FROM source_record_links l
+                  JOIN source_records r ON r.id = l.source_record_id
+                  WHERE l.patient_id = p.id AND r.received_at >= $1
+              )
+          ))
+      AND ($2::timestamptz IS NULL OR (p.deceased AND p.deceased_datetime < $2))
+      AND ($3 OR p.deleted_at IS NULL)
+      AND ($4 OR p.anonymized_at IS NULL)
+    ORDER BY p.updated_at
+    LIMIT $5";
+
+#[derive(QueryableByName)]
+struct CandidateRow {
+    #[diesel(sql_type = SqlUuid)]
+    id: Uuid,
+}
+
+/// Repository for the retention job
+pub struct RetentionRepository {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl RetentionRepository {
+    /// Create a new retention repository
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+
+    /// Get a database connection from the pool
+    fn get_conn(&self) -> Result<diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>> {
+        self.pool.get().map_err(|e| crate::Error::Pool(e.to_string()))
+    }
+
+    /// Patients inactive since `inactive_before` and deceased before `deceased_before`
+    ///
+    /// Unset cutoffs are not checked. Soft-deleted and already anonymized
+    /// patients are skipped unless asked for.
+    pub fn find_candidates(
+        &self,
+        inactive_before: Option<DateTime<Utc>>,
+        deceased_before: Option<DateTime<Utc>>,
+        include_deleted: bool,
+        include_anonymized: bool,
+        limit: i64,
+    ) -> Result<Vec<Uuid>> {
+        let mut conn = self.get_conn()?;
+
+        let rows: Vec<CandidateRow> = diesel::sql_query(CANDIDATES_SQL)
+            .bind::<Nullable<Timestamptz>, _>(inactive_before)
+            .bind::<Nullable<Timestamptz>, _>(deceased_before)
+            .bind::<Bool, _>(include_deleted)
+            .bind::<Bool, _>(include_anonymized)
+            .bind::<BigInt, _>(limit)
+            .load(&mut conn)?;
+
+        Ok(rows.into_iter().map(|row| row.id).collect())
+    }
+
+    /// Record that a patient's direct identifiers have been stripped
+    pub fn mark_anonymized(&self, patient_id: &Uuid) -> Result<()> {
+        let mut conn = self.get_conn()?;
+
+        diesel::update(patients::table.filter(patients::id.eq(patient_id)))
+            .set(patients::anonymized_at.eq(Some(Utc::now())))
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    /// Rewrite the payloads of the source records currently linked to a patient
+    ///
+    /// Source records are otherwise immutable; this is the one path that
+    /// changes them, so anonymized demographics do not survive in the feed
+    /// copies. Returns the number of records rewritten.
+    pub fn redact_source_records(
+        &self,
+        patient_id: &Uuid,
+        redact: impl Fn(Patient) -> Patient,
+    ) -> Result<usize> {
+        let mut conn = self.get_conn()?;
+
+        conn.transaction(|conn| {
+            let db_records: Vec<DbSourceRecord> = source_records::table
+                .inner_join(source_record_links::table)
+                .filter(source_record_links::patient_id.eq(patient_id))
+                .filter(source_record_links::unlinked_at.is_null())
+                .select(DbSourceRecord::as_select())
+                .load(conn)?;
+
+            for db_record in &db_records {
+                let patient: Patient = serde_json::from_value(db_record.payload.clone())
+                    .map_err(|e| crate::Error::Internal(format!("Invalid source record payload: {}", e)))?;
+                let payload = serde_json::to_value(redact(patient))
+                    .map_err(|e| crate::Error::Internal(format!("Failed to serialize patient: {}", e)))?;
+
+                diesel::update(source_records::table.filter(source_records::id.eq(db_record.id)))
+                    .set(source_records::payload.eq(payload))
+                    .execute(conn)?;
+            }
+
+            Ok(db_records.len())
+        })
+    }
+}
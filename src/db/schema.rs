@@ -5,6 +5,18 @@
 
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    assigning_authorities (id) {
+        id -> Uuid,
+        system -> Varchar,
+        oid -> Nullable<Varchar>,
+        name -> Varchar,
+        active -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     audit_log (id) {
         id -> Uuid,
@@ -20,6 +32,110 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    change_requests (id) {
+        id -> Uuid,
+        patient_id -> Uuid,
+        changes -> Jsonb,
+        reason -> Nullable<Text>,
+        submitted_by -> Nullable<Varchar>,
+        status -> Varchar,
+        submitted_at -> Timestamptz,
+        reviewed_by -> Nullable<Varchar>,
+        reviewed_at -> Nullable<Timestamptz>,
+        review_note -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    duplicate_candidates (id) {
+        id -> Uuid,
+        patient_id -> Uuid,
+        candidate_id -> Uuid,
+        score -> Numeric,
+        status -> Varchar,
+        detected_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    duplicate_decisions (id) {
+        id -> Uuid,
+        duplicate_candidate_id -> Uuid,
+        action -> Varchar,
+        reason_code -> Varchar,
+        reason -> Nullable<Text>,
+        decided_by -> Nullable<Varchar>,
+        decided_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    import_checkpoints (id) {
+        id -> Uuid,
+        path -> Text,
+        requested_by -> Nullable<Varchar>,
+        status -> Varchar,
+        job_id -> Uuid,
+        byte_offset -> Int8,
+        lines_processed -> Int8,
+        created -> Int8,
+        duplicates -> Int8,
+        invalid -> Int8,
+        error -> Nullable<Text>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    matching_kpis_daily (day, source_system) {
+        day -> Date,
+        source_system -> Varchar,
+        records_received -> Int8,
+        new_patients -> Int8,
+        duplicates_created -> Int8,
+        auto_links -> Int8,
+        manual_links -> Int8,
+        average_match_score -> Nullable<Float8>,
+        overlay_incidents -> Int8,
+        computed_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    matching_settings (id) {
+        id -> Uuid,
+        profile -> Varchar,
+        version -> Int4,
+        settings -> Jsonb,
+        changed_by -> Nullable<Varchar>,
+        note -> Nullable<Text>,
+        changed_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    message_archive (id) {
+        id -> Uuid,
+        patient_id -> Uuid,
+        audit_log_id -> Nullable<Uuid>,
+        action -> Varchar,
+        channel -> Varchar,
+        content_type -> Varchar,
+        payload -> Text,
+        received_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    mrn_sequences (system) {
+        system -> Varchar,
+        next_value -> Int8,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     organization_addresses (id) {
         id -> Uuid,
@@ -95,6 +211,9 @@ diesel::table! {
         is_primary -> Bool,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        verification_status -> Varchar,
+        period_start -> Nullable<Timestamptz>,
+        period_end -> Nullable<Timestamptz>,
     }
 }
 
@@ -108,6 +227,36 @@ diesel::table! {
         is_primary -> Bool,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        period_start -> Nullable<Timestamptz>,
+        period_end -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    patient_field_provenance (patient_id, field) {
+        patient_id -> Uuid,
+        field -> Varchar,
+        source -> Varchar,
+        recorded_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    patient_group_members (group_id, patient_id) {
+        group_id -> Uuid,
+        patient_id -> Uuid,
+        added_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    patient_groups (id) {
+        id -> Uuid,
+        name -> Varchar,
+        description -> Nullable<Text>,
+        created_by -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
     }
 }
 
@@ -122,6 +271,9 @@ diesel::table! {
         assigner -> Nullable<Varchar>,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        verification_status -> Varchar,
+        period_start -> Nullable<Timestamptz>,
+        period_end -> Nullable<Timestamptz>,
     }
 }
 
@@ -148,6 +300,10 @@ diesel::table! {
         address_score -> Nullable<Numeric>,
         identifier_score -> Nullable<Numeric>,
         calculated_at -> Timestamptz,
+        algorithm_version -> Nullable<Varchar>,
+        config_hash -> Nullable<Varchar>,
+        patient_updated_at -> Nullable<Timestamptz>,
+        candidate_updated_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -166,6 +322,27 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    patient_related_persons (id) {
+        id -> Uuid,
+        patient_id -> Uuid,
+        position -> Int4,
+        contact -> Jsonb,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    patient_watches (id) {
+        id -> Uuid,
+        patient_id -> Uuid,
+        callback_url -> Nullable<Varchar>,
+        event_types -> Array<Text>,
+        created_at -> Timestamptz,
+        created_by -> Nullable<Varchar>,
+    }
+}
+
 diesel::table! {
     patients (id) {
         id -> Uuid,
@@ -183,31 +360,137 @@ diesel::table! {
         updated_by -> Nullable<Varchar>,
         deleted_at -> Nullable<Timestamptz>,
         deleted_by -> Nullable<Varchar>,
+        gender_identity -> Nullable<Varchar>,
+        pronouns -> Nullable<Varchar>,
+        anonymized_at -> Nullable<Timestamptz>,
+        fingerprint -> Nullable<Varchar>,
+        confidentiality -> Varchar,
+        photo_hashes -> Array<Text>,
+        status -> Varchar,
+    }
+}
+
+diesel::table! {
+    practitioners (id) {
+        id -> Uuid,
+        npi -> Nullable<Varchar>,
+        family_name -> Varchar,
+        active -> Bool,
+        resource -> Jsonb,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        deleted_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    quarantined_records (id) {
+        id -> Uuid,
+        channel -> Varchar,
+        content_type -> Varchar,
+        payload -> Text,
+        errors -> Jsonb,
+        patient_id -> Nullable<Uuid>,
+        status -> Varchar,
+        received_at -> Timestamptz,
+        resolved_at -> Nullable<Timestamptz>,
+        resolved_patient_id -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    record_locks (patient_id) {
+        patient_id -> Uuid,
+        locked_by -> Varchar,
+        reason -> Nullable<Text>,
+        acquired_at -> Timestamptz,
+        expires_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    source_record_links (id) {
+        id -> Uuid,
+        source_record_id -> Uuid,
+        patient_id -> Uuid,
+        match_score -> Nullable<Numeric>,
+        linked_at -> Timestamptz,
+        linked_by -> Nullable<Varchar>,
+        unlinked_at -> Nullable<Timestamptz>,
+        unlinked_by -> Nullable<Varchar>,
+        confidence -> Numeric,
+        needs_reverification -> Bool,
+        verified_at -> Nullable<Timestamptz>,
+        verified_by -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    source_records (id) {
+        id -> Uuid,
+        source_system -> Varchar,
+        source_record_id -> Varchar,
+        payload -> Jsonb,
+        received_at -> Timestamptz,
+        received_by -> Nullable<Varchar>,
     }
 }
 
+diesel::joinable!(change_requests -> patients (patient_id));
+diesel::joinable!(duplicate_candidates -> patients (patient_id));
+diesel::joinable!(duplicate_decisions -> duplicate_candidates (duplicate_candidate_id));
+diesel::joinable!(message_archive -> audit_log (audit_log_id));
+diesel::joinable!(message_archive -> patients (patient_id));
 diesel::joinable!(organization_addresses -> organizations (organization_id));
 diesel::joinable!(organization_contacts -> organizations (organization_id));
 diesel::joinable!(organization_identifiers -> organizations (organization_id));
 diesel::joinable!(patient_addresses -> patients (patient_id));
 diesel::joinable!(patient_contacts -> patients (patient_id));
+diesel::joinable!(patient_field_provenance -> patients (patient_id));
+diesel::joinable!(patient_group_members -> patient_groups (group_id));
+diesel::joinable!(patient_group_members -> patients (patient_id));
 diesel::joinable!(patient_identifiers -> patients (patient_id));
 diesel::joinable!(patient_links -> patients (patient_id));
 diesel::joinable!(patient_match_scores -> patients (patient_id));
 diesel::joinable!(patient_names -> patients (patient_id));
+diesel::joinable!(patient_related_persons -> patients (patient_id));
+diesel::joinable!(patient_watches -> patients (patient_id));
 diesel::joinable!(patients -> organizations (managing_organization_id));
+diesel::joinable!(quarantined_records -> patients (patient_id));
+diesel::joinable!(record_locks -> patients (patient_id));
+diesel::joinable!(source_record_links -> patients (patient_id));
+diesel::joinable!(source_record_links -> source_records (source_record_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    assigning_authorities,
     audit_log,
+    change_requests,
+    duplicate_candidates,
+    duplicate_decisions,
+    import_checkpoints,
+    matching_kpis_daily,
+    matching_settings,
+    message_archive,
+    mrn_sequences,
     organization_addresses,
     organization_contacts,
     organization_identifiers,
     organizations,
     patient_addresses,
     patient_contacts,
+    patient_field_provenance,
+    patient_group_members,
+    patient_groups,
     patient_identifiers,
     patient_links,
     patient_match_scores,
     patient_names,
+    patient_related_persons,
+    patient_watches,
     patients,
+    practitioners,
+    quarantined_records,
+    record_locks,
+    source_record_links,
+    source_records,
 );
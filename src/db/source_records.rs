@@ -0,0 +1,412 @@
+//! Source record repository
+//!
+//! Stores feed submissions unchanged and manages their links to enterprise
+//! patient records.
+
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::PgConnection;
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::models::{Patient, SourceRecord, SourceRecordLink};
+use crate::Result;
+use super::models::{DbSourceRecord, NewDbSourceRecord, DbSourceRecordLink, NewDbSourceRecordLink};
+use super::schema::{source_records, source_record_links};
+
+/// Source record repository trait
+pub trait SourceRecordRepository: Send + Sync {
+    /// Store a new, immutable source record
+    fn receive(
+        &self,
+        source_system: &str,
+        source_record_id: &str,
+        patient: &Patient,
+        received_by: Option<String>,
+    ) -> Result<SourceRecord>;
+
+    /// Get a source record by ID
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<SourceRecord>>;
+
+    /// List every submission of a record from a source system, newest first
+    fn list_by_source(&self, source_system: &str, source_record_id: &str) -> Result<Vec<SourceRecord>>;
+
+    /// Count the source records received from a source system
+    fn count_by_source_system(&self, source_system: &str) -> Result<i64>;
+
+    /// List the source records received from a source system, oldest first
+    fn list_by_source_system(&self, source_system: &str, limit: i64, offset: i64) -> Result<Vec<SourceRecord>>;
+
+    /// Permanently delete a source record and its link history
+    fn purge(&self, id: &Uuid) -> Result<bool>;
+
+    /// List the source records currently linked to an enterprise record
+    fn list_for_patient(&self, patient_id: &Uuid) -> Result<Vec<SourceRecord>>;
+
+    /// Link a source record to an enterprise record, superseding any current link
+    fn link(
+        &self,
+        source_record_id: &Uuid,
+        patient_id: &Uuid,
+        match_score: Option<f64>,
+        linked_by: Option<String>,
+    ) -> Result<SourceRecordLink>;
+
+    /// End the current link of a source record
+    fn unlink(&self, source_record_id: &Uuid, unlinked_by: Option<String>) -> Result<()>;
+
+    /// Get the current link of a source record
+    fn current_link(&self, source_record_id: &Uuid) -> Result<Option<SourceRecordLink>>;
+
+    /// List all current links
+    fn list_active_links(&self, limit: i64, offset: i64) -> Result<Vec<SourceRecordLink>>;
+
+    /// Set a link's confidence and whether it needs re-verification
+    fn set_confidence(&self, link_id: &Uuid, confidence: f64, needs_reverification: bool) -> Result<()>;
+
+    /// List current links flagged for re-verification, least confident first
+    fn list_needing_reverification(&self, limit: i64, offset: i64) -> Result<Vec<SourceRecordLink>>;
+
+    /// Record that a steward verified a current link, restoring full
+    /// confidence, or return `None` if there is no such current link
+    fn verify_link(&self, link_id: &Uuid, verified_by: Option<String>) -> Result<Option<SourceRecordLink>>;
+
+    /// List source records received in `[since, until)`, oldest first
+    fn list_received(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<SourceRecord>>;
+}
+
+/// Diesel-based source record repository implementation
+pub struct DieselSourceRecordRepository {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl DieselSourceRecordRepository {
+    /// Create a new repository with the given connection pool
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+
+    /// Get a database connection from the pool
+    fn get_conn(&self) -> Result<diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>> {
+        self.pool.get().map_err(|e| crate::Error::Pool(e.to_string()))
+    }
+
+    /// Store a new source record through `conn`, so it can share the
+    /// transaction of the patient write it came with
+    pub(crate) fn receive_in(
+        conn: &mut PgConnection,
+        source_system: &str,
+        source_record_id: &str,
+        patient: &Patient,
+        received_by: Option<String>,
+    ) -> Result<SourceRecord> {
+        let payload = serde_json::to_value(patient)
+            .map_err(|e| crate::Error::Internal(format!("Failed to serialize patient: {}", e)))?;
+
+        let new_record = NewDbSourceRecord {
+            source_system: source_system.to_string(),
+            source_record_id: source_record_id.to_string(),
+            payload,
+            received_by,
+        };
+
+        let db_record: DbSourceRecord = diesel::insert_into(source_records::table)
+            .values(&new_record)
+            .get_result(conn)?;
+
+        Self::to_source_record(db_record)
+    }
+
+    /// Link a source record through `conn`, superseding any current link
+    pub(crate) fn link_in(
+        conn: &mut PgConnection,
+        source_record_id: &Uuid,
+        patient_id: &Uuid,
+        match_score: Option<f64>,
+        linked_by: Option<String>,
+    ) -> Result<SourceRecordLink> {
+        let confidence = Self::to_decimal(SourceRecordLink::initial_confidence(match_score), "link confidence")?;
+        let match_score = match match_score {
+            Some(score) => Some(Self::to_decimal(score, "match score")?),
+            None => None,
+        };
+
+        let db_link = conn.transaction(|conn| {
+            // Supersede the current link, if any
+            diesel::update(
+                source_record_links::table
+                    .filter(source_record_links::source_record_id.eq(source_record_id))
+                    .filter(source_record_links::unlinked_at.is_null()),
+            )
+            .set((
+                source_record_links::unlinked_at.eq(Some(Utc::now())),
+                source_record_links::unlinked_by.eq(linked_by.clone()),
+            ))
+            .execute(conn)?;
+
+            let new_link = NewDbSourceRecordLink {
+                source_record_id: *source_record_id,
+                patient_id: *patient_id,
+                match_score,
+                linked_by,
+                confidence,
+            };
+
+            diesel::insert_into(source_record_links::table)
+                .values(&new_link)
+                .get_result::<DbSourceRecordLink>(conn)
+        })?;
+
+        Ok(Self::to_link(db_link))
+    }
+
+    /// Convert a database source record to the domain model
+    fn to_source_record(db_record: DbSourceRecord) -> Result<SourceRecord> {
+        let patient: Patient = serde_json::from_value(db_record.payload)
+            .map_err(|e| crate::Error::Internal(format!("Invalid source record payload: {}", e)))?;
+
+        Ok(SourceRecord {
+            id: db_record.id,
+            source_system: db_record.source_system,
+            source_record_id: db_record.source_record_id,
+            patient,
+            received_at: db_record.received_at,
+            received_by: db_record.received_by,
+        })
+    }
+
+    /// Convert a confidence or match score to its stored form
+    fn to_decimal(value: f64, what: &str) -> Result<BigDecimal> {
+        BigDecimal::try_from(value).map_err(|e| crate::Error::Validation(format!("Invalid {}: {}", what, e)))
+    }
+
+    /// Convert a database link to the domain model
+    fn to_link(db_link: DbSourceRecordLink) -> SourceRecordLink {
+        SourceRecordLink {
+            id: db_link.id,
+            source_record_id: db_link.source_record_id,
+            patient_id: db_link.patient_id,
+            match_score: db_link.match_score.and_then(|s| s.to_f64()),
+            linked_at: db_link.linked_at,
+            linked_by: db_link.linked_by,
+            unlinked_at: db_link.unlinked_at,
+            unlinked_by: db_link.unlinked_by,
+            confidence: db_link.confidence.to_f64().unwrap_or(1.0),
+            needs_reverification: db_link.needs_reverification,
+            verified_at: db_link.verified_at,
+            verified_by: db_link.verified_by,
+        }
+    }
+}
+
+impl SourceRecordRepository for DieselSourceRecordRepository {
+    fn receive(
+        &self,
+        source_system: &str,
+        source_record_id: &str,
+        patient: &Patient,
+        received_by: Option<String>,
+    ) -> Result<SourceRecord> {
+        let mut conn = self.get_conn()?;
+        Self::receive_in(&mut conn, source_system, source_record_id, patient, received_by)
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<SourceRecord>> {
+        let mut conn = self.get_conn()?;
+
+        let db_record: Option<DbSourceRecord> = source_records::table
+            .filter(source_records::id.eq(id))
+            .first(&mut conn)
+            .optional()?;
+
+        db_record.map(Self::to_source_record).transpose()
+    }
+
+    fn list_by_source(&self, source_system: &str, source_record_id: &str) -> Result<Vec<SourceRecord>> {
+        let mut conn = self.get_conn()?;
+
+        let db_records: Vec<DbSourceRecord> = source_records::table
+            .filter(source_records::source_system.eq(source_system))
+            .filter(source_records::source_record_id.eq(source_record_id))
+            .order(source_records::received_at.desc())
+            .load(&mut conn)?;
+
+        db_records.into_iter().map(Self::to_source_record).collect()
+    }
+
+    fn count_by_source_system(&self, source_system: &str) -> Result<i64> {
+        let mut conn = self.get_conn()?;
+
+        let count = source_records::table
+            .filter(source_records::source_system.eq(source_system))
+            .count()
+            .get_result(&mut conn)?;
+
+        Ok(count)
+    }
+
+    fn list_by_source_system(&self, source_system: &str, limit: i64, offset: i64) -> Result<Vec<SourceRecord>> {
+        let mut conn = self.get_conn()?;
+
+        let db_records: Vec<DbSourceRecord> = source_records::table
+            .filter(source_records::source_system.eq(source_system))
+            .order((source_records::received_at.asc(), source_records::id.asc()))
+            .limit(limit)
+            .offset(offset)
+            .load(&mut conn)?;
+
+        db_records.into_iter().map(Self::to_source_record).collect()
+    }
+
+    fn purge(&self, id: &Uuid) -> Result<bool> {
+        let mut conn = self.get_conn()?;
+
+        // Links are removed with the record by ON DELETE CASCADE
+        let deleted = diesel::delete(source_records::table.filter(source_records::id.eq(id)))
+            .execute(&mut conn)?;
+
+        Ok(deleted > 0)
+    }
+
+    fn list_for_patient(&self, patient_id: &Uuid) -> Result<Vec<SourceRecord>> {
+        let mut conn = self.get_conn()?;
+
+        let db_records: Vec<DbSourceRecord> = source_records::table
+            .inner_join(source_record_links::table)
+            .filter(source_record_links::patient_id.eq(patient_id))
+            .filter(source_record_links::unlinked_at.is_null())
+            .order(source_records::received_at.desc())
+            .select(DbSourceRecord::as_select())
+            .load(&mut conn)?;
+
+        db_records.into_iter().map(Self::to_source_record).collect()
+    }
+
+    fn link(
+        &self,
+        source_record_id: &Uuid,
+        patient_id: &Uuid,
+        match_score: Option<f64>,
+        linked_by: Option<String>,
+    ) -> Result<SourceRecordLink> {
+        let mut conn = self.get_conn()?;
+        Self::link_in(&mut conn, source_record_id, patient_id, match_score, linked_by)
+    }
+
+    fn unlink(&self, source_record_id: &Uuid, unlinked_by: Option<String>) -> Result<()> {
+        let mut conn = self.get_conn()?;
+
+        diesel::update(
+            source_record_links::table
+                .filter(source_record_links::source_record_id.eq(source_record_id))
+                .filter(source_record_links::unlinked_at.is_null()),
+        )
+        .set((
+            source_record_links::unlinked_at.eq(Some(Utc::now())),
+            source_record_links::unlinked_by.eq(unlinked_by),
+        ))
+        .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    fn current_link(&self, source_record_id: &Uuid) -> Result<Option<SourceRecordLink>> {
+        let mut conn = self.get_conn()?;
+
+        let db_link: Option<DbSourceRecordLink> = source_record_links::table
+            .filter(source_record_links::source_record_id.eq(source_record_id))
+            .filter(source_record_links::unlinked_at.is_null())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(db_link.map(Self::to_link))
+    }
+
+    fn list_active_links(&self, limit: i64, offset: i64) -> Result<Vec<SourceRecordLink>> {
+        let mut conn = self.get_conn()?;
+
+        let db_links: Vec<DbSourceRecordLink> = source_record_links::table
+            .filter(source_record_links::unlinked_at.is_null())
+            .order(source_record_links::linked_at.asc())
+            .limit(limit)
+            .offset(offset)
+            .load(&mut conn)?;
+
+        Ok(db_links.into_iter().map(Self::to_link).collect())
+    }
+
+    fn set_confidence(&self, link_id: &Uuid, confidence: f64, needs_reverification: bool) -> Result<()> {
+        let mut conn = self.get_conn()?;
+
+        let confidence = Self::to_decimal(confidence.clamp(0.0, 1.0), "link confidence")?;
+        diesel::update(source_record_links::table.find(link_id))
+            .set((
+                source_record_links::confidence.eq(confidence),
+                source_record_links::needs_reverification.eq(needs_reverification),
+            ))
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    fn list_needing_reverification(&self, limit: i64, offset: i64) -> Result<Vec<SourceRecordLink>> {
+        let mut conn = self.get_conn()?;
+
+        let db_links: Vec<DbSourceRecordLink> = source_record_links::table
+            .filter(source_record_links::needs_reverification.eq(true))
+            .filter(source_record_links::unlinked_at.is_null())
+            .order((source_record_links::confidence.asc(), source_record_links::linked_at.asc()))
+            .limit(limit)
+            .offset(offset)
+            .load(&mut conn)?;
+
+        Ok(db_links.into_iter().map(Self::to_link).collect())
+    }
+
+    fn verify_link(&self, link_id: &Uuid, verified_by: Option<String>) -> Result<Option<SourceRecordLink>> {
+        let mut conn = self.get_conn()?;
+
+        let db_link: Option<DbSourceRecordLink> = diesel::update(
+            source_record_links::table
+                .find(link_id)
+                .filter(source_record_links::unlinked_at.is_null()),
+        )
+        .set((
+            source_record_links::confidence.eq(BigDecimal::from(1)),
+            source_record_links::needs_reverification.eq(false),
+            source_record_links::verified_at.eq(Some(Utc::now())),
+            source_record_links::verified_by.eq(verified_by),
+        ))
+        .get_result(&mut conn)
+        .optional()?;
+
+        Ok(db_link.map(Self::to_link))
+    }
+
+    fn list_received(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<SourceRecord>> {
+        let mut conn = self.get_conn()?;
+
+        let db_records: Vec<DbSourceRecord> = source_records::table
+            .filter(source_records::received_at.ge(since))
+            .filter(source_records::received_at.lt(until))
+            .order((source_records::received_at.asc(), source_records::id.asc()))
+            .limit(limit)
+            .offset(offset)
+            .load(&mut conn)?;
+
+        db_records.into_iter().map(Self::to_source_record).collect()
+    }
+}
@@ -0,0 +1,206 @@
+//! Aggregate counts for operational dashboards
+
+use diesel::dsl::count_star;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::sql_types::{BigInt, Double, Text};
+use diesel::PgConnection;
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::Result;
+use super::schema::{patient_links, patients};
+
+/// Scored pairs at or above the threshold whose patients are not yet linked
+const PENDING_REVIEW_SQL: &str = "
+    SELECT COUNT(*) AS count
+    FROM patient_match_scores s
+    JOIN patients a ON a.id = s.patient_id AND a.deleted_at IS NULL
+    JOIN patients b ON b.id = s.candidate_id AND b.deleted_at IS NULL
+    WHERE s.total_score >= $1::numeric
+      AND NOT EXISTS (
+          SELECT 1 FROM patient_links l
+          WHERE (l.patient_id = s.patient_id AND l.other_patient_id = s.candidate_id)
+             OR (l.patient_id = s.candidate_id AND l.other_patient_id = s.patient_id)
+      )";
+
+/// Live patients by lowercased primary family name
+const FAMILY_NAME_COUNTS_SQL: &str = "
+    SELECT lower(trim(n.family)) AS family, COUNT(DISTINCT n.patient_id) AS count
+    FROM patient_names n
+    JOIN patients p ON p.id = n.patient_id AND p.deleted_at IS NULL
+    WHERE n.is_primary AND trim(n.family) <> ''
+    GROUP BY 1";
+
+/// Patient counts, excluding soft-deleted records
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PatientCounts {
+    pub total: i64,
+    pub active: i64,
+    pub deceased: i64,
+    pub by_gender: Vec<GenderCount>,
+    pub by_managing_organization: Vec<OrganizationCount>,
+}
+
+/// Number of patients with a gender
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct GenderCount {
+    pub gender: String,
+    pub count: i64,
+}
+
+/// Number of patients managed by an organization (`None` for unmanaged)
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct OrganizationCount {
+    pub organization_id: Option<Uuid>,
+    pub count: i64,
+}
+
+/// Patient link counts
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LinkCounts {
+    pub total: i64,
+    /// Merges, counted by their `ReplacedBy` links
+    pub merges: i64,
+    pub by_type: Vec<LinkTypeCount>,
+}
+
+/// Number of links of a type
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LinkTypeCount {
+    pub link_type: String,
+    pub count: i64,
+}
+
+#[derive(QueryableByName)]
+struct CountRow {
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+#[derive(QueryableByName)]
+struct FamilyNameRow {
+    #[diesel(sql_type = Text)]
+    family: String,
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+/// Repository for aggregate statistics
+pub struct StatisticsRepository {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl StatisticsRepository {
+    /// Create a new statistics repository
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+
+    /// Get a database connection from the pool
+    fn get_conn(&self) -> Result<diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>> {
+        self.pool.get().map_err(|e| crate::Error::Pool(e.to_string()))
+    }
+
+    /// Count patients overall and grouped by gender and managing organization
+    pub fn patient_counts(&self) -> Result<PatientCounts> {
+        let mut conn = self.get_conn()?;
+        let live = patients::deleted_at.is_null();
+
+        let total = patients::table
+            .filter(live)
+            .count()
+            .get_result::<i64>(&mut conn)?;
+        let active = patients::table
+            .filter(live)
+            .filter(patients::active.eq(true))
+            .count()
+            .get_result::<i64>(&mut conn)?;
+        let deceased = patients::table
+            .filter(live)
+            .filter(patients::deceased.eq(true))
+            .count()
+            .get_result::<i64>(&mut conn)?;
+
+        let by_gender = patients::table
+            .filter(live)
+            .group_by(patients::gender)
+            .select((patients::gender, count_star()))
+            .order(patients::gender.asc())
+            .load::<(String, i64)>(&mut conn)?
+            .into_iter()
+            .map(|(gender, count)| GenderCount { gender, count })
+            .collect();
+
+        let by_managing_organization = patients::table
+            .filter(live)
+            .group_by(patients::managing_organization_id)
+            .select((patients::managing_organization_id, count_star()))
+            .order(count_star().desc())
+            .load::<(Option<Uuid>, i64)>(&mut conn)?
+            .into_iter()
+            .map(|(organization_id, count)| OrganizationCount { organization_id, count })
+            .collect();
+
+        Ok(PatientCounts {
+            total,
+            active,
+            deceased,
+            by_gender,
+            by_managing_organization,
+        })
+    }
+
+    /// Count patient links, grouped by link type
+    pub fn link_counts(&self) -> Result<LinkCounts> {
+        let mut conn = self.get_conn()?;
+
+        let by_type: Vec<LinkTypeCount> = patient_links::table
+            .group_by(patient_links::link_type)
+            .select((patient_links::link_type, count_star()))
+            .order(patient_links::link_type.asc())
+            .load::<(String, i64)>(&mut conn)?
+            .into_iter()
+            .map(|(link_type, count)| LinkTypeCount { link_type, count })
+            .collect();
+
+        let total = by_type.iter().map(|c| c.count).sum();
+        let merges = by_type
+            .iter()
+            .filter(|c| c.link_type == "ReplacedBy")
+            .map(|c| c.count)
+            .sum();
+
+        Ok(LinkCounts { total, merges, by_type })
+    }
+
+    /// Count live patients, overall and by lowercased primary family name
+    pub fn family_name_counts(&self) -> Result<(i64, Vec<(String, i64)>)> {
+        let mut conn = self.get_conn()?;
+
+        let total = patients::table
+            .filter(patients::deleted_at.is_null())
+            .count()
+            .get_result::<i64>(&mut conn)?;
+        let counts = diesel::sql_query(FAMILY_NAME_COUNTS_SQL)
+            .load::<FamilyNameRow>(&mut conn)?
+            .into_iter()
+            .map(|row| (row.family, row.count))
+            .collect();
+
+        Ok((total, counts))
+    }
+
+    /// Count candidate duplicates scoring at or above the threshold that
+    /// have not been linked yet
+    pub fn pending_review_count(&self, threshold: f64) -> Result<i64> {
+        let mut conn = self.get_conn()?;
+
+        let row: CountRow = diesel::sql_query(PENDING_REVIEW_SQL)
+            .bind::<Double, _>(threshold)
+            .get_result(&mut conn)?;
+
+        Ok(row.count)
+    }
+}
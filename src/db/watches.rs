@@ -0,0 +1,120 @@
+//! Patient watch repository
+
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::models::PatientWatch;
+use crate::Result;
+use super::models::{DbPatientWatch, NewDbPatientWatch};
+use super::schema::patient_watches;
+
+/// Patient watch repository trait
+pub trait WatchRepository: Send + Sync {
+    /// Subscribe to a patient's changes
+    fn create(
+        &self,
+        patient_id: &Uuid,
+        callback_url: Option<String>,
+        event_types: Vec<String>,
+        created_by: Option<String>,
+    ) -> Result<PatientWatch>;
+
+    /// Get a watch by ID
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<PatientWatch>>;
+
+    /// Remove a watch, returning whether it existed
+    fn delete(&self, id: &Uuid) -> Result<bool>;
+
+    /// List the watches on any of the given patients
+    fn list_for_patients(&self, patient_ids: &[Uuid]) -> Result<Vec<PatientWatch>>;
+}
+
+/// Diesel-based watch repository implementation
+pub struct DieselWatchRepository {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl DieselWatchRepository {
+    /// Create a new repository with the given connection pool
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+
+    /// Get a database connection from the pool
+    fn get_conn(&self) -> Result<diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>> {
+        self.pool.get().map_err(|e| crate::Error::Pool(e.to_string()))
+    }
+
+    /// Convert a database watch to the domain model
+    fn to_watch(db_watch: DbPatientWatch) -> PatientWatch {
+        PatientWatch {
+            id: db_watch.id,
+            patient_id: db_watch.patient_id,
+            callback_url: db_watch.callback_url,
+            event_types: db_watch.event_types,
+            created_at: db_watch.created_at,
+            created_by: db_watch.created_by,
+        }
+    }
+}
+
+impl WatchRepository for DieselWatchRepository {
+    fn create(
+        &self,
+        patient_id: &Uuid,
+        callback_url: Option<String>,
+        event_types: Vec<String>,
+        created_by: Option<String>,
+    ) -> Result<PatientWatch> {
+        let mut conn = self.get_conn()?;
+
+        let new_watch = NewDbPatientWatch {
+            patient_id: *patient_id,
+            callback_url,
+            event_types,
+            created_by,
+        };
+
+        let db_watch: DbPatientWatch = diesel::insert_into(patient_watches::table)
+            .values(&new_watch)
+            .returning(DbPatientWatch::as_returning())
+            .get_result(&mut conn)?;
+
+        Ok(Self::to_watch(db_watch))
+    }
+
+    fn get_by_id(&self, id: &Uuid) -> Result<Option<PatientWatch>> {
+        let mut conn = self.get_conn()?;
+
+        let db_watch = patient_watches::table
+            .find(id)
+            .select(DbPatientWatch::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(db_watch.map(Self::to_watch))
+    }
+
+    fn delete(&self, id: &Uuid) -> Result<bool> {
+        let mut conn = self.get_conn()?;
+
+        let deleted = diesel::delete(patient_watches::table.find(id))
+            .execute(&mut conn)?;
+
+        Ok(deleted > 0)
+    }
+
+    fn list_for_patients(&self, patient_ids: &[Uuid]) -> Result<Vec<PatientWatch>> {
+        let mut conn = self.get_conn()?;
+
+        let db_watches = patient_watches::table
+            .filter(patient_watches::patient_id.eq_any(patient_ids))
+            .order(patient_watches::created_at.asc())
+            .select(DbPatientWatch::as_select())
+            .load(&mut conn)?;
+
+        Ok(db_watches.into_iter().map(Self::to_watch).collect())
+    }
+}
@@ -0,0 +1,65 @@
+//! Request deadlines for cooperative cancellation
+//!
+//! A request's time budget is set for the task handling it with [`scope`].
+//! Long-running repository, search and matching loops call [`check`] between
+//! steps and give up with [`Error::Timeout`] once the deadline has passed,
+//! instead of running to completion for a client that has been answered
+//! already. Code running outside a scope, such as background jobs, is never
+//! cancelled.
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use crate::{Error, Result};
+
+tokio::task_local! {
+    static DEADLINE: Instant;
+}
+
+/// Run `future` with a deadline
+pub async fn scope<F: Future>(deadline: Instant, future: F) -> F::Output {
+    DEADLINE.scope(deadline, future).await
+}
+
+/// Time left before the current deadline, if there is one
+pub fn remaining() -> Option<Duration> {
+    DEADLINE
+        .try_with(|deadline| deadline.saturating_duration_since(Instant::now()))
+        .ok()
+}
+
+/// Whether the current deadline has passed
+pub fn expired() -> bool {
+    remaining() == Some(Duration::ZERO)
+}
+
+/// Fail with [`Error::Timeout`] if the current deadline has passed
+pub fn check() -> Result<()> {
+    if expired() {
+        return Err(Error::Timeout("Request time budget exhausted".to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_check_in_scope() {
+        assert!(check().is_ok());
+        assert_eq!(remaining(), None);
+
+        scope(Instant::now() + Duration::from_secs(60), async {
+            assert!(check().is_ok());
+            assert!(remaining().unwrap() > Duration::from_secs(50));
+        })
+        .await;
+
+        scope(Instant::now(), async {
+            assert!(expired());
+            assert!(matches!(check(), Err(Error::Timeout(_))));
+        })
+        .await;
+    }
+}
@@ -20,6 +20,9 @@ pub enum Error {
     #[error("Patient not found: {0}")]
     PatientNotFound(String),
 
+    #[error("Patient deleted: {0}")]
+    PatientDeleted(String),
+
     #[error("Validation error: {0}")]
     Validation(String),
 
@@ -35,11 +38,23 @@ pub enum Error {
     #[error("Streaming error: {0}")]
     Streaming(String),
 
+    #[error("Notification error: {0}")]
+    Notification(String),
+
     #[error("FHIR error: {0}")]
     Fhir(String),
 
     #[error("Internal error: {0}")]
     Internal(String),
+
+    #[error("Timeout: {0}")]
+    Timeout(String),
+
+    #[error("Unsupported: {0}")]
+    Unsupported(String),
+
+    #[error("Merge conflict: {0}")]
+    MergeConflict(crate::matching::MergeConflict),
 }
 
 impl Error {
@@ -0,0 +1,333 @@
+//! De-identification of exported patients
+//!
+//! Follows the HIPAA Safe Harbor method for the fields the MPI holds:
+//!
+//! - names, telecom, photos, contact persons and street address lines are
+//!   removed
+//! - ZIP codes are cut to their first three digits, or `000` for the
+//!   three-digit areas with fewer than 20,000 people
+//! - dates are shifted by a per-patient offset, and birth dates are removed
+//!   for patients aged 90 or over
+//! - identifiers are replaced by a keyed HMAC-SHA256 of their system and value
+//!
+//! Patient IDs and link targets are replaced by keyed pseudonyms, so linked
+//! records stay linked in the extract and the same patient gets the same
+//! pseudonym in every extract made with the same key. Without the key the
+//! pseudonyms and hashes cannot be reversed or recomputed from known values.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::config::ExportConfig;
+use crate::models::{Address, HumanName, Identifier, NameUse, Patient, PatientLink, Period, VerificationStatus};
+use crate::Result;
+
+/// Shortest accepted HMAC key, in bytes
+pub const MIN_KEY_BYTES: usize = 32;
+
+/// Three-digit ZIP areas with 20,000 or fewer residents (2000 Census), which
+/// Safe Harbor requires to be reported as `000`
+const RESTRICTED_ZIP3: [&str; 17] = [
+    "036", "059", "063", "102", "203", "556", "692", "790", "821", "823", "830", "831", "878", "879",
+    "884", "890", "893",
+];
+
+/// Age from which birth dates are withheld
+const AGGREGATED_AGE: i32 = 90;
+
+/// Keyed patient de-identifier
+#[derive(Clone)]
+pub struct Deidentifier {
+    key: Vec<u8>,
+    max_date_shift_days: i64,
+    as_of: NaiveDate,
+}
+
+impl std::fmt::Debug for Deidentifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Deidentifier")
+            .field("max_date_shift_days", &self.max_date_shift_days)
+            .field("as_of", &self.as_of)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Deidentifier {
+    /// Create a de-identifier with the given secret key
+    pub fn new(key: &[u8]) -> Result<Self> {
+        if key.len() < MIN_KEY_BYTES {
+            return Err(crate::Error::Validation(format!(
+                "De-identification key must be at least {} bytes",
+                MIN_KEY_BYTES
+            )));
+        }
+        Ok(Self {
+            key: key.to_vec(),
+            max_date_shift_days: 0,
+            as_of: Utc::now().date_naive(),
+        })
+    }
+
+    /// Create a de-identifier from the `export` configuration
+    pub fn from_config(config: &ExportConfig) -> Result<Self> {
+        let key = config.hmac_key.as_deref().ok_or_else(|| {
+            crate::Error::Config("export.hmac_key must be set for de-identified exports".to_string())
+        })?;
+        Ok(Self::new(key.as_bytes())?.with_max_date_shift_days(config.max_date_shift_days))
+    }
+
+    /// Shift dates by up to this many days either way
+    pub fn with_max_date_shift_days(mut self, days: u32) -> Self {
+        self.max_date_shift_days = days as i64;
+        self
+    }
+
+    /// Compute ages as of this date rather than today
+    pub fn with_as_of(mut self, as_of: NaiveDate) -> Self {
+        self.as_of = as_of;
+        self
+    }
+
+    fn mac(&self, domain: &str, data: &[u8]) -> [u8; 32] {
+        let mut mac = <Hmac<Sha256> as KeyInit>::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        // Separate the uses of the key so one output cannot stand in for another
+        mac.update(domain.as_bytes());
+        mac.update(&[0]);
+        mac.update(data);
+        mac.finalize().into_bytes().into()
+    }
+
+    /// Stable pseudonym for a patient ID
+    pub fn pseudonym(&self, id: &Uuid) -> Uuid {
+        let digest = self.mac("patient-id", id.as_bytes());
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&digest[..16]);
+        uuid::Builder::from_custom_bytes(bytes).into_uuid()
+    }
+
+    /// Hex HMAC of an identifier's system and value
+    pub fn hash_identifier(&self, identifier: &Identifier) -> String {
+        let data = format!("{}|{}", identifier.system, identifier.value.trim());
+        self.mac("identifier", data.as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Days every date of a patient is moved by
+    pub fn date_shift_days(&self, id: &Uuid) -> i64 {
+        if self.max_date_shift_days == 0 {
+            return 0;
+        }
+        let digest = self.mac("date-shift", id.as_bytes());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        let span = 2 * self.max_date_shift_days as u64 + 1;
+        (u64::from_le_bytes(bytes) % span) as i64 - self.max_date_shift_days
+    }
+
+    /// De-identified copy of a patient
+    pub fn deidentify(&self, patient: &Patient) -> Patient {
+        let shift = Duration::days(self.date_shift_days(&patient.id));
+        let shift_datetime = |datetime: DateTime<Utc>| datetime + shift;
+
+        let birth_date = patient
+            .birth_date
+            .filter(|birth_date| age_on(*birth_date, self.as_of) < AGGREGATED_AGE)
+            .map(|birth_date| birth_date + shift);
+
+        let identifiers = patient
+            .identifiers
+            .iter()
+            .map(|identifier| Identifier {
+                use_type: identifier.use_type.clone(),
+                identifier_type: identifier.identifier_type.clone(),
+                system: identifier.system.clone(),
+                value: self.hash_identifier(identifier),
+                assigner: None,
+                verification: identifier.verification,
+                period: identifier.period.map(|period| Period {
+                    start: period.start.map(shift_datetime),
+                    end: period.end.map(shift_datetime),
+                }),
+            })
+            .collect();
+
+        let addresses = patient
+            .addresses
+            .iter()
+            .filter_map(|address| {
+                let coarse = Address {
+                    line1: None,
+                    line2: None,
+                    city: None,
+                    state: address.state.clone(),
+                    postal_code: address.postal_code.as_deref().and_then(zip3),
+                    country: address.country.clone(),
+                    verification: VerificationStatus::Unverified,
+                    period: None,
+                };
+                let empty = coarse.state.is_none() && coarse.postal_code.is_none() && coarse.country.is_none();
+                (!empty).then_some(coarse)
+            })
+            .collect();
+
+        let links = patient
+            .links
+            .iter()
+            .map(|link| PatientLink {
+                other_patient_id: self.pseudonym(&link.other_patient_id),
+                link_type: link.link_type.clone(),
+            })
+            .collect();
+
+        Patient {
+            id: self.pseudonym(&patient.id),
+            identifiers,
+            active: patient.active,
+            name: HumanName {
+                use_type: Some(NameUse::Anonymous),
+                family: String::new(),
+                given: Vec::new(),
+                prefix: Vec::new(),
+                suffix: Vec::new(),
+            },
+            additional_names: Vec::new(),
+            telecom: Vec::new(),
+            gender: patient.gender,
+            gender_identity: patient.gender_identity.clone(),
+            pronouns: patient.pronouns.clone(),
+            birth_date,
+            deceased: patient.deceased,
+            deceased_datetime: patient.deceased_datetime.map(shift_datetime),
+            addresses,
+            marital_status: patient.marital_status.clone(),
+            multiple_birth: patient.multiple_birth,
+            photo: Vec::new(),
+            photo_hashes: Vec::new(),
+            managing_organization: patient.managing_organization,
+            links,
+            contacts: Vec::new(),
+            confidentiality: patient.confidentiality,
+            status: patient.status,
+            created_at: shift_datetime(patient.created_at),
+            updated_at: shift_datetime(patient.updated_at),
+        }
+    }
+}
+
+/// First three digits of a US ZIP code, or `000` for sparsely populated areas
+pub fn zip3(postal_code: &str) -> Option<String> {
+    let digits: String = postal_code.chars().filter(|c| !c.is_whitespace()).take(5).collect();
+    if digits.len() < 3 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let prefix = &digits[..3];
+    if RESTRICTED_ZIP3.contains(&prefix) {
+        Some("000".to_string())
+    } else {
+        Some(prefix.to_string())
+    }
+}
+
+fn age_on(birth_date: NaiveDate, on: NaiveDate) -> i32 {
+    let mut age = on.year() - birth_date.year();
+    if (on.month(), on.day()) < (birth_date.month(), birth_date.day()) {
+        age -= 1;
+    }
+    age
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ContactPoint, ContactPointSystem, Gender, LinkType};
+
+    const KEY: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    fn patient() -> Patient {
+        let mut patient = crate::fixtures::patient("Achterberg", &["Femke"], Gender::Female);
+        patient.birth_date = NaiveDate::from_ymd_opt(1980, 2, 14);
+        patient.identifiers.push(Identifier::ssn("123-45-6789".to_string()));
+        patient.telecom.push(ContactPoint {
+            system: ContactPointSystem::Email,
+            value: "femke@example.com".to_string(),
+            use_type: None,
+            period: None,
+        });
+        patient.addresses.push(Address {
+            line1: Some("4 Elm St".to_string()),
+            line2: None,
+            city: Some("Burlington".to_string()),
+            state: Some("VT".to_string()),
+            postal_code: Some("05401-1234".to_string()),
+            country: Some("US".to_string()),
+            verification: VerificationStatus::Unverified,
+            period: None,
+        });
+        patient
+    }
+
+    #[test]
+    fn test_requires_long_key() {
+        assert!(Deidentifier::new(b"short").is_err());
+        assert!(Deidentifier::from_config(&ExportConfig::default()).is_err());
+        assert!(Deidentifier::new(KEY).is_ok());
+    }
+
+    #[test]
+    fn test_zip3() {
+        assert_eq!(zip3("05401-1234").as_deref(), Some("054"));
+        assert_eq!(zip3("03601").as_deref(), Some("000"));
+        assert_eq!(zip3("SW1A 1AA"), None);
+        assert_eq!(zip3("05"), None);
+    }
+
+    #[test]
+    fn test_deidentify_strips_direct_identifiers() {
+        let deidentifier = Deidentifier::new(KEY)
+            .unwrap()
+            .with_max_date_shift_days(30)
+            .with_as_of(NaiveDate::from_ymd_opt(2026, 1, 1).unwrap());
+        let mut original = patient();
+        let other = Uuid::new_v4();
+        original.links.push(PatientLink { other_patient_id: other, link_type: LinkType::Refer });
+
+        let result = deidentifier.deidentify(&original);
+
+        assert_ne!(result.id, original.id);
+        assert_eq!(result.id, deidentifier.pseudonym(&original.id));
+        assert_eq!(result.links[0].other_patient_id, deidentifier.pseudonym(&other));
+        assert!(result.name.family.is_empty() && result.name.given.is_empty());
+        assert!(result.telecom.is_empty());
+        assert_eq!(result.identifiers[0].value.len(), 64);
+        assert_ne!(result.identifiers[0].value, "123-45-6789");
+        assert_eq!(result.addresses[0].postal_code.as_deref(), Some("054"));
+        assert!(result.addresses[0].line1.is_none() && result.addresses[0].city.is_none());
+
+        let shift = deidentifier.date_shift_days(&original.id);
+        assert!((-30..=30).contains(&shift));
+        assert_eq!(result.birth_date, original.birth_date.map(|d| d + Duration::days(shift)));
+
+        // The same key gives the same output; another key does not
+        assert_eq!(deidentifier.deidentify(&original).identifiers[0].value, result.identifiers[0].value);
+        let other_key = Deidentifier::new(b"fedcba9876543210fedcba9876543210").unwrap();
+        assert_ne!(other_key.pseudonym(&original.id), result.id);
+    }
+
+    #[test]
+    fn test_withholds_birth_date_at_ninety() {
+        let deidentifier = Deidentifier::new(KEY)
+            .unwrap()
+            .with_as_of(NaiveDate::from_ymd_opt(2026, 1, 1).unwrap());
+        let mut patient = patient();
+
+        patient.birth_date = NaiveDate::from_ymd_opt(1936, 1, 2);
+        assert!(deidentifier.deidentify(&patient).birth_date.is_some());
+
+        patient.birth_date = NaiveDate::from_ymd_opt(1936, 1, 1);
+        assert!(deidentifier.deidentify(&patient).birth_date.is_none());
+    }
+}
@@ -0,0 +1,249 @@
+//! Bulk patient export
+//!
+//! Writes every active patient, or the members of one patient group, as
+//! newline-delimited JSON, one `Patient` per line. With a [`Deidentifier`] the extract is de-identified on the way out
+//! but stays linked: the same patient always gets the same pseudonym, and
+//! link targets use the same pseudonyms.
+
+pub mod deidentify;
+
+use std::io::Write;
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use utoipa::ToSchema;
+
+use crate::db::{AuditLogRepository, PatientRepository};
+use crate::jobs::JobHandle;
+use crate::models::{Patient, PatientGroup};
+use crate::Result;
+
+pub use deidentify::Deidentifier;
+
+/// Number of patients fetched per page
+const BATCH_SIZE: i64 = 500;
+
+/// Bytes buffered before a chunk is sent to a streaming client
+const STREAM_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Chunks queued ahead of a slow streaming client
+const STREAM_BUFFER_CHUNKS: usize = 8;
+
+/// Parameters of a bulk export
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ExportRequest {
+    /// Server-side file the NDJSON extract is written to
+    pub path: String,
+
+    /// De-identify patients using `export.hmac_key` (default true)
+    #[serde(default = "default_deidentify")]
+    pub deidentify: bool,
+
+    /// User requesting the export, recorded in the audit log; taken from
+    /// `X-User-Id`, never from the request body
+    #[serde(default, skip_deserializing)]
+    pub requested_by: Option<String>,
+
+    /// Export only the members of this patient group
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<uuid::Uuid>,
+}
+
+fn default_deidentify() -> bool {
+    true
+}
+
+/// Outcome of a bulk export
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ExportReport {
+    /// File written
+    pub path: String,
+    /// Patients written
+    pub patients: u64,
+    /// Whether the extract was de-identified
+    pub deidentified: bool,
+}
+
+/// Writes patients as NDJSON, optionally de-identified
+pub struct PatientExporter {
+    patients: Arc<dyn PatientRepository>,
+    deidentifier: Option<Deidentifier>,
+    audit_log: Option<Arc<AuditLogRepository>>,
+    group: Option<PatientGroup>,
+}
+
+impl PatientExporter {
+    /// Create an exporter reading from the given repository
+    pub fn new(patients: Arc<dyn PatientRepository>) -> Self {
+        Self {
+            patients,
+            deidentifier: None,
+            audit_log: None,
+            group: None,
+        }
+    }
+
+    /// De-identify every exported patient
+    pub fn with_deidentifier(mut self, deidentifier: Deidentifier) -> Self {
+        self.deidentifier = Some(deidentifier);
+        self
+    }
+
+    /// Record each export in the audit log
+    pub fn with_audit_log(mut self, audit_log: Arc<AuditLogRepository>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Export only the group's members instead of every active patient
+    ///
+    /// Members deleted since they were added are left out.
+    pub fn with_group(mut self, group: PatientGroup) -> Self {
+        self.group = Some(group);
+        self
+    }
+
+    /// Next page of patients to export, empty once all are written
+    fn page(&self, offset: i64) -> Result<Vec<Patient>> {
+        let Some(group) = &self.group else {
+            return self.patients.list_active(BATCH_SIZE, offset);
+        };
+        let mut patients = Vec::new();
+        for id in group.members.iter().skip(offset as usize).take(BATCH_SIZE as usize) {
+            if let Some(patient) = self.patients.get_by_id(id)? {
+                patients.push(patient);
+            }
+        }
+        Ok(patients)
+    }
+
+    /// Write all active patients, or the group's members, to `out`,
+    /// returning the number written
+    pub fn write_ndjson<W: Write>(&self, mut out: W, handle: Option<&JobHandle>) -> Result<u64> {
+        let mut written = 0;
+        let mut offset = 0;
+        loop {
+            let patients = self.page(offset)?;
+            let read = match &self.group {
+                Some(group) => (group.members.len() as i64 - offset).clamp(0, BATCH_SIZE),
+                None => patients.len() as i64,
+            };
+            if read == 0 {
+                break;
+            }
+            offset += read;
+
+            for patient in &patients {
+                let line = match &self.deidentifier {
+                    Some(deidentifier) => serde_json::to_string(&deidentifier.deidentify(patient)),
+                    None => serde_json::to_string(patient),
+                }
+                .map_err(|e| crate::Error::Internal(format!("Failed to serialize patient: {}", e)))?;
+                writeln!(out, "{}", line).map_err(|e| crate::Error::Internal(e.to_string()))?;
+            }
+
+            written += patients.len() as u64;
+            if let Some(handle) = handle {
+                handle.advance(patients.len() as u64);
+            }
+        }
+        out.flush().map_err(|e| crate::Error::Internal(e.to_string()))?;
+        Ok(written)
+    }
+
+    /// Stream the export as NDJSON chunks instead of writing a server-side file
+    ///
+    /// Patients are written on the blocking thread pool as the receiver is
+    /// drained. A failure part way through ends the stream with the error;
+    /// dropping the receiver stops the export at its next write.
+    pub fn stream(self, requested_by: Option<String>) -> mpsc::Receiver<std::io::Result<Bytes>> {
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER_CHUNKS);
+        tokio::task::spawn_blocking(move || {
+            let export_id = uuid::Uuid::new_v4();
+            if let Some(audit_log) = &self.audit_log {
+                let values = serde_json::json!({
+                    "stream": true,
+                    "deidentify": self.deidentifier.is_some(),
+                    "requested_by": requested_by,
+                    "group": self.group.as_ref().map(|group| group.id),
+                });
+                if let Err(e) = audit_log.log_create("PatientExport", export_id, values, requested_by.clone(), None, None) {
+                    tracing::error!("Failed to log audit: {}", e);
+                }
+            }
+
+            let out = std::io::BufWriter::with_capacity(STREAM_CHUNK_BYTES, ChannelWriter { tx: tx.clone() });
+            match self.write_ndjson(out, None) {
+                Ok(patients) => tracing::info!(
+                    "Streamed {} patients in export {}{}",
+                    patients,
+                    export_id,
+                    if self.deidentifier.is_some() { " (de-identified)" } else { "" }
+                ),
+                Err(e) => {
+                    tracing::warn!("Streamed export {} failed: {}", export_id, e);
+                    let _ = tx.blocking_send(Err(std::io::Error::other(e.to_string())));
+                }
+            }
+        });
+        rx
+    }
+
+    /// Run the export to `request.path` in the background
+    pub fn spawn(self, request: ExportRequest, handle: JobHandle) -> tokio::task::JoinHandle<()> {
+        tokio::task::spawn_blocking(move || {
+            handle.start(None);
+            if let Some(audit_log) = &self.audit_log {
+                let values = serde_json::to_value(&request).unwrap_or_default();
+                if let Err(e) = audit_log.log_create("PatientExport", handle.id(), values, request.requested_by.clone(), None, None) {
+                    tracing::error!("Failed to log audit: {}", e);
+                }
+            }
+
+            let result = std::fs::File::create(&request.path)
+                .map_err(|e| crate::Error::Internal(format!("Failed to create {}: {}", request.path, e)))
+                .and_then(|file| self.write_ndjson(std::io::BufWriter::new(file), Some(&handle)));
+
+            match result {
+                Ok(patients) => {
+                    let report = ExportReport {
+                        path: request.path.clone(),
+                        patients,
+                        deidentified: self.deidentifier.is_some(),
+                    };
+                    tracing::info!(
+                        "Exported {} patients to {}{}",
+                        patients,
+                        request.path,
+                        if report.deidentified { " (de-identified)" } else { "" }
+                    );
+                    handle.complete(serde_json::to_value(&report).unwrap_or_default());
+                }
+                Err(e) => {
+                    tracing::warn!("Export to {} failed: {}", request.path, e);
+                    handle.fail(&e.to_string());
+                }
+            }
+        })
+    }
+}
+
+/// Sends everything written to it down a channel, one chunk per write
+struct ChannelWriter {
+    tx: mpsc::Sender<std::io::Result<Bytes>>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.tx
+            .blocking_send(Ok(Bytes::copy_from_slice(buf)))
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Export stream closed by the client"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,17 @@
+//! Fixtures shared by the unit tests
+
+use crate::models::{Gender, HumanName, Patient};
+
+/// A new patient with only a legal name and a gender
+pub fn patient(family: &str, given: &[&str], gender: Gender) -> Patient {
+    Patient::new(
+        HumanName {
+            use_type: None,
+            family: family.to_string(),
+            given: given.iter().map(|given| given.to_string()).collect(),
+            prefix: vec![],
+            suffix: vec![],
+        },
+        gender,
+    )
+}
@@ -0,0 +1,432 @@
+//! Bulk patient import
+//!
+//! Loads a server-side newline-delimited JSON file, one `Patient` per line,
+//! the format the bulk export writes. Every `import.checkpoint_every` lines
+//! the import stores an [`ImportCheckpoint`] with the byte offset of the
+//! next line, so an import that crashed, or failed on a database outage, is
+//! resumed from there rather than from the start of the file.
+//!
+//! Lines processed after the last checkpoint are read again on resume. They
+//! are not stored twice: a line whose patient ID is already stored, or whose
+//! [record fingerprint](crate::matching::record_fingerprint) matches a
+//! stored patient, is counted as a duplicate and skipped. Lines without an
+//! ID get one derived from their identifiers when `identifiers.id_namespaces`
+//! covers one of them, and are then recognised by ID too. Otherwise they get
+//! a random one, so only the fingerprint guards against duplicates; patients
+//! too sparse to have a fingerprint can then be stored twice.
+
+use std::io::{BufRead, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::config::{IdentifierConfig, ImportConfig};
+use crate::db::{AuditLogRepository, ImportCheckpointRepository, PatientRepository};
+use crate::jobs::JobHandle;
+use crate::models::import_checkpoint::{IMPORT_COMPLETED, IMPORT_FAILED, IMPORT_RUNNING};
+use crate::models::{ImportCheckpoint, Patient, SourceSubmission};
+use crate::search::SearchBackend;
+use crate::validation::assign_patient_id;
+use crate::Result;
+
+/// Source system of imported patients, as kept in their source records
+pub const IMPORT_SOURCE: &str = "import";
+
+/// Parameters of a bulk import
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ImportRequest {
+    /// Server-side NDJSON file to import, relative to `import.import_dir`
+    pub path: String,
+
+    /// User requesting the import, recorded in the audit log; taken from
+    /// `X-User-Id`, never from the request body
+    #[serde(default, skip_deserializing)]
+    pub requested_by: Option<String>,
+}
+
+/// Resolve a requested import file inside the import directory
+///
+/// `requested` is taken relative to the import directory. Both are
+/// canonicalized, so `..` components and symlinks are followed before the
+/// check, and a file outside the directory is refused. The file must exist.
+pub fn import_path<P: AsRef<Path>>(import_dir: P, requested: &str) -> Result<PathBuf> {
+    let import_dir = import_dir.as_ref();
+    std::fs::create_dir_all(import_dir)
+        .map_err(|e| crate::Error::Config(format!("Failed to create import directory: {}", e)))?;
+    let base = import_dir
+        .canonicalize()
+        .map_err(|e| crate::Error::Config(format!("Failed to resolve import directory: {}", e)))?;
+
+    let resolved = base
+        .join(requested)
+        .canonicalize()
+        .map_err(|e| crate::Error::Validation(format!("Import file '{}' cannot be resolved: {}", requested, e)))?;
+    if resolved == base || !resolved.starts_with(&base) {
+        return Err(crate::Error::Validation(format!(
+            "Import file '{}' is outside the import directory",
+            requested
+        )));
+    }
+    Ok(resolved)
+}
+
+/// What became of one line of an import file
+#[derive(Debug, Clone)]
+pub enum LineOutcome {
+    /// The patient was stored
+    Created(Patient),
+    /// The patient was already stored, by ID or fingerprint
+    Duplicate(Patient),
+    /// The line is not a patient that can be stored; the reason is logged
+    Invalid(String),
+}
+
+/// Imports patients from NDJSON, checkpointing as it goes
+pub struct PatientImporter {
+    patients: Arc<dyn PatientRepository>,
+    search_engine: Arc<dyn SearchBackend>,
+    checkpoints: Arc<dyn ImportCheckpointRepository>,
+    checkpoint_every: u64,
+    import_dir: PathBuf,
+    identifiers: IdentifierConfig,
+    audit_log: Option<Arc<AuditLogRepository>>,
+}
+
+impl PatientImporter {
+    /// Create an importer storing into the given repositories and search index
+    pub fn new(
+        patients: Arc<dyn PatientRepository>,
+        search_engine: Arc<dyn SearchBackend>,
+        checkpoints: Arc<dyn ImportCheckpointRepository>,
+        config: &ImportConfig,
+    ) -> Self {
+        Self {
+            patients,
+            search_engine,
+            checkpoints,
+            checkpoint_every: config.checkpoint_every.max(1),
+            import_dir: PathBuf::from(&config.import_dir),
+            identifiers: IdentifierConfig::default(),
+            audit_log: None,
+        }
+    }
+
+    /// Derive the IDs of patients imported without one through
+    /// `identifiers.id_namespaces`
+    pub fn with_identifiers(mut self, identifiers: &IdentifierConfig) -> Self {
+        self.identifiers = identifiers.clone();
+        self
+    }
+
+    /// Record each import, and each resume, in the audit log
+    pub fn with_audit_log(mut self, audit_log: Arc<AuditLogRepository>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Store the patient on one line, unless it is already stored, keeping
+    /// the line as its source record
+    pub fn import_line(&self, line: &str, requested_by: Option<String>) -> Result<LineOutcome> {
+        let mut patient: Patient = match serde_json::from_str(line) {
+            Ok(patient) => patient,
+            Err(e) => return Ok(LineOutcome::Invalid(e.to_string())),
+        };
+        if patient.id.is_nil() {
+            match assign_patient_id(&self.identifiers, self.patients.as_ref(), &mut patient) {
+                Ok(Some(existing)) => return Ok(LineOutcome::Duplicate(existing)),
+                Ok(None) => {}
+                Err(e @ crate::Error::PatientDeleted(_)) => return Ok(LineOutcome::Invalid(e.to_string())),
+                Err(e) => return Err(e),
+            }
+        } else if let Some(existing) = self.patients.get_by_id_for_update(&patient.id)? {
+            return Ok(LineOutcome::Duplicate(existing));
+        }
+        crate::matching::refresh_photo_hashes(&mut patient);
+        if let Some(existing) = crate::matching::find_resubmitted(&patient, self.patients.as_ref())? {
+            return Ok(LineOutcome::Duplicate(existing));
+        }
+        let submission = SourceSubmission::new(IMPORT_SOURCE, None, &patient).with_received_by(requested_by);
+        Ok(LineOutcome::Created(self.patients.create_from_source(&patient, &submission)?))
+    }
+
+    /// Import `input` from the checkpoint's offset to its end
+    ///
+    /// The checkpoint is advanced line by line and stored every
+    /// `checkpoint_every` lines and at the end of the input; the caller
+    /// stores it on failure. Patients are indexed for search before each
+    /// checkpoint is stored, and before a failure is returned. Duplicates
+    /// are indexed again too, as a crash can come between storing a patient
+    /// and indexing it.
+    pub fn run<R: BufRead + Seek>(
+        &self,
+        mut input: R,
+        checkpoint: &mut ImportCheckpoint,
+        handle: Option<&JobHandle>,
+    ) -> Result<()> {
+        input
+            .seek(SeekFrom::Start(checkpoint.byte_offset.max(0) as u64))
+            .map_err(|e| crate::Error::Internal(format!("Failed to seek in {}: {}", checkpoint.path, e)))?;
+
+        let mut line = String::new();
+        let mut pending: Vec<Patient> = Vec::new();
+        let mut since_checkpoint = 0;
+        loop {
+            line.clear();
+            let read = input
+                .read_line(&mut line)
+                .map_err(|e| crate::Error::Internal(format!("Failed to read {}: {}", checkpoint.path, e)))?;
+            if read == 0 {
+                break;
+            }
+
+            if !line.trim().is_empty() {
+                let outcome = match self.import_line(line.trim(), checkpoint.requested_by.clone()) {
+                    Ok(outcome) => outcome,
+                    Err(e) => {
+                        self.index(&mut pending);
+                        return Err(e);
+                    }
+                };
+                match outcome {
+                    LineOutcome::Created(patient) => {
+                        checkpoint.created += 1;
+                        pending.push(patient);
+                    }
+                    LineOutcome::Duplicate(patient) => {
+                        checkpoint.duplicates += 1;
+                        pending.push(patient);
+                    }
+                    LineOutcome::Invalid(reason) => {
+                        checkpoint.invalid += 1;
+                        tracing::warn!(
+                            "Import {} skipped line {}: {}",
+                            checkpoint.id,
+                            checkpoint.lines_processed + 1,
+                            reason
+                        );
+                    }
+                }
+                checkpoint.lines_processed += 1;
+                since_checkpoint += 1;
+                if let Some(handle) = handle {
+                    handle.advance(1);
+                }
+            }
+            checkpoint.byte_offset += read as i64;
+
+            if since_checkpoint >= self.checkpoint_every {
+                self.save(checkpoint, &mut pending)?;
+                since_checkpoint = 0;
+            }
+        }
+        self.save(checkpoint, &mut pending)
+    }
+
+    /// Index the patients processed since the last checkpoint
+    fn index(&self, pending: &mut Vec<Patient>) {
+        if pending.is_empty() {
+            return;
+        }
+        if let Err(e) = self.search_engine.index_patients(pending) {
+            tracing::warn!("Failed to index imported patients in search engine: {}", e);
+        }
+        pending.clear();
+    }
+
+    /// Index the patients processed since the last checkpoint, then store
+    /// the checkpoint
+    fn save(&self, checkpoint: &mut ImportCheckpoint, pending: &mut Vec<Patient>) -> Result<()> {
+        self.index(pending);
+        *checkpoint = self.checkpoints.save(checkpoint)?;
+        Ok(())
+    }
+
+    /// Mark the checkpoint as running under `handle`, then import its file
+    /// from its offset
+    fn resume(&self, checkpoint: &mut ImportCheckpoint, handle: &JobHandle) -> Result<()> {
+        let previous = checkpoint.clone();
+        checkpoint.job_id = handle.id();
+        checkpoint.status = IMPORT_RUNNING.to_string();
+        checkpoint.error = None;
+        *checkpoint = self.checkpoints.save(checkpoint)?;
+
+        if let Some(audit_log) = &self.audit_log {
+            let values = serde_json::to_value(&*checkpoint).unwrap_or_default();
+            let requested_by = checkpoint.requested_by.clone();
+            let logged = if previous.lines_processed > 0 {
+                let old_values = serde_json::to_value(&previous).unwrap_or_default();
+                audit_log.log_update("PatientImport", checkpoint.id, old_values, values, requested_by, None, None)
+            } else {
+                audit_log.log_create("PatientImport", checkpoint.id, values, requested_by, None, None)
+            };
+            if let Err(e) = logged {
+                tracing::error!("Failed to log audit: {}", e);
+            }
+        }
+
+        let path = import_path(&self.import_dir, &checkpoint.path)?;
+        let file = std::fs::File::open(&path)
+            .map_err(|e| crate::Error::Internal(format!("Failed to open {}: {}", checkpoint.path, e)))?;
+        self.run(std::io::BufReader::new(file), checkpoint, Some(handle))
+    }
+
+    /// Run the import from `checkpoint` in the background, under `handle`
+    ///
+    /// Starts a new import from a fresh checkpoint, or resumes one from a
+    /// stored checkpoint. The job's result is the final checkpoint.
+    pub fn spawn(self, mut checkpoint: ImportCheckpoint, handle: JobHandle) -> tokio::task::JoinHandle<()> {
+        tokio::task::spawn_blocking(move || {
+            handle.start(None);
+            match self.resume(&mut checkpoint, &handle) {
+                Ok(()) => {
+                    checkpoint.status = IMPORT_COMPLETED.to_string();
+                    if let Err(e) = self.checkpoints.save(&checkpoint) {
+                        tracing::warn!("Failed to store checkpoint of import {}: {}", checkpoint.id, e);
+                    }
+                    tracing::info!(
+                        "Imported {}: {} patients created, {} duplicates, {} invalid lines",
+                        checkpoint.path,
+                        checkpoint.created,
+                        checkpoint.duplicates,
+                        checkpoint.invalid
+                    );
+                    handle.complete(serde_json::to_value(&checkpoint).unwrap_or_default());
+                }
+                Err(e) => {
+                    checkpoint.status = IMPORT_FAILED.to_string();
+                    checkpoint.error = Some(e.to_string());
+                    if let Err(e) = self.checkpoints.save(&checkpoint) {
+                        tracing::warn!("Failed to store checkpoint of import {}: {}", checkpoint.id, e);
+                    }
+                    tracing::warn!(
+                        "Import of {} failed after {} lines: {}",
+                        checkpoint.path,
+                        checkpoint.lines_processed,
+                        e
+                    );
+                    handle.fail(&e.to_string());
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use chrono::NaiveDate;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+    use crate::db::{InMemoryImportCheckpointRepository, InMemoryPatientRepository};
+    use crate::models::{Gender, Identifier};
+    use crate::search::SearchEngine;
+
+    fn patient(family: &str, given: &str, ssn: &str) -> Patient {
+        let mut patient = crate::fixtures::patient(family, &[given], Gender::Male);
+        patient.birth_date = NaiveDate::from_ymd_opt(1968, 4, 19);
+        patient.identifiers.push(Identifier::ssn(ssn.to_string()));
+        patient
+    }
+
+    fn ndjson(patients: &[Patient]) -> String {
+        patients.iter().map(|p| serde_json::to_string(p).unwrap() + "\n").collect()
+    }
+
+    fn importer(
+        patients: Arc<InMemoryPatientRepository>,
+        checkpoints: Arc<InMemoryImportCheckpointRepository>,
+        temp_dir: &TempDir,
+    ) -> PatientImporter {
+        PatientImporter::new(
+            patients,
+            Arc::new(SearchEngine::new(temp_dir.path()).unwrap()),
+            checkpoints,
+            &ImportConfig { checkpoint_every: 2, import_dir: temp_dir.path().to_string_lossy().into_owned() },
+        )
+    }
+
+    #[test]
+    fn test_import_path_stays_in_import_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let import_dir = temp_dir.path().join("imports");
+        let path = import_path(&import_dir, "patients.ndjson");
+        assert!(path.is_err(), "the directory is created, but the file must exist");
+        std::fs::write(import_dir.join("patients.ndjson"), "").unwrap();
+        std::fs::write(temp_dir.path().join("secret.ndjson"), "").unwrap();
+
+        let path = import_path(&import_dir, "patients.ndjson").unwrap();
+        assert_eq!(path, import_dir.canonicalize().unwrap().join("patients.ndjson"));
+        assert!(import_path(&import_dir, "../secret.ndjson").is_err());
+        assert!(import_path(&import_dir, "/etc/passwd").is_err());
+        assert!(import_path(&import_dir, "missing.ndjson").is_err());
+        assert!(import_path(&import_dir, ".").is_err());
+
+        // A symlink out of the directory is followed before the check
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(temp_dir.path(), import_dir.join("escape")).unwrap();
+            assert!(import_path(&import_dir, "escape/secret.ndjson").is_err());
+        }
+    }
+
+    #[test]
+    fn test_checkpoints_offset_and_counts() {
+        let temp_dir = TempDir::new().unwrap();
+        let patients = Arc::new(InMemoryPatientRepository::new());
+        let checkpoints = Arc::new(InMemoryImportCheckpointRepository::new());
+        let importer = importer(patients.clone(), checkpoints.clone(), &temp_dir);
+
+        let file = format!(
+            "{}not a patient\n\n{}",
+            ndjson(&[patient("Okafor", "Chidi", "321-54-9876")]),
+            ndjson(&[patient("Lindqvist", "Erik", "432-65-0987")])
+        );
+        let mut checkpoint = ImportCheckpoint::new(Uuid::new_v4(), "patients.ndjson".to_string(), None);
+        importer.run(Cursor::new(file.clone()), &mut checkpoint, None).unwrap();
+
+        assert_eq!(checkpoint.byte_offset, file.len() as i64);
+        assert_eq!(checkpoint.lines_processed, 3);
+        assert_eq!((checkpoint.created, checkpoint.duplicates, checkpoint.invalid), (2, 0, 1));
+        assert_eq!(checkpoints.get_by_id(&checkpoint.id).unwrap(), Some(checkpoint));
+    }
+
+    #[test]
+    fn test_resume_does_not_duplicate_patients() {
+        let temp_dir = TempDir::new().unwrap();
+        let patients = Arc::new(InMemoryPatientRepository::new());
+        let checkpoints = Arc::new(InMemoryImportCheckpointRepository::new());
+        let importer = importer(patients.clone(), checkpoints.clone(), &temp_dir);
+
+        let mut records = vec![
+            patient("Okafor", "Chidi", "321-54-9876"),
+            patient("Lindqvist", "Erik", "432-65-0987"),
+            patient("Haddad", "Samir", "543-76-1098"),
+        ];
+        // Sent without IDs, so only fingerprints recognize them
+        for record in &mut records {
+            record.id = Uuid::nil();
+        }
+        let file = ndjson(&records);
+
+        // The first two lines were stored, but the crash came before the
+        // checkpoint after them was written
+        let id = Uuid::new_v4();
+        for line in file.lines().take(2) {
+            importer.import_line(line, None).unwrap();
+        }
+        let stored = checkpoints.save(&ImportCheckpoint::new(id, "patients.ndjson".to_string(), None)).unwrap();
+
+        let mut checkpoint = checkpoints.get_by_id(&id).unwrap().unwrap();
+        assert_eq!(checkpoint, stored);
+        importer.run(Cursor::new(file), &mut checkpoint, None).unwrap();
+        assert_eq!((checkpoint.created, checkpoint.duplicates), (1, 2));
+        assert_eq!(patients.list_active(10, 0).unwrap().len(), 3);
+
+        // Resuming a finished import reads nothing more
+        importer.run(Cursor::new(ndjson(&records)), &mut checkpoint, None).unwrap();
+        assert_eq!((checkpoint.lines_processed, checkpoint.created), (3, 1));
+    }
+}
@@ -0,0 +1,419 @@
+//! Data integrity check
+//!
+//! Finds records the database constraints allow but the rest of the MPI
+//! does not expect: live patients without a primary name (which cannot be
+//! loaded), links to soft-deleted patients, child rows left without their
+//! patient by imports run with the foreign keys disabled, MRNs held by two
+//! patients under one assigning authority, and patients missing from or
+//! left behind in the search index.
+//!
+//! The check reports every problem it finds. With repairs requested it also
+//! fixes the ones that have a single safe fix: promoting a name to primary,
+//! removing dangling links and orphaned rows, and reindexing or unindexing
+//! patients. Duplicate MRNs need a steward to decide who keeps the number,
+//! so they are only reported. Each repair to a patient is written to the
+//! audit log.
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::config::IntegrityConfig;
+use crate::db::integrity::SharedMrn;
+use crate::db::{AuditLogRepository, IntegrityRepository, PatientRepository};
+use crate::models::AuthorityRegistry;
+use crate::search::SearchBackend;
+use crate::Result;
+
+/// Kind of integrity problem
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrityIssueKind {
+    /// A live patient has no primary name row
+    MissingPrimaryName,
+    /// A live patient links to a soft-deleted one
+    DanglingLink,
+    /// A name, identifier, address, contact or link row has no patient row
+    OrphanedRow,
+    /// Two live patients hold the same MRN under one assigning authority
+    DuplicateMrn,
+    /// A live patient has no search index document
+    MissingFromIndex,
+    /// The search index has a document for a deleted or unknown patient
+    StaleInIndex,
+}
+
+/// One integrity problem
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct IntegrityIssue {
+    /// What is wrong
+    pub kind: IntegrityIssueKind,
+    /// Patients concerned; two or more for a duplicate MRN
+    pub patient_ids: Vec<Uuid>,
+    /// Offending row, for problems with a single row
+    pub row_id: Option<Uuid>,
+    /// Description for the steward
+    pub detail: String,
+    /// Whether the check can repair it
+    pub fixable: bool,
+    /// Whether this run repaired it
+    pub fixed: bool,
+}
+
+/// Problems of one kind found by a check
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct IntegrityIssueCount {
+    /// Problems found
+    pub found: usize,
+    /// Problems repaired
+    pub fixed: usize,
+    /// Whether `integrity.max_issues` cut the list short, so more may remain
+    pub truncated: bool,
+}
+
+/// Outcome of an integrity check
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct IntegrityReport {
+    /// When the check started
+    pub checked_at: DateTime<Utc>,
+    /// Whether fixable problems were repaired
+    pub fix: bool,
+    /// Counts by kind, for every kind checked
+    #[schema(value_type = Object)]
+    pub counts: BTreeMap<IntegrityIssueKind, IntegrityIssueCount>,
+    /// Why the search index was not compared with the database, if it was not
+    pub index_skipped: Option<String>,
+    /// Problems found, grouped by kind
+    pub issues: Vec<IntegrityIssue>,
+}
+
+impl IntegrityReport {
+    /// Problems found across all kinds
+    pub fn found(&self) -> usize {
+        self.counts.values().map(|count| count.found).sum()
+    }
+
+    /// Problems repaired across all kinds
+    pub fn fixed(&self) -> usize {
+        self.counts.values().map(|count| count.fixed).sum()
+    }
+
+    fn record(&mut self, issue: IntegrityIssue) {
+        let count = self.counts.entry(issue.kind).or_default();
+        count.found += 1;
+        if issue.fixed {
+            count.fixed += 1;
+        }
+        self.issues.push(issue);
+    }
+
+    /// Record that `kind` was checked, and whether the `found` problems
+    /// reached `limit`, so that more may remain
+    fn checked(&mut self, kind: IntegrityIssueKind, found: usize, limit: i64) {
+        self.counts.entry(kind).or_default().truncated = found as i64 >= limit;
+    }
+}
+
+/// Checks the database and search index, repairing what it can if asked
+pub struct IntegrityCheck {
+    repository: Arc<IntegrityRepository>,
+    patients: Arc<dyn PatientRepository>,
+    search_engine: Arc<dyn SearchBackend>,
+    audit_log: Arc<AuditLogRepository>,
+    authorities: AuthorityRegistry,
+    config: IntegrityConfig,
+}
+
+impl IntegrityCheck {
+    /// Create a check over the given repositories, search index and
+    /// registered assigning authorities
+    pub fn new(
+        repository: Arc<IntegrityRepository>,
+        patients: Arc<dyn PatientRepository>,
+        search_engine: Arc<dyn SearchBackend>,
+        audit_log: Arc<AuditLogRepository>,
+        authorities: AuthorityRegistry,
+        config: IntegrityConfig,
+    ) -> Self {
+        Self {
+            repository,
+            patients,
+            search_engine,
+            audit_log,
+            authorities,
+            config,
+        }
+    }
+
+    /// Run every check, repairing fixable problems when `fix` is set
+    ///
+    /// `requested_by` is recorded in the audit log with each repair.
+    pub fn run(&self, fix: bool, requested_by: Option<&str>) -> Result<IntegrityReport> {
+        let mut report = IntegrityReport {
+            checked_at: Utc::now(),
+            fix,
+            counts: BTreeMap::new(),
+            index_skipped: None,
+            issues: Vec::new(),
+        };
+        let repair = Repair { audit_log: &self.audit_log, requested_by };
+        let limit = self.config.max_issues.max(1);
+
+        let missing_names = self.repository.missing_primary_names(limit)?;
+        report.checked(IntegrityIssueKind::MissingPrimaryName, missing_names.len(), limit);
+        for patient in missing_names {
+            let fixable = patient.names > 0;
+            let fixed = fix && fixable && repair.apply(
+                patient.id,
+                "promote_primary_name",
+                || self.repository.promote_primary_name(&patient.id),
+            );
+            report.record(IntegrityIssue {
+                kind: IntegrityIssueKind::MissingPrimaryName,
+                patient_ids: vec![patient.id],
+                row_id: None,
+                detail: match patient.names {
+                    0 => "Patient has no names".to_string(),
+                    n => format!("None of the patient's {} names is primary", n),
+                },
+                fixable,
+                fixed,
+            });
+        }
+
+        let links = self.repository.dangling_links(limit)?;
+        report.checked(IntegrityIssueKind::DanglingLink, links.len(), limit);
+        for link in links {
+            let fixed = fix && repair.apply(link.patient_id, "delete_link", || self.repository.delete_link(&link.id));
+            report.record(IntegrityIssue {
+                kind: IntegrityIssueKind::DanglingLink,
+                patient_ids: vec![link.patient_id, link.other_patient_id],
+                row_id: Some(link.id),
+                detail: format!("{} link to deleted patient {}", link.link_type, link.other_patient_id),
+                fixable: true,
+                fixed,
+            });
+        }
+
+        let orphans = self.repository.orphaned_rows(limit)?;
+        report.checked(IntegrityIssueKind::OrphanedRow, orphans.len(), limit);
+        for orphan in orphans {
+            // The patient is gone, so there is no record to audit against
+            let fixed = fix && match self.repository.delete_orphan(&orphan) {
+                Ok(deleted) => deleted,
+                Err(e) => {
+                    tracing::warn!("Failed to delete orphaned {} row {}: {}", orphan.table_name, orphan.id, e);
+                    false
+                }
+            };
+            report.record(IntegrityIssue {
+                kind: IntegrityIssueKind::OrphanedRow,
+                patient_ids: vec![orphan.patient_id],
+                row_id: Some(orphan.id),
+                detail: format!("{} row without its patient", orphan.table_name),
+                fixable: true,
+                fixed,
+            });
+        }
+
+        let shared = self.repository.shared_mrns(limit)?;
+        report.checked(IntegrityIssueKind::DuplicateMrn, shared.len(), limit);
+        for (authority, value, patient_ids) in duplicate_mrns(&self.authorities, shared) {
+            report.record(IntegrityIssue {
+                kind: IntegrityIssueKind::DuplicateMrn,
+                patient_ids,
+                row_id: None,
+                detail: format!("MRN {} from {} is held by more than one patient", value, authority),
+                fixable: false,
+                fixed: false,
+            });
+        }
+
+        self.check_index(&mut report, fix, limit)?;
+        Ok(report)
+    }
+
+    /// Compare the search index with the live patients
+    fn check_index(&self, report: &mut IntegrityReport, fix: bool, limit: i64) -> Result<()> {
+        // The index is listed first: a patient created in between then shows
+        // as missing from the index, which reindexing fixes harmlessly,
+        // rather than as a stale document whose removal would hide them
+        let indexed = match self.search_engine.patient_ids() {
+            Ok(indexed) => indexed,
+            Err(e) => {
+                report.index_skipped = Some(e.to_string());
+                return Ok(());
+            }
+        };
+        let live = self.repository.live_patient_ids()?;
+        let live_ids: HashSet<String> = live.iter().map(Uuid::to_string).collect();
+
+        let missing: Vec<Uuid> = live.into_iter().filter(|id| !indexed.contains(&id.to_string())).collect();
+        report.checked(IntegrityIssueKind::MissingFromIndex, missing.len(), limit + 1);
+        for patient_id in missing.into_iter().take(limit as usize) {
+            let fixed = fix && self.reindex(&patient_id);
+            report.record(IntegrityIssue {
+                kind: IntegrityIssueKind::MissingFromIndex,
+                patient_ids: vec![patient_id],
+                row_id: None,
+                detail: "Patient is not in the search index".to_string(),
+                fixable: true,
+                fixed,
+            });
+        }
+
+        let mut stale: Vec<String> = indexed.into_iter().filter(|id| !live_ids.contains(id)).collect();
+        stale.sort();
+        report.checked(IntegrityIssueKind::StaleInIndex, stale.len(), limit + 1);
+        for document_id in stale.into_iter().take(limit as usize) {
+            let fixed = fix && match self.search_engine.delete_patient(&document_id) {
+                Ok(()) => true,
+                Err(e) => {
+                    tracing::warn!("Failed to remove stale document {} from the search index: {}", document_id, e);
+                    false
+                }
+            };
+            report.record(IntegrityIssue {
+                kind: IntegrityIssueKind::StaleInIndex,
+                patient_ids: Uuid::parse_str(&document_id).into_iter().collect(),
+                row_id: None,
+                detail: format!("Search index has a document for {}, which is not a live patient", document_id),
+                fixable: true,
+                fixed,
+            });
+        }
+
+        if fix {
+            self.search_engine.reload()?;
+        }
+        Ok(())
+    }
+
+    /// Index a live patient again; false if it could not be loaded or indexed
+    fn reindex(&self, patient_id: &Uuid) -> bool {
+        let indexed = self
+            .patients
+            .get_by_id(patient_id)
+            .and_then(|patient| match patient {
+                Some(patient) => self.search_engine.index_patient(&patient).map(|()| true),
+                None => Ok(false),
+            });
+        match indexed {
+            Ok(indexed) => indexed,
+            Err(e) => {
+                tracing::warn!("Failed to reindex patient {}: {}", patient_id, e);
+                false
+            }
+        }
+    }
+}
+
+/// Applies a repair to a patient's rows and audits it
+struct Repair<'a> {
+    audit_log: &'a AuditLogRepository,
+    requested_by: Option<&'a str>,
+}
+
+impl Repair<'_> {
+    /// Run `change`, returning whether it changed anything
+    fn apply(&self, patient_id: Uuid, action: &str, change: impl FnOnce() -> Result<bool>) -> bool {
+        match change() {
+            Ok(true) => {
+                let details = serde_json::json!({ "repair": action });
+                if let Err(e) = self.audit_log.log_integrity_repair(
+                    patient_id,
+                    details,
+                    self.requested_by.map(str::to_string),
+                ) {
+                    tracing::warn!("Failed to audit integrity repair of patient {}: {}", patient_id, e);
+                }
+                true
+            }
+            Ok(false) => false,
+            Err(e) => {
+                tracing::warn!("Integrity repair {} of patient {} failed: {}", action, patient_id, e);
+                false
+            }
+        }
+    }
+}
+
+/// MRN values held by more than one patient under the same authority, as
+/// (authority name, value, patients)
+///
+/// An authority issues under its own system, its OID and `urn:oid:<oid>`,
+/// so the same value under any of them is the same MRN. Values from
+/// unregistered systems are not compared, as for new registrations.
+fn duplicate_mrns(authorities: &AuthorityRegistry, shared: Vec<SharedMrn>) -> Vec<(String, String, Vec<Uuid>)> {
+    let mut holders: BTreeMap<(String, String), Vec<Uuid>> = BTreeMap::new();
+    for mrn in shared {
+        let authority = authorities
+            .find(&mrn.system)
+            .or_else(|| mrn.assigner.as_deref().and_then(|assigner| authorities.find(assigner)));
+        let Some(authority) = authority else {
+            continue;
+        };
+        let patients = holders.entry((authority.name.clone(), mrn.value)).or_default();
+        if !patients.contains(&mrn.patient_id) {
+            patients.push(mrn.patient_id);
+        }
+    }
+    holders
+        .into_iter()
+        .filter(|(_, patients)| patients.len() > 1)
+        .map(|((authority, value), patients)| (authority, value, patients))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AssigningAuthority;
+
+    fn authority(name: &str, system: &str, oid: Option<&str>) -> AssigningAuthority {
+        AssigningAuthority {
+            id: Uuid::new_v4(),
+            system: system.to_string(),
+            oid: oid.map(str::to_string),
+            name: name.to_string(),
+            active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn mrn(patient_id: Uuid, system: &str, value: &str) -> SharedMrn {
+        SharedMrn {
+            patient_id,
+            system: system.to_string(),
+            assigner: None,
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_duplicate_mrns_are_grouped_by_authority() {
+        let registry = AuthorityRegistry::new(vec![
+            authority("General Hospital", "GENERAL", Some("1.2.3")),
+            authority("County Clinic", "COUNTY", None),
+        ]);
+        let (a, b, c, d) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        let duplicates = duplicate_mrns(&registry, vec![
+            // The same MRN written against the system and the OID
+            mrn(a, "GENERAL", "1001"),
+            mrn(b, "urn:oid:1.2.3", "1001"),
+            // The same value from two authorities is not a conflict
+            mrn(c, "COUNTY", "1001"),
+            // Unregistered systems are not compared
+            mrn(d, "UNKNOWN", "2002"),
+            mrn(a, "UNKNOWN", "2002"),
+        ]);
+
+        assert_eq!(duplicates, vec![("General Hospital".to_string(), "1001".to_string(), vec![a, b])]);
+    }
+}
@@ -0,0 +1,313 @@
+//! Decay of source record link confidence
+//!
+//! A link is as good as the demographics it was made on. When the
+//! enterprise record's name or address later changes, the source record
+//! linked to it may no longer belong there: a feed may have been linked to
+//! the wrong twin, or a correction may have gone to the wrong record. This
+//! scheduled job compares every current link's source record with its
+//! enterprise record and lowers the link's confidence for each component
+//! that has diverged. Automatic links that end below
+//! `link_confidence.reverify_below` are flagged for stewards, who verify
+//! them again through the REST API. Links flagged for the first time are
+//! possible overlays and raise an `overlay` alert.
+//!
+//! Confidence is recomputed from the link's recorded confidence on every
+//! run, so it does not compound, and recovers if the demographics agree
+//! again. A link verified since its enterprise record last changed is left
+//! at full confidence.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::config::LinkConfidenceConfig;
+use crate::db::{PatientRepository, SourceRecordRepository};
+use crate::matching::algorithms::{address_matching, name_matching};
+use crate::models::{Patient, SourceRecordLink};
+use crate::notifications::{Alert, AlertKind, Notifier};
+use crate::Result;
+
+/// Number of links fetched per page
+const BATCH_SIZE: i64 = 500;
+
+/// Least name or address similarity still counted as agreeing
+const AGREEMENT: f64 = 0.85;
+
+/// Smallest confidence change written back; confidence is stored to four places
+const CONFIDENCE_EPSILON: f64 = 0.00005;
+
+/// Demographics on which a source record and its enterprise record no longer agree
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Divergence {
+    /// The source record's name matches none of the enterprise record's names
+    pub name: bool,
+    /// The source record's addresses match none of the enterprise record's current addresses
+    pub address: bool,
+}
+
+/// Outcome of one link confidence run
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct LinkConfidenceReport {
+    /// Current links compared
+    pub links_checked: usize,
+    /// Links whose confidence went down
+    pub lowered: usize,
+    /// Links now flagged for re-verification
+    pub flagged: usize,
+    /// Flagged links that were not flagged before this run
+    pub newly_flagged: usize,
+}
+
+/// Where a source record's demographics have diverged from its enterprise record's
+///
+/// Missing values never count as diverging: a source record without an
+/// address agrees with any address.
+pub fn divergence(record: &Patient, patient: &Patient) -> Divergence {
+    let name_agrees = std::iter::once(&patient.name)
+        .chain(&patient.additional_names)
+        .any(|name| name_matching::match_names(&record.name, name) >= AGREEMENT);
+
+    let current: Vec<_> = patient.addresses.iter().filter(|address| address.is_current()).cloned().collect();
+    let address_agrees = record.addresses.is_empty()
+        || current.is_empty()
+        || record
+            .addresses
+            .iter()
+            .any(|address| current.iter().any(|other| address_matching::match_address(address, other) >= AGREEMENT));
+
+    Divergence {
+        name: !name_agrees,
+        address: !address_agrees,
+    }
+}
+
+/// Confidence of a link recorded at `confidence` after `divergence`
+pub fn decayed_confidence(confidence: f64, divergence: Divergence, config: &LinkConfidenceConfig) -> f64 {
+    let mut decayed = confidence;
+    if divergence.name {
+        decayed *= 1.0 - config.name_decay.clamp(0.0, 1.0);
+    }
+    if divergence.address {
+        decayed *= 1.0 - config.address_decay.clamp(0.0, 1.0);
+    }
+    decayed
+}
+
+/// Lowers the confidence of links whose demographics have diverged
+pub struct LinkConfidenceJob {
+    patients: Arc<dyn PatientRepository>,
+    source_records: Arc<dyn SourceRecordRepository>,
+    config: LinkConfidenceConfig,
+    automatic_linker: String,
+    notifier: Option<Arc<Notifier>>,
+}
+
+impl LinkConfidenceJob {
+    /// Create a job over the given repositories
+    ///
+    /// Links made by `automatic_linker`, or by nobody, are the automatic
+    /// links that may be flagged for re-verification.
+    pub fn new(
+        patients: Arc<dyn PatientRepository>,
+        source_records: Arc<dyn SourceRecordRepository>,
+        config: LinkConfidenceConfig,
+        automatic_linker: String,
+    ) -> Self {
+        Self {
+            patients,
+            source_records,
+            config,
+            automatic_linker,
+            notifier: None,
+        }
+    }
+
+    /// Alert stewards through `notifier` when links are newly flagged
+    pub fn with_notifier(mut self, notifier: Arc<Notifier>) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// Recompute the confidence of every current link
+    pub fn run(&self) -> Result<LinkConfidenceReport> {
+        let mut report = LinkConfidenceReport::default();
+        let mut offset = 0;
+        loop {
+            let links = self.source_records.list_active_links(BATCH_SIZE, offset)?;
+            if links.is_empty() {
+                break;
+            }
+            offset += links.len() as i64;
+
+            for link in &links {
+                let record = self.source_records.get_by_id(&link.source_record_id)?;
+                let patient = self.patients.get_by_id(&link.patient_id)?;
+                if let (Some(record), Some(patient)) = (record, patient) {
+                    self.review_link(&mut report, link, &record.patient, &patient)?;
+                }
+            }
+        }
+        if let Some(notifier) = self.notifier.as_ref().filter(|_| report.newly_flagged > 0) {
+            notifier.notify(Alert::new(AlertKind::Overlay).with("count", report.newly_flagged));
+        }
+        Ok(report)
+    }
+
+    /// Recompute one link's confidence from its source record and enterprise record
+    pub fn review_link(
+        &self,
+        report: &mut LinkConfidenceReport,
+        link: &SourceRecordLink,
+        record: &Patient,
+        patient: &Patient,
+    ) -> Result<()> {
+        report.links_checked += 1;
+
+        let confidence = match link.verified_at {
+            Some(verified_at) if verified_at >= patient.updated_at => 1.0,
+            Some(_) => decayed_confidence(1.0, divergence(record, patient), &self.config),
+            None => decayed_confidence(
+                SourceRecordLink::initial_confidence(link.match_score),
+                divergence(record, patient),
+                &self.config,
+            ),
+        };
+        let automatic = link.linked_by.as_deref().is_none_or(|by| by == self.automatic_linker);
+        let needs_reverification = automatic && confidence < self.config.reverify_below;
+
+        if confidence < link.confidence - CONFIDENCE_EPSILON {
+            report.lowered += 1;
+        }
+        if needs_reverification {
+            report.flagged += 1;
+            if !link.needs_reverification {
+                report.newly_flagged += 1;
+            }
+        }
+        if (confidence - link.confidence).abs() > CONFIDENCE_EPSILON || needs_reverification != link.needs_reverification {
+            self.source_records.set_confidence(&link.id, confidence, needs_reverification)?;
+        }
+        Ok(())
+    }
+
+    /// Run now, then every `interval_hours`
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let interval = Duration::from_secs(self.config.interval_hours.max(1) * 3600);
+            let job = Arc::new(self);
+            loop {
+                let runner = job.clone();
+                match tokio::task::spawn_blocking(move || runner.run()).await {
+                    Ok(Ok(report)) => tracing::info!(
+                        "Link confidence job checked {} links: {} lowered, {} need re-verification ({} new)",
+                        report.links_checked,
+                        report.lowered,
+                        report.flagged,
+                        report.newly_flagged
+                    ),
+                    Ok(Err(e)) => tracing::warn!("Link confidence job failed: {}", e),
+                    Err(e) => {
+                        tracing::error!("Link confidence job stopped: {}", e);
+                        return;
+                    }
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use crate::db::{InMemoryPatientRepository, InMemorySourceRecordRepository};
+    use crate::models::{Address, Gender, Period, VerificationStatus};
+
+    fn patient(family: &str, given: &str, street: &str) -> Patient {
+        let mut patient = crate::fixtures::patient(family, &[given], Gender::Female);
+        patient.addresses = vec![Address {
+            line1: Some(street.to_string()),
+            line2: None,
+            city: Some("Springfield".to_string()),
+            state: Some("IL".to_string()),
+            postal_code: Some("62701".to_string()),
+            country: Some("US".to_string()),
+            verification: VerificationStatus::Unverified,
+            period: None,
+        }];
+        patient
+    }
+
+    #[test]
+    fn test_divergence() {
+        let record = patient("Delacroix", "Amelie", "12 Elm Street");
+        assert_eq!(divergence(&record, &record.clone()), Divergence::default());
+
+        let renamed = patient("Wozniak", "Beata", "12 Elm Street");
+        assert_eq!(divergence(&record, &renamed), Divergence { name: true, address: false });
+
+        // An address the patient has moved away from no longer counts
+        let mut moved = patient("Delacroix", "Amelie", "900 Harbor Boulevard");
+        moved.addresses[0].city = Some("Peoria".to_string());
+        moved.addresses[0].postal_code = Some("61602".to_string());
+        let mut former = record.addresses[0].clone();
+        former.period = Some(Period { start: None, end: Some(Utc::now()) });
+        moved.addresses.push(former);
+        assert_eq!(divergence(&record, &moved), Divergence { name: false, address: true });
+
+        let mut no_address = record.clone();
+        no_address.addresses.clear();
+        assert_eq!(divergence(&no_address, &moved), Divergence::default());
+    }
+
+    #[test]
+    fn test_decayed_confidence() {
+        let config = LinkConfidenceConfig::default();
+        let both = Divergence { name: true, address: true };
+        assert_eq!(decayed_confidence(0.9, Divergence::default(), &config), 0.9);
+        assert!((decayed_confidence(1.0, both, &config) - 0.72).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_flags_diverged_automatic_links() {
+        let patients = Arc::new(InMemoryPatientRepository::new());
+        let source_records = Arc::new(InMemorySourceRecordRepository::new());
+
+        let enterprise = patients.create(&patient("Delacroix", "Amelie", "12 Elm Street")).unwrap();
+        let automatic = source_records.receive("lab-feed", "L1", &enterprise, None).unwrap();
+        let manual = source_records.receive("adt", "A1", &enterprise, None).unwrap();
+        source_records.link(&automatic.id, &enterprise.id, Some(0.8), None).unwrap();
+        source_records.link(&manual.id, &enterprise.id, None, Some("steward".to_string())).unwrap();
+
+        let job = LinkConfidenceJob::new(
+            patients.clone(),
+            source_records.clone(),
+            LinkConfidenceConfig::default(),
+            "system".to_string(),
+        );
+        let report = job.run().unwrap();
+        assert_eq!((report.links_checked, report.lowered, report.flagged), (2, 0, 0));
+
+        let mut renamed = enterprise.clone();
+        renamed.name.family = "Wozniak".to_string();
+        renamed.name.given = vec!["Beata".to_string()];
+        patients.update(&renamed).unwrap();
+
+        let report = job.run().unwrap();
+        assert_eq!((report.links_checked, report.lowered, report.flagged), (2, 2, 1));
+        assert_eq!(report.newly_flagged, 1);
+        assert_eq!(job.run().unwrap().newly_flagged, 0);
+        let flagged = source_records.list_needing_reverification(10, 0).unwrap();
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].source_record_id, automatic.id);
+        assert!((flagged[0].confidence - 0.64).abs() < 1e-9);
+
+        // Once verified, the link stays confident until the patient changes again
+        source_records.verify_link(&flagged[0].id, Some("steward".to_string())).unwrap();
+        let report = job.run().unwrap();
+        assert_eq!((report.lowered, report.flagged), (0, 0));
+    }
+}
@@ -0,0 +1,247 @@
+//! Background administrative jobs
+//!
+//! Long-running admin operations (such as purging a source system's records)
+//! run on the blocking thread pool and report progress through a shared
+//! [`JobRegistry`], which the REST API exposes for polling. The registry is
+//! in memory, so job history is lost on restart; the audit log keeps the
+//! permanent record of what a job changed.
+//!
+//! Scheduled housekeeping, such as the retention policy, the name
+//! frequency recount, link confidence decay and the review backlog alert,
+//! lives here too, as does the data integrity check. A job that fails
+//! raises a `job_failed` steward alert.
+
+pub mod integrity;
+pub mod link_confidence;
+pub mod name_frequency;
+pub mod purge;
+pub mod retention;
+pub mod review_backlog;
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::notifications::{Alert, AlertKind, Notifier};
+
+pub use integrity::{IntegrityCheck, IntegrityIssue, IntegrityIssueCount, IntegrityIssueKind, IntegrityReport};
+pub use link_confidence::{LinkConfidenceJob, LinkConfidenceReport};
+pub use name_frequency::NameFrequencyJob;
+pub use purge::{PurgeMode, SourcePurgeJob, SourcePurgeReport, SourcePurgeRequest};
+pub use retention::{RetentionJob, RetentionReport};
+pub use review_backlog::ReviewBacklogMonitor;
+
+/// Lifecycle state of a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Accepted but not yet started
+    Queued,
+    /// Working through its items
+    Running,
+    /// Finished; `result` holds the report
+    Completed,
+    /// Stopped early; `error` says why
+    Failed,
+}
+
+/// A background job and its progress
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Job {
+    /// Job identifier
+    pub id: Uuid,
+
+    /// What the job does (e.g. "source_purge")
+    pub kind: String,
+
+    /// Current state
+    pub status: JobStatus,
+
+    /// Items to process, once known
+    pub total: Option<u64>,
+
+    /// Items processed so far
+    pub processed: u64,
+
+    /// User or system that started the job
+    pub requested_by: Option<String>,
+
+    /// When the job was accepted
+    pub created_at: DateTime<Utc>,
+
+    /// When the job finished or failed
+    pub finished_at: Option<DateTime<Utc>>,
+
+    /// Job-specific report, once completed
+    #[schema(value_type = Option<Object>)]
+    pub result: Option<serde_json::Value>,
+
+    /// Failure reason
+    pub error: Option<String>,
+}
+
+impl Job {
+    /// Fraction of items processed, if the total is known
+    pub fn fraction_done(&self) -> Option<f64> {
+        match self.total {
+            Some(0) => Some(1.0),
+            Some(total) => Some(self.processed as f64 / total as f64),
+            None => None,
+        }
+    }
+}
+
+/// In-memory table of jobs started by this process
+#[derive(Debug, Default)]
+pub struct JobRegistry {
+    jobs: RwLock<HashMap<Uuid, Job>>,
+    notifier: Option<Arc<Notifier>>,
+}
+
+impl JobRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Alert stewards through `notifier` when a job fails
+    pub fn with_notifier(mut self, notifier: Arc<Notifier>) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// Register a new queued job and return a handle for reporting its progress
+    pub fn create(self: &Arc<Self>, kind: &str, requested_by: Option<String>) -> JobHandle {
+        let job = Job {
+            id: Uuid::new_v4(),
+            kind: kind.to_string(),
+            status: JobStatus::Queued,
+            total: None,
+            processed: 0,
+            requested_by,
+            created_at: Utc::now(),
+            finished_at: None,
+            result: None,
+            error: None,
+        };
+        let id = job.id;
+        self.jobs.write().expect("job registry poisoned").insert(id, job);
+        JobHandle { registry: self.clone(), id }
+    }
+
+    /// Get a job by ID
+    pub fn get(&self, id: &Uuid) -> Option<Job> {
+        self.jobs.read().expect("job registry poisoned").get(id).cloned()
+    }
+
+    /// List jobs, newest first
+    pub fn list(&self) -> Vec<Job> {
+        let mut jobs: Vec<Job> = self.jobs.read().expect("job registry poisoned").values().cloned().collect();
+        jobs.sort_by_key(|job| std::cmp::Reverse(job.created_at));
+        jobs
+    }
+
+    fn update(&self, id: &Uuid, change: impl FnOnce(&mut Job)) {
+        if let Some(job) = self.jobs.write().expect("job registry poisoned").get_mut(id) {
+            change(job);
+        }
+    }
+}
+
+/// Progress reporter for one job
+#[derive(Debug, Clone)]
+pub struct JobHandle {
+    registry: Arc<JobRegistry>,
+    id: Uuid,
+}
+
+impl JobHandle {
+    /// The job's identifier
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// The job as currently recorded
+    pub fn snapshot(&self) -> Option<Job> {
+        self.registry.get(&self.id)
+    }
+
+    /// Mark the job running with `total` items to process, if known
+    pub fn start(&self, total: Option<u64>) {
+        self.registry.update(&self.id, |job| {
+            job.status = JobStatus::Running;
+            job.total = total;
+        });
+    }
+
+    /// Record `count` more items processed
+    pub fn advance(&self, count: u64) {
+        self.registry.update(&self.id, |job| job.processed += count);
+    }
+
+    /// Mark the job completed with its report
+    pub fn complete(&self, result: serde_json::Value) {
+        self.registry.update(&self.id, |job| {
+            job.status = JobStatus::Completed;
+            job.finished_at = Some(Utc::now());
+            job.result = Some(result);
+        });
+    }
+
+    /// Mark the job failed, raising a `job_failed` alert
+    pub fn fail(&self, error: &str) {
+        self.registry.update(&self.id, |job| {
+            job.status = JobStatus::Failed;
+            job.finished_at = Some(Utc::now());
+            job.error = Some(error.to_string());
+        });
+        if let (Some(notifier), Some(job)) = (&self.registry.notifier, self.snapshot()) {
+            notifier.notify(
+                Alert::new(AlertKind::JobFailed)
+                    .with("job_id", job.id)
+                    .with("job_kind", &job.kind)
+                    .with("requested_by", job.requested_by.as_deref().unwrap_or("the system"))
+                    .with("processed", job.processed)
+                    .with("error", error),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_progress() {
+        let registry = Arc::new(JobRegistry::new());
+        let handle = registry.create("source_purge", Some("steward-1".to_string()));
+
+        let job = registry.get(&handle.id()).unwrap();
+        assert_eq!(job.status, JobStatus::Queued);
+        assert_eq!(job.fraction_done(), None);
+
+        handle.start(Some(4));
+        handle.advance(1);
+        let job = handle.snapshot().unwrap();
+        assert_eq!(job.status, JobStatus::Running);
+        assert_eq!(job.fraction_done(), Some(0.25));
+
+        handle.advance(3);
+        handle.complete(serde_json::json!({"patients_deleted": 2}));
+        let job = handle.snapshot().unwrap();
+        assert_eq!(job.status, JobStatus::Completed);
+        assert_eq!(job.processed, 4);
+        assert!(job.finished_at.is_some());
+        assert_eq!(job.result.unwrap()["patients_deleted"], 2);
+
+        let failed = registry.create("source_purge", None);
+        failed.fail("database unavailable");
+        assert_eq!(registry.list().len(), 2);
+        assert_eq!(registry.get(&failed.id()).unwrap().status, JobStatus::Failed);
+    }
+}
@@ -0,0 +1,53 @@
+//! Recount of family name frequencies for match scoring
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::db::StatisticsRepository;
+use crate::matching::NameFrequencies;
+use crate::Result;
+
+/// Recounts family names among stored patients into the scorer's table
+pub struct NameFrequencyJob {
+    statistics: Arc<StatisticsRepository>,
+    frequencies: Arc<NameFrequencies>,
+    refresh_hours: u64,
+}
+
+impl NameFrequencyJob {
+    /// Create a job filling `frequencies` from `statistics`
+    pub fn new(statistics: Arc<StatisticsRepository>, frequencies: Arc<NameFrequencies>, refresh_hours: u64) -> Self {
+        Self {
+            statistics,
+            frequencies,
+            refresh_hours,
+        }
+    }
+
+    /// Recount now, returning the number of distinct family names
+    pub fn run(&self) -> Result<usize> {
+        let (total, counts) = self.statistics.family_name_counts()?;
+        self.frequencies.replace(total, counts);
+        Ok(self.frequencies.len())
+    }
+
+    /// Run now, then every `refresh_hours`
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let interval = Duration::from_secs(self.refresh_hours.max(1) * 3600);
+            let job = Arc::new(self);
+            loop {
+                let runner = job.clone();
+                match tokio::task::spawn_blocking(move || runner.run()).await {
+                    Ok(Ok(names)) => tracing::info!("Counted {} family names for match scoring", names),
+                    Ok(Err(e)) => tracing::warn!("Name frequency job failed: {}", e),
+                    Err(e) => {
+                        tracing::error!("Name frequency job stopped: {}", e);
+                        return;
+                    }
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+}
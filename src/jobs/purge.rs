@@ -0,0 +1,333 @@
+//! Bulk removal of records contributed by one source system
+//!
+//! Used to back out a bad feed run. Every source record received from the
+//! system is detached from its enterprise patient; patients whose current
+//! source records all came from that system are removed as well, while
+//! patients that other systems also contribute to are kept and reported.
+//!
+//! A soft delete ends the links and soft-deletes the patients, so the data
+//! can still be inspected. A purge permanently deletes the source records
+//! and the patients. A dry run walks the same records and reports what
+//! would change without writing anything.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::db::{AuditLogRepository, PatientRepository, SourceRecordRepository};
+use crate::models::SourceRecord;
+use crate::search::SearchBackend;
+use crate::Result;
+use super::JobHandle;
+
+/// Number of source records fetched per page
+const BATCH_SIZE: i64 = 500;
+
+/// Number of removed patient IDs listed in the report
+const REPORTED_PATIENT_IDS: usize = 1000;
+
+/// How removed records are disposed of
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PurgeMode {
+    /// End source record links and soft-delete patients
+    #[default]
+    SoftDelete,
+    /// Permanently delete source records and patients
+    Purge,
+}
+
+/// Parameters of a purge by source system
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SourcePurgeRequest {
+    /// Source system whose records are removed (e.g. "lab-feed")
+    pub source_system: String,
+
+    /// Soft delete (default) or permanent purge
+    #[serde(default)]
+    pub mode: PurgeMode,
+
+    /// Report what would be removed without changing anything
+    #[serde(default)]
+    pub dry_run: bool,
+
+    /// User requesting the purge, recorded in the audit log; taken from
+    /// `X-User-Id`, never from the request body
+    #[serde(default, skip_deserializing)]
+    pub requested_by: Option<String>,
+}
+
+/// Outcome of a purge by source system
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct SourcePurgeReport {
+    /// Purged source system
+    pub source_system: String,
+
+    /// Whether this was a dry run; if so the counts are what would change
+    pub dry_run: bool,
+
+    /// Source records examined
+    pub source_records_examined: u64,
+
+    /// Source records unlinked (soft delete) or deleted (purge)
+    pub source_records_removed: u64,
+
+    /// Patients soft-deleted or deleted because only this source contributed to them
+    pub patients_removed: u64,
+
+    /// Patients kept because other sources also contribute to them
+    pub patients_retained: u64,
+
+    /// IDs of removed patients, up to the first 1000
+    pub removed_patient_ids: Vec<Uuid>,
+
+    /// IDs of retained patients, up to the first 1000, for steward review
+    pub retained_patient_ids: Vec<Uuid>,
+}
+
+/// Removes the records contributed by one source system
+pub struct SourcePurgeJob {
+    patients: Arc<dyn PatientRepository>,
+    source_records: Arc<dyn SourceRecordRepository>,
+    search_engine: Arc<dyn SearchBackend>,
+    audit_log: Option<Arc<AuditLogRepository>>,
+}
+
+impl SourcePurgeJob {
+    /// Create a job over the given repositories and search index
+    pub fn new(
+        patients: Arc<dyn PatientRepository>,
+        source_records: Arc<dyn SourceRecordRepository>,
+        search_engine: Arc<dyn SearchBackend>,
+    ) -> Self {
+        Self {
+            patients,
+            source_records,
+            search_engine,
+            audit_log: None,
+        }
+    }
+
+    /// Record the job and every removed source record in the audit log
+    pub fn with_audit_log(mut self, audit_log: Arc<AuditLogRepository>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Run the purge in the background, reporting progress through `handle`
+    pub fn spawn(self, request: SourcePurgeRequest, handle: JobHandle) -> tokio::task::JoinHandle<()> {
+        tokio::task::spawn_blocking(move || {
+            self.audit_job_started(&request, &handle);
+            match self.run(&request, &handle) {
+                Ok(report) => {
+                    tracing::info!(
+                        "Source purge of '{}'{}: {} source records, {} patients removed, {} retained",
+                        report.source_system,
+                        if report.dry_run { " (dry run)" } else { "" },
+                        report.source_records_removed,
+                        report.patients_removed,
+                        report.patients_retained
+                    );
+                    let result = serde_json::to_value(&report).unwrap_or_default();
+                    self.audit_job_finished(&request, &handle, result.clone());
+                    handle.complete(result);
+                }
+                Err(e) => {
+                    tracing::warn!("Source purge of '{}' failed: {}", request.source_system, e);
+                    self.audit_job_finished(&request, &handle, serde_json::json!({ "error": e.to_string() }));
+                    handle.fail(&e.to_string());
+                }
+            }
+        })
+    }
+
+    /// Remove (or, for a dry run, count) the source system's records
+    pub fn run(&self, request: &SourcePurgeRequest, handle: &JobHandle) -> Result<SourcePurgeReport> {
+        let source_system = request.source_system.as_str();
+        let removing = !request.dry_run;
+        let mut report = SourcePurgeReport {
+            source_system: source_system.to_string(),
+            dry_run: request.dry_run,
+            ..Default::default()
+        };
+
+        handle.start(Some(self.source_records.count_by_source_system(source_system)?.max(0) as u64));
+
+        // Each patient is judged once, when first reached and before any of
+        // its links are touched
+        let mut seen: HashSet<Uuid> = HashSet::new();
+        let mut offset = 0;
+        loop {
+            let records = self.source_records.list_by_source_system(source_system, BATCH_SIZE, offset)?;
+            if records.is_empty() {
+                break;
+            }
+            // Purged records leave the listing, so only skip past the rest
+            if request.dry_run || request.mode == PurgeMode::SoftDelete {
+                offset += records.len() as i64;
+            }
+
+            for record in &records {
+                report.source_records_examined += 1;
+                let link = self.source_records.current_link(&record.id)?;
+
+                if let Some(link) = &link {
+                    if seen.insert(link.patient_id) {
+                        let contributors = self.source_records.list_for_patient(&link.patient_id)?;
+                        if originates_from(&contributors, source_system) {
+                            report.patients_removed += 1;
+                            push_capped(&mut report.removed_patient_ids, link.patient_id);
+                            if removing {
+                                self.remove_patient(&link.patient_id, request.mode)?;
+                            }
+                        } else {
+                            report.patients_retained += 1;
+                            push_capped(&mut report.retained_patient_ids, link.patient_id);
+                        }
+                    }
+                }
+
+                match (request.mode, &link) {
+                    (PurgeMode::SoftDelete, Some(link)) => {
+                        report.source_records_removed += 1;
+                        if removing {
+                            self.source_records.unlink(&record.id, Some(purged_by(request, handle)))?;
+                            self.audit_removal("DELETE", "SourceRecordLink", link.id, link, request);
+                        }
+                    }
+                    (PurgeMode::SoftDelete, None) => {}
+                    (PurgeMode::Purge, _) => {
+                        report.source_records_removed += 1;
+                        if removing {
+                            self.source_records.purge(&record.id)?;
+                            self.audit_removal("PURGE", "SourceRecord", record.id, record, request);
+                        }
+                    }
+                }
+            }
+
+            handle.advance(records.len() as u64);
+        }
+
+        Ok(report)
+    }
+
+    /// Soft-delete or purge a patient and drop it from the search index
+    fn remove_patient(&self, patient_id: &Uuid, mode: PurgeMode) -> Result<()> {
+        match mode {
+            PurgeMode::SoftDelete => self.patients.delete(patient_id)?,
+            PurgeMode::Purge => self.patients.purge(patient_id)?,
+        }
+        if let Err(e) = self.search_engine.delete_patient(&patient_id.to_string()) {
+            tracing::warn!("Failed to remove purged patient {} from search index: {}", patient_id, e);
+        }
+        Ok(())
+    }
+
+    fn audit_removal<T: Serialize>(
+        &self,
+        action: &str,
+        entity_type: &str,
+        entity_id: Uuid,
+        old_values: &T,
+        request: &SourcePurgeRequest,
+    ) {
+        let Some(audit_log) = &self.audit_log else {
+            return;
+        };
+        let old_values = serde_json::to_value(old_values).unwrap_or_default();
+        let user = request.requested_by.clone();
+        let result = match action {
+            "PURGE" => audit_log.log_purge(entity_type, entity_id, old_values, user, None, None),
+            _ => audit_log.log_delete(entity_type, entity_id, old_values, user, None, None),
+        };
+        if let Err(e) = result {
+            tracing::error!("Failed to log audit: {}", e);
+        }
+    }
+
+    fn audit_job_started(&self, request: &SourcePurgeRequest, handle: &JobHandle) {
+        if let Some(audit_log) = &self.audit_log {
+            let values = serde_json::to_value(request).unwrap_or_default();
+            if let Err(e) = audit_log.log_create("SourcePurgeJob", handle.id(), values, request.requested_by.clone(), None, None) {
+                tracing::error!("Failed to log audit: {}", e);
+            }
+        }
+    }
+
+    fn audit_job_finished(&self, request: &SourcePurgeRequest, handle: &JobHandle, outcome: serde_json::Value) {
+        if let Some(audit_log) = &self.audit_log {
+            let values = serde_json::to_value(request).unwrap_or_default();
+            if let Err(e) = audit_log.log_update("SourcePurgeJob", handle.id(), values, outcome, request.requested_by.clone(), None, None) {
+                tracing::error!("Failed to log audit: {}", e);
+            }
+        }
+    }
+}
+
+/// Whether every current source record of a patient came from `source_system`
+pub fn originates_from(contributors: &[SourceRecord], source_system: &str) -> bool {
+    !contributors.is_empty() && contributors.iter().all(|record| record.source_system == source_system)
+}
+
+/// Name recorded on links ended by the purge
+fn purged_by(request: &SourcePurgeRequest, handle: &JobHandle) -> String {
+    match &request.requested_by {
+        Some(user) => format!("{} (purge {})", user, handle.id()),
+        None => format!("purge {}", handle.id()),
+    }
+}
+
+fn push_capped(ids: &mut Vec<Uuid>, id: Uuid) {
+    if ids.len() < REPORTED_PATIENT_IDS {
+        ids.push(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Gender, HumanName, Patient};
+
+    fn record(source_system: &str) -> SourceRecord {
+        SourceRecord {
+            id: Uuid::new_v4(),
+            source_system: source_system.to_string(),
+            source_record_id: "R1".to_string(),
+            patient: Patient::new(
+                HumanName {
+                    use_type: None,
+                    family: "Okafor".to_string(),
+                    given: vec![],
+                    prefix: vec![],
+                    suffix: vec![],
+                },
+                Gender::Unknown,
+            ),
+            received_at: chrono::Utc::now(),
+            received_by: None,
+        }
+    }
+
+    #[test]
+    fn test_originates_from() {
+        assert!(originates_from(&[record("lab-feed"), record("lab-feed")], "lab-feed"));
+        assert!(!originates_from(&[record("lab-feed"), record("adt-hospital-a")], "lab-feed"));
+        assert!(!originates_from(&[], "lab-feed"));
+    }
+
+    #[test]
+    fn test_request_defaults_to_soft_delete() {
+        let request: SourcePurgeRequest = serde_json::from_str(r#"{"source_system": "lab-feed"}"#).unwrap();
+        assert_eq!(request.mode, PurgeMode::SoftDelete);
+        assert!(!request.dry_run);
+
+        let request: SourcePurgeRequest =
+            serde_json::from_str(r#"{"source_system": "lab-feed", "mode": "purge", "dry_run": true}"#).unwrap();
+        assert_eq!(request.mode, PurgeMode::Purge);
+        assert!(request.dry_run);
+    }
+}
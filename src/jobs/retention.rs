@@ -0,0 +1,311 @@
+//! Retention policy for inactive and deceased patients
+//!
+//! Rules from `retention.rules` are evaluated in order by a scheduled job.
+//! Each rule selects patients by how long they have been inactive and how
+//! long ago they died, then either purges them or anonymizes them.
+//!
+//! Anonymization keeps the record, its ID, its patient links and its source
+//! record links, so linkage and counts survive, but strips the direct
+//! identifiers: names, identifiers, telecom, photos and street addresses.
+//! Dates are reduced to the year and addresses to state and country. The
+//! payloads of the linked source records are rewritten the same way.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Months, NaiveDate, TimeZone, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::config::{RetentionAction, RetentionConfig, RetentionRule};
+use crate::db::{PatientRepository, RetentionRepository};
+use crate::models::{Address, HumanName, NameUse, Patient, VerificationStatus};
+use crate::search::SearchBackend;
+use crate::Result;
+
+/// Patients affected by one rule in one run
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RuleOutcome {
+    /// Rule name
+    pub rule: String,
+    /// Patients anonymized or purged
+    pub patients: usize,
+    /// What was done to them
+    pub action: String,
+}
+
+/// Outcome of one retention run
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct RetentionReport {
+    /// Per-rule results, in rule order; rules without conditions are omitted
+    pub rules: Vec<RuleOutcome>,
+}
+
+/// Dates a rule's patients must fall before
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cutoffs {
+    /// Last change to the patient or its source records
+    pub inactive_before: Option<DateTime<Utc>>,
+    /// Date of death
+    pub deceased_before: Option<DateTime<Utc>>,
+}
+
+/// Applies the configured retention rules
+pub struct RetentionJob {
+    patients: Arc<dyn PatientRepository>,
+    repository: Arc<RetentionRepository>,
+    search_engine: Arc<dyn SearchBackend>,
+    config: RetentionConfig,
+}
+
+impl RetentionJob {
+    /// Create a job over the given repositories and search index
+    pub fn new(
+        patients: Arc<dyn PatientRepository>,
+        repository: Arc<RetentionRepository>,
+        search_engine: Arc<dyn SearchBackend>,
+        config: RetentionConfig,
+    ) -> Self {
+        Self {
+            patients,
+            repository,
+            search_engine,
+            config,
+        }
+    }
+
+    /// Apply every rule as of `now`
+    pub fn run(&self, now: DateTime<Utc>) -> Result<RetentionReport> {
+        let mut report = RetentionReport::default();
+        for rule in &self.config.rules {
+            let Some(cutoffs) = cutoffs(rule, now) else {
+                tracing::warn!("Retention rule '{}' has no conditions and was skipped", rule.name);
+                continue;
+            };
+            let patients = self.apply_rule(rule.action, cutoffs)?;
+            report.rules.push(RuleOutcome {
+                rule: rule.name.clone(),
+                patients,
+                action: match rule.action {
+                    RetentionAction::Anonymize => "anonymized",
+                    RetentionAction::Purge => "purged",
+                }
+                .to_string(),
+            });
+        }
+        Ok(report)
+    }
+
+    fn apply_rule(&self, action: RetentionAction, cutoffs: Cutoffs) -> Result<usize> {
+        // Handled patients drop out of the candidate query, so each batch
+        // starts from the top
+        let purging = action == RetentionAction::Purge;
+        let mut count = 0;
+        loop {
+            let candidates = self.repository.find_candidates(
+                cutoffs.inactive_before,
+                cutoffs.deceased_before,
+                purging,
+                purging,
+                self.config.batch_size.max(1),
+            )?;
+            if candidates.is_empty() {
+                return Ok(count);
+            }
+            for patient_id in &candidates {
+                match action {
+                    RetentionAction::Anonymize => self.anonymize_patient(patient_id)?,
+                    RetentionAction::Purge => self.patients.purge(patient_id)?,
+                }
+                if let Err(e) = self.search_engine.delete_patient(&patient_id.to_string()) {
+                    tracing::warn!("Failed to remove patient {} from search index: {}", patient_id, e);
+                }
+            }
+            count += candidates.len();
+        }
+    }
+
+    fn anonymize_patient(&self, patient_id: &Uuid) -> Result<()> {
+        if let Some(patient) = self.patients.get_by_id(patient_id)? {
+            self.patients.update(&anonymize(patient))?;
+        }
+        self.repository.redact_source_records(patient_id, anonymize)?;
+        self.repository.mark_anonymized(patient_id)
+    }
+
+    /// Run now, then every `interval_hours`
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let interval = Duration::from_secs(self.config.interval_hours.max(1) * 3600);
+            let job = Arc::new(self);
+            loop {
+                let runner = job.clone();
+                match tokio::task::spawn_blocking(move || runner.run(Utc::now())).await {
+                    Ok(Ok(report)) => {
+                        for outcome in &report.rules {
+                            tracing::info!(
+                                "Retention rule '{}': {} patients {}",
+                                outcome.rule,
+                                outcome.patients,
+                                outcome.action
+                            );
+                        }
+                    }
+                    Ok(Err(e)) => tracing::warn!("Retention job failed: {}", e),
+                    Err(e) => {
+                        tracing::error!("Retention job stopped: {}", e);
+                        return;
+                    }
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+}
+
+/// The inactivity and death cutoffs of a rule as of `now`
+///
+/// Returns `None` for a rule without conditions.
+pub fn cutoffs(rule: &RetentionRule, now: DateTime<Utc>) -> Option<Cutoffs> {
+    let years_before = |years: u32| now.checked_sub_months(Months::new(years.saturating_mul(12)));
+    let inactive_before = rule.inactive_years.map(years_before);
+    let deceased_before = rule.deceased_years.map(years_before);
+    if inactive_before.is_none() && deceased_before.is_none() {
+        return None;
+    }
+    // A cutoff too far back to represent matches nobody
+    Some(Cutoffs {
+        inactive_before: inactive_before.map(|cutoff| cutoff.unwrap_or(DateTime::<Utc>::MIN_UTC)),
+        deceased_before: deceased_before.map(|cutoff| cutoff.unwrap_or(DateTime::<Utc>::MIN_UTC)),
+    })
+}
+
+/// Strip a patient's direct identifiers, keeping its ID and links
+pub fn anonymize(patient: Patient) -> Patient {
+    let mut addresses: Vec<Address> = Vec::new();
+    for address in patient.addresses {
+        let coarse = Address {
+            line1: None,
+            line2: None,
+            city: None,
+            state: address.state,
+            postal_code: None,
+            country: address.country,
+            verification: VerificationStatus::Unverified,
+            period: None,
+        };
+        let duplicate = addresses
+            .iter()
+            .any(|kept| kept.state == coarse.state && kept.country == coarse.country);
+        if (coarse.state.is_some() || coarse.country.is_some()) && !duplicate {
+            addresses.push(coarse);
+        }
+    }
+
+    Patient {
+        identifiers: Vec::new(),
+        name: HumanName {
+            use_type: Some(NameUse::Anonymous),
+            family: String::new(),
+            given: Vec::new(),
+            prefix: Vec::new(),
+            suffix: Vec::new(),
+        },
+        additional_names: Vec::new(),
+        telecom: Vec::new(),
+        birth_date: patient.birth_date.and_then(|date| NaiveDate::from_ymd_opt(date.year(), 1, 1)),
+        deceased_datetime: patient
+            .deceased_datetime
+            .and_then(|datetime| Utc.with_ymd_and_hms(datetime.year(), 1, 1, 0, 0, 0).single()),
+        addresses,
+        marital_status: None,
+        photo: Vec::new(),
+        photo_hashes: Vec::new(),
+        ..patient
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ContactPoint, ContactPointSystem, Gender, Identifier, PatientLink, LinkType};
+
+    fn rule(inactive_years: Option<u32>, deceased_years: Option<u32>) -> RetentionRule {
+        RetentionRule {
+            name: "test".to_string(),
+            inactive_years,
+            deceased_years,
+            action: RetentionAction::Anonymize,
+        }
+    }
+
+    #[test]
+    fn test_cutoffs() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 15, 12, 0, 0).unwrap();
+
+        let both = cutoffs(&rule(Some(10), Some(2)), now).unwrap();
+        assert_eq!(both.inactive_before, Some(Utc.with_ymd_and_hms(2016, 3, 15, 12, 0, 0).unwrap()));
+        assert_eq!(both.deceased_before, Some(Utc.with_ymd_and_hms(2024, 3, 15, 12, 0, 0).unwrap()));
+
+        let inactive_only = cutoffs(&rule(Some(7), None), now).unwrap();
+        assert!(inactive_only.inactive_before.is_some());
+        assert!(inactive_only.deceased_before.is_none());
+
+        assert!(cutoffs(&rule(None, None), now).is_none());
+    }
+
+    #[test]
+    fn test_anonymize_keeps_linkage() {
+        let mut patient = Patient::new(
+            HumanName {
+                use_type: None,
+                family: "Lindqvist".to_string(),
+                given: vec!["Ingrid".to_string()],
+                prefix: vec![],
+                suffix: vec![],
+            },
+            Gender::Female,
+        );
+        let other = Uuid::new_v4();
+        patient.birth_date = NaiveDate::from_ymd_opt(1941, 6, 30);
+        patient.deceased = true;
+        patient.deceased_datetime = Some(Utc.with_ymd_and_hms(2019, 11, 2, 8, 30, 0).unwrap());
+        patient.identifiers.push(Identifier::mrn("HOSP-A".to_string(), "12345".to_string()));
+        patient.telecom.push(ContactPoint {
+            system: ContactPointSystem::Phone,
+            value: "207-555-0142".to_string(),
+            use_type: None,
+            period: None,
+        });
+        let address = Address {
+            line1: Some("12 Harbour Rd".to_string()),
+            line2: None,
+            city: Some("Portland".to_string()),
+            state: Some("ME".to_string()),
+            postal_code: Some("04101".to_string()),
+            country: Some("US".to_string()),
+            verification: VerificationStatus::Unverified,
+            period: None,
+        };
+        patient.addresses = vec![address.clone(), Address { line1: Some("Old St".to_string()), ..address }];
+        patient.links.push(PatientLink { other_patient_id: other, link_type: LinkType::Seealso });
+
+        let id = patient.id;
+        let anonymized = anonymize(patient);
+
+        assert_eq!(anonymized.id, id);
+        assert_eq!(anonymized.links.len(), 1);
+        assert_eq!(anonymized.links[0].other_patient_id, other);
+        assert!(anonymized.name.family.is_empty());
+        assert!(anonymized.name.given.is_empty());
+        assert!(anonymized.identifiers.is_empty());
+        assert!(anonymized.telecom.is_empty());
+        assert_eq!(anonymized.birth_date, NaiveDate::from_ymd_opt(1941, 1, 1));
+        assert_eq!(anonymized.deceased_datetime, Some(Utc.with_ymd_and_hms(2019, 1, 1, 0, 0, 0).unwrap()));
+        assert_eq!(anonymized.addresses.len(), 1);
+        assert_eq!(anonymized.addresses[0].state.as_deref(), Some("ME"));
+        assert!(anonymized.addresses[0].postal_code.is_none());
+        assert!(anonymized.addresses[0].line1.is_none());
+    }
+}
@@ -0,0 +1,104 @@
+//! Alerts on the length of the duplicate review queue
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::ReviewBacklogAlertConfig;
+use crate::db::DuplicateCandidateRepository;
+use crate::notifications::{Alert, AlertKind, Notifier};
+use crate::Result;
+
+/// Raises a `review_backlog` alert when the queue reaches its threshold
+///
+/// The alert is raised once per crossing: the monitor stays quiet while the
+/// queue stays at or above the threshold, and re-arms once it drops below.
+pub struct ReviewBacklogMonitor {
+    duplicates: Arc<dyn DuplicateCandidateRepository>,
+    notifier: Arc<Notifier>,
+    config: ReviewBacklogAlertConfig,
+    alerted: AtomicBool,
+}
+
+impl ReviewBacklogMonitor {
+    /// Create a monitor of `duplicates` alerting through `notifier`
+    pub fn new(
+        duplicates: Arc<dyn DuplicateCandidateRepository>,
+        notifier: Arc<Notifier>,
+        config: ReviewBacklogAlertConfig,
+    ) -> Self {
+        Self {
+            duplicates,
+            notifier,
+            config,
+            alerted: AtomicBool::new(false),
+        }
+    }
+
+    /// Check the queue now, returning the pending count and whether an
+    /// alert was raised
+    pub fn check(&self) -> Result<(i64, bool)> {
+        let pending = self.duplicates.count_pending()?;
+        if pending < self.config.threshold {
+            self.alerted.store(false, Ordering::Relaxed);
+            return Ok((pending, false));
+        }
+        if self.alerted.swap(true, Ordering::Relaxed) {
+            return Ok((pending, false));
+        }
+        self.notifier.notify(
+            Alert::new(AlertKind::ReviewBacklog)
+                .with("pending", pending)
+                .with("threshold", self.config.threshold),
+        );
+        Ok((pending, true))
+    }
+
+    /// Check now, then every `interval_minutes`
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let interval = Duration::from_secs(self.config.interval_minutes.max(1) * 60);
+            let monitor = Arc::new(self);
+            loop {
+                let runner = monitor.clone();
+                match tokio::task::spawn_blocking(move || runner.check()).await {
+                    Ok(Ok((pending, true))) => tracing::warn!("Duplicate review queue holds {} pairs", pending),
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => tracing::warn!("Review backlog check failed: {}", e),
+                    Err(e) => {
+                        tracing::error!("Review backlog monitor stopped: {}", e);
+                        return;
+                    }
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+    use crate::db::InMemoryDuplicateCandidateRepository;
+
+    #[test]
+    fn test_alerts_once_per_crossing() {
+        let duplicates = Arc::new(InMemoryDuplicateCandidateRepository::new());
+        let monitor = ReviewBacklogMonitor::new(
+            duplicates.clone(),
+            Arc::new(Notifier::disabled()),
+            ReviewBacklogAlertConfig {
+                threshold: 2,
+                interval_minutes: 15,
+            },
+        );
+
+        duplicates.enqueue(&Uuid::new_v4(), &Uuid::new_v4(), 0.8).unwrap();
+        assert_eq!(monitor.check().unwrap(), (1, false));
+        duplicates.enqueue(&Uuid::new_v4(), &Uuid::new_v4(), 0.8).unwrap();
+        assert_eq!(monitor.check().unwrap(), (2, true));
+        duplicates.enqueue(&Uuid::new_v4(), &Uuid::new_v4(), 0.8).unwrap();
+        assert_eq!(monitor.check().unwrap(), (3, false));
+    }
+}
@@ -11,17 +11,41 @@
 //! - PostgreSQL persistence via Diesel
 //! - Event streaming via Fluvio
 //! - Distributed tracing and observability via OpenTelemetry
+//! - Matching quality KPI reporting
+//! - Background admin jobs such as purging a source system
+//! - Email and webhook alerts to data stewards
+//! - Linked, de-identified bulk exports for research
+//! - Resumable bulk imports that never store a patient twice
+//! - A typed client generated from the OpenAPI document (`client` feature)
 
 // Module declarations
 pub mod api;
+pub mod circuit_breaker;
 pub mod config;
 pub mod db;
+pub mod deadline;
 pub mod error;
+pub mod export;
+pub mod import;
+pub mod jobs;
 pub mod matching;
 pub mod models;
+pub mod notifications;
 pub mod observability;
+pub mod reload;
+pub mod reporting;
 pub mod search;
 pub mod streaming;
+pub mod validation;
+
+#[cfg(test)]
+mod fixtures;
+
+#[cfg(feature = "testdata")]
+pub mod testdata;
+
+#[cfg(feature = "client")]
+pub mod client;
 
 // Re-exports
 pub use error::{Error, Result};
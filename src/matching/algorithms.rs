@@ -4,15 +4,16 @@
 //! - Name matching (fuzzy and phonetic)
 //! - Date of birth matching
 //! - Gender matching
-//! - Address matching
-//! - Identifier matching
+//! - Address matching, against current and former addresses
+//! - Identifier matching, discounting expired identifiers
 
-use strsim::{jaro_winkler, levenshtein, normalized_levenshtein};
+use strsim::jaro_winkler;
 use fuzzy_matcher::FuzzyMatcher;
 use fuzzy_matcher::skim::SkimMatcherV2;
-use chrono::{NaiveDate, Datelike};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
 
-use crate::models::{Patient, HumanName, Address, Identifier};
+use crate::models::{Patient, HumanName, NameUse, Address, Identifier, VerificationStatus};
+use super::similarity::{JaroWinklerLevenshtein, NameSimilarity, SimilarityAlgorithm};
 
 /// Name matching algorithms
 pub mod name_matching {
@@ -20,13 +21,19 @@ pub mod name_matching {
 
     /// Calculate similarity between two names using multiple algorithms
     pub fn match_names(name1: &HumanName, name2: &HumanName) -> f64 {
+        match_names_with(name1, name2, &NameSimilarity::default())
+    }
+
+    /// Calculate similarity between two names, comparing family and given
+    /// names with the chosen comparators
+    pub fn match_names_with(name1: &HumanName, name2: &HumanName, similarity: &NameSimilarity) -> f64 {
         // Weight factors for different components
         const FAMILY_WEIGHT: f64 = 0.5;
         const GIVEN_WEIGHT: f64 = 0.4;
         const PREFIX_SUFFIX_WEIGHT: f64 = 0.1;
 
-        let family_score = match_family_names(&name1.family, &name2.family);
-        let given_score = match_given_names(&name1.given, &name2.given);
+        let family_score = family_similarity(&name1.family, &name2.family, similarity.family.as_ref());
+        let given_score = given_similarity(&name1.given, &name2.given, similarity.given.as_ref());
         let prefix_suffix_score = match_prefix_suffix(
             &name1.prefix,
             &name2.prefix,
@@ -39,8 +46,40 @@ pub mod name_matching {
             + (prefix_suffix_score * PREFIX_SUFFIX_WEIGHT)
     }
 
+    /// Least similarity of each swapped component for names to count as transposed
+    const TRANSPOSED_MIN_SIMILARITY: f64 = 0.9;
+
+    /// Name score, and whether it came from given and family names swapped
+    ///
+    /// Feeds often send the family name first ("SMITH JOHN" for "JOHN
+    /// SMITH"). When each family name agrees with the other's first given
+    /// name, the swapped comparison is credited at
+    /// `similarity.transposed_score` of its score if that beats the straight one.
+    pub fn match_names_transposable(name1: &HumanName, name2: &HumanName, similarity: &NameSimilarity) -> (f64, bool) {
+        let straight = match_names_with(name1, name2, similarity);
+        let Some(given2) = name2.given.first() else {
+            return (straight, false);
+        };
+        let family_as_given = family_similarity(&name1.family, given2, similarity.family.as_ref());
+        let given_as_family = given_similarity(&name1.given, std::slice::from_ref(&name2.family), similarity.given.as_ref());
+        if family_as_given < TRANSPOSED_MIN_SIMILARITY || given_as_family < TRANSPOSED_MIN_SIMILARITY {
+            return (straight, false);
+        }
+
+        let transposed = (family_as_given + given_as_family) / 2.0 * similarity.transposed_score;
+        if transposed > straight {
+            (transposed, true)
+        } else {
+            (straight, false)
+        }
+    }
+
     /// Match family names using fuzzy string matching
     pub fn match_family_names(family1: &str, family2: &str) -> f64 {
+        family_similarity(family1, family2, &JaroWinklerLevenshtein)
+    }
+
+    fn family_similarity(family1: &str, family2: &str, algorithm: &dyn SimilarityAlgorithm) -> f64 {
         if family1.is_empty() || family2.is_empty() {
             return 0.0;
         }
@@ -54,18 +93,15 @@ pub mod name_matching {
             return 1.0;
         }
 
-        // Use Jaro-Winkler (good for name matching)
-        let jw_score = jaro_winkler(&f1, &f2);
-
-        // Use normalized Levenshtein distance
-        let lev_score = normalized_levenshtein(&f1, &f2);
-
-        // Take the maximum score
-        f64::max(jw_score, lev_score)
+        algorithm.similarity(&f1, &f2)
     }
 
     /// Match given names (array of names)
     pub fn match_given_names(given1: &[String], given2: &[String]) -> f64 {
+        given_similarity(given1, given2, &JaroWinklerLevenshtein)
+    }
+
+    fn given_similarity(given1: &[String], given2: &[String], algorithm: &dyn SimilarityAlgorithm) -> f64 {
         if given1.is_empty() || given2.is_empty() {
             return 0.0;
         }
@@ -84,10 +120,7 @@ pub mod name_matching {
         }
 
         // Fuzzy match
-        let jw_score = jaro_winkler(&first1, &first2);
-        let lev_score = normalized_levenshtein(&first1, &first2);
-
-        f64::max(jw_score, lev_score)
+        algorithm.similarity(&first1, &first2)
     }
 
     /// Check if two names are known variants/nicknames
@@ -120,6 +153,63 @@ pub mod name_matching {
         false
     }
 
+    /// Generation a name's suffixes denote: 1 for Sr., 2 for Jr. or II,
+    /// 3 for III and so on
+    pub fn generation(suffixes: &[String]) -> Option<u8> {
+        suffixes.iter().find_map(|suffix| {
+            let suffix: String = suffix
+                .chars()
+                .filter(|c| c.is_alphanumeric())
+                .flat_map(char::to_lowercase)
+                .collect();
+            match suffix.as_str() {
+                "sr" | "senior" => Some(1),
+                "jr" | "junior" | "ii" | "2nd" => Some(2),
+                "iii" | "3rd" => Some(3),
+                "iv" | "4th" => Some(4),
+                "v" | "5th" => Some(5),
+                _ => None,
+            }
+        })
+    }
+
+    /// Whether both names carry a generational suffix and they differ, as
+    /// for a father and son of the same name
+    pub fn generations_conflict(name1: &HumanName, name2: &HumanName) -> bool {
+        match (generation(&name1.suffix), generation(&name2.suffix)) {
+            (Some(g1), Some(g2)) => g1 != g2,
+            _ => false,
+        }
+    }
+
+    /// Name credit for a record whose maiden name is the other's family name
+    const MAIDEN_NAME_CREDIT: f64 = 0.95;
+
+    /// Name score for a pair where one record has a maiden name equal to
+    /// the other's family name, both share a first given name and both
+    /// have the same birth date
+    ///
+    /// A woman registered before and after marrying is otherwise scored on
+    /// two different family names.
+    pub fn match_maiden_names(patient1: &Patient, patient2: &Patient, similarity: &NameSimilarity) -> Option<f64> {
+        if patient1.birth_date.is_none() || patient1.birth_date != patient2.birth_date {
+            return None;
+        }
+        let recorded_as_maiden = |maiden: &Patient, current: &Patient| {
+            let legal = current.legal_name();
+            let family = legal.family.trim().to_lowercase();
+            std::iter::once(&maiden.name)
+                .chain(&maiden.additional_names)
+                .filter(|name| name.use_type == Some(NameUse::Maiden))
+                .any(|name| {
+                    !family.is_empty()
+                        && name.family.trim().to_lowercase() == family
+                        && given_similarity(&maiden.legal_name().given, &legal.given, similarity.given.as_ref()) >= 0.95
+                })
+        };
+        (recorded_as_maiden(patient1, patient2) || recorded_as_maiden(patient2, patient1)).then_some(MAIDEN_NAME_CREDIT)
+    }
+
     /// Match prefix and suffix arrays
     fn match_prefix_suffix(
         prefix1: &[String],
@@ -248,18 +338,53 @@ pub mod gender_matching {
 /// Address matching
 pub mod address_matching {
     use super::*;
+    use crate::validation::{normalize_postal_code, PostalFormat};
 
     /// Match addresses using multiple components
     pub fn match_addresses(addresses1: &[Address], addresses2: &[Address]) -> f64 {
-        if addresses1.is_empty() || addresses2.is_empty() {
-            return 0.0;
-        }
+        match_addresses_verified(addresses1, addresses2).0
+    }
+
+    /// Years after which a former address counts half as much
+    const FORMER_ADDRESS_HALF_LIFE_YEARS: f64 = 5.0;
+
+    /// Match addresses, along with the better verification status of the
+    /// two addresses compared
+    pub fn match_addresses_verified(addresses1: &[Address], addresses2: &[Address]) -> (f64, VerificationStatus) {
+        match_addresses_at(addresses1, addresses2, Utc::now())
+    }
 
-        // Compare primary addresses if available
-        let addr1 = addresses1.first().unwrap();
-        let addr2 = addresses2.first().unwrap();
+    /// Best score of any pair of current or former addresses as of `now`
+    ///
+    /// A pair with a former address is discounted by how long ago the
+    /// patient moved away, so a years-old address agreeing counts for less
+    /// than a current one.
+    pub fn match_addresses_at(
+        addresses1: &[Address],
+        addresses2: &[Address],
+        now: DateTime<Utc>,
+    ) -> (f64, VerificationStatus) {
+        let mut best = (0.0, VerificationStatus::Unverified);
+        for addr1 in addresses1 {
+            for addr2 in addresses2 {
+                let score = match_address(addr1, addr2) * recency(addr1, now) * recency(addr2, now);
+                if score > best.0 {
+                    best = (score, addr1.verification.max(addr2.verification));
+                }
+            }
+        }
+        best
+    }
 
-        match_address(addr1, addr2)
+    /// Weight of an address by how long ago it stopped being used
+    fn recency(address: &Address, now: DateTime<Utc>) -> f64 {
+        match address.ended() {
+            None => 1.0,
+            Some(end) => {
+                let years = (now - end).num_days().max(0) as f64 / 365.25;
+                0.5_f64.powf(years / FORMER_ADDRESS_HALF_LIFE_YEARS)
+            }
+        }
     }
 
     /// Match individual addresses
@@ -272,6 +397,7 @@ pub mod address_matching {
         let postal_score = match_postal_codes(
             addr1.postal_code.as_deref(),
             addr2.postal_code.as_deref(),
+            addr1.country.as_deref().or(addr2.country.as_deref()),
         );
 
         let city_score = match_cities(
@@ -296,33 +422,47 @@ pub mod address_matching {
     }
 
     /// Match postal codes
-    pub(crate) fn match_postal_codes(zip1: Option<&str>, zip2: Option<&str>) -> f64 {
-        match (zip1, zip2) {
-            (None, None) => 0.0,
-            (None, Some(_)) | (Some(_), None) => 0.0,
-            (Some(z1), Some(z2)) => {
-                let z1 = z1.trim().replace("-", "");
-                let z2 = z2.trim().replace("-", "");
+    ///
+    /// The format comes from the address country when known, otherwise it is
+    /// detected from the codes. Codes in the same delivery ZIP (ignoring the
+    /// +4) score 0.95 and codes in the same region (ZIP sectional center,
+    /// Canadian forward sortation area, UK outward code) score 0.70. Codes
+    /// in no recognized format fall back to comparing leading characters.
+    pub(crate) fn match_postal_codes(zip1: Option<&str>, zip2: Option<&str>, country: Option<&str>) -> f64 {
+        let (z1, z2) = match (zip1, zip2) {
+            (Some(z1), Some(z2)) => (normalize_postal_code(z1), normalize_postal_code(z2)),
+            _ => return 0.0,
+        };
+        if z1.is_empty() || z2.is_empty() {
+            return 0.0;
+        }
+        if z1 == z2 {
+            return 1.0;
+        }
 
-                if z1 == z2 {
-                    return 1.0;
-                }
+        let format = country
+            .and_then(PostalFormat::for_country)
+            .or_else(|| PostalFormat::detect(&z1));
 
-                // Match first 5 digits (US ZIP)
-                if z1.len() >= 5 && z2.len() >= 5 {
-                    if &z1[0..5] == &z2[0..5] {
-                        return 0.95;
-                    }
+        match format {
+            Some(format) if format.is_valid(&z1) && format.is_valid(&z2) => {
+                if format == PostalFormat::UsZip && z1[..5] == z2[..5] {
+                    0.95
+                } else if format.region(&z1) == format.region(&z2) {
+                    0.70
+                } else {
+                    0.0
                 }
-
-                // Match first 3 digits (same area)
-                if z1.len() >= 3 && z2.len() >= 3 {
-                    if &z1[0..3] == &z2[0..3] {
-                        return 0.70;
-                    }
+            }
+            _ => {
+                let prefix = |z: &str, n: usize| z.chars().take(n).collect::<String>();
+                if z1.chars().count() >= 5 && z2.chars().count() >= 5 && prefix(&z1, 5) == prefix(&z2, 5) {
+                    0.95
+                } else if z1.chars().count() >= 3 && z2.chars().count() >= 3 && prefix(&z1, 3) == prefix(&z2, 3) {
+                    0.70
+                } else {
+                    0.0
                 }
-
-                0.0
             }
         }
     }
@@ -407,20 +547,48 @@ pub mod identifier_matching {
 
     /// Match patient identifiers
     pub fn match_identifiers(ids1: &[Identifier], ids2: &[Identifier]) -> f64 {
-        if ids1.is_empty() || ids2.is_empty() {
-            return 0.0;
-        }
+        match_identifiers_verified(ids1, ids2).0
+    }
 
-        let mut max_score = 0.0;
+    /// Weight of a pair in which either identifier had been retired
+    const EXPIRED_IDENTIFIER_WEIGHT: f64 = 0.7;
+
+    /// Match patient identifiers, along with the better verification status
+    /// of the pair behind the best score
+    ///
+    /// Among equally good pairs the best verified one wins.
+    pub fn match_identifiers_verified(ids1: &[Identifier], ids2: &[Identifier]) -> (f64, VerificationStatus) {
+        match_identifiers_at(ids1, ids2, Utc::now())
+    }
+
+    /// Best score of any pair of identifiers as of `now`
+    ///
+    /// A pair with an identifier whose period had ended by `now` counts for
+    /// less, since a retired number may have been reissued to someone else.
+    pub fn match_identifiers_at(
+        ids1: &[Identifier],
+        ids2: &[Identifier],
+        now: DateTime<Utc>,
+    ) -> (f64, VerificationStatus) {
+        let mut best = (0.0, VerificationStatus::Unverified);
 
         for id1 in ids1 {
             for id2 in ids2 {
-                let score = match_identifier(id1, id2);
-                max_score = f64::max(max_score, score);
+                let mut score = match_identifier(id1, id2);
+                if id1.is_expired_at(now) || id2.is_expired_at(now) {
+                    score *= EXPIRED_IDENTIFIER_WEIGHT;
+                }
+                if score <= 0.0 {
+                    continue;
+                }
+                let status = id1.verification.max(id2.verification);
+                if score > best.0 || (score == best.0 && status > best.1) {
+                    best = (score, status);
+                }
             }
         }
 
-        max_score
+        best
     }
 
     /// Match individual identifiers
@@ -547,13 +715,100 @@ mod tests {
         let score = address_matching::match_postal_codes(
             Some("12345"),
             Some("12345"),
+            None,
         );
         assert_eq!(score, 1.0);
 
         let score = address_matching::match_postal_codes(
             Some("12345-6789"),
             Some("12345"),
+            None,
         );
         assert!(score > 0.90);
     }
+
+    #[test]
+    fn test_postal_code_match_by_country() {
+        // Same UK outward code, different inward code
+        assert_eq!(address_matching::match_postal_codes(Some("SW1A 1AA"), Some("sw1a 2aa"), Some("GB")), 0.70);
+        // B1 and B11 are different districts despite sharing characters
+        assert_eq!(address_matching::match_postal_codes(Some("B1 1AA"), Some("B11 1AA"), None), 0.0);
+        // Same Canadian forward sortation area
+        assert_eq!(address_matching::match_postal_codes(Some("K1A 0B1"), Some("K1A 0A6"), Some("Canada")), 0.70);
+        assert_eq!(address_matching::match_postal_codes(Some("K1A 0B1"), Some("K1P 1J9"), Some("CA")), 0.0);
+    }
+
+    #[test]
+    fn test_former_address_match_decays() {
+        let address = |line1: &str, postal_code: &str| Address {
+            line1: Some(line1.to_string()),
+            line2: None,
+            city: Some("Leeds".to_string()),
+            state: None,
+            postal_code: Some(postal_code.to_string()),
+            country: Some("GB".to_string()),
+            verification: VerificationStatus::Unverified,
+            period: None,
+        };
+        let now = Utc::now();
+        let moved = |years: i64| crate::models::Period {
+            start: None,
+            end: Some(now - chrono::Duration::days(years * 365)),
+        };
+        let incoming = [address("3 Kirkstall Road", "LS3 1HS")];
+        let current = address("88 Otley Road", "LS16 5JT");
+        let mut former = address("3 Kirkstall Road", "LS3 1HS");
+
+        former.period = Some(moved(1));
+        let (recent, _) = address_matching::match_addresses_at(&incoming, &[current.clone(), former.clone()], now);
+        former.period = Some(moved(5));
+        let (old, _) = address_matching::match_addresses_at(&incoming, &[current.clone(), former.clone()], now);
+        let (current_only, _) = address_matching::match_addresses_at(&incoming, &[current], now);
+
+        // Discounted by half every five years from the undecayed score
+        let undecayed = address_matching::match_address(&incoming[0], &former);
+        let decay = |years: i64| 0.5_f64.powf((years * 365) as f64 / 365.25 / 5.0);
+        assert!((recent - undecayed * decay(1)).abs() < 1e-9, "{} {}", recent, undecayed);
+        assert!((old - undecayed * decay(5)).abs() < 1e-9, "{} {}", old, undecayed);
+        assert!(old < recent && old > current_only, "{} {}", old, current_only);
+    }
+
+    #[test]
+    fn test_identifier_match_reports_verification() {
+        let mut on_file = Identifier::ssn("123-45-6789".to_string());
+        on_file.verification = VerificationStatus::DocumentVerified;
+        let mrn = Identifier::mrn("GENERAL".to_string(), "A100".to_string());
+        let incoming = [Identifier::ssn("123456789".to_string())];
+
+        let (score, status) = identifier_matching::match_identifiers_verified(&incoming, &[mrn.clone(), on_file]);
+        assert_eq!(score, 0.98);
+        assert_eq!(status, VerificationStatus::DocumentVerified);
+
+        // A verified identifier that does not match lends no weight
+        let mut other = Identifier::ssn("987-65-4321".to_string());
+        other.verification = VerificationStatus::DocumentVerified;
+        let (score, status) = identifier_matching::match_identifiers_verified(&incoming, &[mrn, other]);
+        assert_eq!(score, 0.0);
+        assert_eq!(status, VerificationStatus::Unverified);
+    }
+
+    #[test]
+    fn test_expired_identifier_counts_less() {
+        let now = Utc::now();
+        let mut retired = Identifier::mrn("GENERAL".to_string(), "A100".to_string());
+        retired.period = Some(crate::models::Period {
+            start: None,
+            end: Some(now - chrono::Duration::days(30)),
+        });
+        let incoming = [Identifier::mrn("GENERAL".to_string(), "A100".to_string())];
+
+        let (current, _) = identifier_matching::match_identifiers_at(&incoming, &incoming, now);
+        let (expired, _) = identifier_matching::match_identifiers_at(&incoming, &[retired.clone()], now);
+        assert_eq!(current, 1.0);
+        assert!(expired > 0.0 && expired < current, "{}", expired);
+
+        // Not yet retired at the time of matching
+        let (before_end, _) = identifier_matching::match_identifiers_at(&incoming, &[retired], now - chrono::Duration::days(60));
+        assert_eq!(before_end, 1.0);
+    }
 }
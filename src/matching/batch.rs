@@ -0,0 +1,433 @@
+//! Bulk matching of record batches
+//!
+//! Payer rosters and registry extracts arrive thousands of records at a
+//! time, and many of their records share a family name and birth year.
+//! Matching them one request at a time repeats the same blocking searches
+//! and loads the same stored patients again and again. A [`BatchMatcher`]
+//! blocks the whole batch first, searching each distinct blocking key once
+//! and loading each candidate once, then scores the records on several
+//! threads sharing those candidates.
+
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::Datelike;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::config::{BlockingPass, BlockingStrategy};
+use crate::db::PatientRepository;
+use crate::jobs::JobHandle;
+use crate::models::{AuthorityRegistry, Patient};
+use crate::search::{blocking_keys, BlockingQuery, SearchBackend};
+use crate::Result;
+use super::{identifier_candidates, quality_label, PatientMatcher};
+
+/// One record of a batch
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct BatchMatchRecord {
+    /// Caller's key for the record, such as a roster member ID, echoed in the result
+    #[serde(default)]
+    pub reference: Option<String>,
+
+    /// Patient to match against existing records
+    #[serde(flatten)]
+    pub patient: Patient,
+}
+
+/// A stored patient matching a batch record
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchMatch {
+    pub patient_id: Uuid,
+    pub score: f64,
+    /// "certain", "probable" or "possible"
+    pub quality: String,
+}
+
+/// Matches for one batch record
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchMatchResult {
+    /// Position of the record in the batch
+    pub index: usize,
+
+    /// The record's `reference`, if it had one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+
+    /// Matches at or above the threshold, best first
+    pub matches: Vec<BatchMatch>,
+
+    /// Why the record could not be matched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Counts over a matched batch
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct BatchMatchSummary {
+    /// Records in the batch
+    pub records: usize,
+    /// Records with at least one match
+    pub matched: usize,
+    /// Records with no match
+    pub unmatched: usize,
+    /// Records that could not be matched
+    pub failed: usize,
+}
+
+impl BatchMatchSummary {
+    /// Summarize a batch's results
+    pub fn of(results: &[BatchMatchResult]) -> Self {
+        let mut summary = Self {
+            records: results.len(),
+            ..Self::default()
+        };
+        for result in results {
+            if result.error.is_some() {
+                summary.failed += 1;
+            } else if result.matches.is_empty() {
+                summary.unmatched += 1;
+            } else {
+                summary.matched += 1;
+            }
+        }
+        summary
+    }
+}
+
+/// How a batch is matched
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    /// Minimum score of returned matches
+    pub threshold: f64,
+    /// Most matches returned per record
+    pub limit: usize,
+    /// How candidates are found beyond the exact MRN and SSN lookup
+    pub blocking: BlockingStrategy,
+    /// Most candidates taken from the search index per blocking key
+    pub max_candidates: usize,
+    /// Searches of the `multi_pass` strategy, in order
+    pub passes: Vec<BlockingPass>,
+    /// Scoring threads; the number of CPUs when 0
+    pub workers: usize,
+}
+
+/// What a record is blocked on
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum BlockingKey {
+    /// Family name and, unless blocking on the name alone, birth year
+    NameAndYear(String, Option<i32>),
+    /// Precomputed index blocking keys
+    Keys(Vec<String>),
+    /// Inputs of the configured blocking passes
+    MultiPass(BlockingQuery),
+}
+
+/// Patients found for a blocking key, or why the search failed
+type Block = std::result::Result<Vec<Uuid>, String>;
+
+/// Matches batches of records against stored patients
+pub struct BatchMatcher {
+    patients: Arc<dyn PatientRepository>,
+    search: Arc<dyn SearchBackend>,
+    matcher: Arc<dyn PatientMatcher>,
+    authorities: AuthorityRegistry,
+    options: BatchOptions,
+}
+
+impl BatchMatcher {
+    /// Create a batch matcher scoring with `matcher`
+    pub fn new(
+        patients: Arc<dyn PatientRepository>,
+        search: Arc<dyn SearchBackend>,
+        matcher: Arc<dyn PatientMatcher>,
+        authorities: AuthorityRegistry,
+        options: BatchOptions,
+    ) -> Self {
+        Self {
+            patients,
+            search,
+            matcher,
+            authorities,
+            options,
+        }
+    }
+
+    /// Match every record, returning results in batch order
+    ///
+    /// Each record processed advances `progress`, when given.
+    pub fn run(&self, records: &[BatchMatchRecord], progress: Option<&JobHandle>) -> Vec<BatchMatchResult> {
+        let keys: Vec<Option<BlockingKey>> = records.iter().map(|r| self.blocking_key(&r.patient)).collect();
+        let (blocks, neighbours) = self.block(&keys);
+
+        let workers = match self.options.workers {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            workers => workers,
+        };
+        let chunk_size = records.len().div_ceil(workers).max(1);
+
+        std::thread::scope(|scope| {
+            let threads: Vec<_> = records
+                .chunks(chunk_size)
+                .zip(keys.chunks(chunk_size))
+                .enumerate()
+                .map(|(chunk, (records, keys))| {
+                    let (blocks, neighbours) = (&blocks, &neighbours);
+                    scope.spawn(move || {
+                        records
+                            .iter()
+                            .zip(keys)
+                            .enumerate()
+                            .map(|(i, (record, key))| {
+                                let block = key.as_ref().and_then(|key| blocks.get(key));
+                                let result = self.match_record(chunk * chunk_size + i, record, block, neighbours);
+                                if let Some(progress) = progress {
+                                    progress.advance(1);
+                                }
+                                result
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            threads
+                .into_iter()
+                .flat_map(|thread| thread.join().expect("batch scoring thread panicked"))
+                .collect()
+        })
+    }
+
+    /// Match the batch as a background job, writing the results to
+    /// `path` as NDJSON
+    ///
+    /// The job's report is the [`BatchMatchSummary`].
+    pub fn spawn(self, records: Vec<BatchMatchRecord>, path: PathBuf, handle: JobHandle) -> tokio::task::JoinHandle<()> {
+        tokio::task::spawn_blocking(move || {
+            handle.start(Some(records.len() as u64));
+            let results = self.run(&records, Some(&handle));
+            let summary = BatchMatchSummary::of(&results);
+
+            match write_ndjson(&path, &results) {
+                Ok(()) => {
+                    tracing::info!(
+                        "Batch match job {} matched {} of {} records",
+                        handle.id(),
+                        summary.matched,
+                        summary.records
+                    );
+                    handle.complete(serde_json::to_value(&summary).unwrap_or_default());
+                }
+                Err(e) => {
+                    tracing::warn!("Batch match job {} failed: {}", handle.id(), e);
+                    handle.fail(&e.to_string());
+                }
+            }
+        })
+    }
+
+    fn blocking_key(&self, patient: &Patient) -> Option<BlockingKey> {
+        match self.options.blocking {
+            BlockingStrategy::BlockingKeys => {
+                let keys = blocking_keys(patient);
+                return (!keys.is_empty()).then_some(BlockingKey::Keys(keys));
+            }
+            BlockingStrategy::MultiPass => return Some(BlockingKey::MultiPass(BlockingQuery::of(patient))),
+            _ => {}
+        }
+        let family = patient.legal_name().family.trim().to_lowercase();
+        if family.is_empty() {
+            return None;
+        }
+        match self.options.blocking {
+            BlockingStrategy::NameAndBirthYear => {
+                Some(BlockingKey::NameAndYear(family, patient.birth_date.map(|d| d.year())))
+            }
+            BlockingStrategy::Name => Some(BlockingKey::NameAndYear(family, None)),
+            BlockingStrategy::BlockingKeys | BlockingStrategy::MultiPass | BlockingStrategy::IdentifierOnly => None,
+        }
+    }
+
+    /// Search each distinct blocking key once and load every candidate once
+    ///
+    /// A key whose search failed maps to the error, reported on its records.
+    fn block(&self, keys: &[Option<BlockingKey>]) -> (HashMap<BlockingKey, Block>, HashMap<Uuid, Patient>) {
+        let mut blocks = HashMap::new();
+        for key in keys.iter().flatten() {
+            if blocks.contains_key(key) {
+                continue;
+            }
+            let ids = match key {
+                BlockingKey::NameAndYear(family, birth_year) => {
+                    self.search.search_by_name_and_year(family, *birth_year, self.options.max_candidates)
+                }
+                BlockingKey::Keys(keys) => self.search.search_by_blocking_keys(keys, self.options.max_candidates),
+                BlockingKey::MultiPass(query) => query
+                    .run(self.search.as_ref(), &self.options.passes, self.options.max_candidates)
+                    .map(|blocked| blocked.ids),
+            };
+            let ids = ids
+                .map(|ids| ids.iter().filter_map(|id| Uuid::parse_str(id).ok()).collect::<Vec<_>>())
+                .map_err(|e| format!("Blocking search failed: {}", e));
+            blocks.insert(key.clone(), ids);
+        }
+
+        let mut neighbours = HashMap::new();
+        let ids: HashSet<Uuid> = blocks.values().flatten().flatten().copied().collect();
+        for id in ids {
+            match self.patients.get_by_id(&id) {
+                Ok(Some(patient)) => {
+                    neighbours.insert(id, patient);
+                }
+                Ok(None) => tracing::warn!("Patient {} found in search index but not in database", id),
+                Err(e) => tracing::error!("Failed to fetch patient {}: {}", id, e),
+            }
+        }
+        (blocks, neighbours)
+    }
+
+    fn match_record(
+        &self,
+        index: usize,
+        record: &BatchMatchRecord,
+        block: Option<&Block>,
+        neighbours: &HashMap<Uuid, Patient>,
+    ) -> BatchMatchResult {
+        let mut result = BatchMatchResult {
+            index,
+            reference: record.reference.clone(),
+            matches: Vec::new(),
+            error: None,
+        };
+        match self.score(&record.patient, block, neighbours) {
+            Ok(matches) => result.matches = matches,
+            Err(e) => result.error = Some(e),
+        }
+        result
+    }
+
+    fn score(
+        &self,
+        patient: &Patient,
+        block: Option<&Block>,
+        neighbours: &HashMap<Uuid, Patient>,
+    ) -> std::result::Result<Vec<BatchMatch>, String> {
+        let mut candidates = identifier_candidates(patient, self.patients.as_ref(), &self.authorities)
+            .unwrap_or_else(|e| {
+                tracing::warn!("Exact identifier lookup failed, falling back to blocking: {}", e);
+                Vec::new()
+            });
+        let mut seen: HashSet<Uuid> = candidates.iter().map(|c| c.id).collect();
+        if let Some(block) = block {
+            let ids = block.as_ref().map_err(Clone::clone)?;
+            for id in ids {
+                if *id != patient.id && seen.insert(*id) {
+                    candidates.extend(neighbours.get(id).cloned());
+                }
+            }
+        }
+
+        let results = self.matcher.find_matches(patient, &candidates).map_err(|e| format!("Matching failed: {}", e))?;
+        Ok(results
+            .into_iter()
+            .filter(|m| m.score >= self.options.threshold)
+            .take(self.options.limit)
+            .map(|m| BatchMatch {
+                patient_id: m.patient.id,
+                score: m.score,
+                quality: quality_label(m.score).to_string(),
+            })
+            .collect())
+    }
+}
+
+fn write_ndjson(path: &Path, results: &[BatchMatchResult]) -> Result<()> {
+    let io_error = |e: std::io::Error| crate::Error::Internal(format!("Failed to write {}: {}", path.display(), e));
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(io_error)?;
+    }
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(path).map_err(io_error)?);
+    for result in results {
+        let line = serde_json::to_string(result).map_err(|e| crate::Error::Internal(e.to_string()))?;
+        writeln!(writer, "{}", line).map_err(io_error)?;
+    }
+    writer.flush().map_err(io_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::db::InMemoryPatientRepository;
+    use crate::matching::ProbabilisticMatcher;
+    use crate::models::{Address, Gender, Identifier};
+    use crate::search::SearchEngine;
+    use chrono::NaiveDate;
+    use tempfile::TempDir;
+
+    fn patient(family: &str, given: &str, ssn: &str) -> Patient {
+        let mut patient = crate::fixtures::patient(family, &[given], Gender::Male);
+        patient.birth_date = NaiveDate::from_ymd_opt(1971, 11, 2);
+        patient.addresses.push(Address {
+            line1: Some("41 Harbour Street".to_string()),
+            line2: None,
+            city: Some("Portland".to_string()),
+            state: Some("ME".to_string()),
+            postal_code: Some("04101".to_string()),
+            country: Some("US".to_string()),
+            verification: Default::default(),
+            period: None,
+        });
+        if !ssn.is_empty() {
+            patient.identifiers.push(Identifier::ssn(ssn.to_string()));
+        }
+        patient
+    }
+
+    #[test]
+    fn test_batch_results_in_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let search = Arc::new(SearchEngine::new(temp_dir.path()).unwrap());
+        let patients = Arc::new(InMemoryPatientRepository::new());
+        let okafor = patients.create(&patient("Okafor", "Chidi", "321-54-9876")).unwrap();
+        let lindqvist = patients.create(&patient("Lindqvist", "Erik", "432-65-0987")).unwrap();
+        search.index_patients(&[okafor.clone(), lindqvist.clone()]).unwrap();
+        search.reload().unwrap();
+
+        let matcher = BatchMatcher::new(
+            patients,
+            search,
+            Arc::new(ProbabilisticMatcher::new(Config::default().matching)),
+            AuthorityRegistry::default(),
+            BatchOptions {
+                threshold: 0.7,
+                limit: 3,
+                blocking: BlockingStrategy::NameAndBirthYear,
+                max_candidates: 100,
+                passes: Vec::new(),
+                workers: 2,
+            },
+        );
+        let records: Vec<BatchMatchRecord> = [
+            ("m-1", patient("Lindqvist", "Erik", "432-65-0987")),
+            // No identifier; found by blocking alone
+            ("m-2", patient("Okafor", "Chidi", "")),
+            ("m-3", patient("Abernathy", "Ruth", "")),
+        ]
+        .into_iter()
+        .map(|(reference, patient)| BatchMatchRecord { reference: Some(reference.to_string()), patient })
+        .collect();
+
+        let results = matcher.run(&records, None);
+        assert_eq!(results.iter().map(|r| r.index).collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert_eq!(results[0].reference.as_deref(), Some("m-1"));
+        assert_eq!(results[0].matches[0].patient_id, lindqvist.id);
+        assert_eq!(results[1].matches[0].patient_id, okafor.id);
+        assert!(results[2].matches.is_empty());
+
+        let summary = BatchMatchSummary::of(&results);
+        assert_eq!((summary.records, summary.matched, summary.unmatched, summary.failed), (3, 2, 1, 0));
+    }
+}
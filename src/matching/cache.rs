@@ -0,0 +1,261 @@
+//! Pair score caching
+//!
+//! The re-linkage review and repeated match requests for the same stored
+//! patient score the same pairs over and over. [`CachingMatcher`] keeps each
+//! pair's score in `patient_match_scores` and reuses it while it is still
+//! valid: scored by the same algorithm version, under the same matching
+//! configuration and name frequencies, from the same versions of both
+//! records. Changing either record moves its `updated_at` and so invalidates
+//! every pair it is in.
+//!
+//! Only pairs of stored patients can be cached, since the stored scores
+//! reference both patients. Scores are stored to four decimal places.
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use super::{MatchResult, MatchScoreBreakdown, NameFrequencies, PatientMatcher};
+use crate::config::MatchingConfig;
+use crate::models::Patient;
+use crate::Result;
+
+/// Version of the scoring algorithms; scores from other versions are recomputed
+pub const ALGORITHM_VERSION: &str = concat!("mpi/", env!("CARGO_PKG_VERSION"));
+
+/// Everything a cached score depends on
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PairKey {
+    pub patient_id: Uuid,
+    pub candidate_id: Uuid,
+    pub patient_updated_at: DateTime<Utc>,
+    pub candidate_updated_at: DateTime<Utc>,
+    pub algorithm_version: String,
+    pub config_hash: String,
+}
+
+/// A pair's score and its components
+#[derive(Debug, Clone)]
+pub struct CachedScore {
+    pub score: f64,
+    pub breakdown: MatchScoreBreakdown,
+}
+
+/// Store of pair scores
+pub trait PairScoreCache: Send + Sync {
+    /// The score stored under exactly `key`, if any
+    fn get(&self, key: &PairKey) -> Result<Option<CachedScore>>;
+
+    /// Store a score, replacing whatever was stored for the same pair
+    fn put(&self, key: &PairKey, score: &CachedScore) -> Result<()>;
+}
+
+/// Short hash of the settings that change scores
+pub fn config_hash(config: &MatchingConfig) -> String {
+    short_hash(&serde_json::to_string(config).unwrap_or_default())
+}
+
+fn short_hash(text: &str) -> String {
+    Sha256::digest(text.as_bytes())[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Matcher that reuses stored scores of the matcher it wraps
+pub struct CachingMatcher {
+    inner: Arc<dyn PatientMatcher>,
+    cache: Arc<dyn PairScoreCache>,
+    config_hash: String,
+    name_frequencies: Option<Arc<NameFrequencies>>,
+}
+
+impl CachingMatcher {
+    /// Wrap `inner`, whose scores follow `config`
+    pub fn new(inner: Arc<dyn PatientMatcher>, cache: Arc<dyn PairScoreCache>, config: &MatchingConfig) -> Self {
+        Self {
+            inner,
+            cache,
+            config_hash: config_hash(config),
+            name_frequencies: None,
+        }
+    }
+
+    /// Key scores also by the name frequencies `inner` weighs names by: their
+    /// settings, and the counts in effect when a pair is scored
+    pub fn with_name_frequencies(mut self, frequencies: Arc<NameFrequencies>) -> Self {
+        let settings = serde_json::to_string(frequencies.config()).unwrap_or_default();
+        self.config_hash = short_hash(&format!("{}|{}", self.config_hash, settings));
+        self.name_frequencies = Some(frequencies);
+        self
+    }
+
+    /// Cache key of a pair
+    pub fn key(&self, patient: &Patient, candidate: &Patient) -> PairKey {
+        let config_hash = match &self.name_frequencies {
+            Some(frequencies) => format!("{}-{}", self.config_hash, frequencies.digest()),
+            None => self.config_hash.clone(),
+        };
+        PairKey {
+            patient_id: patient.id,
+            candidate_id: candidate.id,
+            patient_updated_at: patient.updated_at,
+            candidate_updated_at: candidate.updated_at,
+            algorithm_version: ALGORITHM_VERSION.to_string(),
+            config_hash,
+        }
+    }
+}
+
+impl PatientMatcher for CachingMatcher {
+    fn match_patients(&self, patient: &Patient, candidate: &Patient) -> Result<MatchResult> {
+        let key = self.key(patient, candidate);
+        match self.cache.get(&key) {
+            Ok(Some(cached)) => {
+                return Ok(MatchResult {
+                    patient: candidate.clone(),
+                    score: cached.score,
+                    breakdown: cached.breakdown,
+                });
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to read cached score of {} and {}: {}", patient.id, candidate.id, e),
+        }
+
+        let result = self.inner.match_patients(patient, candidate)?;
+        let cached = CachedScore {
+            score: result.score,
+            breakdown: result.breakdown.clone(),
+        };
+        if let Err(e) = self.cache.put(&key, &cached) {
+            tracing::warn!("Failed to cache score of {} and {}: {}", patient.id, candidate.id, e);
+        }
+        Ok(result)
+    }
+
+    fn find_matches(&self, patient: &Patient, candidates: &[Patient]) -> Result<Vec<MatchResult>> {
+        let mut matches = Vec::new();
+        for candidate in candidates {
+            crate::deadline::check()?;
+            let result = self.match_patients(patient, candidate)?;
+            if self.is_match(result.score) {
+                matches.push(result);
+            }
+        }
+
+        // Sort by score descending
+        matches.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        Ok(matches)
+    }
+
+    fn is_match(&self, score: f64) -> bool {
+        self.inner.is_match(score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::patient;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use crate::config::Config;
+    use crate::db::InMemoryPairScoreCache;
+    use crate::matching::ProbabilisticMatcher;
+    use crate::models::Gender;
+
+    /// Counts the pairs it actually scores
+    struct CountingMatcher {
+        inner: ProbabilisticMatcher,
+        scored: AtomicUsize,
+    }
+
+    impl PatientMatcher for CountingMatcher {
+        fn match_patients(&self, patient: &Patient, candidate: &Patient) -> Result<MatchResult> {
+            self.scored.fetch_add(1, Ordering::SeqCst);
+            self.inner.match_patients(patient, candidate)
+        }
+
+        fn find_matches(&self, patient: &Patient, candidates: &[Patient]) -> Result<Vec<MatchResult>> {
+            self.inner.find_matches(patient, candidates)
+        }
+
+        fn is_match(&self, score: f64) -> bool {
+            self.inner.is_match(score)
+        }
+    }
+
+    #[test]
+    fn test_reuses_scores_until_a_record_or_config_changes() {
+        let config = Config::default().matching;
+        let counting = Arc::new(CountingMatcher {
+            inner: ProbabilisticMatcher::new(config.clone()),
+            scored: AtomicUsize::new(0),
+        });
+        let cache = Arc::new(InMemoryPairScoreCache::new());
+        let matcher = CachingMatcher::new(counting.clone(), cache.clone(), &config);
+
+        let a = patient("Owusu", &["Ama"], Gender::Female);
+        let mut b = patient("Owusu", &["Ama"], Gender::Female);
+        let first = matcher.match_patients(&a, &b).unwrap();
+        let second = matcher.match_patients(&a, &b).unwrap();
+        assert_eq!(counting.scored.load(Ordering::SeqCst), 1);
+        assert_eq!(first.score, second.score);
+        assert_eq!(second.patient.id, b.id);
+
+        // An updated record is scored again
+        b.updated_at += chrono::Duration::seconds(1);
+        matcher.match_patients(&a, &b).unwrap();
+        assert_eq!(counting.scored.load(Ordering::SeqCst), 2);
+
+        // So is every pair under a different configuration
+        let mut changed = config.clone();
+        changed.threshold_score = 0.6;
+        assert_ne!(config_hash(&changed), config_hash(&config));
+        let matcher = CachingMatcher::new(counting.clone(), cache, &changed);
+        matcher.match_patients(&a, &b).unwrap();
+        assert_eq!(counting.scored.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_rescores_when_name_frequencies_change() {
+        let config = Config::default();
+        let counting = Arc::new(CountingMatcher {
+            inner: ProbabilisticMatcher::new(config.matching.clone()),
+            scored: AtomicUsize::new(0),
+        });
+        let cache = Arc::new(InMemoryPairScoreCache::new());
+        let frequencies = Arc::new(NameFrequencies::new(config.name_frequency.clone()));
+        let matcher = CachingMatcher::new(counting.clone(), cache.clone(), &config.matching)
+            .with_name_frequencies(frequencies.clone());
+
+        let a = patient("Owusu", &["Ama"], Gender::Female);
+        let b = patient("Owusu", &["Ama"], Gender::Female);
+        matcher.match_patients(&a, &b).unwrap();
+        matcher.match_patients(&a, &b).unwrap();
+        assert_eq!(counting.scored.load(Ordering::SeqCst), 1);
+
+        // A recount with new counts changes the key; the same counts do not
+        frequencies.replace(10_000, vec![("Owusu".to_string(), 1_000)]);
+        matcher.match_patients(&a, &b).unwrap();
+        assert_eq!(counting.scored.load(Ordering::SeqCst), 2);
+        frequencies.replace(10_000, vec![("OWUSU".to_string(), 1_000)]);
+        matcher.match_patients(&a, &b).unwrap();
+        assert_eq!(counting.scored.load(Ordering::SeqCst), 2);
+
+        // So do different frequency settings
+        let mut settings = config.name_frequency.clone();
+        settings.min_factor = 0.9;
+        let other = Arc::new(NameFrequencies::new(settings));
+        other.replace(10_000, vec![("Owusu".to_string(), 1_000)]);
+        let matcher = CachingMatcher::new(counting.clone(), cache, &config.matching).with_name_frequencies(other);
+        matcher.match_patients(&a, &b).unwrap();
+        assert_eq!(counting.scored.load(Ordering::SeqCst), 3);
+    }
+}
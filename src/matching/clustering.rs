@@ -0,0 +1,340 @@
+//! Cluster-based entity resolution over confirmed links
+//!
+//! Links are made one pair at a time, so A linked to B and B linked to C puts
+//! A and C in the same person even when nobody compared them. This job takes
+//! the transitive closure of the same-person links (`refer`, `replaces` and
+//! `replaced-by`; `seealso` does not assert identity) between active
+//! patients with a union-find, then validates each cluster by scoring every
+//! pair in it. Clusters with a pair below the validation score, or too large
+//! to validate, are reported as conflicting for steward review. Links are not
+//! modified.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::config::ClusteringConfig;
+use crate::db::PatientRepository;
+use crate::models::{LinkType, Patient};
+use crate::Result;
+use super::PatientMatcher;
+
+/// Number of patients fetched per page while collecting links
+const BATCH_SIZE: i64 = 500;
+
+/// Two members of a cluster that do not match each other
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ClusterConflict {
+    pub patient_id: Uuid,
+    pub other_patient_id: Uuid,
+    /// Score of the pair under the current configuration
+    pub score: f64,
+}
+
+/// Patients linked, directly or through each other, as one person
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PatientCluster {
+    /// Lowest member ID; stable while that record stays in the cluster
+    pub cluster_id: Uuid,
+    /// Member patient IDs, in ascending order
+    pub members: Vec<Uuid>,
+    /// Member pairs scoring below the validation score
+    pub conflicts: Vec<ClusterConflict>,
+    /// Larger than `clustering.max_cluster_size`, so not validated
+    pub oversized: bool,
+}
+
+impl PatientCluster {
+    /// Whether the cluster needs a steward's review
+    pub fn is_conflicting(&self) -> bool {
+        self.oversized || !self.conflicts.is_empty()
+    }
+}
+
+/// Outcome of a clustering run
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct ClusterReport {
+    /// Active patients read
+    pub patients_checked: usize,
+    /// Clusters of two or more patients
+    pub clusters: usize,
+    /// Clusters whose pairs were all scored
+    pub clusters_validated: usize,
+    /// Clusters flagged for steward review
+    pub conflicting: Vec<PatientCluster>,
+}
+
+/// Disjoint sets of patient IDs with path halving and union by size
+#[derive(Default)]
+struct UnionFind {
+    index: HashMap<Uuid, usize>,
+    ids: Vec<Uuid>,
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl UnionFind {
+    fn insert(&mut self, id: Uuid) -> usize {
+        if let Some(&i) = self.index.get(&id) {
+            return i;
+        }
+        let i = self.ids.len();
+        self.index.insert(id, i);
+        self.ids.push(id);
+        self.parent.push(i);
+        self.size.push(1);
+        i
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: Uuid, b: Uuid) {
+        let (a, b) = (self.insert(a), self.insert(b));
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+    }
+
+    /// Sets of two or more, each sorted, ordered by their lowest member
+    fn sets(mut self) -> Vec<Vec<Uuid>> {
+        let mut sets: HashMap<usize, Vec<Uuid>> = HashMap::new();
+        for i in 0..self.ids.len() {
+            let root = self.find(i);
+            sets.entry(root).or_default().push(self.ids[i]);
+        }
+        let sorted: BTreeMap<Uuid, Vec<Uuid>> = sets
+            .into_values()
+            .filter(|members| members.len() > 1)
+            .map(|mut members| {
+                members.sort();
+                (members[0], members)
+            })
+            .collect();
+        sorted.into_values().collect()
+    }
+}
+
+/// Whether a link asserts that both records are the same person
+pub(crate) fn same_person(link_type: &LinkType) -> bool {
+    !matches!(link_type, LinkType::Seealso)
+}
+
+/// Groups linked patients into clusters and validates them
+pub struct ClusteringJob<'a> {
+    matcher: &'a dyn PatientMatcher,
+    config: ClusteringConfig,
+}
+
+impl<'a> ClusteringJob<'a> {
+    /// Create a job validating clusters with the given matcher
+    pub fn new(matcher: &'a dyn PatientMatcher, config: ClusteringConfig) -> Self {
+        Self { matcher, config }
+    }
+
+    /// Cluster every active patient by its same-person links
+    pub fn run(&self, patients: &dyn PatientRepository) -> Result<ClusterReport> {
+        let mut report = ClusterReport::default();
+        let mut active = Vec::new();
+        let mut links = Vec::new();
+
+        let mut offset = 0;
+        loop {
+            let page = patients.list_active(BATCH_SIZE, offset)?;
+            if page.is_empty() {
+                break;
+            }
+            offset += page.len() as i64;
+
+            for patient in page {
+                active.push(patient.id);
+                links.extend(
+                    patient
+                        .links
+                        .iter()
+                        .filter(|link| same_person(&link.link_type))
+                        .map(|link| (patient.id, link.other_patient_id)),
+                );
+            }
+        }
+        report.patients_checked = active.len();
+
+        // Retired records (merged away or deleted) do not join clusters
+        active.sort();
+        let mut sets = UnionFind::default();
+        for (a, b) in links {
+            if active.binary_search(&b).is_ok() {
+                sets.union(a, b);
+            }
+        }
+
+        for members in sets.sets() {
+            crate::deadline::check()?;
+            report.clusters += 1;
+            let cluster = self.validate(members, patients)?;
+            if !cluster.oversized {
+                report.clusters_validated += 1;
+            }
+            if cluster.is_conflicting() {
+                report.conflicting.push(cluster);
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Score every pair in a cluster, recording those that do not match
+    fn validate(&self, members: Vec<Uuid>, patients: &dyn PatientRepository) -> Result<PatientCluster> {
+        let mut cluster = PatientCluster {
+            cluster_id: members[0],
+            members,
+            conflicts: Vec::new(),
+            oversized: false,
+        };
+        if cluster.members.len() > self.config.max_cluster_size {
+            cluster.oversized = true;
+            return Ok(cluster);
+        }
+
+        let mut records: Vec<Patient> = Vec::with_capacity(cluster.members.len());
+        for id in &cluster.members {
+            if let Some(patient) = patients.get_by_id(id)? {
+                records.push(patient);
+            }
+        }
+
+        for (i, patient) in records.iter().enumerate() {
+            for other in &records[i + 1..] {
+                let result = self.matcher.match_patients(patient, other)?;
+                if !self.pair_matches(result.score) {
+                    cluster.conflicts.push(ClusterConflict {
+                        patient_id: patient.id,
+                        other_patient_id: other.id,
+                        score: result.score,
+                    });
+                }
+            }
+        }
+
+        Ok(cluster)
+    }
+
+    fn pair_matches(&self, score: f64) -> bool {
+        match self.config.min_pair_score {
+            Some(min) => score >= min,
+            None => self.matcher.is_match(score),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::db::InMemoryPatientRepository;
+    use crate::matching::ProbabilisticMatcher;
+    use crate::models::{Gender, PatientLink};
+    use chrono::NaiveDate;
+
+    fn patient(family: &str, given: &str, year: i32) -> Patient {
+        let mut patient = crate::fixtures::patient(family, &[given], Gender::Male);
+        patient.birth_date = NaiveDate::from_ymd_opt(year, 6, 1);
+        patient
+    }
+
+    fn link(patient: &mut Patient, other: &Patient, link_type: LinkType) {
+        patient.links.push(PatientLink {
+            other_patient_id: other.id,
+            link_type,
+        });
+    }
+
+    #[test]
+    fn test_union_find_groups_transitively() {
+        let ids: Vec<Uuid> = (0..5).map(|_| Uuid::new_v4()).collect();
+        let mut sets = UnionFind::default();
+        sets.union(ids[0], ids[1]);
+        sets.union(ids[2], ids[1]);
+        sets.union(ids[3], ids[4]);
+        sets.union(ids[4], ids[3]);
+
+        let mut first = vec![ids[0], ids[1], ids[2]];
+        let mut second = vec![ids[3], ids[4]];
+        first.sort();
+        second.sort();
+        let mut expected = vec![first, second];
+        expected.sort_by_key(|members| members[0]);
+        assert_eq!(sets.sets(), expected);
+    }
+
+    #[test]
+    fn test_flags_clusters_with_unmatched_pairs() {
+        let patients = InMemoryPatientRepository::new();
+        let a = patient("Lindqvist", "Erik", 1970);
+        let b = patient("Lindqvist", "Erik", 1970);
+        let mut c = patient("Lindqvist", "Erik", 1970);
+        let mut d = patient("Okafor", "Chidi", 1991);
+        // a ~ b directly; b ~ c through a link on c; c ~ d pulls in a stranger
+        let mut a_linked = a.clone();
+        link(&mut a_linked, &b, LinkType::Refer);
+        link(&mut c, &b, LinkType::Refer);
+        link(&mut d, &c, LinkType::Replaces);
+        // "see also" links do not join clusters
+        let mut e = patient("Lindqvist", "Erik", 1970);
+        link(&mut e, &a, LinkType::Seealso);
+        for p in [&a_linked, &b, &c, &d, &e] {
+            patients.create(p).unwrap();
+        }
+
+        let matcher = ProbabilisticMatcher::new(Config::default().matching);
+        let config = ClusteringConfig {
+            min_pair_score: Some(0.6),
+            ..ClusteringConfig::default()
+        };
+        let report = ClusteringJob::new(&matcher, config).run(&patients).unwrap();
+
+        assert_eq!(report.patients_checked, 5);
+        assert_eq!(report.clusters, 1);
+        assert_eq!(report.conflicting.len(), 1);
+        let cluster = &report.conflicting[0];
+        assert_eq!(cluster.members.len(), 4);
+        assert_eq!(cluster.cluster_id, *cluster.members.iter().min().unwrap());
+        assert_eq!(cluster.conflicts.len(), 3);
+        assert!(cluster
+            .conflicts
+            .iter()
+            .all(|conflict| conflict.patient_id == d.id || conflict.other_patient_id == d.id));
+    }
+
+    #[test]
+    fn test_oversized_clusters_are_not_scored() {
+        let patients = InMemoryPatientRepository::new();
+        let first = patient("Lindqvist", "Erik", 1970);
+        let mut second = patient("Lindqvist", "Erik", 1970);
+        link(&mut second, &first, LinkType::Refer);
+        patients.create(&first).unwrap();
+        patients.create(&second).unwrap();
+
+        let matcher = ProbabilisticMatcher::new(Config::default().matching);
+        let config = ClusteringConfig {
+            max_cluster_size: 1,
+            ..ClusteringConfig::default()
+        };
+        let report = ClusteringJob::new(&matcher, config).run(&patients).unwrap();
+        assert_eq!(report.clusters_validated, 0);
+        assert!(report.conflicting[0].oversized);
+    }
+}
@@ -0,0 +1,234 @@
+//! Structured logging of match decisions
+//!
+//! [`DecisionLoggingMatcher`] wraps another matcher and emits one tracing
+//! event per scored pair under the [`TARGET`] target, with the query patient
+//! hash, candidate ID, score, threshold, decision, matcher and scoring time.
+//! Non-matches are scored and logged too, so false negatives can be analyzed
+//! from the logs afterwards without score persistence.
+//!
+//! The query patient is identified only by a hash of its ID and core
+//! demographics; names and dates never appear in the log. The hash is stable,
+//! so repeated queries for the same patient can be grouped.
+//!
+//! Sampling is deterministic per query and candidate: a pair that was logged
+//! once is logged every time it is scored at the same rate.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use super::{MatchResult, PatientMatcher};
+use crate::config::DecisionLogConfig;
+use crate::models::Patient;
+use crate::Result;
+
+/// Tracing target of match decision events
+pub const TARGET: &str = "mpi::match_decision";
+
+/// Matcher that logs every decision of the matcher it wraps
+pub struct DecisionLoggingMatcher {
+    inner: Arc<dyn PatientMatcher>,
+    matcher: String,
+    threshold: f64,
+    config: DecisionLogConfig,
+}
+
+impl DecisionLoggingMatcher {
+    /// Wrap `inner`, logging under the given matcher name and threshold
+    pub fn new(
+        inner: Arc<dyn PatientMatcher>,
+        matcher: impl Into<String>,
+        threshold: f64,
+        config: DecisionLogConfig,
+    ) -> Self {
+        Self {
+            inner,
+            matcher: matcher.into(),
+            threshold,
+            config,
+        }
+    }
+
+    /// Whether a decision is logged under the configured sampling
+    pub fn should_log(&self, query_hash: &str, candidate_id: &Uuid, score: f64, is_match: bool) -> bool {
+        let near_miss = !is_match && score >= self.threshold - self.config.near_miss_margin;
+        near_miss || sampled(query_hash, candidate_id, self.config.sample_rate)
+    }
+
+    fn score(&self, patient: &Patient, query_hash: &str, candidate: &Patient) -> Result<MatchResult> {
+        let started = Instant::now();
+        let result = self.inner.match_patients(patient, candidate)?;
+        let elapsed = started.elapsed();
+
+        let is_match = self.inner.is_match(result.score);
+        if self.should_log(query_hash, &candidate.id, result.score, is_match) {
+            tracing::info!(
+                target: TARGET,
+                query_hash,
+                candidate_id = %candidate.id,
+                score = result.score,
+                threshold = self.threshold,
+                decision = if is_match { "match" } else { "no_match" },
+                matcher = %self.matcher,
+                duration_us = elapsed.as_micros() as u64,
+                components = %result.breakdown.summary(),
+                "Match decision"
+            );
+        }
+        Ok(result)
+    }
+}
+
+impl PatientMatcher for DecisionLoggingMatcher {
+    fn match_patients(&self, patient: &Patient, candidate: &Patient) -> Result<MatchResult> {
+        self.score(patient, &query_hash(patient), candidate)
+    }
+
+    fn find_matches(&self, patient: &Patient, candidates: &[Patient]) -> Result<Vec<MatchResult>> {
+        let query_hash = query_hash(patient);
+        let mut matches = Vec::new();
+        for candidate in candidates {
+            crate::deadline::check()?;
+            let result = self.score(patient, &query_hash, candidate)?;
+            if self.inner.is_match(result.score) {
+                matches.push(result);
+            }
+        }
+
+        // Sort by score descending
+        matches.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        Ok(matches)
+    }
+
+    fn is_match(&self, score: f64) -> bool {
+        self.inner.is_match(score)
+    }
+}
+
+/// Short stable hash identifying a query patient without revealing it
+pub fn query_hash(patient: &Patient) -> String {
+    let name = patient.legal_name();
+    let data = format!(
+        "{}|{}|{}|{}|{:?}",
+        patient.id,
+        name.family.to_lowercase(),
+        name.given.join(" ").to_lowercase(),
+        patient.birth_date.map(|date| date.to_string()).unwrap_or_default(),
+        patient.gender,
+    );
+    Sha256::digest(data.as_bytes())[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn sampled(query_hash: &str, candidate_id: &Uuid, rate: f64) -> bool {
+    if rate >= 1.0 {
+        return true;
+    }
+    if rate <= 0.0 {
+        return false;
+    }
+    let mut hasher = Sha256::new();
+    hasher.update(query_hash.as_bytes());
+    hasher.update(candidate_id.as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hasher.finalize()[..8]);
+    (u64::from_le_bytes(bytes) as f64 / u64::MAX as f64) < rate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MatchingConfig;
+    use crate::matching::ProbabilisticMatcher;
+    use crate::models::Gender;
+    use chrono::NaiveDate;
+
+    fn patient(family: &str, given: &str) -> Patient {
+        let mut patient = crate::fixtures::patient(family, &[given], Gender::Female);
+        patient.birth_date = NaiveDate::from_ymd_opt(1975, 4, 9);
+        patient
+    }
+
+    fn logging_matcher(sample_rate: f64) -> DecisionLoggingMatcher {
+        let config = MatchingConfig {
+            threshold_score: 0.60,
+            exact_match_score: 1.0,
+            fuzzy_match_score: 0.8,
+            weights: Default::default(),
+            verification: Default::default(),
+            transliteration: Default::default(),
+            similarity: Default::default(),
+            blocking: Default::default(),
+            photo: Default::default(),
+        };
+        DecisionLoggingMatcher::new(
+            Arc::new(ProbabilisticMatcher::new(config)),
+            "probabilistic",
+            0.60,
+            DecisionLogConfig {
+                enabled: true,
+                sample_rate,
+                near_miss_margin: 0.1,
+            },
+        )
+    }
+
+    #[test]
+    fn test_find_matches_unchanged() {
+        let query = patient("Okafor", "Adaeze");
+        let candidates = vec![patient("Okafor", "Adaeze"), patient("Lindgren", "Maja")];
+
+        let matcher = logging_matcher(1.0);
+        let logged = matcher.find_matches(&query, &candidates).unwrap();
+        let plain = matcher.inner.find_matches(&query, &candidates).unwrap();
+
+        assert_eq!(logged.len(), plain.len());
+        for (logged, plain) in logged.iter().zip(&plain) {
+            assert_eq!(logged.patient.id, plain.patient.id);
+            assert_eq!(logged.score, plain.score);
+        }
+    }
+
+    #[test]
+    fn test_query_hash_hides_demographics() {
+        let query = patient("Okafor", "Adaeze");
+        let hash = query_hash(&query);
+
+        assert_eq!(hash.len(), 16);
+        assert_eq!(hash, query_hash(&query));
+        assert!(!hash.to_lowercase().contains("okafor"));
+
+        let mut renamed = query.clone();
+        renamed.name.family = "Okafor-Bello".to_string();
+        assert_ne!(query_hash(&renamed), hash);
+    }
+
+    #[test]
+    fn test_sampling() {
+        let id = Uuid::new_v4();
+
+        let none = logging_matcher(0.0);
+        assert!(!none.should_log("abc", &id, 0.95, true));
+        assert!(!none.should_log("abc", &id, 0.10, false));
+        // Near misses are always logged
+        assert!(none.should_log("abc", &id, 0.55, false));
+
+        let all = logging_matcher(1.0);
+        assert!(all.should_log("abc", &id, 0.10, false));
+
+        let ids: Vec<Uuid> = (0..2000).map(|_| Uuid::new_v4()).collect();
+        let some = logging_matcher(0.25);
+        let logged = ids.iter().filter(|id| some.should_log("abc", id, 0.95, true)).count();
+        assert!((300..700).contains(&logged), "logged {} of 2000", logged);
+        assert!(ids.iter().all(|id| some.should_log("abc", id, 0.95, true) == some.should_log("abc", id, 0.95, true)));
+    }
+}
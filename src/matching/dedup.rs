@@ -0,0 +1,221 @@
+//! Incremental duplicate detection
+//!
+//! Full-table scans find duplicates long after they were created. In
+//! incremental mode every `Created` and `Updated` event is handed to a
+//! background thread, which scores the changed record against its blocking
+//! neighbourhood (the same name and birth year search used by match
+//! requests) and queues pairs at or above the threshold for review within
+//! seconds of the change. Pairs already in the queue, reviewed or not, are
+//! left alone, and records already linked to each other are skipped.
+
+use std::sync::mpsc;
+use std::sync::Arc;
+
+use chrono::Datelike;
+use uuid::Uuid;
+
+use crate::db::{DuplicateCandidateRepository, PatientRepository};
+use crate::models::Patient;
+use crate::search::SearchBackend;
+use crate::streaming::{EventEnvelope, EventProducer, PatientEvent};
+use crate::Result;
+use super::PatientMatcher;
+
+/// Scores changed records against their neighbours and queues likely duplicates
+pub struct DuplicateDetector {
+    patients: Arc<dyn PatientRepository>,
+    search: Arc<dyn SearchBackend>,
+    matcher: Arc<dyn PatientMatcher>,
+    queue: Arc<dyn DuplicateCandidateRepository>,
+    neighborhood_size: usize,
+}
+
+impl DuplicateDetector {
+    /// Create a detector fetching up to `neighborhood_size` candidates per record
+    pub fn new(
+        patients: Arc<dyn PatientRepository>,
+        search: Arc<dyn SearchBackend>,
+        matcher: Arc<dyn PatientMatcher>,
+        queue: Arc<dyn DuplicateCandidateRepository>,
+        neighborhood_size: usize,
+    ) -> Self {
+        Self {
+            patients,
+            search,
+            matcher,
+            queue,
+            neighborhood_size,
+        }
+    }
+
+    /// Score `patient` against its blocking neighbourhood, returning how many
+    /// new pairs were queued
+    pub fn check(&self, patient: &Patient) -> Result<usize> {
+        let ids = self.search.search_by_name_and_year(
+            &patient.legal_name().family,
+            patient.birth_date.map(|d| d.year()),
+            self.neighborhood_size,
+        )?;
+
+        let mut neighbors = Vec::new();
+        for id in ids {
+            let Ok(id) = Uuid::parse_str(&id) else {
+                continue;
+            };
+            if id == patient.id || patient.links.iter().any(|link| link.other_patient_id == id) {
+                continue;
+            }
+            if let Some(neighbor) = self.patients.get_by_id(&id)? {
+                neighbors.push(neighbor);
+            }
+        }
+
+        let mut queued = 0;
+        for result in self.matcher.find_matches(patient, &neighbors)? {
+            if self.queue.enqueue(&patient.id, &result.patient.id, result.score)?.is_some() {
+                queued += 1;
+            }
+        }
+        Ok(queued)
+    }
+
+    /// Check every record received on `changes` on a background thread
+    ///
+    /// The thread stops once every sender has been dropped.
+    pub fn spawn(self, changes: mpsc::Receiver<Patient>) {
+        std::thread::Builder::new()
+            .name("incremental-dedup".to_string())
+            .spawn(move || {
+                for patient in changes {
+                    match self.check(&patient) {
+                        Ok(0) => {}
+                        Ok(queued) => tracing::info!("Queued {} possible duplicates of patient {}", queued, patient.id),
+                        Err(e) => tracing::warn!("Duplicate check of patient {} failed: {}", patient.id, e),
+                    }
+                }
+            })
+            .expect("failed to spawn incremental dedup thread");
+    }
+}
+
+/// Event producer that also hands created and updated records to a
+/// [`DuplicateDetector`]
+///
+/// Records are only queued here; detection never delays or fails the publish.
+pub struct DedupEventProducer {
+    inner: Arc<dyn EventProducer>,
+    changes: mpsc::Sender<Patient>,
+}
+
+impl DedupEventProducer {
+    /// Wrap a producer, returning the receiver to pass to [`DuplicateDetector::spawn`]
+    pub fn new(inner: Arc<dyn EventProducer>) -> (Self, mpsc::Receiver<Patient>) {
+        let (changes, received) = mpsc::channel();
+        (Self { inner, changes }, received)
+    }
+
+    fn enqueue(&self, event: &PatientEvent) {
+        if let PatientEvent::Created { patient, .. } | PatientEvent::Updated { patient, .. } = event {
+            if self.changes.send(patient.clone()).is_err() {
+                tracing::warn!("Incremental dedup thread has stopped; skipping patient {}", patient.id);
+            }
+        }
+    }
+}
+
+impl EventProducer for DedupEventProducer {
+    fn publish(&self, event: PatientEvent) -> Result<()> {
+        self.inner.publish(event.clone())?;
+        self.enqueue(&event);
+        Ok(())
+    }
+
+    fn publish_envelope(&self, envelope: EventEnvelope) -> Result<()> {
+        let event = envelope.event.clone();
+        self.inner.publish_envelope(envelope)?;
+        self.enqueue(&event);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::db::{InMemoryDuplicateCandidateRepository, InMemoryPatientRepository};
+    use crate::matching::ProbabilisticMatcher;
+    use crate::models::{Address, DuplicateCandidate, Gender, Identifier};
+    use crate::search::SearchEngine;
+    use crate::streaming::InMemoryEventPublisher;
+    use chrono::{NaiveDate, Utc};
+    use tempfile::TempDir;
+
+    fn patient(family: &str, given: &str, ssn: &str) -> Patient {
+        let mut patient = crate::fixtures::patient(family, &[given], Gender::Female);
+        patient.birth_date = NaiveDate::from_ymd_opt(1984, 3, 9);
+        patient.addresses.push(Address {
+            line1: Some("12 Marina Road".to_string()),
+            line2: None,
+            city: Some("Lagos".to_string()),
+            state: None,
+            postal_code: Some("101001".to_string()),
+            country: Some("NG".to_string()),
+            verification: Default::default(),
+            period: None,
+        });
+        patient.identifiers.push(Identifier::ssn(ssn.to_string()));
+        patient
+    }
+
+    #[test]
+    fn test_queues_new_pairs_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let search = Arc::new(SearchEngine::new(temp_dir.path()).unwrap());
+        let patients = Arc::new(InMemoryPatientRepository::new());
+        let queue = Arc::new(InMemoryDuplicateCandidateRepository::new());
+        let detector = DuplicateDetector::new(
+            patients.clone(),
+            search.clone(),
+            Arc::new(ProbabilisticMatcher::new(Config::default().matching)),
+            queue.clone(),
+            100,
+        );
+
+        let existing = patients.create(&patient("Adeyemi", "Folake", "123-45-6789")).unwrap();
+        let mut sibling = patient("Adeyemi", "Bisi", "234-56-7890");
+        sibling.gender = Gender::Male;
+        let sibling = patients.create(&sibling).unwrap();
+        search.index_patients(&[existing.clone(), sibling]).unwrap();
+        search.reload().unwrap();
+
+        let incoming = patients.create(&patient("Adeyemi", "Folake", "123-45-6789")).unwrap();
+        assert_eq!(detector.check(&incoming).unwrap(), 1);
+        search.index_patient(&incoming).unwrap();
+        search.reload().unwrap();
+        // Found again from the other side, the pair is not queued twice
+        assert_eq!(detector.check(&existing).unwrap(), 0);
+
+        let pending = queue.list_pending(10, 0).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(
+            (pending[0].patient_id, pending[0].candidate_id),
+            DuplicateCandidate::ordered(existing.id, incoming.id)
+        );
+    }
+
+    #[test]
+    fn test_forwards_created_and_updated_records() {
+        let (producer, changes) = DedupEventProducer::new(Arc::new(InMemoryEventPublisher::new()));
+        let created = patient("Adeyemi", "Folake", "123-45-6789");
+        producer
+            .publish(PatientEvent::Created { patient: created.clone(), timestamp: Utc::now() })
+            .unwrap();
+        producer
+            .publish(PatientEvent::Deleted { patient_id: created.id, timestamp: Utc::now() })
+            .unwrap();
+        drop(producer);
+
+        let received: Vec<_> = changes.iter().map(|p| p.id).collect();
+        assert_eq!(received, vec![created.id]);
+    }
+}
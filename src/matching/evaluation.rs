@@ -0,0 +1,491 @@
+//! Matching accuracy evaluation
+//!
+//! Scores a labeled set of record pairs with a matcher and reports precision,
+//! recall and F1 at each threshold, plus the ROC curve, so a configuration
+//! can be validated against known outcomes before it is deployed.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use serde::Serialize;
+
+use crate::models::{Address, Gender, HumanName, Identifier, IdentifierType, Patient, VerificationStatus};
+use crate::validation::{detect_date_order, parse_date, DateOrder};
+use crate::{Error, Result};
+use super::PatientMatcher;
+
+/// A pair of patient records with a known match outcome
+#[derive(Debug, Clone)]
+pub struct LabeledPair {
+    pub patient: Patient,
+    pub candidate: Patient,
+    /// Whether the two records belong to the same person
+    pub is_match: bool,
+}
+
+/// Confusion matrix and derived metrics at one threshold
+#[derive(Debug, Clone, Serialize)]
+pub struct ThresholdMetrics {
+    pub threshold: f64,
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub true_negatives: usize,
+    pub false_negatives: usize,
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+    pub false_positive_rate: f64,
+}
+
+impl ThresholdMetrics {
+    fn from_scores(threshold: f64, scored: &[(f64, bool)]) -> Self {
+        let (mut tp, mut fp, mut tn, mut fn_) = (0, 0, 0, 0);
+        for &(score, is_match) in scored {
+            match (score >= threshold, is_match) {
+                (true, true) => tp += 1,
+                (true, false) => fp += 1,
+                (false, false) => tn += 1,
+                (false, true) => fn_ += 1,
+            }
+        }
+
+        let precision = ratio(tp, tp + fp);
+        let recall = ratio(tp, tp + fn_);
+        let f1 = if precision + recall > 0.0 {
+            2.0 * precision * recall / (precision + recall)
+        } else {
+            0.0
+        };
+
+        Self {
+            threshold,
+            true_positives: tp,
+            false_positives: fp,
+            true_negatives: tn,
+            false_negatives: fn_,
+            precision,
+            recall,
+            f1,
+            false_positive_rate: ratio(fp, fp + tn),
+        }
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+/// Evaluation results across a range of thresholds
+#[derive(Debug, Clone, Serialize)]
+pub struct EvaluationReport {
+    pub pairs: usize,
+    pub matches: usize,
+    pub non_matches: usize,
+    /// Metrics at each evaluated threshold, in ascending threshold order
+    pub thresholds: Vec<ThresholdMetrics>,
+    /// Area under the ROC curve
+    pub roc_auc: f64,
+}
+
+impl EvaluationReport {
+    /// Threshold with the highest F1 score
+    pub fn best_f1(&self) -> Option<&ThresholdMetrics> {
+        self.thresholds
+            .iter()
+            .max_by(|a, b| a.f1.partial_cmp(&b.f1).unwrap_or(std::cmp::Ordering::Equal))
+    }
+}
+
+/// Default thresholds: 0.00 to 1.00 in steps of 0.05
+pub fn default_thresholds() -> Vec<f64> {
+    (0..=20).map(|i| i as f64 * 0.05).collect()
+}
+
+/// Score every pair with the matcher and compute metrics at each threshold
+pub fn evaluate(
+    matcher: &dyn PatientMatcher,
+    pairs: &[LabeledPair],
+    thresholds: &[f64],
+) -> Result<EvaluationReport> {
+    let scored = pairs
+        .iter()
+        .map(|pair| {
+            matcher
+                .match_patients(&pair.patient, &pair.candidate)
+                .map(|result| (result.score, pair.is_match))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut thresholds = thresholds.to_vec();
+    thresholds.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let metrics: Vec<ThresholdMetrics> = thresholds
+        .iter()
+        .map(|&t| ThresholdMetrics::from_scores(t, &scored))
+        .collect();
+
+    let matches = scored.iter().filter(|(_, m)| *m).count();
+
+    Ok(EvaluationReport {
+        pairs: scored.len(),
+        matches,
+        non_matches: scored.len() - matches,
+        roc_auc: roc_auc(&metrics),
+        thresholds: metrics,
+    })
+}
+
+/// Trapezoidal area under the ROC curve, anchored at (0,0) and (1,1)
+fn roc_auc(metrics: &[ThresholdMetrics]) -> f64 {
+    let mut points: Vec<(f64, f64)> = metrics
+        .iter()
+        .map(|m| (m.false_positive_rate, m.recall))
+        .collect();
+    points.push((0.0, 0.0));
+    points.push((1.0, 1.0));
+    points.sort_by(|a, b| {
+        a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    points
+        .windows(2)
+        .map(|w| (w[1].0 - w[0].0) * (w[1].1 + w[0].1) / 2.0)
+        .sum()
+}
+
+/// Read labeled pairs from CSV
+///
+/// The first row is a header. `label` is required and accepts `1`/`0`,
+/// `true`/`false` or `match`/`non-match`. Each record uses the columns
+/// `family`, `given` (space separated), `birth_date`, `gender`, `line1`,
+/// `city`, `state`, `postal_code`, `country` and `mrn`, prefixed with `a_`
+/// or `b_`. Only `a_family` and `b_family` are required.
+///
+/// Birth dates may be ISO or year-last (`DD/MM/YYYY` or `MM/DD/YYYY`). When
+/// `date_order` is `None` the order is inferred from the file, and a file
+/// whose year-last dates could be read either way is rejected.
+pub fn read_pairs_csv<R: BufRead>(reader: R, date_order: Option<DateOrder>) -> Result<Vec<LabeledPair>> {
+    let mut lines = reader.lines();
+    let header = match lines.next() {
+        Some(line) => line.map_err(|e| Error::Validation(format!("Failed to read CSV: {}", e)))?,
+        None => return Ok(Vec::new()),
+    };
+    let columns: HashMap<String, usize> = split_csv_line(&header)
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| (name.trim().to_lowercase(), i))
+        .collect();
+
+    for required in ["label", "a_family", "b_family"] {
+        if !columns.contains_key(required) {
+            return Err(Error::Validation(format!("CSV is missing column '{}'", required)));
+        }
+    }
+
+    let mut rows = Vec::new();
+    for (line_number, line) in lines.enumerate() {
+        let line = line.map_err(|e| Error::Validation(format!("Failed to read CSV: {}", e)))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        rows.push((line_number + 2, split_csv_line(&line)));
+    }
+
+    let date_order = date_order.or_else(|| {
+        let birth_dates: Vec<usize> = ["a_birth_date", "b_birth_date"]
+            .iter()
+            .filter_map(|c| columns.get(*c).copied())
+            .collect();
+        detect_date_order(rows.iter().flat_map(|(_, fields)| {
+            birth_dates.iter().filter_map(|&i| fields.get(i).map(String::as_str))
+        }))
+    });
+
+    rows.into_iter()
+        .map(|(line, fields)| {
+            let row = CsvRow { columns: &columns, fields, line, date_order };
+            Ok(LabeledPair {
+                patient: row.patient("a")?,
+                candidate: row.patient("b")?,
+                is_match: row.label()?,
+            })
+        })
+        .collect()
+}
+
+struct CsvRow<'a> {
+    columns: &'a HashMap<String, usize>,
+    fields: Vec<String>,
+    line: usize,
+    date_order: Option<DateOrder>,
+}
+
+impl CsvRow<'_> {
+    fn get(&self, column: &str) -> Option<&str> {
+        self.columns
+            .get(column)
+            .and_then(|&i| self.fields.get(i))
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+    }
+
+    fn label(&self) -> Result<bool> {
+        match self.get("label").map(|v| v.to_lowercase()).as_deref() {
+            Some("1" | "true" | "match") => Ok(true),
+            Some("0" | "false" | "non-match" | "nonmatch") => Ok(false),
+            other => Err(Error::Validation(format!(
+                "Line {}: invalid label {:?}", self.line, other
+            ))),
+        }
+    }
+
+    fn patient(&self, prefix: &str) -> Result<Patient> {
+        let col = |name: &str| format!("{}_{}", prefix, name);
+
+        let gender = match self.get(&col("gender")).map(|v| v.to_lowercase()).as_deref() {
+            Some("male" | "m") => Gender::Male,
+            Some("female" | "f") => Gender::Female,
+            Some("other" | "o") => Gender::Other,
+            _ => Gender::Unknown,
+        };
+
+        let mut patient = Patient::new(
+            HumanName {
+                use_type: None,
+                family: self.get(&col("family")).unwrap_or_default().to_string(),
+                given: self
+                    .get(&col("given"))
+                    .map(|v| v.split_whitespace().map(String::from).collect())
+                    .unwrap_or_default(),
+                prefix: vec![],
+                suffix: vec![],
+            },
+            gender,
+        );
+
+        if let Some(dob) = self.get(&col("birth_date")) {
+            let parsed = parse_date(dob, self.date_order).map_err(|e| {
+                Error::Validation(format!("Line {}: invalid {}: {}", self.line, col("birth_date"), e))
+            })?;
+            patient.birth_date = Some(parsed.date);
+        }
+
+        let line1 = self.get(&col("line1"));
+        let city = self.get(&col("city"));
+        let state = self.get(&col("state"));
+        let postal_code = self.get(&col("postal_code"));
+        let country = self.get(&col("country"));
+        if line1.is_some() || city.is_some() || state.is_some() || postal_code.is_some() {
+            patient.addresses.push(Address {
+                line1: line1.map(String::from),
+                line2: None,
+                city: city.map(String::from),
+                state: state.map(String::from),
+                postal_code: postal_code.map(String::from),
+                country: country.map(String::from),
+                verification: VerificationStatus::Unverified,
+                period: None,
+            });
+        }
+
+        if let Some(mrn) = self.get(&col("mrn")) {
+            patient.identifiers.push(Identifier::mrn("EVAL".to_string(), mrn.to_string()));
+        }
+
+        Ok(patient)
+    }
+}
+
+/// Columns written for each record of a pair, after its `a_` or `b_` prefix
+const RECORD_COLUMNS: [&str; 10] = [
+    "family", "given", "birth_date", "gender", "line1", "city", "state", "postal_code", "country", "mrn",
+];
+
+/// Write labeled pairs as CSV that [`read_pairs_csv`] reads back
+///
+/// Each record contributes its primary name, first address and first MRN.
+pub fn write_pairs_csv<W: Write>(mut writer: W, pairs: &[LabeledPair]) -> Result<()> {
+    let write_error = |e: std::io::Error| Error::Internal(format!("Failed to write CSV: {}", e));
+
+    let mut header = vec!["label".to_string()];
+    for prefix in ["a", "b"] {
+        header.extend(RECORD_COLUMNS.iter().map(|column| format!("{}_{}", prefix, column)));
+    }
+    writeln!(writer, "{}", header.join(",")).map_err(write_error)?;
+
+    for pair in pairs {
+        let mut fields = vec![if pair.is_match { "1" } else { "0" }.to_string()];
+        fields.extend(record_fields(&pair.patient));
+        fields.extend(record_fields(&pair.candidate));
+        let line: Vec<String> = fields.iter().map(|field| quote_csv_field(field)).collect();
+        writeln!(writer, "{}", line.join(",")).map_err(write_error)?;
+    }
+    Ok(())
+}
+
+/// A record's values in the order of [`RECORD_COLUMNS`]
+fn record_fields(patient: &Patient) -> Vec<String> {
+    let address = patient.addresses.first();
+    let address_field = |field: fn(&Address) -> &Option<String>| {
+        address.and_then(|a| field(a).clone()).unwrap_or_default()
+    };
+    let gender = match patient.gender {
+        Gender::Male => "male",
+        Gender::Female => "female",
+        Gender::Other => "other",
+        Gender::Unknown => "",
+    };
+    let mrn = patient
+        .identifiers
+        .iter()
+        .find(|identifier| matches!(identifier.identifier_type, IdentifierType::MRN))
+        .map(|identifier| identifier.value.clone())
+        .unwrap_or_default();
+
+    vec![
+        patient.name.family.clone(),
+        patient.name.given.join(" "),
+        patient.birth_date.map(|date| date.to_string()).unwrap_or_default(),
+        gender.to_string(),
+        address_field(|a| &a.line1),
+        address_field(|a| &a.city),
+        address_field(|a| &a.state),
+        address_field(|a| &a.postal_code),
+        address_field(|a| &a.country),
+        mrn,
+    ]
+}
+
+/// Quote a CSV field if it holds a separator, quote or line break
+fn quote_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Split a CSV line, honouring double-quoted fields
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::config::MatchingConfig;
+    use crate::matching::ProbabilisticMatcher;
+
+    const CSV: &str = "\
+label,a_family,a_given,a_birth_date,a_gender,b_family,b_given,b_birth_date,b_gender
+1,Smith,John,1980-01-15,male,Smith,John,1980-01-15,male
+1,Johnson,Mary,1975-03-12,female,Jonson,Mary,1975-12-03,female
+0,Smith,John,1980-01-15,male,Garcia,Maria,1992-07-04,female
+0,\"Lee\",Anna,1960-05-05,female,Wright,Tom,2001-11-30,male
+";
+
+    #[test]
+    fn test_read_pairs_csv() {
+        let pairs = read_pairs_csv(CSV.as_bytes(), None).unwrap();
+
+        assert_eq!(pairs.len(), 4);
+        assert!(pairs[0].is_match);
+        assert!(!pairs[2].is_match);
+        assert_eq!(pairs[3].patient.name.family, "Lee");
+        assert_eq!(pairs[1].candidate.birth_date, NaiveDate::from_ymd_opt(1975, 12, 3));
+    }
+
+    #[test]
+    fn test_read_pairs_csv_day_first_dates() {
+        let csv = "\
+label,a_family,a_birth_date,b_family,b_birth_date
+1,Smith,03/04/1980,Smith,03/04/1980
+1,Jones,25/12/1975,Jones,24/12/1975
+";
+        let pairs = read_pairs_csv(csv.as_bytes(), None).unwrap();
+        assert_eq!(pairs[0].patient.birth_date, NaiveDate::from_ymd_opt(1980, 4, 3));
+
+        // Without a day over 12 the file cannot be read without a configured order
+        let ambiguous = "label,a_family,a_birth_date,b_family\n1,Smith,03/04/1980,Smith\n";
+        assert!(read_pairs_csv(ambiguous.as_bytes(), None).is_err());
+        let pairs = read_pairs_csv(ambiguous.as_bytes(), Some(DateOrder::Mdy)).unwrap();
+        assert_eq!(pairs[0].patient.birth_date, NaiveDate::from_ymd_opt(1980, 3, 4));
+    }
+
+    #[test]
+    fn test_write_pairs_csv_round_trip() {
+        let mut pairs = read_pairs_csv(CSV.as_bytes(), None).unwrap();
+        pairs[0].patient.name.family = "O'Brien, Jr".to_string();
+        pairs[0].patient.identifiers.push(Identifier::mrn("EVAL".to_string(), "A\"17".to_string()));
+
+        let mut csv = Vec::new();
+        write_pairs_csv(&mut csv, &pairs).unwrap();
+        let read = read_pairs_csv(csv.as_slice(), None).unwrap();
+
+        assert_eq!(read.len(), pairs.len());
+        assert_eq!(read[0].patient.name.family, "O'Brien, Jr");
+        assert_eq!(read[0].patient.identifiers[0].value, "A\"17");
+        assert_eq!(read[1].candidate.birth_date, pairs[1].candidate.birth_date);
+        assert_eq!(read[2].candidate.gender, Gender::Female);
+        assert!(read[0].is_match && !read[3].is_match);
+    }
+
+    #[test]
+    fn test_read_pairs_csv_missing_column() {
+        let result = read_pairs_csv("a_family,b_family\nSmith,Smith\n".as_bytes(), None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let matcher = ProbabilisticMatcher::new(MatchingConfig {
+            threshold_score: 0.7,
+            exact_match_score: 1.0,
+            fuzzy_match_score: 0.8,
+            weights: Default::default(),
+            verification: Default::default(),
+            transliteration: Default::default(),
+            similarity: Default::default(),
+            blocking: Default::default(),
+            photo: Default::default(),
+        });
+        let pairs = read_pairs_csv(CSV.as_bytes(), None).unwrap();
+
+        let report = evaluate(&matcher, &pairs, &default_thresholds()).unwrap();
+
+        assert_eq!(report.pairs, 4);
+        assert_eq!(report.matches, 2);
+        assert_eq!(report.thresholds.len(), 21);
+
+        // Everything is a predicted match at zero
+        let zero = &report.thresholds[0];
+        assert_eq!(zero.recall, 1.0);
+        assert_eq!(zero.false_positive_rate, 1.0);
+
+        // The matches are cleanly separable from the non-matches
+        let best = report.best_f1().unwrap();
+        assert_eq!(best.f1, 1.0);
+        assert_eq!(report.roc_auc, 1.0);
+    }
+}
@@ -0,0 +1,111 @@
+//! Exact-duplicate fingerprints
+//!
+//! Interface engines resend registrations after timeouts and restarts, and
+//! import files are loaded twice. Such a resubmission is the same record
+//! byte for byte, give or take case, punctuation and spacing, and needs no
+//! scoring to recognize. Each stored patient carries a fingerprint of its
+//! normalized legal name, birth date, sex and SSN last four, indexed in
+//! `patients.fingerprint`, so ingestion finds the earlier copy with one
+//! lookup before any fuzzy matching runs.
+
+use sha2::{Digest, Sha256};
+
+use crate::db::PatientRepository;
+use crate::models::{IdentifierType, Patient};
+use crate::Result;
+
+/// Prefix of the hashed data, bumped whenever normalization changes
+const FINGERPRINT_VERSION: &str = "v1";
+
+/// Fingerprint of a patient's identifying fields, as 64 hex digits
+///
+/// Patients without a family name or birth date have none: too many
+/// different people would share it.
+pub fn record_fingerprint(patient: &Patient) -> Option<String> {
+    let name = patient.legal_name();
+    let family = normalize(&name.family);
+    let birth_date = patient.birth_date?;
+    if family.is_empty() {
+        return None;
+    }
+    let given: Vec<String> = name.given.iter().map(|g| normalize(g)).filter(|g| !g.is_empty()).collect();
+    let ssn_last4 = patient
+        .identifiers
+        .iter()
+        .find(|i| i.identifier_type == IdentifierType::SSN)
+        .map(|i| {
+            let digits: Vec<char> = i.value.chars().filter(char::is_ascii_digit).collect();
+            digits[digits.len().saturating_sub(4)..].iter().collect::<String>()
+        })
+        .unwrap_or_default();
+
+    let data = format!(
+        "{}|{}|{}|{}|{:?}|{}",
+        FINGERPRINT_VERSION,
+        family,
+        given.join(" "),
+        birth_date,
+        patient.gender,
+        ssn_last4
+    );
+    Some(Sha256::digest(data.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// A stored patient `patient` is an exact resubmission of, if any
+pub fn find_resubmitted(patient: &Patient, patients: &dyn PatientRepository) -> Result<Option<Patient>> {
+    let Some(fingerprint) = record_fingerprint(patient) else {
+        return Ok(None);
+    };
+    for id in patients.find_by_fingerprint(&fingerprint)? {
+        if id == patient.id {
+            continue;
+        }
+        if let Some(existing) = patients.get_by_id_for_update(&id)? {
+            return Ok(Some(existing));
+        }
+    }
+    Ok(None)
+}
+
+/// Lowercase letters and digits only
+fn normalize(value: &str) -> String {
+    value.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::InMemoryPatientRepository;
+    use crate::models::{Gender, Identifier};
+    use chrono::NaiveDate;
+
+    fn patient(family: &str, given: &str, ssn: &str) -> Patient {
+        let mut patient = crate::fixtures::patient(family, &[given], Gender::Female);
+        patient.birth_date = NaiveDate::from_ymd_opt(1958, 6, 21);
+        patient.identifiers.push(Identifier::ssn(ssn.to_string()));
+        patient
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_case_punctuation_and_ssn_prefix() {
+        let original = record_fingerprint(&patient("O'Brien", "Mary-Kate", "123-45-6789")).unwrap();
+        assert_eq!(original.len(), 64);
+        assert_eq!(record_fingerprint(&patient("OBRIEN", "mary kate", "999-99-6789")).unwrap(), original);
+        assert_ne!(record_fingerprint(&patient("O'Brien", "Mary-Kate", "123-45-6780")).unwrap(), original);
+
+        let mut undated = patient("O'Brien", "Mary-Kate", "123-45-6789");
+        undated.birth_date = None;
+        assert_eq!(record_fingerprint(&undated), None);
+    }
+
+    #[test]
+    fn test_find_resubmitted() {
+        let patients = InMemoryPatientRepository::new();
+        let stored = patients.create(&patient("Haddad", "Rania", "234-56-7890")).unwrap();
+
+        let resent = patient("HADDAD", "Rania", "234-56-7890");
+        assert_eq!(find_resubmitted(&resent, &patients).unwrap().unwrap().id, stored.id);
+        assert!(find_resubmitted(&stored, &patients).unwrap().is_none());
+        assert!(find_resubmitted(&patient("Haddad", "Rana", "234-56-7890"), &patients).unwrap().is_none());
+    }
+}
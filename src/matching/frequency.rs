@@ -0,0 +1,153 @@
+//! Family name frequencies
+//!
+//! Agreeing on a family name shared by a tenth of the population says little
+//! about whether two records are the same person; agreeing on a rare one says
+//! a lot. [`NameFrequencies`] holds how often each family name occurs among
+//! stored patients, refreshed by [`crate::jobs::NameFrequencyJob`], and turns
+//! it into a factor the probabilistic scorer scales the name weight by.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use sha2::{Digest, Sha256};
+
+use crate::config::NameFrequencyConfig;
+
+#[derive(Default)]
+struct FrequencyTable {
+    /// Patients counted
+    total: i64,
+    /// Patients by lowercased family name
+    family: HashMap<String, i64>,
+    /// Short hash of the counts, empty before the first count
+    digest: String,
+}
+
+/// Family name frequencies among stored patients
+pub struct NameFrequencies {
+    config: NameFrequencyConfig,
+    table: RwLock<FrequencyTable>,
+}
+
+impl NameFrequencies {
+    /// An empty table, which leaves name weights unchanged until filled
+    pub fn new(config: NameFrequencyConfig) -> Self {
+        Self {
+            config,
+            table: RwLock::new(FrequencyTable::default()),
+        }
+    }
+
+    /// Replace the table with counts of `total` patients by family name
+    pub fn replace(&self, total: i64, counts: impl IntoIterator<Item = (String, i64)>) {
+        let mut family: HashMap<String, i64> = HashMap::new();
+        for (name, count) in counts {
+            *family.entry(normalize(&name)).or_default() += count;
+        }
+        let digest = digest(total, &family);
+        *self.table.write().unwrap_or_else(|e| e.into_inner()) = FrequencyTable { total, family, digest };
+    }
+
+    /// Settings the factors follow
+    pub fn config(&self) -> &NameFrequencyConfig {
+        &self.config
+    }
+
+    /// Short hash of the counts in effect, which changes whenever a recount
+    /// changes them and is the same on every instance holding the same counts
+    pub fn digest(&self) -> String {
+        self.table.read().unwrap_or_else(|e| e.into_inner()).digest.clone()
+    }
+
+    /// Distinct family names counted
+    pub fn len(&self) -> usize {
+        self.table.read().unwrap_or_else(|e| e.into_inner()).family.len()
+    }
+
+    /// Whether no names have been counted yet
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Share of patients with this family name, if the name has been counted
+    /// in a population of at least `min_population`
+    pub fn frequency(&self, family: &str) -> Option<f64> {
+        let table = self.table.read().unwrap_or_else(|e| e.into_inner());
+        if table.total <= 0 || table.total < self.config.min_population {
+            return None;
+        }
+        let count = *table.family.get(&normalize(family))?;
+        Some(count as f64 / table.total as f64)
+    }
+
+    /// Factor to scale the name weight by when comparing these family names
+    ///
+    /// The more common of the two names decides. A name as rare as
+    /// `reference_frequency` or rarer keeps the full weight; more common
+    /// names lose weight with the log of their frequency, down to
+    /// `min_factor`. Unknown names keep the full weight.
+    pub fn factor(&self, family1: &str, family2: &str) -> f64 {
+        let frequency = match (self.frequency(family1), self.frequency(family2)) {
+            (Some(f1), Some(f2)) => f64::max(f1, f2),
+            (Some(f), None) | (None, Some(f)) => f,
+            (None, None) => return 1.0,
+        };
+        let reference = self.config.reference_frequency.clamp(f64::MIN_POSITIVE, 0.5);
+        if frequency <= reference {
+            return 1.0;
+        }
+        let factor = frequency.ln() / reference.ln();
+        factor.clamp(self.config.min_factor.clamp(0.0, 1.0), 1.0)
+    }
+}
+
+fn normalize(family: &str) -> String {
+    family.trim().to_lowercase()
+}
+
+fn digest(total: i64, family: &HashMap<String, i64>) -> String {
+    let mut names: Vec<(&String, &i64)> = family.iter().collect();
+    names.sort();
+    let mut hasher = Sha256::new();
+    hasher.update(total.to_le_bytes());
+    for (name, count) in names {
+        hasher.update(name.as_bytes());
+        hasher.update([0]);
+        hasher.update(count.to_le_bytes());
+    }
+    hasher.finalize()[..8].iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_common_names_weigh_less() {
+        let frequencies = NameFrequencies::new(NameFrequencyConfig {
+            min_population: 100,
+            ..Default::default()
+        });
+        assert_eq!(frequencies.factor("Nguyen", "Nguyen"), 1.0);
+
+        frequencies.replace(
+            10_000,
+            vec![
+                ("Nguyen".to_string(), 1_000),
+                ("Zyskowski".to_string(), 1),
+                ("Smith".to_string(), 100),
+            ],
+        );
+        assert_eq!(frequencies.len(), 3);
+
+        let nguyen = frequencies.factor("NGUYEN", "Nguyen");
+        let smith = frequencies.factor("Smith", "Smith");
+        assert!(nguyen < smith && smith < 1.0, "{} {}", nguyen, smith);
+        assert_eq!(nguyen, 0.5);
+        assert_eq!(frequencies.factor("Zyskowski", "Zyskowski"), 1.0);
+        assert_eq!(frequencies.factor("Okonkwo", "Okonkwo"), 1.0);
+
+        frequencies.replace(50, vec![("Nguyen".to_string(), 5)]);
+        assert_eq!(frequencies.factor("Nguyen", "Nguyen"), 1.0);
+    }
+}
@@ -0,0 +1,302 @@
+//! Checks that a merge keeps the link graph consistent
+//!
+//! A merge retires the source record and points it at the surviving target
+//! with a `replaced-by` link. Merging a record that is already replaced, or
+//! into one that is, leaves chains that nothing follows; merging inactive
+//! records revives or buries the wrong one; and merging two records whose
+//! linked records do not match joins two people into one cluster. The
+//! [`MergeGuard`] finds all of these before anything is written and reports
+//! them together as a [`MergeConflict`].
+
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::config::ClusteringConfig;
+use crate::db::PatientRepository;
+use crate::models::{LinkType, Patient};
+use crate::{Error, Result};
+use super::clustering::same_person;
+use super::PatientMatcher;
+
+/// A condition that stops a merge
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+#[serde(tag = "condition", rename_all = "snake_case")]
+pub enum MergeBlocker {
+    /// The source and target are the same record
+    SameRecord,
+    /// The record is inactive, such as one already merged away
+    Inactive { patient_id: Uuid },
+    /// The record already has a `replaced-by` link
+    AlreadyReplaced { patient_id: Uuid, replaced_by: Uuid },
+    /// A record linked to one side does not match a record linked to the other
+    ConflictingClusters {
+        patient_id: Uuid,
+        other_patient_id: Uuid,
+        /// Score of the pair under the current configuration
+        score: f64,
+    },
+    /// The merged cluster would exceed `clustering.max_cluster_size`, so it
+    /// could not be validated
+    ClusterTooLarge { members: usize },
+}
+
+impl fmt::Display for MergeBlocker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeBlocker::SameRecord => write!(f, "a record cannot be merged into itself"),
+            MergeBlocker::Inactive { patient_id } => write!(f, "patient {} is inactive", patient_id),
+            MergeBlocker::AlreadyReplaced { patient_id, replaced_by } => {
+                write!(f, "patient {} is already replaced by {}", patient_id, replaced_by)
+            }
+            MergeBlocker::ConflictingClusters { patient_id, other_patient_id, score } => write!(
+                f,
+                "linked patients {} and {} do not match (score {:.3})",
+                patient_id, other_patient_id, score
+            ),
+            MergeBlocker::ClusterTooLarge { members } => {
+                write!(f, "the merged cluster of {} patients is too large to validate", members)
+            }
+        }
+    }
+}
+
+/// A merge refused because of the listed conditions
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct MergeConflict {
+    /// Record that would have been retired
+    pub source_id: Uuid,
+    /// Record that would have survived
+    pub target_id: Uuid,
+    /// Every condition found, not only the first
+    pub blockers: Vec<MergeBlocker>,
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "merge of {} into {} is blocked: ", self.source_id, self.target_id)?;
+        for (i, blocker) in self.blockers.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}", blocker)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks a merge before it is applied
+pub struct MergeGuard<'a> {
+    patients: &'a dyn PatientRepository,
+    clusters: Option<(&'a dyn PatientMatcher, ClusteringConfig)>,
+}
+
+impl<'a> MergeGuard<'a> {
+    /// Guard checking the records themselves
+    pub fn new(patients: &'a dyn PatientRepository) -> Self {
+        Self { patients, clusters: None }
+    }
+
+    /// Also score the records linked to each side against each other, as
+    /// the clustering job validates clusters
+    pub fn with_cluster_check(mut self, matcher: &'a dyn PatientMatcher, config: ClusteringConfig) -> Self {
+        self.clusters = Some((matcher, config));
+        self
+    }
+
+    /// Fail with [`Error::MergeConflict`] if `source` may not be merged into `target`
+    ///
+    /// Clusters are only compared once both records pass the cheaper checks.
+    pub fn check(&self, source: &Patient, target: &Patient) -> Result<()> {
+        let mut blockers = Vec::new();
+        if source.id == target.id {
+            blockers.push(MergeBlocker::SameRecord);
+        } else {
+            for patient in [source, target] {
+                if !patient.active {
+                    blockers.push(MergeBlocker::Inactive { patient_id: patient.id });
+                }
+                if let Some(link) = patient.links.iter().find(|link| matches!(link.link_type, LinkType::ReplacedBy)) {
+                    blockers.push(MergeBlocker::AlreadyReplaced {
+                        patient_id: patient.id,
+                        replaced_by: link.other_patient_id,
+                    });
+                }
+            }
+            if blockers.is_empty() {
+                if let Some((matcher, config)) = &self.clusters {
+                    blockers = self.cluster_blockers(source, target, *matcher, config)?;
+                }
+            }
+        }
+
+        if blockers.is_empty() {
+            return Ok(());
+        }
+        Err(Error::MergeConflict(MergeConflict {
+            source_id: source.id,
+            target_id: target.id,
+            blockers,
+        }))
+    }
+
+    /// Pairs across the two clusters that do not match
+    ///
+    /// The pair being merged is not scored: whoever asked for the merge
+    /// has already judged it.
+    fn cluster_blockers(
+        &self,
+        source: &Patient,
+        target: &Patient,
+        matcher: &dyn PatientMatcher,
+        config: &ClusteringConfig,
+    ) -> Result<Vec<MergeBlocker>> {
+        let source_cluster = self.cluster(source, config.max_cluster_size)?;
+        if source_cluster.iter().any(|member| member.id == target.id) {
+            // Already one person; the merge only tidies up
+            return Ok(Vec::new());
+        }
+        let target_cluster = self.cluster(target, config.max_cluster_size)?;
+        let members = source_cluster.len() + target_cluster.len();
+        if members > config.max_cluster_size {
+            return Ok(vec![MergeBlocker::ClusterTooLarge { members }]);
+        }
+
+        let mut blockers = Vec::new();
+        for patient in &source_cluster {
+            for other in &target_cluster {
+                if patient.id == source.id && other.id == target.id {
+                    continue;
+                }
+                crate::deadline::check()?;
+                let score = matcher.match_patients(patient, other)?.score;
+                let matches = match config.min_pair_score {
+                    Some(min) => score >= min,
+                    None => matcher.is_match(score),
+                };
+                if !matches {
+                    blockers.push(MergeBlocker::ConflictingClusters {
+                        patient_id: patient.id,
+                        other_patient_id: other.id,
+                        score,
+                    });
+                }
+            }
+        }
+        Ok(blockers)
+    }
+
+    /// Active records joined to `patient` by same-person links in either
+    /// direction, `patient` first; stops growing past `limit`
+    fn cluster(&self, patient: &Patient, limit: usize) -> Result<Vec<Patient>> {
+        let linked = |from: &Patient, to: &Patient| {
+            from.links
+                .iter()
+                .any(|link| link.other_patient_id == to.id && same_person(&link.link_type))
+        };
+
+        let mut members = vec![patient.clone()];
+        let mut seen: HashSet<Uuid> = HashSet::from([patient.id]);
+        let mut next = 0;
+        while next < members.len() && members.len() <= limit {
+            let current = members[next].clone();
+            next += 1;
+
+            let mut neighbours: Vec<Uuid> = current.links.iter().map(|link| link.other_patient_id).collect();
+            neighbours.extend(self.patients.find_linked_to(&current.id)?);
+            for id in neighbours {
+                if seen.contains(&id) {
+                    continue;
+                }
+                let Some(other) = self.patients.get_by_id_for_update(&id)? else {
+                    continue;
+                };
+                if other.active && (linked(&current, &other) || linked(&other, &current)) {
+                    seen.insert(id);
+                    members.push(other);
+                }
+            }
+        }
+        Ok(members)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::db::InMemoryPatientRepository;
+    use crate::matching::ProbabilisticMatcher;
+    use crate::models::{Gender, PatientLink};
+    use chrono::NaiveDate;
+
+    fn patient(family: &str, given: &str, year: i32) -> Patient {
+        let mut patient = crate::fixtures::patient(family, &[given], Gender::Male);
+        patient.birth_date = NaiveDate::from_ymd_opt(year, 6, 1);
+        patient
+    }
+
+    fn blockers(result: Result<()>) -> Vec<MergeBlocker> {
+        match result {
+            Err(Error::MergeConflict(conflict)) => conflict.blockers,
+            other => panic!("expected a merge conflict, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_lists_every_blocking_condition() {
+        let patients = InMemoryPatientRepository::new();
+        let guard = MergeGuard::new(&patients);
+        let survivor = patient("Lindqvist", "Erik", 1970);
+        let mut retired = patient("Lindqvist", "Erik", 1970);
+        retired.active = false;
+        retired.links.push(PatientLink {
+            other_patient_id: survivor.id,
+            link_type: LinkType::ReplacedBy,
+        });
+
+        assert!(guard.check(&patient("Lindqvist", "Erik", 1970), &survivor).is_ok());
+        assert_eq!(blockers(guard.check(&survivor, &survivor)), vec![MergeBlocker::SameRecord]);
+        assert_eq!(
+            blockers(guard.check(&retired, &survivor)),
+            vec![
+                MergeBlocker::Inactive { patient_id: retired.id },
+                MergeBlocker::AlreadyReplaced { patient_id: retired.id, replaced_by: survivor.id },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_blocks_merging_clusters_of_different_people() {
+        let patients = InMemoryPatientRepository::new();
+        let source = patient("Lindqvist", "Erik", 1970);
+        let target = patient("Lindqvist", "Erik", 1970);
+        // A stranger already linked to the target, through a link on the stranger
+        let mut stranger = patient("Okafor", "Chidi", 1991);
+        stranger.links.push(PatientLink {
+            other_patient_id: target.id,
+            link_type: LinkType::Refer,
+        });
+        for p in [&source, &target, &stranger] {
+            patients.create(p).unwrap();
+        }
+
+        let matcher = ProbabilisticMatcher::new(Config::default().matching);
+        let config = ClusteringConfig {
+            min_pair_score: Some(0.6),
+            ..ClusteringConfig::default()
+        };
+        let guard = MergeGuard::new(&patients).with_cluster_check(&matcher, config);
+
+        let found = blockers(guard.check(&source, &target));
+        assert_eq!(found.len(), 1);
+        assert!(matches!(
+            found[0],
+            MergeBlocker::ConflictingClusters { patient_id, other_patient_id, .. }
+                if patient_id == source.id && other_patient_id == stranger.id
+        ));
+    }
+}
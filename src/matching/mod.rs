@@ -6,8 +6,38 @@ use crate::Result;
 
 pub mod algorithms;
 pub mod scoring;
+pub mod relinkage;
+pub mod evaluation;
+pub mod transliteration;
+pub mod decision_log;
+pub mod reloadable;
+pub mod prefilter;
+pub mod cache;
+pub mod dedup;
+pub mod clustering;
+pub mod merge;
+pub mod practitioner;
+pub mod batch;
+pub mod fingerprint;
+pub mod photo;
+pub mod similarity;
+pub mod frequency;
 
 pub use scoring::{ProbabilisticScorer, DeterministicScorer, MatchQuality};
+pub use relinkage::{RelinkageJob, RelinkageReport, RelinkagePair};
+pub use decision_log::DecisionLoggingMatcher;
+pub use reloadable::ReloadableMatcher;
+pub use prefilter::{identifier_candidates, settled_by_identifiers};
+pub use cache::{CachingMatcher, PairScoreCache};
+pub use dedup::{DedupEventProducer, DuplicateDetector};
+pub use clustering::{ClusterConflict, ClusterReport, ClusteringJob, PatientCluster};
+pub use merge::{MergeBlocker, MergeConflict, MergeGuard};
+pub use practitioner::{PractitionerMatch, PractitionerMatcher};
+pub use fingerprint::{find_resubmitted, record_fingerprint};
+pub use frequency::NameFrequencies;
+pub use photo::{photo_similarity, refresh_photo_hashes};
+pub use similarity::{NameSimilarity, SimilarityAlgorithm, SimilarityRegistry};
+pub use batch::{BatchMatchRecord, BatchMatchResult, BatchMatchSummary, BatchMatcher, BatchOptions};
 
 /// Match result containing a patient and their match score
 #[derive(Debug, Clone)]
@@ -18,13 +48,19 @@ pub struct MatchResult {
 }
 
 /// Breakdown of match score components
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
 pub struct MatchScoreBreakdown {
     pub name_score: f64,
     pub birth_date_score: f64,
     pub gender_score: f64,
     pub address_score: f64,
     pub identifier_score: f64,
+    /// The name score came from given and family names swapped
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub names_transposed: bool,
+    /// Similarity of the pair's closest photo hashes, when both have any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub photo_score: Option<f64>,
 }
 
 impl MatchScoreBreakdown {
@@ -56,6 +92,17 @@ impl MatchScoreBreakdown {
     }
 }
 
+/// Label of a score in match API results: "certain", "probable" or "possible"
+pub fn quality_label(score: f64) -> &'static str {
+    if score >= 0.9 {
+        "certain"
+    } else if score >= 0.7 {
+        "probable"
+    } else {
+        "possible"
+    }
+}
+
 /// Patient matcher trait
 pub trait PatientMatcher: Send + Sync {
     /// Match a patient against a candidate
@@ -80,6 +127,12 @@ impl ProbabilisticMatcher {
         }
     }
 
+    /// Weigh family name agreement by how common the name is
+    pub fn with_name_frequencies(mut self, frequencies: std::sync::Arc<NameFrequencies>) -> Self {
+        self.scorer = self.scorer.with_name_frequencies(frequencies);
+        self
+    }
+
     /// Get the configured threshold (not implemented yet)
     pub fn threshold(&self) -> f64 {
         0.85 // TODO: expose config properly
@@ -97,11 +150,14 @@ impl PatientMatcher for ProbabilisticMatcher {
     }
 
     fn find_matches(&self, patient: &Patient, candidates: &[Patient]) -> Result<Vec<MatchResult>> {
-        let mut matches: Vec<MatchResult> = candidates
-            .iter()
-            .map(|candidate| self.scorer.calculate_score(patient, candidate))
-            .filter(|result| self.is_match(result.score))
-            .collect();
+        let mut matches = Vec::new();
+        for candidate in candidates {
+            crate::deadline::check()?;
+            let result = self.scorer.calculate_score(patient, candidate);
+            if self.is_match(result.score) {
+                matches.push(result);
+            }
+        }
 
         // Sort by score descending
         matches.sort_by(|a, b| {
@@ -137,11 +193,14 @@ impl PatientMatcher for DeterministicMatcher {
     }
 
     fn find_matches(&self, patient: &Patient, candidates: &[Patient]) -> Result<Vec<MatchResult>> {
-        let mut matches: Vec<MatchResult> = candidates
-            .iter()
-            .map(|candidate| self.scorer.calculate_score(patient, candidate))
-            .filter(|result| self.is_match(result.score))
-            .collect();
+        let mut matches = Vec::new();
+        for candidate in candidates {
+            crate::deadline::check()?;
+            let result = self.scorer.calculate_score(patient, candidate);
+            if self.is_match(result.score) {
+                matches.push(result);
+            }
+        }
 
         // Sort by score descending
         matches.sort_by(|a, b| {
@@ -169,6 +228,12 @@ mod tests {
             threshold_score: 0.85,
             exact_match_score: 1.0,
             fuzzy_match_score: 0.8,
+            weights: Default::default(),
+            verification: Default::default(),
+            transliteration: Default::default(),
+            similarity: Default::default(),
+            blocking: Default::default(),
+            photo: Default::default(),
         }
     }
 
@@ -187,6 +252,8 @@ mod tests {
             additional_names: vec![],
             telecom: vec![],
             gender: Gender::Male,
+            gender_identity: None,
+            pronouns: None,
             birth_date: dob,
             deceased: false,
             deceased_datetime: None,
@@ -194,8 +261,12 @@ mod tests {
             marital_status: None,
             multiple_birth: None,
             photo: vec![],
+            photo_hashes: vec![],
             managing_organization: None,
             links: vec![],
+            contacts: vec![],
+            confidentiality: Default::default(),
+            status: Default::default(),
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         }
@@ -207,6 +278,12 @@ mod tests {
             threshold_score: 0.70, // Lower threshold for test
             exact_match_score: 1.0,
             fuzzy_match_score: 0.8,
+            weights: Default::default(),
+            verification: Default::default(),
+            transliteration: Default::default(),
+            similarity: Default::default(),
+            blocking: Default::default(),
+            photo: Default::default(),
         };
         let matcher = ProbabilisticMatcher::new(config);
 
@@ -252,6 +329,8 @@ mod tests {
             gender_score: 1.0,
             address_score: 0.70,
             identifier_score: 0.40,
+            names_transposed: false,
+            photo_score: None,
         };
 
         let summary = breakdown.summary();
@@ -0,0 +1,172 @@
+//! Perceptual hashes of patient photos
+//!
+//! A registration photo retaken at another clinic is never the same bytes,
+//! but shrunk to a 9×8 grayscale thumbnail its brightness gradients mostly
+//! survive. Each uploaded photo is reduced to a 64-bit difference hash
+//! (dHash), kept in `patients.photo_hashes` although the photo itself is not
+//! stored. The share of equal bits between two hashes is a weak matching
+//! signal, off unless `matching.photo.weight` is set, and is shown to data
+//! stewards in the duplicate review queue.
+//!
+//! Decoding photos needs the `photo-hash` feature. Without it, hashes a
+//! source system computed itself are kept as sent.
+
+use crate::models::Patient;
+
+/// Width of the thumbnail a hash is taken from, one more than the bits per row
+const THUMBNAIL_WIDTH: usize = 9;
+
+/// Height of the thumbnail, one row of bits each
+const THUMBNAIL_HEIGHT: usize = 8;
+
+/// Difference hash of a 9×8 grayscale thumbnail given row by row
+///
+/// Each bit is set where a pixel is brighter than its right-hand neighbour.
+pub fn difference_hash(pixels: &[u8; THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT]) -> u64 {
+    pixels
+        .chunks(THUMBNAIL_WIDTH)
+        .flat_map(|row| row.windows(2))
+        .fold(0u64, |hash, pair| (hash << 1) | u64::from(pair[0] > pair[1]))
+}
+
+/// Share of equal bits between two hashes, from 0.0 to 1.0
+pub fn hash_similarity(a: u64, b: u64) -> f64 {
+    1.0 - f64::from((a ^ b).count_ones()) / 64.0
+}
+
+/// Stored form of a hash, 16 hex digits
+pub fn format_hash(hash: u64) -> String {
+    format!("{:016x}", hash)
+}
+
+/// Parse the stored form of a hash
+pub fn parse_hash(value: &str) -> Option<u64> {
+    if value.len() != 16 {
+        return None;
+    }
+    u64::from_str_radix(value, 16).ok()
+}
+
+/// Best similarity between a photo of one patient and a photo of the other
+///
+/// None unless both have photo hashes.
+pub fn photo_similarity(patient: &Patient, candidate: &Patient) -> Option<f64> {
+    let theirs: Vec<u64> = candidate.photo_hashes.iter().filter_map(|h| parse_hash(h)).collect();
+    patient
+        .photo_hashes
+        .iter()
+        .filter_map(|h| parse_hash(h))
+        .flat_map(|ours| theirs.iter().map(move |other| hash_similarity(ours, *other)))
+        .reduce(f64::max)
+}
+
+/// Hash of one uploaded photo, given as a `data:` URI or bare base64
+///
+/// Links to photos held elsewhere are not fetched and have no hash, nor do
+/// images that do not decode.
+#[cfg(feature = "photo-hash")]
+pub fn hash_photo(photo: &str) -> Option<u64> {
+    use base64::Engine;
+    use image::imageops::FilterType;
+
+    let encoded = match photo.strip_prefix("data:") {
+        Some(uri) => uri.split_once(";base64,")?.1,
+        None => photo,
+    };
+    let bytes = base64::engine::general_purpose::STANDARD.decode(encoded.trim()).ok()?;
+    let thumbnail = image::load_from_memory(&bytes)
+        .ok()?
+        .resize_exact(THUMBNAIL_WIDTH as u32, THUMBNAIL_HEIGHT as u32, FilterType::Triangle)
+        .to_luma8();
+    let pixels: [u8; THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT] = thumbnail.into_raw().try_into().ok()?;
+    Some(difference_hash(&pixels))
+}
+
+/// Photos are not decoded without the `photo-hash` feature
+#[cfg(not(feature = "photo-hash"))]
+pub fn hash_photo(_photo: &str) -> Option<u64> {
+    None
+}
+
+/// Set a patient's photo hashes from its photos, at upload
+///
+/// With the `photo-hash` feature, the hashes of a patient with photos are
+/// replaced by those of the photos. Otherwise the hashes sent with the
+/// record are kept, less any that are not 16 hex digits.
+pub fn refresh_photo_hashes(patient: &mut Patient) {
+    if cfg!(feature = "photo-hash") && !patient.photo.is_empty() {
+        patient.photo_hashes = patient.photo.iter().filter_map(|photo| hash_photo(photo)).map(format_hash).collect();
+    } else {
+        patient.photo_hashes.retain(|hash| parse_hash(hash).is_some());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Gender;
+
+    fn patient(photo_hashes: &[&str]) -> Patient {
+        let mut patient = crate::fixtures::patient("Halvorsen", &["Ingrid"], Gender::Female);
+        patient.photo_hashes = photo_hashes.iter().map(|h| h.to_string()).collect();
+        patient
+    }
+
+    #[test]
+    fn test_difference_hash_follows_gradients() {
+        // Brightness falling left to right sets every bit; rising sets none
+        let falling: [u8; 72] = std::array::from_fn(|i| 255 - (i % 9) as u8 * 20);
+        let rising: [u8; 72] = std::array::from_fn(|i| (i % 9) as u8 * 20);
+        assert_eq!(difference_hash(&falling), u64::MAX);
+        assert_eq!(difference_hash(&rising), 0);
+
+        // Uniform brightening changes no gradient
+        let brighter: [u8; 72] = std::array::from_fn(|i| falling[i].saturating_sub(60) + 30);
+        assert_eq!(difference_hash(&brighter), difference_hash(&falling));
+    }
+
+    #[test]
+    fn test_hash_similarity_and_stored_form() {
+        assert_eq!(hash_similarity(0x0f, 0x0f), 1.0);
+        assert_eq!(hash_similarity(0, u64::MAX), 0.0);
+        assert_eq!(hash_similarity(0, 0xff), 0.875);
+
+        assert_eq!(format_hash(0xff), "00000000000000ff");
+        assert_eq!(parse_hash("00000000000000ff"), Some(0xff));
+        assert_eq!(parse_hash("ff"), None);
+        assert_eq!(parse_hash("not a hash at al"), None);
+    }
+
+    #[test]
+    fn test_photo_similarity_takes_best_pair() {
+        let on_file = patient(&["ffffffffffffffff", "0000000000000000"]);
+        let incoming = patient(&["00000000000000ff"]);
+        assert_eq!(photo_similarity(&on_file, &incoming), Some(0.875));
+        assert_eq!(photo_similarity(&incoming, &on_file), Some(0.875));
+        assert_eq!(photo_similarity(&on_file, &patient(&[])), None);
+    }
+
+    #[test]
+    fn test_refresh_keeps_valid_hashes_without_photos() {
+        let mut sent = patient(&["00000000000000ff", "bogus"]);
+        refresh_photo_hashes(&mut sent);
+        assert_eq!(sent.photo_hashes, vec!["00000000000000ff".to_string()]);
+    }
+
+    #[cfg(feature = "photo-hash")]
+    #[test]
+    fn test_hashes_uploaded_photo() {
+        use base64::Engine;
+        use std::io::Cursor;
+
+        let image = image::GrayImage::from_fn(90, 80, |x, _| image::Luma([255 - (x as u8) * 2]));
+        let mut png = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png).unwrap();
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&png);
+
+        let mut uploaded = patient(&["00000000000000ff"]);
+        uploaded.photo = vec![format!("data:image/png;base64,{}", encoded), "https://example.org/photo.png".to_string()];
+        refresh_photo_hashes(&mut uploaded);
+        assert_eq!(uploaded.photo_hashes, vec![format_hash(u64::MAX)]);
+    }
+}
@@ -0,0 +1,162 @@
+//! Practitioner deduplication
+//!
+//! Provider masters from different feeds name the same practitioner in
+//! slightly different ways. Pairs are scored by the same probabilistic scorer
+//! as patients, comparing each practitioner as a patient-shaped record with
+//! no birth date, under the weights in `practitioners.matching`. Candidates
+//! are the practitioners sharing the NPI or the family name.
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::config::MatchingConfig;
+use crate::db::PractitionerRepository;
+use crate::models::Practitioner;
+use crate::Result;
+use super::{MatchScoreBreakdown, PatientMatcher, ProbabilisticMatcher};
+
+/// A practitioner that may be the same provider, with its score
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PractitionerMatch {
+    pub practitioner: Practitioner,
+    pub score: f64,
+    pub breakdown: MatchScoreBreakdown,
+}
+
+/// Scores pairs of practitioners with provider-specific weights
+pub struct PractitionerMatcher {
+    matcher: ProbabilisticMatcher,
+}
+
+impl PractitionerMatcher {
+    /// Create a matcher with the threshold and weights in `config`
+    pub fn new(config: MatchingConfig) -> Self {
+        Self {
+            matcher: ProbabilisticMatcher::new(config),
+        }
+    }
+
+    /// Score a practitioner against a candidate
+    pub fn match_practitioners(&self, practitioner: &Practitioner, candidate: &Practitioner) -> Result<PractitionerMatch> {
+        let result = self
+            .matcher
+            .match_patients(&practitioner.as_patient_record(), &candidate.as_patient_record())?;
+        Ok(PractitionerMatch {
+            practitioner: candidate.clone(),
+            score: result.score,
+            breakdown: result.breakdown,
+        })
+    }
+
+    /// Candidates at or above the threshold, best first
+    pub fn find_matches(&self, practitioner: &Practitioner, candidates: &[Practitioner]) -> Result<Vec<PractitionerMatch>> {
+        let mut matches = Vec::new();
+        for candidate in candidates {
+            crate::deadline::check()?;
+            if candidate.id == practitioner.id {
+                continue;
+            }
+            let result = self.match_practitioners(practitioner, candidate)?;
+            if self.matcher.is_match(result.score) {
+                matches.push(result);
+            }
+        }
+
+        matches.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        Ok(matches)
+    }
+
+    /// Stored practitioners sharing the NPI or family name of `practitioner`
+    ///
+    /// At most `limit` are fetched by family name; NPI holders are always
+    /// included.
+    pub fn candidates(
+        &self,
+        practitioner: &Practitioner,
+        practitioners: &dyn PractitionerRepository,
+        limit: i64,
+    ) -> Result<Vec<Practitioner>> {
+        let mut candidates = match practitioner.npi() {
+            Some(npi) => practitioners.find_by_npi(npi)?,
+            None => Vec::new(),
+        };
+        candidates.extend(practitioners.find_by_family_name(&practitioner.name.family, limit)?);
+
+        let mut seen = HashSet::new();
+        candidates.retain(|candidate| candidate.id != practitioner.id && seen.insert(candidate.id));
+        Ok(candidates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::db::InMemoryPractitionerRepository;
+    use crate::models::practitioner::NPI_SYSTEM;
+    use crate::models::{HumanName, Identifier, IdentifierType};
+
+    fn practitioner(family: &str, given: &str, npi: Option<&str>) -> Practitioner {
+        let mut practitioner = Practitioner::new(HumanName {
+            use_type: None,
+            family: family.to_string(),
+            given: vec![given.to_string()],
+            prefix: vec!["Dr".to_string()],
+            suffix: vec![],
+        });
+        if let Some(npi) = npi {
+            practitioner
+                .identifiers
+                .push(Identifier::new(IdentifierType::NPI, NPI_SYSTEM.to_string(), npi.to_string()));
+        }
+        practitioner
+    }
+
+    fn matcher() -> PractitionerMatcher {
+        PractitionerMatcher::new(Config::default().practitioners.matching)
+    }
+
+    #[test]
+    fn test_same_npi_and_name_match() {
+        let stored = practitioner("Mensah", "Kofi", Some("1234567893"));
+        let incoming = practitioner("Mensah", "Kofi", Some("1234567893"));
+
+        let matches = matcher().find_matches(&incoming, std::slice::from_ref(&stored)).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].practitioner.id, stored.id);
+    }
+
+    #[test]
+    fn test_name_alone_is_not_enough() {
+        let stored = practitioner("Mensah", "Kofi", Some("1234567893"));
+        let namesake = practitioner("Mensah", "Kofi", None);
+        let other_npi = practitioner("Mensah", "Kofi", Some("1245319599"));
+
+        let matcher = matcher();
+        assert!(matcher.find_matches(&namesake, std::slice::from_ref(&stored)).unwrap().is_empty());
+        assert!(matcher.find_matches(&other_npi, &[stored]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_candidates_share_npi_or_family_name() {
+        let repository = InMemoryPractitionerRepository::new();
+        let by_npi = repository.create(&practitioner("Asante", "Kofi", Some("1234567893"))).unwrap();
+        let by_name = repository.create(&practitioner("mensah", "Ama", None)).unwrap();
+        repository.create(&practitioner("Boateng", "Yaw", None)).unwrap();
+        let incoming = repository.create(&practitioner("Mensah", "Kofi", Some("1234567893"))).unwrap();
+
+        let candidates = matcher().candidates(&incoming, &repository, 10).unwrap();
+        let mut ids: Vec<_> = candidates.iter().map(|c| c.id).collect();
+        ids.sort();
+        let mut expected = vec![by_npi.id, by_name.id];
+        expected.sort();
+        assert_eq!(ids, expected);
+    }
+}
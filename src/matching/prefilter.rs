@@ -0,0 +1,129 @@
+//! Exact identifier pre-filtering
+//!
+//! Most match requests come from registration systems that send the
+//! patient's MRN or SSN. When that identifier is already on file, the patient
+//! holding it is nearly always the match, and finding it takes one indexed
+//! lookup instead of a name search and a scoring pass over up to a hundred
+//! blocked candidates.
+
+use std::collections::HashSet;
+
+use super::{MatchResult, PatientMatcher};
+use crate::db::PatientRepository;
+use crate::models::{AuthorityRegistry, IdentifierType, Patient};
+use crate::Result;
+
+/// Identifier types exact enough to pick a candidate on their own
+pub const PREFILTER_IDENTIFIER_TYPES: &[IdentifierType] = &[IdentifierType::MRN, IdentifierType::SSN];
+
+/// Stored patients holding one of `patient`'s MRNs or SSNs
+///
+/// An MRN from a registered assigning authority is looked up under every
+/// system of that authority; other identifiers only under their own system.
+/// `patient` itself is never returned.
+pub fn identifier_candidates(
+    patient: &Patient,
+    patients: &dyn PatientRepository,
+    authorities: &AuthorityRegistry,
+) -> Result<Vec<Patient>> {
+    let mut seen = HashSet::new();
+    let mut candidates = Vec::new();
+    for identifier in &patient.identifiers {
+        let value = identifier.value.trim();
+        if value.is_empty() || !PREFILTER_IDENTIFIER_TYPES.contains(&identifier.identifier_type) {
+            continue;
+        }
+        let systems = match authorities.for_identifier(identifier) {
+            Some(authority) if identifier.identifier_type == IdentifierType::MRN => authority.systems(),
+            _ => vec![identifier.system.clone()],
+        };
+        for id in patients.find_by_identifier(&systems, value)? {
+            if id == patient.id || !seen.insert(id) {
+                continue;
+            }
+            if let Some(candidate) = patients.get_by_id(&id)? {
+                candidates.push(candidate);
+            }
+        }
+    }
+    Ok(candidates)
+}
+
+/// Whether the scored identifier candidates settle the request
+///
+/// Only a candidate reaching the matcher's own threshold does; a lower
+/// request threshold lets weaker candidates into the response but does not
+/// skip blocking for them.
+pub fn settled_by_identifiers(matcher: &dyn PatientMatcher, results: &[MatchResult]) -> bool {
+    results.iter().any(|result| matcher.is_match(result.score))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::InMemoryPatientRepository;
+    use crate::matching::ProbabilisticMatcher;
+    use crate::models::{Gender, Identifier};
+
+    fn patient(family: &str, identifiers: Vec<Identifier>) -> Patient {
+        let mut patient = crate::fixtures::patient(family, &[], Gender::Unknown);
+        patient.identifiers = identifiers;
+        patient
+    }
+
+    #[test]
+    fn test_finds_holders_of_mrn_and_ssn() {
+        let patients = InMemoryPatientRepository::new();
+        let by_mrn = patients.create(&patient("Osei", vec![Identifier::mrn("GENERAL".to_string(), "100".to_string())])).unwrap();
+        let by_ssn = patients.create(&patient("Mensah", vec![Identifier::ssn("123-45-6789".to_string())])).unwrap();
+        patients.create(&patient("Boateng", vec![Identifier::mrn("EAST".to_string(), "100".to_string())])).unwrap();
+
+        let incoming = patient(
+            "Osei",
+            vec![
+                Identifier::mrn("GENERAL".to_string(), " 100 ".to_string()),
+                Identifier::ssn("123-45-6789".to_string()),
+            ],
+        );
+        let found: Vec<_> = identifier_candidates(&incoming, &patients, &AuthorityRegistry::default())
+            .unwrap()
+            .into_iter()
+            .map(|p| p.id)
+            .collect();
+        assert_eq!(found, vec![by_mrn.id, by_ssn.id]);
+    }
+
+    #[test]
+    fn test_other_identifier_types_and_self_are_skipped() {
+        let patients = InMemoryPatientRepository::new();
+        let npi = Identifier::new(IdentifierType::NPI, "http://hl7.org/fhir/sid/us-npi".to_string(), "1234567893".to_string());
+        patients.create(&patient("Osei", vec![npi.clone()])).unwrap();
+        let incoming = patient("Osei", vec![npi]);
+        assert!(identifier_candidates(&incoming, &patients, &AuthorityRegistry::default()).unwrap().is_empty());
+
+        let stored = patients.create(&patient("Mensah", vec![Identifier::ssn("123-45-6789".to_string())])).unwrap();
+        assert!(identifier_candidates(&stored, &patients, &AuthorityRegistry::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_settled_only_at_matcher_threshold() {
+        let config = crate::config::Config::default().matching;
+        let matcher = ProbabilisticMatcher::new(config.clone());
+        let holder = |family: &str| {
+            let mut holder = patient(family, vec![Identifier::ssn("123-45-6789".to_string())]);
+            holder.birth_date = chrono::NaiveDate::from_ymd_opt(1984, 6, 12);
+            holder
+        };
+        let incoming = holder("Osei");
+
+        // Same SSN, different name: above the default request threshold of
+        // 0.5 but below the matcher's
+        let namesake = matcher.match_patients(&incoming, &holder("Mensah")).unwrap();
+        assert!(namesake.score >= 0.5 && namesake.score < config.threshold_score, "{}", namesake.score);
+        assert!(!settled_by_identifiers(&matcher, std::slice::from_ref(&namesake)));
+
+        let mut confirmed = namesake.clone();
+        confirmed.score = config.threshold_score;
+        assert!(settled_by_identifiers(&matcher, &[namesake, confirmed]));
+    }
+}
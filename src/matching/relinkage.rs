@@ -0,0 +1,248 @@
+//! Re-linkage review after matching configuration changes
+//!
+//! Existing links were made under the weights and thresholds in force at the
+//! time. This job re-scores linked pairs and previously scored non-links with
+//! the current matcher and reports the pairs whose decision would now change.
+//! It only reports; links are not modified.
+
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::db::{MatchScoreRepository, PatientRepository, SourceRecordRepository};
+use crate::models::Patient;
+use crate::Result;
+use super::PatientMatcher;
+
+/// Number of rows fetched per page while walking links and scores
+const BATCH_SIZE: i64 = 500;
+
+/// A pair of records whose match decision changed
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RelinkagePair {
+    /// Source record (for links) or patient (for scored pairs)
+    pub record_id: Uuid,
+    /// Enterprise patient (for links) or candidate patient (for scored pairs)
+    pub patient_id: Uuid,
+    /// Score recorded when the pair was last evaluated, if known
+    pub previous_score: Option<f64>,
+    /// Score under the current configuration
+    pub new_score: f64,
+}
+
+/// Outcome of a re-linkage review
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct RelinkageReport {
+    /// Number of linked pairs re-scored
+    pub links_checked: usize,
+    /// Number of unlinked scored pairs re-scored
+    pub non_links_checked: usize,
+    /// Linked pairs that now fall below the match threshold
+    pub weakened_links: Vec<RelinkagePair>,
+    /// Unlinked pairs that now meet the match threshold
+    pub new_matches: Vec<RelinkagePair>,
+}
+
+/// Re-scores existing linkage decisions with the current matcher
+pub struct RelinkageJob<'a> {
+    matcher: &'a dyn PatientMatcher,
+    pair_matcher: &'a dyn PatientMatcher,
+}
+
+impl<'a> RelinkageJob<'a> {
+    /// Create a new job using the given matcher
+    pub fn new(matcher: &'a dyn PatientMatcher) -> Self {
+        Self { matcher, pair_matcher: matcher }
+    }
+
+    /// Score pairs of stored patients with `matcher` instead, typically a
+    /// [`CachingMatcher`](super::CachingMatcher) so unchanged pairs are not
+    /// scored again
+    pub fn with_pair_matcher(mut self, matcher: &'a dyn PatientMatcher) -> Self {
+        self.pair_matcher = matcher;
+        self
+    }
+
+    /// Review all active source record links and scored candidate pairs
+    pub fn run(
+        &self,
+        patients: &dyn PatientRepository,
+        source_records: &dyn SourceRecordRepository,
+        match_scores: &MatchScoreRepository,
+    ) -> Result<RelinkageReport> {
+        let mut report = RelinkageReport::default();
+
+        let mut offset = 0;
+        loop {
+            let links = source_records.list_active_links(BATCH_SIZE, offset)?;
+            if links.is_empty() {
+                break;
+            }
+            offset += links.len() as i64;
+
+            for link in links {
+                let record = source_records.get_by_id(&link.source_record_id)?;
+                let patient = patients.get_by_id(&link.patient_id)?;
+                if let (Some(record), Some(patient)) = (record, patient) {
+                    self.review_linked_pair(
+                        &mut report,
+                        link.source_record_id,
+                        &record.patient,
+                        &patient,
+                        link.match_score,
+                    )?;
+                }
+            }
+        }
+
+        let mut offset = 0;
+        loop {
+            let scores = match_scores.list(BATCH_SIZE, offset)?;
+            if scores.is_empty() {
+                break;
+            }
+            offset += scores.len() as i64;
+
+            for score in scores {
+                let patient = patients.get_by_id(&score.patient_id)?;
+                let candidate = patients.get_by_id(&score.candidate_id)?;
+                if let (Some(patient), Some(candidate)) = (patient, candidate) {
+                    // Pairs that have since been linked are covered above
+                    if patient.links.iter().any(|l| l.other_patient_id == candidate.id) {
+                        continue;
+                    }
+                    let previous_score = bigdecimal::ToPrimitive::to_f64(&score.total_score);
+                    self.review_unlinked_pair(&mut report, &patient, &candidate, previous_score)?;
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Re-score a linked pair, reporting it if it no longer matches
+    pub fn review_linked_pair(
+        &self,
+        report: &mut RelinkageReport,
+        record_id: Uuid,
+        record: &Patient,
+        patient: &Patient,
+        previous_score: Option<f64>,
+    ) -> Result<()> {
+        report.links_checked += 1;
+
+        let result = self.matcher.match_patients(record, patient)?;
+        if !self.matcher.is_match(result.score) {
+            report.weakened_links.push(RelinkagePair {
+                record_id,
+                patient_id: patient.id,
+                previous_score,
+                new_score: result.score,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Re-score an unlinked pair, reporting it if it now matches
+    pub fn review_unlinked_pair(
+        &self,
+        report: &mut RelinkageReport,
+        patient: &Patient,
+        candidate: &Patient,
+        previous_score: Option<f64>,
+    ) -> Result<()> {
+        report.non_links_checked += 1;
+
+        let result = self.pair_matcher.match_patients(patient, candidate)?;
+        if self.pair_matcher.is_match(result.score) {
+            report.new_matches.push(RelinkagePair {
+                record_id: patient.id,
+                patient_id: candidate.id,
+                previous_score,
+                new_score: result.score,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MatchingConfig;
+    use crate::matching::ProbabilisticMatcher;
+    use crate::models::{Gender, HumanName};
+    use chrono::NaiveDate;
+
+    fn create_test_patient(family: &str, given: &str) -> Patient {
+        let mut patient = Patient::new(
+            HumanName {
+                use_type: None,
+                family: family.to_string(),
+                given: vec![given.to_string()],
+                prefix: vec![],
+                suffix: vec![],
+            },
+            Gender::Male,
+        );
+        patient.birth_date = NaiveDate::from_ymd_opt(1980, 1, 15);
+        patient
+    }
+
+    fn matcher_with_threshold(threshold_score: f64) -> ProbabilisticMatcher {
+        ProbabilisticMatcher::new(MatchingConfig {
+            threshold_score,
+            exact_match_score: 1.0,
+            fuzzy_match_score: 0.8,
+            weights: Default::default(),
+            verification: Default::default(),
+            transliteration: Default::default(),
+            similarity: Default::default(),
+            blocking: Default::default(),
+            photo: Default::default(),
+        })
+    }
+
+    #[test]
+    fn test_raised_threshold_weakens_link() {
+        // Name, DOB and gender agree but there is no address or identifier,
+        // which scores around 0.75
+        let record = create_test_patient("Smith", "John");
+        let patient = create_test_patient("Smith", "John");
+
+        let lenient = matcher_with_threshold(0.70);
+        let mut report = RelinkageReport::default();
+        RelinkageJob::new(&lenient)
+            .review_linked_pair(&mut report, record.id, &record, &patient, Some(0.75))
+            .unwrap();
+        assert_eq!(report.links_checked, 1);
+        assert!(report.weakened_links.is_empty());
+
+        let strict = matcher_with_threshold(0.90);
+        let mut report = RelinkageReport::default();
+        RelinkageJob::new(&strict)
+            .review_linked_pair(&mut report, record.id, &record, &patient, Some(0.75))
+            .unwrap();
+        assert_eq!(report.weakened_links.len(), 1);
+        assert_eq!(report.weakened_links[0].patient_id, patient.id);
+    }
+
+    #[test]
+    fn test_lowered_threshold_finds_new_match() {
+        let patient = create_test_patient("Smith", "John");
+        let candidate = create_test_patient("Smyth", "John");
+        let unrelated = create_test_patient("Johnson", "Bob");
+
+        let matcher = matcher_with_threshold(0.60);
+        let job = RelinkageJob::new(&matcher);
+        let mut report = RelinkageReport::default();
+        job.review_unlinked_pair(&mut report, &patient, &candidate, Some(0.55)).unwrap();
+        job.review_unlinked_pair(&mut report, &patient, &unrelated, Some(0.20)).unwrap();
+
+        assert_eq!(report.non_links_checked, 2);
+        assert_eq!(report.new_matches.len(), 1);
+        assert_eq!(report.new_matches[0].patient_id, candidate.id);
+    }
+}
@@ -0,0 +1,67 @@
+//! Matcher that can be replaced while requests are running
+//!
+//! [`ReloadableMatcher`] delegates to another matcher that a configuration
+//! reload swaps out. A call already in progress finishes with the matcher it
+//! started with, so one request never mixes two thresholds.
+
+use std::sync::{Arc, RwLock};
+
+use super::{MatchResult, PatientMatcher};
+use crate::models::Patient;
+use crate::Result;
+
+/// Matcher delegating to a replaceable inner matcher
+pub struct ReloadableMatcher {
+    inner: RwLock<Arc<dyn PatientMatcher>>,
+}
+
+impl ReloadableMatcher {
+    /// Delegate to `inner` until it is replaced
+    pub fn new(inner: Arc<dyn PatientMatcher>) -> Self {
+        Self {
+            inner: RwLock::new(inner),
+        }
+    }
+
+    /// Use `inner` for every call from now on
+    pub fn replace(&self, inner: Arc<dyn PatientMatcher>) {
+        *self.inner.write().unwrap_or_else(|e| e.into_inner()) = inner;
+    }
+
+    fn current(&self) -> Arc<dyn PatientMatcher> {
+        self.inner.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl PatientMatcher for ReloadableMatcher {
+    fn match_patients(&self, patient: &Patient, candidate: &Patient) -> Result<MatchResult> {
+        self.current().match_patients(patient, candidate)
+    }
+
+    fn find_matches(&self, patient: &Patient, candidates: &[Patient]) -> Result<Vec<MatchResult>> {
+        self.current().find_matches(patient, candidates)
+    }
+
+    fn is_match(&self, score: f64) -> bool {
+        self.current().is_match(score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::matching::ProbabilisticMatcher;
+
+    #[test]
+    fn test_replace_changes_threshold() {
+        let mut config = Config::default().matching;
+        config.threshold_score = 0.9;
+        let matcher = ReloadableMatcher::new(Arc::new(ProbabilisticMatcher::new(config.clone())));
+        assert!(!matcher.is_match(0.8));
+
+        config.threshold_score = 0.7;
+        matcher.replace(Arc::new(ProbabilisticMatcher::new(config)));
+        assert!(matcher.is_match(0.8));
+    }
+}
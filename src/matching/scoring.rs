@@ -3,24 +3,63 @@
 //! This module combines individual matching algorithm scores into
 //! overall match scores using configurable weights.
 
-use crate::models::Patient;
+use std::sync::Arc;
+
+use crate::models::{HumanName, Patient};
 use crate::config::MatchingConfig;
 use super::{MatchResult, MatchScoreBreakdown};
+use super::frequency::NameFrequencies;
+use super::photo::photo_similarity;
+use super::similarity::NameSimilarity;
+use super::transliteration::Transliterator;
 use super::algorithms::{
     name_matching, dob_matching, gender_matching,
     address_matching, identifier_matching,
 };
 
+/// Share of the score kept by a pair whose generational suffixes conflict
+const GENERATION_CONFLICT_PENALTY: f64 = 0.8;
+
+/// Name score of a pair, counting maiden names and swapped given and family
+/// names, and whether the swap earned it
+fn name_score(
+    patient: &Patient,
+    candidate: &Patient,
+    name1: &HumanName,
+    name2: &HumanName,
+    names: &NameSimilarity,
+) -> (f64, bool) {
+    let (score, transposed) = name_matching::match_names_transposable(name1, name2, names);
+    match name_matching::match_maiden_names(patient, candidate, names) {
+        Some(maiden) if maiden > score => (maiden, false),
+        _ => (score, transposed),
+    }
+}
+
 /// Probabilistic scoring strategy
 pub struct ProbabilisticScorer {
     /// Configuration for matching thresholds and weights
     config: MatchingConfig,
+    /// Romanizes names written in different scripts
+    transliterator: Transliterator,
+    /// Comparators for family and given names
+    names: NameSimilarity,
+    /// Family name frequencies the name weight is scaled by, if any
+    frequencies: Option<Arc<NameFrequencies>>,
 }
 
 impl ProbabilisticScorer {
     /// Create a new probabilistic scorer with configuration
     pub fn new(config: MatchingConfig) -> Self {
-        Self { config }
+        let transliterator = Transliterator::from_config(&config.transliteration);
+        let names = NameSimilarity::from_config(&config.similarity);
+        Self { config, transliterator, names, frequencies: None }
+    }
+
+    /// Give agreement on a common family name less weight
+    pub fn with_name_frequencies(mut self, frequencies: Arc<NameFrequencies>) -> Self {
+        self.frequencies = Some(frequencies);
+        self
     }
 
     /// Calculate match score between two patients
@@ -29,15 +68,13 @@ impl ProbabilisticScorer {
         patient: &Patient,
         candidate: &Patient,
     ) -> MatchResult {
-        // Weight factors for each component
-        const NAME_WEIGHT: f64 = 0.35;
-        const DOB_WEIGHT: f64 = 0.30;
-        const GENDER_WEIGHT: f64 = 0.10;
-        const ADDRESS_WEIGHT: f64 = 0.15;
-        const IDENTIFIER_WEIGHT: f64 = 0.10;
+        let weights = &self.config.weights;
 
         // Calculate individual component scores
-        let name_score = name_matching::match_names(&patient.name, &candidate.name);
+        let (name1, name2) = self
+            .transliterator
+            .comparable_names(patient.legal_name(), candidate.legal_name());
+        let (name_score, names_transposed) = name_score(patient, candidate, &name1, &name2, &self.names);
 
         let birth_date_score = dob_matching::match_birth_dates(
             patient.birth_date,
@@ -49,22 +86,49 @@ impl ProbabilisticScorer {
             candidate.gender,
         );
 
-        let address_score = address_matching::match_addresses(
+        let (address_score, address_verification) = address_matching::match_addresses_verified(
             &patient.addresses,
             &candidate.addresses,
         );
 
-        let identifier_score = identifier_matching::match_identifiers(
+        let (identifier_score, identifier_verification) = identifier_matching::match_identifiers_verified(
             &patient.identifiers,
             &candidate.identifiers,
         );
 
+        // Verified addresses and identifiers carry more weight
+        let verification = &self.config.verification;
+        let address_weight = weights.address * verification.multiplier(address_verification);
+        let identifier_weight = weights.identifier * verification.multiplier(identifier_verification);
+
+        // Agreeing on a common family name is weaker evidence
+        let name_weight = match &self.frequencies {
+            Some(frequencies) => weights.name * frequencies.factor(&name1.family, &name2.family),
+            None => weights.name,
+        };
+
         // Calculate weighted total score
-        let total_score = (name_score * NAME_WEIGHT)
-            + (birth_date_score * DOB_WEIGHT)
-            + (gender_score * GENDER_WEIGHT)
-            + (address_score * ADDRESS_WEIGHT)
-            + (identifier_score * IDENTIFIER_WEIGHT);
+        let mut total_score = ((name_score * name_weight)
+            + (birth_date_score * weights.birth_date)
+            + (gender_score * weights.gender)
+            + (address_score * address_weight)
+            + (identifier_score * identifier_weight))
+            .min(1.0);
+
+        // Similar photos add a little; dissimilar ones take nothing away
+        let photo = &self.config.photo;
+        let photo_score = photo_similarity(patient, candidate);
+        if let Some(similarity) = photo_score.filter(|s| photo.weight > 0.0 && *s >= photo.min_similarity) {
+            total_score = (total_score + similarity * photo.weight).min(1.0);
+        }
+
+        // A father and son can share a name, address and even a birth date
+        // typo; conflicting Jr./Sr./III suffixes keep the pair below the
+        // threshold, for review rather than linking
+        if name_matching::generations_conflict(&name1, &name2) {
+            let below_threshold = (self.config.threshold_score - 0.01).max(0.0);
+            total_score = (total_score * GENERATION_CONFLICT_PENALTY).min(below_threshold);
+        }
 
         let breakdown = MatchScoreBreakdown {
             name_score,
@@ -72,6 +136,8 @@ impl ProbabilisticScorer {
             gender_score,
             address_score,
             identifier_score,
+            names_transposed,
+            photo_score,
         };
 
         MatchResult {
@@ -104,12 +170,18 @@ impl ProbabilisticScorer {
 pub struct DeterministicScorer {
     /// Configuration for matching
     config: MatchingConfig,
+    /// Romanizes names written in different scripts
+    transliterator: Transliterator,
+    /// Comparators for family and given names
+    names: NameSimilarity,
 }
 
 impl DeterministicScorer {
     /// Create a new deterministic scorer
     pub fn new(config: MatchingConfig) -> Self {
-        Self { config }
+        let transliterator = Transliterator::from_config(&config.transliteration);
+        let names = NameSimilarity::from_config(&config.similarity);
+        Self { config, transliterator, names }
     }
 
     /// Calculate match score using strict rules
@@ -138,12 +210,17 @@ impl DeterministicScorer {
                     gender_score: 0.0,
                     address_score: 0.0,
                     identifier_score,
+                    names_transposed: false,
+                    photo_score: None,
                 },
             };
         }
 
         // Rule 2: Name + DOB + Gender must all match
-        let name_score = name_matching::match_names(&patient.name, &candidate.name);
+        let (name1, name2) = self
+            .transliterator
+            .comparable_names(patient.legal_name(), candidate.legal_name());
+        let (name_score, names_transposed) = name_score(patient, candidate, &name1, &name2, &self.names);
         let dob_score = dob_matching::match_birth_dates(
             patient.birth_date,
             candidate.birth_date,
@@ -181,18 +258,25 @@ impl DeterministicScorer {
         }
 
         // Calculate final score as percentage of available points
-        let final_score = if points_available > 0.0 {
+        let mut final_score: f64 = if points_available > 0.0 {
             total_score / points_available
         } else {
             0.0
         };
 
+        // Conflicting generational suffixes fail the name rule
+        if name_matching::generations_conflict(&name1, &name2) {
+            final_score = final_score.min(0.5);
+        }
+
         let breakdown = MatchScoreBreakdown {
             name_score,
             birth_date_score: dob_score,
             gender_score,
             address_score,
             identifier_score,
+            names_transposed,
+            photo_score: None,
         };
 
         MatchResult {
@@ -241,6 +325,7 @@ impl MatchQuality {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::MatchWeights;
     use crate::models::{HumanName, Gender};
     use chrono::NaiveDate;
 
@@ -249,6 +334,12 @@ mod tests {
             threshold_score: 0.85,
             exact_match_score: 1.0,
             fuzzy_match_score: 0.8,
+            weights: Default::default(),
+            verification: Default::default(),
+            transliteration: Default::default(),
+            similarity: Default::default(),
+            blocking: Default::default(),
+            photo: Default::default(),
         }
     }
 
@@ -267,6 +358,8 @@ mod tests {
             additional_names: vec![],
             telecom: vec![],
             gender: Gender::Male,
+            gender_identity: None,
+            pronouns: None,
             birth_date: dob,
             deceased: false,
             deceased_datetime: None,
@@ -274,8 +367,12 @@ mod tests {
             marital_status: None,
             multiple_birth: None,
             photo: vec![],
+            photo_hashes: vec![],
             managing_organization: None,
             links: vec![],
+            contacts: vec![],
+            confidentiality: Default::default(),
+            status: Default::default(),
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         }
@@ -333,6 +430,29 @@ mod tests {
         assert!(!scorer.is_match(result.score));
     }
 
+    #[test]
+    fn test_custom_weights() {
+        let mut config = create_test_config();
+        config.weights = MatchWeights {
+            name: 0.50,
+            birth_date: 0.40,
+            gender: 0.10,
+            address: 0.0,
+            identifier: 0.0,
+        };
+        let scorer = ProbabilisticScorer::new(config);
+
+        let dob = NaiveDate::from_ymd_opt(1980, 1, 15);
+        let patient1 = create_test_patient("Smith", dob);
+        let patient2 = create_test_patient("Smith", dob);
+
+        let result = scorer.calculate_score(&patient1, &patient2);
+
+        // Address and identifier carry no weight, so name/dob/gender reach 1.0
+        assert!(result.score >= 0.99, "Reweighted exact match should score ~1.0, got {}", result.score);
+        assert!(scorer.is_match(result.score));
+    }
+
     #[test]
     fn test_deterministic_exact_match() {
         let config = create_test_config();
@@ -348,6 +468,31 @@ mod tests {
         assert!(scorer.is_match(result.score));
     }
 
+    #[test]
+    fn test_conflicting_generations_are_not_linked() {
+        let config = MatchingConfig {
+            threshold_score: 0.70,
+            ..create_test_config()
+        };
+        let scorer = ProbabilisticScorer::new(config.clone());
+
+        let dob = NaiveDate::from_ymd_opt(1980, 1, 15);
+        let mut father = create_test_patient("Smith", dob);
+        father.name.suffix = vec!["Sr.".to_string()];
+        let mut son = create_test_patient("Smith", dob);
+        son.name.suffix = vec!["JR".to_string()];
+
+        let result = scorer.calculate_score(&father, &son);
+        assert!(!scorer.is_match(result.score), "got {}", result.score);
+        assert_eq!(scorer.classify_match(result.score), MatchQuality::Possible);
+        let deterministic = DeterministicScorer::new(config);
+        assert!(!deterministic.is_match(deterministic.calculate_score(&father, &son).score));
+
+        let mut same = son.clone();
+        same.name.suffix = vec!["Jr".to_string()];
+        assert!(scorer.is_match(scorer.calculate_score(&same, &son).score));
+    }
+
     #[test]
     fn test_match_quality_classification() {
         assert_eq!(ProbabilisticScorer::new(create_test_config())
@@ -362,4 +507,121 @@ mod tests {
         assert_eq!(ProbabilisticScorer::new(create_test_config())
             .classify_match(0.30), MatchQuality::Unlikely);
     }
+
+    #[test]
+    fn test_preferred_name_does_not_replace_legal_name() {
+        use crate::models::NameUse;
+
+        let scorer = ProbabilisticScorer::new(create_test_config());
+        let dob = NaiveDate::from_ymd_opt(1980, 1, 15);
+
+        // Registered under the preferred name, with the legal name alongside
+        let mut registered = create_test_patient("Smith", dob);
+        registered.name.use_type = Some(NameUse::Usual);
+        registered.name.given = vec!["Sam".to_string()];
+        registered.additional_names.push(HumanName {
+            use_type: Some(NameUse::Official),
+            family: "Smith".to_string(),
+            given: vec!["John".to_string()],
+            prefix: vec![],
+            suffix: vec![],
+        });
+        let from_lab = create_test_patient("Smith", dob);
+
+        let result = scorer.calculate_score(&registered, &from_lab);
+        assert_eq!(result.breakdown.name_score, 1.0);
+    }
+
+    #[test]
+    fn test_maiden_name_matches_married_name() {
+        use crate::models::NameUse;
+
+        let scorer = ProbabilisticScorer::new(create_test_config());
+        let dob = NaiveDate::from_ymd_opt(1980, 1, 15);
+
+        // Registered before marrying, and again afterwards with the maiden name kept
+        let before = create_test_patient("Jones", dob);
+        let mut after = create_test_patient("Smith", dob);
+        after.additional_names.push(HumanName {
+            use_type: Some(NameUse::Maiden),
+            family: "Jones".to_string(),
+            given: vec!["John".to_string()],
+            prefix: vec![],
+            suffix: vec![],
+        });
+
+        let result = scorer.calculate_score(&before, &after);
+        assert_eq!(result.breakdown.name_score, 0.95);
+        assert_eq!(scorer.calculate_score(&after, &before).breakdown.name_score, 0.95);
+
+        // Without the same birth date the maiden name earns nothing extra
+        let born_later = create_test_patient("Jones", NaiveDate::from_ymd_opt(1981, 1, 15));
+        assert!(scorer.calculate_score(&born_later, &after).breakdown.name_score < 0.95);
+    }
+
+    #[test]
+    fn test_transposed_names_are_credited_and_flagged() {
+        let scorer = ProbabilisticScorer::new(create_test_config());
+        let dob = NaiveDate::from_ymd_opt(1980, 1, 15);
+
+        let on_file = create_test_patient("Smith", dob);
+        let mut swapped = create_test_patient("John", dob);
+        swapped.name.given = vec!["Smith".to_string()];
+
+        let result = scorer.calculate_score(&swapped, &on_file);
+        assert!(result.breakdown.names_transposed);
+        assert!((result.breakdown.name_score - 0.9).abs() < 1e-9);
+        assert!(!scorer.calculate_score(&on_file, &on_file).breakdown.names_transposed);
+
+        let mut config = create_test_config();
+        config.similarity.transposed_name_score = 0.5;
+        let strict = ProbabilisticScorer::new(config).calculate_score(&swapped, &on_file);
+        assert!(strict.breakdown.name_score < result.breakdown.name_score);
+    }
+
+    #[test]
+    fn test_verified_identifier_counts_more() {
+        use crate::models::{Identifier, VerificationStatus};
+
+        let scorer = ProbabilisticScorer::new(create_test_config());
+        let dob = NaiveDate::from_ymd_opt(1980, 1, 15);
+
+        let mut incoming = create_test_patient("Smith", dob);
+        incoming.identifiers.push(Identifier::ssn("123-45-6789".to_string()));
+        let mut on_file = create_test_patient("Smith", dob);
+        on_file.identifiers.push(Identifier::ssn("123-45-6789".to_string()));
+        let unverified = scorer.calculate_score(&incoming, &on_file);
+
+        on_file.identifiers[0].verification = VerificationStatus::DocumentVerified;
+        let verified = scorer.calculate_score(&incoming, &on_file);
+
+        // Identifier weight 0.10, multiplied by 1.5 for a document-verified SSN
+        assert!((verified.score - unverified.score - 0.05).abs() < 1e-9);
+        assert_eq!(verified.breakdown.identifier_score, unverified.breakdown.identifier_score);
+    }
+
+    #[test]
+    fn test_similar_photos_add_weak_evidence() {
+        let dob = NaiveDate::from_ymd_opt(1980, 1, 15);
+        let mut incoming = create_test_patient("Smith", dob);
+        let mut on_file = create_test_patient("Smith", dob);
+        incoming.photo_hashes = vec!["00000000000000ff".to_string()];
+        on_file.photo_hashes = vec!["000000000000007f".to_string()];
+
+        // Reported, but not counted while the weight is 0.0
+        let off = ProbabilisticScorer::new(create_test_config()).calculate_score(&incoming, &on_file);
+        assert_eq!(off.breakdown.photo_score, Some(63.0 / 64.0));
+
+        let mut config = create_test_config();
+        config.photo.weight = 0.05;
+        let scorer = ProbabilisticScorer::new(config);
+        let similar = scorer.calculate_score(&incoming, &on_file);
+        assert!((similar.score - off.score - 0.05 * 63.0 / 64.0).abs() < 1e-9);
+
+        // A different photo neither adds nor takes away
+        on_file.photo_hashes = vec!["ffffffffffffff00".to_string()];
+        let different = scorer.calculate_score(&incoming, &on_file);
+        assert_eq!(different.breakdown.photo_score, Some(0.0));
+        assert_eq!(different.score, off.score);
+    }
 }
@@ -0,0 +1,219 @@
+//! Pluggable string similarity
+//!
+//! Name components are compared by a [`SimilarityAlgorithm`] looked up by
+//! name in the process-wide registry, so a deployment can add its own
+//! comparator, such as a surname comparator tuned to its population, and
+//! select it in `matching.similarity` without changing the matching code:
+//!
+//! ```ignore
+//! similarity::register(Arc::new(HispanicSurnames));
+//! // matching.similarity.family_name = "hispanic_surnames"
+//! ```
+//!
+//! Register comparators before the configuration is validated and the
+//! matcher built. Built in are `jaro_winkler`, `levenshtein` (normalized),
+//! and `jaro_winkler_levenshtein`, the higher of the two and the default.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, LazyLock, RwLock};
+
+use strsim::{jaro_winkler, normalized_levenshtein};
+
+use crate::config::SimilarityConfig;
+
+/// Name of the comparator used when none is configured
+pub const DEFAULT_ALGORITHM: &str = "jaro_winkler_levenshtein";
+
+/// A string comparator
+pub trait SimilarityAlgorithm: Send + Sync {
+    /// Name the comparator is registered and configured under
+    fn name(&self) -> &str;
+
+    /// Similarity of two trimmed, lowercased, non-identical strings, from
+    /// 0.0 (unrelated) to 1.0 (the same)
+    fn similarity(&self, a: &str, b: &str) -> f64;
+}
+
+/// Jaro-Winkler similarity
+pub struct JaroWinkler;
+
+impl SimilarityAlgorithm for JaroWinkler {
+    fn name(&self) -> &str {
+        "jaro_winkler"
+    }
+
+    fn similarity(&self, a: &str, b: &str) -> f64 {
+        jaro_winkler(a, b)
+    }
+}
+
+/// Levenshtein distance normalized by the longer string's length
+pub struct Levenshtein;
+
+impl SimilarityAlgorithm for Levenshtein {
+    fn name(&self) -> &str {
+        "levenshtein"
+    }
+
+    fn similarity(&self, a: &str, b: &str) -> f64 {
+        normalized_levenshtein(a, b)
+    }
+}
+
+/// The higher of Jaro-Winkler and normalized Levenshtein similarity
+pub struct JaroWinklerLevenshtein;
+
+impl SimilarityAlgorithm for JaroWinklerLevenshtein {
+    fn name(&self) -> &str {
+        DEFAULT_ALGORITHM
+    }
+
+    fn similarity(&self, a: &str, b: &str) -> f64 {
+        f64::max(jaro_winkler(a, b), normalized_levenshtein(a, b))
+    }
+}
+
+/// Comparators by name
+pub struct SimilarityRegistry {
+    algorithms: BTreeMap<String, Arc<dyn SimilarityAlgorithm>>,
+}
+
+impl SimilarityRegistry {
+    /// A registry holding the built-in comparators
+    pub fn new() -> Self {
+        let mut registry = Self { algorithms: BTreeMap::new() };
+        registry.register(Arc::new(JaroWinkler));
+        registry.register(Arc::new(Levenshtein));
+        registry.register(Arc::new(JaroWinklerLevenshtein));
+        registry
+    }
+
+    /// Add a comparator, replacing any registered under the same name
+    pub fn register(&mut self, algorithm: Arc<dyn SimilarityAlgorithm>) {
+        self.algorithms.insert(algorithm.name().to_string(), algorithm);
+    }
+
+    /// The comparator registered under `name`
+    pub fn get(&self, name: &str) -> Option<Arc<dyn SimilarityAlgorithm>> {
+        self.algorithms.get(name).cloned()
+    }
+
+    /// Names of the registered comparators, sorted
+    pub fn names(&self) -> Vec<String> {
+        self.algorithms.keys().cloned().collect()
+    }
+}
+
+impl Default for SimilarityRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static REGISTRY: LazyLock<RwLock<SimilarityRegistry>> = LazyLock::new(|| RwLock::new(SimilarityRegistry::new()));
+
+/// Add a comparator to the process-wide registry
+pub fn register(algorithm: Arc<dyn SimilarityAlgorithm>) {
+    REGISTRY.write().unwrap_or_else(|e| e.into_inner()).register(algorithm);
+}
+
+/// The comparator registered under `name` in the process-wide registry
+pub fn lookup(name: &str) -> Option<Arc<dyn SimilarityAlgorithm>> {
+    REGISTRY.read().unwrap_or_else(|e| e.into_inner()).get(name)
+}
+
+/// Names of the comparators in the process-wide registry, sorted
+pub fn registered() -> Vec<String> {
+    REGISTRY.read().unwrap_or_else(|e| e.into_inner()).names()
+}
+
+/// Share of the name score kept by transposed given and family names
+pub const DEFAULT_TRANSPOSED_SCORE: f64 = 0.9;
+
+/// The comparators for each name component
+#[derive(Clone)]
+pub struct NameSimilarity {
+    pub family: Arc<dyn SimilarityAlgorithm>,
+    pub given: Arc<dyn SimilarityAlgorithm>,
+    /// Share of the score kept when given and family names only agree swapped
+    pub transposed_score: f64,
+}
+
+impl NameSimilarity {
+    /// Comparators named in `config`
+    ///
+    /// A name not registered falls back to the default comparator; config
+    /// validation reports it before a matcher is built.
+    pub fn from_config(config: &SimilarityConfig) -> Self {
+        let resolve = |name: &str| {
+            lookup(name).unwrap_or_else(|| {
+                tracing::warn!("Similarity algorithm '{}' is not registered, using {}", name, DEFAULT_ALGORITHM);
+                Arc::new(JaroWinklerLevenshtein)
+            })
+        };
+        Self {
+            family: resolve(&config.family_name),
+            given: resolve(&config.given_name),
+            transposed_score: config.transposed_name_score,
+        }
+    }
+}
+
+impl Default for NameSimilarity {
+    fn default() -> Self {
+        Self {
+            family: Arc::new(JaroWinklerLevenshtein),
+            given: Arc::new(JaroWinklerLevenshtein),
+            transposed_score: DEFAULT_TRANSPOSED_SCORE,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matching::algorithms::name_matching;
+    use crate::models::HumanName;
+
+    /// Treats names differing only in a leading "de " as the same
+    struct IgnoreParticle;
+
+    impl SimilarityAlgorithm for IgnoreParticle {
+        fn name(&self) -> &str {
+            "test_ignore_particle"
+        }
+
+        fn similarity(&self, a: &str, b: &str) -> f64 {
+            if a.trim_start_matches("de ") == b.trim_start_matches("de ") {
+                1.0
+            } else {
+                jaro_winkler(a, b)
+            }
+        }
+    }
+
+    #[test]
+    fn test_registered_algorithm_is_configurable_by_name() {
+        assert!(lookup("test_ignore_particle").is_none());
+        register(Arc::new(IgnoreParticle));
+        assert!(registered().contains(&"test_ignore_particle".to_string()));
+
+        let config = SimilarityConfig {
+            family_name: "test_ignore_particle".to_string(),
+            ..Default::default()
+        };
+        let name = |family: &str| HumanName {
+            use_type: None,
+            family: family.to_string(),
+            given: vec!["Lucia".to_string()],
+            prefix: vec![],
+            suffix: vec![],
+        };
+        let (a, b) = (name("de la Cruz"), name("la Cruz"));
+
+        assert!(name_matching::match_names(&a, &b) < 1.0);
+        let custom = NameSimilarity::from_config(&config);
+        assert_eq!(name_matching::match_names_with(&a, &b, &custom), 1.0);
+        assert_eq!(NameSimilarity::from_config(&SimilarityConfig::default()).family.name(), DEFAULT_ALGORITHM);
+    }
+}
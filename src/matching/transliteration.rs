@@ -0,0 +1,439 @@
+//! Script detection and transliteration to Latin
+//!
+//! Names recorded in different scripts are romanized before comparison, so
+//! "Иванов" scores as an exact match for "Ivanov". Built-in tables cover
+//! Cyrillic, Greek, Arabic, Hebrew and kana; Hangul is romanized
+//! algorithmically (Revised Romanization). Han characters need a reading
+//! dictionary, so they are only romanized through configured tables.
+//!
+//! Arabic and Hebrew are usually written without vowels, so names in those
+//! scripts are compared on their consonant skeletons.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::config::TransliterationConfig;
+use crate::models::HumanName;
+
+/// Writing system of a piece of text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Script {
+    Latin,
+    Cyrillic,
+    Greek,
+    Arabic,
+    Hebrew,
+    Han,
+    Kana,
+    Hangul,
+    Other,
+}
+
+impl Script {
+    /// Script of a single character, `None` for digits, punctuation and marks
+    pub fn of(c: char) -> Option<Script> {
+        let script = match c as u32 {
+            0x41..=0x5A | 0x61..=0x7A | 0xC0..=0x24F | 0x1E00..=0x1EFF => Script::Latin,
+            0x370..=0x3FF | 0x1F00..=0x1FFF => Script::Greek,
+            0x400..=0x52F => Script::Cyrillic,
+            0x590..=0x5FF => Script::Hebrew,
+            0x600..=0x6FF | 0x750..=0x77F => Script::Arabic,
+            0x3040..=0x30FF => Script::Kana,
+            0x3400..=0x4DBF | 0x4E00..=0x9FFF => Script::Han,
+            0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Script::Hangul,
+            _ if c.is_alphabetic() => Script::Other,
+            _ => return None,
+        };
+        Some(script)
+    }
+
+    /// Scripts that normally omit vowels
+    pub fn is_abjad(&self) -> bool {
+        matches!(self, Script::Arabic | Script::Hebrew)
+    }
+}
+
+/// The most common script among the letters of `text`, Latin if there are none
+pub fn detect_script(text: &str) -> Script {
+    let mut counts: BTreeMap<Script, usize> = BTreeMap::new();
+    for script in text.chars().filter_map(Script::of) {
+        *counts.entry(script).or_insert(0) += 1;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(script, _)| script)
+        .unwrap_or(Script::Latin)
+}
+
+/// Drop vowels and repeated letters from romanized text
+///
+/// "muhammad" and the romanized Arabic "mhmd" both become "mhmd".
+pub fn consonant_skeleton(latin: &str) -> String {
+    let mut skeleton = String::with_capacity(latin.len());
+    for c in latin.chars().filter(|c| !matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y')) {
+        if !skeleton.ends_with(c) {
+            skeleton.push(c);
+        }
+    }
+    skeleton
+}
+
+/// Replacement strings for one script, matched longest key first
+#[derive(Debug, Clone, Default)]
+struct Table {
+    entries: HashMap<String, String>,
+    longest_key: usize,
+}
+
+impl Table {
+    fn insert(&mut self, key: &str, value: &str) {
+        let key = key.to_lowercase();
+        self.longest_key = self.longest_key.max(key.chars().count());
+        self.entries.insert(key, value.to_lowercase());
+    }
+
+    /// The longest entry at the start of `chars`, with its length in chars
+    fn lookup(&self, chars: &[char]) -> Option<(&str, usize)> {
+        (1..=self.longest_key.min(chars.len())).rev().find_map(|len| {
+            let key: String = chars[..len].iter().collect();
+            self.entries.get(&key).map(|value| (value.as_str(), len))
+        })
+    }
+}
+
+/// Romanizes text using per-script tables
+#[derive(Debug, Clone)]
+pub struct Transliterator {
+    enabled: bool,
+    tables: HashMap<Script, Table>,
+}
+
+impl Default for Transliterator {
+    fn default() -> Self {
+        let mut tables: HashMap<Script, Table> = HashMap::new();
+        for (script, entries) in [
+            (Script::Latin, LATIN),
+            (Script::Cyrillic, CYRILLIC),
+            (Script::Greek, GREEK),
+            (Script::Arabic, ARABIC),
+            (Script::Hebrew, HEBREW),
+            (Script::Kana, HIRAGANA),
+        ] {
+            let table = tables.entry(script).or_default();
+            for (key, value) in entries {
+                table.insert(key, value);
+            }
+        }
+        Self { enabled: true, tables }
+    }
+}
+
+impl Transliterator {
+    /// Built-in tables extended or overridden by configured ones
+    pub fn from_config(config: &TransliterationConfig) -> Self {
+        let mut transliterator = Self {
+            enabled: config.enabled,
+            ..Self::default()
+        };
+        for (script, entries) in &config.tables {
+            let table = transliterator.tables.entry(*script).or_default();
+            for (key, value) in entries {
+                table.insert(key, value);
+            }
+        }
+        transliterator
+    }
+
+    /// Whether names are romanized at all
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Lowercase Latin form of `text`
+    ///
+    /// Latin diacritics are removed; characters with no table entry are kept.
+    pub fn to_latin(&self, text: &str) -> String {
+        let chars: Vec<char> = text.to_lowercase().chars().collect();
+        let mut latin = String::with_capacity(text.len());
+        let mut geminate = false;
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            let script = Script::of(c);
+
+            // Small tsu doubles the following consonant
+            if matches!(c, 'っ' | 'ッ') {
+                geminate = true;
+                i += 1;
+                continue;
+            }
+
+            let kana;
+            let lookup_chars = if script == Some(Script::Kana) {
+                kana = chars[i..].iter().take(4).map(|c| katakana_to_hiragana(*c)).collect::<Vec<_>>();
+                &kana[..]
+            } else {
+                &chars[i..]
+            };
+
+            let (romanized, consumed): (Cow<str>, usize) = match script
+                .and_then(|s| self.tables.get(&s))
+                .and_then(|table| table.lookup(lookup_chars))
+            {
+                Some((value, len)) => (Cow::Borrowed(value), len),
+                None if script == Some(Script::Hangul) => (
+                    romanize_hangul(c).map(Cow::Owned).unwrap_or_else(|| Cow::Owned(c.to_string())),
+                    1,
+                ),
+                None => (Cow::Owned(strip_diacritics(c)), 1),
+            };
+
+            if geminate {
+                if let Some(first) = romanized.chars().next().filter(|f| !"aeiou".contains(*f)) {
+                    latin.push(first);
+                }
+                geminate = false;
+            }
+            latin.push_str(&romanized);
+            i += consumed;
+        }
+
+        latin
+    }
+
+    /// Romanized copies of two names when either is not in Latin script
+    ///
+    /// Names already in Latin script are returned unchanged. When either
+    /// name is written in an abjad, both are reduced to consonant skeletons.
+    pub fn comparable_names<'a>(
+        &self,
+        name1: &'a HumanName,
+        name2: &'a HumanName,
+    ) -> (Cow<'a, HumanName>, Cow<'a, HumanName>) {
+        if !self.enabled {
+            return (Cow::Borrowed(name1), Cow::Borrowed(name2));
+        }
+
+        let script1 = name_script(name1);
+        let script2 = name_script(name2);
+        if script1 == Script::Latin && script2 == Script::Latin {
+            return (Cow::Borrowed(name1), Cow::Borrowed(name2));
+        }
+
+        let skeleton = script1.is_abjad() || script2.is_abjad();
+        let romanize = |part: &str| {
+            let latin = self.to_latin(part);
+            if skeleton { consonant_skeleton(&latin) } else { latin }
+        };
+        let romanize_name = |name: &HumanName| HumanName {
+            use_type: name.use_type.clone(),
+            family: romanize(&name.family),
+            given: name.given.iter().map(|g| romanize(g)).collect(),
+            prefix: name.prefix.clone(),
+            suffix: name.suffix.clone(),
+        };
+
+        (Cow::Owned(romanize_name(name1)), Cow::Owned(romanize_name(name2)))
+    }
+}
+
+fn name_script(name: &HumanName) -> Script {
+    let mut text = name.family.clone();
+    for given in &name.given {
+        text.push(' ');
+        text.push_str(given);
+    }
+    detect_script(&text)
+}
+
+fn strip_diacritics(c: char) -> String {
+    std::iter::once(c)
+        .nfd()
+        .filter(|c| !('\u{300}'..='\u{36F}').contains(c))
+        .collect()
+}
+
+fn katakana_to_hiragana(c: char) -> char {
+    match c as u32 {
+        0x30A1..=0x30F6 => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+/// Revised Romanization of a precomposed Hangul syllable
+fn romanize_hangul(c: char) -> Option<String> {
+    const INITIALS: [&str; 19] = [
+        "g", "kk", "n", "d", "tt", "r", "m", "b", "pp", "s", "ss", "", "j", "jj", "ch", "k", "t", "p", "h",
+    ];
+    const MEDIALS: [&str; 21] = [
+        "a", "ae", "ya", "yae", "eo", "e", "yeo", "ye", "o", "wa", "wae", "oe", "yo", "u", "wo", "we", "wi",
+        "yu", "eu", "ui", "i",
+    ];
+    const FINALS: [&str; 28] = [
+        "", "k", "k", "k", "n", "n", "n", "t", "l", "k", "m", "l", "l", "l", "p", "l", "m", "p", "p", "t", "t",
+        "ng", "t", "t", "k", "t", "p", "t",
+    ];
+
+    let index = (c as u32).checked_sub(0xAC00).filter(|i| *i < 11172)? as usize;
+    Some(format!(
+        "{}{}{}",
+        INITIALS[index / 588],
+        MEDIALS[(index % 588) / 28],
+        FINALS[index % 28]
+    ))
+}
+
+/// Latin letters without a canonical decomposition
+const LATIN: &[(&str, &str)] = &[
+    ("ß", "ss"), ("æ", "ae"), ("œ", "oe"), ("ø", "o"), ("ł", "l"), ("đ", "d"), ("ð", "d"), ("þ", "th"),
+    ("ı", "i"),
+];
+
+/// Russian, Ukrainian and Belarusian letters (BGN/PCGN, simplified)
+const CYRILLIC: &[(&str, &str)] = &[
+    ("а", "a"), ("б", "b"), ("в", "v"), ("г", "g"), ("д", "d"), ("е", "e"), ("ё", "e"), ("ж", "zh"),
+    ("з", "z"), ("и", "i"), ("й", "y"), ("к", "k"), ("л", "l"), ("м", "m"), ("н", "n"), ("о", "o"),
+    ("п", "p"), ("р", "r"), ("с", "s"), ("т", "t"), ("у", "u"), ("ф", "f"), ("х", "kh"), ("ц", "ts"),
+    ("ч", "ch"), ("ш", "sh"), ("щ", "shch"), ("ъ", ""), ("ы", "y"), ("ь", ""), ("э", "e"), ("ю", "yu"),
+    ("я", "ya"), ("і", "i"), ("ї", "yi"), ("є", "ye"), ("ґ", "g"), ("ў", "u"),
+];
+
+/// Modern Greek (ELOT 743, simplified)
+const GREEK: &[(&str, &str)] = &[
+    ("α", "a"), ("β", "v"), ("γ", "g"), ("δ", "d"), ("ε", "e"), ("ζ", "z"), ("η", "i"), ("θ", "th"),
+    ("ι", "i"), ("κ", "k"), ("λ", "l"), ("μ", "m"), ("ν", "n"), ("ξ", "x"), ("ο", "o"), ("π", "p"),
+    ("ρ", "r"), ("σ", "s"), ("ς", "s"), ("τ", "t"), ("υ", "y"), ("φ", "f"), ("χ", "ch"), ("ψ", "ps"),
+    ("ω", "o"), ("ά", "a"), ("έ", "e"), ("ή", "i"), ("ί", "i"), ("ό", "o"), ("ύ", "y"), ("ώ", "o"),
+    ("ϊ", "i"), ("ϋ", "y"), ("ΐ", "i"), ("ΰ", "y"), ("ου", "ou"), ("μπ", "b"), ("ντ", "d"),
+];
+
+/// Arabic consonants; short vowels are not written
+const ARABIC: &[(&str, &str)] = &[
+    ("ا", "a"), ("أ", "a"), ("إ", "i"), ("آ", "a"), ("ب", "b"), ("ت", "t"), ("ث", "th"), ("ج", "j"),
+    ("ح", "h"), ("خ", "kh"), ("د", "d"), ("ذ", "dh"), ("ر", "r"), ("ز", "z"), ("س", "s"), ("ش", "sh"),
+    ("ص", "s"), ("ض", "d"), ("ط", "t"), ("ظ", "z"), ("ع", ""), ("غ", "gh"), ("ف", "f"), ("ق", "q"),
+    ("ك", "k"), ("ل", "l"), ("م", "m"), ("ن", "n"), ("ه", "h"), ("و", "w"), ("ي", "y"), ("ى", "a"),
+    ("ة", "a"), ("ء", ""), ("ئ", ""), ("ؤ", ""), ("\u{651}", ""),
+];
+
+/// Hebrew consonants, including final forms
+const HEBREW: &[(&str, &str)] = &[
+    ("א", ""), ("ב", "b"), ("ג", "g"), ("ד", "d"), ("ה", "h"), ("ו", "v"), ("ז", "z"), ("ח", "kh"),
+    ("ט", "t"), ("י", "y"), ("כ", "k"), ("ך", "k"), ("ל", "l"), ("מ", "m"), ("ם", "m"), ("נ", "n"),
+    ("ן", "n"), ("ס", "s"), ("ע", ""), ("פ", "p"), ("ף", "f"), ("צ", "ts"), ("ץ", "ts"), ("ק", "k"),
+    ("ר", "r"), ("ש", "sh"), ("ת", "t"),
+];
+
+/// Hiragana (katakana is folded onto it) in Hepburn romanization
+const HIRAGANA: &[(&str, &str)] = &[
+    ("あ", "a"), ("い", "i"), ("う", "u"), ("え", "e"), ("お", "o"),
+    ("か", "ka"), ("き", "ki"), ("く", "ku"), ("け", "ke"), ("こ", "ko"),
+    ("が", "ga"), ("ぎ", "gi"), ("ぐ", "gu"), ("げ", "ge"), ("ご", "go"),
+    ("さ", "sa"), ("し", "shi"), ("す", "su"), ("せ", "se"), ("そ", "so"),
+    ("ざ", "za"), ("じ", "ji"), ("ず", "zu"), ("ぜ", "ze"), ("ぞ", "zo"),
+    ("た", "ta"), ("ち", "chi"), ("つ", "tsu"), ("て", "te"), ("と", "to"),
+    ("だ", "da"), ("ぢ", "ji"), ("づ", "zu"), ("で", "de"), ("ど", "do"),
+    ("な", "na"), ("に", "ni"), ("ぬ", "nu"), ("ね", "ne"), ("の", "no"),
+    ("は", "ha"), ("ひ", "hi"), ("ふ", "fu"), ("へ", "he"), ("ほ", "ho"),
+    ("ば", "ba"), ("び", "bi"), ("ぶ", "bu"), ("べ", "be"), ("ぼ", "bo"),
+    ("ぱ", "pa"), ("ぴ", "pi"), ("ぷ", "pu"), ("ぺ", "pe"), ("ぽ", "po"),
+    ("ま", "ma"), ("み", "mi"), ("む", "mu"), ("め", "me"), ("も", "mo"),
+    ("や", "ya"), ("ゆ", "yu"), ("よ", "yo"),
+    ("ら", "ra"), ("り", "ri"), ("る", "ru"), ("れ", "re"), ("ろ", "ro"),
+    ("わ", "wa"), ("を", "o"), ("ん", "n"), ("ー", ""),
+    ("きゃ", "kya"), ("きゅ", "kyu"), ("きょ", "kyo"), ("ぎゃ", "gya"), ("ぎゅ", "gyu"), ("ぎょ", "gyo"),
+    ("しゃ", "sha"), ("しゅ", "shu"), ("しょ", "sho"), ("じゃ", "ja"), ("じゅ", "ju"), ("じょ", "jo"),
+    ("ちゃ", "cha"), ("ちゅ", "chu"), ("ちょ", "cho"), ("にゃ", "nya"), ("にゅ", "nyu"), ("にょ", "nyo"),
+    ("ひゃ", "hya"), ("ひゅ", "hyu"), ("ひょ", "hyo"), ("びゃ", "bya"), ("びゅ", "byu"), ("びょ", "byo"),
+    ("ぴゃ", "pya"), ("ぴゅ", "pyu"), ("ぴょ", "pyo"), ("みゃ", "mya"), ("みゅ", "myu"), ("みょ", "myo"),
+    ("りゃ", "rya"), ("りゅ", "ryu"), ("りょ", "ryo"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matching::algorithms::name_matching::match_names;
+
+    fn name(family: &str, given: &str) -> HumanName {
+        HumanName {
+            use_type: None,
+            family: family.to_string(),
+            given: vec![given.to_string()],
+            prefix: vec![],
+            suffix: vec![],
+        }
+    }
+
+    fn romanized(t: &Transliterator, name1: HumanName, name2: HumanName) -> (HumanName, HumanName) {
+        let (a, b) = t.comparable_names(&name1, &name2);
+        (a.into_owned(), b.into_owned())
+    }
+
+    #[test]
+    fn test_detect_script() {
+        assert_eq!(detect_script("Ivanov"), Script::Latin);
+        assert_eq!(detect_script("Иванов"), Script::Cyrillic);
+        assert_eq!(detect_script("محمد"), Script::Arabic);
+        assert_eq!(detect_script("김민준"), Script::Hangul);
+        assert_eq!(detect_script("王"), Script::Han);
+        assert_eq!(detect_script("123-"), Script::Latin);
+    }
+
+    #[test]
+    fn test_to_latin() {
+        let t = Transliterator::default();
+        assert_eq!(t.to_latin("Иванов"), "ivanov");
+        assert_eq!(t.to_latin("Щербаков"), "shcherbakov");
+        assert_eq!(t.to_latin("Παπαδόπουλος"), "papadopoulos");
+        assert_eq!(t.to_latin("김민준"), "gimminjun");
+        assert_eq!(t.to_latin("やまもと"), "yamamoto");
+        assert_eq!(t.to_latin("キョウコ"), "kyouko");
+        assert_eq!(t.to_latin("はっとり"), "hattori");
+        assert_eq!(t.to_latin("José Müller"), "jose muller");
+        assert_eq!(t.to_latin("Strauß"), "strauss");
+    }
+
+    #[test]
+    fn test_cross_script_names_match() {
+        let t = Transliterator::default();
+
+        let (a, b) = romanized(&t, name("Иванов", "Сергей"), name("Ivanov", "Sergey"));
+        assert_eq!(match_names(&a, &b), 1.0);
+
+        let (a, b) = romanized(&t, name("محمد", "علي"), name("Muhammad", "Ali"));
+        assert_eq!(a.family, b.family);
+        assert!(match_names(&a, &b) > 0.9);
+
+        // Latin names are compared as written
+        let (jose, ana) = (name("José", "Ana"), name("Jose", "Ana"));
+        let (a, _) = t.comparable_names(&jose, &ana);
+        assert!(matches!(a, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_configured_tables() {
+        let config = TransliterationConfig {
+            enabled: true,
+            tables: BTreeMap::from([(
+                Script::Han,
+                BTreeMap::from([("王".to_string(), "Wang".to_string()), ("芳".to_string(), "fang".to_string())]),
+            )]),
+        };
+        let t = Transliterator::from_config(&config);
+
+        let (a, b) = romanized(&t, name("王", "芳"), name("Wang", "Fang"));
+        assert_eq!(match_names(&a, &b), 1.0);
+        // Built-in tables are kept
+        assert_eq!(t.to_latin("Иванов"), "ivanov");
+
+        let disabled = Transliterator::from_config(&TransliterationConfig { enabled: false, ..config });
+        let (a, _) = romanized(&disabled, name("王", "芳"), name("Wang", "Fang"));
+        assert_eq!(a.family, "王");
+    }
+}
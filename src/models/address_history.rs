@@ -0,0 +1,52 @@
+//! Former addresses
+//!
+//! Patients move, and a feed that still has the old address should still
+//! find them. When an update drops one of a patient's addresses, the
+//! address is kept with the end of its period set, so matching can compare
+//! against where the patient used to live.
+
+use chrono::{DateTime, Utc};
+
+use super::{Address, Patient, Period};
+
+impl Address {
+    /// When the patient stopped using the address, if they have
+    pub fn ended(&self) -> Option<DateTime<Utc>> {
+        self.period.and_then(|period| period.end)
+    }
+
+    /// Whether the patient still uses the address
+    pub fn is_current(&self) -> bool {
+        self.ended().is_none()
+    }
+}
+
+impl Patient {
+    /// Keep `old`'s addresses this version no longer has, as of `now`
+    ///
+    /// A current address that was dropped is kept with its period ended at
+    /// `now`; a former address is kept as it was, and one sent back as
+    /// former keeps the period on file. Former addresses follow the current
+    /// ones.
+    pub fn keep_address_history_from(&mut self, old: &Patient, now: DateTime<Utc>) {
+        for address in self.addresses.iter_mut().filter(|address| !address.is_current()) {
+            if let Some(on_file) = old.addresses.iter().find(|o| !o.is_current() && o.same_as(address)) {
+                address.period = on_file.period;
+            }
+        }
+        for address in &old.addresses {
+            if self.addresses.iter().any(|kept| kept.same_as(address)) {
+                continue;
+            }
+            let mut former = address.clone();
+            if former.is_current() {
+                former.period = Some(Period {
+                    start: address.period.and_then(|period| period.start),
+                    end: Some(now),
+                });
+            }
+            self.addresses.push(former);
+        }
+        self.addresses.sort_by_key(|address| address.ended().map(std::cmp::Reverse));
+    }
+}
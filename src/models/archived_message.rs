@@ -0,0 +1,47 @@
+//! Archived inbound message model definition
+//!
+//! The raw payload behind each create and update is kept as it arrived, so
+//! a steward can see exactly what a source system sent when a record looks
+//! wrong.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use utoipa::ToSchema;
+
+/// Channel of a payload received through the REST API
+pub const CHANNEL_REST: &str = "rest";
+
+/// Channel of a payload received through the FHIR API
+pub const CHANNEL_FHIR: &str = "fhir";
+
+/// Channel of a payload received over the HL7 v2 MLLP listener
+pub const CHANNEL_HL7: &str = "hl7";
+
+/// The payload that created or updated a patient
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ArchivedMessage {
+    /// Unique archive entry identifier
+    pub id: Uuid,
+
+    /// Patient the payload created or updated
+    pub patient_id: Uuid,
+
+    /// Audit log entry of the change, when one was recorded
+    pub audit_log_id: Option<Uuid>,
+
+    /// Audit action of the change, "CREATE" or "UPDATE"
+    pub action: String,
+
+    /// Channel the payload arrived on: "rest", "fhir" or "hl7"
+    pub channel: String,
+
+    /// Media type of the payload, e.g. "application/fhir+json"
+    pub content_type: String,
+
+    /// The payload exactly as received
+    pub payload: String,
+
+    /// When the payload was received
+    pub received_at: DateTime<Utc>,
+}
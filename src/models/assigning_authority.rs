@@ -0,0 +1,88 @@
+//! Assigning authority model definition
+//!
+//! An assigning authority is the facility or agency that issues
+//! identifiers in one namespace, such as a hospital's MRNs or the SSA's
+//! SSNs. The registry names the authority behind an identifier `system`,
+//! and decides which systems are accepted and how far MRN uniqueness reaches.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use utoipa::ToSchema;
+
+use super::Identifier;
+
+/// A registered issuer of identifiers
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AssigningAuthority {
+    /// Unique authority identifier
+    pub id: Uuid,
+
+    /// Identifier system the authority issues under (e.g. "urn:oid:1.2.3" or "GENERAL")
+    pub system: String,
+
+    /// OID of the authority, also accepted as a system bare or as `urn:oid:<oid>`
+    pub oid: Option<String>,
+
+    /// Name shown to people (e.g. "General Hospital")
+    pub name: String,
+
+    /// Whether identifiers from this authority are accepted
+    pub active: bool,
+
+    /// Created timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// Last updated timestamp
+    pub updated_at: DateTime<Utc>,
+}
+
+impl AssigningAuthority {
+    /// Every identifier system that names this authority
+    pub fn systems(&self) -> Vec<String> {
+        let mut systems = vec![self.system.clone()];
+        if let Some(oid) = &self.oid {
+            systems.push(oid.clone());
+            systems.push(format!("urn:oid:{}", oid));
+        }
+        systems.dedup();
+        systems
+    }
+
+    /// Whether `system` names this authority
+    pub fn issues(&self, system: &str) -> bool {
+        self.system == system
+            || self.oid.as_deref().is_some_and(|oid| {
+                system == oid || system.strip_prefix("urn:oid:") == Some(oid)
+            })
+    }
+}
+
+/// Registered assigning authorities, looked up by identifier system
+#[derive(Debug, Clone, Default)]
+pub struct AuthorityRegistry {
+    authorities: Vec<AssigningAuthority>,
+}
+
+impl AuthorityRegistry {
+    /// Registry of `authorities`
+    pub fn new(authorities: Vec<AssigningAuthority>) -> Self {
+        Self { authorities }
+    }
+
+    /// Whether no authority is registered
+    pub fn is_empty(&self) -> bool {
+        self.authorities.is_empty()
+    }
+
+    /// The authority named by `system`
+    pub fn find(&self, system: &str) -> Option<&AssigningAuthority> {
+        self.authorities.iter().find(|authority| authority.issues(system))
+    }
+
+    /// The authority behind an identifier, by its system or else its assigner
+    pub fn for_identifier(&self, identifier: &Identifier) -> Option<&AssigningAuthority> {
+        self.find(&identifier.system)
+            .or_else(|| identifier.assigner.as_deref().and_then(|assigner| self.find(assigner)))
+    }
+}
@@ -0,0 +1,131 @@
+//! Patient change request model definition
+//!
+//! Patients may not write to their records, but they spot mistakes in them.
+//! A correction proposed through a patient portal waits as a change request
+//! until a data steward approves it, which applies it to the record, or
+//! rejects it.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use utoipa::ToSchema;
+
+use super::{Address, ContactPoint, Gender, HumanName, Patient};
+
+/// State of a request waiting for a steward
+pub const CHANGE_PENDING: &str = "pending";
+
+/// State of a request applied to the patient
+pub const CHANGE_APPROVED: &str = "approved";
+
+/// State of a request a steward turned down
+pub const CHANGE_REJECTED: &str = "rejected";
+
+/// Source recorded against fields set by an approved request
+pub const CHANGE_REQUEST_SOURCE: &str = "change-request";
+
+/// Demographic corrections a patient may propose; fields left out are unchanged
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct DemographicChanges {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<HumanName>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gender: Option<Gender>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gender_identity: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pronouns: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub birth_date: Option<NaiveDate>,
+
+    /// Replaces all of the patient's contact points
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub telecom: Option<Vec<ContactPoint>>,
+
+    /// Replaces all of the patient's addresses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub addresses: Option<Vec<Address>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub marital_status: Option<String>,
+}
+
+impl DemographicChanges {
+    /// Whether no field would change
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.gender.is_none()
+            && self.gender_identity.is_none()
+            && self.pronouns.is_none()
+            && self.birth_date.is_none()
+            && self.telecom.is_none()
+            && self.addresses.is_none()
+            && self.marital_status.is_none()
+    }
+
+    /// Overwrite `patient`'s fields with the proposed ones
+    pub fn apply_to(&self, patient: &mut Patient) {
+        if let Some(name) = &self.name {
+            patient.name = name.clone();
+        }
+        if let Some(gender) = self.gender {
+            patient.gender = gender;
+        }
+        if let Some(gender_identity) = &self.gender_identity {
+            patient.gender_identity = Some(gender_identity.clone());
+        }
+        if let Some(pronouns) = &self.pronouns {
+            patient.pronouns = Some(pronouns.clone());
+        }
+        if let Some(birth_date) = self.birth_date {
+            patient.birth_date = Some(birth_date);
+        }
+        if let Some(telecom) = &self.telecom {
+            patient.telecom = telecom.clone();
+        }
+        if let Some(addresses) = &self.addresses {
+            patient.addresses = addresses.clone();
+        }
+        if let Some(marital_status) = &self.marital_status {
+            patient.marital_status = Some(marital_status.clone());
+        }
+    }
+}
+
+/// A proposed correction to a patient's demographics
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ChangeRequest {
+    /// Unique change request identifier
+    pub id: Uuid,
+
+    /// Patient the correction is for
+    pub patient_id: Uuid,
+
+    /// Fields to change and their proposed values
+    pub changes: DemographicChanges,
+
+    /// Why the patient says the record is wrong
+    pub reason: Option<String>,
+
+    /// User who submitted the request, from `X-User-Id`
+    pub submitted_by: Option<String>,
+
+    /// "pending", "approved" or "rejected"
+    pub status: String,
+
+    /// When the request was submitted
+    pub submitted_at: DateTime<Utc>,
+
+    /// Steward who approved or rejected the request
+    pub reviewed_by: Option<String>,
+
+    /// When the request was approved or rejected
+    pub reviewed_at: Option<DateTime<Utc>>,
+
+    /// Steward's note to the patient, e.g. why it was rejected
+    pub review_note: Option<String>,
+}
@@ -0,0 +1,88 @@
+//! Confidentiality of patient records
+//!
+//! Staff, public figures and patients under protection orders are flagged by
+//! a privacy officer. Users without a privileged role see such records in
+//! searches only [`masked`](Patient::masked) or not at all, and every
+//! unmasked access is audited.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{Gender, HumanName, NameUse, Patient};
+
+/// How closely access to a patient's record is guarded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidentiality {
+    #[default]
+    Normal,
+    /// Shown only to privileged users, e.g. staff or protected patients
+    Restricted,
+    /// Restricted, and of interest to the press, e.g. public figures
+    Vip,
+}
+
+impl Confidentiality {
+    /// Stored form, as in `patients.confidentiality`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Confidentiality::Normal => "normal",
+            Confidentiality::Restricted => "restricted",
+            Confidentiality::Vip => "vip",
+        }
+    }
+
+    /// Parse the stored form
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "normal" => Some(Confidentiality::Normal),
+            "restricted" => Some(Confidentiality::Restricted),
+            "vip" => Some(Confidentiality::Vip),
+            _ => None,
+        }
+    }
+
+    /// Whether access is limited to privileged users
+    pub fn is_restricted(&self) -> bool {
+        *self != Confidentiality::Normal
+    }
+}
+
+impl std::fmt::Display for Confidentiality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Patient {
+    /// The record as shown to users not allowed to see it: its ID, status
+    /// and confidentiality, with every demographic field removed
+    pub fn masked(&self) -> Patient {
+        Patient {
+            identifiers: Vec::new(),
+            name: HumanName {
+                use_type: Some(NameUse::Anonymous),
+                family: String::new(),
+                given: Vec::new(),
+                prefix: Vec::new(),
+                suffix: Vec::new(),
+            },
+            additional_names: Vec::new(),
+            telecom: Vec::new(),
+            gender: Gender::Unknown,
+            gender_identity: None,
+            pronouns: None,
+            birth_date: None,
+            deceased_datetime: None,
+            addresses: Vec::new(),
+            marital_status: None,
+            multiple_birth: None,
+            photo: Vec::new(),
+            photo_hashes: Vec::new(),
+            managing_organization: None,
+            links: Vec::new(),
+            contacts: Vec::new(),
+            ..self.clone()
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Duplicate candidate model definition
+//!
+//! A duplicate candidate is a pair of enterprise records that scored at or
+//! above the match threshold and waits in the review queue for a data
+//! steward to decide whether they are the same person. Each decision is
+//! kept as a [`ReviewDecision`], so a decided pair can be reopened and its
+//! history shows who decided what and why.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use utoipa::ToSchema;
+
+/// Review state of a newly queued pair
+pub const PENDING_REVIEW: &str = "pending";
+
+/// Review state of a pair a steward found to be the same person
+pub const DUPLICATE_CONFIRMED: &str = "confirmed";
+
+/// Review state of a pair a steward found to be different people
+pub const DUPLICATE_REJECTED: &str = "rejected";
+
+/// A pair of records that may be the same patient
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DuplicateCandidate {
+    /// Unique queue entry identifier
+    pub id: Uuid,
+
+    /// The record of the pair with the lower ID
+    pub patient_id: Uuid,
+
+    /// The record of the pair with the higher ID
+    pub candidate_id: Uuid,
+
+    /// Match score when the pair was found
+    pub score: f64,
+
+    /// Review state: "pending", "confirmed" or "rejected"
+    pub status: String,
+
+    /// When the pair was queued
+    pub detected_at: DateTime<Utc>,
+
+    /// Similarity of the pair's closest photo hashes, from 0.0 to 1.0, when
+    /// both records have photos; filled in for the review queue, not stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub photo_similarity: Option<f64>,
+}
+
+impl DuplicateCandidate {
+    /// The pair's two IDs in the order they are stored
+    pub fn ordered(a: Uuid, b: Uuid) -> (Uuid, Uuid) {
+        if a < b { (a, b) } else { (b, a) }
+    }
+}
+
+/// What a steward did with a pair
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewAction {
+    /// The records are the same person
+    Confirm,
+    /// The records are different people
+    Reject,
+    /// Put a decided pair back in the queue
+    Reopen,
+}
+
+impl ReviewAction {
+    /// Stored form, as in `duplicate_decisions.action`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReviewAction::Confirm => "confirm",
+            ReviewAction::Reject => "reject",
+            ReviewAction::Reopen => "reopen",
+        }
+    }
+
+    /// Parse the stored form
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "confirm" => Some(ReviewAction::Confirm),
+            "reject" => Some(ReviewAction::Reject),
+            "reopen" => Some(ReviewAction::Reopen),
+            _ => None,
+        }
+    }
+
+    /// Review state of the pair after this action
+    pub fn status(&self) -> &'static str {
+        match self {
+            ReviewAction::Confirm => DUPLICATE_CONFIRMED,
+            ReviewAction::Reject => DUPLICATE_REJECTED,
+            ReviewAction::Reopen => PENDING_REVIEW,
+        }
+    }
+}
+
+/// Coded reason for a review decision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewReason {
+    /// Confirm: both records carry the same trusted identifier
+    SameIdentifier,
+    /// Confirm: the demographics agree closely enough on their own
+    SameDemographics,
+    /// Confirm: checked with the patient
+    ConfirmedWithPatient,
+    /// Confirm: checked with the registering source system
+    ConfirmedWithSource,
+    /// Reject: the records describe different people
+    DifferentPerson,
+    /// Reject: twins or other siblings of a multiple birth
+    MultipleBirth,
+    /// Reject: relatives sharing an address or contact details
+    FamilyMember,
+    /// Reject: an identifier was wrongly shared between the records
+    SharedIdentifier,
+    /// Reopen: new information calls the decision into question
+    NewInformation,
+    /// Reopen: the decision was recorded by mistake
+    DecidedInError,
+    /// Any action; the free-text reason is required
+    Other,
+}
+
+impl ReviewReason {
+    /// Stored form, as in `duplicate_decisions.reason_code`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReviewReason::SameIdentifier => "same_identifier",
+            ReviewReason::SameDemographics => "same_demographics",
+            ReviewReason::ConfirmedWithPatient => "confirmed_with_patient",
+            ReviewReason::ConfirmedWithSource => "confirmed_with_source",
+            ReviewReason::DifferentPerson => "different_person",
+            ReviewReason::MultipleBirth => "multiple_birth",
+            ReviewReason::FamilyMember => "family_member",
+            ReviewReason::SharedIdentifier => "shared_identifier",
+            ReviewReason::NewInformation => "new_information",
+            ReviewReason::DecidedInError => "decided_in_error",
+            ReviewReason::Other => "other",
+        }
+    }
+
+    /// Parse the stored form
+    pub fn parse(value: &str) -> Option<Self> {
+        [
+            ReviewReason::SameIdentifier,
+            ReviewReason::SameDemographics,
+            ReviewReason::ConfirmedWithPatient,
+            ReviewReason::ConfirmedWithSource,
+            ReviewReason::DifferentPerson,
+            ReviewReason::MultipleBirth,
+            ReviewReason::FamilyMember,
+            ReviewReason::SharedIdentifier,
+            ReviewReason::NewInformation,
+            ReviewReason::DecidedInError,
+            ReviewReason::Other,
+        ]
+        .into_iter()
+        .find(|reason| reason.as_str() == value)
+    }
+
+    /// Whether the reason can explain `action`
+    pub fn applies_to(&self, action: ReviewAction) -> bool {
+        match self {
+            ReviewReason::SameIdentifier
+            | ReviewReason::SameDemographics
+            | ReviewReason::ConfirmedWithPatient
+            | ReviewReason::ConfirmedWithSource => action == ReviewAction::Confirm,
+            ReviewReason::DifferentPerson
+            | ReviewReason::MultipleBirth
+            | ReviewReason::FamilyMember
+            | ReviewReason::SharedIdentifier => action == ReviewAction::Reject,
+            ReviewReason::NewInformation | ReviewReason::DecidedInError => action == ReviewAction::Reopen,
+            ReviewReason::Other => true,
+        }
+    }
+}
+
+/// A steward's decision on a queued pair
+///
+/// Decisions are only ever added; reopening a pair records a new decision
+/// rather than removing the old one.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReviewDecision {
+    /// Unique decision identifier
+    pub id: Uuid,
+
+    /// Queue entry decided on
+    pub duplicate_candidate_id: Uuid,
+
+    /// What the steward did
+    pub action: ReviewAction,
+
+    /// Coded reason, for reporting and training data
+    pub reason_code: ReviewReason,
+
+    /// Steward's explanation, required with reason code `other`
+    pub reason: Option<String>,
+
+    /// User who decided, from `X-User-Id`
+    pub decided_by: Option<String>,
+
+    /// When the decision was recorded
+    pub decided_at: DateTime<Utc>,
+}
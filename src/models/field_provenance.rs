@@ -0,0 +1,40 @@
+//! Field provenance model definition
+//!
+//! The master record is assembled from many feeds and edits. For each
+//! demographic field it keeps which source supplied the current value and
+//! when, so a steward can answer "where did this address come from" and
+//! survivorship can refuse a less authoritative source overwriting it.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Patient fields whose provenance is tracked, named as serialized
+pub const DEMOGRAPHIC_FIELDS: &[&str] = &[
+    "name",
+    "additional_names",
+    "telecom",
+    "gender",
+    "gender_identity",
+    "pronouns",
+    "birth_date",
+    "deceased",
+    "deceased_datetime",
+    "addresses",
+    "marital_status",
+    "multiple_birth",
+];
+
+/// The source of one field's current value
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FieldProvenance {
+    /// Field name, one of [`DEMOGRAPHIC_FIELDS`]
+    pub field: String,
+
+    /// Source that supplied the value: the sender's `X-Source-System`, a
+    /// FHIR `meta.source`, the HL7 sending application, or the channel
+    pub source: String,
+
+    /// When the value was stored
+    pub recorded_at: DateTime<Utc>,
+}
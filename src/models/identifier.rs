@@ -1,8 +1,11 @@
 //! Identifier model definition
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use super::{Period, VerificationStatus};
+
 /// Patient or organization identifier (MRN, SSN, NPI, etc.)
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Identifier {
@@ -20,6 +23,14 @@ pub struct Identifier {
 
     /// Organization that issued the identifier
     pub assigner: Option<String>,
+
+    /// How far the identifier has been checked
+    #[serde(default)]
+    pub verification: VerificationStatus,
+
+    /// When the identifier was valid; a retired identifier has an end
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<Period>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -84,9 +95,16 @@ impl Identifier {
             system,
             value,
             assigner: None,
+            verification: VerificationStatus::Unverified,
+            period: None,
         }
     }
 
+    /// Whether the identifier had been retired by `now`
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        self.period.is_some_and(|period| period.has_ended_at(now))
+    }
+
     /// Create a Medical Record Number identifier
     pub fn mrn(facility: String, value: String) -> Self {
         Self::new(
@@ -0,0 +1,82 @@
+//! Patient import checkpoint model definition
+//!
+//! A bulk import records how far into its file it got, so an import that
+//! crashed or was stopped resumes from there instead of from the first
+//! line. The checkpoint outlives the in-memory job that wrote it.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use utoipa::ToSchema;
+
+/// State of an import still working through its file, or that stopped
+/// without finishing
+pub const IMPORT_RUNNING: &str = "running";
+
+/// State of an import that reached the end of its file
+pub const IMPORT_COMPLETED: &str = "completed";
+
+/// State of an import that stopped on an error
+pub const IMPORT_FAILED: &str = "failed";
+
+/// How far a bulk import has got through its file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ImportCheckpoint {
+    /// Import identifier, the ID of the job that started it
+    pub id: Uuid,
+
+    /// Server-side NDJSON file being imported
+    pub path: String,
+
+    /// User or system that started the import
+    pub requested_by: Option<String>,
+
+    /// "running", "completed" or "failed"
+    pub status: String,
+
+    /// Job currently or last working on the import
+    pub job_id: Uuid,
+
+    /// Byte offset of the first line not yet processed
+    pub byte_offset: i64,
+
+    /// Lines processed so far
+    pub lines_processed: i64,
+
+    /// Patients created
+    pub created: i64,
+
+    /// Lines skipped because the patient was already stored
+    pub duplicates: i64,
+
+    /// Lines that could not be parsed or stored
+    pub invalid: i64,
+
+    /// Why the import last stopped, if it failed
+    pub error: Option<String>,
+
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ImportCheckpoint {
+    /// A checkpoint at the start of `path`
+    pub fn new(id: Uuid, path: String, requested_by: Option<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            id,
+            path,
+            requested_by,
+            status: IMPORT_RUNNING.to_string(),
+            job_id: id,
+            byte_offset: 0,
+            lines_processed: 0,
+            created: 0,
+            duplicates: 0,
+            invalid: 0,
+            error: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! Stored matching profile model definition
+//!
+//! Administrators change a profile's threshold, weights and blocking through
+//! the admin API instead of the configuration file. Every change is stored
+//! as a new version, so earlier settings can be looked up and restored.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use utoipa::ToSchema;
+
+use crate::config::MatchingProfile;
+
+/// One version of a stored matching profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct MatchingSettingsVersion {
+    /// Unique version identifier
+    pub id: Uuid,
+
+    /// Profile name; `default` holds the settings in `matching`
+    pub profile: String,
+
+    /// Version number, from 1 for each profile
+    pub version: i32,
+
+    /// Settings in effect from this version
+    pub settings: MatchingProfile,
+
+    /// User who made the change, from `X-User-Id`
+    pub changed_by: Option<String>,
+
+    /// Why the change was made
+    pub note: Option<String>,
+
+    /// When the version was stored
+    pub changed_at: DateTime<Utc>,
+}
@@ -6,11 +6,46 @@ use uuid::Uuid;
 
 pub mod patient;
 pub mod organization;
+pub mod practitioner;
 pub mod identifier;
+pub mod assigning_authority;
+pub mod duplicate_candidate;
+pub mod source_record;
+pub mod watch;
+pub mod record_lock;
+pub mod verification;
+pub mod archived_message;
+pub mod quarantined_record;
+pub mod confidentiality;
+pub mod change_request;
+pub mod field_provenance;
+pub mod period;
+pub mod address_history;
+pub mod matching_settings;
+pub mod patient_group;
+pub mod patient_status;
+pub mod import_checkpoint;
 
-pub use patient::{Patient, HumanName, NameUse, PatientLink, LinkType};
+pub use patient::{Patient, HumanName, NameUse, PatientContact, PatientLink, LinkType};
 pub use organization::Organization;
+pub use practitioner::{Practitioner, Qualification};
 pub use identifier::{Identifier, IdentifierType, IdentifierUse};
+pub use assigning_authority::{AssigningAuthority, AuthorityRegistry};
+pub use duplicate_candidate::{DuplicateCandidate, ReviewAction, ReviewDecision, ReviewReason};
+pub use source_record::{SourceRecord, SourceRecordLink, SourceSubmission};
+pub use watch::PatientWatch;
+pub use record_lock::RecordLock;
+pub use verification::VerificationStatus;
+pub use archived_message::ArchivedMessage;
+pub use quarantined_record::QuarantinedRecord;
+pub use confidentiality::Confidentiality;
+pub use change_request::{ChangeRequest, DemographicChanges};
+pub use field_provenance::FieldProvenance;
+pub use period::Period;
+pub use matching_settings::MatchingSettingsVersion;
+pub use patient_group::PatientGroup;
+pub use patient_status::PatientStatus;
+pub use import_checkpoint::ImportCheckpoint;
 
 /// Gender enumeration per FHIR specification
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
@@ -31,6 +66,12 @@ pub struct Address {
     pub state: Option<String>,
     pub postal_code: Option<String>,
     pub country: Option<String>,
+    /// How far the address has been checked
+    #[serde(default)]
+    pub verification: VerificationStatus,
+    /// When the address was in use; former addresses have an end
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<Period>,
 }
 
 /// Contact information
@@ -39,6 +80,9 @@ pub struct ContactPoint {
     pub system: ContactPointSystem,
     pub value: String,
     pub use_type: Option<ContactPointUse>,
+    /// When the contact point was in use
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<Period>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use utoipa::ToSchema;
 
-use super::{Address, ContactPoint, Gender, Identifier};
+use super::{Address, Confidentiality, ContactPoint, Gender, Identifier, PatientStatus};
 
 /// Patient resource
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -28,9 +28,17 @@ pub struct Patient {
     /// Telecom contacts
     pub telecom: Vec<ContactPoint>,
 
-    /// Gender
+    /// Administrative gender
     pub gender: Gender,
 
+    /// Self-reported gender identity, e.g. "non-binary"
+    #[serde(default)]
+    pub gender_identity: Option<String>,
+
+    /// Pronouns, e.g. "they/them"
+    #[serde(default)]
+    pub pronouns: Option<String>,
+
     /// Birth date
     pub birth_date: Option<NaiveDate>,
 
@@ -52,12 +60,31 @@ pub struct Patient {
     /// Photo attachments
     pub photo: Vec<String>,
 
+    /// Perceptual hashes of the photos, 16 hex digits each, kept when the
+    /// photos themselves are not; see [`crate::matching::photo`]
+    #[serde(default)]
+    pub photo_hashes: Vec<String>,
+
     /// Managing organization
     pub managing_organization: Option<Uuid>,
 
     /// Links to other patient records
     pub links: Vec<PatientLink>,
 
+    /// Next of kin, guardians and other people to contact about the patient
+    #[serde(default)]
+    pub contacts: Vec<PatientContact>,
+
+    /// Who may see the record; changed only by a privacy officer, and
+    /// ignored on create and update
+    #[serde(default)]
+    pub confidentiality: Confidentiality,
+
+    /// Lifecycle status; follows `active`, `deceased` and the links on
+    /// create and update, and is otherwise changed through the status endpoint
+    #[serde(default)]
+    pub status: PatientStatus,
+
     /// Created timestamp
     pub created_at: DateTime<Utc>,
 
@@ -75,7 +102,7 @@ pub struct HumanName {
     pub suffix: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum NameUse {
     Usual,
@@ -87,6 +114,21 @@ pub enum NameUse {
     Maiden,
 }
 
+impl NameUse {
+    /// Serialized form, as in FHIR `HumanName.use`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NameUse::Usual => "usual",
+            NameUse::Official => "official",
+            NameUse::Temp => "temp",
+            NameUse::Nickname => "nickname",
+            NameUse::Anonymous => "anonymous",
+            NameUse::Old => "old",
+            NameUse::Maiden => "maiden",
+        }
+    }
+}
+
 /// Patient link to another patient record
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PatientLink {
@@ -94,6 +136,32 @@ pub struct PatientLink {
     pub link_type: LinkType,
 }
 
+/// A person to contact about a patient, served as a FHIR RelatedPerson
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PatientContact {
+    /// Identifier of the contact, kept across updates
+    #[serde(default = "Uuid::new_v4")]
+    pub id: Uuid,
+
+    /// Relationship codes, from HL7 v2 table 0131 (e.g. "N" for next of
+    /// kin, "C" for emergency contact) or v3 RoleCode (e.g. "MTH", "GUARD")
+    #[serde(default)]
+    pub relationship: Vec<String>,
+
+    /// Contact name
+    pub name: Option<HumanName>,
+
+    /// Telecom contacts
+    #[serde(default)]
+    pub telecom: Vec<ContactPoint>,
+
+    /// Contact address
+    pub address: Option<Address>,
+
+    /// Administrative gender
+    pub gender: Option<Gender>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum LinkType {
@@ -119,6 +187,8 @@ impl Patient {
             additional_names: Vec::new(),
             telecom: Vec::new(),
             gender,
+            gender_identity: None,
+            pronouns: None,
             birth_date: None,
             deceased: false,
             deceased_datetime: None,
@@ -126,8 +196,12 @@ impl Patient {
             marital_status: None,
             multiple_birth: None,
             photo: Vec::new(),
+            photo_hashes: Vec::new(),
             managing_organization: None,
             links: Vec::new(),
+            contacts: Vec::new(),
+            confidentiality: Confidentiality::Normal,
+            status: PatientStatus::Active,
             created_at: now,
             updated_at: now,
         }
@@ -135,7 +209,45 @@ impl Patient {
 
     /// Get full name as a string
     pub fn full_name(&self) -> String {
-        let given = self.name.given.join(" ");
-        format!("{} {}", given, self.name.family)
+        self.name.display()
+    }
+
+    /// The legal name, used for matching
+    ///
+    /// This is the primary name unless it is marked as usual or a nickname
+    /// and an official name is recorded alongside it.
+    pub fn legal_name(&self) -> &HumanName {
+        match self.name.use_type {
+            Some(NameUse::Usual) | Some(NameUse::Nickname) => self
+                .additional_names
+                .iter()
+                .find(|n| n.use_type == Some(NameUse::Official))
+                .unwrap_or(&self.name),
+            _ => &self.name,
+        }
+    }
+
+    /// The name the patient goes by, when it differs from the legal name
+    pub fn preferred_name(&self) -> Option<&HumanName> {
+        let legal = self.legal_name();
+        std::iter::once(&self.name)
+            .chain(&self.additional_names)
+            .find(|n| n.use_type == Some(NameUse::Usual) && !std::ptr::eq(*n, legal))
+    }
+}
+
+impl HumanName {
+    /// Given names followed by the family name
+    pub fn display(&self) -> String {
+        format!("{} {}", self.given.join(" "), self.family)
+    }
+
+    /// Suffixes lowercased without punctuation, so "Jr." and "JR" compare equal
+    pub fn suffix_keys(&self) -> Vec<String> {
+        self.suffix
+            .iter()
+            .map(|suffix| suffix.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect())
+            .filter(|key: &String| !key.is_empty())
+            .collect()
     }
 }
@@ -0,0 +1,49 @@
+//! Patient group model definition
+//!
+//! A group is a named cohort of patients, such as the records of a dedup
+//! cluster or the patients a batch match job found, kept so the same set
+//! can be exported or matched again. Groups are exposed as FHIR `Group`
+//! resources.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use utoipa::ToSchema;
+
+/// A named set of patients
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PatientGroup {
+    /// Unique group identifier
+    pub id: Uuid,
+
+    /// Name shown to users
+    pub name: String,
+
+    /// What the group holds and why
+    pub description: Option<String>,
+
+    /// Member patient IDs, in the order they were added
+    pub members: Vec<Uuid>,
+
+    /// User who created the group, from `X-User-Id`
+    pub created_by: Option<String>,
+
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PatientGroup {
+    /// A new empty group
+    pub fn new(name: String, description: Option<String>, created_by: Option<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            description,
+            members: Vec::new(),
+            created_by,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Patient lifecycle status
+//!
+//! A patient is active, inactive, deceased, merged into another record, or
+//! entered in error. The status is stored alongside the `active` and
+//! `deceased` flags FHIR expects and kept in step with them. Creates take
+//! the status the flags imply; updates may only move it along an allowed
+//! [transition](PatientStatus::can_transition_to), and entering a record in
+//! error is only possible through the status endpoint.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{LinkType, Patient};
+use crate::{Error, Result};
+
+/// Where a patient record is in its lifecycle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum PatientStatus {
+    #[default]
+    Active,
+    /// No longer in use, e.g. a record retired by its source
+    Inactive,
+    Deceased,
+    /// Merged into the record its `ReplacedBy` link points at; final
+    Merged,
+    /// Created in error, e.g. a test record or one registered twice by mistake
+    EnteredInError,
+}
+
+impl PatientStatus {
+    /// Stored form, as in `patients.status`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PatientStatus::Active => "active",
+            PatientStatus::Inactive => "inactive",
+            PatientStatus::Deceased => "deceased",
+            PatientStatus::Merged => "merged",
+            PatientStatus::EnteredInError => "entered-in-error",
+        }
+    }
+
+    /// Parse the stored form
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "active" => Some(PatientStatus::Active),
+            "inactive" => Some(PatientStatus::Inactive),
+            "deceased" => Some(PatientStatus::Deceased),
+            "merged" => Some(PatientStatus::Merged),
+            "entered-in-error" => Some(PatientStatus::EnteredInError),
+            _ => None,
+        }
+    }
+
+    /// Status implied by a patient's `active` and `deceased` flags and links
+    ///
+    /// A record is never implied to be entered in error.
+    pub fn implied_by(patient: &Patient) -> Self {
+        let replaced = patient.links.iter().any(|link| matches!(link.link_type, LinkType::ReplacedBy));
+        if !patient.active && replaced {
+            PatientStatus::Merged
+        } else if patient.deceased || patient.deceased_datetime.is_some() {
+            PatientStatus::Deceased
+        } else if !patient.active {
+            PatientStatus::Inactive
+        } else {
+            PatientStatus::Active
+        }
+    }
+
+    /// Whether a patient may move from this status to `to`
+    ///
+    /// Merged records never change status again. Deceased records only
+    /// return to active to correct a wrongly reported death.
+    pub fn can_transition_to(&self, to: PatientStatus) -> bool {
+        use PatientStatus::*;
+        match (self, to) {
+            (from, to) if *from == to => true,
+            (Merged, _) => false,
+            (_, EnteredInError) => true,
+            (Active, Inactive | Deceased | Merged) => true,
+            (Inactive, Active | Deceased | Merged) => true,
+            (Deceased, Active | Merged) => true,
+            (EnteredInError, Active | Inactive) => true,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for PatientStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Patient {
+    /// Move the patient to `status`, setting the `active` and `deceased`
+    /// flags to match
+    ///
+    /// `deceased_datetime` is recorded when moving to deceased. Fails with a
+    /// validation error if the transition is not allowed.
+    pub fn transition_to(&mut self, status: PatientStatus, deceased_datetime: Option<DateTime<Utc>>) -> Result<()> {
+        if !self.status.can_transition_to(status) {
+            return Err(Error::Validation(format!(
+                "Patient {} cannot move from {} to {}",
+                self.id, self.status, status
+            )));
+        }
+        match status {
+            PatientStatus::Active => {
+                self.active = true;
+                self.deceased = false;
+                self.deceased_datetime = None;
+            }
+            PatientStatus::Deceased => {
+                self.active = true;
+                self.deceased = true;
+                self.deceased_datetime = deceased_datetime.or(self.deceased_datetime);
+            }
+            PatientStatus::Inactive | PatientStatus::Merged | PatientStatus::EnteredInError => {
+                self.active = false;
+            }
+        }
+        self.status = status;
+        Ok(())
+    }
+
+    /// Take the status of the stored record, moved to the one this
+    /// record's flags imply if they imply a different one than before
+    ///
+    /// Clients cannot set the status directly through an update. Fails with
+    /// a validation error if the flags ask for a transition that is not
+    /// allowed, e.g. reactivating a merged record.
+    pub fn keep_status_from(&mut self, old: &Patient) -> Result<()> {
+        let implied = PatientStatus::implied_by(self);
+        if implied == PatientStatus::implied_by(old) {
+            self.status = old.status;
+            return Ok(());
+        }
+        if !old.status.can_transition_to(implied) {
+            return Err(Error::Validation(format!(
+                "Patient {} cannot move from {} to {}",
+                self.id, old.status, implied
+            )));
+        }
+        self.status = implied;
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+//! Validity periods
+//!
+//! Identifiers, addresses and contact points carry the span they were valid
+//! for, as FHIR's `Period` does. Feeds often supply these dates, e.g. when
+//! an MRN was retired or a phone number stopped being used.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Time span a value was in use
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Period {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<DateTime<Utc>>,
+
+    /// When the value stopped being used; set for former values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<DateTime<Utc>>,
+}
+
+impl Period {
+    /// A period with the given bounds, or `None` when neither is known
+    pub fn from_bounds(start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) -> Option<Self> {
+        (start.is_some() || end.is_some()).then_some(Self { start, end })
+    }
+
+    /// Whether the period had ended by `now`
+    pub fn has_ended_at(&self, now: DateTime<Utc>) -> bool {
+        self.end.is_some_and(|end| end <= now)
+    }
+}
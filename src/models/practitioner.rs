@@ -0,0 +1,133 @@
+//! Practitioner model definition
+//!
+//! Providers are cross-referenced alongside patients: referrals, results and
+//! claims name the ordering or attending practitioner, and each feed keeps
+//! its own provider master. A practitioner is identified by their NPI where
+//! they have one.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use utoipa::ToSchema;
+
+use super::{Address, ContactPoint, Gender, HumanName, Identifier, IdentifierType, Patient};
+
+/// Identifier system of the US National Provider Identifier
+pub const NPI_SYSTEM: &str = "http://hl7.org/fhir/sid/us-npi";
+
+/// Healthcare provider resource
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Practitioner {
+    /// Unique practitioner identifier
+    pub id: Uuid,
+
+    /// Practitioner identifiers (NPI, state license, etc.)
+    pub identifiers: Vec<Identifier>,
+
+    /// Active status
+    pub active: bool,
+
+    /// Practitioner name
+    pub name: HumanName,
+
+    /// Additional names
+    #[serde(default)]
+    pub additional_names: Vec<HumanName>,
+
+    /// Telecom contacts
+    #[serde(default)]
+    pub telecom: Vec<ContactPoint>,
+
+    /// Practice addresses
+    #[serde(default)]
+    pub addresses: Vec<Address>,
+
+    /// Administrative gender
+    #[serde(default)]
+    pub gender: Option<Gender>,
+
+    /// Licenses, certifications and degrees
+    #[serde(default)]
+    pub qualifications: Vec<Qualification>,
+
+    /// Created timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// Updated timestamp
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A license, certification or degree held by a practitioner
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Qualification {
+    /// Code system of `code`, e.g. the NUCC taxonomy or v2 table 0360
+    pub system: Option<String>,
+
+    /// Qualification code, e.g. "MD" or "207R00000X"
+    pub code: String,
+
+    /// Human-readable name of the qualification
+    pub display: Option<String>,
+
+    /// Identifier of the qualification, e.g. a state license number
+    pub identifier: Option<String>,
+
+    /// Organization that issued it, e.g. a state medical board
+    pub issuer: Option<String>,
+
+    /// First day it is valid
+    pub valid_from: Option<NaiveDate>,
+
+    /// Last day it is valid
+    pub valid_until: Option<NaiveDate>,
+}
+
+impl Practitioner {
+    /// Create a new practitioner
+    pub fn new(name: HumanName) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            identifiers: Vec::new(),
+            active: true,
+            name,
+            additional_names: Vec::new(),
+            telecom: Vec::new(),
+            addresses: Vec::new(),
+            gender: None,
+            qualifications: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// The practitioner's NPI, if they have one
+    pub fn npi(&self) -> Option<&str> {
+        self.identifiers
+            .iter()
+            .find(|id| id.identifier_type == IdentifierType::NPI)
+            .map(|id| id.value.trim())
+    }
+
+    /// Get full name as a string
+    pub fn full_name(&self) -> String {
+        self.name.display()
+    }
+
+    /// The practitioner as a patient record with no birth date
+    ///
+    /// Lets the patient matching and FHIR element mapping handle the
+    /// elements both resources share.
+    pub fn as_patient_record(&self) -> Patient {
+        let mut record = Patient::new(self.name.clone(), self.gender.unwrap_or(Gender::Unknown));
+        record.id = self.id;
+        record.identifiers = self.identifiers.clone();
+        record.active = self.active;
+        record.additional_names = self.additional_names.clone();
+        record.telecom = self.telecom.clone();
+        record.addresses = self.addresses.clone();
+        record.created_at = self.created_at;
+        record.updated_at = self.updated_at;
+        record
+    }
+}
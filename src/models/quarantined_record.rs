@@ -0,0 +1,55 @@
+//! Quarantined inbound record model definition
+//!
+//! An inbound payload that failed validation is parked with the reasons it
+//! was refused, so a data steward can correct and resubmit it or discard it
+//! instead of the record silently going missing.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use utoipa::ToSchema;
+
+/// State of a record waiting for a steward
+pub const QUARANTINE_PENDING: &str = "pending";
+
+/// State of a record stored after being resubmitted
+pub const QUARANTINE_RESUBMITTED: &str = "resubmitted";
+
+/// State of a record a steward threw away
+pub const QUARANTINE_DISCARDED: &str = "discarded";
+
+/// An inbound payload refused by validation
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct QuarantinedRecord {
+    /// Unique quarantine entry identifier
+    pub id: Uuid,
+
+    /// Channel the payload arrived on: "fhir" or "hl7"
+    pub channel: String,
+
+    /// Media type of the payload
+    pub content_type: String,
+
+    /// The payload as received, or as last edited by a steward
+    pub payload: String,
+
+    /// Why the payload was refused: an OperationOutcome for FHIR, the
+    /// ERR segment details for HL7
+    #[schema(value_type = Object)]
+    pub errors: serde_json::Value,
+
+    /// Patient the payload was updating, when it was an update
+    pub patient_id: Option<Uuid>,
+
+    /// "pending", "resubmitted" or "discarded"
+    pub status: String,
+
+    /// When the payload was first refused
+    pub received_at: DateTime<Utc>,
+
+    /// When the record was resubmitted or discarded
+    pub resolved_at: Option<DateTime<Utc>>,
+
+    /// Patient stored from the resubmitted payload
+    pub resolved_patient_id: Option<Uuid>,
+}
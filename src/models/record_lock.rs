@@ -0,0 +1,35 @@
+//! Record lock model definition
+//!
+//! A lock reserves a patient record for one data steward while they review
+//! or merge it. Locks are advisory and expire on their own.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use utoipa::ToSchema;
+
+/// A steward's hold on a patient record
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RecordLock {
+    /// Locked patient
+    pub patient_id: Uuid,
+
+    /// Steward or system holding the lock
+    pub locked_by: String,
+
+    /// Why the record is locked (e.g. "Reviewing duplicate pair")
+    pub reason: Option<String>,
+
+    /// When the holder first acquired the lock
+    pub acquired_at: DateTime<Utc>,
+
+    /// When the lock lapses unless renewed
+    pub expires_at: DateTime<Utc>,
+}
+
+impl RecordLock {
+    /// Whether a writer identifying as `holder` may change the record
+    pub fn permits(&self, holder: Option<&str>) -> bool {
+        holder == Some(self.locked_by.as_str())
+    }
+}
@@ -0,0 +1,144 @@
+//! Source record model definition
+//!
+//! A source record is the patient data exactly as a feed submitted it. Source
+//! records are never edited; the enterprise record (`Patient`) is curated from
+//! them and linked back through `SourceRecordLink`.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use utoipa::ToSchema;
+
+use super::{IdentifierType, Patient};
+
+/// Immutable patient record as received from a source system
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SourceRecord {
+    /// Unique source record identifier
+    pub id: Uuid,
+
+    /// Submitting system (e.g. "lab-feed", "adt-hospital-a")
+    pub source_system: String,
+
+    /// Record identifier within the source system
+    pub source_record_id: String,
+
+    /// Demographics as submitted
+    pub patient: Patient,
+
+    /// Received timestamp
+    pub received_at: DateTime<Utc>,
+
+    /// User or system that submitted the record
+    pub received_by: Option<String>,
+}
+
+/// A feed submission, kept as a source record linked to the enterprise
+/// record it created or updated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceSubmission {
+    /// Submitting system
+    pub source_system: String,
+
+    /// Record identifier within the source system
+    pub source_record_id: String,
+
+    /// User or system that submitted the record
+    pub received_by: Option<String>,
+}
+
+impl SourceSubmission {
+    /// Submission of `patient` by `source_system`, identified within the
+    /// source by `source_record_id` or, when the source gave none, by the
+    /// patient's first MRN or else its ID
+    pub fn new(source_system: &str, source_record_id: Option<&str>, patient: &Patient) -> Self {
+        let source_record_id = source_record_id
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+            .or_else(|| {
+                patient
+                    .identifiers
+                    .iter()
+                    .find(|identifier| identifier.identifier_type == IdentifierType::MRN)
+                    .map(|identifier| identifier.value.clone())
+            })
+            .unwrap_or_else(|| patient.id.to_string());
+        Self {
+            source_system: source_system.to_string(),
+            source_record_id,
+            received_by: None,
+        }
+    }
+
+    /// Record who submitted it
+    pub fn with_received_by(mut self, received_by: Option<String>) -> Self {
+        self.received_by = received_by;
+        self
+    }
+}
+
+/// Link from a source record to the enterprise record it contributes to
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SourceRecordLink {
+    /// Unique link identifier
+    pub id: Uuid,
+
+    /// Linked source record
+    pub source_record_id: Uuid,
+
+    /// Enterprise (master) patient record
+    pub patient_id: Uuid,
+
+    /// Match score that justified the link, if it was made by the matcher
+    pub match_score: Option<f64>,
+
+    /// Linked timestamp
+    pub linked_at: DateTime<Utc>,
+
+    /// User or system that created the link
+    pub linked_by: Option<String>,
+
+    /// Set when the link is superseded by a re-link or removed
+    pub unlinked_at: Option<DateTime<Utc>>,
+
+    /// User or system that ended the link
+    pub unlinked_by: Option<String>,
+
+    /// Confidence in the link, from 0.0 to 1.0
+    ///
+    /// Recorded from the match score when the link is made, or 1.0 for links
+    /// made without one, and lowered by the link confidence job as the
+    /// enterprise record's demographics drift from the source record's.
+    #[serde(default = "full_confidence")]
+    pub confidence: f64,
+
+    /// Set on automatic links whose confidence fell below
+    /// `link_confidence.reverify_below`, until a steward verifies them
+    #[serde(default)]
+    pub needs_reverification: bool,
+
+    /// When a steward last verified the link
+    #[serde(default)]
+    pub verified_at: Option<DateTime<Utc>>,
+
+    /// Steward who last verified the link
+    #[serde(default)]
+    pub verified_by: Option<String>,
+}
+
+fn full_confidence() -> f64 {
+    1.0
+}
+
+impl SourceRecordLink {
+    /// Whether this is the current link for its source record
+    pub fn is_active(&self) -> bool {
+        self.unlinked_at.is_none()
+    }
+
+    /// Confidence recorded for a new link with the given match score
+    pub fn initial_confidence(match_score: Option<f64>) -> f64 {
+        match_score.map_or(1.0, |score| score.clamp(0.0, 1.0))
+    }
+}
@@ -0,0 +1,109 @@
+//! Verification status of demographic fields
+//!
+//! Identifiers and addresses each carry how far they have been checked.
+//! Statuses are ordered, weakest first, so a verified value is never
+//! silently downgraded when the same value arrives again unverified.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{Address, Identifier, Patient};
+
+/// How far an identifier or address has been checked
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum VerificationStatus {
+    /// Recorded as received
+    #[default]
+    Unverified,
+    /// Confirmed by the patient at registration or check-in
+    PatientConfirmed,
+    /// Checked against a document such as a card, license or passport
+    DocumentVerified,
+}
+
+impl VerificationStatus {
+    /// Stored form, as in the `verification_status` columns
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerificationStatus::Unverified => "unverified",
+            VerificationStatus::PatientConfirmed => "patient-confirmed",
+            VerificationStatus::DocumentVerified => "document-verified",
+        }
+    }
+
+    /// Parse the stored form
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "unverified" => Some(VerificationStatus::Unverified),
+            "patient-confirmed" => Some(VerificationStatus::PatientConfirmed),
+            "document-verified" => Some(VerificationStatus::DocumentVerified),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for VerificationStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Identifier {
+    /// Whether `other` records the same identifier, ignoring case and formatting
+    pub fn same_as(&self, other: &Identifier) -> bool {
+        self.identifier_type == other.identifier_type
+            && self.system == other.system
+            && comparable(&self.value) == comparable(&other.value)
+    }
+}
+
+impl Address {
+    /// Whether `other` records the same address, ignoring case and formatting
+    pub fn same_as(&self, other: &Address) -> bool {
+        let fields = |a: &Address| {
+            [&a.line1, &a.line2, &a.city, &a.state, &a.postal_code, &a.country]
+                .map(|field| field.as_deref().map(comparable).unwrap_or_default())
+        };
+        fields(self) == fields(other)
+    }
+}
+
+impl Patient {
+    /// Keep the stronger status for identifiers and addresses also on `other`
+    ///
+    /// Used when a record is replaced by a new version or absorbs a merged
+    /// duplicate, so a document check done once is not lost.
+    pub fn keep_verification_from(&mut self, other: &Patient) {
+        for identifier in &mut self.identifiers {
+            if let Some(status) = other
+                .identifiers
+                .iter()
+                .filter(|o| identifier.same_as(o))
+                .map(|o| o.verification)
+                .max()
+            {
+                identifier.verification = identifier.verification.max(status);
+            }
+        }
+        for address in &mut self.addresses {
+            if let Some(status) = other
+                .addresses
+                .iter()
+                .filter(|o| address.same_as(o))
+                .map(|o| o.verification)
+                .max()
+            {
+                address.verification = address.verification.max(status);
+            }
+        }
+    }
+}
+
+fn comparable(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
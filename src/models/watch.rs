@@ -0,0 +1,38 @@
+//! Patient watch model definition
+//!
+//! A watch subscribes a caller to one patient's changes, delivered to a
+//! webhook or to server-sent event streams.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use utoipa::ToSchema;
+
+/// Subscription to a single patient's change events
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PatientWatch {
+    /// Unique watch identifier
+    pub id: Uuid,
+
+    /// Watched patient
+    pub patient_id: Uuid,
+
+    /// Webhook receiving a POST per notification; SSE-only when absent
+    pub callback_url: Option<String>,
+
+    /// Event types to deliver (e.g. "Updated", "Merged"); all when empty
+    pub event_types: Vec<String>,
+
+    /// Created timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// User or system that created the watch
+    pub created_by: Option<String>,
+}
+
+impl PatientWatch {
+    /// Whether the watch wants events of this type
+    pub fn accepts(&self, event_type: &str) -> bool {
+        self.event_types.is_empty() || self.event_types.iter().any(|t| t == event_type)
+    }
+}
@@ -0,0 +1,412 @@
+//! Steward alerts by email and webhook
+//!
+//! Things a data steward should hear about without watching a dashboard
+//! raise an [`Alert`]: links flagged as possible overlays, a duplicate
+//! review queue past its threshold, a failed background job, and a
+//! privileged user opening a restricted record. The [`Notifier`] renders
+//! each alert from its type's template and delivers it to the recipients
+//! `notifications.routes` lists for that type, on a background thread so
+//! the code raising it never waits for a mail server.
+
+pub mod smtp;
+pub mod webhook;
+
+use std::collections::BTreeMap;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::config::{AlertTemplate, NotificationConfig};
+use crate::Result;
+
+pub use smtp::SmtpChannel;
+pub use webhook::WebhookChannel;
+
+/// Delivery attempts per alert and recipient
+const DELIVERY_ATTEMPTS: u32 = 3;
+
+/// What an alert is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    /// Source record links flagged as possibly attached to the wrong patient
+    Overlay,
+    /// The duplicate review queue reached its alert threshold
+    ReviewBacklog,
+    /// A background job failed
+    JobFailed,
+    /// A privileged user opened a restricted or VIP patient's record
+    BreakTheGlass,
+}
+
+impl AlertKind {
+    /// Every alert type
+    pub const ALL: [AlertKind; 4] = [
+        AlertKind::Overlay,
+        AlertKind::ReviewBacklog,
+        AlertKind::JobFailed,
+        AlertKind::BreakTheGlass,
+    ];
+
+    /// Key of the alert type in `notifications.routes` and `notifications.templates`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertKind::Overlay => "overlay",
+            AlertKind::ReviewBacklog => "review_backlog",
+            AlertKind::JobFailed => "job_failed",
+            AlertKind::BreakTheGlass => "break_the_glass",
+        }
+    }
+
+    /// Parse a configuration key
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == value)
+    }
+
+    /// Built-in subject and body, used unless `notifications.templates` replaces them
+    fn default_template(&self) -> (&'static str, &'static str) {
+        match self {
+            AlertKind::Overlay => (
+                "MPI: {{count}} source record links may be overlays",
+                "The link confidence job flagged {{count}} automatic links whose source records no longer \
+                 agree with their patients' names or addresses. Review them at \
+                 GET /api/v1/links/reverification.",
+            ),
+            AlertKind::ReviewBacklog => (
+                "MPI: {{pending}} possible duplicates awaiting review",
+                "The duplicate review queue holds {{pending}} pairs, at or above the alert threshold of \
+                 {{threshold}}.",
+            ),
+            AlertKind::JobFailed => (
+                "MPI: {{job_kind}} job failed",
+                "Job {{job_id}} ({{job_kind}}), started by {{requested_by}}, failed after processing \
+                 {{processed}} items: {{error}}",
+            ),
+            AlertKind::BreakTheGlass => (
+                "MPI: {{confidentiality}} patient record opened",
+                "{{user}} opened the record of {{confidentiality}} patient {{patient_id}} through \
+                 {{endpoint}} from {{ip_address}}.",
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for AlertKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Something stewards should be told about, with the values its template fills in
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub timestamp: DateTime<Utc>,
+    pub fields: BTreeMap<String, String>,
+}
+
+impl Alert {
+    /// Create an alert raised now, with no fields
+    pub fn new(kind: AlertKind) -> Self {
+        Self {
+            kind,
+            timestamp: Utc::now(),
+            fields: BTreeMap::new(),
+        }
+    }
+
+    /// Set a field for the template
+    pub fn with(mut self, name: &str, value: impl ToString) -> Self {
+        self.fields.insert(name.to_string(), value.to_string());
+        self
+    }
+}
+
+/// An alert rendered for delivery
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertMessage {
+    pub subject: String,
+    pub body: String,
+    #[serde(flatten)]
+    pub alert: Alert,
+}
+
+/// Where an alert is delivered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recipient {
+    Email(String),
+    Webhook(String),
+}
+
+impl Recipient {
+    /// Parse a recipient from `notifications.routes`: an http(s) URL or an email address
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if value.starts_with("http://") || value.starts_with("https://") {
+            return Some(Recipient::Webhook(value.to_string()));
+        }
+        // Either a bare address or `Display Name <address>`
+        let address = match (value.find('<'), value.strip_suffix('>')) {
+            (Some(open), Some(inner)) => &inner[open + 1..],
+            _ => value,
+        };
+        let (local, domain) = address.split_once('@')?;
+        if local.is_empty() || domain.is_empty() || address.contains(char::is_whitespace) {
+            return None;
+        }
+        Some(Recipient::Email(value.to_string()))
+    }
+}
+
+/// Delivers rendered alerts to one kind of recipient
+pub trait AlertChannel: Send + Sync {
+    fn send(&self, recipient: &str, message: &AlertMessage) -> Result<()>;
+}
+
+/// Fill `{{field}}` placeholders from the alert; unknown ones become "unknown"
+pub fn render(template: &str, alert: &Alert) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        let name = rest[start + 2..start + 2 + length].trim();
+        match name {
+            "kind" => rendered.push_str(alert.kind.as_str()),
+            "timestamp" => rendered.push_str(&alert.timestamp.to_rfc3339()),
+            _ => rendered.push_str(alert.fields.get(name).map_or("unknown", String::as_str)),
+        }
+        rest = &rest[start + 2 + length + 2..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Routes alerts to their recipients
+pub struct Notifier {
+    routes: BTreeMap<AlertKind, Vec<Recipient>>,
+    templates: BTreeMap<AlertKind, AlertTemplate>,
+    deliveries: Option<mpsc::Sender<(Recipient, AlertMessage)>>,
+}
+
+impl std::fmt::Debug for Notifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Notifier").field("routes", &self.routes).finish_non_exhaustive()
+    }
+}
+
+impl Notifier {
+    /// A notifier that sends nothing
+    pub fn disabled() -> Self {
+        Self {
+            routes: BTreeMap::new(),
+            templates: BTreeMap::new(),
+            deliveries: None,
+        }
+    }
+
+    /// Build the notifier `config` describes, sending email through
+    /// `config.smtp` and webhooks over HTTP
+    pub fn from_config(config: &NotificationConfig) -> Result<Self> {
+        if !config.enabled {
+            return Ok(Self::disabled());
+        }
+        let email = match &config.smtp {
+            Some(smtp) => Some(Arc::new(SmtpChannel::new(smtp)?) as Arc<dyn AlertChannel>),
+            None => None,
+        };
+        Ok(Self::with_channels(config, email, Arc::new(WebhookChannel::default())))
+    }
+
+    /// Build a notifier for `config` that delivers through the given channels
+    ///
+    /// Email recipients are skipped when there is no email channel.
+    pub fn with_channels(
+        config: &NotificationConfig,
+        email: Option<Arc<dyn AlertChannel>>,
+        webhook: Arc<dyn AlertChannel>,
+    ) -> Self {
+        let mut routes = BTreeMap::new();
+        for (key, recipients) in &config.routes {
+            let Some(kind) = AlertKind::parse(key) else {
+                tracing::warn!("Ignoring notification route for unknown alert type '{}'", key);
+                continue;
+            };
+            let recipients: Vec<Recipient> = recipients
+                .iter()
+                .filter_map(|recipient| Recipient::parse(recipient))
+                .filter(|recipient| email.is_some() || !matches!(recipient, Recipient::Email(_)))
+                .collect();
+            routes.insert(kind, recipients);
+        }
+        let templates = config
+            .templates
+            .iter()
+            .filter_map(|(key, template)| AlertKind::parse(key).map(|kind| (kind, template.clone())))
+            .collect();
+
+        let (queue, deliveries) = mpsc::channel::<(Recipient, AlertMessage)>();
+        std::thread::Builder::new()
+            .name("steward-alerts".to_string())
+            .spawn(move || {
+                for (recipient, message) in deliveries {
+                    match &recipient {
+                        Recipient::Email(address) => {
+                            if let Some(email) = &email {
+                                deliver(email.as_ref(), address, &message);
+                            }
+                        }
+                        Recipient::Webhook(url) => deliver(webhook.as_ref(), url, &message),
+                    }
+                }
+            })
+            .expect("failed to spawn alert delivery thread");
+
+        Self {
+            routes,
+            templates,
+            deliveries: Some(queue),
+        }
+    }
+
+    /// Recipients of an alert type
+    pub fn recipients(&self, kind: AlertKind) -> &[Recipient] {
+        self.routes.get(&kind).map_or(&[], Vec::as_slice)
+    }
+
+    /// Render an alert from its type's template
+    pub fn message(&self, alert: Alert) -> AlertMessage {
+        let (subject, body) = match self.templates.get(&alert.kind) {
+            Some(template) => (render(&template.subject, &alert), render(&template.body, &alert)),
+            None => {
+                let (subject, body) = alert.kind.default_template();
+                (render(subject, &alert), render(body, &alert))
+            }
+        };
+        AlertMessage { subject, body, alert }
+    }
+
+    /// Queue an alert for its type's recipients, returning how many there are
+    pub fn notify(&self, alert: Alert) -> usize {
+        let recipients = self.recipients(alert.kind);
+        let Some(queue) = self.deliveries.as_ref().filter(|_| !recipients.is_empty()) else {
+            return 0;
+        };
+        let message = self.message(alert);
+        for recipient in recipients {
+            if queue.send((recipient.clone(), message.clone())).is_err() {
+                tracing::warn!("Alert delivery thread has stopped; dropping {} alert", message.alert.kind);
+                return 0;
+            }
+        }
+        recipients.len()
+    }
+}
+
+fn deliver(channel: &dyn AlertChannel, recipient: &str, message: &AlertMessage) {
+    for attempt in 1..=DELIVERY_ATTEMPTS {
+        match channel.send(recipient, message) {
+            Ok(()) => return,
+            Err(e) if attempt < DELIVERY_ATTEMPTS => {
+                tracing::debug!("{} (attempt {} of {})", e, attempt, DELIVERY_ATTEMPTS);
+                std::thread::sleep(Duration::from_secs(1 << attempt));
+            }
+            Err(e) => tracing::warn!("{}; giving up on {} alert", e, message.alert.kind),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct RecordingChannel {
+        sent: Mutex<mpsc::Sender<(String, AlertMessage)>>,
+    }
+
+    impl AlertChannel for RecordingChannel {
+        fn send(&self, recipient: &str, message: &AlertMessage) -> Result<()> {
+            let _ = self.sent.lock().unwrap().send((recipient.to_string(), message.clone()));
+            Ok(())
+        }
+    }
+
+    fn recording() -> (Arc<dyn AlertChannel>, mpsc::Receiver<(String, AlertMessage)>) {
+        let (sent, received) = mpsc::channel();
+        (Arc::new(RecordingChannel { sent: Mutex::new(sent) }), received)
+    }
+
+    #[test]
+    fn test_render_fills_fields() {
+        let alert = Alert::new(AlertKind::ReviewBacklog).with("pending", 612).with("threshold", 500);
+        assert_eq!(render("{{pending}} of {{ threshold }} ({{kind}})", &alert), "612 of 500 (review_backlog)");
+        assert_eq!(render("{{missing}} and {{unclosed", &alert), "unknown and {{unclosed");
+    }
+
+    #[test]
+    fn test_recipient_parse() {
+        assert_eq!(
+            Recipient::parse("https://hooks.example.org/mpi"),
+            Some(Recipient::Webhook("https://hooks.example.org/mpi".to_string()))
+        );
+        assert_eq!(
+            Recipient::parse("Data Stewards <stewards@example.org>"),
+            Some(Recipient::Email("Data Stewards <stewards@example.org>".to_string()))
+        );
+        assert!(Recipient::parse("stewards@example.org").is_some());
+        assert!(Recipient::parse("stewards").is_none());
+        assert!(Recipient::parse("ftp://example.org").is_none());
+    }
+
+    #[test]
+    fn test_routes_alerts_by_type() {
+        let mut config = NotificationConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        config.routes.insert(
+            "job_failed".to_string(),
+            vec!["stewards@example.org".to_string(), "https://hooks.example.org/mpi".to_string()],
+        );
+        config.routes.insert("break_the_glass".to_string(), vec!["privacy@example.org".to_string()]);
+        config.templates.insert(
+            "job_failed".to_string(),
+            AlertTemplate {
+                subject: "{{job_kind}} failed".to_string(),
+                body: "{{error}}".to_string(),
+            },
+        );
+        let (email, emails) = recording();
+        let (webhook, webhooks) = recording();
+        let notifier = Notifier::with_channels(&config, Some(email), webhook);
+
+        let alert = Alert::new(AlertKind::JobFailed).with("job_kind", "batch_match").with("error", "disk full");
+        assert_eq!(notifier.notify(alert), 2);
+        assert_eq!(notifier.notify(Alert::new(AlertKind::Overlay).with("count", 3)), 0);
+
+        let (address, message) = emails.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(address, "stewards@example.org");
+        assert_eq!((message.subject.as_str(), message.body.as_str()), ("batch_match failed", "disk full"));
+        let (url, _) = webhooks.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(url, "https://hooks.example.org/mpi");
+    }
+
+    #[test]
+    fn test_email_recipients_need_smtp() {
+        let mut config = NotificationConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        config.routes.insert("overlay".to_string(), vec!["stewards@example.org".to_string()]);
+        let (webhook, _) = recording();
+        let notifier = Notifier::with_channels(&config, None, webhook);
+        assert!(notifier.recipients(AlertKind::Overlay).is_empty());
+        assert_eq!(Notifier::disabled().notify(Alert::new(AlertKind::Overlay)), 0);
+    }
+}
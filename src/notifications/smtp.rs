@@ -0,0 +1,69 @@
+//! Alerts sent as email
+
+use std::time::Duration;
+
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+
+use crate::config::SmtpConfig;
+use crate::{Error, Result};
+use super::{AlertChannel, AlertMessage};
+
+/// Seconds to wait for the mail server
+const SMTP_TIMEOUT_SECS: u64 = 30;
+
+/// Channel that mails the alert as plain text through an SMTP server
+pub struct SmtpChannel {
+    transport: SmtpTransport,
+    from: Mailbox,
+}
+
+impl SmtpChannel {
+    /// Create a channel for the mail server `config` describes
+    pub fn new(config: &SmtpConfig) -> Result<Self> {
+        let from = config
+            .from
+            .parse::<Mailbox>()
+            .map_err(|e| Error::Config(format!("notifications.smtp.from '{}' is not an address: {}", config.from, e)))?;
+        let builder = if config.starttls {
+            SmtpTransport::starttls_relay(&config.host)
+                .map_err(|e| Error::Config(format!("notifications.smtp.host '{}': {}", config.host, e)))?
+        } else {
+            SmtpTransport::builder_dangerous(&config.host)
+        };
+        let mut builder = builder
+            .port(config.port)
+            .timeout(Some(Duration::from_secs(SMTP_TIMEOUT_SECS)));
+        if let Some(username) = &config.username {
+            builder = builder.credentials(Credentials::new(
+                username.clone(),
+                config.password.clone().unwrap_or_default(),
+            ));
+        }
+        Ok(Self {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+impl AlertChannel for SmtpChannel {
+    fn send(&self, recipient: &str, message: &AlertMessage) -> Result<()> {
+        let to = recipient
+            .parse::<Mailbox>()
+            .map_err(|e| Error::Notification(format!("'{}' is not an email address: {}", recipient, e)))?;
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(&message.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(message.body.clone())
+            .map_err(|e| Error::Notification(format!("Failed to build alert email: {}", e)))?;
+        self.transport
+            .send(&email)
+            .map(|_| ())
+            .map_err(|e| Error::Notification(format!("Alert delivery to {} failed: {}", recipient, e)))
+    }
+}
@@ -0,0 +1,36 @@
+//! Alerts posted to webhooks
+
+use std::time::Duration;
+
+use crate::{Error, Result};
+use super::{AlertChannel, AlertMessage};
+
+/// Channel that POSTs the alert to the recipient URL as JSON
+pub struct WebhookChannel {
+    agent: ureq::Agent,
+}
+
+impl WebhookChannel {
+    /// Create a channel with the given request timeout
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new().timeout(timeout).build(),
+        }
+    }
+}
+
+impl Default for WebhookChannel {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(10))
+    }
+}
+
+impl AlertChannel for WebhookChannel {
+    fn send(&self, recipient: &str, message: &AlertMessage) -> Result<()> {
+        self.agent
+            .post(recipient)
+            .send_json(message)
+            .map(|_| ())
+            .map_err(|e| Error::Notification(format!("Alert delivery to {} failed: {}", recipient, e)))
+    }
+}
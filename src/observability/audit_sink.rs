@@ -0,0 +1,362 @@
+//! Forwarding of audit entries to SIEM collectors
+//!
+//! Hospital security teams watch MPI access from their SIEM, not from the
+//! `audit_log` table. Every entry written there is also handed to an
+//! [`AuditForwarder`], which delivers it from a background thread to each
+//! configured [`AuditSink`]: syslog carrying CEF, or an HTTP collector.
+//!
+//! The table stays the record of truth. Forwarding never delays or fails the
+//! audited request; entries that cannot be delivered are logged and dropped.
+//! Old and new values are not forwarded, since they hold patient data the
+//! SIEM has no need for.
+
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::config::{AuditSinkConfig, ObservabilityConfig, SyslogTransport};
+use crate::db::models::DbAuditLog;
+use crate::{Error, Result};
+
+/// Entries waiting for delivery before new ones are dropped
+const QUEUE_CAPACITY: usize = 10_000;
+
+/// Delivery attempts per entry and sink
+const DELIVERY_ATTEMPTS: u32 = 3;
+
+/// Connect and request timeout of the sinks
+const SINK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Syslog facility local0 and severity informational
+const SYSLOG_PRIORITY: u8 = 16 * 8 + 6;
+
+const CEF_VENDOR: &str = "SixArm";
+const CEF_PRODUCT: &str = "master-patient-index";
+
+/// An audit entry as forwarded, without old and new values
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    pub user_id: Option<String>,
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: Uuid,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl From<&DbAuditLog> for AuditEntry {
+    fn from(log: &DbAuditLog) -> Self {
+        Self {
+            id: log.id,
+            timestamp: log.timestamp,
+            user_id: log.user_id.clone(),
+            action: log.action.clone(),
+            entity_type: log.entity_type.clone(),
+            entity_id: log.entity_id,
+            ip_address: log.ip_address.clone(),
+            user_agent: log.user_agent.clone(),
+        }
+    }
+}
+
+/// Delivers audit entries to one collector
+pub trait AuditSink: Send + Sync {
+    /// Where entries go, for log messages
+    fn name(&self) -> &str;
+
+    fn send(&self, entry: &AuditEntry) -> Result<()>;
+}
+
+/// Sink writing RFC 5424 syslog messages with a CEF payload
+pub struct SyslogSink {
+    name: String,
+    address: String,
+    transport: SyslogTransport,
+    hostname: String,
+    app_name: String,
+    udp: Mutex<Option<UdpSocket>>,
+    tcp: Mutex<Option<TcpStream>>,
+}
+
+impl SyslogSink {
+    /// Send to `address` (`host:port`) as `app_name`
+    pub fn new(address: &str, transport: SyslogTransport, app_name: &str) -> Self {
+        Self {
+            name: format!("syslog://{}", address),
+            address: address.to_string(),
+            transport,
+            hostname: std::env::var("HOSTNAME").ok().filter(|h| !h.is_empty()).unwrap_or_else(|| "-".to_string()),
+            app_name: app_name.to_string(),
+            udp: Mutex::new(None),
+            tcp: Mutex::new(None),
+        }
+    }
+
+    /// The syslog message for an entry
+    pub fn message(&self, entry: &AuditEntry) -> String {
+        format!(
+            "<{}>1 {} {} {} - - - {}",
+            SYSLOG_PRIORITY,
+            entry.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+            self.hostname,
+            self.app_name,
+            cef(entry)
+        )
+    }
+
+    fn failed(&self, e: impl std::fmt::Display) -> Error {
+        Error::Internal(format!("Audit forwarding to {} failed: {}", self.name, e))
+    }
+}
+
+impl AuditSink for SyslogSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn send(&self, entry: &AuditEntry) -> Result<()> {
+        let message = self.message(entry);
+        let addr = self
+            .address
+            .to_socket_addrs()
+            .map_err(|e| self.failed(e))?
+            .next()
+            .ok_or_else(|| self.failed("address does not resolve"))?;
+
+        match self.transport {
+            SyslogTransport::Udp => {
+                let mut socket = self.udp.lock().unwrap_or_else(|e| e.into_inner());
+                if socket.is_none() {
+                    let bind = if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+                    *socket = Some(UdpSocket::bind(bind).map_err(|e| self.failed(e))?);
+                }
+                let socket = socket.as_ref().expect("socket bound above");
+                socket.send_to(message.as_bytes(), addr).map_err(|e| self.failed(e))?;
+            }
+            SyslogTransport::Tcp => {
+                let mut stream = self.tcp.lock().unwrap_or_else(|e| e.into_inner());
+                if stream.is_none() {
+                    let connected = TcpStream::connect_timeout(&addr, SINK_TIMEOUT).map_err(|e| self.failed(e))?;
+                    connected.set_write_timeout(Some(SINK_TIMEOUT)).map_err(|e| self.failed(e))?;
+                    *stream = Some(connected);
+                }
+                let framed = format!("{} {}", message.len(), message);
+                if let Err(e) = stream.as_mut().expect("stream connected above").write_all(framed.as_bytes()) {
+                    // Reconnect on the next attempt
+                    *stream = None;
+                    return Err(self.failed(e));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sink POSTing each entry as JSON
+pub struct HttpAuditSink {
+    url: String,
+    authorization: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl HttpAuditSink {
+    /// POST to `url`, with an `Authorization` header if given
+    pub fn new(url: &str, authorization: Option<String>) -> Self {
+        Self {
+            url: url.to_string(),
+            authorization,
+            agent: ureq::AgentBuilder::new().timeout(SINK_TIMEOUT).build(),
+        }
+    }
+}
+
+impl AuditSink for HttpAuditSink {
+    fn name(&self) -> &str {
+        &self.url
+    }
+
+    fn send(&self, entry: &AuditEntry) -> Result<()> {
+        let mut request = self.agent.post(&self.url);
+        if let Some(authorization) = &self.authorization {
+            request = request.set("Authorization", authorization);
+        }
+        request
+            .send_json(entry)
+            .map(|_| ())
+            .map_err(|e| Error::Internal(format!("Audit forwarding to {} failed: {}", self.url, e)))
+    }
+}
+
+/// Queues audit entries for delivery to the sinks on a background thread
+pub struct AuditForwarder {
+    queue: SyncSender<AuditEntry>,
+}
+
+impl AuditForwarder {
+    /// Start delivering to `sinks`
+    pub fn new(sinks: Vec<Arc<dyn AuditSink>>) -> Self {
+        let (queue, entries) = mpsc::sync_channel::<AuditEntry>(QUEUE_CAPACITY);
+        std::thread::Builder::new()
+            .name("audit-forwarder".to_string())
+            .spawn(move || {
+                for entry in entries {
+                    for sink in &sinks {
+                        deliver(sink.as_ref(), &entry);
+                    }
+                }
+            })
+            .expect("failed to spawn audit forwarding thread");
+        Self { queue }
+    }
+
+    /// A forwarder for the configured sinks, if there are any
+    pub fn from_config(config: &ObservabilityConfig) -> Option<Self> {
+        if config.audit_sinks.is_empty() {
+            return None;
+        }
+        let sinks = config
+            .audit_sinks
+            .iter()
+            .map(|sink| match sink {
+                AuditSinkConfig::Syslog { address, transport } => {
+                    Arc::new(SyslogSink::new(address, *transport, &config.service_name)) as Arc<dyn AuditSink>
+                }
+                AuditSinkConfig::Http { url, authorization } => {
+                    Arc::new(HttpAuditSink::new(url, authorization.clone())) as Arc<dyn AuditSink>
+                }
+            })
+            .collect();
+        Some(Self::new(sinks))
+    }
+
+    /// Queue an entry, dropping it if the queue is full
+    pub fn forward(&self, entry: AuditEntry) {
+        match self.queue.try_send(entry) {
+            Ok(()) => {}
+            Err(TrySendError::Full(entry)) => {
+                tracing::warn!("Audit forwarding queue is full; entry {} not forwarded", entry.id)
+            }
+            Err(TrySendError::Disconnected(entry)) => {
+                tracing::warn!("Audit forwarding thread has stopped; entry {} not forwarded", entry.id)
+            }
+        }
+    }
+}
+
+fn deliver(sink: &dyn AuditSink, entry: &AuditEntry) {
+    for attempt in 1..=DELIVERY_ATTEMPTS {
+        match sink.send(entry) {
+            Ok(()) => return,
+            Err(e) if attempt < DELIVERY_ATTEMPTS => {
+                tracing::debug!("{} (attempt {} of {})", e, attempt, DELIVERY_ATTEMPTS);
+                std::thread::sleep(Duration::from_millis(250 << attempt));
+            }
+            Err(e) => tracing::warn!("{}; audit entry {} not forwarded", e, entry.id),
+        }
+    }
+}
+
+/// CEF severity, 0 to 10, of an audited action
+fn cef_severity(action: &str) -> u8 {
+    match action {
+        "PURGE" | "ANONYMIZE" => 7,
+        "DELETE" | "MERGE" | "CONFIG_RELOAD" | "CONFIDENTIALITY" | "RESTRICTED_ACCESS" => 5,
+        _ => 3,
+    }
+}
+
+/// An entry as an ArcSight Common Event Format record
+pub fn cef(entry: &AuditEntry) -> String {
+    let mut extensions = vec![
+        format!("rt={}", entry.timestamp.timestamp_millis()),
+        format!("act={}", cef_value(&entry.action)),
+        format!("externalId={}", entry.id),
+        "cs1Label=entityType".to_string(),
+        format!("cs1={}", cef_value(&entry.entity_type)),
+        "cs2Label=entityId".to_string(),
+        format!("cs2={}", entry.entity_id),
+    ];
+    if let Some(user_id) = &entry.user_id {
+        extensions.push(format!("suser={}", cef_value(user_id)));
+    }
+    if let Some(ip_address) = &entry.ip_address {
+        extensions.push(format!("src={}", cef_value(ip_address)));
+    }
+    if let Some(user_agent) = &entry.user_agent {
+        extensions.push(format!("requestClientApplication={}", cef_value(user_agent)));
+    }
+
+    format!(
+        "CEF:0|{}|{}|{}|{}|{}|{}|{}",
+        CEF_VENDOR,
+        CEF_PRODUCT,
+        env!("CARGO_PKG_VERSION"),
+        cef_header(&entry.action),
+        cef_header(&format!("{} {}", entry.entity_type, entry.action)),
+        cef_severity(&entry.action),
+        extensions.join(" ")
+    )
+}
+
+/// Escape a CEF header field
+fn cef_header(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+/// Escape a CEF extension value
+fn cef_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('=', "\\=")
+        .replace('\r', "\\r")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> AuditEntry {
+        AuditEntry {
+            id: Uuid::nil(),
+            timestamp: DateTime::parse_from_rfc3339("2024-12-28T10:15:00Z").unwrap().with_timezone(&Utc),
+            user_id: Some("steward=1".to_string()),
+            action: "PURGE".to_string(),
+            entity_type: "patient".to_string(),
+            entity_id: Uuid::nil(),
+            ip_address: Some("10.0.0.7".to_string()),
+            user_agent: None,
+        }
+    }
+
+    #[test]
+    fn test_cef_record() {
+        let record = cef(&entry());
+        assert!(record.starts_with("CEF:0|SixArm|master-patient-index|"));
+        assert!(record.contains("|PURGE|patient PURGE|7|rt=1735380900000 act=PURGE "));
+        assert!(record.contains("suser=steward\\=1 src=10.0.0.7"));
+        assert!(!record.contains("requestClientApplication"));
+    }
+
+    #[test]
+    fn test_syslog_over_udp() {
+        let collector = UdpSocket::bind("127.0.0.1:0").unwrap();
+        collector.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let sink = SyslogSink::new(&collector.local_addr().unwrap().to_string(), SyslogTransport::Udp, "mpi");
+
+        sink.send(&entry()).unwrap();
+        let mut buffer = [0u8; 2048];
+        let len = collector.recv(&mut buffer).unwrap();
+        let received = std::str::from_utf8(&buffer[..len]).unwrap();
+        assert!(received.starts_with("<134>1 2024-12-28T10:15:00.000Z "));
+        assert!(received.contains(" mpi - - - CEF:0|"));
+    }
+}
@@ -1,3 +1,195 @@
 //! Metrics collection
+//!
+//! Prometheus metrics, served at `/metrics` in the text exposition format.
+//! Match requests are timed per stage so a slow request can be traced to
+//! the exact identifier lookup, blocking (the search index query),
+//! candidate hydration (fetching the candidates from the database) or
+//! scoring.
 
-// OpenTelemetry metrics implementation
+use std::time::Duration;
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+/// Bucket upper bounds in seconds, from 1 ms to 10 s
+const MATCH_STAGE_BUCKETS: &[f64] = &[
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Stage of a match request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchStage {
+    /// Exact MRN and SSN lookup ahead of blocking
+    IdentifierLookup,
+    /// Candidate search in the index
+    Blocking,
+    /// Loading the candidates from the database
+    Hydration,
+    /// Scoring the candidates
+    Scoring,
+    /// The whole request
+    Total,
+}
+
+impl MatchStage {
+    /// Value of the `stage` label
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchStage::IdentifierLookup => "identifier_lookup",
+            MatchStage::Blocking => "blocking",
+            MatchStage::Hydration => "hydration",
+            MatchStage::Scoring => "scoring",
+            MatchStage::Total => "total",
+        }
+    }
+}
+
+/// Prometheus metrics of the MPI
+pub struct Metrics {
+    registry: Registry,
+    match_stage_duration: HistogramVec,
+    requests_shed: IntCounterVec,
+    breaker_state: IntGaugeVec,
+    breaker_trips: IntCounterVec,
+    blocking_pass_runs: IntCounterVec,
+    blocking_pass_candidates: IntCounterVec,
+}
+
+impl Metrics {
+    /// Create and register all metrics
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let match_stage_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "mpi_match_stage_duration_seconds",
+                "Time spent in each stage of a match request",
+            )
+            .buckets(MATCH_STAGE_BUCKETS.to_vec()),
+            &["stage"],
+        )
+        .expect("valid histogram definition");
+        let requests_shed = IntCounterVec::new(
+            Opts::new("mpi_requests_shed_total", "Requests rejected by load shedding"),
+            &["reason"],
+        )
+        .expect("valid counter definition");
+        let breaker_state = IntGaugeVec::new(
+            Opts::new(
+                "mpi_circuit_breaker_state",
+                "Circuit breaker state: 0 closed, 1 half-open, 2 open",
+            ),
+            &["breaker"],
+        )
+        .expect("valid gauge definition");
+        let breaker_trips = IntCounterVec::new(
+            Opts::new("mpi_circuit_breaker_trips_total", "Times a circuit breaker has opened"),
+            &["breaker"],
+        )
+        .expect("valid counter definition");
+        let blocking_pass_runs = IntCounterVec::new(
+            Opts::new("mpi_blocking_pass_runs_total", "Times each multi-pass blocking pass has run"),
+            &["pass"],
+        )
+        .expect("valid counter definition");
+        let blocking_pass_candidates = IntCounterVec::new(
+            Opts::new(
+                "mpi_blocking_pass_candidates_total",
+                "Candidates each multi-pass blocking pass added to those of earlier passes",
+            ),
+            &["pass"],
+        )
+        .expect("valid counter definition");
+        registry
+            .register(Box::new(match_stage_duration.clone()))
+            .expect("metric registered once");
+        registry
+            .register(Box::new(requests_shed.clone()))
+            .expect("metric registered once");
+        registry
+            .register(Box::new(breaker_state.clone()))
+            .expect("metric registered once");
+        registry
+            .register(Box::new(breaker_trips.clone()))
+            .expect("metric registered once");
+        registry
+            .register(Box::new(blocking_pass_runs.clone()))
+            .expect("metric registered once");
+        registry
+            .register(Box::new(blocking_pass_candidates.clone()))
+            .expect("metric registered once");
+
+        Self {
+            registry,
+            match_stage_duration,
+            requests_shed,
+            breaker_state,
+            breaker_trips,
+            blocking_pass_runs,
+            blocking_pass_candidates,
+        }
+    }
+
+    /// Record the time taken by one stage of a match request
+    pub fn observe_match_stage(&self, stage: MatchStage, elapsed: Duration) {
+        self.match_stage_duration
+            .with_label_values(&[stage.as_str()])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Count a request rejected by load shedding
+    pub fn observe_shed(&self, reason: &str) {
+        self.requests_shed.with_label_values(&[reason]).inc();
+    }
+
+    /// Set the state gauge of a circuit breaker
+    pub fn observe_breaker_state(&self, breaker: &str, state: i64) {
+        self.breaker_state.with_label_values(&[breaker]).set(state);
+    }
+
+    /// Count a circuit breaker opening
+    pub fn observe_breaker_trip(&self, breaker: &str) {
+        self.breaker_trips.with_label_values(&[breaker]).inc();
+    }
+
+    /// Count a run of a multi-pass blocking pass and the candidates it added
+    pub fn observe_blocking_pass(&self, pass: &str, added: usize) {
+        self.blocking_pass_runs.with_label_values(&[pass]).inc();
+        self.blocking_pass_candidates.with_label_values(&[pass]).inc_by(added as u64);
+    }
+
+    /// All metrics in the Prometheus text format
+    pub fn render(&self) -> crate::Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .map_err(|e| crate::Error::Internal(format!("Failed to encode metrics: {}", e)))?;
+        String::from_utf8(buffer).map_err(|e| crate::Error::Internal(e.to_string()))
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_stage_histograms() {
+        let metrics = Metrics::new();
+        metrics.observe_match_stage(MatchStage::Blocking, Duration::from_millis(3));
+        metrics.observe_match_stage(MatchStage::Scoring, Duration::from_millis(40));
+        metrics.observe_match_stage(MatchStage::Scoring, Duration::from_millis(60));
+
+        let text = metrics.render().unwrap();
+
+        assert!(text.contains("# TYPE mpi_match_stage_duration_seconds histogram"));
+        assert!(text.contains("mpi_match_stage_duration_seconds_count{stage=\"blocking\"} 1"));
+        assert!(text.contains("mpi_match_stage_duration_seconds_count{stage=\"scoring\"} 2"));
+        assert!(text.contains("mpi_match_stage_duration_seconds_bucket{stage=\"scoring\",le=\"0.05\"} 1"));
+    }
+}
@@ -1,20 +1,53 @@
 //! Observability setup with OpenTelemetry
 
+use std::sync::Arc;
+
 use opentelemetry::{global, KeyValue};
 use opentelemetry_sdk::{
     trace::{self, Tracer},
     Resource,
 };
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use tracing_subscriber::{layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter};
 
 use crate::config::ObservabilityConfig;
 use crate::Result;
 
+pub mod audit_sink;
 pub mod metrics;
 pub mod traces;
 
+/// Changes the log filter of the subscriber installed by [`init_telemetry`]
+#[derive(Clone)]
+pub struct LogLevelHandle {
+    reload: Arc<dyn Fn(EnvFilter) -> std::result::Result<(), reload::Error> + Send + Sync>,
+}
+
+impl LogLevelHandle {
+    /// Wrap the reload handle of any subscriber's `EnvFilter`
+    pub fn new<S: 'static>(handle: reload::Handle<EnvFilter, S>) -> Self {
+        Self {
+            reload: Arc::new(move |filter| handle.reload(filter)),
+        }
+    }
+
+    /// Replace the log filter, e.g. `info` or `master_patient_index=debug,warn`
+    pub fn set(&self, level: &str) -> Result<()> {
+        (self.reload)(parse_log_level(level)?)
+            .map_err(|e| crate::Error::Internal(format!("Failed to change log level: {}", e)))
+    }
+}
+
+/// Parse a log level or filter directive
+pub fn parse_log_level(level: &str) -> Result<EnvFilter> {
+    EnvFilter::try_new(level)
+        .map_err(|e| crate::Error::Config(format!("Invalid log level '{}': {}", level, e)))
+}
+
 /// Initialize OpenTelemetry tracing and logging
-pub fn init_telemetry(config: &ObservabilityConfig) -> Result<()> {
+///
+/// Returns a handle for changing the log level later, as on a configuration
+/// reload.
+pub fn init_telemetry(config: &ObservabilityConfig) -> Result<LogLevelHandle> {
     // Set up resource with service information
     let resource = Resource::new(vec![
         KeyValue::new("service.name", config.service_name.clone()),
@@ -30,6 +63,7 @@ pub fn init_telemetry(config: &ObservabilityConfig) -> Result<()> {
     // Set up tracing subscriber
     let env_filter = EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| EnvFilter::new(&config.log_level));
+    let (env_filter, handle) = reload::Layer::new(env_filter);
 
     tracing_subscriber::registry()
         .with(env_filter)
@@ -37,7 +71,7 @@ pub fn init_telemetry(config: &ObservabilityConfig) -> Result<()> {
         // .with(tracing_opentelemetry::layer().with_tracer(tracer))
         .init();
 
-    Ok(())
+    Ok(LogLevelHandle::new(handle))
 }
 
 /// Shutdown OpenTelemetry
@@ -0,0 +1,207 @@
+//! Runtime configuration reload
+//!
+//! `POST /api/v1/admin/config/reload` and SIGHUP re-read the configuration
+//! and apply the settings that can change without a restart: the matching
+//! threshold and weights, the load shedding limits and the log level. Every
+//! other setting keeps its startup value until the process restarts,
+//! including whether load shedding is enabled at all.
+//!
+//! A reload that changes anything is recorded in the audit log as a
+//! `CONFIG_RELOAD` of the `Config` entity, with the old and new settings.
+//!
+//! Matching profiles stored through the admin API are held here too, and
+//! are re-read from the database on reload as well as when they change.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::config::{Config, MatchWeights};
+use crate::matching::ReloadableMatcher;
+use crate::models::MatchingSettingsVersion;
+use crate::observability::LogLevelHandle;
+use crate::Result;
+
+/// Settings applied by a reload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ReloadableSettings {
+    /// Probabilistic match threshold
+    pub threshold_score: f64,
+    /// Probabilistic component weights
+    pub weights: MatchWeights,
+    /// Pool wait above which requests are shed
+    pub max_pool_wait_ms: u64,
+    /// Match requests allowed in flight at once
+    pub max_inflight_matches: usize,
+    /// Seconds clients are told to wait when shed
+    pub retry_after_secs: u64,
+    /// Log level or filter directive
+    pub log_level: String,
+}
+
+impl ReloadableSettings {
+    /// The reloadable part of `config`
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            threshold_score: config.matching.threshold_score,
+            weights: config.matching.weights.clone(),
+            max_pool_wait_ms: config.load_shedding.max_pool_wait_ms,
+            max_inflight_matches: config.load_shedding.max_inflight_matches,
+            retry_after_secs: config.load_shedding.retry_after_secs,
+            log_level: config.observability.log_level.clone(),
+        }
+    }
+
+    /// Overwrite the reloadable part of `config`
+    pub fn apply(&self, config: &mut Config) {
+        config.matching.threshold_score = self.threshold_score;
+        config.matching.weights = self.weights.clone();
+        config.load_shedding.max_pool_wait_ms = self.max_pool_wait_ms;
+        config.load_shedding.max_inflight_matches = self.max_inflight_matches;
+        config.load_shedding.retry_after_secs = self.retry_after_secs;
+        config.observability.log_level = self.log_level.clone();
+    }
+}
+
+/// Outcome of a reload
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReloadReport {
+    /// Whether any reloadable setting changed
+    pub changed: bool,
+    pub old: ReloadableSettings,
+    pub new: ReloadableSettings,
+}
+
+/// Reads the configuration on reload
+pub type ConfigLoader = Box<dyn Fn() -> Result<Config> + Send + Sync>;
+
+/// Current reloadable settings and the components a reload updates in place
+///
+/// Applying a reload is [`AppState::reload_config`](crate::api::rest::AppState::reload_config).
+pub struct ConfigReloader {
+    settings: Mutex<ReloadableSettings>,
+    loader: ConfigLoader,
+    matcher: Arc<ReloadableMatcher>,
+    log_level: Mutex<Option<LogLevelHandle>>,
+    profiles: Mutex<BTreeMap<String, MatchingSettingsVersion>>,
+}
+
+impl ConfigReloader {
+    /// Start from the settings in `config`, reloading with [`Config::from_env`]
+    pub fn new(config: &Config, matcher: Arc<ReloadableMatcher>) -> Self {
+        Self {
+            settings: Mutex::new(ReloadableSettings::from_config(config)),
+            loader: Box::new(Config::from_env),
+            matcher,
+            log_level: Mutex::new(None),
+            profiles: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Read the configuration from somewhere else
+    pub fn with_loader(mut self, loader: ConfigLoader) -> Self {
+        self.loader = loader;
+        self
+    }
+
+    /// Change the log level through `handle` on reload
+    ///
+    /// Without a handle the log level is validated and recorded but the
+    /// subscriber keeps its filter.
+    pub fn set_log_level_handle(&self, handle: LogLevelHandle) {
+        *self.log_level.lock().unwrap_or_else(|e| e.into_inner()) = Some(handle);
+    }
+
+    /// Settings currently in effect
+    pub fn settings(&self) -> ReloadableSettings {
+        self.settings.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// The matcher a reload replaces
+    pub fn matcher(&self) -> &Arc<ReloadableMatcher> {
+        &self.matcher
+    }
+
+    /// Read and validate the configuration without applying it
+    pub fn load(&self) -> Result<ReloadableSettings> {
+        let config = (self.loader)()?;
+        config.matching.validate()?;
+        crate::observability::parse_log_level(&config.observability.log_level)?;
+        Ok(ReloadableSettings::from_config(&config))
+    }
+
+    /// Change the log level, if a handle was given
+    pub fn set_log_level(&self, level: &str) -> Result<()> {
+        match &*self.log_level.lock().unwrap_or_else(|e| e.into_inner()) {
+            Some(handle) => handle.set(level),
+            None => Ok(()),
+        }
+    }
+
+    /// Record `settings` as in effect
+    pub fn store(&self, settings: ReloadableSettings) {
+        *self.settings.lock().unwrap_or_else(|e| e.into_inner()) = settings;
+    }
+
+    /// Stored matching profiles in effect, by name
+    pub fn stored_profiles(&self) -> BTreeMap<String, MatchingSettingsVersion> {
+        self.profiles.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// The stored matching profile called `name`, if any
+    pub fn stored_profile(&self, name: &str) -> Option<MatchingSettingsVersion> {
+        self.profiles.lock().unwrap_or_else(|e| e.into_inner()).get(name).cloned()
+    }
+
+    /// Record `profiles` as the stored matching profiles in effect
+    pub fn store_profiles(&self, profiles: BTreeMap<String, MatchingSettingsVersion>) {
+        *self.profiles.lock().unwrap_or_else(|e| e.into_inner()) = profiles;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matching::ProbabilisticMatcher;
+
+    fn reloader(loaded: Config) -> ConfigReloader {
+        let config = Config::default();
+        let matcher = Arc::new(ReloadableMatcher::new(Arc::new(ProbabilisticMatcher::new(config.matching.clone()))));
+        ConfigReloader::new(&config, matcher).with_loader(Box::new(move || Ok(loaded.clone())))
+    }
+
+    #[test]
+    fn test_settings_round_trip() {
+        let mut config = Config::default();
+        config.matching.threshold_score = 0.7;
+        config.load_shedding.max_inflight_matches = 4;
+        config.observability.log_level = "debug".to_string();
+        let settings = ReloadableSettings::from_config(&config);
+
+        let mut other = Config::default();
+        settings.apply(&mut other);
+        assert_eq!(ReloadableSettings::from_config(&other), settings);
+        assert_eq!(other.matching.threshold_score, 0.7);
+        assert_eq!(other.load_shedding.max_inflight_matches, 4);
+    }
+
+    #[test]
+    fn test_load_validates() {
+        let mut config = Config::default();
+        config.matching.threshold_score = 0.6;
+        assert_eq!(reloader(config.clone()).load().unwrap().threshold_score, 0.6);
+
+        config.matching.threshold_score = 1.5;
+        assert!(reloader(config.clone()).load().is_err());
+
+        config.matching.threshold_score = 0.6;
+        config.matching.weights.name = -1.0;
+        assert!(reloader(config.clone()).load().is_err());
+
+        let mut config = Config::default();
+        config.observability.log_level = "master_patient_index=loud".to_string();
+        assert!(reloader(config).load().is_err());
+    }
+}
@@ -0,0 +1,376 @@
+//! Per-source data quality
+//!
+//! Scores each submitted source record on the demographics that matching
+//! depends on, then aggregates by source system alongside the duplicate
+//! creation rate from the matching KPIs. Sources are ranked worst first, so
+//! HIM teams know which feed to fix first.
+
+use std::collections::BTreeMap;
+
+use chrono::{NaiveDate, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::db::SourceRecordRepository;
+use crate::models::{Gender, Patient, SourceRecord};
+use crate::validation::PostalFormat;
+use crate::Result;
+use super::{DailyMatchingKpis, KpiCounts};
+
+/// Source records loaded per query while building a report
+const BATCH_SIZE: i64 = 1000;
+
+/// A demographic field scored for completeness and validity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityField {
+    FamilyName,
+    GivenName,
+    BirthDate,
+    Gender,
+    Address,
+    PostalCode,
+    Telecom,
+    Identifier,
+}
+
+impl QualityField {
+    /// Every scored field
+    pub const ALL: [QualityField; 8] = [
+        QualityField::FamilyName,
+        QualityField::GivenName,
+        QualityField::BirthDate,
+        QualityField::Gender,
+        QualityField::Address,
+        QualityField::PostalCode,
+        QualityField::Telecom,
+        QualityField::Identifier,
+    ];
+
+    /// Contribution to the record score, roughly following the match weights
+    pub fn weight(&self) -> f64 {
+        match self {
+            QualityField::FamilyName => 0.20,
+            QualityField::GivenName => 0.15,
+            QualityField::BirthDate => 0.20,
+            QualityField::Gender => 0.05,
+            QualityField::Address => 0.10,
+            QualityField::PostalCode => 0.10,
+            QualityField::Telecom => 0.05,
+            QualityField::Identifier => 0.15,
+        }
+    }
+
+    /// Whether the field is present and plausible
+    pub fn is_usable(&self, patient: &Patient, today: NaiveDate) -> bool {
+        match self {
+            QualityField::FamilyName => !patient.name.family.trim().is_empty(),
+            QualityField::GivenName => patient.name.given.iter().any(|g| !g.trim().is_empty()),
+            QualityField::BirthDate => patient.birth_date.is_some_and(|d| {
+                d <= today && d >= NaiveDate::from_ymd_opt(1900, 1, 1).expect("valid date")
+            }),
+            QualityField::Gender => patient.gender != Gender::Unknown,
+            QualityField::Address => patient.addresses.iter().any(|a| {
+                a.line1.as_deref().is_some_and(|l| !l.trim().is_empty())
+                    && a.city.as_deref().is_some_and(|c| !c.trim().is_empty())
+            }),
+            QualityField::PostalCode => patient.addresses.iter().any(|a| {
+                a.postal_code.as_deref().is_some_and(|p| {
+                    match a.country.as_deref().and_then(PostalFormat::for_country) {
+                        Some(format) => format.is_valid(p),
+                        None => p.chars().filter(|c| c.is_ascii_alphanumeric()).count() >= 3,
+                    }
+                })
+            }),
+            QualityField::Telecom => patient.telecom.iter().any(|t| !t.value.trim().is_empty()),
+            QualityField::Identifier => patient.identifiers.iter().any(|i| !i.value.trim().is_empty()),
+        }
+    }
+}
+
+/// Quality of a single source record
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordQuality {
+    /// Weighted share of usable fields, from 0.0 to 1.0
+    pub score: f64,
+    /// Fields that were missing or implausible
+    pub missing: Vec<QualityField>,
+}
+
+/// Score a record's demographics
+pub fn record_quality(patient: &Patient, today: NaiveDate) -> RecordQuality {
+    let mut score = 0.0;
+    let mut missing = Vec::new();
+    for field in QualityField::ALL {
+        if field.is_usable(patient, today) {
+            score += field.weight();
+        } else {
+            missing.push(field);
+        }
+    }
+    RecordQuality { score, missing }
+}
+
+/// Data quality of one source system
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SourceDataQuality {
+    pub source_system: String,
+    /// Source records received in the range
+    pub records: usize,
+    /// Mean record quality score, from 0.0 to 1.0; absent without records
+    pub average_quality_score: Option<f64>,
+    /// Share of records missing each field
+    pub missing_field_rates: BTreeMap<QualityField, f64>,
+    /// New enterprise records created from this source
+    pub new_patients: i64,
+    /// Of those, records later merged as duplicates
+    pub duplicates_created: i64,
+    pub duplicate_creation_rate: Option<f64>,
+}
+
+/// Data quality per source system over a date range
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DataQualityReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Sources ordered worst first: lowest quality score, then highest duplicate rate
+    pub sources: Vec<SourceDataQuality>,
+}
+
+#[derive(Default)]
+struct SourceTally {
+    records: usize,
+    score_sum: f64,
+    missing: BTreeMap<QualityField, usize>,
+    kpis: KpiCounts,
+}
+
+/// Accumulates source records and matching KPIs into a report
+pub struct DataQualityReportBuilder {
+    from: NaiveDate,
+    to: NaiveDate,
+    today: NaiveDate,
+    sources: BTreeMap<String, SourceTally>,
+}
+
+impl DataQualityReportBuilder {
+    /// Start a report for an inclusive date range
+    pub fn new(from: NaiveDate, to: NaiveDate) -> Self {
+        Self {
+            from,
+            to,
+            today: Utc::now().date_naive(),
+            sources: BTreeMap::new(),
+        }
+    }
+
+    /// Score one received source record
+    pub fn add_record(&mut self, record: &SourceRecord) {
+        let quality = record_quality(&record.patient, self.today);
+        let tally = self.sources.entry(record.source_system.clone()).or_default();
+        tally.records += 1;
+        tally.score_sum += quality.score;
+        for field in quality.missing {
+            *tally.missing.entry(field).or_insert(0) += 1;
+        }
+    }
+
+    /// Add daily matching KPIs for their duplicate counts
+    pub fn add_kpis(&mut self, kpis: &[DailyMatchingKpis]) {
+        for row in kpis {
+            self.sources
+                .entry(row.source_system.clone())
+                .or_default()
+                .kpis
+                .accumulate(&row.counts);
+        }
+    }
+
+    /// Finish the report, worst sources first
+    pub fn build(self) -> DataQualityReport {
+        let mut sources: Vec<SourceDataQuality> = self
+            .sources
+            .into_iter()
+            .map(|(source_system, tally)| {
+                let records = tally.records.max(1) as f64;
+                let missing_field_rates = QualityField::ALL
+                    .iter()
+                    .map(|field| {
+                        let missing = tally.missing.get(field).copied().unwrap_or(0);
+                        (*field, missing as f64 / records)
+                    })
+                    .collect();
+                SourceDataQuality {
+                    source_system,
+                    records: tally.records,
+                    average_quality_score: (tally.records > 0).then(|| tally.score_sum / records),
+                    missing_field_rates,
+                    new_patients: tally.kpis.new_patients,
+                    duplicates_created: tally.kpis.duplicates_created,
+                    duplicate_creation_rate: tally.kpis.duplicate_rate(),
+                }
+            })
+            .collect();
+
+        sources.sort_by(|a, b| {
+            a.average_quality_score
+                .unwrap_or(f64::MAX)
+                .total_cmp(&b.average_quality_score.unwrap_or(f64::MAX))
+                .then(
+                    b.duplicate_creation_rate
+                        .unwrap_or(0.0)
+                        .total_cmp(&a.duplicate_creation_rate.unwrap_or(0.0)),
+                )
+                .then(a.source_system.cmp(&b.source_system))
+        });
+
+        DataQualityReport {
+            from: self.from,
+            to: self.to,
+            sources,
+        }
+    }
+}
+
+/// Build a report from the source records received in an inclusive date range
+pub fn data_quality_report(
+    source_records: &dyn SourceRecordRepository,
+    kpis: &[DailyMatchingKpis],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<DataQualityReport> {
+    let since = from.and_hms_opt(0, 0, 0).expect("valid time").and_utc();
+    let until = (to + chrono::Duration::days(1)).and_hms_opt(0, 0, 0).expect("valid time").and_utc();
+
+    let mut builder = DataQualityReportBuilder::new(from, to);
+    let mut offset = 0;
+    loop {
+        let batch = source_records.list_received(since, until, BATCH_SIZE, offset)?;
+        for record in &batch {
+            builder.add_record(record);
+        }
+        if (batch.len() as i64) < BATCH_SIZE {
+            break;
+        }
+        offset += BATCH_SIZE;
+    }
+    builder.add_kpis(kpis);
+
+    Ok(builder.build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Address, HumanName, Identifier, IdentifierType, VerificationStatus};
+    use uuid::Uuid;
+
+    fn complete_patient() -> Patient {
+        let mut patient = Patient::new(
+            HumanName {
+                use_type: None,
+                family: "Ramírez".to_string(),
+                given: vec!["Lucía".to_string()],
+                prefix: vec![],
+                suffix: vec![],
+            },
+            Gender::Female,
+        );
+        patient.birth_date = NaiveDate::from_ymd_opt(1984, 6, 2);
+        patient.addresses.push(Address {
+            line1: Some("12 Harbour Rd".to_string()),
+            line2: None,
+            city: Some("Portland".to_string()),
+            state: Some("ME".to_string()),
+            postal_code: Some("04101".to_string()),
+            country: None,
+            verification: VerificationStatus::Unverified,
+            period: None,
+        });
+        patient.telecom.push(crate::models::ContactPoint {
+            system: crate::models::ContactPointSystem::Phone,
+            value: "207-555-0100".to_string(),
+            use_type: None,
+            period: None,
+        });
+        patient.identifiers.push(Identifier::new(
+            IdentifierType::MRN,
+            "urn:mrn".to_string(),
+            "A1001".to_string(),
+        ));
+        patient
+    }
+
+    fn record(source: &str, patient: Patient) -> SourceRecord {
+        SourceRecord {
+            id: Uuid::new_v4(),
+            source_system: source.to_string(),
+            source_record_id: Uuid::new_v4().to_string(),
+            patient,
+            received_at: Utc::now(),
+            received_by: None,
+        }
+    }
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()
+    }
+
+    #[test]
+    fn test_complete_record_scores_one() {
+        let quality = record_quality(&complete_patient(), today());
+        assert!((quality.score - 1.0).abs() < 1e-9);
+        assert!(quality.missing.is_empty());
+    }
+
+    #[test]
+    fn test_missing_and_implausible_fields() {
+        let mut patient = complete_patient();
+        patient.birth_date = NaiveDate::from_ymd_opt(2030, 1, 1);
+        patient.gender = Gender::Unknown;
+        patient.identifiers.clear();
+
+        let quality = record_quality(&patient, today());
+        assert_eq!(
+            quality.missing,
+            vec![QualityField::BirthDate, QualityField::Gender, QualityField::Identifier]
+        );
+        assert!((quality.score - 0.60).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_report_ranks_worst_source_first() {
+        let mut sparse = complete_patient();
+        sparse.addresses.clear();
+        sparse.telecom.clear();
+
+        let mut builder = DataQualityReportBuilder::new(today(), today());
+        builder.add_record(&record("clinic", complete_patient()));
+        builder.add_record(&record("lab", complete_patient()));
+        builder.add_record(&record("lab", sparse));
+        builder.add_kpis(&[DailyMatchingKpis {
+            day: today(),
+            source_system: "lab".to_string(),
+            counts: KpiCounts {
+                new_patients: 4,
+                duplicates_created: 1,
+                ..Default::default()
+            },
+        }]);
+
+        let report = builder.build();
+
+        assert_eq!(report.sources.len(), 2);
+        let lab = &report.sources[0];
+        assert_eq!(lab.source_system, "lab");
+        assert_eq!(lab.records, 2);
+        assert!((lab.average_quality_score.unwrap() - 0.875).abs() < 1e-9);
+        assert_eq!(lab.missing_field_rates[&QualityField::PostalCode], 0.5);
+        assert_eq!(lab.missing_field_rates[&QualityField::FamilyName], 0.0);
+        assert_eq!(lab.duplicate_creation_rate, Some(0.25));
+
+        let clinic = &report.sources[1];
+        assert_eq!(clinic.source_system, "clinic");
+        assert_eq!(clinic.duplicate_creation_rate, None);
+    }
+}
@@ -0,0 +1,294 @@
+//! Matching quality KPIs
+//!
+//! Tracks how well the MPI is keeping one record per person, per source
+//! system and day:
+//! - duplicate creation rate: new enterprise records that were later merged
+//!   into another record
+//! - auto-link vs manual-review ratio: links made by the matcher versus by a
+//!   reviewer
+//! - average match score of the links made
+//! - overlay incidents: links a reviewer removed because the source record
+//!   had been attached to the wrong person
+//!
+//! A scheduled job recomputes a trailing window of days and persists them,
+//! so a record merged days later still counts as a duplicate on the day it
+//! was created.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{NaiveDate, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::config::ReportingConfig;
+use crate::db::MatchingKpiRepository;
+use crate::Result;
+
+pub mod data_quality;
+
+pub use data_quality::{DataQualityReport, SourceDataQuality, QualityField};
+
+/// Matching KPIs for one source system on one day
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct DailyMatchingKpis {
+    pub day: NaiveDate,
+    pub source_system: String,
+    #[serde(flatten)]
+    pub counts: KpiCounts,
+}
+
+/// Counts and averages shared by daily rows and per-source totals
+#[derive(Debug, Clone, Default, PartialEq, Serialize, ToSchema)]
+pub struct KpiCounts {
+    /// Source records received
+    pub records_received: i64,
+    /// Links that created a new enterprise record
+    pub new_patients: i64,
+    /// New enterprise records that have since been merged into another
+    pub duplicates_created: i64,
+    /// Links made by the matcher
+    pub auto_links: i64,
+    /// Links made by a reviewer
+    pub manual_links: i64,
+    /// Mean match score of the links made
+    pub average_match_score: Option<f64>,
+    /// Links a reviewer removed as wrongly attached
+    pub overlay_incidents: i64,
+}
+
+impl KpiCounts {
+    /// Share of new enterprise records that turned out to be duplicates
+    pub fn duplicate_rate(&self) -> Option<f64> {
+        ratio(self.duplicates_created, self.new_patients)
+    }
+
+    /// Share of links made without manual review
+    pub fn auto_link_ratio(&self) -> Option<f64> {
+        ratio(self.auto_links, self.links())
+    }
+
+    fn links(&self) -> i64 {
+        self.auto_links + self.manual_links
+    }
+
+    /// Add another set of counts, weighting averages by link count
+    pub fn accumulate(&mut self, other: &KpiCounts) {
+        self.average_match_score = match (self.average_match_score, other.average_match_score) {
+            (Some(a), Some(b)) => {
+                let (wa, wb) = (self.links().max(1) as f64, other.links().max(1) as f64);
+                Some((a * wa + b * wb) / (wa + wb))
+            }
+            (a, b) => a.or(b),
+        };
+        self.records_received += other.records_received;
+        self.new_patients += other.new_patients;
+        self.duplicates_created += other.duplicates_created;
+        self.auto_links += other.auto_links;
+        self.manual_links += other.manual_links;
+        self.overlay_incidents += other.overlay_incidents;
+    }
+}
+
+fn ratio(numerator: i64, denominator: i64) -> Option<f64> {
+    (denominator > 0).then(|| numerator as f64 / denominator as f64)
+}
+
+/// Matching KPIs for a source system over the whole report range
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SourceKpiTotals {
+    pub source_system: String,
+    #[serde(flatten)]
+    pub counts: KpiCounts,
+    pub duplicate_rate: Option<f64>,
+    pub auto_link_ratio: Option<f64>,
+}
+
+/// Matching KPIs over a date range
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MatchingReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// One row per source system and day, oldest first
+    pub daily: Vec<DailyMatchingKpis>,
+    /// Totals per source system across the range
+    pub by_source: Vec<SourceKpiTotals>,
+}
+
+impl MatchingReport {
+    /// Build a report from daily rows
+    pub fn new(from: NaiveDate, to: NaiveDate, daily: Vec<DailyMatchingKpis>) -> Self {
+        let mut totals: BTreeMap<&str, KpiCounts> = BTreeMap::new();
+        for row in &daily {
+            totals
+                .entry(row.source_system.as_str())
+                .or_default()
+                .accumulate(&row.counts);
+        }
+
+        let by_source = totals
+            .into_iter()
+            .map(|(source_system, counts)| SourceKpiTotals {
+                source_system: source_system.to_string(),
+                duplicate_rate: counts.duplicate_rate(),
+                auto_link_ratio: counts.auto_link_ratio(),
+                counts,
+            })
+            .collect();
+
+        Self { from, to, daily, by_source }
+    }
+}
+
+/// Recomputes and persists daily KPIs for a trailing window of days
+pub struct MatchingKpiJob {
+    repository: Arc<MatchingKpiRepository>,
+    config: ReportingConfig,
+}
+
+impl MatchingKpiJob {
+    /// Create a job writing through the given repository
+    pub fn new(repository: Arc<MatchingKpiRepository>, config: ReportingConfig) -> Self {
+        Self { repository, config }
+    }
+
+    /// Recompute the completed days in the lookback window ending before `today`
+    pub fn run(&self, today: NaiveDate) -> Result<usize> {
+        let mut rows = 0;
+        for days_ago in (1..=self.config.lookback_days.max(1)).rev() {
+            let day = today - chrono::Duration::days(days_ago as i64);
+            rows += self.run_day(day)?;
+        }
+        Ok(rows)
+    }
+
+    /// Recompute and persist one day, returning the number of source rows
+    pub fn run_day(&self, day: NaiveDate) -> Result<usize> {
+        let kpis = self.repository.compute_day(day, &self.config.automatic_linker)?;
+        self.repository.save(&kpis)?;
+        Ok(kpis.len())
+    }
+
+    /// Run now, then shortly after each UTC midnight
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let job = Arc::new(self);
+            loop {
+                let today = Utc::now().date_naive();
+                let runner = job.clone();
+                match tokio::task::spawn_blocking(move || runner.run(today)).await {
+                    Ok(Ok(rows)) => tracing::info!("Computed matching KPIs: {} source-day rows", rows),
+                    Ok(Err(e)) => tracing::warn!("Matching KPI job failed: {}", e),
+                    Err(e) => {
+                        tracing::error!("Matching KPI job stopped: {}", e);
+                        return;
+                    }
+                }
+                tokio::time::sleep(until_next_run()).await;
+            }
+        })
+    }
+}
+
+/// Time until five minutes past the next UTC midnight
+fn until_next_run() -> Duration {
+    let now = Utc::now();
+    let next = (now.date_naive() + chrono::Duration::days(1))
+        .and_hms_opt(0, 5, 0)
+        .expect("valid time")
+        .and_utc();
+    (next - now).to_std().unwrap_or(Duration::from_secs(60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, d).unwrap()
+    }
+
+    fn row(d: u32, source: &str, counts: KpiCounts) -> DailyMatchingKpis {
+        DailyMatchingKpis {
+            day: day(d),
+            source_system: source.to_string(),
+            counts,
+        }
+    }
+
+    #[test]
+    fn test_rates() {
+        let counts = KpiCounts {
+            new_patients: 8,
+            duplicates_created: 2,
+            auto_links: 9,
+            manual_links: 3,
+            ..Default::default()
+        };
+        assert_eq!(counts.duplicate_rate(), Some(0.25));
+        assert_eq!(counts.auto_link_ratio(), Some(0.75));
+
+        let empty = KpiCounts::default();
+        assert_eq!(empty.duplicate_rate(), None);
+        assert_eq!(empty.auto_link_ratio(), None);
+    }
+
+    #[test]
+    fn test_report_totals_by_source() {
+        let daily = vec![
+            row(1, "lab", KpiCounts {
+                records_received: 10,
+                new_patients: 4,
+                duplicates_created: 1,
+                auto_links: 3,
+                manual_links: 1,
+                average_match_score: Some(0.9),
+                overlay_incidents: 0,
+            }),
+            row(1, "registration", KpiCounts {
+                records_received: 5,
+                new_patients: 5,
+                auto_links: 5,
+                average_match_score: Some(0.95),
+                ..Default::default()
+            }),
+            row(2, "lab", KpiCounts {
+                records_received: 6,
+                new_patients: 4,
+                duplicates_created: 3,
+                auto_links: 8,
+                manual_links: 4,
+                average_match_score: Some(0.8),
+                overlay_incidents: 2,
+            }),
+        ];
+
+        let report = MatchingReport::new(day(1), day(2), daily);
+
+        assert_eq!(report.by_source.len(), 2);
+        let lab = &report.by_source[0];
+        assert_eq!(lab.source_system, "lab");
+        assert_eq!(lab.counts.records_received, 16);
+        assert_eq!(lab.counts.overlay_incidents, 2);
+        assert_eq!(lab.duplicate_rate, Some(0.5));
+        assert_eq!(lab.auto_link_ratio, Some(11.0 / 16.0));
+        // 4 links at 0.9 and 12 at 0.8
+        let average = lab.counts.average_match_score.unwrap();
+        assert!((average - 0.825).abs() < 1e-9);
+
+        let registration = &report.by_source[1];
+        assert_eq!(registration.counts.average_match_score, Some(0.95));
+        assert_eq!(registration.duplicate_rate, Some(0.0));
+    }
+
+    #[test]
+    fn test_report_serializes_flat_rows() {
+        let report = MatchingReport::new(day(1), day(1), vec![row(1, "lab", KpiCounts::default())]);
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(json["daily"][0]["source_system"], "lab");
+        assert_eq!(json["daily"][0]["records_received"], 0);
+        assert!(json["by_source"][0]["duplicate_rate"].is_null());
+    }
+}
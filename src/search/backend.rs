@@ -0,0 +1,292 @@
+//! Pluggable search backends
+//!
+//! The API and matching code retrieve candidates through [`SearchBackend`],
+//! so the embedded Tantivy index can be swapped for an external search
+//! service. Features that only some backends support have default
+//! implementations that return an error.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::config::{Config, SearchBackendKind};
+use crate::matching::transliteration::Transliterator;
+use crate::db::{DbPool, DieselPatientRepository};
+use crate::models::Patient;
+use crate::{Error, Result};
+use super::{FuzzyOptions, IndexStats, SearchEngine, SearchHit, SnapshotInfo, Suggestion};
+
+/// Candidate retrieval and indexing operations
+pub trait SearchBackend: Send + Sync {
+    /// Backend name, for logs and error messages
+    fn name(&self) -> &'static str;
+
+    /// Index or re-index a patient
+    fn index_patient(&self, patient: &Patient) -> Result<()>;
+
+    /// Bulk index multiple patients
+    fn index_patients(&self, patients: &[Patient]) -> Result<()>;
+
+    /// Replace and remove documents as one batch
+    fn apply_changes(&self, upserts: &[Patient], removals: &[String]) -> Result<()>;
+
+    /// Remove a patient from the index
+    fn delete_patient(&self, patient_id: &str) -> Result<()>;
+
+    /// Remove every document from the index
+    fn clear(&self) -> Result<()>;
+
+    /// Search for patients by query string
+    fn search(&self, query_str: &str, limit: usize) -> Result<Vec<String>> {
+        Ok(self
+            .search_with_scores(query_str, limit)?
+            .into_iter()
+            .map(|hit| hit.patient_id)
+            .collect())
+    }
+
+    /// Search for patients, returning relevance scores and highlights
+    fn search_with_scores(&self, query_str: &str, limit: usize) -> Result<Vec<SearchHit>>;
+
+    /// Fuzzy search for patients by name or identifier, as far from the
+    /// query as `options` allows
+    fn fuzzy_search(&self, query_str: &str, options: &FuzzyOptions, limit: usize) -> Result<Vec<String>> {
+        Ok(self
+            .fuzzy_search_with_scores(query_str, options, limit)?
+            .into_iter()
+            .map(|hit| hit.patient_id)
+            .collect())
+    }
+
+    /// Fuzzy search for patients by name or identifier, returning relevance scores
+    fn fuzzy_search_with_scores(&self, query_str: &str, options: &FuzzyOptions, limit: usize) -> Result<Vec<SearchHit>>;
+
+    /// Search by name and birth year (for blocking in matching)
+    fn search_by_name_and_year(
+        &self,
+        family_name: &str,
+        birth_year: Option<i32>,
+        limit: usize,
+    ) -> Result<Vec<String>>;
+
+    /// Patients sharing any of the given precomputed blocking keys
+    fn search_by_blocking_keys(&self, _keys: &[String], _limit: usize) -> Result<Vec<String>> {
+        Err(self.unsupported("blocking key search"))
+    }
+
+    /// Search by normalized phone number and/or email; every value given must match
+    fn search_by_telecom(&self, _phone: Option<&str>, _email: Option<&str>, _limit: usize) -> Result<Vec<SearchHit>> {
+        Err(self.unsupported("telecom search"))
+    }
+
+    /// The given patients whose records `organization` manages, for scoping
+    /// search and match candidates to one facility
+    fn in_organization(&self, _patient_ids: &[String], _organization: &Uuid) -> Result<HashSet<String>> {
+        Err(self.unsupported("organization filtering"))
+    }
+
+    /// Suggest indexed family names close to the input
+    fn suggest(&self, _query_str: &str, _limit: usize) -> Result<Vec<Suggestion>> {
+        Err(self.unsupported("suggestions"))
+    }
+
+    /// Document and segment counts
+    fn stats(&self) -> Result<IndexStats> {
+        Err(self.unsupported("index statistics"))
+    }
+
+    /// IDs of every indexed patient, for comparing the index with the database
+    fn patient_ids(&self) -> Result<HashSet<String>> {
+        Err(self.unsupported("listing indexed patients"))
+    }
+
+    /// Write a consistent copy of the index to the given directory
+    fn snapshot(&self, _path: &Path) -> Result<SnapshotInfo> {
+        Err(self.unsupported("snapshots"))
+    }
+
+    /// Restore the index from a snapshot directory
+    fn restore(&self, _path: &Path) -> Result<SnapshotInfo> {
+        Err(self.unsupported("snapshots"))
+    }
+
+    /// Make recent changes visible to searches
+    fn reload(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Error for a feature this backend does not provide
+    fn unsupported(&self, feature: &str) -> Error {
+        Error::Search(format!("The {} search backend does not support {}", self.name(), feature))
+    }
+}
+
+impl SearchBackend for SearchEngine {
+    fn name(&self) -> &'static str {
+        "tantivy"
+    }
+
+    fn index_patient(&self, patient: &Patient) -> Result<()> {
+        SearchEngine::index_patient(self, patient)
+    }
+
+    fn index_patients(&self, patients: &[Patient]) -> Result<()> {
+        SearchEngine::index_patients(self, patients)
+    }
+
+    fn apply_changes(&self, upserts: &[Patient], removals: &[String]) -> Result<()> {
+        SearchEngine::apply_changes(self, upserts, removals)
+    }
+
+    fn delete_patient(&self, patient_id: &str) -> Result<()> {
+        SearchEngine::delete_patient(self, patient_id)
+    }
+
+    fn clear(&self) -> Result<()> {
+        SearchEngine::clear(self)
+    }
+
+    fn search(&self, query_str: &str, limit: usize) -> Result<Vec<String>> {
+        SearchEngine::search(self, query_str, limit)
+    }
+
+    fn search_with_scores(&self, query_str: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        SearchEngine::search_with_scores(self, query_str, limit)
+    }
+
+    fn fuzzy_search(&self, query_str: &str, options: &FuzzyOptions, limit: usize) -> Result<Vec<String>> {
+        SearchEngine::fuzzy_search(self, query_str, options, limit)
+    }
+
+    fn fuzzy_search_with_scores(&self, query_str: &str, options: &FuzzyOptions, limit: usize) -> Result<Vec<SearchHit>> {
+        SearchEngine::fuzzy_search_with_scores(self, query_str, options, limit)
+    }
+
+    fn search_by_name_and_year(
+        &self,
+        family_name: &str,
+        birth_year: Option<i32>,
+        limit: usize,
+    ) -> Result<Vec<String>> {
+        SearchEngine::search_by_name_and_year(self, family_name, birth_year, limit)
+    }
+
+    fn search_by_blocking_keys(&self, keys: &[String], limit: usize) -> Result<Vec<String>> {
+        SearchEngine::search_by_blocking_keys(self, keys, limit)
+    }
+
+    fn search_by_telecom(&self, phone: Option<&str>, email: Option<&str>, limit: usize) -> Result<Vec<SearchHit>> {
+        SearchEngine::search_by_telecom(self, phone, email, limit)
+    }
+
+    fn in_organization(&self, patient_ids: &[String], organization: &Uuid) -> Result<HashSet<String>> {
+        SearchEngine::in_organization(self, patient_ids, organization)
+    }
+
+    fn suggest(&self, query_str: &str, limit: usize) -> Result<Vec<Suggestion>> {
+        SearchEngine::suggest(self, query_str, limit)
+    }
+
+    fn stats(&self) -> Result<IndexStats> {
+        SearchEngine::stats(self)
+    }
+
+    fn patient_ids(&self) -> Result<HashSet<String>> {
+        SearchEngine::patient_ids(self)
+    }
+
+    fn snapshot(&self, path: &Path) -> Result<SnapshotInfo> {
+        SearchEngine::snapshot(self, path)
+    }
+
+    fn restore(&self, path: &Path) -> Result<SnapshotInfo> {
+        SearchEngine::restore(self, path)
+    }
+
+    fn reload(&self) -> Result<()> {
+        SearchEngine::reload(self)
+    }
+}
+
+/// Create the search backend selected in configuration
+///
+/// The Postgres backend searches through the pool; Tantivy only uses it to
+/// rebuild an index whose schema is stale. Tantivy indexes names with the
+/// same transliteration tables the matcher uses. With partitioning enabled,
+/// a second Tantivy index or OpenSearch index holds the cold partition.
+pub fn create_backend(app_config: &Config, pool: &DbPool) -> Result<Arc<dyn SearchBackend>> {
+    let config = &app_config.search;
+    if !config.partitioning.enabled {
+        return create_partition(app_config, pool, None);
+    }
+    if config.backend == SearchBackendKind::Postgres {
+        return Err(Error::Config(
+            "search.partitioning is not supported by the postgres search backend".to_string(),
+        ));
+    }
+    let hot = create_partition(app_config, pool, Some(Partition::Hot))?;
+    let cold = create_partition(app_config, pool, Some(Partition::Cold))?;
+    Ok(Arc::new(super::PartitionedIndex::new(hot, cold)))
+}
+
+/// Which half of a partitioned index to create
+#[derive(Clone, Copy, PartialEq)]
+enum Partition {
+    Hot,
+    Cold,
+}
+
+fn create_partition(app_config: &Config, pool: &DbPool, partition: Option<Partition>) -> Result<Arc<dyn SearchBackend>> {
+    let config = &app_config.search;
+    match config.backend {
+        SearchBackendKind::Tantivy => {
+            let transliterator = Transliterator::from_config(&app_config.matching.transliteration);
+            let index_path = match partition {
+                Some(Partition::Cold) => &config.partitioning.cold_index_path,
+                _ => &config.index_path,
+            };
+            // An index built with an older schema is rebuilt from the database before use
+            let patients = DieselPatientRepository::new(pool.clone());
+            match partition {
+                None => super::rebuild_if_stale(Path::new(index_path), &patients, &transliterator)?,
+                Some(partition) => super::rebuild_if_stale_where(
+                    Path::new(index_path),
+                    &patients,
+                    &transliterator,
+                    |patient| super::partition::is_hot(patient) == (partition == Partition::Hot),
+                )?,
+            };
+
+            let engine = SearchEngine::new(index_path)?
+                .with_field_boosts(config.field_boosts.clone())
+                .with_transliterator(transliterator);
+            Ok(Arc::new(engine))
+        }
+        SearchBackendKind::Postgres => {
+            Ok(Arc::new(super::postgres::PostgresSearchBackend::new(pool.clone())))
+        }
+        #[cfg(feature = "opensearch")]
+        SearchBackendKind::OpenSearch => {
+            let mut opensearch = config.opensearch.clone().ok_or_else(|| {
+                Error::Config("search.opensearch must be set for the opensearch backend".to_string())
+            })?;
+            if partition == Some(Partition::Cold) {
+                opensearch.index = config
+                    .partitioning
+                    .cold_index
+                    .clone()
+                    .unwrap_or_else(|| format!("{}-cold", opensearch.index));
+            }
+            let backend = super::opensearch::OpenSearchBackend::new(opensearch)
+                .with_field_boosts(config.field_boosts.clone());
+            backend.ensure_index()?;
+            Ok(Arc::new(backend))
+        }
+        #[cfg(not(feature = "opensearch"))]
+        SearchBackendKind::OpenSearch => Err(Error::Config(
+            "The opensearch search backend requires the `opensearch` feature".to_string(),
+        )),
+    }
+}
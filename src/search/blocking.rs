@@ -0,0 +1,361 @@
+//! Precomputed blocking keys
+//!
+//! Blocking by fuzzy queries over raw family names is the most expensive
+//! part of a match request. Each patient document instead carries keys
+//! computed at index time, and candidates are the documents sharing a key
+//! with the incoming record, found by exact term lookups:
+//!
+//! - `sy:<soundex(family)>:<birth year>`, e.g. `sy:S530:1980`
+//! - `ib:<first given initial>:<birth date>`, e.g. `ib:j:1980-01-15`
+//! - `zf:<first three of postal code>:<first three of family>`, e.g. `zf:606:smi`
+//!
+//! Family name keys are computed for maiden names as well, and postal code
+//! keys for current addresses only.
+//!
+//! The `multi_pass` blocking strategy runs an ordered list of searches, each
+//! a [`BlockingPass`], adding the candidates of each to those of the passes
+//! before it until enough are found. A record whose family name is
+//! misspelled is still found by the passes that do not compare it.
+
+use chrono::Datelike;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::config::BlockingPass;
+use crate::models::Patient;
+use crate::Result;
+use super::backend::SearchBackend;
+use super::maiden_names;
+
+/// A kind of blocking key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockingKeyKind {
+    /// Soundex code of the family name and birth year
+    SoundexYear,
+    /// First initial of the given name and full birth date
+    InitialBirthDate,
+    /// First three characters of the postal code and of the family name
+    Zip3FamilyPrefix,
+}
+
+impl BlockingKeyKind {
+    /// Every kind, in the order they are indexed
+    pub const ALL: [BlockingKeyKind; 3] = [
+        BlockingKeyKind::SoundexYear,
+        BlockingKeyKind::InitialBirthDate,
+        BlockingKeyKind::Zip3FamilyPrefix,
+    ];
+
+    /// Keys of this kind for a patient; none when a component is missing
+    pub fn keys(self, patient: &Patient) -> Vec<String> {
+        let legal_name = patient.legal_name();
+        let families = || std::iter::once(legal_name.family.as_str()).chain(maiden_names(patient));
+
+        let mut keys: Vec<String> = match self {
+            BlockingKeyKind::SoundexYear => match patient.birth_date {
+                Some(birth_date) => families()
+                    .filter_map(soundex)
+                    .map(|code| format!("sy:{}:{}", code, birth_date.year()))
+                    .collect(),
+                None => Vec::new(),
+            },
+            BlockingKeyKind::InitialBirthDate => {
+                let initial = legal_name
+                    .given
+                    .first()
+                    .and_then(|given| given.chars().find(|c| c.is_alphanumeric()))
+                    .map(|c| c.to_lowercase().to_string());
+                match (initial, patient.birth_date) {
+                    (Some(initial), Some(birth_date)) => vec![format!("ib:{}:{}", initial, birth_date)],
+                    _ => Vec::new(),
+                }
+            }
+            BlockingKeyKind::Zip3FamilyPrefix => {
+                let prefixes: Vec<String> = families().filter_map(|family| leading(family, 3)).collect();
+                patient
+                    .addresses
+                    .iter()
+                    .filter(|address| address.is_current())
+                    .filter_map(|address| address.postal_code.as_deref().and_then(|code| leading(code, 3)))
+                    .flat_map(|zip3| prefixes.iter().map(move |prefix| format!("zf:{}:{}", zip3, prefix)))
+                    .collect()
+            }
+        };
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+}
+
+/// Every blocking key of a patient, as indexed
+pub fn blocking_keys(patient: &Patient) -> Vec<String> {
+    BlockingKeyKind::ALL.iter().flat_map(|kind| kind.keys(patient)).collect()
+}
+
+/// What a record's blocking passes search for
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockingQuery {
+    family: String,
+    birth_year: Option<i32>,
+    keys: Vec<(BlockingKeyKind, Vec<String>)>,
+}
+
+/// Candidates found by one blocking pass
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PassYield {
+    pub pass: BlockingPass,
+    /// Candidates the pass's search returned
+    pub found: usize,
+    /// Candidates not already found by an earlier pass and within the cap
+    pub added: usize,
+    /// Why the search failed, if it did
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Candidates of multi-pass blocking and what each pass contributed
+#[derive(Debug, Clone, Default)]
+pub struct MultiPassBlocking {
+    /// Candidate patient IDs, in the order found
+    pub ids: Vec<String>,
+    /// Passes run, in order; passes after the cap was reached are not run
+    pub passes: Vec<PassYield>,
+}
+
+impl BlockingQuery {
+    /// The family name, birth year and blocking keys of a patient
+    pub fn of(patient: &Patient) -> Self {
+        Self {
+            family: patient.legal_name().family.trim().to_lowercase(),
+            birth_year: patient.birth_date.map(|d| d.year()),
+            keys: BlockingKeyKind::ALL.iter().map(|kind| (*kind, kind.keys(patient))).collect(),
+        }
+    }
+
+    fn keys(&self, kind: BlockingKeyKind) -> &[String] {
+        self.keys
+            .iter()
+            .find(|(k, _)| *k == kind)
+            .map(|(_, keys)| keys.as_slice())
+            .unwrap_or_default()
+    }
+
+    fn search(&self, search: &dyn SearchBackend, pass: BlockingPass, limit: usize) -> Result<Vec<String>> {
+        let keys = match pass {
+            BlockingPass::NameAndBirthYear | BlockingPass::Name if self.family.is_empty() => return Ok(Vec::new()),
+            BlockingPass::NameAndBirthYear => return search.search_by_name_and_year(&self.family, self.birth_year, limit),
+            BlockingPass::Name => return search.search_by_name_and_year(&self.family, None, limit),
+            BlockingPass::SoundexYear => self.keys(BlockingKeyKind::SoundexYear),
+            BlockingPass::InitialBirthDate => self.keys(BlockingKeyKind::InitialBirthDate),
+            BlockingPass::Zip3FamilyPrefix => self.keys(BlockingKeyKind::Zip3FamilyPrefix),
+        };
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        search.search_by_blocking_keys(keys, limit)
+    }
+
+    /// Run `passes` in order until `max_candidates` are found
+    ///
+    /// A failed pass is recorded and skipped; the error is only returned
+    /// when every pass run failed.
+    pub fn run(&self, search: &dyn SearchBackend, passes: &[BlockingPass], max_candidates: usize) -> Result<MultiPassBlocking> {
+        let mut result = MultiPassBlocking::default();
+        let mut first_error = None;
+        for pass in passes {
+            if result.ids.len() >= max_candidates {
+                break;
+            }
+            match self.search(search, *pass, max_candidates) {
+                Ok(ids) => {
+                    let found = ids.len();
+                    let before = result.ids.len();
+                    for id in ids {
+                        if result.ids.len() >= max_candidates {
+                            break;
+                        }
+                        if !result.ids.contains(&id) {
+                            result.ids.push(id);
+                        }
+                    }
+                    result.passes.push(PassYield { pass: *pass, found, added: result.ids.len() - before, error: None });
+                }
+                Err(e) => {
+                    tracing::warn!("Blocking pass {} failed: {}", pass.as_str(), e);
+                    result.passes.push(PassYield { pass: *pass, found: 0, added: 0, error: Some(e.to_string()) });
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        match first_error {
+            Some(e) if result.passes.iter().all(|pass| pass.error.is_some()) => Err(e),
+            _ => Ok(result),
+        }
+    }
+}
+
+/// The first `len` letters and digits of `text`, lowercased, if it has that many
+fn leading(text: &str, len: usize) -> Option<String> {
+    let prefix: String = text
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .take(len)
+        .collect();
+    (prefix.chars().count() == len).then_some(prefix)
+}
+
+/// American Soundex code of a name, from its ASCII letters
+///
+/// Returns `None` for a name without any, such as one in another script.
+pub fn soundex(name: &str) -> Option<String> {
+    fn digit(c: char) -> Option<char> {
+        match c {
+            'B' | 'F' | 'P' | 'V' => Some('1'),
+            'C' | 'G' | 'J' | 'K' | 'Q' | 'S' | 'X' | 'Z' => Some('2'),
+            'D' | 'T' => Some('3'),
+            'L' => Some('4'),
+            'M' | 'N' => Some('5'),
+            'R' => Some('6'),
+            _ => None,
+        }
+    }
+
+    let mut letters = name.chars().filter(char::is_ascii_alphabetic).map(|c| c.to_ascii_uppercase());
+    let first = letters.next()?;
+    let mut code = String::from(first);
+    let mut previous = digit(first);
+    for c in letters {
+        let current = digit(c);
+        if let Some(d) = current {
+            if current != previous {
+                code.push(d);
+                if code.len() == 4 {
+                    break;
+                }
+            }
+        }
+        // H and W do not separate letters with the same code; vowels do
+        if c != 'H' && c != 'W' {
+            previous = current;
+        }
+    }
+    while code.len() < 4 {
+        code.push('0');
+    }
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Address, Gender, HumanName, NameUse};
+    use crate::search::SearchEngine;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_soundex() {
+        for (name, code) in [
+            ("Robert", "R163"),
+            ("Rupert", "R163"),
+            ("Ashcraft", "A261"),
+            ("Tymczak", "T522"),
+            ("Pfister", "P236"),
+            ("Lee", "L000"),
+            ("O'Brien", "O165"),
+        ] {
+            assert_eq!(soundex(name).as_deref(), Some(code), "{}", name);
+        }
+        assert_eq!(soundex("Иванов"), None);
+    }
+
+    #[test]
+    fn test_blocking_keys() {
+        let mut patient = Patient::new(
+            HumanName {
+                use_type: None,
+                family: "Smith".to_string(),
+                given: vec!["jane".to_string()],
+                prefix: vec![],
+                suffix: vec![],
+            },
+            Gender::Female,
+        );
+        patient.birth_date = NaiveDate::from_ymd_opt(1980, 1, 15);
+        patient.additional_names.push(HumanName {
+            use_type: Some(NameUse::Maiden),
+            family: "Jones".to_string(),
+            given: vec!["Jane".to_string()],
+            prefix: vec![],
+            suffix: vec![],
+        });
+        patient.addresses.push(Address {
+            line1: None,
+            line2: None,
+            city: None,
+            state: None,
+            postal_code: Some("60614".to_string()),
+            country: None,
+            verification: Default::default(),
+            period: None,
+        });
+
+        assert_eq!(
+            blocking_keys(&patient),
+            vec![
+                "sy:J520:1980",
+                "sy:S530:1980",
+                "ib:j:1980-01-15",
+                "zf:606:jon",
+                "zf:606:smi",
+            ]
+        );
+
+        patient.birth_date = None;
+        assert!(BlockingKeyKind::SoundexYear.keys(&patient).is_empty());
+        assert!(BlockingKeyKind::InitialBirthDate.keys(&patient).is_empty());
+    }
+
+    #[test]
+    fn test_passes_run_until_the_cap() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+        let patient = |family: &str, given: &str, birth_date: &str| {
+            let mut patient = Patient::new(
+                HumanName {
+                    use_type: None,
+                    family: family.to_string(),
+                    given: vec![given.to_string()],
+                    prefix: vec![],
+                    suffix: vec![],
+                },
+                Gender::Female,
+            );
+            patient.birth_date = NaiveDate::parse_from_str(birth_date, "%Y-%m-%d").ok();
+            patient
+        };
+        // Only the first initial and birth date pass finds the misspelled surname
+        let on_file = patient("Kowalczyk", "Anna", "1975-03-02");
+        let namesake = patient("Smith", "Anna", "1990-07-01");
+        engine.index_patients(&[on_file.clone(), namesake]).unwrap();
+        engine.reload().unwrap();
+
+        let query = BlockingQuery::of(&patient("Qoualchik", "Anna", "1975-03-02"));
+        let passes = [BlockingPass::SoundexYear, BlockingPass::InitialBirthDate, BlockingPass::Zip3FamilyPrefix];
+        let blocked = query.run(&engine, &passes, 10).unwrap();
+        assert_eq!(blocked.ids, vec![on_file.id.to_string()]);
+        let added: Vec<_> = blocked.passes.iter().map(|pass| (pass.pass, pass.added)).collect();
+        assert_eq!(
+            added,
+            vec![
+                (BlockingPass::SoundexYear, 0),
+                (BlockingPass::InitialBirthDate, 1),
+                (BlockingPass::Zip3FamilyPrefix, 0),
+            ]
+        );
+
+        // Once the cap is reached, later passes are not run
+        let capped = query.run(&engine, &[BlockingPass::InitialBirthDate, BlockingPass::SoundexYear], 1).unwrap();
+        assert_eq!(capped.passes.len(), 1);
+    }
+}
@@ -0,0 +1,224 @@
+//! Bulk indexing of patient streams
+//!
+//! [`SearchBackend::index_patients`] takes every patient at once and
+//! commits them together, which is fine for a page of results but not for
+//! reindexing millions of records. A [`BulkIndexer`] instead pulls patients
+//! from an iterator and commits every `search.bulk_index.batch_size`, so
+//! only one batch is held in memory. Each batch replaces the patients'
+//! existing documents, so a live index can be reindexed in place.
+//! Producers that push rather than pull, such as an async loader, get a
+//! bounded [channel](BulkIndexer::channel) whose sends wait while the
+//! indexer is behind.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Instant;
+
+use serde::Serialize;
+use tokio::sync::mpsc;
+use utoipa::ToSchema;
+
+use crate::config::BulkIndexConfig;
+use crate::db::PatientRepository;
+use crate::jobs::JobHandle;
+use crate::models::Patient;
+use crate::Result;
+use super::SearchBackend;
+
+/// Outcome of a bulk indexing run
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct BulkIndexReport {
+    /// Patients written to the index
+    pub patients_indexed: u64,
+    /// Index commits made
+    pub batches: u64,
+    /// Time taken
+    pub elapsed_ms: u64,
+}
+
+/// Indexes patients from a stream in fixed-size commits
+pub struct BulkIndexer {
+    backend: Arc<dyn SearchBackend>,
+    batch_size: usize,
+    queue_batches: usize,
+}
+
+impl BulkIndexer {
+    /// Create an indexer writing to `backend`
+    pub fn new(backend: Arc<dyn SearchBackend>, config: &BulkIndexConfig) -> Self {
+        Self {
+            backend,
+            batch_size: config.batch_size.max(1),
+            queue_batches: config.queue_batches.max(1),
+        }
+    }
+
+    /// Index every patient `patients` yields, committing once per batch
+    ///
+    /// The next patient is not read until the current batch is committed.
+    /// Progress is reported through `handle` after each commit. The first
+    /// error from the iterator or the backend stops the run; batches
+    /// committed before it stay in the index.
+    pub fn index_iter<I>(&self, patients: I, handle: Option<&JobHandle>) -> Result<BulkIndexReport>
+    where
+        I: IntoIterator<Item = Result<Patient>>,
+    {
+        let started = Instant::now();
+        let mut report = BulkIndexReport::default();
+        let mut batch = Vec::with_capacity(self.batch_size);
+        for patient in patients {
+            batch.push(patient?);
+            if batch.len() == self.batch_size {
+                self.commit(&mut batch, &mut report, handle)?;
+            }
+        }
+        self.commit(&mut batch, &mut report, handle)?;
+        report.elapsed_ms = started.elapsed().as_millis() as u64;
+        Ok(report)
+    }
+
+    /// Write one batch to the index and empty it
+    fn commit(&self, batch: &mut Vec<Patient>, report: &mut BulkIndexReport, handle: Option<&JobHandle>) -> Result<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.backend.apply_changes(batch, &[])?;
+        report.patients_indexed += batch.len() as u64;
+        report.batches += 1;
+        if let Some(handle) = handle {
+            handle.advance(batch.len() as u64);
+        }
+        batch.clear();
+        Ok(())
+    }
+
+    /// Index patients sent down the returned channel, on the blocking
+    /// thread pool
+    ///
+    /// The channel holds `queue_batches` batches; once it is full, sends
+    /// wait until the indexer has taken a batch's worth. Dropping the sender
+    /// commits what is left and ends the run. If indexing fails, the
+    /// receiver is dropped and further sends fail.
+    pub fn channel(self, handle: Option<JobHandle>) -> (mpsc::Sender<Patient>, tokio::task::JoinHandle<Result<BulkIndexReport>>) {
+        let (tx, mut rx) = mpsc::channel(self.batch_size * self.queue_batches);
+        let task = tokio::task::spawn_blocking(move || {
+            let patients = std::iter::from_fn(|| rx.blocking_recv().map(Ok));
+            self.index_iter(patients, handle.as_ref())
+        });
+        (tx, task)
+    }
+
+    /// Index every patient `patients` yields in the background, reporting
+    /// progress through `handle`
+    ///
+    /// `total` is the expected number of patients, if known. The job's
+    /// result is the [`BulkIndexReport`].
+    pub fn spawn<I>(self, patients: I, total: Option<u64>, handle: JobHandle) -> tokio::task::JoinHandle<()>
+    where
+        I: IntoIterator<Item = Result<Patient>> + Send + 'static,
+    {
+        tokio::task::spawn_blocking(move || {
+            handle.start(total);
+            match self.index_iter(patients, Some(&handle)) {
+                Ok(report) => {
+                    tracing::info!(
+                        "Bulk indexed {} patients in {} commits in {} ms",
+                        report.patients_indexed,
+                        report.batches,
+                        report.elapsed_ms
+                    );
+                    handle.complete(serde_json::to_value(&report).unwrap_or_default());
+                }
+                Err(e) => {
+                    tracing::warn!("Bulk indexing failed: {}", e);
+                    handle.fail(&e.to_string());
+                }
+            }
+        })
+    }
+}
+
+/// Every active patient, read from the repository one page at a time as
+/// the iterator is advanced
+pub fn active_patients(patients: Arc<dyn PatientRepository>, page_size: i64) -> ActivePatients {
+    ActivePatients {
+        patients,
+        page_size: page_size.max(1),
+        offset: 0,
+        page: VecDeque::new(),
+        done: false,
+    }
+}
+
+/// Iterator returned by [`active_patients`]
+pub struct ActivePatients {
+    patients: Arc<dyn PatientRepository>,
+    page_size: i64,
+    offset: i64,
+    page: VecDeque<Patient>,
+    done: bool,
+}
+
+impl Iterator for ActivePatients {
+    type Item = Result<Patient>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.page.is_empty() && !self.done {
+            match self.patients.list_active(self.page_size, self.offset) {
+                Ok(page) => {
+                    self.done = (page.len() as i64) < self.page_size;
+                    self.offset += page.len() as i64;
+                    self.page = page.into();
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        self.page.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::patient;
+    use tempfile::TempDir;
+    use crate::db::InMemoryPatientRepository;
+    use crate::models::Gender;
+    use crate::search::SearchEngine;
+
+    #[test]
+    fn test_index_iter_commits_per_batch() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = Arc::new(SearchEngine::new(temp_dir.path()).unwrap());
+        let repository = Arc::new(InMemoryPatientRepository::new());
+        for family in ["Adeyemi", "Balogun", "Chukwu", "Danjuma", "Eze"] {
+            repository.create(&patient(family, &["Ngozi"], Gender::Female)).unwrap();
+        }
+
+        let indexer = BulkIndexer::new(engine.clone(), &BulkIndexConfig { batch_size: 2, queue_batches: 1 });
+        let report = indexer.index_iter(active_patients(repository, 3), None).unwrap();
+        assert_eq!((report.patients_indexed, report.batches), (5, 3));
+
+        engine.reload().unwrap();
+        assert_eq!(engine.search("Danjuma", 10).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_channel_indexes_everything_sent() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = Arc::new(SearchEngine::new(temp_dir.path()).unwrap());
+        let indexer = BulkIndexer::new(engine.clone(), &BulkIndexConfig { batch_size: 2, queue_batches: 1 });
+
+        let (tx, task) = indexer.channel(None);
+        for family in ["Adeyemi", "Balogun", "Chukwu"] {
+            tx.send(patient(family, &["Ngozi"], Gender::Female)).await.unwrap();
+        }
+        drop(tx);
+
+        let report = task.await.unwrap().unwrap();
+        assert_eq!((report.patients_indexed, report.batches), (3, 2));
+    }
+}
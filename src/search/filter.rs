@@ -0,0 +1,609 @@
+//! SCIM-style filter expressions over patients
+//!
+//! `GET /api/v1/patients?_filter=...` takes an expression in the filter
+//! syntax of SCIM (RFC 7644 section 3.4.2.2), e.g.
+//! `family eq "Smith" and birthDate ge "1980-01-01"`, so integrators can
+//! combine criteria without an endpoint per combination.
+//!
+//! Supported are the comparison operators `eq`, `ne`, `co`, `sw`, `ew`,
+//! `gt`, `ge`, `lt` and `le`, the presence operator `pr`, and `and`, `or`,
+//! `not (...)` and parentheses. Text comparisons ignore case. An attribute
+//! with several values, such as `given` or `identifier`, matches when any
+//! of its values does; `ne` matches when none of them equals the value.
+//!
+//! Only active patients are searched unless the filter names `active`. The
+//! family name, birth date and `active` comparisons that every match must
+//! satisfy are applied in the database; the rest of the filter is evaluated
+//! as the narrowed patients are paged out of the repository, so a filter
+//! without such a comparison is bounded by the request deadline rather than
+//! by an index.
+
+use std::fmt;
+
+use chrono::NaiveDate;
+
+use crate::db::{PatientCriteria, PatientRepository};
+use crate::models::{Address, ContactPointSystem, Gender, HumanName, Patient};
+use crate::{Error, Result};
+
+/// Patient attribute a filter can compare
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterAttribute {
+    Id,
+    Family,
+    Given,
+    Name,
+    BirthDate,
+    Gender,
+    Active,
+    Deceased,
+    Identifier,
+    Phone,
+    Email,
+    City,
+    State,
+    PostalCode,
+    Suffix,
+    NameUse,
+}
+
+impl FilterAttribute {
+    const ALL: [(&'static str, FilterAttribute); 16] = [
+        ("id", Self::Id),
+        ("family", Self::Family),
+        ("given", Self::Given),
+        ("name", Self::Name),
+        ("birthDate", Self::BirthDate),
+        ("gender", Self::Gender),
+        ("active", Self::Active),
+        ("deceased", Self::Deceased),
+        ("identifier", Self::Identifier),
+        ("phone", Self::Phone),
+        ("email", Self::Email),
+        ("city", Self::City),
+        ("state", Self::State),
+        ("postalCode", Self::PostalCode),
+        ("suffix", Self::Suffix),
+        ("nameUse", Self::NameUse),
+    ];
+
+    /// Attribute names are case-insensitive, as in SCIM
+    fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+            .map(|(_, attribute)| *attribute)
+    }
+
+    fn is_boolean(self) -> bool {
+        matches!(self, Self::Active | Self::Deceased)
+    }
+
+    /// Values of the attribute, lowercased except for dates
+    fn values(self, patient: &Patient) -> Vec<String> {
+        let names = || std::iter::once(&patient.name).chain(&patient.additional_names);
+        let telecom = |system: fn(&ContactPointSystem) -> bool| -> Vec<String> {
+            patient
+                .telecom
+                .iter()
+                .filter(|contact| system(&contact.system))
+                .map(|contact| contact.value.trim().to_lowercase())
+                .collect()
+        };
+        let address = |part: fn(&Address) -> Option<&String>| -> Vec<String> {
+            patient
+                .addresses
+                .iter()
+                .filter_map(part)
+                .map(|value| value.trim().to_lowercase())
+                .collect()
+        };
+
+        match self {
+            Self::Id => vec![patient.id.to_string()],
+            Self::Family => names().map(|name| name.family.to_lowercase()).collect(),
+            Self::Given => names().flat_map(|name| &name.given).map(|given| given.to_lowercase()).collect(),
+            Self::Name => names()
+                .flat_map(|name| std::iter::once(&name.family).chain(&name.given))
+                .map(|part| part.to_lowercase())
+                .collect(),
+            Self::BirthDate => patient.birth_date.iter().map(|date| date.to_string()).collect(),
+            Self::Gender => vec![match patient.gender {
+                Gender::Male => "male",
+                Gender::Female => "female",
+                Gender::Other => "other",
+                Gender::Unknown => "unknown",
+            }
+            .to_string()],
+            Self::Active => vec![patient.active.to_string()],
+            Self::Deceased => vec![patient.deceased.to_string()],
+            Self::Identifier => patient.identifiers.iter().map(|id| id.value.trim().to_lowercase()).collect(),
+            Self::Phone => telecom(|system| matches!(system, ContactPointSystem::Phone | ContactPointSystem::Sms)),
+            Self::Email => telecom(|system| matches!(system, ContactPointSystem::Email)),
+            Self::City => address(|address| address.city.as_ref()),
+            Self::State => address(|address| address.state.as_ref()),
+            Self::PostalCode => address(|address| address.postal_code.as_ref()),
+            Self::Suffix => names().flat_map(HumanName::suffix_keys).collect(),
+            Self::NameUse => super::name_uses(patient).into_iter().map(str::to_string).collect(),
+        }
+    }
+}
+
+/// Comparison operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Co,
+    Sw,
+    Ew,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl CompareOp {
+    fn parse(word: &str) -> Option<Self> {
+        Some(match word.to_ascii_lowercase().as_str() {
+            "eq" => Self::Eq,
+            "ne" => Self::Ne,
+            "co" => Self::Co,
+            "sw" => Self::Sw,
+            "ew" => Self::Ew,
+            "gt" => Self::Gt,
+            "ge" => Self::Ge,
+            "lt" => Self::Lt,
+            "le" => Self::Le,
+            _ => return None,
+        })
+    }
+
+    fn compare(self, actual: &str, expected: &str) -> bool {
+        match self {
+            Self::Eq => actual == expected,
+            Self::Ne => actual != expected,
+            Self::Co => actual.contains(expected),
+            Self::Sw => actual.starts_with(expected),
+            Self::Ew => actual.ends_with(expected),
+            Self::Gt => actual > expected,
+            Self::Ge => actual >= expected,
+            Self::Lt => actual < expected,
+            Self::Le => actual <= expected,
+        }
+    }
+}
+
+/// A parsed filter expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatientFilter {
+    And(Box<PatientFilter>, Box<PatientFilter>),
+    Or(Box<PatientFilter>, Box<PatientFilter>),
+    Not(Box<PatientFilter>),
+    /// The attribute has at least one non-empty value
+    Present(FilterAttribute),
+    /// The attribute compared with a normalized value
+    Compare(FilterAttribute, CompareOp, String),
+}
+
+impl PatientFilter {
+    /// Parse a filter expression, rejecting unknown attributes and malformed values
+    pub fn parse(expression: &str) -> Result<Self> {
+        let tokens = tokenize(expression)?;
+        let mut parser = Parser { tokens, position: 0 };
+        let filter = parser.or()?;
+        match parser.tokens.get(parser.position) {
+            None => Ok(filter),
+            Some(token) => Err(invalid(format!("unexpected {}", token))),
+        }
+    }
+
+    /// Whether a patient satisfies the filter
+    pub fn matches(&self, patient: &Patient) -> bool {
+        match self {
+            Self::And(left, right) => left.matches(patient) && right.matches(patient),
+            Self::Or(left, right) => left.matches(patient) || right.matches(patient),
+            Self::Not(inner) => !inner.matches(patient),
+            Self::Present(attribute) => attribute.values(patient).iter().any(|value| !value.is_empty()),
+            Self::Compare(attribute, CompareOp::Ne, expected) => {
+                !attribute.values(patient).iter().any(|value| value == expected)
+            }
+            Self::Compare(attribute, op, expected) => {
+                attribute.values(patient).iter().any(|value| op.compare(value, expected))
+            }
+        }
+    }
+
+    /// Patients satisfying the filter, oldest first, skipping the first `offset`
+    pub fn find(&self, patients: &dyn PatientRepository, limit: usize, offset: usize) -> Result<Vec<Patient>> {
+        let criteria = self.criteria();
+        let mut matched = Vec::new();
+        let mut skipped = 0;
+        let mut after = None;
+        loop {
+            crate::deadline::check()?;
+            let page = patients.scan(&criteria, after, SCAN_PAGE_SIZE)?;
+            after = page.last().map(|patient| (patient.created_at, patient.id));
+            for patient in &page {
+                if !self.matches(patient) {
+                    continue;
+                }
+                if skipped < offset {
+                    skipped += 1;
+                    continue;
+                }
+                matched.push(patient.clone());
+                if matched.len() == limit {
+                    return Ok(matched);
+                }
+            }
+            if (page.len() as i64) < SCAN_PAGE_SIZE {
+                return Ok(matched);
+            }
+        }
+    }
+
+    /// Database predicates every patient the filter matches satisfies
+    ///
+    /// Only active patients are scanned unless the filter names `active`.
+    pub fn criteria(&self) -> PatientCriteria {
+        let mut criteria = PatientCriteria {
+            active: (!self.names(FilterAttribute::Active)).then_some(true),
+            ..Default::default()
+        };
+        self.narrow(&mut criteria);
+        criteria
+    }
+
+    /// Whether the filter compares the attribute anywhere
+    fn names(&self, attribute: FilterAttribute) -> bool {
+        match self {
+            Self::And(left, right) | Self::Or(left, right) => left.names(attribute) || right.names(attribute),
+            Self::Not(inner) => inner.names(attribute),
+            Self::Present(named) | Self::Compare(named, _, _) => *named == attribute,
+        }
+    }
+
+    /// Add the comparisons the whole filter requires to `criteria`
+    fn narrow(&self, criteria: &mut PatientCriteria) {
+        match self {
+            Self::And(left, right) => {
+                left.narrow(criteria);
+                right.narrow(criteria);
+            }
+            Self::Compare(FilterAttribute::Active, CompareOp::Eq, value) => criteria.active = Some(value == "true"),
+            Self::Compare(FilterAttribute::Family, CompareOp::Eq, value) => criteria.family = Some(value.clone()),
+            Self::Compare(FilterAttribute::BirthDate, op, value) => {
+                let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") else {
+                    return;
+                };
+                let (from, to) = match op {
+                    CompareOp::Eq => (Some(date), Some(date)),
+                    CompareOp::Gt => (date.succ_opt(), None),
+                    CompareOp::Ge => (Some(date), None),
+                    CompareOp::Lt => (None, date.pred_opt()),
+                    CompareOp::Le => (None, Some(date)),
+                    _ => (None, None),
+                };
+                criteria.born_from = criteria.born_from.max(from);
+                criteria.born_to = match (criteria.born_to, to) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Patients read from the repository at a time while filtering
+const SCAN_PAGE_SIZE: i64 = 500;
+
+fn invalid(message: String) -> Error {
+    Error::Validation(format!("Invalid _filter: {}", message))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Word(String),
+    Text(String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Open => write!(f, "'('"),
+            Token::Close => write!(f, "')'"),
+            Token::Word(word) => write!(f, "'{}'", word),
+            Token::Text(text) => write!(f, "\"{}\"", text),
+        }
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '"' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(escaped) => text.push(escaped),
+                            None => return Err(invalid("unterminated string".to_string())),
+                        },
+                        Some(c) => text.push(c),
+                        None => return Err(invalid("unterminated string".to_string())),
+                    }
+                }
+                tokens.push(Token::Text(text));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over `or` > `and` > `not`/parentheses > comparison
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn next_is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.tokens.get(self.position), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn or(&mut self) -> Result<PatientFilter> {
+        let mut filter = self.and()?;
+        while self.next_is_keyword("or") {
+            self.position += 1;
+            filter = PatientFilter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<PatientFilter> {
+        let mut filter = self.unary()?;
+        while self.next_is_keyword("and") {
+            self.position += 1;
+            filter = PatientFilter::And(Box::new(filter), Box::new(self.unary()?));
+        }
+        Ok(filter)
+    }
+
+    fn unary(&mut self) -> Result<PatientFilter> {
+        if self.next_is_keyword("not") {
+            self.position += 1;
+            if self.tokens.get(self.position) != Some(&Token::Open) {
+                return Err(invalid("'not' must be followed by '('".to_string()));
+            }
+            return Ok(PatientFilter::Not(Box::new(self.unary()?)));
+        }
+        match self.next() {
+            Some(Token::Open) => {
+                let filter = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(filter),
+                    Some(token) => Err(invalid(format!("expected ')' but found {}", token))),
+                    None => Err(invalid("missing ')'".to_string())),
+                }
+            }
+            Some(Token::Word(name)) => self.comparison(&name),
+            Some(token) => Err(invalid(format!("expected an attribute but found {}", token))),
+            None => Err(invalid("expression is incomplete".to_string())),
+        }
+    }
+
+    fn comparison(&mut self, name: &str) -> Result<PatientFilter> {
+        let attribute = FilterAttribute::parse(name).ok_or_else(|| invalid(format!("unknown attribute '{}'", name)))?;
+        let op = match self.next() {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("pr") => return Ok(PatientFilter::Present(attribute)),
+            Some(Token::Word(word)) => {
+                CompareOp::parse(&word).ok_or_else(|| invalid(format!("unknown operator '{}'", word)))?
+            }
+            Some(token) => return Err(invalid(format!("expected an operator after '{}' but found {}", name, token))),
+            None => return Err(invalid(format!("expected an operator after '{}'", name))),
+        };
+
+        let value = match self.next() {
+            Some(Token::Text(text)) => text,
+            Some(Token::Word(word)) if word == "null" => {
+                // `eq null` is absence and `ne null` presence, as in SCIM
+                let present = PatientFilter::Present(attribute);
+                return match op {
+                    CompareOp::Eq => Ok(PatientFilter::Not(Box::new(present))),
+                    CompareOp::Ne => Ok(present),
+                    _ => Err(invalid("null can only be compared with eq or ne".to_string())),
+                };
+            }
+            Some(Token::Word(word)) if word == "true" || word == "false" => word,
+            Some(token) => return Err(invalid(format!("expected a value after '{}' but found {}", name, token))),
+            None => return Err(invalid(format!("expected a value after '{}'", name))),
+        };
+
+        let value = if attribute.is_boolean() {
+            if !matches!(op, CompareOp::Eq | CompareOp::Ne) || (value != "true" && value != "false") {
+                return Err(invalid(format!("'{}' can only be compared with true or false using eq or ne", name)));
+            }
+            value
+        } else if attribute == FilterAttribute::Suffix {
+            value.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+        } else if attribute == FilterAttribute::BirthDate {
+            NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+                .map_err(|_| invalid(format!("'{}' is not a date in YYYY-MM-DD form", value)))?
+                .to_string()
+        } else {
+            value.trim().to_lowercase()
+        };
+
+        Ok(PatientFilter::Compare(attribute, op, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::InMemoryPatientRepository;
+    use crate::models::{Identifier, NameUse};
+    use uuid::Uuid;
+
+    fn patient(family: &str, given: &str, birth_date: &str) -> Patient {
+        let mut patient = crate::fixtures::patient(family, &[given], Gender::Female);
+        patient.birth_date = NaiveDate::parse_from_str(birth_date, "%Y-%m-%d").ok();
+        patient
+    }
+
+    fn matches(expression: &str, patient: &Patient) -> bool {
+        PatientFilter::parse(expression).unwrap().matches(patient)
+    }
+
+    #[test]
+    fn test_comparisons_and_logic() {
+        let smith = patient("Smith", "Jane", "1985-06-01");
+
+        assert!(matches(r#"family eq "smith" and birthDate ge "1980-01-01""#, &smith));
+        assert!(!matches(r#"family eq "Smith" and birthDate lt "1980-01-01""#, &smith));
+        assert!(matches(r#"family eq "Jones" or (given sw "ja" and gender eq "female")"#, &smith));
+        assert!(matches(r#"not (family co "mit") or active eq true"#, &smith));
+        assert!(!matches(r#"NOT (family ew "th")"#, &smith));
+        assert!(matches(r#"name eq "jane" and identifier eq null"#, &smith));
+    }
+
+    #[test]
+    fn test_multi_valued_attributes() {
+        let mut smith = patient("Smith", "Jane", "1985-06-01");
+        smith.identifiers.push(Identifier::mrn("MAIN".to_string(), "MRN-001".to_string()));
+        smith.identifiers.push(Identifier::mrn("EAST".to_string(), "MRN-002".to_string()));
+
+        assert!(matches(r#"identifier eq "mrn-002""#, &smith));
+        assert!(matches("identifier pr", &smith));
+        assert!(!matches(r#"identifier ne "MRN-001""#, &smith));
+        assert!(matches(r#"identifier ne "MRN-003""#, &smith));
+    }
+
+    #[test]
+    fn test_suffix_and_name_use() {
+        let mut smith = patient("Smith", "Robert", "1985-06-01");
+        smith.name.suffix.push("Jr.".to_string());
+        smith.additional_names.push(HumanName {
+            use_type: Some(NameUse::Maiden),
+            family: "Jones".to_string(),
+            given: vec![],
+            prefix: vec![],
+            suffix: vec![],
+        });
+
+        assert!(matches(r#"suffix eq "JR""#, &smith));
+        assert!(!matches(r#"suffix eq "Sr.""#, &smith));
+        assert!(matches(r#"nameUse eq "maiden" and family eq "jones""#, &smith));
+        assert!(matches(r#"nameUse eq "official""#, &smith));
+        assert!(!matches("suffix pr", &patient("Smith", "Jane", "1985-06-01")));
+    }
+
+    #[test]
+    fn test_find_pages_through_matches() {
+        let repository = InMemoryPatientRepository::new();
+        for (family, year) in [("Smith", 1970), ("Smith", 1985), ("Jones", 1990), ("Smith", 1992)] {
+            repository.create(&patient(family, "Jane", &format!("{}-01-01", year))).unwrap();
+        }
+        let filter = PatientFilter::parse(r#"family eq "Smith" and birthDate ge "1980-01-01""#).unwrap();
+
+        let first = filter.find(&repository, 1, 0).unwrap();
+        let rest = filter.find(&repository, 10, 1).unwrap();
+        assert_eq!(first.len(), 1);
+        assert_eq!(rest.len(), 1);
+        assert_ne!(first[0].id, rest[0].id);
+        assert!(filter.find(&repository, 10, 2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_criteria_from_required_comparisons() {
+        let criteria = |expression: &str| PatientFilter::parse(expression).unwrap().criteria();
+        let date = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok();
+
+        let narrowed = criteria(r#"family eq "Smith" and birthDate gt "1980-01-01" and birthDate le "1990-12-31""#);
+        assert_eq!(narrowed.active, Some(true));
+        assert_eq!(narrowed.family.as_deref(), Some("smith"));
+        assert_eq!((narrowed.born_from, narrowed.born_to), (date("1980-01-02"), date("1990-12-31")));
+
+        // Comparisons under `or` and `not` are not required of every match
+        assert_eq!(criteria(r#"family eq "Smith" or given eq "jane""#), criteria(r#"given eq "jane""#));
+        assert_eq!(criteria(r#"not (birthDate lt "1980-01-01")"#).born_to, None);
+
+        // Naming `active` anywhere scans inactive patients too
+        assert_eq!(criteria("active eq false").active, Some(false));
+        assert_eq!(criteria(r#"active eq false or family eq "Smith""#).active, None);
+    }
+
+    #[test]
+    fn test_find_inactive_patients() {
+        let repository = InMemoryPatientRepository::new();
+        let active = repository.create(&patient("Smith", "Jane", "1985-06-01")).unwrap();
+        let mut inactive = patient("Smith", "Joan", "1985-06-01");
+        inactive.active = false;
+        let inactive = repository.create(&inactive).unwrap();
+
+        let find = |expression: &str| -> Vec<Uuid> {
+            let filter = PatientFilter::parse(expression).unwrap();
+            filter.find(&repository, 10, 0).unwrap().into_iter().map(|p| p.id).collect()
+        };
+        assert_eq!(find(r#"family eq "Smith""#), vec![active.id]);
+        assert_eq!(find("active eq false"), vec![inactive.id]);
+        assert_eq!(find(r#"family eq "Smith" and active ne true"#), vec![inactive.id]);
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        for expression in [
+            r#"surname eq "Smith""#,
+            r#"family like "Smith""#,
+            r#"family eq "Smith"#,
+            r#"(family eq "Smith""#,
+            r#"birthDate ge "1980""#,
+            r#"active gt true"#,
+            r#"family eq "Smith" family"#,
+            r#"not family eq "Smith""#,
+            "",
+        ] {
+            assert!(
+                matches!(PatientFilter::parse(expression), Err(Error::Validation(_))),
+                "{} should be rejected",
+                expression
+            );
+        }
+    }
+}
@@ -1,17 +1,62 @@
 //! Search index management with Tantivy
 
 use tantivy::{
-    schema::{Schema, Field, STORED, TEXT, STRING, FAST},
-    Index, IndexWriter, IndexReader, ReloadPolicy,
-    collector::TopDocs,
-    query::QueryParser,
+    schema::{Schema, Field, STORED, TEXT, STRING, FAST, Value},
+    Index, IndexWriter, IndexReader, ReloadPolicy, TantivyDocument,
+    collector::{DocSetCollector, TopDocs},
+    query::{AllQuery, QueryParser},
+    directory::{Directory, TerminatingWrite},
     doc,
 };
-use std::path::Path;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
 use crate::Result;
 
+/// Version of [`PatientIndexSchema`], recorded next to each index
+///
+/// Bump it whenever the fields change, so existing indexes are detected as
+/// stale and rebuilt.
+pub const SCHEMA_VERSION: u32 = 4;
+
+/// File in the index directory recording its schema version
+const SCHEMA_FILE: &str = "mpi_schema.json";
+
+/// Whether an index directory matches the current schema
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaStatus {
+    /// No index in the directory yet
+    Missing,
+    /// The index has the current fields and version
+    Current,
+    /// The index was built with other fields or another version
+    Stale {
+        /// Recorded version; indexes from before versioning have none
+        found: Option<u32>,
+    },
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SchemaFile {
+    schema_version: u32,
+}
+
+fn read_schema_version(index_path: &Path) -> Option<u32> {
+    let bytes = std::fs::read(index_path.join(SCHEMA_FILE)).ok()?;
+    serde_json::from_slice::<SchemaFile>(&bytes)
+        .ok()
+        .map(|file| file.schema_version)
+}
+
+fn write_schema_version(index_path: &Path) -> Result<()> {
+    let bytes = serde_json::to_vec(&SchemaFile { schema_version: SCHEMA_VERSION })
+        .map_err(|e| crate::Error::Search(format!("Failed to encode schema version: {}", e)))?;
+    std::fs::write(index_path.join(SCHEMA_FILE), bytes)
+        .map_err(|e| crate::Error::Search(format!("Failed to write schema version: {}", e)))
+}
+
 /// Fields in the patient search index
 #[derive(Clone)]
 pub struct PatientIndexSchema {
@@ -27,6 +72,12 @@ pub struct PatientIndexSchema {
     pub state: Field,
     pub identifiers: Field,
     pub active: Field,
+    pub telecom: Field,
+    pub maiden_name: Field,
+    pub name_suffix: Field,
+    pub name_use: Field,
+    pub blocking_keys: Field,
+    pub managing_organization: Field,
 }
 
 impl PatientIndexSchema {
@@ -57,6 +108,24 @@ impl PatientIndexSchema {
         // Active status (for filtering)
         let active = schema_builder.add_text_field("active", STRING | FAST);
 
+        // Normalized phone numbers and emails, as `phone:<digits>` and `email:<address>`
+        let telecom = schema_builder.add_text_field("telecom", STRING);
+
+        // Family names recorded as maiden names, so blocking finds patients
+        // under the name they were registered with before marrying
+        let maiden_name = schema_builder.add_text_field("maiden_name", TEXT);
+
+        // Normalized suffixes of the legal name (e.g. `jr`, `iii`) and the
+        // uses of every recorded name (e.g. `official`, `maiden`)
+        let name_suffix = schema_builder.add_text_field("name_suffix", STRING);
+        let name_use = schema_builder.add_text_field("name_use", STRING);
+
+        // Precomputed blocking keys, see [`super::blocking`]
+        let blocking_keys = schema_builder.add_text_field("blocking_keys", STRING);
+
+        // Organization managing the record, for scoping lookups to a facility
+        let managing_organization = schema_builder.add_text_field("managing_organization", STRING);
+
         let schema = schema_builder.build();
 
         Self {
@@ -72,6 +141,12 @@ impl PatientIndexSchema {
             state,
             identifiers,
             active,
+            telecom,
+            maiden_name,
+            name_suffix,
+            name_use,
+            blocking_keys,
+            managing_organization,
         }
     }
 }
@@ -93,8 +168,9 @@ impl PatientIndex {
     /// Create a new index at the given path
     pub fn create<P: AsRef<Path>>(index_path: P) -> Result<Self> {
         let schema_def = PatientIndexSchema::new();
-        let index = Index::create_in_dir(index_path, schema_def.schema.clone())
+        let index = Index::create_in_dir(index_path.as_ref(), schema_def.schema.clone())
             .map_err(|e| crate::Error::Search(format!("Failed to create index: {}", e)))?;
+        write_schema_version(index_path.as_ref())?;
 
         let reader = index
             .reader_builder()
@@ -110,9 +186,25 @@ impl PatientIndex {
     }
 
     /// Open an existing index at the given path
+    ///
+    /// Fails for an index with a stale schema, which must be rebuilt first.
     pub fn open<P: AsRef<Path>>(index_path: P) -> Result<Self> {
+        let path = index_path.as_ref();
+        if let SchemaStatus::Stale { found } = Self::schema_status(path)? {
+            return Err(crate::Error::Search(format!(
+                "Index at {} has schema version {}, expected {}; it must be rebuilt",
+                path.display(),
+                found.map_or_else(|| "unknown".to_string(), |v| v.to_string()),
+                SCHEMA_VERSION
+            )));
+        }
+        // Indexes from before versioning that already have the current fields
+        if read_schema_version(path).is_none() {
+            write_schema_version(path)?;
+        }
+
         let schema_def = PatientIndexSchema::new();
-        let index = Index::open_in_dir(index_path)
+        let index = Index::open_in_dir(path)
             .map_err(|e| crate::Error::Search(format!("Failed to open index: {}", e)))?;
 
         let reader = index
@@ -140,6 +232,57 @@ impl PatientIndex {
         }
     }
 
+    /// Compare the index at the given path with the current schema
+    pub fn schema_status<P: AsRef<Path>>(index_path: P) -> Result<SchemaStatus> {
+        let path = index_path.as_ref();
+        if !path.join(META_FILE).exists() {
+            return Ok(SchemaStatus::Missing);
+        }
+        let found = read_schema_version(path);
+        let index = Index::open_in_dir(path)
+            .map_err(|e| crate::Error::Search(format!("Failed to open index: {}", e)))?;
+        let same_fields = index.schema() == PatientIndexSchema::new().schema;
+        if same_fields && found.is_none_or(|version| version == SCHEMA_VERSION) {
+            Ok(SchemaStatus::Current)
+        } else {
+            Ok(SchemaStatus::Stale { found })
+        }
+    }
+
+    /// Move a fully built index from `rebuilt_path` into `index_path`
+    ///
+    /// Both directories must be on the same filesystem. The old index is
+    /// renamed aside and then removed; processes that still have its files
+    /// open keep reading them until they reopen the index.
+    pub fn swap_in<P: AsRef<Path>, Q: AsRef<Path>>(index_path: P, rebuilt_path: Q) -> Result<()> {
+        let index_path = index_path.as_ref();
+        let rebuilt_path = rebuilt_path.as_ref();
+        if !rebuilt_path.join(META_FILE).exists() {
+            return Err(crate::Error::Search(format!(
+                "No rebuilt index found at {}",
+                rebuilt_path.display()
+            )));
+        }
+
+        let retired_path = sibling_path(index_path, "retired");
+        if retired_path.exists() {
+            std::fs::remove_dir_all(&retired_path)
+                .map_err(|e| crate::Error::Search(format!("Failed to remove {}: {}", retired_path.display(), e)))?;
+        }
+        if index_path.exists() {
+            std::fs::rename(index_path, &retired_path)
+                .map_err(|e| crate::Error::Search(format!("Failed to move old index aside: {}", e)))?;
+        }
+        std::fs::rename(rebuilt_path, index_path)
+            .map_err(|e| crate::Error::Search(format!("Failed to move rebuilt index into place: {}", e)))?;
+
+        // The new index is live; a leftover old copy is only wasted space
+        if let Err(e) = std::fs::remove_dir_all(&retired_path) {
+            tracing::warn!("Failed to remove old index at {}: {}", retired_path.display(), e);
+        }
+        Ok(())
+    }
+
     /// Get an index writer
     pub fn writer(&self, heap_size_mb: usize) -> Result<IndexWriter> {
         self.index
@@ -180,6 +323,25 @@ impl PatientIndex {
         })
     }
 
+    /// IDs of every patient with a document in the index
+    pub fn patient_ids(&self) -> Result<HashSet<String>> {
+        let searcher = self.reader.searcher();
+        let addresses = searcher
+            .search(&AllQuery, &DocSetCollector)
+            .map_err(|e| crate::Error::Search(format!("Failed to list documents: {}", e)))?;
+
+        let mut ids = HashSet::with_capacity(addresses.len());
+        for address in addresses {
+            let doc: TantivyDocument = searcher
+                .doc(address)
+                .map_err(|e| crate::Error::Search(format!("Failed to retrieve document: {}", e)))?;
+            if let Some(id) = doc.get_first(self.schema.id).and_then(|value| value.as_str()) {
+                ids.insert(id.to_string());
+            }
+        }
+        Ok(ids)
+    }
+
     /// Optimize the index (wait for merges to complete)
     pub fn optimize(&self) -> Result<()> {
         let mut writer = self.writer(50)?;
@@ -188,15 +350,187 @@ impl PatientIndex {
             .map_err(|e| crate::Error::Search(format!("Failed to optimize index: {}", e)))?;
         Ok(())
     }
+
+    /// Copy a consistent snapshot of the index into `dest_path`
+    ///
+    /// The index writer lock is held for the duration of the copy so that no
+    /// commit or merge can change the set of live segment files underneath us.
+    pub fn snapshot<P: AsRef<Path>>(&self, dest_path: P) -> Result<SnapshotInfo> {
+        let dest = dest_path.as_ref();
+        let _writer = self.writer(50)?;
+        let directory = self.index.directory();
+
+        let meta_bytes = directory
+            .atomic_read(Path::new(META_FILE))
+            .map_err(|e| crate::Error::Search(format!("Failed to read index meta: {}", e)))?;
+        let meta = self.index
+            .load_metas()
+            .map_err(|e| crate::Error::Search(format!("Failed to load index meta: {}", e)))?;
+
+        std::fs::create_dir_all(dest)
+            .map_err(|e| crate::Error::Search(format!("Failed to create snapshot directory: {}", e)))?;
+
+        let files: HashSet<PathBuf> = meta.segments.iter().flat_map(|s| s.list_files()).collect();
+        let mut num_files = 0;
+        for file in &files {
+            // Not every segment component exists (e.g. no deletes yet)
+            if !directory.exists(file).unwrap_or(false) {
+                continue;
+            }
+            let bytes = directory
+                .open_read(file)
+                .map_err(|e| crate::Error::Search(format!("Failed to open {:?}: {}", file, e)))?
+                .read_bytes()
+                .map_err(|e| crate::Error::Search(format!("Failed to read {:?}: {}", file, e)))?;
+            std::fs::write(dest.join(file), bytes.as_slice())
+                .map_err(|e| crate::Error::Search(format!("Failed to write {:?}: {}", file, e)))?;
+            num_files += 1;
+        }
+
+        // Written last, so a partially copied snapshot is never openable
+        std::fs::write(dest.join(META_FILE), &meta_bytes)
+            .map_err(|e| crate::Error::Search(format!("Failed to write index meta: {}", e)))?;
+
+        Ok(SnapshotInfo {
+            path: dest.to_string_lossy().to_string(),
+            num_docs: meta.segments.iter().map(|s| s.num_docs() as usize).sum(),
+            num_segments: meta.segments.len(),
+            num_files: num_files + 1,
+        })
+    }
+
+    /// Replace the contents of the index with a snapshot taken by [`PatientIndex::snapshot`]
+    ///
+    /// Segment files are copied in first and `meta.json` is swapped atomically,
+    /// so readers either see the old index or the restored one.
+    pub fn restore<P: AsRef<Path>>(&self, src_path: P) -> Result<SnapshotInfo> {
+        let src = src_path.as_ref();
+        if !src.join(META_FILE).exists() {
+            return Err(crate::Error::Search(format!(
+                "No index snapshot found at {}",
+                src.display()
+            )));
+        }
+
+        let snapshot = Index::open_in_dir(src)
+            .map_err(|e| crate::Error::Search(format!("Failed to open snapshot: {}", e)))?;
+        let meta = snapshot
+            .load_metas()
+            .map_err(|e| crate::Error::Search(format!("Failed to load snapshot meta: {}", e)))?;
+        if meta.schema != self.schema.schema {
+            return Err(crate::Error::Search(
+                "Snapshot schema does not match the current index schema".to_string(),
+            ));
+        }
+
+        let _writer = self.writer(50)?;
+        let directory = self.index.directory();
+
+        let files: HashSet<PathBuf> = meta.segments.iter().flat_map(|s| s.list_files()).collect();
+        let mut num_files = 0;
+        for file in &files {
+            let src_file = src.join(file);
+            if !src_file.exists() {
+                continue;
+            }
+            num_files += 1;
+            // Segment file names are unique per segment, so an existing file is identical
+            if directory.exists(file).unwrap_or(false) {
+                continue;
+            }
+            let bytes = std::fs::read(&src_file)
+                .map_err(|e| crate::Error::Search(format!("Failed to read {:?}: {}", src_file, e)))?;
+            let mut write = directory
+                .open_write(file)
+                .map_err(|e| crate::Error::Search(format!("Failed to create {:?}: {}", file, e)))?;
+            write
+                .write_all(&bytes)
+                .and_then(|_| write.terminate())
+                .map_err(|e| crate::Error::Search(format!("Failed to write {:?}: {}", file, e)))?;
+        }
+
+        let meta_bytes = std::fs::read(src.join(META_FILE))
+            .map_err(|e| crate::Error::Search(format!("Failed to read snapshot meta: {}", e)))?;
+        directory
+            .atomic_write(Path::new(META_FILE), &meta_bytes)
+            .map_err(|e| crate::Error::Search(format!("Failed to write index meta: {}", e)))?;
+
+        self.reload()?;
+
+        Ok(SnapshotInfo {
+            path: src.to_string_lossy().to_string(),
+            num_docs: meta.segments.iter().map(|s| s.num_docs() as usize).sum(),
+            num_segments: meta.segments.len(),
+            num_files: num_files + 1,
+        })
+    }
+}
+
+/// Name of the Tantivy file that lists the committed segments
+const META_FILE: &str = "meta.json";
+
+/// `<index_path>.<suffix>`, next to the index so a rename stays on one filesystem
+pub(crate) fn sibling_path(index_path: &Path, suffix: &str) -> PathBuf {
+    let mut name = index_path
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_else(|| "index".into());
+    name.push(".");
+    name.push(suffix);
+    index_path.with_file_name(name)
+}
+
+/// Resolve a requested snapshot location inside `backup_dir`
+///
+/// `requested` is taken relative to the backup directory. Both are
+/// canonicalized, so `..` components and symlinks are followed before the
+/// check, and a location outside the directory, or the directory itself,
+/// is refused. The snapshot itself need not exist yet, but its parent must.
+pub fn snapshot_path<P: AsRef<Path>>(backup_dir: P, requested: &str) -> Result<PathBuf> {
+    let backup_dir = backup_dir.as_ref();
+    std::fs::create_dir_all(backup_dir)
+        .map_err(|e| crate::Error::Search(format!("Failed to create backup directory: {}", e)))?;
+    let base = backup_dir
+        .canonicalize()
+        .map_err(|e| crate::Error::Search(format!("Failed to resolve backup directory: {}", e)))?;
+
+    let outside = || crate::Error::Validation(format!("Snapshot path '{}' is outside the backup directory", requested));
+    let joined = base.join(requested);
+    let resolved = match joined.canonicalize() {
+        Ok(path) => path,
+        Err(_) => {
+            let name = joined.file_name().ok_or_else(outside)?;
+            let parent = joined
+                .parent()
+                .ok_or_else(outside)?
+                .canonicalize()
+                .map_err(|e| crate::Error::Validation(format!("Snapshot path '{}' cannot be resolved: {}", requested, e)))?;
+            parent.join(name)
+        }
+    };
+
+    if resolved == base || !resolved.starts_with(&base) {
+        return Err(outside());
+    }
+    Ok(resolved)
 }
 
 /// Index statistics
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
 pub struct IndexStats {
     pub num_docs: usize,
     pub num_segments: usize,
 }
 
+/// Result of an index snapshot or restore
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
+pub struct SnapshotInfo {
+    pub path: String,
+    pub num_docs: usize,
+    pub num_segments: usize,
+    pub num_files: usize,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -224,6 +558,26 @@ mod tests {
         let _ = schema.gender;
     }
 
+    #[test]
+    fn test_snapshot_path_stays_in_backup_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let backup_dir = temp_dir.path().join("backups");
+
+        let nightly = snapshot_path(&backup_dir, "nightly").unwrap();
+        assert_eq!(nightly, backup_dir.canonicalize().unwrap().join("nightly"));
+        assert!(snapshot_path(&backup_dir, "../nightly").is_err());
+        assert!(snapshot_path(&backup_dir, "/etc").is_err());
+        assert!(snapshot_path(&backup_dir, ".").is_err());
+        assert!(snapshot_path(&backup_dir, "").is_err());
+
+        // A symlink out of the directory is followed before the check
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(temp_dir.path(), backup_dir.join("escape")).unwrap();
+            assert!(snapshot_path(&backup_dir, "escape/nightly").is_err());
+        }
+    }
+
     #[test]
     fn test_create_or_open() {
         let temp_dir = TempDir::new().unwrap();
@@ -236,4 +590,94 @@ mod tests {
         let index2 = PatientIndex::create_or_open(temp_dir.path()).unwrap();
         assert_eq!(index2.stats().unwrap().num_docs, 0);
     }
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let temp_dir = TempDir::new().unwrap();
+        let snapshot_dir = TempDir::new().unwrap();
+        let index = PatientIndex::create(temp_dir.path()).unwrap();
+        let schema = index.schema().clone();
+
+        let mut writer = index.writer(50).unwrap();
+        writer.add_document(doc!(schema.id => "p1", schema.family_name => "Smith")).unwrap();
+        writer.commit().unwrap();
+        drop(writer);
+
+        let info = index.snapshot(snapshot_dir.path()).unwrap();
+        assert_eq!(info.num_docs, 1);
+        assert!(snapshot_dir.path().join("meta.json").exists());
+
+        // Add a second document after the snapshot, then roll back to it
+        let mut writer = index.writer(50).unwrap();
+        writer.add_document(doc!(schema.id => "p2", schema.family_name => "Jones")).unwrap();
+        writer.commit().unwrap();
+        drop(writer);
+        index.reload().unwrap();
+        assert_eq!(index.stats().unwrap().num_docs, 2);
+
+        index.restore(snapshot_dir.path()).unwrap();
+        assert_eq!(index.stats().unwrap().num_docs, 1);
+    }
+
+    #[test]
+    fn test_schema_status() {
+        let temp_dir = TempDir::new().unwrap();
+        assert_eq!(PatientIndex::schema_status(temp_dir.path()).unwrap(), SchemaStatus::Missing);
+
+        PatientIndex::create(temp_dir.path()).unwrap();
+        assert_eq!(PatientIndex::schema_status(temp_dir.path()).unwrap(), SchemaStatus::Current);
+
+        std::fs::write(temp_dir.path().join(SCHEMA_FILE), r#"{"schema_version":0}"#).unwrap();
+        assert_eq!(
+            PatientIndex::schema_status(temp_dir.path()).unwrap(),
+            SchemaStatus::Stale { found: Some(0) }
+        );
+        assert!(PatientIndex::open(temp_dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_schema_status_detects_changed_fields() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut builder = Schema::builder();
+        builder.add_text_field("id", STRING | STORED);
+        builder.add_text_field("family_name", TEXT | STORED);
+        Index::create_in_dir(temp_dir.path(), builder.build()).unwrap();
+
+        assert_eq!(
+            PatientIndex::schema_status(temp_dir.path()).unwrap(),
+            SchemaStatus::Stale { found: None }
+        );
+    }
+
+    #[test]
+    fn test_swap_in() {
+        let temp_dir = TempDir::new().unwrap();
+        let index_path = temp_dir.path().join("index");
+        let rebuilt_path = sibling_path(&index_path, "rebuild");
+        std::fs::create_dir_all(&index_path).unwrap();
+        std::fs::create_dir_all(&rebuilt_path).unwrap();
+        PatientIndex::create(&index_path).unwrap();
+
+        let rebuilt = PatientIndex::create(&rebuilt_path).unwrap();
+        let schema = rebuilt.schema().clone();
+        let mut writer = rebuilt.writer(50).unwrap();
+        writer.add_document(doc!(schema.id => "p1", schema.family_name => "Smith")).unwrap();
+        writer.commit().unwrap();
+        drop(writer);
+        drop(rebuilt);
+
+        PatientIndex::swap_in(&index_path, &rebuilt_path).unwrap();
+        assert!(!rebuilt_path.exists());
+        assert!(!sibling_path(&index_path, "retired").exists());
+        assert_eq!(PatientIndex::open(&index_path).unwrap().stats().unwrap().num_docs, 1);
+    }
+
+    #[test]
+    fn test_restore_missing_snapshot() {
+        let temp_dir = TempDir::new().unwrap();
+        let missing_dir = TempDir::new().unwrap();
+        let index = PatientIndex::create(temp_dir.path()).unwrap();
+
+        assert!(index.restore(missing_dir.path()).is_err());
+    }
 }
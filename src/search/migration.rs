@@ -0,0 +1,161 @@
+//! Side-by-side rebuilds of an index with a stale schema
+//!
+//! When [`PatientIndexSchema`](super::PatientIndexSchema) gains or changes
+//! fields, the existing index cannot be opened as-is. Instead of deleting it
+//! by hand, a new index is built from the patient table in a sibling
+//! directory while the old one stays untouched, then moved into place with
+//! [`PatientIndex::swap_in`]. Replicas still serving from the old index keep
+//! working until they reopen it.
+
+use std::path::Path;
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::db::PatientRepository;
+use crate::matching::transliteration::Transliterator;
+use crate::models::Patient;
+use crate::Result;
+use super::index::{sibling_path, SchemaStatus, SCHEMA_VERSION};
+use super::{PatientIndex, SearchEngine};
+
+/// Patients read from the repository per indexing commit
+const BATCH_SIZE: i64 = 1000;
+
+/// Outcome of a schema rebuild
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaRebuildReport {
+    /// Schema version of the replaced index, if it recorded one
+    pub previous_version: Option<u32>,
+    /// Schema version of the rebuilt index
+    pub schema_version: u32,
+    /// Patients written to the rebuilt index
+    pub patients_indexed: u64,
+    /// Time taken to build and swap in the new index
+    pub elapsed_ms: u64,
+}
+
+/// Rebuild the index at `index_path` from `patients` if its schema is stale
+///
+/// Returns `None` when there is no index yet or it is already current.
+/// Documents are built with `transliterator`, so it must match the one the
+/// search engine will use.
+pub fn rebuild_if_stale(
+    index_path: &Path,
+    patients: &dyn PatientRepository,
+    transliterator: &Transliterator,
+) -> Result<Option<SchemaRebuildReport>> {
+    rebuild_if_stale_where(index_path, patients, transliterator, |_| true)
+}
+
+/// Rebuild the index at `index_path` with the patients `keep` selects, if
+/// its schema is stale
+///
+/// Used for the partitions of a [`PartitionedIndex`](super::partition::PartitionedIndex),
+/// which each hold only some of the patients.
+pub fn rebuild_if_stale_where(
+    index_path: &Path,
+    patients: &dyn PatientRepository,
+    transliterator: &Transliterator,
+    keep: impl Fn(&Patient) -> bool,
+) -> Result<Option<SchemaRebuildReport>> {
+    let previous_version = match PatientIndex::schema_status(index_path)? {
+        SchemaStatus::Missing | SchemaStatus::Current => return Ok(None),
+        SchemaStatus::Stale { found } => found,
+    };
+    tracing::warn!(
+        "Search index at {} has a stale schema (version {:?}, expected {}); rebuilding",
+        index_path.display(),
+        previous_version,
+        SCHEMA_VERSION
+    );
+
+    let started = Instant::now();
+    let rebuild_path = sibling_path(index_path, "rebuild");
+    // A previous rebuild may have been interrupted part way
+    if rebuild_path.exists() {
+        std::fs::remove_dir_all(&rebuild_path)
+            .map_err(|e| crate::Error::Search(format!("Failed to remove {}: {}", rebuild_path.display(), e)))?;
+    }
+    std::fs::create_dir_all(&rebuild_path)
+        .map_err(|e| crate::Error::Search(format!("Failed to create {}: {}", rebuild_path.display(), e)))?;
+
+    let patients_indexed = {
+        let engine = SearchEngine::new(&rebuild_path)?.with_transliterator(transliterator.clone());
+        let mut indexed = 0;
+        let mut offset = 0;
+        loop {
+            let batch = patients.list_active(BATCH_SIZE, offset)?;
+            if batch.is_empty() {
+                break;
+            }
+            offset += batch.len() as i64;
+            let batch: Vec<Patient> = batch.into_iter().filter(|patient| keep(patient)).collect();
+            engine.index_patients(&batch)?;
+            indexed += batch.len() as u64;
+        }
+        indexed
+    };
+
+    PatientIndex::swap_in(index_path, &rebuild_path)?;
+
+    let report = SchemaRebuildReport {
+        previous_version,
+        schema_version: SCHEMA_VERSION,
+        patients_indexed,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    };
+    tracing::info!(
+        "Rebuilt search index at {} with {} patients in {} ms",
+        index_path.display(),
+        report.patients_indexed,
+        report.elapsed_ms
+    );
+    Ok(Some(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::InMemoryPatientRepository;
+    use crate::models::{Gender, HumanName, Patient};
+    use tantivy::schema::{Schema, STORED, STRING};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rebuild_if_stale() {
+        let temp_dir = TempDir::new().unwrap();
+        let index_path = temp_dir.path().join("index");
+        std::fs::create_dir_all(&index_path).unwrap();
+
+        // An index from an older schema with only an id field
+        let mut builder = Schema::builder();
+        builder.add_text_field("id", STRING | STORED);
+        tantivy::Index::create_in_dir(&index_path, builder.build()).unwrap();
+
+        let repository = InMemoryPatientRepository::new();
+        for family in ["Okonkwo", "Okafor", "Nwosu"] {
+            let name = HumanName {
+                use_type: None,
+                family: family.to_string(),
+                given: vec!["Ada".to_string()],
+                prefix: vec![],
+                suffix: vec![],
+            };
+            repository.create(&Patient::new(name, Gender::Female)).unwrap();
+        }
+
+        let report = rebuild_if_stale(&index_path, &repository, &Transliterator::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(report.previous_version, None);
+        assert_eq!(report.patients_indexed, 3);
+        assert_eq!(PatientIndex::schema_status(&index_path).unwrap(), SchemaStatus::Current);
+
+        let engine = SearchEngine::new(&index_path).unwrap();
+        assert_eq!(engine.stats().unwrap().num_docs, 3);
+
+        // Nothing to do once current
+        assert!(rebuild_if_stale(&index_path, &repository, &Transliterator::default()).unwrap().is_none());
+    }
+}
@@ -2,43 +2,253 @@
 
 use tantivy::{
     collector::TopDocs,
-    query::{Query, QueryParser, FuzzyTermQuery, BooleanQuery, TermQuery, Occur},
-    schema::{Term, Value},
+    query::{Query, QueryParser, FuzzyTermQuery, BooleanQuery, RegexQuery, TermQuery, TermSetQuery, Occur},
+    schema::{IndexRecordOption, Term, Value},
     doc,
     DocAddress,
+    TantivyDocument,
 };
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
+use tantivy::snippet::SnippetGenerator;
+use uuid::Uuid;
 
-use crate::models::Patient;
+use crate::config::FieldBoosts;
+use crate::matching::transliteration::{detect_script, Script, Transliterator};
+use crate::models::{NameUse, Patient};
+use crate::validation::telecom::{email_key, phone_key, telecom_term};
 use crate::Result;
 
 pub mod index;
+pub mod blocking;
 pub mod query;
+pub mod filter;
+pub mod projection;
+pub mod backend;
+pub mod postgres;
+pub mod replication;
+pub mod migration;
+pub mod partition;
+pub mod bulk;
+#[cfg(feature = "opensearch")]
+pub mod opensearch;
+
+pub use index::{snapshot_path, PatientIndex, PatientIndexSchema, IndexStats, SchemaStatus, SnapshotInfo, SCHEMA_VERSION};
+pub use migration::{rebuild_if_stale, rebuild_if_stale_where, SchemaRebuildReport};
+pub use partition::PartitionedIndex;
+pub use bulk::{active_patients, BulkIndexReport, BulkIndexer};
+pub use projection::SearchIndexProjection;
+pub use backend::{SearchBackend, create_backend};
+pub use filter::PatientFilter;
+pub use blocking::{blocking_keys, BlockingKeyKind, BlockingQuery, MultiPassBlocking, PassYield};
+
+/// Results fetched per result wanted when scoping a lookup to an
+/// organization, since the scope is applied after retrieval
+pub const ORGANIZATION_OVERFETCH: usize = 5;
+
+/// A search result with its relevance score
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub patient_id: String,
+    pub score: f32,
+    /// Highlighted fragments keyed by field name, with matched terms wrapped in `<b>` tags
+    pub highlights: HashMap<String, String>,
+}
+
+/// Field compared with the query in a fuzzy search
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FuzzyField {
+    FamilyName,
+    GivenNames,
+    Identifiers,
+}
+
+impl std::str::FromStr for FuzzyField {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "family_name" => Ok(Self::FamilyName),
+            "given_names" => Ok(Self::GivenNames),
+            "identifiers" => Ok(Self::Identifiers),
+            other => Err(crate::Error::Validation(format!(
+                "Unknown fuzzy search field '{}'; expected family_name, given_names or identifiers",
+                other
+            ))),
+        }
+    }
+}
+
+/// How far a fuzzy search strays from the query
+///
+/// The default is the original behaviour: family names within two edits,
+/// counting a swap of adjacent characters as one edit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyOptions {
+    /// Most edits per term, from 0 to [`FuzzyOptions::MAX_DISTANCE`]
+    pub distance: u8,
+    /// Leading characters of each term that must match exactly
+    pub prefix_length: usize,
+    /// Count a swap of adjacent characters as one edit rather than two
+    pub transpositions: bool,
+    /// Fields compared with the query; a hit in any of them matches
+    pub fields: Vec<FuzzyField>,
+}
+
+impl FuzzyOptions {
+    /// Largest supported edit distance
+    pub const MAX_DISTANCE: u8 = 2;
+
+    /// Check the distance is supported and at least one field is searched
+    pub fn validate(&self) -> Result<()> {
+        if self.distance > Self::MAX_DISTANCE {
+            return Err(crate::Error::Validation(format!(
+                "Fuzzy distance must be at most {}",
+                Self::MAX_DISTANCE
+            )));
+        }
+        if self.fields.is_empty() {
+            return Err(crate::Error::Validation("At least one fuzzy search field is required".to_string()));
+        }
+        Ok(())
+    }
+}
+
+impl Default for FuzzyOptions {
+    fn default() -> Self {
+        Self {
+            distance: 2,
+            prefix_length: 0,
+            transpositions: true,
+            fields: vec![FuzzyField::FamilyName],
+        }
+    }
+}
 
-pub use index::{PatientIndex, PatientIndexSchema, IndexStats};
+/// A suggested family name for typeahead and "did you mean" prompts
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
+pub struct Suggestion {
+    /// Indexed (lowercased) family name term
+    pub term: String,
+    /// Number of indexed patients with this family name
+    pub doc_freq: u32,
+    /// Edit distance from the query (0 for prefix completions)
+    pub distance: usize,
+}
 
 /// Search engine for patient records
 pub struct SearchEngine {
     index: PatientIndex,
+    field_boosts: FieldBoosts,
+    transliterator: Transliterator,
 }
 
 impl SearchEngine {
     /// Create a new search engine instance
     pub fn new<P: AsRef<Path>>(index_path: P) -> Result<Self> {
         let index = PatientIndex::create_or_open(index_path)?;
-        Ok(Self { index })
+        Ok(Self {
+            index,
+            field_boosts: FieldBoosts::default(),
+            transliterator: Transliterator::default(),
+        })
+    }
+
+    /// Set the per-field boosts used by [`SearchEngine::search`]
+    pub fn with_field_boosts(mut self, field_boosts: FieldBoosts) -> Self {
+        self.field_boosts = field_boosts;
+        self
+    }
+
+    /// Set the tables used to index and query names in non-Latin scripts
+    pub fn with_transliterator(mut self, transliterator: Transliterator) -> Self {
+        self.transliterator = transliterator;
+        self
+    }
+
+    /// Latin form of text written in another script, if transliteration is on
+    fn latin_form(&self, text: &str) -> Option<String> {
+        (self.transliterator.is_enabled() && detect_script(text) != Script::Latin)
+            .then(|| self.transliterator.to_latin(text))
     }
 
     /// Index a patient record
     pub fn index_patient(&self, patient: &Patient) -> Result<()> {
         let mut writer = self.index.writer(50)?;
+
+        writer.add_document(self.build_document(patient))
+            .map_err(|e| crate::Error::Search(format!("Failed to add document: {}", e)))?;
+
+        writer.commit()
+            .map_err(|e| crate::Error::Search(format!("Failed to commit: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Bulk index multiple patients
+    pub fn index_patients(&self, patients: &[Patient]) -> Result<()> {
+        let mut writer = self.index.writer(100)?;
+
+        for patient in patients {
+            writer.add_document(self.build_document(patient))
+                .map_err(|e| crate::Error::Search(format!("Failed to add document: {}", e)))?;
+        }
+
+        writer.commit()
+            .map_err(|e| crate::Error::Search(format!("Failed to commit: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Replace and remove documents in a single commit
+    ///
+    /// Any existing document for an upserted or removed patient is deleted
+    /// first, so each patient ends up with at most one document.
+    pub fn apply_changes(&self, upserts: &[Patient], removals: &[String]) -> Result<()> {
+        let mut writer = self.index.writer(100)?;
+        let schema = self.index.schema();
+
+        for patient_id in upserts.iter().map(|p| p.id.to_string()).chain(removals.iter().cloned()) {
+            writer.delete_term(Term::from_field_text(schema.id, &patient_id));
+        }
+
+        for patient in upserts {
+            writer.add_document(self.build_document(patient))
+                .map_err(|e| crate::Error::Search(format!("Failed to add document: {}", e)))?;
+        }
+
+        writer.commit()
+            .map_err(|e| crate::Error::Search(format!("Failed to commit: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Remove every document from the index
+    pub fn clear(&self) -> Result<()> {
+        let mut writer = self.index.writer(50)?;
+
+        writer.delete_all_documents()
+            .map_err(|e| crate::Error::Search(format!("Failed to clear index: {}", e)))?;
+
+        writer.commit()
+            .map_err(|e| crate::Error::Search(format!("Failed to commit: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Build the index document for a patient
+    fn build_document(&self, patient: &Patient) -> TantivyDocument {
         let schema = self.index.schema();
 
-        // Build full name
-        let full_name = patient.full_name();
+        // Legal name fields carry the higher boosts; a preferred name is only
+        // added to full_name so it is findable without outranking the legal name
+        let legal_name = patient.legal_name();
+        let full_name = legal_name.display();
 
         // Collect given names
-        let given_names = patient.name.given.join(" ");
+        let given_names = legal_name.given.join(" ");
 
         // Collect identifiers
         let identifiers: Vec<String> = patient
@@ -59,11 +269,10 @@ impl SearchEngine {
             (String::new(), String::new(), String::new())
         };
 
-        // Create document
-        let doc = doc!(
+        let mut document = doc!(
             schema.id => patient.id.to_string(),
-            schema.family_name => patient.name.family.clone(),
-            schema.given_names => given_names,
+            schema.family_name => legal_name.family.clone(),
+            schema.given_names => given_names.clone(),
             schema.full_name => full_name,
             schema.birth_date => patient.birth_date.map(|d| d.to_string()).unwrap_or_default(),
             schema.gender => format!("{:?}", patient.gender).to_lowercase(),
@@ -73,72 +282,65 @@ impl SearchEngine {
             schema.identifiers => identifiers_str,
             schema.active => if patient.active { "true" } else { "false" },
         );
+        if let Some(preferred) = patient.preferred_name() {
+            document.add_text(schema.full_name, preferred.display());
+        }
 
-        writer.add_document(doc)
-            .map_err(|e| crate::Error::Search(format!("Failed to add document: {}", e)))?;
-
-        writer.commit()
-            .map_err(|e| crate::Error::Search(format!("Failed to commit: {}", e)))?;
-
-        Ok(())
-    }
-
-    /// Bulk index multiple patients
-    pub fn index_patients(&self, patients: &[Patient]) -> Result<()> {
-        let mut writer = self.index.writer(100)?;
-        let schema = self.index.schema();
-
-        for patient in patients {
-            let full_name = patient.full_name();
-            let given_names = patient.name.given.join(" ");
-            let identifiers: Vec<String> = patient
-                .identifiers
-                .iter()
-                .map(|id| format!("{}:{}", id.identifier_type.to_string(), id.value))
-                .collect();
-            let identifiers_str = identifiers.join(" ");
-
-            let (postal_code, city, state) = if let Some(addr) = patient.addresses.first() {
-                (
-                    addr.postal_code.clone().unwrap_or_default(),
-                    addr.city.clone().unwrap_or_default(),
-                    addr.state.clone().unwrap_or_default(),
-                )
-            } else {
-                (String::new(), String::new(), String::new())
-            };
-
-            let doc = doc!(
-                schema.id => patient.id.to_string(),
-                schema.family_name => patient.name.family.clone(),
-                schema.given_names => given_names,
-                schema.full_name => full_name,
-                schema.birth_date => patient.birth_date.map(|d| d.to_string()).unwrap_or_default(),
-                schema.gender => format!("{:?}", patient.gender).to_lowercase(),
-                schema.postal_code => postal_code,
-                schema.city => city,
-                schema.state => state,
-                schema.identifiers => identifiers_str,
-                schema.active => if patient.active { "true" } else { "false" },
-            );
-
-            writer.add_document(doc)
-                .map_err(|e| crate::Error::Search(format!("Failed to add document: {}", e)))?;
+        // Romanized forms make names in other scripts findable from Latin queries
+        for name in std::iter::once(legal_name).chain(patient.preferred_name()) {
+            if let Some(latin) = self.latin_form(&name.display()) {
+                document.add_text(schema.full_name, latin);
+            }
+        }
+        if let Some(latin) = self.latin_form(&legal_name.family) {
+            document.add_text(schema.family_name, latin);
+        }
+        if let Some(latin) = self.latin_form(&given_names) {
+            document.add_text(schema.given_names, latin);
         }
 
-        writer.commit()
-            .map_err(|e| crate::Error::Search(format!("Failed to commit: {}", e)))?;
+        for term in patient.telecom.iter().filter_map(telecom_term) {
+            document.add_text(schema.telecom, term);
+        }
 
-        Ok(())
+        for maiden in maiden_names(patient) {
+            document.add_text(schema.maiden_name, maiden);
+            if let Some(latin) = self.latin_form(maiden) {
+                document.add_text(schema.maiden_name, latin);
+            }
+        }
+        for suffix in legal_name.suffix_keys() {
+            document.add_text(schema.name_suffix, suffix);
+        }
+        for name_use in name_uses(patient) {
+            document.add_text(schema.name_use, name_use);
+        }
+        for key in blocking_keys(patient) {
+            document.add_text(schema.blocking_keys, key);
+        }
+        if let Some(organization) = patient.managing_organization {
+            document.add_text(schema.managing_organization, organization.to_string());
+        }
+        document
     }
 
     /// Search for patients by query string
     pub fn search(&self, query_str: &str, limit: usize) -> Result<Vec<String>> {
+        Ok(self
+            .search_with_scores(query_str, limit)?
+            .into_iter()
+            .map(|hit| hit.patient_id)
+            .collect())
+    }
+
+    /// Search for patients by query string, returning relevance scores
+    pub fn search_with_scores(&self, query_str: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        crate::deadline::check()?;
         let searcher = self.index.reader().searcher();
         let schema = self.index.schema();
 
         // Create query parser for name and identifier fields
-        let query_parser = QueryParser::for_index(
+        let mut query_parser = QueryParser::for_index(
             self.index.index(),
             vec![
                 schema.full_name,
@@ -147,58 +349,119 @@ impl SearchEngine {
                 schema.identifiers,
             ],
         );
-
+        query_parser.set_field_boost(schema.identifiers, self.field_boosts.identifiers);
+        query_parser.set_field_boost(schema.family_name, self.field_boosts.family_name);
+        query_parser.set_field_boost(schema.given_names, self.field_boosts.given_names);
+        query_parser.set_field_boost(schema.full_name, self.field_boosts.full_name);
+
+        // Terms are OR'd, so a romanized copy also matches Latin-script records
+        let query_str = match self.latin_form(query_str) {
+            Some(latin) => Cow::Owned(format!("{} {}", query_str, latin)),
+            None => Cow::Borrowed(query_str),
+        };
         let query = query_parser
-            .parse_query(query_str)
+            .parse_query(&query_str)
             .map_err(|e| crate::Error::Search(format!("Failed to parse query: {}", e)))?;
 
         let top_docs = searcher
             .search(&query, &TopDocs::with_limit(limit))
             .map_err(|e| crate::Error::Search(format!("Search failed: {}", e)))?;
 
-        let mut patient_ids = Vec::new();
-        for (_score, doc_address) in top_docs {
-            let retrieved_doc: tantivy::TantivyDocument = searcher
-                .doc(doc_address)
-                .map_err(|e| crate::Error::Search(format!("Failed to retrieve document: {}", e)))?;
-
-            if let Some(id_value) = retrieved_doc.get_first(schema.id) {
-                if let Some(id_text) = id_value.as_str() {
-                    patient_ids.push(id_text.to_string());
-                }
-            }
+        // Highlight matched terms in the name and identifier fields
+        let mut generators = Vec::new();
+        for (name, field) in [("full_name", schema.full_name), ("identifiers", schema.identifiers)] {
+            let generator = SnippetGenerator::create(&searcher, query.as_ref(), field)
+                .map_err(|e| crate::Error::Search(format!("Failed to create snippet generator: {}", e)))?;
+            generators.push((name, generator));
         }
 
-        Ok(patient_ids)
+        self.collect_hits(&searcher, top_docs, &generators)
     }
 
     /// Search for patients with fuzzy matching
-    pub fn fuzzy_search(&self, query_str: &str, limit: usize) -> Result<Vec<String>> {
+    pub fn fuzzy_search(&self, query_str: &str, options: &FuzzyOptions, limit: usize) -> Result<Vec<String>> {
+        Ok(self
+            .fuzzy_search_with_scores(query_str, options, limit)?
+            .into_iter()
+            .map(|hit| hit.patient_id)
+            .collect())
+    }
+
+    /// Search for patients with fuzzy matching, returning relevance scores
+    ///
+    /// Each word of the query is compared with the terms of each field in
+    /// `options`; a document matching any of them is a hit.
+    pub fn fuzzy_search_with_scores(&self, query_str: &str, options: &FuzzyOptions, limit: usize) -> Result<Vec<SearchHit>> {
+        crate::deadline::check()?;
+        options.validate()?;
         let searcher = self.index.reader().searcher();
         let schema = self.index.schema();
 
-        // Build fuzzy query for family name
-        let term = Term::from_field_text(schema.family_name, query_str);
-        let fuzzy_query = FuzzyTermQuery::new(term, 2, true);
+        // Split as the default tokenizer does, so words are alphanumeric
+        let words: Vec<String> = query_str
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+        for field in &options.fields {
+            let field = match field {
+                FuzzyField::FamilyName => schema.family_name,
+                FuzzyField::GivenNames => schema.given_names,
+                FuzzyField::Identifiers => schema.identifiers,
+            };
+            for word in &words {
+                let term = Term::from_field_text(field, word);
+                let fuzzy: Box<dyn Query> = Box::new(FuzzyTermQuery::new(term, options.distance, options.transpositions));
+                if options.prefix_length == 0 {
+                    clauses.push((Occur::Should, fuzzy));
+                    continue;
+                }
+
+                // Alphanumeric words need no regex escaping
+                let prefix: String = word.chars().take(options.prefix_length).collect();
+                let prefix_query = RegexQuery::from_pattern(&format!("{}.*", prefix), field)
+                    .map_err(|e| crate::Error::Search(format!("Failed to build prefix query: {}", e)))?;
+                let both = BooleanQuery::new(vec![(Occur::Must, fuzzy), (Occur::Must, Box::new(prefix_query))]);
+                clauses.push((Occur::Should, Box::new(both)));
+            }
+        }
+        if clauses.is_empty() {
+            return Ok(Vec::new());
+        }
 
         let top_docs = searcher
-            .search(&fuzzy_query, &TopDocs::with_limit(limit))
+            .search(&BooleanQuery::new(clauses), &TopDocs::with_limit(limit))
             .map_err(|e| crate::Error::Search(format!("Fuzzy search failed: {}", e)))?;
 
-        let mut patient_ids = Vec::new();
-        for (_score, doc_address) in top_docs {
-            let retrieved_doc: tantivy::TantivyDocument = searcher
-                .doc(doc_address)
-                .map_err(|e| crate::Error::Search(format!("Failed to retrieve document: {}", e)))?;
+        self.collect_hits(&searcher, top_docs, &[])
+    }
 
-            if let Some(id_value) = retrieved_doc.get_first(schema.id) {
-                if let Some(id_text) = id_value.as_str() {
-                    patient_ids.push(id_text.to_string());
-                }
-            }
-        }
+    /// Search by phone number and/or email; every value given must match
+    ///
+    /// Values are normalized the same way as indexed contact points, so
+    /// punctuation, country codes and letter case do not matter.
+    pub fn search_by_telecom(&self, phone: Option<&str>, email: Option<&str>, limit: usize) -> Result<Vec<SearchHit>> {
+        let schema = self.index.schema();
+        let Some(terms) = telecom_query_terms(phone, email) else {
+            return Ok(Vec::new());
+        };
 
-        Ok(patient_ids)
+        let clauses: Vec<(Occur, Box<dyn Query>)> = terms
+            .iter()
+            .map(|term| {
+                let query = TermQuery::new(Term::from_field_text(schema.telecom, term), IndexRecordOption::Basic);
+                (Occur::Must, Box::new(query) as Box<dyn Query>)
+            })
+            .collect();
+
+        let searcher = self.index.reader().searcher();
+        let top_docs = searcher
+            .search(&BooleanQuery::new(clauses), &TopDocs::with_limit(limit))
+            .map_err(|e| crate::Error::Search(format!("Telecom search failed: {}", e)))?;
+
+        self.collect_hits(&searcher, top_docs, &[])
     }
 
     /// Search by name and birth year (for blocking in matching)
@@ -208,12 +471,21 @@ impl SearchEngine {
         birth_year: Option<i32>,
         limit: usize,
     ) -> Result<Vec<String>> {
+        crate::deadline::check()?;
         let searcher = self.index.reader().searcher();
         let schema = self.index.schema();
 
-        // Build fuzzy query for family name
-        let name_term = Term::from_field_text(schema.family_name, family_name);
-        let name_query: Box<dyn Query> = Box::new(FuzzyTermQuery::new(name_term, 2, true));
+        // Build fuzzy query for family and maiden names, in their romanized
+        // forms too
+        let latin = self.latin_form(family_name);
+        let mut name_clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+        for field in [schema.family_name, schema.maiden_name] {
+            for name in std::iter::once(family_name).chain(latin.as_deref()) {
+                let term = Term::from_field_text(field, name);
+                name_clauses.push((Occur::Should, Box::new(FuzzyTermQuery::new(term, 2, true))));
+            }
+        }
+        let name_query: Box<dyn Query> = Box::new(BooleanQuery::new(name_clauses));
 
         // If birth year provided, add it to the query
         let final_query: Box<dyn Query> = if let Some(year) = birth_year {
@@ -255,6 +527,154 @@ impl SearchEngine {
         Ok(patient_ids)
     }
 
+    /// Patients sharing any of the given blocking keys, those sharing the
+    /// most first
+    pub fn search_by_blocking_keys(&self, keys: &[String], limit: usize) -> Result<Vec<String>> {
+        crate::deadline::check()?;
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        let searcher = self.index.reader().searcher();
+        let schema = self.index.schema();
+
+        let clauses: Vec<(Occur, Box<dyn Query>)> = keys
+            .iter()
+            .map(|key| {
+                let term = Term::from_field_text(schema.blocking_keys, key);
+                (Occur::Should, Box::new(TermQuery::new(term, IndexRecordOption::Basic)) as Box<dyn Query>)
+            })
+            .collect();
+        let top_docs = searcher
+            .search(&BooleanQuery::new(clauses), &TopDocs::with_limit(limit))
+            .map_err(|e| crate::Error::Search(format!("Blocking key search failed: {}", e)))?;
+
+        Ok(self
+            .collect_hits(&searcher, top_docs, &[])?
+            .into_iter()
+            .map(|hit| hit.patient_id)
+            .collect())
+    }
+
+    /// The given patients that `organization` manages
+    pub fn in_organization(&self, patient_ids: &[String], organization: &Uuid) -> Result<HashSet<String>> {
+        crate::deadline::check()?;
+        if patient_ids.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let searcher = self.index.reader().searcher();
+        let schema = self.index.schema();
+
+        let ids = TermSetQuery::new(patient_ids.iter().map(|id| Term::from_field_text(schema.id, id)));
+        let organization = Term::from_field_text(schema.managing_organization, &organization.to_string());
+        let query = BooleanQuery::new(vec![
+            (Occur::Must, Box::new(ids) as Box<dyn Query>),
+            (Occur::Must, Box::new(TermQuery::new(organization, IndexRecordOption::Basic))),
+        ]);
+        let top_docs = searcher
+            .search(&query, &TopDocs::with_limit(patient_ids.len()))
+            .map_err(|e| crate::Error::Search(format!("Organization filter failed: {}", e)))?;
+
+        Ok(self
+            .collect_hits(&searcher, top_docs, &[])?
+            .into_iter()
+            .map(|hit| hit.patient_id)
+            .collect())
+    }
+
+    /// Suggest family names that complete or correct the given input
+    ///
+    /// Walks the family name term dictionary of every segment, so the cost is
+    /// proportional to the number of distinct family names in the index.
+    pub fn suggest(&self, query_str: &str, limit: usize) -> Result<Vec<Suggestion>> {
+        let query = query_str.trim().to_lowercase();
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Short inputs tolerate fewer typos, otherwise everything is a suggestion
+        let max_distance = if query.chars().count() <= 4 { 1 } else { 2 };
+
+        let searcher = self.index.reader().searcher();
+        let schema = self.index.schema();
+
+        let mut doc_freqs: HashMap<String, u32> = HashMap::new();
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader
+                .inverted_index(schema.family_name)
+                .map_err(|e| crate::Error::Search(format!("Failed to open term dictionary: {}", e)))?;
+            let mut stream = inverted_index
+                .terms()
+                .stream()
+                .map_err(|e| crate::Error::Search(format!("Failed to stream terms: {}", e)))?;
+
+            while let Some((key, term_info)) = stream.next() {
+                if let Ok(term) = std::str::from_utf8(key) {
+                    *doc_freqs.entry(term.to_string()).or_insert(0) += term_info.doc_freq;
+                }
+            }
+        }
+
+        let mut suggestions: Vec<Suggestion> = doc_freqs
+            .into_iter()
+            .filter_map(|(term, doc_freq)| {
+                let distance = if term.starts_with(&query) {
+                    0
+                } else {
+                    strsim::levenshtein(&query, &term)
+                };
+                (distance <= max_distance).then_some(Suggestion { term, doc_freq, distance })
+            })
+            .collect();
+
+        // Closest first, then most common
+        suggestions.sort_by(|a, b| {
+            a.distance
+                .cmp(&b.distance)
+                .then(b.doc_freq.cmp(&a.doc_freq))
+                .then(a.term.cmp(&b.term))
+        });
+        suggestions.truncate(limit);
+
+        Ok(suggestions)
+    }
+
+    /// Resolve scored document addresses to patient IDs and highlights
+    fn collect_hits(
+        &self,
+        searcher: &tantivy::Searcher,
+        top_docs: Vec<(f32, DocAddress)>,
+        generators: &[(&str, SnippetGenerator)],
+    ) -> Result<Vec<SearchHit>> {
+        let schema = self.index.schema();
+
+        let mut hits = Vec::new();
+        for (score, doc_address) in top_docs {
+            let retrieved_doc: tantivy::TantivyDocument = searcher
+                .doc(doc_address)
+                .map_err(|e| crate::Error::Search(format!("Failed to retrieve document: {}", e)))?;
+
+            let mut highlights = HashMap::new();
+            for (name, generator) in generators {
+                let snippet = generator.snippet_from_doc(&retrieved_doc);
+                if !snippet.is_empty() {
+                    highlights.insert(name.to_string(), snippet.to_html());
+                }
+            }
+
+            if let Some(id_value) = retrieved_doc.get_first(schema.id) {
+                if let Some(id_text) = id_value.as_str() {
+                    hits.push(SearchHit {
+                        patient_id: id_text.to_string(),
+                        score,
+                        highlights,
+                    });
+                }
+            }
+        }
+
+        Ok(hits)
+    }
+
     /// Remove a patient from the index
     pub fn delete_patient(&self, patient_id: &str) -> Result<()> {
         let mut writer = self.index.writer(50)?;
@@ -274,24 +694,75 @@ impl SearchEngine {
         self.index.stats()
     }
 
+    /// IDs of every indexed patient
+    pub fn patient_ids(&self) -> Result<HashSet<String>> {
+        self.index.patient_ids()
+    }
+
     /// Optimize the index
     pub fn optimize(&self) -> Result<()> {
         self.index.optimize()
     }
 
+    /// Write a consistent copy of the index to the given directory
+    pub fn snapshot<P: AsRef<Path>>(&self, path: P) -> Result<SnapshotInfo> {
+        self.index.snapshot(path)
+    }
+
+    /// Restore the index from a snapshot directory
+    pub fn restore<P: AsRef<Path>>(&self, path: P) -> Result<SnapshotInfo> {
+        self.index.restore(path)
+    }
+
     /// Manually reload the index reader (useful for tests to ensure documents are visible)
     pub fn reload(&self) -> Result<()> {
         self.index.reload()
     }
 }
 
+/// Family names the patient was recorded under as maiden names
+pub(crate) fn maiden_names(patient: &Patient) -> impl Iterator<Item = &str> {
+    std::iter::once(&patient.name)
+        .chain(&patient.additional_names)
+        .filter(|name| name.use_type == Some(NameUse::Maiden) && !name.family.is_empty())
+        .map(|name| name.family.as_str())
+}
+
+/// Distinct uses of the patient's names; a name without one counts as official
+pub(crate) fn name_uses(patient: &Patient) -> Vec<&'static str> {
+    let mut uses: Vec<&'static str> = std::iter::once(&patient.name)
+        .chain(&patient.additional_names)
+        .map(|name| name.use_type.as_ref().map_or("official", NameUse::as_str))
+        .collect();
+    uses.sort_unstable();
+    uses.dedup();
+    uses
+}
+
+/// Indexed `phone:`/`email:` terms for a telecom search
+///
+/// Returns `None` when no value was given or a value cannot be normalized,
+/// so a search for a malformed number finds nothing rather than everything.
+pub(crate) fn telecom_query_terms(phone: Option<&str>, email: Option<&str>) -> Option<Vec<String>> {
+    if phone.is_none() && email.is_none() {
+        return None;
+    }
+    let mut terms = Vec::new();
+    if let Some(phone) = phone {
+        terms.push(format!("phone:{}", phone_key(phone)?));
+    }
+    if let Some(email) = email {
+        terms.push(format!("email:{}", email_key(email)?));
+    }
+    Some(terms)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::models::{HumanName, Gender};
     use chrono::{Utc, NaiveDate};
     use tempfile::TempDir;
-    use uuid::Uuid;
 
     fn create_test_patient(family: &str, given: &str, birth_date: Option<NaiveDate>) -> Patient {
         Patient {
@@ -308,6 +779,8 @@ mod tests {
             additional_names: vec![],
             telecom: vec![],
             gender: Gender::Male,
+            gender_identity: None,
+            pronouns: None,
             birth_date,
             deceased: false,
             deceased_datetime: None,
@@ -315,8 +788,12 @@ mod tests {
             marital_status: None,
             multiple_birth: None,
             photo: vec![],
+            photo_hashes: vec![],
             managing_organization: None,
             links: vec![],
+            contacts: vec![],
+            confidentiality: Default::default(),
+            status: Default::default(),
             created_at: Utc::now(),
             updated_at: Utc::now(),
         }
@@ -346,11 +823,38 @@ mod tests {
         engine.reload().unwrap(); // Ensure reader sees new document
 
         // Fuzzy search with typo
-        let results = engine.fuzzy_search("Smyth", 10).unwrap();
+        let results = engine.fuzzy_search("Smyth", &FuzzyOptions::default(), 10).unwrap();
         assert_eq!(results.len(), 1);
         assert_eq!(results[0], patient.id.to_string());
     }
 
+    #[test]
+    fn test_fuzzy_search_options() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let patient = create_test_patient("Smith", "Jonathan", None);
+        engine.index_patient(&patient).unwrap();
+        engine.reload().unwrap();
+
+        let strict = FuzzyOptions { distance: 1, ..FuzzyOptions::default() };
+        assert_eq!(engine.fuzzy_search("Smyth", &strict, 10).unwrap().len(), 1);
+        assert!(engine.fuzzy_search("Smythe", &strict, 10).unwrap().is_empty());
+
+        // The first letters must match exactly
+        let prefixed = FuzzyOptions { prefix_length: 2, ..FuzzyOptions::default() };
+        assert_eq!(engine.fuzzy_search("Smyth", &prefixed, 10).unwrap().len(), 1);
+        assert!(engine.fuzzy_search("Snith", &prefixed, 10).unwrap().is_empty());
+
+        // Given names are only searched when asked for
+        assert!(engine.fuzzy_search("Jonatan", &FuzzyOptions::default(), 10).unwrap().is_empty());
+        let given = FuzzyOptions { fields: vec![FuzzyField::GivenNames], ..FuzzyOptions::default() };
+        assert_eq!(engine.fuzzy_search("Jonatan", &given, 10).unwrap().len(), 1);
+
+        let too_far = FuzzyOptions { distance: 3, ..FuzzyOptions::default() };
+        assert!(engine.fuzzy_search("Smyth", &too_far, 10).is_err());
+    }
+
     #[test]
     fn test_bulk_indexing() {
         let temp_dir = TempDir::new().unwrap();
@@ -388,6 +892,21 @@ mod tests {
         assert_eq!(results.len(), 0);
     }
 
+    #[test]
+    fn test_patient_ids() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let kept = create_test_patient("Smith", "John", None);
+        let deleted = create_test_patient("Jones", "Mary", None);
+        engine.index_patients(&[kept.clone(), deleted.clone()]).unwrap();
+        engine.delete_patient(&deleted.id.to_string()).unwrap();
+        engine.reload().unwrap();
+
+        let ids = engine.patient_ids().unwrap();
+        assert_eq!(ids, HashSet::from([kept.id.to_string()]));
+    }
+
     #[test]
     fn test_search_by_name_and_year() {
         let temp_dir = TempDir::new().unwrap();
@@ -402,4 +921,210 @@ mod tests {
         assert_eq!(results.len(), 1);
         assert_eq!(results[0], patient.id.to_string());
     }
+
+    #[test]
+    fn test_search_by_blocking_keys() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let smyth = create_test_patient("Smyth", "John", NaiveDate::from_ymd_opt(1980, 1, 15));
+        let jones = create_test_patient("Jones", "Mary", NaiveDate::from_ymd_opt(1980, 1, 15));
+        engine.index_patients(&[smyth.clone(), jones]).unwrap();
+        engine.reload().unwrap();
+
+        // Same Soundex code and birth year, different spelling
+        let incoming = create_test_patient("Smith", "Jon", NaiveDate::from_ymd_opt(1980, 6, 1));
+        let results = engine.search_by_blocking_keys(&blocking_keys(&incoming), 10).unwrap();
+        assert_eq!(results, vec![smyth.id.to_string()]);
+        assert!(engine.search_by_blocking_keys(&[], 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_maiden_names_and_suffixes_are_indexed() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let mut married = create_test_patient("Smith", "Ada", NaiveDate::from_ymd_opt(1980, 1, 15));
+        married.additional_names.push(HumanName {
+            use_type: Some(NameUse::Maiden),
+            family: "Okafor".to_string(),
+            given: vec!["Ada".to_string()],
+            prefix: vec![],
+            suffix: vec![],
+        });
+        let mut junior = create_test_patient("Smith", "Robert", None);
+        junior.name.suffix.push("Jr.".to_string());
+        let senior = create_test_patient("Smith", "Robert", None);
+        engine.index_patients(&[married.clone(), junior.clone(), senior]).unwrap();
+        engine.reload().unwrap();
+
+        let results = engine.search_by_name_and_year("okafor", Some(1980), 10).unwrap();
+        assert_eq!(results, vec![married.id.to_string()]);
+
+        let results = engine.search("+robert +name_suffix:jr", 10).unwrap();
+        assert_eq!(results, vec![junior.id.to_string()]);
+        let results = engine.search("name_use:maiden", 10).unwrap();
+        assert_eq!(results, vec![married.id.to_string()]);
+    }
+    #[test]
+    fn test_field_boosts_rank_identifier_first() {
+        let temp_dir = TempDir::new().unwrap();
+        let boosts = FieldBoosts {
+            identifiers: 10.0,
+            ..FieldBoosts::default()
+        };
+        let engine = SearchEngine::new(temp_dir.path()).unwrap().with_field_boosts(boosts);
+
+        let name_match = create_test_patient("Carter", "Ann", None);
+        let mut id_match = create_test_patient("Jones", "Bob", None);
+        id_match.identifiers.push(crate::models::Identifier::mrn(
+            "general".to_string(),
+            "carter".to_string(),
+        ));
+        engine.index_patients(&[name_match, id_match.clone()]).unwrap();
+        engine.reload().unwrap();
+
+        let hits = engine.search_with_scores("carter", 10).unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].patient_id, id_match.id.to_string());
+        assert!(hits[0].score > hits[1].score);
+    }
+
+    #[test]
+    fn test_search_highlights() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let mut patient = create_test_patient("Smith", "John", None);
+        patient.identifiers.push(crate::models::Identifier::mrn(
+            "general".to_string(),
+            "12345".to_string(),
+        ));
+        engine.index_patient(&patient).unwrap();
+        engine.reload().unwrap();
+
+        let hits = engine.search_with_scores("smith 12345", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].highlights["full_name"], "John <b>Smith</b>");
+        assert_eq!(hits[0].highlights["identifiers"], "MRN:<b>12345</b>");
+    }
+
+    #[test]
+    fn test_suggest() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let patients = vec![
+            create_test_patient("Smith", "John", None),
+            create_test_patient("Smith", "Jane", None),
+            create_test_patient("Smithson", "Bob", None),
+            create_test_patient("Williams", "Ann", None),
+        ];
+        engine.index_patients(&patients).unwrap();
+        engine.reload().unwrap();
+
+        // Prefix completions, most common first
+        let suggestions = engine.suggest("Smi", 10).unwrap();
+        let terms: Vec<&str> = suggestions.iter().map(|s| s.term.as_str()).collect();
+        assert_eq!(terms, vec!["smith", "smithson"]);
+        assert_eq!(suggestions[0].doc_freq, 2);
+
+        // Typo correction
+        let suggestions = engine.suggest("Wiliams", 10).unwrap();
+        assert_eq!(suggestions[0].term, "williams");
+        assert_eq!(suggestions[0].distance, 1);
+
+        assert!(engine.suggest("", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_preferred_name_is_indexed_below_legal_name() {
+        use crate::models::NameUse;
+
+        let temp_dir = TempDir::new().unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let mut known_as = create_test_patient("Taylor", "Robert", None);
+        known_as.name.use_type = Some(NameUse::Official);
+        known_as.additional_names.push(HumanName {
+            use_type: Some(NameUse::Usual),
+            family: "Taylor".to_string(),
+            given: vec!["Robin".to_string()],
+            prefix: vec![],
+            suffix: vec![],
+        });
+        let legally_named = create_test_patient("Jones", "Robin", None);
+        engine.index_patients(&[known_as.clone(), legally_named.clone()]).unwrap();
+        engine.reload().unwrap();
+
+        let results = engine.search("Robin", 10).unwrap();
+        assert_eq!(results, vec![legally_named.id.to_string(), known_as.id.to_string()]);
+    }
+
+    #[test]
+    fn test_search_across_scripts() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let cyrillic = create_test_patient("Иванов", "Сергей", None);
+        let latin = create_test_patient("Petrov", "Anton", None);
+        engine.index_patients(&[cyrillic.clone(), latin.clone()]).unwrap();
+        engine.reload().unwrap();
+
+        assert_eq!(engine.search("Ivanov", 10).unwrap(), vec![cyrillic.id.to_string()]);
+        assert_eq!(engine.search("Петров", 10).unwrap(), vec![latin.id.to_string()]);
+        assert_eq!(
+            engine.search_by_name_and_year("Ivanov", None, 10).unwrap(),
+            vec![cyrillic.id.to_string()]
+        );
+    }
+
+    #[test]
+    fn test_search_by_telecom() {
+        use crate::models::{ContactPoint, ContactPointSystem};
+
+        let temp_dir = TempDir::new().unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let contact = |system, value: &str| ContactPoint { system, value: value.to_string(), use_type: None, period: None };
+        let mut caller = create_test_patient("Okafor", "Ada", None);
+        caller.telecom = vec![
+            contact(ContactPointSystem::Phone, "+1 (207) 555-0142"),
+            contact(ContactPointSystem::Email, "Ada.Okafor@example.org"),
+        ];
+        let mut other = create_test_patient("Okafor", "Chidi", None);
+        other.telecom = vec![contact(ContactPointSystem::Phone, "207-555-0199")];
+        engine.index_patients(&[caller.clone(), other]).unwrap();
+        engine.reload().unwrap();
+
+        let ids = |hits: Vec<SearchHit>| hits.into_iter().map(|h| h.patient_id).collect::<Vec<_>>();
+        let expected = vec![caller.id.to_string()];
+        assert_eq!(ids(engine.search_by_telecom(Some("207.555.0142"), None, 10).unwrap()), expected);
+        assert_eq!(ids(engine.search_by_telecom(None, Some("ada.okafor@EXAMPLE.org"), 10).unwrap()), expected);
+        assert!(engine
+            .search_by_telecom(Some("207-555-0199"), Some("ada.okafor@example.org"), 10)
+            .unwrap()
+            .is_empty());
+        assert!(engine.search_by_telecom(Some("555"), None, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_in_organization() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let campus = Uuid::new_v4();
+        let mut north = create_test_patient("Mensah", "Kofi", None);
+        north.managing_organization = Some(campus);
+        let mut south = create_test_patient("Mensah", "Ama", None);
+        south.managing_organization = Some(Uuid::new_v4());
+        let unassigned = create_test_patient("Mensah", "Yaw", None);
+        engine.index_patients(&[north.clone(), south.clone(), unassigned.clone()]).unwrap();
+        engine.reload().unwrap();
+
+        let ids: Vec<String> = [&north, &south, &unassigned].iter().map(|p| p.id.to_string()).collect();
+        let scoped = engine.in_organization(&ids, &campus).unwrap();
+        assert_eq!(scoped, HashSet::from([north.id.to_string()]));
+        assert!(engine.in_organization(&[], &campus).unwrap().is_empty());
+    }
 }
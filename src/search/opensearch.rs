@@ -0,0 +1,516 @@
+//! OpenSearch/Elasticsearch search backend
+//!
+//! Stores one document per patient in a single index and retrieves
+//! candidates with `multi_match` queries. Works with OpenSearch 2.x and
+//! Elasticsearch 7.x/8.x, which share the APIs used here.
+//!
+//! Enabled with the `opensearch` feature.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use base64::Engine as _;
+use chrono::Datelike;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::config::{FieldBoosts, OpenSearchConfig};
+use crate::models::Patient;
+use crate::validation::telecom::telecom_term;
+use crate::{Error, Result};
+use super::backend::SearchBackend;
+use super::{blocking_keys, maiden_names, name_uses, telecom_query_terms, FuzzyField, FuzzyOptions, SearchHit};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Search backend backed by an OpenSearch or Elasticsearch cluster
+pub struct OpenSearchBackend {
+    config: OpenSearchConfig,
+    field_boosts: FieldBoosts,
+    agent: ureq::Agent,
+}
+
+impl OpenSearchBackend {
+    /// Create a backend for the configured cluster and index
+    pub fn new(config: OpenSearchConfig) -> Self {
+        Self {
+            config,
+            field_boosts: FieldBoosts::default(),
+            agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+        }
+    }
+
+    /// Set the per-field boosts used by searches
+    pub fn with_field_boosts(mut self, field_boosts: FieldBoosts) -> Self {
+        self.field_boosts = field_boosts;
+        self
+    }
+
+    /// Create the patient index with its mapping if it does not exist
+    pub fn ensure_index(&self) -> Result<()> {
+        match self.send("HEAD", &self.index_url(""), None).map_err(|e| *e) {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(404, _)) => {
+                self.request("PUT", &self.index_url(""), Some(index_mapping()))?;
+                tracing::info!("Created OpenSearch index {}", self.config.index);
+                Ok(())
+            }
+            Err(e) => Err(search_error("check index", e)),
+        }
+    }
+
+    fn index_url(&self, path: &str) -> String {
+        format!("{}/{}{}", self.config.url.trim_end_matches('/'), self.config.index, path)
+    }
+
+    fn send(&self, method: &str, url: &str, body: Option<Value>) -> std::result::Result<ureq::Response, Box<ureq::Error>> {
+        let request = self.authorize(self.agent.request(method, url));
+
+        match body {
+            Some(body) => request.send_json(body),
+            None => request.call(),
+        }
+        .map_err(Box::new)
+    }
+
+    fn authorize(&self, request: ureq::Request) -> ureq::Request {
+        match &self.config.username {
+            Some(username) => {
+                let credentials = format!("{}:{}", username, self.config.password.as_deref().unwrap_or(""));
+                let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
+                request.set("Authorization", &format!("Basic {}", encoded))
+            }
+            None => request,
+        }
+    }
+
+    fn request(&self, method: &str, url: &str, body: Option<Value>) -> Result<Value> {
+        let response = self
+            .send(method, url, body)
+            .map_err(|e| search_error(method, *e))?;
+        if method == "HEAD" {
+            return Ok(Value::Null);
+        }
+        response
+            .into_json()
+            .map_err(|e| Error::Search(format!("Invalid OpenSearch response: {}", e)))
+    }
+
+    fn bulk(&self, upserts: &[Patient], removals: &[String]) -> Result<()> {
+        if upserts.is_empty() && removals.is_empty() {
+            return Ok(());
+        }
+
+        let mut body = String::new();
+        for patient_id in removals {
+            body.push_str(&json!({ "delete": { "_id": patient_id } }).to_string());
+            body.push('\n');
+        }
+        for patient in upserts {
+            body.push_str(&json!({ "index": { "_id": patient.id.to_string() } }).to_string());
+            body.push('\n');
+            body.push_str(&patient_document(patient).to_string());
+            body.push('\n');
+        }
+
+        let response: Value = self
+            .authorize(self.agent.post(&self.index_url("/_bulk?refresh=wait_for")))
+            .set("Content-Type", "application/x-ndjson")
+            .send_string(&body)
+            .map_err(|e| search_error("bulk", e))?
+            .into_json()
+            .map_err(|e| Error::Search(format!("Invalid OpenSearch response: {}", e)))?;
+
+        // Deleting a document that is not indexed is not an error
+        let failed = response["items"]
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| item.as_object()?.values().next())
+                    .filter(|result| {
+                        let status = result["status"].as_u64().unwrap_or(0);
+                        status >= 300 && !(status == 404 && result["result"] == "not_found")
+                    })
+                    .count()
+            })
+            .unwrap_or(0);
+        if failed > 0 {
+            return Err(Error::Search(format!("{} bulk operations failed", failed)));
+        }
+
+        Ok(())
+    }
+
+    fn query(&self, body: Value) -> Result<Vec<SearchHit>> {
+        let response = self.request("POST", &self.index_url("/_search"), Some(body))?;
+        Ok(parse_hits(&response))
+    }
+}
+
+impl SearchBackend for OpenSearchBackend {
+    fn name(&self) -> &'static str {
+        "opensearch"
+    }
+
+    fn index_patient(&self, patient: &Patient) -> Result<()> {
+        let url = self.index_url(&format!("/_doc/{}?refresh=wait_for", patient.id));
+        self.request("PUT", &url, Some(patient_document(patient)))?;
+        Ok(())
+    }
+
+    fn index_patients(&self, patients: &[Patient]) -> Result<()> {
+        self.bulk(patients, &[])
+    }
+
+    fn apply_changes(&self, upserts: &[Patient], removals: &[String]) -> Result<()> {
+        self.bulk(upserts, removals)
+    }
+
+    fn delete_patient(&self, patient_id: &str) -> Result<()> {
+        let url = self.index_url(&format!("/_doc/{}?refresh=wait_for", patient_id));
+        match self.send("DELETE", &url, None).map_err(|e| *e) {
+            Ok(_) | Err(ureq::Error::Status(404, _)) => Ok(()),
+            Err(e) => Err(search_error("delete", e)),
+        }
+    }
+
+    fn clear(&self) -> Result<()> {
+        let body = json!({ "query": { "match_all": {} } });
+        self.request("POST", &self.index_url("/_delete_by_query?refresh=true"), Some(body))?;
+        Ok(())
+    }
+
+    fn search_with_scores(&self, query_str: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        self.query(search_body(query_str, limit, &self.field_boosts))
+    }
+
+    fn fuzzy_search_with_scores(&self, query_str: &str, options: &FuzzyOptions, limit: usize) -> Result<Vec<SearchHit>> {
+        options.validate()?;
+        self.query(fuzzy_search_body(query_str, options, limit))
+    }
+
+    fn search_by_name_and_year(
+        &self,
+        family_name: &str,
+        birth_year: Option<i32>,
+        limit: usize,
+    ) -> Result<Vec<String>> {
+        Ok(self
+            .query(name_and_year_body(family_name, birth_year, limit))?
+            .into_iter()
+            .map(|hit| hit.patient_id)
+            .collect())
+    }
+
+    fn search_by_blocking_keys(&self, keys: &[String], limit: usize) -> Result<Vec<String>> {
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(self
+            .query(blocking_keys_body(keys, limit))?
+            .into_iter()
+            .map(|hit| hit.patient_id)
+            .collect())
+    }
+
+    fn search_by_telecom(&self, phone: Option<&str>, email: Option<&str>, limit: usize) -> Result<Vec<SearchHit>> {
+        match telecom_query_terms(phone, email) {
+            Some(terms) => self.query(telecom_body(&terms, limit)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn in_organization(&self, patient_ids: &[String], organization: &Uuid) -> Result<HashSet<String>> {
+        if patient_ids.is_empty() {
+            return Ok(HashSet::new());
+        }
+        Ok(self
+            .query(organization_body(patient_ids, organization))?
+            .into_iter()
+            .map(|hit| hit.patient_id)
+            .collect())
+    }
+}
+
+fn search_error(operation: &str, error: ureq::Error) -> Error {
+    match error {
+        ureq::Error::Status(status, response) => Error::Search(format!(
+            "OpenSearch {} failed with status {}: {}",
+            operation,
+            status,
+            response.into_string().unwrap_or_default()
+        )),
+        e => Error::Search(format!("OpenSearch {} failed: {}", operation, e)),
+    }
+}
+
+/// Index settings and field mapping
+fn index_mapping() -> Value {
+    json!({
+        "mappings": {
+            "properties": {
+                "id": { "type": "keyword" },
+                "family_name": { "type": "text" },
+                "given_names": { "type": "text" },
+                "full_name": { "type": "text" },
+                "birth_date": { "type": "keyword" },
+                "birth_year": { "type": "integer" },
+                "gender": { "type": "keyword" },
+                "postal_code": { "type": "keyword" },
+                "city": { "type": "text" },
+                "state": { "type": "keyword" },
+                "identifiers": { "type": "text", "analyzer": "whitespace" },
+                "active": { "type": "boolean" },
+                "telecom": { "type": "keyword" },
+                "maiden_name": { "type": "text" },
+                "name_suffix": { "type": "keyword" },
+                "name_use": { "type": "keyword" },
+                "blocking_keys": { "type": "keyword" },
+                "managing_organization": { "type": "keyword" }
+            }
+        }
+    })
+}
+
+/// Document for a patient, with the same fields as the Tantivy schema
+fn patient_document(patient: &Patient) -> Value {
+    let address = patient.addresses.first();
+    let identifiers: Vec<String> = patient
+        .identifiers
+        .iter()
+        .map(|id| format!("{}:{}", id.identifier_type, id.value))
+        .collect();
+
+    // A preferred name is searchable through full_name only, so matches on
+    // the legal name rank higher
+    let legal_name = patient.legal_name();
+    let full_name = match patient.preferred_name() {
+        Some(preferred) => json!([legal_name.display(), preferred.display()]),
+        None => json!(legal_name.display()),
+    };
+
+    json!({
+        "id": patient.id.to_string(),
+        "family_name": legal_name.family,
+        "given_names": legal_name.given.join(" "),
+        "full_name": full_name,
+        "birth_date": patient.birth_date.map(|d| d.to_string()),
+        "birth_year": patient.birth_date.map(|d| d.year()),
+        "gender": format!("{:?}", patient.gender).to_lowercase(),
+        "postal_code": address.and_then(|a| a.postal_code.clone()),
+        "city": address.and_then(|a| a.city.clone()),
+        "state": address.and_then(|a| a.state.clone()),
+        "identifiers": identifiers.join(" "),
+        "active": patient.active,
+        "telecom": patient.telecom.iter().filter_map(telecom_term).collect::<Vec<_>>(),
+        "maiden_name": maiden_names(patient).collect::<Vec<_>>(),
+        "name_suffix": legal_name.suffix_keys(),
+        "name_use": name_uses(patient),
+        "blocking_keys": blocking_keys(patient),
+        "managing_organization": patient.managing_organization.map(|id| id.to_string()),
+    })
+}
+
+fn search_body(query_str: &str, limit: usize, boosts: &FieldBoosts) -> Value {
+    json!({
+        "size": limit,
+        "query": {
+            "multi_match": {
+                "query": query_str,
+                "fields": [
+                    format!("identifiers^{}", boosts.identifiers),
+                    format!("family_name^{}", boosts.family_name),
+                    format!("given_names^{}", boosts.given_names),
+                    format!("full_name^{}", boosts.full_name),
+                ]
+            }
+        },
+        "highlight": {
+            "pre_tags": ["<b>"],
+            "post_tags": ["</b>"],
+            "fields": { "full_name": {}, "identifiers": {} }
+        }
+    })
+}
+
+fn fuzzy_search_body(query_str: &str, options: &FuzzyOptions, limit: usize) -> Value {
+    let fields: Vec<&str> = options
+        .fields
+        .iter()
+        .map(|field| match field {
+            FuzzyField::FamilyName => "family_name",
+            FuzzyField::GivenNames => "given_names",
+            FuzzyField::Identifiers => "identifiers",
+        })
+        .collect();
+
+    json!({
+        "size": limit,
+        "query": {
+            "multi_match": {
+                "query": query_str,
+                "fields": fields,
+                "fuzziness": options.distance.to_string(),
+                "prefix_length": options.prefix_length,
+                "fuzzy_transpositions": options.transpositions
+            }
+        }
+    })
+}
+
+fn name_and_year_body(family_name: &str, birth_year: Option<i32>, limit: usize) -> Value {
+    let mut should = Vec::new();
+    if let Some(year) = birth_year {
+        should.push(json!({ "term": { "birth_year": year } }));
+    }
+
+    json!({
+        "size": limit,
+        "query": {
+            "bool": {
+                "must": [{ "multi_match": {
+                    "query": family_name,
+                    "fields": ["family_name", "maiden_name"],
+                    "fuzziness": 2
+                } }],
+                "should": should
+            }
+        }
+    })
+}
+
+/// Documents sharing any key, those sharing the most first
+fn blocking_keys_body(keys: &[String], limit: usize) -> Value {
+    let should: Vec<Value> = keys.iter().map(|key| json!({ "term": { "blocking_keys": key } })).collect();
+    json!({
+        "size": limit,
+        "query": { "bool": { "should": should, "minimum_should_match": 1 } }
+    })
+}
+
+fn organization_body(patient_ids: &[String], organization: &Uuid) -> Value {
+    json!({
+        "size": patient_ids.len(),
+        "_source": false,
+        "query": {
+            "bool": {
+                "filter": [
+                    { "ids": { "values": patient_ids } },
+                    { "term": { "managing_organization": organization.to_string() } }
+                ]
+            }
+        }
+    })
+}
+
+fn telecom_body(terms: &[String], limit: usize) -> Value {
+    let must: Vec<Value> = terms.iter().map(|term| json!({ "term": { "telecom": term } })).collect();
+    json!({
+        "size": limit,
+        "query": { "bool": { "must": must } }
+    })
+}
+
+fn parse_hits(response: &Value) -> Vec<SearchHit> {
+    response["hits"]["hits"]
+        .as_array()
+        .map(|hits| {
+            hits.iter()
+                .filter_map(|hit| {
+                    let patient_id = hit["_id"].as_str()?.to_string();
+                    let highlights: HashMap<String, String> = hit["highlight"]
+                        .as_object()
+                        .map(|fields| {
+                            fields
+                                .iter()
+                                .filter_map(|(field, fragments)| {
+                                    Some((field.clone(), fragments.get(0)?.as_str()?.to_string()))
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default();
+
+                    Some(SearchHit {
+                        patient_id,
+                        score: hit["_score"].as_f64().unwrap_or(0.0) as f32,
+                        highlights,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Gender, HumanName, Identifier};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_patient_document() {
+        let mut patient = Patient::new(
+            HumanName {
+                use_type: None,
+                family: "Nakamura".to_string(),
+                given: vec!["Yuki".to_string()],
+                prefix: vec![],
+                suffix: vec![],
+            },
+            Gender::Female,
+        );
+        patient.birth_date = NaiveDate::from_ymd_opt(1990, 4, 2);
+        patient.identifiers.push(Identifier::mrn("MAIN".to_string(), "12345".to_string()));
+
+        let doc = patient_document(&patient);
+
+        assert_eq!(doc["full_name"], "Yuki Nakamura");
+        assert_eq!(doc["birth_year"], 1990);
+        assert_eq!(doc["gender"], "female");
+        assert_eq!(doc["identifiers"], "MRN:12345");
+        assert_eq!(doc["name_use"], json!(["official"]));
+    }
+
+    #[test]
+    fn test_search_body_applies_boosts() {
+        let body = search_body("smith", 5, &FieldBoosts::default());
+
+        assert_eq!(body["size"], 5);
+        assert_eq!(body["query"]["multi_match"]["fields"][0], "identifiers^3");
+    }
+
+    #[test]
+    fn test_fuzzy_search_body_applies_options() {
+        let options = FuzzyOptions {
+            distance: 1,
+            prefix_length: 2,
+            transpositions: false,
+            fields: vec![FuzzyField::GivenNames, FuzzyField::Identifiers],
+        };
+        let body = fuzzy_search_body("jon", &options, 10);
+        let multi_match = &body["query"]["multi_match"];
+
+        assert_eq!(multi_match["fields"], json!(["given_names", "identifiers"]));
+        assert_eq!(multi_match["fuzziness"], "1");
+        assert_eq!(multi_match["prefix_length"], 2);
+        assert_eq!(multi_match["fuzzy_transpositions"], false);
+    }
+
+    #[test]
+    fn test_parse_hits() {
+        let response = json!({
+            "hits": { "hits": [
+                { "_id": "a", "_score": 2.5, "highlight": { "full_name": ["John <b>Smith</b>"] } },
+                { "_id": "b", "_score": 1.0 }
+            ] }
+        });
+
+        let hits = parse_hits(&response);
+
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].patient_id, "a");
+        assert_eq!(hits[0].highlights["full_name"], "John <b>Smith</b>");
+        assert!(hits[1].highlights.is_empty());
+    }
+}
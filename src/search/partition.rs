@@ -0,0 +1,239 @@
+//! Hot/cold partitioning of the search index
+//!
+//! Installations with tens of millions of historical records spend most of
+//! each interactive lookup scanning patients who are inactive or have died.
+//! [`PartitionedIndex`] keeps active, living patients in a hot index and the
+//! rest in a cold one. Searches query the hot partition first and only ask
+//! the cold partition for the results still missing, so a lookup that the
+//! hot partition answers never touches the historical records.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::models::Patient;
+use crate::Result;
+use super::backend::SearchBackend;
+use super::{FuzzyOptions, IndexStats, SearchHit, SnapshotInfo, Suggestion};
+
+/// Snapshot subdirectory of each partition
+const HOT_DIR: &str = "hot";
+const COLD_DIR: &str = "cold";
+
+/// Whether a patient belongs in the hot partition
+pub fn is_hot(patient: &Patient) -> bool {
+    patient.active && !patient.deceased && patient.deceased_datetime.is_none()
+}
+
+/// Search backend split into a hot partition of active, living patients and
+/// a cold partition of everyone else
+///
+/// A patient is indexed in one partition and removed from the other, so
+/// one who is deactivated or dies moves to the cold partition with the
+/// next change.
+pub struct PartitionedIndex {
+    hot: Arc<dyn SearchBackend>,
+    cold: Arc<dyn SearchBackend>,
+}
+
+impl PartitionedIndex {
+    /// Partition across two backends of the same kind
+    pub fn new(hot: Arc<dyn SearchBackend>, cold: Arc<dyn SearchBackend>) -> Self {
+        Self { hot, cold }
+    }
+
+    /// Hot results, topped up from the cold partition up to `limit`
+    fn hot_first<T>(
+        &self,
+        limit: usize,
+        search: impl Fn(&dyn SearchBackend, usize) -> Result<Vec<T>>,
+    ) -> Result<Vec<T>> {
+        let mut results = search(self.hot.as_ref(), limit)?;
+        if results.len() < limit {
+            results.extend(search(self.cold.as_ref(), limit - results.len())?);
+        }
+        Ok(results)
+    }
+}
+
+impl SearchBackend for PartitionedIndex {
+    fn name(&self) -> &'static str {
+        self.hot.name()
+    }
+
+    fn index_patient(&self, patient: &Patient) -> Result<()> {
+        self.apply_changes(std::slice::from_ref(patient), &[])
+    }
+
+    fn index_patients(&self, patients: &[Patient]) -> Result<()> {
+        self.apply_changes(patients, &[])
+    }
+
+    fn apply_changes(&self, upserts: &[Patient], removals: &[String]) -> Result<()> {
+        let (hot, cold): (Vec<Patient>, Vec<Patient>) = upserts.iter().cloned().partition(is_hot);
+        let ids = |patients: &[Patient]| patients.iter().map(|p| p.id.to_string()).collect::<Vec<_>>();
+
+        // Each partition drops the patients moving to the other
+        let hot_removals: Vec<String> = removals.iter().cloned().chain(ids(&cold)).collect();
+        let cold_removals: Vec<String> = removals.iter().cloned().chain(ids(&hot)).collect();
+        if !hot.is_empty() || !hot_removals.is_empty() {
+            self.hot.apply_changes(&hot, &hot_removals)?;
+        }
+        if !cold.is_empty() || !cold_removals.is_empty() {
+            self.cold.apply_changes(&cold, &cold_removals)?;
+        }
+        Ok(())
+    }
+
+    fn delete_patient(&self, patient_id: &str) -> Result<()> {
+        self.hot.delete_patient(patient_id)?;
+        self.cold.delete_patient(patient_id)
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.hot.clear()?;
+        self.cold.clear()
+    }
+
+    fn search_with_scores(&self, query_str: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        self.hot_first(limit, |backend, limit| backend.search_with_scores(query_str, limit))
+    }
+
+    fn fuzzy_search_with_scores(&self, query_str: &str, options: &FuzzyOptions, limit: usize) -> Result<Vec<SearchHit>> {
+        self.hot_first(limit, |backend, limit| backend.fuzzy_search_with_scores(query_str, options, limit))
+    }
+
+    fn search_by_name_and_year(
+        &self,
+        family_name: &str,
+        birth_year: Option<i32>,
+        limit: usize,
+    ) -> Result<Vec<String>> {
+        self.hot_first(limit, |backend, limit| backend.search_by_name_and_year(family_name, birth_year, limit))
+    }
+
+    fn search_by_blocking_keys(&self, keys: &[String], limit: usize) -> Result<Vec<String>> {
+        self.hot_first(limit, |backend, limit| backend.search_by_blocking_keys(keys, limit))
+    }
+
+    fn search_by_telecom(&self, phone: Option<&str>, email: Option<&str>, limit: usize) -> Result<Vec<SearchHit>> {
+        self.hot_first(limit, |backend, limit| backend.search_by_telecom(phone, email, limit))
+    }
+
+    fn in_organization(&self, patient_ids: &[String], organization: &Uuid) -> Result<HashSet<String>> {
+        let mut scoped = self.hot.in_organization(patient_ids, organization)?;
+        scoped.extend(self.cold.in_organization(patient_ids, organization)?);
+        Ok(scoped)
+    }
+
+    /// Suggestions from both partitions, with the patient counts of a term
+    /// added together
+    fn suggest(&self, query_str: &str, limit: usize) -> Result<Vec<Suggestion>> {
+        let mut merged: HashMap<String, Suggestion> = HashMap::new();
+        for suggestion in self.hot.suggest(query_str, limit)?.into_iter().chain(self.cold.suggest(query_str, limit)?) {
+            merged
+                .entry(suggestion.term.clone())
+                .and_modify(|existing| existing.doc_freq += suggestion.doc_freq)
+                .or_insert(suggestion);
+        }
+
+        // Closest first, then most common, as a single index orders them
+        let mut suggestions: Vec<Suggestion> = merged.into_values().collect();
+        suggestions.sort_by(|a, b| {
+            a.distance
+                .cmp(&b.distance)
+                .then(b.doc_freq.cmp(&a.doc_freq))
+                .then(a.term.cmp(&b.term))
+        });
+        suggestions.truncate(limit);
+        Ok(suggestions)
+    }
+
+    fn stats(&self) -> Result<IndexStats> {
+        let hot = self.hot.stats()?;
+        let cold = self.cold.stats()?;
+        Ok(IndexStats {
+            num_docs: hot.num_docs + cold.num_docs,
+            num_segments: hot.num_segments + cold.num_segments,
+        })
+    }
+
+    fn patient_ids(&self) -> Result<HashSet<String>> {
+        let mut ids = self.hot.patient_ids()?;
+        ids.extend(self.cold.patient_ids()?);
+        Ok(ids)
+    }
+
+    /// Snapshot each partition into its own subdirectory of `path`
+    fn snapshot(&self, path: &Path) -> Result<SnapshotInfo> {
+        let hot = self.hot.snapshot(&path.join(HOT_DIR))?;
+        let cold = self.cold.snapshot(&path.join(COLD_DIR))?;
+        Ok(combined(path, hot, cold))
+    }
+
+    /// Restore each partition from its subdirectory of `path`
+    fn restore(&self, path: &Path) -> Result<SnapshotInfo> {
+        let hot = self.hot.restore(&path.join(HOT_DIR))?;
+        let cold = self.cold.restore(&path.join(COLD_DIR))?;
+        Ok(combined(path, hot, cold))
+    }
+
+    fn reload(&self) -> Result<()> {
+        self.hot.reload()?;
+        self.cold.reload()
+    }
+}
+
+fn combined(path: &Path, hot: SnapshotInfo, cold: SnapshotInfo) -> SnapshotInfo {
+    SnapshotInfo {
+        path: path.to_string_lossy().to_string(),
+        num_docs: hot.num_docs + cold.num_docs,
+        num_segments: hot.num_segments + cold.num_segments,
+        num_files: hot.num_files + cold.num_files,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::patient;
+    use crate::models::Gender;
+    use crate::search::SearchEngine;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_patients_move_between_partitions() {
+        let hot_dir = TempDir::new().unwrap();
+        let cold_dir = TempDir::new().unwrap();
+        let hot = Arc::new(SearchEngine::new(hot_dir.path()).unwrap());
+        let cold = Arc::new(SearchEngine::new(cold_dir.path()).unwrap());
+        let index = PartitionedIndex::new(hot.clone(), cold.clone());
+
+        let living = patient("Lindqvist", &["Sam"], Gender::Unknown);
+        let mut deceased = patient("Lindqvist", &["Sam"], Gender::Unknown);
+        deceased.deceased = true;
+        index.index_patients(&[living.clone(), deceased.clone()]).unwrap();
+        index.reload().unwrap();
+
+        assert_eq!(hot.search("Lindqvist", 10).unwrap(), vec![living.id.to_string()]);
+        assert_eq!(cold.search("Lindqvist", 10).unwrap(), vec![deceased.id.to_string()]);
+
+        // Hot results come first, topped up from the cold partition
+        assert_eq!(index.search("Lindqvist", 1).unwrap(), vec![living.id.to_string()]);
+        assert_eq!(
+            index.search("Lindqvist", 10).unwrap(),
+            vec![living.id.to_string(), deceased.id.to_string()]
+        );
+
+        let mut inactive = living.clone();
+        inactive.active = false;
+        index.index_patient(&inactive).unwrap();
+        index.reload().unwrap();
+        assert!(hot.search("Lindqvist", 10).unwrap().is_empty());
+        assert_eq!(cold.search("Lindqvist", 10).unwrap().len(), 2);
+        assert_eq!(index.stats().unwrap().num_docs, 2);
+    }
+}
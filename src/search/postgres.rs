@@ -0,0 +1,369 @@
+//! Postgres trigram search backend
+//!
+//! Searches the patient tables directly with `pg_trgm`, so nothing has to be
+//! persisted outside the database. Intended for deployments that cannot keep
+//! a local Tantivy directory, such as ephemeral containers without a shared
+//! volume. Relies on the trigram indexes from the
+//! `2024122800000007_add_trigram_search` migration.
+//!
+//! The patient tables are the index, so the indexing operations are no-ops.
+
+use std::collections::{HashMap, HashSet};
+
+use diesel::prelude::*;
+use diesel::sql_types::{Array, BigInt, Bool, Float4, Integer, Nullable, Text, Uuid as SqlUuid};
+use uuid::Uuid;
+
+use crate::db::{get_connection, DbPool};
+use crate::models::Patient;
+use crate::validation::{email_key, phone_key};
+use crate::Result;
+use super::backend::SearchBackend;
+use super::{telecom_query_terms, FuzzyField, FuzzyOptions, SearchHit, Suggestion};
+
+/// Names whose words closely match the query, plus exact identifier matches
+const SEARCH_SQL: &str = "
+    SELECT patient_id, MAX(score) AS score FROM (
+        SELECT n.patient_id::text AS patient_id,
+               word_similarity($1, patient_name_text(n.family, n.given)) AS score
+        FROM patient_names n
+        JOIN patients p ON p.id = n.patient_id
+        WHERE p.deleted_at IS NULL
+          AND $1 <% patient_name_text(n.family, n.given)
+        UNION ALL
+        SELECT i.patient_id::text, 1.0::real
+        FROM patient_identifiers i
+        JOIN patients p ON p.id = i.patient_id
+        WHERE p.deleted_at IS NULL
+          AND lower(i.value) = $1
+    ) hits
+    GROUP BY patient_id
+    ORDER BY score DESC, patient_id
+    LIMIT $2";
+
+/// Names and identifiers within an edit distance of a query word
+///
+/// `$3` is the largest distance, `$4` the prefix length that must match
+/// exactly, and `$5`, `$6` and `$7` switch the family name, given name and
+/// identifier branches on. Relies on `levenshtein` from the
+/// `2024122800000019_add_fuzzy_distance` migration.
+const FUZZY_SEARCH_SQL: &str = "
+    SELECT patient_id, MAX(score) AS score FROM (
+        SELECT n.patient_id::text AS patient_id,
+               similarity(lower(n.family), w.word) AS score
+        FROM patient_names n
+        JOIN patients p ON p.id = n.patient_id
+        CROSS JOIN unnest(string_to_array($1, ' ')) AS w(word)
+        WHERE $5 AND p.deleted_at IS NULL
+          AND levenshtein(lower(n.family), w.word) <= $3
+          AND left(lower(n.family), $4) = left(w.word, $4)
+        UNION ALL
+        SELECT n.patient_id::text, similarity(lower(g.name), w.word)
+        FROM patient_names n
+        JOIN patients p ON p.id = n.patient_id
+        CROSS JOIN unnest(n.given) AS g(name)
+        CROSS JOIN unnest(string_to_array($1, ' ')) AS w(word)
+        WHERE $6 AND p.deleted_at IS NULL
+          AND levenshtein(lower(g.name), w.word) <= $3
+          AND left(lower(g.name), $4) = left(w.word, $4)
+        UNION ALL
+        SELECT i.patient_id::text, similarity(lower(i.value), w.word)
+        FROM patient_identifiers i
+        JOIN patients p ON p.id = i.patient_id
+        CROSS JOIN unnest(string_to_array($1, ' ')) AS w(word)
+        WHERE $7 AND p.deleted_at IS NULL
+          AND levenshtein(lower(i.value), w.word) <= $3
+          AND left(lower(i.value), $4) = left(w.word, $4)
+    ) hits
+    GROUP BY patient_id
+    ORDER BY score DESC, patient_id
+    LIMIT $2";
+
+/// Similar family names, optionally restricted to a birth year
+const NAME_AND_YEAR_SQL: &str = "
+    SELECT n.patient_id::text AS patient_id,
+           MAX(similarity(lower(n.family), $1)) AS score
+    FROM patient_names n
+    JOIN patients p ON p.id = n.patient_id
+    WHERE p.deleted_at IS NULL
+      AND lower(n.family) % $1
+      AND ($2::int IS NULL OR EXTRACT(YEAR FROM p.birth_date)::int = $2)
+    GROUP BY n.patient_id
+    ORDER BY score DESC, patient_id
+    LIMIT $3";
+
+/// Patients with a contact point for each given phone key and email
+const TELECOM_SQL: &str = "
+    SELECT p.id::text AS patient_id, 1.0::real AS score
+    FROM patients p
+    WHERE p.deleted_at IS NULL
+      AND ($1::text IS NULL OR EXISTS (
+            SELECT 1 FROM patient_contacts c
+            WHERE c.patient_id = p.id
+              AND c.system IN ('Phone', 'Sms')
+              AND right(regexp_replace(c.value, '[^0-9]', '', 'g'), 10) = $1))
+      AND ($2::text IS NULL OR EXISTS (
+            SELECT 1 FROM patient_contacts c
+            WHERE c.patient_id = p.id
+              AND c.system = 'Email'
+              AND lower(trim(c.value)) = $2))
+    ORDER BY p.id
+    LIMIT $3";
+
+/// The given patients managed by an organization
+const ORGANIZATION_SQL: &str = "
+    SELECT p.id::text AS patient_id, 1.0::real AS score
+    FROM patients p
+    WHERE p.id = ANY($1)
+      AND p.managing_organization_id = $2";
+
+/// Family names that start with or resemble the query
+const SUGGEST_SQL: &str = "
+    SELECT lower(n.family) AS term, COUNT(DISTINCT n.patient_id) AS doc_freq
+    FROM patient_names n
+    JOIN patients p ON p.id = n.patient_id
+    WHERE p.deleted_at IS NULL
+      AND (starts_with(lower(n.family), $1) OR lower(n.family) % $1)
+    GROUP BY lower(n.family)
+    ORDER BY MAX(similarity(lower(n.family), $1)) DESC
+    LIMIT $2";
+
+#[derive(QueryableByName)]
+struct ScoredRow {
+    #[diesel(sql_type = Text)]
+    patient_id: String,
+    #[diesel(sql_type = Float4)]
+    score: f32,
+}
+
+#[derive(QueryableByName)]
+struct TermRow {
+    #[diesel(sql_type = Text)]
+    term: String,
+    #[diesel(sql_type = BigInt)]
+    doc_freq: i64,
+}
+
+/// Search backend that queries Postgres with trigram similarity
+pub struct PostgresSearchBackend {
+    pool: DbPool,
+}
+
+impl PostgresSearchBackend {
+    /// Create a backend that searches through the given pool
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    fn scored_search(&self, sql: &str, query_str: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        let query = normalize_query(query_str);
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = get_connection(&self.pool)?;
+        let rows: Vec<ScoredRow> = diesel::sql_query(sql)
+            .bind::<Text, _>(&query)
+            .bind::<BigInt, _>(limit as i64)
+            .load(&mut conn)?;
+
+        Ok(rows.into_iter().map(to_hit).collect())
+    }
+}
+
+impl SearchBackend for PostgresSearchBackend {
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+
+    fn index_patient(&self, _patient: &Patient) -> Result<()> {
+        Ok(())
+    }
+
+    fn index_patients(&self, _patients: &[Patient]) -> Result<()> {
+        Ok(())
+    }
+
+    fn apply_changes(&self, _upserts: &[Patient], _removals: &[String]) -> Result<()> {
+        Ok(())
+    }
+
+    fn delete_patient(&self, _patient_id: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn search_with_scores(&self, query_str: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        self.scored_search(SEARCH_SQL, query_str, limit)
+    }
+
+    /// Levenshtein distance counts a swap of adjacent characters as two
+    /// edits, so `options.transpositions` is not honoured here
+    fn fuzzy_search_with_scores(&self, query_str: &str, options: &FuzzyOptions, limit: usize) -> Result<Vec<SearchHit>> {
+        options.validate()?;
+        let query = normalize_query(query_str);
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = get_connection(&self.pool)?;
+        let rows: Vec<ScoredRow> = diesel::sql_query(FUZZY_SEARCH_SQL)
+            .bind::<Text, _>(&query)
+            .bind::<BigInt, _>(limit as i64)
+            .bind::<Integer, _>(i32::from(options.distance))
+            .bind::<Integer, _>(options.prefix_length.min(i32::MAX as usize) as i32)
+            .bind::<Bool, _>(options.fields.contains(&FuzzyField::FamilyName))
+            .bind::<Bool, _>(options.fields.contains(&FuzzyField::GivenNames))
+            .bind::<Bool, _>(options.fields.contains(&FuzzyField::Identifiers))
+            .load(&mut conn)?;
+
+        Ok(rows.into_iter().map(to_hit).collect())
+    }
+
+    fn search_by_name_and_year(
+        &self,
+        family_name: &str,
+        birth_year: Option<i32>,
+        limit: usize,
+    ) -> Result<Vec<String>> {
+        let family_name = normalize_query(family_name);
+        if family_name.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = get_connection(&self.pool)?;
+        let rows: Vec<ScoredRow> = diesel::sql_query(NAME_AND_YEAR_SQL)
+            .bind::<Text, _>(&family_name)
+            .bind::<Nullable<Integer>, _>(birth_year)
+            .bind::<BigInt, _>(limit as i64)
+            .load(&mut conn)?;
+
+        Ok(rows.into_iter().map(|row| row.patient_id).collect())
+    }
+
+    fn search_by_telecom(&self, phone: Option<&str>, email: Option<&str>, limit: usize) -> Result<Vec<SearchHit>> {
+        if telecom_query_terms(phone, email).is_none() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = get_connection(&self.pool)?;
+        let rows: Vec<ScoredRow> = diesel::sql_query(TELECOM_SQL)
+            .bind::<Nullable<Text>, _>(phone.and_then(phone_key))
+            .bind::<Nullable<Text>, _>(email.and_then(email_key))
+            .bind::<BigInt, _>(limit as i64)
+            .load(&mut conn)?;
+
+        Ok(rows.into_iter().map(to_hit).collect())
+    }
+
+    fn in_organization(&self, patient_ids: &[String], organization: &Uuid) -> Result<HashSet<String>> {
+        let ids: Vec<Uuid> = patient_ids.iter().filter_map(|id| Uuid::parse_str(id).ok()).collect();
+        if ids.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        let mut conn = get_connection(&self.pool)?;
+        let rows: Vec<ScoredRow> = diesel::sql_query(ORGANIZATION_SQL)
+            .bind::<Array<SqlUuid>, _>(&ids)
+            .bind::<SqlUuid, _>(organization)
+            .load(&mut conn)?;
+
+        Ok(rows.into_iter().map(|row| row.patient_id).collect())
+    }
+
+    fn suggest(&self, query_str: &str, limit: usize) -> Result<Vec<Suggestion>> {
+        let query = normalize_query(query_str);
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = get_connection(&self.pool)?;
+        let rows: Vec<TermRow> = diesel::sql_query(SUGGEST_SQL)
+            .bind::<Text, _>(&query)
+            .bind::<BigInt, _>(limit as i64)
+            .load(&mut conn)?;
+
+        let mut suggestions: Vec<Suggestion> = rows
+            .into_iter()
+            .map(|row| {
+                let distance = if row.term.starts_with(&query) {
+                    0
+                } else {
+                    strsim::levenshtein(&query, &row.term)
+                };
+                Suggestion {
+                    term: row.term,
+                    doc_freq: row.doc_freq.try_into().unwrap_or(u32::MAX),
+                    distance,
+                }
+            })
+            .collect();
+
+        suggestions.sort_by(|a, b| {
+            a.distance
+                .cmp(&b.distance)
+                .then(b.doc_freq.cmp(&a.doc_freq))
+                .then(a.term.cmp(&b.term))
+        });
+        Ok(suggestions)
+    }
+}
+
+/// Lowercase and collapse whitespace to match the indexed name text
+fn normalize_query(query_str: &str) -> String {
+    query_str
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn to_hit(row: ScoredRow) -> SearchHit {
+    SearchHit {
+        patient_id: row.patient_id,
+        score: row.score,
+        highlights: HashMap::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::r2d2::{ConnectionManager, Pool};
+
+    fn unconnected_backend() -> PostgresSearchBackend {
+        let manager = ConnectionManager::new("postgres://localhost/unused");
+        PostgresSearchBackend::new(Pool::builder().build_unchecked(manager))
+    }
+
+    #[test]
+    fn test_normalize_query() {
+        assert_eq!(normalize_query("  Smith \t John "), "smith john");
+        assert_eq!(normalize_query("MRN-001"), "mrn-001");
+        assert_eq!(normalize_query("   "), "");
+    }
+
+    #[test]
+    fn test_blank_queries_do_not_hit_the_database() {
+        let backend = unconnected_backend();
+
+        assert!(backend.search("  ", 10).unwrap().is_empty());
+        assert!(backend.fuzzy_search("", &FuzzyOptions::default(), 10).unwrap().is_empty());
+        assert!(backend.search_by_name_and_year(" ", Some(1980), 10).unwrap().is_empty());
+        assert!(backend.suggest("", 10).unwrap().is_empty());
+        assert!(backend.search_by_telecom(Some("555"), None, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_indexing_is_a_no_op() {
+        let backend = unconnected_backend();
+
+        assert_eq!(backend.name(), "postgres");
+        assert!(backend.delete_patient("anything").is_ok());
+        assert!(backend.clear().is_ok());
+        assert!(backend.snapshot(std::path::Path::new("/tmp/unused")).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! Search index as a projection of patient events
+
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::models::Patient;
+use crate::streaming::replay::Projection;
+use crate::streaming::PatientEvent;
+use crate::Result;
+use super::SearchBackend;
+
+/// Number of pending changes buffered before they are committed
+const FLUSH_THRESHOLD: usize = 1000;
+
+/// Rebuilds the search index from patient events
+///
+/// Changes are buffered by patient and committed in batches, so a patient
+/// updated many times during replay is only written once per batch.
+pub struct SearchIndexProjection<'a> {
+    engine: &'a dyn SearchBackend,
+    /// Latest state per patient; `None` means remove from the index
+    pending: HashMap<Uuid, Option<Patient>>,
+}
+
+impl<'a> SearchIndexProjection<'a> {
+    /// Create a projection that writes to the given search backend
+    pub fn new(engine: &'a dyn SearchBackend) -> Self {
+        Self {
+            engine,
+            pending: HashMap::new(),
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut upserts = Vec::new();
+        let mut removals = Vec::new();
+        for (patient_id, patient) in self.pending.drain() {
+            match patient {
+                Some(patient) => upserts.push(patient),
+                None => removals.push(patient_id.to_string()),
+            }
+        }
+
+        self.engine.apply_changes(&upserts, &removals)
+    }
+}
+
+impl Projection for SearchIndexProjection<'_> {
+    fn name(&self) -> &str {
+        "search_index"
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.pending.clear();
+        self.engine.clear()
+    }
+
+    fn apply(&mut self, event: &PatientEvent) -> Result<()> {
+        match event {
+            PatientEvent::Created { patient, .. } | PatientEvent::Updated { patient, .. } => {
+                self.pending.insert(patient.id, Some(patient.clone()));
+            }
+            PatientEvent::Deleted { patient_id, .. } => {
+                self.pending.insert(*patient_id, None);
+            }
+            // The merged-away record is no longer searchable
+            PatientEvent::Merged { source_id, .. } => {
+                self.pending.insert(*source_id, None);
+            }
+            PatientEvent::Linked { .. } | PatientEvent::Unlinked { .. } => {}
+        }
+
+        if self.pending.len() >= FLUSH_THRESHOLD {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.flush()?;
+        self.engine.reload()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::patient;
+    use crate::models::Gender;
+    use crate::search::SearchEngine;
+    use crate::streaming::replay::{replay, ReplayStart};
+    use crate::streaming::InMemoryEventPublisher;
+    use crate::streaming::EventProducer;
+    use chrono::{Duration, Utc};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_replay_rebuilds_search_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+        let events = InMemoryEventPublisher::new();
+        let now = Utc::now();
+
+        let mut kept = patient("Okafor", &["Alex"], Gender::Unknown);
+        let removed = patient("Lindqvist", &["Alex"], Gender::Unknown);
+        events.publish(PatientEvent::Created { patient: kept.clone(), timestamp: now }).unwrap();
+        events.publish(PatientEvent::Created { patient: removed.clone(), timestamp: now }).unwrap();
+        kept.name.family = "Okafor-Reyes".to_string();
+        events.publish(PatientEvent::Updated { patient: kept.clone(), timestamp: now }).unwrap();
+        events.publish(PatientEvent::Deleted { patient_id: removed.id, timestamp: now }).unwrap();
+
+        // Stale content that the rebuild should discard
+        engine.index_patient(&patient("Stale", &["Alex"], Gender::Unknown)).unwrap();
+
+        let mut projection = SearchIndexProjection::new(&engine);
+        let report = replay(&events, ReplayStart::Beginning, &mut [&mut projection]).unwrap();
+
+        assert!(report.full_rebuild);
+        assert_eq!(report.events_replayed, 4);
+        assert_eq!(engine.stats().unwrap().num_docs, 1);
+        assert_eq!(engine.search("Reyes", 10).unwrap(), vec![kept.id.to_string()]);
+        assert!(engine.search("Lindqvist", 10).unwrap().is_empty());
+        assert!(engine.search("Stale", 10).unwrap().is_empty());
+
+        // Replaying from a later time applies only newer events, in place
+        let later = patient("Abara", &["Alex"], Gender::Unknown);
+        events.publish(PatientEvent::Created { patient: later.clone(), timestamp: now + Duration::minutes(5) }).unwrap();
+
+        let mut projection = SearchIndexProjection::new(&engine);
+        let start = ReplayStart::Timestamp(now + Duration::minutes(1));
+        let report = replay(&events, start, &mut [&mut projection]).unwrap();
+
+        assert!(!report.full_rebuild);
+        assert_eq!(report.events_replayed, 1);
+        assert_eq!(engine.stats().unwrap().num_docs, 2);
+    }
+
+    #[test]
+    fn test_unsupported_replay_keeps_search_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+        engine.index_patient(&patient("Okafor", &["Alex"], Gender::Unknown)).unwrap();
+        engine.reload().unwrap();
+
+        let mut projection = SearchIndexProjection::new(&engine);
+        let source = crate::streaming::consumer::FluvioConsumer {};
+        let result = replay(&source, ReplayStart::Beginning, &mut [&mut projection]);
+
+        assert!(matches!(result, Err(crate::Error::Unsupported(_))));
+        assert_eq!(engine.stats().unwrap().num_docs, 1);
+    }
+}
@@ -0,0 +1,672 @@
+//! Shared search index across API replicas
+//!
+//! The Tantivy index lives on local disk, so replicas that each index the
+//! writes they happen to serve drift apart. With replication enabled, one
+//! writer instance owns the index: it applies the patient event stream and
+//! periodically publishes snapshots to a directory shared by all replicas.
+//! Readers skip local indexing and restore the newest published snapshot
+//! whenever it changes.
+//!
+//! The writer is either fixed in configuration or elected by holding a
+//! Postgres advisory lock, so a replacement takes over if it goes away.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use diesel::sql_types::{BigInt, Bool};
+use diesel::PgConnection;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::config::{IndexReplicationConfig, IndexRole};
+use crate::db::{get_connection, DbPool};
+use crate::models::Patient;
+use crate::streaming::replay::{replay, EventSource, ReplayStart};
+use crate::{Error, Result};
+use super::backend::SearchBackend;
+use super::{FuzzyOptions, IndexStats, SearchHit, SearchIndexProjection, SnapshotInfo, Suggestion};
+
+/// File in the snapshot directory naming the newest generation
+const LATEST_FILE: &str = "LATEST";
+
+/// File in each generation recording how far into the event stream it is
+const MANIFEST_FILE: &str = "replication.json";
+
+/// Generations kept, so readers mid-restore never lose their source
+const KEEP_GENERATIONS: usize = 3;
+
+/// Search backend whose writes only take effect on the writer instance
+///
+/// Readers receive index changes through published snapshots, so the
+/// indexing calls made by request handlers are skipped there.
+pub struct ReplicatedIndex {
+    inner: Arc<dyn SearchBackend>,
+    writer: AtomicBool,
+}
+
+impl ReplicatedIndex {
+    /// Wrap a local backend, starting in the given role
+    pub fn new(inner: Arc<dyn SearchBackend>, is_writer: bool) -> Self {
+        Self {
+            inner,
+            writer: AtomicBool::new(is_writer),
+        }
+    }
+
+    /// Whether this instance currently owns the index
+    pub fn is_writer(&self) -> bool {
+        self.writer.load(Ordering::SeqCst)
+    }
+
+    /// Promote to writer or demote to reader
+    pub fn set_writer(&self, is_writer: bool) {
+        self.writer.store(is_writer, Ordering::SeqCst);
+    }
+}
+
+impl SearchBackend for ReplicatedIndex {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn index_patient(&self, patient: &Patient) -> Result<()> {
+        if self.is_writer() {
+            self.inner.index_patient(patient)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn index_patients(&self, patients: &[Patient]) -> Result<()> {
+        if self.is_writer() {
+            self.inner.index_patients(patients)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn apply_changes(&self, upserts: &[Patient], removals: &[String]) -> Result<()> {
+        if self.is_writer() {
+            self.inner.apply_changes(upserts, removals)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn delete_patient(&self, patient_id: &str) -> Result<()> {
+        if self.is_writer() {
+            self.inner.delete_patient(patient_id)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn clear(&self) -> Result<()> {
+        if self.is_writer() {
+            self.inner.clear()
+        } else {
+            Ok(())
+        }
+    }
+
+    fn search(&self, query_str: &str, limit: usize) -> Result<Vec<String>> {
+        self.inner.search(query_str, limit)
+    }
+
+    fn search_with_scores(&self, query_str: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        self.inner.search_with_scores(query_str, limit)
+    }
+
+    fn fuzzy_search(&self, query_str: &str, options: &FuzzyOptions, limit: usize) -> Result<Vec<String>> {
+        self.inner.fuzzy_search(query_str, options, limit)
+    }
+
+    fn fuzzy_search_with_scores(&self, query_str: &str, options: &FuzzyOptions, limit: usize) -> Result<Vec<SearchHit>> {
+        self.inner.fuzzy_search_with_scores(query_str, options, limit)
+    }
+
+    fn search_by_name_and_year(
+        &self,
+        family_name: &str,
+        birth_year: Option<i32>,
+        limit: usize,
+    ) -> Result<Vec<String>> {
+        self.inner.search_by_name_and_year(family_name, birth_year, limit)
+    }
+
+    fn search_by_blocking_keys(&self, keys: &[String], limit: usize) -> Result<Vec<String>> {
+        self.inner.search_by_blocking_keys(keys, limit)
+    }
+
+    fn in_organization(&self, patient_ids: &[String], organization: &Uuid) -> Result<HashSet<String>> {
+        self.inner.in_organization(patient_ids, organization)
+    }
+
+    fn suggest(&self, query_str: &str, limit: usize) -> Result<Vec<Suggestion>> {
+        self.inner.suggest(query_str, limit)
+    }
+
+    fn stats(&self) -> Result<IndexStats> {
+        self.inner.stats()
+    }
+
+    fn patient_ids(&self) -> Result<HashSet<String>> {
+        self.inner.patient_ids()
+    }
+
+    fn snapshot(&self, path: &Path) -> Result<SnapshotInfo> {
+        self.inner.snapshot(path)
+    }
+
+    fn restore(&self, path: &Path) -> Result<SnapshotInfo> {
+        self.inner.restore(path)
+    }
+
+    fn reload(&self) -> Result<()> {
+        self.inner.reload()
+    }
+}
+
+/// Position of a published snapshot in the event stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SnapshotManifest {
+    generation: String,
+    last_event_at: Option<DateTime<Utc>>,
+}
+
+/// Published snapshots in a directory shared by all replicas
+///
+/// Each snapshot is written to its own generation directory before the
+/// `LATEST` pointer is atomically replaced, so readers never see a partial one.
+pub struct SnapshotStore {
+    dir: PathBuf,
+}
+
+impl SnapshotStore {
+    /// Use the given shared directory
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Directory holding a generation
+    pub fn path(&self, generation: &str) -> PathBuf {
+        self.dir.join(generation)
+    }
+
+    /// Name of the newest published generation, if any
+    pub fn latest(&self) -> Result<Option<String>> {
+        match std::fs::read_to_string(self.dir.join(LATEST_FILE)) {
+            Ok(contents) => Ok(Some(contents.trim().to_string()).filter(|g| !g.is_empty())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(Error::Search(format!("Failed to read latest snapshot pointer: {}", e))),
+        }
+    }
+
+    /// Snapshot the backend as a new generation and make it the latest
+    pub fn publish(
+        &self,
+        backend: &dyn SearchBackend,
+        last_event_at: Option<DateTime<Utc>>,
+    ) -> Result<(String, SnapshotInfo)> {
+        let generation = Utc::now().format("%Y%m%dT%H%M%S%.6fZ").to_string();
+        let path = self.path(&generation);
+        let info = backend.snapshot(&path)?;
+
+        let manifest = SnapshotManifest {
+            generation: generation.clone(),
+            last_event_at,
+        };
+        let manifest = serde_json::to_vec(&manifest)
+            .map_err(|e| Error::Search(format!("Failed to encode snapshot manifest: {}", e)))?;
+        std::fs::write(path.join(MANIFEST_FILE), manifest)
+            .map_err(|e| Error::Search(format!("Failed to write snapshot manifest: {}", e)))?;
+
+        let pointer = self.dir.join(format!("{}.tmp", LATEST_FILE));
+        std::fs::write(&pointer, &generation)
+            .and_then(|_| std::fs::rename(&pointer, self.dir.join(LATEST_FILE)))
+            .map_err(|e| Error::Search(format!("Failed to update latest snapshot pointer: {}", e)))?;
+
+        self.prune()?;
+        Ok((generation, info))
+    }
+
+    /// Event stream position recorded with a generation
+    fn manifest(&self, generation: &str) -> Result<SnapshotManifest> {
+        let bytes = std::fs::read(self.path(generation).join(MANIFEST_FILE))
+            .map_err(|e| Error::Search(format!("Failed to read snapshot manifest: {}", e)))?;
+        serde_json::from_slice(&bytes)
+            .map_err(|e| Error::Search(format!("Invalid snapshot manifest: {}", e)))
+    }
+
+    /// Remove all but the newest generations
+    fn prune(&self) -> Result<()> {
+        let entries = std::fs::read_dir(&self.dir)
+            .map_err(|e| Error::Search(format!("Failed to list snapshots: {}", e)))?;
+        let mut generations: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_dir())
+            .collect();
+        generations.sort();
+
+        let excess = generations.len().saturating_sub(KEEP_GENERATIONS);
+        for path in generations.into_iter().take(excess) {
+            std::fs::remove_dir_all(&path)
+                .map_err(|e| Error::Search(format!("Failed to remove old snapshot {:?}: {}", path, e)))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(QueryableByName)]
+struct LockRow {
+    #[diesel(sql_type = Bool)]
+    acquired: bool,
+}
+
+/// Writer election through a session-level Postgres advisory lock
+///
+/// The lock is held by a connection checked out of the pool for as long as
+/// this instance leads, and is released by Postgres if the instance dies.
+pub struct LeaderElection {
+    pool: DbPool,
+    lock_id: i64,
+    conn: Option<PooledConnection<ConnectionManager<PgConnection>>>,
+}
+
+impl LeaderElection {
+    /// Contend for the given advisory lock key
+    pub fn new(pool: DbPool, lock_id: i64) -> Self {
+        Self {
+            pool,
+            lock_id,
+            conn: None,
+        }
+    }
+
+    /// Try to become, or confirm still being, the leader
+    pub fn poll(&mut self) -> bool {
+        if let Some(conn) = self.conn.as_mut() {
+            if diesel::sql_query("SELECT 1").execute(conn).is_ok() {
+                return true;
+            }
+            tracing::warn!("Lost the connection holding the index writer lock");
+            self.conn = None;
+        }
+
+        match self.try_acquire() {
+            Ok(acquired) => acquired,
+            Err(e) => {
+                tracing::warn!("Index writer election failed: {}", e);
+                false
+            }
+        }
+    }
+
+    fn try_acquire(&mut self) -> Result<bool> {
+        let mut conn = get_connection(&self.pool)?;
+        let row: LockRow = diesel::sql_query("SELECT pg_try_advisory_lock($1) AS acquired")
+            .bind::<BigInt, _>(self.lock_id)
+            .get_result(&mut conn)?;
+        if row.acquired {
+            self.conn = Some(conn);
+        }
+        Ok(row.acquired)
+    }
+}
+
+/// How an instance decides whether it is the writer
+pub enum RoleSource {
+    /// Role fixed in configuration
+    Fixed(bool),
+    /// Writer while holding the advisory lock
+    Elected(Box<LeaderElection>),
+}
+
+impl RoleSource {
+    fn is_writer(&mut self) -> bool {
+        match self {
+            RoleSource::Fixed(is_writer) => *is_writer,
+            RoleSource::Elected(election) => election.poll(),
+        }
+    }
+}
+
+/// What one replication tick did
+#[derive(Debug, Clone, Default)]
+pub struct ReplicationTick {
+    /// Whether this instance was the writer during the tick
+    pub is_writer: bool,
+    /// Events replayed into the index when new ones arrived
+    ///
+    /// Includes the previously last event, since catch-up starts at its timestamp.
+    pub events_replayed: usize,
+    /// Generation published by the writer
+    pub published: Option<String>,
+    /// Generation restored from the shared directory
+    pub restored: Option<String>,
+}
+
+/// Drives replication: event catch-up and publishing on the writer,
+/// snapshot refresh on readers
+pub struct IndexReplicator {
+    index: Arc<ReplicatedIndex>,
+    store: SnapshotStore,
+    events: Arc<dyn EventSource>,
+    role: RoleSource,
+    publish_interval: Duration,
+    last_event_at: Option<DateTime<Utc>>,
+    bootstrapped: bool,
+    unpublished_changes: bool,
+    last_published: Option<Instant>,
+    restored_generation: Option<String>,
+}
+
+impl IndexReplicator {
+    /// Create a replicator for the index, snapshot store, and event topic
+    pub fn new(
+        index: Arc<ReplicatedIndex>,
+        store: SnapshotStore,
+        events: Arc<dyn EventSource>,
+        role: RoleSource,
+    ) -> Self {
+        Self {
+            index,
+            store,
+            events,
+            role,
+            publish_interval: Duration::from_secs(60),
+            last_event_at: None,
+            bootstrapped: false,
+            unpublished_changes: false,
+            last_published: None,
+            restored_generation: None,
+        }
+    }
+
+    /// Set the minimum time between published snapshots
+    pub fn with_publish_interval(mut self, publish_interval: Duration) -> Self {
+        self.publish_interval = publish_interval;
+        self
+    }
+
+    /// Run one round of election, catch-up, publishing, or refresh
+    pub fn tick(&mut self) -> Result<ReplicationTick> {
+        let is_writer = self.role.is_writer();
+        if is_writer != self.index.is_writer() {
+            tracing::info!(
+                "Search index role changed to {}",
+                if is_writer { "writer" } else { "reader" }
+            );
+            self.index.set_writer(is_writer);
+            // A new writer resumes from the newest published snapshot
+            self.bootstrapped = false;
+        }
+
+        if is_writer {
+            self.writer_tick()
+        } else {
+            self.reader_tick()
+        }
+    }
+
+    fn writer_tick(&mut self) -> Result<ReplicationTick> {
+        let mut tick = ReplicationTick {
+            is_writer: true,
+            ..Default::default()
+        };
+
+        if !self.bootstrapped {
+            if let Some(generation) = self.store.latest()? {
+                if self.restored_generation.as_ref() != Some(&generation) {
+                    self.restore(&generation)?;
+                    tick.restored = Some(generation);
+                }
+            }
+            self.bootstrapped = true;
+        }
+
+        let start = match self.last_event_at {
+            Some(since) => ReplayStart::Timestamp(since),
+            None => ReplayStart::Beginning,
+        };
+        let mut projection = SearchIndexProjection::new(self.index.as_ref());
+        let report = replay(self.events.as_ref(), start, &mut [&mut projection])?;
+
+        // Timestamp starts are inclusive, so the last seen event comes back every tick
+        if report.last_event_at > self.last_event_at || report.full_rebuild {
+            tick.events_replayed = report.events_replayed;
+            self.last_event_at = report.last_event_at.or(self.last_event_at);
+            self.unpublished_changes = true;
+        }
+
+        let due = self
+            .last_published
+            .is_none_or(|at| at.elapsed() >= self.publish_interval);
+        if self.unpublished_changes && due {
+            let (generation, info) = self.store.publish(self.index.as_ref(), self.last_event_at)?;
+            tracing::info!(
+                "Published search index snapshot {} ({} documents)",
+                generation,
+                info.num_docs
+            );
+            self.restored_generation = Some(generation.clone());
+            self.unpublished_changes = false;
+            self.last_published = Some(Instant::now());
+            tick.published = Some(generation);
+        }
+
+        Ok(tick)
+    }
+
+    fn reader_tick(&mut self) -> Result<ReplicationTick> {
+        let mut tick = ReplicationTick::default();
+        if let Some(generation) = self.store.latest()? {
+            if self.restored_generation.as_ref() != Some(&generation) {
+                self.restore(&generation)?;
+                tick.restored = Some(generation);
+            }
+        }
+        Ok(tick)
+    }
+
+    fn restore(&mut self, generation: &str) -> Result<()> {
+        let manifest = self.store.manifest(generation)?;
+        let info = self.index.restore(&self.store.path(generation))?;
+        tracing::info!(
+            "Restored search index snapshot {} ({} documents)",
+            generation,
+            info.num_docs
+        );
+        self.last_event_at = manifest.last_event_at;
+        self.restored_generation = Some(manifest.generation);
+        Ok(())
+    }
+
+    /// Tick on a fixed interval in the background until the runtime shuts down
+    pub fn spawn(self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut replicator = self;
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let handle = tokio::task::spawn_blocking(move || {
+                    let result = replicator.tick();
+                    (replicator, result)
+                });
+                let (returned, result) = match handle.await {
+                    Ok(output) => output,
+                    Err(e) => {
+                        tracing::error!("Search index replication stopped: {}", e);
+                        return;
+                    }
+                };
+                replicator = returned;
+                if let Err(e) = result {
+                    tracing::warn!("Search index replication tick failed: {}", e);
+                }
+            }
+        })
+    }
+}
+
+/// A running replicator and the index it maintains
+pub struct ReplicationHandle {
+    /// Backend to serve requests from in place of the wrapped one
+    pub index: Arc<ReplicatedIndex>,
+    /// Background replication task
+    pub task: tokio::task::JoinHandle<()>,
+}
+
+/// Wrap a backend for replication and start the background replicator
+///
+/// Returns `None` when the role is `standalone`, or when the backend is
+/// already shared (OpenSearch, Postgres) and needs no replication.
+pub fn start_replication(
+    config: &IndexReplicationConfig,
+    backend: Arc<dyn SearchBackend>,
+    events: Arc<dyn EventSource>,
+    pool: &DbPool,
+) -> Result<Option<ReplicationHandle>> {
+    if config.role == IndexRole::Standalone {
+        return Ok(None);
+    }
+    if backend.name() != "tantivy" {
+        tracing::warn!(
+            "The {} search backend is shared by all replicas; ignoring index replication",
+            backend.name()
+        );
+        return Ok(None);
+    }
+
+    std::fs::create_dir_all(&config.snapshot_dir)
+        .map_err(|e| Error::Search(format!("Failed to create snapshot directory: {}", e)))?;
+
+    let role = match config.role {
+        IndexRole::Writer => RoleSource::Fixed(true),
+        IndexRole::Elected => RoleSource::Elected(Box::new(LeaderElection::new(pool.clone(), config.election_lock_id))),
+        _ => RoleSource::Fixed(false),
+    };
+    let index = Arc::new(ReplicatedIndex::new(backend, config.role == IndexRole::Writer));
+    let replicator = IndexReplicator::new(index.clone(), SnapshotStore::new(&config.snapshot_dir), events, role)
+        .with_publish_interval(Duration::from_secs(config.publish_interval_secs));
+    let task = replicator.spawn(Duration::from_secs(config.refresh_interval_secs.max(1)));
+
+    Ok(Some(ReplicationHandle { index, task }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::patient;
+    use crate::models::Gender;
+    use crate::search::SearchEngine;
+    use crate::streaming::{EventProducer, InMemoryEventPublisher, PatientEvent};
+    use tempfile::TempDir;
+
+    fn replicated(dir: &Path, is_writer: bool) -> Arc<ReplicatedIndex> {
+        let engine = SearchEngine::new(dir).unwrap();
+        Arc::new(ReplicatedIndex::new(Arc::new(engine), is_writer))
+    }
+
+    #[test]
+    fn test_reader_skips_local_indexing() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = replicated(temp_dir.path(), false);
+
+        index.index_patient(&patient("Nakamura", &["Sam"], Gender::Unknown)).unwrap();
+        index.reload().unwrap();
+        assert!(index.search("Nakamura", 10).unwrap().is_empty());
+
+        index.set_writer(true);
+        index.index_patient(&patient("Nakamura", &["Sam"], Gender::Unknown)).unwrap();
+        index.reload().unwrap();
+        assert_eq!(index.search("Nakamura", 10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_writer_publishes_and_reader_refreshes() {
+        let shared = TempDir::new().unwrap();
+        let writer_dir = TempDir::new().unwrap();
+        let reader_dir = TempDir::new().unwrap();
+        let events = Arc::new(InMemoryEventPublisher::new());
+
+        let first = patient("Achterberg", &["Sam"], Gender::Unknown);
+        events.publish(PatientEvent::Created { patient: first.clone(), timestamp: Utc::now() }).unwrap();
+
+        let writer_index = replicated(writer_dir.path(), true);
+        let mut writer = IndexReplicator::new(
+            writer_index.clone(),
+            SnapshotStore::new(shared.path()),
+            events.clone(),
+            RoleSource::Fixed(true),
+        )
+        .with_publish_interval(Duration::ZERO);
+
+        let reader_index = replicated(reader_dir.path(), false);
+        let mut reader = IndexReplicator::new(
+            reader_index.clone(),
+            SnapshotStore::new(shared.path()),
+            events.clone(),
+            RoleSource::Fixed(false),
+        );
+
+        // Nothing published yet
+        assert!(reader.tick().unwrap().restored.is_none());
+
+        let tick = writer.tick().unwrap();
+        assert_eq!(tick.events_replayed, 1);
+        let generation = tick.published.expect("writer should publish");
+
+        let tick = reader.tick().unwrap();
+        assert_eq!(tick.restored, Some(generation.clone()));
+        assert_eq!(reader_index.search("Achterberg", 10).unwrap(), vec![first.id.to_string()]);
+
+        // No new events: nothing to publish, nothing to refresh
+        assert!(writer.tick().unwrap().published.is_none());
+        assert!(reader.tick().unwrap().restored.is_none());
+
+        let second = patient("Oyelaran", &["Sam"], Gender::Unknown);
+        events.publish(PatientEvent::Created { patient: second.clone(), timestamp: Utc::now() }).unwrap();
+        let tick = writer.tick().unwrap();
+        assert!(tick.events_replayed > 0);
+        assert!(tick.published.is_some());
+
+        reader.tick().unwrap();
+        assert_eq!(reader_index.search("Oyelaran", 10).unwrap(), vec![second.id.to_string()]);
+    }
+
+    #[test]
+    fn test_store_keeps_recent_generations() {
+        let shared = TempDir::new().unwrap();
+        let index_dir = TempDir::new().unwrap();
+        let engine = SearchEngine::new(index_dir.path()).unwrap();
+        engine.index_patient(&patient("Halvorsen", &["Sam"], Gender::Unknown)).unwrap();
+
+        let store = SnapshotStore::new(shared.path());
+        assert!(store.latest().unwrap().is_none());
+
+        let mut published = Vec::new();
+        for _ in 0..KEEP_GENERATIONS + 2 {
+            let (generation, info) = store.publish(&engine, None).unwrap();
+            assert_eq!(info.num_docs, 1);
+            published.push(generation);
+        }
+
+        assert_eq!(store.latest().unwrap().as_ref(), published.last());
+        assert!(!store.path(&published[0]).exists());
+        assert!(store.path(published.last().unwrap()).exists());
+        let remaining = std::fs::read_dir(shared.path())
+            .unwrap()
+            .filter(|e| e.as_ref().unwrap().path().is_dir())
+            .count();
+        assert_eq!(remaining, KEEP_GENERATIONS);
+    }
+}
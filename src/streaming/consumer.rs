@@ -1,7 +1,8 @@
 //! Event consumer implementation
 
 use super::{EventConsumer, PatientEvent};
-use crate::Result;
+use super::replay::{EventSource, ReplayStart};
+use crate::{Error, Result};
 
 pub struct FluvioConsumer {
     // Fluvio consumer will be initialized here
@@ -18,3 +19,14 @@ impl EventConsumer for FluvioConsumer {
         todo!("Implement event consumption")
     }
 }
+
+impl EventSource for FluvioConsumer {
+    /// Replay is not available from Fluvio yet, so a replay against it fails
+    /// before any projection is reset
+    fn read_from(&self, _start: ReplayStart) -> Result<Box<dyn Iterator<Item = Result<PatientEvent>> + '_>> {
+        // TODO: Open a partition consumer at Offset::beginning() or the first
+        // offset at the timestamp, and decode records with EventCodec
+        Err(Error::Unsupported("Event replay from Fluvio is not implemented".to_string()))
+    }
+}
+
@@ -0,0 +1,273 @@
+//! Versioned event envelope and wire formats
+//!
+//! Every event on the topic is wrapped in an [`EventEnvelope`] carrying the
+//! schema version, a unique event id, the producing service and the tenant,
+//! so consumers can detect and adapt to model changes.
+//!
+//! Version 1 events were bare [`PatientEvent`] JSON objects with no envelope.
+//! [`EventCodec::decode`] recognises them and upcasts them to the current
+//! version.
+//!
+//! The Protobuf format uses this message layout:
+//!
+//! ```proto
+//! message EventEnvelope {
+//!   uint32 schema_version = 1;
+//!   string event_id = 2;
+//!   string producer = 3;
+//!   optional string tenant = 4;
+//!   string event_type = 5;
+//!   string patient_id = 6;
+//!   int64 timestamp_micros = 7;
+//!   bytes payload = 8;  // PatientEvent as JSON
+//!   optional string origin = 9;
+//! }
+//! ```
+//!
+//! The header fields let brokers and consumers route and filter without
+//! decoding the payload.
+
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::config::{EventFormat, StreamingConfig};
+use crate::{Error, Result};
+use super::PatientEvent;
+
+/// Current event schema version
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+/// Producer name recorded on upcast v1 events, which did not carry one
+const UNKNOWN_PRODUCER: &str = "unknown";
+
+/// Envelope carried by every published event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventEnvelope {
+    pub schema_version: u32,
+    pub event_id: Uuid,
+    pub producer: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// Instance where the change was first made, when it was forwarded
+    /// by another instance; `None` means the producer made the change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    pub event: PatientEvent,
+}
+
+impl EventEnvelope {
+    /// Wrap an event at the current schema version
+    pub fn new(event: PatientEvent, producer: impl Into<String>) -> Self {
+        Self {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            event_id: Uuid::new_v4(),
+            producer: producer.into(),
+            tenant: None,
+            origin: None,
+            event,
+        }
+    }
+
+    /// Instance where the change was first made
+    pub fn origin(&self) -> &str {
+        self.origin.as_deref().unwrap_or(&self.producer)
+    }
+
+    /// Set the tenant
+    pub fn with_tenant(mut self, tenant: Option<String>) -> Self {
+        self.tenant = tenant;
+        self
+    }
+
+    /// Upcast a version 1 event, which had no envelope
+    fn from_v1(event: PatientEvent) -> Self {
+        Self::new(event, UNKNOWN_PRODUCER)
+    }
+}
+
+/// Protobuf wire form of [`EventEnvelope`]
+#[derive(Clone, PartialEq, Message)]
+struct ProtoEventEnvelope {
+    #[prost(uint32, tag = "1")]
+    schema_version: u32,
+    #[prost(string, tag = "2")]
+    event_id: String,
+    #[prost(string, tag = "3")]
+    producer: String,
+    #[prost(string, optional, tag = "4")]
+    tenant: Option<String>,
+    #[prost(string, tag = "5")]
+    event_type: String,
+    #[prost(string, tag = "6")]
+    patient_id: String,
+    #[prost(int64, tag = "7")]
+    timestamp_micros: i64,
+    #[prost(bytes = "vec", tag = "8")]
+    payload: Vec<u8>,
+    #[prost(string, optional, tag = "9")]
+    origin: Option<String>,
+}
+
+/// Encodes and decodes events in the configured wire format
+#[derive(Debug, Clone)]
+pub struct EventCodec {
+    format: EventFormat,
+    producer: String,
+    tenant: Option<String>,
+}
+
+impl EventCodec {
+    /// Create a codec for the given format, stamping events with `producer`
+    pub fn new(format: EventFormat, producer: impl Into<String>) -> Self {
+        Self {
+            format,
+            producer: producer.into(),
+            tenant: None,
+        }
+    }
+
+    /// Create a codec using the format, instance id and tenant from streaming configuration
+    pub fn from_config(config: &StreamingConfig) -> Self {
+        Self::new(config.format, config.instance_id.clone()).with_tenant(config.tenant.clone())
+    }
+
+    /// Set the tenant stamped on encoded events
+    pub fn with_tenant(mut self, tenant: Option<String>) -> Self {
+        self.tenant = tenant;
+        self
+    }
+
+    /// Wrap an event in an envelope and encode it
+    pub fn encode(&self, event: &PatientEvent) -> Result<Vec<u8>> {
+        let envelope = EventEnvelope::new(event.clone(), self.producer.clone())
+            .with_tenant(self.tenant.clone());
+        self.encode_envelope(&envelope)
+    }
+
+    /// Encode an existing envelope
+    pub fn encode_envelope(&self, envelope: &EventEnvelope) -> Result<Vec<u8>> {
+        match self.format {
+            EventFormat::Json => serde_json::to_vec(envelope)
+                .map_err(|e| Error::Streaming(format!("Failed to encode event: {}", e))),
+            EventFormat::Protobuf => {
+                let payload = serde_json::to_vec(&envelope.event)
+                    .map_err(|e| Error::Streaming(format!("Failed to encode event: {}", e)))?;
+                let proto = ProtoEventEnvelope {
+                    schema_version: envelope.schema_version,
+                    event_id: envelope.event_id.to_string(),
+                    producer: envelope.producer.clone(),
+                    tenant: envelope.tenant.clone(),
+                    event_type: envelope.event.event_type().to_string(),
+                    patient_id: envelope.event.patient_id().to_string(),
+                    timestamp_micros: envelope.event.timestamp().timestamp_micros(),
+                    payload,
+                    origin: envelope.origin.clone(),
+                };
+                Ok(proto.encode_to_vec())
+            }
+        }
+    }
+
+    /// Decode an event, upcasting older schema versions
+    pub fn decode(&self, bytes: &[u8]) -> Result<EventEnvelope> {
+        match self.format {
+            EventFormat::Json => decode_json(bytes),
+            EventFormat::Protobuf => {
+                let proto = ProtoEventEnvelope::decode(bytes)
+                    .map_err(|e| Error::Streaming(format!("Failed to decode event: {}", e)))?;
+                let event = serde_json::from_slice(&proto.payload)
+                    .map_err(|e| Error::Streaming(format!("Failed to decode event payload: {}", e)))?;
+                let event_id = Uuid::parse_str(&proto.event_id)
+                    .map_err(|e| Error::Streaming(format!("Invalid event id: {}", e)))?;
+
+                Ok(EventEnvelope {
+                    schema_version: proto.schema_version,
+                    event_id,
+                    producer: proto.producer,
+                    tenant: proto.tenant,
+                    origin: proto.origin,
+                    event,
+                })
+            }
+        }
+    }
+}
+
+fn decode_json(bytes: &[u8]) -> Result<EventEnvelope> {
+    let value: serde_json::Value = serde_json::from_slice(bytes)
+        .map_err(|e| Error::Streaming(format!("Failed to decode event: {}", e)))?;
+
+    let schema_version = value.get("schema_version").and_then(|v| v.as_u64());
+    match schema_version {
+        Some(version) if version > CURRENT_SCHEMA_VERSION as u64 => Err(Error::Streaming(format!(
+            "Unsupported event schema version {} (latest is {})",
+            version, CURRENT_SCHEMA_VERSION
+        ))),
+        Some(_) => serde_json::from_value(value)
+            .map_err(|e| Error::Streaming(format!("Failed to decode event: {}", e))),
+        None => {
+            let event = serde_json::from_value(value)
+                .map_err(|e| Error::Streaming(format!("Failed to decode v1 event: {}", e)))?;
+            Ok(EventEnvelope::from_v1(event))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn deleted_event() -> PatientEvent {
+        PatientEvent::Deleted {
+            patient_id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let codec = EventCodec::new(EventFormat::Json, "mpi-test")
+            .with_tenant(Some("north".to_string()));
+        let event = deleted_event();
+
+        let envelope = codec.decode(&codec.encode(&event).unwrap()).unwrap();
+
+        assert_eq!(envelope.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(envelope.producer, "mpi-test");
+        assert_eq!(envelope.tenant.as_deref(), Some("north"));
+        assert_eq!(envelope.event.patient_id(), event.patient_id());
+    }
+
+    #[test]
+    fn test_protobuf_round_trip() {
+        let codec = EventCodec::new(EventFormat::Protobuf, "mpi-test");
+        let event = deleted_event();
+
+        let envelope = codec.decode(&codec.encode(&event).unwrap()).unwrap();
+
+        assert_eq!(envelope.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(envelope.event.event_type(), "Deleted");
+        assert_eq!(envelope.event.patient_id(), event.patient_id());
+        assert_eq!(envelope.tenant, None);
+    }
+
+    #[test]
+    fn test_upcast_v1_event() {
+        let event = deleted_event();
+        let v1 = serde_json::to_vec(&event).unwrap();
+
+        let envelope = EventCodec::new(EventFormat::Json, "mpi-test").decode(&v1).unwrap();
+
+        assert_eq!(envelope.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(envelope.producer, UNKNOWN_PRODUCER);
+        assert_eq!(envelope.event.patient_id(), event.patient_id());
+    }
+
+    #[test]
+    fn test_reject_future_version() {
+        let bytes = br#"{"schema_version": 99, "event_id": "00000000-0000-0000-0000-000000000000", "producer": "x", "event": {}}"#;
+        assert!(EventCodec::new(EventFormat::Json, "mpi-test").decode(bytes).is_err());
+    }
+}
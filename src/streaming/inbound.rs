@@ -0,0 +1,470 @@
+//! Inbound consumer for federated MPI topologies
+//!
+//! Applies patient changes published by another MPI instance to the local
+//! store, so instances can be chained hub-and-spoke or peer-to-peer.
+//!
+//! Loops are prevented by source tagging: every envelope records the
+//! instance where the change originated, changes that originated here are
+//! skipped, and applied changes are forwarded with their origin preserved.
+//! The repository given to [`InboundApplier`] must therefore not publish
+//! events itself, or applied changes would be re-published as local ones.
+//!
+//! Merges are checked with a [`MergeGuard`] first. A merge that would
+//! corrupt the link graph is refused with [`Error::MergeConflict`] and left
+//! for a steward, while the rest of the stream is still applied.
+
+use std::sync::Arc;
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::config::{ClusteringConfig, InboundTopicConfig};
+use crate::db::PatientRepository;
+use crate::matching::{MergeConflict, MergeGuard, PatientMatcher};
+use crate::models::{LinkType, PatientLink};
+use crate::{Error, Result};
+use super::{EventEnvelope, EventProducer, PatientEvent};
+
+/// What happened to an inbound event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InboundOutcome {
+    /// The change was applied locally
+    Applied,
+    /// The change originated at this instance
+    SkippedOwnChange,
+    /// This kind of change is disabled for the topic
+    SkippedDisabled,
+    /// The change refers to a patient that does not exist locally
+    SkippedMissingPatient,
+}
+
+/// Counts of inbound events by outcome
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InboundReport {
+    pub applied: usize,
+    pub skipped_own_change: usize,
+    pub skipped_disabled: usize,
+    pub skipped_missing_patient: usize,
+    /// Merges refused by the merge checks, with the conditions found
+    pub merge_conflicts: Vec<MergeConflict>,
+}
+
+impl InboundReport {
+    fn record(&mut self, outcome: InboundOutcome) {
+        match outcome {
+            InboundOutcome::Applied => self.applied += 1,
+            InboundOutcome::SkippedOwnChange => self.skipped_own_change += 1,
+            InboundOutcome::SkippedDisabled => self.skipped_disabled += 1,
+            InboundOutcome::SkippedMissingPatient => self.skipped_missing_patient += 1,
+        }
+    }
+}
+
+/// Applies events from one remote topic to the local patient store
+pub struct InboundApplier {
+    instance_id: String,
+    topic: InboundTopicConfig,
+    patients: Arc<dyn PatientRepository>,
+    forward_to: Option<Arc<dyn EventProducer>>,
+    cluster_check: Option<(Arc<dyn PatientMatcher>, ClusteringConfig)>,
+}
+
+impl InboundApplier {
+    /// Create an applier for a topic
+    ///
+    /// `patients` must not have an event publisher attached; see the module docs.
+    pub fn new(
+        instance_id: impl Into<String>,
+        topic: InboundTopicConfig,
+        patients: Arc<dyn PatientRepository>,
+    ) -> Self {
+        Self {
+            instance_id: instance_id.into(),
+            topic,
+            patients,
+            forward_to: None,
+            cluster_check: None,
+        }
+    }
+
+    /// Forward applied changes to a local producer, preserving their origin
+    pub fn with_forwarding(mut self, producer: Arc<dyn EventProducer>) -> Self {
+        self.forward_to = Some(producer);
+        self
+    }
+
+    /// Refuse merges joining linked records that `matcher` does not match
+    ///
+    /// See [`MergeGuard::with_cluster_check`].
+    pub fn with_cluster_check(mut self, matcher: Arc<dyn PatientMatcher>, config: ClusteringConfig) -> Self {
+        self.cluster_check = Some((matcher, config));
+        self
+    }
+
+    /// Apply a stream of inbound events
+    pub fn apply_all<I>(&self, envelopes: I) -> Result<InboundReport>
+    where
+        I: IntoIterator<Item = Result<EventEnvelope>>,
+    {
+        let mut report = InboundReport::default();
+        for envelope in envelopes {
+            match self.apply(&envelope?) {
+                Err(Error::MergeConflict(conflict)) => {
+                    tracing::warn!("Inbound topic {}: {}", self.topic.topic, conflict);
+                    report.merge_conflicts.push(conflict);
+                }
+                outcome => report.record(outcome?),
+            }
+        }
+
+        tracing::info!(
+            "Inbound topic {}: {} applied, {} own changes, {} disabled, {} missing patient, {} merge conflicts",
+            self.topic.topic,
+            report.applied,
+            report.skipped_own_change,
+            report.skipped_disabled,
+            report.skipped_missing_patient,
+            report.merge_conflicts.len()
+        );
+
+        Ok(report)
+    }
+
+    /// Apply one inbound event
+    pub fn apply(&self, envelope: &EventEnvelope) -> Result<InboundOutcome> {
+        if envelope.origin() == self.instance_id {
+            return Ok(InboundOutcome::SkippedOwnChange);
+        }
+
+        let outcome = match &envelope.event {
+            PatientEvent::Created { patient, .. } | PatientEvent::Updated { patient, .. } => {
+                let enabled = match envelope.event {
+                    PatientEvent::Created { .. } => self.topic.apply_creates,
+                    _ => self.topic.apply_updates,
+                };
+                if !enabled {
+                    return Ok(InboundOutcome::SkippedDisabled);
+                }
+
+                // Upsert, since a create may be redelivered and an update may
+                // arrive for a patient this instance has not seen yet
+                if self.patients.get_by_id(&patient.id)?.is_some() {
+                    self.patients.update(patient)?;
+                } else {
+                    self.patients.create(patient)?;
+                }
+                InboundOutcome::Applied
+            }
+            PatientEvent::Deleted { patient_id, .. } => {
+                if !self.topic.apply_deletes {
+                    return Ok(InboundOutcome::SkippedDisabled);
+                }
+                if self.patients.get_by_id(patient_id)?.is_none() {
+                    return Ok(InboundOutcome::SkippedMissingPatient);
+                }
+                self.patients.delete(patient_id)?;
+                InboundOutcome::Applied
+            }
+            PatientEvent::Merged { source_id, target_id, .. } => {
+                if !self.topic.apply_merges {
+                    return Ok(InboundOutcome::SkippedDisabled);
+                }
+                let (Some(source), Some(target)) =
+                    (self.patients.get_by_id(source_id)?, self.patients.get_by_id(target_id)?)
+                else {
+                    return Ok(InboundOutcome::SkippedMissingPatient);
+                };
+                // A redelivered merge has nothing left to do
+                let replaced_by_target = source.links.iter().any(|link| {
+                    link.other_patient_id == *target_id && matches!(link.link_type, LinkType::ReplacedBy)
+                });
+                if replaced_by_target {
+                    return Ok(InboundOutcome::Applied);
+                }
+                let guard = match &self.cluster_check {
+                    Some((matcher, config)) => MergeGuard::new(self.patients.as_ref())
+                        .with_cluster_check(matcher.as_ref(), config.clone()),
+                    None => MergeGuard::new(self.patients.as_ref()),
+                };
+                guard.check(&source, &target)?;
+
+                // The survivor keeps verification done on the merged-away
+                // record for identifiers and addresses they share
+                self.update_links(target_id, |patient| patient.keep_verification_from(&source))?;
+                // The merged-away record is retired and points at the survivor
+                self.update_links(source_id, |patient| {
+                    patient.active = false;
+                    add_link(&mut patient.links, *target_id, LinkType::ReplacedBy);
+                })?
+            }
+            PatientEvent::Linked { patient_id, linked_id, .. } => {
+                if !self.topic.apply_links {
+                    return Ok(InboundOutcome::SkippedDisabled);
+                }
+                self.update_links(patient_id, |patient| {
+                    add_link(&mut patient.links, *linked_id, LinkType::Seealso);
+                })?
+            }
+            PatientEvent::Unlinked { patient_id, unlinked_id, .. } => {
+                if !self.topic.apply_links {
+                    return Ok(InboundOutcome::SkippedDisabled);
+                }
+                self.update_links(patient_id, |patient| {
+                    patient.links.retain(|link| link.other_patient_id != *unlinked_id);
+                })?
+            }
+        };
+
+        if outcome == InboundOutcome::Applied {
+            if let Some(producer) = &self.forward_to {
+                let mut forwarded = envelope.clone();
+                forwarded.origin = Some(envelope.origin().to_string());
+                forwarded.producer = self.instance_id.clone();
+                producer.publish_envelope(forwarded)?;
+            }
+        }
+
+        Ok(outcome)
+    }
+
+    fn update_links(
+        &self,
+        patient_id: &Uuid,
+        change: impl FnOnce(&mut crate::models::Patient),
+    ) -> Result<InboundOutcome> {
+        match self.patients.get_by_id(patient_id)? {
+            Some(mut patient) => {
+                change(&mut patient);
+                self.patients.update(&patient)?;
+                Ok(InboundOutcome::Applied)
+            }
+            None => Ok(InboundOutcome::SkippedMissingPatient),
+        }
+    }
+}
+
+fn add_link(links: &mut Vec<PatientLink>, other_patient_id: Uuid, link_type: LinkType) {
+    if !links.iter().any(|link| link.other_patient_id == other_patient_id) {
+        links.push(PatientLink { other_patient_id, link_type });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::patient;
+    use crate::db::PatientCriteria;
+    use crate::models::{Confidentiality, Gender, Patient, PatientStatus, SourceSubmission};
+    use crate::streaming::InMemoryEventPublisher;
+    use chrono::Utc;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct InMemoryPatients {
+        patients: Mutex<HashMap<Uuid, Patient>>,
+    }
+
+    impl PatientRepository for InMemoryPatients {
+        fn create(&self, patient: &Patient) -> Result<Patient> {
+            self.patients.lock().unwrap().insert(patient.id, patient.clone());
+            Ok(patient.clone())
+        }
+
+        fn create_from_source(&self, patient: &Patient, _source: &SourceSubmission) -> Result<Patient> {
+            self.create(patient)
+        }
+
+        fn get_by_id(&self, id: &Uuid) -> Result<Option<Patient>> {
+            Ok(self.patients.lock().unwrap().get(id).cloned())
+        }
+
+        fn is_deleted(&self, _id: &Uuid) -> Result<bool> {
+            Ok(false)
+        }
+
+        fn update(&self, patient: &Patient) -> Result<Patient> {
+            self.create(patient)
+        }
+
+        fn update_from_source(&self, patient: &Patient, _source: &SourceSubmission) -> Result<Patient> {
+            self.create(patient)
+        }
+
+        fn delete(&self, id: &Uuid) -> Result<()> {
+            self.patients.lock().unwrap().remove(id);
+            Ok(())
+        }
+
+        fn purge(&self, id: &Uuid) -> Result<()> {
+            self.delete(id)
+        }
+
+        fn search(&self, _query: &str) -> Result<Vec<Patient>> {
+            Ok(vec![])
+        }
+
+        fn list_active(&self, _limit: i64, _offset: i64) -> Result<Vec<Patient>> {
+            Ok(vec![])
+        }
+
+        fn scan(
+            &self,
+            _criteria: &PatientCriteria,
+            _after: Option<(chrono::DateTime<Utc>, Uuid)>,
+            _limit: i64,
+        ) -> Result<Vec<Patient>> {
+            Ok(vec![])
+        }
+
+        fn find_by_identifier(&self, _systems: &[String], _value: &str) -> Result<Vec<Uuid>> {
+            Ok(vec![])
+        }
+
+        fn find_by_contact(&self, _contact_id: &Uuid) -> Result<Option<Uuid>> {
+            Ok(None)
+        }
+
+        fn find_by_fingerprint(&self, _fingerprint: &str) -> Result<Vec<Uuid>> {
+            Ok(vec![])
+        }
+
+        fn find_linked_to(&self, id: &Uuid) -> Result<Vec<Uuid>> {
+            let patients = self.patients.lock().unwrap();
+            Ok(patients
+                .values()
+                .filter(|patient| patient.links.iter().any(|link| link.other_patient_id == *id))
+                .map(|patient| patient.id)
+                .collect())
+        }
+
+        fn set_confidentiality(&self, _id: &Uuid, _confidentiality: Confidentiality) -> Result<Option<Patient>> {
+            Ok(None)
+        }
+
+        fn set_status(
+            &self,
+            _id: &Uuid,
+            _status: PatientStatus,
+            _deceased_datetime: Option<chrono::DateTime<Utc>>,
+        ) -> Result<Option<Patient>> {
+            Ok(None)
+        }
+    }
+
+    fn topic() -> InboundTopicConfig {
+        InboundTopicConfig {
+            topic: "hub-patient-events".to_string(),
+            apply_creates: true,
+            apply_updates: true,
+            apply_merges: true,
+            apply_deletes: false,
+            apply_links: true,
+        }
+    }
+
+    #[test]
+    fn test_apply_and_forward_remote_changes() {
+        let patients = Arc::new(InMemoryPatients::default());
+        let forwarded = Arc::new(InMemoryEventPublisher::new());
+        let applier = InboundApplier::new("spoke-a", topic(), patients.clone())
+            .with_forwarding(forwarded.clone());
+
+        let survivor = patient("Moreau", &["Sam"], Gender::Unknown);
+        let duplicate = patient("Moreau", &["Sam"], Gender::Unknown);
+        let events = vec![
+            PatientEvent::Created { patient: survivor.clone(), timestamp: Utc::now() },
+            PatientEvent::Created { patient: duplicate.clone(), timestamp: Utc::now() },
+            PatientEvent::Merged { source_id: duplicate.id, target_id: survivor.id, timestamp: Utc::now() },
+            PatientEvent::Deleted { patient_id: survivor.id, timestamp: Utc::now() },
+        ];
+
+        let report = applier
+            .apply_all(events.into_iter().map(|e| Ok(EventEnvelope::new(e, "hub"))))
+            .unwrap();
+
+        assert_eq!(report.applied, 3);
+        assert_eq!(report.skipped_disabled, 1);
+
+        let merged = patients.get_by_id(&duplicate.id).unwrap().unwrap();
+        assert!(!merged.active);
+        assert_eq!(merged.links[0].other_patient_id, survivor.id);
+        assert!(patients.get_by_id(&survivor.id).unwrap().is_some());
+
+        assert_eq!(forwarded.event_count(), 3);
+    }
+
+    #[test]
+    fn test_skip_own_changes() {
+        let patients = Arc::new(InMemoryPatients::default());
+        let applier = InboundApplier::new("spoke-a", topic(), patients.clone());
+
+        let local = patient("Haddad", &["Sam"], Gender::Unknown);
+        let event = PatientEvent::Created { patient: local.clone(), timestamp: Utc::now() };
+
+        // Published here, forwarded by the hub, and delivered back
+        let mut echoed = EventEnvelope::new(event, "hub");
+        echoed.origin = Some("spoke-a".to_string());
+
+        assert_eq!(applier.apply(&echoed).unwrap(), InboundOutcome::SkippedOwnChange);
+        assert!(patients.get_by_id(&local.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_refuse_merges_that_break_the_link_graph() {
+        let patients = Arc::new(InMemoryPatients::default());
+        let applier = InboundApplier::new("spoke-a", topic(), patients.clone());
+
+        let survivor = patient("Novak", &["Sam"], Gender::Unknown);
+        let duplicate = patient("Novak", &["Sam"], Gender::Unknown);
+        let other = patient("Novak", &["Sam"], Gender::Unknown);
+        for p in [&survivor, &duplicate, &other] {
+            patients.create(p).unwrap();
+        }
+
+        let merge = |source: &Patient, target: &Patient| -> Result<EventEnvelope> {
+            Ok(EventEnvelope::new(
+                PatientEvent::Merged { source_id: source.id, target_id: target.id, timestamp: Utc::now() },
+                "hub",
+            ))
+        };
+        let report = applier
+            .apply_all(vec![
+                merge(&duplicate, &survivor),
+                // Redelivered
+                merge(&duplicate, &survivor),
+                // The duplicate is already replaced by the survivor
+                merge(&duplicate, &other),
+            ])
+            .unwrap();
+
+        assert_eq!(report.applied, 2);
+        assert_eq!(report.merge_conflicts.len(), 1);
+        assert_eq!(report.merge_conflicts[0].blockers.len(), 2);
+        let retired = patients.get_by_id(&duplicate.id).unwrap().unwrap();
+        assert_eq!(retired.links.len(), 1);
+        assert_eq!(retired.links[0].other_patient_id, survivor.id);
+    }
+
+    #[test]
+    fn test_merge_keeps_verification() {
+        use crate::models::{Identifier, VerificationStatus};
+
+        let patients = Arc::new(InMemoryPatients::default());
+        let applier = InboundApplier::new("spoke-a", topic(), patients.clone());
+
+        let mut survivor = patient("Okafor", &["Sam"], Gender::Unknown);
+        survivor.identifiers.push(Identifier::ssn("123-45-6789".to_string()));
+        let mut duplicate = patient("Okafor", &["Sam"], Gender::Unknown);
+        duplicate.identifiers.push(Identifier::ssn("123456789".to_string()));
+        duplicate.identifiers[0].verification = VerificationStatus::DocumentVerified;
+        patients.create(&survivor).unwrap();
+        patients.create(&duplicate).unwrap();
+
+        let merge = PatientEvent::Merged { source_id: duplicate.id, target_id: survivor.id, timestamp: Utc::now() };
+        assert_eq!(applier.apply(&EventEnvelope::new(merge, "hub")).unwrap(), InboundOutcome::Applied);
+
+        let survivor = patients.get_by_id(&survivor.id).unwrap().unwrap();
+        assert_eq!(survivor.identifiers[0].verification, VerificationStatus::DocumentVerified);
+    }
+}
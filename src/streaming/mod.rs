@@ -9,6 +9,12 @@ use crate::Result;
 
 pub mod producer;
 pub mod consumer;
+pub mod envelope;
+pub mod replay;
+pub mod inbound;
+pub mod watch;
+
+pub use envelope::{EventCodec, EventEnvelope, CURRENT_SCHEMA_VERSION};
 
 /// Patient event types
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +41,18 @@ impl PatientEvent {
         }
     }
 
+    /// Name of the event variant, as used in the `event_type` tag
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            PatientEvent::Created { .. } => "Created",
+            PatientEvent::Updated { .. } => "Updated",
+            PatientEvent::Deleted { .. } => "Deleted",
+            PatientEvent::Merged { .. } => "Merged",
+            PatientEvent::Linked { .. } => "Linked",
+            PatientEvent::Unlinked { .. } => "Unlinked",
+        }
+    }
+
     /// Get the patient ID involved in the event
     pub fn patient_id(&self) -> Uuid {
         match self {
@@ -52,9 +70,17 @@ impl PatientEvent {
 pub trait EventProducer: Send + Sync {
     /// Publish a patient event
     fn publish(&self, event: PatientEvent) -> Result<()>;
+
+    /// Publish an event with an existing envelope, preserving its id and origin
+    ///
+    /// Used when forwarding changes received from another instance. Producers
+    /// that do not carry envelopes publish the bare event.
+    fn publish_envelope(&self, envelope: EventEnvelope) -> Result<()> {
+        self.publish(envelope.event)
+    }
 }
 
-pub use producer::InMemoryEventPublisher;
+pub use producer::{CircuitBreakingProducer, InMemoryEventPublisher};
 
 /// Event consumer trait
 pub trait EventConsumer {
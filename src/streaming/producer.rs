@@ -1,7 +1,9 @@
 //! Event producer implementations
 
 use std::sync::{Arc, Mutex};
-use super::{EventProducer, PatientEvent};
+use super::{EventEnvelope, EventProducer, PatientEvent};
+use super::replay::{EventSource, ReplayStart};
+use crate::circuit_breaker::CircuitBreaker;
 use crate::Result;
 
 /// In-memory event publisher for development/testing
@@ -45,14 +47,7 @@ impl EventProducer for InMemoryEventPublisher {
     fn publish(&self, event: PatientEvent) -> Result<()> {
         tracing::info!(
             "Publishing event: {} for patient {}",
-            match &event {
-                PatientEvent::Created { .. } => "Created",
-                PatientEvent::Updated { .. } => "Updated",
-                PatientEvent::Deleted { .. } => "Deleted",
-                PatientEvent::Merged { .. } => "Merged",
-                PatientEvent::Linked { .. } => "Linked",
-                PatientEvent::Unlinked { .. } => "Unlinked",
-            },
+            event.event_type(),
             event.patient_id()
         );
 
@@ -61,6 +56,43 @@ impl EventProducer for InMemoryEventPublisher {
     }
 }
 
+impl EventSource for InMemoryEventPublisher {
+    fn read_from(&self, start: ReplayStart) -> Result<Box<dyn Iterator<Item = Result<PatientEvent>> + '_>> {
+        let events: Vec<PatientEvent> = self
+            .get_events()
+            .into_iter()
+            .filter(|event| start.includes(event))
+            .collect();
+        Ok(Box::new(events.into_iter().map(Ok)))
+    }
+}
+
+/// Producer that stops calling a failing broker until it recovers
+///
+/// While the breaker is open, publishing fails at once instead of waiting on
+/// the broker.
+pub struct CircuitBreakingProducer {
+    inner: Arc<dyn EventProducer>,
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl CircuitBreakingProducer {
+    /// Guard `inner` with `breaker`
+    pub fn new(inner: Arc<dyn EventProducer>, breaker: Arc<CircuitBreaker>) -> Self {
+        Self { inner, breaker }
+    }
+}
+
+impl EventProducer for CircuitBreakingProducer {
+    fn publish(&self, event: PatientEvent) -> Result<()> {
+        self.breaker.call(|| self.inner.publish(event))
+    }
+
+    fn publish_envelope(&self, envelope: EventEnvelope) -> Result<()> {
+        self.breaker.call(|| self.inner.publish_envelope(envelope))
+    }
+}
+
 /// Fluvio event producer (for production use)
 pub struct FluvioProducer {
     // Fluvio producer will be initialized here
@@ -0,0 +1,114 @@
+//! Event replay for rebuilding derived state
+//!
+//! Projections such as the search index are derived entirely from patient
+//! events. Replaying the event topic into a projection rebuilds it without
+//! touching the primary database, which is how projections are recovered
+//! after loss or corruption.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::Result;
+use super::PatientEvent;
+
+/// Where in the topic to start replaying
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayStart {
+    /// The first retained event; projections are reset before replay
+    Beginning,
+    /// The first event at or after the given time; projections are updated in place
+    Timestamp(DateTime<Utc>),
+}
+
+impl ReplayStart {
+    /// Whether an event falls within the replay range
+    pub fn includes(&self, event: &PatientEvent) -> bool {
+        match self {
+            ReplayStart::Beginning => true,
+            ReplayStart::Timestamp(since) => event.timestamp() >= *since,
+        }
+    }
+}
+
+/// A readable, replayable event topic
+pub trait EventSource: Send + Sync {
+    /// Read events in publication order, starting at the given position
+    fn read_from(&self, start: ReplayStart) -> Result<Box<dyn Iterator<Item = Result<PatientEvent>> + '_>>;
+}
+
+/// State derived from patient events
+pub trait Projection {
+    /// Name used in replay reports
+    fn name(&self) -> &str;
+
+    /// Discard all state before a full replay
+    fn reset(&mut self) -> Result<()>;
+
+    /// Apply one event
+    fn apply(&mut self, event: &PatientEvent) -> Result<()>;
+
+    /// Flush any buffered changes
+    fn finish(&mut self) -> Result<()>;
+}
+
+/// Outcome of a replay
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReplayReport {
+    /// Whether projections were reset and rebuilt from the beginning
+    pub full_rebuild: bool,
+    /// Number of events applied to each projection
+    pub events_replayed: usize,
+    /// Timestamp of the last event replayed
+    pub last_event_at: Option<DateTime<Utc>>,
+    /// Projections that were rebuilt
+    pub projections: Vec<String>,
+}
+
+/// Replay events from a source into projections
+pub fn replay(
+    source: &dyn EventSource,
+    start: ReplayStart,
+    projections: &mut [&mut dyn Projection],
+) -> Result<ReplayReport> {
+    // Opened first, so a source that cannot replay leaves projections intact
+    let events = source.read_from(start)?;
+
+    let full_rebuild = start == ReplayStart::Beginning;
+    if full_rebuild {
+        for projection in projections.iter_mut() {
+            projection.reset()?;
+        }
+    }
+
+    let mut events_replayed = 0;
+    let mut last_event_at = None;
+    for event in events {
+        let event = event?;
+        for projection in projections.iter_mut() {
+            projection.apply(&event)?;
+        }
+        events_replayed += 1;
+        last_event_at = Some(event.timestamp());
+    }
+
+    for projection in projections.iter_mut() {
+        projection.finish()?;
+    }
+
+    let report = ReplayReport {
+        full_rebuild,
+        events_replayed,
+        last_event_at,
+        projections: projections.iter().map(|p| p.name().to_string()).collect(),
+    };
+
+    tracing::info!(
+        "Replayed {} events into {:?} (full rebuild: {})",
+        report.events_replayed,
+        report.projections,
+        report.full_rebuild
+    );
+
+    Ok(report)
+}
@@ -0,0 +1,438 @@
+//! Per-patient change notifications
+//!
+//! Callers watch individual patients instead of consuming the whole event
+//! topic. [`NotifyingEventProducer`] wraps the application's producer and,
+//! for every published event, looks up the watches on the patients involved.
+//! Each matching watch gets a [`WatchNotification`] on the in-process
+//! broadcast channel (served as server-sent events) and, when it has a
+//! callback URL, a webhook POST from a background delivery thread.
+//!
+//! Notifications go to whoever holds the watch, privileged or not, so a
+//! restricted or VIP patient in an event is always sent masked.
+
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::circuit_breaker::CircuitBreakers;
+use crate::db::WatchRepository;
+use crate::{Error, Result};
+use super::{EventEnvelope, EventProducer, PatientEvent};
+
+/// Notifications buffered for slow SSE subscribers before they lag
+const STREAM_CAPACITY: usize = 1024;
+
+/// Delivery attempts per webhook notification
+const WEBHOOK_ATTEMPTS: u32 = 3;
+
+/// Event types a watch can filter on
+pub const WATCHABLE_EVENT_TYPES: [&str; 6] = ["Created", "Updated", "Deleted", "Merged", "Linked", "Unlinked"];
+
+/// A change to a watched patient
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct WatchNotification {
+    pub watch_id: Uuid,
+    /// The watched patient, which may be either side of a merge or link
+    pub patient_id: Uuid,
+    pub event_type: String,
+    pub timestamp: DateTime<Utc>,
+    #[schema(value_type = Object)]
+    pub event: PatientEvent,
+}
+
+/// Delivers a notification to a webhook
+pub trait WebhookSender: Send + Sync {
+    fn send(&self, url: &str, notification: &WatchNotification) -> Result<()>;
+}
+
+/// Webhook sender that POSTs the notification as JSON
+pub struct HttpWebhookSender {
+    agent: ureq::Agent,
+}
+
+impl HttpWebhookSender {
+    /// Create a sender with the given request timeout
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new().timeout(timeout).build(),
+        }
+    }
+}
+
+impl Default for HttpWebhookSender {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(10))
+    }
+}
+
+impl WebhookSender for HttpWebhookSender {
+    fn send(&self, url: &str, notification: &WatchNotification) -> Result<()> {
+        self.agent
+            .post(url)
+            .send_json(notification)
+            .map(|_| ())
+            .map_err(|e| Error::Streaming(format!("Webhook delivery to {} failed: {}", url, e)))
+    }
+}
+
+/// Webhook sender with a circuit breaker per destination host
+///
+/// A subscriber whose endpoint is down stops costing a timeout per delivery
+/// attempt, without affecting deliveries to other hosts.
+pub struct CircuitBreakingWebhookSender {
+    inner: Arc<dyn WebhookSender>,
+    breakers: Arc<CircuitBreakers>,
+}
+
+impl CircuitBreakingWebhookSender {
+    /// Guard `inner` with breakers from `breakers`, named `webhook:<host>`
+    pub fn new(inner: Arc<dyn WebhookSender>, breakers: Arc<CircuitBreakers>) -> Self {
+        Self { inner, breakers }
+    }
+}
+
+impl WebhookSender for CircuitBreakingWebhookSender {
+    fn send(&self, url: &str, notification: &WatchNotification) -> Result<()> {
+        self.breakers
+            .get(&format!("webhook:{}", url_authority(url)))
+            .call(|| self.inner.send(url, notification))
+    }
+}
+
+/// Host and port of a URL, without scheme, credentials or path
+fn url_authority(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    authority.rsplit_once('@').map_or(authority, |(_, host)| host)
+}
+
+/// Fans published events out to the watches on the patients involved
+pub struct WatchNotifier {
+    watches: Arc<dyn WatchRepository>,
+    stream: broadcast::Sender<WatchNotification>,
+    webhooks: Option<mpsc::Sender<(String, WatchNotification)>>,
+}
+
+impl WatchNotifier {
+    /// Create a notifier that streams notifications but sends no webhooks
+    pub fn new(watches: Arc<dyn WatchRepository>) -> Self {
+        let (stream, _) = broadcast::channel(STREAM_CAPACITY);
+        Self {
+            watches,
+            stream,
+            webhooks: None,
+        }
+    }
+
+    /// Deliver webhooks through the given sender on a background thread
+    pub fn with_webhook_sender(mut self, sender: Arc<dyn WebhookSender>) -> Self {
+        let (queue, deliveries) = mpsc::channel::<(String, WatchNotification)>();
+        std::thread::Builder::new()
+            .name("watch-webhooks".to_string())
+            .spawn(move || {
+                for (url, notification) in deliveries {
+                    deliver(sender.as_ref(), &url, &notification);
+                }
+            })
+            .expect("failed to spawn webhook delivery thread");
+        self.webhooks = Some(queue);
+        self
+    }
+
+    /// Receive every notification; SSE handlers filter by watch
+    pub fn subscribe(&self) -> broadcast::Receiver<WatchNotification> {
+        self.stream.subscribe()
+    }
+
+    /// Notify the watches affected by an event, returning how many matched
+    pub fn notify(&self, event: &PatientEvent) -> Result<usize> {
+        let watches = self.watches.list_for_patients(&involved_patients(event))?;
+        let event = masked(event);
+
+        let mut notified = 0;
+        for watch in watches.iter().filter(|w| w.accepts(event.event_type())) {
+            let notification = WatchNotification {
+                watch_id: watch.id,
+                patient_id: watch.patient_id,
+                event_type: event.event_type().to_string(),
+                timestamp: event.timestamp(),
+                event: event.clone(),
+            };
+
+            if let (Some(url), Some(queue)) = (&watch.callback_url, &self.webhooks) {
+                if queue.send((url.clone(), notification.clone())).is_err() {
+                    tracing::warn!("Webhook delivery thread has stopped; dropping notification for watch {}", watch.id);
+                }
+            }
+            // No subscribers is not an error
+            let _ = self.stream.send(notification);
+            notified += 1;
+        }
+
+        Ok(notified)
+    }
+}
+
+fn deliver(sender: &dyn WebhookSender, url: &str, notification: &WatchNotification) {
+    for attempt in 1..=WEBHOOK_ATTEMPTS {
+        match sender.send(url, notification) {
+            Ok(()) => return,
+            Err(e) if attempt < WEBHOOK_ATTEMPTS => {
+                tracing::debug!("{} (attempt {} of {})", e, attempt, WEBHOOK_ATTEMPTS);
+                std::thread::sleep(Duration::from_secs(1 << attempt));
+            }
+            Err(e) => tracing::warn!("{}; giving up on watch {}", e, notification.watch_id),
+        }
+    }
+}
+
+/// The event with a restricted or VIP patient in it masked
+fn masked(event: &PatientEvent) -> PatientEvent {
+    match event {
+        PatientEvent::Created { patient, timestamp } if patient.confidentiality.is_restricted() => {
+            PatientEvent::Created { patient: patient.masked(), timestamp: *timestamp }
+        }
+        PatientEvent::Updated { patient, timestamp } if patient.confidentiality.is_restricted() => {
+            PatientEvent::Updated { patient: patient.masked(), timestamp: *timestamp }
+        }
+        other => other.clone(),
+    }
+}
+
+/// Every patient an event concerns, including the other side of merges and links
+fn involved_patients(event: &PatientEvent) -> Vec<Uuid> {
+    match event {
+        PatientEvent::Created { patient, .. } | PatientEvent::Updated { patient, .. } => vec![patient.id],
+        PatientEvent::Deleted { patient_id, .. } => vec![*patient_id],
+        PatientEvent::Merged { source_id, target_id, .. } => vec![*source_id, *target_id],
+        PatientEvent::Linked { patient_id, linked_id, .. } => vec![*patient_id, *linked_id],
+        PatientEvent::Unlinked { patient_id, unlinked_id, .. } => vec![*patient_id, *unlinked_id],
+    }
+}
+
+/// Event producer that also notifies patient watches
+///
+/// Notification failures are logged and never fail the publish.
+pub struct NotifyingEventProducer {
+    inner: Arc<dyn EventProducer>,
+    notifier: Arc<WatchNotifier>,
+}
+
+impl NotifyingEventProducer {
+    /// Wrap a producer
+    pub fn new(inner: Arc<dyn EventProducer>, notifier: Arc<WatchNotifier>) -> Self {
+        Self { inner, notifier }
+    }
+
+    fn notify(&self, event: &PatientEvent) {
+        if let Err(e) = self.notifier.notify(event) {
+            tracing::warn!("Failed to notify watches of {} event: {}", event.event_type(), e);
+        }
+    }
+}
+
+impl EventProducer for NotifyingEventProducer {
+    fn publish(&self, event: PatientEvent) -> Result<()> {
+        self.inner.publish(event.clone())?;
+        self.notify(&event);
+        Ok(())
+    }
+
+    fn publish_envelope(&self, envelope: EventEnvelope) -> Result<()> {
+        let event = envelope.event.clone();
+        self.inner.publish_envelope(envelope)?;
+        self.notify(&event);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::patient;
+    use std::sync::Mutex;
+    use crate::models::{Confidentiality, Gender, PatientWatch};
+    use crate::streaming::InMemoryEventPublisher;
+
+    #[derive(Default)]
+    struct InMemoryWatches {
+        watches: Mutex<Vec<PatientWatch>>,
+    }
+
+    impl WatchRepository for InMemoryWatches {
+        fn create(
+            &self,
+            patient_id: &Uuid,
+            callback_url: Option<String>,
+            event_types: Vec<String>,
+            created_by: Option<String>,
+        ) -> Result<PatientWatch> {
+            let watch = PatientWatch {
+                id: Uuid::new_v4(),
+                patient_id: *patient_id,
+                callback_url,
+                event_types,
+                created_at: Utc::now(),
+                created_by,
+            };
+            self.watches.lock().unwrap().push(watch.clone());
+            Ok(watch)
+        }
+
+        fn get_by_id(&self, id: &Uuid) -> Result<Option<PatientWatch>> {
+            Ok(self.watches.lock().unwrap().iter().find(|w| w.id == *id).cloned())
+        }
+
+        fn delete(&self, id: &Uuid) -> Result<bool> {
+            let mut watches = self.watches.lock().unwrap();
+            let before = watches.len();
+            watches.retain(|w| w.id != *id);
+            Ok(watches.len() < before)
+        }
+
+        fn list_for_patients(&self, patient_ids: &[Uuid]) -> Result<Vec<PatientWatch>> {
+            Ok(self
+                .watches
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|w| patient_ids.contains(&w.patient_id))
+                .cloned()
+                .collect())
+        }
+    }
+
+    struct RecordingSender {
+        sent: Mutex<mpsc::Sender<(String, Uuid)>>,
+    }
+
+    impl WebhookSender for RecordingSender {
+        fn send(&self, url: &str, notification: &WatchNotification) -> Result<()> {
+            self.sent.lock().unwrap().send((url.to_string(), notification.watch_id)).unwrap();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_notifies_watchers_of_both_sides_of_a_merge() {
+        let watches = Arc::new(InMemoryWatches::default());
+        let notifier = Arc::new(WatchNotifier::new(watches.clone()));
+        let producer = NotifyingEventProducer::new(Arc::new(InMemoryEventPublisher::new()), notifier.clone());
+        let mut stream = notifier.subscribe();
+
+        let source = Uuid::new_v4();
+        let target = Uuid::new_v4();
+        let on_source = watches.create(&source, None, vec![], None).unwrap();
+        let on_target = watches.create(&target, None, vec!["Merged".to_string()], None).unwrap();
+        watches.create(&Uuid::new_v4(), None, vec![], None).unwrap();
+
+        producer
+            .publish(PatientEvent::Merged { source_id: source, target_id: target, timestamp: Utc::now() })
+            .unwrap();
+
+        let first = stream.try_recv().unwrap();
+        let second = stream.try_recv().unwrap();
+        assert_eq!((first.watch_id, first.patient_id), (on_source.id, source));
+        assert_eq!((second.watch_id, second.patient_id), (on_target.id, target));
+        assert_eq!(second.event_type, "Merged");
+        assert!(stream.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_event_type_filter() {
+        let watches = Arc::new(InMemoryWatches::default());
+        let notifier = WatchNotifier::new(watches.clone());
+        let patient = patient("Mbeki", &["Thandi"], Gender::Female);
+        watches.create(&patient.id, None, vec!["Merged".to_string()], None).unwrap();
+
+        let updated = PatientEvent::Updated { patient: patient.clone(), timestamp: Utc::now() };
+        assert_eq!(notifier.notify(&updated).unwrap(), 0);
+
+        watches.create(&patient.id, None, vec![], None).unwrap();
+        assert_eq!(notifier.notify(&updated).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_restricted_patients_are_sent_masked() {
+        let watches = Arc::new(InMemoryWatches::default());
+        let notifier = WatchNotifier::new(watches.clone());
+        let mut stream = notifier.subscribe();
+        let mut patient = patient("Mbeki", &["Thandi"], Gender::Female);
+        patient.confidentiality = Confidentiality::Vip;
+        watches.create(&patient.id, None, vec![], None).unwrap();
+
+        notifier.notify(&PatientEvent::Updated { patient: patient.clone(), timestamp: Utc::now() }).unwrap();
+        let notification = stream.try_recv().unwrap();
+        let PatientEvent::Updated { patient: sent, .. } = notification.event else {
+            panic!("expected an Updated event");
+        };
+        assert_eq!(sent.id, patient.id);
+        assert!(sent.name.family.is_empty());
+    }
+
+    #[test]
+    fn test_webhook_delivery() {
+        let watches = Arc::new(InMemoryWatches::default());
+        let (sent, received) = mpsc::channel();
+        let notifier = WatchNotifier::new(watches.clone())
+            .with_webhook_sender(Arc::new(RecordingSender { sent: Mutex::new(sent) }));
+        let patient = patient("Mbeki", &["Thandi"], Gender::Female);
+        let watch = watches
+            .create(&patient.id, Some("https://care.example.org/hooks/mpi".to_string()), vec![], None)
+            .unwrap();
+        watches.create(&patient.id, None, vec![], None).unwrap();
+
+        let notified = notifier
+            .notify(&PatientEvent::Updated { patient, timestamp: Utc::now() })
+            .unwrap();
+        assert_eq!(notified, 2);
+
+        let (url, watch_id) = received.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(url, "https://care.example.org/hooks/mpi");
+        assert_eq!(watch_id, watch.id);
+        // The SSE-only watch sends no webhook
+        assert!(received.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn test_webhook_breaker_per_host() {
+        struct FailingSender;
+        impl WebhookSender for FailingSender {
+            fn send(&self, _url: &str, _notification: &WatchNotification) -> Result<()> {
+                Err(Error::Streaming("connection refused".to_string()))
+            }
+        }
+
+        assert_eq!(url_authority("https://user:pw@hooks.example.com:8443/mpi?x=1"), "hooks.example.com:8443");
+        assert_eq!(url_authority("http://10.0.0.5/callback"), "10.0.0.5");
+
+        let breakers = Arc::new(crate::circuit_breaker::CircuitBreakers::new(
+            crate::config::CircuitBreakerConfig { failure_threshold: 2, open_secs: 60 },
+        ));
+        let sender = CircuitBreakingWebhookSender::new(Arc::new(FailingSender), breakers.clone());
+        let notification = WatchNotification {
+            watch_id: Uuid::new_v4(),
+            patient_id: Uuid::new_v4(),
+            event_type: "Created".to_string(),
+            timestamp: Utc::now(),
+            event: PatientEvent::Deleted { patient_id: Uuid::new_v4(), timestamp: Utc::now() },
+        };
+
+        for _ in 0..3 {
+            assert!(sender.send("https://down.example.com/hook", &notification).is_err());
+        }
+        assert!(sender.send("https://up.example.com/hook", &notification).is_err());
+
+        let statuses = breakers.statuses();
+        assert_eq!(statuses.len(), 2);
+        assert_eq!(statuses[0].name, "webhook:down.example.com");
+        assert_eq!(statuses[0].state, crate::circuit_breaker::BreakerState::Open);
+        assert_eq!(statuses[1].state, crate::circuit_breaker::BreakerState::Closed);
+    }
+}
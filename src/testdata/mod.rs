@@ -0,0 +1,272 @@
+//! Synthetic patient data for benchmarks and accuracy testing
+//!
+//! Generates realistic-looking patients from a seeded random source, and
+//! duplicates of those patients with the kinds of errors seen in real feeds:
+//! typos, transposed date of birth digits, nicknames, and moved addresses.
+//!
+//! Enabled with the `testdata` feature.
+
+use chrono::{Datelike, NaiveDate};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::models::{Address, Gender, HumanName, Identifier, NameUse, Patient, VerificationStatus};
+
+const FAMILY_NAMES: &[&str] = &[
+    "Smith", "Johnson", "Williams", "Brown", "Jones", "Garcia", "Miller", "Davis",
+    "Rodriguez", "Martinez", "Hernandez", "Lopez", "Gonzalez", "Wilson", "Anderson",
+    "Thomas", "Taylor", "Moore", "Jackson", "Martin", "Lee", "Perez", "Thompson",
+    "White", "Harris", "Sanchez", "Clark", "Ramirez", "Lewis", "Robinson", "Walker",
+    "Young", "Allen", "King", "Wright", "Scott", "Torres", "Nguyen", "Hill", "Flores",
+];
+
+const MALE_GIVEN_NAMES: &[&str] = &[
+    "William", "Robert", "Richard", "James", "John", "Michael", "Christopher",
+    "Anthony", "Thomas", "Joseph", "Charles", "David", "Daniel", "Matthew", "Andrew",
+];
+
+const FEMALE_GIVEN_NAMES: &[&str] = &[
+    "Elizabeth", "Margaret", "Catherine", "Jennifer", "Mary", "Patricia", "Linda",
+    "Barbara", "Susan", "Jessica", "Sarah", "Karen", "Nancy", "Lisa", "Emily",
+];
+
+/// Formal names and a common nickname for each
+const NICKNAMES: &[(&str, &str)] = &[
+    ("William", "Bill"),
+    ("Robert", "Bob"),
+    ("Richard", "Rick"),
+    ("James", "Jim"),
+    ("John", "Jack"),
+    ("Michael", "Mike"),
+    ("Christopher", "Chris"),
+    ("Anthony", "Tony"),
+    ("Thomas", "Tom"),
+    ("Joseph", "Joe"),
+    ("Charles", "Charlie"),
+    ("Elizabeth", "Liz"),
+    ("Margaret", "Peggy"),
+    ("Catherine", "Kate"),
+    ("Jennifer", "Jen"),
+];
+
+const STREET_NAMES: &[&str] = &[
+    "Main St", "Oak Ave", "Maple Dr", "Cedar Ln", "Pine St", "Elm St", "Washington Blvd",
+    "Lake Rd", "Hill St", "Park Ave", "Church St", "Spring St",
+];
+
+const CITIES: &[(&str, &str, &str)] = &[
+    ("Springfield", "IL", "627"),
+    ("Portland", "OR", "972"),
+    ("Austin", "TX", "787"),
+    ("Columbus", "OH", "432"),
+    ("Madison", "WI", "537"),
+    ("Denver", "CO", "802"),
+    ("Albany", "NY", "122"),
+    ("Raleigh", "NC", "276"),
+];
+
+/// Probabilities of each kind of error when generating a duplicate
+#[derive(Debug, Clone)]
+pub struct ErrorRates {
+    /// Single-character typo in the family name
+    pub typo: f64,
+    /// Transposed digits in the date of birth
+    pub transposed_dob: f64,
+    /// Nickname used in place of the formal given name
+    pub nickname: f64,
+    /// Different current address
+    pub moved_address: f64,
+}
+
+impl Default for ErrorRates {
+    fn default() -> Self {
+        Self {
+            typo: 0.2,
+            transposed_dob: 0.1,
+            nickname: 0.2,
+            moved_address: 0.3,
+        }
+    }
+}
+
+/// Seeded generator of synthetic patients
+pub struct PatientGenerator {
+    rng: StdRng,
+    error_rates: ErrorRates,
+    next_mrn: u64,
+}
+
+impl PatientGenerator {
+    /// Create a generator with the default error rates
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            error_rates: ErrorRates::default(),
+            next_mrn: 100_000,
+        }
+    }
+
+    /// Set the error rates used for duplicates
+    pub fn with_error_rates(mut self, error_rates: ErrorRates) -> Self {
+        self.error_rates = error_rates;
+        self
+    }
+
+    /// Generate a new patient
+    pub fn patient(&mut self) -> Patient {
+        let gender = if self.rng.gen_bool(0.5) { Gender::Male } else { Gender::Female };
+        let given_names = match gender {
+            Gender::Male => MALE_GIVEN_NAMES,
+            _ => FEMALE_GIVEN_NAMES,
+        };
+
+        let mut patient = Patient::new(
+            HumanName {
+                use_type: Some(NameUse::Official),
+                family: self.pick(FAMILY_NAMES).to_string(),
+                given: vec![self.pick(given_names).to_string()],
+                prefix: vec![],
+                suffix: vec![],
+            },
+            gender,
+        );
+
+        let year = self.rng.gen_range(1930..=2020);
+        let month = self.rng.gen_range(1..=12);
+        let day = self.rng.gen_range(1..=28);
+        patient.birth_date = NaiveDate::from_ymd_opt(year, month, day);
+        patient.addresses = vec![self.address()];
+
+        self.next_mrn += 1;
+        patient.identifiers = vec![Identifier::mrn("TEST".to_string(), self.next_mrn.to_string())];
+
+        patient
+    }
+
+    /// Generate `count` new patients
+    pub fn patients(&mut self, count: usize) -> Vec<Patient> {
+        (0..count).map(|_| self.patient()).collect()
+    }
+
+    /// Generate another record for the same person, as a second source system
+    /// might submit it, with errors injected according to the error rates
+    pub fn duplicate(&mut self, original: &Patient) -> Patient {
+        let mut patient = Patient::new(original.name.clone(), original.gender);
+        patient.birth_date = original.birth_date;
+        patient.addresses = original.addresses.clone();
+
+        if self.rng.gen_bool(self.error_rates.typo) {
+            patient.name.family = self.typo(&patient.name.family);
+        }
+
+        if self.rng.gen_bool(self.error_rates.nickname) {
+            if let Some(given) = patient.name.given.first_mut() {
+                if let Some((_, nickname)) = NICKNAMES.iter().find(|(formal, _)| formal == given) {
+                    *given = nickname.to_string();
+                }
+            }
+        }
+
+        if self.rng.gen_bool(self.error_rates.transposed_dob) {
+            patient.birth_date = patient.birth_date.map(transpose_dob);
+        }
+
+        if self.rng.gen_bool(self.error_rates.moved_address) {
+            patient.addresses = vec![self.address()];
+        }
+
+        patient
+    }
+
+    fn pick<'a>(&mut self, values: &[&'a str]) -> &'a str {
+        values.choose(&mut self.rng).copied().unwrap_or_default()
+    }
+
+    fn address(&mut self) -> Address {
+        let (city, state, zip_prefix) = *CITIES.choose(&mut self.rng).expect("cities is non-empty");
+        Address {
+            line1: Some(format!("{} {}", self.rng.gen_range(1..9999), self.pick(STREET_NAMES))),
+            line2: None,
+            city: Some(city.to_string()),
+            state: Some(state.to_string()),
+            postal_code: Some(format!("{}{:02}", zip_prefix, self.rng.gen_range(0..100))),
+            country: Some("US".to_string()),
+            verification: VerificationStatus::Unverified,
+            period: None,
+        }
+    }
+
+    /// Substitute, delete, or swap one character
+    fn typo(&mut self, value: &str) -> String {
+        let mut chars: Vec<char> = value.chars().collect();
+        if chars.len() < 3 {
+            return value.to_string();
+        }
+
+        // Keep the first letter, which is rarely mistyped
+        let i = self.rng.gen_range(1..chars.len() - 1);
+        match self.rng.gen_range(0..3) {
+            0 => chars[i] = self.rng.gen_range(b'a'..=b'z') as char,
+            1 => {
+                chars.remove(i);
+            }
+            _ => chars.swap(i, i + 1),
+        }
+        chars.into_iter().collect()
+    }
+}
+
+/// Swap day and month where possible, otherwise the last two digits of the year
+fn transpose_dob(date: NaiveDate) -> NaiveDate {
+    if date.day() <= 12 && date.day() != date.month() {
+        if let Some(swapped) = NaiveDate::from_ymd_opt(date.year(), date.day(), date.month()) {
+            return swapped;
+        }
+    }
+
+    let year = date.year();
+    let swapped_year = year - year % 100 + (year % 10) * 10 + (year % 100) / 10;
+    NaiveDate::from_ymd_opt(swapped_year, date.month(), date.day()).unwrap_or(date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generator_is_deterministic() {
+        let a = PatientGenerator::new(42).patients(5);
+        let b = PatientGenerator::new(42).patients(5);
+
+        for (x, y) in a.iter().zip(b.iter()) {
+            assert_eq!(x.full_name(), y.full_name());
+            assert_eq!(x.birth_date, y.birth_date);
+        }
+    }
+
+    #[test]
+    fn test_duplicate_without_errors_is_identical() {
+        let mut generator = PatientGenerator::new(7).with_error_rates(ErrorRates {
+            typo: 0.0,
+            transposed_dob: 0.0,
+            nickname: 0.0,
+            moved_address: 0.0,
+        });
+        let original = generator.patient();
+        let duplicate = generator.duplicate(&original);
+
+        assert_ne!(original.id, duplicate.id);
+        assert_eq!(original.full_name(), duplicate.full_name());
+        assert_eq!(original.birth_date, duplicate.birth_date);
+    }
+
+    #[test]
+    fn test_transpose_dob() {
+        let date = NaiveDate::from_ymd_opt(1985, 3, 12).unwrap();
+        assert_eq!(transpose_dob(date), NaiveDate::from_ymd_opt(1985, 12, 3).unwrap());
+
+        let date = NaiveDate::from_ymd_opt(1985, 3, 20).unwrap();
+        assert_eq!(transpose_dob(date), NaiveDate::from_ymd_opt(1958, 3, 20).unwrap());
+    }
+}
@@ -0,0 +1,177 @@
+//! Birth date parsing with day/month order detection
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+/// Order of the day and month in numeric dates such as `03/04/1985`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateOrder {
+    /// Year first (`1985/04/03`), as in ISO 8601
+    Ymd,
+    /// Month first (`04/03/1985`), as in the US
+    Mdy,
+    /// Day first (`03/04/1985`), as in the UK, Canada and most of Europe
+    Dmy,
+}
+
+/// A parsed date and whether the other day/month order would also have been valid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParsedDate {
+    pub date: NaiveDate,
+    /// Both DD/MM and MM/DD readings were valid and differ
+    pub ambiguous: bool,
+}
+
+/// Parse a date
+///
+/// ISO dates (`1985-04-03`) and HL7 dates (`19850403`) are always accepted.
+/// Other numeric dates separated by `/`, `.` or `-` need a four-digit year.
+/// Year-last dates are read in `order` when it is [`DateOrder::Mdy`] or
+/// [`DateOrder::Dmy`]. Otherwise they are accepted only when a single
+/// reading is valid, so `25/12/1985` parses but `03/04/1985` is an error.
+pub fn parse_date(text: &str, order: Option<DateOrder>) -> Result<ParsedDate> {
+    let text = text.trim();
+    let unambiguous = |date| Ok(ParsedDate { date, ambiguous: false });
+
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return unambiguous(date);
+    }
+    if text.len() == 8 && text.bytes().all(|b| b.is_ascii_digit()) {
+        if let Ok(date) = NaiveDate::parse_from_str(text, "%Y%m%d") {
+            return unambiguous(date);
+        }
+    }
+
+    let invalid = || Error::Validation(format!("Unrecognized date '{}'", text));
+    let parts = numeric_parts(text).ok_or_else(invalid)?;
+
+    if parts[0].1 == 4 {
+        return NaiveDate::from_ymd_opt(parts[0].0 as i32, parts[1].0, parts[2].0)
+            .map_or_else(|| Err(invalid()), unambiguous);
+    }
+    if parts[2].1 != 4 {
+        return Err(Error::Validation(format!(
+            "Date '{}' needs a four-digit year", text
+        )));
+    }
+
+    let (first, second, year) = (parts[0].0, parts[1].0, parts[2].0 as i32);
+    let month_first = NaiveDate::from_ymd_opt(year, first, second);
+    let day_first = NaiveDate::from_ymd_opt(year, second, first);
+    let ambiguous = month_first.is_some() && day_first.is_some() && first != second;
+
+    let chosen = match order {
+        Some(DateOrder::Mdy) => month_first.ok_or_else(|| {
+            Error::Validation(format!("Date '{}' is not a valid MM/DD/YYYY date", text))
+        })?,
+        Some(DateOrder::Dmy) => day_first.ok_or_else(|| {
+            Error::Validation(format!("Date '{}' is not a valid DD/MM/YYYY date", text))
+        })?,
+        Some(DateOrder::Ymd) | None => match (month_first, day_first) {
+            _ if ambiguous => {
+                return Err(Error::Validation(format!(
+                    "Date '{}' could be DD/MM or MM/DD; configure the source's date order", text
+                )))
+            }
+            (Some(date), _) | (None, Some(date)) => date,
+            (None, None) => return Err(invalid()),
+        },
+    };
+
+    Ok(ParsedDate { date: chosen, ambiguous })
+}
+
+/// Infer the day/month order of a batch of year-last dates
+///
+/// A value whose first number is over 12 is day-first; one whose second
+/// number is over 12 is month-first. Returns `None` when the batch has no
+/// such value or has both kinds.
+pub fn detect_date_order<'a>(values: impl IntoIterator<Item = &'a str>) -> Option<DateOrder> {
+    let mut day_first = false;
+    let mut month_first = false;
+
+    for parts in values.into_iter().filter_map(numeric_parts) {
+        if parts[2].1 != 4 {
+            continue;
+        }
+        match (parts[0].0 > 12, parts[1].0 > 12) {
+            (true, false) => day_first = true,
+            (false, true) => month_first = true,
+            _ => {}
+        }
+    }
+
+    match (day_first, month_first) {
+        (true, false) => Some(DateOrder::Dmy),
+        (false, true) => Some(DateOrder::Mdy),
+        _ => None,
+    }
+}
+
+/// Split `a/b/c`, `a.b.c` or `a-b-c` into (value, digit count) pairs
+fn numeric_parts(text: &str) -> Option<[(u32, usize); 3]> {
+    let mut parts = text.trim().split(['/', '.', '-']).map(|part| {
+        let part = part.trim();
+        if part.is_empty() || part.len() > 4 || !part.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        part.parse::<u32>().ok().map(|value| (value, part.len()))
+    });
+
+    let result = [parts.next()??, parts.next()??, parts.next()??];
+    parts.next().is_none().then_some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ymd(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_iso_and_hl7_dates() {
+        for text in ["1985-04-03", "19850403", "1985/04/03"] {
+            let parsed = parse_date(text, Some(DateOrder::Dmy)).unwrap();
+            assert_eq!(parsed, ParsedDate { date: ymd(1985, 4, 3), ambiguous: false }, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_day_month_order() {
+        let us = parse_date("04/03/1985", Some(DateOrder::Mdy)).unwrap();
+        let uk = parse_date("04/03/1985", Some(DateOrder::Dmy)).unwrap();
+        assert_eq!(us.date, ymd(1985, 4, 3));
+        assert_eq!(uk.date, ymd(1985, 3, 4));
+        assert!(us.ambiguous && uk.ambiguous);
+
+        // A day over 12 cannot be read month-first
+        assert!(parse_date("25.12.1985", Some(DateOrder::Mdy)).is_err());
+        let christmas = parse_date("25.12.1985", Some(DateOrder::Dmy)).unwrap();
+        assert_eq!(christmas, ParsedDate { date: ymd(1985, 12, 25), ambiguous: false });
+
+        // Same day and month reads the same either way
+        assert!(!parse_date("05/05/1985", Some(DateOrder::Mdy)).unwrap().ambiguous);
+    }
+
+    #[test]
+    fn test_unknown_order_rejects_ambiguous_dates() {
+        assert_eq!(parse_date("25/12/1985", None).unwrap().date, ymd(1985, 12, 25));
+        assert_eq!(parse_date("12/25/1985", None).unwrap().date, ymd(1985, 12, 25));
+        assert!(parse_date("03/04/1985", None).is_err());
+        assert!(parse_date("03/04/85", Some(DateOrder::Mdy)).is_err());
+        assert!(parse_date("April 3rd", None).is_err());
+    }
+
+    #[test]
+    fn test_detect_date_order() {
+        assert_eq!(detect_date_order(["03/04/1985", "25/12/1990"]), Some(DateOrder::Dmy));
+        assert_eq!(detect_date_order(["03/04/1985", "12/25/1990"]), Some(DateOrder::Mdy));
+        assert_eq!(detect_date_order(["03/04/1985", "1990-12-25"]), None);
+        assert_eq!(detect_date_order(["25/12/1985", "12/25/1990"]), None);
+    }
+}
@@ -0,0 +1,309 @@
+//! Structural checks for identifiers with a known format
+//!
+//! NPIs carry a Luhn check digit, SSNs have area, group and serial numbers
+//! that are never issued, and many facilities number their MRNs in a fixed
+//! pattern. A value failing these checks is almost always a typo or a value
+//! keyed into the wrong field, and would otherwise match nobody or the wrong
+//! patient. Messages never repeat the value, since it may be an SSN.
+//!
+//! [`IdentifierRules`] adds the assigning authority registry: identifiers
+//! from unknown or inactive authorities, and MRNs another patient already
+//! holds under the same authority.
+
+use uuid::Uuid;
+
+use crate::config::IdentifierConfig;
+use crate::db::PatientRepository;
+use crate::models::{AuthorityRegistry, Identifier, IdentifierType, Patient};
+use crate::Result;
+
+/// Format checks together with the registered assigning authorities
+#[derive(Debug, Clone, Default)]
+pub struct IdentifierRules {
+    /// MRN patterns and whether authorities must be registered
+    pub config: IdentifierConfig,
+    /// Authorities registered when the rules were built
+    pub authorities: AuthorityRegistry,
+}
+
+/// An MRN already held by another patient under the same authority
+#[derive(Debug, Clone, PartialEq)]
+pub struct MrnConflict {
+    /// Position of the MRN among the patient's identifiers
+    pub index: usize,
+    /// Name of the authority that issued it
+    pub authority: String,
+    /// The patient that already holds it
+    pub patient_id: Uuid,
+}
+
+impl IdentifierRules {
+    /// Rules from `config` and the `authorities` registered now
+    pub fn new(config: IdentifierConfig, authorities: AuthorityRegistry) -> Self {
+        Self { config, authorities }
+    }
+
+    /// Why an identifier is not accepted, if it is not
+    ///
+    /// Format problems come first, then the identifier's authority: an
+    /// inactive one is refused, and an unregistered one only when
+    /// `require_registered_authority` is set.
+    pub fn problem(&self, identifier: &Identifier) -> Option<String> {
+        if let Some(problem) = identifier_problem(identifier, &self.config) {
+            return Some(problem);
+        }
+        match self.authorities.for_identifier(identifier) {
+            Some(authority) if !authority.active => {
+                Some(format!("Assigning authority '{}' is inactive", authority.name))
+            }
+            None if self.config.require_registered_authority => {
+                Some(format!("System '{}' is not a registered assigning authority", identifier.system))
+            }
+            _ => None,
+        }
+    }
+
+    /// MRNs on `patient` that another patient holds under the same authority
+    ///
+    /// An authority may issue under several systems (its own, its OID and
+    /// `urn:oid:<oid>`), so the same number written against any of them is
+    /// the same MRN. MRNs from unregistered systems are not checked here.
+    pub fn mrn_conflicts(&self, patient: &Patient, patients: &dyn PatientRepository) -> Result<Vec<MrnConflict>> {
+        let mut conflicts = Vec::new();
+        for (index, identifier) in patient.identifiers.iter().enumerate() {
+            if identifier.identifier_type != IdentifierType::MRN {
+                continue;
+            }
+            let Some(authority) = self.authorities.for_identifier(identifier) else {
+                continue;
+            };
+            let holders = patients.find_by_identifier(&authority.systems(), identifier.value.trim())?;
+            if let Some(patient_id) = holders.into_iter().find(|id| *id != patient.id) {
+                conflicts.push(MrnConflict {
+                    index,
+                    authority: authority.name.clone(),
+                    patient_id,
+                });
+            }
+        }
+        Ok(conflicts)
+    }
+}
+
+/// Why an identifier's value does not fit the rules for its type, if it does not
+///
+/// NPIs and SSNs are always checked. MRNs are checked when
+/// `config.mrn_patterns` has patterns for their system or assigner. Other
+/// types are accepted as they are.
+pub fn identifier_problem(identifier: &Identifier, config: &IdentifierConfig) -> Option<String> {
+    match identifier.identifier_type {
+        IdentifierType::NPI => npi_problem(&identifier.value),
+        IdentifierType::SSN => ssn_problem(&identifier.value),
+        IdentifierType::MRN => mrn_problem(identifier, config),
+        _ => None,
+    }
+}
+
+/// Ten digits, the last a Luhn check digit computed with the `80840` prefix
+pub fn npi_problem(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.len() != 10 || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return Some("NPI must be ten digits".to_string());
+    }
+    if !luhn_valid(&format!("80840{}", value)) {
+        return Some("NPI check digit is wrong".to_string());
+    }
+    None
+}
+
+/// Nine digits, optionally written `AAA-GG-SSSS`, in a range the SSA issues
+///
+/// Area numbers 000, 666 and 900-999, group number 00 and serial number
+/// 0000 are never assigned.
+pub fn ssn_problem(value: &str) -> Option<String> {
+    let digits: String = value.chars().filter(|c| !matches!(c, '-' | ' ')).collect();
+    if digits.len() != 9 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Some("SSN must be nine digits".to_string());
+    }
+
+    let (area, group, serial) = (&digits[..3], &digits[3..5], &digits[5..]);
+    let part = if area == "000" || area == "666" || area.starts_with('9') {
+        "area number"
+    } else if group == "00" {
+        "group number"
+    } else if serial == "0000" {
+        "serial number"
+    } else {
+        return None;
+    };
+    Some(format!("SSN {} is never issued", part))
+}
+
+/// Match an MRN against the patterns configured for its authority
+fn mrn_problem(identifier: &Identifier, config: &IdentifierConfig) -> Option<String> {
+    let (authority, patterns) = std::iter::once(&identifier.system)
+        .chain(&identifier.assigner)
+        .find_map(|authority| config.mrn_patterns.get_key_value(authority))?;
+
+    let value = identifier.value.trim();
+    if patterns.iter().any(|pattern| fits(value, pattern)) {
+        return None;
+    }
+    Some(format!(
+        "MRN does not match the format used by '{}' ({})",
+        authority,
+        patterns.join(", ")
+    ))
+}
+
+/// Whether `value` fits `pattern`: `9` is a digit, `A` a letter, `X` either,
+/// and any other character stands for itself
+fn fits(value: &str, pattern: &str) -> bool {
+    value.chars().count() == pattern.chars().count()
+        && value.chars().zip(pattern.chars()).all(|(c, p)| match p {
+            '9' => c.is_ascii_digit(),
+            'A' => c.is_ascii_alphabetic(),
+            'X' => c.is_ascii_alphanumeric(),
+            _ => c.eq_ignore_ascii_case(&p),
+        })
+}
+
+/// Luhn checksum over a string of ASCII digits
+fn luhn_valid(digits: &str) -> bool {
+    let sum: u32 = digits
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(i, b)| {
+            let digit = u32::from(b - b'0');
+            match i % 2 {
+                0 => digit,
+                _ if digit * 2 > 9 => digit * 2 - 9,
+                _ => digit * 2,
+            }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::InMemoryPatientRepository;
+    use crate::models::{AssigningAuthority, Gender, HumanName};
+    use chrono::Utc;
+
+    #[test]
+    fn test_npi_check_digit() {
+        assert_eq!(npi_problem("1234567893"), None);
+        assert_eq!(npi_problem(" 1245319599 "), None);
+        assert_eq!(npi_problem("1234567890").unwrap(), "NPI check digit is wrong");
+        assert!(npi_problem("123456789").is_some());
+        assert!(npi_problem("12345678AB").is_some());
+    }
+
+    #[test]
+    fn test_ssn_ranges() {
+        assert_eq!(ssn_problem("123-45-6789"), None);
+        assert_eq!(ssn_problem("123456789"), None);
+        for never_issued in ["000-12-3456", "666-12-3456", "912-34-5678", "123-00-4567", "123-45-0000"] {
+            assert!(ssn_problem(never_issued).is_some(), "{}", never_issued);
+        }
+        assert_eq!(ssn_problem("123-00-4567").unwrap(), "SSN group number is never issued");
+        assert_eq!(ssn_problem("123-45-678").unwrap(), "SSN must be nine digits");
+    }
+
+    #[test]
+    fn test_mrn_patterns_by_authority() {
+        let mut config = IdentifierConfig::default();
+        config
+            .mrn_patterns
+            .insert("GENERAL".to_string(), vec!["A999".to_string(), "99-9999".to_string()]);
+
+        let mrn = |value: &str| {
+            let mut identifier = Identifier::mrn("EAST".to_string(), value.to_string());
+            identifier.assigner = Some("GENERAL".to_string());
+            identifier
+        };
+        assert_eq!(identifier_problem(&mrn("A100"), &config), None);
+        assert_eq!(identifier_problem(&mrn("12-3456"), &config), None);
+        assert_eq!(
+            identifier_problem(&mrn("100"), &config).unwrap(),
+            "MRN does not match the format used by 'GENERAL' (A999, 99-9999)"
+        );
+
+        // Authorities without patterns are not checked
+        let other = Identifier::mrn("EAST".to_string(), "anything".to_string());
+        assert_eq!(identifier_problem(&other, &config), None);
+    }
+
+    fn authority(system: &str, oid: Option<&str>, active: bool) -> AssigningAuthority {
+        AssigningAuthority {
+            id: Uuid::new_v4(),
+            system: system.to_string(),
+            oid: oid.map(str::to_string),
+            name: format!("{} Hospital", system),
+            active,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_authority_rules() {
+        let mut rules = IdentifierRules::new(
+            IdentifierConfig::default(),
+            AuthorityRegistry::new(vec![
+                authority("GENERAL", Some("1.2.3"), true),
+                authority("CLOSED", None, false),
+            ]),
+        );
+        let mrn = |system: &str| Identifier::new(IdentifierType::MRN, system.to_string(), "100".to_string());
+
+        assert_eq!(rules.problem(&mrn("urn:oid:1.2.3")), None);
+        assert_eq!(rules.problem(&mrn("CLOSED")).unwrap(), "Assigning authority 'CLOSED Hospital' is inactive");
+        assert_eq!(rules.problem(&mrn("EAST")), None);
+
+        rules.config.require_registered_authority = true;
+        assert_eq!(
+            rules.problem(&mrn("EAST")).unwrap(),
+            "System 'EAST' is not a registered assigning authority"
+        );
+    }
+
+    #[test]
+    fn test_mrn_conflicts_span_authority_systems() {
+        let rules = IdentifierRules::new(
+            IdentifierConfig::default(),
+            AuthorityRegistry::new(vec![authority("GENERAL", Some("1.2.3"), true)]),
+        );
+        let patients = InMemoryPatientRepository::new();
+        let patient = |system: &str, value: &str| {
+            let mut patient = Patient::new(
+                HumanName {
+                    use_type: None,
+                    family: "Osei".to_string(),
+                    given: vec![],
+                    prefix: vec![],
+                    suffix: vec![],
+                },
+                Gender::Unknown,
+            );
+            patient
+                .identifiers
+                .push(Identifier::new(IdentifierType::MRN, system.to_string(), value.to_string()));
+            patient
+        };
+        let existing = patients.create(&patient("GENERAL", "100")).unwrap();
+
+        let conflicts = rules.mrn_conflicts(&patient("urn:oid:1.2.3", "100"), &patients).unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!((conflicts[0].index, conflicts[0].patient_id), (0, existing.id));
+        assert_eq!(conflicts[0].authority, "GENERAL Hospital");
+
+        // The patient itself, other numbers and unregistered systems do not conflict
+        assert!(rules.mrn_conflicts(&existing, &patients).unwrap().is_empty());
+        assert!(rules.mrn_conflicts(&patient("GENERAL", "101"), &patients).unwrap().is_empty());
+        assert!(rules.mrn_conflicts(&patient("EAST", "100"), &patients).unwrap().is_empty());
+    }
+}
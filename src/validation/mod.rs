@@ -0,0 +1,24 @@
+//! Locale-aware validation of demographic input
+//!
+//! Source systems disagree on how they write birth dates (`03/04/1985` is
+//! March 4th in the US and 3 April in most other places), postal codes and
+//! phone numbers. These helpers read them according to the sender's locale,
+//! report input that cannot be read unambiguously, and reduce equivalent
+//! spellings to one comparable form. Identifiers with a known structure are
+//! checked against it, and MRNs the MPI issues itself are built to one.
+//! New patients can be given IDs derived from their source identifiers, so
+//! replayed feeds do not create them twice.
+
+pub mod dates;
+pub mod identifiers;
+pub mod mrn;
+pub mod patient_ids;
+pub mod postal;
+pub mod telecom;
+
+pub use dates::{detect_date_order, parse_date, DateOrder, ParsedDate};
+pub use identifiers::{identifier_problem, IdentifierRules, MrnConflict};
+pub use mrn::{assign_mrn, issue_mrn};
+pub use patient_ids::{assign_patient_id, derive_patient_id};
+pub use postal::{normalize_postal_code, PostalFormat};
+pub use telecom::{email_key, phone_key};
@@ -0,0 +1,122 @@
+//! Deterministic patient IDs for idempotent feeds
+//!
+//! A source system that resends a record, or a feed replayed after an
+//! outage, would otherwise create a patient with a fresh random ID each
+//! time. With a UUIDv5 namespace configured for the source's assigning
+//! authority in `identifiers.id_namespaces`, the ID is instead derived
+//! from the authority and the source record's identifier, so every replay
+//! of the record arrives with the ID of the patient it created first.
+
+use uuid::Uuid;
+
+use crate::config::IdentifierConfig;
+use crate::db::PatientRepository;
+use crate::models::Patient;
+use crate::Result;
+
+/// ID derived from the patient's first current identifier whose system or
+/// assigner has a configured namespace, if any
+pub fn derive_patient_id(config: &IdentifierConfig, patient: &Patient) -> Option<Uuid> {
+    let now = chrono::Utc::now();
+    patient
+        .identifiers
+        .iter()
+        .filter(|identifier| !identifier.is_expired_at(now) && !identifier.value.trim().is_empty())
+        .find_map(|identifier| {
+            let (authority, namespace) = std::iter::once(&identifier.system)
+                .chain(&identifier.assigner)
+                .find_map(|authority| config.id_namespaces.get_key_value(authority))?;
+            let name = format!("{}|{}", authority, identifier.value.trim());
+            Some(Uuid::new_v5(namespace, name.as_bytes()))
+        })
+}
+
+/// Give a patient that arrived without an ID one, derived when possible
+/// and random otherwise
+///
+/// Returns the stored patient when the derived ID is already on file: the
+/// incoming patient is a replay of that patient's source record and should
+/// not be created again. Fails with [`Error::PatientDeleted`] when the
+/// derived ID belongs to a soft-deleted patient, whose row keeps the ID
+/// until it is purged.
+///
+/// [`Error::PatientDeleted`]: crate::Error::PatientDeleted
+pub fn assign_patient_id(
+    config: &IdentifierConfig,
+    patients: &dyn PatientRepository,
+    patient: &mut Patient,
+) -> Result<Option<Patient>> {
+    if !patient.id.is_nil() {
+        return Ok(None);
+    }
+    match derive_patient_id(config, patient) {
+        Some(id) => {
+            patient.id = id;
+            if let Some(existing) = patients.get_by_id_for_update(&id)? {
+                return Ok(Some(existing));
+            }
+            if patients.is_deleted(&id)? {
+                return Err(crate::Error::PatientDeleted(id.to_string()));
+            }
+            Ok(None)
+        }
+        None => {
+            patient.id = Uuid::new_v4();
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::InMemoryPatientRepository;
+    use crate::models::{Gender, Identifier};
+
+    fn patient(mrn: &str) -> Patient {
+        let mut patient = crate::fixtures::patient("Nakamura", &["Haruto"], Gender::Male);
+        patient.id = Uuid::nil();
+        patient.identifiers.push(Identifier::mrn("GENERAL".to_string(), mrn.to_string()));
+        patient
+    }
+
+    #[test]
+    fn test_replay_maps_to_stored_patient() {
+        let mut config = IdentifierConfig::default();
+        let patients = InMemoryPatientRepository::new();
+
+        // Without a namespace for the authority, IDs are random
+        let mut unconfigured = patient("100");
+        assert!(assign_patient_id(&config, &patients, &mut unconfigured).unwrap().is_none());
+        assert_eq!(derive_patient_id(&config, &unconfigured), None);
+        assert_eq!(unconfigured.id.get_version_num(), 4);
+
+        config
+            .id_namespaces
+            .insert("urn:oid:facility:GENERAL".to_string(), Uuid::new_v4());
+        let mut first = patient("100");
+        assert!(assign_patient_id(&config, &patients, &mut first).unwrap().is_none());
+        assert_eq!(first.id.get_version_num(), 5);
+        let stored = patients.create(&first).unwrap();
+
+        let mut replay = patient(" 100 ");
+        let existing = assign_patient_id(&config, &patients, &mut replay).unwrap();
+        assert_eq!(existing.map(|p| p.id), Some(stored.id));
+
+        let mut other = patient("101");
+        assert!(assign_patient_id(&config, &patients, &mut other).unwrap().is_none());
+        assert_ne!(other.id, stored.id);
+
+        // A soft-deleted patient keeps its ID, so the replay cannot be created
+        patients.delete(&stored.id).unwrap();
+        let mut after_delete = patient("100");
+        let result = assign_patient_id(&config, &patients, &mut after_delete);
+        assert!(matches!(result, Err(crate::Error::PatientDeleted(id)) if id == stored.id.to_string()));
+
+        // Once purged, the record is created again under the same ID
+        patients.purge(&stored.id).unwrap();
+        let mut after_purge = patient("100");
+        assert!(assign_patient_id(&config, &patients, &mut after_purge).unwrap().is_none());
+        assert_eq!(after_purge.id, stored.id);
+    }
+}
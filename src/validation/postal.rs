@@ -0,0 +1,139 @@
+//! Postal code formats by country
+
+/// How a country structures its postal codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostalFormat {
+    /// US ZIP or ZIP+4 (`04101`, `04101-1234`)
+    UsZip,
+    /// Canadian `A9A 9A9`; the first three characters are the forward sortation area
+    Canadian,
+    /// UK outward code (`SW1A`, `B1`, `M60`) and inward code (`1AA`)
+    UnitedKingdom,
+    /// Dutch `9999 AA`
+    Dutch,
+    /// Fixed-length all-digit codes, as used across most of Europe, Asia and Oceania
+    Numeric(usize),
+}
+
+impl PostalFormat {
+    /// Format used by a country, given its ISO 3166 code or English name
+    pub fn for_country(country: &str) -> Option<Self> {
+        let format = match country.trim().to_uppercase().as_str() {
+            "US" | "USA" | "UNITED STATES" | "UNITED STATES OF AMERICA" => PostalFormat::UsZip,
+            "CA" | "CAN" | "CANADA" => PostalFormat::Canadian,
+            "GB" | "GBR" | "UK" | "UNITED KINGDOM" | "GG" | "JE" | "IM" => PostalFormat::UnitedKingdom,
+            "NL" | "NLD" | "NETHERLANDS" => PostalFormat::Dutch,
+            "AU" | "AUS" | "AUSTRALIA" | "AT" | "AUSTRIA" | "BE" | "BELGIUM" | "CH" | "SWITZERLAND"
+            | "DK" | "DENMARK" | "NO" | "NORWAY" | "NZ" | "NEW ZEALAND" | "ZA" | "SOUTH AFRICA" => {
+                PostalFormat::Numeric(4)
+            }
+            "DE" | "DEU" | "GERMANY" | "FR" | "FRA" | "FRANCE" | "ES" | "ESP" | "SPAIN" | "IT" | "ITA"
+            | "ITALY" | "SE" | "SWEDEN" | "FI" | "FINLAND" | "MX" | "MEXICO" => PostalFormat::Numeric(5),
+            "IN" | "IND" | "INDIA" | "CN" | "CHN" | "CHINA" => PostalFormat::Numeric(6),
+            "JP" | "JPN" | "JAPAN" => PostalFormat::Numeric(7),
+            "BR" | "BRA" | "BRAZIL" => PostalFormat::Numeric(8),
+            _ => return None,
+        };
+        Some(format)
+    }
+
+    /// Guess the format of a code whose country is unknown
+    ///
+    /// Five- and nine-digit codes are taken to be US ZIP codes.
+    pub fn detect(code: &str) -> Option<Self> {
+        let code = normalize_postal_code(code);
+        [PostalFormat::UsZip, PostalFormat::Canadian, PostalFormat::UnitedKingdom, PostalFormat::Dutch]
+            .into_iter()
+            .find(|format| format.is_valid(&code))
+    }
+
+    /// Whether a code is well formed
+    pub fn is_valid(self, code: &str) -> bool {
+        let code = normalize_postal_code(code);
+        match self {
+            PostalFormat::UsZip => fits(&code, "99999") || fits(&code, "999999999"),
+            PostalFormat::Canadian => fits(&code, "A9A9A9"),
+            PostalFormat::UnitedKingdom => {
+                code.len() > 3 && {
+                    let (outward, inward) = code.split_at(code.len() - 3);
+                    fits(inward, "9AA")
+                        && ["A9", "A99", "AA9", "AA99", "A9A", "AA9A"]
+                            .iter()
+                            .any(|pattern| fits(outward, pattern))
+                }
+            }
+            PostalFormat::Dutch => fits(&code, "9999AA"),
+            PostalFormat::Numeric(len) => code.len() == len && code.bytes().all(|b| b.is_ascii_digit()),
+        }
+    }
+
+    /// The broad area a normalized, valid code belongs to
+    ///
+    /// This is the ZIP sectional center, the Canadian forward sortation
+    /// area, the UK outward code, the Dutch four-digit district, or the
+    /// leading digits of a numeric code.
+    pub fn region(self, code: &str) -> &str {
+        let len = match self {
+            PostalFormat::UsZip | PostalFormat::Canadian => 3,
+            PostalFormat::UnitedKingdom => code.len().saturating_sub(3),
+            PostalFormat::Dutch => 4,
+            PostalFormat::Numeric(len) if len >= 6 => 3,
+            PostalFormat::Numeric(_) => 2,
+        };
+        code.get(..len).unwrap_or(code)
+    }
+}
+
+/// Uppercase a postal code and drop spaces and hyphens
+pub fn normalize_postal_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+/// Match a code against a pattern of `A` (letter) and `9` (digit)
+fn fits(code: &str, pattern: &str) -> bool {
+    code.len() == pattern.len()
+        && code.bytes().zip(pattern.bytes()).all(|(c, p)| match p {
+            b'A' => c.is_ascii_alphabetic(),
+            _ => c.is_ascii_digit(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validation_by_country() {
+        let uk = PostalFormat::for_country("United Kingdom").unwrap();
+        for code in ["SW1A 1AA", "B1 1AA", "M60 1QD", "EC1A 1BB", "w1a 0ax"] {
+            assert!(uk.is_valid(code), "{}", code);
+        }
+        assert!(!uk.is_valid("04101"));
+
+        let canada = PostalFormat::for_country("ca").unwrap();
+        assert!(canada.is_valid("K1A 0B1"));
+        assert!(!canada.is_valid("K1A0B"));
+
+        assert!(PostalFormat::for_country("US").unwrap().is_valid("04101-1234"));
+        assert!(PostalFormat::for_country("DE").unwrap().is_valid("10115"));
+        assert!(!PostalFormat::for_country("DE").unwrap().is_valid("1011"));
+        assert_eq!(PostalFormat::for_country("Atlantis"), None);
+    }
+
+    #[test]
+    fn test_detect_and_region() {
+        assert_eq!(PostalFormat::detect("04101"), Some(PostalFormat::UsZip));
+        assert_eq!(PostalFormat::detect("h2x 1y4"), Some(PostalFormat::Canadian));
+        assert_eq!(PostalFormat::detect("B11 1AA"), Some(PostalFormat::UnitedKingdom));
+        assert_eq!(PostalFormat::detect("1012 AB"), Some(PostalFormat::Dutch));
+        assert_eq!(PostalFormat::detect("hello"), None);
+
+        let uk = PostalFormat::UnitedKingdom;
+        assert_eq!(uk.region(&normalize_postal_code("SW1A 1AA")), "SW1A");
+        assert_eq!(uk.region(&normalize_postal_code("B1 1AA")), "B1");
+        assert_eq!(PostalFormat::Canadian.region("H2X1Y4"), "H2X");
+    }
+}
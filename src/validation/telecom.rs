@@ -0,0 +1,64 @@
+//! Phone number and email normalization for telecom search
+
+use crate::models::{ContactPoint, ContactPointSystem};
+
+/// Trailing digits compared between phone numbers
+///
+/// Ten digits covers a NANP number without its `1` country code and most
+/// national numbers without their trunk prefix or international dialing code,
+/// so `+1 (207) 555-0142`, `1-207-555-0142` and `207.555.0142` compare equal.
+const PHONE_KEY_DIGITS: usize = 10;
+
+/// Fewest digits accepted as a phone number
+const MIN_PHONE_DIGITS: usize = 7;
+
+/// Comparable form of a phone number: its last ten digits
+///
+/// Returns `None` for values with too few digits to identify anyone.
+pub fn phone_key(phone: &str) -> Option<String> {
+    let digits: Vec<char> = phone.chars().filter(char::is_ascii_digit).collect();
+    if digits.len() < MIN_PHONE_DIGITS {
+        return None;
+    }
+    Some(digits[digits.len().saturating_sub(PHONE_KEY_DIGITS)..].iter().collect())
+}
+
+/// Comparable form of an email address
+pub fn email_key(email: &str) -> Option<String> {
+    let email = email.trim().to_lowercase();
+    let (local, domain) = email.split_once('@')?;
+    (!local.is_empty() && !domain.is_empty()).then_some(email)
+}
+
+/// Search term for a contact point, prefixed `phone:` or `email:`
+pub fn telecom_term(contact: &ContactPoint) -> Option<String> {
+    match contact.system {
+        ContactPointSystem::Phone | ContactPointSystem::Sms => {
+            phone_key(&contact.value).map(|key| format!("phone:{}", key))
+        }
+        ContactPointSystem::Email => email_key(&contact.value).map(|key| format!("email:{}", key)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phone_key() {
+        for phone in ["+1 (207) 555-0142", "1-207-555-0142", "207.555.0142"] {
+            assert_eq!(phone_key(phone).as_deref(), Some("2075550142"), "{}", phone);
+        }
+        // UK number with and without the trunk prefix
+        assert_eq!(phone_key("+44 20 7946 0958"), phone_key("020 7946 0958"));
+        assert_eq!(phone_key("x1234"), None);
+    }
+
+    #[test]
+    fn test_email_key() {
+        assert_eq!(email_key(" Ana.Lopez@Example.org ").as_deref(), Some("ana.lopez@example.org"));
+        assert_eq!(email_key("not-an-email"), None);
+        assert_eq!(email_key("@example.org"), None);
+    }
+}
@@ -357,3 +357,990 @@ async fn test_get_patient_not_found() {
 
     assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
+
+#[tokio::test]
+async fn test_get_stats() {
+    let app = common::create_test_router();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/stats")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let stats: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    let patients = &stats["data"]["patients"];
+    assert!(patients["total"].as_i64().unwrap() >= patients["active"].as_i64().unwrap());
+    assert!(stats["data"]["pending_review"].as_i64().is_some());
+    assert_eq!(stats["data"]["search_backend"], "tantivy");
+}
+
+#[tokio::test]
+async fn test_record_lock_blocks_other_stewards() {
+    let app = common::create_test_router();
+
+    // Create a duplicate pair
+    let mut patients = Vec::new();
+    for given in ["Lock", "Locke"] {
+        let patient_json = json!({
+            "id": "00000000-0000-0000-0000-000000000000",
+            "name": {
+                "use": "official",
+                "family": common::unique_patient_name("Lock"),
+                "given": [given]
+            },
+            "birth_date": "1979-03-14",
+            "gender": "male"
+        });
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/patients")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&patient_json).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let api_response: ApiResponse<Patient> = serde_json::from_slice(&body).unwrap();
+        patients.push(api_response.data.unwrap());
+    }
+
+    // Alice locks both records
+    let lock_json = json!({ "locked_by": "alice", "reason": "Reviewing pair", "with": [patients[1].id] });
+    let lock_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/v1/patients/{}/lock", patients[0].id))
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&lock_json).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(lock_response.status(), StatusCode::OK);
+
+    // Bob can neither lock nor change the second record
+    let bob_lock = json!({ "locked_by": "bob" });
+    let bob_lock_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/v1/patients/{}/lock", patients[1].id))
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&bob_lock).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(bob_lock_response.status(), StatusCode::LOCKED);
+
+    let bob_update = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/v1/patients/{}", patients[1].id))
+                .header("content-type", "application/json")
+                .header("x-lock-holder", "bob")
+                .body(Body::from(serde_json::to_vec(&patients[1]).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(bob_update.status(), StatusCode::LOCKED);
+    let body = axum::body::to_bytes(bob_update.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(error["error"]["code"], "LOCKED");
+    assert_eq!(error["error"]["details"]["locked_by"], "alice");
+
+    // Alice can, and then releases her lock
+    let alice_update = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/v1/patients/{}", patients[1].id))
+                .header("content-type", "application/json")
+                .header("x-lock-holder", "alice")
+                .body(Body::from(serde_json::to_vec(&patients[1]).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(alice_update.status(), StatusCode::OK);
+
+    for patient in &patients {
+        let unlock_response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri(format!("/api/v1/patients/{}/lock", patient.id))
+                    .header("x-lock-holder", "alice")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(unlock_response.status(), StatusCode::NO_CONTENT);
+    }
+}
+
+#[tokio::test]
+async fn test_relinkage_reviews_patients_created_through_rest() {
+    use master_patient_index::models::Gender;
+
+    let app = common::create_test_router();
+
+    // Create a patient through the REST feed path
+    let mut patient =
+        common::create_patient_from_source(&app, "relink-feed", &common::feed_patient("Relink")).await;
+
+    // The enterprise record drifts away from what the first submission said
+    patient.name.family = common::unique_patient_name("Drifted");
+    patient.name.given = vec!["Someone".to_string()];
+    patient.birth_date = chrono::NaiveDate::from_ymd_opt(1951, 9, 30);
+    patient.gender = Gender::Male;
+    let update_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/v1/patients/{}", patient.id))
+                .header("content-type", "application/json")
+                .header("x-source-system", "relink-feed")
+                .body(Body::from(serde_json::to_vec(&patient).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(update_response.status(), StatusCode::OK);
+
+    let relink_response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/admin/relink")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(relink_response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(relink_response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    // Both submissions were linked, and the first no longer matches
+    assert!(report["data"]["links_checked"].as_u64().unwrap() >= 2);
+    let weakened = report["data"]["weakened_links"].as_array().unwrap();
+    assert!(weakened.iter().any(|pair| pair["patient_id"] == patient.id.to_string()));
+}
+
+#[tokio::test]
+async fn test_matching_kpis_count_patients_created_through_rest() {
+    use std::sync::Arc;
+    use master_patient_index::db::MatchingKpiRepository;
+    use master_patient_index::reporting::MatchingKpiJob;
+
+    let state = common::create_test_app_state();
+    let app = master_patient_index::api::rest::create_router(state.clone());
+    let source_system = common::unique_patient_name("kpi-feed");
+
+    common::create_patient_from_source(&app, &source_system, &common::feed_patient("Kpi")).await;
+
+    let today = chrono::Utc::now().date_naive();
+    let job = MatchingKpiJob::new(
+        Arc::new(MatchingKpiRepository::new(state.db_pool.clone())),
+        state.config.reporting.clone(),
+    );
+    job.run_day(today).unwrap();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!(
+                    "/api/v1/reports/matching?from={today}&to={today}&source_system={source_system}"
+                ))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    // The submission was received, linked automatically, and made a new patient
+    let daily = report["data"]["daily"].as_array().unwrap();
+    assert_eq!(daily.len(), 1);
+    assert_eq!(daily[0]["records_received"], 1);
+    assert_eq!(daily[0]["auto_links"], 1);
+    assert_eq!(daily[0]["new_patients"], 1);
+}
+
+#[tokio::test]
+async fn test_data_quality_scores_patients_created_through_rest() {
+    let app = common::create_test_router();
+    let source_system = common::unique_patient_name("quality-feed");
+
+    common::create_patient_from_source(&app, &source_system, &common::feed_patient("Quality")).await;
+
+    let today = chrono::Utc::now().date_naive();
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!(
+                    "/api/v1/reports/data-quality?from={today}&to={today}&source_system={source_system}"
+                ))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    // The submission is scored: it has a birth date but no telecom
+    let sources = report["data"]["sources"].as_array().unwrap();
+    assert_eq!(sources.len(), 1);
+    assert_eq!(sources[0]["records"], 1);
+    assert_eq!(sources[0]["missing_field_rates"]["birth_date"], 0.0);
+    assert_eq!(sources[0]["missing_field_rates"]["telecom"], 1.0);
+}
+
+#[tokio::test]
+async fn test_purge_removes_patients_created_through_rest() {
+    use master_patient_index::jobs::{PurgeMode, SourcePurgeJob, SourcePurgeRequest};
+
+    let state = common::create_test_app_state();
+    let app = master_patient_index::api::rest::create_router(state.clone());
+    let source_system = common::unique_patient_name("purge-feed");
+
+    // One patient only the purged feed contributes to, and one another
+    // feed has since updated
+    let only = common::create_patient_from_source(&app, &source_system, &common::feed_patient("PurgeOnly")).await;
+    let shared = common::create_patient_from_source(&app, &source_system, &common::feed_patient("PurgeShared")).await;
+    let update_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/v1/patients/{}", shared.id))
+                .header("content-type", "application/json")
+                .header("x-source-system", "purge-other-feed")
+                .body(Body::from(serde_json::to_vec(&shared).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(update_response.status(), StatusCode::OK);
+
+    let request = SourcePurgeRequest {
+        source_system: source_system.clone(),
+        mode: PurgeMode::SoftDelete,
+        dry_run: false,
+        requested_by: None,
+    };
+    let handle = state.jobs.create("source_purge", None);
+    let report = SourcePurgeJob::new(
+        state.patient_repository.clone(),
+        state.source_records.clone(),
+        state.search_engine.clone(),
+    )
+    .run(&request, &handle)
+    .unwrap();
+
+    assert_eq!(report.source_records_examined, 2);
+    assert_eq!(report.source_records_removed, 2);
+    assert_eq!(report.removed_patient_ids, vec![only.id]);
+    assert_eq!(report.retained_patient_ids, vec![shared.id]);
+    assert!(state.patient_repository.get_by_id(&only.id).unwrap().is_none());
+    assert!(state.patient_repository.get_by_id(&shared.id).unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_link_confidence_decays_for_patients_created_through_rest() {
+    use master_patient_index::models::{Gender, SourceRecordLink};
+
+    let state = common::create_test_app_state();
+    let app = master_patient_index::api::rest::create_router(state.clone());
+    let source_system = common::unique_patient_name("confidence-feed");
+
+    let mut patient =
+        common::create_patient_from_source(&app, &source_system, &common::feed_patient("Confidence")).await;
+
+    // Another feed rewrites the demographics the first submission carried
+    patient.name.family = common::unique_patient_name("Diverged");
+    patient.name.given = vec!["Someone".to_string()];
+    patient.birth_date = chrono::NaiveDate::from_ymd_opt(1951, 9, 30);
+    patient.gender = Gender::Male;
+    let update_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/v1/patients/{}", patient.id))
+                .header("content-type", "application/json")
+                .header("x-source-system", "confidence-other-feed")
+                .body(Body::from(serde_json::to_vec(&patient).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(update_response.status(), StatusCode::OK);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/admin/link-confidence")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The first submission's link lost the confidence a name divergence costs
+    let records = state.source_records.list_for_patient(&patient.id).unwrap();
+    let record = records.iter().find(|r| r.source_system == source_system).unwrap();
+    let link = state.source_records.current_link(&record.id).unwrap().unwrap();
+    let expected = SourceRecordLink::initial_confidence(None) * (1.0 - state.config.link_confidence.name_decay);
+    assert!((link.confidence - expected).abs() < 0.0001);
+}
+
+#[tokio::test]
+async fn test_summary_lists_records_of_patients_created_through_rest() {
+    let app = common::create_test_router();
+    let source_system = common::unique_patient_name("summary-feed");
+
+    let patient = common::create_patient_from_source(&app, &source_system, &common::feed_patient("Summary")).await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/v1/patients/{}/summary", patient.id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let summary: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    // The submission contributes to the record through its current link
+    let contributing = summary["data"]["source_records"].as_array().unwrap();
+    assert_eq!(contributing.len(), 1);
+    assert_eq!(contributing[0]["record"]["source_system"], source_system.as_str());
+    assert_eq!(contributing[0]["record"]["source_record_id"], patient.id.to_string());
+    assert_eq!(contributing[0]["link"]["patient_id"], patient.id.to_string());
+
+    let audit = summary["data"]["audit"].as_array().unwrap();
+    assert!(audit.iter().any(|log| log["action"] == "CREATE"));
+}
+
+#[tokio::test]
+async fn test_search_snapshot_requires_admin_and_backup_dir() {
+    let app = common::create_test_router();
+    let snapshot = |roles: Option<&str>, path: &str| {
+        let mut request = Request::builder()
+            .method("POST")
+            .uri("/api/v1/admin/search/snapshot")
+            .header("content-type", "application/json");
+        if let Some(roles) = roles {
+            request = request.header("x-user-roles", roles);
+        }
+        request.body(Body::from(json!({ "path": path }).to_string())).unwrap()
+    };
+
+    let response = app.clone().oneshot(snapshot(None, "nightly")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = app.oneshot(snapshot(Some("mpi-admin"), "../../etc")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_filter_narrows_patients_in_the_database() {
+    let app = common::create_test_router();
+    let patient = common::create_patient_from_source(&app, "filter-feed", &common::feed_patient("Filter")).await;
+
+    let filter = format!(
+        "family%20eq%20%22{}%22%20and%20birthDate%20ge%20%221983-01-01%22%20and%20birthDate%20lt%20%221984-01-01%22",
+        patient.name.family
+    );
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/v1/patients?_filter={}", filter))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let api_response: ApiResponse<Vec<Patient>> = serde_json::from_slice(&body).unwrap();
+
+    let found = api_response.data.unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].id, patient.id);
+}
+
+#[tokio::test]
+async fn test_load_shedding_reads_wait_of_created_pool() {
+    let mut config = master_patient_index::config::Config::from_env().expect("Failed to load test config");
+    config.load_shedding.enabled = true;
+    config.load_shedding.max_pool_wait_ms = 0;
+    let state = common::create_test_app_state_with_config(config);
+    let app = master_patient_index::api::rest::create_router(state.clone());
+
+    // Any checkout from the state's own pool is a wait above the limit of 0ms
+    drop(state.db_pool.get().unwrap());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/v1/patients/{}", uuid::Uuid::new_v4()))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(response.headers().contains_key("retry-after"));
+
+    let response = app
+        .oneshot(Request::builder().uri("/api/v1/health").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_replay_requires_admin() {
+    let app = common::create_test_router();
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/admin/replay")
+                .header("content-type", "application/json")
+                .header("x-user-roles", "registrar")
+                .body(Body::from(json!({}).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_source_purge_requires_admin() {
+    let app = common::create_test_router();
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/admin/purge")
+                .header("content-type", "application/json")
+                .header("x-user-roles", "registrar")
+                .body(Body::from(json!({"source_system": "lab-feed", "mode": "purge"}).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_export_requires_admin() {
+    let app = common::create_test_router();
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/api/v1/admin/export?deidentify=false")
+                .header("accept", "application/x-ndjson")
+                .header("x-user-roles", "registrar")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_config_reload_requires_admin() {
+    let app = common::create_test_router();
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/admin/config/reload")
+                .header("x-user-roles", "registrar")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_matching_profile_changes_require_admin() {
+    let app = common::create_test_router();
+    for (method, uri, body) in [
+        ("PUT", "/api/v1/admin/matching/profiles/tenant-a", json!({"threshold_score": 0.9})),
+        ("POST", "/api/v1/admin/matching/profiles/tenant-a/restore", json!({"version": 1})),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header("content-type", "application/json")
+                    .header("x-user-roles", "registrar")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{} {}", method, uri);
+    }
+}
+
+#[tokio::test]
+async fn test_import_requires_admin_and_import_dir() {
+    let app = common::create_test_router();
+    let import = |roles: Option<&str>, path: &str| {
+        let mut request = Request::builder()
+            .method("POST")
+            .uri("/api/v1/admin/import")
+            .header("content-type", "application/json");
+        if let Some(roles) = roles {
+            request = request.header("x-user-roles", roles);
+        }
+        request.body(Body::from(json!({ "path": path }).to_string())).unwrap()
+    };
+
+    let response = app.clone().oneshot(import(None, "patients.ndjson")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = app.oneshot(import(Some("mpi-admin"), "/etc/passwd")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+/// A router deriving patient IDs from MRNs under a facility of its own
+fn replaying_router(facility: &str) -> axum::Router {
+    let mut config = master_patient_index::config::Config::from_env().expect("Failed to load test config");
+    config
+        .identifiers
+        .id_namespaces
+        .insert(format!("urn:oid:facility:{}", facility), uuid::Uuid::new_v4());
+    master_patient_index::api::rest::create_router(common::create_test_app_state_with_config(config))
+}
+
+fn mrn_patient(suffix: &str, facility: &str) -> Patient {
+    let mut patient = common::feed_patient(suffix);
+    patient
+        .identifiers
+        .push(master_patient_index::models::Identifier::mrn(facility.to_string(), "MRN-1".to_string()));
+    patient
+}
+
+#[tokio::test]
+async fn test_replay_masks_restricted_patient() {
+    let facility = common::unique_patient_name("Facility");
+    let app = replaying_router(&facility);
+    let patient = mrn_patient("Replay", &facility);
+    let created = common::create_patient_from_source(&app, "replay-feed", &patient).await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/v1/patients/{}/confidentiality", created.id))
+                .header("content-type", "application/json")
+                .header("x-user-roles", "privacy-officer")
+                .body(Body::from(json!({"confidentiality": "restricted"}).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/patients")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&patient).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let replayed: ApiResponse<Patient> = serde_json::from_slice(&body).unwrap();
+    let replayed = replayed.data.unwrap();
+    assert_eq!(replayed.id, created.id);
+    assert!(replayed.name.family.is_empty());
+    assert!(replayed.identifiers.is_empty());
+}
+
+#[tokio::test]
+async fn test_replay_of_deleted_patient_conflicts() {
+    let facility = common::unique_patient_name("Facility");
+    let app = replaying_router(&facility);
+    let patient = mrn_patient("Deleted", &facility);
+    let created = common::create_patient_from_source(&app, "replay-feed", &patient).await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri(format!("/api/v1/patients/{}", created.id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/patients")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&patient).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_timeline_of_restricted_patient_is_redacted() {
+    let app = common::create_test_router();
+    let patient = common::create_patient_from_source(&app, "timeline-feed", &common::feed_patient("Timeline")).await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/v1/patients/{}/confidentiality", patient.id))
+                .header("content-type", "application/json")
+                .header("x-user-roles", "privacy-officer")
+                .body(Body::from(json!({"confidentiality": "restricted"}).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/v1/patients/{}/timeline", patient.id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let timeline: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let entries = timeline["data"]["entries"].as_array().unwrap();
+    assert!(!entries.is_empty());
+    assert!(entries.iter().all(|entry| entry.get("state").is_none()));
+    assert!(!String::from_utf8_lossy(&body).contains(&patient.name.family));
+}
+
+/// Make a patient restricted, as a privacy officer
+async fn restrict(app: &axum::Router, id: uuid::Uuid) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/v1/patients/{}/confidentiality", id))
+                .header("content-type", "application/json")
+                .header("x-user-roles", "privacy-officer")
+                .body(Body::from(json!({"confidentiality": "restricted"}).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+/// GET `uri`, with `roles` in `X-User-Roles` when given, returning the status and body
+async fn get_as(app: &axum::Router, uri: &str, roles: Option<&str>) -> (StatusCode, String) {
+    let mut request = Request::builder().uri(uri);
+    if let Some(roles) = roles {
+        request = request.header("x-user-roles", roles);
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8_lossy(&body).into_owned())
+}
+
+#[tokio::test]
+async fn test_resubmission_of_restricted_patient_is_masked() {
+    let app = common::create_test_router();
+    let submitted = common::feed_patient("Resubmit");
+    let patient = common::create_patient_from_source(&app, "resubmit-feed", &submitted).await;
+    restrict(&app, patient.id).await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/patients")
+                .header("content-type", "application/json")
+                .header("x-source-system", "resubmit-feed")
+                .body(Body::from(serde_json::to_vec(&submitted).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let resubmitted: ApiResponse<Patient> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(resubmitted.data.unwrap().id, patient.id);
+    assert!(!String::from_utf8_lossy(&body).contains(&patient.name.family));
+}
+
+#[tokio::test]
+async fn test_fhir_history_of_restricted_patient_is_masked() {
+    let app = common::create_test_router();
+    let patient = common::create_patient_from_source(&app, "history-feed", &common::feed_patient("History")).await;
+    restrict(&app, patient.id).await;
+
+    let uri = format!("/fhir/Patient/{}/_history", patient.id);
+    let (status, body) = get_as(&app, &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let bundle: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert!(bundle["entry"].as_array().unwrap().iter().any(|entry| entry.get("resource").is_some()));
+    assert!(!body.contains(&patient.name.family));
+
+    let (_, body) = get_as(&app, &uri, Some("privacy-officer")).await;
+    assert!(body.contains(&patient.name.family));
+}
+
+#[tokio::test]
+async fn test_audit_log_of_restricted_patient_is_masked() {
+    let app = common::create_test_router();
+    let patient = common::create_patient_from_source(&app, "audit-feed", &common::feed_patient("Audit")).await;
+    restrict(&app, patient.id).await;
+
+    let uri = format!("/api/v1/patients/{}/audit", patient.id);
+    let (status, body) = get_as(&app, &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let logs: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert!(logs["data"].as_array().unwrap().iter().any(|log| log["action"] == "CREATE"));
+    assert!(!body.contains(&patient.name.family));
+
+    let (_, body) = get_as(&app, &uri, Some("privacy-officer")).await;
+    assert!(body.contains(&patient.name.family));
+}
+
+#[tokio::test]
+async fn test_suggestions_leave_out_restricted_family_names() {
+    let app = common::create_test_router();
+    // One indexed term, so no other patient's family name shares it
+    let mut submitted = common::feed_patient("Suggest");
+    submitted.name.family = format!("Suggest{}", uuid::Uuid::new_v4().simple());
+    let patient = common::create_patient_from_source(&app, "suggest-feed", &submitted).await;
+    restrict(&app, patient.id).await;
+
+    let term = patient.name.family.to_lowercase();
+    let uri = format!("/api/v1/patients/suggest?q={}", term);
+    let suggested = |body: &str| {
+        let response: serde_json::Value = serde_json::from_str(body).unwrap();
+        response["data"]["suggestions"].to_string()
+    };
+
+    // The index reader picks up the new patient shortly after the commit
+    let mut visible = false;
+    for _ in 0..50 {
+        let (_, body) = get_as(&app, &uri, Some("privacy-officer")).await;
+        if suggested(&body).contains(&term) {
+            visible = true;
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert!(visible);
+
+    let (status, body) = get_as(&app, &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!suggested(&body).contains(&term));
+}
+
+#[tokio::test]
+async fn test_related_persons_of_restricted_patient_are_hidden() {
+    let app = common::create_test_router();
+    let mut submitted = common::feed_patient("Related");
+    let contact_family = common::unique_patient_name("Contact");
+    submitted.contacts.push(master_patient_index::models::PatientContact {
+        id: uuid::Uuid::new_v4(),
+        relationship: vec!["N".to_string()],
+        name: Some(master_patient_index::models::HumanName {
+            use_type: None,
+            family: contact_family.clone(),
+            given: vec!["Kin".to_string()],
+            prefix: vec![],
+            suffix: vec![],
+        }),
+        telecom: vec![],
+        address: None,
+        gender: None,
+    });
+    let patient = common::create_patient_from_source(&app, "related-feed", &submitted).await;
+    let contact_id = patient.contacts[0].id;
+    restrict(&app, patient.id).await;
+
+    let (status, body) = get_as(&app, &format!("/fhir/RelatedPerson/{}", contact_id), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(!body.contains(&contact_family));
+
+    let (status, body) = get_as(&app, &format!("/fhir/RelatedPerson?patient=Patient/{}", patient.id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!body.contains(&contact_family));
+
+    let (status, body) =
+        get_as(&app, &format!("/fhir/RelatedPerson/{}", contact_id), Some("privacy-officer")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains(&contact_family));
+}
+
+#[tokio::test]
+async fn test_provenance_of_restricted_patient_is_audited() {
+    let app = common::create_test_router();
+    let patient = common::create_patient_from_source(&app, "provenance-feed", &common::feed_patient("Provenance")).await;
+    restrict(&app, patient.id).await;
+
+    let (status, body) = get_as(&app, &format!("/fhir/Provenance?target=Patient/{}", patient.id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!body.contains(&patient.name.family));
+
+    let (_, body) = get_as(&app, &format!("/api/v1/patients/{}/audit", patient.id), Some("privacy-officer")).await;
+    let logs: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert!(logs["data"].as_array().unwrap().iter().any(|log| {
+        log["action"] == "RESTRICTED_ACCESS" && log["new_values"]["endpoint"] == "GET /fhir/Provenance"
+    }));
+}
+
+#[tokio::test]
+async fn test_group_leaves_out_excluded_restricted_members() {
+    let mut config = master_patient_index::config::Config::from_env().expect("Failed to load test config");
+    config.privacy.restricted_search = master_patient_index::config::RestrictedSearch::Exclude;
+    let app = master_patient_index::api::rest::create_router(common::create_test_app_state_with_config(config));
+    let patient = common::create_patient_from_source(&app, "group-feed", &common::feed_patient("Group")).await;
+
+    let group = json!({
+        "resourceType": "Group",
+        "type": "person",
+        "membership": "enumerated",
+        "name": common::unique_patient_name("Cohort"),
+        "member": [{"entity": {"reference": format!("Patient/{}", patient.id)}}]
+    });
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/fhir/Group")
+                .header("content-type", "application/json")
+                .body(Body::from(group.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let created: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let uri = format!("/fhir/Group/{}", created["id"].as_str().unwrap());
+    restrict(&app, patient.id).await;
+
+    let (status, body) = get_as(&app, &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!body.contains(&patient.id.to_string()));
+
+    let (_, body) = get_as(&app, &uri, Some("privacy-officer")).await;
+    assert!(body.contains(&patient.id.to_string()));
+}
+
+#[tokio::test]
+async fn test_archived_messages_of_restricted_patient_are_emptied() {
+    let app = common::create_test_router();
+    let patient = common::create_patient_from_source(&app, "messages-feed", &common::feed_patient("Messages")).await;
+    restrict(&app, patient.id).await;
+
+    let uri = format!("/api/v1/patients/{}/messages", patient.id);
+    let (status, body) = get_as(&app, &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!body.contains(&patient.name.family));
+
+    let (_, body) = get_as(&app, &uri, Some("privacy-officer")).await;
+    assert!(body.contains(&patient.name.family));
+}
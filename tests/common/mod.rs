@@ -3,30 +3,42 @@
 use master_patient_index::{
     config::Config,
     db::create_pool,
-    search::SearchEngine,
+    search::create_backend,
     matching::ProbabilisticMatcher,
     api::rest::{AppState, create_router},
+    api::ApiResponse,
+    models::{Gender, HumanName, Patient},
 };
-use axum::Router;
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use tower::ServiceExt;
 
 /// Create a test application state for integration tests
 pub fn create_test_app_state() -> AppState {
     // Load test configuration
     let config = Config::from_env().expect("Failed to load test config");
 
+    create_test_app_state_with_config(config)
+}
+
+/// Create a test application state for integration tests from `config`
+pub fn create_test_app_state_with_config(config: Config) -> AppState {
     // Create database pool
-    let db_pool = create_pool(&config.database)
+    let (db_pool, pool_wait) = create_pool(&config.database)
         .expect("Failed to create database pool");
 
-    // Create search engine
-    let search_engine = SearchEngine::new(&config.search.index_path)
-        .expect("Failed to create search engine");
+    // Create search backend
+    let search_engine = create_backend(&config, &db_pool)
+        .expect("Failed to create search backend");
 
     // Create matcher
     let matcher = ProbabilisticMatcher::new(config.matching.clone());
 
     // Create application state
-    AppState::new(db_pool, search_engine, matcher, config)
+    AppState::new(db_pool, pool_wait, search_engine, matcher, config)
 }
 
 /// Create a test router with test application state
@@ -41,3 +53,44 @@ pub fn unique_patient_name(suffix: &str) -> String {
     let timestamp = Utc::now().timestamp_micros();
     format!("TestPatient{}_{}", suffix, timestamp)
 }
+
+/// A patient with a unique name, ready to POST
+pub fn feed_patient(suffix: &str) -> Patient {
+    let mut patient = Patient::new(
+        HumanName {
+            use_type: None,
+            family: unique_patient_name(suffix),
+            given: vec![suffix.to_string()],
+            prefix: vec![],
+            suffix: vec![],
+        },
+        Gender::Female,
+    );
+    patient.id = uuid::Uuid::nil();
+    patient.birth_date = chrono::NaiveDate::from_ymd_opt(1983, 2, 7);
+    patient
+}
+
+/// Create a patient through the REST API as a submission from `source_system`
+pub async fn create_patient_from_source(app: &Router, source_system: &str, patient: &Patient) -> Patient {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/patients")
+                .header("content-type", "application/json")
+                .header("x-source-system", source_system)
+                .body(Body::from(serde_json::to_vec(patient).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(status, StatusCode::CREATED, "{}", String::from_utf8_lossy(&body));
+    let api_response: ApiResponse<Patient> = serde_json::from_slice(&body).unwrap();
+    api_response.data.unwrap()
+}